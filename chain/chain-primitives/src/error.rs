@@ -4,6 +4,7 @@ use near_primitives::errors::{ChunkAccessError, EpochError, StorageError};
 use near_primitives::shard_layout::ShardLayoutError;
 use near_primitives::sharding::{BadHeaderForProtocolVersionError, ChunkHash, ShardChunkHeader};
 use near_primitives::types::{BlockHeight, EpochId, ShardId, ShardIndex};
+use near_primitives::validator_signer::SignerError;
 use near_time::Utc;
 use std::io;
 
@@ -53,6 +54,12 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("Transaction is invalid: {error}")]
+    InvalidTransaction {
+        error: near_primitives::errors::InvalidTxError,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -71,6 +78,9 @@ pub enum Error {
     /// Chunks missing with header info.
     #[error("Chunks Missing: {0:?}")]
     ChunksMissing(Vec<ShardChunkHeader>),
+    /// Chunk stored on the node can't be decoded or doesn't match its header.
+    #[error("Corrupted Chunk: {0:?}")]
+    CorruptedChunk(Box<ShardChunkHeader>),
     /// Block is pending optimistic block execution.
     #[error("Block Pending Optimistic Execution")]
     BlockPendingOptimisticExecution,
@@ -233,6 +243,9 @@ pub enum Error {
     /// GC error.
     #[error("GC Error: {0}")]
     GCError(String),
+    /// The validator signer couldn't sign a message.
+    #[error("Signer Error: {0}")]
+    SignerError(#[from] SignerError),
     /// Resharding error.
     #[error("Resharding Error: {0}")]
     ReshardingError(String),
@@ -270,6 +283,7 @@ impl Error {
             | Error::Orphan
             | Error::ChunkMissing(_)
             | Error::ChunksMissing(_)
+            | Error::CorruptedChunk(_)
             | Error::BlockPendingOptimisticExecution
             | Error::InvalidChunkHeight
             | Error::IOErr(_)
@@ -279,6 +293,7 @@ impl Error {
             | Error::CannotBeFinalized
             | Error::StorageError(_)
             | Error::GCError(_)
+            | Error::SignerError(_)
             | Error::ReshardingError(_)
             | Error::DBNotFoundErr(_) => false,
             Error::InvalidBlockPastTime(_, _)
@@ -351,6 +366,7 @@ impl Error {
             Error::Orphan => "orphan",
             Error::ChunkMissing(_) => "chunk_missing",
             Error::ChunksMissing(_) => "chunks_missing",
+            Error::CorruptedChunk(_) => "corrupted_chunk",
             Error::BlockPendingOptimisticExecution => "block_pending_optimistic_execution",
             Error::InvalidChunkHeight => "invalid_chunk_height",
             Error::IOErr(_) => "io_err",
@@ -360,6 +376,7 @@ impl Error {
             Error::CannotBeFinalized => "cannot_be_finalized",
             Error::StorageError(_) => "storage_error",
             Error::GCError(_) => "gc_error",
+            Error::SignerError(_) => "signer_error",
             Error::DBNotFoundErr(_) => "db_not_found_err",
             Error::InvalidBlockPastTime(_, _) => "invalid_block_past_time",
             Error::InvalidBlockFutureTime(_) => "invalid_block_future_time",
@@ -81,6 +81,9 @@ pub struct BlockProcessingArtifact {
     pub orphans_missing_chunks: Vec<OrphanMissingChunks>,
     pub blocks_missing_chunks: Vec<BlockMissingChunks>,
     pub invalid_chunks: Vec<ShardChunkHeader>,
+    /// Chunks evicted from the store because they were corrupted, which must
+    /// be downloaded again.
+    pub corrupted_chunks: Vec<ShardChunkHeader>,
 }
 
 #[derive(Debug)]
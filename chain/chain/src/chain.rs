@@ -5,12 +5,15 @@ use crate::block_processing_utils::{
 };
 use crate::blocks_delay_tracker::BlocksDelayTracker;
 use crate::chain_update::ChainUpdate;
+use crate::corrupted_chunks::{CorruptedChunks, MAX_CORRUPTED_CHUNK_EVICTIONS};
 use crate::crypto_hash_timer::CryptoHashTimer;
+use crate::deferred_postprocessing::{DeferredBlockPostprocessing, DeferredPostprocessingPool};
 use crate::lightclient::get_epoch_block_producers_view;
 use crate::missing_chunks::{MissingChunksPool, OptimisticBlockChunksPool};
 use crate::orphan::{Orphan, OrphanBlockPool};
 use crate::pending::PendingBlocksPool;
 use crate::rayon_spawner::RayonAsyncComputationSpawner;
+use crate::receipt_audit::ReceiptAudit;
 use crate::resharding::manager::ReshardingManager;
 use crate::resharding::types::ReshardingSender;
 use crate::sharding::{get_receipts_shuffle_salt, shuffle_receipt_proofs};
@@ -18,7 +21,7 @@ use crate::signature_verification::{
     verify_block_header_signature_with_epoch_manager, verify_block_vrf,
     verify_chunk_header_signature_with_epoch_manager,
 };
-use crate::state_snapshot_actor::SnapshotCallbacks;
+use crate::state_snapshot_actor::{RestorePointTrigger, SnapshotCallbacks};
 use crate::state_sync::ChainStateSyncAdapter;
 use crate::stateless_validation::chunk_endorsement::{
     validate_chunk_endorsements_in_block, validate_chunk_endorsements_in_header,
@@ -50,6 +53,7 @@ use near_async::messaging::{IntoMultiSender, noop};
 use near_async::time::{Clock, Duration, Instant};
 use near_chain_configs::MutableValidatorSigner;
 use near_chain_primitives::error::{BlockKnownError, Error};
+use near_client_primitives::debug::ReceiptAuditView;
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_assignment::shard_id_to_uid;
 use near_epoch_manager::shard_tracker::ShardTracker;
@@ -77,7 +81,9 @@ use near_primitives::state_sync::ReceiptProofResponse;
 use near_primitives::stateless_validation::state_witness::{
     ChunkStateWitness, ChunkStateWitnessSize,
 };
-use near_primitives::transaction::{ExecutionOutcomeWithIdAndProof, SignedTransaction};
+use near_primitives::transaction::{
+    ExecutionOutcomeWithId, ExecutionOutcomeWithIdAndProof, SignedTransaction,
+};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{
     AccountId, Balance, BlockHeight, BlockHeightDelta, EpochId, NumBlocks, ShardId, ShardIndex,
@@ -87,11 +93,12 @@ use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{
     BlockStatusView, DroppedReason, ExecutionOutcomeWithIdView, ExecutionStatusView,
     FinalExecutionOutcomeView, FinalExecutionOutcomeWithReceiptView, FinalExecutionStatus,
-    LightClientBlockView, SignedTransactionView,
+    LightClientBlockView, ReceiptTreeExecutionStateView, ReceiptTreeExecutionView, ReceiptTreeView,
+    SignedTransactionView,
 };
 use near_store::adapter::chain_store::ChainStoreAdapter;
 use near_store::get_genesis_state_roots;
-use near_store::{DBCol, StateSnapshotConfig};
+use near_store::{DBCol, StateSnapshotConfig, Store};
 use node_runtime::SignedValidPeriodTransactions;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use std::cell::Cell;
@@ -346,6 +353,19 @@ pub struct Chain {
 
     /// Manages all tasks related to resharding.
     pub resharding_manager: ReshardingManager,
+
+    /// Whether writes from postprocessing of blocks which don't become the
+    /// head are deferred until their fork is extended.
+    defer_fork_postprocessing: bool,
+    /// Maximum number of chunks of a block applied at once, unbounded if
+    /// `None`.
+    max_concurrent_shard_applies: Option<usize>,
+    /// Deferred postprocessing of blocks on non-head forks.
+    deferred_postprocessing: DeferredPostprocessingPool,
+    /// Chunks found corrupted in the store and evicted, to be downloaded again.
+    corrupted_chunks: CorruptedChunks,
+    /// Set if `enable_receipt_audit` is set in the config.
+    receipt_audit: Option<ReceiptAudit>,
 }
 
 impl Drop for Chain {
@@ -443,9 +463,23 @@ impl Chain {
             pending_state_patch: Default::default(),
             snapshot_callbacks: None,
             resharding_manager,
+            defer_fork_postprocessing: false,
+            max_concurrent_shard_applies: None,
+            deferred_postprocessing: Default::default(),
+            corrupted_chunks: Default::default(),
+            receipt_audit: None,
         })
     }
 
+    /// Makes the lookups of the blocks, chunks, receipts and outcomes of a
+    /// view client chain read through to the cold store, see
+    /// `NodeStorage::get_read_through_store`.  Only the chain store is
+    /// affected: the epoch manager and the runtime, and so the state, keep
+    /// reading from the store they were created with.
+    pub fn set_read_through_store(&mut self, store: Store) {
+        self.chain_store = self.chain_store.with_store(store);
+    }
+
     pub fn new(
         clock: Clock,
         epoch_manager: Arc<dyn EpochManagerAdapter>,
@@ -475,6 +509,7 @@ impl Chain {
             chain_config.save_trie_changes,
             transaction_validity_period,
         );
+        chain_store.set_transactions_by_signer_limit(chain_config.transactions_by_signer_limit);
         let state_sync_adapter = ChainStateSyncAdapter::new(
             clock.clone(),
             ChainStoreAdapter::new(chain_store.store()),
@@ -570,6 +605,10 @@ impl Chain {
         let (sc, rc) = unbounded();
         let resharding_manager =
             ReshardingManager::new(chain_store.store(), epoch_manager.clone(), resharding_sender);
+        // Loaded even if deferring is disabled now, since the forks deferred
+        // before the restart can still be extended.
+        let deferred_postprocessing = DeferredPostprocessingPool::load(&chain_store.store())?;
+        let corrupted_chunks = CorruptedChunks::load(&chain_store.store())?;
         Ok(Chain {
             clock: clock.clone(),
             chain_store,
@@ -597,6 +636,11 @@ impl Chain {
             pending_state_patch: Default::default(),
             snapshot_callbacks,
             resharding_manager,
+            defer_fork_postprocessing: chain_config.defer_fork_postprocessing,
+            max_concurrent_shard_applies: chain_config.max_concurrent_shard_applies,
+            deferred_postprocessing,
+            corrupted_chunks,
+            receipt_audit: chain_config.enable_receipt_audit.then(ReceiptAudit::new),
         })
     }
 
@@ -1105,6 +1149,9 @@ impl Chain {
                     true,
                 ) {
                     if let Err(_) = self.chain_store.get_chunk(&chunk_hash) {
+                        if self.chain_store.chunk_exists(&chunk_hash)? {
+                            return Err(Error::CorruptedChunk(Box::new(chunk_header.clone())));
+                        }
                         missing.push(chunk_header.clone());
                     }
                 }
@@ -1315,6 +1362,7 @@ impl Chain {
 
         let block_height = block.height();
         let prev_block_hash = *block.prev_block_hash();
+        self.apply_deferred_postprocessing(&prev_block_hash)?;
         let prev_block = self.get_block(&prev_block_hash)?;
         let prev_prev_hash = prev_block.header().prev_hash();
         let prev_chunk_headers =
@@ -1636,6 +1684,10 @@ impl Chain {
             return Err(Error::InvalidSignature);
         }
 
+        // The fork the block extends must have its postprocessing written
+        // before the chunks of the block can be applied.
+        self.apply_deferred_postprocessing(block.header().prev_hash())?;
+
         // 1) preprocess the block where we verify that the block is valid and ready to be processed
         //    No chain updates are applied at this step.
         let state_patch = self.pending_state_patch.take();
@@ -1695,6 +1747,26 @@ impl Chain {
                             "Process block: missing chunks"
                         );
                     }
+                    Error::CorruptedChunk(chunk_header) => {
+                        let block_hash = *block.hash();
+                        let chunk_hash = chunk_header.chunk_hash();
+                        // The chunk is downloaded again like a missing one, and
+                        // the block is processed once it's saved.
+                        self.evict_corrupted_chunk(chunk_header)?;
+                        block_processing_artifact
+                            .corrupted_chunks
+                            .push(ShardChunkHeader::clone(chunk_header));
+                        self.blocks_delay_tracker.mark_block_has_missing_chunks(block.hash());
+                        let orphan = Orphan { block, provenance, added: self.clock.now() };
+                        self.blocks_with_missing_chunks
+                            .add_block_with_missing_chunks(orphan, vec![chunk_hash.clone()]);
+                        warn!(
+                            target: "chain",
+                            ?block_hash,
+                            ?chunk_hash,
+                            "Process block: corrupted chunk evicted"
+                        );
+                    }
                     Error::BlockPendingOptimisticExecution => {
                         let block_hash = *block.hash();
                         self.blocks_delay_tracker.mark_block_pending_execution(&block_hash);
@@ -1766,10 +1838,11 @@ impl Chain {
     ) {
         let sc = self.apply_chunks_sender.clone();
         let clock = self.clock.clone();
+        let max_concurrency = self.max_concurrent_shard_applies;
         self.apply_chunks_spawner.spawn("apply_chunks", move || {
             let apply_all_chunks_start_time = clock.now();
             // do_apply_chunks runs `work` in parallel, but still waits for all of them to finish
-            let res = do_apply_chunks(block.clone(), block_height, work);
+            let res = do_apply_chunks(block.clone(), block_height, work, max_concurrency);
             // If we encounter error here, that means the receiver is deallocated and the client
             // thread is already shut down. The node is already crashed, so we can unwrap here
             metrics::APPLY_ALL_CHUNKS_TIME.with_label_values(&[block.as_ref()]).observe(
@@ -1795,17 +1868,43 @@ impl Chain {
         // for generating a state witness. Storage space optimization.
         let should_save_state_transition_data =
             self.should_produce_state_witness_for_this_or_next_epoch(me, block.header())?;
+        let defer_if_fork = self.defer_fork_postprocessing;
         let mut chain_update = self.chain_update();
         let new_head = chain_update.postprocess_block(
             block,
             block_preprocess_info,
             apply_results,
             should_save_state_transition_data,
+            defer_if_fork,
         )?;
+        let deferred = chain_update.take_deferred_postprocessing()?;
         chain_update.commit()?;
+        if let Some(deferred) = deferred {
+            debug!(target: "chain", hash = %block.hash(), height = block.header().height(), "Deferring postprocessing of fork block");
+            self.deferred_postprocessing.insert(deferred);
+        }
         Ok(new_head)
     }
 
+    /// Writes deferred postprocessing of the block and of all its ancestors
+    /// which have it deferred.  Must be called before applying chunks on top
+    /// of the block since that requires its flat storage delta.
+    fn apply_deferred_postprocessing(&mut self, block_hash: &CryptoHash) -> Result<(), Error> {
+        if !self.deferred_postprocessing.contains(block_hash) {
+            return Ok(());
+        }
+        for deferred in self.deferred_postprocessing.take_with_ancestors(block_hash) {
+            debug!(target: "chain", hash = %deferred.block_hash, height = deferred.height, "Applying deferred postprocessing of fork block");
+            // Each block is committed separately since the flat storage delta
+            // of a block refers to the delta of its parent in the store.
+            let mut chain_update = self.chain_update();
+            chain_update.apply_deferred_postprocessing(deferred)?;
+            chain_update.commit()?;
+            metrics::DEFERRED_POSTPROCESSING_APPLIED_TOTAL.inc();
+        }
+        Ok(())
+    }
+
     /// Run postprocessing on this block, which stores the block on chain.
     /// Check that if accepting the block unlocks any orphans in the orphan pool and start
     /// the processing of those blocks.
@@ -1861,6 +1960,14 @@ impl Chain {
                 Ok(new_head) => new_head,
             };
 
+        // Resharding needs flat storage at the last block of the epoch, even
+        // if it's on a fork.
+        if self.deferred_postprocessing.contains(block.hash())
+            && self.epoch_manager.is_next_block_epoch_start(block.hash())?
+        {
+            self.apply_deferred_postprocessing(block.hash())?;
+        }
+
         self.update_optimistic_blocks_pool(&block)?;
 
         let epoch_id = block.header().epoch_id();
@@ -1900,7 +2007,7 @@ impl Chain {
                     self.chain_store.store_update(),
                     &block,
                     shard_uid,
-                    self.runtime_adapter.get_tries(),
+                    self.runtime_adapter.as_ref(),
                 )?;
 
                 // Update flat storage head to be the last final block. Note that this update happens
@@ -1920,6 +2027,27 @@ impl Chain {
         }
 
         self.pending_state_patch.clear();
+        self.runtime_adapter.get_tries().maybe_save_access_heat_map();
+
+        let pruned = self.deferred_postprocessing.prune(self.chain_store.final_head()?.height);
+        if !pruned.is_empty() {
+            let mut store_update = self.chain_store.store().store_update();
+            for block_hash in &pruned {
+                DeferredBlockPostprocessing::remove(block_hash, &mut store_update);
+            }
+            store_update.commit()?;
+        }
+        while let Some(block_hash) = self.deferred_postprocessing.overflowing_block() {
+            self.apply_deferred_postprocessing(&block_hash)?;
+        }
+
+        if let Some(receipt_audit) = &mut self.receipt_audit {
+            if let Err(err) =
+                receipt_audit.audit_final_blocks(&self.chain_store, self.epoch_manager.as_ref())
+            {
+                warn!(target: "chain", ?err, "Failed to audit the receipts of the final blocks");
+            }
+        }
 
         if let Some(tip) = &new_head {
             // TODO: move this logic of tracking validators metrics to EpochManager
@@ -2535,6 +2663,31 @@ impl Chain {
         chain_store_update.commit()
     }
 
+    /// Deletes a chunk which got corrupted in the store, so that it's
+    /// downloaded again. Panics if the same chunk got corrupted too many times,
+    /// as the node can't recover from such a storage failure by itself.
+    fn evict_corrupted_chunk(&mut self, chunk_header: &ShardChunkHeader) -> Result<(), Error> {
+        let chunk_hash = chunk_header.chunk_hash();
+        let mut store_update = self.chain_store.store().store_update();
+        let evictions = self.corrupted_chunks.record_eviction(chunk_header, &mut store_update)?;
+        if evictions > MAX_CORRUPTED_CHUNK_EVICTIONS {
+            panic!(
+                "Chunk {:?} is still corrupted after being downloaded {} times, the database must be repaired",
+                chunk_hash, MAX_CORRUPTED_CHUNK_EVICTIONS
+            );
+        }
+        metrics::CORRUPTED_CHUNKS_EVICTED_TOTAL.inc();
+        store_update.delete(DBCol::Chunks, chunk_hash.as_ref());
+        store_update.commit()?;
+        Ok(())
+    }
+
+    /// Returns the headers of the chunks evicted as corrupted which weren't
+    /// downloaded again yet, also before a restart.
+    pub fn corrupted_chunks_to_fetch(&self) -> Result<Vec<ShardChunkHeader>, Error> {
+        self.corrupted_chunks.not_refetched(&self.chain_store)
+    }
+
     /// Drop all downloaded or generated state parts and headers.
     pub fn clear_all_downloaded_parts(&mut self) -> Result<(), Error> {
         tracing::debug!(target: "state_sync", "Clear old state parts");
@@ -2731,12 +2884,15 @@ impl Chain {
                 shard_id,
                 true,
             ) {
+                // Also done for the chunks applied before a restart, in case
+                // the node stopped before updating the shard. Moving the flat
+                // head back to a block behind it does nothing.
                 let shard_uid = shard_id_to_uid(self.epoch_manager.as_ref(), shard_id, epoch_id)?;
                 self.resharding_manager.start_resharding(
                     self.chain_store.store_update(),
                     &block,
                     shard_uid,
-                    self.runtime_adapter.get_tries(),
+                    self.runtime_adapter.as_ref(),
                 )?;
                 self.update_flat_storage_and_memtrie(&block, shard_id)?;
             }
@@ -2905,6 +3061,92 @@ impl Chain {
         Ok(FinalExecutionOutcomeView { status, transaction, transaction_outcome, receipts_outcome })
     }
 
+    /// Returns the tree of receipts executed for the given transaction, across
+    /// shards and blocks. The outcomes are indexed by the id of the transaction
+    /// or receipt, and by the block in which they were executed, so the tree is
+    /// walked from the receipt ids in the outcomes. The receipts which aren't
+    /// executed yet, or whose receiver is on a shard not tracked by `me`, are
+    /// the leaves of the tree.
+    pub fn get_receipt_tree(
+        &self,
+        transaction_hash: &CryptoHash,
+        me: Option<&AccountId>,
+    ) -> Result<ReceiptTreeView, Error> {
+        let outcome = self.get_execution_outcome(transaction_hash)?;
+        self.get_receipt_tree_from_outcome(outcome, me)
+    }
+
+    fn get_receipt_tree_from_outcome(
+        &self,
+        outcome: ExecutionOutcomeWithIdAndProof,
+        me: Option<&AccountId>,
+    ) -> Result<ReceiptTreeView, Error> {
+        let header = self.get_block_header(&outcome.block_hash)?;
+        let shard_layout = self.epoch_manager.get_shard_layout(header.epoch_id())?;
+        let ExecutionOutcomeWithId { id, outcome } = outcome.outcome_with_id;
+        let shard_id = shard_layout.account_id_to_shard_id(&outcome.executor_id);
+        let mut receipts = Vec::with_capacity(outcome.receipt_ids.len());
+        for receipt_id in &outcome.receipt_ids {
+            let receipt = match self.get_execution_outcome(receipt_id) {
+                Ok(outcome) => self.get_receipt_tree_from_outcome(outcome, me)?,
+                Err(Error::DBNotFoundErr(_)) => {
+                    let execution = self.get_unexecuted_receipt_state(
+                        receipt_id,
+                        header.hash(),
+                        shard_id,
+                        &shard_layout,
+                        me,
+                    )?;
+                    ReceiptTreeView { id: *receipt_id, execution, receipts: vec![] }
+                }
+                Err(err) => return Err(err),
+            };
+            receipts.push(receipt);
+        }
+        let execution = ReceiptTreeExecutionView {
+            shard_id,
+            executor_id: outcome.executor_id,
+            block_hash: *header.hash(),
+            block_height: header.height(),
+            gas_burnt: outcome.gas_burnt,
+            tokens_burnt: outcome.tokens_burnt,
+            status: outcome.status.into(),
+        };
+        Ok(ReceiptTreeView {
+            id,
+            execution: ReceiptTreeExecutionStateView::Executed(execution),
+            receipts,
+        })
+    }
+
+    /// Returns the state of a receipt without an execution outcome, produced
+    /// by the chunk of `shard_id` applied in `block_hash`. The outcome is only
+    /// missing because the receipt isn't executed yet if `me` tracks the shard
+    /// of its receiver.
+    fn get_unexecuted_receipt_state(
+        &self,
+        receipt_id: &CryptoHash,
+        block_hash: &CryptoHash,
+        shard_id: ShardId,
+        shard_layout: &ShardLayout,
+        me: Option<&AccountId>,
+    ) -> Result<ReceiptTreeExecutionStateView, Error> {
+        // The local receipts aren't in the outgoing receipts, but their
+        // receiver is on the tracked shard which produced them.
+        let outgoing_receipts = self.chain_store.get_outgoing_receipts(block_hash, shard_id)?;
+        let Some(receipt) =
+            outgoing_receipts.iter().find(|receipt| receipt.receipt_id() == receipt_id)
+        else {
+            return Ok(ReceiptTreeExecutionStateView::Pending);
+        };
+        let receiver_shard_id = shard_layout.account_id_to_shard_id(receipt.receiver_id());
+        if self.shard_tracker.cares_about_shard(me, block_hash, receiver_shard_id, true) {
+            Ok(ReceiptTreeExecutionStateView::Pending)
+        } else {
+            Ok(ReceiptTreeExecutionStateView::Untracked { shard_id: receiver_shard_id })
+        }
+    }
+
     /// Returns corresponding receipts for provided outcome
     /// The incoming list in receipts_outcome may be partial
     pub fn get_transaction_result_with_receipt(
@@ -3202,6 +3444,13 @@ impl Chain {
         if !shard_context.should_apply_chunk {
             return Ok(None);
         }
+        // E.g. a chunk applied while catching up before a restart, as catch up
+        // starts again from the first block of the epoch.
+        if self.chain_store.is_chunk_apply_journaled(&block.block_hash, shard_id)? {
+            debug!(target: "chain", ?shard_id, block_hash = ?block.block_hash, ?mode, "Chunk already applied");
+            metrics::CHUNK_APPLIES_SKIPPED.inc();
+            return Ok(None);
+        }
 
         let chunk_header = chunk_headers.get(shard_index).ok_or(Error::InvalidShardId(shard_id))?;
         let is_new_chunk = chunk_header.is_new_chunk(block_height);
@@ -3344,9 +3593,72 @@ impl Chain {
             }
             SnapshotAction::None => {}
         };
+        if let Some(trigger) = self.should_make_restore_point()? {
+            let head = self.head()?;
+            let make_restore_point_callback = &snapshot_callbacks.make_restore_point_callback;
+            make_restore_point_callback(trigger, head.height, head.last_block_hash);
+        }
         Ok(())
     }
 
+    /// Checks whether the restore point policy asks for a restore point of the
+    /// current head. Like `should_make_snapshot`, this is called before the
+    /// head is updated to the block being processed.
+    fn should_make_restore_point(&self) -> Result<Option<RestorePointTrigger>, Error> {
+        let tries = self.runtime_adapter.get_tries();
+        let Some(policy) = tries.state_snapshot_config().restore_point_policy() else {
+            return Ok(None);
+        };
+        let head = self.head()?;
+        if head.prev_block_hash == CryptoHash::default() {
+            return Ok(None);
+        }
+
+        if policy.before_protocol_upgrade
+            && self.epoch_manager.is_next_block_epoch_start(&head.last_block_hash)?
+        {
+            let protocol_version = self.epoch_manager.get_epoch_protocol_version(&head.epoch_id)?;
+            let next_protocol_version =
+                self.epoch_manager.get_epoch_protocol_version(&head.next_epoch_id)?;
+            if next_protocol_version > protocol_version {
+                return Ok(Some(RestorePointTrigger::BeforeProtocolUpgrade));
+            }
+        }
+        if policy.every_n_blocks.is_some() {
+            // Compare with the previous block rather than check the head height
+            // alone, so that skipped heights don't cause missed restore points.
+            let prev_height = self.get_block_header(&head.prev_block_hash)?.height();
+            if policy.reaches_every_n_blocks(prev_height, head.height) {
+                return Ok(Some(RestorePointTrigger::EveryNBlocks));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Requests a restore point of the current head, if the restore point
+    /// policy allows taking them on demand. Returns the height of the head.
+    pub fn request_restore_point(&self) -> Result<BlockHeight, Error> {
+        let tries = self.runtime_adapter.get_tries();
+        if !tries
+            .state_snapshot_config()
+            .restore_point_policy()
+            .is_some_and(|policy| policy.on_demand)
+        {
+            return Err(Error::Other("on-demand restore points are disabled".to_string()));
+        }
+        let Some(snapshot_callbacks) = &self.snapshot_callbacks else {
+            return Err(Error::Other("state snapshots are not available".to_string()));
+        };
+        let head = self.head()?;
+        let make_restore_point_callback = &snapshot_callbacks.make_restore_point_callback;
+        make_restore_point_callback(
+            RestorePointTrigger::OnDemand,
+            head.height,
+            head.last_block_hash,
+        );
+        Ok(head.height)
+    }
+
     /// Function to check whether we need to create a new snapshot while processing the current block
     /// Note that this functions is called as a part of block preprocessing, so the head is not updated to current block
     fn should_make_snapshot(&self) -> Result<SnapshotAction, Error> {
@@ -3458,6 +3770,11 @@ impl Chain {
         self.chain_store.final_head()
     }
 
+    /// Returns the state of the receipt audit, or `None` if it's disabled.
+    pub fn get_receipt_audit_view(&self) -> Option<ReceiptAuditView> {
+        self.receipt_audit.as_ref().map(ReceiptAudit::view)
+    }
+
     /// Gets a block by hash.
     #[inline]
     pub fn get_block(&self, hash: &CryptoHash) -> Result<Block, Error> {
@@ -3763,20 +4080,53 @@ impl Chain {
     }
 }
 
+/// Applies the chunks of a block in parallel on the rayon thread pool and
+/// returns the results in the order of `work`.
+/// If `max_concurrency` is set, at most that many chunks are applied at once,
+/// which bounds the memory used by nodes tracking many shards.
 pub fn do_apply_chunks(
     block: BlockToApply,
     block_height: BlockHeight,
     work: Vec<UpdateShardJob>,
+    max_concurrency: Option<usize>,
 ) -> Vec<(ShardId, CachedShardUpdateKey, Result<ShardUpdateResult, Error>)> {
     let parent_span =
         tracing::debug_span!(target: "chain", "do_apply_chunks", block_height, ?block).entered();
-    work.into_par_iter()
-        .map(|(shard_id, cached_shard_update_key, task)| {
-            // As chunks can be processed in parallel, make sure they are all tracked as children of
-            // a single span.
-            (shard_id, cached_shard_update_key, task(&parent_span))
-        })
-        .collect()
+    let num_jobs = work.len();
+    let num_lanes = max_concurrency.map_or(num_jobs, |max| max.max(1).min(num_jobs));
+    if num_lanes == num_jobs {
+        return work
+            .into_par_iter()
+            .map(|(shard_id, cached_shard_update_key, task)| {
+                // As chunks can be processed in parallel, make sure they are all tracked as children of
+                // a single span.
+                (shard_id, cached_shard_update_key, task(&parent_span))
+            })
+            .collect();
+    }
+
+    // Every lane applies the next pending chunk until none is left, so that a
+    // lane which is done early picks up the remaining work instead of waiting.
+    let pending = parking_lot::Mutex::new(work.into_iter().enumerate());
+    let results = parking_lot::Mutex::new(Vec::with_capacity(num_jobs));
+    rayon::scope(|scope| {
+        for _ in 0..num_lanes {
+            scope.spawn(|_| {
+                loop {
+                    let Some((index, (shard_id, cached_shard_update_key, task))) =
+                        pending.lock().next()
+                    else {
+                        break;
+                    };
+                    let result = task(&parent_span);
+                    results.lock().push((index, (shard_id, cached_shard_update_key, result)));
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
 }
 
 pub fn collect_receipts<'a, T>(receipt_proofs: T) -> Vec<Receipt>
@@ -1,6 +1,7 @@
 use crate::approval_verification::verify_approvals_and_threshold_orphan;
 use crate::block_processing_utils::BlockPreprocessInfo;
 use crate::chain::collect_receipts_from_response;
+use crate::deferred_postprocessing::{DeferredBlockPostprocessing, DeferredShardPostprocessing};
 use crate::metrics::{SHARD_LAYOUT_NUM_SHARDS, SHARD_LAYOUT_VERSION};
 use crate::store::utils::get_block_header_on_chain_by_height;
 use crate::store::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
@@ -15,7 +16,7 @@ use near_chain_primitives::error::Error;
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_assignment::shard_id_to_uid;
 use near_primitives::apply::ApplyChunkReason;
-use near_primitives::block::{Block, Tip};
+use near_primitives::block::{Block, MaybeNew, Tip};
 use near_primitives::block_header::BlockHeader;
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::hash::CryptoHash;
@@ -38,6 +39,8 @@ pub struct ChainUpdate<'a> {
     runtime_adapter: Arc<dyn RuntimeAdapter>,
     chain_store_update: ChainStoreUpdate<'a>,
     doomslug_threshold_mode: DoomslugThresholdMode,
+    /// Set while postprocessing a fork block whose writes are deferred.
+    deferred_postprocessing: Option<DeferredBlockPostprocessing>,
 }
 
 impl<'a> ChainUpdate<'a> {
@@ -57,7 +60,13 @@ impl<'a> ChainUpdate<'a> {
         doomslug_threshold_mode: DoomslugThresholdMode,
         chain_store_update: ChainStoreUpdate<'a>,
     ) -> Self {
-        ChainUpdate { epoch_manager, runtime_adapter, chain_store_update, doomslug_threshold_mode }
+        ChainUpdate {
+            epoch_manager,
+            runtime_adapter,
+            chain_store_update,
+            doomslug_threshold_mode,
+            deferred_postprocessing: None,
+        }
     }
 
     /// Commit changes to the chain into the database.
@@ -79,6 +88,33 @@ impl<'a> ChainUpdate<'a> {
         Ok(())
     }
 
+    /// Indexes the transactions of the new chunk of the shard by signer, if
+    /// the index is enabled.
+    fn save_transactions_by_signer(
+        &mut self,
+        block: &Block,
+        shard_id: ShardId,
+    ) -> Result<(), Error> {
+        if self.chain_store_update.chain_store().transactions_by_signer_limit().is_none() {
+            return Ok(());
+        }
+        let chunk_header = block.chunks().iter().find_map(|chunk_header| match chunk_header {
+            MaybeNew::New(chunk_header) if chunk_header.shard_id() == shard_id => {
+                Some(chunk_header)
+            }
+            _ => None,
+        });
+        let Some(chunk_header) = chunk_header else {
+            return Ok(());
+        };
+        let chunk = self.chain_store_update.get_chunk(&chunk_header.chunk_hash())?;
+        self.chain_store_update.save_transactions_by_signer(
+            block.hash(),
+            block.header().height(),
+            chunk.to_transactions(),
+        )
+    }
+
     /// Process results of applying chunk
     fn process_apply_chunk_result(
         &mut self,
@@ -111,15 +147,36 @@ impl<'a> ChainUpdate<'a> {
                     ),
                 );
 
-                let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
-                let store_update = flat_storage_manager.save_flat_state_changes(
-                    *block_hash,
-                    *prev_hash,
-                    height,
-                    shard_uid,
-                    apply_result.trie_changes.state_changes(),
-                )?;
-                self.chain_store_update.merge(store_update.into());
+                if let Some(deferred) = &mut self.deferred_postprocessing {
+                    deferred.shards.push(DeferredShardPostprocessing {
+                        shard_uid,
+                        state_changes: apply_result.trie_changes.state_changes().to_vec(),
+                        outcomes: Some((apply_result.outcomes, outcome_paths)),
+                        stats: apply_result.stats,
+                    });
+                } else {
+                    let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
+                    let store_update = flat_storage_manager.save_flat_state_changes(
+                        *block_hash,
+                        *prev_hash,
+                        height,
+                        shard_uid,
+                        apply_result.trie_changes.state_changes(),
+                    )?;
+                    self.chain_store_update.merge(store_update.into());
+                    // Save receipt and transaction results.
+                    self.chain_store_update.save_outcomes_with_proofs(
+                        block_hash,
+                        shard_id,
+                        apply_result.outcomes,
+                        outcome_paths,
+                    );
+                    self.chain_store_update.save_chunk_apply_stats(
+                        *block_hash,
+                        shard_id,
+                        apply_result.stats,
+                    );
+                }
 
                 self.chain_store_update.save_trie_changes(*block_hash, apply_result.trie_changes);
                 self.chain_store_update.save_outgoing_receipt(
@@ -127,13 +184,8 @@ impl<'a> ChainUpdate<'a> {
                     shard_id,
                     apply_result.outgoing_receipts,
                 );
-                // Save receipt and transaction results.
-                self.chain_store_update.save_outcomes_with_proofs(
-                    block_hash,
-                    shard_id,
-                    apply_result.outcomes,
-                    outcome_paths,
-                );
+                self.save_transactions_by_signer(block, shard_id)?;
+                self.chain_store_update.save_chunk_apply_journal(*block_hash, shard_id);
                 if should_save_state_transition_data {
                     self.chain_store_update.save_state_transition_data(
                         *block_hash,
@@ -143,11 +195,6 @@ impl<'a> ChainUpdate<'a> {
                         apply_result.contract_updates,
                     );
                 }
-                self.chain_store_update.save_chunk_apply_stats(
-                    *block_hash,
-                    shard_id,
-                    apply_result.stats,
-                );
             }
             ShardUpdateResult::OldChunk(OldChunkResult { shard_uid, apply_result }) => {
                 // The chunk is missing but some fields may need to be updated
@@ -157,18 +204,33 @@ impl<'a> ChainUpdate<'a> {
                 let mut new_extra = ChunkExtra::clone(&old_extra);
                 *new_extra.state_root_mut() = apply_result.new_root;
 
-                let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
-                let store_update = flat_storage_manager.save_flat_state_changes(
-                    *block_hash,
-                    *prev_hash,
-                    height,
-                    shard_uid,
-                    apply_result.trie_changes.state_changes(),
-                )?;
-                self.chain_store_update.merge(store_update.into());
+                if let Some(deferred) = &mut self.deferred_postprocessing {
+                    deferred.shards.push(DeferredShardPostprocessing {
+                        shard_uid,
+                        state_changes: apply_result.trie_changes.state_changes().to_vec(),
+                        outcomes: None,
+                        stats: apply_result.stats,
+                    });
+                } else {
+                    let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
+                    let store_update = flat_storage_manager.save_flat_state_changes(
+                        *block_hash,
+                        *prev_hash,
+                        height,
+                        shard_uid,
+                        apply_result.trie_changes.state_changes(),
+                    )?;
+                    self.chain_store_update.merge(store_update.into());
+                    self.chain_store_update.save_chunk_apply_stats(
+                        *block_hash,
+                        shard_uid.shard_id(),
+                        apply_result.stats,
+                    );
+                }
 
                 self.chain_store_update.save_chunk_extra(block_hash, &shard_uid, new_extra);
                 self.chain_store_update.save_trie_changes(*block_hash, apply_result.trie_changes);
+                self.chain_store_update.save_chunk_apply_journal(*block_hash, shard_uid.shard_id());
                 if should_save_state_transition_data {
                     self.chain_store_update.save_state_transition_data(
                         *block_hash,
@@ -178,11 +240,6 @@ impl<'a> ChainUpdate<'a> {
                         apply_result.contract_updates,
                     );
                 }
-                self.chain_store_update.save_chunk_apply_stats(
-                    *block_hash,
-                    shard_uid.shard_id(),
-                    apply_result.stats,
-                );
             }
         };
         Ok(())
@@ -208,6 +265,10 @@ impl<'a> ChainUpdate<'a> {
 
     /// This is the last step of process_block_single, where we take the preprocess block info
     /// apply chunk results and store the results on chain.
+    ///
+    /// If `defer_if_fork` is set and the block won't become the new head, its
+    /// flat state changes, outcomes and apply stats are not written.  They can
+    /// be retrieved with [`Self::take_deferred_postprocessing`] instead.
     #[tracing::instrument(
         level = "debug",
         target = "chain",
@@ -220,8 +281,16 @@ impl<'a> ChainUpdate<'a> {
         block_preprocess_info: BlockPreprocessInfo,
         apply_chunks_results: Vec<(ShardId, Result<ShardUpdateResult, Error>)>,
         should_save_state_transition_data: bool,
+        defer_if_fork: bool,
     ) -> Result<Option<Tip>, Error> {
         let prev_hash = block.header().prev_hash();
+        if defer_if_fork && block.header().height() <= self.chain_store_update.head()?.height {
+            self.deferred_postprocessing = Some(DeferredBlockPostprocessing::new(
+                *block.hash(),
+                *prev_hash,
+                block.header().height(),
+            ));
+        }
         let results = apply_chunks_results.into_iter().map(|(shard_id, x)| {
             if let Err(err) = &x {
                 warn!(target: "chain", ?shard_id, hash = %block.hash(), %err, "Error in applying chunk for block");
@@ -300,6 +369,53 @@ impl<'a> ChainUpdate<'a> {
         Ok(res)
     }
 
+    /// Takes the deferred postprocessing of the block and saves it in the
+    /// same update as the rest of the block.
+    pub(crate) fn take_deferred_postprocessing(
+        &mut self,
+    ) -> Result<Option<DeferredBlockPostprocessing>, Error> {
+        let Some(deferred) = self.deferred_postprocessing.take() else {
+            return Ok(None);
+        };
+        let mut store_update = self.chain_store_update.store().store_update();
+        deferred.save(&mut store_update)?;
+        self.chain_store_update.merge(store_update);
+        Ok(Some(deferred))
+    }
+
+    /// Writes postprocessing of a fork block which was previously deferred.
+    pub(crate) fn apply_deferred_postprocessing(
+        &mut self,
+        deferred: DeferredBlockPostprocessing,
+    ) -> Result<(), Error> {
+        let DeferredBlockPostprocessing { block_hash, prev_hash, height, shards } = deferred;
+        let mut store_update = self.chain_store_update.store().store_update();
+        DeferredBlockPostprocessing::remove(&block_hash, &mut store_update);
+        self.chain_store_update.merge(store_update);
+        let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
+        for shard in shards {
+            let shard_id = shard.shard_uid.shard_id();
+            let store_update = flat_storage_manager.save_flat_state_changes(
+                block_hash,
+                prev_hash,
+                height,
+                shard.shard_uid,
+                &shard.state_changes,
+            )?;
+            self.chain_store_update.merge(store_update.into());
+            if let Some((outcomes, outcome_paths)) = shard.outcomes {
+                self.chain_store_update.save_outcomes_with_proofs(
+                    &block_hash,
+                    shard_id,
+                    outcomes,
+                    outcome_paths,
+                );
+            }
+            self.chain_store_update.save_chunk_apply_stats(block_hash, shard_id, shard.stats);
+        }
+        Ok(())
+    }
+
     pub fn create_light_client_block(
         &mut self,
         header: &BlockHeader,
@@ -391,6 +507,13 @@ impl<'a> ChainUpdate<'a> {
             let tip = Tip::from_header(header);
 
             self.chain_store_update.save_body_head(&tip)?;
+            // Only the final head of the store moves with the head. The last
+            // final block of the headers, and what follows it, like the flat
+            // storage head and the finality in the epoch manager, still
+            // follow doomslug and lag behind.
+            if self.doomslug_threshold_mode == DoomslugThresholdMode::SingleBlockFinality {
+                self.chain_store_update.save_final_head(&tip)?;
+            }
             metrics::BLOCK_HEIGHT_HEAD.set(tip.height as i64);
             metrics::BLOCK_ORDINAL_HEAD.set(header.block_ordinal() as i64);
             debug!(target: "chain", "Head updated to {} at {}", tip.last_block_hash, tip.height);
@@ -0,0 +1,78 @@
+//! Chunks evicted from the store because they got corrupted.
+//!
+//! A chunk which can't be decoded from the store, or whose stored hash doesn't
+//! match its header, is deleted so that it's fetched again from the peers.
+//! The evicted chunks are saved in `DBCol::Misc` together with the number of
+//! times each of them was evicted, so the fetch is retried until the chunk is
+//! saved again, also after a restart, and a chunk which keeps getting
+//! corrupted is noticed.
+
+use crate::Error;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::sharding::ShardChunkHeader;
+use near_store::adapter::chain_store::ChainStoreAdapter;
+use near_store::db::CORRUPTED_CHUNKS_KEY;
+use near_store::{DBCol, Store, StoreUpdate};
+
+/// Number of times a corrupted chunk is evicted from the store and downloaded
+/// again before the node gives up.
+pub(crate) const MAX_CORRUPTED_CHUNK_EVICTIONS: u32 = 3;
+
+/// Maximum number of evicted chunks which are remembered.  When exceeded, the
+/// one evicted the longest time ago is forgotten.
+const MAX_CORRUPTED_CHUNKS: usize = 100;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct CorruptedChunk {
+    header: ShardChunkHeader,
+    evictions: u32,
+}
+
+/// The evicted chunks, ordered from the least to the most recently evicted.
+#[derive(Default, BorshSerialize, BorshDeserialize)]
+pub(crate) struct CorruptedChunks {
+    chunks: Vec<CorruptedChunk>,
+}
+
+impl CorruptedChunks {
+    /// Loads the evicted chunks saved before a restart.
+    pub fn load(store: &Store) -> std::io::Result<Self> {
+        Ok(store.get_ser(DBCol::Misc, CORRUPTED_CHUNKS_KEY)?.unwrap_or_default())
+    }
+
+    /// Records another eviction of the chunk and saves the records in
+    /// `store_update`.  Returns the number of times the chunk was evicted.
+    pub fn record_eviction(
+        &mut self,
+        header: &ShardChunkHeader,
+        store_update: &mut StoreUpdate,
+    ) -> std::io::Result<u32> {
+        let chunk_hash = header.chunk_hash();
+        let mut chunk = match self.chunks.iter().position(|c| c.header.chunk_hash() == chunk_hash) {
+            Some(index) => self.chunks.remove(index),
+            None => CorruptedChunk { header: header.clone(), evictions: 0 },
+        };
+        chunk.evictions += 1;
+        let evictions = chunk.evictions;
+        self.chunks.push(chunk);
+        if self.chunks.len() > MAX_CORRUPTED_CHUNKS {
+            self.chunks.remove(0);
+        }
+        store_update.set_ser(DBCol::Misc, CORRUPTED_CHUNKS_KEY, self)?;
+        Ok(evictions)
+    }
+
+    /// Returns the headers of the evicted chunks which weren't saved again yet.
+    pub fn not_refetched(
+        &self,
+        chain_store: &ChainStoreAdapter,
+    ) -> Result<Vec<ShardChunkHeader>, Error> {
+        let mut headers = vec![];
+        for chunk in &self.chunks {
+            if !chain_store.chunk_exists(&chunk.header.chunk_hash())? {
+                headers.push(chunk.header.clone());
+            }
+        }
+        Ok(headers)
+    }
+}
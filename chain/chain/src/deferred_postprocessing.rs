@@ -0,0 +1,216 @@
+//! Lazy postprocessing of blocks on non-head forks.
+//!
+//! Every applied block gets its flat state delta, transaction outcomes and
+//! chunk apply stats written to the database, even if it is on a fork which
+//! will never become canonical.  During forky periods this is a lot of wasted
+//! IO.  With `defer_fork_postprocessing` enabled, for blocks which don't become
+//! the head these writes are kept in memory instead and only performed once
+//! the fork gets extended, i.e. once a child of such block is about to be
+//! applied.  Entries for blocks which can no longer be extended because they
+//! are at or below the final head are dropped.
+//!
+//! The deferred writes are also saved in `DBCol::DeferredPostprocessing`, in
+//! the same update as the rest of the block, and loaded back on start, so a
+//! fork can still be extended after a restart.  The single row is much
+//! smaller than the writes it stands for, which are spread over several
+//! columns and shards.
+
+use crate::metrics;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::chunk_apply_stats::ChunkApplyStatsV0;
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::MerklePath;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::transaction::ExecutionOutcomeWithId;
+use near_primitives::types::{BlockHeight, RawStateChangesWithTrieKey};
+use near_store::{DBCol, Store, StoreUpdate};
+use std::collections::HashMap;
+
+/// Maximum number of blocks with deferred postprocessing.  When exceeded, the
+/// lowest block is postprocessed right away.
+pub(crate) const MAX_DEFERRED_BLOCKS: usize = 64;
+
+/// Writes for a single shard of a block which were deferred.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub(crate) struct DeferredShardPostprocessing {
+    pub shard_uid: ShardUId,
+    pub state_changes: Vec<RawStateChangesWithTrieKey>,
+    /// Outcomes with their proofs.  `None` for missing chunks.
+    pub outcomes: Option<(Vec<ExecutionOutcomeWithId>, Vec<MerklePath>)>,
+    pub stats: ChunkApplyStatsV0,
+}
+
+/// Writes for a block which were deferred.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub(crate) struct DeferredBlockPostprocessing {
+    pub block_hash: CryptoHash,
+    pub prev_hash: CryptoHash,
+    pub height: BlockHeight,
+    pub shards: Vec<DeferredShardPostprocessing>,
+}
+
+impl DeferredBlockPostprocessing {
+    pub fn new(block_hash: CryptoHash, prev_hash: CryptoHash, height: BlockHeight) -> Self {
+        Self { block_hash, prev_hash, height, shards: vec![] }
+    }
+
+    /// Saves the deferred writes, so that they survive a restart.
+    pub fn save(&self, store_update: &mut StoreUpdate) -> std::io::Result<()> {
+        store_update.set_ser(DBCol::DeferredPostprocessing, self.block_hash.as_ref(), self)
+    }
+
+    /// Removes the saved writes, once they are written or dropped.
+    pub fn remove(block_hash: &CryptoHash, store_update: &mut StoreUpdate) {
+        store_update.delete(DBCol::DeferredPostprocessing, block_hash.as_ref());
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct DeferredPostprocessingPool {
+    blocks: HashMap<CryptoHash, DeferredBlockPostprocessing>,
+}
+
+impl DeferredPostprocessingPool {
+    /// Loads the deferred postprocessing saved before a restart.
+    pub fn load(store: &Store) -> std::io::Result<Self> {
+        let mut pool = Self::default();
+        for item in store.iter_ser::<DeferredBlockPostprocessing>(DBCol::DeferredPostprocessing) {
+            let (_, block) = item?;
+            pool.blocks.insert(block.block_hash, block);
+        }
+        metrics::DEFERRED_POSTPROCESSING_BLOCKS.set(pool.blocks.len() as i64);
+        Ok(pool)
+    }
+
+    pub fn insert(&mut self, block: DeferredBlockPostprocessing) {
+        self.blocks.insert(block.block_hash, block);
+        metrics::DEFERRED_POSTPROCESSING_BLOCKS.set(self.blocks.len() as i64);
+    }
+
+    pub fn contains(&self, block_hash: &CryptoHash) -> bool {
+        self.blocks.contains_key(block_hash)
+    }
+
+    /// Removes `block_hash` and all its ancestors from the pool and returns
+    /// them ordered from the oldest, i.e. in the order they need to be
+    /// written.
+    pub fn take_with_ancestors(
+        &mut self,
+        block_hash: &CryptoHash,
+    ) -> Vec<DeferredBlockPostprocessing> {
+        let mut result = vec![];
+        let mut hash = *block_hash;
+        while let Some(block) = self.blocks.remove(&hash) {
+            hash = block.prev_hash;
+            result.push(block);
+        }
+        result.reverse();
+        metrics::DEFERRED_POSTPROCESSING_BLOCKS.set(self.blocks.len() as i64);
+        result
+    }
+
+    /// Returns the lowest block if there are more than [`MAX_DEFERRED_BLOCKS`]
+    /// blocks in the pool.  None of its ancestors is in the pool.
+    pub fn overflowing_block(&self) -> Option<CryptoHash> {
+        if self.blocks.len() <= MAX_DEFERRED_BLOCKS {
+            return None;
+        }
+        self.blocks.values().min_by_key(|block| block.height).map(|block| block.block_hash)
+    }
+
+    /// Drops blocks which can no longer be extended and returns their hashes,
+    /// so that their saved writes can be removed.
+    pub fn prune(&mut self, final_height: BlockHeight) -> Vec<CryptoHash> {
+        let pruned = self
+            .blocks
+            .values()
+            .filter(|block| block.height <= final_height)
+            .map(|block| block.block_hash)
+            .collect::<Vec<_>>();
+        for block_hash in &pruned {
+            self.blocks.remove(block_hash);
+        }
+        metrics::DEFERRED_POSTPROCESSING_DISCARDED_TOTAL.inc_by(pruned.len() as u64);
+        metrics::DEFERRED_POSTPROCESSING_BLOCKS.set(self.blocks.len() as i64);
+        pruned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeferredBlockPostprocessing, DeferredPostprocessingPool};
+    use near_primitives::hash::CryptoHash;
+    use near_store::test_utils::create_test_store;
+
+    fn block(prev: &DeferredBlockPostprocessing) -> DeferredBlockPostprocessing {
+        let hash = CryptoHash::hash_borsh((prev.block_hash, prev.height + 1));
+        DeferredBlockPostprocessing::new(hash, prev.block_hash, prev.height + 1)
+    }
+
+    #[test]
+    fn test_take_with_ancestors() {
+        let mut pool = DeferredPostprocessingPool::default();
+        let root = DeferredBlockPostprocessing::new(
+            CryptoHash::hash_bytes(b"root"),
+            CryptoHash::default(),
+            10,
+        );
+        let a1 = block(&root);
+        let a2 = block(&a1);
+        let b1 =
+            DeferredBlockPostprocessing::new(CryptoHash::hash_bytes(b"b1"), root.block_hash, 12);
+        let (root_hash, a1_hash, a2_hash, b1_hash) =
+            (root.block_hash, a1.block_hash, a2.block_hash, b1.block_hash);
+        // The root isn't deferred, e.g. because it was the head when processed.
+        pool.insert(a1);
+        pool.insert(a2);
+        pool.insert(b1);
+
+        let taken = pool.take_with_ancestors(&a2_hash);
+        let taken = taken.iter().map(|block| block.block_hash).collect::<Vec<_>>();
+        assert_eq!(taken, vec![a1_hash, a2_hash]);
+        assert!(pool.contains(&b1_hash));
+        assert!(pool.take_with_ancestors(&root_hash).is_empty());
+
+        assert_eq!(pool.prune(12), vec![b1_hash]);
+        assert!(!pool.contains(&b1_hash));
+    }
+
+    /// The deferred blocks are loaded back after a restart, until they are
+    /// pruned.
+    #[test]
+    fn test_load_after_restart() {
+        let store = create_test_store();
+        let root = DeferredBlockPostprocessing::new(
+            CryptoHash::hash_bytes(b"root"),
+            CryptoHash::default(),
+            10,
+        );
+        let a1 = block(&root);
+        let a2 = block(&a1);
+        let (a1_hash, a2_hash) = (a1.block_hash, a2.block_hash);
+        let mut pool = DeferredPostprocessingPool::default();
+        let mut store_update = store.store_update();
+        for block in [a1, a2] {
+            block.save(&mut store_update).unwrap();
+            pool.insert(block);
+        }
+        store_update.commit().unwrap();
+
+        let mut pool = DeferredPostprocessingPool::load(&store).unwrap();
+        assert!(pool.contains(&a1_hash) && pool.contains(&a2_hash));
+        let taken = pool.take_with_ancestors(&a2_hash);
+        assert_eq!(
+            taken.iter().map(|block| block.prev_hash).collect::<Vec<_>>(),
+            vec![root.block_hash, a1_hash]
+        );
+
+        let mut store_update = store.store_update();
+        DeferredBlockPostprocessing::remove(&a1_hash, &mut store_update);
+        store_update.commit().unwrap();
+        let mut pool = DeferredPostprocessingPool::load(&store).unwrap();
+        assert!(!pool.contains(&a1_hash) && pool.contains(&a2_hash));
+        assert_eq!(pool.prune(11), Vec::<CryptoHash>::new());
+        assert_eq!(pool.prune(12), vec![a2_hash]);
+    }
+}
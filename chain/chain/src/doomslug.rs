@@ -35,10 +35,24 @@ const MAX_HISTORY_SIZE: usize = 1000;
 ///             and is what should be used in production (and what guarantees finality)
 /// `NoApprovals` means the block production is not blocked on approvals. This is used
 ///             in many tests (e.g. `cross_shard_tx`) to create lots of forks.
+/// `SingleBlockFinality` means the block production is not blocked on approvals either, and
+///             every block which becomes the head is final, so that the blocks not building
+///             on the head are dropped. This is used in tests which want the chain to
+///             finalize immediately and don't deal with forks. Only the final head of the
+///             chain store is moved: `BlockHeader::last_final_block`, and the flat storage
+///             head and the epoch manager finality derived from it, still follow doomslug.
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum DoomslugThresholdMode {
     NoApprovals,
     TwoThirds,
+    SingleBlockFinality,
+}
+
+impl DoomslugThresholdMode {
+    /// Whether the blocks can only be produced with enough approvals.
+    pub fn requires_approvals(self) -> bool {
+        self == DoomslugThresholdMode::TwoThirds
+    }
 }
 
 /// The result of processing an approval.
@@ -240,7 +254,7 @@ impl DoomslugApprovalsTracker {
         if (self.approved_stake_this_epoch > self.total_stake_this_epoch * 2 / 3
             && (self.approved_stake_next_epoch > self.total_stake_next_epoch * 2 / 3
                 || self.total_stake_next_epoch == 0))
-            || self.threshold_mode == DoomslugThresholdMode::NoApprovals
+            || !self.threshold_mode.requires_approvals()
         {
             if self.time_passed_threshold == None {
                 self.time_passed_threshold = Some(self.clock.now());
@@ -540,15 +554,18 @@ impl Doomslug {
         target_height: BlockHeight,
         signer: &Option<Arc<ValidatorSigner>>,
     ) -> Option<Approval> {
-        signer.as_ref().map(|signer| {
-            Approval::new(self.tip.block_hash, self.tip.height, target_height, &*signer)
-        })
+        let signer = signer.as_ref()?;
+        Approval::new(self.tip.block_hash, self.tip.height, target_height, signer)
+            .inspect_err(|err| {
+                tracing::error!(target: "doomslug", target_height, ?err, "Failed to sign approval");
+            })
+            .ok()
     }
 
     /// Determines whether a block has enough approvals to be produced.
     /// In production (with `mode == HalfStake`) we require the total stake of all the approvals to
     /// be strictly more than half of the total stake. For many non-doomslug specific tests
-    /// (with `mode == NoApprovals`) no approvals are needed.
+    /// (with `mode == NoApprovals` or `mode == SingleBlockFinality`) no approvals are needed.
     ///
     /// # Arguments
     /// * `mode`      - whether we want half of the total stake or just a single approval
@@ -559,7 +576,7 @@ impl Doomslug {
         approvals: &[Option<Box<Signature>>],
         stakes: &[(Balance, Balance)],
     ) -> bool {
-        if mode == DoomslugThresholdMode::NoApprovals {
+        if !mode.requires_approvals() {
             return true;
         }
 
@@ -958,7 +975,7 @@ mod tests {
         // "test1", 2 -> 2
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 2, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -967,7 +984,7 @@ mod tests {
         // "test3", 4 -> 3
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -976,7 +993,7 @@ mod tests {
         // "test4", 4 -> 4
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -987,7 +1004,7 @@ mod tests {
         // "test1", 4 -> same account, still 5
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -996,7 +1013,7 @@ mod tests {
         // "test2", 4 -> 5
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[1]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(clock.now()),
@@ -1005,7 +1022,7 @@ mod tests {
         // "test1", 4 -> 7
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(clock.now()),
@@ -1014,7 +1031,7 @@ mod tests {
         // "test4", 2 -> 3
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 2, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -1025,7 +1042,7 @@ mod tests {
         // "test3", 2 -> 6
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 2, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(clock.now()),
@@ -1034,7 +1051,7 @@ mod tests {
         // A different parent hash
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[2]), 2, 4, &signers[1]),
+                &Approval::new(hash(&[2]), 2, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -1060,13 +1077,13 @@ mod tests {
         let clock = FakeClock::new(Utc::UNIX_EPOCH);
         let mut tracker = DoomslugApprovalsTrackersAtHeight::new(clock.clock());
 
-        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]);
-        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]);
-        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]);
+        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap();
+        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap();
+        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap();
 
-        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]);
-        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]);
-        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]);
+        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]).unwrap();
+        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]).unwrap();
+        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]).unwrap();
 
         // Process first approval, and then process it again and make sure it works
         tracker.process_approval(&a1_1, &stakes, DoomslugThresholdMode::TwoThirds);
@@ -2,7 +2,10 @@ use near_chain_primitives::Error;
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_assignment::shard_id_to_uid;
 use near_primitives::block::Tip;
-use near_store::flat::{FlatStorageManager, FlatStorageStatus};
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::ShardId;
+use near_store::adapter::StoreAdapter;
+use near_store::flat::{BlockInfo, FlatStorageManager, FlatStorageRebuilder, FlatStorageStatus};
 
 use crate::{Chain, ChainStoreAccess};
 
@@ -30,6 +33,44 @@ impl Chain {
 
         Ok(())
     }
+
+    /// Starts the rebuild of the flat storage of `shard_id` from its trie at
+    /// the flat head, see `FlatStorageRebuilder`. The values are copied on a
+    /// background thread. Returns the shard and the flat head.
+    pub fn start_flat_storage_rebuild(
+        &self,
+        shard_id: ShardId,
+    ) -> Result<(ShardUId, BlockInfo), Error> {
+        let final_head = self.chain_store().final_head()?;
+        let shard_uid =
+            shard_id_to_uid(self.epoch_manager.as_ref(), shard_id, &final_head.epoch_id)?;
+        let rebuilder = FlatStorageRebuilder::start(
+            self.runtime_adapter.get_flat_storage_manager(),
+            self.chain_store().store().flat_store(),
+            shard_uid,
+            |flat_head| {
+                let chunk_extra = self.get_chunk_extra(&flat_head.hash, &shard_uid)?;
+                Ok::<_, Error>(
+                    self.runtime_adapter
+                        .get_tries()
+                        .get_view_trie_for_shard(shard_uid, *chunk_extra.state_root()),
+                )
+            },
+        )?;
+        let flat_head = *rebuilder.flat_head();
+        std::thread::Builder::new()
+            .name("flat_storage_rebuild".to_string())
+            .spawn(move || match rebuilder.run() {
+                Ok(num_values) => {
+                    tracing::info!(target: "chain", ?shard_uid, num_values, "Flat storage rebuilt");
+                }
+                Err(err) => {
+                    tracing::error!(target: "chain", ?shard_uid, ?err, "Flat storage rebuild failed");
+                }
+            })
+            .map_err(|err| Error::Other(format!("Cannot spawn flat storage rebuild: {err}")))?;
+        Ok((shard_uid, flat_head))
+    }
 }
 
 fn init_flat_storage_for_current_epoch(
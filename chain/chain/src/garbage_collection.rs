@@ -7,23 +7,38 @@ use near_chain_primitives::Error;
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_assignment::shard_id_to_uid;
 use near_epoch_manager::shard_tracker::ShardTracker;
-use near_primitives::block::Block;
+use near_primitives::block::{Block, MaybeNew};
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::get_block_shard_uid;
 use near_primitives::state_sync::{StateHeaderKey, StatePartKey};
+use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
     AccountId, BlockHeight, BlockHeightDelta, EpochId, NumBlocks, ShardId,
 };
 use near_primitives::utils::{
-    get_block_shard_id, get_block_shard_id_rev, get_outcome_id_block_hash, index_to_bytes,
+    get_block_shard_id, get_block_shard_id_rev, get_outcome_id_block_hash,
+    get_transactions_by_signer_key, index_to_bytes,
 };
 use near_store::adapter::trie_store::get_shard_uid_mapping;
 use near_store::adapter::{StoreAdapter, StoreUpdateAdapter};
+use near_store::db::RPC_LIGHT_CHUNK_EXTRA_TAIL_KEY;
 use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId, StoreUpdate};
 
 use crate::types::RuntimeAdapter;
 use crate::{Chain, ChainStore, ChainStoreAccess, ChainStoreUpdate, metrics};
 
+/// Number of blocks below the final block for which a node with the
+/// `rpc-light` profile keeps the chunk extras of the blocks on forks.
+pub const RPC_LIGHT_CHUNK_EXTRA_HORIZON: BlockHeightDelta = 1000;
+
+/// Maximum number of heights for which the chunk extras are deleted at every
+/// garbage collection call of a node with the `rpc-light` profile.
+pub(crate) const RPC_LIGHT_GC_HEIGHTS_PER_STEP: BlockHeightDelta = 100;
+
+/// Maximum number of keys of every column of the state witness data deleted
+/// at every garbage collection call of a node with the `rpc-light` profile.
+pub(crate) const RPC_LIGHT_GC_KEYS_PER_STEP: usize = 1000;
+
 #[derive(Clone)]
 pub enum GCMode {
     Fork(ShardTries),
@@ -365,6 +380,69 @@ impl ChainStore {
         Ok(())
     }
 
+    /// Garbage collect data which a node with the `rpc-light` profile doesn't
+    /// keep, see `NodeProfile::RpcLight`.
+    ///
+    /// The data used only to produce and validate state witnesses is deleted,
+    /// up to `RPC_LIGHT_GC_KEYS_PER_STEP` keys of every column per call.  The
+    /// chunk extras of the blocks which are not on the canonical chain are
+    /// deleted from the saved chunk extra tail up to
+    /// `RPC_LIGHT_CHUNK_EXTRA_HORIZON` blocks below the final block, instead of
+    /// waiting for the regular garbage collection.  The chunk extras of the
+    /// canonical blocks are kept, the queries about these blocks need them.
+    pub fn clear_rpc_light_data(
+        &mut self,
+        epoch_manager: &dyn EpochManagerAdapter,
+    ) -> Result<(), Error> {
+        let _span =
+            tracing::debug_span!(target: "garbage_collection", "clear_rpc_light_data").entered();
+        let mut chain_store_update = self.store_update();
+        for col in [
+            DBCol::StateTransitionData,
+            DBCol::LatestChunkStateWitnesses,
+            DBCol::LatestWitnessesByIndex,
+        ] {
+            for res in chain_store_update.store().iter(col).take(RPC_LIGHT_GC_KEYS_PER_STEP) {
+                let key = res?.0;
+                chain_store_update.gc_col(col, &key);
+            }
+        }
+
+        let final_head = chain_store_update.final_head()?;
+        let stop_height = final_head.height.saturating_sub(RPC_LIGHT_CHUNK_EXTRA_HORIZON);
+        let chunk_extra_tail = chain_store_update
+            .store()
+            .get_ser::<BlockHeight>(DBCol::Misc, RPC_LIGHT_CHUNK_EXTRA_TAIL_KEY)?
+            .unwrap_or_default();
+        let mut height = chunk_extra_tail.max(chain_store_update.tail()? + 1);
+        let mut remaining = RPC_LIGHT_GC_HEIGHTS_PER_STEP;
+        while height < stop_height && remaining > 0 {
+            let block_height = height;
+            height += 1;
+            remaining -= 1;
+            let canonical_hash = chain_store_update.get_block_hash_by_height(block_height).ok();
+            let block_hashes = chain_store_update
+                .chain_store()
+                .get_all_block_hashes_by_height(block_height)?
+                .values()
+                .flatten()
+                .filter(|block_hash| Some(**block_hash) != canonical_hash)
+                .cloned()
+                .collect::<Vec<_>>();
+            for block_hash in block_hashes {
+                for shard_uid in chain_store_update.get_shard_uids_to_gc(epoch_manager, &block_hash)
+                {
+                    let block_shard_uid = get_block_shard_uid(&block_hash, &shard_uid);
+                    chain_store_update.gc_col(DBCol::ChunkExtra, &block_shard_uid);
+                }
+            }
+        }
+        let mut store_update = chain_store_update.store().store_update();
+        store_update.set_ser(DBCol::Misc, RPC_LIGHT_CHUNK_EXTRA_TAIL_KEY, &height)?;
+        chain_store_update.merge(store_update);
+        chain_store_update.commit()
+    }
+
     /// Garbage collect data which archival node doesn’t need to keep.
     ///
     /// Normally, archival nodes keep all the data from the genesis block and
@@ -688,6 +766,7 @@ impl<'a> ChainStoreUpdate<'a> {
             self.gc_outgoing_receipts(&block_hash, shard_id);
             self.gc_col(DBCol::IncomingReceipts, &block_shard_id);
             self.gc_col(DBCol::ChunkApplyStats, &block_shard_id);
+            self.gc_col(DBCol::ChunkApplyJournal, &block_shard_id);
 
             // For incoming State Parts it's done in chain.clear_downloaded_parts()
             // The following code is mostly for outgoing State Parts.
@@ -710,6 +789,7 @@ impl<'a> ChainStoreUpdate<'a> {
         }
 
         // 3. Delete block_hash-indexed data
+        self.gc_transactions_by_signer(&block)?;
         self.gc_col(DBCol::Block, block_hash.as_bytes());
         self.gc_col(DBCol::BlockExtra, block_hash.as_bytes());
         self.gc_col(DBCol::NextBlockHashes, block_hash.as_bytes());
@@ -731,6 +811,7 @@ impl<'a> ChainStoreUpdate<'a> {
             _ => self.gc_col(DBCol::BlockInfo, block_hash.as_bytes()),
         }
         self.gc_col(DBCol::StateDlInfos, block_hash.as_bytes());
+        self.gc_col(DBCol::DeferredPostprocessing, block_hash.as_bytes());
 
         // 4. Update or delete block_hash_per_height
         self.gc_col_block_per_height(&block_hash, height, block.header().epoch_id())?;
@@ -827,6 +908,8 @@ impl<'a> ChainStoreUpdate<'a> {
 
             // delete DBCol::ChunkExtra based on shard_uid since it's indexed by shard_uid in the storage
             self.gc_col(DBCol::ChunkExtra, &block_shard_id);
+            // the chunk has to be applied again if the block is processed again
+            self.gc_col(DBCol::ChunkApplyJournal, &get_block_shard_id(&block_hash, shard_id));
 
             // delete state parts and state headers
             if let Ok(shard_state_header) =
@@ -845,6 +928,7 @@ impl<'a> ChainStoreUpdate<'a> {
         }
 
         // 2. Delete block_hash-indexed data
+        self.gc_transactions_by_signer(&block)?;
         self.gc_col(DBCol::Block, block_hash.as_bytes());
         self.gc_col(DBCol::BlockExtra, block_hash.as_bytes());
         self.gc_col(DBCol::NextBlockHashes, block_hash.as_bytes());
@@ -986,6 +1070,82 @@ impl<'a> ChainStoreUpdate<'a> {
         Ok(())
     }
 
+    /// Removes the transactions of the new chunks of a garbage collected
+    /// block from `DBCol::TransactionsBySigner`. The transactions are indexed
+    /// by the height of the block which includes the chunk, which is greater
+    /// than the height at which the chunk was created if it missed some
+    /// blocks, so the index is cleared together with the blocks rather than
+    /// the chunks.
+    ///
+    /// A block on a fork may include the same transactions at the same height
+    /// as the canonical block. Only the entries pointing to the collected
+    /// block are touched, and the ones shared with the canonical block are
+    /// pointed to it instead of being removed.
+    fn gc_transactions_by_signer(&mut self, block: &Block) -> Result<(), Error> {
+        if self.chain_store().transactions_by_signer_limit().is_none() {
+            return Ok(());
+        }
+        let block_hash = block.hash();
+        let height = block.header().height();
+        let canonical_hash = match self.chain_store().get_block_hash_by_height(height) {
+            Ok(canonical_hash) if canonical_hash != *block_hash => Some(canonical_hash),
+            Ok(_) | Err(Error::DBNotFoundErr(_)) => None,
+            Err(err) => return Err(err),
+        };
+        let canonical_transactions = match canonical_hash {
+            Some(canonical_hash) => match self.get_block(&canonical_hash) {
+                Ok(canonical_block) => self
+                    .new_chunk_transactions(&canonical_block)?
+                    .iter()
+                    .map(|transaction| transaction.get_hash())
+                    .collect(),
+                Err(Error::DBNotFoundErr(_)) => HashSet::new(),
+                Err(err) => return Err(err),
+            },
+            None => HashSet::new(),
+        };
+
+        for transaction in self.new_chunk_transactions(block)? {
+            let tx_hash = transaction.get_hash();
+            let key = get_transactions_by_signer_key(
+                transaction.transaction.signer_id(),
+                height,
+                &tx_hash,
+            );
+            let indexed_block_hash =
+                self.store().get_ser::<CryptoHash>(DBCol::TransactionsBySigner, &key)?;
+            if indexed_block_hash.as_ref() != Some(block_hash) {
+                continue;
+            }
+            match canonical_hash {
+                Some(canonical_hash) if canonical_transactions.contains(&tx_hash) => {
+                    let mut store_update = self.store().store_update();
+                    store_update.set_ser(DBCol::TransactionsBySigner, &key, &canonical_hash)?;
+                    self.merge(store_update);
+                }
+                _ => self.gc_col(DBCol::TransactionsBySigner, &key),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the transactions of the new chunks of the block which are
+    /// available, i.e. the ones of the tracked shards.
+    fn new_chunk_transactions(&self, block: &Block) -> Result<Vec<Arc<SignedTransaction>>, Error> {
+        let mut transactions = vec![];
+        for chunk_header in block.chunks().iter() {
+            let MaybeNew::New(chunk_header) = chunk_header else {
+                continue;
+            };
+            match self.get_chunk(&chunk_header.chunk_hash()) {
+                Ok(chunk) => transactions.extend(chunk.to_transactions().iter().cloned()),
+                Err(Error::ChunkMissing(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(transactions)
+    }
+
     fn gc_col(&mut self, col: DBCol, key: &[u8]) {
         let mut store_update = self.store().store_update();
         match col {
@@ -1093,6 +1253,15 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::ChunkApplyStats => {
                 store_update.delete(col, key);
             }
+            DBCol::TransactionsBySigner => {
+                store_update.delete(col, key);
+            }
+            DBCol::ChunkApplyJournal => {
+                store_update.delete(col, key);
+            }
+            DBCol::DeferredPostprocessing => {
+                store_update.delete(col, key);
+            }
             DBCol::DbVersion
             | DBCol::BlockMisc
             | DBCol::_GCCount
@@ -1128,6 +1297,8 @@ impl<'a> ChainStoreUpdate<'a> {
             // Note that StateSyncHashes should not ever have too many keys in them
             // because we remove unneeded keys as we add new ones.
             | DBCol::StateSyncHashes
+            // Like block headers, skip pointers are never garbage collected.
+            | DBCol::BlockSkipAncestor
             => unreachable!(),
         }
         self.merge(store_update);
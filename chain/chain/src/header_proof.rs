@@ -0,0 +1,62 @@
+use near_chain_primitives::Error;
+use near_primitives::block::Block;
+use near_primitives::block_header_proof::{ShardHeaderField, ShardHeaderFieldProof};
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::merklize;
+use near_primitives::types::ShardIndex;
+
+/// Creates the proof of the given chunk header field of a shard against the
+/// hash of the block, see `ShardHeaderFieldProof::verify`.
+pub fn create_shard_header_field_proof(
+    block: &Block,
+    field: ShardHeaderField,
+    shard_index: ShardIndex,
+) -> Result<ShardHeaderFieldProof, Error> {
+    let chunks = block.chunks();
+    let values = chunks
+        .iter_raw()
+        .map(|chunk| match field {
+            ShardHeaderField::PrevStateRoot => chunk.prev_state_root(),
+            ShardHeaderField::PrevOutcomeRoot => chunk.prev_outcome_root(),
+        })
+        .collect::<Vec<CryptoHash>>();
+    let Some(&value) = values.get(shard_index) else {
+        return Err(Error::InvalidShardIndex(shard_index));
+    };
+    let (_, mut paths) = merklize(&values);
+    let path = paths.swap_remove(shard_index);
+    Ok(ShardHeaderFieldProof {
+        header: block.header().clone().into(),
+        field,
+        shard_index,
+        value,
+        path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::block_header_proof::verify_header_lite;
+    use near_primitives::test_utils::TestBlockBuilder;
+
+    use super::*;
+    use crate::test_utils::setup;
+
+    #[test]
+    fn test_shard_header_field_proof() {
+        let (chain, _, _, signer) = setup(near_time::Clock::real());
+        let genesis = chain.get_block(chain.genesis().hash()).unwrap();
+        let block = TestBlockBuilder::new(near_time::Clock::real(), &genesis, signer).build();
+        let block_hash = *block.hash();
+        for field in [ShardHeaderField::PrevStateRoot, ShardHeaderField::PrevOutcomeRoot] {
+            for shard_index in 0..block.chunks().len() {
+                let proof = create_shard_header_field_proof(&block, field, shard_index).unwrap();
+                assert!(verify_header_lite(&proof.header, &block_hash));
+                assert!(proof.verify(&block_hash, block.chunks().len()));
+                assert!(!proof.verify(block.header().prev_hash(), block.chunks().len()));
+            }
+            let shard_index = block.chunks().len();
+            assert!(create_shard_header_field_proof(&block, field, shard_index).is_err());
+        }
+    }
+}
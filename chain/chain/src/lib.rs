@@ -4,9 +4,12 @@ pub use block_processing_utils::BlockProcessingArtifact;
 pub use chain::{Chain, check_known, collect_receipts};
 pub use chain_update::ChainUpdate;
 pub use doomslug::{Doomslug, DoomslugBlockProductionReadiness, DoomslugThresholdMode};
+pub use header_proof::create_shard_header_field_proof;
 pub use lightclient::{create_light_client_block_view, get_epoch_block_producers_view};
 pub use near_chain_primitives::{self, Error};
 pub use near_primitives::receipt::ReceiptResult;
+pub use store::ancestor_skip_list::migrate_45_to_46;
+pub use store::transactions_by_signer::migrate_46_to_47;
 pub use store::utils::{
     check_transaction_validity_period, get_chunk_clone_from_header,
     get_incoming_receipts_for_shard, retrieve_headers,
@@ -23,17 +26,21 @@ mod block_processing_utils;
 pub mod blocks_delay_tracker;
 pub mod chain;
 mod chain_update;
+mod corrupted_chunks;
 pub mod crypto_hash_timer;
+mod deferred_postprocessing;
 mod doomslug;
 pub mod flat_storage_init;
 mod garbage_collection;
 pub mod genesis;
+mod header_proof;
 mod lightclient;
 pub mod metrics;
 pub mod missing_chunks;
 pub mod orphan;
 pub mod pending;
 pub mod rayon_spawner;
+mod receipt_audit;
 pub mod resharding;
 pub mod runtime;
 pub mod sharding;
@@ -24,6 +24,13 @@ pub static BLOCK_PROCESSING_ATTEMPTS_TOTAL: LazyLock<IntCounter> = LazyLock::new
     )
     .unwrap()
 });
+pub static CORRUPTED_CHUNKS_EVICTED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_corrupted_chunks_evicted_total",
+        "Number of chunks evicted from the store to be downloaded again because they were corrupted",
+    )
+    .unwrap()
+});
 pub static BLOCK_PROCESSED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
     try_create_int_counter("near_block_processed_total", "Total number of blocks processed")
         .unwrap()
@@ -203,6 +210,13 @@ pub(crate) static SCHEDULED_CATCHUP_BLOCK: LazyLock<IntGauge> = LazyLock::new(||
     )
     .unwrap()
 });
+pub(crate) static CHUNK_APPLIES_SKIPPED: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_chunk_applies_skipped_total",
+        "Number of chunks not applied again, since they were applied before a restart",
+    )
+    .unwrap()
+});
 pub(crate) static LARGEST_TARGET_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
     try_create_int_gauge(
         "near_largest_target_height",
@@ -307,3 +321,60 @@ pub(crate) static STATE_TRANSITION_DATA_GC_TIME: LazyLock<Histogram> = LazyLock:
     )
     .unwrap()
 });
+
+pub(crate) static RESTORE_POINTS_TAKEN: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_restore_points_taken_total",
+        "Number of restore points taken, by the policy trigger which requested them",
+        &["trigger"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DEFERRED_POSTPROCESSING_BLOCKS: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_deferred_postprocessing_blocks",
+        "Number of blocks on non-head forks whose postprocessing writes are held in memory",
+    )
+    .unwrap()
+});
+
+pub(crate) static DEFERRED_POSTPROCESSING_APPLIED_TOTAL: LazyLock<IntCounter> = LazyLock::new(
+    || {
+        try_create_int_counter(
+            "near_deferred_postprocessing_applied_total",
+            "Number of fork blocks whose deferred postprocessing was written because the fork was extended",
+        )
+        .unwrap()
+    },
+);
+
+pub(crate) static DEFERRED_POSTPROCESSING_DISCARDED_TOTAL: LazyLock<IntCounter> = LazyLock::new(
+    || {
+        try_create_int_counter(
+            "near_deferred_postprocessing_discarded_total",
+            "Number of fork blocks whose deferred postprocessing was never written because the fork was abandoned",
+        )
+        .unwrap()
+    },
+);
+
+pub(crate) static RECEIPT_AUDIT_DISCREPANCIES_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(
+    || {
+        try_create_int_counter_vec(
+        "near_receipt_audit_discrepancies_total",
+        "Number of receipts lost, delivered more than once or delivered without being produced, found by the receipt audit",
+        &["kind"],
+    )
+    .unwrap()
+    },
+);
+
+pub(crate) static RESTORE_POINTS_SKIPPED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_restore_points_skipped_total",
+        "Number of requested restore points which were not taken, by reason",
+        &["reason"],
+    )
+    .unwrap()
+});
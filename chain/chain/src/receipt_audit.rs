@@ -0,0 +1,332 @@
+//! Audit of the delivery of the receipts between shards, for debugging.
+//!
+//! The outgoing receipts produced by the application of the chunk of a shard
+//! are included in the next chunk of the shard, and delivered to their target
+//! shards with the receipt proofs of that chunk.  For every final block, the
+//! audit compares the receipts delivered by the new chunks of the block with
+//! the outgoing receipts produced by the previous chunk of their shard, and
+//! records the receipts which were lost, delivered more than once or delivered
+//! without being produced.  Only the receipts between the shards tracked by
+//! the node are audited, since the node has neither the outgoing receipts nor
+//! the receipt proofs of the other shards.
+
+use crate::metrics;
+use crate::store::{ChainStore, ChainStoreAccess};
+use lru::LruCache;
+use near_chain_primitives::Error;
+use near_client_primitives::debug::{
+    ReceiptAuditDiscrepancyKind, ReceiptAuditDiscrepancyView, ReceiptAuditView,
+};
+use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::block::MaybeNew;
+use near_primitives::hash::CryptoHash;
+use near_primitives::receipt::Receipt;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::sharding::{ReceiptProof, ShardProof};
+use near_primitives::types::{AccountId, BlockHeight, ShardId};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+/// Number of discrepancies kept for the debug page.
+const MAX_DISCREPANCIES: usize = 1000;
+/// Number of delivered receipts remembered to detect duplicates.
+const DELIVERED_RECEIPTS_CACHE_SIZE: usize = 100_000;
+/// If the final head moves further than this at once, e.g. after sync, the
+/// audit starts over from the final head instead of catching up.
+const MAX_BLOCKS_AUDITED_AT_ONCE: BlockHeight = 100;
+
+/// Outgoing receipts of a shard which weren't delivered yet.
+struct ProducedReceipts {
+    /// The block in which the chunk producing the receipts was applied.
+    block_hash: CryptoHash,
+    /// Ids and receivers of the receipts.
+    receipts: Vec<(CryptoHash, AccountId)>,
+}
+
+pub(crate) struct ReceiptAudit {
+    /// Height of the last final block audited.
+    audited_height: Option<BlockHeight>,
+    /// Outgoing receipts of the last chunk applied for every shard.
+    produced: HashMap<ShardId, ProducedReceipts>,
+    /// Ids of the recently delivered receipts.
+    delivered: LruCache<CryptoHash, ()>,
+    num_receipts_produced: u64,
+    num_receipts_delivered: u64,
+    /// The most recent discrepancies, oldest first.
+    discrepancies: VecDeque<ReceiptAuditDiscrepancyView>,
+}
+
+impl ReceiptAudit {
+    pub fn new() -> Self {
+        Self {
+            audited_height: None,
+            produced: HashMap::new(),
+            delivered: LruCache::new(NonZeroUsize::new(DELIVERED_RECEIPTS_CACHE_SIZE).unwrap()),
+            num_receipts_produced: 0,
+            num_receipts_delivered: 0,
+            discrepancies: VecDeque::new(),
+        }
+    }
+
+    /// Audits the final blocks which weren't audited yet.  The audit starts at
+    /// the final head the first time it's called.
+    pub fn audit_final_blocks(
+        &mut self,
+        chain_store: &ChainStore,
+        epoch_manager: &dyn EpochManagerAdapter,
+    ) -> Result<(), Error> {
+        let final_height = chain_store.final_head()?.height;
+        let start_height = match self.audited_height {
+            Some(height) if height + MAX_BLOCKS_AUDITED_AT_ONCE >= final_height => height + 1,
+            Some(_) => {
+                // The receipts produced before the gap can't be matched anymore.
+                self.produced.clear();
+                final_height
+            }
+            None => final_height,
+        };
+        for height in start_height..=final_height {
+            let block_hash = match chain_store.get_block_hash_by_height(height) {
+                Ok(block_hash) => block_hash,
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let block = chain_store.get_block(&block_hash)?;
+            let shard_layout = epoch_manager.get_shard_layout(block.header().epoch_id())?;
+            let mut delivered = HashMap::new();
+            let mut produced = vec![];
+            for shard_id in shard_layout.shard_ids() {
+                if let Some(proofs) =
+                    not_found_as_none(chain_store.get_incoming_receipts(&block_hash, shard_id))?
+                {
+                    delivered.insert(shard_id, proofs);
+                }
+                if let Some(receipts) =
+                    not_found_as_none(chain_store.get_outgoing_receipts(&block_hash, shard_id))?
+                {
+                    produced.push((shard_id, receipts));
+                }
+            }
+            let new_chunk_shard_ids: Vec<ShardId> = block
+                .chunks()
+                .iter()
+                .filter_map(|chunk| match chunk {
+                    MaybeNew::New(chunk) => Some(chunk.shard_id()),
+                    MaybeNew::Old(_) => None,
+                })
+                .collect();
+            self.audit_block(
+                block_hash,
+                height,
+                &shard_layout,
+                &new_chunk_shard_ids,
+                &delivered,
+                produced,
+            );
+            self.audited_height = Some(height);
+        }
+        Ok(())
+    }
+
+    /// Audits the receipts delivered by the new chunks of a block, given by
+    /// target shard, then records the receipts produced by the chunks applied
+    /// in the block.
+    fn audit_block(
+        &mut self,
+        block_hash: CryptoHash,
+        height: BlockHeight,
+        shard_layout: &ShardLayout,
+        new_chunk_shard_ids: &[ShardId],
+        delivered: &HashMap<ShardId, Arc<Vec<ReceiptProof>>>,
+        produced: Vec<(ShardId, Arc<Vec<Receipt>>)>,
+    ) {
+        // The receipts of the shards which don't exist anymore after resharding
+        // are delivered by their children, which aren't audited.
+        self.produced.retain(|shard_id, _| shard_layout.shard_ids().any(|id| id == *shard_id));
+
+        // Target shard of the receipts delivered by every shard.
+        let mut delivered_by_shard: HashMap<ShardId, HashMap<CryptoHash, ShardId>> = HashMap::new();
+        for proofs in delivered.values() {
+            for ReceiptProof(receipts, ShardProof { from_shard_id, to_shard_id, .. }) in
+                proofs.iter()
+            {
+                for receipt in receipts {
+                    let receipt_id = *receipt.receipt_id();
+                    self.num_receipts_delivered += 1;
+                    if self.delivered.put(receipt_id, ()).is_some() {
+                        self.record(ReceiptAuditDiscrepancyView {
+                            kind: ReceiptAuditDiscrepancyKind::Duplicate,
+                            receipt_id,
+                            from_shard_id: *from_shard_id,
+                            to_shard_id: *to_shard_id,
+                            produced_in_block: None,
+                            delivery_block_hash: block_hash,
+                            delivery_block_height: height,
+                        });
+                    }
+                    delivered_by_shard
+                        .entry(*from_shard_id)
+                        .or_default()
+                        .insert(receipt_id, *to_shard_id);
+                }
+            }
+        }
+
+        for shard_id in new_chunk_shard_ids {
+            let Some(produced) = self.produced.remove(shard_id) else {
+                continue;
+            };
+            let delivered_receipts = delivered_by_shard.remove(shard_id).unwrap_or_default();
+            let mut produced_ids = HashSet::new();
+            for (receipt_id, receiver_id) in produced.receipts {
+                produced_ids.insert(receipt_id);
+                let to_shard_id = shard_layout.account_id_to_shard_id(&receiver_id);
+                // The node has no receipt proofs for the shards it doesn't track.
+                if delivered_receipts.contains_key(&receipt_id)
+                    || !delivered.contains_key(&to_shard_id)
+                {
+                    continue;
+                }
+                self.record(ReceiptAuditDiscrepancyView {
+                    kind: ReceiptAuditDiscrepancyKind::Lost,
+                    receipt_id,
+                    from_shard_id: *shard_id,
+                    to_shard_id,
+                    produced_in_block: Some(produced.block_hash),
+                    delivery_block_hash: block_hash,
+                    delivery_block_height: height,
+                });
+            }
+            for (receipt_id, to_shard_id) in delivered_receipts {
+                if produced_ids.contains(&receipt_id) {
+                    continue;
+                }
+                self.record(ReceiptAuditDiscrepancyView {
+                    kind: ReceiptAuditDiscrepancyKind::Unexpected,
+                    receipt_id,
+                    from_shard_id: *shard_id,
+                    to_shard_id,
+                    produced_in_block: None,
+                    delivery_block_hash: block_hash,
+                    delivery_block_height: height,
+                });
+            }
+        }
+
+        for (shard_id, receipts) in produced {
+            self.num_receipts_produced += receipts.len() as u64;
+            let receipts = receipts
+                .iter()
+                .map(|receipt| (*receipt.receipt_id(), receipt.receiver_id().clone()))
+                .collect();
+            self.produced.insert(shard_id, ProducedReceipts { block_hash, receipts });
+        }
+    }
+
+    fn record(&mut self, discrepancy: ReceiptAuditDiscrepancyView) {
+        tracing::warn!(target: "chain", ?discrepancy, "Receipt audit discrepancy");
+        let kind = match discrepancy.kind {
+            ReceiptAuditDiscrepancyKind::Lost => "lost",
+            ReceiptAuditDiscrepancyKind::Duplicate => "duplicate",
+            ReceiptAuditDiscrepancyKind::Unexpected => "unexpected",
+        };
+        metrics::RECEIPT_AUDIT_DISCREPANCIES_TOTAL.with_label_values(&[kind]).inc();
+        if self.discrepancies.len() == MAX_DISCREPANCIES {
+            self.discrepancies.pop_front();
+        }
+        self.discrepancies.push_back(discrepancy);
+    }
+
+    pub fn view(&self) -> ReceiptAuditView {
+        ReceiptAuditView {
+            audited_height: self.audited_height,
+            num_receipts_produced: self.num_receipts_produced,
+            num_receipts_delivered: self.num_receipts_delivered,
+            discrepancies: self.discrepancies.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+fn not_found_as_none<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::DBNotFoundErr(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReceiptAudit;
+    use near_client_primitives::debug::ReceiptAuditDiscrepancyKind;
+    use near_primitives::hash::hash;
+    use near_primitives::receipt::{Receipt, ReceiptPriority};
+    use near_primitives::shard_layout::ShardLayout;
+    use near_primitives::sharding::{ReceiptProof, ShardProof};
+    use near_primitives::types::ShardId;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn receipt(id: &[u8], receiver_id: &str) -> Receipt {
+        let mut receipt = Receipt::new_balance_refund(
+            &receiver_id.parse().unwrap(),
+            1,
+            ReceiptPriority::NoPriority,
+        );
+        receipt.set_receipt_id(hash(id));
+        receipt
+    }
+
+    fn proof(receipts: Vec<Receipt>, from_shard_id: ShardId, to_shard_id: ShardId) -> ReceiptProof {
+        ReceiptProof(receipts, ShardProof { from_shard_id, to_shard_id, proof: vec![] })
+    }
+
+    #[test]
+    fn test_receipt_audit() {
+        let shard_layout = ShardLayout::multi_shard_custom(vec!["m".parse().unwrap()], 0);
+        let shard_ids: Vec<ShardId> = shard_layout.shard_ids().collect();
+        let (s0, s1) = (shard_ids[0], shard_ids[1]);
+        let (a, b, c) = (receipt(b"a", "alice"), receipt(b"b", "zoe"), receipt(b"c", "zoe"));
+        let mut audit = ReceiptAudit::new();
+
+        // Shard 0 produces receipts to both shards at height 1.
+        let block1 = hash(b"block1");
+        audit.audit_block(
+            block1,
+            1,
+            &shard_layout,
+            &[s0],
+            &HashMap::new(),
+            vec![(s0, Arc::new(vec![a.clone(), b.clone()]))],
+        );
+        // The next chunk of shard 0 delivers `a` and an unexpected `c`, but loses `b`.
+        let block2 = hash(b"block2");
+        let delivered = HashMap::from([
+            (s0, Arc::new(vec![proof(vec![a.clone()], s0, s0)])),
+            (s1, Arc::new(vec![proof(vec![c.clone()], s0, s1)])),
+        ]);
+        audit.audit_block(block2, 2, &shard_layout, &[s0], &delivered, vec![]);
+        // `a` is delivered again by shard 1.
+        let block3 = hash(b"block3");
+        let delivered = HashMap::from([(s0, Arc::new(vec![proof(vec![a], s1, s0)]))]);
+        audit.audit_block(block3, 3, &shard_layout, &[s1], &delivered, vec![]);
+
+        let view = audit.view();
+        assert_eq!(view.num_receipts_produced, 2);
+        assert_eq!(view.num_receipts_delivered, 3);
+        let discrepancies: Vec<_> = view
+            .discrepancies
+            .iter()
+            .map(|d| (d.kind, d.receipt_id, d.from_shard_id, d.to_shard_id, d.produced_in_block))
+            .collect();
+        assert_eq!(
+            discrepancies,
+            vec![
+                (ReceiptAuditDiscrepancyKind::Duplicate, hash(b"a"), s1, s0, None),
+                (ReceiptAuditDiscrepancyKind::Unexpected, hash(b"c"), s0, s1, None),
+                (ReceiptAuditDiscrepancyKind::Lost, hash(b"b"), s0, s1, Some(block1)),
+            ]
+        );
+    }
+}
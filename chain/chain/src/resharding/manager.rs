@@ -1,6 +1,7 @@
 use super::event_type::{ReshardingEventType, ReshardingSplitShardParams};
 use super::types::{ReshardingSender, ScheduleResharding};
 use crate::ChainStoreUpdate;
+use crate::types::RuntimeAdapter;
 use itertools::Itertools;
 use near_async::messaging::CanSend;
 use near_chain_primitives::Error;
@@ -41,7 +42,7 @@ impl ReshardingManager {
         chain_store_update: ChainStoreUpdate,
         block: &Block,
         shard_uid: ShardUId,
-        tries: ShardTries,
+        runtime_adapter: &dyn RuntimeAdapter,
     ) -> Result<(), Error> {
         let block_hash = block.hash();
         let block_height = block.header().height();
@@ -80,7 +81,13 @@ impl ReshardingManager {
             ReshardingEventType::from_shard_layout(&next_shard_layout, block_info)?;
         match resharding_event_type {
             Some(ReshardingEventType::SplitShard(split_shard_event)) => {
-                self.split_shard(chain_store_update, block, shard_uid, tries, split_shard_event)?;
+                self.split_shard(
+                    chain_store_update,
+                    block,
+                    shard_uid,
+                    runtime_adapter,
+                    split_shard_event,
+                )?;
             }
             None => {
                 tracing::warn!(target: "resharding", ?resharding_event_type, "unsupported resharding event type, skipping");
@@ -91,10 +98,10 @@ impl ReshardingManager {
 
     fn split_shard(
         &self,
-        chain_store_update: ChainStoreUpdate,
+        mut chain_store_update: ChainStoreUpdate,
         block: &Block,
         shard_uid: ShardUId,
-        tries: ShardTries,
+        runtime_adapter: &dyn RuntimeAdapter,
         split_shard_event: ReshardingSplitShardParams,
     ) -> Result<(), Error> {
         if split_shard_event.parent_shard != shard_uid {
@@ -103,6 +110,29 @@ impl ReshardingManager {
             return Ok(());
         }
 
+        // The runtimes which don't keep their state in the tries split it themselves.
+        let parent_chunk_extra =
+            self.store.chain_store().get_chunk_extra(block.hash(), &shard_uid)?;
+        if let Some((left_state_root, right_state_root)) = runtime_adapter
+            .split_state(*parent_chunk_extra.state_root(), &split_shard_event.boundary_account)?
+        {
+            for (child_shard_uid, state_root) in [
+                (split_shard_event.left_child_shard, left_state_root),
+                (split_shard_event.right_child_shard, right_state_root),
+            ] {
+                let mut child_chunk_extra = ChunkExtra::clone(&parent_chunk_extra);
+                *child_chunk_extra.state_root_mut() = state_root;
+                chain_store_update.save_chunk_extra(
+                    block.hash(),
+                    &child_shard_uid,
+                    child_chunk_extra,
+                );
+            }
+            chain_store_update.commit()?;
+            return Ok(());
+        }
+        let tries = runtime_adapter.get_tries();
+
         // Reshard the State column by setting ShardUId mapping from children to ancestor.
         self.set_state_shard_uid_mapping(&split_shard_event)?;
 
@@ -101,6 +101,21 @@ impl QueryError {
         }
     }
 
+    pub fn from_dry_run_transaction_error(
+        error: node_runtime::state_viewer::errors::DryRunTransactionError,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    ) -> Self {
+        match error {
+            node_runtime::state_viewer::errors::DryRunTransactionError::InvalidTransaction {
+                error,
+            } => Self::InvalidTransaction { error, block_height, block_hash },
+            node_runtime::state_viewer::errors::DryRunTransactionError::InternalError {
+                error_message,
+            } => Self::InternalError { error_message, block_height, block_hash },
+        }
+    }
+
     pub fn from_epoch_error(
         error: near_primitives::errors::EpochError,
         block_height: near_primitives::types::BlockHeight,
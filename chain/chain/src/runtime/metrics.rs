@@ -1,6 +1,6 @@
 use near_o11y::metrics::{
-    HistogramVec, IntGaugeVec, exponential_buckets, linear_buckets, try_create_histogram_vec,
-    try_create_int_gauge_vec,
+    HistogramVec, IntCounterVec, IntGaugeVec, exponential_buckets, linear_buckets,
+    try_create_histogram_vec, try_create_int_counter_vec, try_create_int_gauge_vec,
 };
 
 use std::sync::LazyLock;
@@ -24,6 +24,24 @@ pub(crate) static DELAYED_RECEIPTS_COUNT: LazyLock<IntGaugeVec> = LazyLock::new(
     .unwrap()
 });
 
+pub(crate) static APPLY_CHUNK_STORAGE_OPS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_apply_chunk_storage_ops_total",
+        "Storage operations done while applying chunks, by shard and operation: trie_node_read, trie_node_write, flat_storage_hit or flat_storage_miss",
+        &["shard_id", "op"],
+    )
+    .unwrap()
+});
+
+pub(crate) static APPLY_CHUNK_STORAGE_BYTES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_apply_chunk_storage_bytes_total",
+        "Bytes read from and written to the storage while applying chunks, by shard and direction",
+        &["shard_id", "direction"],
+    )
+    .unwrap()
+});
+
 pub(crate) static PREPARE_TX_SIZE: LazyLock<HistogramVec> = LazyLock::new(|| {
     try_create_histogram_vec(
         "near_prepare_tx_size",
@@ -59,6 +77,8 @@ pub(crate) static PREPARE_TX_REJECTED: LazyLock<HistogramVec> = LazyLock::new(||
         // - invalid_tx             The tx failed validation or the signer has not enough funds.
         // - invalid_block_hash     The block_hash field on the tx is expired or not on the canonical chain.
         // - congestion             The receiver shard is congested.
+        // - signer_limit           The signer reached its limit of transactions in the chunk.
+        //                          Counted once per access key, the transactions stay in the pool.
         &["shard_id", "reason"],
         // Histogram boundaries are inclusive. Pick the first boundary below 1
         // to have 0 values as a separate bucket.
@@ -15,43 +15,49 @@ use near_parameters::{RuntimeConfig, RuntimeConfigStore};
 use near_pool::types::TransactionGroupIterator;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::apply::ApplyChunkReason;
+use near_primitives::chunk_apply_stats::StorageIoStats;
 use near_primitives::congestion_info::{
     CongestionControl, ExtendedCongestionInfo, RejectTransactionReason, ShardAcceptsTransactions,
 };
+use near_primitives::epoch_manager::EpochConfig;
 use near_primitives::errors::{InvalidTxError, RuntimeError, StorageError};
 use near_primitives::hash::{CryptoHash, hash};
 use near_primitives::receipt::Receipt;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::state_part::PartId;
-use near_primitives::transaction::{SignedTransaction, ValidatedTransaction};
+use near_primitives::transaction::{
+    ExecutionOutcomeWithIdAndProof, SignedTransaction, ValidatedTransaction,
+};
 use near_primitives::types::{
     AccountId, Balance, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, MerkleHash,
-    ShardId, StateChangeCause, StateRoot, StateRootNode,
+    ShardId, StateChangeCause, StateChanges, StateChangesExt, StateRoot, StateRootNode,
 };
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, ContractCodeView, QueryRequest, QueryResponse,
-    QueryResponseKind, ViewStateResult,
+    AccessKeyInfoView, CallResult, ContractCodeView, DryRunResultView, ExecutionOutcomeWithIdView,
+    QueryRequest, QueryResponse, QueryResponseKind, ReceiptView, StateChangesView,
+    StateOverrideView, ViewStatePagedResult, ViewStateResult,
 };
 use near_store::adapter::{StoreAdapter, StoreUpdateAdapter};
 use near_store::db::metadata::DbKind;
 use near_store::flat::FlatStorageManager;
+use near_store::trie::io_stats::TrieIoCounters;
 use near_store::{
     ApplyStatePartResult, COLD_HEAD_KEY, DBCol, ShardTries, StateSnapshotConfig, Store, Trie,
-    TrieConfig, TrieUpdate, WrappedTrieChanges,
+    TrieChanges, TrieConfig, TrieUpdate, WrappedTrieChanges,
 };
 use near_vm_runner::ContractCode;
 use near_vm_runner::{ContractRuntimeCache, precompile_contract};
 use node_runtime::adapter::ViewRuntimeAdapter;
 use node_runtime::config::tx_cost;
-use node_runtime::state_viewer::{TrieViewer, ViewApplyState};
+use node_runtime::state_viewer::{DryRunResult, TrieViewer, ViewApplyState};
 use node_runtime::{
     ApplyState, Runtime, SignedValidPeriodTransactions, ValidatorAccountsUpdate,
     get_signer_and_access_key, set_tx_state_changes, validate_transaction,
     verify_and_charge_tx_ephemeral,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -95,7 +101,17 @@ impl NightshadeRuntime {
             None => RuntimeConfigStore::for_chain_id(&genesis_config.chain_id),
         };
 
-        let runtime = Runtime::new();
+        let paused_accounts = if genesis_config.chain_id == near_primitives::chains::MAINNET
+            || genesis_config.chain_id == near_primitives::chains::TESTNET
+        {
+            if !genesis_config.paused_accounts.is_empty() {
+                tracing::warn!(target: "runtime", chain_id = %genesis_config.chain_id, "Ignoring paused_accounts in genesis config");
+            }
+            vec![]
+        } else {
+            genesis_config.paused_accounts.clone()
+        };
+        let runtime = Runtime::new().with_paused_accounts(paused_accounts);
         let trie_viewer = TrieViewer::new(trie_viewer_state_size_limit, max_gas_burnt_view);
         let flat_storage_manager = FlatStorageManager::new(store.flat_store());
         let epoch_config = epoch_manager.read().get_epoch_config(genesis_config.protocol_version);
@@ -256,8 +272,9 @@ impl NightshadeRuntime {
             trie_access_tracker_state: Default::default(),
         };
 
+        let io_counters = trie.io_counters();
         let instant = Instant::now();
-        let apply_result = self
+        let mut apply_result = self
             .runtime
             .apply(
                 trie,
@@ -296,6 +313,9 @@ impl NightshadeRuntime {
         if let Some(mut metrics) = apply_result.metrics {
             metrics.report(&shard_label);
         }
+        let storage_io = storage_io_stats(&io_counters, &apply_result.trie_changes);
+        report_storage_io_metrics(&shard_label, &storage_io);
+        apply_result.stats.storage_io = storage_io;
 
         let total_balance_burnt = apply_result
             .stats
@@ -431,6 +451,283 @@ impl NightshadeRuntime {
 
         Ok(state_part)
     }
+
+    /// Runs `request` like `RuntimeAdapter::query`. The requests which only
+    /// read the state look it up in `view`, which is opened by the first of
+    /// them and can be shared with the next requests against the same state.
+    fn query_with_view(
+        &self,
+        view: &mut Option<TrieUpdate>,
+        shard_uid: ShardUId,
+        state_root: &StateRoot,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        prev_block_hash: &CryptoHash,
+        block_hash: &CryptoHash,
+        epoch_id: &EpochId,
+        request: &QueryRequest,
+    ) -> Result<QueryResponse, crate::near_chain_primitives::error::QueryError> {
+        match request {
+            QueryRequest::ViewAccount { account_id } => {
+                let state_update = self.view_trie_update(view, shard_uid, *state_root);
+                let account =
+                    self.trie_viewer.view_account(state_update, account_id).map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_view_account_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::ViewAccount(account.into()),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::ViewCode { account_id } => {
+                let state_update = self.view_trie_update(view, shard_uid, *state_root);
+                let contract_code = self
+                    .trie_viewer
+                    .view_contract_code(state_update, account_id)
+                    .map_err(|err| {
+                    crate::near_chain_primitives::error::QueryError::from_view_contract_code_error(
+                        err,
+                        block_height,
+                        *block_hash,
+                    )
+                })?;
+                let hash = *contract_code.hash();
+                let contract_code_view = ContractCodeView { hash, code: contract_code.into_code() };
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::ViewCode(contract_code_view),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::CallFunction { account_id, method_name, args } => {
+                let mut logs = vec![];
+                let (epoch_height, current_protocol_version) = {
+                    let epoch_manager = self.epoch_manager.read();
+                    let epoch_info = epoch_manager.get_epoch_info(epoch_id).map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_epoch_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                    (epoch_info.epoch_height(), epoch_info.protocol_version())
+                };
+
+                let call_function_result = self
+                    .call_function(
+                        &shard_uid,
+                        *state_root,
+                        block_height,
+                        block_timestamp,
+                        prev_block_hash,
+                        block_hash,
+                        epoch_height,
+                        epoch_id,
+                        account_id,
+                        method_name,
+                        args.as_ref(),
+                        &mut logs,
+                        self.epoch_manager.as_ref(),
+                        current_protocol_version,
+                    )
+                    .map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_call_function_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::CallResult(CallResult {
+                        result: call_function_result,
+                        logs,
+                    }),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::DryRunTransaction {
+                signed_transaction,
+                skip_signature_check,
+                state_overrides,
+            } => {
+                let (epoch_height, current_protocol_version) = {
+                    let epoch_manager = self.epoch_manager.read();
+                    let epoch_info = epoch_manager.get_epoch_info(epoch_id).map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_epoch_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                    (epoch_info.epoch_height(), epoch_info.protocol_version())
+                };
+                // Transactions included on top of this block pay its next gas price.
+                let gas_price = self
+                    .store
+                    .chain_store()
+                    .get_block_header(block_hash)
+                    .map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::InternalError {
+                            error_message: err.to_string(),
+                            block_height,
+                            block_hash: *block_hash,
+                        }
+                    })?
+                    .next_gas_price();
+
+                let dry_run_result = self
+                    .dry_run_transaction(
+                        &shard_uid,
+                        *state_root,
+                        block_height,
+                        block_timestamp,
+                        prev_block_hash,
+                        block_hash,
+                        epoch_height,
+                        epoch_id,
+                        gas_price,
+                        signed_transaction.clone(),
+                        *skip_signature_check,
+                        state_overrides,
+                        self.epoch_manager.as_ref(),
+                        current_protocol_version,
+                    )
+                    .map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_dry_run_transaction_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                let dry_run_result_view =
+                    dry_run_result_view(dry_run_result, block_hash).map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::InternalError {
+                            error_message: err.to_string(),
+                            block_height,
+                            block_hash: *block_hash,
+                        }
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::DryRunResult(dry_run_result_view),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::ViewState { account_id, prefix, include_proof } => {
+                let view_state_result = self
+                    .view_state(
+                        &shard_uid,
+                        *state_root,
+                        block_hash,
+                        account_id,
+                        prefix.as_ref(),
+                        *include_proof,
+                    )
+                    .map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_view_state_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::ViewState(view_state_result),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::ViewStatePaged {
+                account_id,
+                prefix,
+                start_key,
+                limit,
+                include_proof,
+            } => {
+                let view_state_result = self
+                    .view_state_paged(
+                        &shard_uid,
+                        *state_root,
+                        block_hash,
+                        account_id,
+                        prefix.as_ref(),
+                        start_key.as_ref().map_or(&[][..], |key| key.as_slice()),
+                        *limit,
+                        *include_proof,
+                    )
+                    .map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_view_state_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::ViewStatePaged(view_state_result),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::ViewAccessKeyList { account_id } => {
+                let state_update = self.view_trie_update(view, shard_uid, *state_root);
+                let access_key_list =
+                    self.trie_viewer.view_access_keys(state_update, account_id).map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_view_access_key_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::AccessKeyList(
+                        access_key_list
+                            .into_iter()
+                            .map(|(public_key, access_key)| AccessKeyInfoView {
+                                public_key,
+                                access_key: access_key.into(),
+                            })
+                            .collect(),
+                    ),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::ViewAccessKey { account_id, public_key } => {
+                let state_update = self.view_trie_update(view, shard_uid, *state_root);
+                let access_key = self
+                    .trie_viewer
+                    .view_access_key(state_update, account_id, public_key)
+                    .map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_view_access_key_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::AccessKey(access_key.into()),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+        }
+    }
+
+    /// Returns the trie update of the state in `view`, and opens it first if
+    /// none of the previous requests did.
+    fn view_trie_update<'a>(
+        &self,
+        view: &'a mut Option<TrieUpdate>,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+    ) -> &'a TrieUpdate {
+        view.get_or_insert_with(|| self.tries.new_trie_update_view(shard_uid, state_root))
+    }
 }
 
 fn format_total_gas_burnt(gas: Gas) -> String {
@@ -439,6 +736,37 @@ fn format_total_gas_burnt(gas: Gas) -> String {
     format!("{:.0}", ((gas as f64) / 1e14).ceil() * 100.0)
 }
 
+/// Combines the reads counted by the trie the chunk was applied with and the
+/// writes of the resulting trie changes.
+fn storage_io_stats(io_counters: &TrieIoCounters, trie_changes: &TrieChanges) -> StorageIoStats {
+    StorageIoStats {
+        trie_node_writes: (trie_changes.insertions().len() + trie_changes.deletions().len()) as u64,
+        bytes_written: trie_changes
+            .insertions()
+            .iter()
+            .map(|insertion| insertion.payload().len() as u64)
+            .sum(),
+        ..io_counters.read_stats()
+    }
+}
+
+fn report_storage_io_metrics(shard_label: &str, storage_io: &StorageIoStats) {
+    for (op, count) in [
+        ("trie_node_read", storage_io.trie_node_reads),
+        ("trie_node_write", storage_io.trie_node_writes),
+        ("flat_storage_hit", storage_io.flat_storage_hits),
+        ("flat_storage_miss", storage_io.flat_storage_misses),
+    ] {
+        metrics::APPLY_CHUNK_STORAGE_OPS.with_label_values(&[shard_label, op]).inc_by(count);
+    }
+    for (direction, bytes) in [("read", storage_io.bytes_read), ("write", storage_io.bytes_written)]
+    {
+        metrics::APPLY_CHUNK_STORAGE_BYTES
+            .with_label_values(&[shard_label, direction])
+            .inc_by(bytes);
+    }
+}
+
 impl RuntimeAdapter for NightshadeRuntime {
     fn store(&self) -> &Store {
         &self.store
@@ -560,7 +888,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         time_limit: Option<Duration>,
     ) -> Result<PreparedTransactions, Error> {
         let start_time = std::time::Instant::now();
-        let PrepareTransactionsChunkContext { shard_id, .. } = chunk;
+        let PrepareTransactionsChunkContext { shard_id, signer_limits, .. } = chunk;
 
         let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_block.block_hash)?;
         let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
@@ -605,14 +933,21 @@ impl RuntimeAdapter for NightshadeRuntime {
 
         let transactions_gas_limit = chunk_tx_gas_limit(runtime_config, &prev_block, shard_id);
 
-        let mut result = PreparedTransactions { transactions: Vec::new(), limited_by: None };
+        let mut result = PreparedTransactions {
+            transactions: Vec::new(),
+            limited_by: None,
+            limited_signers: Vec::new(),
+        };
         let mut num_checked_transactions = 0;
+        let mut num_transactions_by_signer: HashMap<AccountId, usize> = HashMap::new();
+        let mut limited_signers = HashSet::new();
 
         let size_limit = runtime_config.witness_config.combined_transactions_size_limit as u64;
         // for metrics only
         let mut rejected_due_to_congestion = 0;
         let mut rejected_invalid_tx = 0;
         let mut rejected_invalid_for_chain = 0;
+        let mut rejected_due_to_signer_limit = 0;
 
         // Add new transactions to the result until some limit is hit or the transactions run out.
         'add_txs_loop: while let Some(transaction_group_iter) = transaction_groups.next() {
@@ -639,6 +974,23 @@ impl RuntimeAdapter for NightshadeRuntime {
                 break;
             }
 
+            // Leave the rest of the group in the pool if its signer has already reached
+            // its limit, so that the other signers get their share of the chunk.
+            if let (Some(signer_limits), Some(tx_peek)) =
+                (&signer_limits, transaction_group_iter.peek_next())
+            {
+                let signer_id = tx_peek.signer_id();
+                let num_transactions =
+                    num_transactions_by_signer.get(signer_id).copied().unwrap_or_default();
+                if num_transactions >= signer_limits.get(signer_id) {
+                    tracing::trace!(target: "runtime", ?signer_id, num_transactions, "deferring transactions of the signer that reached its limit");
+                    limited_signers.insert(signer_id.clone());
+                    rejected_due_to_signer_limit += 1;
+                    transaction_group_iter.defer();
+                    continue;
+                }
+            }
+
             // Take a single transaction from this transaction group
             while let Some(tx_peek) = transaction_group_iter.peek_next() {
                 // Stop adding transactions if the size limit would be exceeded
@@ -706,6 +1058,9 @@ impl RuntimeAdapter for NightshadeRuntime {
                         state_update.commit(StateChangeCause::NotWritableToDisk);
                         total_gas_burnt += cost.gas_burnt;
                         total_size += validated_tx.get_size();
+                        *num_transactions_by_signer
+                            .entry(validated_tx.signer_id().clone())
+                            .or_default() += 1;
                         result.transactions.push(validated_tx);
                         // Take one transaction from this group, no more.
                         break;
@@ -718,6 +1073,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                 }
             }
         }
+        result.limited_signers = limited_signers.into_iter().collect();
         debug!(target: "runtime", limited_by=?result.limited_by, "Transaction filtering results {} valid out of {} pulled from the pool", result.transactions.len(), num_checked_transactions);
         let shard_label = shard_id.to_string();
         metrics::PREPARE_TX_SIZE.with_label_values(&[&shard_label]).observe(total_size as f64);
@@ -730,6 +1086,9 @@ impl RuntimeAdapter for NightshadeRuntime {
         metrics::PREPARE_TX_REJECTED
             .with_label_values(&[&shard_label, "invalid_block_hash"])
             .observe(rejected_invalid_for_chain as f64);
+        metrics::PREPARE_TX_REJECTED
+            .with_label_values(&[&shard_label, "signer_limit"])
+            .observe(rejected_due_to_signer_limit as f64);
         metrics::PREPARE_TX_GAS.with_label_values(&[&shard_label]).observe(total_gas_burnt as f64);
         metrics::CONGESTION_PREPARE_TX_GAS_LIMIT
             .with_label_values(&[&shard_label])
@@ -843,143 +1202,48 @@ impl RuntimeAdapter for NightshadeRuntime {
         epoch_id: &EpochId,
         request: &QueryRequest,
     ) -> Result<QueryResponse, crate::near_chain_primitives::error::QueryError> {
-        match request {
-            QueryRequest::ViewAccount { account_id } => {
-                let account =
-                    self.view_account(&shard_uid, *state_root, account_id).map_err(|err| {
-                        crate::near_chain_primitives::error::QueryError::from_view_account_error(
-                            err,
-                            block_height,
-                            *block_hash,
-                        )
-                    })?;
-                Ok(QueryResponse {
-                    kind: QueryResponseKind::ViewAccount(account.into()),
-                    block_height,
-                    block_hash: *block_hash,
-                })
-            }
-            QueryRequest::ViewCode { account_id } => {
-                let contract_code = self
-                    .view_contract_code(&shard_uid,  *state_root, account_id)
-                    .map_err(|err| crate::near_chain_primitives::error::QueryError::from_view_contract_code_error(err, block_height, *block_hash))?;
-                let hash = *contract_code.hash();
-                let contract_code_view = ContractCodeView { hash, code: contract_code.into_code() };
-                Ok(QueryResponse {
-                    kind: QueryResponseKind::ViewCode(contract_code_view),
-                    block_height,
-                    block_hash: *block_hash,
-                })
-            }
-            QueryRequest::CallFunction { account_id, method_name, args } => {
-                let mut logs = vec![];
-                let (epoch_height, current_protocol_version) = {
-                    let epoch_manager = self.epoch_manager.read();
-                    let epoch_info = epoch_manager.get_epoch_info(epoch_id).map_err(|err| {
-                        crate::near_chain_primitives::error::QueryError::from_epoch_error(
-                            err,
-                            block_height,
-                            *block_hash,
-                        )
-                    })?;
-                    (epoch_info.epoch_height(), epoch_info.protocol_version())
-                };
+        self.query_with_view(
+            &mut None,
+            shard_uid,
+            state_root,
+            block_height,
+            block_timestamp,
+            prev_block_hash,
+            block_hash,
+            epoch_id,
+            request,
+        )
+    }
 
-                let call_function_result = self
-                    .call_function(
-                        &shard_uid,
-                        *state_root,
-                        block_height,
-                        block_timestamp,
-                        prev_block_hash,
-                        block_hash,
-                        epoch_height,
-                        epoch_id,
-                        account_id,
-                        method_name,
-                        args.as_ref(),
-                        &mut logs,
-                        self.epoch_manager.as_ref(),
-                        current_protocol_version,
-                    )
-                    .map_err(|err| {
-                        crate::near_chain_primitives::error::QueryError::from_call_function_error(
-                            err,
-                            block_height,
-                            *block_hash,
-                        )
-                    })?;
-                Ok(QueryResponse {
-                    kind: QueryResponseKind::CallResult(CallResult {
-                        result: call_function_result,
-                        logs,
-                    }),
-                    block_height,
-                    block_hash: *block_hash,
-                })
-            }
-            QueryRequest::ViewState { account_id, prefix, include_proof } => {
-                let view_state_result = self
-                    .view_state(
-                        &shard_uid,
-                        *state_root,
-                        account_id,
-                        prefix.as_ref(),
-                        *include_proof,
-                    )
-                    .map_err(|err| {
-                        crate::near_chain_primitives::error::QueryError::from_view_state_error(
-                            err,
-                            block_height,
-                            *block_hash,
-                        )
-                    })?;
-                Ok(QueryResponse {
-                    kind: QueryResponseKind::ViewState(view_state_result),
-                    block_height,
-                    block_hash: *block_hash,
-                })
-            }
-            QueryRequest::ViewAccessKeyList { account_id } => {
-                let access_key_list =
-                    self.view_access_keys(&shard_uid, *state_root, account_id).map_err(|err| {
-                        crate::near_chain_primitives::error::QueryError::from_view_access_key_error(
-                            err,
-                            block_height,
-                            *block_hash,
-                        )
-                    })?;
-                Ok(QueryResponse {
-                    kind: QueryResponseKind::AccessKeyList(
-                        access_key_list
-                            .into_iter()
-                            .map(|(public_key, access_key)| AccessKeyInfoView {
-                                public_key,
-                                access_key: access_key.into(),
-                            })
-                            .collect(),
-                    ),
-                    block_height,
-                    block_hash: *block_hash,
-                })
-            }
-            QueryRequest::ViewAccessKey { account_id, public_key } => {
-                let access_key = self
-                    .view_access_key(&shard_uid, *state_root, account_id, public_key)
-                    .map_err(|err| {
-                        crate::near_chain_primitives::error::QueryError::from_view_access_key_error(
-                            err,
-                            block_height,
-                            *block_hash,
-                        )
-                    })?;
-                Ok(QueryResponse {
-                    kind: QueryResponseKind::AccessKey(access_key.into()),
+    fn query_batch(
+        &self,
+        shard_uid: ShardUId,
+        state_root: &StateRoot,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        prev_block_hash: &CryptoHash,
+        block_hash: &CryptoHash,
+        epoch_id: &EpochId,
+        requests: &[&QueryRequest],
+    ) -> Vec<Result<QueryResponse, crate::near_chain_primitives::error::QueryError>> {
+        // The requests which only read the state share the trie of the state.
+        let mut view = None;
+        requests
+            .iter()
+            .map(|request| {
+                self.query_with_view(
+                    &mut view,
+                    shard_uid,
+                    state_root,
                     block_height,
-                    block_hash: *block_hash,
-                })
-            }
-        }
+                    block_timestamp,
+                    prev_block_hash,
+                    block_hash,
+                    epoch_id,
+                    request,
+                )
+            })
+            .collect()
     }
 
     // Wrapper to get the metrics.
@@ -1099,33 +1363,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         genesis_config.protocol_version = protocol_version;
 
         let epoch_config = self.epoch_manager.get_epoch_config(epoch_id)?;
-        genesis_config.epoch_length = epoch_config.epoch_length;
-        genesis_config.num_block_producer_seats = epoch_config.num_block_producer_seats;
-        genesis_config.num_block_producer_seats_per_shard =
-            epoch_config.num_block_producer_seats_per_shard;
-        genesis_config.avg_hidden_validator_seats_per_shard =
-            epoch_config.avg_hidden_validator_seats_per_shard;
-        genesis_config.block_producer_kickout_threshold =
-            epoch_config.block_producer_kickout_threshold;
-        genesis_config.chunk_producer_kickout_threshold =
-            epoch_config.chunk_producer_kickout_threshold;
-        genesis_config.chunk_validator_only_kickout_threshold =
-            epoch_config.chunk_validator_only_kickout_threshold;
-        genesis_config.target_validator_mandates_per_shard =
-            epoch_config.target_validator_mandates_per_shard;
-        genesis_config.max_kickout_stake_perc = epoch_config.validator_max_kickout_stake_perc;
-        genesis_config.online_min_threshold = epoch_config.online_min_threshold;
-        genesis_config.online_max_threshold = epoch_config.online_max_threshold;
-        genesis_config.fishermen_threshold = epoch_config.fishermen_threshold;
-        genesis_config.minimum_stake_divisor = epoch_config.minimum_stake_divisor;
-        genesis_config.protocol_upgrade_stake_threshold =
-            epoch_config.protocol_upgrade_stake_threshold;
-        genesis_config.shard_layout = epoch_config.shard_layout;
-        genesis_config.num_chunk_only_producer_seats = epoch_config.num_chunk_only_producer_seats;
-        genesis_config.minimum_validators_per_shard = epoch_config.minimum_validators_per_shard;
-        genesis_config.minimum_stake_ratio = epoch_config.minimum_stake_ratio;
-        genesis_config.shuffle_shard_assignment_for_chunk_producers =
-            epoch_config.shuffle_shard_assignment_for_chunk_producers;
+        set_epoch_config(&mut genesis_config, epoch_config);
 
         let runtime_config =
             self.runtime_config_store.get_config(protocol_version).as_ref().clone();
@@ -1184,6 +1422,34 @@ impl RuntimeAdapter for NightshadeRuntime {
     }
 }
 
+/// Overrides the fields of `genesis_config` which are given by the config of the epoch.
+pub(crate) fn set_epoch_config(genesis_config: &mut GenesisConfig, epoch_config: EpochConfig) {
+    genesis_config.epoch_length = epoch_config.epoch_length;
+    genesis_config.num_block_producer_seats = epoch_config.num_block_producer_seats;
+    genesis_config.num_block_producer_seats_per_shard =
+        epoch_config.num_block_producer_seats_per_shard;
+    genesis_config.avg_hidden_validator_seats_per_shard =
+        epoch_config.avg_hidden_validator_seats_per_shard;
+    genesis_config.block_producer_kickout_threshold = epoch_config.block_producer_kickout_threshold;
+    genesis_config.chunk_producer_kickout_threshold = epoch_config.chunk_producer_kickout_threshold;
+    genesis_config.chunk_validator_only_kickout_threshold =
+        epoch_config.chunk_validator_only_kickout_threshold;
+    genesis_config.target_validator_mandates_per_shard =
+        epoch_config.target_validator_mandates_per_shard;
+    genesis_config.max_kickout_stake_perc = epoch_config.validator_max_kickout_stake_perc;
+    genesis_config.online_min_threshold = epoch_config.online_min_threshold;
+    genesis_config.online_max_threshold = epoch_config.online_max_threshold;
+    genesis_config.fishermen_threshold = epoch_config.fishermen_threshold;
+    genesis_config.minimum_stake_divisor = epoch_config.minimum_stake_divisor;
+    genesis_config.protocol_upgrade_stake_threshold = epoch_config.protocol_upgrade_stake_threshold;
+    genesis_config.shard_layout = epoch_config.shard_layout;
+    genesis_config.num_chunk_only_producer_seats = epoch_config.num_chunk_only_producer_seats;
+    genesis_config.minimum_validators_per_shard = epoch_config.minimum_validators_per_shard;
+    genesis_config.minimum_stake_ratio = epoch_config.minimum_stake_ratio;
+    genesis_config.shuffle_shard_assignment_for_chunk_producers =
+        epoch_config.shuffle_shard_assignment_for_chunk_producers;
+}
+
 /// How much gas of the next chunk we want to spend on converting new
 /// transactions to receipts.
 fn chunk_tx_gas_limit(
@@ -1231,6 +1497,36 @@ fn congestion_control_accepts_transaction(
     Ok(shard_accepts_transactions.is_yes())
 }
 
+fn dry_run_result_view(
+    dry_run_result: DryRunResult,
+    block_hash: &CryptoHash,
+) -> Result<DryRunResultView, std::io::Error> {
+    let DryRunResult { outcomes, receipts_not_executed, state_changes } = dry_run_result;
+    let gas_burnt = outcomes.iter().map(|outcome| outcome.outcome.gas_burnt).sum();
+    let tokens_burnt = outcomes.iter().map(|outcome| outcome.outcome.tokens_burnt).sum();
+    let mut outcomes = outcomes.into_iter().map(|outcome_with_id| {
+        ExecutionOutcomeWithIdView::from(ExecutionOutcomeWithIdAndProof {
+            proof: vec![],
+            block_hash: *block_hash,
+            outcome_with_id,
+        })
+    });
+    let transaction_outcome = outcomes.next().expect("dry run always has a transaction outcome");
+    let state_changes: StateChangesView =
+        StateChanges::from_changes(state_changes.into_iter().map(Ok))?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+    Ok(DryRunResultView {
+        transaction_outcome,
+        receipts_outcome: outcomes.collect(),
+        receipts_not_executed: receipts_not_executed.into_iter().map(ReceiptView::from).collect(),
+        gas_burnt,
+        tokens_burnt,
+        state_changes,
+    })
+}
+
 impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
     fn view_account(
         &self,
@@ -1292,6 +1588,48 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         )
     }
 
+    fn dry_run_transaction(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        height: BlockHeight,
+        block_timestamp: u64,
+        prev_block_hash: &CryptoHash,
+        block_hash: &CryptoHash,
+        epoch_height: EpochHeight,
+        epoch_id: &EpochId,
+        gas_price: Balance,
+        signed_tx: SignedTransaction,
+        skip_signature_check: bool,
+        state_overrides: &[StateOverrideView],
+        epoch_info_provider: &dyn EpochInfoProvider,
+        current_protocol_version: ProtocolVersion,
+    ) -> Result<DryRunResult, node_runtime::state_viewer::errors::DryRunTransactionError> {
+        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        let view_state = ViewApplyState {
+            shard_id: shard_uid.shard_id(),
+            block_height: height,
+            prev_block_hash: *prev_block_hash,
+            block_hash: *block_hash,
+            epoch_id: *epoch_id,
+            epoch_height,
+            block_timestamp,
+            current_protocol_version,
+            cache: Some(self.compiled_contract_cache.handle()),
+        };
+        let config = self.runtime_config_store.get_config(current_protocol_version);
+        self.trie_viewer.dry_run_transaction(
+            state_update,
+            view_state,
+            Arc::clone(config),
+            gas_price,
+            signed_tx,
+            skip_signature_check,
+            state_overrides,
+            epoch_info_provider,
+        )
+    }
+
     fn view_access_key(
         &self,
         shard_uid: &ShardUId,
@@ -1318,11 +1656,38 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
     ) -> Result<ViewStateResult, node_runtime::state_viewer::errors::ViewStateError> {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        let trie =
+            self.tries.get_trie_with_block_hash_for_shard(*shard_uid, state_root, block_hash, true);
+        let state_update = TrieUpdate::new(trie);
         self.trie_viewer.view_state(&state_update, account_id, prefix, include_proof)
     }
+
+    fn view_state_paged(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        block_hash: &CryptoHash,
+        account_id: &AccountId,
+        prefix: &[u8],
+        start_key: &[u8],
+        limit: std::num::NonZeroU32,
+        include_proof: bool,
+    ) -> Result<ViewStatePagedResult, node_runtime::state_viewer::errors::ViewStateError> {
+        let trie =
+            self.tries.get_trie_with_block_hash_for_shard(*shard_uid, state_root, block_hash, true);
+        let state_update = TrieUpdate::new(trie);
+        self.trie_viewer.view_state_paged(
+            &state_update,
+            account_id,
+            prefix,
+            start_key,
+            limit,
+            include_proof,
+        )
+    }
 }
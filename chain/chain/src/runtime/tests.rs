@@ -1,6 +1,6 @@
 use super::*;
 use crate::rayon_spawner::RayonAsyncComputationSpawner;
-use crate::types::{BlockType, ChainConfig, RuntimeStorageConfig};
+use crate::types::{BlockType, ChainConfig, RuntimeStorageConfig, SignerTransactionLimits};
 use crate::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode};
 use assert_matches::assert_matches;
 use near_async::messaging::{IntoMultiSender, noop};
@@ -862,6 +862,7 @@ fn test_get_validator_info() {
             num_expected_endorsements: expected_endorsements[0],
             num_expected_endorsements_per_shard: vec![expected_endorsements[0]],
             num_produced_endorsements_per_shard: vec![expected_endorsements[0]],
+            delegation: None,
         },
         CurrentEpochValidatorInfo {
             account_id: "test2".parse().unwrap(),
@@ -880,6 +881,7 @@ fn test_get_validator_info() {
             num_expected_endorsements: expected_endorsements[1],
             num_expected_endorsements_per_shard: vec![expected_endorsements[1]],
             num_produced_endorsements_per_shard: vec![expected_endorsements[1]],
+            delegation: None,
         },
     ];
     let next_epoch_validator_info = vec![
@@ -1275,6 +1277,39 @@ fn test_trie_and_flat_state_equality() {
     assert_eq!(state_value, view_state_value);
 }
 
+/// Check that the storage I/O stats of an applied chunk count the trie nodes
+/// read from the store, but not the ones served by the shard cache.
+#[test]
+fn test_storage_io_stats_trie_node_reads() {
+    let num_nodes = 2;
+    let validators = (0..num_nodes)
+        .map(|i| AccountId::try_from(format!("test{}", i + 1)).unwrap())
+        .collect::<Vec<_>>();
+    let env = TestEnv::new(vec![validators.clone()], 4, false);
+    let signer = InMemorySigner::test_signer(&validators[0]);
+    let transfer_tx = SignedTransaction::from_actions(
+        4,
+        signer.get_account_id(),
+        validators[1].clone(),
+        &signer,
+        vec![Action::Transfer(TransferAction { deposit: 10 })],
+        // runtime does not validate block history
+        CryptoHash::default(),
+        0,
+    );
+    let shard_id = env.epoch_manager.shard_ids(&env.head.epoch_id).unwrap()[0];
+    let new_hash = hash(&[(env.head.height + 1) as u8]);
+
+    // The genesis state is written directly to the store, so the nodes updated
+    // by the transfer are read from the store first, then from the shard cache.
+    let first = env.apply_new_chunk(shard_id, new_hash, vec![transfer_tx.clone()], &[]);
+    let second = env.apply_new_chunk(shard_id, new_hash, vec![transfer_tx], &[]);
+    assert_eq!(first.new_root, second.new_root);
+    assert!(first.stats.storage_io.trie_node_reads > 0, "{:?}", first.stats.storage_io);
+    assert!(first.stats.storage_io.bytes_read > 0, "{:?}", first.stats.storage_io);
+    assert_eq!(second.stats.storage_io.trie_node_reads, 0, "{:?}", second.stats.storage_io);
+}
+
 /// Check that mainnet genesis hash still matches, to make sure that we're still backwards compatible.
 #[test]
 fn test_genesis_hash() {
@@ -1401,6 +1436,7 @@ fn prepare_transactions(
     chain: &Chain,
     transaction_groups: &mut dyn TransactionGroupIterator,
     storage_config: RuntimeStorageConfig,
+    signer_limits: Option<SignerTransactionLimits>,
 ) -> Result<PreparedTransactions, Error> {
     let prev_hash = env.head.prev_block_hash;
     let shard_layout = env.epoch_manager.get_shard_layout_from_prev_block(&prev_hash).unwrap();
@@ -1413,6 +1449,7 @@ fn prepare_transactions(
         PrepareTransactionsChunkContext {
             shard_id,
             gas_limit: env.runtime.genesis_config.gas_limit,
+            signer_limits,
         },
         PrepareTransactionsBlockContext {
             next_gas_price: env.runtime.genesis_config.min_gas_price,
@@ -1467,7 +1504,7 @@ fn test_prepare_transactions_helper(
 
     let mut transaction_groups = PoolIteratorWrapper::new(&mut transaction_pool);
     let prepared_transactions =
-        match prepare_transactions(&env, &chain, &mut transaction_groups, storage_config) {
+        match prepare_transactions(&env, &chain, &mut transaction_groups, storage_config, None) {
             Ok(prepared_transactions) => prepared_transactions,
             Err(err) => {
                 return Err(err);
@@ -1476,6 +1513,54 @@ fn test_prepare_transactions_helper(
     Ok((transactions_count, prepared_transactions))
 }
 
+/// Check that the transactions of a signer above its limit are left in the pool.
+#[test]
+fn test_prepare_transactions_signer_limits() {
+    let (env, chain, mut transaction_pool) = get_test_env_with_chain_and_pool();
+    let transactions_count = transaction_pool.len();
+    let limited_signer: AccountId = "test1".parse().unwrap();
+    let signer_limits = SignerTransactionLimits {
+        default_limit: 2,
+        limits: HashMap::from([(limited_signer.clone(), 1)]),
+    };
+
+    let storage_config = RuntimeStorageConfig {
+        state_root: env.state_roots[0],
+        use_flat_storage: true,
+        source: StorageDataSource::Db,
+        state_patch: Default::default(),
+    };
+    let mut transaction_groups = PoolIteratorWrapper::new(&mut transaction_pool);
+    let prepared_transactions = prepare_transactions(
+        &env,
+        &chain,
+        &mut transaction_groups,
+        storage_config,
+        Some(signer_limits.clone()),
+    )
+    .unwrap();
+    drop(transaction_groups);
+
+    let mut num_transactions_by_signer = HashMap::new();
+    for tx in &prepared_transactions.transactions {
+        *num_transactions_by_signer.entry(tx.signer_id().clone()).or_insert(0) += 1;
+    }
+    assert_eq!(num_transactions_by_signer.len(), 4);
+    for (signer_id, num_transactions) in &num_transactions_by_signer {
+        assert_eq!(*num_transactions, signer_limits.get(signer_id));
+    }
+    // Every signer had 3 transactions in the pool, so all of them reached their limit.
+    let mut limited_signers = prepared_transactions.limited_signers;
+    limited_signers.sort();
+    let mut signers = num_transactions_by_signer.keys().cloned().collect::<Vec<_>>();
+    signers.sort();
+    assert_eq!(limited_signers, signers);
+    assert_eq!(
+        transaction_pool.len(),
+        transactions_count - prepared_transactions.transactions.len()
+    );
+}
+
 #[test]
 #[cfg_attr(not(feature = "test_features"), ignore)]
 fn test_storage_proof_garbage() {
@@ -1,3 +1,4 @@
+use crate::metrics;
 use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt};
 use near_async::messaging::{Actor, CanSend, Handler, HandlerWithContext, Sender};
 use near_async::time::Duration;
@@ -10,16 +11,24 @@ use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::{BlockHeight, EpochHeight, ShardIndex};
 use near_store::flat::FlatStorageManager;
 use near_store::{ShardTries, StateSnapshotConfig};
+use std::path::Path;
 use std::sync::Arc;
+use sysinfo::{DiskExt, System, SystemExt};
 
 /// Runs tasks related to state snapshots.
 /// There are three main handlers in StateSnapshotActor and they are called in sequence
 /// 1. [`DeleteSnapshotRequest`]: deletes a snapshot.
 /// 2. [`CreateSnapshotRequest`]: creates a new snapshot.
+///
+/// Independently of these, [`CreateRestorePointRequest`] takes restore points
+/// according to the configured [`near_store::config::StateSnapshotPolicy`].
 pub struct StateSnapshotActor {
     flat_storage_manager: FlatStorageManager,
     network_adapter: PeerManagerAdapter,
     tries: ShardTries,
+    /// Height of the most recent restore point, used to drop requests which
+    /// queued up while a restore point was being taken.
+    last_restore_point_height: Option<BlockHeight>,
 }
 
 impl Actor for StateSnapshotActor {}
@@ -30,7 +39,7 @@ impl StateSnapshotActor {
         network_adapter: PeerManagerAdapter,
         tries: ShardTries,
     ) -> Self {
-        Self { flat_storage_manager, network_adapter, tries }
+        Self { flat_storage_manager, network_adapter, tries, last_restore_point_height: None }
     }
 }
 
@@ -53,6 +62,26 @@ pub struct CreateSnapshotRequest {
     block: Block,
 }
 
+/// Why a restore point is taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum RestorePointTrigger {
+    EveryNBlocks,
+    BeforeProtocolUpgrade,
+    OnDemand,
+}
+
+#[derive(actix::Message, Debug)]
+#[rtype(result = "()")]
+pub struct CreateRestorePointRequest {
+    /// Height of the head when the restore point was requested. The restore
+    /// point is a checkpoint of the database at the time it is taken, so it
+    /// may include a few blocks past this height.
+    pub height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub trigger: RestorePointTrigger,
+}
+
 impl std::fmt::Debug for CreateSnapshotRequest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CreateSnapshotRequest")
@@ -179,6 +208,84 @@ impl StateSnapshotActor {
     }
 }
 
+impl StateSnapshotActor {
+    pub fn handle_create_restore_point_request(
+        &mut self,
+        msg: CreateRestorePointRequest,
+        ctx: &mut dyn DelayedActionRunner<Self>,
+    ) {
+        let state_snapshot_config = self.tries.state_snapshot_config();
+        let (Some(policy), Some(restore_points_dir)) = (
+            state_snapshot_config.restore_point_policy(),
+            state_snapshot_config.restore_points_dir(),
+        ) else {
+            tracing::info!(target: "state_snapshot", ?msg, "Restore points are disabled");
+            return;
+        };
+        if self.last_restore_point_height.is_some_and(|height| height >= msg.height) {
+            tracing::debug!(target: "state_snapshot", ?msg, last_restore_point_height = ?self.last_restore_point_height, "Skipping restore point, a more recent one was already taken");
+            metrics::RESTORE_POINTS_SKIPPED.with_label_values(&["already_taken"]).inc();
+            return;
+        }
+        // Don't compete with the state snapshot for disk bandwidth. The state
+        // snapshot also keeps the flat head locked until it's done, so come
+        // back once it has been taken.
+        if self.flat_storage_manager.snapshot_hash_wanted().is_some() {
+            tracing::debug!(target: "state_snapshot", ?msg, "Postpone CreateRestorePointRequest until the state snapshot is taken");
+            ctx.run_later(
+                "StateSnapshotActor CreateRestorePoint",
+                Duration::seconds(1),
+                move |act, ctx| {
+                    act.handle_create_restore_point_request(msg, ctx);
+                },
+            );
+            return;
+        }
+        let min_free_disk_space = policy.min_free_disk_space.as_u64();
+        match available_disk_space(restore_points_dir) {
+            Some(available) if available < min_free_disk_space => {
+                tracing::warn!(target: "state_snapshot", ?msg, available, min_free_disk_space, "Not enough free disk space, skipping restore point");
+                metrics::RESTORE_POINTS_SKIPPED.with_label_values(&["low_disk_space"]).inc();
+                return;
+            }
+            Some(_) => {}
+            None => {
+                tracing::warn!(target: "state_snapshot", ?restore_points_dir, "Could not determine free disk space, taking restore point anyway");
+            }
+        }
+
+        let max_restore_points = policy.max_restore_points;
+        tracing::info!(target: "state_snapshot", ?msg, "Handle CreateRestorePointRequest");
+        match self.tries.create_restore_point(msg.height, msg.block_hash) {
+            Ok(_) => {
+                self.last_restore_point_height = Some(msg.height);
+                metrics::RESTORE_POINTS_TAKEN.with_label_values(&[msg.trigger.into()]).inc();
+            }
+            Err(err) => {
+                tracing::error!(target: "state_snapshot", ?err, "Restore point creation failed");
+                return;
+            }
+        }
+        if let Err(err) = self.tries.prune_restore_points(max_restore_points) {
+            tracing::error!(target: "state_snapshot", ?err, "Failed to delete old restore points");
+        }
+    }
+}
+
+/// Returns the space available on the disk holding `path`, i.e. the disk with
+/// the longest mount point which is a prefix of `path`.
+fn available_disk_space(path: &Path) -> Option<u64> {
+    let path = path.ancestors().find_map(|ancestor| ancestor.canonicalize().ok())?;
+    let mut system = System::new();
+    system.refresh_disks_list();
+    system
+        .disks()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
 impl Handler<DeleteSnapshotRequest> for StateSnapshotActor {
     #[perf]
     fn handle(&mut self, msg: DeleteSnapshotRequest) {
@@ -193,6 +300,13 @@ impl HandlerWithContext<CreateSnapshotRequest> for StateSnapshotActor {
     }
 }
 
+impl HandlerWithContext<CreateRestorePointRequest> for StateSnapshotActor {
+    #[perf]
+    fn handle(&mut self, msg: CreateRestorePointRequest, ctx: &mut dyn DelayedActionRunner<Self>) {
+        self.handle_create_restore_point_request(msg, ctx)
+    }
+}
+
 #[derive(Clone, MultiSend, MultiSenderFrom)]
 pub struct StateSnapshotSenderForStateSnapshot {
     create_snapshot: Sender<CreateSnapshotRequest>,
@@ -202,6 +316,7 @@ pub struct StateSnapshotSenderForStateSnapshot {
 pub struct StateSnapshotSenderForClient {
     delete_snapshot: Sender<DeleteSnapshotRequest>,
     create_snapshot: Sender<CreateSnapshotRequest>,
+    create_restore_point: Sender<CreateRestorePointRequest>,
 }
 
 type MakeSnapshotCallback = Arc<
@@ -213,9 +328,13 @@ type MakeSnapshotCallback = Arc<
 
 type DeleteSnapshotCallback = Arc<dyn Fn() -> () + Send + Sync + 'static>;
 
+type MakeRestorePointCallback =
+    Arc<dyn Fn(RestorePointTrigger, BlockHeight, CryptoHash) -> () + Send + Sync + 'static>;
+
 pub struct SnapshotCallbacks {
     pub make_snapshot_callback: MakeSnapshotCallback,
     pub delete_snapshot_callback: DeleteSnapshotCallback,
+    pub make_restore_point_callback: MakeRestorePointCallback,
 }
 
 /// Sends a request to make a state snapshot.
@@ -256,3 +375,18 @@ pub fn get_delete_snapshot_callback(
         sender.send(DeleteSnapshotRequest {});
     })
 }
+
+/// Sends a request to take a restore point.
+pub fn get_make_restore_point_callback(
+    sender: StateSnapshotSenderForClient,
+) -> MakeRestorePointCallback {
+    Arc::new(move |trigger, height, block_hash| {
+        tracing::info!(
+            target: "state_snapshot",
+            ?trigger,
+            height,
+            ?block_hash,
+            "make_restore_point_callback sends `CreateRestorePointRequest` to state_snapshot_addr");
+        sender.send(CreateRestorePointRequest { height, block_hash, trigger });
+    })
+}
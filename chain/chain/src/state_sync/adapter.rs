@@ -273,6 +273,17 @@ impl ChainStateSyncAdapter {
         Ok(shard_state_header)
     }
 
+    /// Returns the state part if it was generated or received before.
+    pub fn get_stored_state_part(
+        &self,
+        shard_id: ShardId,
+        part_id: u64,
+        sync_hash: CryptoHash,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let key = borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id))?;
+        Ok(self.chain_store.store_ref().get(DBCol::StateParts, &key)?.map(Into::into))
+    }
+
     pub fn get_state_response_part(
         &mut self,
         shard_id: ShardId,
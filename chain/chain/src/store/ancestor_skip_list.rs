@@ -0,0 +1,231 @@
+//! Skip-list over block headers used to find ancestors of a block quickly.
+//!
+//! Every block stores a pointer to one of its ancestors, the skip ancestor, in
+//! `DBCol::BlockSkipAncestor`. The skip ancestor is the ancestor with the
+//! largest height not exceeding `skip_height(block.height())`. The skip heights
+//! are chosen in the same way as in Bitcoin's `CBlockIndex::pskip`, which makes
+//! ancestor lookups take a logarithmic number of steps in the distance.
+//!
+//! Blocks without a recorded skip ancestor, e.g. the ones whose ancestors are
+//! missing after epoch sync, are handled by stepping to the previous block. The
+//! skip ancestors of the blocks saved before the column was introduced are
+//! recorded by the database migration, see [`migrate_45_to_46`].
+
+use std::collections::HashMap;
+
+use near_chain_primitives::Error;
+use near_primitives::block::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+use near_store::DBCol;
+use near_store::adapter::StoreAdapter;
+use near_store::migrations::MigrationContext;
+
+/// Clears the lowest set bit of `n`.
+fn invert_lowest_one(n: BlockHeight) -> BlockHeight {
+    n & n.wrapping_sub(1)
+}
+
+/// Returns the height the skip pointer of a block at `height` should point to.
+/// The result is always strictly lower than `height` (except for 0).
+pub fn skip_height(height: BlockHeight) -> BlockHeight {
+    if height < 2 {
+        return 0;
+    }
+    // Use a skip height that is not too far back for odd heights, so that
+    // consecutive lookups can make progress in both small and large steps.
+    if height & 1 == 1 {
+        invert_lowest_one(invert_lowest_one(height - 1)) + 1
+    } else {
+        invert_lowest_one(height)
+    }
+}
+
+/// Walks back from `header` and returns its first ancestor (or the header
+/// itself) whose height is not greater than `height`.
+///
+/// `get_header` and `get_skip_ancestor` provide access to the headers and the
+/// skip pointers, so that the walk can be done both on the committed store and
+/// on a pending store update.
+pub fn find_ancestor_at_or_below(
+    mut header: BlockHeader,
+    height: BlockHeight,
+    get_header: impl Fn(&CryptoHash) -> Result<BlockHeader, Error>,
+    get_skip_ancestor: impl Fn(&CryptoHash) -> Result<Option<(BlockHeight, CryptoHash)>, Error>,
+) -> Result<BlockHeader, Error> {
+    while header.height() > height {
+        let next_hash = match get_skip_ancestor(header.hash())? {
+            // Only jump if we can't overshoot the ancestor we are looking for.
+            Some((skip_height, skip_hash)) if skip_height >= height => skip_hash,
+            _ => *header.prev_hash(),
+        };
+        header = get_header(&next_hash)?;
+    }
+    Ok(header)
+}
+
+/// Computes the skip ancestor for a new block, given its header.
+/// Returns `None` if the ancestors of the block are not available.
+pub fn compute_skip_ancestor(
+    header: &BlockHeader,
+    get_header: impl Fn(&CryptoHash) -> Result<BlockHeader, Error>,
+    get_skip_ancestor: impl Fn(&CryptoHash) -> Result<Option<(BlockHeight, CryptoHash)>, Error>,
+) -> Result<Option<(BlockHeight, CryptoHash)>, Error> {
+    if header.is_genesis() {
+        return Ok(None);
+    }
+    let prev_header = match get_header(header.prev_hash()) {
+        Ok(prev_header) => prev_header,
+        Err(Error::DBNotFoundErr(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    match find_ancestor_at_or_below(
+        prev_header,
+        skip_height(header.height()),
+        get_header,
+        get_skip_ancestor,
+    ) {
+        Ok(ancestor) => Ok(Some((ancestor.height(), *ancestor.hash()))),
+        // Headers below a certain height may be missing, e.g. after epoch sync.
+        Err(Error::DBNotFoundErr(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Migrates the database from version 45 to 46.
+///
+/// Records the skip ancestors of the headers saved before
+/// `DBCol::BlockSkipAncestor` was introduced, going up from the genesis to the
+/// header head, so that the ancestors of a header are always handled before it.
+pub fn migrate_45_to_46(ctx: &mut MigrationContext) -> anyhow::Result<()> {
+    let chain_store = ctx.store().chain_store();
+    let header_head = match chain_store.header_head() {
+        Ok(header_head) => header_head,
+        // Nothing to do for an empty database.
+        Err(Error::DBNotFoundErr(_)) => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let start_height = match ctx.resume_key() {
+        Some(key) => BlockHeight::from_be_bytes(key.try_into()?) + 1,
+        None => chain_store.get_genesis_height(),
+    };
+    // Skip ancestors which are not written to the store yet.
+    let mut pending = HashMap::new();
+    for height in start_height..=header_head.height {
+        for hash in chain_store.get_all_header_hashes_by_height(height)? {
+            if chain_store.get_block_skip_ancestor(&hash)?.is_some() {
+                continue;
+            }
+            let header = chain_store.get_block_header(&hash)?;
+            let skip_ancestor = compute_skip_ancestor(
+                &header,
+                |hash| chain_store.get_block_header(hash),
+                |hash| match pending.get(hash) {
+                    Some(skip_ancestor) => Ok(*skip_ancestor),
+                    None => chain_store.get_block_skip_ancestor(hash),
+                },
+            )?;
+            if let Some(skip_ancestor) = skip_ancestor {
+                ctx.set_ser(DBCol::BlockSkipAncestor, hash.as_ref(), &skip_ancestor)?;
+            }
+            pending.insert(hash, skip_ancestor);
+        }
+        if ctx.checkpoint(&height.to_be_bytes())? {
+            pending.clear();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use near_async::time::Clock;
+    use near_primitives::block::{Block, Tip};
+    use near_primitives::test_utils::{TestBlockBuilder, create_test_signer};
+    use near_store::adapter::StoreAdapter;
+    use near_store::migrations::MigrationContext;
+    use near_store::{DBCol, HEADER_HEAD_KEY};
+
+    use super::{migrate_45_to_46, skip_height};
+    use crate::ChainStoreAccess;
+    use crate::test_utils::get_chain;
+
+    #[test]
+    fn test_skip_height_is_lower() {
+        for height in 2..10_000 {
+            assert!(skip_height(height) < height, "height {height}");
+        }
+        assert_eq!(skip_height(0), 0);
+        assert_eq!(skip_height(1), 0);
+    }
+
+    #[test]
+    fn test_skip_height_values() {
+        assert_eq!(skip_height(8), 0);
+        assert_eq!(skip_height(12), 8);
+        assert_eq!(skip_height(1024), 0);
+        assert_eq!(skip_height(1025), 1);
+        assert_eq!(skip_height(15), 9);
+    }
+
+    #[test]
+    fn test_migrate_skip_ancestors() {
+        let mut chain = get_chain(Clock::real());
+        let genesis = chain.get_block_by_height(0).unwrap();
+        let signer = Arc::new(create_test_signer("test1"));
+        let mut save_header = |block: &Block| {
+            let mut store_update = chain.mut_chain_store().store_update();
+            store_update.save_block_header(block.header().clone()).unwrap();
+            store_update.commit().unwrap();
+        };
+        let mut headers = vec![];
+        let mut prev_block = genesis;
+        let mut fork_base = None;
+        // Skip some heights, so that the skip ancestors aren't always at the
+        // skip heights.
+        for height in (1..100).filter(|height| height % 7 != 0) {
+            let block = TestBlockBuilder::new(Clock::real(), &prev_block, signer.clone())
+                .height(height)
+                .build();
+            save_header(&block);
+            headers.push(*block.hash());
+            if height == 50 {
+                fork_base = Some(block.clone());
+            }
+            prev_block = block;
+        }
+        let header_head = Tip::from_header(prev_block.header());
+        let mut prev_block = fork_base.unwrap();
+        for height in 51..60 {
+            let block = TestBlockBuilder::new(Clock::real(), &prev_block, signer.clone())
+                .height(height)
+                .build();
+            save_header(&block);
+            headers.push(*block.hash());
+            prev_block = block;
+        }
+
+        let store = chain.chain_store().store();
+        let chain_store = store.chain_store();
+        let mut store_update = store.store_update();
+        store_update.set_ser(DBCol::BlockMisc, HEADER_HEAD_KEY, &header_head).unwrap();
+        store_update.commit().unwrap();
+        let skip_ancestors: Vec<_> = headers
+            .iter()
+            .map(|hash| chain_store.get_block_skip_ancestor(hash).unwrap().unwrap())
+            .collect();
+        let mut store_update = store.store_update();
+        store_update.delete_all(DBCol::BlockSkipAncestor);
+        store_update.commit().unwrap();
+        assert_eq!(chain_store.get_block_skip_ancestor(&headers[0]).unwrap(), None);
+
+        let mut ctx = MigrationContext::new(&store, 45).unwrap();
+        migrate_45_to_46(&mut ctx).unwrap();
+        ctx.finish().unwrap();
+        for (hash, skip_ancestor) in headers.iter().zip(skip_ancestors) {
+            assert_eq!(chain_store.get_block_skip_ancestor(hash).unwrap(), Some(skip_ancestor));
+        }
+    }
+}
@@ -52,8 +52,10 @@ use std::ops::Deref;
 use std::sync::Arc;
 use utils::check_transaction_validity_period;
 
+pub mod ancestor_skip_list;
 mod latest_witnesses;
 mod merkle_proof;
+mod transactions_by_signer;
 pub mod utils;
 
 /// Filter receipts mode for incoming receipts collection.
@@ -270,6 +272,9 @@ pub struct ChainStore {
     save_trie_changes: bool,
     /// The maximum number of blocks for which a transaction is valid since its creation.
     pub(super) transaction_validity_period: BlockHeightDelta,
+    /// Maximum number of transactions indexed per signer in
+    /// `DBCol::TransactionsBySigner`, the index is not maintained if `None`.
+    transactions_by_signer_limit: Option<usize>,
 }
 
 impl Deref for ChainStore {
@@ -302,9 +307,26 @@ impl ChainStore {
             latest_known: std::cell::Cell::new(None),
             save_trie_changes,
             transaction_validity_period,
+            transactions_by_signer_limit: None,
         }
     }
 
+    pub fn set_transactions_by_signer_limit(&mut self, limit: Option<usize>) {
+        self.transactions_by_signer_limit = limit;
+    }
+
+    pub fn transactions_by_signer_limit(&self) -> Option<usize> {
+        self.transactions_by_signer_limit
+    }
+
+    /// A chain store with the same config on top of `store`.
+    pub fn with_store(&self, store: Store) -> ChainStore {
+        let mut chain_store =
+            ChainStore::new(store, self.save_trie_changes, self.transaction_validity_period);
+        chain_store.set_transactions_by_signer_limit(self.transactions_by_signer_limit);
+        chain_store
+    }
+
     pub fn store_update(&mut self) -> ChainStoreUpdate<'_> {
         ChainStoreUpdate::new(self)
     }
@@ -1041,6 +1063,7 @@ pub struct ChainStoreUpdate<'a> {
     add_state_sync_infos: Vec<StateSyncInfo>,
     remove_state_sync_infos: Vec<CryptoHash>,
     chunk_apply_stats: HashMap<(CryptoHash, ShardId), ChunkApplyStats>,
+    chunk_apply_journal: Vec<(CryptoHash, ShardId)>,
 }
 
 impl<'a> ChainStoreUpdate<'a> {
@@ -1064,6 +1087,7 @@ impl<'a> ChainStoreUpdate<'a> {
             add_state_sync_infos: vec![],
             remove_state_sync_infos: vec![],
             chunk_apply_stats: HashMap::default(),
+            chunk_apply_journal: vec![],
         }
     }
 
@@ -1666,6 +1690,12 @@ impl<'a> ChainStoreUpdate<'a> {
         self.chunk_apply_stats.insert((block_hash, shard_id), ChunkApplyStats::V0(stats));
     }
 
+    /// Records that the chunk of the shard in the block was applied, see
+    /// `DBCol::ChunkApplyJournal`.
+    pub fn save_chunk_apply_journal(&mut self, block_hash: CryptoHash, shard_id: ShardId) {
+        self.chunk_apply_journal.push((block_hash, shard_id));
+    }
+
     pub fn inc_block_refcount(&mut self, block_hash: &CryptoHash) -> Result<(), Error> {
         let refcount = match self.get_block_refcount(block_hash) {
             Ok(refcount) => refcount,
@@ -1783,6 +1813,7 @@ impl<'a> ChainStoreUpdate<'a> {
                 headers_by_height.entry(header.height()).or_default().push(header);
                 store_update.insert_ser(DBCol::BlockHeader, hash.as_ref(), header)?;
             }
+            let mut skip_ancestors = HashMap::new();
             for (height, headers) in headers_by_height {
                 let mut hash_set = match self.chain_store.get_all_header_hashes_by_height(height) {
                     Ok(hashes) => hashes,
@@ -1797,6 +1828,24 @@ impl<'a> ChainStoreUpdate<'a> {
                 )?;
                 for header in headers {
                     crate::state_sync::update_sync_hashes(self, &mut store_update, header)?;
+                    // Headers are processed in the order of height, so the skip
+                    // ancestors of the headers in this update are known by now.
+                    let skip_ancestor = ancestor_skip_list::compute_skip_ancestor(
+                        header,
+                        |hash| self.get_block_header(hash),
+                        |hash| match skip_ancestors.get(hash) {
+                            Some(skip_ancestor) => Ok(*skip_ancestor),
+                            None => self.chain_store.get_block_skip_ancestor(hash),
+                        },
+                    )?;
+                    if let Some(skip_ancestor) = skip_ancestor {
+                        store_update.set_ser(
+                            DBCol::BlockSkipAncestor,
+                            header.hash().as_ref(),
+                            &skip_ancestor,
+                        )?;
+                    }
+                    skip_ancestors.insert(*header.hash(), skip_ancestor);
                 }
             }
             for ((block_hash, shard_uid), chunk_extra) in
@@ -2077,6 +2126,13 @@ impl<'a> ChainStoreUpdate<'a> {
                 stats,
             )?;
         }
+        for (block_hash, shard_id) in &self.chunk_apply_journal {
+            store_update.set_ser(
+                DBCol::ChunkApplyJournal,
+                &get_block_shard_id(block_hash, *shard_id),
+                &(),
+            )?;
+        }
         for other in self.store_updates.drain(..) {
             store_update.merge(other);
         }
@@ -0,0 +1,133 @@
+//! Index of the recent transactions of each signer, see `DBCol::TransactionsBySigner`.
+//!
+//! The index is only maintained when `transactions_by_signer_limit` is set.
+//! Transactions are added when the chunk which includes them is applied and
+//! removed together with the block which includes the chunk by the garbage
+//! collection, see `ChainStoreUpdate::gc_transactions_by_signer`, or earlier when
+//! the signer has more than `transactions_by_signer_limit` indexed
+//! transactions. The chunks saved before the index was introduced are indexed
+//! by the database migration, see [`migrate_46_to_47`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use near_chain_primitives::Error;
+use near_primitives::block::MaybeNew;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, BlockHeight};
+use near_primitives::utils::{get_transactions_by_signer_key, get_transactions_by_signer_prefix};
+use near_store::DBCol;
+use near_store::adapter::StoreAdapter;
+use near_store::migrations::MigrationContext;
+
+use super::{ChainStoreAccess, ChainStoreUpdate};
+
+impl<'a> ChainStoreUpdate<'a> {
+    /// Indexes the transactions of a chunk applied in the block with the
+    /// given hash and height. The height is the one of the block, not the one
+    /// at which the chunk was created, so that the entries can be found again
+    /// when the block is garbage collected.
+    pub(crate) fn save_transactions_by_signer(
+        &mut self,
+        block_hash: &CryptoHash,
+        height: BlockHeight,
+        transactions: &[Arc<SignedTransaction>],
+    ) -> Result<(), Error> {
+        let Some(limit) = self.chain_store().transactions_by_signer_limit() else {
+            return Ok(());
+        };
+        let mut by_signer = HashMap::<&AccountId, Vec<CryptoHash>>::new();
+        for transaction in transactions {
+            by_signer
+                .entry(transaction.transaction.signer_id())
+                .or_default()
+                .push(transaction.get_hash());
+        }
+
+        let mut store_update = self.store().store_update();
+        for (signer_id, mut tx_hashes) in by_signer {
+            tx_hashes.drain(..tx_hashes.len().saturating_sub(limit));
+            for tx_hash in &tx_hashes {
+                let key = get_transactions_by_signer_key(signer_id, height, tx_hash);
+                store_update.set_ser(DBCol::TransactionsBySigner, &key, block_hash)?;
+            }
+            // Drop the oldest transactions of the signer over the limit.
+            let indexed = self.chain_store().get_transactions_by_signer(signer_id)?;
+            let excess = (indexed.len() + tx_hashes.len()).saturating_sub(limit);
+            for (height, tx_hash, _) in indexed.into_iter().take(excess) {
+                let key = get_transactions_by_signer_key(signer_id, height, &tx_hash);
+                store_update.delete(DBCol::TransactionsBySigner, &key);
+            }
+        }
+        self.merge(store_update);
+        Ok(())
+    }
+}
+
+/// Migrates the database from version 46 to 47.
+///
+/// Indexes the transactions of the chunks kept in the store if the index is
+/// enabled. The blocks are visited from the head down to the tail, so that
+/// only the `limit` most recent transactions of every signer are indexed.
+pub fn migrate_46_to_47(ctx: &mut MigrationContext, limit: Option<usize>) -> anyhow::Result<()> {
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+    let chain_store = ctx.store().chain_store();
+    let head = match chain_store.head() {
+        Ok(head) => head,
+        // Nothing to do for an empty database.
+        Err(Error::DBNotFoundErr(_)) => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    // Number of indexed transactions by the signer key prefix. The column is
+    // only written by this migration so far, so the numbers are recovered from
+    // it when resuming.
+    let mut num_indexed = HashMap::<Vec<u8>, usize>::new();
+    let end_height = match ctx.resume_key() {
+        Some(key) => {
+            for item in ctx.store().iter(DBCol::TransactionsBySigner) {
+                let (signer_key, _) = item?;
+                // The prefix is followed by the height and the transaction hash.
+                let prefix = signer_key[..signer_key.len().saturating_sub(40)].to_vec();
+                *num_indexed.entry(prefix).or_default() += 1;
+            }
+            BlockHeight::from_be_bytes(key.try_into()?)
+        }
+        None => head.height + 1,
+    };
+    for height in (chain_store.tail()?..end_height).rev() {
+        let block_hash = match chain_store.get_block_hash_by_height(height) {
+            Ok(block_hash) => block_hash,
+            Err(Error::DBNotFoundErr(_)) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let block = chain_store.get_block(&block_hash)?;
+        for chunk_header in block.chunks().iter() {
+            let MaybeNew::New(chunk_header) = chunk_header else {
+                continue;
+            };
+            // Only the chunks of the tracked shards are available.
+            let chunk = match chain_store.get_chunk(&chunk_header.chunk_hash()) {
+                Ok(chunk) => chunk,
+                Err(Error::ChunkMissing(_)) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            for transaction in chunk.to_transactions().iter().rev() {
+                let signer_id = transaction.transaction.signer_id();
+                let indexed =
+                    num_indexed.entry(get_transactions_by_signer_prefix(signer_id)).or_default();
+                if *indexed >= limit {
+                    continue;
+                }
+                *indexed += 1;
+                let key =
+                    get_transactions_by_signer_key(signer_id, height, &transaction.get_hash());
+                ctx.set_ser(DBCol::TransactionsBySigner, &key, &block_hash)?;
+            }
+        }
+        ctx.checkpoint(&height.to_be_bytes())?;
+    }
+    Ok(())
+}
@@ -13,10 +13,14 @@ use near_store::adapter::chain_store::ChainStoreAdapter;
 
 use crate::byzantine_assert;
 
+use super::ancestor_skip_list::find_ancestor_at_or_below;
 use super::{ReceiptFilter, filter_incoming_receipts_for_shard};
 
 /// Get full chunk from header with `height_included` taken from `header`, with
 /// possible error that contains the header for further retrieval.
+/// Returns `Error::CorruptedChunk` if the stored chunk can't be decoded or
+/// its hash doesn't match the header. The chunk content isn't checked again,
+/// as chunks are validated against their header before they are saved.
 /// TODO: consider less hacky way to set `height_included` for chunks.
 pub fn get_chunk_clone_from_header(
     chain_store: &ChainStoreAdapter,
@@ -25,9 +29,15 @@ pub fn get_chunk_clone_from_header(
     let shard_chunk_result = chain_store.get_chunk(&header.chunk_hash());
     match shard_chunk_result {
         Err(_) => {
+            if chain_store.chunk_exists(&header.chunk_hash())? {
+                return Err(Error::CorruptedChunk(Box::new(header.clone())));
+            }
             return Err(Error::ChunksMissing(vec![header.clone()]));
         }
         Ok(shard_chunk) => {
+            if shard_chunk.chunk_hash() != header.chunk_hash() {
+                return Err(Error::CorruptedChunk(Box::new(header.clone())));
+            }
             byzantine_assert!(header.height_included() > 0 || header.height_created() == 0);
             if header.height_included() == 0 && header.height_created() > 0 {
                 return Err(Error::Other(format!(
@@ -43,22 +53,25 @@ pub fn get_chunk_clone_from_header(
 }
 
 /// Returns block header from the current chain defined by `sync_hash` for given height if present.
+/// Ancestors are found with the skip-list stored in `DBCol::BlockSkipAncestor`, which
+/// takes a logarithmic number of header reads instead of walking back one block at a time.
 pub fn get_block_header_on_chain_by_height(
     chain_store: &ChainStoreAdapter,
     sync_hash: &CryptoHash,
     height: BlockHeight,
 ) -> Result<BlockHeader, Error> {
-    let mut header = chain_store.get_block_header(sync_hash)?;
-    let mut hash = *sync_hash;
-    while header.height() > height {
-        hash = *header.prev_hash();
-        header = chain_store.get_block_header(&hash)?;
-    }
+    let header = chain_store.get_block_header(sync_hash)?;
+    let header = find_ancestor_at_or_below(
+        header,
+        height,
+        |hash| chain_store.get_block_header(hash),
+        |hash| chain_store.get_block_skip_ancestor(hash),
+    )?;
     let header_height = header.height();
     if header_height < height {
         return Err(Error::InvalidBlockHeight(header_height));
     }
-    chain_store.get_block_header(&hash)
+    Ok(header)
 }
 
 /// For a given transaction, it expires if the block that the chunk points to is more than `validity_period`
@@ -32,7 +32,10 @@ use near_store::test_utils::create_test_store;
 use num_rational::Ratio;
 use tracing::debug;
 
-pub use self::kv_runtime::{KeyValueRuntime, MockEpochManager, account_id_to_shard_id};
+pub use self::kv_runtime::{
+    KeyValueRuntime, KeyValueRuntimeOptions, KvCongestionBacklog, KvCongestionModel,
+    KvReceiptOutcome, MockEpochManager, ReceiptProcessor, account_id_to_shard_id,
+};
 pub use self::validator_schedule::ValidatorSchedule;
 use near_async::messaging::{IntoMultiSender, noop};
 
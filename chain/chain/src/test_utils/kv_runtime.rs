@@ -1,14 +1,15 @@
 use super::ValidatorSchedule;
 use crate::BlockHeader;
+use crate::runtime::set_epoch_config;
 use crate::types::{
-    ApplyChunkBlockContext, ApplyChunkResult, ApplyChunkShardContext,
-    PrepareTransactionsBlockContext, PrepareTransactionsChunkContext, PreparedTransactions,
-    RuntimeAdapter, RuntimeStorageConfig,
+    ApplyChunkBlockContext, ApplyChunkResult, ApplyChunkShardContext, ChainGenesis,
+    PrepareTransactionsBlockContext, PrepareTransactionsChunkContext, PrepareTransactionsLimit,
+    PreparedTransactions, RuntimeAdapter, RuntimeStorageConfig,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use itertools::Itertools;
 use near_async::time::Duration;
-use near_chain_configs::{DEFAULT_GC_NUM_EPOCHS_TO_KEEP, ProtocolConfig};
+use near_chain_configs::{DEFAULT_GC_NUM_EPOCHS_TO_KEEP, GenesisConfig, ProtocolConfig};
 use near_chain_primitives::Error;
 use near_crypto::{KeyType, PublicKey, SecretKey};
 use near_epoch_manager::EpochManagerAdapter;
@@ -19,7 +20,10 @@ use near_primitives::apply::ApplyChunkReason;
 use near_primitives::bandwidth_scheduler::BandwidthRequests;
 use near_primitives::block::Tip;
 use near_primitives::chunk_apply_stats::ChunkApplyStatsV0;
-use near_primitives::congestion_info::{CongestionInfo, ExtendedCongestionInfo};
+use near_primitives::congestion_info::{
+    BlockCongestionInfo, CongestionControl, CongestionInfo, ExtendedCongestionInfo,
+    RejectTransactionReason, ShardAcceptsTransactions,
+};
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::epoch_info::{EpochInfo, RngSeed};
 use near_primitives::epoch_manager::EpochConfig;
@@ -29,33 +33,41 @@ use near_primitives::hash::{CryptoHash, hash};
 use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptV0};
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::state_part::PartId;
+use near_primitives::state_sync::STATE_PART_MEMORY_LIMIT;
 use near_primitives::stateless_validation::ChunkProductionKey;
 use near_primitives::stateless_validation::validator_assignment::ChunkValidatorAssignments;
 use near_primitives::transaction::{
     Action, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus,
-    SignedTransaction, TransferAction, ValidatedTransaction,
+    SignedTransaction, StakeAction, TransferAction, ValidatedTransaction,
 };
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{
-    AccountId, ApprovalStake, Balance, BlockHeight, EpochHeight, EpochId, Nonce, NumShards,
-    ShardId, ShardIndex, StateRoot, StateRootNode, ValidatorInfoIdentifier,
+    AccountId, AccountInfo, ApprovalStake, Balance, BlockHeight, EpochHeight, EpochId, Gas, Nonce,
+    NumShards, ShardId, ShardIndex, StateRoot, StateRootNode, ValidatorInfoIdentifier,
 };
 use near_primitives::version::{PROTOCOL_VERSION, ProtocolVersion};
 use near_primitives::views::{
     AccessKeyInfoView, AccessKeyList, CallResult, ContractCodeView, EpochValidatorInfo,
-    QueryRequest, QueryResponse, QueryResponseKind, ViewStateResult,
+    ProtocolVersionVotesView, QueryRequest, QueryResponse, QueryResponseKind, ViewStatePagedResult,
+    ViewStateResult,
 };
 use near_store::test_utils::TestTriesBuilder;
+use near_store::trie::TrieRecorder;
+use near_store::trie::mem::memtrie_update::TrackingMode;
+use near_store::trie::mem::memtries::MemTries;
 use near_store::{
-    DBCol, ShardTries, Store, StoreUpdate, Trie, TrieChanges, WrappedTrieChanges,
+    DBCol, PartialStorage, ShardTries, Store, StoreUpdate, Trie, TrieChanges, WrappedTrieChanges,
     set_genesis_height, set_genesis_state_roots,
 };
 use near_vm_runner::{ContractCode, ContractRuntimeCache, NoContractRuntimeCache};
 use node_runtime::SignedValidPeriodTransactions;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::Path;
 use std::sync::Arc;
+use strum::IntoEnumIterator;
 
 /// Simple key value runtime for tests.
 ///
@@ -71,20 +83,99 @@ use std::sync::Arc;
 ///   * Doesn't have WASM runtime, so can only process simple transfer
 ///     transaction
 ///   * Uses hard-coded validator schedule instead of using `EpochManager` and
-///     staking to assign block and chunk producers.
+///     staking to assign block and chunk producers, unless the schedule is
+///     built with `ValidatorSchedule::staking_proposals`.
+///   * Uses fake state roots and empty storage proofs, unless the runtime is
+///     created with `KeyValueRuntimeOptions::memtrie`.
 pub struct KeyValueRuntime {
     store: Store,
     tries: ShardTries,
-    num_shards: NumShards,
+    epoch_manager: Arc<MockEpochManager>,
     epoch_length: u64,
     no_gc: bool,
+    transfer_gas: Gas,
     runtime_config: RuntimeConfig,
 
     // A mapping state_root => {account id => amounts}, for transactions and receipts
     state: RwLock<HashMap<StateRoot, KVState>>,
     state_size: RwLock<HashMap<StateRoot, u64>>,
+    /// Parts of the states being synced, until all of them are applied.
+    state_parts: RwLock<HashMap<StateRoot, BTreeMap<u64, Vec<u8>>>>,
     headers_cache: RwLock<HashMap<CryptoHash, BlockHeader>>,
     contract_cache: NoContractRuntimeCache,
+    receipt_processor: Option<Box<ReceiptProcessor>>,
+    /// Memtries of the shards the state is written to, if enabled.
+    memtries: Option<Mutex<HashMap<ShardUId, MemTries>>>,
+    congestion_model: Option<KvCongestionModel>,
+    chain_genesis: Option<ChainGenesis>,
+}
+
+/// Options of `KeyValueRuntime::new_with_options`.
+#[derive(Default)]
+pub struct KeyValueRuntimeOptions {
+    pub no_gc: bool,
+    /// Gas burnt by every transfer, by the transaction and by the receipt.
+    /// The signer of a transaction pays for both at the gas price of the
+    /// block. Once the gas burnt in a chunk reaches the chunk gas limit, the
+    /// remaining receipts are delayed to the next chunk, and no more
+    /// transactions are taken from the pool.
+    pub transfer_gas: Gas,
+    pub receipt_processor: Option<Box<ReceiptProcessor>>,
+    /// Writes the state of every chunk to an in-memory trie of the shard, so
+    /// that the state roots and the storage proofs are the ones of a real
+    /// trie holding the state, e.g. for benchmarks. The state is read back
+    /// from the memtrie, and its roots are deleted along with the garbage
+    /// collected blocks. State sync and shard layout changes are not supported
+    /// in this mode.
+    pub memtrie: bool,
+    /// Congestion reported by the shards. If set, the transactions to
+    /// congested shards are rejected and the outgoing receipts are buffered
+    /// while their receiving shard is congested, as with congestion control.
+    /// Otherwise no shard is ever congested.
+    pub congestion_model: Option<KvCongestionModel>,
+    /// Genesis of the chain, whose chain id, genesis time and height, gas
+    /// limit, gas prices and transaction validity period are reported by
+    /// `get_protocol_config`. Otherwise they are left to their defaults.
+    pub chain_genesis: Option<ChainGenesis>,
+}
+
+/// Congestion reported by the chunks applied by `KeyValueRuntime`. It's
+/// independent of the receipts actually delayed and buffered by the runtime.
+pub enum KvCongestionModel {
+    /// Every shard reports the given backlog, the shards not listed none.
+    Fixed(HashMap<ShardId, KvCongestionBacklog>),
+    /// Congestion info of a shard at a block height. It must be deterministic,
+    /// every validator of the chunk reports the same.
+    Custom(Box<dyn Fn(ShardId, BlockHeight) -> CongestionInfo + Send + Sync>),
+}
+
+/// Backlog of a shard reported by `KvCongestionModel::Fixed`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KvCongestionBacklog {
+    /// Gas of the delayed receipts, which makes up the incoming congestion.
+    pub delayed_receipts_gas: Gas,
+    /// Gas of the receipts buffered for other shards, which makes up the
+    /// outgoing congestion.
+    pub buffered_receipts_gas: Gas,
+}
+
+/// Decides how `KeyValueRuntime::apply_chunk` processes an incoming receipt,
+/// see `KeyValueRuntime::new_with_receipt_processor`. Called again for a
+/// receipt every time it's taken from the delayed receipts.
+pub type ReceiptProcessor = dyn Fn(&Receipt) -> KvReceiptOutcome + Send + Sync;
+
+/// How a receipt is processed, returned by a `ReceiptProcessor`.
+pub enum KvReceiptOutcome {
+    /// Processes the receipt as without a receipt processor: the deposit of a
+    /// transfer is credited to the receiver.
+    Default,
+    /// Records an outcome with the given status and burnt gas instead. No
+    /// balance is changed and no refund is created, so the deposit of a failed
+    /// transfer is lost.
+    Outcome { status: ExecutionStatus, gas_burnt: Gas },
+    /// Puts the receipt to the delayed receipts of the shard, which are
+    /// processed before the incoming receipts of the next chunk.
+    Delay,
 }
 
 /// DEPRECATED. DO NOT USE for new tests. Use the real EpochManager, familiarize
@@ -98,10 +189,31 @@ pub struct MockEpochManager {
     epoch_length: u64,
     /// A pre determined list of validator sets. We rotate validator set in this list.
     /// Epoch i uses validators from `validators_by_valset[i % validators_by_valset.len()]`.
-    validators_by_valset: Vec<EpochValidatorSet>,
+    /// With `staking_proposals`, epoch i uses `validators_by_valset[i]` instead,
+    /// and the sets of the new epochs are appended as they start.
+    validators_by_valset: RwLock<Vec<EpochValidatorSet>>,
+    /// See `ValidatorSchedule::staking_proposals`.
+    staking_proposals: bool,
+    validator_groups: u64,
+    /// Latest proposal of every account, by index of the validator set of the
+    /// epoch in which it was made. Only used with `staking_proposals`.
+    proposals_by_valset: RwLock<HashMap<usize, BTreeMap<AccountId, ValidatorStake>>>,
+    /// Number of blocks produced by every account, by index of the validator
+    /// set of the epoch. Only used with `staking_proposals`.
+    blocks_produced_by_valset: RwLock<HashMap<usize, HashMap<AccountId, u64>>>,
+    /// Protocol version of the epochs, by the same index as
+    /// `validators_by_valset`.  Empty if all epochs use `PROTOCOL_VERSION`.
+    protocol_versions_by_valset: Vec<ProtocolVersion>,
+    /// Shard layout of the epochs, by the same index as `validators_by_valset`.
+    /// Empty if all epochs use the V0 layout with `num_shards` shards.
+    shard_layouts_by_valset: Vec<ShardLayout>,
+    /// Chunk validators of every shard with their assignment stakes, by the
+    /// same index as `validators_by_valset` and then by shard index. Empty if
+    /// all the block producers validate every shard.
+    chunk_validators_by_valset: Vec<Vec<Vec<(AccountId, Balance)>>>,
     /// Maps from account id to validator stake for all validators, both block producers and
     /// chunk producers
-    validators: HashMap<AccountId, ValidatorStake>,
+    validators: RwLock<HashMap<AccountId, ValidatorStake>>,
 
     headers_cache: RwLock<HashMap<CryptoHash, BlockHeader>>,
     hash_to_epoch: RwLock<HashMap<CryptoHash, EpochId>>,
@@ -118,12 +230,37 @@ pub struct MockEpochManager {
 /// Block producers are assigned to shards via `validator_groups`.
 /// Each shard will have `block_producers.len() / validator_groups` of validators who are also block
 /// producers
+#[derive(BorshSerialize, BorshDeserialize)]
 struct EpochValidatorSet {
     block_producers: Vec<ValidatorStake>,
     /// index of this list is shard_id
     chunk_producers: Vec<Vec<ValidatorStake>>,
 }
 
+impl EpochValidatorSet {
+    fn new(
+        block_producers: Vec<ValidatorStake>,
+        validator_groups: u64,
+        num_shards: NumShards,
+    ) -> Self {
+        // cspell:ignore coef
+        let validators_per_shard = (block_producers.len() / validator_groups as usize).max(1);
+        let coefficient = block_producers.len() / num_shards as usize;
+
+        let chunk_producers: Vec<Vec<ValidatorStake>> = (0..num_shards)
+            .map(|shard_index| {
+                let shard_index = shard_index as usize;
+                let offset = (shard_index * coefficient / validators_per_shard
+                    * validators_per_shard)
+                    .min(block_producers.len().saturating_sub(validators_per_shard));
+                block_producers[offset..offset + validators_per_shard].to_vec()
+            })
+            .collect();
+
+        EpochValidatorSet { block_producers, chunk_producers }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Hash, PartialEq, Eq, Ord, PartialOrd, Clone, Debug)]
 struct AccountNonce(AccountId, Nonce);
 
@@ -132,6 +269,95 @@ struct KVState {
     amounts: HashMap<AccountId, u128>,
     receipt_nonces: HashSet<CryptoHash>,
     tx_nonces: HashSet<AccountNonce>,
+    delayed_receipts: Vec<Receipt>,
+    /// Outgoing receipts waiting for their receiving shard to be less
+    /// congested, only with a `KvCongestionModel`.
+    buffered_receipts: Vec<Receipt>,
+}
+
+/// Size of the state parts of the serialized `KVState`. It's tiny, so that
+/// state sync goes through multiple parts even for the small states of tests.
+const KV_STATE_PART_SIZE: usize = 64;
+
+/// A state part of `KeyValueRuntime`. The state root is the hash of the hashes
+/// of all the parts, so a part can be validated on its own.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct KVStatePart {
+    part_hashes: Vec<CryptoHash>,
+    data: Vec<u8>,
+}
+
+fn kv_state_part_hashes(data: &[u8]) -> Vec<CryptoHash> {
+    data.chunks(KV_STATE_PART_SIZE).map(hash).collect()
+}
+
+fn kv_state_root(part_hashes: &[CryptoHash]) -> StateRoot {
+    CryptoHash::hash_borsh(part_hashes)
+}
+
+/// Entries of a `KVState` in its memtrie: one per balance, receipt nonce,
+/// transaction nonce, delayed receipt and buffered receipt. The receipts are
+/// keyed by their big-endian index, so that they are iterated in order. All
+/// the values are small enough to be inlined in the memtrie.
+fn kv_state_trie_entries(state: &KVState) -> BTreeMap<Vec<u8>, Vec<u8>> {
+    let key = |prefix: u8, suffix: &[u8]| [&[prefix][..], suffix].concat();
+    let mut entries = BTreeMap::new();
+    for (account_id, amount) in &state.amounts {
+        entries.insert(key(0, account_id.as_str().as_bytes()), amount.to_le_bytes().to_vec());
+    }
+    for receipt_nonce in &state.receipt_nonces {
+        entries.insert(key(1, receipt_nonce.as_bytes()), vec![1]);
+    }
+    for tx_nonce in &state.tx_nonces {
+        entries.insert(key(2, &borsh::to_vec(tx_nonce).unwrap()), vec![1]);
+    }
+    for (prefix, receipts) in [(3, &state.delayed_receipts), (4, &state.buffered_receipts)] {
+        for (index, receipt) in receipts.iter().enumerate() {
+            entries.insert(
+                key(prefix, &(index as u32).to_be_bytes()),
+                borsh::to_vec(receipt).unwrap(),
+            );
+        }
+    }
+    entries
+}
+
+/// Inverse of `kv_state_trie_entries`, from the entries in key order.
+fn kv_state_from_trie_entries(
+    entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+) -> Result<KVState, Error> {
+    let mut state = KVState {
+        amounts: HashMap::new(),
+        receipt_nonces: HashSet::new(),
+        tx_nonces: HashSet::new(),
+        delayed_receipts: vec![],
+        buffered_receipts: vec![],
+    };
+    let invalid_entry = |key: &[u8]| Error::Other(format!("invalid state entry {key:?}"));
+    for (key, value) in entries {
+        match key.split_first() {
+            Some((0, account_id)) => {
+                let account_id = std::str::from_utf8(account_id)
+                    .ok()
+                    .and_then(|account_id| account_id.parse().ok())
+                    .ok_or_else(|| invalid_entry(&key))?;
+                let amount = value.try_into().map_err(|_| invalid_entry(&key))?;
+                state.amounts.insert(account_id, u128::from_le_bytes(amount));
+            }
+            Some((1, receipt_nonce)) => {
+                let receipt_nonce =
+                    CryptoHash::try_from(receipt_nonce).map_err(|_| invalid_entry(&key))?;
+                state.receipt_nonces.insert(receipt_nonce);
+            }
+            Some((2, tx_nonce)) => {
+                state.tx_nonces.insert(AccountNonce::try_from_slice(tx_nonce)?);
+            }
+            Some((3, _)) => state.delayed_receipts.push(Receipt::try_from_slice(&value)?),
+            Some((4, _)) => state.buffered_receipts.push(Receipt::try_from_slice(&value)?),
+            _ => return Err(invalid_entry(&key)),
+        }
+    }
+    Ok(state)
 }
 
 impl MockEpochManager {
@@ -151,10 +377,14 @@ impl MockEpochManager {
         let map_with_default_hash3 = HashMap::from([(EpochId::default(), 0)]);
 
         let mut validators = HashMap::new();
+        let num_shards_by_valset = |valset: usize| {
+            vs.shard_layouts.get(valset).map_or(vs.num_shards, |layout| layout.num_shards())
+        };
         let mut validators_by_valset: Vec<EpochValidatorSet> = vs
             .block_producers
             .iter()
-            .map(|account_ids| {
+            .enumerate()
+            .map(|(valset, account_ids)| {
                 let block_producers: Vec<ValidatorStake> = account_ids
                     .iter()
                     .map(|account_id| {
@@ -168,28 +398,18 @@ impl MockEpochManager {
                         stake
                     })
                     .collect();
-
-                // cspell:ignore coef
-                let validators_per_shard = block_producers.len() / vs.validator_groups as usize;
-                let coefficient = block_producers.len() / vs.num_shards as usize;
-
-                let chunk_producers: Vec<Vec<ValidatorStake>> = (0..vs.num_shards)
-                    .map(|shard_index| {
-                        let shard_index = shard_index as usize;
-                        let offset =
-                            shard_index * coefficient / validators_per_shard * validators_per_shard;
-                        block_producers[offset..offset + validators_per_shard].to_vec()
-                    })
-                    .collect();
-
-                EpochValidatorSet { block_producers, chunk_producers }
+                EpochValidatorSet::new(
+                    block_producers,
+                    vs.validator_groups,
+                    num_shards_by_valset(valset),
+                )
             })
             .collect();
 
         if !vs.chunk_only_producers.is_empty() {
             assert_eq!(validators_by_valset.len(), vs.chunk_only_producers.len());
             for (epoch_idx, epoch_cops) in vs.chunk_only_producers.into_iter().enumerate() {
-                assert_eq!(epoch_cops.len() as u64, vs.num_shards);
+                assert_eq!(epoch_cops.len() as u64, num_shards_by_valset(epoch_idx));
                 for (shard_idx, shard_cops) in epoch_cops.into_iter().enumerate() {
                     for account_id in shard_cops {
                         let stake = ValidatorStake::new(
@@ -206,12 +426,68 @@ impl MockEpochManager {
             }
         }
 
+        if !vs.protocol_versions.is_empty() {
+            assert_eq!(validators_by_valset.len(), vs.protocol_versions.len());
+        }
+
+        if !vs.shard_layouts.is_empty() {
+            assert_eq!(validators_by_valset.len(), vs.shard_layouts.len());
+            assert!(!vs.protocol_versions.is_empty(), "shard layouts need protocol versions");
+            for i in 1..vs.shard_layouts.len() {
+                assert!(
+                    vs.shard_layouts[i] == vs.shard_layouts[i - 1]
+                        || vs.protocol_versions[i] > vs.protocol_versions[i - 1],
+                    "shard layout of epoch {i} changes without a protocol upgrade"
+                );
+            }
+        }
+
+        if !vs.chunk_validators.is_empty() {
+            assert_eq!(validators_by_valset.len(), vs.chunk_validators.len());
+            for (epoch_idx, epoch_chunk_validators) in vs.chunk_validators.iter().enumerate() {
+                assert_eq!(epoch_chunk_validators.len() as u64, num_shards_by_valset(epoch_idx));
+                let epoch_validators = &validators_by_valset[epoch_idx];
+                for (account_id, _) in epoch_chunk_validators.iter().flatten() {
+                    assert!(
+                        epoch_validators
+                            .block_producers
+                            .iter()
+                            .chain(epoch_validators.chunk_producers.iter().flatten())
+                            .any(|stake| stake.account_id() == account_id),
+                        "chunk validator {account_id} is not a validator of epoch {epoch_idx}"
+                    );
+                }
+            }
+        }
+
+        if vs.staking_proposals {
+            assert_eq!(validators_by_valset.len(), 1, "staking starts from the genesis validators");
+            assert!(vs.protocol_versions.is_empty(), "staking doesn't support protocol versions");
+            assert!(vs.shard_layouts.is_empty(), "staking doesn't support shard layouts");
+            assert!(vs.chunk_validators.is_empty(), "staking doesn't support chunk validators");
+            assert!(
+                validators_by_valset[0]
+                    .chunk_producers
+                    .iter()
+                    .flatten()
+                    .all(|stake| validators_by_valset[0].block_producers.contains(stake)),
+                "staking doesn't support chunk only producers"
+            );
+        }
+
         Arc::new(MockEpochManager {
             store,
             num_shards: vs.num_shards,
             epoch_length,
-            validators,
-            validators_by_valset,
+            validators: RwLock::new(validators),
+            validators_by_valset: RwLock::new(validators_by_valset),
+            staking_proposals: vs.staking_proposals,
+            validator_groups: vs.validator_groups,
+            proposals_by_valset: RwLock::new(HashMap::new()),
+            blocks_produced_by_valset: RwLock::new(HashMap::new()),
+            protocol_versions_by_valset: vs.protocol_versions,
+            shard_layouts_by_valset: vs.shard_layouts,
+            chunk_validators_by_valset: vs.chunk_validators,
             headers_cache: RwLock::new(HashMap::new()),
             hash_to_epoch: RwLock::new(HashMap::new()),
             hash_to_next_epoch_approvals_req: RwLock::new(HashMap::new()),
@@ -281,26 +557,145 @@ impl MockEpochManager {
         hash_to_valset.insert(next_epoch, valset + 1);
         epoch_start_map.insert(prev_hash, epoch_start);
 
-        Ok((epoch, valset as usize % self.validators_by_valset.len(), next_epoch))
+        if self.staking_proposals {
+            self.add_validator_sets_until(valset as usize + 1);
+        }
+
+        Ok((epoch, self.valset_index(valset), next_epoch))
+    }
+
+    /// Index of `validators_by_valset` of the `valset`-th validator set. The
+    /// schedule loops around, unless it has shard layouts.
+    fn valset_index(&self, valset: u64) -> usize {
+        let len = self.validators_by_valset.read().len();
+        if self.staking_proposals {
+            valset as usize
+        } else if !self.shard_layouts_by_valset.is_empty() {
+            (valset as usize).min(len - 1)
+        } else {
+            valset as usize % len
+        }
+    }
+
+    /// Index of `validators_by_valset` of the validator set preceding the
+    /// `valset`-th one.
+    fn prev_valset_index(&self, valset: u64) -> usize {
+        if self.staking_proposals || !self.shard_layouts_by_valset.is_empty() {
+            self.valset_index(valset.saturating_sub(1))
+        } else {
+            self.valset_index(valset.wrapping_sub(1))
+        }
+    }
+
+    /// Appends the validator sets derived from the proposals until the set
+    /// with index `valset` exists. The set of epoch `T + 2` is the set of
+    /// epoch `T + 1` with the kickouts and the proposals of epoch `T`, so the
+    /// proposals of the blocks processed after `T + 1` started are ignored.
+    fn add_validator_sets_until(&self, valset: usize) {
+        let mut validators_by_valset = self.validators_by_valset.write();
+        while validators_by_valset.len() <= valset {
+            let next = validators_by_valset.len();
+            let mut block_producers = validators_by_valset[next - 1].block_producers.clone();
+            if next >= 2 {
+                let ended = next - 2;
+                if let Some(produced) = self.blocks_produced_by_valset.read().get(&ended) {
+                    let kicked_out: Vec<AccountId> = validators_by_valset[ended]
+                        .block_producers
+                        .iter()
+                        .map(|stake| stake.account_id().clone())
+                        .filter(|account_id| !produced.contains_key(account_id))
+                        .collect();
+                    let remaining: Vec<ValidatorStake> = block_producers
+                        .iter()
+                        .filter(|stake| !kicked_out.contains(stake.account_id()))
+                        .cloned()
+                        .collect();
+                    if !remaining.is_empty() {
+                        block_producers = remaining;
+                    }
+                }
+                if let Some(proposals) = self.proposals_by_valset.read().get(&ended) {
+                    for (account_id, proposal) in proposals {
+                        let position = block_producers
+                            .iter()
+                            .position(|stake| stake.account_id() == account_id);
+                        match (position, proposal.stake()) {
+                            (Some(position), 0) if block_producers.len() > 1 => {
+                                block_producers.remove(position);
+                            }
+                            (Some(_), 0) => {}
+                            (Some(position), _) => block_producers[position] = proposal.clone(),
+                            (None, 0) => {}
+                            (None, _) => block_producers.push(proposal.clone()),
+                        }
+                    }
+                }
+            }
+            let mut validators = self.validators.write();
+            for stake in &block_producers {
+                validators.insert(stake.account_id().clone(), stake.clone());
+            }
+            validators_by_valset.push(EpochValidatorSet::new(
+                block_producers,
+                self.validator_groups,
+                self.num_shards,
+            ));
+        }
     }
 
-    fn get_block_producers(&self, valset: usize) -> &[ValidatorStake] {
-        &self.validators_by_valset[valset].block_producers
+    fn get_block_producers(&self, valset: usize) -> Vec<ValidatorStake> {
+        self.validators_by_valset.read()[valset].block_producers.clone()
     }
 
     fn get_chunk_producers(&self, valset: usize, shard_index: ShardIndex) -> Vec<ValidatorStake> {
-        self.validators_by_valset[valset].chunk_producers[shard_index].clone()
+        self.validators_by_valset.read()[valset].chunk_producers[shard_index].clone()
     }
 
     fn get_valset_for_epoch(&self, epoch_id: &EpochId) -> Result<usize, EpochError> {
         // conveniently here if the prev_hash is passed mistakenly instead of the epoch_hash,
         // the `unwrap` will trigger
-        Ok(*self
+        let valset = *self
             .hash_to_valset
             .read()
             .get(epoch_id)
-            .ok_or(EpochError::EpochOutOfBounds(*epoch_id))? as usize
-            % self.validators_by_valset.len())
+            .ok_or(EpochError::EpochOutOfBounds(*epoch_id))?;
+        Ok(self.valset_index(valset))
+    }
+
+    fn get_protocol_version_for_epoch(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<ProtocolVersion, EpochError> {
+        if self.protocol_versions_by_valset.is_empty() {
+            return Ok(PROTOCOL_VERSION);
+        }
+        Ok(self.protocol_versions_by_valset[self.get_valset_for_epoch(epoch_id)?])
+    }
+
+    fn default_shard_layout(&self) -> ShardLayout {
+        #[allow(deprecated)]
+        ShardLayout::v0(self.num_shards, 0)
+    }
+
+    fn get_shard_layout_by_valset(&self, valset: usize) -> ShardLayout {
+        match self.shard_layouts_by_valset.get(valset) {
+            Some(shard_layout) => shard_layout.clone(),
+            None => self.default_shard_layout(),
+        }
+    }
+
+    /// Shard layout of the epoch of the block, unlike `get_epoch_id` which
+    /// returns the epoch of the next block.
+    fn get_shard_layout_of_block(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<ShardLayout, EpochError> {
+        if block_hash == &CryptoHash::default() {
+            return self.get_shard_layout(&EpochId::default());
+        }
+        let header =
+            self.get_block_header(block_hash)?.ok_or(EpochError::MissingBlock(*block_hash))?;
+        self.get_shard_layout_from_prev_block(header.prev_hash())
     }
 
     fn get_block_header(&self, hash: &CryptoHash) -> Result<Option<BlockHeader>, EpochError> {
@@ -317,14 +712,52 @@ impl MockEpochManager {
 }
 
 impl KeyValueRuntime {
-    pub fn new(store: Store, epoch_manager: &MockEpochManager) -> Arc<Self> {
+    pub fn new(store: Store, epoch_manager: &Arc<MockEpochManager>) -> Arc<Self> {
         Self::new_with_no_gc(store, epoch_manager, false)
     }
     pub fn new_with_no_gc(
         store: Store,
-        epoch_manager: &MockEpochManager,
+        epoch_manager: &Arc<MockEpochManager>,
         no_gc: bool,
     ) -> Arc<Self> {
+        Self::new_with_options(
+            store,
+            epoch_manager,
+            KeyValueRuntimeOptions { no_gc, ..Default::default() },
+        )
+    }
+
+    /// Creates a runtime which asks `receipt_processor` how to process every
+    /// incoming receipt, so that tests can simulate failures, burnt gas and
+    /// delayed receipts.
+    pub fn new_with_receipt_processor(
+        store: Store,
+        epoch_manager: &Arc<MockEpochManager>,
+        receipt_processor: impl Fn(&Receipt) -> KvReceiptOutcome + Send + Sync + 'static,
+    ) -> Arc<Self> {
+        Self::new_with_options(
+            store,
+            epoch_manager,
+            KeyValueRuntimeOptions {
+                receipt_processor: Some(Box::new(receipt_processor)),
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn new_with_options(
+        store: Store,
+        epoch_manager: &Arc<MockEpochManager>,
+        options: KeyValueRuntimeOptions,
+    ) -> Arc<Self> {
+        let KeyValueRuntimeOptions {
+            no_gc,
+            transfer_gas,
+            receipt_processor,
+            memtrie,
+            congestion_model,
+            chain_genesis,
+        } = options;
         let epoch_id = EpochId::default();
         let shard_layout = epoch_manager.get_shard_layout(&epoch_id).unwrap();
         let epoch_length = epoch_manager.get_epoch_config(&epoch_id).unwrap().epoch_length;
@@ -335,6 +768,7 @@ impl KeyValueRuntime {
         let mut initial_amounts = HashMap::new();
         for (i, validator_stake) in epoch_manager
             .validators_by_valset
+            .read()
             .iter()
             .flat_map(|set| set.block_producers.iter())
             .enumerate()
@@ -346,6 +780,8 @@ impl KeyValueRuntime {
             amounts: initial_amounts,
             receipt_nonces: HashSet::default(),
             tx_nonces: HashSet::default(),
+            delayed_receipts: vec![],
+            buffered_receipts: vec![],
         };
         let data = borsh::to_vec(&kv_state).unwrap();
         let data_len = data.len() as u64;
@@ -365,13 +801,19 @@ impl KeyValueRuntime {
             store,
             tries,
             no_gc,
-            num_shards: shard_layout.num_shards(),
+            transfer_gas,
+            epoch_manager: epoch_manager.clone(),
             epoch_length,
             headers_cache: RwLock::new(HashMap::new()),
             state: RwLock::new(state),
             state_size: RwLock::new(state_size),
+            state_parts: RwLock::new(HashMap::new()),
             contract_cache: NoContractRuntimeCache,
             runtime_config: RuntimeConfig::test(),
+            receipt_processor,
+            memtries: memtrie.then(|| Mutex::new(HashMap::new())),
+            congestion_model,
+            chain_genesis,
         })
     }
 
@@ -387,8 +829,276 @@ impl KeyValueRuntime {
         Ok(None)
     }
 
-    fn get_congestion_info() -> CongestionInfo {
-        CongestionInfo::default()
+    /// Congestion info of the chunk of `shard_id` at `height`, given by the
+    /// congestion model.
+    fn get_congestion_info(
+        &self,
+        shard_layout: &ShardLayout,
+        shard_id: ShardId,
+        height: BlockHeight,
+    ) -> Result<CongestionInfo, Error> {
+        let backlogs = match &self.congestion_model {
+            None => return Ok(CongestionInfo::default()),
+            Some(KvCongestionModel::Custom(congestion_info)) => {
+                return Ok(congestion_info(shard_id, height));
+            }
+            Some(KvCongestionModel::Fixed(backlogs)) => backlogs,
+        };
+        let mut congestion_info = CongestionInfo::default();
+        if let Some(backlog) = backlogs.get(&shard_id) {
+            // A single backlog added to an empty one can't overflow.
+            congestion_info.add_delayed_receipt_gas(backlog.delayed_receipts_gas).unwrap();
+            congestion_info.add_buffered_receipt_gas(backlog.buffered_receipts_gas).unwrap();
+        }
+        let all_shards = shard_layout.shard_ids().collect_vec();
+        let shard_index = shard_layout.get_shard_index(shard_id)? as u64;
+        congestion_info.finalize_allowed_shard(
+            shard_id,
+            &all_shards,
+            height.wrapping_add(shard_index),
+        );
+        Ok(congestion_info)
+    }
+
+    fn shard_accepts_transactions(
+        &self,
+        congestion_info: &ExtendedCongestionInfo,
+    ) -> ShardAcceptsTransactions {
+        CongestionControl::new(
+            self.runtime_config.congestion_control_config,
+            congestion_info.congestion_info,
+            congestion_info.missed_chunks_count,
+        )
+        .shard_accepts_transactions()
+    }
+
+    /// Forwards the buffered receipts and then the new outgoing receipts as
+    /// long as the congestion of their receiving shards allows it, and buffers
+    /// the rest, keeping the order of the receipts to every shard. Returns the
+    /// forwarded receipts.
+    fn forward_outgoing_receipts(
+        &self,
+        state: &mut KVState,
+        new_receipts: Vec<Receipt>,
+        shard_layout: &ShardLayout,
+        shard_id: ShardId,
+        congestion_info: &BlockCongestionInfo,
+    ) -> Vec<Receipt> {
+        let mut outgoing_gas_limits = HashMap::new();
+        let mut forwarded_receipts = vec![];
+        let buffered_receipts = std::mem::take(&mut state.buffered_receipts);
+        for receipt in buffered_receipts.into_iter().chain(new_receipts) {
+            let receiver_shard_id = shard_layout.account_id_to_shard_id(receipt.receiver_id());
+            let gas_limit = outgoing_gas_limits.entry(receiver_shard_id).or_insert_with(|| {
+                congestion_info.get(&receiver_shard_id).map_or(Gas::MAX, |info| {
+                    CongestionControl::new(
+                        self.runtime_config.congestion_control_config,
+                        info.congestion_info,
+                        info.missed_chunks_count,
+                    )
+                    .outgoing_gas_limit(shard_id)
+                })
+            });
+            if *gas_limit > 0 && *gas_limit >= self.transfer_gas {
+                *gas_limit -= self.transfer_gas;
+                forwarded_receipts.push(receipt);
+            } else {
+                *gas_limit = 0;
+                state.buffered_receipts.push(receipt);
+            }
+        }
+        forwarded_receipts
+    }
+
+    /// Returns the state at `state_root`. With memtries, the states of the
+    /// chunks are read from the memtrie of their shard, and only the genesis
+    /// state, at the empty root, is kept in memory.
+    fn get_kv_state(&self, state_root: &StateRoot) -> Result<KVState, Error> {
+        let unknown_state_root = || Error::Other(format!("unknown state root {state_root}"));
+        let memtries = match &self.memtries {
+            Some(memtries) if *state_root != Trie::EMPTY_ROOT => memtries.lock(),
+            _ => return self.state.read().get(state_root).cloned().ok_or_else(unknown_state_root),
+        };
+        let (shard_uid, memtries) = memtries
+            .iter()
+            .find(|(_, memtries)| memtries.get_root(state_root).is_ok())
+            .ok_or_else(unknown_state_root)?;
+        let trie = self.tries.get_trie_for_shard(*shard_uid, *state_root);
+        let entries = memtries.get_iter(&trie)?.collect::<Result<Vec<_>, _>>()?;
+        kv_state_from_trie_entries(entries)
+    }
+
+    /// Writes `state` to the memtrie of the shard on top of the state at
+    /// `prev_state_root`. The genesis state, at the empty root, is written
+    /// entirely by the first chunk. Returns the new state root and the storage
+    /// proof of the nodes accessed by the update. The roots below the GC stop
+    /// height are deleted, unless GC is disabled.
+    fn update_memtrie(
+        &self,
+        memtries: &Mutex<HashMap<ShardUId, MemTries>>,
+        shard_uid: ShardUId,
+        prev_state_root: StateRoot,
+        state: &KVState,
+        block_height: BlockHeight,
+    ) -> Result<(StateRoot, PartialStorage), Error> {
+        let mut prev_entries = if prev_state_root == Trie::EMPTY_ROOT {
+            BTreeMap::new()
+        } else {
+            kv_state_trie_entries(&self.get_kv_state(&prev_state_root)?)
+        };
+        let mut memtries = memtries.lock();
+        let memtries = memtries.entry(shard_uid).or_insert_with(|| MemTries::new(shard_uid));
+        let mut recorder = TrieRecorder::new(None);
+        let changes = {
+            let mut update = memtries
+                .update(prev_state_root, TrackingMode::RefcountsAndAccesses(&mut recorder))?;
+            for (key, value) in kv_state_trie_entries(state) {
+                if prev_entries.remove(&key).as_ref() != Some(&value) {
+                    update.insert(&key, value)?;
+                }
+            }
+            for key in prev_entries.keys() {
+                update.delete(key)?;
+            }
+            update.to_memtrie_changes_only()
+        };
+        let state_root = memtries.apply_memtrie_changes(block_height, &changes);
+        if !self.no_gc {
+            memtries.delete_until_height(
+                block_height.saturating_sub(DEFAULT_GC_NUM_EPOCHS_TO_KEEP * self.epoch_length),
+            );
+        }
+        Ok((state_root, recorder.recorded_storage()))
+    }
+}
+
+/// Snapshot of a chain built with the `KeyValueRuntime` and the
+/// `MockEpochManager`: the content of their store, which holds the chain too,
+/// and their in-memory state.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct KvChainSnapshot {
+    /// Raw key-value pairs of every non-empty column, by column name.
+    store: Vec<(String, Vec<(Vec<u8>, Vec<u8>)>)>,
+    state: HashMap<StateRoot, KVState>,
+    state_size: HashMap<StateRoot, u64>,
+    state_parts: HashMap<StateRoot, BTreeMap<u64, Vec<u8>>>,
+    validators_by_valset: Vec<EpochValidatorSet>,
+    proposals_by_valset: HashMap<u64, BTreeMap<AccountId, ValidatorStake>>,
+    blocks_produced_by_valset: HashMap<u64, HashMap<AccountId, u64>>,
+    validators: HashMap<AccountId, ValidatorStake>,
+    hash_to_epoch: HashMap<CryptoHash, EpochId>,
+    hash_to_next_epoch_approvals_req: HashMap<CryptoHash, bool>,
+    hash_to_next_epoch: HashMap<CryptoHash, EpochId>,
+    hash_to_valset: HashMap<EpochId, u64>,
+    epoch_start: HashMap<CryptoHash, u64>,
+}
+
+impl KeyValueRuntime {
+    /// Saves the chain built with this runtime and its epoch manager to
+    /// `path`, so that a test can restore it with `restore_snapshot` and go on
+    /// from the current head instead of producing the blocks again. The
+    /// snapshot is deterministic: the same chain always gives the same file.
+    ///
+    /// The chain must be stored in the store of the runtime, and the runtime
+    /// must not use memtries, which aren't saved.
+    pub fn save_snapshot(&self, path: &Path) -> io::Result<()> {
+        assert!(self.memtries.is_none(), "snapshots of memtries are not supported");
+        let epoch_manager = &self.epoch_manager;
+        let mut store = vec![];
+        for col in DBCol::iter() {
+            let entries = self
+                .store
+                .iter_raw_bytes(col)
+                .map(|item| item.map(|(key, value)| (key.into_vec(), value.into_vec())))
+                .collect::<io::Result<Vec<_>>>()?;
+            if !entries.is_empty() {
+                store.push((<&str>::from(col).to_string(), entries));
+            }
+        }
+        let snapshot = KvChainSnapshot {
+            store,
+            state: self.state.read().clone(),
+            state_size: self.state_size.read().clone(),
+            state_parts: self.state_parts.read().clone(),
+            validators_by_valset: epoch_manager
+                .validators_by_valset
+                .read()
+                .iter()
+                .map(|set| EpochValidatorSet {
+                    block_producers: set.block_producers.clone(),
+                    chunk_producers: set.chunk_producers.clone(),
+                })
+                .collect(),
+            proposals_by_valset: epoch_manager
+                .proposals_by_valset
+                .read()
+                .iter()
+                .map(|(valset, proposals)| (*valset as u64, proposals.clone()))
+                .collect(),
+            blocks_produced_by_valset: epoch_manager
+                .blocks_produced_by_valset
+                .read()
+                .iter()
+                .map(|(valset, blocks_produced)| (*valset as u64, blocks_produced.clone()))
+                .collect(),
+            validators: epoch_manager.validators.read().clone(),
+            hash_to_epoch: epoch_manager.hash_to_epoch.read().clone(),
+            hash_to_next_epoch_approvals_req: epoch_manager
+                .hash_to_next_epoch_approvals_req
+                .read()
+                .clone(),
+            hash_to_next_epoch: epoch_manager.hash_to_next_epoch.read().clone(),
+            hash_to_valset: epoch_manager.hash_to_valset.read().clone(),
+            epoch_start: epoch_manager.epoch_start.read().clone(),
+        };
+        std::fs::write(path, borsh::to_vec(&snapshot)?)
+    }
+
+    /// Restores the chain saved by `save_snapshot` to this runtime, its epoch
+    /// manager and their store. They must be created with the same validator
+    /// schedule and options as the ones the snapshot was saved from, and no
+    /// chain must be built on top of them yet. The chain can then be opened on
+    /// the store, with its head at the head of the snapshot.
+    pub fn restore_snapshot(&self, path: &Path) -> io::Result<()> {
+        assert!(self.memtries.is_none(), "snapshots of memtries are not supported");
+        let snapshot = KvChainSnapshot::try_from_slice(&std::fs::read(path)?)?;
+        let mut store_update = self.store.store_update();
+        for (col_name, entries) in &snapshot.store {
+            let col =
+                DBCol::iter().find(|col| <&str>::from(*col) == col_name).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("unknown column {col_name}"))
+                })?;
+            for (key, value) in entries {
+                store_update.set_raw_bytes(col, key, value);
+            }
+        }
+        store_update.commit()?;
+
+        *self.state.write() = snapshot.state;
+        *self.state_size.write() = snapshot.state_size;
+        *self.state_parts.write() = snapshot.state_parts;
+        self.headers_cache.write().clear();
+        let epoch_manager = &self.epoch_manager;
+        *epoch_manager.validators_by_valset.write() = snapshot.validators_by_valset;
+        *epoch_manager.proposals_by_valset.write() = snapshot
+            .proposals_by_valset
+            .into_iter()
+            .map(|(valset, proposals)| (valset as usize, proposals))
+            .collect();
+        *epoch_manager.blocks_produced_by_valset.write() = snapshot
+            .blocks_produced_by_valset
+            .into_iter()
+            .map(|(valset, blocks_produced)| (valset as usize, blocks_produced))
+            .collect();
+        *epoch_manager.validators.write() = snapshot.validators;
+        epoch_manager.headers_cache.write().clear();
+        *epoch_manager.hash_to_epoch.write() = snapshot.hash_to_epoch;
+        *epoch_manager.hash_to_next_epoch_approvals_req.write() =
+            snapshot.hash_to_next_epoch_approvals_req;
+        *epoch_manager.hash_to_next_epoch.write() = snapshot.hash_to_next_epoch;
+        *epoch_manager.hash_to_valset.write() = snapshot.hash_to_valset;
+        *epoch_manager.epoch_start.write() = snapshot.epoch_start;
+        Ok(())
     }
 }
 
@@ -464,18 +1174,29 @@ impl EpochManagerAdapter for MockEpochManager {
     /// - block producers
     /// - chunk producers
     /// All the other fields have a hardcoded value or left empty.
-    fn get_epoch_info(&self, _epoch_id: &EpochId) -> Result<Arc<EpochInfo>, EpochError> {
-        let validators = self.validators.iter().map(|(_, stake)| stake.clone()).collect();
-        let mut validator_to_index = HashMap::new();
-        for (i, (account_id, _)) in self.validators.iter().enumerate() {
-            validator_to_index.insert(account_id.clone(), i as u64);
-        }
-        let bp_settlement = self.validators_by_valset[0]
+    fn get_epoch_info(&self, epoch_id: &EpochId) -> Result<Arc<EpochInfo>, EpochError> {
+        let valset = self.get_valset_for_epoch(epoch_id)?;
+        let validators_by_valset = self.validators_by_valset.read();
+        let epoch_validators = &validators_by_valset[valset];
+        // The validators of the epoch, with their stake in the epoch.
+        let validators = epoch_validators
+            .block_producers
+            .iter()
+            .chain(epoch_validators.chunk_producers.iter().flatten())
+            .unique_by(|stake| stake.account_id().clone())
+            .cloned()
+            .collect_vec();
+        let validator_to_index: HashMap<AccountId, u64> = validators
+            .iter()
+            .enumerate()
+            .map(|(i, stake)| (stake.account_id().clone(), i as u64))
+            .collect();
+        let bp_settlement = epoch_validators
             .block_producers
             .iter()
             .map(|stake| *validator_to_index.get(stake.account_id()).unwrap())
             .collect();
-        let cp_settlement = self.validators_by_valset[0]
+        let cp_settlement = epoch_validators
             .chunk_producers
             .iter()
             .map(|vec| {
@@ -495,22 +1216,30 @@ impl EpochManagerAdapter for MockEpochManager {
             HashMap::new(),
             1,
             1,
-            PROTOCOL_VERSION,
+            self.get_protocol_version_for_epoch(epoch_id)?,
             RngSeed::default(),
             Default::default(),
         )))
     }
 
-    fn get_shard_layout(&self, _epoch_id: &EpochId) -> Result<ShardLayout, EpochError> {
-        #[allow(deprecated)]
-        Ok(ShardLayout::v0(self.num_shards, 0))
+    fn get_shard_layout(&self, epoch_id: &EpochId) -> Result<ShardLayout, EpochError> {
+        if self.shard_layouts_by_valset.is_empty() {
+            return Ok(self.default_shard_layout());
+        }
+        Ok(self.get_shard_layout_by_valset(self.get_valset_for_epoch(epoch_id)?))
     }
 
     fn get_shard_layout_from_protocol_version(
         &self,
-        _protocol_version: ProtocolVersion,
+        protocol_version: ProtocolVersion,
     ) -> ShardLayout {
-        self.get_shard_layout(&EpochId::default()).unwrap()
+        // The layout of the last epoch with at most this protocol version.
+        let valset = self
+            .protocol_versions_by_valset
+            .iter()
+            .rposition(|version| *version <= protocol_version)
+            .unwrap_or(0);
+        self.get_shard_layout_by_valset(valset)
     }
 
     fn get_shard_config(&self, _epoch_id: &EpochId) -> Result<ShardConfig, EpochError> {
@@ -571,12 +1300,9 @@ impl EpochManagerAdapter for MockEpochManager {
         shard_ids: Vec<ShardId>,
     ) -> Result<Vec<(ShardId, ShardIndex)>, Error> {
         let mut prev_shard_ids = vec![];
-        let shard_layout = self.get_shard_layout_from_prev_block(prev_hash)?;
         for shard_id in shard_ids {
-            // This is not correct if there was a resharding event in between
-            // the previous and current block.
-            let prev_shard_id = shard_id;
-            let prev_shard_index = shard_layout.get_shard_index(prev_shard_id)?;
+            let (_, prev_shard_id, prev_shard_index) =
+                self.get_prev_shard_id_from_prev_hash(prev_hash, shard_id)?;
             prev_shard_ids.push((prev_shard_id, prev_shard_index));
         }
 
@@ -589,19 +1315,31 @@ impl EpochManagerAdapter for MockEpochManager {
         shard_id: ShardId,
     ) -> Result<(ShardLayout, ShardId, ShardIndex), EpochError> {
         let shard_layout = self.get_shard_layout_from_prev_block(prev_hash)?;
-        // This is not correct if there was a resharding event in between
-        // the previous and current block.
-        let prev_shard_id = shard_id;
-        let prev_shard_index = shard_layout.get_shard_index(prev_shard_id)?;
-        Ok((shard_layout, prev_shard_id, prev_shard_index))
+        if self.shard_layouts_by_valset.is_empty() {
+            let shard_index = shard_layout.get_shard_index(shard_id)?;
+            return Ok((shard_layout, shard_id, shard_index));
+        }
+        // The shard layout only changes at the epoch boundaries.
+        let prev_shard_layout = self.get_shard_layout_of_block(prev_hash)?;
+        if prev_shard_layout != shard_layout {
+            let parent_shard_id = shard_layout.get_parent_shard_id(shard_id)?;
+            let parent_shard_index = prev_shard_layout.get_shard_index(parent_shard_id)?;
+            Ok((prev_shard_layout, parent_shard_id, parent_shard_index))
+        } else {
+            let shard_index = shard_layout.get_shard_index(shard_id)?;
+            Ok((shard_layout, shard_id, shard_index))
+        }
     }
 
     fn get_shard_layout_from_prev_block(
         &self,
-        _parent_hash: &CryptoHash,
+        parent_hash: &CryptoHash,
     ) -> Result<ShardLayout, EpochError> {
-        #[allow(deprecated)]
-        Ok(ShardLayout::v0(self.num_shards, 0))
+        if self.shard_layouts_by_valset.is_empty() {
+            return Ok(self.default_shard_layout());
+        }
+        let epoch_id = self.get_epoch_id_from_prev_block(parent_hash)?;
+        self.get_shard_layout(&epoch_id)
     }
 
     fn get_epoch_id(&self, block_hash: &CryptoHash) -> Result<EpochId, EpochError> {
@@ -751,13 +1489,18 @@ impl EpochManagerAdapter for MockEpochManager {
     fn get_chunk_validator_assignments(
         &self,
         epoch_id: &EpochId,
-        _shard_id: ShardId,
+        shard_id: ShardId,
         _height: BlockHeight,
     ) -> Result<Arc<ChunkValidatorAssignments>, EpochError> {
+        let valset = self.get_valset_for_epoch(epoch_id)?;
+        if let Some(epoch_chunk_validators) = self.chunk_validators_by_valset.get(valset) {
+            let shard_index = self.get_shard_layout(epoch_id)?.get_shard_index(shard_id)?;
+            let chunk_validators = epoch_chunk_validators[shard_index].clone();
+            return Ok(Arc::new(ChunkValidatorAssignments::new(chunk_validators)));
+        }
         let chunk_validators = self
-            .get_block_producers(self.get_valset_for_epoch(epoch_id)?)
+            .get_block_producers(valset)
             .into_iter()
-            .cloned()
             .map(|validator| validator.account_and_stake())
             .collect();
         Ok(Arc::new(ChunkValidatorAssignments::new(chunk_validators)))
@@ -768,7 +1511,9 @@ impl EpochManagerAdapter for MockEpochManager {
         epoch_id: &EpochId,
         account_id: &AccountId,
     ) -> Result<ValidatorStake, EpochError> {
-        let validators = &self.validators_by_valset[self.get_valset_for_epoch(epoch_id)?];
+        let valset = self.get_valset_for_epoch(epoch_id)?;
+        let validators_by_valset = self.validators_by_valset.read();
+        let validators = &validators_by_valset[valset];
         for validator_stake in &validators.block_producers {
             if validator_stake.account_id() == account_id {
                 return Ok(validator_stake.clone());
@@ -798,19 +1543,58 @@ impl EpochManagerAdapter for MockEpochManager {
         })
     }
 
+    fn get_protocol_version_votes(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, EpochError> {
+        let epoch_id = self.get_epoch_id(block_hash)?;
+        let next_epoch_protocol_version =
+            self.get_protocol_version_for_epoch(&self.get_next_epoch_id(block_hash)?)?;
+        Ok(ProtocolVersionVotesView {
+            epoch_id,
+            epoch_height: 1,
+            protocol_version: self.get_protocol_version_for_epoch(&epoch_id)?,
+            next_epoch_protocol_version,
+            projected_protocol_version: next_epoch_protocol_version,
+            total_stake: 0,
+            threshold_stake: 0,
+            versions: vec![],
+            validators: vec![],
+        })
+    }
+
+    /// With `staking_proposals`, records the proposals and the producer of
+    /// the block for the validator set of the epoch after the next one.
     fn add_validator_proposals(
         &self,
-        _block_info: BlockInfo,
+        block_info: BlockInfo,
         _random_value: CryptoHash,
     ) -> Result<StoreUpdate, EpochError> {
+        if self.staking_proposals && !block_info.is_genesis() {
+            let epoch_id = self.get_epoch_id_from_prev_block(block_info.prev_hash())?;
+            let valset = self.get_valset_for_epoch(&epoch_id)?;
+            let producer = self.get_block_producer(&epoch_id, block_info.height())?;
+            *self
+                .blocks_produced_by_valset
+                .write()
+                .entry(valset)
+                .or_default()
+                .entry(producer)
+                .or_default() += 1;
+            let mut proposals_by_valset = self.proposals_by_valset.write();
+            let proposals = proposals_by_valset.entry(valset).or_default();
+            for proposal in block_info.proposals_iter() {
+                proposals.insert(proposal.account_id().clone(), proposal);
+            }
+        }
         Ok(self.store.store_update())
     }
 
     fn get_epoch_protocol_version(
         &self,
-        _epoch_id: &EpochId,
+        epoch_id: &EpochId,
     ) -> Result<ProtocolVersion, EpochError> {
-        Ok(PROTOCOL_VERSION)
+        self.get_protocol_version_for_epoch(epoch_id)
     }
 
     fn init_after_epoch_sync(
@@ -885,12 +1669,10 @@ impl EpochManagerAdapter for MockEpochManager {
         //    we check if we care about a shard. Please do not remove the unwrap, fix the logic of
         //    the calling function.
         let epoch_valset = self.get_epoch_and_valset(*parent_hash).unwrap();
-        let shard_layout = self.get_shard_layout_from_prev_block(parent_hash)?;
+        let next_valset = self.valset_index(epoch_valset.1 as u64 + 1);
+        let shard_layout = self.get_shard_layout_by_valset(next_valset);
         let shard_index = shard_layout.get_shard_index(shard_id)?;
-        let chunk_producers = self.get_chunk_producers(
-            (epoch_valset.1 + 1) % self.validators_by_valset.len(),
-            shard_index,
-        );
+        let chunk_producers = self.get_chunk_producers(next_valset, shard_index);
         for validator in chunk_producers {
             if validator.account_id() == account_id {
                 return Ok(true);
@@ -909,12 +1691,11 @@ impl EpochManagerAdapter for MockEpochManager {
         //    we check if we care about a shard. Please do not remove the unwrap, fix the logic of
         //    the calling function.
         let epoch_valset = self.get_epoch_and_valset(*parent_hash).unwrap();
-        let shard_layout = self.get_shard_layout_from_prev_block(parent_hash)?;
+        let valset = *self.hash_to_valset.read().get(&epoch_valset.0).unwrap();
+        let prev_valset = self.prev_valset_index(valset);
+        let shard_layout = self.get_shard_layout_by_valset(prev_valset);
         let shard_index = shard_layout.get_shard_index(shard_id)?;
-        let chunk_producers = self.get_chunk_producers(
-            (epoch_valset.1.wrapping_sub(1)) % self.validators_by_valset.len(),
-            shard_index,
-        );
+        let chunk_producers = self.get_chunk_producers(prev_valset, shard_index);
         for validator in chunk_producers {
             if validator.account_id() == account_id {
                 return Ok(true);
@@ -955,7 +1736,7 @@ impl EpochManagerAdapter for MockEpochManager {
         &self,
         _epoch_id: &EpochId,
     ) -> Result<Vec<ValidatorStake>, EpochError> {
-        Ok(self.validators.iter().map(|(_, v)| v.clone()).collect())
+        Ok(self.validators.read().iter().map(|(_, v)| v.clone()).collect())
     }
 }
 
@@ -991,17 +1772,41 @@ impl RuntimeAdapter for KeyValueRuntime {
         Ok(self.tries.get_view_trie_for_shard(ShardUId::new(0, shard_id), state_root))
     }
 
-    fn get_shard_layout(&self, _protocol_version: ProtocolVersion) -> ShardLayout {
-        ShardLayout::multi_shard(self.num_shards, 0)
+    fn get_shard_layout(&self, protocol_version: ProtocolVersion) -> ShardLayout {
+        if self.epoch_manager.shard_layouts_by_valset.is_empty() {
+            return ShardLayout::multi_shard(self.epoch_manager.num_shards, 0);
+        }
+        self.epoch_manager.get_shard_layout_from_protocol_version(protocol_version)
     }
 
     fn validate_tx(
         &self,
-        _shard_layout: &ShardLayout,
+        shard_layout: &ShardLayout,
         signed_tx: SignedTransaction,
         _protocol_version: ProtocolVersion,
-        _receiver_congestion_info: Option<ExtendedCongestionInfo>,
+        receiver_congestion_info: Option<ExtendedCongestionInfo>,
     ) -> Result<ValidatedTransaction, (InvalidTxError, SignedTransaction)> {
+        let receiver_congestion_info =
+            receiver_congestion_info.filter(|_| self.congestion_model.is_some());
+        if let Some(congestion_info) = receiver_congestion_info {
+            if let ShardAcceptsTransactions::No(reason) =
+                self.shard_accepts_transactions(&congestion_info)
+            {
+                let shard_id =
+                    shard_layout.account_id_to_shard_id(signed_tx.transaction.receiver_id()).into();
+                let err = match reason {
+                    RejectTransactionReason::IncomingCongestion { congestion_level }
+                    | RejectTransactionReason::OutgoingCongestion { congestion_level }
+                    | RejectTransactionReason::MemoryCongestion { congestion_level } => {
+                        InvalidTxError::ShardCongested { shard_id, congestion_level }
+                    }
+                    RejectTransactionReason::MissedChunks { missed_chunks } => {
+                        InvalidTxError::ShardStuck { shard_id, missed_chunks }
+                    }
+                };
+                return Err((err, signed_tx));
+            }
+        }
         Ok(ValidatedTransaction::new_for_test(signed_tx))
     }
 
@@ -1019,17 +1824,34 @@ impl RuntimeAdapter for KeyValueRuntime {
     fn prepare_transactions(
         &self,
         _storage: RuntimeStorageConfig,
-        _chunk: PrepareTransactionsChunkContext,
-        _prev_block: PrepareTransactionsBlockContext,
+        chunk: PrepareTransactionsChunkContext,
+        prev_block: PrepareTransactionsBlockContext,
         transaction_groups: &mut dyn TransactionGroupIterator,
         _chain_validate: &dyn Fn(&SignedTransaction) -> bool,
         _time_limit: Option<Duration>,
     ) -> Result<PreparedTransactions, Error> {
+        let shard_layout =
+            self.epoch_manager.get_shard_layout_from_prev_block(&prev_block.block_hash)?;
         let mut res = vec![];
+        let mut limited_by = None;
         while let Some(iter) = transaction_groups.next() {
-            res.push(iter.next().unwrap());
+            if self.transfer_gas > 0 && res.len() as Gas * self.transfer_gas >= chunk.gas_limit {
+                limited_by = Some(PrepareTransactionsLimit::Gas);
+                break;
+            }
+            let validated_tx = iter.next().unwrap();
+            if self.congestion_model.is_some() {
+                let receiver_shard_id =
+                    shard_layout.account_id_to_shard_id(validated_tx.receiver_id());
+                if prev_block.congestion_info.get(&receiver_shard_id).is_some_and(
+                    |congestion_info| self.shard_accepts_transactions(congestion_info).is_no(),
+                ) {
+                    continue;
+                }
+            }
+            res.push(validated_tx);
         }
-        Ok(PreparedTransactions { transactions: res, limited_by: None })
+        Ok(PreparedTransactions { transactions: res, limited_by, limited_signers: vec![] })
     }
 
     fn apply_chunk(
@@ -1042,20 +1864,63 @@ impl RuntimeAdapter for KeyValueRuntime {
         transactions: SignedValidPeriodTransactions,
     ) -> Result<ApplyChunkResult, Error> {
         let mut tx_results = vec![];
+        let mut validator_proposals = vec![];
         let shard_id = chunk.shard_id;
+        let shard_layout =
+            self.epoch_manager.get_shard_layout_from_prev_block(&block.prev_block_hash)?;
 
-        let mut state = self.state.read().get(&storage_config.state_root).cloned().unwrap();
+        let mut state = self.get_kv_state(&storage_config.state_root)?;
 
         let mut balance_transfers = vec![];
-
-        for receipt in receipts {
+        let mut total_gas_burnt = 0;
+        let mut total_balance_burnt = 0;
+
+        let delayed_receipts = std::mem::take(&mut state.delayed_receipts);
+        let mut processed_delayed_receipts = vec![];
+        for (index, receipt) in delayed_receipts.iter().chain(receipts).enumerate() {
+            // Receipts burning no gas never fill the chunk.
+            if total_gas_burnt > 0 && total_gas_burnt >= chunk.gas_limit {
+                state.delayed_receipts.push(receipt.clone());
+                continue;
+            }
+            let outcome = match &self.receipt_processor {
+                Some(receipt_processor) => receipt_processor(receipt),
+                None => KvReceiptOutcome::Default,
+            };
+            if matches!(outcome, KvReceiptOutcome::Delay) {
+                state.delayed_receipts.push(receipt.clone());
+                continue;
+            }
+            if index < delayed_receipts.len() {
+                processed_delayed_receipts.push(receipt.clone());
+            }
+            if let KvReceiptOutcome::Outcome { status, gas_burnt } = outcome {
+                assert!(
+                    state.receipt_nonces.insert(*receipt.receipt_id()),
+                    "receipts should never be applied twice"
+                );
+                let tokens_burnt = gas_burnt as Balance * block.gas_price;
+                total_gas_burnt += gas_burnt;
+                total_balance_burnt += tokens_burnt;
+                tx_results.push(ExecutionOutcomeWithId {
+                    id: receipt.get_hash(),
+                    outcome: ExecutionOutcome {
+                        status,
+                        logs: vec![],
+                        receipt_ids: vec![],
+                        gas_burnt,
+                        compute_usage: Some(gas_burnt),
+                        tokens_burnt,
+                        executor_id: receipt.receiver_id().clone(),
+                        metadata: ExecutionMetadata::V1,
+                    },
+                });
+                continue;
+            }
             if let ReceiptEnum::Action(action) | ReceiptEnum::PromiseYield(action) =
                 receipt.receipt()
             {
-                assert_eq!(
-                    account_id_to_shard_id(receipt.receiver_id(), self.num_shards),
-                    shard_id
-                );
+                assert_eq!(shard_layout.account_id_to_shard_id(receipt.receiver_id()), shard_id);
                 if !state.receipt_nonces.contains(receipt.receipt_id()) {
                     state.receipt_nonces.insert(*receipt.receipt_id());
                     if let Action::Transfer(TransferAction { deposit }) = action.actions[0] {
@@ -1066,6 +1931,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                             deposit,
                             0,
                         ));
+                        total_gas_burnt += self.transfer_gas;
                     }
                 } else {
                     panic!("receipts should never be applied twice");
@@ -1077,12 +1943,36 @@ impl RuntimeAdapter for KeyValueRuntime {
 
         for transaction in transactions.iter_nonexpired_transactions() {
             assert_eq!(
-                account_id_to_shard_id(transaction.transaction.signer_id(), self.num_shards),
+                shard_layout.account_id_to_shard_id(transaction.transaction.signer_id()),
                 shard_id
             );
             if transaction.transaction.actions().is_empty() {
                 continue;
             }
+            // Staking only makes a proposal, the stake isn't locked.
+            if let Action::Stake(stake) = &transaction.transaction.actions()[0] {
+                let StakeAction { stake, public_key } = stake.as_ref();
+                let signer_id = transaction.transaction.signer_id();
+                validator_proposals.push(ValidatorStake::new(
+                    signer_id.clone(),
+                    public_key.clone(),
+                    *stake,
+                ));
+                tx_results.push(ExecutionOutcomeWithId {
+                    id: transaction.get_hash(),
+                    outcome: ExecutionOutcome {
+                        status: ExecutionStatus::SuccessValue(vec![]),
+                        logs: vec![],
+                        receipt_ids: vec![],
+                        gas_burnt: 0,
+                        compute_usage: Some(0),
+                        tokens_burnt: 0,
+                        executor_id: signer_id.clone(),
+                        metadata: ExecutionMetadata::V1,
+                    },
+                });
+                continue;
+            }
             if let Action::Transfer(TransferAction { deposit }) =
                 transaction.transaction.actions()[0]
             {
@@ -1101,6 +1991,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                         deposit,
                         transaction.transaction.nonce(),
                     ));
+                    total_gas_burnt += self.transfer_gas;
                 } else {
                     balance_transfers.push((
                         transaction.get_hash(),
@@ -1109,6 +2000,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                         0,
                         transaction.transaction.nonce(),
                     ));
+                    total_gas_burnt += self.transfer_gas;
                 }
             } else {
                 unreachable!();
@@ -1116,24 +2008,30 @@ impl RuntimeAdapter for KeyValueRuntime {
         }
 
         let mut outgoing_receipts = vec![];
+        let transfer_cost = self.transfer_gas as Balance * block.gas_price;
 
         for (hash, from, to, amount, nonce) in balance_transfers {
             let mut good_to_go = false;
 
-            if account_id_to_shard_id(&from, self.num_shards) != shard_id {
+            if shard_layout.account_id_to_shard_id(&from) != shard_id {
                 // This is a receipt, was already debited
                 good_to_go = true;
             } else if let Some(balance) = state.amounts.get(&from) {
-                if *balance >= amount {
-                    let new_balance = balance - amount;
+                // The signer prepays the gas of the receipt sent to another
+                // shard along with the gas of the transaction.
+                let num_transfers =
+                    if shard_layout.account_id_to_shard_id(&to) == shard_id { 1 } else { 2 };
+                let cost = amount + num_transfers * transfer_cost;
+                if *balance >= cost {
+                    let new_balance = balance - cost;
                     state.amounts.insert(from.clone(), new_balance);
                     good_to_go = true;
                 }
             }
 
             if good_to_go {
-                let new_receipt_hashes = if account_id_to_shard_id(&to, self.num_shards) == shard_id
-                {
+                total_balance_burnt += transfer_cost;
+                let new_receipt_hashes = if shard_layout.account_id_to_shard_id(&to) == shard_id {
                     state.amounts.insert(to.clone(), state.amounts.get(&to).unwrap_or(&0) + amount);
                     vec![]
                 } else {
@@ -1162,9 +2060,9 @@ impl RuntimeAdapter for KeyValueRuntime {
                         status: ExecutionStatus::SuccessValue(vec![]),
                         logs: vec![],
                         receipt_ids: new_receipt_hashes,
-                        gas_burnt: 0,
-                        compute_usage: Some(0),
-                        tokens_burnt: 0,
+                        gas_burnt: self.transfer_gas,
+                        compute_usage: Some(self.transfer_gas),
+                        tokens_burnt: self.transfer_gas as Balance * block.gas_price,
                         executor_id: to.clone(),
                         metadata: ExecutionMetadata::V1,
                     },
@@ -1172,16 +2070,39 @@ impl RuntimeAdapter for KeyValueRuntime {
             }
         }
 
+        if self.congestion_model.is_some() {
+            outgoing_receipts = self.forward_outgoing_receipts(
+                &mut state,
+                outgoing_receipts,
+                &shard_layout,
+                shard_id,
+                &block.congestion_info,
+            );
+        }
+        let congestion_info = self.get_congestion_info(&shard_layout, shard_id, block.height)?;
+
+        let shard_uid = ShardUId::from_shard_id_and_layout(shard_id, &shard_layout);
         let data = borsh::to_vec(&state)?;
         let state_size = data.len() as u64;
-        let state_root = hash(&data);
-        self.state.write().insert(state_root, state);
-        self.state_size.write().insert(state_root, state_size);
-        let storage_proof = Some(Default::default());
+        let (state_root, storage_proof) = match &self.memtries {
+            Some(memtries) => self.update_memtrie(
+                memtries,
+                shard_uid,
+                storage_config.state_root,
+                &state,
+                block.height,
+            )?,
+            None => {
+                let state_root = kv_state_root(&kv_state_part_hashes(&data));
+                self.state.write().insert(state_root, state);
+                self.state_size.write().insert(state_root, state_size);
+                (state_root, Default::default())
+            }
+        };
         Ok(ApplyChunkResult {
             trie_changes: WrappedTrieChanges::new(
                 self.get_tries(),
-                ShardUId::new(0, shard_id),
+                shard_uid,
                 TrieChanges::empty(state_root),
                 Default::default(),
                 block.height,
@@ -1189,14 +2110,14 @@ impl RuntimeAdapter for KeyValueRuntime {
             new_root: state_root,
             outcomes: tx_results,
             outgoing_receipts,
-            validator_proposals: vec![],
-            total_gas_burnt: 0,
-            total_balance_burnt: 0,
-            proof: storage_proof,
-            processed_delayed_receipts: vec![],
+            validator_proposals,
+            total_gas_burnt,
+            total_balance_burnt,
+            proof: Some(storage_proof),
+            processed_delayed_receipts,
             processed_yield_timeouts: vec![],
             applied_receipts_hash: hash(&borsh::to_vec(receipts).unwrap()),
-            congestion_info: Some(Self::get_congestion_info()),
+            congestion_info: Some(congestion_info),
             bandwidth_requests: BandwidthRequests::empty(),
             bandwidth_scheduler_state_hash: CryptoHash::default(),
             contract_updates: Default::default(),
@@ -1219,8 +2140,8 @@ impl RuntimeAdapter for KeyValueRuntime {
             QueryRequest::ViewAccount { account_id, .. } => Ok(QueryResponse {
                 kind: QueryResponseKind::ViewAccount(
                     Account::new(
-                        self.state.read().get(state_root).map_or_else(
-                            || 0,
+                        self.get_kv_state(state_root).map_or_else(
+                            |_| 0,
                             |state| *state.amounts.get(account_id).unwrap_or(&0),
                         ),
                         0,
@@ -1263,6 +2184,15 @@ impl RuntimeAdapter for KeyValueRuntime {
                 block_height,
                 block_hash: *block_hash,
             }),
+            QueryRequest::ViewStatePaged { .. } => Ok(QueryResponse {
+                kind: QueryResponseKind::ViewStatePaged(ViewStatePagedResult {
+                    values: Default::default(),
+                    next_key: None,
+                    proof: vec![],
+                }),
+                block_height,
+                block_hash: *block_hash,
+            }),
             QueryRequest::CallFunction { .. } => Ok(QueryResponse {
                 kind: QueryResponseKind::CallResult(CallResult {
                     result: Default::default(),
@@ -1271,6 +2201,13 @@ impl RuntimeAdapter for KeyValueRuntime {
                 block_height,
                 block_hash: *block_hash,
             }),
+            QueryRequest::DryRunTransaction { .. } => {
+                Err(near_chain_primitives::error::QueryError::InternalError {
+                    error_message: "dry run is not supported by KeyValueRuntime".to_string(),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
         }
     }
 
@@ -1281,17 +2218,29 @@ impl RuntimeAdapter for KeyValueRuntime {
         state_root: &StateRoot,
         part_id: PartId,
     ) -> Result<Vec<u8>, Error> {
-        if part_id.idx != 0 {
-            return Ok(vec![]);
-        }
-        let state = self.state.read().get(state_root).unwrap().clone();
+        let state = self.get_kv_state(state_root)?;
         let data = borsh::to_vec(&state).expect("should never fall");
-        Ok(data)
+        let part_hashes = kv_state_part_hashes(&data);
+        if part_id.total != part_hashes.len() as u64 {
+            return Err(Error::Other(format!(
+                "state has {} parts, requested {:?}",
+                part_hashes.len(),
+                part_id
+            )));
+        }
+        let data = data.chunks(KV_STATE_PART_SIZE).nth(part_id.idx as usize).unwrap().to_vec();
+        Ok(borsh::to_vec(&KVStatePart { part_hashes, data })?)
     }
 
-    fn validate_state_part(&self, _state_root: &StateRoot, _part_id: PartId, _data: &[u8]) -> bool {
-        // We do not care about deeper validation in test_utils
-        true
+    fn validate_state_part(&self, state_root: &StateRoot, part_id: PartId, data: &[u8]) -> bool {
+        let Ok(part) = KVStatePart::try_from_slice(data) else {
+            return false;
+        };
+        // The genesis state is stored under `Trie::EMPTY_ROOT` rather than
+        // its real root, so only the hash of the part is checked for it.
+        (*state_root == Trie::EMPTY_ROOT || kv_state_root(&part.part_hashes) == *state_root)
+            && part.part_hashes.len() as u64 == part_id.total
+            && part.part_hashes.get(part_id.idx as usize) == Some(&hash(&part.data))
     }
 
     fn apply_state_part(
@@ -1302,14 +2251,20 @@ impl RuntimeAdapter for KeyValueRuntime {
         data: &[u8],
         _epoch_id: &EpochId,
     ) -> Result<(), Error> {
-        if part_id.idx != 0 {
-            return Ok(());
-        }
-        let state = KVState::try_from_slice(data).unwrap();
-        self.state.write().insert(*state_root, state.clone());
-        let data = borsh::to_vec(&state)?;
-        let state_size = data.len() as u64;
-        self.state_size.write().insert(*state_root, state_size);
+        let part = KVStatePart::try_from_slice(data)?;
+        // The state can only be deserialized once all the parts are applied.
+        let data = {
+            let mut state_parts = self.state_parts.write();
+            let parts = state_parts.entry(*state_root).or_default();
+            parts.insert(part_id.idx, part.data);
+            if parts.len() as u64 != part_id.total {
+                return Ok(());
+            }
+            state_parts.remove(state_root).unwrap().into_values().concat()
+        };
+        let state = KVState::try_from_slice(&data)?;
+        self.state.write().insert(*state_root, state);
+        self.state_size.write().insert(*state_root, data.len() as u64);
         Ok(())
     }
 
@@ -1319,11 +2274,16 @@ impl RuntimeAdapter for KeyValueRuntime {
         _block_hash: &CryptoHash,
         state_root: &StateRoot,
     ) -> Result<StateRootNode, Error> {
-        let data = borsh::to_vec(&self.state.read().get(state_root).unwrap().clone())
-            .expect("should never fall")
-            .into();
-        let memory_usage = *self.state_size.read().get(state_root).unwrap();
-        Ok(StateRootNode { data, memory_usage })
+        let data: Vec<u8> =
+            borsh::to_vec(&self.get_kv_state(state_root)?).expect("should never fall");
+        // The memory usage determines the number of state parts, see
+        // `get_num_state_parts`. The sizes of the states in memtries aren't
+        // kept, they are the sizes of their data.
+        let state_size =
+            self.state_size.read().get(state_root).copied().unwrap_or(data.len() as u64);
+        let num_parts = (state_size as usize).div_ceil(KV_STATE_PART_SIZE) as u64;
+        let memory_usage = num_parts * STATE_PART_MEMORY_LIMIT.as_u64();
+        Ok(StateRootNode { data: data.into(), memory_usage })
     }
 
     fn validate_state_root_node(
@@ -1369,19 +2329,96 @@ impl RuntimeAdapter for KeyValueRuntime {
         }
     }
 
-    fn get_protocol_config(&self, _epoch_id: &EpochId) -> Result<ProtocolConfig, Error> {
-        Err(Error::Other("get_protocol_config should not be used in KeyValueRuntime".into()))
+    /// Returns a genesis config with the validators, protocol version and mock
+    /// epoch config of the epoch and the parameters of the chain genesis, if
+    /// given, along with the runtime config of the runtime.
+    fn get_protocol_config(&self, epoch_id: &EpochId) -> Result<ProtocolConfig, Error> {
+        let epoch_info = self.epoch_manager.get_epoch_info(epoch_id)?;
+        let validators = epoch_info
+            .validators_iter()
+            .map(|stake| {
+                let (account_id, public_key, amount) = stake.destructure();
+                AccountInfo { account_id, public_key, amount }
+            })
+            .collect();
+        let mut genesis_config = GenesisConfig {
+            protocol_version: epoch_info.protocol_version(),
+            genesis_height: 0,
+            validators,
+            ..Default::default()
+        };
+        if let Some(chain_genesis) = &self.chain_genesis {
+            genesis_config.chain_id = chain_genesis.chain_id.clone();
+            genesis_config.genesis_time = chrono::DateTime::from_timestamp_nanos(
+                chain_genesis.time.unix_timestamp_nanos() as i64,
+            );
+            genesis_config.genesis_height = chain_genesis.height;
+            genesis_config.gas_limit = chain_genesis.gas_limit;
+            genesis_config.min_gas_price = chain_genesis.min_gas_price;
+            genesis_config.max_gas_price = chain_genesis.max_gas_price;
+            genesis_config.total_supply = chain_genesis.total_supply;
+            genesis_config.gas_price_adjustment_rate = chain_genesis.gas_price_adjustment_rate;
+            genesis_config.transaction_validity_period = chain_genesis.transaction_validity_period;
+        }
+        set_epoch_config(&mut genesis_config, self.epoch_manager.get_epoch_config(epoch_id)?);
+        Ok(ProtocolConfig { genesis_config, runtime_config: self.runtime_config.clone() })
     }
 
     fn get_runtime_config(&self, _protocol_version: ProtocolVersion) -> &RuntimeConfig {
         &self.runtime_config
     }
 
-    fn will_shard_layout_change_next_epoch(
+    fn will_shard_layout_change_next_epoch(&self, parent_hash: &CryptoHash) -> Result<bool, Error> {
+        Ok(self.epoch_manager.will_shard_layout_change(parent_hash)?)
+    }
+
+    fn split_state(
         &self,
-        _parent_hash: &CryptoHash,
-    ) -> Result<bool, Error> {
-        Ok(false)
+        state_root: StateRoot,
+        boundary_account: &AccountId,
+    ) -> Result<Option<(StateRoot, StateRoot)>, Error> {
+        if self.memtries.is_some() {
+            return Err(Error::Other(
+                "shard layout changes are not supported with memtries".to_string(),
+            ));
+        }
+        let state = self.get_kv_state(&state_root)?;
+        let is_left = |account_id: &AccountId| account_id < boundary_account;
+        let split = |left: bool| KVState {
+            amounts: state
+                .amounts
+                .iter()
+                .filter(|(account_id, _)| is_left(account_id) == left)
+                .map(|(account_id, amount)| (account_id.clone(), *amount))
+                .collect(),
+            receipt_nonces: state.receipt_nonces.clone(),
+            tx_nonces: state
+                .tx_nonces
+                .iter()
+                .filter(|nonce| is_left(&nonce.0) == left)
+                .cloned()
+                .collect(),
+            delayed_receipts: state
+                .delayed_receipts
+                .iter()
+                .filter(|receipt| is_left(receipt.receiver_id()) == left)
+                .cloned()
+                .collect(),
+            buffered_receipts: state
+                .buffered_receipts
+                .iter()
+                .filter(|receipt| is_left(receipt.predecessor_id()) == left)
+                .cloned()
+                .collect(),
+        };
+        let insert = |state: KVState| -> Result<StateRoot, Error> {
+            let data = borsh::to_vec(&state)?;
+            let state_root = kv_state_root(&kv_state_part_hashes(&data));
+            self.state.write().insert(state_root, state);
+            self.state_size.write().insert(state_root, data.len() as u64);
+            Ok(state_root)
+        };
+        Ok(Some((insert(split(true))?, insert(split(false))?)))
     }
 
     fn compiled_contract_cache(&self) -> &dyn ContractRuntimeCache {
@@ -1397,3 +2434,952 @@ impl RuntimeAdapter for KeyValueRuntime {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BlockType;
+    use near_async::time::{Clock, Utc};
+    use near_crypto::{InMemorySigner, Signature};
+    use near_pool::{InsertTransactionResult, PoolIteratorWrapper, TransactionPool};
+    use near_primitives::bandwidth_scheduler::BlockBandwidthRequests;
+    use near_primitives::block::Block;
+    use near_primitives::errors::{ActionError, ActionErrorKind};
+    use near_primitives::genesis::{genesis_block, genesis_chunks};
+    use near_primitives::receipt::ReceiptPriority;
+    use near_primitives::state::PartialState;
+    use near_primitives::state_sync::get_num_state_parts;
+    use near_primitives::test_utils::{TestBlockBuilder, create_test_signer};
+    use near_primitives::types::validator_stake::ValidatorStakeIter;
+    use near_store::test_utils::{create_test_store, test_populate_trie};
+    use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+    fn runtime() -> Arc<KeyValueRuntime> {
+        let accounts = (0..8).map(|i| format!("test{i}").parse().unwrap()).collect();
+        let vs = ValidatorSchedule::new().block_producers_per_epoch(vec![accounts]);
+        let store = create_test_store();
+        let epoch_manager = MockEpochManager::new_with_validators(store.clone(), vs, 5);
+        KeyValueRuntime::new(store, &epoch_manager)
+    }
+
+    #[test]
+    fn test_state_parts() {
+        let source = runtime();
+        // Store the genesis state under its real root.
+        let state = source.state.read().get(&Trie::EMPTY_ROOT).unwrap().clone();
+        let data = borsh::to_vec(&state).unwrap();
+        let state_root = kv_state_root(&kv_state_part_hashes(&data));
+        source.state.write().insert(state_root, state);
+        source.state_size.write().insert(state_root, data.len() as u64);
+
+        let shard_id = ShardId::new(0);
+        let block_hash = CryptoHash::default();
+        let root_node = source.get_state_root_node(shard_id, &block_hash, &state_root).unwrap();
+        let num_parts = get_num_state_parts(root_node.memory_usage);
+        assert!(num_parts > 1);
+        let parts = (0..num_parts)
+            .map(|idx| {
+                let part_id = PartId::new(idx, num_parts);
+                let part =
+                    source.obtain_state_part(shard_id, &block_hash, &state_root, part_id).unwrap();
+                assert!(source.validate_state_part(&state_root, part_id, &part));
+                part
+            })
+            .collect_vec();
+        assert!(!source.validate_state_part(&state_root, PartId::new(1, num_parts), &parts[0]));
+        assert!(!source.validate_state_part(
+            &CryptoHash::hash_bytes(b"x"),
+            PartId::new(0, num_parts),
+            &parts[0]
+        ));
+        let mut tampered = KVStatePart::try_from_slice(&parts[0]).unwrap();
+        tampered.data[0] ^= 1;
+        let tampered = borsh::to_vec(&tampered).unwrap();
+        assert!(!source.validate_state_part(&state_root, PartId::new(0, num_parts), &tampered));
+
+        // The state is complete once all the parts are applied, in any order.
+        let target = runtime();
+        for (idx, part) in parts.iter().enumerate().rev() {
+            assert!(!target.state.read().contains_key(&state_root));
+            let part_id = PartId::new(idx as u64, num_parts);
+            target
+                .apply_state_part(shard_id, &state_root, part_id, part, &EpochId::default())
+                .unwrap();
+        }
+        let synced = target.get_state_root_node(shard_id, &block_hash, &state_root).unwrap();
+        assert_eq!(synced, root_node);
+    }
+
+    #[test]
+    fn test_protocol_config() {
+        let runtime = runtime();
+        let protocol_config = runtime.get_protocol_config(&EpochId::default()).unwrap();
+        let genesis_config = &protocol_config.genesis_config;
+        assert_eq!(genesis_config.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(genesis_config.epoch_length, 5);
+        assert_eq!(
+            genesis_config.shard_layout,
+            runtime.epoch_manager.get_shard_layout(&EpochId::default()).unwrap()
+        );
+        let validators =
+            genesis_config.validators.iter().map(|info| info.account_id.as_str()).collect_vec();
+        assert_eq!(validators, (0..8).map(|i| format!("test{i}")).collect_vec());
+        assert_eq!(protocol_config.runtime_config, RuntimeConfig::test());
+
+        // The parameters of the chain genesis are reported when it's given.
+        let chain_genesis = ChainGenesis {
+            time: Utc::from_unix_timestamp(1_000_000).unwrap(),
+            height: 0,
+            gas_limit: 1_000_000,
+            min_gas_price: 100,
+            max_gas_price: 1_000_000_000,
+            total_supply: 1_000_000_000,
+            gas_price_adjustment_rate: num_rational::Rational32::new(1, 100),
+            transaction_validity_period: 20,
+            epoch_length: 5,
+            protocol_version: PROTOCOL_VERSION,
+            chain_id: "kv".to_string(),
+        };
+        let runtime = KeyValueRuntime::new_with_options(
+            create_test_store(),
+            &runtime.epoch_manager,
+            KeyValueRuntimeOptions {
+                chain_genesis: Some(chain_genesis.clone()),
+                ..Default::default()
+            },
+        );
+        let genesis_config =
+            runtime.get_protocol_config(&EpochId::default()).unwrap().genesis_config;
+        assert_eq!(genesis_config.chain_id, chain_genesis.chain_id);
+        assert_eq!(genesis_config.genesis_time.timestamp(), 1_000_000);
+        assert_eq!(genesis_config.gas_limit, chain_genesis.gas_limit);
+        assert_eq!(genesis_config.min_gas_price, chain_genesis.min_gas_price);
+        assert_eq!(genesis_config.max_gas_price, chain_genesis.max_gas_price);
+        assert_eq!(genesis_config.total_supply, chain_genesis.total_supply);
+        assert_eq!(
+            genesis_config.gas_price_adjustment_rate,
+            chain_genesis.gas_price_adjustment_rate
+        );
+        assert_eq!(
+            genesis_config.transaction_validity_period,
+            chain_genesis.transaction_validity_period
+        );
+    }
+
+    #[test]
+    fn test_memtrie_state_roots() {
+        let accounts = (0..4).map(|i| format!("test{i}").parse().unwrap()).collect();
+        let vs = ValidatorSchedule::new().block_producers_per_epoch(vec![accounts]);
+        let store = create_test_store();
+        let epoch_manager = MockEpochManager::new_with_validators(store.clone(), vs, 5);
+        let runtime = KeyValueRuntime::new_with_options(
+            store,
+            &epoch_manager,
+            KeyValueRuntimeOptions { memtrie: true, ..Default::default() },
+        );
+        let memtries = runtime.memtries.as_ref().unwrap();
+        let shard_uid = ShardUId::single_shard();
+
+        let expected_root = |state: &KVState| {
+            let entries = kv_state_trie_entries(state)
+                .into_iter()
+                .map(|(key, value)| (key, Some(value)))
+                .collect();
+            let tries = TestTriesBuilder::new().build();
+            test_populate_trie(&tries, &Trie::EMPTY_ROOT, shard_uid, entries)
+        };
+        let state_bytes = |state: &KVState| borsh::to_vec(state).unwrap();
+
+        // The genesis state is written entirely on top of the empty root, and
+        // the state root is the one of a trie holding the state. The state is
+        // read back from the memtrie.
+        let genesis_state = runtime.get_kv_state(&Trie::EMPTY_ROOT).unwrap();
+        let (state_root, _) = runtime
+            .update_memtrie(memtries, shard_uid, Trie::EMPTY_ROOT, &genesis_state, 1)
+            .unwrap();
+        assert_eq!(state_root, expected_root(&genesis_state));
+        assert!(runtime.state.read().get(&state_root).is_none());
+        assert_eq!(
+            state_bytes(&runtime.get_kv_state(&state_root).unwrap()),
+            state_bytes(&genesis_state)
+        );
+
+        // Only the nodes on the paths of the updated entries are in the proof.
+        let mut state = genesis_state;
+        let account_id: AccountId = "test0".parse().unwrap();
+        *state.amounts.get_mut(&account_id).unwrap() -= 10;
+        state.tx_nonces.insert(AccountNonce(account_id.clone(), 1));
+        state.delayed_receipts = (0..3)
+            .map(|i| Receipt::new_balance_refund(&account_id, i, ReceiptPriority::NoPriority))
+            .collect();
+        let (new_state_root, storage_proof) =
+            runtime.update_memtrie(memtries, shard_uid, state_root, &state, 2).unwrap();
+        assert_eq!(new_state_root, expected_root(&state));
+        let PartialState::TrieValues(nodes) = storage_proof.nodes;
+        assert!(!nodes.is_empty());
+        assert_eq!(
+            state_bytes(&runtime.get_kv_state(&new_state_root).unwrap()),
+            state_bytes(&state)
+        );
+
+        // Writing the same state again gives the same root.
+        let (same_state_root, _) =
+            runtime.update_memtrie(memtries, shard_uid, state_root, &state, 2).unwrap();
+        assert_eq!(same_state_root, new_state_root);
+
+        // The processed receipts are deleted from the trie.
+        state.delayed_receipts.remove(0);
+        let (next_state_root, _) =
+            runtime.update_memtrie(memtries, shard_uid, new_state_root, &state, 3).unwrap();
+        assert_eq!(next_state_root, expected_root(&state));
+        assert_eq!(
+            state_bytes(&runtime.get_kv_state(&next_state_root).unwrap()),
+            state_bytes(&state)
+        );
+
+        // The roots below the GC stop height are deleted, and reading them
+        // fails.
+        let gc_stop_height = 3;
+        let height = gc_stop_height + DEFAULT_GC_NUM_EPOCHS_TO_KEEP * 5;
+        runtime.update_memtrie(memtries, shard_uid, next_state_root, &state, height).unwrap();
+        assert!(runtime.get_kv_state(&state_root).is_err());
+        assert!(runtime.get_kv_state(&new_state_root).is_err());
+        assert!(runtime.get_kv_state(&next_state_root).is_ok());
+        assert_eq!(memtries.lock()[&shard_uid].num_roots(), 2);
+    }
+
+    /// Genesis block, known to the epoch manager.
+    fn genesis(epoch_manager: &MockEpochManager) -> Block {
+        let shard_layout = epoch_manager.get_shard_layout(&EpochId::default()).unwrap();
+        let shard_ids = shard_layout.shard_ids().collect_vec();
+        let genesis_chunks = genesis_chunks(
+            vec![Trie::EMPTY_ROOT; shard_ids.len()],
+            vec![Default::default(); shard_ids.len()],
+            &shard_ids,
+            1_000_000,
+            0,
+            PROTOCOL_VERSION,
+        );
+        let genesis = genesis_block(
+            PROTOCOL_VERSION,
+            genesis_chunks.into_iter().map(|chunk| chunk.take_header()).collect(),
+            Utc::now_utc(),
+            0,
+            100,
+            1_000_000_000,
+            &[],
+        );
+        epoch_manager.headers_cache.write().insert(*genesis.hash(), genesis.header().clone());
+        genesis
+    }
+
+    /// Block at the height following `prev`, known to the epoch manager. The
+    /// blocks at consecutive heights are final after two more blocks.
+    fn next_block(epoch_manager: &MockEpochManager, prev: &Block) -> Block {
+        let signer = Arc::new(create_test_signer("test0"));
+        let block = TestBlockBuilder::new(Clock::real(), prev, signer).build();
+        epoch_manager.headers_cache.write().insert(*block.hash(), block.header().clone());
+        block
+    }
+
+    #[test]
+    fn test_staking_proposals() {
+        let account = |name: &str| -> AccountId { name.parse().unwrap() };
+        let stake = |name: &str, amount: Balance| {
+            ValidatorStake::new(
+                account(name),
+                SecretKey::from_seed(KeyType::ED25519, name).public_key(),
+                amount,
+            )
+        };
+        let vs = ValidatorSchedule::new()
+            .block_producers_per_epoch(vec![vec![
+                account("test0"),
+                account("test1"),
+                account("test2"),
+            ]])
+            .staking_proposals(true);
+        let epoch_manager = MockEpochManager::new_with_validators(create_test_store(), vs, 5);
+
+        let mut blocks = vec![genesis(&epoch_manager)];
+        let mut block_producers_by_valset = vec![];
+        for height in 1..=20 {
+            let block = next_block(&epoch_manager, blocks.last().unwrap());
+            let header = block.header();
+            let epoch_id = epoch_manager.get_epoch_id_from_prev_block(header.prev_hash()).unwrap();
+            let valset = epoch_manager.get_valset_for_epoch(&epoch_id).unwrap();
+            if block_producers_by_valset.len() == valset {
+                let block_producers = epoch_manager
+                    .get_epoch_block_producers_ordered(&epoch_id)
+                    .unwrap()
+                    .into_iter()
+                    .map(|stake| stake.take_account_id().to_string())
+                    .collect_vec();
+                block_producers_by_valset.push(block_producers);
+            }
+            let proposals =
+                if height == 2 { vec![stake("test3", 500), stake("test1", 0)] } else { vec![] };
+            let producer = epoch_manager.get_block_producer(&epoch_id, height).unwrap();
+            // test2 misses all its blocks of the second epoch.
+            if valset == 1 && producer == account("test2") {
+                blocks.push(block);
+                continue;
+            }
+            let block_info = BlockInfo::new(
+                *header.hash(),
+                height,
+                0,
+                CryptoHash::default(),
+                *header.prev_hash(),
+                proposals,
+                vec![],
+                0,
+                PROTOCOL_VERSION,
+                0,
+                None,
+            );
+            epoch_manager.add_validator_proposals(block_info, CryptoHash::default()).unwrap();
+            blocks.push(block);
+        }
+
+        // The proposals of the first epoch take effect in the third one, and
+        // test2 is kicked out of the fourth one.
+        assert_eq!(
+            block_producers_by_valset,
+            [
+                vec!["test0", "test1", "test2"],
+                vec!["test0", "test1", "test2"],
+                vec!["test0", "test2", "test3"],
+                vec!["test0", "test3"],
+            ]
+        );
+        let epoch_id = epoch_manager.get_epoch_id_from_prev_block(blocks[15].hash()).unwrap();
+        assert_eq!(
+            epoch_manager.get_validator_by_account_id(&epoch_id, &account("test3")).unwrap(),
+            stake("test3", 500)
+        );
+        assert!(epoch_manager.get_validator_by_account_id(&epoch_id, &account("test1")).is_err());
+
+        // The epoch info has the validators of its epoch.
+        let epoch_info = epoch_manager.get_epoch_info(&epoch_id).unwrap();
+        let block_producers = epoch_info
+            .block_producers_settlement()
+            .iter()
+            .map(|&id| epoch_info.get_validator(id).take_account_id())
+            .collect_vec();
+        assert_eq!(block_producers, [account("test0"), account("test3")]);
+        assert_eq!(
+            epoch_info.get_validator_by_account(&account("test3")),
+            Some(stake("test3", 500))
+        );
+        assert!(epoch_info.get_validator_by_account(&account("test1")).is_none());
+    }
+
+    #[test]
+    fn test_protocol_version_schedule() {
+        let vs = ValidatorSchedule::new()
+            .block_producers_per_epoch(vec![
+                vec!["test0".parse().unwrap()],
+                vec!["test1".parse().unwrap()],
+            ])
+            .protocol_version_per_epoch(vec![PROTOCOL_VERSION - 1, PROTOCOL_VERSION]);
+        let epoch_manager = MockEpochManager::new_with_validators(create_test_store(), vs, 5);
+        assert_eq!(
+            epoch_manager.get_epoch_protocol_version(&EpochId::default()).unwrap(),
+            PROTOCOL_VERSION - 1
+        );
+
+        let mut blocks = vec![genesis(&epoch_manager)];
+        for _ in 1..=20 {
+            blocks.push(next_block(&epoch_manager, blocks.last().unwrap()));
+        }
+        // The epochs start at the blocks 1, 6, 11 and 16, and the schedule
+        // loops back to the first version in the third epoch.
+        for height in 1..=20 {
+            let prev_hash = blocks[height - 1].hash();
+            let epoch_id = epoch_manager.get_epoch_id_from_prev_block(prev_hash).unwrap();
+            let expected =
+                if (height - 1) / 5 % 2 == 0 { PROTOCOL_VERSION - 1 } else { PROTOCOL_VERSION };
+            assert_eq!(epoch_manager.get_epoch_protocol_version(&epoch_id).unwrap(), expected);
+            assert_eq!(
+                epoch_manager.get_epoch_info(&epoch_id).unwrap().protocol_version(),
+                expected
+            );
+        }
+
+        // The votes in the second epoch announce the version of the third one.
+        let votes = epoch_manager.get_protocol_version_votes(blocks[7].hash()).unwrap();
+        assert_eq!(votes.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(votes.next_epoch_protocol_version, PROTOCOL_VERSION - 1);
+
+        let epoch_manager = MockEpochManager::new(create_test_store(), 5);
+        let mut blocks = vec![genesis(&epoch_manager)];
+        for _ in 1..=10 {
+            blocks.push(next_block(&epoch_manager, blocks.last().unwrap()));
+        }
+        for prev_block in &blocks {
+            let epoch_id = epoch_manager.get_epoch_id_from_prev_block(prev_block.hash()).unwrap();
+            assert_eq!(
+                epoch_manager.get_epoch_protocol_version(&epoch_id).unwrap(),
+                PROTOCOL_VERSION
+            );
+        }
+    }
+
+    #[test]
+    fn test_shard_layout_change() {
+        let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+        let shard_layout = ShardLayout::multi_shard_custom(vec!["test2".parse().unwrap()], 3);
+        let next_shard_layout =
+            ShardLayout::derive_shard_layout(&shard_layout, "test5".parse().unwrap());
+        let vs = ValidatorSchedule::new()
+            .block_producers_per_epoch(vec![accounts.clone(), accounts])
+            .protocol_version_per_epoch(vec![PROTOCOL_VERSION - 1, PROTOCOL_VERSION])
+            .shard_layout_per_epoch(vec![shard_layout.clone(), next_shard_layout.clone()]);
+        let store = create_test_store();
+        let epoch_manager = MockEpochManager::new_with_validators(store.clone(), vs, 5);
+        let runtime = KeyValueRuntime::new(store, &epoch_manager);
+
+        for (protocol_version, expected) in [
+            (PROTOCOL_VERSION - 2, &shard_layout),
+            (PROTOCOL_VERSION - 1, &shard_layout),
+            (PROTOCOL_VERSION, &next_shard_layout),
+        ] {
+            assert_eq!(
+                &epoch_manager.get_shard_layout_from_protocol_version(protocol_version),
+                expected
+            );
+            assert_eq!(&runtime.get_shard_layout(protocol_version), expected);
+        }
+
+        let mut blocks = vec![genesis(&epoch_manager)];
+        for _ in 1..=20 {
+            blocks.push(next_block(&epoch_manager, blocks.last().unwrap()));
+        }
+        // Block 6 is the first block of the second epoch, and the schedule
+        // doesn't loop back to the first layout in the later epochs.
+        for height in 1..=20 {
+            let prev_hash = blocks[height - 1].hash();
+            let expected =
+                if height < 6 { shard_layout.clone() } else { next_shard_layout.clone() };
+            assert_eq!(
+                epoch_manager.get_shard_layout_from_prev_block(prev_hash).unwrap(),
+                expected
+            );
+            assert_eq!(runtime.will_shard_layout_change_next_epoch(prev_hash).unwrap(), height < 6,);
+        }
+
+        // The parents of the new shards are in the last block of the first epoch.
+        let shard_ids = next_shard_layout.shard_ids().collect_vec();
+        let prev_shard_ids = shard_ids
+            .iter()
+            .map(|&shard_id| {
+                let parent_shard_id = next_shard_layout.get_parent_shard_id(shard_id).unwrap();
+                (parent_shard_id, shard_layout.get_shard_index(parent_shard_id).unwrap())
+            })
+            .collect_vec();
+        assert_ne!(prev_shard_ids.iter().map(|(shard_id, _)| *shard_id).collect_vec(), shard_ids);
+        assert_eq!(
+            epoch_manager.get_prev_shard_ids(blocks[5].hash(), shard_ids.clone()).unwrap(),
+            prev_shard_ids
+        );
+        let same_shard_ids = shard_ids
+            .iter()
+            .map(|&shard_id| (shard_id, next_shard_layout.get_shard_index(shard_id).unwrap()))
+            .collect_vec();
+        assert_eq!(
+            epoch_manager.get_prev_shard_ids(blocks[6].hash(), shard_ids.clone()).unwrap(),
+            same_shard_ids
+        );
+        assert_eq!(
+            epoch_manager.get_prev_shard_ids(blocks[16].hash(), shard_ids).unwrap(),
+            same_shard_ids
+        );
+    }
+
+    #[test]
+    fn test_split_state() {
+        let runtime = runtime();
+        let account = |name: &str| -> AccountId { name.parse().unwrap() };
+        let receipt = |receiver: &str| {
+            Receipt::new_balance_refund(&account(receiver), 1, ReceiptPriority::NoPriority)
+        };
+        let state = KVState {
+            amounts: (0..10).map(|i| (account(&format!("test{i}")), i)).collect(),
+            receipt_nonces: HashSet::from([CryptoHash::hash_bytes(b"receipt")]),
+            tx_nonces: HashSet::from([
+                AccountNonce(account("test1"), 1),
+                AccountNonce(account("test7"), 1),
+            ]),
+            delayed_receipts: vec![receipt("test2"), receipt("test8")],
+            buffered_receipts: vec![],
+        };
+        let data = borsh::to_vec(&state).unwrap();
+        let state_root = kv_state_root(&kv_state_part_hashes(&data));
+        runtime.state.write().insert(state_root, state);
+
+        let (left, right) = runtime.split_state(state_root, &account("test5")).unwrap().unwrap();
+        let left = runtime.get_kv_state(&left).unwrap();
+        let right = runtime.get_kv_state(&right).unwrap();
+        let accounts = |state: &KVState| state.amounts.keys().cloned().sorted().collect_vec();
+        assert_eq!(accounts(&left), (0..5).map(|i| account(&format!("test{i}"))).collect_vec());
+        assert_eq!(accounts(&right), (5..10).map(|i| account(&format!("test{i}"))).collect_vec());
+        assert_eq!(left.tx_nonces, HashSet::from([AccountNonce(account("test1"), 1)]));
+        assert_eq!(right.tx_nonces, HashSet::from([AccountNonce(account("test7"), 1)]));
+        assert_eq!(left.delayed_receipts, vec![receipt("test2")]);
+        assert_eq!(right.delayed_receipts, vec![receipt("test8")]);
+        assert_eq!(left.receipt_nonces, right.receipt_nonces);
+    }
+
+    #[test]
+    fn test_chunk_validator_assignments() {
+        let account = |name: &str| -> AccountId { name.parse().unwrap() };
+        let accounts = (0..4).map(|i| account(&format!("test{i}"))).collect_vec();
+        let vs = ValidatorSchedule::new_with_shards(2)
+            .block_producers_per_epoch(vec![accounts.clone(), accounts])
+            .chunk_validators_per_epoch_per_shard(vec![
+                vec![
+                    vec![(account("test0"), 100), (account("test1"), 300)],
+                    vec![(account("test2"), 100), (account("test3"), 100)],
+                ],
+                vec![vec![(account("test3"), 100)], vec![(account("test0"), 100)]],
+            ]);
+        let epoch_manager = MockEpochManager::new_with_validators(create_test_store(), vs, 5);
+
+        let mut blocks = vec![genesis(&epoch_manager)];
+        for _ in 1..=6 {
+            blocks.push(next_block(&epoch_manager, blocks.last().unwrap()));
+        }
+        let assignments = |prev_block: &Block, shard_id: u64| {
+            let epoch_id = epoch_manager.get_epoch_id_from_prev_block(prev_block.hash()).unwrap();
+            epoch_manager
+                .get_chunk_validator_assignments(&epoch_id, ShardId::new(shard_id), 1)
+                .unwrap()
+        };
+        let endorse = |assignments: &Arc<ChunkValidatorAssignments>, names: &[&str]| {
+            let accounts = names.iter().map(|name| account(name)).collect_vec();
+            let signatures = accounts.iter().map(|account_id| (account_id, Signature::default()));
+            assignments.compute_endorsement_state(signatures.collect()).is_endorsed
+        };
+
+        // Only the validators assigned to the shard count, by their stakes.
+        let shard0 = assignments(&blocks[0], 0);
+        assert_eq!(shard0.ordered_chunk_validators(), vec![account("test0"), account("test1")]);
+        assert!(endorse(&shard0, &["test1"]));
+        assert!(!endorse(&shard0, &["test0"]));
+        assert!(!endorse(&shard0, &["test0", "test2", "test3"]));
+        let shard1 = assignments(&blocks[0], 1);
+        assert!(!endorse(&shard1, &["test2"]));
+        assert!(endorse(&shard1, &["test2", "test3"]));
+
+        // Block 6 is the first block of the second epoch.
+        let shard0 = assignments(&blocks[5], 0);
+        assert_eq!(shard0.ordered_chunk_validators(), vec![account("test3")]);
+        assert!(!endorse(&shard0, &[]));
+        assert!(endorse(&assignments(&blocks[5], 1), &["test0"]));
+    }
+
+    #[test]
+    fn test_congestion_model() {
+        let account = |name: &str| -> AccountId { name.parse().unwrap() };
+        let accounts = (0..4).map(|i| account(&format!("test{i}"))).collect_vec();
+        let vs =
+            ValidatorSchedule::new_with_shards(2).block_producers_per_epoch(vec![accounts.clone()]);
+        let store = create_test_store();
+        let epoch_manager = MockEpochManager::new_with_validators(store.clone(), vs, 5);
+        let genesis = genesis(&epoch_manager);
+        let shard_layout = epoch_manager.get_shard_layout(&EpochId::default()).unwrap();
+        let sender = account("test0");
+        let sender_shard_id = shard_layout.account_id_to_shard_id(&sender);
+        let receiver = accounts
+            .into_iter()
+            .find(|account_id| shard_layout.account_id_to_shard_id(account_id) != sender_shard_id)
+            .unwrap();
+        let receiver_shard_id = shard_layout.account_id_to_shard_id(&receiver);
+
+        let config = RuntimeConfig::test().congestion_control_config;
+        let backlog = KvCongestionBacklog {
+            delayed_receipts_gas: config.max_congestion_incoming_gas,
+            buffered_receipts_gas: 0,
+        };
+        let runtime = KeyValueRuntime::new_with_options(
+            store,
+            &epoch_manager,
+            KeyValueRuntimeOptions {
+                transfer_gas: 1,
+                congestion_model: Some(KvCongestionModel::Fixed(HashMap::from([(
+                    receiver_shard_id,
+                    backlog,
+                )]))),
+                ..Default::default()
+            },
+        );
+        // At even heights, the receiving shard only allows itself to send it
+        // receipts.
+        let congested = runtime.get_congestion_info(&shard_layout, receiver_shard_id, 2).unwrap();
+        assert_eq!(congested.delayed_receipts_gas(), config.max_congestion_incoming_gas as u128);
+        assert_eq!(ShardId::from(congested.allowed_shard()), receiver_shard_id);
+        let congested = ExtendedCongestionInfo::new(congested, 0);
+        let uncongested = ExtendedCongestionInfo::new(CongestionInfo::default(), 0);
+
+        // The transactions to the congested shard are rejected.
+        let signer = InMemorySigner::from_seed(sender.clone(), KeyType::ED25519, sender.as_str());
+        let tx = SignedTransaction::send_money(
+            1,
+            sender,
+            receiver.clone(),
+            &signer,
+            10,
+            *genesis.hash(),
+        );
+        let Err((err, _)) =
+            runtime.validate_tx(&shard_layout, tx.clone(), PROTOCOL_VERSION, Some(congested))
+        else {
+            panic!("the transaction to the congested shard should be rejected");
+        };
+        assert!(matches!(err, InvalidTxError::ShardCongested { .. }));
+        assert!(
+            runtime
+                .validate_tx(&shard_layout, tx.clone(), PROTOCOL_VERSION, Some(uncongested))
+                .is_ok()
+        );
+
+        let apply = |state_root,
+                     height: BlockHeight,
+                     receiver_congestion_info,
+                     transactions: Vec<_>| {
+            let block = ApplyChunkBlockContext {
+                block_type: BlockType::Normal,
+                height,
+                block_hash: CryptoHash::hash_bytes(&height.to_le_bytes()),
+                prev_block_hash: *genesis.hash(),
+                block_timestamp: 0,
+                gas_price: 100,
+                random_seed: CryptoHash::default(),
+                congestion_info: BlockCongestionInfo::new(BTreeMap::from([
+                    (sender_shard_id, uncongested),
+                    (receiver_shard_id, receiver_congestion_info),
+                ])),
+                bandwidth_requests: BlockBandwidthRequests::empty(),
+            };
+            let shard = ApplyChunkShardContext {
+                shard_id: sender_shard_id,
+                last_validator_proposals: ValidatorStakeIter::empty(),
+                gas_limit: 1_000_000,
+                is_new_chunk: true,
+            };
+            let num_transactions = transactions.len();
+            runtime
+                .apply_chunk(
+                    RuntimeStorageConfig::new(state_root, false),
+                    ApplyChunkReason::UpdateTrackedShard,
+                    shard,
+                    block,
+                    &[],
+                    SignedValidPeriodTransactions::new(transactions, vec![true; num_transactions]),
+                )
+                .unwrap()
+        };
+
+        // The receipt of a transfer is buffered while the receiving shard is
+        // congested, and forwarded once it isn't anymore.
+        let result = apply(Trie::EMPTY_ROOT, 1, congested, vec![tx]);
+        assert!(result.outgoing_receipts.is_empty());
+        assert_eq!(
+            result.congestion_info,
+            Some(runtime.get_congestion_info(&shard_layout, sender_shard_id, 1).unwrap())
+        );
+        let result = apply(result.new_root, 2, congested, vec![]);
+        assert!(result.outgoing_receipts.is_empty());
+        let result = apply(result.new_root, 3, uncongested, vec![]);
+        assert_eq!(result.outgoing_receipts.len(), 1);
+        assert_eq!(result.outgoing_receipts[0].receiver_id(), &receiver);
+        let result = apply(result.new_root, 4, uncongested, vec![]);
+        assert!(result.outgoing_receipts.is_empty());
+    }
+
+    #[test]
+    fn test_receipt_processor() {
+        let account = |name: &str| -> AccountId { name.parse().unwrap() };
+        let accounts = (0..4).map(|i| account(&format!("test{i}"))).collect_vec();
+        let vs = ValidatorSchedule::new_with_shards(2).block_producers_per_epoch(vec![accounts]);
+        let store = create_test_store();
+        let epoch_manager = MockEpochManager::new_with_validators(store.clone(), vs, 5);
+        let genesis = genesis(&epoch_manager);
+        let shard_layout = epoch_manager.get_shard_layout(&EpochId::default()).unwrap();
+        let shard_id = shard_layout.account_id_to_shard_id(&account("test0"));
+        // The deposits of the receipts from another shard were already debited.
+        let (receivers, senders): (Vec<_>, Vec<_>) = (0..20)
+            .map(|i| account(&format!("user{i}")))
+            .partition(|account_id| shard_layout.account_id_to_shard_id(account_id) == shard_id);
+        let [succeeded, failed, delayed] = [0, 1, 2].map(|i| receivers[i].clone());
+        let transfer = |receiver_id: &AccountId, deposit: Balance| {
+            Receipt::V0(ReceiptV0 {
+                predecessor_id: senders[0].clone(),
+                receiver_id: receiver_id.clone(),
+                receipt_id: CryptoHash::hash_bytes(receiver_id.as_bytes()),
+                receipt: ReceiptEnum::Action(ActionReceipt {
+                    signer_id: senders[0].clone(),
+                    signer_public_key: PublicKey::empty(KeyType::ED25519),
+                    gas_price: 100,
+                    output_data_receivers: vec![],
+                    input_data_ids: vec![],
+                    actions: vec![Action::Transfer(TransferAction { deposit })],
+                }),
+            })
+        };
+        let failure = ExecutionStatus::Failure(
+            ActionError {
+                index: Some(0),
+                kind: ActionErrorKind::AccountDoesNotExist { account_id: failed.clone() },
+            }
+            .into(),
+        );
+
+        let delay = Arc::new(AtomicBool::new(true));
+        let runtime = KeyValueRuntime::new_with_receipt_processor(store, &epoch_manager, {
+            let (failed, delayed, failure, delay) =
+                (failed.clone(), delayed.clone(), failure.clone(), delay.clone());
+            move |receipt: &Receipt| {
+                if receipt.receiver_id() == &failed {
+                    KvReceiptOutcome::Outcome { status: failure.clone(), gas_burnt: 5 }
+                } else if receipt.receiver_id() == &delayed && delay.load(AtomicOrdering::Relaxed) {
+                    KvReceiptOutcome::Delay
+                } else {
+                    KvReceiptOutcome::Default
+                }
+            }
+        });
+        let apply = |state_root, height: BlockHeight, receipts: &[Receipt]| {
+            let block = ApplyChunkBlockContext {
+                block_type: BlockType::Normal,
+                height,
+                block_hash: CryptoHash::hash_bytes(&height.to_le_bytes()),
+                prev_block_hash: *genesis.hash(),
+                block_timestamp: 0,
+                gas_price: 100,
+                random_seed: CryptoHash::default(),
+                congestion_info: BlockCongestionInfo::default(),
+                bandwidth_requests: BlockBandwidthRequests::empty(),
+            };
+            let shard = ApplyChunkShardContext {
+                shard_id,
+                last_validator_proposals: ValidatorStakeIter::empty(),
+                gas_limit: 1_000_000,
+                is_new_chunk: true,
+            };
+            runtime
+                .apply_chunk(
+                    RuntimeStorageConfig::new(state_root, false),
+                    ApplyChunkReason::UpdateTrackedShard,
+                    shard,
+                    block,
+                    receipts,
+                    SignedValidPeriodTransactions::new(vec![], vec![]),
+                )
+                .unwrap()
+        };
+
+        // Only the default outcome credits the deposit. The failed receipt
+        // burns the gas of its outcome and its deposit is lost.
+        let receipts = [transfer(&succeeded, 10), transfer(&failed, 20), transfer(&delayed, 30)];
+        let result = apply(Trie::EMPTY_ROOT, 1, &receipts);
+        let state = runtime.get_kv_state(&result.new_root).unwrap();
+        assert_eq!(state.amounts.get(&succeeded), Some(&10));
+        assert_eq!(state.amounts.get(&failed), None);
+        assert_eq!(state.amounts.get(&delayed), None);
+        let [outcome] = result.outcomes.as_slice() else {
+            panic!("expected only the outcome of the failed receipt");
+        };
+        assert_eq!(outcome.id, receipts[1].get_hash());
+        assert_eq!(outcome.outcome.status, failure);
+        assert_eq!(outcome.outcome.gas_burnt, 5);
+        assert_eq!(result.total_gas_burnt, 5);
+        assert_eq!(result.total_balance_burnt, 500);
+        assert_eq!(state.delayed_receipts, vec![receipts[2].clone()]);
+        assert!(result.processed_delayed_receipts.is_empty());
+
+        // The delayed receipt is asked about again in the next chunk.
+        delay.store(false, AtomicOrdering::Relaxed);
+        let result = apply(result.new_root, 2, &[]);
+        let state = runtime.get_kv_state(&result.new_root).unwrap();
+        assert_eq!(state.amounts.get(&delayed), Some(&30));
+        assert!(state.delayed_receipts.is_empty());
+        assert_eq!(result.processed_delayed_receipts, vec![receipts[2].clone()]);
+    }
+
+    #[test]
+    fn test_transfer_gas() {
+        let account = |name: &str| -> AccountId { name.parse().unwrap() };
+        let accounts = (0..4).map(|i| account(&format!("test{i}"))).collect_vec();
+        let vs = ValidatorSchedule::new_with_shards(2).block_producers_per_epoch(vec![accounts]);
+        let store = create_test_store();
+        let epoch_manager = MockEpochManager::new_with_validators(store.clone(), vs, 5);
+        let genesis = genesis(&epoch_manager);
+        let shard_layout = epoch_manager.get_shard_layout(&EpochId::default()).unwrap();
+        let sender = account("test0");
+        let shard_id = shard_layout.account_id_to_shard_id(&sender);
+        let (local, remote): (Vec<_>, Vec<_>) = (0..20)
+            .map(|i| account(&format!("user{i}")))
+            .partition(|account_id| shard_layout.account_id_to_shard_id(account_id) == shard_id);
+        let runtime = KeyValueRuntime::new_with_options(
+            store,
+            &epoch_manager,
+            KeyValueRuntimeOptions { transfer_gas: 10, ..Default::default() },
+        );
+        let signer = InMemorySigner::from_seed(sender.clone(), KeyType::ED25519, sender.as_str());
+        let send_money = |nonce, receiver_id: &AccountId| {
+            SignedTransaction::send_money(
+                nonce,
+                sender.clone(),
+                receiver_id.clone(),
+                &signer,
+                100,
+                *genesis.hash(),
+            )
+        };
+        let apply = |state_root,
+                     height: BlockHeight,
+                     receipts: &[Receipt],
+                     transactions: Vec<_>| {
+            let block = ApplyChunkBlockContext {
+                block_type: BlockType::Normal,
+                height,
+                block_hash: CryptoHash::hash_bytes(&height.to_le_bytes()),
+                prev_block_hash: *genesis.hash(),
+                block_timestamp: 0,
+                gas_price: 2,
+                random_seed: CryptoHash::default(),
+                congestion_info: BlockCongestionInfo::default(),
+                bandwidth_requests: BlockBandwidthRequests::empty(),
+            };
+            let shard = ApplyChunkShardContext {
+                shard_id,
+                last_validator_proposals: ValidatorStakeIter::empty(),
+                gas_limit: 25,
+                is_new_chunk: true,
+            };
+            let num_transactions = transactions.len();
+            runtime
+                .apply_chunk(
+                    RuntimeStorageConfig::new(state_root, false),
+                    ApplyChunkReason::UpdateTrackedShard,
+                    shard,
+                    block,
+                    receipts,
+                    SignedValidPeriodTransactions::new(transactions, vec![true; num_transactions]),
+                )
+                .unwrap()
+        };
+
+        // The signer pays for the gas of the transactions, and prepays the gas
+        // of the receipt of the transfer to another shard.
+        let initial_balance = runtime.get_kv_state(&Trie::EMPTY_ROOT).unwrap().amounts[&sender];
+        let transactions = vec![send_money(1, &local[0]), send_money(2, &remote[0])];
+        let result = apply(Trie::EMPTY_ROOT, 1, &[], transactions);
+        let state = runtime.get_kv_state(&result.new_root).unwrap();
+        assert_eq!(state.amounts[&sender], initial_balance - 200 - 3 * 20);
+        assert_eq!(state.amounts[&local[0]], 100);
+        assert_eq!(result.total_gas_burnt, 20);
+        assert_eq!(result.total_balance_burnt, 40);
+        assert_eq!(result.outgoing_receipts.len(), 1);
+
+        // The receipts are delayed once the gas burnt reaches the gas limit.
+        let receipts = (1..=4)
+            .map(|nonce| {
+                Receipt::V0(ReceiptV0 {
+                    predecessor_id: remote[0].clone(),
+                    receiver_id: local[1].clone(),
+                    receipt_id: create_receipt_nonce(
+                        remote[0].clone(),
+                        local[1].clone(),
+                        100,
+                        nonce,
+                    ),
+                    receipt: ReceiptEnum::Action(ActionReceipt {
+                        signer_id: remote[0].clone(),
+                        signer_public_key: PublicKey::empty(KeyType::ED25519),
+                        gas_price: 2,
+                        output_data_receivers: vec![],
+                        input_data_ids: vec![],
+                        actions: vec![Action::Transfer(TransferAction { deposit: 100 })],
+                    }),
+                })
+            })
+            .collect_vec();
+        let result = apply(result.new_root, 2, &receipts, vec![]);
+        let state = runtime.get_kv_state(&result.new_root).unwrap();
+        assert_eq!(state.amounts[&local[1]], 300);
+        assert_eq!(state.delayed_receipts, vec![receipts[3].clone()]);
+        assert_eq!(result.total_gas_burnt, 30);
+        let result = apply(result.new_root, 3, &[], vec![]);
+        assert_eq!(result.processed_delayed_receipts, vec![receipts[3].clone()]);
+        assert_eq!(runtime.get_kv_state(&result.new_root).unwrap().amounts[&local[1]], 400);
+
+        // The transactions taken from the pool are limited by the gas limit too.
+        let mut pool = TransactionPool::new([0; 32], None, "");
+        for nonce in 3..8 {
+            let tx = ValidatedTransaction::new_for_test(send_money(nonce, &local[0]));
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        let prepared = runtime
+            .prepare_transactions(
+                RuntimeStorageConfig::new(result.new_root, false),
+                PrepareTransactionsChunkContext { shard_id, gas_limit: 25, signer_limits: None },
+                PrepareTransactionsBlockContext {
+                    next_gas_price: 2,
+                    height: 4,
+                    block_hash: *genesis.hash(),
+                    congestion_info: BlockCongestionInfo::default(),
+                },
+                &mut PoolIteratorWrapper::new(&mut pool),
+                &|_: &SignedTransaction| true,
+                None,
+            )
+            .unwrap();
+        assert_eq!(prepared.transactions.len(), 3);
+        assert_eq!(prepared.limited_by, Some(PrepareTransactionsLimit::Gas));
+    }
+
+    /// Round-trips the store and the in-memory maps. A real chain is restored
+    /// in `restore_kv_runtime_snapshot_in_test_loop` of the integration tests.
+    #[test]
+    fn test_snapshot() {
+        let source = runtime();
+        let state_root = CryptoHash::hash_bytes(b"root");
+        let state = source.state.read().get(&Trie::EMPTY_ROOT).unwrap().clone();
+        source.state.write().insert(state_root, state);
+        source.state_size.write().insert(state_root, 42);
+        let block_hash = CryptoHash::hash_bytes(b"block");
+        let epoch_id = EpochId(CryptoHash::hash_bytes(b"epoch"));
+        source.epoch_manager.hash_to_epoch.write().insert(block_hash, epoch_id);
+        source.epoch_manager.hash_to_valset.write().insert(epoch_id, 0);
+        source.epoch_manager.epoch_start.write().insert(block_hash, 1);
+        let mut store_update = source.store.store_update();
+        store_update.set_raw_bytes(DBCol::Misc, b"key", b"value");
+        store_update.commit().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot");
+        source.save_snapshot(&path).unwrap();
+        // The same chain always gives the same snapshot.
+        let path2 = dir.path().join("snapshot2");
+        source.save_snapshot(&path2).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), std::fs::read(&path2).unwrap());
+
+        let target = runtime();
+        target.restore_snapshot(&path).unwrap();
+        assert_eq!(
+            borsh::to_vec(&*target.state.read()).unwrap(),
+            borsh::to_vec(&*source.state.read()).unwrap()
+        );
+        assert_eq!(target.state_size.read().get(&state_root), Some(&42));
+        assert_eq!(target.epoch_manager.hash_to_epoch.read().get(&block_hash), Some(&epoch_id));
+        assert_eq!(target.epoch_manager.epoch_start.read().get(&block_hash), Some(&1));
+        assert_eq!(target.store.get(DBCol::Misc, b"key").unwrap().as_deref(), Some(&b"value"[..]));
+        // Restoring the snapshot gives the snapshot it was saved from.
+        let path3 = dir.path().join("snapshot3");
+        target.save_snapshot(&path3).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), std::fs::read(&path3).unwrap());
+    }
+}
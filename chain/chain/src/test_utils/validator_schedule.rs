@@ -1,4 +1,6 @@
-use near_primitives::types::{AccountId, NumShards};
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::{AccountId, Balance, NumShards};
+use near_primitives::version::ProtocolVersion;
 use std::collections::HashSet;
 
 /// Validator schedule describes how block and chunk producers are selected by
@@ -13,8 +15,12 @@ use std::collections::HashSet;
 pub struct ValidatorSchedule {
     pub(super) block_producers: Vec<Vec<AccountId>>,
     pub(super) chunk_only_producers: Vec<Vec<Vec<AccountId>>>,
+    pub(super) chunk_validators: Vec<Vec<Vec<(AccountId, Balance)>>>,
     pub(super) validator_groups: u64,
     pub(super) num_shards: NumShards,
+    pub(super) protocol_versions: Vec<ProtocolVersion>,
+    pub(super) shard_layouts: Vec<ShardLayout>,
+    pub(super) staking_proposals: bool,
 }
 
 impl ValidatorSchedule {
@@ -26,8 +32,12 @@ impl ValidatorSchedule {
         Self {
             block_producers: Vec::new(),
             chunk_only_producers: Vec::new(),
+            chunk_validators: Vec::new(),
             validator_groups: 1,
             num_shards,
+            protocol_versions: Vec::new(),
+            shard_layouts: Vec::new(),
+            staking_proposals: false,
         }
     }
 
@@ -55,6 +65,22 @@ impl ValidatorSchedule {
         self
     }
 
+    /// Specifies, for each shard in each epoch, the chunk validators assigned
+    /// to the shard together with their assignment stakes, which decide
+    /// whether a chunk has enough endorsements.
+    ///
+    /// Loops around together with the block producers, so it must have the
+    /// same length as `block_producers_per_epoch`, and every chunk validator
+    /// must be a validator of its epoch. If not set, all the block producers
+    /// of the epoch validate every shard with equal stakes.
+    pub fn chunk_validators_per_epoch_per_shard(
+        mut self,
+        chunk_validators: Vec<Vec<Vec<(AccountId, Balance)>>>,
+    ) -> Self {
+        self.chunk_validators = chunk_validators;
+        self
+    }
+
     /// Controls how chunk_producers are selected from the block producers.
     ///
     /// This parameter splits the validators in each shard into that many
@@ -71,6 +97,50 @@ impl ValidatorSchedule {
         self
     }
 
+    /// Specifies, for each epoch, the protocol version of the epoch.
+    ///
+    /// Loops around together with the block producers, so it must have the
+    /// same length as `block_producers_per_epoch`.  If not set, all epochs use
+    /// `PROTOCOL_VERSION`.
+    pub fn protocol_version_per_epoch(mut self, protocol_versions: Vec<ProtocolVersion>) -> Self {
+        self.protocol_versions = protocol_versions;
+        self
+    }
+
+    /// Specifies, for each epoch, the shard layout of the epoch.
+    ///
+    /// Must have the same length as `block_producers_per_epoch`, and the
+    /// schedule then doesn't loop around: the epochs after the last one keep
+    /// its validators and layout, as a layout can't go back to an ancestor.
+    /// The chunk producers are assigned to the shards of the layout of their
+    /// epoch, which overrides `num_shards`. A layout must be derived from the
+    /// one of the previous epoch when it differs, and the protocol versions
+    /// must be set and change with it, as the layouts are looked up by
+    /// protocol version too. If not set, all epochs use the V0 layout with
+    /// `num_shards` shards.
+    pub fn shard_layout_per_epoch(mut self, shard_layouts: Vec<ShardLayout>) -> Self {
+        self.shard_layouts = shard_layouts;
+        self
+    }
+
+    /// Derives the validator sets from the staking proposals instead of
+    /// rotating through `block_producers_per_epoch`, which must then have a
+    /// single entry with the genesis block producers.
+    ///
+    /// As in the real epoch manager, the proposals made in epoch `T` take
+    /// effect in epoch `T + 2`: a proposal with a positive stake adds the
+    /// account to the block producers or updates its stake, and a proposal
+    /// with a zero stake removes it. The block producers which didn't produce
+    /// any block in epoch `T` are kicked out of epoch `T + 2` too, unless that
+    /// would leave no block producer at all.
+    ///
+    /// Without it, the legacy fixed schedule is used and the proposals are
+    /// ignored.
+    pub fn staking_proposals(mut self, staking_proposals: bool) -> Self {
+        self.staking_proposals = staking_proposals;
+        self
+    }
+
     pub fn num_shards(mut self, num_shards: NumShards) -> Self {
         self.num_shards = num_shards;
         self
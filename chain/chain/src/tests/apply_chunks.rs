@@ -0,0 +1,52 @@
+use crate::Error;
+use crate::chain::do_apply_chunks;
+use crate::update_shard::ShardUpdateResult;
+use near_primitives::hash::CryptoHash;
+use near_primitives::optimistic_block::{BlockToApply, CachedShardUpdateKey};
+use near_primitives::types::ShardId;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Applies `num_shards` chunks with at most `max_concurrency` of them at once
+/// and returns the maximal number of chunks observed being applied at once.
+type Task = Box<dyn FnOnce(&tracing::Span) -> Result<ShardUpdateResult, Error> + Send + Sync>;
+
+fn apply_chunks(num_shards: u64, max_concurrency: Option<usize>) -> usize {
+    let applying = Arc::new(AtomicUsize::new(0));
+    let max_applying = Arc::new(AtomicUsize::new(0));
+    let work = (0..num_shards)
+        .map(|shard_id| {
+            let applying = applying.clone();
+            let max_applying = max_applying.clone();
+            let task = move |_: &tracing::Span| {
+                let now_applying = applying.fetch_add(1, Ordering::SeqCst) + 1;
+                max_applying.fetch_max(now_applying, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                applying.fetch_sub(1, Ordering::SeqCst);
+                Err(Error::Other(shard_id.to_string()))
+            };
+            let key = CachedShardUpdateKey::new(CryptoHash::hash_borsh(shard_id));
+            (ShardId::new(shard_id), key, Box::new(task) as Task)
+        })
+        .collect();
+    let results =
+        do_apply_chunks(BlockToApply::Normal(CryptoHash::default()), 1, work, max_concurrency);
+
+    // The results are returned in the order of the work.
+    assert_eq!(results.len(), num_shards as usize);
+    for (shard_id, (result_shard_id, key, result)) in (0..num_shards).zip(results) {
+        assert_eq!(result_shard_id, ShardId::new(shard_id));
+        assert_eq!(key, CachedShardUpdateKey::new(CryptoHash::hash_borsh(shard_id)));
+        assert_eq!(result.unwrap_err().to_string(), Error::Other(shard_id.to_string()).to_string());
+    }
+    max_applying.load(Ordering::SeqCst)
+}
+
+#[test]
+fn test_do_apply_chunks_max_concurrency() {
+    assert!(apply_chunks(8, Some(2)) <= 2);
+    assert_eq!(apply_chunks(3, Some(1)), 1);
+    assert!(apply_chunks(4, Some(10)) <= 4);
+    apply_chunks(6, None);
+    apply_chunks(0, Some(2));
+}
@@ -4,7 +4,10 @@ use rand::Rng;
 use std::sync::Arc;
 
 use crate::chain::Chain;
-use crate::garbage_collection::GCMode;
+use crate::garbage_collection::{
+    GCMode, RPC_LIGHT_CHUNK_EXTRA_HORIZON, RPC_LIGHT_GC_HEIGHTS_PER_STEP,
+    RPC_LIGHT_GC_KEYS_PER_STEP,
+};
 use crate::test_utils::{
     get_chain, get_chain_with_epoch_length, get_chain_with_epoch_length_and_num_shards,
     get_chain_with_num_shards,
@@ -13,14 +16,19 @@ use crate::types::Tip;
 use crate::{ChainStoreAccess, StoreValidator};
 
 use near_chain_configs::{DEFAULT_GC_NUM_EPOCHS_TO_KEEP, GCConfig, GenesisConfig};
+use near_crypto::InMemorySigner;
 use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::block::Block;
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::merkle::PartialMerkleTree;
 use near_primitives::shard_layout::ShardUId;
+use near_primitives::sharding::{ShardChunk, ShardChunkHeader, ShardChunkV2};
 use near_primitives::test_utils::{TestBlockBuilder, create_test_signer};
-use near_primitives::types::{BlockHeight, NumBlocks, StateRoot};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_primitives::types::{AccountId, BlockHeight, NumBlocks, StateRoot};
 use near_primitives::validator_signer::ValidatorSigner;
+use near_store::db::RPC_LIGHT_CHUNK_EXTRA_TAIL_KEY;
 use near_store::test_utils::gen_changes;
 use near_store::{DBCol, ShardTries, Trie, WrappedTrieChanges};
 
@@ -721,6 +729,84 @@ fn test_clear_old_data() {
     }
 }
 
+/// The `rpc-light` garbage collection deletes the chunk extras of the fork
+/// blocks below the horizon and keeps the ones of the canonical blocks, which
+/// the queries need. The state witness data is deleted in bounded steps.
+#[test]
+fn test_clear_rpc_light_data() {
+    let mut chain = get_chain(Clock::real());
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+
+    // A fork block at height 2, built directly on top of genesis.
+    let fork_block =
+        TestBlockBuilder::new(Clock::real(), &genesis, signer.clone()).height(2).build();
+    let mut store_update = chain.mut_chain_store().store_update();
+    store_update.save_block(fork_block.clone());
+    store_update.inc_block_refcount(genesis.hash()).unwrap();
+    store_update.save_block_header(fork_block.header().clone()).unwrap();
+    store_update.merge(
+        epoch_manager
+            .add_validator_proposals(
+                BlockInfo::from_header(fork_block.header(), 0),
+                *fork_block.header().random_value(),
+            )
+            .unwrap(),
+    );
+    store_update.commit().unwrap();
+
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    let max_height = RPC_LIGHT_CHUNK_EXTRA_HORIZON + 2 * RPC_LIGHT_GC_HEIGHTS_PER_STEP + 10;
+    for height in 1..=max_height {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            height,
+        );
+    }
+    let shard_layout = epoch_manager.get_shard_layout(fork_block.header().epoch_id()).unwrap();
+    let shard_uid = shard_layout.shard_uids().next().unwrap();
+    let mut store_update = chain.mut_chain_store().store_update();
+    for block_hash in [fork_block.hash(), blocks[2].hash()] {
+        store_update.save_chunk_extra(
+            block_hash,
+            &shard_uid,
+            ChunkExtra::new_with_only_state_root(&Trie::EMPTY_ROOT),
+        );
+    }
+    store_update.save_final_head(&Tip::from_header(blocks[max_height as usize].header())).unwrap();
+    store_update.commit().unwrap();
+
+    let store = chain.chain_store().store();
+    let mut store_update = store.store_update();
+    for i in 0..RPC_LIGHT_GC_KEYS_PER_STEP as u64 + 1 {
+        store_update.set(DBCol::StateTransitionData, &i.to_le_bytes(), &[]);
+    }
+    store_update.commit().unwrap();
+
+    chain.mut_chain_store().clear_rpc_light_data(epoch_manager.as_ref()).unwrap();
+    assert!(chain.get_chunk_extra(fork_block.hash(), &shard_uid).is_err());
+    assert!(chain.get_chunk_extra(blocks[2].hash(), &shard_uid).is_ok());
+    assert_eq!(store.iter(DBCol::StateTransitionData).count(), 1);
+    // The tail is saved, so that the next call continues after it.
+    assert_eq!(
+        store.get_ser::<BlockHeight>(DBCol::Misc, RPC_LIGHT_CHUNK_EXTRA_TAIL_KEY).unwrap(),
+        Some(RPC_LIGHT_GC_HEIGHTS_PER_STEP + 1)
+    );
+
+    chain.mut_chain_store().clear_rpc_light_data(epoch_manager.as_ref()).unwrap();
+    assert_eq!(store.iter(DBCol::StateTransitionData).count(), 0);
+    assert_eq!(
+        store.get_ser::<BlockHeight>(DBCol::Misc, RPC_LIGHT_CHUNK_EXTRA_TAIL_KEY).unwrap(),
+        Some(2 * RPC_LIGHT_GC_HEIGHTS_PER_STEP + 1)
+    );
+}
+
 // Adds block to the chain at given height after prev_block.
 fn add_block(
     chain: &mut Chain,
@@ -733,8 +819,6 @@ fn add_block(
     let next_epoch_id = epoch_manager
         .get_next_epoch_id_from_prev_block(prev_block.hash())
         .expect("block must exist");
-    let mut store_update = chain.mut_chain_store().store_update();
-
     let block = if next_epoch_id == *prev_block.header().next_epoch_id() {
         TestBlockBuilder::new(Clock::real(), &prev_block, signer).height(height).build()
     } else {
@@ -747,6 +831,19 @@ fn add_block(
             .next_bp_hash(next_bp_hash)
             .build()
     };
+    save_canonical_block(chain, epoch_manager, prev_block, blocks, block);
+}
+
+// Saves the block built on top of prev_block as the new head of the chain.
+fn save_canonical_block(
+    chain: &mut Chain,
+    epoch_manager: &dyn EpochManagerAdapter,
+    prev_block: &mut Block,
+    blocks: &mut Vec<Block>,
+    block: Block,
+) {
+    let height = block.header().height();
+    let mut store_update = chain.mut_chain_store().store_update();
     blocks.push(block.clone());
     store_update.save_block(block.clone());
     store_update.inc_block_refcount(block.header().prev_hash()).unwrap();
@@ -765,7 +862,100 @@ fn add_block(
         .unwrap();
     store_update.merge(epoch_manager_update);
     store_update.commit().unwrap();
-    *prev_block = block.clone();
+    *prev_block = block;
+}
+
+/// The transactions are indexed by signer at the height of the block which
+/// includes their chunk. After a skipped height that is above the height at
+/// which the chunk was created, and the entries must still be removed when the
+/// block is garbage collected. A fork block including the same chunk at the
+/// same height must not remove the entry of the canonical block.
+#[test]
+fn test_gc_transactions_by_signer_after_skipped_height() {
+    let mut chain = get_chain(Clock::real());
+    chain.mut_chain_store().set_transactions_by_signer_limit(Some(10));
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis.clone();
+    let mut blocks = vec![prev_block.clone()];
+    for height in 1..=2 {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            height,
+        );
+    }
+
+    // Height 3 is skipped, the chunk created for it is included at height 4.
+    let account_id: AccountId = "test1".parse().unwrap();
+    let transaction = Arc::new(SignedTransaction::send_money(
+        1,
+        account_id.clone(),
+        account_id.clone(),
+        &InMemorySigner::test_signer(&account_id),
+        100,
+        *genesis.hash(),
+    ));
+    let shard_id = prev_block.chunks().iter_raw().next().unwrap().shard_id();
+    let mut chunk_header = ShardChunkHeader::new_dummy(3, shard_id, *prev_block.hash());
+    *chunk_header.height_included_mut() = 4;
+    let chunk = ShardChunk::V2(ShardChunkV2 {
+        chunk_hash: chunk_header.chunk_hash(),
+        header: chunk_header.clone(),
+        transactions: vec![transaction.as_ref().clone()],
+        prev_outgoing_receipts: vec![],
+    });
+    let mut block =
+        TestBlockBuilder::new(Clock::real(), &prev_block, signer.clone()).height(4).build();
+    block.set_chunks(vec![chunk_header.clone()]);
+    let mut fork_block =
+        TestBlockBuilder::new(Clock::real(), &blocks[1], signer.clone()).height(4).build();
+    fork_block.set_chunks(vec![chunk_header]);
+    save_canonical_block(&mut chain, epoch_manager.as_ref(), &mut prev_block, &mut blocks, block);
+    add_block(&mut chain, epoch_manager.as_ref(), &mut prev_block, &mut blocks, signer, 5);
+
+    let mut store_update = chain.mut_chain_store().store_update();
+    store_update.save_block(fork_block.clone());
+    store_update.inc_block_refcount(blocks[1].hash()).unwrap();
+    store_update.save_block_header(fork_block.header().clone()).unwrap();
+    store_update.merge(
+        epoch_manager
+            .add_validator_proposals(
+                BlockInfo::from_header(fork_block.header(), 0),
+                *fork_block.header().random_value(),
+            )
+            .unwrap(),
+    );
+    store_update.save_chunk(chunk);
+    // The fork block is applied last, so the entry points to it.
+    for block_hash in [blocks[3].hash(), fork_block.hash()] {
+        store_update.save_transactions_by_signer(block_hash, 4, &[transaction.clone()]).unwrap();
+    }
+    store_update.commit().unwrap();
+    let indexed =
+        |chain: &Chain| chain.chain_store().get_transactions_by_signer(&account_id).unwrap();
+    assert_eq!(indexed(&chain), vec![(4, transaction.get_hash(), *fork_block.hash())]);
+
+    let tries = chain.runtime_adapter.get_tries();
+    let mut store_update = chain.mut_chain_store().store_update();
+    store_update
+        .clear_block_data(epoch_manager.as_ref(), *fork_block.hash(), GCMode::Fork(tries.clone()))
+        .unwrap();
+    store_update.commit().unwrap();
+    assert_eq!(indexed(&chain), vec![(4, transaction.get_hash(), *blocks[3].hash())]);
+
+    // The canonical garbage collection of a block clears its previous block.
+    let mut store_update = chain.mut_chain_store().store_update();
+    store_update
+        .clear_block_data(epoch_manager.as_ref(), *blocks[4].hash(), GCMode::Canonical(tries))
+        .unwrap();
+    store_update.commit().unwrap();
+    assert!(chain.get_block(blocks[3].hash()).is_err());
+    assert!(indexed(&chain).is_empty());
 }
 
 #[test]
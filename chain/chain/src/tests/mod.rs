@@ -1,3 +1,4 @@
+mod apply_chunks;
 mod doomslug;
 mod garbage_collection;
 mod simple_chain;
@@ -95,7 +95,8 @@ fn build_chain_with_orphans() {
         clock,
         None,
         None,
-    );
+    )
+    .unwrap();
     assert_matches!(chain.process_block_test(&None, block).unwrap_err(), Error::Orphan);
     assert_matches!(
         chain.process_block_test(&None, blocks.pop().unwrap()).unwrap_err(),
@@ -320,7 +321,8 @@ fn test_pending_block() {
         block2.header().raw_timestamp(),
         None,
         near_primitives::optimistic_block::OptimisticBlockAdvType::Normal,
-    );
+    )
+    .unwrap();
     chain
         .process_optimistic_block(
             &me,
@@ -411,7 +413,8 @@ fn test_pending_block_same_height() {
         block2.header().raw_timestamp(),
         None,
         near_primitives::optimistic_block::OptimisticBlockAdvType::Normal,
-    );
+    )
+    .unwrap();
 
     // Process the optimistic block
     let chunk_headers = block2.chunks().iter_raw().cloned().collect();
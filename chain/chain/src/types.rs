@@ -29,8 +29,8 @@ use near_primitives::transaction::ValidatedTransaction;
 use near_primitives::transaction::{ExecutionOutcomeWithId, SignedTransaction};
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
 use near_primitives::types::{
-    Balance, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash, NumBlocks, ShardId,
-    StateRoot, StateRootNode,
+    AccountId, Balance, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash, NumBlocks,
+    ShardId, StateRoot, StateRootNode,
 };
 use near_primitives::utils::to_timestamp;
 use near_primitives::version::PROD_GENESIS_PROTOCOL_VERSION;
@@ -43,6 +43,7 @@ use near_vm_runner::ContractCode;
 use near_vm_runner::ContractRuntimeCache;
 use node_runtime::SignedValidPeriodTransactions;
 use num_rational::Rational32;
+use std::collections::HashMap;
 use tracing::instrument;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
@@ -203,6 +204,18 @@ pub struct ChainConfig {
     pub background_migration_threads: usize,
     /// The resharding configuration.
     pub resharding_config: MutableConfigValue<ReshardingConfig>,
+    /// Whether to defer writing flat storage deltas, outcomes and apply stats
+    /// of blocks which don't become the head until their fork is extended.
+    pub defer_fork_postprocessing: bool,
+    /// Maximum number of transactions indexed per signer, see
+    /// `DBCol::TransactionsBySigner`. The index is not maintained if `None`.
+    pub transactions_by_signer_limit: Option<usize>,
+    /// Maximum number of chunks of a block applied at once, unbounded if
+    /// `None`.
+    pub max_concurrent_shard_applies: Option<usize>,
+    /// Whether to audit the delivery of the receipts between shards, see
+    /// `ReceiptAudit`.
+    pub enable_receipt_audit: bool,
 }
 
 impl ChainConfig {
@@ -214,6 +227,10 @@ impl ChainConfig {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            defer_fork_postprocessing: false,
+            transactions_by_signer_limit: None,
+            max_concurrent_shard_applies: None,
+            enable_receipt_audit: false,
         }
     }
 }
@@ -336,6 +353,9 @@ pub struct PreparedTransactions {
     pub transactions: Vec<ValidatedTransaction>,
     /// Describes which limit was hit when preparing the transactions.
     pub limited_by: Option<PrepareTransactionsLimit>,
+    /// Signers which had transactions left in the pool because they reached
+    /// their limit in `SignerTransactionLimits`.
+    pub limited_signers: Vec<AccountId>,
 }
 
 /// Chunk producer prepares transactions from the transaction pool
@@ -371,6 +391,23 @@ impl From<&Block> for PrepareTransactionsBlockContext {
 pub struct PrepareTransactionsChunkContext {
     pub shard_id: ShardId,
     pub gas_limit: Gas,
+    /// If set, limits the number of transactions of each signer in the chunk.
+    pub signer_limits: Option<SignerTransactionLimits>,
+}
+
+/// Limits on the number of transactions of a single signer included in a chunk.
+#[derive(Debug, Clone, Default)]
+pub struct SignerTransactionLimits {
+    /// Limit of the signers which aren't in `limits`.
+    pub default_limit: usize,
+    /// Lower limits of the signers deprioritized by the chunk producer.
+    pub limits: HashMap<AccountId, usize>,
+}
+
+impl SignerTransactionLimits {
+    pub fn get(&self, signer_id: &AccountId) -> usize {
+        self.limits.get(signer_id).copied().unwrap_or(self.default_limit)
+    }
 }
 
 /// Bridge between the chain and the runtime.
@@ -445,6 +482,18 @@ pub trait RuntimeAdapter: Send + Sync {
     /// Current epoch is the epoch of the block after `parent_hash`
     fn will_shard_layout_change_next_epoch(&self, parent_hash: &CryptoHash) -> Result<bool, Error>;
 
+    /// Splits the state at `state_root` of a shard split at `boundary_account`
+    /// into the states of its children, returning their state roots. Returns
+    /// `None` if the state is kept in the tries, which the `ReshardingManager`
+    /// splits with the memtries.
+    fn split_state(
+        &self,
+        _state_root: StateRoot,
+        _boundary_account: &AccountId,
+    ) -> Result<Option<(StateRoot, StateRoot)>, Error> {
+        Ok(None)
+    }
+
     /// Get the block height for which garbage collection should not go over
     fn get_gc_stop_height(&self, block_hash: &CryptoHash) -> BlockHeight;
 
@@ -474,6 +523,37 @@ pub trait RuntimeAdapter: Send + Sync {
         request: &QueryRequest,
     ) -> Result<QueryResponse, near_chain_primitives::error::QueryError>;
 
+    /// Runs several queries against the same state of a shard and returns
+    /// their results in the order of the `requests`. The runtime may share the
+    /// trie of the state between the requests instead of opening it for each.
+    fn query_batch(
+        &self,
+        shard_uid: ShardUId,
+        state_root: &StateRoot,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        prev_block_hash: &CryptoHash,
+        block_hash: &CryptoHash,
+        epoch_id: &EpochId,
+        requests: &[&QueryRequest],
+    ) -> Vec<Result<QueryResponse, near_chain_primitives::error::QueryError>> {
+        requests
+            .iter()
+            .map(|request| {
+                self.query(
+                    shard_uid,
+                    state_root,
+                    block_height,
+                    block_timestamp,
+                    prev_block_hash,
+                    block_hash,
+                    epoch_id,
+                    request,
+                )
+            })
+            .collect()
+    }
+
     /// Get part of the state corresponding to the given state root.
     /// `prev_hash` is a block whose post state root is `state_root`.
     /// Returns error when storage is inconsistent.
@@ -581,7 +661,7 @@ mod tests {
         assert!(b1.header().verify_block_producer(&signer.public_key()));
         let other_signer = create_test_signer("other2");
         let approvals =
-            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).signature))];
+            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).unwrap().signature))];
         let b2 =
             TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).approvals(approvals).build();
         b2.header().verify_block_producer(&signer.public_key());
@@ -8,6 +8,7 @@ pub enum Error {
     InvalidChunkPartId,
     InvalidChunkShardId,
     InvalidMerkleProof,
+    InvalidReceiptProofFragment,
     InvalidChunkSignature,
     InvalidChunkHeader,
     InvalidChunk,
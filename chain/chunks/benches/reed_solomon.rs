@@ -0,0 +1,94 @@
+//! Benchmarks of encoding and reconstructing chunk-sized payloads with the
+//! Reed-Solomon backends of `near_primitives::reed_solomon`.
+//!
+//! Run with `cargo bench -p near-chunks --bench reed_solomon`. Part counts
+//! follow the number of chunk producers, so the larger configurations
+//! correspond to networks with many shards. The smallest payload shows the
+//! fixed cost of every call, such as computing the coding matrices.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use near_primitives::reed_solomon::{
+    ReedSolomonBackend, reed_solomon_decode_with_backend, reed_solomon_encode_with_backend,
+};
+use rand::{Rng, SeedableRng};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+const BACKENDS: [ReedSolomonBackend; 2] = [ReedSolomonBackend::Erasure, ReedSolomonBackend::Simd];
+
+/// (data parts, parity parts). `galois_8` supports at most 256 parts in total.
+const PART_COUNTS: [(usize, usize); 2] = [(33, 67), (85, 171)];
+
+const PAYLOAD_SIZES: [usize; 4] = [64 << 10, 1 << 20, 8 << 20, 32 << 20];
+
+fn payload(len: usize) -> Vec<u8> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(len as u64);
+    let mut bytes = vec![0; len];
+    rng.fill(&mut bytes[..]);
+    bytes
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reed_solomon_encode");
+    group.sample_size(10);
+    for (data_parts, parity_parts) in PART_COUNTS {
+        let rs = ReedSolomon::new(data_parts, parity_parts).unwrap();
+        for len in PAYLOAD_SIZES {
+            let data = payload(len);
+            group.throughput(Throughput::Bytes(len as u64));
+            for backend in BACKENDS {
+                let id = BenchmarkId::new(
+                    format!("{backend:?}/{}_parts", data_parts + parity_parts),
+                    len,
+                );
+                group.bench_with_input(id, &data, |b, data| {
+                    b.iter(|| reed_solomon_encode_with_backend(backend, &rs, data))
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+/// Reconstructs the payload from the parity parts only, which is the most
+/// expensive case since every data part has to be recomputed.
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reed_solomon_decode");
+    group.sample_size(10);
+    for (data_parts, parity_parts) in PART_COUNTS {
+        let rs = ReedSolomon::new(data_parts, parity_parts).unwrap();
+        for len in PAYLOAD_SIZES {
+            let (parts, encoded_length) =
+                reed_solomon_encode_with_backend(ReedSolomonBackend::Erasure, &rs, &payload(len));
+            group.throughput(Throughput::Bytes(len as u64));
+            for backend in BACKENDS {
+                let id = BenchmarkId::new(
+                    format!("{backend:?}/{}_parts", data_parts + parity_parts),
+                    len,
+                );
+                group.bench_function(id, |b| {
+                    b.iter_batched(
+                        || {
+                            let mut parts = parts.clone();
+                            parts[..data_parts].fill(None);
+                            parts
+                        },
+                        |mut parts| {
+                            reed_solomon_decode_with_backend::<Vec<u8>>(
+                                backend,
+                                &rs,
+                                &mut parts,
+                                encoded_length,
+                            )
+                            .unwrap()
+                        },
+                        BatchSize::LargeInput,
+                    )
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
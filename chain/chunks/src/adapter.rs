@@ -66,4 +66,10 @@ pub enum ShardsManagerRequestFromClient {
         epoch_id: EpochId,
         ancestor_hash: CryptoHash,
     },
+    /// Fetches the whole chunk from the peers that have it, also when it is too
+    /// old to be requested with `RequestChunks` or the node doesn't track its
+    /// shard. The header must be taken from a known block, as the fetched parts
+    /// are validated against it. Once the chunk is fetched, a response of
+    /// ShardsManagerResponse::ChunkFetched is sent back to the client.
+    FetchChunk(ShardChunkHeader),
 }
@@ -0,0 +1,102 @@
+//! Fetching of whole chunks requested with `ShardsManagerRequestFromClient::FetchChunk`.
+//!
+//! Unlike the chunks of new blocks, which are assembled from the parts owned by
+//! the block producers, these may be arbitrarily old chunks the node never
+//! received, e.g. for the sync code or for inspecting the chain. They are
+//! requested with `ChunkRequestMsg` from the peers tracking the shard, or from
+//! the archival peers for chunks outside of the recent epochs, and are decoded
+//! once enough valid parts were received.
+
+use near_async::time;
+use near_chunks_primitives::Error;
+use near_primitives::merkle::verify_path;
+use near_primitives::sharding::{ChunkHash, PartialEncodedChunkPart, ShardChunkHeader};
+use std::collections::HashMap;
+
+/// How long to wait for the parts before requesting the chunk from another peer.
+pub(crate) const CHUNK_FETCH_RETRY: time::Duration = time::Duration::seconds(2);
+/// How long to keep requesting the chunk before giving up.
+pub(crate) const CHUNK_FETCH_MAX: time::Duration = time::Duration::minutes(1);
+
+pub(crate) struct ChunkFetch {
+    /// The header the parts are validated against. It comes from the caller,
+    /// who must have taken it from a known block.
+    pub header: ShardChunkHeader,
+    pub parts: HashMap<u64, PartialEncodedChunkPart>,
+    added: time::Instant,
+    last_requested: time::Instant,
+}
+
+impl ChunkFetch {
+    /// Adds the parts after checking their merkle proofs against the header.
+    /// Nothing is added if any of them is invalid.
+    pub fn add_parts(
+        &mut self,
+        parts: Vec<PartialEncodedChunkPart>,
+        num_total_parts: usize,
+    ) -> Result<(), Error> {
+        for part in &parts {
+            if part.part_ord as usize >= num_total_parts {
+                return Err(Error::InvalidChunkPartId);
+            }
+            if !verify_path(self.header.encoded_merkle_root(), &part.merkle_proof, &part.part) {
+                return Err(Error::InvalidMerkleProof);
+            }
+        }
+        for part in parts {
+            self.parts.insert(part.part_ord, part);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct ChunkFetchPool {
+    fetches: HashMap<ChunkHash, ChunkFetch>,
+}
+
+impl ChunkFetchPool {
+    pub fn new() -> Self {
+        Self { fetches: HashMap::new() }
+    }
+
+    /// Returns false if the chunk is already being fetched.
+    pub fn insert(&mut self, header: ShardChunkHeader, now: time::Instant) -> bool {
+        let chunk_hash = header.chunk_hash();
+        if self.fetches.contains_key(&chunk_hash) {
+            return false;
+        }
+        self.fetches.insert(
+            chunk_hash,
+            ChunkFetch { header, parts: HashMap::new(), added: now, last_requested: now },
+        );
+        true
+    }
+
+    pub fn get_mut(&mut self, chunk_hash: &ChunkHash) -> Option<&mut ChunkFetch> {
+        self.fetches.get_mut(chunk_hash)
+    }
+
+    pub fn remove(&mut self, chunk_hash: &ChunkHash) -> Option<ChunkFetch> {
+        self.fetches.remove(chunk_hash)
+    }
+
+    /// Returns the headers of the chunks to request again, after dropping the
+    /// ones which have been fetched for longer than `CHUNK_FETCH_MAX`.
+    pub fn fetch(&mut self, now: time::Instant) -> Vec<ShardChunkHeader> {
+        self.fetches.retain(|chunk_hash, fetch| {
+            let keep = now - fetch.added < CHUNK_FETCH_MAX;
+            if !keep {
+                tracing::warn!(target: "chunks", ?chunk_hash, "Gave up fetching the chunk");
+            }
+            keep
+        });
+        let mut headers = vec![];
+        for fetch in self.fetches.values_mut() {
+            if now - fetch.last_requested >= CHUNK_FETCH_RETRY {
+                fetch.last_requested = now;
+                headers.push(fetch.header.clone());
+            }
+        }
+        headers
+    }
+}
@@ -1,6 +1,7 @@
 use actix::Message;
 use itertools::Itertools;
-use near_pool::types::TransactionGroupIterator;
+use near_chain_configs::TransactionPriorityConfig;
+use near_pool::types::{TransactionClassifier, TransactionGroupIterator, TransactionLane};
 use near_pool::{InsertTransactionResult, PoolIteratorWrapper, TransactionPool};
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::transaction::{SignedTransaction, ValidatedTransaction};
@@ -9,7 +10,8 @@ use near_primitives::{
     sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
     types::{AccountId, ShardId},
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
@@ -30,6 +32,40 @@ pub enum ShardsManagerResponse {
     /// block, so that if we are a block producer, we may create a block that contains
     /// this chunk now. The producer of this chunk is also provided.
     ChunkHeaderReadyForInclusion { chunk_header: ShardChunkHeader, chunk_producer: AccountId },
+    /// Notifies the client that a chunk requested with
+    /// `ShardsManagerRequestFromClient::FetchChunk` was fetched and validated.
+    ChunkFetched(ShardChunk),
+}
+
+/// Puts the transactions matching a `TransactionPriorityConfig` in the
+/// priority lane of the pool.
+struct PriorityConfigClassifier {
+    priority_signers: HashSet<AccountId>,
+    min_priority_fee: Option<u64>,
+}
+
+impl PriorityConfigClassifier {
+    fn new(config: &TransactionPriorityConfig) -> Self {
+        Self {
+            priority_signers: config.priority_signers.iter().cloned().collect(),
+            min_priority_fee: config.min_priority_fee,
+        }
+    }
+}
+
+impl TransactionClassifier for PriorityConfigClassifier {
+    fn lane(&self, tx: &SignedTransaction) -> TransactionLane {
+        let is_priority_signer = self.priority_signers.contains(tx.transaction.signer_id());
+        let has_priority_fee = match (self.min_priority_fee, tx.transaction.priority_fee()) {
+            (Some(min_priority_fee), Some(priority_fee)) => priority_fee >= min_priority_fee,
+            _ => false,
+        };
+        if is_priority_signer || has_priority_fee {
+            TransactionLane::Priority
+        } else {
+            TransactionLane::Regular
+        }
+    }
 }
 
 pub struct ShardedTransactionPool {
@@ -42,11 +78,21 @@ pub struct ShardedTransactionPool {
     /// If set, new transactions that bring the size of the pool over this limit will be rejected.
     /// The size is tracked and enforced separately for each shard.
     pool_size_limit: Option<u64>,
+
+    /// Decides the lane of the transactions in the pool of every shard.
+    classifier: Option<Arc<dyn TransactionClassifier>>,
 }
 
 impl ShardedTransactionPool {
-    pub fn new(rng_seed: RngSeed, pool_size_limit: Option<u64>) -> Self {
-        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit }
+    pub fn new(
+        rng_seed: RngSeed,
+        pool_size_limit: Option<u64>,
+        priority_config: Option<&TransactionPriorityConfig>,
+    ) -> Self {
+        let classifier = priority_config.map(|config| {
+            Arc::new(PriorityConfigClassifier::new(config)) as Arc<dyn TransactionClassifier>
+        });
+        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit, classifier }
     }
 
     pub fn get_pool_iterator(&mut self, shard_uid: ShardUId) -> Option<PoolIteratorWrapper<'_>> {
@@ -62,6 +108,13 @@ impl ShardedTransactionPool {
         self.pool_for_shard(shard_uid).insert_transaction(validated_tx)
     }
 
+    /// Returns all transactions in the pools of all shards, in no particular order.
+    pub fn transactions(&self) -> impl Iterator<Item = (ShardUId, &ValidatedTransaction)> {
+        self.tx_pools
+            .iter()
+            .flat_map(|(shard_uid, pool)| pool.transactions().map(|tx| (*shard_uid, tx)))
+    }
+
     pub fn remove_transactions(&mut self, shard_uid: ShardUId, signed_txs: &[SignedTransaction]) {
         if let Some(pool) = self.tx_pools.get_mut(&shard_uid) {
             pool.remove_transactions(signed_txs)
@@ -82,11 +135,15 @@ impl ShardedTransactionPool {
 
     fn pool_for_shard(&mut self, shard_uid: ShardUId) -> &mut TransactionPool {
         self.tx_pools.entry(shard_uid).or_insert_with(|| {
-            TransactionPool::new(
+            let mut pool = TransactionPool::new(
                 Self::random_seed(&self.rng_seed, shard_uid.shard_id()),
                 self.pool_size_limit,
                 &shard_uid.to_string(),
-            )
+            );
+            if let Some(classifier) = &self.classifier {
+                pool.set_classifier(classifier.clone());
+            }
+            pool
         })
     }
 
@@ -201,7 +258,7 @@ mod tests {
             "tge-lockup.sweat".parse().unwrap(),
         );
 
-        let mut pool = ShardedTransactionPool::new(TEST_SEED, None);
+        let mut pool = ShardedTransactionPool::new(TEST_SEED, None, None);
 
         let mut shard_id_to_accounts: HashMap<ShardId, _> = HashMap::new();
         shard_id_to_accounts.insert(ShardId::new(0), vec!["aaa", "abcd", "a-a-a-a-a"]);
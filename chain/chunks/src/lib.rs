@@ -1,7 +1,9 @@
 pub mod adapter;
 mod chunk_cache;
+mod chunk_fetch;
 pub mod client;
 pub mod logic;
 pub mod metrics;
+mod receipt_proof_fragments;
 pub mod shards_manager_actor;
 pub mod test_utils;
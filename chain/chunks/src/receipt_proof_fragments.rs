@@ -0,0 +1,298 @@
+//! Reassembly of receipt proofs sent as fragments, see `ReceiptProofFragment`.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use lru::LruCache;
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::sharding::receipt_proof_fragment::{
+    ReceiptProofFragment, ReceiptProofFragmentsError, assemble_receipt_proof, split_receipt_proof,
+};
+use near_primitives::sharding::{ChunkHash, ReceiptProof};
+use near_primitives::types::ShardId;
+
+const RECEIPT_PROOF_FRAGMENTS_CACHE_SIZE: usize = 1000;
+
+/// Maximum total size of the data of the fragments from a single peer that
+/// are waiting for the other fragments of their proofs.
+const MAX_RECEIPT_PROOF_FRAGMENTS_BYTES_PER_PEER: usize = 64 * 1024 * 1024;
+
+/// Maximum total size of the reassembled proofs waiting for the headers of
+/// their chunks.
+const MAX_RECEIPT_PROOFS_BYTES: usize = 256 * 1024 * 1024;
+
+/// Fragments are never smaller than this, so that splitting a proof of a
+/// chunk with small parts doesn't result in too many messages.
+const MIN_RECEIPT_PROOF_FRAGMENT_SIZE: usize = 64 * 1024;
+
+/// Maximum size of a fragment of a receipt proof of a chunk, which is about
+/// the size of a part of the chunk. Receipt proofs larger than that are sent
+/// as fragments instead of together with the parts.
+pub(crate) fn max_receipt_proof_fragment_size(
+    encoded_length: u64,
+    num_total_parts: usize,
+) -> usize {
+    let part_size = (encoded_length as usize).div_ceil(num_total_parts);
+    part_size.max(MIN_RECEIPT_PROOF_FRAGMENT_SIZE)
+}
+
+/// Splits the receipt proofs larger than `max_fragment_size` into fragments.
+/// Returns the proofs which are small enough to be sent as they are and the
+/// fragments of the other ones.
+pub(crate) fn split_large_receipt_proofs(
+    chunk_hash: &ChunkHash,
+    receipt_proofs: Vec<Arc<ReceiptProof>>,
+    max_fragment_size: usize,
+) -> (Vec<Arc<ReceiptProof>>, Vec<ReceiptProofFragment>) {
+    let mut fragments = vec![];
+    let receipt_proofs = receipt_proofs
+        .into_iter()
+        .filter(|proof| {
+            let size = borsh::object_length(proof.as_ref()).unwrap();
+            if size <= max_fragment_size {
+                return true;
+            }
+            fragments.extend(split_receipt_proof(chunk_hash, proof, max_fragment_size));
+            false
+        })
+        .collect();
+    (receipt_proofs, fragments)
+}
+
+/// Identifies the fragments of a single proof. All of the metadata is part of
+/// the key so that invalid fragments can't prevent the valid ones from being
+/// reassembled.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct FragmentsKey {
+    chunk_hash: ChunkHash,
+    to_shard_id: ShardId,
+    fragments_root: CryptoHash,
+    num_data_fragments: u32,
+    num_total_fragments: u32,
+    encoded_length: u64,
+}
+
+impl FragmentsKey {
+    fn new(fragment: &ReceiptProofFragment) -> Self {
+        Self {
+            chunk_hash: fragment.chunk_hash.clone(),
+            to_shard_id: fragment.to_shard_id,
+            fragments_root: fragment.fragments_root,
+            num_data_fragments: fragment.num_data_fragments,
+            num_total_fragments: fragment.num_total_fragments,
+            encoded_length: fragment.encoded_length,
+        }
+    }
+}
+
+/// Fragments of a proof received so far, with the peers they came from.
+type Fragments = HashMap<u32, (PeerId, ReceiptProofFragment)>;
+
+/// Fragments of receipt proofs until enough fragments of a proof are received,
+/// and the reassembled proofs until the header of their chunk is known.
+pub(crate) struct ReceiptProofFragmentsCache {
+    fragments: LruCache<FragmentsKey, Fragments>,
+    /// Total size of the data of the fragments in `fragments` by the peer
+    /// they came from. The fragments of a peer exceeding
+    /// `MAX_RECEIPT_PROOF_FRAGMENTS_BYTES_PER_PEER` are dropped.
+    bytes_per_peer: HashMap<PeerId, usize>,
+    /// Proofs which were already reassembled, so that the remaining fragments
+    /// are dropped instead of being collected again.
+    assembled: LruCache<FragmentsKey, ()>,
+    /// Reassembled proofs which still need to be verified against the
+    /// receipts root in the chunk header.
+    proofs: LruCache<ChunkHash, Vec<(usize, ReceiptProof)>>,
+    /// Total size of the proofs in `proofs`.
+    proofs_bytes: usize,
+}
+
+impl ReceiptProofFragmentsCache {
+    pub fn new() -> Self {
+        let capacity = NonZeroUsize::new(RECEIPT_PROOF_FRAGMENTS_CACHE_SIZE).unwrap();
+        Self {
+            fragments: LruCache::new(capacity),
+            bytes_per_peer: HashMap::new(),
+            assembled: LruCache::new(capacity),
+            proofs: LruCache::new(capacity),
+            proofs_bytes: 0,
+        }
+    }
+
+    /// Adds a fragment received from `peer_id`, which must have been verified
+    /// with `ReceiptProofFragment::verify`, and returns the reassembled proof
+    /// once enough of its fragments are received. Returns `Ok(None)` if the
+    /// fragment is dropped because the peer sent too many fragments.
+    pub fn insert_fragment(
+        &mut self,
+        peer_id: PeerId,
+        fragment: ReceiptProofFragment,
+    ) -> Result<Option<ReceiptProof>, ReceiptProofFragmentsError> {
+        let key = FragmentsKey::new(&fragment);
+        if self.assembled.contains(&key) {
+            return Ok(None);
+        }
+        let size = fragment.data.len();
+        let peer_bytes = self.bytes_per_peer.get(&peer_id).copied().unwrap_or_default();
+        if peer_bytes + size > MAX_RECEIPT_PROOF_FRAGMENTS_BYTES_PER_PEER {
+            tracing::debug!(target: "chunks", %peer_id, chunk_hash = ?key.chunk_hash, "Dropping receipt proof fragment over the peer limit");
+            return Ok(None);
+        }
+        if !self.fragments.contains(&key) {
+            if let Some((_, evicted)) = self.fragments.push(key.clone(), HashMap::new()) {
+                self.release(evicted);
+            }
+        }
+        let fragments = self.fragments.get_mut(&key).unwrap();
+        if fragments.contains_key(&fragment.fragment_ord) {
+            return Ok(None);
+        }
+        fragments.insert(fragment.fragment_ord, (peer_id.clone(), fragment));
+        *self.bytes_per_peer.entry(peer_id).or_default() += size;
+        if fragments.len() < key.num_data_fragments as usize {
+            return Ok(None);
+        }
+        let fragments = self.fragments.pop(&key).unwrap_or_default();
+        let fragments = self.release(fragments);
+        self.assembled.put(key, ());
+        assemble_receipt_proof(fragments).map(Some)
+    }
+
+    /// Subtracts the sizes of the fragments from the bytes of their peers.
+    fn release(&mut self, fragments: Fragments) -> Vec<ReceiptProofFragment> {
+        fragments
+            .into_values()
+            .map(|(peer_id, fragment)| {
+                if let Some(bytes) = self.bytes_per_peer.get_mut(&peer_id) {
+                    *bytes -= fragment.data.len();
+                    if *bytes == 0 {
+                        self.bytes_per_peer.remove(&peer_id);
+                    }
+                }
+                fragment
+            })
+            .collect()
+    }
+
+    /// Keeps a reassembled proof until the header of its chunk is known. The
+    /// least recently used proofs are dropped to stay within
+    /// `MAX_RECEIPT_PROOFS_BYTES`.
+    pub fn insert_proof(&mut self, chunk_hash: ChunkHash, proof: ReceiptProof) {
+        let size = borsh::object_length(&proof).unwrap();
+        if !self.proofs.contains(&chunk_hash) {
+            if let Some((_, evicted)) = self.proofs.push(chunk_hash.clone(), Vec::new()) {
+                self.proofs_bytes -= evicted.iter().map(|(size, _)| size).sum::<usize>();
+            }
+        }
+        self.proofs.get_mut(&chunk_hash).unwrap().push((size, proof));
+        self.proofs_bytes += size;
+        while self.proofs_bytes > MAX_RECEIPT_PROOFS_BYTES {
+            let Some((_, evicted)) = self.proofs.pop_lru() else {
+                break;
+            };
+            self.proofs_bytes -= evicted.iter().map(|(size, _)| size).sum::<usize>();
+        }
+    }
+
+    pub fn pop_proofs(&mut self, chunk_hash: &ChunkHash) -> Vec<ReceiptProof> {
+        let proofs = self.proofs.pop(chunk_hash).unwrap_or_default();
+        self.proofs_bytes -= proofs.iter().map(|(size, _)| size).sum::<usize>();
+        proofs.into_iter().map(|(_, proof)| proof).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{KeyType, PublicKey};
+    use near_primitives::receipt::{Receipt, ReceiptPriority};
+    use near_primitives::sharding::ShardProof;
+
+    fn receipt_proof(num_receipts: u128) -> ReceiptProof {
+        let receipts = (0..num_receipts)
+            .map(|i| {
+                Receipt::new_balance_refund(
+                    &format!("account{i}.near").parse().unwrap(),
+                    i,
+                    ReceiptPriority::NoPriority,
+                )
+            })
+            .collect();
+        let shard_proof = ShardProof {
+            from_shard_id: ShardId::new(0),
+            to_shard_id: ShardId::new(1),
+            proof: vec![],
+        };
+        ReceiptProof(receipts, shard_proof)
+    }
+
+    fn peer_id(seed: &str) -> PeerId {
+        PeerId::new(PublicKey::from_seed(KeyType::ED25519, seed))
+    }
+
+    #[test]
+    fn test_reassemble_receipt_proof() {
+        let proof = receipt_proof(100);
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        let fragments = split_receipt_proof(&chunk_hash, &proof, 1000);
+        let num_data_fragments = fragments[0].num_data_fragments as usize;
+        assert!(num_data_fragments > 2);
+
+        let mut cache = ReceiptProofFragmentsCache::new();
+        let peer_id = peer_id("producer");
+        // Fragments of a forged proof don't interfere with the real one.
+        let mut forged = fragments[0].clone();
+        forged.fragments_root = CryptoHash::default();
+        assert_eq!(cache.insert_fragment(peer_id.clone(), forged), Ok(None));
+
+        // The parity fragments are enough, the first data fragment is lost.
+        let (last, rest) = fragments[1..=num_data_fragments].split_last().unwrap();
+        for fragment in rest.iter().rev() {
+            assert_eq!(cache.insert_fragment(peer_id.clone(), fragment.clone()), Ok(None));
+            // Duplicates are ignored.
+            assert_eq!(cache.insert_fragment(peer_id.clone(), fragment.clone()), Ok(None));
+        }
+        assert_eq!(cache.insert_fragment(peer_id.clone(), last.clone()), Ok(Some(proof.clone())));
+        // The fragments arriving after the proof was reassembled are dropped.
+        assert_eq!(cache.insert_fragment(peer_id.clone(), fragments[0].clone()), Ok(None));
+        // Only the forged fragment is left.
+        assert_eq!(cache.bytes_per_peer[&peer_id], fragments[0].data.len());
+
+        cache.insert_proof(chunk_hash.clone(), proof.clone());
+        assert_eq!(cache.pop_proofs(&chunk_hash), vec![proof]);
+        assert!(cache.pop_proofs(&chunk_hash).is_empty());
+        assert_eq!(cache.proofs_bytes, 0);
+    }
+
+    /// A peer can't fill the cache with fragments which are never completed.
+    #[test]
+    fn test_receipt_proof_fragments_bytes_per_peer() {
+        let proof = receipt_proof(3000);
+        let size = borsh::object_length(&proof).unwrap();
+        let fragments = split_receipt_proof(&ChunkHash::default(), &proof, size.div_ceil(2));
+        let fragment_size = fragments[0].data.len();
+        let max_fragments = MAX_RECEIPT_PROOF_FRAGMENTS_BYTES_PER_PEER / fragment_size;
+        assert!(max_fragments < RECEIPT_PROOF_FRAGMENTS_CACHE_SIZE);
+
+        let mut cache = ReceiptProofFragmentsCache::new();
+        let spammer = peer_id("spammer");
+        for i in 0..max_fragments + 10 {
+            let mut fragment = fragments[0].clone();
+            fragment.chunk_hash = ChunkHash(CryptoHash::hash_borsh(i));
+            assert_eq!(cache.insert_fragment(spammer.clone(), fragment), Ok(None));
+        }
+        assert_eq!(cache.bytes_per_peer[&spammer], max_fragments * fragment_size);
+        assert_eq!(cache.fragments.len(), max_fragments);
+
+        // The fragments of other peers are still accepted.
+        let producer = peer_id("producer");
+        let num_data_fragments = fragments[0].num_data_fragments as usize;
+        let mut result = Ok(None);
+        for fragment in &fragments[..num_data_fragments] {
+            result = cache.insert_fragment(producer.clone(), fragment.clone());
+        }
+        assert_eq!(result, Ok(Some(proof)));
+        assert!(!cache.bytes_per_peer.contains_key(&producer));
+    }
+}
@@ -52,6 +52,16 @@
 //! or partial chunk requests. Before that, they are temporarily stored in `chunk_forwards_cache`.
 //! After that, they are processed as a PartialEncodedChunk message containing the cached parts.
 //!
+//! ** Receipt proof fragments
+//! With `ProtocolFeature::ReceiptProofFragments`, receipt proofs larger than a chunk part are not
+//! sent in the PartialEncodedChunk messages of the chunk producer, but erasure coded into
+//! fragments of about the size of a part, which are sent as separate ReceiptProofFragment
+//! messages. Fragments are collected in `receipt_proof_fragments` until enough of them are
+//! received to reassemble the proof, after which it is processed like a receipt proof received
+//! in a PartialEncodedChunk message. The size of the fragments kept for each peer is limited. If
+//! too many fragments are lost, the proof is requested as usual with a
+//! PartialEncodedChunkRequest.
+//!
 //! ** Processing chunks
 //! Function `process_partial_encoded_chunk` processes a partial encoded chunk message.
 //! 1) validates the parts and receipts in the message
@@ -80,16 +90,20 @@
 
 use crate::adapter::ShardsManagerRequestFromClient;
 use crate::chunk_cache::{EncodedChunksCache, EncodedChunksCacheEntry};
+use crate::chunk_fetch::ChunkFetchPool;
 use crate::client::ShardsManagerResponse;
 use crate::logic::{
     chunk_needs_to_be_fetched_from_archival, create_partial_chunk, make_outgoing_receipts_proofs,
     make_partial_encoded_chunk_from_owned_parts_and_needed_receipts, need_part, need_receipt,
 };
 use crate::metrics;
+use crate::receipt_proof_fragments::{
+    ReceiptProofFragmentsCache, max_receipt_proof_fragment_size, split_large_receipt_proofs,
+};
 use ::time::ext::InstantExt as _;
 use actix::Actor;
 use near_async::actix_wrapper::ActixWrapper;
-use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt};
+use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt, PeriodicSchedule};
 use near_async::messaging::{self, Handler, HandlerWithContext, Sender};
 use near_async::time::Duration;
 use near_async::time::{self, Clock};
@@ -106,8 +120,8 @@ pub use near_chunks_primitives::Error;
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_network::shards_manager::ShardsManagerRequestFromNetwork;
 use near_network::types::{
-    AccountIdOrPeerTrackingShard, PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg,
-    PartialEncodedChunkResponseMsg,
+    AccountIdOrPeerTrackingShard, ChunkRequestMsg, ChunkResponseMsg, PartialEncodedChunkForwardMsg,
+    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
 };
 use near_network::types::{NetworkRequests, PeerManagerMessageRequest};
 use near_performance_metrics_macros::perf;
@@ -115,8 +129,10 @@ use near_primitives::block::Tip;
 use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, verify_path};
+use near_primitives::network::PeerId;
 use near_primitives::receipt::Receipt;
 use near_primitives::reed_solomon::{reed_solomon_decode, reed_solomon_encode};
+use near_primitives::sharding::receipt_proof_fragment::ReceiptProofFragment;
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, EncodedShardChunkBody, PartialEncodedChunk,
     PartialEncodedChunkPart, PartialEncodedChunkV2, ShardChunk, ShardChunkHeader,
@@ -128,6 +144,7 @@ use near_primitives::types::{
 };
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::MaybeValidated;
+use near_primitives::version::ProtocolFeature;
 use near_store::adapter::StoreAdapter;
 use near_store::adapter::chunk_store::ChunkStoreAdapter;
 use near_store::{DBCol, HEAD_KEY, HEADER_HEAD_KEY, Store};
@@ -278,6 +295,8 @@ pub struct ShardsManagerActor {
     encoded_chunks: EncodedChunksCache,
     requested_partial_encoded_chunks: RequestPool,
     chunk_forwards_cache: lru::LruCache<ChunkHash, HashMap<u64, PartialEncodedChunkPart>>,
+    receipt_proof_fragments: ReceiptProofFragmentsCache,
+    chunk_fetches: ChunkFetchPool,
 
     // This is a best-effort cache of the chain's head, not the source of truth. The source
     // of truth is in the chain store and written to by the Client.
@@ -405,6 +424,8 @@ impl ShardsManagerActor {
             chunk_forwards_cache: lru::LruCache::new(
                 NonZeroUsize::new(CHUNK_FORWARD_CACHE_SIZE).unwrap(),
             ),
+            receipt_proof_fragments: ReceiptProofFragmentsCache::new(),
+            chunk_fetches: ChunkFetchPool::new(),
             chain_head: initial_chain_head,
             chain_header_head: initial_chain_header_head,
             chunk_request_retry_period,
@@ -415,13 +436,10 @@ impl ShardsManagerActor {
         &mut self,
         delayed_action_runner: &mut dyn DelayedActionRunner<Self>,
     ) {
-        delayed_action_runner.run_later(
+        delayed_action_runner.run_periodically(
             "resend_chunk_requests",
-            self.chunk_request_retry_period,
-            move |this, delayed_action_runner| {
-                this.resend_chunk_requests();
-                this.periodically_resend_chunk_requests(delayed_action_runner);
-            },
+            PeriodicSchedule::new(self.chunk_request_retry_period),
+            Self::resend_chunk_requests,
         )
     }
 
@@ -894,6 +912,136 @@ impl ShardsManagerActor {
                 }
             }
         }
+        // Request the fetched chunks from other peers if the responses didn't arrive.
+        for header in self.chunk_fetches.fetch(self.clock.now().into()) {
+            self.request_chunk_fetch(&header, me.as_ref());
+        }
+    }
+
+    /// Starts fetching the whole chunk, see `ShardsManagerRequestFromClient::FetchChunk`.
+    fn fetch_chunk(&mut self, header: ShardChunkHeader, me: Option<&AccountId>) {
+        let chunk_hash = header.chunk_hash();
+        if let Ok(chunk) = self.store.get_chunk(&chunk_hash) {
+            debug!(target: "chunks", ?chunk_hash, "Not fetching chunk, already stored.");
+            self.client_adapter.send(ShardsManagerResponse::ChunkFetched(chunk));
+            return;
+        }
+        if !self.chunk_fetches.insert(header.clone(), self.clock.now().into()) {
+            debug!(target: "chunks", ?chunk_hash, "Not fetching chunk, already being fetched.");
+            return;
+        }
+        self.request_chunk_fetch(&header, me);
+    }
+
+    /// Requests the whole chunk from a peer tracking its shard, which has to be
+    /// an archival one if the chunk isn't from the recent epochs.
+    fn request_chunk_fetch(&self, header: &ShardChunkHeader, me: Option<&AccountId>) {
+        let prev_block_hash = header.prev_block_hash();
+        let shard_id = header.shard_id();
+        // The epoch of a chunk too old to be known to the epoch manager is
+        // garbage collected, so only archival nodes have the chunk.
+        let request_from_archival = chunk_needs_to_be_fetched_from_archival(
+            prev_block_hash,
+            &self.chain_header_head.last_block_hash,
+            self.epoch_manager.as_ref(),
+        )
+        .unwrap_or(true);
+        let account_id = if request_from_archival {
+            None
+        } else {
+            self.get_random_target_tracking_shard(prev_block_hash, shard_id, me).unwrap_or_default()
+        };
+        debug!(target: "chunks", chunk_hash = ?header.chunk_hash(), ?shard_id, ?account_id, request_from_archival, "Requesting whole chunk");
+        let target = AccountIdOrPeerTrackingShard {
+            account_id,
+            prefer_peer: true,
+            shard_id,
+            only_archival: request_from_archival,
+            min_height: header.height_created(),
+        };
+        self.peer_manager_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::ChunkRequest {
+                target,
+                request: ChunkRequestMsg { chunk_hash: header.chunk_hash() },
+            },
+        ));
+    }
+
+    fn process_chunk_request(&self, request: ChunkRequestMsg, route_back: CryptoHash) {
+        let _span = tracing::debug_span!(
+            target: "chunks",
+            "process_chunk_request",
+            chunk_hash = %request.chunk_hash.0)
+        .entered();
+        let response = self.prepare_chunk_response(request.chunk_hash);
+        if response.parts.is_empty() {
+            debug!(target: "chunks", "Not responding, chunk not found");
+            return;
+        }
+        self.peer_manager_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::ChunkResponse { route_back, response },
+        ));
+    }
+
+    /// Finds enough parts to reconstruct the chunk. They are encoded from the
+    /// stored chunk if the cache and the partial chunk don't have enough of them.
+    fn prepare_chunk_response(&self, chunk_hash: ChunkHash) -> ChunkResponseMsg {
+        let num_data_parts = self.epoch_manager.num_data_parts();
+        let all_part_ords =
+            (0..self.epoch_manager.num_total_parts() as u64).collect::<HashSet<_>>();
+        let (_, mut response) =
+            self.prepare_partial_encoded_chunk_response(PartialEncodedChunkRequestMsg {
+                chunk_hash: chunk_hash.clone(),
+                part_ords: all_part_ords.iter().copied().collect(),
+                tracking_shards: HashSet::new(),
+            });
+        if response.parts.len() < num_data_parts {
+            if let Ok(chunk) = self.store.get_chunk(&chunk_hash) {
+                response.parts.clear();
+                self.lookup_partial_encoded_chunk_from_chunk_storage(
+                    all_part_ords,
+                    HashSet::new(),
+                    &mut response,
+                    &chunk,
+                );
+            }
+        }
+        let mut parts = response.parts;
+        parts.sort_by_key(|part| part.part_ord);
+        parts.truncate(num_data_parts);
+        ChunkResponseMsg { chunk_hash, parts }
+    }
+
+    /// Adds the parts of a chunk being fetched, and once there are enough of
+    /// them, decodes the chunk and sends it to the client.
+    fn process_chunk_response(&mut self, response: ChunkResponseMsg) -> Result<(), Error> {
+        let num_total_parts = self.epoch_manager.num_total_parts();
+        let Some(fetch) = self.chunk_fetches.get_mut(&response.chunk_hash) else {
+            debug!(target: "chunks", chunk_hash = ?response.chunk_hash, "Ignoring response for chunk not being fetched");
+            return Ok(());
+        };
+        fetch.add_parts(response.parts, num_total_parts)?;
+        if fetch.parts.len() < self.epoch_manager.num_data_parts() {
+            return Ok(());
+        }
+
+        // The parts match the header, so if they can't be decoded the chunk
+        // itself is invalid and fetching it again wouldn't help.
+        let fetch = self.chunk_fetches.remove(&response.chunk_hash).unwrap();
+        let mut encoded_chunk = EncodedShardChunk::from_header(fetch.header, num_total_parts);
+        for (part_ord, part) in fetch.parts {
+            encoded_chunk.content_mut().parts[part_ord as usize] = Some(part.part);
+        }
+        let ChunkStatus::Complete(_) = self.check_chunk_complete(&mut encoded_chunk) else {
+            return Err(Error::InvalidChunk);
+        };
+        let chunk = ShardChunkWithEncoding::from_encoded_shard_chunk(encoded_chunk)?;
+        if !validate_chunk_proofs(chunk.to_shard_chunk(), self.view_epoch_manager.as_ref())? {
+            return Err(Error::InvalidChunk);
+        }
+        debug!(target: "chunks", chunk_hash = ?response.chunk_hash, "Fetched chunk");
+        self.client_adapter.send(ShardsManagerResponse::ChunkFetched(chunk.into_parts().0));
+        Ok(())
     }
 
     fn process_partial_encoded_chunk_request(
@@ -1357,6 +1505,42 @@ impl ShardsManagerActor {
         Ok(())
     }
 
+    /// Processes a fragment of a receipt proof sent by `peer_id`, see `ReceiptProofFragment`. Once
+    /// enough fragments of the proof are received, the proof is processed like a partial encoded
+    /// chunk containing it, or cached until the chunk header is known.
+    fn process_receipt_proof_fragment(
+        &mut self,
+        fragment: ReceiptProofFragment,
+        peer_id: PeerId,
+        me: Option<&AccountId>,
+    ) -> Result<(), Error> {
+        if !fragment.verify() {
+            return Err(Error::InvalidReceiptProofFragment);
+        }
+        let chunk_hash = fragment.chunk_hash.clone();
+        let receipt_proof = match self.receipt_proof_fragments.insert_fragment(peer_id, fragment) {
+            Ok(Some(receipt_proof)) => receipt_proof,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                debug!(target: "chunks", ?chunk_hash, ?err, "Failed to reassemble receipt proof");
+                return Err(Error::InvalidReceiptProofFragment);
+            }
+        };
+        let header = match self.get_partial_encoded_chunk_header(&chunk_hash) {
+            Ok(header) => header,
+            Err(Error::UnknownChunk) => {
+                self.receipt_proof_fragments.insert_proof(chunk_hash, receipt_proof);
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        let partial_chunk = PartialEncodedChunk::new(header, vec![], vec![receipt_proof]);
+        // The header was validated when it was inserted in `encoded_chunks`, the receipt proof
+        // is validated against it in `process_partial_encoded_chunk`.
+        self.process_partial_encoded_chunk(MaybeValidated::from_validated(partial_chunk), me)?;
+        Ok(())
+    }
+
     /// Validate a chunk header
     /// 1) check that the chunk header is signed by the correct chunk producer for the chunk at
     ///    the height for the shard
@@ -1460,6 +1644,7 @@ impl ShardsManagerActor {
         if self.encoded_chunks.get_or_insert_from_header(header).complete {
             return false;
         }
+        let mut processed_cached = false;
         if let Some(parts) = self.chunk_forwards_cache.pop(&header.chunk_hash()) {
             // Note that we don't need any further validation for the forwarded part.
             // The forwarded part was earlier validated via validate_partial_encoded_chunk_forward,
@@ -1474,9 +1659,25 @@ impl ShardsManagerActor {
                 parts.into_values(),
                 Vec::new().into_iter(),
             );
-            return true;
+            processed_cached = true;
+        }
+        // Unlike forwarded parts, receipt proofs reassembled from fragments were not validated
+        // against anything from the chunk header yet.
+        let receipts = self
+            .receipt_proof_fragments
+            .pop_proofs(&header.chunk_hash())
+            .into_iter()
+            .filter(|proof| proof.verify_against_receipt_root(header.prev_outgoing_receipts_root()))
+            .collect::<Vec<_>>();
+        if !receipts.is_empty() {
+            self.encoded_chunks.merge_in_partial_encoded_chunk(
+                header,
+                Vec::new().into_iter(),
+                receipts.into_iter(),
+            );
+            processed_cached = true;
         }
-        !header_known_before
+        processed_cached || !header_known_before
     }
 
     /// Processes a partial encoded chunk message, which means
@@ -2059,18 +2260,33 @@ impl ShardsManagerActor {
         .into_iter()
         .map(Arc::new)
         .collect::<Vec<_>>();
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
+        let (receipt_proofs, receipt_proof_fragments) =
+            if ProtocolFeature::ReceiptProofFragments.enabled(protocol_version) {
+                let max_fragment_size = max_receipt_proof_fragment_size(
+                    encoded_chunk.encoded_length(),
+                    self.epoch_manager.num_total_parts(),
+                );
+                split_large_receipt_proofs(
+                    &chunk_header.chunk_hash(),
+                    receipt_proofs,
+                    max_fragment_size,
+                )
+            } else {
+                (receipt_proofs, vec![])
+            };
         for (to_whom, part_ords) in block_producer_mapping {
+            let cares_about_shard = |shard_id| {
+                self.shard_tracker.cares_about_shard_this_or_next_epoch(
+                    Some(&to_whom),
+                    &prev_block_hash,
+                    shard_id,
+                    false,
+                )
+            };
             let part_receipt_proofs = receipt_proofs
                 .iter()
-                .filter(|proof| {
-                    let proof_shard_id = proof.1.to_shard_id;
-                    self.shard_tracker.cares_about_shard_this_or_next_epoch(
-                        Some(&to_whom),
-                        &prev_block_hash,
-                        proof_shard_id,
-                        false,
-                    )
-                })
+                .filter(|proof| cares_about_shard(proof.1.to_shard_id))
                 .cloned()
                 .collect();
 
@@ -2088,6 +2304,16 @@ impl ShardsManagerActor {
                         partial_encoded_chunk,
                     },
                 ));
+                for fragment in &receipt_proof_fragments {
+                    if cares_about_shard(fragment.to_shard_id) {
+                        self.peer_manager_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                            NetworkRequests::ReceiptProofFragment {
+                                account_id: to_whom.clone(),
+                                fragment: fragment.clone(),
+                            },
+                        ));
+                    }
+                }
             }
         }
 
@@ -2141,6 +2367,9 @@ impl ShardsManagerActor {
                 epoch_id,
                 ancestor_hash,
             } => self.request_chunks_for_orphan(chunks_to_request, &epoch_id, ancestor_hash, me),
+            ShardsManagerRequestFromClient::FetchChunk(chunk_header) => {
+                self.fetch_chunk(chunk_header, me)
+            }
             ShardsManagerRequestFromClient::CheckIncompleteChunks(prev_block_hash) => {
                 self.check_incomplete_chunks(&prev_block_hash, me)
             }
@@ -2216,6 +2445,15 @@ impl ShardsManagerActor {
                         |_|{ return HandleNetworkRequestResult::Ok; }
                     )
             }
+            ShardsManagerRequestFromNetwork::ProcessReceiptProofFragment { fragment, peer_id } => {
+                match self.process_receipt_proof_fragment(fragment, peer_id, me) {
+                    Ok(()) => HandleNetworkRequestResult::Ok,
+                    Err(err) => {
+                        warn!(target: "chunks", ?err, "Error processing receipt proof fragment");
+                        HandleNetworkRequestResult::Err
+                    }
+                }
+            }
             ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkResponse {
                 partial_encoded_chunk_response,
                 received_time,
@@ -2243,6 +2481,19 @@ impl ShardsManagerActor {
                 );
                 HandleNetworkRequestResult::Ok
             }
+            ShardsManagerRequestFromNetwork::ProcessChunkRequest { chunk_request, route_back } => {
+                self.process_chunk_request(chunk_request, route_back);
+                HandleNetworkRequestResult::Ok
+            }
+            ShardsManagerRequestFromNetwork::ProcessChunkResponse(response) => {
+                match self.process_chunk_response(response) {
+                    Ok(()) => HandleNetworkRequestResult::Ok,
+                    Err(err) => {
+                        warn!(target: "chunks", ?err, "Error processing chunk response");
+                        HandleNetworkRequestResult::Err
+                    }
+                }
+            }
         }
     }
 }
@@ -2945,6 +3196,63 @@ mod test {
         assert_eq!(response.parts.len(), fixture.all_part_ords.len());
     }
 
+    #[test]
+    fn test_fetch_chunk() {
+        let mut fixture = ChunkTestFixture::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            FakeClock::default().clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.store.clone(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        shards_manager.handle_client_request(ShardsManagerRequestFromClient::FetchChunk(
+            fixture.mock_chunk_header.clone(),
+        ));
+        match fixture.mock_network.pop().unwrap().as_network_requests() {
+            NetworkRequests::ChunkRequest { request, .. } => {
+                assert_eq!(request.chunk_hash, chunk_hash)
+            }
+            request => panic!("unexpected request {:?}", request),
+        }
+
+        // Serve the response from the stored chunk, as an archival node would.
+        let mut update = fixture.chain_store.store_update();
+        update.save_chunk(fixture.mock_encoded_chunk.decode_chunk().unwrap());
+        update.commit().unwrap();
+        let mut response = shards_manager.prepare_chunk_response(chunk_hash.clone());
+        assert_eq!(response.parts.len(), fixture.epoch_manager.num_data_parts());
+
+        let mut invalid_response = response.clone();
+        invalid_response.parts[0].part = vec![0; 10].into_boxed_slice();
+        assert_matches!(
+            shards_manager.process_chunk_response(invalid_response),
+            Err(Error::InvalidMerkleProof)
+        );
+
+        // The chunk is decoded once enough parts are received.
+        let last_part = response.parts.pop().unwrap();
+        shards_manager.process_chunk_response(response).unwrap();
+        assert!(fixture.mock_client_adapter.pop().is_none());
+        shards_manager
+            .process_chunk_response(ChunkResponseMsg {
+                chunk_hash: chunk_hash.clone(),
+                parts: vec![last_part],
+            })
+            .unwrap();
+        assert_matches!(
+            fixture.mock_client_adapter.pop(),
+            Some(ShardsManagerResponse::ChunkFetched(chunk)) if chunk.chunk_hash() == chunk_hash
+        );
+    }
+
     #[test]
     fn test_chunk_response_combining_cache_and_partial_chunks() {
         let mut fixture = ChunkTestFixture::default();
@@ -170,7 +170,8 @@ impl ChunkTestFixture {
             BandwidthRequests::empty(),
             &signer,
             &rs,
-        );
+        )
+        .unwrap();
 
         let mock_encoded_chunk = mock_chunk.into_parts().1;
 
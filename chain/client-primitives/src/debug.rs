@@ -2,6 +2,7 @@
 //! without backwards compatibility of JSON encoding.
 use crate::types::StatusError;
 use near_primitives::congestion_info::CongestionInfo;
+use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::{EpochId, ShardId};
 use near_primitives::views::{
     CatchupStatusView, ChainProcessingInfo, EpochValidatorInfo, RequestedStatePartsView,
@@ -25,6 +26,61 @@ pub struct TrackedShardsView {
     pub shards_tracked_next_epoch: Vec<bool>,
 }
 
+/// Flat storage rebuild started by the debug API.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct FlatStorageRebuildView {
+    pub shard_uid: ShardUId,
+    /// The final block the flat storage is rebuilt at.
+    pub flat_head_hash: CryptoHash,
+    pub flat_head_height: BlockHeight,
+}
+
+/// Chunk fetch started by the debug API.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ChunkFetchView {
+    pub chunk_hash: ChunkHash,
+    /// Whether the chunk was already in the store, in which case it isn't
+    /// fetched again.
+    pub already_saved: bool,
+}
+
+/// Kind of a discrepancy found by the receipt audit.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptAuditDiscrepancyKind {
+    /// Produced by a shard but not delivered with its next chunk.
+    Lost,
+    /// Delivered more than once.
+    Duplicate,
+    /// Delivered with a chunk of a shard, but not produced by the previous
+    /// chunk of the shard.
+    Unexpected,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptAuditDiscrepancyView {
+    pub kind: ReceiptAuditDiscrepancyKind,
+    pub receipt_id: CryptoHash,
+    pub from_shard_id: ShardId,
+    pub to_shard_id: ShardId,
+    /// The block in which the chunk of `from_shard_id` producing the receipt
+    /// was applied. `None` unless the receipt is lost.
+    pub produced_in_block: Option<CryptoHash>,
+    /// The block with the chunk which delivered the receipt, or should have.
+    pub delivery_block_hash: CryptoHash,
+    pub delivery_block_height: BlockHeight,
+}
+
+/// State of the audit of the receipts delivered between the shards.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ReceiptAuditView {
+    /// Height of the last final block audited.
+    pub audited_height: Option<BlockHeight>,
+    pub num_receipts_produced: u64,
+    pub num_receipts_delivered: u64,
+    /// The most recent discrepancies, newest first.
+    pub discrepancies: Vec<ReceiptAuditDiscrepancyView>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct EpochInfoView {
     pub epoch_height: u64,
@@ -267,6 +323,12 @@ pub enum DebugStatus {
     ChainProcessingStatus,
     // The state parts already requested.
     RequestedStateParts,
+    // Starts the rebuild of the flat storage of the shard.
+    RebuildFlatStorage(ShardId),
+    // Fetches the chunk of the shard included in the block from the peers.
+    FetchChunk(CryptoHash, ShardId),
+    // Discrepancies between the receipts produced and delivered by the shards.
+    ReceiptAudit,
 }
 
 impl actix::Message for DebugStatus {
@@ -288,4 +350,7 @@ pub enum DebugStatusResponse {
     ChainProcessingStatus(ChainProcessingInfo),
     // The state parts already requested.
     RequestedStateParts(Vec<RequestedStatePartsView>),
+    FlatStorageRebuild(FlatStorageRebuildView),
+    ChunkFetch(ChunkFetchView),
+    ReceiptAudit(ReceiptAuditView),
 }
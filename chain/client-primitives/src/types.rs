@@ -10,10 +10,12 @@ use near_primitives::types::{
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, GasPriceView,
-    LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView, QueryRequest,
-    QueryResponse, ReceiptView, SplitStorageInfoView, StateChangesKindsView,
-    StateChangesRequestView, StateChangesView, StateSyncStatusView, SyncStatusView, TxStatusView,
+    AccountsBatchView, BlockView, ChunkView, EpochValidatorDutiesView, EpochValidatorInfo,
+    ExecutionOutcomeWithIdView, GasPriceView, LightClientBlockLiteView, LightClientBlockView,
+    MaintenanceWindowsView, ProtocolVersionVotesView, QueryRequest, QueryResponse, ReceiptTreeView,
+    ReceiptView, SignerTransactionView, SplitStorageInfoView, StateChangesKindsView,
+    StateChangesRequestView, StateChangesView, StateSyncStatusView, SyncStatusView,
+    TxForwardingRecordView, TxRoutingStatusView, TxStatusView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 use near_time::Duration;
@@ -42,6 +44,12 @@ impl From<near_primitives::errors::EpochError> for Error {
     }
 }
 
+impl From<near_primitives::validator_signer::SignerError> for Error {
+    fn from(err: near_primitives::validator_signer::SignerError) -> Self {
+        Error::Chain(err.into())
+    }
+}
+
 /// Various status of syncing a specific shard.
 #[derive(Clone, Debug, Copy)]
 pub enum ShardSyncStatus {
@@ -377,7 +385,46 @@ impl Message for Query {
     type Result = Result<QueryResponse, QueryError>;
 }
 
-#[derive(thiserror::Error, Debug)]
+/// Maximum number of requests in a single `QueryBatch`.
+pub const MAX_QUERY_BATCH_SIZE: usize = 100;
+
+/// Maximum number of requests running contract code, i.e. function calls and
+/// dry runs of transactions, in a single `QueryBatch`. The whole batch runs on
+/// one thread of the view client, which these would otherwise hold for as long
+/// as a hundred separate queries.
+pub const MAX_QUERY_BATCH_EXECUTIONS: usize = 10;
+
+/// Runs several queries against the same block. The block is looked up once,
+/// and so is the state root of every shard the requests touch. The requests
+/// which only read the state of a shard share its trie. Every request gets its
+/// own result; the batch as a whole only fails if the block does, or if it has
+/// more requests than the limits above.
+#[derive(Clone, Debug)]
+pub struct QueryBatch {
+    pub block_reference: BlockReference,
+    pub requests: Vec<QueryRequest>,
+}
+
+impl Message for QueryBatch {
+    type Result = Result<Vec<Result<QueryResponse, QueryError>>, QueryError>;
+}
+
+/// Maximum number of accounts in a single `ViewAccountsBatch` request.
+pub const MAX_VIEW_ACCOUNTS_BATCH_SIZE: usize = 100;
+
+/// Looks up the accounts as of the final block. Unlike `Query`, the accounts
+/// are read directly from flat storage, so all of them need to belong to
+/// shards tracked by the node.
+#[derive(Clone, Debug)]
+pub struct ViewAccountsBatch {
+    pub account_ids: Vec<AccountId>,
+}
+
+impl Message for ViewAccountsBatch {
+    type Result = Result<AccountsBatchView, QueryError>;
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
 pub enum QueryError {
     #[error("There are no fully synchronized blocks on the node yet")]
     NoSyncedBlocks,
@@ -423,6 +470,14 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("Transaction is invalid: {error}")]
+    InvalidTransaction {
+        error: near_primitives::errors::InvalidTxError,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("The batch of queries is too large: {error_message}")]
+    BatchTooLarge { error_message: String },
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
     InternalError { error_message: String },
     #[error(
@@ -641,6 +696,29 @@ impl Message for TxStatus {
     type Result = Result<TxStatusView, TxStatusError>;
 }
 
+/// Returns the most recent transactions of a signer, newest first. Requires
+/// `transactions_by_signer_limit` to be set in the client config.
+#[derive(Debug)]
+pub struct GetTransactionsBySigner {
+    pub signer_id: AccountId,
+    /// Return at most this many transactions.
+    pub limit: Option<usize>,
+}
+
+impl Message for GetTransactionsBySigner {
+    type Result = Result<Vec<SignerTransactionView>, TxStatusError>;
+}
+
+/// Returns the tree of receipts executed for a transaction, across shards.
+#[derive(Debug)]
+pub struct GetReceiptTree {
+    pub tx_hash: CryptoHash,
+}
+
+impl Message for GetReceiptTree {
+    type Result = Result<ReceiptTreeView, TxStatusError>;
+}
+
 #[derive(Debug)]
 pub struct GetValidatorInfo {
     pub epoch_reference: EpochReference,
@@ -688,6 +766,27 @@ impl Message for GetValidatorOrdered {
     type Result = Result<Vec<ValidatorStakeView>, GetValidatorInfoError>;
 }
 
+/// Protocol version votes cast in the epoch of the block up to that block.
+#[derive(Debug)]
+pub struct GetProtocolVersionVotes {
+    pub block_id: MaybeBlockId,
+}
+
+impl Message for GetProtocolVersionVotes {
+    type Result = Result<ProtocolVersionVotesView, GetValidatorInfoError>;
+}
+
+/// Duties of the node's own validator account in the most recent epochs, or
+/// only in the given epoch.
+#[derive(Debug)]
+pub struct GetValidatorDuties {
+    pub epoch_id: Option<EpochId>,
+}
+
+impl Message for GetValidatorDuties {
+    type Result = Result<Vec<EpochValidatorDutiesView>, GetValidatorInfoError>;
+}
+
 #[derive(Debug)]
 pub struct GetStateChanges {
     pub block_hash: CryptoHash,
@@ -984,6 +1083,27 @@ impl From<near_chain_primitives::Error> for GetMaintenanceWindowsError {
     }
 }
 
+/// Returns the forwarding decisions recently made by the RPC handler for a transaction.
+#[derive(Debug)]
+pub struct GetTxForwardingInfo {
+    pub tx_hash: CryptoHash,
+}
+
+impl Message for GetTxForwardingInfo {
+    type Result = Vec<TxForwardingRecordView>;
+}
+
+/// Returns the routing status of a transaction submitted to the node, if it was
+/// forwarded to other chunk producers and the rebroadcast is enabled.
+#[derive(Debug)]
+pub struct GetTxRoutingStatus {
+    pub tx_hash: CryptoHash,
+}
+
+impl Message for GetTxRoutingStatus {
+    type Result = Option<TxRoutingStatusView>;
+}
+
 #[derive(Debug)]
 pub struct GetClientConfig {}
 
@@ -1014,6 +1134,41 @@ impl From<near_chain_primitives::Error> for GetClientConfigError {
     }
 }
 
+/// Asks the node to take a restore point of its current head, see
+/// `near_store::config::StateSnapshotPolicy`. Returns the height of the head.
+#[derive(Debug)]
+pub struct RequestRestorePoint {}
+
+impl Message for RequestRestorePoint {
+    type Result = Result<BlockHeight, RequestRestorePointError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RequestRestorePointError {
+    #[error("Restore points are unavailable: {0}")]
+    Unavailable(String),
+    #[error("IO Error: {0}")]
+    IOError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error(
+        "It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}"
+    )]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for RequestRestorePointError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::Other(error) => Self::Unavailable(error),
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GetSplitStorageInfo {}
 
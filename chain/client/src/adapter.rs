@@ -4,13 +4,18 @@ use near_async::actix::AddrWithAutoSpanContextExt;
 use near_async::messaging::IntoSender;
 use near_network::client::ClientSenderForNetwork;
 
+/// The state requests of the peers are sent to `state_request_view_client_addr`,
+/// which can be a view client of its own so that they don't hold up the other
+/// requests.
 pub fn client_sender_for_network(
     client_addr: actix::Addr<ClientActor>,
     view_client_addr: actix::Addr<ViewClientActor>,
+    state_request_view_client_addr: actix::Addr<ViewClientActor>,
     rpc_handler: actix::Addr<RpcHandlerActor>,
 ) -> ClientSenderForNetwork {
     let client_addr = client_addr.with_auto_span_context();
     let view_client_addr = view_client_addr.with_auto_span_context();
+    let state_request_view_client_addr = state_request_view_client_addr.with_auto_span_context();
     let rpc_handler = rpc_handler.with_auto_span_context();
     ClientSenderForNetwork {
         block: client_addr.clone().into_sender(),
@@ -19,8 +24,8 @@ pub fn client_sender_for_network(
         block_headers_request: view_client_addr.clone().into_sender(),
         block_request: view_client_addr.clone().into_sender(),
         network_info: client_addr.clone().into_sender(),
-        state_request_header: view_client_addr.clone().into_sender(),
-        state_request_part: view_client_addr.clone().into_sender(),
+        state_request_header: state_request_view_client_addr.clone().into_sender(),
+        state_request_part: state_request_view_client_addr.into_sender(),
         state_response: client_addr.clone().into_sender(),
         tx_status_request: view_client_addr.clone().into_sender(),
         tx_status_response: view_client_addr.clone().into_sender(),
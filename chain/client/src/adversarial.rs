@@ -1,11 +1,17 @@
 #[cfg(feature = "test_features")]
 mod adv {
+    use near_primitives::block::{Approval, ApprovalInner, Block};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::validator_signer::{SignerError, ValidatorSigner};
     use std::sync::atomic::Ordering;
 
     #[derive(Default)]
     struct Inner {
         disable_header_sync: std::sync::atomic::AtomicBool,
         disable_doomslug: std::sync::atomic::AtomicBool,
+        double_sign_approvals: std::sync::atomic::AtomicBool,
+        produce_equivocating_blocks: std::sync::atomic::AtomicBool,
+        produce_invalid_chunk_headers: std::sync::atomic::AtomicBool,
         is_archival: bool,
     }
 
@@ -33,10 +39,68 @@ mod adv {
             self.0.disable_doomslug.store(value, Ordering::SeqCst);
         }
 
+        /// Whether every approval is sent together with a conflicting
+        /// approval for the same target height, signed with the same key.
+        pub fn double_sign_approvals(&self) -> bool {
+            self.0.double_sign_approvals.load(Ordering::SeqCst)
+        }
+
+        pub fn set_double_sign_approvals(&self, value: bool) {
+            self.0.double_sign_approvals.store(value, Ordering::SeqCst);
+        }
+
+        /// Whether every block produced is sent together with another block
+        /// at the same height on top of the same previous block.
+        pub fn produce_equivocating_blocks(&self) -> bool {
+            self.0.produce_equivocating_blocks.load(Ordering::SeqCst)
+        }
+
+        pub fn set_produce_equivocating_blocks(&self, value: bool) {
+            self.0.produce_equivocating_blocks.store(value, Ordering::SeqCst);
+        }
+
+        /// Whether the chunks produced have a signed header with a state root
+        /// which doesn't match the state after the previous chunk.
+        pub fn produce_invalid_chunk_headers(&self) -> bool {
+            self.0.produce_invalid_chunk_headers.load(Ordering::SeqCst)
+        }
+
+        pub fn set_produce_invalid_chunk_headers(&self, value: bool) {
+            self.0.produce_invalid_chunk_headers.store(value, Ordering::SeqCst);
+        }
+
         pub fn is_archival(&self) -> bool {
             self.0.is_archival
         }
     }
+
+    /// Returns an approval for the same target height as `approval`, signed
+    /// by the same validator, which conflicts with it: an endorsement of the
+    /// parent block if `approval` is a skip, and a skip otherwise.
+    pub(crate) fn conflicting_approval(
+        approval: &Approval,
+        parent_hash: &CryptoHash,
+        signer: &ValidatorSigner,
+    ) -> Result<Approval, SignerError> {
+        let target_height = approval.target_height;
+        let inner = match approval.inner {
+            ApprovalInner::Endorsement(_) => ApprovalInner::Skip(target_height.saturating_sub(2)),
+            ApprovalInner::Skip(_) => ApprovalInner::Endorsement(*parent_hash),
+        };
+        let signature = signer.sign_bytes(&Approval::get_data_for_sig(&inner, target_height))?;
+        Ok(Approval { inner, target_height, signature, account_id: signer.validator_id().clone() })
+    }
+
+    /// Returns a block at the same height and on top of the same previous
+    /// block as `block`, signed by the same block producer, which differs from
+    /// it only by its timestamp.
+    pub(crate) fn equivocating_block(block: &Block, signer: &ValidatorSigner) -> Block {
+        let mut block = block.clone();
+        let header = block.mut_header();
+        header.set_timestamp(header.raw_timestamp() + 1);
+        header.resign(signer);
+        block
+    }
 }
 
 #[cfg(not(feature = "test_features"))]
@@ -60,3 +124,72 @@ mod adv {
 }
 
 pub use adv::Controls;
+#[cfg(feature = "test_features")]
+pub(crate) use adv::{conflicting_approval, equivocating_block};
+
+#[cfg(all(test, feature = "test_features"))]
+mod tests {
+    use super::{conflicting_approval, equivocating_block};
+    use near_async::time::{Clock, Utc};
+    use near_primitives::block::{Approval, ApprovalInner};
+    use near_primitives::genesis::{genesis_block, genesis_chunks};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::test_utils::{TestBlockBuilder, create_test_signer};
+    use near_primitives::types::ShardId;
+    use near_primitives::version::PROTOCOL_VERSION;
+    use near_store::Trie;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_conflicting_approval() {
+        let signer = create_test_signer("test");
+        let parent_hash = CryptoHash::hash_bytes(b"parent");
+        for approval in [
+            Approval::new(parent_hash, 9, 10, &signer).unwrap(),
+            Approval::new(parent_hash, 8, 10, &signer).unwrap(),
+        ] {
+            let conflicting = conflicting_approval(&approval, &parent_hash, &signer).unwrap();
+            assert_eq!(conflicting.target_height, approval.target_height);
+            assert_eq!(conflicting.account_id, approval.account_id);
+            assert_ne!(conflicting.inner, approval.inner);
+            assert!(conflicting.signature.verify(
+                &Approval::get_data_for_sig(&conflicting.inner, conflicting.target_height),
+                &signer.public_key(),
+            ));
+        }
+        let skip = Approval::new(parent_hash, 8, 10, &signer).unwrap();
+        assert_eq!(
+            conflicting_approval(&skip, &parent_hash, &signer).unwrap().inner,
+            ApprovalInner::Endorsement(parent_hash)
+        );
+    }
+
+    #[test]
+    fn test_equivocating_block() {
+        let genesis_chunks = genesis_chunks(
+            vec![Trie::EMPTY_ROOT],
+            vec![Default::default()],
+            &[ShardId::new(0)],
+            1_000_000,
+            0,
+            PROTOCOL_VERSION,
+        );
+        let genesis = genesis_block(
+            PROTOCOL_VERSION,
+            genesis_chunks.into_iter().map(|chunk| chunk.take_header()).collect(),
+            Utc::now_utc(),
+            0,
+            100,
+            1_000_000_000,
+            &vec![],
+        );
+        let signer = Arc::new(create_test_signer("test"));
+        let block = TestBlockBuilder::new(Clock::real(), &genesis, signer.clone()).build();
+
+        let equivocating = equivocating_block(&block, &signer);
+        assert_ne!(equivocating.hash(), block.hash());
+        assert_eq!(equivocating.header().height(), block.header().height());
+        assert_eq!(equivocating.header().prev_hash(), block.header().prev_hash());
+        assert!(equivocating.header().verify_block_producer(&signer.public_key()));
+    }
+}
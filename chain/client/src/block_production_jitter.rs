@@ -0,0 +1,130 @@
+//! Deterministic delay of block production, see
+//! `ClientConfig::block_production_jitter`.
+//!
+//! Block producers with synchronized clocks become ready to produce their
+//! blocks at the same moments, so the blocks, and the chunks and approvals
+//! which follow them, are sent across the network in bursts. Delaying the
+//! production by an offset derived from the block producer and the height
+//! spreads them out. The offset is deterministic, so that the delay of every
+//! block can be told from the logs.
+
+use near_async::time::{Duration, Instant};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockHeight};
+
+use crate::metrics;
+
+/// Block production which is being delayed.
+struct DelayedBlock {
+    height: BlockHeight,
+    prev_block_hash: CryptoHash,
+    until: Instant,
+    offset: Duration,
+}
+
+pub(crate) struct BlockProductionJitter {
+    /// Maximum offset, `None` if the jitter is disabled.
+    max_jitter: Option<Duration>,
+    delayed: Option<DelayedBlock>,
+}
+
+impl BlockProductionJitter {
+    /// The jitter is capped at half of `min_block_production_delay`, so that a
+    /// delayed block still arrives long before the other validators skip its
+    /// height, and the delay can't add up with the next block.
+    pub fn new(max_jitter: Option<Duration>, min_block_production_delay: Duration) -> Self {
+        let max_jitter = max_jitter.map(|jitter| jitter.min(min_block_production_delay / 2));
+        Self { max_jitter, delayed: None }
+    }
+
+    /// Offset of the block produced by `account_id` at `height`, uniformly
+    /// distributed up to the maximum jitter.
+    fn offset(max_jitter: Duration, account_id: &AccountId, height: BlockHeight) -> Duration {
+        let max_nanos = max_jitter.whole_nanoseconds().max(0) as u64;
+        if max_nanos == 0 {
+            return Duration::ZERO;
+        }
+        let hash = CryptoHash::hash_borsh((account_id, height));
+        let value = u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap());
+        Duration::nanoseconds((value % (max_nanos + 1)) as i64)
+    }
+
+    /// Returns whether the block producer which is otherwise ready to produce
+    /// the block at `height` should keep waiting for its offset, counted from
+    /// the first time it was ready.
+    pub fn should_wait(
+        &mut self,
+        now: Instant,
+        account_id: &AccountId,
+        height: BlockHeight,
+        prev_block_hash: &CryptoHash,
+    ) -> bool {
+        let Some(max_jitter) = self.max_jitter else {
+            return false;
+        };
+        let delayed = match self.delayed.take() {
+            Some(delayed)
+                if delayed.height == height && delayed.prev_block_hash == *prev_block_hash =>
+            {
+                delayed
+            }
+            _ => {
+                let offset = Self::offset(max_jitter, account_id, height);
+                DelayedBlock {
+                    height,
+                    prev_block_hash: *prev_block_hash,
+                    until: now + offset,
+                    offset,
+                }
+            }
+        };
+        if now < delayed.until {
+            self.delayed = Some(delayed);
+            return true;
+        }
+        metrics::BLOCK_PRODUCTION_JITTER_DELAY.observe(delayed.offset.as_seconds_f64());
+        tracing::debug!(target: "client", height, offset = ?delayed.offset, "Done waiting for the block production jitter");
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_async::time::{FakeClock, Utc};
+
+    #[test]
+    fn test_block_production_jitter() {
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let prev_block_hash = CryptoHash::default();
+        let account_id: AccountId = "test0".parse().unwrap();
+        let max_jitter = Duration::milliseconds(100);
+        let mut jitter = BlockProductionJitter::new(Some(max_jitter), Duration::milliseconds(600));
+
+        // The offsets are deterministic, bounded and differ between heights.
+        let offsets = (0..100)
+            .map(|height| BlockProductionJitter::offset(max_jitter, &account_id, height))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets[7], BlockProductionJitter::offset(max_jitter, &account_id, 7));
+        assert!(offsets.iter().all(|offset| *offset <= max_jitter));
+        assert!(offsets.iter().any(|offset| *offset != offsets[0]));
+
+        // Waits for the offset of the height.
+        let offset = offsets[10];
+        if offset > Duration::ZERO {
+            assert!(jitter.should_wait(clock.now(), &account_id, 10, &prev_block_hash));
+            clock.advance(offset - Duration::nanoseconds(1));
+            assert!(jitter.should_wait(clock.now(), &account_id, 10, &prev_block_hash));
+            clock.advance(Duration::nanoseconds(1));
+        }
+        assert!(!jitter.should_wait(clock.now(), &account_id, 10, &prev_block_hash));
+
+        // Capped at half of the block production delay.
+        let jitter = BlockProductionJitter::new(Some(max_jitter), Duration::milliseconds(100));
+        assert_eq!(jitter.max_jitter, Some(Duration::milliseconds(50)));
+
+        // Disabled without a maximum jitter.
+        let mut jitter = BlockProductionJitter::new(None, Duration::milliseconds(600));
+        assert!(!jitter.should_wait(clock.now(), &account_id, 11, &prev_block_hash));
+    }
+}
@@ -1,6 +1,7 @@
 //! Helper functions for obtaining chunks from the chunk_distribution_network.
 
 use borsh::BorshDeserialize;
+use futures::future::{self, Either};
 use near_async::messaging::Sender;
 use near_chain::{
     blocks_delay_tracker::BlocksDelayTracker, chain::BlockMissingChunks,
@@ -16,6 +17,8 @@ use near_primitives::{
 use std::fmt;
 use tracing::{debug, error};
 
+use crate::metrics;
+
 /// Basic interface for the chunk distribution network.
 pub trait ChunkDistributionClient {
     type Error;
@@ -55,12 +58,21 @@ impl ChunkDistributionNetwork {
     pub fn enabled(&self) -> bool {
         self.config.enabled
     }
+
+    pub fn config(&self) -> &ChunkDistributionNetworkConfig {
+        &self.config
+    }
 }
 
+/// Requests the missing chunks of the blocks and the orphans. Every chunk is
+/// looked up in the chunk distribution network first, and requested from the
+/// peers if it isn't found there or the lookup takes longer than
+/// `config.hedge_delay`. The failed lookups are retried with backoff.
 pub fn request_missing_chunks<C>(
     blocks_missing_chunks: Vec<BlockMissingChunks>,
     orphans_missing_chunks: Vec<OrphanMissingChunks>,
     client: C,
+    config: &ChunkDistributionNetworkConfig,
     blocks_delay_tracker: &mut BlocksDelayTracker,
     shards_manager_adapter: &Sender<ShardsManagerRequestFromClient>,
 ) where
@@ -68,108 +80,168 @@ pub fn request_missing_chunks<C>(
     C::Error: fmt::Debug,
 {
     for BlockMissingChunks { prev_hash, missing_chunks } in blocks_missing_chunks {
-        for chunk in missing_chunks {
-            blocks_delay_tracker.mark_chunk_requested(&chunk);
-            request_missing_chunk(client.clone(), chunk, shards_manager_adapter.clone(), prev_hash);
+        for header in missing_chunks {
+            blocks_delay_tracker.mark_chunk_requested(&header);
+            fetch_missing_chunk(
+                client.clone(),
+                config.clone(),
+                MissingChunk::Block { header, prev_hash },
+                shards_manager_adapter.clone(),
+            );
         }
     }
 
     for OrphanMissingChunks { missing_chunks, epoch_id, ancestor_hash } in orphans_missing_chunks {
-        for chunk in missing_chunks {
-            blocks_delay_tracker.mark_chunk_requested(&chunk);
-            request_orphan_chunk(
+        for header in missing_chunks {
+            blocks_delay_tracker.mark_chunk_requested(&header);
+            fetch_missing_chunk(
                 client.clone(),
-                chunk,
-                shards_manager_adapter,
-                epoch_id,
-                ancestor_hash,
+                config.clone(),
+                MissingChunk::Orphan { header, epoch_id, ancestor_hash },
+                shards_manager_adapter.clone(),
             );
         }
     }
 }
 
-fn request_missing_chunk<C>(
+/// A chunk missing from a block or from an orphan.
+enum MissingChunk {
+    Block { header: ShardChunkHeader, prev_hash: CryptoHash },
+    Orphan { header: ShardChunkHeader, epoch_id: EpochId, ancestor_hash: CryptoHash },
+}
+
+impl MissingChunk {
+    /// Returns the hash of the block the chunk is looked up by.
+    fn lookup_hash(&self) -> CryptoHash {
+        match self {
+            Self::Block { prev_hash, .. } => *prev_hash,
+            Self::Orphan { ancestor_hash, .. } => *ancestor_hash,
+        }
+    }
+
+    fn header(&self) -> &ShardChunkHeader {
+        match self {
+            Self::Block { header, .. } | Self::Orphan { header, .. } => header,
+        }
+    }
+
+    /// Message for the shards manager to process the chunk found in the chunk
+    /// distribution network.
+    fn process_request(
+        &self,
+        candidate_chunk: PartialEncodedChunk,
+    ) -> ShardsManagerRequestFromClient {
+        match self {
+            Self::Block { header, prev_hash } => {
+                ShardsManagerRequestFromClient::ProcessOrRequestChunk {
+                    candidate_chunk,
+                    request_header: header.clone(),
+                    prev_hash: *prev_hash,
+                }
+            }
+            Self::Orphan { header, epoch_id, ancestor_hash } => {
+                ShardsManagerRequestFromClient::ProcessOrRequestChunkForOrphan {
+                    candidate_chunk,
+                    request_header: header.clone(),
+                    epoch_id: *epoch_id,
+                    ancestor_hash: *ancestor_hash,
+                }
+            }
+        }
+    }
+
+    /// Message for the shards manager to request the chunk from the peers.
+    fn p2p_request(&self) -> ShardsManagerRequestFromClient {
+        match self {
+            Self::Block { header, prev_hash } => ShardsManagerRequestFromClient::RequestChunks {
+                chunks_to_request: vec![header.clone()],
+                prev_hash: *prev_hash,
+            },
+            Self::Orphan { header, epoch_id, ancestor_hash } => {
+                ShardsManagerRequestFromClient::RequestChunksForOrphan {
+                    chunks_to_request: vec![header.clone()],
+                    epoch_id: *epoch_id,
+                    ancestor_hash: *ancestor_hash,
+                }
+            }
+        }
+    }
+}
+
+fn fetch_missing_chunk<C>(
     client: C,
-    header: ShardChunkHeader,
+    config: ChunkDistributionNetworkConfig,
+    chunk: MissingChunk,
     adapter: Sender<ShardsManagerRequestFromClient>,
-    prev_hash: CryptoHash,
 ) where
     C: ChunkDistributionClient + 'static,
     C::Error: fmt::Debug,
 {
-    let shard_id = header.shard_id();
-    debug!(target: "client", ?shard_id, chunk_hash=?header.chunk_hash(), "request_missing_chunk");
+    let shard_id = chunk.header().shard_id();
+    debug!(target: "client", ?shard_id, chunk_hash=?chunk.header().chunk_hash(), "fetch_missing_chunk");
     near_performance_metrics::actix::spawn("ChunkDistributionNetwork", async move {
-        match client.lookup_chunk(prev_hash, shard_id).await {
-            Ok(Some(chunk)) => {
-                adapter.send(ShardsManagerRequestFromClient::ProcessOrRequestChunk {
-                    candidate_chunk: chunk,
-                    request_header: header,
-                    prev_hash,
-                });
+        let lookup =
+            Box::pin(lookup_chunk_with_retries(&client, &config, chunk.lookup_hash(), shard_id));
+        let hedge = Box::pin(tokio::time::sleep(config.hedge_delay.unsigned_abs()));
+        // Whether the chunk was requested from the peers already.
+        let mut requested = false;
+        let result = match future::select(lookup, hedge).await {
+            Either::Left((result, _)) => result,
+            Either::Right(((), lookup)) => {
+                debug!(target: "client", ?shard_id, chunk_hash=?chunk.header().chunk_hash(), "Chunk Distribution Network lookup is slow, requesting the chunk from peers");
+                metrics::CHUNK_DISTRIBUTION_HEDGED_REQUESTS.inc();
+                adapter.send(chunk.p2p_request());
+                requested = true;
+                lookup.await
+            }
+        };
+        match result {
+            Ok(Some(candidate_chunk)) => {
+                metrics::CHUNK_DISTRIBUTION_LOOKUPS.with_label_values(&["hit"]).inc();
+                adapter.send(chunk.process_request(candidate_chunk));
             }
             Ok(None) => {
-                adapter.send(ShardsManagerRequestFromClient::RequestChunks {
-                    chunks_to_request: vec![header],
-                    prev_hash,
-                });
+                metrics::CHUNK_DISTRIBUTION_LOOKUPS.with_label_values(&["miss"]).inc();
+                if !requested {
+                    adapter.send(chunk.p2p_request());
+                }
             }
             Err(err) => {
+                metrics::CHUNK_DISTRIBUTION_LOOKUPS.with_label_values(&["error"]).inc();
                 error!(target: "client", ?err, "Failed to find chunk via Chunk Distribution Network");
-                adapter.send(ShardsManagerRequestFromClient::RequestChunks {
-                    chunks_to_request: vec![header],
-                    prev_hash,
-                });
+                if !requested {
+                    adapter.send(chunk.p2p_request());
+                }
             }
         }
     });
 }
 
-fn request_orphan_chunk<C>(
-    client: C,
-    header: ShardChunkHeader,
-    adapter: &Sender<ShardsManagerRequestFromClient>,
-    epoch_id: EpochId,
-    ancestor_hash: CryptoHash,
-) where
-    C: ChunkDistributionClient + 'static,
+/// Looks up the chunk in the chunk distribution network. The failed lookups
+/// are retried up to `config.lookup_retries` times, with exponential backoff.
+async fn lookup_chunk_with_retries<C>(
+    client: &C,
+    config: &ChunkDistributionNetworkConfig,
+    prev_hash: CryptoHash,
+    shard_id: ShardId,
+) -> Result<Option<PartialEncodedChunk>, C::Error>
+where
+    C: ChunkDistributionClient,
     C::Error: fmt::Debug,
 {
-    let shard_id = header.shard_id();
-    let thread_local_shards_manager_adapter = adapter.clone();
-    near_performance_metrics::actix::spawn("ChunkDistributionNetwork", async move {
-        match client.lookup_chunk(ancestor_hash, shard_id).await {
-            Ok(Some(chunk)) => {
-                thread_local_shards_manager_adapter.send(
-                    ShardsManagerRequestFromClient::ProcessOrRequestChunkForOrphan {
-                        candidate_chunk: chunk,
-                        request_header: header,
-                        epoch_id,
-                        ancestor_hash,
-                    },
-                );
-            }
-            Ok(None) => {
-                thread_local_shards_manager_adapter.send(
-                    ShardsManagerRequestFromClient::RequestChunksForOrphan {
-                        chunks_to_request: vec![header],
-                        epoch_id,
-                        ancestor_hash,
-                    },
-                );
-            }
+    let mut backoff = config.retry_backoff;
+    for retry in 0..config.lookup_retries {
+        match client.lookup_chunk(prev_hash, shard_id).await {
             Err(err) => {
-                error!(target: "client", ?err, "Failed to find chunk via Chunk Distribution Network");
-                thread_local_shards_manager_adapter.send(
-                    ShardsManagerRequestFromClient::RequestChunksForOrphan {
-                        chunks_to_request: vec![header],
-                        epoch_id,
-                        ancestor_hash,
-                    },
-                );
+                debug!(target: "client", ?err, retry, ?backoff, "Chunk Distribution Network lookup failed, retrying");
+                metrics::CHUNK_DISTRIBUTION_LOOKUP_RETRIES.inc();
+                tokio::time::sleep(backoff.unsigned_abs()).await;
+                backoff = backoff.saturating_mul(2);
             }
+            result => return result,
         }
-    });
+    }
+    client.lookup_chunk(prev_hash, shard_id).await
 }
 
 impl ChunkDistributionClient for ChunkDistributionNetwork {
@@ -217,7 +289,7 @@ mod tests {
     use futures::FutureExt;
     use near_async::{
         messaging::{CanSend, IntoSender},
-        time::Clock,
+        time::{Clock, Duration},
     };
     use near_primitives::{
         bandwidth_scheduler::BandwidthRequests,
@@ -229,13 +301,23 @@ mod tests {
         },
         validator_signer::EmptyValidatorSigner,
     };
-    use std::{collections::HashMap, convert::Infallible, future::Future};
+    use std::{
+        collections::HashMap,
+        convert::Infallible,
+        future::Future,
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+    };
     use tokio::sync::mpsc;
 
     #[test]
     fn test_request_chunks() {
         let (mock_sender, mut message_receiver) = mpsc::unbounded_channel();
         let mut client = MockClient::default();
+        // The failed lookups aren't retried, so the broken client falls back on the peers at once.
+        let config = ChunkDistributionNetworkConfig { lookup_retries: 0, ..Default::default() };
         let missing_chunk = mock_shard_chunk(0, 0u64.into());
         let mut blocks_delay_tracker = BlocksDelayTracker::new(Clock::real());
         let shards_manager = MockSender::new(mock_sender);
@@ -249,6 +331,7 @@ mod tests {
                 Vec::new(),
                 Vec::new(),
                 client.clone(),
+                &config,
                 &mut blocks_delay_tracker,
                 &shards_manager_adapter,
             );
@@ -268,6 +351,7 @@ mod tests {
                 blocks_missing_chunks,
                 Vec::new(),
                 client.clone(),
+                &config,
                 &mut blocks_delay_tracker,
                 &shards_manager_adapter,
             );
@@ -293,6 +377,7 @@ mod tests {
                 Vec::new(),
                 orphans_missing_chunks,
                 client.clone(),
+                &config,
                 &mut blocks_delay_tracker,
                 &shards_manager_adapter,
             );
@@ -328,6 +413,7 @@ mod tests {
                 blocks_missing_chunks,
                 orphans_missing_chunks,
                 client,
+                &config,
                 &mut blocks_delay_tracker,
                 &shards_manager_adapter,
             );
@@ -369,6 +455,7 @@ mod tests {
                 blocks_missing_chunks,
                 orphans_missing_chunks,
                 ErrorClient, // intentionally broken chunk distribution client
+                &config,
                 &mut blocks_delay_tracker,
                 &shards_manager_adapter,
             );
@@ -393,6 +480,78 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_lookup_retries_and_hedging() {
+        let (mock_sender, mut message_receiver) = mpsc::unbounded_channel();
+        let shards_manager_adapter = MockSender::new(mock_sender).into_sender();
+        let mut blocks_delay_tracker = BlocksDelayTracker::new(Clock::real());
+        let chunk = mock_shard_chunk(0, ShardId::new(0));
+        let blocks_missing_chunks = || {
+            vec![BlockMissingChunks {
+                prev_hash: *chunk.prev_block(),
+                missing_chunks: vec![chunk.cloned_header()],
+            }]
+        };
+        let process_request = ShardsManagerRequestFromClient::ProcessOrRequestChunk {
+            candidate_chunk: chunk.clone(),
+            request_header: chunk.cloned_header(),
+            prev_hash: *chunk.prev_block(),
+        };
+        let config = ChunkDistributionNetworkConfig {
+            lookup_retries: 2,
+            retry_backoff: Duration::milliseconds(10),
+            hedge_delay: Duration::milliseconds(200),
+            ..Default::default()
+        };
+
+        let system = actix::System::new();
+
+        // The failed lookups are retried, and the chunk found by the last
+        // retry is processed without requesting it from the peers.
+        let client =
+            FlakyClient { chunk: chunk.clone(), failures_left: Arc::new(AtomicU32::new(2)) };
+        system.block_on(async {
+            request_missing_chunks(
+                blocks_missing_chunks(),
+                Vec::new(),
+                client.clone(),
+                &config,
+                &mut blocks_delay_tracker,
+                &shards_manager_adapter,
+            );
+            let message = message_receiver.recv().await.unwrap();
+            assert_eq!(message, process_request);
+            assert_eq!(client.failures_left.load(Ordering::Relaxed), 0);
+            assert_eq!(message_receiver.try_recv().unwrap_err(), mpsc::error::TryRecvError::Empty);
+        });
+
+        // A lookup slower than the hedge delay makes the chunk requested from
+        // the peers, and the chunk is still processed once the lookup finds it.
+        let client =
+            SlowClient { chunk: chunk.clone(), delay: std::time::Duration::from_millis(500) };
+        system.block_on(async {
+            request_missing_chunks(
+                blocks_missing_chunks(),
+                Vec::new(),
+                client,
+                &config,
+                &mut blocks_delay_tracker,
+                &shards_manager_adapter,
+            );
+            let message = message_receiver.recv().await.unwrap();
+            assert_eq!(
+                message,
+                ShardsManagerRequestFromClient::RequestChunks {
+                    chunks_to_request: vec![chunk.cloned_header()],
+                    prev_hash: *chunk.prev_block()
+                }
+            );
+            let message = message_receiver.recv().await.unwrap();
+            assert_eq!(message, process_request);
+            assert_eq!(message_receiver.try_recv().unwrap_err(), mpsc::error::TryRecvError::Empty);
+        });
+    }
+
     fn mock_shard_chunk(height: u64, shard_id: ShardId) -> PartialEncodedChunk {
         let prev_block_hash =
             hash(&[height.to_le_bytes().as_slice(), shard_id.to_le_bytes().as_slice()].concat());
@@ -416,7 +575,7 @@ mod tests {
             congestion_info: CongestionInfo::default(),
             bandwidth_requests: BandwidthRequests::empty(),
         });
-        let header = ShardChunkHeaderV3::from_inner(header_inner, &signer);
+        let header = ShardChunkHeaderV3::from_inner(header_inner, &signer).unwrap();
         PartialEncodedChunk::V2(PartialEncodedChunkV2 {
             header: ShardChunkHeader::V3(header),
             parts: Vec::new(),
@@ -474,6 +633,64 @@ mod tests {
         }
     }
 
+    /// Client whose lookups fail until `failures_left` drops to zero.
+    #[derive(Debug, Clone)]
+    struct FlakyClient {
+        chunk: PartialEncodedChunk,
+        failures_left: Arc<AtomicU32>,
+    }
+    impl ChunkDistributionClient for FlakyClient {
+        type Error = ();
+        type Response = ();
+
+        async fn lookup_chunk(
+            &self,
+            _prev_hash: CryptoHash,
+            _shard_id: ShardId,
+        ) -> Result<Option<PartialEncodedChunk>, Self::Error> {
+            let failures_left = self.failures_left.load(Ordering::Relaxed);
+            if failures_left > 0 {
+                self.failures_left.store(failures_left - 1, Ordering::Relaxed);
+                return Err(());
+            }
+            Ok(Some(self.chunk.clone()))
+        }
+
+        async fn publish_chunk(
+            &mut self,
+            _chunk: &PartialEncodedChunk,
+        ) -> Result<Self::Response, Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// Client which finds the chunk only after `delay`.
+    #[derive(Debug, Clone)]
+    struct SlowClient {
+        chunk: PartialEncodedChunk,
+        delay: std::time::Duration,
+    }
+    impl ChunkDistributionClient for SlowClient {
+        type Error = Infallible;
+        type Response = ();
+
+        async fn lookup_chunk(
+            &self,
+            _prev_hash: CryptoHash,
+            _shard_id: ShardId,
+        ) -> Result<Option<PartialEncodedChunk>, Self::Error> {
+            tokio::time::sleep(self.delay).await;
+            Ok(Some(self.chunk.clone()))
+        }
+
+        async fn publish_chunk(
+            &mut self,
+            _chunk: &PartialEncodedChunk,
+        ) -> Result<Self::Response, Self::Error> {
+            Ok(())
+        }
+    }
+
     #[derive(Debug)]
     struct MockSender<M> {
         inner: mpsc::UnboundedSender<M>,
@@ -123,6 +123,7 @@ impl ChunkInclusionTracker {
             return Ok(());
         };
 
+        let _timer = metrics::BLOCK_CHUNK_ENDORSEMENTS_COLLECTION_TIME.start_timer();
         for chunk_hash in entry.values() {
             let chunk_info = self.chunk_hash_to_chunk_info.get_mut(chunk_hash).unwrap();
             chunk_info.endorsements =
@@ -181,14 +182,6 @@ impl ChunkInclusionTracker {
         chunk_headers_ready_for_inclusion
     }
 
-    pub fn num_chunk_headers_ready_for_inclusion(
-        &self,
-        epoch_id: &EpochId,
-        prev_block_hash: &CryptoHash,
-    ) -> usize {
-        self.get_chunk_headers_ready_for_inclusion(epoch_id, prev_block_hash).len()
-    }
-
     pub fn get_banned_chunk_producers(&self) -> Vec<(EpochId, Vec<AccountId>)> {
         let mut banned_chunk_producers: HashMap<EpochId, Vec<_>> = HashMap::new();
         for ((epoch_id, account_id), _) in &self.banned_chunk_producers {
@@ -4,9 +4,12 @@ use itertools::Itertools;
 use near_async::time::{Clock, Duration, Instant};
 use near_chain::types::{
     PrepareTransactionsChunkContext, PreparedTransactions, RuntimeAdapter, RuntimeStorageConfig,
+    SignerTransactionLimits,
 };
 use near_chain::{Block, Chain, ChainStore};
-use near_chain_configs::MutableConfigValue;
+use near_chain_configs::{
+    MutableConfigValue, TransactionAdmissionConfig, TransactionPriorityConfig,
+};
 use near_chunks::client::ShardedTransactionPool;
 use near_client_primitives::debug::ChunkProduction;
 use near_client_primitives::types::Error;
@@ -21,12 +24,13 @@ use near_primitives::sharding::{ShardChunkHeader, ShardChunkWithEncoding};
 use near_primitives::stateless_validation::ChunkProductionKey;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::chunk_extra::ChunkExtra;
-use near_primitives::types::{BlockHeight, EpochId, ShardId};
+use near_primitives::types::{AccountId, BlockHeight, EpochId, ShardId};
 use near_primitives::validator_signer::ValidatorSigner;
 use near_store::ShardUId;
 use near_store::adapter::chain_store::ChainStoreAdapter;
 use parking_lot::Mutex;
 use reed_solomon_erasure::galois_8::ReedSolomon;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 use time::ext::InstantExt as _;
@@ -51,6 +55,14 @@ pub struct ProduceChunkResult {
     pub receipts: Vec<Receipt>,
 }
 
+/// Transactions prepared ahead of the chunk production deadline on top of a
+/// particular block. They can only be used for a chunk built on top of that
+/// same block.
+struct SpeculativelyPreparedTransactions {
+    prev_block_hash: CryptoHash,
+    prepared: PreparedTransactions,
+}
+
 /// Handles chunk production.
 pub struct ChunkProducer {
     /// Adversarial controls - should be enabled only to test disruptive
@@ -61,6 +73,9 @@ pub struct ChunkProducer {
     pub produce_invalid_chunks: bool,
     #[cfg(feature = "test_features")]
     pub produce_invalid_tx_in_chunks: bool,
+    /// Shared with the client actor, which sets them when it's created.
+    #[cfg(feature = "test_features")]
+    pub adv: crate::adversarial::Controls,
 
     clock: Clock,
     /// If present, limits adding transactions from the transaction
@@ -75,6 +90,14 @@ pub struct ChunkProducer {
     reed_solomon_encoder: ReedSolomon,
     /// Chunk production timing information. Used only for debug purposes.
     pub chunk_production_info: lru::LruCache<(BlockHeight, ShardId), ChunkProduction>,
+    /// Results of speculative transaction preparation, at most one per shard.
+    /// Entries are dropped as soon as the head moves past their prev block.
+    speculative_preparations: HashMap<ShardUId, SpeculativelyPreparedTransactions>,
+    /// If present, limits the number of transactions of a single signer in a chunk.
+    transaction_admission: Option<TransactionAdmissionConfig>,
+    /// For every shard, the signers which had transactions left in the pool because
+    /// of their limit, with the number of consecutive chunks in which that happened.
+    limited_signers: HashMap<ShardUId, HashMap<AccountId, u32>>,
 }
 
 impl ChunkProducer {
@@ -86,6 +109,8 @@ impl ChunkProducer {
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         rng_seed: RngSeed,
         transaction_pool_size_limit: Option<u64>,
+        transaction_priority: Option<&TransactionPriorityConfig>,
+        transaction_admission: Option<TransactionAdmissionConfig>,
     ) -> Self {
         let data_parts = epoch_manager.num_data_parts();
         let parity_parts = epoch_manager.num_total_parts() - data_parts;
@@ -97,6 +122,8 @@ impl ChunkProducer {
             produce_invalid_chunks: false,
             #[cfg(feature = "test_features")]
             produce_invalid_tx_in_chunks: false,
+            #[cfg(feature = "test_features")]
+            adv: crate::adversarial::Controls::default(),
             clock,
             chunk_transactions_time_limit,
             chain: chain_store.clone(),
@@ -105,11 +132,15 @@ impl ChunkProducer {
             sharded_tx_pool: Arc::new(Mutex::new(ShardedTransactionPool::new(
                 rng_seed,
                 transaction_pool_size_limit,
+                transaction_priority,
             ))),
             reed_solomon_encoder: ReedSolomon::new(data_parts, parity_parts).unwrap(),
             chunk_production_info: lru::LruCache::new(
                 NonZeroUsize::new(PRODUCTION_TIMES_CACHE_SIZE).unwrap(),
             ),
+            speculative_preparations: HashMap::new(),
+            transaction_admission,
+            limited_signers: HashMap::new(),
         }
     }
 
@@ -247,9 +278,11 @@ impl ChunkProducer {
         let prepared_transactions = {
             #[cfg(feature = "test_features")]
             match self.adv_produce_chunks {
-                Some(AdvProduceChunksMode::ProduceWithoutTx) => {
-                    PreparedTransactions { transactions: Vec::new(), limited_by: None }
-                }
+                Some(AdvProduceChunksMode::ProduceWithoutTx) => PreparedTransactions {
+                    transactions: Vec::new(),
+                    limited_by: None,
+                    limited_signers: Vec::new(),
+                },
                 _ => self.prepare_transactions(
                     shard_uid,
                     prev_block,
@@ -283,6 +316,14 @@ impl ChunkProducer {
         let gas_used = chunk_extra.gas_used();
         #[cfg(feature = "test_features")]
         let gas_used = if self.produce_invalid_chunks { gas_used + 1 } else { gas_used };
+        let prev_state_root = *chunk_extra.state_root();
+        #[cfg(feature = "test_features")]
+        let prev_state_root = if self.adv.produce_invalid_chunk_headers() {
+            tracing::info!(target: "adversary", next_height, ?shard_id, "Producing chunk with invalid header");
+            CryptoHash::hash_borsh(&prev_state_root)
+        } else {
+            prev_state_root
+        };
 
         let congestion_info = chunk_extra.congestion_info();
         let bandwidth_requests = chunk_extra.bandwidth_requests();
@@ -292,7 +333,7 @@ impl ChunkProducer {
         );
         let (chunk, merkle_paths) = ShardChunkWithEncoding::new(
             prev_block_hash,
-            *chunk_extra.state_root(),
+            prev_state_root,
             *chunk_extra.outcome_root(),
             next_height,
             shard_id,
@@ -308,7 +349,7 @@ impl ChunkProducer {
             bandwidth_requests.cloned().unwrap_or_else(BandwidthRequests::empty),
             &*validator_signer,
             &mut self.reed_solomon_encoder,
-        );
+        )?;
 
         let encoded_chunk = chunk.to_encoded_shard_chunk();
         span.record("chunk_hash", tracing::field::debug(encoded_chunk.chunk_hash()));
@@ -350,13 +391,151 @@ impl ChunkProducer {
         }))
     }
 
+    /// Validates and orders the pooled transactions of `shard_uid` on top of
+    /// `prev_block` ahead of the chunk production deadline.
+    ///
+    /// The result is reused if the chunk for this shard ends up being produced
+    /// on top of the same block. Otherwise the pass still pays off: transactions
+    /// that are invalid on top of `prev_block` are evicted from the pool and
+    /// the state they touch is warmed up in the storage caches.
+    pub fn speculatively_prepare_transactions(
+        &mut self,
+        prev_block: &Block,
+        shard_uid: ShardUId,
+        chain_validate: &dyn Fn(&SignedTransaction) -> bool,
+    ) -> Result<(), Error> {
+        let prev_block_hash = *prev_block.hash();
+        if self
+            .speculative_preparations
+            .get(&shard_uid)
+            .is_some_and(|speculative| speculative.prev_block_hash == prev_block_hash)
+        {
+            return Ok(());
+        }
+
+        let shard_id = shard_uid.shard_id();
+        let _timer = metrics::SPECULATIVE_PREPARE_TRANSACTIONS_TIME
+            .with_label_values(&[&shard_id.to_string()])
+            .start_timer();
+        let chunk_extra = self
+            .chain
+            .get_chunk_extra(&prev_block_hash, &shard_uid)
+            .map_err(|err| Error::ChunkProducer(format!("No chunk extra available: {}", err)))?;
+        // This runs on the client thread as well, so it can't take longer than
+        // preparing the transactions for a chunk.
+        let prepared = self.prepare_transactions_from_pool(
+            shard_uid,
+            prev_block,
+            chunk_extra.as_ref(),
+            chain_validate,
+            self.chunk_transactions_time_limit.get(),
+        )?;
+        debug!(target: "client", ?shard_uid, %prev_block_hash, num_tx = prepared.transactions.len(), "Speculatively prepared transactions");
+        self.speculative_preparations
+            .insert(shard_uid, SpeculativelyPreparedTransactions { prev_block_hash, prepared });
+        Ok(())
+    }
+
+    /// Drops the speculatively prepared transactions which were not prepared
+    /// on top of the new head, as they can no longer be used.
+    pub fn invalidate_speculative_preparations(&mut self, head_hash: &CryptoHash) {
+        self.speculative_preparations.retain(|shard_uid, speculative| {
+            let keep = &speculative.prev_block_hash == head_hash;
+            if !keep {
+                metrics::SPECULATIVE_PREPARE_TRANSACTIONS_TOTAL
+                    .with_label_values(&[&shard_uid.shard_id().to_string(), "invalidated"])
+                    .inc();
+            }
+            keep
+        });
+    }
+
     /// Prepares an ordered list of valid transactions from the pool up the limits.
+    /// Uses the speculatively prepared transactions if they were prepared on top
+    /// of `prev_block`.
     fn prepare_transactions(
+        &mut self,
+        shard_uid: ShardUId,
+        prev_block: &Block,
+        chunk_extra: &ChunkExtra,
+        chain_validate: &dyn Fn(&SignedTransaction) -> bool,
+    ) -> Result<PreparedTransactions, Error> {
+        let prepared = match self.speculative_preparations.remove(&shard_uid) {
+            Some(speculative) if &speculative.prev_block_hash == prev_block.hash() => {
+                metrics::SPECULATIVE_PREPARE_TRANSACTIONS_TOTAL
+                    .with_label_values(&[&shard_uid.shard_id().to_string(), "hit"])
+                    .inc();
+                speculative.prepared
+            }
+            speculative => {
+                if speculative.is_some() {
+                    metrics::SPECULATIVE_PREPARE_TRANSACTIONS_TOTAL
+                        .with_label_values(&[&shard_uid.shard_id().to_string(), "invalidated"])
+                        .inc();
+                }
+                self.prepare_transactions_from_pool(
+                    shard_uid,
+                    prev_block,
+                    chunk_extra,
+                    chain_validate,
+                    self.chunk_transactions_time_limit.get(),
+                )?
+            }
+        };
+        self.update_limited_signers(shard_uid, &prepared);
+        Ok(prepared)
+    }
+
+    /// Returns the limits of the signers for the next chunk of `shard_uid`. The
+    /// limit of a signer is halved for every consecutive chunk in which it had
+    /// transactions left in the pool because of it.
+    fn signer_limits(&self, shard_uid: ShardUId) -> Option<SignerTransactionLimits> {
+        let config = self.transaction_admission.as_ref()?;
+        let limits = self
+            .limited_signers
+            .get(&shard_uid)
+            .into_iter()
+            .flatten()
+            .map(|(signer_id, num_chunks)| {
+                let limit = config
+                    .max_transactions_per_signer
+                    .checked_shr(*num_chunks)
+                    .unwrap_or_default()
+                    .max(config.min_transactions_per_signer);
+                (signer_id.clone(), limit)
+            })
+            .collect();
+        Some(SignerTransactionLimits { default_limit: config.max_transactions_per_signer, limits })
+    }
+
+    /// Records the signers which reached their limit in the chunk being produced.
+    /// The other signers get the full limit again in the next chunk.
+    fn update_limited_signers(&mut self, shard_uid: ShardUId, prepared: &PreparedTransactions) {
+        if self.transaction_admission.is_none() {
+            return;
+        }
+        let previous = self.limited_signers.remove(&shard_uid).unwrap_or_default();
+        let limited = prepared
+            .limited_signers
+            .iter()
+            .map(|signer_id| {
+                let num_chunks = previous.get(signer_id).copied().unwrap_or_default();
+                (signer_id.clone(), num_chunks + 1)
+            })
+            .collect::<HashMap<_, _>>();
+        metrics::CHUNK_PRODUCER_LIMITED_SIGNERS
+            .with_label_values(&[&shard_uid.shard_id().to_string()])
+            .set(limited.len() as i64);
+        self.limited_signers.insert(shard_uid, limited);
+    }
+
+    fn prepare_transactions_from_pool(
         &self,
         shard_uid: ShardUId,
         prev_block: &Block,
         chunk_extra: &ChunkExtra,
         chain_validate: &dyn Fn(&SignedTransaction) -> bool,
+        time_limit: Option<Duration>,
     ) -> Result<PreparedTransactions, Error> {
         let shard_id = shard_uid.shard_id();
         let mut pool_guard = self.sharded_tx_pool.lock();
@@ -370,14 +549,22 @@ impl ChunkProducer {
             };
             self.runtime_adapter.prepare_transactions(
                 storage_config,
-                PrepareTransactionsChunkContext { shard_id, gas_limit: chunk_extra.gas_limit() },
+                PrepareTransactionsChunkContext {
+                    shard_id,
+                    gas_limit: chunk_extra.gas_limit(),
+                    signer_limits: self.signer_limits(shard_uid),
+                },
                 prev_block.into(),
                 &mut iter,
                 chain_validate,
-                self.chunk_transactions_time_limit.get(),
+                time_limit,
             )?
         } else {
-            PreparedTransactions { transactions: Vec::new(), limited_by: None }
+            PreparedTransactions {
+                transactions: Vec::new(),
+                limited_by: None,
+                limited_signers: Vec::new(),
+            }
         };
         // Reintroduce valid transactions back to the pool. They will be removed when the chunk is
         // included into the block.
@@ -16,6 +16,7 @@ use crate::sync::handler::SyncHandler;
 use crate::sync::header::HeaderSync;
 use crate::sync::state::chain_requests::ChainSenderForStateSync;
 use crate::sync::state::{StateSync, StateSyncResult};
+use crate::validator_duties::ValidatorDutyTracker;
 use crate::{ProduceChunkResult, metrics};
 use itertools::Itertools;
 use near_async::futures::{AsyncComputationSpawner, FutureSpawner};
@@ -35,7 +36,9 @@ use near_chain::{
     BlockProcessingArtifact, BlockStatus, Chain, ChainGenesis, ChainStoreAccess, Doomslug,
     DoomslugThresholdMode, Provenance,
 };
-use near_chain_configs::{ClientConfig, MutableValidatorSigner, UpdatableClientConfig};
+use near_chain_configs::{
+    ClientConfig, MutableValidatorSigner, TrackedShardsConfig, UpdatableClientConfig,
+};
 use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::logic::{create_partial_chunk, persist_chunk};
 use near_client_primitives::types::{Error, StateSyncStatus, SyncStatus};
@@ -72,6 +75,7 @@ use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 use tracing::{debug, debug_span, error, info, warn};
 
 #[cfg(feature = "test_features")]
@@ -79,6 +83,10 @@ use crate::chunk_producer::AdvProduceChunksMode;
 
 const NUM_REBROADCAST_BLOCKS: usize = 30;
 
+/// Number of head updates a subscriber to `Client::head_events` may lag behind
+/// before it misses some.
+const HEAD_EVENTS_CAPACITY: usize = 128;
+
 /// Drop blocks whose height are beyond head + horizon if it is not in the current epoch.
 const BLOCK_HORIZON: u64 = 500;
 
@@ -94,6 +102,17 @@ pub enum AdvProduceBlocksMode {
     OnlyValid,
 }
 
+/// Sent to the subscribers of `Client::head_events` every time a block becomes
+/// the head of the chain.
+#[derive(Clone, Debug)]
+pub struct NewHeadEvent {
+    pub block_hash: CryptoHash,
+    pub height: BlockHeight,
+    /// Last final block as of the new head, `CryptoHash::default()` if no block
+    /// is final yet.
+    pub last_final_block_hash: CryptoHash,
+}
+
 /// The state associated with downloading state for a shard this node will track in the
 /// future but does not currently.
 pub struct CatchupState {
@@ -110,6 +129,9 @@ pub struct Client {
     /// behavior on chain.
     #[cfg(feature = "test_features")]
     pub adv_produce_blocks: Option<AdvProduceBlocksMode>,
+    /// Shared with the client actor, which sets them when it's created.
+    #[cfg(feature = "test_features")]
+    pub adv: crate::adversarial::Controls,
 
     /// Fast Forward accrued delta height used to calculate fast forwarded timestamps for each block.
     #[cfg(feature = "sandbox")]
@@ -154,6 +176,9 @@ pub struct Client {
     /// Cached precomputed set of TIER1 accounts.
     /// See send_network_chain_info().
     tier1_accounts_cache: Option<(EpochId, Arc<AccountKeys>)>,
+    /// Cached block and chunk producers of the next epoch.
+    /// See send_network_chain_info().
+    next_epoch_producers_cache: Option<(EpochId, Arc<AccountKeys>)>,
     /// Resharding sender.
     pub resharding_sender: ReshardingSender,
     /// Helper module for handling chunk production.
@@ -176,6 +201,11 @@ pub struct Client {
     last_optimistic_block_produced: Option<OptimisticBlock>,
     /// Cached precomputed set of the chunk producers for current and next epochs.
     chunk_producer_accounts_cache: Option<(EpochId, Arc<Vec<AccountId>>)>,
+    /// Tracks production and endorsement duties of the node's own validator account.
+    pub(crate) validator_duty_tracker: ValidatorDutyTracker,
+    /// Notified of the head updates, e.g. to stream the new blocks to the RPC
+    /// subscribers without polling.
+    pub head_events: broadcast::Sender<NewHeadEvent>,
 }
 
 impl AsRef<Client> for Client {
@@ -193,9 +223,33 @@ impl Client {
             .config
             .produce_chunk_add_transactions_time_limit
             .update(update_client_config.produce_chunk_add_transactions_time_limit);
+        if let Some(tracked_shards_config) = update_client_config.tracked_shards_config {
+            is_updated |= self.update_tracked_shards_config(tracked_shards_config);
+        }
         is_updated
     }
 
+    /// Reloads the shards to track, see
+    /// `ShardTracker::update_tracked_shards_config`.
+    fn update_tracked_shards_config(&self, tracked_shards_config: TrackedShardsConfig) -> bool {
+        if self.config.archive && !tracked_shards_config.tracks_all_shards() {
+            tracing::warn!(target: "client", ?tracked_shards_config, "Archival nodes must track all shards, ignoring the reloaded tracked shards config");
+            return false;
+        }
+        let result = self.chain.head().and_then(|head| {
+            Ok(self
+                .shard_tracker
+                .update_tracked_shards_config(tracked_shards_config, &head.epoch_id)?)
+        });
+        match result {
+            Ok(is_updated) => is_updated,
+            Err(err) => {
+                tracing::warn!(target: "client", ?err, "Failed to reload the tracked shards config");
+                false
+            }
+        }
+    }
+
     /// Updates client's mutable validator signer.
     /// It will update all validator signers that synchronize with it.
     pub(crate) fn update_validator_signer(&self, signer: Option<Arc<ValidatorSigner>>) -> bool {
@@ -214,7 +268,7 @@ impl Client {
         network_adapter: PeerManagerAdapter,
         shards_manager_sender: Sender<ShardsManagerRequestFromClient>,
         validator_signer: MutableValidatorSigner,
-        enable_doomslug: bool,
+        doomslug_threshold_mode: DoomslugThresholdMode,
         rng_seed: RngSeed,
         snapshot_callbacks: Option<SnapshotCallbacks>,
         async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
@@ -225,15 +279,14 @@ impl Client {
         myself_sender: ClientSenderForClient,
         upgrade_schedule: ProtocolUpgradeVotingSchedule,
     ) -> Result<Self, Error> {
-        let doomslug_threshold_mode = if enable_doomslug {
-            DoomslugThresholdMode::TwoThirds
-        } else {
-            DoomslugThresholdMode::NoApprovals
-        };
         let chain_config = ChainConfig {
             save_trie_changes: config.save_trie_changes,
             background_migration_threads: config.client_background_migration_threads,
             resharding_config: config.resharding_config.clone(),
+            defer_fork_postprocessing: config.defer_fork_postprocessing,
+            transactions_by_signer_limit: config.transactions_by_signer_limit,
+            max_concurrent_shard_applies: config.max_concurrent_shard_applies,
+            enable_receipt_audit: config.enable_receipt_audit,
         };
         let chain = Chain::new(
             clock.clone(),
@@ -272,6 +325,7 @@ impl Client {
             config.block_fetch_horizon,
             config.archive,
             config.state_sync_enabled,
+            config.fast_bootstrap.as_ref().map(|config| config.block_sync_depth),
         );
 
         let state_sync = StateSync::new(
@@ -314,18 +368,25 @@ impl Client {
             runtime_adapter.clone(),
             rng_seed,
             config.transaction_pool_size_limit,
+            config.transaction_priority.as_ref(),
+            config.transaction_admission.clone(),
         );
+        let validator_duty_tracker =
+            ValidatorDutyTracker::new(clock.clone(), chain.chain_store().store());
         let chunk_validator = ChunkValidator::new(
             epoch_manager.clone(),
             network_adapter.clone().into_sender(),
             runtime_adapter.clone(),
             config.orphan_state_witness_pool_size,
             async_computation_spawner,
+            validator_duty_tracker.chunk_validation_timings.clone(),
         );
         let chunk_distribution_network = ChunkDistributionNetwork::from_config(&config);
         Ok(Self {
             #[cfg(feature = "test_features")]
             adv_produce_blocks: None,
+            #[cfg(feature = "test_features")]
+            adv: crate::adversarial::Controls::default(),
             #[cfg(feature = "sandbox")]
             accrued_fastforward_delta: 0,
             clock: clock.clone(),
@@ -359,6 +420,7 @@ impl Client {
             last_time_head_progress_made: clock.now(),
             block_production_info: BlockProductionTracker::new(),
             tier1_accounts_cache: None,
+            next_epoch_producers_cache: None,
             resharding_sender,
             chunk_producer,
             chunk_validator,
@@ -369,6 +431,8 @@ impl Client {
             upgrade_schedule,
             last_optimistic_block_produced: None,
             chunk_producer_accounts_cache: None,
+            validator_duty_tracker,
+            head_events: broadcast::channel(HEAD_EVENTS_CAPACITY).0,
         })
     }
 
@@ -656,7 +720,7 @@ impl Client {
             &*validator_signer,
             self.clock.now_utc().unix_timestamp_nanos() as u64,
             sandbox_delta_time,
-        );
+        )?;
 
         metrics::OPTIMISTIC_BLOCK_PRODUCED_TOTAL.inc();
 
@@ -907,7 +971,7 @@ impl Client {
             self.clock.clone(),
             sandbox_delta_time,
             optimistic_block,
-        );
+        )?;
 
         // Update latest known even before returning block out, to prevent race conditions.
         self.chain
@@ -1233,6 +1297,7 @@ impl Client {
             orphans_missing_chunks,
             blocks_missing_chunks,
             invalid_chunks,
+            corrupted_chunks,
         } = block_processing_artifacts;
         // For any missing chunk, let the ShardsManager know of the chunk header so that it may
         // apply forwarded parts. This may end up completing the chunk.
@@ -1248,6 +1313,12 @@ impl Client {
         // process_chunk_header_from_block call, but that is OK as it would be noop).
         self.request_missing_chunks(blocks_missing_chunks, orphans_missing_chunks);
 
+        // The ShardsManager considers these chunks complete, so they are
+        // fetched whole from the peers instead.
+        for chunk_header in corrupted_chunks {
+            self.fetch_chunk(chunk_header);
+        }
+
         for chunk_header in invalid_chunks {
             if let Err(err) = self.ban_chunk_producer_for_producing_invalid_chunk(chunk_header) {
                 error!(target: "client", ?err, "Failed to ban chunk producer for producing invalid chunk");
@@ -1339,6 +1410,57 @@ impl Client {
         }
     }
 
+    /// Fetches the whole chunk from the peers that have it, e.g. an old chunk or
+    /// a chunk of a shard the node doesn't track. The header must come from a
+    /// known block. The fetched chunk is saved to the store, where the sync code
+    /// and the state viewer can read it.
+    pub fn fetch_chunk(&self, chunk_header: ShardChunkHeader) {
+        self.shards_manager_adapter.send(ShardsManagerRequestFromClient::FetchChunk(chunk_header));
+    }
+
+    /// Fetches again the chunks evicted as corrupted which weren't saved yet,
+    /// e.g. because the previous fetch gave up or the node restarted. Called
+    /// periodically, until the blocks waiting for them can be processed.
+    pub fn fetch_corrupted_chunks(&self) {
+        let chunk_headers = match self.chain.corrupted_chunks_to_fetch() {
+            Ok(chunk_headers) => chunk_headers,
+            Err(err) => {
+                error!(target: "client", ?err, "Error reading corrupted chunks");
+                return;
+            }
+        };
+        for chunk_header in chunk_headers {
+            self.fetch_chunk(chunk_header);
+        }
+    }
+
+    /// Called asynchronously when the ShardsManager fetched a chunk requested with
+    /// `fetch_chunk`. Blocks waiting for the chunk because it was evicted as
+    /// corrupted are processed now.
+    pub fn on_chunk_fetched(
+        &mut self,
+        shard_chunk: ShardChunk,
+        apply_chunks_done_sender: Option<Sender<ApplyChunksDoneMessage>>,
+        signer: &Option<Arc<ValidatorSigner>>,
+    ) {
+        let chunk_hash = shard_chunk.chunk_hash();
+        let mut update = self.chain.mut_chain_store().store_update();
+        match update.chunk_exists(&chunk_hash) {
+            Ok(false) => update.save_chunk(shard_chunk),
+            Ok(true) => return,
+            Err(err) => {
+                error!(target: "client", ?err, "Error checking fetched chunk");
+                return;
+            }
+        }
+        if let Err(err) = update.commit() {
+            error!(target: "client", ?err, "Error saving fetched chunk");
+            return;
+        }
+        self.chain.blocks_with_missing_chunks.accept_chunk(&chunk_hash);
+        self.process_blocks_with_missing_chunks(apply_chunks_done_sender, signer);
+    }
+
     pub fn sync_block_headers(
         &mut self,
         headers: Vec<BlockHeader>,
@@ -1420,20 +1542,34 @@ impl Client {
         let next_block_producer =
             self.epoch_manager.get_block_producer(&next_epoch_id, approval.target_height)?;
         let next_block_producer_id = signer.as_ref().map(|x| x.validator_id());
-        if Some(&next_block_producer) == next_block_producer_id {
-            self.collect_block_approval(&approval, ApprovalType::SelfApproval, signer);
-        } else {
-            debug!(target: "client",
-                approval_inner = ?approval.inner,
-                account_id = ?approval.account_id,
-                next_bp = ?next_block_producer,
-                target_height = approval.target_height,
-                approval_type="PeerApproval",
-                "send_block_approval");
-            let approval_message = ApprovalMessage::new(approval, next_block_producer);
-            self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                NetworkRequests::Approval { approval_message },
-            ));
+        #[cfg(feature = "test_features")]
+        let approvals = match signer {
+            Some(signer) if self.adv.double_sign_approvals() => {
+                let conflicting_approval =
+                    crate::adversarial::conflicting_approval(&approval, parent_hash, signer)?;
+                tracing::info!(target: "adversary", target_height = approval.target_height, "Double signing approval");
+                vec![approval, conflicting_approval]
+            }
+            _ => vec![approval],
+        };
+        #[cfg(not(feature = "test_features"))]
+        let approvals = vec![approval];
+        for approval in approvals {
+            if Some(&next_block_producer) == next_block_producer_id {
+                self.collect_block_approval(&approval, ApprovalType::SelfApproval, signer);
+            } else {
+                debug!(target: "client",
+                    approval_inner = ?approval.inner,
+                    account_id = ?approval.account_id,
+                    next_bp = ?next_block_producer,
+                    target_height = approval.target_height,
+                    approval_type="PeerApproval",
+                    "send_block_approval");
+                let approval_message = ApprovalMessage::new(approval, next_block_producer.clone());
+                self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                    NetworkRequests::Approval { approval_message },
+                ));
+            }
         }
 
         Ok(())
@@ -1489,7 +1625,8 @@ impl Client {
             }
         }
 
-        if status.is_new_head() {
+        let is_new_head = status.is_new_head();
+        if is_new_head {
             let last_final_block = block.header().last_final_block();
             let last_finalized_height = if last_final_block == &CryptoHash::default() {
                 self.chain.genesis().height()
@@ -1498,6 +1635,13 @@ impl Client {
             };
             self.chain.blocks_with_missing_chunks.prune_blocks_below_height(last_finalized_height);
             self.chain.blocks_pending_execution.prune_blocks_below_height(last_finalized_height);
+            // Fails only if nobody is subscribed.
+            let _ = self.head_events.send(NewHeadEvent {
+                block_hash,
+                height: block.header().height(),
+                last_final_block_hash: *last_final_block,
+            });
+            self.chunk_producer.invalidate_speculative_preparations(&block_hash);
 
             // send_network_chain_info should be called whenever the chain head changes.
             // See send_network_chain_info() for more details.
@@ -1562,6 +1706,15 @@ impl Client {
             .send(ShardsManagerRequestFromClient::CheckIncompleteChunks(*block.hash()));
 
         self.process_ready_orphan_witnesses_and_clean_old(&block, signer);
+
+        if is_new_head {
+            if let Err(err) = self.request_missing_state_witnesses(&block, signer) {
+                tracing::debug!(target: "client", ?err, "Failed to request missing state witnesses");
+            }
+            if let Err(err) = self.update_validator_duties(signer) {
+                tracing::debug!(target: "client", ?err, "Failed to update validator duties");
+            }
+        }
     }
 
     /// Reconcile the transaction pool after processing a block.
@@ -1756,6 +1909,62 @@ impl Client {
         }
     }
 
+    /// Speculatively prepares transactions for the chunks this node is going to
+    /// produce soon, on top of the current head. This moves the transaction
+    /// validation work out of the chunk production deadline, see
+    /// `ChunkProducer::speculatively_prepare_transactions`.
+    pub fn speculatively_prepare_transactions(&mut self, signer: &Option<Arc<ValidatorSigner>>) {
+        if !self.config.speculative_transaction_preparation {
+            return;
+        }
+        let Some(signer) = signer else {
+            return;
+        };
+        if self.sync_handler.sync_status.is_syncing() {
+            return;
+        }
+        let _span = debug_span!(target: "client", "speculatively_prepare_transactions").entered();
+        if let Err(err) = self.speculatively_prepare_transactions_on_head(signer) {
+            debug!(target: "client", ?err, "Failed to speculatively prepare transactions");
+        }
+    }
+
+    fn speculatively_prepare_transactions_on_head(
+        &mut self,
+        signer: &Arc<ValidatorSigner>,
+    ) -> Result<(), Error> {
+        let head = self.chain.head()?;
+        let block = self.chain.get_block(&head.last_block_hash)?;
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        for shard_id in self.epoch_manager.shard_ids(&epoch_id)? {
+            // The chunk for `head.height + 1` is normally produced as soon as
+            // the head is accepted, so the upcoming slot is usually the one
+            // after it. Speculating for it still prunes the pool and warms up
+            // the caches, even though the result itself gets invalidated.
+            let is_upcoming_producer = (head.height + 1..=head.height + 2).any(|height| {
+                self.epoch_manager
+                    .get_chunk_producer_info(&ChunkProductionKey {
+                        epoch_id,
+                        height_created: height,
+                        shard_id,
+                    })
+                    .is_ok_and(|info| info.account_id() == signer.validator_id())
+                    && !self.chunk_producer.chunk_production_info.contains(&(height, shard_id))
+            });
+            if !is_upcoming_producer {
+                continue;
+            }
+            let shard_uid = shard_id_to_uid(self.epoch_manager.as_ref(), shard_id, &epoch_id)?;
+            let chain_validate = self.chain.transaction_validity_check(block.header().clone());
+            self.chunk_producer.speculatively_prepare_transactions(
+                &block,
+                shard_uid,
+                &chain_validate,
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn persist_and_distribute_encoded_chunk(
         &mut self,
         chunk: ShardChunkWithEncoding,
@@ -1780,7 +1989,9 @@ impl Client {
 
         let chunk_header = encoded_shard_chunk.cloned_header();
         if let Some(chunk_distribution) = &self.chunk_distribution_network {
-            if chunk_distribution.enabled() {
+            if chunk_distribution.enabled() && self.config.shadow_validation {
+                crate::shadow_validation::record_diverted_message("chunk_distribution_publish");
+            } else if chunk_distribution.enabled() {
                 let partial_chunk_arc = Arc::clone(&partial_chunk_arc);
                 let mut thread_local_client = chunk_distribution.clone();
                 near_performance_metrics::actix::spawn("ChunkDistributionNetwork", async move {
@@ -1819,6 +2030,7 @@ impl Client {
                     blocks_missing_chunks,
                     orphans_missing_chunks,
                     chunk_distribution.clone(),
+                    chunk_distribution.config(),
                     &mut self.chain.blocks_delay_tracker,
                     &self.shards_manager_adapter,
                 );
@@ -2281,6 +2493,36 @@ impl Client {
         Ok(account_keys)
     }
 
+    /// Returns block and chunk producers of the next epoch, which are a subset
+    /// of the TIER1 accounts.  The network connects to them with priority, so
+    /// that the connections are ready before the epoch starts and the first
+    /// approvals and chunk parts of the epoch don't wait for connection setup.
+    fn get_next_epoch_producers(&mut self, tip: &Tip) -> Result<Arc<AccountKeys>, Error> {
+        match &self.next_epoch_producers_cache {
+            Some(it) if it.0 == tip.epoch_id => return Ok(it.1.clone()),
+            _ => {}
+        }
+
+        let _span =
+            tracing::debug_span!(target: "client", "get_next_epoch_producers(): recomputing")
+                .entered();
+
+        let mut account_keys = AccountKeys::new();
+        let epoch_id = &tip.next_epoch_id;
+        let producers = self
+            .epoch_manager
+            .get_epoch_block_producers_ordered(epoch_id)?
+            .into_iter()
+            .chain(self.epoch_manager.get_epoch_chunk_producers(epoch_id)?);
+        for producer in producers {
+            let (account_id, public_key, _) = producer.destructure();
+            account_keys.entry(account_id).or_default().insert(public_key);
+        }
+        let account_keys = Arc::new(account_keys);
+        self.next_epoch_producers_cache = Some((tip.epoch_id, Arc::clone(&account_keys)));
+        Ok(account_keys)
+    }
+
     /// We send the optimistic block to the chunk producers of the current and next epochs.
     pub(crate) fn get_optimistic_block_targets(
         &mut self,
@@ -2328,7 +2570,7 @@ impl Client {
         // convert config tracked shards
         // runtime will track all shards if config tracked shards is not empty
         // https://github.com/near/nearcore/issues/4930
-        let tracked_shards = if self.config.tracked_shards_config.tracks_all_shards() {
+        let tracked_shards = if self.shard_tracker.tracks_all_shards() {
             self.epoch_manager.shard_ids(&tip.epoch_id)?
         } else {
             // TODO(archival_v2): Revisit this to determine if improvements can be made
@@ -2336,11 +2578,13 @@ impl Client {
             vec![]
         };
         let tier1_accounts = self.get_tier1_accounts(&tip)?;
+        let next_epoch_producers = self.get_next_epoch_producers(&tip)?;
         let block = self.chain.get_block(&tip.last_block_hash)?;
         self.network_adapter.send(SetChainInfo(ChainInfo {
             block,
             tracked_shards,
             tier1_accounts,
+            next_epoch_producers,
         }));
         Ok(())
     }
@@ -5,6 +5,7 @@
 //! Unfortunately, this is not the case today. We are in the process of refactoring ClientActor
 //! <https://github.com/near/nearcore/issues/7899>
 
+use crate::block_production_jitter::BlockProductionJitter;
 #[cfg(feature = "test_features")]
 pub use crate::chunk_producer::AdvProduceChunksMode;
 #[cfg(feature = "test_features")]
@@ -13,6 +14,7 @@ use crate::client::{CatchupState, Client, EPOCH_START_INFO_BLOCKS};
 use crate::config_updater::ConfigUpdater;
 use crate::debug::new_network_info_view;
 use crate::info::{InfoHelper, display_sync_status};
+use crate::own_chunks_wait::OwnChunksWait;
 use crate::stateless_validation::chunk_endorsement::ChunkEndorsementTracker;
 use crate::stateless_validation::partial_witness::partial_witness_actor::PartialWitnessSenderForClient;
 use crate::sync::handler::SyncHandlerRequest;
@@ -40,7 +42,8 @@ use near_chain::state_snapshot_actor::SnapshotCallbacks;
 use near_chain::test_utils::format_hash;
 use near_chain::types::RuntimeAdapter;
 use near_chain::{
-    Block, BlockHeader, ChainGenesis, Provenance, byzantine_assert, near_chain_primitives,
+    Block, BlockHeader, ChainGenesis, DoomslugThresholdMode, Provenance, byzantine_assert,
+    near_chain_primitives,
 };
 use near_chain_configs::{ClientConfig, MutableValidatorSigner};
 use near_chain_primitives::error::EpochErrorResultToChainError;
@@ -48,7 +51,8 @@ use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::{ShardedTransactionPool, ShardsManagerResponse};
 use near_client_primitives::types::{
     Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, NetworkInfoResponse,
-    StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus,
+    RequestRestorePoint, RequestRestorePointError, StateSyncStatus, Status, StatusError,
+    StatusSyncInfo, SyncStatus,
 };
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_tracker::ShardTracker;
@@ -90,6 +94,10 @@ const STATUS_WAIT_TIME_MULTIPLIER: i32 = 10;
 /// the current `head`
 const HEAD_STALL_MULTIPLIER: u32 = 4;
 
+/// How often the chunks evicted from the store as corrupted are fetched again,
+/// if they weren't saved yet.
+const CORRUPTED_CHUNKS_FETCH_PERIOD: Duration = Duration::seconds(10);
+
 pub type ClientActor = ActixWrapper<ClientActorInner>;
 
 /// Returns random seed sampled from the current thread
@@ -121,6 +129,7 @@ pub struct StartClientResult {
     pub client_arbiter_handle: actix::ArbiterHandle,
     pub tx_pool: Arc<Mutex<ShardedTransactionPool>>,
     pub chunk_endorsement_tracker: Arc<ChunkEndorsementTracker>,
+    pub head_events: broadcast::Sender<NewHeadEvent>,
 }
 
 /// Starts client in a separate Arbiter (thread).
@@ -164,7 +173,11 @@ pub fn start_client(
         network_adapter.clone(),
         shards_manager_adapter,
         validator_signer,
-        enable_doomslug,
+        if enable_doomslug {
+            DoomslugThresholdMode::TwoThirds
+        } else {
+            DoomslugThresholdMode::NoApprovals
+        },
         seed.unwrap_or_else(random_seed_from_thread),
         snapshot_callbacks,
         Arc::new(RayonAsyncComputationSpawner),
@@ -196,6 +209,7 @@ pub fn start_client(
     let tx_pool = client_actor_inner.client.chunk_producer.sharded_tx_pool.clone();
     let chunk_endorsement_tracker =
         Arc::clone(&client_actor_inner.client.chunk_endorsement_tracker);
+    let head_events = client_actor_inner.client.head_events.clone();
     let client_addr = ClientActor::start_in_arbiter(&client_arbiter_handle, move |_| {
         ActixWrapper::new(client_actor_inner)
     });
@@ -211,6 +225,7 @@ pub fn start_client(
         client_arbiter_handle,
         tx_pool,
         chunk_endorsement_tracker,
+        head_events,
     }
 }
 
@@ -249,9 +264,21 @@ pub struct ClientActorInner {
     /// Last time handle_block_production method was called
     block_production_next_attempt: near_async::time::Utc,
 
+    /// Next time to speculatively prepare transactions for upcoming chunks.
+    speculative_prepare_next_attempt: near_async::time::Utc,
+
+    /// Delays block production for the missing chunks of the tracked shards.
+    own_chunks_wait: OwnChunksWait,
+
+    /// Delays block production by a deterministic offset.
+    block_production_jitter: BlockProductionJitter,
+
     // Last time when log_summary method was called.
     log_summary_timer_next_attempt: near_async::time::Utc,
 
+    /// Next time to fetch the chunks evicted as corrupted again.
+    corrupted_chunks_fetch_next_attempt: near_async::time::Utc,
+
     doomslug_timer_next_attempt: near_async::time::Utc,
     sync_timer_next_attempt: near_async::time::Utc,
     sync_started: bool,
@@ -344,7 +371,22 @@ impl ClientActorInner {
             info!(target: "client", "Starting validator node: {}", vs.validator_id());
             check_validator_tracked_shards(&client, vs.validator_id())?;
         }
+        #[cfg(feature = "test_features")]
+        let client = {
+            let mut client = client;
+            client.adv = adv.clone();
+            client.chunk_producer.adv = adv.clone();
+            client
+        };
         let info_helper = InfoHelper::new(clock.clone(), telemetry_sender, &client.config);
+        let own_chunks_wait = OwnChunksWait::new(
+            client.config.own_chunks_grace_period,
+            client.config.min_block_production_delay,
+        );
+        let block_production_jitter = BlockProductionJitter::new(
+            client.config.block_production_jitter,
+            client.config.min_block_production_delay,
+        );
 
         let now = clock.now_utc();
         Ok(ClientActorInner {
@@ -368,7 +410,11 @@ impl ClientActorInner {
             last_validator_announce_time: None,
             info_helper,
             block_production_next_attempt: now,
+            speculative_prepare_next_attempt: now,
+            own_chunks_wait,
+            block_production_jitter,
             log_summary_timer_next_attempt: now,
+            corrupted_chunks_fetch_next_attempt: now,
             doomslug_timer_next_attempt: now,
             sync_timer_next_attempt: now,
             sync_started: false,
@@ -780,6 +826,7 @@ impl Handler<Status> for ClientActorInner {
             uptime_sec,
             genesis_hash: *self.client.chain.genesis().hash(),
             detailed_debug_status,
+            protocol_readiness: self.info_helper.protocol_readiness.clone(),
         })
     }
 }
@@ -894,6 +941,17 @@ impl ClientActorInner {
         // Start catchup job.
         self.catchup(ctx);
 
+        if self.client.config.persist_tx_pool {
+            let signer = self.client.validator_signer.get();
+            if let Err(err) = self.client.restore_tx_pool(&signer) {
+                tracing::error!(target: "client", ?err, "Failed to restore the transaction pool");
+            }
+        }
+
+        // Warn right away if the binary is too old for the network, the check
+        // is repeated with the log summary.
+        self.info_helper.check_protocol_readiness(&self.client);
+
         if let Err(err) = self.client.send_network_chain_info() {
             tracing::error!(target: "client", ?err, "Failed to update network chain info");
         }
@@ -941,7 +999,13 @@ impl ClientActorInner {
             self.last_validator_announce_time = Some(now);
 
             let announce_account =
-                AnnounceAccount::new(signer.as_ref(), self.node_id.clone(), next_epoch_id);
+                match AnnounceAccount::new(signer.as_ref(), self.node_id.clone(), next_epoch_id) {
+                    Ok(announce_account) => announce_account,
+                    Err(err) => {
+                        error!(target: "client", ?err, "Failed to sign announce account");
+                        return;
+                    }
+                };
             self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::AnnounceAccount(announce_account),
             ));
@@ -1107,18 +1171,50 @@ impl ClientActorInner {
                     &self.client.chunk_endorsement_tracker,
                 )?;
             }
-            let num_chunks = self
+            let ready_chunks = self
                 .client
                 .chunk_inclusion_tracker
-                .num_chunk_headers_ready_for_inclusion(&epoch_id, prev_block_hash);
+                .get_chunk_headers_ready_for_inclusion(&epoch_id, prev_block_hash);
             let shard_ids = self.client.epoch_manager.shard_ids(&epoch_id).unwrap();
-            let have_all_chunks = head.height == 0 || num_chunks == shard_ids.len();
+            let have_all_chunks = head.height == 0 || ready_chunks.len() == shard_ids.len();
 
             if self.client.doomslug.ready_to_produce_block(
                 height,
                 have_all_chunks,
                 log_block_production_info,
             ) {
+                if self.block_production_jitter.should_wait(
+                    self.clock.now(),
+                    &me,
+                    height,
+                    prev_block_hash,
+                ) {
+                    continue;
+                }
+                let num_missing_own_chunks = if have_all_chunks {
+                    0
+                } else {
+                    shard_ids
+                        .iter()
+                        .filter(|&&shard_id| {
+                            !ready_chunks.contains_key(&shard_id)
+                                && self.client.shard_tracker.cares_about_shard(
+                                    Some(&me),
+                                    prev_block_hash,
+                                    shard_id,
+                                    true,
+                                )
+                        })
+                        .count()
+                };
+                if self.own_chunks_wait.should_wait(
+                    self.clock.now(),
+                    height,
+                    prev_block_hash,
+                    num_missing_own_chunks,
+                ) {
+                    continue;
+                }
                 self.client
                     .chunk_inclusion_tracker
                     .record_endorsement_metrics(prev_block_hash, &shard_ids);
@@ -1203,6 +1299,12 @@ impl ClientActorInner {
 
         let validator_signer = self.client.validator_signer.get();
         self.try_process_unfinished_blocks(&validator_signer);
+        if let Err(err) = self.client.check_upcoming_validator_duties(
+            &validator_signer,
+            self.network_info.num_connected_peers,
+        ) {
+            tracing::debug!(target: "client", ?err, "Failed to check upcoming validator duties");
+        }
 
         let mut delay = near_async::time::Duration::seconds(1);
         let now = self.clock.now_utc();
@@ -1241,7 +1343,21 @@ impl ClientActorInner {
                 self.client.config.max_block_production_delay * HEAD_STALL_MULTIPLIER,
             );
 
-            delay = core::cmp::min(delay, self.block_production_next_attempt - now)
+            delay = core::cmp::min(delay, self.block_production_next_attempt - now);
+
+            if self.client.config.speculative_transaction_preparation {
+                self.speculative_prepare_next_attempt = self.run_timer(
+                    self.client.config.block_production_tracking_delay,
+                    self.speculative_prepare_next_attempt,
+                    ctx,
+                    |act, _ctx| {
+                        let signer = act.client.validator_signer.get();
+                        act.client.speculatively_prepare_transactions(&signer)
+                    },
+                    "speculative_prepare",
+                );
+                delay = core::cmp::min(delay, self.speculative_prepare_next_attempt - now);
+            }
         }
 
         self.log_summary_timer_next_attempt = self.run_timer(
@@ -1252,6 +1368,15 @@ impl ClientActorInner {
             "log_summary",
         );
         delay = core::cmp::min(delay, self.log_summary_timer_next_attempt - now);
+
+        self.corrupted_chunks_fetch_next_attempt = self.run_timer(
+            CORRUPTED_CHUNKS_FETCH_PERIOD,
+            self.corrupted_chunks_fetch_next_attempt,
+            ctx,
+            |act, _ctx| act.client.fetch_corrupted_chunks(),
+            "fetch_corrupted_chunks",
+        );
+        delay = core::cmp::min(delay, self.corrupted_chunks_fetch_next_attempt - now);
         timer.observe_duration();
         delay
     }
@@ -1332,11 +1457,25 @@ impl ClientActorInner {
             return Ok(());
         };
 
+        if self.client.config.shadow_validation {
+            info!(target: "client", height = block.header().height(), hash = ?block.hash(), "Shadow validation: produced block");
+            crate::shadow_validation::record_diverted_message("block");
+            return Ok(());
+        }
+
         // If we produced the block, send it out before we apply the block.
         self.client.chain.blocks_delay_tracker.mark_block_received(&block);
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
             NetworkRequests::Block { block: block.clone() },
         ));
+        #[cfg(feature = "test_features")]
+        if let Some(signer) = signer.as_ref().filter(|_| self.adv.produce_equivocating_blocks()) {
+            let equivocating_block = crate::adversarial::equivocating_block(&block, signer);
+            info!(target: "adversary", height = block.header().height(), hash = ?block.hash(), equivocating_hash = ?equivocating_block.hash(), "Producing equivocating block");
+            self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                NetworkRequests::Block { block: equivocating_block },
+            ));
+        }
         // We’ve produced the block so that counts as validated block.
         let block = MaybeValidated::from_validated(block);
         let res = self.client.start_process_block(
@@ -1930,6 +2069,14 @@ impl Handler<ShardsManagerResponse> for ClientActorInner {
                     .chunk_inclusion_tracker
                     .mark_chunk_header_ready_for_inclusion(chunk_header, chunk_producer);
             }
+            ShardsManagerResponse::ChunkFetched(shard_chunk) => {
+                let signer = self.client.validator_signer.get();
+                self.client.on_chunk_fetched(
+                    shard_chunk,
+                    Some(self.client.myself_sender.apply_chunks_done.clone()),
+                    &signer,
+                );
+            }
         }
     }
 }
@@ -1942,6 +2089,17 @@ impl Handler<GetClientConfig> for ClientActorInner {
     }
 }
 
+impl Handler<RequestRestorePoint> for ClientActorInner {
+    fn handle(
+        &mut self,
+        msg: RequestRestorePoint,
+    ) -> Result<BlockHeight, RequestRestorePointError> {
+        tracing::debug!(target: "client", ?msg);
+
+        Ok(self.client.chain.request_restore_point()?)
+    }
+}
+
 impl Handler<ChunkStateWitnessMessage> for ClientActorInner {
     #[perf]
     fn handle(&mut self, msg: ChunkStateWitnessMessage) {
@@ -8,9 +8,9 @@ use near_async::time::{Clock, Instant};
 use near_chain::crypto_hash_timer::CryptoHashTimer;
 use near_chain::{Block, Chain, ChainStoreAccess, near_chain_primitives};
 use near_client_primitives::debug::{
-    ApprovalAtHeightStatus, BlockProduction, ChunkCollection, DebugBlockStatusData,
+    ApprovalAtHeightStatus, BlockProduction, ChunkCollection, ChunkFetchView, DebugBlockStatusData,
     DebugBlockStatusQuery, DebugBlocksStartingMode, DebugStatus, DebugStatusResponse,
-    MissedHeightInfo, ProductionAtHeight, ValidatorStatus,
+    FlatStorageRebuildView, MissedHeightInfo, ProductionAtHeight, ValidatorStatus,
 };
 use near_client_primitives::types::Error;
 use near_client_primitives::{
@@ -199,6 +199,44 @@ impl Handler<DebugStatus> for ClientActorInner {
             DebugStatus::ChainProcessingStatus => Ok(DebugStatusResponse::ChainProcessingStatus(
                 self.client.chain.get_chain_processing_info(),
             )),
+            DebugStatus::RebuildFlatStorage(shard_id) => {
+                let (shard_uid, flat_head) =
+                    self.client.chain.start_flat_storage_rebuild(shard_id)?;
+                Ok(DebugStatusResponse::FlatStorageRebuild(FlatStorageRebuildView {
+                    shard_uid,
+                    flat_head_hash: flat_head.hash,
+                    flat_head_height: flat_head.height,
+                }))
+            }
+            DebugStatus::FetchChunk(block_hash, shard_id) => {
+                let block = self.client.chain.get_block(&block_hash)?;
+                let shard_layout = self
+                    .client
+                    .epoch_manager
+                    .get_shard_layout(block.header().epoch_id())
+                    .map_err(near_chain::Error::from)?;
+                let shard_index =
+                    shard_layout.get_shard_index(shard_id).map_err(near_chain::Error::from)?;
+                let chunk_header = block
+                    .chunks()
+                    .get(shard_index)
+                    .ok_or(near_chain::Error::InvalidShardId(shard_id))?
+                    .clone();
+                let chunk_hash = chunk_header.chunk_hash();
+                let already_saved = self.client.chain.chain_store().chunk_exists(&chunk_hash)?;
+                if !already_saved {
+                    self.client.fetch_chunk(chunk_header);
+                }
+                Ok(DebugStatusResponse::ChunkFetch(ChunkFetchView { chunk_hash, already_saved }))
+            }
+            DebugStatus::ReceiptAudit => match self.client.chain.get_receipt_audit_view() {
+                Some(view) => Ok(DebugStatusResponse::ReceiptAudit(view)),
+                None => Err(StatusError::InternalError {
+                    error_message:
+                        "receipt audit is disabled, see `enable_receipt_audit` in the config"
+                            .to_string(),
+                }),
+            },
         }
     }
 }
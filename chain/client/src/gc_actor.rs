@@ -1,11 +1,11 @@
 use crate::metrics;
-use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt};
+use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt, PeriodicSchedule};
 use near_async::messaging::Actor;
 #[cfg(feature = "test_features")]
 use near_async::messaging::Handler;
 use near_chain::ChainGenesis;
 use near_chain::{ChainStore, ChainStoreAccess, types::RuntimeAdapter};
-use near_chain_configs::{GCConfig, MutableValidatorSigner};
+use near_chain_configs::{GCConfig, MutableValidatorSigner, NodeProfile};
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_store::Store;
@@ -23,6 +23,7 @@ pub struct GCActor {
     shard_tracker: ShardTracker,
     validator_signer: MutableValidatorSigner,
     gc_config: GCConfig,
+    node_profile: NodeProfile,
     is_archive: bool,
     /// In some tests we may want to temporarily disable GC
     no_gc: bool,
@@ -37,12 +38,14 @@ impl GCActor {
         shard_tracker: ShardTracker,
         validator_signer: MutableValidatorSigner,
         gc_config: GCConfig,
+        node_profile: NodeProfile,
         is_archive: bool,
     ) -> Self {
         GCActor {
             store: ChainStore::new(store, true, genesis.transaction_validity_period),
             runtime_adapter,
             gc_config,
+            node_profile,
             epoch_manager,
             shard_tracker,
             validator_signer,
@@ -56,13 +59,17 @@ impl GCActor {
         let me = signer.as_ref().map(|signer| signer.validator_id());
         // A RPC node should do regular garbage collection.
         if !self.is_archive {
-            return self.store.clear_data(
+            let result = self.store.clear_data(
                 &self.gc_config,
                 self.runtime_adapter.clone(),
                 self.epoch_manager.clone(),
                 &self.shard_tracker,
                 me,
             );
+            if self.node_profile != NodeProfile::RpcLight {
+                return result;
+            }
+            return result.and(self.store.clear_rpc_light_data(self.epoch_manager.as_ref()));
         }
         // ReshardingV3 mapping for archival nodes (#12578) was built under assumption
         // that archival nodes keep tracking all shards. If this ever changes and need
@@ -90,24 +97,26 @@ impl GCActor {
         self.store.clear_archive_data(self.gc_config.gc_blocks_limit, self.runtime_adapter.clone())
     }
 
-    fn gc(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
-        if !self.no_gc {
-            let timer = metrics::GC_TIME.start_timer();
-            if let Err(e) = self.clear_data() {
-                warn!(target: "garbage collection", "Error in gc: {}", e);
-            }
-            timer.observe_duration();
+    fn gc(&mut self) {
+        if self.no_gc {
+            return;
         }
-
-        ctx.run_later("garbage collection", self.gc_config.gc_step_period, move |act, ctx| {
-            act.gc(ctx);
-        });
+        let timer = metrics::GC_TIME.start_timer();
+        if let Err(e) = self.clear_data() {
+            warn!(target: "garbage collection", "Error in gc: {}", e);
+        }
+        timer.observe_duration();
     }
 }
 
 impl Actor for GCActor {
     fn start_actor(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
-        self.gc(ctx);
+        self.gc();
+        ctx.run_periodically(
+            "garbage collection",
+            PeriodicSchedule::new(self.gc_config.gc_step_period),
+            Self::gc,
+        );
     }
 }
 
@@ -1,4 +1,5 @@
 use crate::config_updater::ConfigUpdater;
+use crate::protocol_readiness::{check_protocol_readiness, report_protocol_readiness};
 use crate::{SyncStatus, metrics};
 use itertools::Itertools;
 use lru::LruCache;
@@ -23,7 +24,7 @@ use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::{PROTOCOL_VERSION, Version};
 use near_primitives::views::{
     CatchupStatusView, ChunkProcessingStatus, CurrentEpochValidatorInfo, EpochValidatorInfo,
-    ValidatorKickoutView,
+    ProtocolReadinessView, ValidatorKickoutView,
 };
 use near_telemetry::TelemetryEvent;
 use std::cmp::min;
@@ -73,6 +74,8 @@ pub struct InfoHelper {
     prev_sync_requirement: Option<String>,
     /// Number of validators (block + chunk producers) per epoch, cached for a small number of epochs.
     num_validators_per_epoch: LruCache<EpochId, usize>,
+    /// Result of the last check of the protocol versions of the network.
+    pub protocol_readiness: Option<ProtocolReadinessView>,
 }
 
 impl InfoHelper {
@@ -99,6 +102,7 @@ impl InfoHelper {
             enable_multiline_logging: client_config.enable_multiline_logging,
             prev_sync_requirement: None,
             num_validators_per_epoch: LruCache::new(NonZeroUsize::new(3).unwrap()),
+            protocol_readiness: None,
         }
     }
 
@@ -290,6 +294,21 @@ impl InfoHelper {
         }
     }
 
+    /// Checks whether the binary supports the protocol versions the network
+    /// upgrades to, see `protocol_readiness`.
+    pub fn check_protocol_readiness(&mut self, client: &crate::client::Client) {
+        let head = unwrap_or_return!(client.chain.head());
+        match check_protocol_readiness(client.epoch_manager.as_ref(), &head) {
+            Ok(readiness) => {
+                report_protocol_readiness(&readiness, &head);
+                self.protocol_readiness = Some(readiness);
+            }
+            Err(err) => {
+                tracing::debug!(target: "stats", ?err, "Failed to check the protocol readiness");
+            }
+        }
+    }
+
     /// Returns the number of validators in a given epoch (EpochId).
     ///
     /// The set of validators include both block producers and chunk producers.
@@ -350,6 +369,11 @@ impl InfoHelper {
                 .unwrap_or_default()
         };
 
+        if !is_syncing {
+            // Reading the votes is as expensive as the validator info above.
+            self.check_protocol_readiness(client);
+        }
+
         let shard_layout = client.epoch_manager.get_shard_layout(&head.epoch_id).ok();
 
         if let Some(shard_layout) = shard_layout.as_ref() {
@@ -618,7 +642,10 @@ impl InfoHelper {
         // Sign telemetry if there is a signer present.
         if let Some(signer) = signer {
             let content = serde_json::to_string(&json).expect("Telemetry must serialize to JSON");
-            json["signature"] = signer.sign_bytes(content.as_bytes()).to_string().into();
+            match signer.sign_bytes(content.as_bytes()) {
+                Ok(signature) => json["signature"] = signature.to_string().into(),
+                Err(err) => tracing::warn!(target: "telemetry", ?err, "Failed to sign telemetry"),
+            }
         }
         json
     }
@@ -2,36 +2,44 @@ pub use near_client_primitives::types::{
     Error, GetBlock, GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunk,
     GetClientConfig, GetExecutionOutcome, GetExecutionOutcomeResponse,
     GetExecutionOutcomesForBlock, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
-    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetShardChunk, GetSplitStorageInfo,
-    GetStateChanges, GetStateChangesInBlock, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfo, GetValidatorOrdered, Query,
-    QueryError, Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
+    GetNextLightClientBlock, GetProtocolConfig, GetProtocolVersionVotes, GetReceipt,
+    GetReceiptTree, GetShardChunk, GetSplitStorageInfo, GetStateChanges, GetStateChangesInBlock,
+    GetStateChangesWithCauseInBlock, GetStateChangesWithCauseInBlockForTrackedShards,
+    GetTransactionsBySigner, GetTxForwardingInfo, GetTxRoutingStatus, GetValidatorDuties,
+    GetValidatorInfo, GetValidatorOrdered, MAX_QUERY_BATCH_EXECUTIONS, MAX_QUERY_BATCH_SIZE,
+    MAX_VIEW_ACCOUNTS_BATCH_SIZE, Query, QueryBatch, QueryError, RequestRestorePoint, Status,
+    StatusResponse, SyncStatus, TxStatus, TxStatusError, ViewAccountsBatch,
 };
 
-pub use crate::client::Client;
+pub use crate::client::{Client, NewHeadEvent};
 #[cfg(feature = "test_features")]
 pub use crate::client_actor::NetworkAdversarialMessage;
 pub use crate::client_actor::{ClientActor, StartClientResult, start_client};
 pub use crate::config_updater::ConfigUpdater;
+pub use crate::remote_signer::RemoteSigner;
 pub use crate::rpc_handler::{
-    RpcHandler, RpcHandlerActor, RpcHandlerConfig, spawn_rpc_handler_actor,
+    RebroadcastPendingTxs, RpcHandler, RpcHandlerActor, RpcHandlerConfig, spawn_rpc_handler_actor,
 };
+pub use crate::shadow_validation::shadow_validation_network_adapter;
 pub use crate::stateless_validation::chunk_validator::orphan_witness_handling::HandleOrphanWitnessOutcome;
+pub use crate::tx_pool_persistence::TxPoolSaver;
 pub use crate::view_client_actor::{ViewClientActor, ViewClientActorInner};
 pub use chunk_producer::ProduceChunkResult;
 pub use near_chain::stateless_validation::processing_tracker::{
     ProcessingDoneTracker, ProcessingDoneWaiter,
 };
-pub use near_client_primitives::debug::DebugStatus;
+pub use near_client_primitives::debug::{DebugStatus, DebugStatusResponse};
 pub use near_network::client::{
     BlockApproval, BlockResponse, ProcessTxRequest, ProcessTxResponse, SetNetworkInfo,
 };
 pub use stateless_validation::partial_witness::partial_witness_actor::{
     DistributeStateWitnessRequest, PartialWitnessActor, PartialWitnessSenderForClient,
+    RequestMissingStateWitnessRequest,
 };
 
 pub mod adapter;
 pub mod adversarial;
+mod block_production_jitter;
 mod chunk_distribution_network;
 mod chunk_inclusion_tracker;
 mod chunk_producer;
@@ -42,9 +50,19 @@ pub mod debug;
 pub mod gc_actor;
 mod info;
 pub mod metrics;
+mod own_chunks_wait;
+mod pending_txs;
+mod protocol_readiness;
+mod remote_signer;
 mod rpc_handler;
+mod shadow_validation;
+mod state_part_server;
 mod stateless_validation;
 pub mod sync;
 pub mod sync_jobs_actor;
 pub mod test_utils;
+mod tx_forwarding_log;
+mod tx_pool_persistence;
+mod validator_delegation;
+mod validator_duties;
 mod view_client_actor;
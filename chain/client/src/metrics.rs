@@ -2,8 +2,8 @@ use near_o11y::metrics::{
     Counter, CounterVec, Gauge, GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec,
     IntGauge, IntGaugeVec, exponential_buckets, linear_buckets, try_create_counter,
     try_create_counter_vec, try_create_gauge, try_create_gauge_vec, try_create_histogram,
-    try_create_histogram_vec, try_create_int_counter, try_create_int_counter_vec,
-    try_create_int_gauge, try_create_int_gauge_vec,
+    try_create_histogram_vec, try_create_histogram_with_buckets, try_create_int_counter,
+    try_create_int_counter_vec, try_create_int_gauge, try_create_int_gauge_vec,
 };
 use near_store::db::metadata::DB_VERSION;
 use std::sync::LazyLock;
@@ -393,6 +393,25 @@ pub(crate) static TRANSACTION_RECEIVED_NON_VALIDATOR_FORWARDED: LazyLock<IntGaug
         .unwrap()
     });
 
+pub(crate) static TRANSACTION_REBROADCAST: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_transaction_rebroadcast_total",
+        "Number of times transactions not seen included were rebroadcast",
+    )
+    .unwrap()
+});
+
+pub(crate) static SHADOW_VALIDATION_DIVERTED_MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(
+    || {
+        try_create_int_counter_vec(
+            "near_shadow_validation_diverted_messages_total",
+            "Number of produced messages not sent to the network because of shadow validation, by kind",
+            &["kind"],
+        )
+        .unwrap()
+    },
+);
+
 pub(crate) static NODE_PROTOCOL_VERSION: LazyLock<IntGauge> = LazyLock::new(|| {
     try_create_int_gauge("near_node_protocol_version", "Max protocol version supported by the node")
         .unwrap()
@@ -421,6 +440,38 @@ pub(crate) static PRODUCE_CHUNK_TIME: LazyLock<HistogramVec> = LazyLock::new(||
     .unwrap()
 });
 
+pub(crate) static SPECULATIVE_PREPARE_TRANSACTIONS_TIME: LazyLock<HistogramVec> =
+    LazyLock::new(|| {
+        try_create_histogram_vec(
+            "near_speculative_prepare_transactions_time",
+            "Time taken to speculatively prepare transactions ahead of chunk production",
+            &["shard_id"],
+            Some(exponential_buckets(0.001, 2.0, 16).unwrap()),
+        )
+        .unwrap()
+    });
+
+pub(crate) static SPECULATIVE_PREPARE_TRANSACTIONS_TOTAL: LazyLock<IntCounterVec> =
+    LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_speculative_prepare_transactions_total",
+            "Number of speculatively prepared transaction sets by outcome: `hit` if used for \
+            chunk production, `invalidated` if dropped because the head moved on",
+            &["shard_id", "outcome"],
+        )
+        .unwrap()
+    });
+
+pub(crate) static CHUNK_PRODUCER_LIMITED_SIGNERS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_chunk_producer_limited_signers",
+        "Number of signers which reached their limit of transactions in the last chunk produced \
+        by this node, and are deprioritized in the next one",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
 pub(crate) static VIEW_CLIENT_MESSAGE_TIME: LazyLock<HistogramVec> = LazyLock::new(|| {
     try_create_histogram_vec(
         "near_view_client_messages_processing_time",
@@ -431,6 +482,15 @@ pub(crate) static VIEW_CLIENT_MESSAGE_TIME: LazyLock<HistogramVec> = LazyLock::n
     .unwrap()
 });
 
+pub(crate) static VIEW_CLIENT_QUERY_BATCH_REQUESTS: LazyLock<Histogram> = LazyLock::new(|| {
+    try_create_histogram_with_buckets(
+        "near_view_client_query_batch_requests",
+        "Number of requests in the query batches handled by view client",
+        exponential_buckets(1.0, 2.0, 8).unwrap(),
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_REQUESTS_THROTTLED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
     try_create_int_counter(
         "near_state_sync_requests_throttled_total",
@@ -439,6 +499,15 @@ pub(crate) static STATE_SYNC_REQUESTS_THROTTLED_TOTAL: LazyLock<IntCounter> = La
     .unwrap()
 });
 
+pub(crate) static STATE_PART_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_state_part_requests_total",
+        "Number of state part requests served with a state part server, by outcome",
+        &["outcome"],
+    )
+    .unwrap()
+});
+
 pub(crate) static PRODUCE_AND_DISTRIBUTE_CHUNK_TIME: LazyLock<HistogramVec> = LazyLock::new(|| {
     try_create_histogram_vec(
         "near_produce_and_distribute_chunk_time",
@@ -697,6 +766,23 @@ pub(crate) static PARTIAL_WITNESS_CACHE_SIZE: LazyLock<GaugeVec> = LazyLock::new
     .unwrap()
 });
 
+pub(crate) static PARTIAL_WITNESS_REQUESTS_SENT: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_partial_witness_requests_sent",
+        "Number of requests for missed state witness parts sent to chunk producers",
+    )
+    .unwrap()
+});
+
+pub(crate) static PARTIAL_WITNESS_REQUESTS_SERVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_partial_witness_requests_served",
+        "Number of state witness part requests handled by the chunk producer, by outcome",
+        &["outcome"],
+    )
+    .unwrap()
+});
+
 pub(crate) static RECEIVE_WITNESS_ACCESSED_CONTRACT_CODES_TIME: LazyLock<HistogramVec> =
     LazyLock::new(|| {
         try_create_histogram_vec(
@@ -746,3 +832,148 @@ pub(crate) static CHUNK_ENDORSEMENTS_REJECTED: LazyLock<IntCounterVec> = LazyLoc
     )
     .unwrap()
 });
+
+pub(crate) static CHUNK_ENDORSEMENTS_VERIFICATION_TIME: LazyLock<HistogramVec> =
+    LazyLock::new(|| {
+        try_create_histogram_vec(
+            "near_chunk_endorsements_verification_time",
+            "Time taken to verify the signatures of the pending endorsements of a chunk",
+            &["shard_id"],
+            Some(exponential_buckets(0.0001, 2.0, 16).unwrap()),
+        )
+        .unwrap()
+    });
+
+pub(crate) static BLOCK_CHUNK_ENDORSEMENTS_COLLECTION_TIME: LazyLock<Histogram> =
+    LazyLock::new(|| {
+        try_create_histogram_with_buckets(
+            "near_block_chunk_endorsements_collection_time",
+            "Time taken by the block producer to collect and verify chunk endorsements for a block",
+            exponential_buckets(0.0001, 2.0, 16).unwrap(),
+        )
+        .unwrap()
+    });
+
+pub(crate) static BLOCK_PRODUCTION_OWN_CHUNKS_DELAYED: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_block_production_own_chunks_delayed_total",
+        "Number of times block production was delayed to wait for the chunks of the shards tracked by the block producer",
+    )
+    .unwrap()
+});
+
+pub(crate) static BLOCK_PRODUCTION_OWN_CHUNKS_RECOVERED: LazyLock<IntCounter> = LazyLock::new(
+    || {
+        try_create_int_counter(
+            "near_block_production_own_chunks_recovered_total",
+            "Number of chunks of the shards tracked by the block producer which became ready while block production was delayed",
+        )
+        .unwrap()
+    },
+);
+
+pub(crate) static BLOCK_PRODUCTION_OWN_CHUNKS_DELAY: LazyLock<Histogram> = LazyLock::new(|| {
+    try_create_histogram_with_buckets(
+        "near_block_production_own_chunks_delay",
+        "Time block production was delayed to wait for the chunks of the shards tracked by the block producer",
+        exponential_buckets(0.001, 2.0, 12).unwrap(),
+    )
+    .unwrap()
+});
+
+pub(crate) static BLOCK_PRODUCTION_JITTER_DELAY: LazyLock<Histogram> = LazyLock::new(|| {
+    try_create_histogram_with_buckets(
+        "near_block_production_jitter_delay",
+        "Time block production was delayed by the deterministic block production jitter",
+        exponential_buckets(0.001, 2.0, 12).unwrap(),
+    )
+    .unwrap()
+});
+
+pub(crate) static REMOTE_SIGNER_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_remote_signer_requests_total",
+        "Number of signing requests of the remote validator signer by result",
+        &["result"],
+    )
+    .unwrap()
+});
+
+pub(crate) static REMOTE_SIGNER_REQUEST_TIME: LazyLock<Histogram> = LazyLock::new(|| {
+    try_create_histogram_with_buckets(
+        "near_remote_signer_request_time",
+        "Time of the signing requests of the remote validator signer, including failover",
+        exponential_buckets(0.0005, 2.0, 14).unwrap(),
+    )
+    .unwrap()
+});
+
+pub(crate) static PROTOCOL_READINESS_IS_READY: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_protocol_readiness_is_ready",
+        "Whether the binary supports the protocol versions of the current and the next epochs and the one projected from the votes",
+    )
+    .unwrap()
+});
+
+pub(crate) static PROTOCOL_READINESS_DEADLINE_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_protocol_readiness_deadline_height",
+        "Estimated block height from which the binary can't follow the chain because of an unsupported protocol version; zero if the binary is ready",
+    )
+    .unwrap()
+});
+
+pub(crate) static VALIDATOR_UPCOMING_DUTIES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_validator_upcoming_duties",
+        "Number of duties of the node's validator account in the next few heights",
+        &["kind"],
+    )
+    .unwrap()
+});
+
+pub(crate) static VALIDATOR_DUTY_HEALTH_ISSUE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_validator_duty_health_issue",
+        "Whether the node has a health issue which would likely make its validator miss its upcoming duties",
+        &["issue"],
+    )
+    .unwrap()
+});
+
+pub(crate) static VALIDATOR_UPCOMING_DUTY_ALERTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_validator_upcoming_duty_alerts_total",
+        "Number of upcoming duties of the node's validator account found at risk ahead of time, by kind and health issue",
+        &["kind", "issue"],
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_DISTRIBUTION_LOOKUPS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_chunk_distribution_network_lookups_total",
+        "Number of missing chunks looked up in the chunk distribution network, \
+        by result: hit, miss or error",
+        &["result"],
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_DISTRIBUTION_LOOKUP_RETRIES: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_chunk_distribution_network_lookup_retries_total",
+        "Number of failed lookups in the chunk distribution network which were retried",
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_DISTRIBUTION_HEDGED_REQUESTS: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_chunk_distribution_network_hedged_requests_total",
+        "Number of missing chunks requested from the peers because their lookup in the \
+        chunk distribution network took longer than the hedge delay",
+    )
+    .unwrap()
+});
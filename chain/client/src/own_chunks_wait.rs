@@ -0,0 +1,121 @@
+//! Delaying block production for the missing chunks of the shards tracked by
+//! the block producer, see `ClientConfig::own_chunks_grace_period`.
+//!
+//! When the block producer is ready to produce a block, the chunks of the
+//! shards it tracks are usually about to complete, since it receives all of
+//! their parts. Waiting a little for them avoids producing blocks with missing
+//! chunks.
+
+use near_async::time::{Duration, Instant};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+
+use crate::metrics;
+
+/// Block production which is being delayed.
+struct DelayedBlock {
+    height: BlockHeight,
+    prev_block_hash: CryptoHash,
+    since: Instant,
+    num_missing_chunks: usize,
+}
+
+pub(crate) struct OwnChunksWait {
+    /// Maximum delay, `None` if the delay is disabled.
+    grace_period: Option<Duration>,
+    delayed: Option<DelayedBlock>,
+}
+
+impl OwnChunksWait {
+    /// The grace period is capped at `min_block_production_delay`, so that
+    /// the delay never exceeds the time between two blocks.
+    pub fn new(grace_period: Option<Duration>, min_block_production_delay: Duration) -> Self {
+        let grace_period = grace_period.map(|period| period.min(min_block_production_delay));
+        Self { grace_period, delayed: None }
+    }
+
+    /// Returns whether the block producer which is otherwise ready to produce
+    /// the block at `height` should keep waiting, given the number of chunks
+    /// of the shards it tracks which aren't ready for inclusion.
+    pub fn should_wait(
+        &mut self,
+        now: Instant,
+        height: BlockHeight,
+        prev_block_hash: &CryptoHash,
+        num_missing_chunks: usize,
+    ) -> bool {
+        let Some(grace_period) = self.grace_period else {
+            return false;
+        };
+        let delayed = match self.delayed.take() {
+            Some(delayed)
+                if delayed.height == height && delayed.prev_block_hash == *prev_block_hash =>
+            {
+                delayed
+            }
+            _ => {
+                if num_missing_chunks == 0 {
+                    return false;
+                }
+                metrics::BLOCK_PRODUCTION_OWN_CHUNKS_DELAYED.inc();
+                DelayedBlock {
+                    height,
+                    prev_block_hash: *prev_block_hash,
+                    since: now,
+                    num_missing_chunks,
+                }
+            }
+        };
+        let delay = now - delayed.since;
+        if num_missing_chunks > 0 && delay < grace_period {
+            self.delayed = Some(delayed);
+            return true;
+        }
+        metrics::BLOCK_PRODUCTION_OWN_CHUNKS_DELAY.observe(delay.as_seconds_f64());
+        metrics::BLOCK_PRODUCTION_OWN_CHUNKS_RECOVERED
+            .inc_by(delayed.num_missing_chunks.saturating_sub(num_missing_chunks) as u64);
+        tracing::debug!(
+            target: "client",
+            height,
+            ?delay,
+            num_missing_chunks,
+            initially_missing_chunks = delayed.num_missing_chunks,
+            "Done waiting for the chunks of tracked shards"
+        );
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_async::time::{FakeClock, Utc};
+
+    #[test]
+    fn test_own_chunks_wait() {
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let prev_block_hash = CryptoHash::default();
+        let mut wait =
+            OwnChunksWait::new(Some(Duration::milliseconds(300)), Duration::milliseconds(200));
+
+        // Nothing to wait for.
+        assert!(!wait.should_wait(clock.now(), 10, &prev_block_hash, 0));
+
+        // Waits until the chunks arrive.
+        assert!(wait.should_wait(clock.now(), 10, &prev_block_hash, 2));
+        clock.advance(Duration::milliseconds(100));
+        assert!(wait.should_wait(clock.now(), 10, &prev_block_hash, 1));
+        assert!(!wait.should_wait(clock.now(), 10, &prev_block_hash, 0));
+
+        // Gives up after the grace period, capped at the block production delay.
+        assert!(wait.should_wait(clock.now(), 11, &prev_block_hash, 1));
+        clock.advance(Duration::milliseconds(199));
+        assert!(wait.should_wait(clock.now(), 11, &prev_block_hash, 1));
+        clock.advance(Duration::milliseconds(1));
+        assert!(!wait.should_wait(clock.now(), 11, &prev_block_hash, 1));
+
+        // Disabled without a grace period.
+        let mut wait = OwnChunksWait::new(None, Duration::milliseconds(200));
+        assert!(!wait.should_wait(clock.now(), 12, &prev_block_hash, 1));
+    }
+}
@@ -0,0 +1,134 @@
+use lru::LruCache;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::BlockHeight;
+use near_primitives::views::{TxForwardingTargetView, TxRoutingStateView, TxRoutingStatusView};
+use parking_lot::Mutex;
+use std::num::NonZeroUsize;
+
+/// Number of transactions for which the routing status is remembered, including
+/// the ones which are no longer rebroadcast.
+const PENDING_TXS_CAPACITY: usize = 10_000;
+
+struct PendingTx {
+    tx: SignedTransaction,
+    status: TxRoutingStatusView,
+}
+
+/// Transactions submitted to the node and forwarded to other chunk producers,
+/// which the RPC handler rebroadcasts until they are seen included. If a
+/// forwarded copy is dropped, the transaction would otherwise vanish silently.
+pub(crate) struct PendingTxs(Mutex<LruCache<CryptoHash, PendingTx>>);
+
+impl PendingTxs {
+    pub fn new() -> Self {
+        Self(Mutex::new(LruCache::new(NonZeroUsize::new(PENDING_TXS_CAPACITY).unwrap())))
+    }
+
+    /// Starts tracking a transaction forwarded at `head_height`. A transaction
+    /// submitted again keeps its original rebroadcast window.
+    pub fn insert(
+        &self,
+        tx: SignedTransaction,
+        head_height: BlockHeight,
+        forwarded_to: Vec<TxForwardingTargetView>,
+    ) {
+        let mut pending_txs = self.0.lock();
+        let tx_hash = tx.get_hash();
+        if pending_txs.contains(&tx_hash) {
+            return;
+        }
+        let status = TxRoutingStatusView {
+            state: TxRoutingStateView::Pending,
+            received_height: head_height,
+            last_forwarded_height: head_height,
+            num_rebroadcasts: 0,
+            forwarded_to,
+        };
+        pending_txs.push(tx_hash, PendingTx { tx, status });
+    }
+
+    pub fn get(&self, tx_hash: &CryptoHash) -> Option<TxRoutingStatusView> {
+        self.0.lock().peek(tx_hash).map(|pending_tx| pending_tx.status.clone())
+    }
+
+    /// Returns the transactions still rebroadcast, with the height of the head
+    /// when they were submitted.
+    pub fn pending(&self) -> Vec<(SignedTransaction, BlockHeight)> {
+        self.0
+            .lock()
+            .iter()
+            .filter(|(_, pending_tx)| pending_tx.status.state == TxRoutingStateView::Pending)
+            .map(|(_, pending_tx)| (pending_tx.tx.clone(), pending_tx.status.received_height))
+            .collect()
+    }
+
+    /// Stops the rebroadcast of the transaction.
+    pub fn finish(&self, tx_hash: &CryptoHash, state: TxRoutingStateView) {
+        if let Some(pending_tx) = self.0.lock().peek_mut(tx_hash) {
+            pending_tx.status.state = state;
+        }
+    }
+
+    pub fn record_rebroadcast(
+        &self,
+        tx_hash: &CryptoHash,
+        head_height: BlockHeight,
+        forwarded_to: Vec<TxForwardingTargetView>,
+    ) {
+        if let Some(pending_tx) = self.0.lock().peek_mut(tx_hash) {
+            pending_tx.status.last_forwarded_height = head_height;
+            pending_tx.status.num_rebroadcasts += 1;
+            pending_tx.status.forwarded_to = forwarded_to;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingTxs;
+    use near_crypto::{KeyType, PublicKey, Signature};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::{SignedTransaction, Transaction};
+    use near_primitives::views::{TxForwardingTargetView, TxRoutingStateView};
+
+    fn tx(nonce: u64) -> SignedTransaction {
+        SignedTransaction::new(
+            Signature::empty(KeyType::ED25519),
+            Transaction::new_v1(
+                "test".parse().unwrap(),
+                PublicKey::empty(KeyType::ED25519),
+                "other".parse().unwrap(),
+                nonce,
+                CryptoHash::default(),
+                0,
+            ),
+        )
+    }
+
+    #[test]
+    fn test_pending_txs() {
+        let pending_txs = PendingTxs::new();
+        let (tx1, tx2) = (tx(1), tx(2));
+        pending_txs.insert(tx1.clone(), 10, vec![]);
+        pending_txs.insert(tx2.clone(), 11, vec![]);
+        // Submitting the transaction again doesn't extend its window.
+        pending_txs.insert(tx1.clone(), 12, vec![]);
+        let mut pending = pending_txs.pending();
+        pending.sort_by_key(|(_, received_height)| *received_height);
+        assert_eq!(pending, vec![(tx1.clone(), 10), (tx2.clone(), 11)]);
+
+        let target = TxForwardingTargetView { account_id: "cp".parse().unwrap(), height: 14 };
+        pending_txs.record_rebroadcast(&tx1.get_hash(), 13, vec![target.clone()]);
+        pending_txs.finish(&tx2.get_hash(), TxRoutingStateView::Included);
+        assert_eq!(pending_txs.pending(), vec![(tx1.clone(), 10)]);
+
+        let status = pending_txs.get(&tx1.get_hash()).unwrap();
+        assert_eq!(status.state, TxRoutingStateView::Pending);
+        assert_eq!(status.last_forwarded_height, 13);
+        assert_eq!(status.num_rebroadcasts, 1);
+        assert_eq!(status.forwarded_to, vec![target]);
+        assert_eq!(pending_txs.get(&tx2.get_hash()).unwrap().state, TxRoutingStateView::Included);
+        assert!(pending_txs.get(&tx(3).get_hash()).is_none());
+    }
+}
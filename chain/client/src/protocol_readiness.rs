@@ -0,0 +1,115 @@
+//! Check of the protocol versions supported by the binary against the ones of
+//! the network, so that the operators learn that they need to upgrade before
+//! the node stops following the chain.
+//!
+//! The versions of the current and the next epoch are final, while the version
+//! of the epoch after the next one is projected from the votes cast so far in
+//! the current epoch.
+
+use crate::metrics;
+use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::block::Tip;
+use near_primitives::errors::EpochError;
+use near_primitives::types::BlockHeight;
+use near_primitives::version::{MIN_SUPPORTED_PROTOCOL_VERSION, PROTOCOL_VERSION, ProtocolVersion};
+use near_primitives::views::ProtocolReadinessView;
+
+fn is_supported(protocol_version: ProtocolVersion) -> bool {
+    (MIN_SUPPORTED_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version)
+}
+
+/// Returns the readiness of the binary for the protocol versions of the
+/// network as seen at `head`.
+pub(crate) fn check_protocol_readiness(
+    epoch_manager: &dyn EpochManagerAdapter,
+    head: &Tip,
+) -> Result<ProtocolReadinessView, EpochError> {
+    let votes = epoch_manager.get_protocol_version_votes(&head.last_block_hash)?;
+    let block_info = epoch_manager.get_block_info(&head.last_block_hash)?;
+    let next_epoch_start = epoch_manager.get_estimated_next_epoch_start(&block_info)?;
+    let epoch_length = epoch_manager.get_epoch_config(&head.epoch_id)?.epoch_length;
+    Ok(protocol_readiness(
+        votes.protocol_version,
+        votes.next_epoch_protocol_version,
+        votes.projected_protocol_version,
+        next_epoch_start,
+        epoch_length,
+    ))
+}
+
+fn protocol_readiness(
+    protocol_version: ProtocolVersion,
+    next_epoch_protocol_version: ProtocolVersion,
+    projected_protocol_version: ProtocolVersion,
+    next_epoch_start: BlockHeight,
+    epoch_length: BlockHeight,
+) -> ProtocolReadinessView {
+    let deadline_height = if !is_supported(protocol_version) {
+        Some(next_epoch_start.saturating_sub(epoch_length))
+    } else if !is_supported(next_epoch_protocol_version) {
+        Some(next_epoch_start)
+    } else if !is_supported(projected_protocol_version) {
+        Some(next_epoch_start + epoch_length)
+    } else {
+        None
+    };
+    ProtocolReadinessView {
+        min_supported_protocol_version: MIN_SUPPORTED_PROTOCOL_VERSION,
+        max_supported_protocol_version: PROTOCOL_VERSION,
+        protocol_version,
+        next_epoch_protocol_version,
+        projected_protocol_version,
+        is_ready: deadline_height.is_none(),
+        deadline_height,
+    }
+}
+
+/// Exports the readiness as metrics and warns if the binary isn't ready.
+pub(crate) fn report_protocol_readiness(readiness: &ProtocolReadinessView, head: &Tip) {
+    metrics::PROTOCOL_READINESS_IS_READY.set(readiness.is_ready as i64);
+    metrics::PROTOCOL_READINESS_DEADLINE_HEIGHT.set(readiness.deadline_height.unwrap_or(0) as i64);
+    let Some(deadline_height) = readiness.deadline_height else {
+        return;
+    };
+    tracing::warn!(
+        target: "client",
+        head_height = head.height,
+        deadline_height,
+        blocks_left = deadline_height.saturating_sub(head.height),
+        protocol_version = readiness.protocol_version,
+        next_epoch_protocol_version = readiness.next_epoch_protocol_version,
+        projected_protocol_version = readiness.projected_protocol_version,
+        "The binary doesn't support the protocol version of the network. It supports protocol versions {}..={} and won't be able to follow the chain from the estimated height {}. Please update nearcore.",
+        readiness.min_supported_protocol_version,
+        readiness.max_supported_protocol_version,
+        deadline_height,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_readiness() {
+        let v = PROTOCOL_VERSION;
+        let readiness = protocol_readiness(v, v, v, 200, 100);
+        assert!(readiness.is_ready);
+        assert_eq!(readiness.deadline_height, None);
+
+        // Projected upgrade, the epoch after the next one.
+        let readiness = protocol_readiness(v, v, v + 1, 200, 100);
+        assert!(!readiness.is_ready);
+        assert_eq!(readiness.deadline_height, Some(300));
+
+        // Decided upgrade, the next epoch.
+        let readiness = protocol_readiness(v, v + 1, v + 1, 200, 100);
+        assert_eq!(readiness.deadline_height, Some(200));
+
+        // Already past the upgrade, or the network is older than the binary.
+        let readiness = protocol_readiness(v + 1, v + 1, v + 1, 200, 100);
+        assert_eq!(readiness.deadline_height, Some(100));
+        let readiness = protocol_readiness(MIN_SUPPORTED_PROTOCOL_VERSION - 1, v, v, 200, 100);
+        assert_eq!(readiness.deadline_height, Some(100));
+    }
+}
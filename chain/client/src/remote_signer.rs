@@ -0,0 +1,449 @@
+//! Validator signing backend which delegates signing to a remote service, see
+//! `RemoteSignerConfig`.
+//!
+//! The service is called with JSON over HTTP(S):
+//! - `POST <endpoint>/sign` with `{"public_key", "data"}`, where `data` is
+//!   base64 encoded, returns `{"signature"}`.
+//! - `POST <endpoint>/vrf` with the same body returns `{"value", "proof"}`.
+//!
+//! The service responds with `409 Conflict` when it refuses to sign because
+//! of its double-sign protection. Other failures make the signer try the next
+//! endpoint. Signatures returned by the service are verified, so that a
+//! misconfigured service can't make the node publish invalid signatures.
+//! When nothing can be signed the callers get a `SignerError` and don't send
+//! the message.
+//!
+//! The HTTP requests are made on a dedicated thread, but signing is
+//! synchronous for the callers, so the calling actor waits for the response.
+//! `timeout` bounds the whole wait, whatever the number of endpoints tried.
+
+use crate::metrics;
+use near_chain_configs::RemoteSignerConfig;
+use near_crypto::{PublicKey, Signature};
+use near_primitives::serialize::to_base64;
+use near_primitives::validator_signer::{SignerError, ValidatorSigner, ValidatorSigningBackend};
+use std::sync::{Arc, mpsc};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, thiserror::Error)]
+enum RemoteSignerError {
+    #[error("refused by {endpoint}: {reason}")]
+    Refused { endpoint: String, reason: String },
+    #[error("no endpoint is available, last error: {0}")]
+    Unavailable(String),
+}
+
+struct Request {
+    method: &'static str,
+    data: Vec<u8>,
+    /// The caller stops waiting for the response at this point.
+    deadline: Instant,
+    response: mpsc::Sender<Result<serde_json::Value, RemoteSignerError>>,
+}
+
+/// The requests are made by a dedicated thread, because the signer is called
+/// from actors running in async runtimes, where the blocking HTTP client
+/// can't be used.
+pub struct RemoteSigner {
+    public_key: PublicKey,
+    timeout: Duration,
+    requests: mpsc::Sender<Request>,
+    /// Signer of the local key, used when no endpoint is available.
+    local_signer: Option<Arc<ValidatorSigner>>,
+}
+
+impl std::fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("public_key", &self.public_key)
+            .field("has_local_signer", &self.local_signer.is_some())
+            .finish()
+    }
+}
+
+impl RemoteSigner {
+    /// Creates the signer. `local_signer` is required if
+    /// `fallback_to_local_key` is set, and must have the same key.
+    pub fn new(
+        config: &RemoteSignerConfig,
+        local_signer: Option<Arc<ValidatorSigner>>,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(!config.endpoints.is_empty(), "remote signer has no endpoints");
+        // The VRF proofs are checked against the ed25519 key.
+        anyhow::ensure!(
+            matches!(config.public_key, PublicKey::ED25519(_)),
+            "remote signer key {} isn't an ed25519 key",
+            config.public_key,
+        );
+        let local_signer = if config.fallback_to_local_key {
+            let Some(local_signer) = local_signer else {
+                anyhow::bail!("remote signer falls back to the local key, but there is none");
+            };
+            anyhow::ensure!(
+                local_signer.public_key() == config.public_key
+                    && local_signer.validator_id() == &config.account_id,
+                "local key {} of {} doesn't match the remote signer key {} of {}",
+                local_signer.public_key(),
+                local_signer.validator_id(),
+                config.public_key,
+                config.account_id,
+            );
+            Some(local_signer)
+        } else {
+            None
+        };
+        let client = build_client(config)?;
+        let (sender, receiver) = mpsc::channel();
+        let endpoints = config.endpoints.clone();
+        let public_key = config.public_key.clone();
+        std::thread::Builder::new()
+            .name("remote_signer".to_string())
+            .spawn(move || run_requests(client, endpoints, public_key, receiver))?;
+        Ok(Self {
+            public_key: config.public_key.clone(),
+            timeout: config.timeout.max(near_async::time::Duration::ZERO).unsigned_abs(),
+            requests: sender,
+            local_signer,
+        })
+    }
+
+    fn request(
+        &self,
+        method: &'static str,
+        data: &[u8],
+    ) -> Result<serde_json::Value, RemoteSignerError> {
+        let _timer = metrics::REMOTE_SIGNER_REQUEST_TIME.start_timer();
+        let (response, receiver) = mpsc::channel();
+        let deadline = Instant::now() + self.timeout;
+        let request = Request { method, data: data.to_vec(), deadline, response };
+        if self.requests.send(request).is_err() {
+            return Err(RemoteSignerError::Unavailable("request thread stopped".to_string()));
+        }
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(RemoteSignerError::Unavailable("timed out".to_string()))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(RemoteSignerError::Unavailable("request thread stopped".to_string()))
+            }
+        }
+    }
+
+    /// Handles the failure of the remote signer, returning the result of the
+    /// local signer if the service isn't available and there is one.
+    fn fallback<T>(
+        &self,
+        method: &'static str,
+        err: RemoteSignerError,
+        sign_locally: impl FnOnce(&ValidatorSigner) -> Result<T, SignerError>,
+    ) -> Result<T, SignerError> {
+        match (&err, &self.local_signer) {
+            (RemoteSignerError::Unavailable(_), Some(local_signer)) => {
+                tracing::warn!(target: "client", method, %err, "remote signer unavailable, signing with the local key");
+                metrics::REMOTE_SIGNER_REQUESTS.with_label_values(&["local_fallback"]).inc();
+                sign_locally(local_signer)
+            }
+            (RemoteSignerError::Refused { .. }, _) => {
+                tracing::error!(target: "client", method, %err, "remote signer refused to sign");
+                metrics::REMOTE_SIGNER_REQUESTS.with_label_values(&["refused"]).inc();
+                Err(SignerError::Refused(err.to_string()))
+            }
+            (RemoteSignerError::Unavailable(_), None) => {
+                tracing::error!(target: "client", method, %err, "remote signer unavailable");
+                metrics::REMOTE_SIGNER_REQUESTS.with_label_values(&["unavailable"]).inc();
+                Err(SignerError::Unavailable(err.to_string()))
+            }
+        }
+    }
+}
+
+impl ValidatorSigningBackend for RemoteSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    fn sign_bytes(&self, data: &[u8]) -> Result<Signature, SignerError> {
+        let result = self.request("sign", data).and_then(|response| {
+            let signature: Signature = parse_field(&response, "signature")?;
+            if !signature.verify(data, &self.public_key) {
+                return Err(RemoteSignerError::Unavailable("invalid signature".to_string()));
+            }
+            Ok(signature)
+        });
+        match result {
+            Ok(signature) => {
+                metrics::REMOTE_SIGNER_REQUESTS.with_label_values(&["ok"]).inc();
+                Ok(signature)
+            }
+            Err(err) => self.fallback("sign", err, |signer| signer.sign_bytes(data)),
+        }
+    }
+
+    fn compute_vrf_with_proof(
+        &self,
+        data: &[u8],
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), SignerError> {
+        let result = self.request("vrf", data).and_then(|response| {
+            let value = parse_field(&response, "value")?;
+            let proof = parse_field(&response, "proof")?;
+            let vrf_public_key = match &self.public_key {
+                PublicKey::ED25519(key) => near_crypto::key_conversion::convert_public_key(key),
+                PublicKey::SECP256K1(_) => None,
+            };
+            if !vrf_public_key.is_some_and(|key| key.is_vrf_valid(&data, &value, &proof)) {
+                return Err(RemoteSignerError::Unavailable("invalid VRF proof".to_string()));
+            }
+            Ok((value, proof))
+        });
+        match result {
+            Ok(vrf) => {
+                metrics::REMOTE_SIGNER_REQUESTS.with_label_values(&["ok"]).inc();
+                Ok(vrf)
+            }
+            Err(err) => self.fallback("vrf", err, |signer| signer.compute_vrf_with_proof(data)),
+        }
+    }
+}
+
+fn parse_field<T: serde::de::DeserializeOwned>(
+    response: &serde_json::Value,
+    field: &str,
+) -> Result<T, RemoteSignerError> {
+    response
+        .get(field)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .ok_or_else(|| RemoteSignerError::Unavailable(format!("missing or invalid {field}")))
+}
+
+/// The timeout is set for each request, from the deadline of the caller.
+fn build_client(config: &RemoteSignerConfig) -> anyhow::Result<reqwest::blocking::Client> {
+    let mut builder = reqwest::blocking::Client::builder();
+    if let Some(ca_cert_file) = &config.ca_cert_file {
+        let ca_cert = reqwest::Certificate::from_pem(&std::fs::read(ca_cert_file)?)?;
+        builder = builder.add_root_certificate(ca_cert);
+    }
+    match (&config.client_cert_file, &config.client_key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            let identity = reqwest::Identity::from_pkcs8_pem(
+                &std::fs::read(cert_file)?,
+                &std::fs::read(key_file)?,
+            )?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => anyhow::bail!("remote signer needs both client_cert_file and client_key_file"),
+    }
+    Ok(builder.build()?)
+}
+
+fn run_requests(
+    client: reqwest::blocking::Client,
+    endpoints: Vec<String>,
+    public_key: PublicKey,
+    receiver: mpsc::Receiver<Request>,
+) {
+    // Index of the endpoint which responded last, tried first.
+    let mut preferred_endpoint = 0;
+    for request in receiver {
+        // Requests which waited in the queue for longer than their caller are
+        // dropped, so that a slow service doesn't build up a backlog.
+        if request.deadline <= Instant::now() {
+            continue;
+        }
+        let body = serde_json::json!({
+            "public_key": public_key,
+            "data": to_base64(&request.data),
+        });
+        let mut last_error = String::new();
+        let mut result = None;
+        for offset in 0..endpoints.len() {
+            let remaining = request.deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                last_error = "timed out".to_string();
+                break;
+            }
+            let index = (preferred_endpoint + offset) % endpoints.len();
+            let endpoint = &endpoints[index];
+            let url = format!("{}/{}", endpoint.trim_end_matches('/'), request.method);
+            let response = match client.post(&url).timeout(remaining).json(&body).send() {
+                Ok(response) => response,
+                Err(err) => {
+                    tracing::debug!(target: "client", %url, %err, "remote signer request failed");
+                    last_error = err.to_string();
+                    continue;
+                }
+            };
+            preferred_endpoint = index;
+            if response.status() == reqwest::StatusCode::CONFLICT {
+                let reason = response.text().unwrap_or_default();
+                result =
+                    Some(Err(RemoteSignerError::Refused { endpoint: endpoint.clone(), reason }));
+                break;
+            }
+            match response.error_for_status().and_then(|response| response.json()) {
+                Ok(response) => {
+                    result = Some(Ok(response));
+                    break;
+                }
+                Err(err) => last_error = err.to_string(),
+            }
+        }
+        let result = result.unwrap_or(Err(RemoteSignerError::Unavailable(last_error)));
+        let _ = request.response.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_primitives::validator_signer::{ExternalValidatorSigner, InMemoryValidatorSigner};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Minimal signing service which signs every request with the key, or
+    /// refuses all of them.
+    fn start_service(key: ValidatorSigner, refuse: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let data = near_primitives::serialize::from_base64(body["data"].as_str().unwrap())
+                    .unwrap();
+                let (status, response) = if refuse {
+                    ("409 Conflict", "double sign".to_string())
+                } else if request_line.contains("/vrf") {
+                    let (value, proof) = key.compute_vrf_with_proof(&data).unwrap();
+                    ("200 OK", serde_json::json!({"value": value, "proof": proof}).to_string())
+                } else {
+                    let signature = key.sign_bytes(&data).unwrap();
+                    ("200 OK", serde_json::json!({"signature": signature}).to_string())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    fn config(key: &ValidatorSigner, endpoints: Vec<String>) -> RemoteSignerConfig {
+        RemoteSignerConfig {
+            account_id: key.validator_id().clone(),
+            public_key: key.public_key(),
+            endpoints,
+            timeout: near_async::time::Duration::seconds(5),
+            ca_cert_file: None,
+            client_cert_file: None,
+            client_key_file: None,
+            fallback_to_local_key: false,
+        }
+    }
+
+    /// Endpoint on which nothing listens.
+    fn unreachable_endpoint() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    /// Endpoint which accepts connections but never responds.
+    fn hanging_endpoint() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut streams = vec![];
+            for stream in listener.incoming() {
+                streams.push(stream);
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_remote_signer_failover() {
+        let key =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let endpoints = vec![unreachable_endpoint(), start_service(key.clone(), false)];
+        let remote = RemoteSigner::new(&config(&key, endpoints), None).unwrap();
+        let signer = ExternalValidatorSigner::new(key.validator_id().clone(), Arc::new(remote));
+
+        let data = b"approval";
+        assert_eq!(signer.sign_bytes(data), key.sign_bytes(data));
+        assert_eq!(signer.compute_vrf_with_proof(data), key.compute_vrf_with_proof(data));
+    }
+
+    #[test]
+    fn test_remote_signer_refusal_and_local_fallback() {
+        let key =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let local_key = Arc::new(key.clone());
+        let data = b"block";
+
+        // A refusal is never overridden by the local key.
+        let mut refusing = config(&key, vec![start_service(key.clone(), true)]);
+        refusing.fallback_to_local_key = true;
+        let remote = RemoteSigner::new(&refusing, Some(local_key.clone())).unwrap();
+        assert!(matches!(remote.sign_bytes(data), Err(SignerError::Refused(_))));
+        assert!(matches!(remote.compute_vrf_with_proof(data), Err(SignerError::Refused(_))));
+
+        let mut unreachable = config(&key, vec![unreachable_endpoint()]);
+        let remote = RemoteSigner::new(&unreachable, None).unwrap();
+        assert!(matches!(remote.sign_bytes(data), Err(SignerError::Unavailable(_))));
+        assert!(matches!(remote.compute_vrf_with_proof(data), Err(SignerError::Unavailable(_))));
+
+        unreachable.fallback_to_local_key = true;
+        let remote = RemoteSigner::new(&unreachable, Some(local_key)).unwrap();
+        assert_eq!(remote.sign_bytes(data), key.sign_bytes(data));
+
+        // The local key must match the remote one.
+        let other_key = Arc::new(InMemoryValidatorSigner::from_signer(InMemorySigner::from_seed(
+            "test".parse().unwrap(),
+            KeyType::ED25519,
+            "other",
+        )));
+        assert!(RemoteSigner::new(&unreachable, Some(other_key)).is_err());
+    }
+
+    /// The caller waits for at most `timeout`, even if several endpoints hang.
+    #[test]
+    fn test_remote_signer_timeout() {
+        let key =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+        let mut config = config(&key, vec![hanging_endpoint(), hanging_endpoint()]);
+        config.timeout = near_async::time::Duration::milliseconds(200);
+        let remote = RemoteSigner::new(&config, None).unwrap();
+
+        let start = Instant::now();
+        assert!(matches!(remote.sign_bytes(b"chunk"), Err(SignerError::Unavailable(_))));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_remote_signer_rejects_non_ed25519_key() {
+        let key =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::SECP256K1, "test");
+        assert!(RemoteSigner::new(&config(&key, vec![unreachable_endpoint()]), None).is_err());
+    }
+}
@@ -6,7 +6,10 @@ use near_chain::check_transaction_validity_period;
 use near_chain::types::RuntimeAdapter;
 use near_chain::types::Tip;
 use near_chain_configs::MutableValidatorSigner;
+use near_chain_configs::TxRebroadcastConfig;
 use near_chunks::client::ShardedTransactionPool;
+use near_client_primitives::types::GetTxForwardingInfo;
+use near_client_primitives::types::GetTxRoutingStatus;
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_assignment::account_id_to_shard_id;
 use near_epoch_manager::shard_tracker::ShardTracker;
@@ -25,14 +28,21 @@ use near_primitives::types::EpochId;
 use near_primitives::types::ShardId;
 use near_primitives::unwrap_or_return;
 use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::views::TxForwardingOutcomeView;
+use near_primitives::views::TxForwardingRecordView;
+use near_primitives::views::TxForwardingTargetView;
+use near_primitives::views::TxRoutingStateView;
+use near_primitives::views::TxRoutingStatusView;
 use near_store::adapter::StoreAdapter;
 use near_store::adapter::chain_store::ChainStoreAdapter;
 use parking_lot::Mutex;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::metrics;
+use crate::pending_txs::PendingTxs;
 use crate::stateless_validation::chunk_endorsement::ChunkEndorsementTracker;
+use crate::tx_forwarding_log::TxForwardingLog;
 
 pub type RpcHandlerActor = SyncActixWrapper<RpcHandler>;
 
@@ -43,6 +53,31 @@ impl Handler<ProcessTxRequest> for RpcHandler {
     }
 }
 
+impl Handler<GetTxForwardingInfo> for RpcHandler {
+    fn handle(&mut self, msg: GetTxForwardingInfo) -> Vec<TxForwardingRecordView> {
+        self.tx_forwarding_log.get(&msg.tx_hash)
+    }
+}
+
+impl Handler<GetTxRoutingStatus> for RpcHandler {
+    fn handle(&mut self, msg: GetTxRoutingStatus) -> Option<TxRoutingStatusView> {
+        self.pending_txs.get(&msg.tx_hash)
+    }
+}
+
+/// Rebroadcasts the pending transactions. Sent on every new head.
+#[derive(actix::Message, Debug)]
+#[rtype(result = "()")]
+pub struct RebroadcastPendingTxs;
+
+impl Handler<RebroadcastPendingTxs> for RpcHandler {
+    fn handle(&mut self, _msg: RebroadcastPendingTxs) {
+        if let Err(err) = self.rebroadcast_pending_txs() {
+            tracing::error!(target: "client", ?err, "Error rebroadcasting pending transactions");
+        }
+    }
+}
+
 impl Handler<ChunkEndorsementMessage> for RpcHandler {
     #[perf]
     fn handle(&mut self, msg: ChunkEndorsementMessage) {
@@ -83,6 +118,7 @@ pub struct RpcHandlerConfig {
     pub tx_routing_height_horizon: u64,
     pub epoch_length: u64,
     pub transaction_validity_period: BlockHeightDelta,
+    pub tx_rebroadcast: Option<TxRebroadcastConfig>,
 }
 
 /// Accepts and processes rpc requests (`process_tx`, etc) and does some preprocessing on incoming data.
@@ -102,6 +138,9 @@ pub struct RpcHandler {
     validator_signer: MutableValidatorSigner,
     runtime: Arc<dyn RuntimeAdapter>,
     network_adapter: PeerManagerAdapter,
+
+    tx_forwarding_log: Arc<TxForwardingLog>,
+    pending_txs: Arc<PendingTxs>,
 }
 
 impl RpcHandler {
@@ -127,6 +166,8 @@ impl RpcHandler {
             runtime,
             shard_tracker,
             network_adapter,
+            tx_forwarding_log: Arc::new(TxForwardingLog::new()),
+            pending_txs: Arc::new(PendingTxs::new()),
         }
     }
 
@@ -141,13 +182,51 @@ impl RpcHandler {
         check_only: bool,
     ) -> ProcessTxResponse {
         let signer = self.validator_signer.get();
-        unwrap_or_return!(self.process_tx_internal(&tx, is_forwarded, check_only, &signer), {
+        let mut forwarded_to = vec![];
+        let result =
+            self.process_tx_internal(&tx, is_forwarded, check_only, &signer, &mut forwarded_to);
+        if !check_only {
+            if !is_forwarded
+                && self.config.tx_rebroadcast.is_some()
+                && matches!(result, Ok(ProcessTxResponse::RequestRouted))
+            {
+                self.add_pending_tx(&tx, &signer, forwarded_to.clone());
+            }
+            self.record_forwarding_decision(&tx, is_forwarded, &result, forwarded_to);
+        }
+        unwrap_or_return!(result, {
             let me = signer.as_ref().map(|signer| signer.validator_id());
             tracing::debug!(target: "client", ?me, ?tx, "Dropping tx");
             ProcessTxResponse::NoResponse
         })
     }
 
+    /// Records what was done with the transaction in the forwarding log, which is
+    /// exposed through `GetTxForwardingInfo`.
+    fn record_forwarding_decision(
+        &self,
+        tx: &SignedTransaction,
+        is_forwarded: bool,
+        result: &Result<ProcessTxResponse, near_client_primitives::types::Error>,
+        forwarded_to: Vec<TxForwardingTargetView>,
+    ) {
+        let outcome = match result {
+            Ok(ProcessTxResponse::ValidTx) => TxForwardingOutcomeView::Accepted,
+            Ok(ProcessTxResponse::RequestRouted) => TxForwardingOutcomeView::Routed,
+            Ok(ProcessTxResponse::InvalidTx(err)) => {
+                TxForwardingOutcomeView::Invalid { error: err.to_string() }
+            }
+            Ok(ProcessTxResponse::DoesNotTrackShard) => TxForwardingOutcomeView::DoesNotTrackShard,
+            Ok(ProcessTxResponse::NoResponse) => TxForwardingOutcomeView::Dropped { error: None },
+            Err(err) => TxForwardingOutcomeView::Dropped { error: Some(err.to_string()) },
+        };
+        let head_height = self.chain_store.head().map(|head| head.height).unwrap_or_default();
+        self.tx_forwarding_log.record(
+            tx.get_hash(),
+            TxForwardingRecordView { head_height, is_forwarded, outcome, forwarded_to },
+        );
+    }
+
     /// Process transaction and either add it to the mempool or return to redirect to another validator.
     fn process_tx_internal(
         &self,
@@ -155,6 +234,7 @@ impl RpcHandler {
         is_forwarded: bool,
         check_only: bool,
         signer: &Option<Arc<ValidatorSigner>>,
+        forwarded_to: &mut Vec<TxForwardingTargetView>,
     ) -> Result<ProcessTxResponse, near_client_primitives::types::Error> {
         let head = self.chain_store.head()?;
         let me = signer.as_ref().map(|vs| vs.validator_id());
@@ -214,7 +294,7 @@ impl RpcHandler {
                                 "Node has not caught up yet".to_string(),
                             ));
                         } else {
-                            self.forward_tx(&epoch_id, signed_tx, signer)?;
+                            self.forward_tx(&epoch_id, signed_tx, signer, forwarded_to)?;
                             return Ok(ProcessTxResponse::RequestRouted);
                         }
                     }
@@ -263,14 +343,14 @@ impl RpcHandler {
                 metrics::TRANSACTION_RECEIVED_VALIDATOR.inc();
 
                 if !is_forwarded {
-                    self.possibly_forward_tx_to_next_epoch(signed_tx, signer)?;
+                    self.possibly_forward_tx_to_next_epoch(signed_tx, signer, forwarded_to)?;
                 }
                 return Ok(ProcessTxResponse::ValidTx);
             }
             if !is_forwarded {
                 tracing::trace!(target: "client", ?shard_id, tx_hash = ?signed_tx.get_hash(), "Forwarding a transaction.");
                 metrics::TRANSACTION_RECEIVED_NON_VALIDATOR.inc();
-                self.forward_tx(&epoch_id, signed_tx, signer)?;
+                self.forward_tx(&epoch_id, signed_tx, signer, forwarded_to)?;
                 return Ok(ProcessTxResponse::RequestRouted);
             }
             tracing::trace!(target: "client", ?shard_id, tx_hash = ?signed_tx.get_hash(), "Non-validator received a forwarded transaction, dropping it.");
@@ -287,15 +367,18 @@ impl RpcHandler {
             return Ok(ProcessTxResponse::NoResponse);
         }
         // We are not tracking this shard, so there is no way to validate this tx. Just rerouting.
-        self.forward_tx(&epoch_id, signed_tx, signer).map(|()| ProcessTxResponse::RequestRouted)
+        self.forward_tx(&epoch_id, signed_tx, signer, forwarded_to)
+            .map(|()| ProcessTxResponse::RequestRouted)
     }
 
     /// Forwards given transaction to upcoming validators.
+    /// The validators it was forwarded to are appended to `forwarded_to`.
     fn forward_tx(
         &self,
         epoch_id: &EpochId,
         tx: &SignedTransaction,
         signer: &Option<Arc<ValidatorSigner>>,
+        forwarded_to: &mut Vec<TxForwardingTargetView>,
     ) -> Result<(), near_client_primitives::types::Error> {
         let shard_id = account_id_to_shard_id(
             self.epoch_manager.as_ref(),
@@ -307,7 +390,7 @@ impl RpcHandler {
         let head = self.chain_store.header_head()?;
         let maybe_next_epoch_id = self.get_next_epoch_id_if_at_boundary(&head)?;
 
-        let mut validators = HashSet::new();
+        let mut validators = HashMap::new();
         for horizon in (2..=self.config.tx_routing_height_horizon)
             .chain(vec![self.config.tx_routing_height_horizon * 2].into_iter())
         {
@@ -320,7 +403,7 @@ impl RpcHandler {
                     shard_id,
                 })?
                 .take_account_id();
-            validators.insert(validator);
+            validators.entry(validator).or_insert(target_height);
             if let Some(next_epoch_id) = &maybe_next_epoch_id {
                 let next_shard_id = account_id_to_shard_id(
                     self.epoch_manager.as_ref(),
@@ -335,17 +418,18 @@ impl RpcHandler {
                         shard_id: next_shard_id,
                     })?
                     .take_account_id();
-                validators.insert(validator);
+                validators.entry(validator).or_insert(target_height);
             }
         }
 
         if let Some(account_id) = signer.as_ref().map(|bp| bp.validator_id()) {
             validators.remove(account_id);
         }
-        for validator in validators {
+        for (validator, height) in validators {
             let tx_hash = tx.get_hash();
             tracing::trace!(target: "client", me = ?signer.as_ref().map(|bp| bp.validator_id()), ?tx_hash, ?validator, ?shard_id, "Routing a transaction");
 
+            forwarded_to.push(TxForwardingTargetView { account_id: validator.clone(), height });
             // Send message to network to actually forward transaction.
             self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::ForwardTx(validator, tx.clone()),
@@ -355,6 +439,135 @@ impl RpcHandler {
         Ok(())
     }
 
+    /// Starts rebroadcasting a transaction routed to other chunk producers, if
+    /// the node tracks the shard of its signer. The node wouldn't see the
+    /// transactions of the other shards included, and would rebroadcast them
+    /// for the whole window.
+    fn add_pending_tx(
+        &self,
+        tx: &SignedTransaction,
+        signer: &Option<Arc<ValidatorSigner>>,
+        forwarded_to: Vec<TxForwardingTargetView>,
+    ) {
+        let head = match self.chain_store.head() {
+            Ok(head) => head,
+            Err(err) => {
+                tracing::debug!(target: "client", ?err, "Cannot rebroadcast a transaction without a head");
+                return;
+            }
+        };
+        match self.tracks_signer_shard(&head, tx, signer) {
+            Ok(true) => self.pending_txs.insert(tx.clone(), head.height, forwarded_to),
+            Ok(false) => {}
+            Err(err) => {
+                tracing::debug!(target: "client", ?err, "Cannot get the shard of a transaction to rebroadcast");
+            }
+        }
+    }
+
+    /// Whether the node tracks the shard of the signer of the transaction, and
+    /// so sees the transaction included.
+    fn tracks_signer_shard(
+        &self,
+        head: &Tip,
+        tx: &SignedTransaction,
+        signer: &Option<Arc<ValidatorSigner>>,
+    ) -> Result<bool, near_client_primitives::types::Error> {
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        let shard_id = account_id_to_shard_id(
+            self.epoch_manager.as_ref(),
+            tx.transaction.signer_id(),
+            &epoch_id,
+        )?;
+        let me = signer.as_ref().map(|signer| signer.validator_id());
+        Ok(self.shard_tracker.cares_about_shard(me, &head.last_block_hash, shard_id, true))
+    }
+
+    /// Rebroadcasts the transactions submitted to the node which are not seen
+    /// included yet, until the end of their rebroadcast window or until they
+    /// expire. The transactions of the shards the node stopped tracking are
+    /// no longer rebroadcast, their inclusion can't be seen.
+    fn rebroadcast_pending_txs(&self) -> Result<(), near_client_primitives::types::Error> {
+        let Some(rebroadcast_config) = &self.config.tx_rebroadcast else {
+            return Ok(());
+        };
+        let head = self.chain_store.head()?;
+        let head_header = self.chain_store.get_block_header(&head.last_block_hash)?;
+        let signer = self.validator_signer.get();
+        for (tx, received_height) in self.pending_txs.pending() {
+            let tx_hash = tx.get_hash();
+            if self.chain_store.get_transaction(&tx_hash)?.is_some() {
+                self.pending_txs.finish(&tx_hash, TxRoutingStateView::Included);
+                continue;
+            }
+            if head.height > received_height + rebroadcast_config.num_blocks
+                || !self.tracks_signer_shard(&head, &tx, &signer)?
+                || check_transaction_validity_period(
+                    &self.chain_store,
+                    &head_header,
+                    tx.transaction.block_hash(),
+                    self.config.transaction_validity_period,
+                )
+                .is_err()
+            {
+                tracing::debug!(target: "client", ?tx_hash, received_height, "Transaction not seen included, no longer rebroadcasting it");
+                self.pending_txs.finish(&tx_hash, TxRoutingStateView::Abandoned);
+                continue;
+            }
+            let mut forwarded_to = vec![];
+            self.rebroadcast_tx(
+                &head,
+                &tx,
+                &signer,
+                rebroadcast_config.num_chunk_producers,
+                &mut forwarded_to,
+            )?;
+            metrics::TRANSACTION_REBROADCAST.inc();
+            self.pending_txs.record_rebroadcast(&tx_hash, head.height, forwarded_to);
+        }
+        Ok(())
+    }
+
+    /// Forwards the transaction to the chunk producers of its shard at the next
+    /// `num_chunk_producers` heights.
+    fn rebroadcast_tx(
+        &self,
+        head: &Tip,
+        tx: &SignedTransaction,
+        signer: &Option<Arc<ValidatorSigner>>,
+        num_chunk_producers: usize,
+        forwarded_to: &mut Vec<TxForwardingTargetView>,
+    ) -> Result<(), near_client_primitives::types::Error> {
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        let shard_id = account_id_to_shard_id(
+            self.epoch_manager.as_ref(),
+            tx.transaction.signer_id(),
+            &epoch_id,
+        )?;
+        let me = signer.as_ref().map(|signer| signer.validator_id());
+        for height in head.height + 1..=head.height + num_chunk_producers as BlockHeightDelta {
+            let validator = self
+                .epoch_manager
+                .get_chunk_producer_info(&ChunkProductionKey {
+                    epoch_id,
+                    height_created: height,
+                    shard_id,
+                })?
+                .take_account_id();
+            if Some(&validator) == me
+                || forwarded_to.iter().any(|target| target.account_id == validator)
+            {
+                continue;
+            }
+            tracing::trace!(target: "client", ?me, tx_hash = ?tx.get_hash(), ?validator, ?shard_id, "Rebroadcasting a transaction");
+            forwarded_to.push(TxForwardingTargetView { account_id: validator.clone(), height });
+            self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                NetworkRequests::ForwardTx(validator, tx.clone()),
+            ));
+        }
+        Ok(())
+    }
+
     /// Determine if I am a validator in next few blocks for specified shard, assuming epoch doesn't change.
     fn active_validator(
         &self,
@@ -392,12 +605,13 @@ impl RpcHandler {
         &self,
         tx: &SignedTransaction,
         signer: &Option<Arc<ValidatorSigner>>,
+        forwarded_to: &mut Vec<TxForwardingTargetView>,
     ) -> Result<(), near_client_primitives::types::Error> {
         let head = self.chain_store.head()?;
         if let Some(next_epoch_id) = self.get_next_epoch_id_if_at_boundary(&head)? {
-            self.forward_tx(&next_epoch_id, tx, signer)?;
+            self.forward_tx(&next_epoch_id, tx, signer, forwarded_to)?;
         } else {
-            self.forward_tx(&head.epoch_id, tx, signer)?;
+            self.forward_tx(&head.epoch_id, tx, signer, forwarded_to)?;
         }
         Ok(())
     }
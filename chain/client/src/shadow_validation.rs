@@ -0,0 +1,111 @@
+//! Shadow validation: a node with the key of a validator does all the work of
+//! the validator, i.e. produces blocks, chunks and state witnesses and
+//! endorses chunks, but never sends any of it to the network. It is used to
+//! measure whether new hardware keeps up before moving the key over.
+//!
+//! The produced messages are dropped by the network adapter of the client, the
+//! shards manager and the partial witness actor, and counted by the
+//! `near_shadow_validation_diverted_messages_total` metric instead. The
+//! produced blocks are not processed either, so that the chain of the node
+//! keeps following the one of the network. The chunks are not published to
+//! the chunk distribution network either.
+//!
+//! Since the account isn't announced, the state witnesses of the chunks to
+//! endorse are routed to the real validator. The node produces them itself for
+//! the tracked shards and validates them instead, see
+//! `Client::shadow_validate_block_chunks`.
+
+use crate::metrics;
+use near_async::messaging::Sender;
+use near_network::types::{NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest};
+
+/// Wraps the network adapter so that the messages produced by the validator
+/// duties are counted and logged instead of being sent.
+pub fn shadow_validation_network_adapter(
+    network_adapter: PeerManagerAdapter,
+) -> PeerManagerAdapter {
+    let request_sender = network_adapter.request_sender;
+    PeerManagerAdapter {
+        request_sender: Sender::from_fn(move |msg: PeerManagerMessageRequest| {
+            match diverted_message_kind(&msg) {
+                Some(kind) => record_diverted_message(kind),
+                None => request_sender.send(msg),
+            }
+        }),
+        ..network_adapter
+    }
+}
+
+pub(crate) fn record_diverted_message(kind: &'static str) {
+    tracing::debug!(target: "client", kind, "Shadow validation: not sending the message");
+    metrics::SHADOW_VALIDATION_DIVERTED_MESSAGES.with_label_values(&[kind]).inc();
+}
+
+/// Returns the kind of the message if it must not be sent by a shadow
+/// validator. Announcing the account would route the messages meant for the
+/// validator to the shadow node.
+fn diverted_message_kind(msg: &PeerManagerMessageRequest) -> Option<&'static str> {
+    let PeerManagerMessageRequest::NetworkRequests(request) = msg else {
+        return None;
+    };
+    let kind = match request {
+        NetworkRequests::Block { .. } => "block",
+        NetworkRequests::OptimisticBlock { .. } => "optimistic_block",
+        NetworkRequests::Approval { .. } => "approval",
+        NetworkRequests::AnnounceAccount(_) => "announce_account",
+        NetworkRequests::PartialEncodedChunkMessage { .. } => "partial_encoded_chunk",
+        NetworkRequests::PartialEncodedChunkForward { .. } => "partial_encoded_chunk_forward",
+        NetworkRequests::ReceiptProofFragment { .. } => "receipt_proof_fragment",
+        NetworkRequests::ChunkStateWitnessAck(..) => "chunk_state_witness_ack",
+        NetworkRequests::ChunkEndorsement(..) => "chunk_endorsement",
+        NetworkRequests::PartialEncodedStateWitness(_) => "partial_encoded_state_witness",
+        NetworkRequests::PartialEncodedStateWitnessForward(..) => {
+            "partial_encoded_state_witness_forward"
+        }
+        NetworkRequests::ChunkContractAccesses(..) => "chunk_contract_accesses",
+        NetworkRequests::ContractCodeResponse(..) => "contract_code_response",
+        NetworkRequests::PartialEncodedContractDeploys(..) => "partial_encoded_contract_deploys",
+        _ => return None,
+    };
+    Some(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shadow_validation_network_adapter;
+    use near_async::messaging::{CanSend, IntoMultiSender, Sender, noop};
+    use near_network::types::{NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::network::PeerId;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_shadow_validation_network_adapter() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let network_adapter = PeerManagerAdapter {
+            request_sender: Sender::from_fn({
+                let sent = sent.clone();
+                move |msg: PeerManagerMessageRequest| sent.lock().push(msg)
+            }),
+            ..noop().into_multi_sender()
+        };
+        let network_adapter = shadow_validation_network_adapter(network_adapter);
+        network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::PartialEncodedStateWitness(vec![]),
+        ));
+        network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::BlockRequest {
+                hash: CryptoHash::default(),
+                peer_id: PeerId::random(),
+            },
+        ));
+        // Only the block request, needed to follow the chain, is sent.
+        let sent = sent.lock();
+        assert_eq!(sent.len(), 1);
+        assert!(matches!(
+            sent[0],
+            PeerManagerMessageRequest::NetworkRequests(NetworkRequests::BlockRequest { .. })
+        ));
+    }
+}
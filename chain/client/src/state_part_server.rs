@@ -0,0 +1,139 @@
+use crate::metrics;
+use lru::LruCache;
+use near_async::time::{Duration, Instant};
+use near_chain_configs::StatePartServingConfig;
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::types::ShardId;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of peers whose recent state part requests are remembered.
+const MAX_TRACKED_PEERS: usize = 1024;
+
+/// Key of a state part: the sync hash, the shard and the part id.
+pub(crate) type StatePartKey = (CryptoHash, ShardId, u64);
+
+/// State shared by the view client threads serving state parts to the peers,
+/// so that its limits apply to the whole thread pool.
+pub(crate) struct StatePartServer {
+    config: StatePartServingConfig,
+    /// Times of the recently served requests of every peer.
+    peer_requests: Mutex<LruCache<PeerId, VecDeque<Instant>>>,
+    /// Recently generated state parts.
+    parts: Mutex<LruCache<StatePartKey, Vec<u8>>>,
+    /// Number of state parts being generated.
+    num_generating: AtomicUsize,
+}
+
+/// Releases the generation slot taken by `StatePartServer::start_generation`.
+pub(crate) struct PartGenerationGuard<'a>(&'a StatePartServer);
+
+impl Drop for PartGenerationGuard<'_> {
+    fn drop(&mut self) {
+        self.0.num_generating.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl StatePartServer {
+    pub fn new(config: StatePartServingConfig) -> Self {
+        Self {
+            peer_requests: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_TRACKED_PEERS).unwrap())),
+            parts: Mutex::new(LruCache::new(NonZeroUsize::new(config.part_cache_size).unwrap())),
+            num_generating: AtomicUsize::new(0),
+            config,
+        }
+    }
+
+    /// Returns true if the request of `peer_id` needs to be **dropped** because
+    /// the peer exceeded its rate limit of state part requests per `period`.
+    pub fn throttle_peer(&self, peer_id: &PeerId, now: Instant, period: Duration) -> bool {
+        let mut peer_requests = self.peer_requests.lock();
+        let requests = peer_requests.get_or_insert_mut(peer_id.clone(), VecDeque::new);
+        while requests.front().is_some_and(|&instant| now - instant > period) {
+            requests.pop_front();
+        }
+        if requests.len() >= self.config.max_requests_per_peer {
+            metrics::STATE_PART_REQUESTS_TOTAL.with_label_values(&["peer_throttled"]).inc();
+            return true;
+        }
+        requests.push_back(now);
+        false
+    }
+
+    /// Counts a request served with a part stored in the database.
+    pub fn record_stored_part(&self) {
+        metrics::STATE_PART_REQUESTS_TOTAL.with_label_values(&["stored"]).inc();
+    }
+
+    /// Returns the part if it was generated recently.
+    pub fn get_part(&self, key: &StatePartKey) -> Option<Vec<u8>> {
+        let part = self.parts.lock().get(key).cloned();
+        if part.is_some() {
+            metrics::STATE_PART_REQUESTS_TOTAL.with_label_values(&["cached"]).inc();
+        }
+        part
+    }
+
+    /// Takes a generation slot, which is released when the returned guard is
+    /// dropped. Returns `None` if the maximum number of parts are already
+    /// being generated.
+    pub fn start_generation(&self) -> Option<PartGenerationGuard<'_>> {
+        let max = self.config.max_concurrent_part_generation;
+        let result = self
+            .num_generating
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < max).then_some(n + 1));
+        if result.is_err() {
+            metrics::STATE_PART_REQUESTS_TOTAL.with_label_values(&["generation_limit"]).inc();
+            return None;
+        }
+        metrics::STATE_PART_REQUESTS_TOTAL.with_label_values(&["generated"]).inc();
+        Some(PartGenerationGuard(self))
+    }
+
+    pub fn insert_part(&self, key: StatePartKey, part: Vec<u8>) {
+        self.parts.lock().put(key, part);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatePartServer;
+    use near_async::time::{Duration, FakeClock, Utc};
+    use near_chain_configs::StatePartServingConfig;
+    use near_primitives::hash::hash;
+    use near_primitives::network::PeerId;
+    use near_primitives::types::ShardId;
+
+    #[test]
+    fn test_state_part_server_limits() {
+        let server = StatePartServer::new(StatePartServingConfig {
+            max_concurrent_part_generation: 1,
+            max_requests_per_peer: 2,
+            part_cache_size: 1,
+        });
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let period = Duration::seconds(1);
+        let peer = PeerId::random();
+        assert!(!server.throttle_peer(&peer, clock.now(), period));
+        assert!(!server.throttle_peer(&peer, clock.now(), period));
+        assert!(server.throttle_peer(&peer, clock.now(), period));
+        assert!(!server.throttle_peer(&PeerId::random(), clock.now(), period));
+        clock.advance(Duration::seconds(2));
+        assert!(!server.throttle_peer(&peer, clock.now(), period));
+
+        let guard = server.start_generation().unwrap();
+        assert!(server.start_generation().is_none());
+        drop(guard);
+        assert!(server.start_generation().is_some());
+
+        let key = (hash(b"sync"), ShardId::new(0), 0);
+        assert_eq!(server.get_part(&key), None);
+        server.insert_part(key, vec![1]);
+        assert_eq!(server.get_part(&key), Some(vec![1]));
+        server.insert_part((hash(b"sync"), ShardId::new(0), 1), vec![2]);
+        assert_eq!(server.get_part(&key), None);
+    }
+}
@@ -1,4 +1,7 @@
-use super::validate::{ChunkRelevance, validate_chunk_endorsement};
+use super::validate::{
+    ChunkRelevance, validate_chunk_endorsement_relevance, validate_chunk_endorsement_signature,
+    validate_chunk_endorsement_signatures,
+};
 use crate::metrics;
 use near_cache::SyncLruCache;
 use near_chain_primitives::Error;
@@ -7,7 +10,9 @@ use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
 use near_primitives::stateless_validation::ChunkProductionKey;
 use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
-use near_primitives::stateless_validation::validator_assignment::ChunkEndorsementsState;
+use near_primitives::stateless_validation::validator_assignment::{
+    ChunkEndorsementsState, ChunkValidatorAssignments,
+};
 use near_primitives::types::AccountId;
 use near_store::Store;
 use std::collections::HashMap;
@@ -18,14 +23,108 @@ use std::sync::Arc;
 // Ideally, we should not be processing more than num_shards chunks at a time.
 const NUM_CHUNKS_IN_CHUNK_ENDORSEMENTS_CACHE: usize = 100;
 
+// Maximum number of endorsements with unverified signatures kept per validator and chunk.
+// Beyond that, endorsements claiming to come from the validator are verified eagerly, so that
+// a peer spamming bogus endorsements can't grow the cache or push out the genuine one.
+const MAX_UNVERIFIED_ENDORSEMENTS_PER_VALIDATOR: usize = 2;
+
+/// Chunk endorsements received for a single ChunkProductionKey.
+#[derive(Default)]
+struct ChunkEndorsements {
+    /// Endorsements with verified signatures.
+    verified: HashMap<AccountId, (ChunkHash, Signature)>,
+    /// Endorsements which passed the relevance checks but whose signatures are not
+    /// verified yet. Verification is deferred until the endorsements of a chunk
+    /// carry enough stake for the chunk to be included into a block.
+    unverified: HashMap<AccountId, Vec<ChunkEndorsement>>,
+}
+
+impl ChunkEndorsements {
+    fn signatures_for_chunk(&self, chunk_hash: &ChunkHash) -> HashMap<&AccountId, Signature> {
+        self.verified
+            .iter()
+            .filter(|(_, (endorsed_chunk_hash, _))| endorsed_chunk_hash == chunk_hash)
+            .map(|(account_id, (_, signature))| (account_id, signature.clone()))
+            .collect()
+    }
+
+    /// Same as `signatures_for_chunk`, but also includes the unverified endorsements,
+    /// assuming that they are valid.
+    fn optimistic_signatures_for_chunk(
+        &self,
+        chunk_hash: &ChunkHash,
+    ) -> HashMap<&AccountId, Signature> {
+        let mut signatures = self.signatures_for_chunk(chunk_hash);
+        for (account_id, endorsements) in &self.unverified {
+            if let Some(endorsement) =
+                endorsements.iter().find(|endorsement| &endorsement.chunk_hash() == chunk_hash)
+            {
+                signatures.entry(account_id).or_insert_with(|| endorsement.signature());
+            }
+        }
+        signatures
+    }
+
+    /// Takes out the pending endorsements for the given chunk if verifying them is worth it,
+    /// that is if the chunk isn't endorsed yet but would be, assuming they are all valid.
+    fn take_endorsements_to_verify(
+        &mut self,
+        chunk_validator_assignments: &ChunkValidatorAssignments,
+        chunk_hash: &ChunkHash,
+    ) -> Vec<ChunkEndorsement> {
+        let endorsement_state = chunk_validator_assignments
+            .compute_endorsement_state(self.signatures_for_chunk(chunk_hash));
+        if endorsement_state.is_endorsed {
+            return vec![];
+        }
+        let optimistic_state = chunk_validator_assignments
+            .compute_endorsement_state(self.optimistic_signatures_for_chunk(chunk_hash));
+        if optimistic_state.endorsed_stake == endorsement_state.endorsed_stake
+            || !optimistic_state.is_endorsed
+        {
+            return vec![];
+        }
+        let mut to_verify = vec![];
+        self.unverified.retain(|_, endorsements| {
+            endorsements.retain(|endorsement| {
+                if &endorsement.chunk_hash() == chunk_hash {
+                    to_verify.push(endorsement.clone());
+                    return false;
+                }
+                true
+            });
+            !endorsements.is_empty()
+        });
+        to_verify
+    }
+
+    fn accept_verified(&mut self, endorsement: ChunkEndorsement) {
+        let account_id = endorsement.account_id();
+        if self.verified.contains_key(account_id) {
+            return;
+        }
+        self.unverified.remove(account_id);
+        metrics::CHUNK_ENDORSEMENTS_ACCEPTED
+            .with_label_values(&[&endorsement.shard_id().to_string()])
+            .inc();
+        self.verified
+            .insert(account_id.clone(), (endorsement.chunk_hash(), endorsement.signature()));
+    }
+}
+
 /// Module to track chunk endorsements received from chunk validators.
+///
+/// Signatures of the endorsements are verified lazily: an endorsement is only checked for
+/// relevance when it is received, and the signatures of all endorsements for a chunk are
+/// verified in one batch once the endorsements carry enough stake to endorse the chunk. This
+/// way a block producer doesn't spend time verifying endorsements for chunks which can't
+/// be included anyway.
 pub struct ChunkEndorsementTracker {
     epoch_manager: Arc<dyn EpochManagerAdapter>,
     /// Used to find the chain HEAD when validating partial witnesses.
     store: Store,
-    /// We store the validated chunk endorsements received from chunk validators.
-    chunk_endorsements:
-        SyncLruCache<ChunkProductionKey, HashMap<AccountId, (ChunkHash, Signature)>>,
+    /// We store the chunk endorsements received from chunk validators.
+    chunk_endorsements: SyncLruCache<ChunkProductionKey, ChunkEndorsements>,
 }
 
 impl ChunkEndorsementTracker {
@@ -39,7 +138,9 @@ impl ChunkEndorsementTracker {
         }
     }
 
-    // Validate the chunk endorsement and store it in the cache.
+    // Check the relevance of the chunk endorsement and store it in the cache.
+    // The signature is verified once the chunk has enough endorsements, together with the
+    // other pending endorsements of the chunk. An error is returned if it is invalid then.
     pub fn process_chunk_endorsement(&self, endorsement: ChunkEndorsement) -> Result<(), Error> {
         // Check if we have already received chunk endorsement from this validator.
         let key = endorsement.chunk_production_key();
@@ -47,33 +148,101 @@ impl ChunkEndorsementTracker {
 
         {
             let cache = self.chunk_endorsements.lock();
-            if cache.peek(&key).is_some_and(|entry| entry.contains_key(account_id)) {
+            if cache.peek(&key).is_some_and(|entry| {
+                entry.verified.contains_key(account_id)
+                    || entry
+                        .unverified
+                        .get(account_id)
+                        .is_some_and(|pending| pending.contains(&endorsement))
+            }) {
                 tracing::debug!(target: "client", ?endorsement, "Already received chunk endorsement.");
                 return Ok(());
             }
         }
 
-        // Validate the chunk endorsement and store it in the cache.
-        match validate_chunk_endorsement(self.epoch_manager.as_ref(), &endorsement, &self.store)? {
-            ChunkRelevance::Relevant => {
-                let mut cache = self.chunk_endorsements.lock();
-                cache.get_or_insert_mut(key, || HashMap::new()).insert(
-                    account_id.clone(),
-                    (endorsement.chunk_hash(), endorsement.signature()),
-                );
-                metrics::CHUNK_ENDORSEMENTS_ACCEPTED
-                    .with_label_values(&[&endorsement.shard_id().to_string()])
-                    .inc();
-            }
+        match validate_chunk_endorsement_relevance(
+            self.epoch_manager.as_ref(),
+            &endorsement,
+            &self.store,
+        )? {
+            ChunkRelevance::Relevant => {}
             irrelevant => {
                 metrics::CHUNK_ENDORSEMENTS_REJECTED
                     .with_label_values(&[&endorsement.shard_id().to_string(), irrelevant.into()])
                     .inc();
+                return Ok(());
+            }
+        }
+
+        let chunk_validator_assignments = self.epoch_manager.get_chunk_validator_assignments(
+            &key.epoch_id,
+            key.shard_id,
+            key.height_created,
+        )?;
+        let to_verify = {
+            let mut cache = self.chunk_endorsements.lock();
+            let entry = cache.get_or_insert_mut(key.clone(), ChunkEndorsements::default);
+            let pending = entry.unverified.entry(account_id.clone()).or_default();
+            if pending.len() < MAX_UNVERIFIED_ENDORSEMENTS_PER_VALIDATOR {
+                pending.push(endorsement.clone());
+                entry.take_endorsements_to_verify(
+                    &chunk_validator_assignments,
+                    &endorsement.chunk_hash(),
+                )
+            } else {
+                // Too many unverified endorsements from this validator, verify this one right away.
+                vec![endorsement.clone()]
             }
         };
+
+        let invalid = self.verify_endorsements(&key, to_verify);
+        if invalid.contains(&endorsement) {
+            return Err(Error::InvalidChunkEndorsement);
+        }
         Ok(())
     }
 
+    /// Verifies the signatures of the endorsements in one batch, without holding the cache
+    /// lock, and moves the valid ones to the verified endorsements of `key`. Returns the
+    /// invalid endorsements.
+    fn verify_endorsements(
+        &self,
+        key: &ChunkProductionKey,
+        endorsements: Vec<ChunkEndorsement>,
+    ) -> Vec<ChunkEndorsement> {
+        if endorsements.is_empty() {
+            return vec![];
+        }
+        let shard_label = key.shard_id.to_string();
+        let (valid, invalid): (Vec<_>, Vec<_>) = {
+            let _timer = metrics::CHUNK_ENDORSEMENTS_VERIFICATION_TIME
+                .with_label_values(&[&shard_label])
+                .start_timer();
+            let epoch_manager = self.epoch_manager.as_ref();
+            if validate_chunk_endorsement_signatures(epoch_manager, &endorsements).is_ok() {
+                (endorsements, vec![])
+            } else {
+                // The batch doesn't tell which endorsements are invalid.
+                endorsements.into_iter().partition(|endorsement| {
+                    validate_chunk_endorsement_signature(epoch_manager, endorsement).is_ok()
+                })
+            }
+        };
+
+        for endorsement in &invalid {
+            tracing::debug!(target: "client", ?endorsement, "Dropping chunk endorsement which failed signature verification");
+            metrics::CHUNK_ENDORSEMENTS_REJECTED
+                .with_label_values(&[&shard_label, "invalid_signature"])
+                .inc();
+        }
+        let mut cache = self.chunk_endorsements.lock();
+        let entry = cache.get_or_insert_mut(key.clone(), ChunkEndorsements::default);
+        for endorsement in valid {
+            entry.accept_verified(endorsement);
+        }
+        invalid
+    }
+
     /// This function is called by block producer potentially multiple times if there's not enough stake.
     pub fn collect_chunk_endorsements(
         &self,
@@ -92,23 +261,147 @@ impl ChunkEndorsementTracker {
             chunk_header.height_created(),
         )?;
 
-        // Get the chunk_endorsements for the chunk from our cache.
-        // Note that these chunk endorsements are already validated as part of process_chunk_endorsement.
-        // We can safely rely on the following details
-        //    1. The chunk endorsements are from valid chunk_validator for this chunk.
-        //    2. The chunk endorsements signatures are valid.
-        //    3. We still need to validate if the chunk_hash matches the chunk_header.chunk_hash()
+        // Note that the verified chunk endorsements are from valid chunk validators for
+        // this chunk and have valid signatures, but we still need to check that the
+        // chunk_hash matches the chunk_header.chunk_hash().
+        let chunk_hash = chunk_header.chunk_hash();
         let mut cache = self.chunk_endorsements.lock();
-        let entry = cache.get_or_insert(key, || HashMap::new());
-        let validator_signatures = entry
-            .into_iter()
-            .filter(|(_, (chunk_hash, _))| chunk_hash == &chunk_header.chunk_hash())
-            .map(|(account_id, (_, signature))| (account_id, signature.clone()))
-            .collect();
+        let entry = cache.get_or_insert_mut(key, ChunkEndorsements::default);
 
         // [perf] The lock here is held over the `compute_endorsement_state` call. If that constitutes an
         // expensive call AND we get into lock contention here, one needs to investigate into
         // cloning the account_id or using the RwLock.
-        Ok(chunk_validator_assignments.compute_endorsement_state(validator_signatures))
+        Ok(chunk_validator_assignments
+            .compute_endorsement_state(entry.signatures_for_chunk(&chunk_hash)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkEndorsementTracker;
+    use crate::metrics;
+    use near_async::messaging::{IntoMultiSender, noop};
+    use near_async::time::Clock;
+    use near_chain::rayon_spawner::RayonAsyncComputationSpawner;
+    use near_chain::runtime::NightshadeRuntime;
+    use near_chain::types::ChainConfig;
+    use near_chain::{Chain, ChainGenesis, DoomslugThresholdMode};
+    use near_chain_configs::{Genesis, MutableConfigValue};
+    use near_crypto::KeyType;
+    use near_epoch_manager::shard_tracker::ShardTracker;
+    use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+    use near_primitives::sharding::ShardChunkHeader;
+    use near_primitives::stateless_validation::ChunkProductionKey;
+    use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
+    use near_primitives::test_utils::create_test_signer;
+    use near_primitives::types::{AccountId, EpochId};
+    use near_primitives::validator_signer::InMemoryValidatorSigner;
+    use near_store::genesis::initialize_genesis_state;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_deferred_signature_verification() {
+        let store = near_store::test_utils::create_test_store();
+        let accounts: Vec<AccountId> =
+            (0..4).map(|i| format!("test{i}").parse().unwrap()).collect();
+        let genesis = Genesis::test(accounts, 4);
+        let tempdir = tempfile::tempdir().unwrap();
+        initialize_genesis_state(store.clone(), &genesis, Some(tempdir.path()));
+        let epoch_manager = EpochManager::new_arc_handle(store.clone(), &genesis.config, None);
+        let shard_tracker = ShardTracker::new_empty(epoch_manager.clone());
+        let runtime = NightshadeRuntime::test(
+            tempdir.path(),
+            store.clone(),
+            &genesis.config,
+            epoch_manager.clone(),
+        );
+        let chain = Chain::new(
+            Clock::real(),
+            epoch_manager.clone(),
+            shard_tracker,
+            runtime,
+            &ChainGenesis::new(&genesis.config),
+            DoomslugThresholdMode::TwoThirds,
+            ChainConfig::test(),
+            None,
+            Arc::new(RayonAsyncComputationSpawner),
+            MutableConfigValue::new(None, "validator_signer"),
+            noop().into_multi_sender(),
+        )
+        .unwrap();
+
+        let genesis_hash = *chain.genesis().hash();
+        let epoch_id = EpochId::default();
+        let shard_id =
+            epoch_manager.get_shard_layout(&epoch_id).unwrap().shard_ids().next().unwrap();
+        let chunk_header = ShardChunkHeader::new_dummy(1, shard_id, genesis_hash);
+        let key = ChunkProductionKey { shard_id, epoch_id, height_created: 1 };
+        let validators = epoch_manager
+            .get_chunk_validator_assignments(&epoch_id, shard_id, 1)
+            .unwrap()
+            .ordered_chunk_validators();
+        assert_eq!(validators.len(), 4);
+        let endorsement = |account_id: &AccountId| {
+            ChunkEndorsement::new(epoch_id, &chunk_header, &create_test_signer(account_id.as_str()))
+                .unwrap()
+        };
+        // Claims to come from a chunk validator, but is signed with a different key.
+        let forged_endorsement = ChunkEndorsement::new(
+            epoch_id,
+            &chunk_header,
+            &InMemoryValidatorSigner::from_seed(validators[2].clone(), KeyType::ED25519, "forged"),
+        )
+        .unwrap();
+
+        let shard_label = shard_id.to_string();
+        let rejected = || {
+            metrics::CHUNK_ENDORSEMENTS_REJECTED
+                .with_label_values(&[&shard_label, "invalid_signature"])
+                .get()
+        };
+        let verifications = || {
+            metrics::CHUNK_ENDORSEMENTS_VERIFICATION_TIME
+                .with_label_values(&[&shard_label])
+                .get_sample_count()
+        };
+        let rejected_before = rejected();
+        let verifications_before = verifications();
+
+        let tracker = ChunkEndorsementTracker::new(epoch_manager, store);
+        let num_verified =
+            || tracker.chunk_endorsements.lock().peek(&key).map_or(0, |entry| entry.verified.len());
+
+        // Two endorsements out of four don't carry enough stake, so nothing gets verified.
+        tracker.process_chunk_endorsement(endorsement(&validators[0])).unwrap();
+        tracker.process_chunk_endorsement(endorsement(&validators[1])).unwrap();
+        let state = tracker.collect_chunk_endorsements(&chunk_header).unwrap();
+        assert!(!state.is_endorsed);
+        assert_eq!(num_verified(), 0);
+        assert_eq!(verifications(), verifications_before);
+
+        // With the forged endorsement the chunk would be endorsed, so the pending
+        // signatures are verified in one batch and the forged one is rejected.
+        assert!(tracker.process_chunk_endorsement(forged_endorsement).is_err());
+        let state = tracker.collect_chunk_endorsements(&chunk_header).unwrap();
+        assert!(!state.is_endorsed);
+        assert_eq!(state.endorsed_validators_count, 2);
+        assert_eq!(num_verified(), 2);
+        assert_eq!(rejected(), rejected_before + 1);
+        assert_eq!(verifications(), verifications_before + 1);
+
+        // The genuine endorsement of the same validator is still accepted afterwards.
+        tracker.process_chunk_endorsement(endorsement(&validators[2])).unwrap();
+        let state = tracker.collect_chunk_endorsements(&chunk_header).unwrap();
+        assert!(state.is_endorsed);
+        assert_eq!(state.endorsed_validators_count, 3);
+        assert_eq!(num_verified(), 3);
+        assert_eq!(verifications(), verifications_before + 2);
+
+        // Once the chunk is endorsed, further endorsements aren't verified.
+        tracker.process_chunk_endorsement(endorsement(&validators[3])).unwrap();
+        let state = tracker.collect_chunk_endorsements(&chunk_header).unwrap();
+        assert!(state.is_endorsed);
+        assert_eq!(state.endorsed_validators_count, 3);
+        assert_eq!(verifications(), verifications_before + 2);
     }
 }
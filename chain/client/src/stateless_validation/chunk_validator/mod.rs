@@ -2,6 +2,8 @@ pub mod orphan_witness_handling;
 pub mod orphan_witness_pool;
 
 use crate::Client;
+use crate::stateless_validation::partial_witness::partial_witness_actor::RequestMissingStateWitnessRequest;
+use crate::validator_duties::ChunkValidationTimings;
 use itertools::Itertools;
 use near_async::futures::{AsyncComputationSpawner, AsyncComputationSpawnerExt};
 use near_async::messaging::{CanSend, Sender};
@@ -44,6 +46,7 @@ pub struct ChunkValidator {
     orphan_witness_pool: OrphanStateWitnessPool,
     validation_spawner: Arc<dyn AsyncComputationSpawner>,
     main_state_transition_result_cache: chunk_validation::MainStateTransitionCache,
+    chunk_validation_timings: Arc<ChunkValidationTimings>,
 }
 
 impl ChunkValidator {
@@ -53,6 +56,7 @@ impl ChunkValidator {
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         orphan_witness_pool_size: usize,
         validation_spawner: Arc<dyn AsyncComputationSpawner>,
+        chunk_validation_timings: Arc<ChunkValidationTimings>,
     ) -> Self {
         Self {
             epoch_manager,
@@ -62,6 +66,7 @@ impl ChunkValidator {
             validation_spawner,
             main_state_transition_result_cache: chunk_validation::MainStateTransitionCache::default(
             ),
+            chunk_validation_timings,
         }
     }
 
@@ -134,9 +139,13 @@ impl ChunkValidator {
                         signer,
                         &network_sender,
                     );
+                    self.chunk_validation_timings
+                        .record_endorsement_sent(&chunk_header.chunk_hash());
                     return Ok(());
                 }
                 Err(err) => {
+                    self.chunk_validation_timings
+                        .record_validation_failed(&chunk_header.chunk_hash());
                     tracing::error!(
                         target: "client",
                         ?err,
@@ -155,6 +164,7 @@ impl ChunkValidator {
         let runtime_adapter = self.runtime_adapter.clone();
         let cache = self.main_state_transition_result_cache.clone();
         let signer = signer.clone();
+        let chunk_validation_timings = self.chunk_validation_timings.clone();
         self.validation_spawner.spawn("stateless_validation", move || {
             // processing_done_tracker must survive until the processing is finished.
             let _processing_done_tracker_capture: Option<ProcessingDoneTracker> =
@@ -174,8 +184,10 @@ impl ChunkValidator {
                         signer.as_ref(),
                         &network_sender,
                     );
+                    chunk_validation_timings.record_endorsement_sent(&chunk_header.chunk_hash());
                 }
                 Err(err) => {
+                    chunk_validation_timings.record_validation_failed(&chunk_header.chunk_hash());
                     near_chain::stateless_validation::metrics::CHUNK_WITNESS_VALIDATION_FAILED_TOTAL
                         .with_label_values(&[&shard_id.to_string(), err.prometheus_label_value()])
                         .inc();
@@ -196,7 +208,8 @@ impl ChunkValidator {
 /// Sends the chunk endorsement to the next
 /// `NUM_NEXT_BLOCK_PRODUCERS_TO_SEND_CHUNK_ENDORSEMENT` block producers.
 /// Additionally returns chunk endorsement if the signer is one of these block
-/// producers, to be able to process it immediately.
+/// producers, to be able to process it immediately. Nothing is sent if the
+/// endorsement can't be signed.
 pub(crate) fn send_chunk_endorsement_to_block_producers(
     chunk_header: &ShardChunkHeader,
     epoch_manager: &dyn EpochManagerAdapter,
@@ -226,7 +239,13 @@ pub(crate) fn send_chunk_endorsement_to_block_producers(
         "send_chunk_endorsement",
     );
 
-    let endorsement = ChunkEndorsement::new(epoch_id, chunk_header, signer);
+    let endorsement = match ChunkEndorsement::new(epoch_id, chunk_header, signer) {
+        Ok(endorsement) => endorsement,
+        Err(err) => {
+            tracing::error!(target: "client", ?chunk_hash, ?err, "Failed to sign chunk endorsement");
+            return None;
+        }
+    };
     let mut send_to_itself = None;
     for block_producer in block_producers {
         if &block_producer == signer.validator_id() {
@@ -269,6 +288,9 @@ impl Client {
         // This is currently used for network roundtrip time measurement, so we do not need to
         // wait for validation to finish.
         self.send_state_witness_ack(&witness)?;
+        self.validator_duty_tracker
+            .chunk_validation_timings
+            .record_witness_received(&witness.chunk_header().chunk_hash());
 
         if self.config.save_latest_witnesses {
             self.chain.chain_store.save_latest_chunk_state_witness(&witness)?;
@@ -328,4 +350,42 @@ impl Client {
             signer,
         )
     }
+
+    /// When the node is catching up with the chain, the witnesses for the chunks built on
+    /// top of the new head were distributed while the node was still behind and got dropped
+    /// as too early. Asks the partial witness actor to request them from the chunk producers,
+    /// which retain the produced witnesses for `produced_state_witness_retention_heights`.
+    pub fn request_missing_state_witnesses(
+        &self,
+        new_head: &Block,
+        signer: &Option<Arc<ValidatorSigner>>,
+    ) -> Result<(), Error> {
+        let Some(signer) = signer else {
+            return Ok(());
+        };
+        let head_height = new_head.header().height();
+        let header_head_height = self.chain.header_head()?.height;
+        if header_head_height <= head_height
+            || header_head_height
+                > head_height + self.config.produced_state_witness_retention_heights
+        {
+            return Ok(());
+        }
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(new_head.hash())?;
+        let height_created = head_height + 1;
+        for shard_id in self.epoch_manager.shard_ids(&epoch_id)? {
+            let assignments = self.epoch_manager.get_chunk_validator_assignments(
+                &epoch_id,
+                shard_id,
+                height_created,
+            )?;
+            if !assignments.contains(signer.validator_id()) {
+                continue;
+            }
+            self.partial_witness_adapter.send(RequestMissingStateWitnessRequest {
+                key: ChunkProductionKey { shard_id, epoch_id, height_created },
+            });
+        }
+        Ok(())
+    }
 }
@@ -2,5 +2,6 @@ mod encoding;
 mod partial_deploys_tracker;
 pub mod partial_witness_actor;
 mod partial_witness_tracker;
+mod produced_witness_cache;
 
 pub use encoding::witness_part_length;
@@ -17,6 +17,7 @@ use near_network::state_witness::{
     ChunkContractAccessesMessage, ChunkStateWitnessAckMessage, ContractCodeRequestMessage,
     ContractCodeResponseMessage, PartialEncodedContractDeploysMessage,
     PartialEncodedStateWitnessForwardMessage, PartialEncodedStateWitnessMessage,
+    PartialEncodedStateWitnessRequestMessage,
 };
 use near_network::types::{NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest};
 use near_parameters::RuntimeConfig;
@@ -29,13 +30,15 @@ use near_primitives::stateless_validation::contract_distribution::{
     ContractCodeResponse, ContractUpdates, MainTransitionKey, PartialEncodedContractDeploys,
     PartialEncodedContractDeploysPart,
 };
-use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitness;
+use near_primitives::stateless_validation::partial_witness::{
+    PartialEncodedStateWitness, PartialEncodedStateWitnessRequest,
+};
 use near_primitives::stateless_validation::state_witness::{
     ChunkStateWitness, ChunkStateWitnessAck, EncodedChunkStateWitness,
 };
 use near_primitives::stateless_validation::stored_chunk_state_transition_data::StoredChunkStateTransitionData;
-use near_primitives::types::{AccountId, EpochId, ShardId};
-use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::types::{AccountId, BlockHeightDelta, EpochId, ShardId};
+use near_primitives::validator_signer::{SignerError, ValidatorSigner};
 use near_store::adapter::trie_store::TrieStoreAdapter;
 use near_store::{DBCol, StorageError, TrieDBStorage, TrieStorage};
 use near_vm_runner::{ContractCode, ContractRuntimeCache, get_contract_cache_key};
@@ -47,14 +50,17 @@ use crate::stateless_validation::state_witness_tracker::ChunkStateWitnessTracker
 use crate::stateless_validation::validate::{
     ChunkRelevance, validate_chunk_contract_accesses, validate_contract_code_request,
     validate_partial_encoded_contract_deploys, validate_partial_encoded_state_witness,
+    validate_partial_encoded_state_witness_request,
 };
 
 use super::encoding::{CONTRACT_DEPLOYS_RATIO_DATA_PARTS, WITNESS_RATIO_DATA_PARTS};
 use super::partial_deploys_tracker::PartialEncodedContractDeploysTracker;
 use super::partial_witness_tracker::PartialEncodedStateWitnessTracker;
+use super::produced_witness_cache::ProducedStateWitnessCache;
 use near_primitives::utils::compression::CompressedData;
 
 const PROCESSED_CONTRACT_CODE_REQUESTS_CACHE_SIZE: usize = 30;
+const PROCESSED_STATE_WITNESS_REQUESTS_CACHE_SIZE: usize = 30;
 
 pub struct PartialWitnessActor {
     /// Adapter to send messages to the network.
@@ -79,6 +85,11 @@ pub struct PartialWitnessActor {
     partial_witness_spawner: Arc<dyn AsyncComputationSpawner>,
     /// AccountId in the key corresponds to the requester (chunk validator).
     processed_contract_code_requests: LruCache<(ChunkProductionKey, AccountId), ()>,
+    /// Parts of the state witnesses recently distributed by this node, kept to serve
+    /// `PartialEncodedStateWitnessRequest`s from chunk validators which missed them.
+    produced_witness_cache: ProducedStateWitnessCache,
+    /// AccountId in the key corresponds to the requester (chunk validator).
+    processed_state_witness_requests: LruCache<(ChunkProductionKey, AccountId), ()>,
 }
 
 impl Actor for PartialWitnessActor {}
@@ -91,9 +102,19 @@ pub struct DistributeStateWitnessRequest {
     pub main_transition_shard_id: ShardId,
 }
 
+/// Sent by the client (on a chunk validator) when the state witness for the given chunk
+/// was most likely distributed while the node was behind the chain. If no part of the
+/// witness has been received, the parts are requested from the chunk producer.
+#[derive(actix::Message, Debug)]
+#[rtype(result = "()")]
+pub struct RequestMissingStateWitnessRequest {
+    pub key: ChunkProductionKey,
+}
+
 #[derive(Clone, MultiSend, MultiSenderFrom)]
 pub struct PartialWitnessSenderForClient {
     pub distribute_chunk_state_witness: Sender<DistributeStateWitnessRequest>,
+    pub request_missing_state_witness: Sender<RequestMissingStateWitnessRequest>,
 }
 
 impl Handler<DistributeStateWitnessRequest> for PartialWitnessActor {
@@ -105,6 +126,14 @@ impl Handler<DistributeStateWitnessRequest> for PartialWitnessActor {
     }
 }
 
+impl Handler<RequestMissingStateWitnessRequest> for PartialWitnessActor {
+    fn handle(&mut self, msg: RequestMissingStateWitnessRequest) {
+        if let Err(err) = self.handle_request_missing_state_witness(msg.key) {
+            tracing::error!(target: "client", ?err, "Failed to handle request missing state witness request");
+        }
+    }
+}
+
 impl Handler<ChunkStateWitnessAckMessage> for PartialWitnessActor {
     fn handle(&mut self, msg: ChunkStateWitnessAckMessage) {
         self.handle_chunk_state_witness_ack(msg.0);
@@ -127,6 +156,14 @@ impl Handler<PartialEncodedStateWitnessForwardMessage> for PartialWitnessActor {
     }
 }
 
+impl Handler<PartialEncodedStateWitnessRequestMessage> for PartialWitnessActor {
+    fn handle(&mut self, msg: PartialEncodedStateWitnessRequestMessage) {
+        if let Err(err) = self.handle_partial_encoded_state_witness_request(msg.0) {
+            tracing::error!(target: "client", ?err, "Failed to handle PartialEncodedStateWitnessRequestMessage");
+        }
+    }
+}
+
 impl Handler<ChunkContractAccessesMessage> for PartialWitnessActor {
     fn handle(&mut self, msg: ChunkContractAccessesMessage) {
         if let Err(err) = self.handle_chunk_contract_accesses(msg.0) {
@@ -169,6 +206,7 @@ impl PartialWitnessActor {
         runtime: Arc<dyn RuntimeAdapter>,
         compile_contracts_spawner: Arc<dyn AsyncComputationSpawner>,
         partial_witness_spawner: Arc<dyn AsyncComputationSpawner>,
+        produced_witness_retention_heights: BlockHeightDelta,
     ) -> Self {
         let partial_witness_tracker =
             Arc::new(PartialEncodedStateWitnessTracker::new(client_sender, epoch_manager.clone()));
@@ -189,6 +227,12 @@ impl PartialWitnessActor {
             processed_contract_code_requests: LruCache::new(
                 NonZeroUsize::new(PROCESSED_CONTRACT_CODE_REQUESTS_CACHE_SIZE).unwrap(),
             ),
+            produced_witness_cache: ProducedStateWitnessCache::new(
+                produced_witness_retention_heights,
+            ),
+            processed_state_witness_requests: LruCache::new(
+                NonZeroUsize::new(PROCESSED_STATE_WITNESS_REQUESTS_CACHE_SIZE).unwrap(),
+            ),
         }
     }
 
@@ -233,7 +277,7 @@ impl PartialWitnessActor {
                 },
                 &chunk_validators,
                 &signer,
-            );
+            )?;
         }
 
         let witness_bytes = compress_witness(&state_witness)?;
@@ -243,7 +287,7 @@ impl PartialWitnessActor {
             witness_bytes,
             &chunk_validators,
             &signer,
-        );
+        )?;
 
         if !contract_deploys.is_empty() {
             self.send_chunk_contract_deploys_parts(key, contract_deploys)?;
@@ -259,7 +303,7 @@ impl PartialWitnessActor {
         witness_bytes: EncodedChunkStateWitness,
         chunk_validators: &[AccountId],
         signer: &ValidatorSigner,
-    ) -> Vec<(AccountId, PartialEncodedStateWitness)> {
+    ) -> Result<Vec<(AccountId, PartialEncodedStateWitness)>, SignerError> {
         tracing::debug!(
             target: "client",
             chunk_hash=?chunk_header.chunk_hash(),
@@ -285,10 +329,10 @@ impl PartialWitnessActor {
                     part.unwrap().to_vec(),
                     encoded_length,
                     signer,
-                );
-                (chunk_validator.clone(), partial_witness)
+                )?;
+                Ok((chunk_validator.clone(), partial_witness))
             })
-            .collect_vec()
+            .collect()
     }
 
     fn generate_contract_deploys_parts(
@@ -320,10 +364,10 @@ impl PartialWitnessActor {
                         encoded_length,
                     },
                     &signer,
-                );
-                (validator, partial_deploys)
+                )?;
+                Ok((validator, partial_deploys))
             })
-            .collect_vec())
+            .collect::<Result<_, SignerError>>()?)
     }
 
     // Break the state witness into parts and send each part to the corresponding chunk validator owner.
@@ -336,7 +380,7 @@ impl PartialWitnessActor {
         witness_bytes: EncodedChunkStateWitness,
         chunk_validators: &[AccountId],
         signer: &ValidatorSigner,
-    ) {
+    ) -> Result<(), Error> {
         // Capture these values first, as the sources are consumed before calling record_witness_sent.
         let chunk_hash = chunk_header.chunk_hash();
         let witness_size_in_bytes = witness_bytes.size_bytes();
@@ -352,7 +396,7 @@ impl PartialWitnessActor {
             witness_bytes,
             chunk_validators,
             signer,
-        );
+        )?;
         encode_timer.observe_duration();
 
         // Record the witness in order to match the incoming acks for measuring round-trip times.
@@ -363,10 +407,19 @@ impl PartialWitnessActor {
             validator_witness_tuple.len(),
         );
 
+        // Keep the parts around so that they can be sent again to validators which missed them.
+        if let Some((_, part)) = validator_witness_tuple.first() {
+            self.produced_witness_cache.insert(
+                part.chunk_production_key(),
+                validator_witness_tuple.iter().map(|(_, part)| part.clone()).collect(),
+            );
+        }
+
         // Send the parts to the corresponding chunk validator owners.
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
             NetworkRequests::PartialEncodedStateWitness(validator_witness_tuple),
         ));
+        Ok(())
     }
 
     /// Function to handle receiving partial_encoded_state_witness message from chunk producer.
@@ -487,6 +540,72 @@ impl PartialWitnessActor {
         Ok(())
     }
 
+    /// Function to handle the request for missing witness parts from the client (on a chunk validator).
+    fn handle_request_missing_state_witness(&self, key: ChunkProductionKey) -> Result<(), Error> {
+        if self.partial_witness_tracker.has_witness_parts(&key) {
+            return Ok(());
+        }
+        let signer = self.my_validator_signer()?;
+        let chunk_producer = self.epoch_manager.get_chunk_producer_info(&key)?.take_account_id();
+        if &chunk_producer == signer.validator_id() {
+            return Ok(());
+        }
+        tracing::debug!(target: "client", ?key, ?chunk_producer, "Requesting missing state witness parts");
+        metrics::PARTIAL_WITNESS_REQUESTS_SENT.inc();
+        let request = PartialEncodedStateWitnessRequest::new(key, &signer)?;
+        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::PartialEncodedStateWitnessRequest(chunk_producer, request),
+        ));
+        Ok(())
+    }
+
+    /// Function to handle the request for witness parts from a chunk validator (on a chunk producer).
+    /// The retained parts are forwarded to the requester the same way the part owners forward them,
+    /// so the chunk validator processes them as if they arrived in time.
+    fn handle_partial_encoded_state_witness_request(
+        &mut self,
+        request: PartialEncodedStateWitnessRequest,
+    ) -> Result<(), Error> {
+        tracing::debug!(target: "client", ?request, "Receive PartialEncodedStateWitnessRequestMessage");
+        if !validate_partial_encoded_state_witness_request(
+            self.epoch_manager.as_ref(),
+            &request,
+            self.runtime.store(),
+        )?
+        .is_relevant()
+        {
+            return Ok(());
+        }
+
+        let key = request.chunk_production_key();
+        let processed_requests_key = (key.clone(), request.requester().clone());
+        if self.processed_state_witness_requests.contains(&processed_requests_key) {
+            tracing::debug!(
+                target: "client",
+                ?processed_requests_key,
+                "State witness request from this account was already processed"
+            );
+            return Ok(());
+        }
+        self.processed_state_witness_requests.push(processed_requests_key, ());
+
+        let Some(parts) = self.produced_witness_cache.get(key) else {
+            tracing::debug!(target: "client", ?key, "Requested state witness is not retained");
+            metrics::PARTIAL_WITNESS_REQUESTS_SERVED.with_label_values(&["missing"]).inc();
+            return Ok(());
+        };
+        metrics::PARTIAL_WITNESS_REQUESTS_SERVED.with_label_values(&["served"]).inc();
+        for part in parts {
+            self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                NetworkRequests::PartialEncodedStateWitnessForward(
+                    vec![request.requester().clone()],
+                    part.clone(),
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     /// Handles partial contract deploy message received from a peer.
     ///
     /// This message may belong to one of two steps of distributing contract code. In the first step the code is compressed
@@ -638,7 +757,7 @@ impl PartialWitnessActor {
             missing_contract_hashes,
             accesses.main_transition().clone(),
             &signer,
-        );
+        )?;
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
             NetworkRequests::ContractCodeRequest(random_chunk_producer, request),
         ));
@@ -655,7 +774,7 @@ impl PartialWitnessActor {
         main_transition: MainTransitionKey,
         chunk_validators: &[AccountId],
         my_signer: &ValidatorSigner,
-    ) {
+    ) -> Result<(), Error> {
         let chunk_producers: HashSet<AccountId> = self
             .epoch_manager
             .get_epoch_chunk_producers_for_shard(&key.epoch_id, key.shard_id)
@@ -669,12 +788,12 @@ impl PartialWitnessActor {
             .filter(|validator| !chunk_producers.contains(*validator))
             .cloned()
             .collect();
+        let accesses =
+            ChunkContractAccesses::new(key, contract_accesses, main_transition, my_signer)?;
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-            NetworkRequests::ChunkContractAccesses(
-                target_chunk_validators,
-                ChunkContractAccesses::new(key, contract_accesses, main_transition, my_signer),
-            ),
+            NetworkRequests::ChunkContractAccesses(target_chunk_validators, accesses),
         ));
+        Ok(())
     }
 
     /// Retrieves the code for the given contract hashes and distributes them to validator in parts.
@@ -367,6 +367,21 @@ impl PartialEncodedStateWitnessTracker {
         self.process_update(key, false, update)
     }
 
+    /// Returns true if any part of the witness has been received, or the witness
+    /// has already been decoded.
+    pub fn has_witness_parts(&self, key: &ChunkProductionKey) -> bool {
+        if self.processed_witnesses.contains(key) {
+            return true;
+        }
+        let Some(parts_cache_by_shard) = self.parts_cache.lock().get(&key.shard_id).cloned() else {
+            return false;
+        };
+        parts_cache_by_shard
+            .lock()
+            .peek(key)
+            .is_some_and(|entry| !matches!(entry.witness_parts, WitnessPartsState::Empty))
+    }
+
     fn process_update(
         &self,
         key: ChunkProductionKey,
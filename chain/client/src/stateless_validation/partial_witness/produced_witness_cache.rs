@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use near_primitives::stateless_validation::ChunkProductionKey;
+use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitness;
+use near_primitives::types::{BlockHeight, BlockHeightDelta};
+
+/// Keeps the parts of the state witnesses distributed by this chunk producer for the
+/// last `retention_heights` heights. Chunk validators which fell behind and dropped the
+/// parts when they were first sent can request them again, see
+/// `PartialEncodedStateWitnessRequest`.
+pub struct ProducedStateWitnessCache {
+    retention_heights: BlockHeightDelta,
+    /// Highest `height_created` of the witnesses inserted so far.
+    latest_height: BlockHeight,
+    parts: HashMap<ChunkProductionKey, Vec<PartialEncodedStateWitness>>,
+}
+
+impl ProducedStateWitnessCache {
+    pub fn new(retention_heights: BlockHeightDelta) -> Self {
+        Self { retention_heights, latest_height: 0, parts: HashMap::new() }
+    }
+
+    /// Stores the parts of a newly distributed witness and evicts the witnesses which
+    /// are older than the retention window. Does nothing if retention is disabled.
+    pub fn insert(&mut self, key: ChunkProductionKey, parts: Vec<PartialEncodedStateWitness>) {
+        if self.retention_heights == 0 {
+            return;
+        }
+        self.latest_height = self.latest_height.max(key.height_created);
+        let min_height = self.latest_height.saturating_sub(self.retention_heights - 1);
+        self.parts.retain(|key, _| key.height_created >= min_height);
+        if key.height_created >= min_height {
+            self.parts.insert(key, parts);
+        }
+    }
+
+    pub fn get(&self, key: &ChunkProductionKey) -> Option<&[PartialEncodedStateWitness]> {
+        self.parts.get(key).map(|parts| parts.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::stateless_validation::ChunkProductionKey;
+    use near_primitives::types::{EpochId, ShardId};
+
+    use super::ProducedStateWitnessCache;
+
+    fn key(height_created: u64) -> ChunkProductionKey {
+        ChunkProductionKey {
+            shard_id: ShardId::new(0),
+            epoch_id: EpochId::default(),
+            height_created,
+        }
+    }
+
+    #[test]
+    fn test_evicts_witnesses_outside_of_retention_window() {
+        let mut cache = ProducedStateWitnessCache::new(3);
+        for height in 1..=5 {
+            cache.insert(key(height), vec![]);
+        }
+        assert!(cache.get(&key(2)).is_none());
+        for height in 3..=5 {
+            assert!(cache.get(&key(height)).is_some());
+        }
+
+        // A late witness below the window is not retained.
+        cache.insert(key(1), vec![]);
+        assert!(cache.get(&key(1)).is_none());
+    }
+
+    #[test]
+    fn test_zero_retention_disables_cache() {
+        let mut cache = ProducedStateWitnessCache::new(0);
+        cache.insert(key(1), vec![]);
+        assert!(cache.get(&key(1)).is_none());
+    }
+}
@@ -4,12 +4,21 @@ use near_chain::get_chunk_clone_from_header;
 use near_chain::{Block, BlockHeader};
 use near_chain_primitives::Error;
 use near_primitives::sharding::{ShardChunk, ShardChunkHeader};
+use near_primitives::types::AccountId;
 
 impl Client {
     // Temporary feature to make node produce state witness for every chunk in every processed block
     // and then self-validate it.
+    // With `shadow_validation`, the node receives no state witness since it
+    // doesn't claim its account on the network, so it validates the tracked
+    // chunks its validator is assigned to this way instead.
     pub(crate) fn shadow_validate_block_chunks(&mut self, block: &Block) -> Result<(), Error> {
-        if !cfg!(feature = "shadow_chunk_validation") {
+        let shadow_validator = if self.config.shadow_validation {
+            self.validator_signer.get().map(|signer| signer.validator_id().clone())
+        } else {
+            None
+        };
+        if !cfg!(feature = "shadow_chunk_validation") && shadow_validator.is_none() {
             return Ok(());
         }
         let block_hash = block.hash();
@@ -22,6 +31,13 @@ impl Client {
             .enumerate()
             .filter(|(_, chunk)| chunk.is_new_chunk(block.header().height()))
         {
+            if let Some(validator_id) = &shadow_validator {
+                if !cfg!(feature = "shadow_chunk_validation")
+                    && !self.is_shadow_chunk_validator(validator_id, block, chunk)?
+                {
+                    continue;
+                }
+            }
             let chunk = get_chunk_clone_from_header(&self.chain.chain_store, chunk)?;
             // TODO(resharding) This doesn't work if shard layout changes.
             let prev_chunk_header = prev_block_chunks.get(shard_index).unwrap();
@@ -37,11 +53,37 @@ impl Client {
                     ?block_hash,
                     "shadow chunk validation failed"
                 );
+            } else if shadow_validator.is_some() {
+                crate::shadow_validation::record_diverted_message("chunk_endorsement");
             }
         }
         Ok(())
     }
 
+    /// Whether `validator_id` has to endorse `chunk` and the node tracks its
+    /// shard, so that it can produce the state witness itself.
+    fn is_shadow_chunk_validator(
+        &self,
+        validator_id: &AccountId,
+        block: &Block,
+        chunk: &ShardChunkHeader,
+    ) -> Result<bool, Error> {
+        let prev_hash = block.header().prev_hash();
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(prev_hash)?;
+        let assignments = self.epoch_manager.get_chunk_validator_assignments(
+            &epoch_id,
+            chunk.shard_id(),
+            chunk.height_created(),
+        )?;
+        Ok(assignments.contains(validator_id)
+            && self.shard_tracker.cares_about_shard(
+                Some(validator_id),
+                prev_hash,
+                chunk.shard_id(),
+                true,
+            ))
+    }
+
     fn shadow_validate_chunk(
         &mut self,
         prev_block_header: &BlockHeader,
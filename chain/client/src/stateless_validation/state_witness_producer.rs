@@ -85,12 +85,16 @@ impl Client {
         {
             // Bypass state witness validation if we created state witness. Endorse the chunk immediately.
             tracing::debug!(target: "client", chunk_hash=?chunk_header.chunk_hash(), ?shard_id, "send_chunk_endorsement_from_chunk_producer");
-            if let Some(endorsement) = send_chunk_endorsement_to_block_producers(
+            let timings = &self.validator_duty_tracker.chunk_validation_timings;
+            timings.record_witness_received(&chunk_header.chunk_hash());
+            let endorsement = send_chunk_endorsement_to_block_producers(
                 &chunk_header,
                 self.epoch_manager.as_ref(),
                 my_signer.as_ref(),
                 &self.network_adapter.clone().into_sender(),
-            ) {
+            );
+            timings.record_endorsement_sent(&chunk_header.chunk_hash());
+            if let Some(endorsement) = endorsement {
                 self.chunk_endorsement_tracker.process_chunk_endorsement(endorsement)?;
             }
         }
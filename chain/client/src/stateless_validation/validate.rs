@@ -10,6 +10,7 @@ use near_primitives::stateless_validation::contract_distribution::{
 };
 use near_primitives::stateless_validation::partial_witness::{
     MAX_COMPRESSED_STATE_WITNESS_SIZE, PartialEncodedStateWitness,
+    PartialEncodedStateWitnessRequest,
 };
 use near_primitives::types::{AccountId, BlockHeightDelta};
 use near_primitives::validator_signer::ValidatorSigner;
@@ -125,15 +126,26 @@ pub fn validate_chunk_endorsement(
     endorsement: &ChunkEndorsement,
     store: &Store,
 ) -> Result<ChunkRelevance, Error> {
-    require_relevant!(validate_chunk_relevant_as_validator(
+    require_relevant!(validate_chunk_endorsement_relevance(epoch_manager, endorsement, store)?);
+    validate_chunk_endorsement_signature(epoch_manager, endorsement)?;
+
+    Ok(ChunkRelevance::Relevant)
+}
+
+/// Performs the cheap checks of `validate_chunk_endorsement`, skipping the
+/// signature verification. The signature must be checked separately with
+/// `validate_chunk_endorsement_signature` before the endorsement is used.
+pub fn validate_chunk_endorsement_relevance(
+    epoch_manager: &dyn EpochManagerAdapter,
+    endorsement: &ChunkEndorsement,
+    store: &Store,
+) -> Result<ChunkRelevance, Error> {
+    validate_chunk_relevant_as_validator(
         epoch_manager,
         &endorsement.chunk_production_key(),
         endorsement.account_id(),
         store,
-    )?);
-    validate_chunk_endorsement_signature(epoch_manager, endorsement)?;
-
-    Ok(ChunkRelevance::Relevant)
+    )
 }
 
 pub fn validate_chunk_contract_accesses(
@@ -171,6 +183,30 @@ pub fn validate_contract_code_request(
     Ok(ChunkRelevance::Relevant)
 }
 
+pub fn validate_partial_encoded_state_witness_request(
+    epoch_manager: &dyn EpochManagerAdapter,
+    request: &PartialEncodedStateWitnessRequest,
+    store: &Store,
+) -> Result<ChunkRelevance, Error> {
+    require_relevant!(validate_chunk_relevant_as_validator(
+        epoch_manager,
+        request.chunk_production_key(),
+        request.requester(),
+        store
+    )?);
+    if epoch_manager.should_validate_signatures() {
+        let validator = epoch_manager.get_validator_by_account_id(
+            &request.chunk_production_key().epoch_id,
+            request.requester(),
+        )?;
+        if !request.verify_signature(validator.public_key()) {
+            return Err(Error::Other("Invalid state witness request signature".to_owned()));
+        }
+    }
+
+    Ok(ChunkRelevance::Relevant)
+}
+
 fn validate_chunk_relevant_as_validator(
     epoch_manager: &dyn EpochManagerAdapter,
     chunk: &ChunkProductionKey,
@@ -279,7 +315,7 @@ fn validate_chunk_relevant(
     Ok(ChunkRelevance::Relevant)
 }
 
-fn validate_chunk_endorsement_signature(
+pub fn validate_chunk_endorsement_signature(
     epoch_manager: &dyn EpochManagerAdapter,
     endorsement: &ChunkEndorsement,
 ) -> Result<(), Error> {
@@ -295,6 +331,34 @@ fn validate_chunk_endorsement_signature(
     Ok(())
 }
 
+/// Same as `validate_chunk_endorsement_signature` for every endorsement, but verifies all the
+/// signatures in one batch.  An error doesn't tell which of the endorsements are invalid.
+pub fn validate_chunk_endorsement_signatures(
+    epoch_manager: &dyn EpochManagerAdapter,
+    endorsements: &[ChunkEndorsement],
+) -> Result<(), Error> {
+    if epoch_manager.should_validate_signatures() {
+        let validators = endorsements
+            .iter()
+            .map(|endorsement| {
+                epoch_manager.get_validator_by_account_id(
+                    &endorsement.chunk_production_key().epoch_id,
+                    endorsement.account_id(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let endorsements_with_keys: Vec<_> = endorsements
+            .iter()
+            .zip(&validators)
+            .map(|(endorsement, validator)| (endorsement, validator.public_key()))
+            .collect();
+        if !ChunkEndorsement::verify_batch(&endorsements_with_keys) {
+            return Err(Error::InvalidChunkEndorsement);
+        }
+    }
+    Ok(())
+}
+
 fn validate_witness_contract_code_request_signature(
     epoch_manager: &dyn EpochManagerAdapter,
     request: &ContractCodeRequest,
@@ -40,6 +40,10 @@ pub struct BlockSync {
 
     /// Whether State Sync should be enabled when a node falls far enough behind.
     state_sync_enabled: bool,
+
+    /// If set, State Sync is preferred as soon as the node falls more than
+    /// this many blocks behind, see `FastBootstrapConfig`.
+    fast_bootstrap_depth: Option<BlockHeightDelta>,
 }
 
 impl BlockSync {
@@ -49,6 +53,7 @@ impl BlockSync {
         block_fetch_horizon: BlockHeightDelta,
         archive: bool,
         state_sync_enabled: bool,
+        fast_bootstrap_depth: Option<BlockHeightDelta>,
     ) -> Self {
         BlockSync {
             clock,
@@ -57,6 +62,7 @@ impl BlockSync {
             block_fetch_horizon,
             archive,
             state_sync_enabled,
+            fast_bootstrap_depth,
         }
     }
 
@@ -108,11 +114,21 @@ impl BlockSync {
 
         log_assert!(head.height <= header_head.height);
 
-        // Only if the header head is more than one epoch ahead, then consider State Sync.
-        // block_fetch_horizon is used for testing to prevent test nodes from switching to State Sync too eagerly.
-        let prefer_state_sync = head.epoch_id != header_head.epoch_id
-            && head.next_epoch_id != header_head.epoch_id
-            && head.height.saturating_add(self.block_fetch_horizon) < header_head.height;
+        let prefer_state_sync = match self.fast_bootstrap_depth {
+            // State Sync into the next epoch as well, as soon as it saves
+            // applying more than the depth of blocks.
+            Some(depth) => {
+                head.epoch_id != header_head.epoch_id
+                    && head.height.saturating_add(depth) < header_head.height
+            }
+            // Only if the header head is more than one epoch ahead, then consider State Sync.
+            // block_fetch_horizon is used for testing to prevent test nodes from switching to State Sync too eagerly.
+            None => {
+                head.epoch_id != header_head.epoch_id
+                    && head.next_epoch_id != header_head.epoch_id
+                    && head.height.saturating_add(self.block_fetch_horizon) < header_head.height
+            }
+        };
         if prefer_state_sync {
             debug!(
                 target: "sync",
@@ -122,6 +138,7 @@ impl BlockSync {
                 head_height = head.height,
                 header_head_height = header_head.height,
                 block_fetch_horizon = self.block_fetch_horizon,
+                fast_bootstrap_depth = ?self.fast_bootstrap_depth,
                 "Switched from block sync to state sync");
         }
         prefer_state_sync
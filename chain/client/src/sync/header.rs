@@ -803,6 +803,7 @@ mod test {
                                 this_height,
                                 signer.as_ref(),
                             )
+                            .unwrap()
                             .signature,
                         ))
                     })
@@ -817,7 +818,8 @@ mod test {
                 clock.clock(),
                 None,
                 None,
-            );
+            )
+            .unwrap();
             block_merkle_tree.insert(*block.hash());
             chain2.process_block_header(block.header()).unwrap(); // just to validate
             process_block_sync(
@@ -17,7 +17,9 @@ use near_async::messaging::{AsyncSender, IntoSender};
 use near_async::time::{Clock, Duration};
 use near_chain::Chain;
 use near_chain::types::RuntimeAdapter;
-use near_chain_configs::{ExternalStorageConfig, ExternalStorageLocation, SyncConfig};
+use near_chain_configs::{
+    ExternalStorageConfig, ExternalStorageLocation, LocalDirSyncConfig, SyncConfig,
+};
 use near_client_primitives::types::{ShardSyncStatus, StateSyncStatus};
 use near_epoch_manager::EpochManagerAdapter;
 use near_network::types::{
@@ -109,14 +111,19 @@ impl StateSync {
             request_timeout: p2p_timeout,
             state: peer_source_state.clone(),
         }) as Arc<dyn StateSyncDownloadSource>;
-        let (fallback_source, num_attempts_before_fallback, num_concurrent_requests) =
-            if let SyncConfig::ExternalStorage(ExternalStorageConfig {
+        let (
+            preferred_source,
+            fallback_source,
+            num_attempts_before_fallback,
+            num_concurrent_requests,
+        ) = match sync_config {
+            SyncConfig::Peers => (peer_source, None, 0, NUM_CONCURRENT_REQUESTS_FOR_PEERS),
+            SyncConfig::ExternalStorage(ExternalStorageConfig {
                 location,
                 num_concurrent_requests,
                 num_concurrent_requests_during_catchup,
                 external_storage_fallback_threshold,
-            }) = sync_config
-            {
+            }) => {
                 let external = match location {
                     ExternalStorageLocation::S3 { bucket, region, .. } => {
                         let bucket = create_bucket_readonly(
@@ -157,19 +164,41 @@ impl StateSync {
                     backoff: external_backoff,
                 }) as Arc<dyn StateSyncDownloadSource>;
                 (
+                    peer_source,
                     Some(fallback_source),
                     *external_storage_fallback_threshold as usize,
                     num_concurrent_requests.min(NUM_CONCURRENT_REQUESTS_FOR_PEERS),
                 )
-            } else {
-                (None, 0, NUM_CONCURRENT_REQUESTS_FOR_PEERS)
-            };
+            }
+            SyncConfig::LocalDir(LocalDirSyncConfig {
+                root_dir,
+                num_concurrent_requests,
+                num_concurrent_requests_during_catchup,
+            }) => {
+                // The operator-provided directory is the only source. Anything missing or
+                // invalid is retried, so the directory may be filled in while syncing.
+                let num_concurrent_requests = if catchup {
+                    *num_concurrent_requests_during_catchup
+                } else {
+                    *num_concurrent_requests
+                } as usize;
+                let local_source = Arc::new(StateSyncDownloadSourceExternal {
+                    clock: clock.clone(),
+                    store: store.clone(),
+                    chain_id: chain_id.to_string(),
+                    conn: ExternalConnection::Filesystem { root_dir: root_dir.clone() },
+                    timeout: external_timeout,
+                    backoff: external_backoff,
+                }) as Arc<dyn StateSyncDownloadSource>;
+                (local_source, None, 0, num_concurrent_requests)
+            }
+        };
 
         let downloading_task_tracker = TaskTracker::new(num_concurrent_requests);
         let downloader = Arc::new(StateSyncDownloader {
             clock,
             store: store.clone(),
-            preferred_source: peer_source,
+            preferred_source,
             fallback_source,
             num_attempts_before_fallback,
             header_validation_sender: chain_requests_sender.clone().into_sender(),
@@ -45,7 +45,7 @@ impl SyncJobsActor {
     pub fn handle_block_catch_up_request(&mut self, msg: BlockCatchUpRequest) {
         tracing::debug!(target: "sync", ?msg);
         let results =
-            do_apply_chunks(BlockToApply::Normal(msg.block_hash), msg.block_height, msg.work)
+            do_apply_chunks(BlockToApply::Normal(msg.block_hash), msg.block_height, msg.work, None)
                 .into_iter()
                 .map(|res| (res.0, res.2))
                 .collect();
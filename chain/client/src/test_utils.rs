@@ -220,7 +220,8 @@ pub fn create_chunk(
             header.bandwidth_requests().cloned().unwrap_or_else(BandwidthRequests::empty),
             &*signer,
             &rs,
-        );
+        )
+        .unwrap();
         let mut new_encoded_chunk = new_chunk.into_parts().1;
         swap(&mut encoded_chunk, &mut new_encoded_chunk);
         swap(&mut merkle_paths, &mut new_merkle_paths);
@@ -239,7 +240,8 @@ pub fn create_chunk(
 
     let signer = client.validator_signer.get().unwrap();
     let endorsement =
-        ChunkEndorsement::new(EpochId::default(), &encoded_chunk.cloned_header(), signer.as_ref());
+        ChunkEndorsement::new(EpochId::default(), &encoded_chunk.cloned_header(), signer.as_ref())
+            .unwrap();
     block_merkle_tree.insert(*last_block.hash());
     let block = Block::produce(
         PROTOCOL_VERSION,
@@ -262,7 +264,8 @@ pub fn create_chunk(
         client.clock.clone(),
         None,
         None,
-    );
+    )
+    .unwrap();
     let chunk = ShardChunkWithEncoding::from_encoded_shard_chunk(encoded_chunk).unwrap();
     (ProduceChunkResult { chunk, encoded_chunk_parts_paths: merkle_paths, receipts }, block)
 }
@@ -286,11 +289,15 @@ pub fn run_catchup(
         client.run_catchup(highest_height_peers, &block_catch_up, None, &signer)?;
         let mut catchup_done = true;
         for msg in block_messages.write().drain(..) {
-            let results =
-                do_apply_chunks(BlockToApply::Normal(msg.block_hash), msg.block_height, msg.work)
-                    .into_iter()
-                    .map(|res| res.2)
-                    .collect_vec();
+            let results = do_apply_chunks(
+                BlockToApply::Normal(msg.block_hash),
+                msg.block_height,
+                msg.work,
+                None,
+            )
+            .into_iter()
+            .map(|res| res.2)
+            .collect_vec();
             if let Some(CatchupState { catchup, .. }) =
                 client.catchup_state_syncs.get_mut(&msg.sync_hash)
             {
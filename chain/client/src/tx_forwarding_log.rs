@@ -0,0 +1,62 @@
+use lru::LruCache;
+use near_primitives::hash::CryptoHash;
+use near_primitives::views::TxForwardingRecordView;
+use parking_lot::Mutex;
+use std::num::NonZeroUsize;
+
+/// Number of transactions for which the forwarding decisions are remembered.
+const TX_FORWARDING_LOG_CAPACITY: usize = 10_000;
+/// Number of most recent decisions remembered per transaction. The same transaction
+/// may be submitted to, or forwarded to, the node several times.
+const MAX_RECORDS_PER_TX: usize = 8;
+
+/// Remembers how the RPC handler dealt with recently received transactions, so that
+/// it's possible to tell why a transaction never made it into a chunk.
+pub(crate) struct TxForwardingLog(Mutex<LruCache<CryptoHash, Vec<TxForwardingRecordView>>>);
+
+impl TxForwardingLog {
+    pub fn new() -> Self {
+        Self(Mutex::new(LruCache::new(NonZeroUsize::new(TX_FORWARDING_LOG_CAPACITY).unwrap())))
+    }
+
+    pub fn record(&self, tx_hash: CryptoHash, record: TxForwardingRecordView) {
+        let mut log = self.0.lock();
+        let records = log.get_or_insert_mut(tx_hash, Vec::new);
+        if records.len() == MAX_RECORDS_PER_TX {
+            records.remove(0);
+        }
+        records.push(record);
+    }
+
+    pub fn get(&self, tx_hash: &CryptoHash) -> Vec<TxForwardingRecordView> {
+        self.0.lock().peek(tx_hash).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_RECORDS_PER_TX, TxForwardingLog};
+    use near_primitives::hash::hash;
+    use near_primitives::views::{TxForwardingOutcomeView, TxForwardingRecordView};
+
+    fn record(head_height: u64) -> TxForwardingRecordView {
+        TxForwardingRecordView {
+            head_height,
+            is_forwarded: false,
+            outcome: TxForwardingOutcomeView::Routed,
+            forwarded_to: vec![],
+        }
+    }
+
+    #[test]
+    fn test_keeps_most_recent_records() {
+        let log = TxForwardingLog::new();
+        let tx_hash = hash(b"tx");
+        for height in 0..MAX_RECORDS_PER_TX as u64 + 2 {
+            log.record(tx_hash, record(height));
+        }
+        let heights: Vec<_> = log.get(&tx_hash).iter().map(|r| r.head_height).collect();
+        assert_eq!(heights, (2..MAX_RECORDS_PER_TX as u64 + 2).collect::<Vec<_>>());
+        assert!(log.get(&hash(b"other")).is_empty());
+    }
+}
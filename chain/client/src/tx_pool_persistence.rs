@@ -0,0 +1,188 @@
+//! Persistence of the transaction pool across restarts.
+//!
+//! With `ClientConfig::persist_tx_pool`, the transactions in the pool are saved
+//! to the store when the node shuts down and loaded back when it starts, so
+//! that they don't disappear with a restart.  The saved transactions are
+//! validated again on top of the head before they are put back in the pool,
+//! the ones which expired or became invalid while the node was down are
+//! dropped.
+
+use crate::Client;
+use near_chain_primitives::Error;
+use near_chunks::client::ShardedTransactionPool;
+use near_pool::InsertTransactionResult;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::validator_signer::ValidatorSigner;
+use near_store::db::TX_POOL_KEY;
+use near_store::{DBCol, Store};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Saves the transactions of the pool to the store, to be restored by
+/// `Client::restore_tx_pool` at the next start.
+#[derive(Clone)]
+pub struct TxPoolSaver {
+    store: Store,
+    tx_pool: Arc<Mutex<ShardedTransactionPool>>,
+}
+
+impl TxPoolSaver {
+    pub fn new(store: Store, tx_pool: Arc<Mutex<ShardedTransactionPool>>) -> Self {
+        Self { store, tx_pool }
+    }
+
+    /// Saves the transactions currently in the pool, replacing the ones saved
+    /// before.  Returns the number of transactions saved.
+    pub fn save(&self) -> Result<usize, Error> {
+        let transactions = self
+            .tx_pool
+            .lock()
+            .transactions()
+            .map(|(_, validated_tx)| validated_tx.to_signed_tx().clone())
+            .collect::<Vec<_>>();
+        let mut store_update = self.store.store_update();
+        store_update.set_ser(DBCol::Misc, TX_POOL_KEY, &transactions)?;
+        store_update.commit()?;
+        Ok(transactions.len())
+    }
+}
+
+/// Returns the transactions saved by `TxPoolSaver::save`.
+fn saved_transactions(store: &Store) -> Result<Vec<SignedTransaction>, Error> {
+    Ok(store.get_ser(DBCol::Misc, TX_POOL_KEY)?.unwrap_or_default())
+}
+
+/// Removes the saved transactions from the store, once they are restored.
+fn delete_saved_transactions(store: &Store) -> Result<(), Error> {
+    let mut store_update = store.store_update();
+    store_update.delete(DBCol::Misc, TX_POOL_KEY);
+    store_update.commit()?;
+    Ok(())
+}
+
+impl Client {
+    /// Puts the transactions saved at the last shutdown back in the pool. They
+    /// go through the same checks as the transactions submitted to the node,
+    /// on top of the current head, and the ones which don't pass them or
+    /// belong to shards the node doesn't track are dropped. The saved
+    /// transactions are removed from the store only once they are restored,
+    /// so they are kept if the restore fails.
+    pub fn restore_tx_pool(&mut self, signer: &Option<Arc<ValidatorSigner>>) -> Result<(), Error> {
+        let store = self.runtime_adapter.store().clone();
+        let transactions = saved_transactions(&store)?;
+        if transactions.is_empty() {
+            return Ok(());
+        }
+        let me = signer.as_ref().map(|signer| signer.validator_id());
+        let head = self.chain.head()?;
+        let head_block = self.chain.get_block(&head.last_block_hash)?;
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
+        let shard_layout =
+            self.epoch_manager.get_shard_layout_from_protocol_version(protocol_version);
+        let gas_price = head_block.header().next_gas_price();
+        let block_congestion_info = head_block.block_congestion_info();
+
+        let num_saved = transactions.len();
+        let mut num_restored = 0;
+        for signed_tx in transactions {
+            let tx_hash = signed_tx.get_hash();
+            if let Err(err) = self.chain.chain_store().check_transaction_validity_period(
+                head_block.header(),
+                signed_tx.transaction.block_hash(),
+            ) {
+                tracing::debug!(target: "client", ?tx_hash, ?err, "Dropping saved transaction");
+                continue;
+            }
+            let receiver_shard_id =
+                shard_layout.account_id_to_shard_id(signed_tx.transaction.receiver_id());
+            let receiver_congestion_info = block_congestion_info.get(&receiver_shard_id).copied();
+            let validated_tx = match self.runtime_adapter.validate_tx(
+                &shard_layout,
+                signed_tx,
+                protocol_version,
+                receiver_congestion_info,
+            ) {
+                Ok(validated_tx) => validated_tx,
+                Err((err, _)) => {
+                    tracing::debug!(target: "client", ?tx_hash, ?err, "Dropping saved transaction");
+                    continue;
+                }
+            };
+            let shard_uid = shard_layout.account_id_to_shard_uid(validated_tx.signer_id());
+            if !self.shard_tracker.cares_about_shard_this_or_next_epoch(
+                me,
+                &head.last_block_hash,
+                shard_uid.shard_id(),
+                true,
+            ) {
+                continue;
+            }
+            let Ok(chunk_extra) = self.chain.get_chunk_extra(&head.last_block_hash, &shard_uid)
+            else {
+                continue;
+            };
+            if let Err(err) = self.runtime_adapter.can_verify_and_charge_tx(
+                &shard_layout,
+                gas_price,
+                *chunk_extra.state_root(),
+                &validated_tx,
+                protocol_version,
+            ) {
+                tracing::debug!(target: "client", ?tx_hash, ?err, "Dropping saved transaction");
+                continue;
+            }
+            let mut pool = self.chunk_producer.sharded_tx_pool.lock();
+            if pool.insert_transaction(shard_uid, validated_tx) == InsertTransactionResult::Success
+            {
+                num_restored += 1;
+            }
+        }
+        delete_saved_transactions(&store)?;
+        tracing::info!(target: "client", num_saved, num_restored, "Restored the transaction pool");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TxPoolSaver, delete_saved_transactions, saved_transactions};
+    use near_chunks::client::ShardedTransactionPool;
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::transaction::{SignedTransaction, ValidatedTransaction};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_save_tx_pool() {
+        let store = near_store::test_utils::create_test_store();
+        let tx_pool = Arc::new(Mutex::new(ShardedTransactionPool::new([0; 32], None, None)));
+        let saver = TxPoolSaver::new(store.clone(), tx_pool.clone());
+        let signer = InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+        let transactions = (1..=3)
+            .map(|nonce| {
+                SignedTransaction::send_money(
+                    nonce,
+                    "test0".parse().unwrap(),
+                    "test1".parse().unwrap(),
+                    &signer,
+                    10,
+                    CryptoHash::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        for tx in &transactions {
+            let validated_tx = ValidatedTransaction::new_for_test(tx.clone());
+            tx_pool.lock().insert_transaction(ShardUId::single_shard(), validated_tx);
+        }
+
+        assert_eq!(saver.save().unwrap(), 3);
+        let mut saved = saved_transactions(&store).unwrap();
+        saved.sort_by_key(|tx| tx.transaction.nonce());
+        assert_eq!(saved, transactions);
+        delete_saved_transactions(&store).unwrap();
+        assert!(saved_transactions(&store).unwrap().is_empty());
+    }
+}
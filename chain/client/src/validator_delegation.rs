@@ -0,0 +1,313 @@
+//! Delegation info of the current validators, see `ValidatorDelegationInfoConfig`.
+//!
+//! The delegators of a staking pool are listed with the `get_accounts` view
+//! method of the standard staking pool contract, in the state of the first
+//! block of the epoch.  Listing them takes a view call per page of accounts,
+//! so the delegations of all validators of an epoch are read once, in the
+//! background, the first time the epoch is requested.  Until they are read
+//! the validators are returned without them.
+
+use lru::LruCache;
+use near_async::futures::{AsyncComputationSpawner, AsyncComputationSpawnerExt};
+use near_chain::Chain;
+use near_chain::types::RuntimeAdapter;
+use near_chain_configs::ValidatorDelegationInfoConfig;
+use near_epoch_manager::EpochManagerAdapter;
+use near_epoch_manager::shard_assignment::{account_id_to_shard_id, shard_id_to_uid};
+use near_primitives::block::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::dec_format;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{AccountId, Balance, EpochId, StateRoot};
+use near_primitives::views::{
+    EpochValidatorInfo, QueryRequest, QueryResponseKind, ValidatorDelegationView,
+};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+/// Number of epochs for which the delegations are cached.
+const CACHE_SIZE: usize = 4;
+/// Number of accounts requested with a single `get_accounts` call.
+const PAGE_SIZE: u64 = 100;
+/// Pools with more accounts than this get no delegation info, to bound the
+/// number of view calls.
+const MAX_ACCOUNTS: u64 = 50_000;
+
+/// An account as returned by `get_accounts` of the staking pool contract.
+#[derive(serde::Deserialize)]
+struct PoolAccount {
+    #[serde(with = "dec_format")]
+    staked_balance: Balance,
+}
+
+/// Delegations of the validators of an epoch.  Validators whose delegations
+/// can't be read aren't included.
+type EpochDelegations = HashMap<AccountId, ValidatorDelegationView>;
+
+struct DelegationsState {
+    epochs: LruCache<EpochId, Arc<EpochDelegations>>,
+    /// Epochs whose delegations are being read in the background.
+    reading: HashSet<EpochId>,
+}
+
+pub(crate) struct ValidatorDelegations {
+    config: ValidatorDelegationInfoConfig,
+    spawner: Arc<dyn AsyncComputationSpawner>,
+    state: Arc<Mutex<DelegationsState>>,
+}
+
+impl ValidatorDelegations {
+    pub fn new(
+        config: ValidatorDelegationInfoConfig,
+        spawner: Arc<dyn AsyncComputationSpawner>,
+    ) -> Self {
+        let state = DelegationsState {
+            epochs: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+            reading: HashSet::new(),
+        };
+        Self { config, spawner, state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Sets the delegations of the current validators in `validator_info`, if
+    /// they were already read.  Otherwise starts reading them in the background.
+    pub fn attach(
+        &self,
+        chain: &Chain,
+        epoch_manager: &dyn EpochManagerAdapter,
+        runtime: &Arc<dyn RuntimeAdapter>,
+        validator_info: &mut EpochValidatorInfo,
+    ) {
+        let header = match chain.get_block_header_by_height(validator_info.epoch_start_height) {
+            Ok(header) => header,
+            Err(err) => {
+                tracing::debug!(target: "client", ?err, height = validator_info.epoch_start_height, "No epoch start block for validator delegations");
+                return;
+            }
+        };
+        let epoch_id = *header.epoch_id();
+        {
+            let mut state = self.state.lock();
+            if let Some(delegations) = state.epochs.get(&epoch_id) {
+                for validator in &mut validator_info.current_validators {
+                    validator.delegation = delegations.get(&validator.account_id).cloned();
+                }
+                return;
+            }
+            if !state.reading.insert(epoch_id) {
+                return;
+            }
+        }
+
+        // The state roots are found here, as the chain can't be shared with
+        // the background task.  The view calls are what takes the time.
+        let mut pools = vec![];
+        for validator in &validator_info.current_validators {
+            match pool_state_root(chain, epoch_manager, &header, &validator.account_id) {
+                Ok((shard_uid, state_root)) => {
+                    pools.push((validator.account_id.clone(), shard_uid, state_root))
+                }
+                Err(err) => {
+                    tracing::debug!(target: "client", account_id = %validator.account_id, %err, "No state for validator delegations")
+                }
+            }
+        }
+        let code_hashes = self.config.staking_pool_code_hashes.clone();
+        let runtime = runtime.clone();
+        let state = self.state.clone();
+        self.spawner.spawn("validator_delegations", move || {
+            let mut delegations = EpochDelegations::new();
+            for (account_id, shard_uid, state_root) in pools {
+                let query = |request: QueryRequest| {
+                    runtime
+                        .query(
+                            shard_uid,
+                            &state_root,
+                            header.height(),
+                            header.raw_timestamp(),
+                            header.prev_hash(),
+                            header.hash(),
+                            header.epoch_id(),
+                            &request,
+                        )
+                        .map(|response| response.kind)
+                        .map_err(|err| err.to_string())
+                };
+                match read_delegation(&code_hashes, &account_id, query) {
+                    Ok(Some(delegation)) => {
+                        delegations.insert(account_id, delegation);
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::debug!(target: "client", %account_id, %err, "Failed to read validator delegations")
+                    }
+                }
+            }
+            let mut state = state.lock();
+            state.reading.remove(&epoch_id);
+            state.epochs.put(epoch_id, Arc::new(delegations));
+        });
+    }
+}
+
+/// Returns the shard and the state root of the validator account at `header`.
+fn pool_state_root(
+    chain: &Chain,
+    epoch_manager: &dyn EpochManagerAdapter,
+    header: &BlockHeader,
+    account_id: &AccountId,
+) -> Result<(ShardUId, StateRoot), String> {
+    let shard_id = account_id_to_shard_id(epoch_manager, account_id, header.epoch_id())
+        .map_err(|err| err.to_string())?;
+    let shard_uid = shard_id_to_uid(epoch_manager, shard_id, header.epoch_id())
+        .map_err(|err| err.to_string())?;
+    let chunk_extra =
+        chain.get_chunk_extra(header.hash(), &shard_uid).map_err(|err| err.to_string())?;
+    Ok((shard_uid, *chunk_extra.state_root()))
+}
+
+/// Reads the delegations of the staking pool deployed on `account_id` with
+/// `query`.  Returns `None` if the account has none of `code_hashes` deployed.
+fn read_delegation(
+    code_hashes: &[CryptoHash],
+    account_id: &AccountId,
+    query: impl Fn(QueryRequest) -> Result<QueryResponseKind, String>,
+) -> Result<Option<ValidatorDelegationView>, String> {
+    let QueryResponseKind::ViewAccount(account) =
+        query(QueryRequest::ViewAccount { account_id: account_id.clone() })?
+    else {
+        return Err("unexpected response to ViewAccount".to_string());
+    };
+    if !code_hashes.contains(&account.code_hash) {
+        return Ok(None);
+    }
+
+    let mut delegation = ValidatorDelegationView { num_delegators: 0, total_delegated: 0 };
+    let mut from_index = 0;
+    loop {
+        if from_index >= MAX_ACCOUNTS {
+            return Err(format!("the pool has more than {MAX_ACCOUNTS} accounts"));
+        }
+        let args = serde_json::json!({ "from_index": from_index, "limit": PAGE_SIZE });
+        let QueryResponseKind::CallResult(result) = query(QueryRequest::CallFunction {
+            account_id: account_id.clone(),
+            method_name: "get_accounts".to_string(),
+            args: args.to_string().into_bytes().into(),
+        })?
+        else {
+            return Err("unexpected response to CallFunction".to_string());
+        };
+        let accounts: Vec<PoolAccount> =
+            serde_json::from_slice(&result.result).map_err(|err| err.to_string())?;
+        for account in &accounts {
+            if account.staked_balance > 0 {
+                delegation.num_delegators += 1;
+                delegation.total_delegated += account.staked_balance;
+            }
+        }
+        if (accounts.len() as u64) < PAGE_SIZE {
+            return Ok(Some(delegation));
+        }
+        from_index += PAGE_SIZE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_ACCOUNTS, PAGE_SIZE, read_delegation};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::AccountId;
+    use near_primitives::views::{AccountView, CallResult, QueryRequest, QueryResponseKind};
+    use std::cell::Cell;
+
+    fn account_view(code_hash: CryptoHash) -> AccountView {
+        AccountView {
+            amount: 0,
+            locked: 0,
+            code_hash,
+            storage_usage: 0,
+            storage_paid_at: 0,
+            global_contract_hash: None,
+            global_contract_account_id: None,
+        }
+    }
+
+    /// Answers the queries for a pool with `num_accounts` accounts, every
+    /// second of which has a stake of 10.
+    fn pool_query(
+        code_hash: CryptoHash,
+        num_accounts: u64,
+        calls: &Cell<u64>,
+    ) -> impl Fn(QueryRequest) -> Result<QueryResponseKind, String> + '_ {
+        move |request| match request {
+            QueryRequest::ViewAccount { .. } => {
+                Ok(QueryResponseKind::ViewAccount(account_view(code_hash)))
+            }
+            QueryRequest::CallFunction { args, .. } => {
+                calls.set(calls.get() + 1);
+                let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+                let from_index = args["from_index"].as_u64().unwrap();
+                let limit = args["limit"].as_u64().unwrap();
+                let accounts = (from_index..num_accounts.min(from_index + limit))
+                    .map(|i| {
+                        let stake = if i % 2 == 0 { 10 } else { 0 };
+                        serde_json::json!({ "staked_balance": stake.to_string() })
+                    })
+                    .collect::<Vec<_>>();
+                let result = serde_json::to_vec(&accounts).unwrap();
+                Ok(QueryResponseKind::CallResult(CallResult { result, logs: vec![] }))
+            }
+            _ => Err("unexpected request".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_read_delegation_pages() {
+        let code_hash = CryptoHash::hash_bytes(b"pool");
+        let account_id: AccountId = "pool.near".parse().unwrap();
+        let calls = Cell::new(0);
+        let delegation =
+            read_delegation(&[code_hash], &account_id, pool_query(code_hash, 250, &calls))
+                .unwrap()
+                .unwrap();
+        assert_eq!(delegation.num_delegators, 125);
+        assert_eq!(delegation.total_delegated, 1250);
+        assert_eq!(calls.get(), 3);
+
+        // A full last page takes another call to see that it's the last one.
+        let calls = Cell::new(0);
+        let delegation = read_delegation(
+            &[code_hash],
+            &account_id,
+            pool_query(code_hash, 2 * PAGE_SIZE, &calls),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(delegation.num_delegators, PAGE_SIZE);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_read_delegation_unknown_code_hash() {
+        let code_hash = CryptoHash::hash_bytes(b"pool");
+        let account_id: AccountId = "pool.near".parse().unwrap();
+        let calls = Cell::new(0);
+        let other_code_hash = CryptoHash::hash_bytes(b"other");
+        let query = pool_query(other_code_hash, 10, &calls);
+        assert_eq!(read_delegation(&[code_hash], &account_id, &query).unwrap(), None);
+        let query = pool_query(CryptoHash::default(), 10, &calls);
+        assert_eq!(read_delegation(&[code_hash], &account_id, &query).unwrap(), None);
+        assert_eq!(calls.get(), 0);
+    }
+
+    #[test]
+    fn test_read_delegation_too_many_accounts() {
+        let code_hash = CryptoHash::hash_bytes(b"pool");
+        let account_id: AccountId = "pool.near".parse().unwrap();
+        let calls = Cell::new(0);
+        let query = pool_query(code_hash, MAX_ACCOUNTS + 1, &calls);
+        assert!(read_delegation(&[code_hash], &account_id, query).is_err());
+        assert_eq!(calls.get(), MAX_ACCOUNTS / PAGE_SIZE);
+    }
+}
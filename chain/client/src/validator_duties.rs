@@ -0,0 +1,984 @@
+//! Self-report of the duties of the node's own validator account.
+//!
+//! Whenever the final head advances, the blocks which became final are checked
+//! for the blocks, chunks and chunk endorsements the node was expected to
+//! contribute.  Each epoch gets a record with the number of expected and
+//! fulfilled duties and the reasons of the most recent misses.  Records of the
+//! last few epochs are persisted, so that validators can audit their own
+//! performance through the `EXPERIMENTAL_validator_duties` RPC method even
+//! across restarts.  The records are saved every few final blocks and when a
+//! new epoch starts; after a restart the blocks since the last save are simply
+//! evaluated again.
+//!
+//! Whenever the head advances, the duties of the next few heights are looked up
+//! too, and the health of the node is checked ahead of them: whether it has
+//! enough peers, is synced and receives the chunks.  A duty at risk is reported
+//! once through the metrics and a warning, so that operators get alerted before
+//! the miss rather than after.
+
+use crate::Client;
+use crate::metrics;
+use borsh::{BorshDeserialize, BorshSerialize};
+use itertools::Itertools;
+use lru::LruCache;
+use near_async::time::{Clock, Utc};
+use near_chain::{Block, BlockHeader};
+use near_chain_primitives::Error;
+use near_primitives::block::MaybeNew;
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::stateless_validation::ChunkProductionKey;
+use near_primitives::types::{AccountId, BlockHeight, BlockHeightDelta, EpochId, ShardId};
+use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::views::{
+    EpochValidatorDutiesView, MissedValidatorDutyReason, MissedValidatorDutyView,
+    ValidatorDutyKind, ValidatorDutyStatsView,
+};
+use near_store::db::VALIDATOR_DUTIES_KEY;
+use near_store::{DBCol, Store};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+/// Number of most recent epochs for which the duties are kept.
+const NUM_EPOCHS_TO_KEEP: usize = 3;
+/// Number of most recent missed duties kept for each epoch.
+const MAX_MISSES_PER_EPOCH: usize = 256;
+/// Number of chunks for which the witness and endorsement times are remembered.
+const CHUNK_VALIDATION_TIMINGS_CAPACITY: usize = 1024;
+/// Maximum number of final blocks evaluated at once, so that catching up after
+/// a long sync doesn't stall the client.
+const MAX_BLOCKS_TO_EVALUATE: usize = 1000;
+/// Number of heights after the head whose duties are checked ahead of time.
+const UPCOMING_DUTIES_HORIZON: BlockHeightDelta = 5;
+/// Number of final heights between saves of the duty records.
+const SAVE_INTERVAL: BlockHeightDelta = 20;
+
+/// Duty records as persisted in `DBCol::Misc`. These are separate from the RPC
+/// views, so that changing the views doesn't break reading the records saved
+/// by an earlier version of the node.
+#[derive(BorshSerialize, BorshDeserialize)]
+enum StoredValidatorDuties {
+    V1(Vec<EpochValidatorDuties>),
+}
+
+/// Duties of the node's validator account in an epoch.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+struct EpochValidatorDuties {
+    epoch_id: EpochId,
+    account_id: AccountId,
+    last_evaluated_height: BlockHeight,
+    block_production: DutyStats,
+    chunk_production: DutyStats,
+    chunk_endorsement: DutyStats,
+    misses: Vec<MissedDuty>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct DutyStats {
+    expected: u64,
+    fulfilled: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+struct MissedDuty {
+    kind: DutyKind,
+    height: BlockHeight,
+    shard_id: Option<ShardId>,
+    reason: MissReason,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+enum DutyKind {
+    BlockProduction = 0,
+    ChunkProduction = 1,
+    ChunkEndorsement = 2,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+enum MissReason {
+    NotProduced = 0,
+    Orphaned = 1,
+    NotIncluded = 2,
+    WitnessMissing = 3,
+    WitnessLate = 4,
+    ApplySlow = 5,
+    ValidationFailed = 6,
+    EndorsementNotIncluded = 7,
+}
+
+impl From<ValidatorDutyKind> for DutyKind {
+    fn from(kind: ValidatorDutyKind) -> Self {
+        match kind {
+            ValidatorDutyKind::BlockProduction => Self::BlockProduction,
+            ValidatorDutyKind::ChunkProduction => Self::ChunkProduction,
+            ValidatorDutyKind::ChunkEndorsement => Self::ChunkEndorsement,
+        }
+    }
+}
+
+impl From<DutyKind> for ValidatorDutyKind {
+    fn from(kind: DutyKind) -> Self {
+        match kind {
+            DutyKind::BlockProduction => Self::BlockProduction,
+            DutyKind::ChunkProduction => Self::ChunkProduction,
+            DutyKind::ChunkEndorsement => Self::ChunkEndorsement,
+        }
+    }
+}
+
+impl From<MissedValidatorDutyReason> for MissReason {
+    fn from(reason: MissedValidatorDutyReason) -> Self {
+        match reason {
+            MissedValidatorDutyReason::NotProduced => Self::NotProduced,
+            MissedValidatorDutyReason::Orphaned => Self::Orphaned,
+            MissedValidatorDutyReason::NotIncluded => Self::NotIncluded,
+            MissedValidatorDutyReason::WitnessMissing => Self::WitnessMissing,
+            MissedValidatorDutyReason::WitnessLate => Self::WitnessLate,
+            MissedValidatorDutyReason::ApplySlow => Self::ApplySlow,
+            MissedValidatorDutyReason::ValidationFailed => Self::ValidationFailed,
+            MissedValidatorDutyReason::EndorsementNotIncluded => Self::EndorsementNotIncluded,
+        }
+    }
+}
+
+impl From<MissReason> for MissedValidatorDutyReason {
+    fn from(reason: MissReason) -> Self {
+        match reason {
+            MissReason::NotProduced => Self::NotProduced,
+            MissReason::Orphaned => Self::Orphaned,
+            MissReason::NotIncluded => Self::NotIncluded,
+            MissReason::WitnessMissing => Self::WitnessMissing,
+            MissReason::WitnessLate => Self::WitnessLate,
+            MissReason::ApplySlow => Self::ApplySlow,
+            MissReason::ValidationFailed => Self::ValidationFailed,
+            MissReason::EndorsementNotIncluded => Self::EndorsementNotIncluded,
+        }
+    }
+}
+
+impl From<DutyStats> for ValidatorDutyStatsView {
+    fn from(stats: DutyStats) -> Self {
+        Self { expected: stats.expected, fulfilled: stats.fulfilled }
+    }
+}
+
+impl From<&EpochValidatorDuties> for EpochValidatorDutiesView {
+    fn from(record: &EpochValidatorDuties) -> Self {
+        Self {
+            epoch_id: record.epoch_id,
+            account_id: record.account_id.clone(),
+            last_evaluated_height: record.last_evaluated_height,
+            block_production: record.block_production.into(),
+            chunk_production: record.chunk_production.into(),
+            chunk_endorsement: record.chunk_endorsement.into(),
+            misses: record
+                .misses
+                .iter()
+                .map(|miss| MissedValidatorDutyView {
+                    kind: miss.kind.into(),
+                    height: miss.height,
+                    shard_id: miss.shard_id,
+                    reason: miss.reason.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct ChunkValidationTiming {
+    witness_received: Option<Utc>,
+    endorsement_sent: Option<Utc>,
+    validation_failed: bool,
+}
+
+/// Local times at which state witnesses were received and chunk endorsements
+/// sent.  Shared with the chunk validator, which sends the endorsements from
+/// the validation threads.
+pub(crate) struct ChunkValidationTimings {
+    clock: Clock,
+    timings: Mutex<LruCache<ChunkHash, ChunkValidationTiming>>,
+}
+
+impl ChunkValidationTimings {
+    pub fn new(clock: Clock) -> Self {
+        Self {
+            clock,
+            timings: Mutex::new(LruCache::new(
+                NonZeroUsize::new(CHUNK_VALIDATION_TIMINGS_CAPACITY).unwrap(),
+            )),
+        }
+    }
+
+    pub fn record_witness_received(&self, chunk_hash: &ChunkHash) {
+        let now = self.clock.now_utc();
+        let mut timings = self.timings.lock();
+        let timing = timings.get_or_insert_mut(chunk_hash.clone(), Default::default);
+        timing.witness_received.get_or_insert(now);
+    }
+
+    pub fn record_endorsement_sent(&self, chunk_hash: &ChunkHash) {
+        let now = self.clock.now_utc();
+        let mut timings = self.timings.lock();
+        let timing = timings.get_or_insert_mut(chunk_hash.clone(), Default::default);
+        timing.endorsement_sent.get_or_insert(now);
+    }
+
+    pub fn record_validation_failed(&self, chunk_hash: &ChunkHash) {
+        let mut timings = self.timings.lock();
+        timings.get_or_insert_mut(chunk_hash.clone(), Default::default).validation_failed = true;
+    }
+
+    /// Explains why the endorsement for the chunk is missing in the block
+    /// produced at `block_timestamp`.
+    fn missed_endorsement_reason(
+        &self,
+        chunk_hash: &ChunkHash,
+        block_timestamp: Utc,
+    ) -> MissedValidatorDutyReason {
+        let timing = self.timings.lock().peek(chunk_hash).copied().unwrap_or_default();
+        match timing {
+            ChunkValidationTiming { witness_received: None, .. } => {
+                MissedValidatorDutyReason::WitnessMissing
+            }
+            ChunkValidationTiming { witness_received: Some(received), .. }
+                if received > block_timestamp =>
+            {
+                MissedValidatorDutyReason::WitnessLate
+            }
+            ChunkValidationTiming { validation_failed: true, .. } => {
+                MissedValidatorDutyReason::ValidationFailed
+            }
+            ChunkValidationTiming { endorsement_sent: Some(sent), .. }
+                if sent <= block_timestamp =>
+            {
+                MissedValidatorDutyReason::EndorsementNotIncluded
+            }
+            ChunkValidationTiming { .. } => MissedValidatorDutyReason::ApplySlow,
+        }
+    }
+}
+
+/// Outcome of a single duty of the validator.
+#[derive(Debug, PartialEq, Eq)]
+struct DutyOutcome {
+    kind: ValidatorDutyKind,
+    height: BlockHeight,
+    shard_id: Option<ShardId>,
+    result: Result<(), MissedValidatorDutyReason>,
+}
+
+/// Duty of the validator at a height after the head.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct UpcomingDuty {
+    kind: ValidatorDutyKind,
+    height: BlockHeight,
+    shard_id: Option<ShardId>,
+}
+
+/// Condition of the node which would likely make it miss its upcoming duties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DutyHealthIssue {
+    /// Fewer connected peers than `min_num_peers`.
+    NotEnoughPeers,
+    /// The node is syncing, so it can't follow the chain.
+    Syncing,
+    /// The head block misses chunks of the shards of the upcoming chunk
+    /// duties, so the chunk parts of these shards may not be arriving.
+    ChunksMissing,
+}
+
+impl DutyHealthIssue {
+    const ALL: [Self; 3] = [Self::NotEnoughPeers, Self::Syncing, Self::ChunksMissing];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::NotEnoughPeers => "not_enough_peers",
+            Self::Syncing => "syncing",
+            Self::ChunksMissing => "chunks_missing",
+        }
+    }
+}
+
+/// Returns the health issues of the node which put its upcoming duties at
+/// risk. Missing chunks only matter for the shards of the chunk duties.
+fn duty_health_issues(
+    num_connected_peers: usize,
+    min_num_peers: usize,
+    is_syncing: bool,
+    upcoming_duties: &[UpcomingDuty],
+    missing_chunk_shards: &HashSet<ShardId>,
+) -> Vec<DutyHealthIssue> {
+    let mut issues = vec![];
+    if num_connected_peers < min_num_peers {
+        issues.push(DutyHealthIssue::NotEnoughPeers);
+    }
+    if is_syncing {
+        issues.push(DutyHealthIssue::Syncing);
+    }
+    if upcoming_duties
+        .iter()
+        .filter_map(|duty| duty.shard_id)
+        .any(|shard_id| missing_chunk_shards.contains(&shard_id))
+    {
+        issues.push(DutyHealthIssue::ChunksMissing);
+    }
+    issues
+}
+
+fn duty_kind_label(kind: ValidatorDutyKind) -> &'static str {
+    match kind {
+        ValidatorDutyKind::BlockProduction => "block_production",
+        ValidatorDutyKind::ChunkProduction => "chunk_production",
+        ValidatorDutyKind::ChunkEndorsement => "chunk_endorsement",
+    }
+}
+
+pub(crate) struct ValidatorDutyTracker {
+    store: Store,
+    pub chunk_validation_timings: Arc<ChunkValidationTimings>,
+    /// Duty records of the most recent epochs, oldest first.
+    records: Vec<EpochValidatorDuties>,
+    /// Height of the last final block whose duties were evaluated.
+    last_evaluated_height: Option<BlockHeight>,
+    /// `last_evaluated_height` when the records were last saved.
+    last_saved_height: Option<BlockHeight>,
+    /// Whether the record of a new epoch was started since the last save.
+    has_new_record: bool,
+    /// Height of the head for which the upcoming duties were last looked up.
+    last_checked_head_height: Option<BlockHeight>,
+    /// Duties of the heights after the last checked head.
+    upcoming_duties: Vec<UpcomingDuty>,
+    /// Shards whose chunks are missing in the last checked head block.
+    missing_chunk_shards: HashSet<ShardId>,
+    /// Upcoming duties already reported at risk, so that each is reported once.
+    alerted_duties: HashSet<UpcomingDuty>,
+}
+
+impl ValidatorDutyTracker {
+    pub fn new(clock: Clock, store: Store) -> Self {
+        let records = load_records(&store).unwrap_or_else(|err| {
+            tracing::warn!(target: "client", ?err, "Failed to load validator duties");
+            vec![]
+        });
+        let last_evaluated_height = records.last().map(|record| record.last_evaluated_height);
+        Self {
+            store,
+            chunk_validation_timings: Arc::new(ChunkValidationTimings::new(clock)),
+            records,
+            last_evaluated_height,
+            last_saved_height: last_evaluated_height,
+            has_new_record: false,
+            last_checked_head_height: None,
+            upcoming_duties: vec![],
+            missing_chunk_shards: HashSet::new(),
+            alerted_duties: HashSet::new(),
+        }
+    }
+
+    /// Returns the upcoming duties at risk which were not reported yet.
+    fn duties_to_alert(
+        &mut self,
+        head_height: BlockHeight,
+        issues: &[DutyHealthIssue],
+    ) -> Vec<UpcomingDuty> {
+        self.alerted_duties.retain(|duty| duty.height > head_height);
+        if issues.is_empty() {
+            return vec![];
+        }
+        self.upcoming_duties
+            .iter()
+            .filter(|duty| self.alerted_duties.insert((*duty).clone()))
+            .cloned()
+            .collect()
+    }
+
+    /// Adds the outcomes of duties in a final block to the record of its epoch.
+    fn record(
+        &mut self,
+        epoch_id: EpochId,
+        account_id: &AccountId,
+        height: BlockHeight,
+        outcomes: Vec<DutyOutcome>,
+    ) {
+        let is_new_record = !self
+            .records
+            .last()
+            .is_some_and(|record| record.epoch_id == epoch_id && &record.account_id == account_id);
+        if is_new_record {
+            if outcomes.is_empty() {
+                return;
+            }
+            self.records.push(EpochValidatorDuties {
+                epoch_id,
+                account_id: account_id.clone(),
+                last_evaluated_height: height,
+                block_production: Default::default(),
+                chunk_production: Default::default(),
+                chunk_endorsement: Default::default(),
+                misses: vec![],
+            });
+            if self.records.len() > NUM_EPOCHS_TO_KEEP {
+                self.records.remove(0);
+            }
+            self.has_new_record = true;
+        }
+        let record = self.records.last_mut().unwrap();
+        record.last_evaluated_height = height;
+        for outcome in outcomes {
+            let stats = match outcome.kind {
+                ValidatorDutyKind::BlockProduction => &mut record.block_production,
+                ValidatorDutyKind::ChunkProduction => &mut record.chunk_production,
+                ValidatorDutyKind::ChunkEndorsement => &mut record.chunk_endorsement,
+            };
+            stats.expected += 1;
+            stats.fulfilled += outcome.result.is_ok() as u64;
+            if let Err(reason) = outcome.result {
+                if record.misses.len() == MAX_MISSES_PER_EPOCH {
+                    record.misses.remove(0);
+                }
+                record.misses.push(MissedDuty {
+                    kind: outcome.kind.into(),
+                    height: outcome.height,
+                    shard_id: outcome.shard_id,
+                    reason: reason.into(),
+                });
+            }
+        }
+    }
+
+    /// Saves the records if enough final blocks were evaluated since the
+    /// last save, or if the record of a new epoch was started.
+    fn maybe_save(&mut self) -> Result<(), Error> {
+        let Some(height) = self.last_evaluated_height else {
+            return Ok(());
+        };
+        let is_save_due = self
+            .last_saved_height
+            .is_none_or(|saved_height| height >= saved_height + SAVE_INTERVAL);
+        if is_save_due || self.has_new_record {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        let mut store_update = self.store.store_update();
+        store_update.set_ser(
+            DBCol::Misc,
+            VALIDATOR_DUTIES_KEY,
+            &StoredValidatorDuties::V1(self.records.clone()),
+        )?;
+        store_update.commit()?;
+        self.last_saved_height = self.last_evaluated_height;
+        self.has_new_record = false;
+        Ok(())
+    }
+}
+
+fn load_records(store: &Store) -> Result<Vec<EpochValidatorDuties>, Error> {
+    Ok(match store.get_ser(DBCol::Misc, VALIDATOR_DUTIES_KEY)? {
+        Some(StoredValidatorDuties::V1(records)) => records,
+        None => vec![],
+    })
+}
+
+/// Returns the persisted duty records of the most recent epochs, oldest first.
+pub(crate) fn load_validator_duties(store: &Store) -> Result<Vec<EpochValidatorDutiesView>, Error> {
+    Ok(load_records(store)?.iter().map(Into::into).collect())
+}
+
+impl Client {
+    /// Evaluates the duties of the node's validator account in blocks which
+    /// became final since the last call.
+    pub(crate) fn update_validator_duties(
+        &mut self,
+        signer: &Option<Arc<ValidatorSigner>>,
+    ) -> Result<(), Error> {
+        let Some(signer) = signer else {
+            return Ok(());
+        };
+        if self.sync_handler.sync_status.is_syncing() {
+            return Ok(());
+        }
+        let final_head = self.chain.final_head()?;
+        let last_evaluated_height = self.validator_duty_tracker.last_evaluated_height;
+        if last_evaluated_height.is_some_and(|height| height >= final_head.height) {
+            return Ok(());
+        }
+
+        // Collect the newly finalized blocks, starting from the most recent.
+        // On the first run only the final head is evaluated.
+        let mut headers = vec![];
+        let mut header = self.chain.get_block_header(&final_head.last_block_hash)?;
+        while header.height() > self.chain.genesis().height()
+            && headers.len() < MAX_BLOCKS_TO_EVALUATE
+        {
+            let prev_header = self.chain.get_block_header(header.prev_hash())?;
+            headers.push((header, prev_header.clone()));
+            match last_evaluated_height {
+                Some(height) if prev_header.height() > height => header = prev_header,
+                _ => break,
+            }
+        }
+
+        let account_id = signer.validator_id();
+        for (header, prev_header) in headers.into_iter().rev() {
+            let block = match self.chain.get_block(header.hash()) {
+                Ok(block) => block,
+                // The block may be missing e.g. after state sync.
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let outcomes = self.evaluate_validator_duties(account_id, &block, &prev_header)?;
+            self.validator_duty_tracker.record(
+                *header.epoch_id(),
+                account_id,
+                header.height(),
+                outcomes,
+            );
+        }
+        self.validator_duty_tracker.last_evaluated_height = Some(final_head.height);
+        self.validator_duty_tracker.maybe_save()
+    }
+
+    /// Checks the health of the node ahead of the duties of its validator
+    /// account in the next few heights. The duties are looked up once per
+    /// head, while the health is checked on every call, so that the metrics
+    /// stay current while the head doesn't move, e.g. during sync.
+    pub(crate) fn check_upcoming_validator_duties(
+        &mut self,
+        signer: &Option<Arc<ValidatorSigner>>,
+        num_connected_peers: usize,
+    ) -> Result<(), Error> {
+        let Some(signer) = signer else {
+            return Ok(());
+        };
+        let head = self.chain.head()?;
+        let is_syncing = self.sync_handler.sync_status.is_syncing();
+        if is_syncing {
+            // The head of a syncing node is behind, so are the duties after it.
+            let tracker = &mut self.validator_duty_tracker;
+            tracker.last_checked_head_height = None;
+            tracker.upcoming_duties.clear();
+            tracker.missing_chunk_shards.clear();
+        } else if self.validator_duty_tracker.last_checked_head_height != Some(head.height) {
+            let upcoming_duties =
+                self.upcoming_validator_duties(signer.validator_id(), &head.last_block_hash)?;
+            let head_block = self.chain.get_block(&head.last_block_hash)?;
+            let missing_chunk_shards = head_block
+                .chunks()
+                .iter()
+                .filter_map(|chunk| match chunk {
+                    MaybeNew::New(_) => None,
+                    MaybeNew::Old(chunk) => Some(chunk.shard_id()),
+                })
+                .collect();
+            let tracker = &mut self.validator_duty_tracker;
+            tracker.last_checked_head_height = Some(head.height);
+            tracker.upcoming_duties = upcoming_duties;
+            tracker.missing_chunk_shards = missing_chunk_shards;
+        }
+
+        let tracker = &mut self.validator_duty_tracker;
+        let issues = duty_health_issues(
+            num_connected_peers,
+            self.config.min_num_peers,
+            is_syncing,
+            &tracker.upcoming_duties,
+            &tracker.missing_chunk_shards,
+        );
+        for issue in DutyHealthIssue::ALL {
+            metrics::VALIDATOR_DUTY_HEALTH_ISSUE
+                .with_label_values(&[issue.as_str()])
+                .set(issues.contains(&issue) as i64);
+        }
+        for kind in [
+            ValidatorDutyKind::BlockProduction,
+            ValidatorDutyKind::ChunkProduction,
+            ValidatorDutyKind::ChunkEndorsement,
+        ] {
+            let count = tracker.upcoming_duties.iter().filter(|duty| duty.kind == kind).count();
+            metrics::VALIDATOR_UPCOMING_DUTIES
+                .with_label_values(&[duty_kind_label(kind)])
+                .set(count as i64);
+        }
+
+        for duty in tracker.duties_to_alert(head.height, &issues) {
+            for issue in &issues {
+                metrics::VALIDATOR_UPCOMING_DUTY_ALERTS
+                    .with_label_values(&[duty_kind_label(duty.kind), issue.as_str()])
+                    .inc();
+            }
+            tracing::warn!(
+                target: "client",
+                kind = ?duty.kind,
+                height = duty.height,
+                shard_id = ?duty.shard_id,
+                head_height = head.height,
+                ?issues,
+                "Upcoming validator duty at risk"
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the duties of `account_id` in the next few heights after the
+    /// block, within the epoch of the next block.
+    fn upcoming_validator_duties(
+        &self,
+        account_id: &AccountId,
+        prev_block_hash: &CryptoHash,
+    ) -> Result<Vec<UpcomingDuty>, Error> {
+        let prev_height = self.chain.get_block_header(prev_block_hash)?.height();
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(prev_block_hash)?;
+        let epoch_start_height = if self.epoch_manager.is_next_block_epoch_start(prev_block_hash)? {
+            prev_height + 1
+        } else {
+            self.epoch_manager.get_epoch_start_height(prev_block_hash)?
+        };
+        // The epoch may end later, but not earlier.
+        let epoch_end_height =
+            epoch_start_height + self.epoch_manager.get_epoch_config(&epoch_id)?.epoch_length;
+        let shard_ids = self.epoch_manager.get_shard_layout(&epoch_id)?.shard_ids().collect_vec();
+
+        let mut duties = vec![];
+        for height in prev_height + 1..=prev_height + UPCOMING_DUTIES_HORIZON {
+            if height >= epoch_end_height {
+                break;
+            }
+            if &self.epoch_manager.get_block_producer(&epoch_id, height)? == account_id {
+                duties.push(UpcomingDuty {
+                    kind: ValidatorDutyKind::BlockProduction,
+                    height,
+                    shard_id: None,
+                });
+            }
+            for &shard_id in &shard_ids {
+                let chunk_producer =
+                    self.epoch_manager.get_chunk_producer_info(&ChunkProductionKey {
+                        epoch_id,
+                        height_created: height,
+                        shard_id,
+                    })?;
+                if chunk_producer.account_id() == account_id {
+                    duties.push(UpcomingDuty {
+                        kind: ValidatorDutyKind::ChunkProduction,
+                        height,
+                        shard_id: Some(shard_id),
+                    });
+                }
+                let chunk_validators = self
+                    .epoch_manager
+                    .get_chunk_validator_assignments(&epoch_id, shard_id, height)?;
+                if chunk_validators.contains(account_id) {
+                    duties.push(UpcomingDuty {
+                        kind: ValidatorDutyKind::ChunkEndorsement,
+                        height,
+                        shard_id: Some(shard_id),
+                    });
+                }
+            }
+        }
+        Ok(duties)
+    }
+
+    fn evaluate_validator_duties(
+        &self,
+        account_id: &AccountId,
+        block: &Block,
+        prev_header: &BlockHeader,
+    ) -> Result<Vec<DutyOutcome>, Error> {
+        let header = block.header();
+        let epoch_id = header.epoch_id();
+        let mut outcomes = vec![];
+
+        // Heights skipped since the previous block count as missed block
+        // production duties of their producers.
+        for height in prev_header.height() + 1..=header.height() {
+            if &self.epoch_manager.get_block_producer(epoch_id, height)? != account_id {
+                continue;
+            }
+            let result = if height == header.height() {
+                Ok(())
+            } else if self
+                .chain
+                .chain_store()
+                .get_all_block_hashes_by_height(height)?
+                .values()
+                .any(|hashes| !hashes.is_empty())
+            {
+                Err(MissedValidatorDutyReason::Orphaned)
+            } else {
+                Err(MissedValidatorDutyReason::NotProduced)
+            };
+            outcomes.push(DutyOutcome {
+                kind: ValidatorDutyKind::BlockProduction,
+                height,
+                shard_id: None,
+                result,
+            });
+        }
+
+        let shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
+        let chunk_height = prev_header.height() + 1;
+        for (shard_index, chunk_header) in block.chunks().iter_deprecated().enumerate() {
+            let shard_id = shard_layout.get_shard_id(shard_index)?;
+            let is_new_chunk = chunk_header.is_new_chunk(header.height());
+
+            let chunk_producer =
+                self.epoch_manager.get_chunk_producer_info(&ChunkProductionKey {
+                    epoch_id: *epoch_id,
+                    height_created: chunk_height,
+                    shard_id,
+                })?;
+            if chunk_producer.account_id() == account_id {
+                let result = if is_new_chunk {
+                    Ok(())
+                } else if self
+                    .chunk_producer
+                    .chunk_production_info
+                    .contains(&(chunk_height, shard_id))
+                {
+                    Err(MissedValidatorDutyReason::NotIncluded)
+                } else {
+                    Err(MissedValidatorDutyReason::NotProduced)
+                };
+                outcomes.push(DutyOutcome {
+                    kind: ValidatorDutyKind::ChunkProduction,
+                    height: header.height(),
+                    shard_id: Some(shard_id),
+                    result,
+                });
+            }
+
+            // Endorsements are only evaluated for chunks which made it into
+            // the block, since only those have endorsement signatures.
+            if !is_new_chunk {
+                continue;
+            }
+            let chunk_validators = self
+                .epoch_manager
+                .get_chunk_validator_assignments(epoch_id, shard_id, chunk_header.height_created())?
+                .ordered_chunk_validators();
+            let Some(index) = chunk_validators.iter().position(|validator| validator == account_id)
+            else {
+                continue;
+            };
+            let is_endorsed = block
+                .chunk_endorsements()
+                .get(shard_index)
+                .and_then(|signatures| signatures.get(index))
+                .is_some_and(|signature| signature.is_some());
+            let result = if is_endorsed {
+                Ok(())
+            } else {
+                Err(self
+                    .validator_duty_tracker
+                    .chunk_validation_timings
+                    .missed_endorsement_reason(&chunk_header.chunk_hash(), header.timestamp()))
+            };
+            outcomes.push(DutyOutcome {
+                kind: ValidatorDutyKind::ChunkEndorsement,
+                height: header.height(),
+                shard_id: Some(shard_id),
+                result,
+            });
+        }
+        Ok(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChunkValidationTimings, DutyHealthIssue, DutyOutcome, MAX_MISSES_PER_EPOCH,
+        NUM_EPOCHS_TO_KEEP, SAVE_INTERVAL, UpcomingDuty, ValidatorDutyTracker, duty_health_issues,
+        load_records, load_validator_duties,
+    };
+    use near_async::time::{Duration, FakeClock, Utc};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::sharding::ChunkHash;
+    use near_primitives::types::{AccountId, EpochId, ShardId};
+    use near_primitives::views::{MissedValidatorDutyReason, ValidatorDutyKind};
+    use std::collections::HashSet;
+
+    fn block_production(height: u64, result: Result<(), MissedValidatorDutyReason>) -> DutyOutcome {
+        DutyOutcome { kind: ValidatorDutyKind::BlockProduction, height, shard_id: None, result }
+    }
+
+    #[test]
+    fn test_record_duties() {
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let store = near_store::test_utils::create_test_store();
+        let mut tracker = ValidatorDutyTracker::new(clock.clock(), store.clone());
+        let account_id: AccountId = "test0".parse().unwrap();
+        let epoch_id = |i: u8| EpochId(CryptoHash::hash_bytes(&[i]));
+
+        // Epochs without any duties don't get a record.
+        tracker.record(epoch_id(0), &account_id, 1, vec![]);
+        assert!(tracker.records.is_empty());
+
+        tracker.record(epoch_id(0), &account_id, 2, vec![block_production(2, Ok(()))]);
+        tracker.record(
+            epoch_id(0),
+            &account_id,
+            4,
+            vec![block_production(3, Err(MissedValidatorDutyReason::NotProduced))],
+        );
+        tracker.record(epoch_id(0), &account_id, 5, vec![]);
+        let record = &tracker.records[0];
+        assert_eq!(record.last_evaluated_height, 5);
+        assert_eq!((record.block_production.expected, record.block_production.fulfilled), (2, 1));
+        assert_eq!(record.misses.len(), 1);
+        assert_eq!(record.misses[0].height, 3);
+
+        let misses = (0..MAX_MISSES_PER_EPOCH as u64 + 1)
+            .map(|height| block_production(height, Err(MissedValidatorDutyReason::Orphaned)))
+            .collect();
+        tracker.record(epoch_id(0), &account_id, 6, misses);
+        assert_eq!(tracker.records[0].misses.len(), MAX_MISSES_PER_EPOCH);
+        assert_eq!(tracker.records[0].misses[0].height, 0);
+
+        for i in 1..=NUM_EPOCHS_TO_KEEP as u8 {
+            tracker.record(
+                epoch_id(i),
+                &account_id,
+                10 * i as u64,
+                vec![block_production(10 * i as u64, Ok(()))],
+            );
+        }
+        assert_eq!(tracker.records.len(), NUM_EPOCHS_TO_KEEP);
+        assert_eq!(tracker.records[0].epoch_id, epoch_id(1));
+
+        tracker.save().unwrap();
+        assert_eq!(load_records(&store).unwrap(), tracker.records);
+        let views = load_validator_duties(&store).unwrap();
+        assert_eq!(views.len(), NUM_EPOCHS_TO_KEEP);
+        assert_eq!(views[0].epoch_id, epoch_id(1));
+        assert_eq!(
+            (views[0].block_production.expected, views[0].block_production.fulfilled),
+            (1, 1)
+        );
+        let tracker = ValidatorDutyTracker::new(clock.clock(), store);
+        assert_eq!(tracker.last_evaluated_height, Some(10 * NUM_EPOCHS_TO_KEEP as u64));
+    }
+
+    #[test]
+    fn test_save_interval() {
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let store = near_store::test_utils::create_test_store();
+        let mut tracker = ValidatorDutyTracker::new(clock.clock(), store.clone());
+        let account_id: AccountId = "test0".parse().unwrap();
+        let epoch_id = |i: u8| EpochId(CryptoHash::hash_bytes(&[i]));
+        let saved_height =
+            || load_records(&store).unwrap().last().map(|record| record.last_evaluated_height);
+        let mut evaluate = |epoch_id, height| {
+            tracker.record(epoch_id, &account_id, height, vec![block_production(height, Ok(()))]);
+            tracker.last_evaluated_height = Some(height);
+            tracker.maybe_save().unwrap();
+        };
+
+        evaluate(epoch_id(0), 1);
+        assert_eq!(saved_height(), Some(1));
+        for height in 2..1 + SAVE_INTERVAL {
+            evaluate(epoch_id(0), height);
+        }
+        assert_eq!(saved_height(), Some(1));
+        evaluate(epoch_id(0), 1 + SAVE_INTERVAL);
+        assert_eq!(saved_height(), Some(1 + SAVE_INTERVAL));
+
+        // The record of a new epoch is saved right away.
+        evaluate(epoch_id(1), 2 + SAVE_INTERVAL);
+        assert_eq!(saved_height(), Some(2 + SAVE_INTERVAL));
+    }
+
+    #[test]
+    fn test_missed_endorsement_reason() {
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let timings = ChunkValidationTimings::new(clock.clock());
+        let block_timestamp = Utc::UNIX_EPOCH + Duration::seconds(1);
+        let chunk = |i: u8| ChunkHash(CryptoHash::hash_bytes(&[i]));
+        let reason = |i| timings.missed_endorsement_reason(&chunk(i), block_timestamp);
+
+        assert_eq!(reason(0), MissedValidatorDutyReason::WitnessMissing);
+
+        timings.record_witness_received(&chunk(1));
+        assert_eq!(reason(1), MissedValidatorDutyReason::ApplySlow);
+        timings.record_endorsement_sent(&chunk(1));
+        assert_eq!(reason(1), MissedValidatorDutyReason::EndorsementNotIncluded);
+
+        timings.record_witness_received(&chunk(2));
+        timings.record_validation_failed(&chunk(2));
+        assert_eq!(reason(2), MissedValidatorDutyReason::ValidationFailed);
+
+        timings.record_witness_received(&chunk(3));
+        clock.advance(Duration::seconds(2));
+        timings.record_endorsement_sent(&chunk(3));
+        assert_eq!(reason(3), MissedValidatorDutyReason::ApplySlow);
+
+        timings.record_witness_received(&chunk(4));
+        assert_eq!(reason(4), MissedValidatorDutyReason::WitnessLate);
+    }
+
+    #[test]
+    fn test_duty_health_issues() {
+        let block_duty =
+            UpcomingDuty { kind: ValidatorDutyKind::BlockProduction, height: 2, shard_id: None };
+        let chunk_duty = UpcomingDuty {
+            kind: ValidatorDutyKind::ChunkEndorsement,
+            height: 2,
+            shard_id: Some(ShardId::new(1)),
+        };
+        let missing_chunk_shards = HashSet::from([ShardId::new(0)]);
+
+        assert!(duty_health_issues(5, 5, false, &[], &HashSet::new()).is_empty());
+        assert_eq!(
+            duty_health_issues(4, 5, true, &[], &HashSet::new()),
+            vec![DutyHealthIssue::NotEnoughPeers, DutyHealthIssue::Syncing]
+        );
+        // Missing chunks of the other shards don't put the duties at risk.
+        assert!(
+            duty_health_issues(
+                5,
+                5,
+                false,
+                &[block_duty, chunk_duty.clone()],
+                &missing_chunk_shards
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            duty_health_issues(5, 5, false, &[chunk_duty], &HashSet::from([ShardId::new(1)])),
+            vec![DutyHealthIssue::ChunksMissing]
+        );
+    }
+
+    #[test]
+    fn test_duties_to_alert() {
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let mut tracker =
+            ValidatorDutyTracker::new(clock.clock(), near_store::test_utils::create_test_store());
+        let duty = |height| UpcomingDuty {
+            kind: ValidatorDutyKind::BlockProduction,
+            height,
+            shard_id: None,
+        };
+        tracker.upcoming_duties = vec![duty(2), duty(3)];
+
+        assert!(tracker.duties_to_alert(1, &[]).is_empty());
+        let issues = [DutyHealthIssue::NotEnoughPeers];
+        assert_eq!(tracker.duties_to_alert(1, &issues), vec![duty(2), duty(3)]);
+        // Each duty is only reported once.
+        assert!(tracker.duties_to_alert(1, &issues).is_empty());
+        tracker.upcoming_duties = vec![duty(3), duty(4)];
+        assert_eq!(tracker.duties_to_alert(2, &issues), vec![duty(4)]);
+        assert_eq!(tracker.alerted_duties, HashSet::from([duty(3), duty(4)]));
+    }
+}
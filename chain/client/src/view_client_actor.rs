@@ -1,20 +1,27 @@
 //! Readonly view of the chain and state of the database.
 //! Useful for querying from RPC.
 
+use crate::state_part_server::StatePartServer;
+use crate::validator_delegation::ValidatorDelegations;
 use crate::{
-    GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock, GetShardChunk, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, metrics, sync,
+    GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock, GetProtocolVersionVotes,
+    GetReceiptTree, GetShardChunk, GetStateChanges, GetStateChangesInBlock,
+    GetTransactionsBySigner, GetValidatorDuties, GetValidatorInfo, GetValidatorOrdered,
+    MAX_QUERY_BATCH_EXECUTIONS, MAX_QUERY_BATCH_SIZE, MAX_VIEW_ACCOUNTS_BATCH_SIZE, QueryBatch,
+    ViewAccountsBatch, metrics, sync, validator_duties,
 };
 use actix::{Addr, SyncArbiter};
 use near_async::actix_wrapper::SyncActixWrapper;
 use near_async::messaging::{Actor, CanSend, Handler};
 use near_async::time::{Clock, Duration, Instant};
+use near_chain::rayon_spawner::RayonAsyncComputationSpawner;
 use near_chain::types::{RuntimeAdapter, Tip};
 use near_chain::{
     Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode, MerkleProofAccess,
     get_epoch_block_producers_view, retrieve_headers,
 };
 
+use borsh::BorshDeserialize;
 use near_chain_configs::{ClientConfig, MutableValidatorSigner, ProtocolConfigView};
 use near_chain_primitives::error::EpochErrorResultToChainError;
 use near_client_primitives::types::{
@@ -39,6 +46,7 @@ use near_network::types::{
     StateResponseInfo, StateResponseInfoV2,
 };
 use near_performance_metrics_macros::perf;
+use near_primitives::account::Account;
 use near_primitives::block::{Block, BlockHeader};
 use near_primitives::epoch_info::EpochInfo;
 use near_primitives::hash::CryptoHash;
@@ -46,24 +54,28 @@ use near_primitives::merkle::{PartialMerkleTree, merklize};
 use near_primitives::network::AnnounceAccount;
 use near_primitives::receipt::Receipt;
 use near_primitives::sharding::ShardChunk;
+use near_primitives::state::FlatStateValue;
 use near_primitives::state_sync::{
     ShardStateSyncResponse, ShardStateSyncResponseHeader, ShardStateSyncResponseV3,
 };
 use near_primitives::stateless_validation::ChunkProductionKey;
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
     AccountId, BlockHeight, BlockId, BlockReference, EpochReference, Finality, MaybeBlockId,
-    ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
+    ShardId, StateRoot, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
 };
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ExecutionStatusView,
+    AccountView, AccountsBatchEntryView, AccountsBatchView, BlockView, ChunkView,
+    EpochValidatorDutiesView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ExecutionStatusView,
     FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView,
-    LightClientBlockView, MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView,
-    SignedTransactionView, SplitStorageInfoView, StateChangesKindsView, StateChangesView,
-    TxExecutionStatus, TxStatusView,
+    LightClientBlockView, MaintenanceWindowsView, ProtocolVersionVotesView, QueryRequest,
+    QueryResponse, ReceiptTreeView, ReceiptView, SignedTransactionView, SignerTransactionView,
+    SplitStorageInfoView, StateChangesKindsView, StateChangesView, TxExecutionStatus, TxStatusView,
 };
-use near_store::{COLD_HEAD_KEY, DBCol, FINAL_HEAD_KEY, HEAD_KEY};
+use near_store::trie::AccessOptions;
+use near_store::{COLD_HEAD_KEY, DBCol, FINAL_HEAD_KEY, HEAD_KEY, ShardUId, Store};
 use parking_lot::{Mutex, RwLock};
 use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
@@ -104,6 +116,10 @@ pub struct ViewClientActorInner {
     pub config: ClientConfig,
     request_manager: Arc<RwLock<ViewClientRequestManager>>,
     state_request_cache: Arc<Mutex<VecDeque<Instant>>>,
+    /// Set if `state_part_serving` is enabled in the config.
+    state_part_server: Option<Arc<StatePartServer>>,
+    /// Set if `validator_delegation_info` is enabled in the config.
+    validator_delegations: Option<ValidatorDelegations>,
 }
 
 impl ViewClientRequestManager {
@@ -128,23 +144,30 @@ impl ViewClientActorInner {
         epoch_manager: Arc<dyn EpochManagerAdapter>,
         shard_tracker: ShardTracker,
         runtime: Arc<dyn RuntimeAdapter>,
+        read_through_store: Option<Store>,
         network_adapter: PeerManagerAdapter,
         config: ClientConfig,
         adv: crate::adversarial::Controls,
     ) -> Addr<ViewClientActor> {
+        // Shared by the threads so that the limits of the state part serving
+        // apply to the whole pool.
+        let state_part_server =
+            config.state_part_serving.clone().map(|config| Arc::new(StatePartServer::new(config)));
         SyncArbiter::start(config.view_client_threads, move || {
-            let view_client_actor = ViewClientActorInner::new(
+            let mut view_client_actor = ViewClientActorInner::new(
                 clock.clone(),
                 validator.clone(),
                 chain_genesis.clone(),
                 epoch_manager.clone(),
                 shard_tracker.clone(),
                 runtime.clone(),
+                read_through_store.clone(),
                 network_adapter.clone(),
                 config.clone(),
                 adv.clone(),
             )
             .unwrap();
+            view_client_actor.state_part_server = state_part_server.clone();
             SyncActixWrapper::new(view_client_actor)
         })
     }
@@ -156,12 +179,13 @@ impl ViewClientActorInner {
         epoch_manager: Arc<dyn EpochManagerAdapter>,
         shard_tracker: ShardTracker,
         runtime: Arc<dyn RuntimeAdapter>,
+        read_through_store: Option<Store>,
         network_adapter: PeerManagerAdapter,
         config: ClientConfig,
         adv: crate::adversarial::Controls,
     ) -> Result<Self, Error> {
         // TODO: should we create shared ChainStore that is passed to both Client and ViewClient?
-        let chain = Chain::new_for_view_client(
+        let mut chain = Chain::new_for_view_client(
             clock.clone(),
             epoch_manager.clone(),
             shard_tracker.clone(),
@@ -170,6 +194,14 @@ impl ViewClientActorInner {
             DoomslugThresholdMode::TwoThirds,
             config.save_trie_changes,
         )?;
+        if let Some(store) = read_through_store {
+            chain.set_read_through_store(store);
+        }
+        let validator_delegations = config.validator_delegation_info.clone().map(|config| {
+            ValidatorDelegations::new(config, Arc::new(RayonAsyncComputationSpawner))
+        });
+        let state_part_server =
+            config.state_part_serving.clone().map(|config| Arc::new(StatePartServer::new(config)));
         Ok(Self {
             clock,
             adv,
@@ -182,6 +214,8 @@ impl ViewClientActorInner {
             config,
             request_manager: Arc::new(RwLock::new(ViewClientRequestManager::new())),
             state_request_cache: Arc::new(Mutex::new(VecDeque::default())),
+            state_part_server,
+            validator_delegations,
         })
     }
 
@@ -347,26 +381,136 @@ impl ViewClientActorInner {
     }
 
     fn handle_query(&self, msg: Query) -> Result<QueryResponse, QueryError> {
-        let header = self.get_block_header_by_reference(&msg.block_reference);
-        let header = match header {
+        let header = self.get_query_block_header(msg.block_reference)?;
+        let (shard_id, shard_uid) = self.get_query_shard(&header, &msg.request)?;
+        let state_root = self.get_query_state_root(&header, shard_id, &shard_uid)?;
+        self.runtime
+            .query(
+                shard_uid,
+                &state_root,
+                header.height(),
+                header.raw_timestamp(),
+                header.prev_hash(),
+                header.hash(),
+                header.epoch_id(),
+                &msg.request,
+            )
+            .map_err(query_error_from_runtime)
+    }
+
+    fn handle_query_batch(
+        &self,
+        msg: QueryBatch,
+    ) -> Result<Vec<Result<QueryResponse, QueryError>>, QueryError> {
+        if msg.requests.len() > MAX_QUERY_BATCH_SIZE {
+            return Err(QueryError::BatchTooLarge {
+                error_message: format!(
+                    "at most {} queries can be batched, got {}",
+                    MAX_QUERY_BATCH_SIZE,
+                    msg.requests.len()
+                ),
+            });
+        }
+        let num_executions = msg
+            .requests
+            .iter()
+            .filter(|request| {
+                matches!(
+                    request,
+                    QueryRequest::CallFunction { .. } | QueryRequest::DryRunTransaction { .. }
+                )
+            })
+            .count();
+        if num_executions > MAX_QUERY_BATCH_EXECUTIONS {
+            return Err(QueryError::BatchTooLarge {
+                error_message: format!(
+                    "at most {} function calls and dry runs can be batched, got {}",
+                    MAX_QUERY_BATCH_EXECUTIONS, num_executions
+                ),
+            });
+        }
+        metrics::VIEW_CLIENT_QUERY_BATCH_REQUESTS.observe(msg.requests.len() as f64);
+        let header = self.get_query_block_header(msg.block_reference)?;
+
+        // The requests are run shard by shard, so that the requests touching
+        // the same shard share its state root and its trie.
+        let mut results: Vec<Option<Result<QueryResponse, QueryError>>> =
+            (0..msg.requests.len()).map(|_| None).collect();
+        let mut requests_by_shard: HashMap<ShardUId, (ShardId, Vec<usize>)> = HashMap::new();
+        for (index, request) in msg.requests.iter().enumerate() {
+            match self.get_query_shard(&header, request) {
+                Ok((shard_id, shard_uid)) => {
+                    requests_by_shard.entry(shard_uid).or_insert((shard_id, vec![])).1.push(index)
+                }
+                Err(err) => results[index] = Some(Err(err)),
+            }
+        }
+        for (shard_uid, (shard_id, indices)) in requests_by_shard {
+            let state_root = match self.get_query_state_root(&header, shard_id, &shard_uid) {
+                Ok(state_root) => state_root,
+                Err(err) => {
+                    for index in indices {
+                        results[index] = Some(Err(err.clone()));
+                    }
+                    continue;
+                }
+            };
+            let requests = indices.iter().map(|index| &msg.requests[*index]).collect::<Vec<_>>();
+            let shard_results = self.runtime.query_batch(
+                shard_uid,
+                &state_root,
+                header.height(),
+                header.raw_timestamp(),
+                header.prev_hash(),
+                header.hash(),
+                header.epoch_id(),
+                &requests,
+            );
+            for (index, result) in indices.into_iter().zip(shard_results) {
+                results[index] = Some(result.map_err(query_error_from_runtime));
+            }
+        }
+        Ok(results
+            .into_iter()
+            .map(|result| result.expect("every request of the batch should get a result"))
+            .collect())
+    }
+
+    fn get_query_block_header(
+        &self,
+        block_reference: BlockReference,
+    ) -> Result<BlockHeader, QueryError> {
+        let header = self.get_block_header_by_reference(&block_reference);
+        match header {
             Ok(Some(header)) => Ok(header),
             Ok(None) => Err(QueryError::NoSyncedBlocks),
             Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => {
-                Err(QueryError::UnknownBlock { block_reference: msg.block_reference })
+                Err(QueryError::UnknownBlock { block_reference })
             }
             Err(near_chain::near_chain_primitives::Error::IOErr(err)) => {
                 Err(QueryError::InternalError { error_message: err.to_string() })
             }
             Err(err) => Err(QueryError::Unreachable { error_message: err.to_string() }),
-        }?;
+        }
+    }
 
-        let account_id = match &msg.request {
+    /// Returns the shard whose state `request` is run against.
+    fn get_query_shard(
+        &self,
+        header: &BlockHeader,
+        request: &QueryRequest,
+    ) -> Result<(ShardId, ShardUId), QueryError> {
+        let account_id = match request {
             QueryRequest::ViewAccount { account_id, .. } => account_id,
             QueryRequest::ViewState { account_id, .. } => account_id,
+            QueryRequest::ViewStatePaged { account_id, .. } => account_id,
             QueryRequest::ViewAccessKey { account_id, .. } => account_id,
             QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
             QueryRequest::CallFunction { account_id, .. } => account_id,
             QueryRequest::ViewCode { account_id, .. } => account_id,
+            QueryRequest::DryRunTransaction { signed_transaction, .. } => {
+                signed_transaction.transaction.signer_id()
+            }
         };
         let shard_id =
             account_id_to_shard_id(self.epoch_manager.as_ref(), account_id, header.epoch_id())
@@ -374,10 +518,18 @@ impl ViewClientActorInner {
         let shard_uid =
             shard_id_to_uid(self.epoch_manager.as_ref(), shard_id, header.epoch_id())
                 .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+        Ok((shard_id, shard_uid))
+    }
 
+    fn get_query_state_root(
+        &self,
+        header: &BlockHeader,
+        shard_id: ShardId,
+        shard_uid: &ShardUId,
+    ) -> Result<StateRoot, QueryError> {
         let tip = self.chain.head();
         let chunk_extra =
-            self.chain.get_chunk_extra(header.hash(), &shard_uid).map_err(|err| match err {
+            self.chain.get_chunk_extra(header.hash(), shard_uid).map_err(|err| match err {
                 near_chain::near_chain_primitives::Error::DBNotFoundErr(_) => match tip {
                     Ok(tip) => {
                         let gc_stop_height = self.runtime.get_gc_stop_height(&tip.last_block_hash);
@@ -398,63 +550,91 @@ impl ViewClientActorInner {
                 _ => QueryError::Unreachable { error_message: err.to_string() },
             })?;
 
-        let state_root = chunk_extra.state_root();
-        match self.runtime.query(
-            shard_uid,
-            state_root,
-            header.height(),
-            header.raw_timestamp(),
-            header.prev_hash(),
-            header.hash(),
-            header.epoch_id(),
-            &msg.request,
-        ) {
-            Ok(query_response) => Ok(query_response),
-            Err(query_error) => Err(match query_error {
-                near_chain::near_chain_primitives::error::QueryError::InternalError {
-                    error_message,
-                    ..
-                } => QueryError::InternalError { error_message },
-                near_chain::near_chain_primitives::error::QueryError::InvalidAccount {
-                    requested_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::InvalidAccount { requested_account_id, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::UnknownAccount {
-                    requested_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::UnknownAccount { requested_account_id, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::NoContractCode {
-                    contract_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::NoContractCode { contract_account_id, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::UnknownAccessKey {
-                    public_key,
-                    block_height,
-                    block_hash,
-                } => QueryError::UnknownAccessKey { public_key, block_height, block_hash },
-                near_chain::near_chain_primitives::error::QueryError::ContractExecutionError {
-                    error_message,
-                    block_hash,
-                    block_height,
-                } => QueryError::ContractExecutionError {
-                    vm_error: error_message,
-                    block_height,
-                    block_hash,
-                },
-                near_chain::near_chain_primitives::error::QueryError::TooLargeContractState {
-                    requested_account_id,
-                    block_height,
-                    block_hash,
-                } => QueryError::TooLargeContractState {
-                    contract_account_id: requested_account_id,
-                    block_height,
-                    block_hash,
-                },
-            }),
+        Ok(*chunk_extra.state_root())
+    }
+
+    /// Reads the accounts of the final block directly from flat storage, which
+    /// avoids traversing the trie for every account. The accounts of a shard
+    /// are read in the order of their keys.
+    fn view_accounts_batch(
+        &self,
+        account_ids: Vec<AccountId>,
+    ) -> Result<AccountsBatchView, QueryError> {
+        fn internal_error(err: impl std::fmt::Display) -> QueryError {
+            QueryError::InternalError { error_message: err.to_string() }
+        }
+        if account_ids.len() > MAX_VIEW_ACCOUNTS_BATCH_SIZE {
+            return Err(QueryError::BatchTooLarge {
+                error_message: format!(
+                    "too many accounts requested: {}, at most {} are allowed",
+                    account_ids.len(),
+                    MAX_VIEW_ACCOUNTS_BATCH_SIZE
+                ),
+            });
+        }
+        let tip = match self.chain.final_head() {
+            Ok(tip) => tip,
+            Err(near_chain::near_chain_primitives::Error::DBNotFoundErr(_)) => {
+                return Err(QueryError::NoSyncedBlocks);
+            }
+            Err(err) => return Err(internal_error(err)),
+        };
+
+        let mut accounts_by_shard: HashMap<(ShardId, ShardUId), Vec<usize>> = HashMap::new();
+        for (index, account_id) in account_ids.iter().enumerate() {
+            let shard_id =
+                account_id_to_shard_id(self.epoch_manager.as_ref(), account_id, &tip.epoch_id)
+                    .map_err(internal_error)?;
+            let shard_uid = shard_id_to_uid(self.epoch_manager.as_ref(), shard_id, &tip.epoch_id)
+                .map_err(internal_error)?;
+            accounts_by_shard.entry((shard_id, shard_uid)).or_default().push(index);
+        }
+
+        let flat_storage_manager = self.runtime.get_flat_storage_manager();
+        let mut accounts = vec![None; account_ids.len()];
+        for ((shard_id, shard_uid), indices) in accounts_by_shard {
+            let chunk_view = flat_storage_manager
+                .chunk_view(shard_uid, tip.last_block_hash)
+                .ok_or(QueryError::UnavailableShard { requested_shard_id: shard_id })?;
+            // Values changed since the flat storage head are only stored as
+            // references to the trie values.
+            let chunk_extra = self
+                .chain
+                .get_chunk_extra(&tip.last_block_hash, &shard_uid)
+                .map_err(internal_error)?;
+            let trie =
+                self.runtime.get_tries().get_trie_for_shard(shard_uid, *chunk_extra.state_root());
+            let mut keys = indices
+                .into_iter()
+                .map(|index| {
+                    (TrieKey::Account { account_id: account_ids[index].clone() }.to_vec(), index)
+                })
+                .collect::<Vec<_>>();
+            keys.sort();
+            for (key, index) in keys {
+                let Some(value) = chunk_view.get_value(&key).map_err(internal_error)? else {
+                    continue;
+                };
+                let bytes = match value {
+                    FlatStateValue::Inlined(bytes) => bytes,
+                    FlatStateValue::Ref(value_ref) => trie
+                        .retrieve_value(&value_ref.hash, AccessOptions::DEFAULT)
+                        .map_err(internal_error)?,
+                };
+                let account = Account::try_from_slice(&bytes).map_err(internal_error)?;
+                accounts[index] = Some(AccountView::from(account));
+            }
         }
+
+        Ok(AccountsBatchView {
+            block_hash: tip.last_block_hash,
+            block_height: tip.height,
+            accounts: account_ids
+                .into_iter()
+                .zip(accounts)
+                .map(|(account_id, account)| AccountsBatchEntryView { account_id, account })
+                .collect(),
+        })
     }
 
     // Return the lowest status the node can proof
@@ -682,6 +862,44 @@ impl ViewClientActorInner {
         cache.push_back(now);
         false
     }
+
+    /// Returns the state part, from the cache of the state part server if
+    /// there is one or from the database. Returns `Ok(None)` if the part needs
+    /// to be generated but the maximum number of parts are already being
+    /// generated.
+    fn get_state_part(
+        &mut self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        part_id: u64,
+    ) -> Result<Option<Vec<u8>>, near_chain::Error> {
+        let Some(server) = self.state_part_server.clone() else {
+            return self
+                .chain
+                .state_sync_adapter
+                .get_state_response_part(shard_id, part_id, sync_hash)
+                .map(Some);
+        };
+        let key = (sync_hash, shard_id, part_id);
+        if let Some(part) = server.get_part(&key) {
+            return Ok(Some(part));
+        }
+        // The parts generated before only need to be read, so they don't take
+        // a generation slot.
+        if let Some(part) =
+            self.chain.state_sync_adapter.get_stored_state_part(shard_id, part_id, sync_hash)?
+        {
+            server.record_stored_part();
+            return Ok(Some(part));
+        }
+        let Some(_guard) = server.start_generation() else {
+            return Ok(None);
+        };
+        let part =
+            self.chain.state_sync_adapter.get_state_response_part(shard_id, part_id, sync_hash)?;
+        server.insert_part(key, part.clone());
+        Ok(Some(part))
+    }
 }
 
 impl Handler<Query> for ViewClientActorInner {
@@ -693,6 +911,30 @@ impl Handler<Query> for ViewClientActorInner {
     }
 }
 
+impl Handler<QueryBatch> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: QueryBatch,
+    ) -> Result<Vec<Result<QueryResponse, QueryError>>, QueryError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer =
+            metrics::VIEW_CLIENT_MESSAGE_TIME.with_label_values(&["QueryBatch"]).start_timer();
+        self.handle_query_batch(msg)
+    }
+}
+
+impl Handler<ViewAccountsBatch> for ViewClientActorInner {
+    #[perf]
+    fn handle(&mut self, msg: ViewAccountsBatch) -> Result<AccountsBatchView, QueryError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["ViewAccountsBatch"])
+            .start_timer();
+        self.view_accounts_batch(msg.account_ids)
+    }
+}
+
 /// Handles retrieving block from the chain.
 impl Handler<GetBlock> for ViewClientActorInner {
     #[perf]
@@ -728,6 +970,58 @@ impl Handler<GetBlockWithMerkleTree> for ViewClientActorInner {
     }
 }
 
+fn query_error_from_runtime(
+    query_error: near_chain::near_chain_primitives::error::QueryError,
+) -> QueryError {
+    match query_error {
+        near_chain::near_chain_primitives::error::QueryError::InternalError {
+            error_message,
+            ..
+        } => QueryError::InternalError { error_message },
+        near_chain::near_chain_primitives::error::QueryError::InvalidAccount {
+            requested_account_id,
+            block_height,
+            block_hash,
+        } => QueryError::InvalidAccount { requested_account_id, block_height, block_hash },
+        near_chain::near_chain_primitives::error::QueryError::UnknownAccount {
+            requested_account_id,
+            block_height,
+            block_hash,
+        } => QueryError::UnknownAccount { requested_account_id, block_height, block_hash },
+        near_chain::near_chain_primitives::error::QueryError::NoContractCode {
+            contract_account_id,
+            block_height,
+            block_hash,
+        } => QueryError::NoContractCode { contract_account_id, block_height, block_hash },
+        near_chain::near_chain_primitives::error::QueryError::UnknownAccessKey {
+            public_key,
+            block_height,
+            block_hash,
+        } => QueryError::UnknownAccessKey { public_key, block_height, block_hash },
+        near_chain::near_chain_primitives::error::QueryError::ContractExecutionError {
+            error_message,
+            block_hash,
+            block_height,
+        } => {
+            QueryError::ContractExecutionError { vm_error: error_message, block_height, block_hash }
+        }
+        near_chain::near_chain_primitives::error::QueryError::TooLargeContractState {
+            requested_account_id,
+            block_height,
+            block_hash,
+        } => QueryError::TooLargeContractState {
+            contract_account_id: requested_account_id,
+            block_height,
+            block_hash,
+        },
+        near_chain::near_chain_primitives::error::QueryError::InvalidTransaction {
+            error,
+            block_height,
+            block_hash,
+        } => QueryError::InvalidTransaction { error, block_height, block_hash },
+    }
+}
+
 fn get_chunk_from_block(
     block: Block,
     shard_id: ShardId,
@@ -826,6 +1120,53 @@ impl Handler<TxStatus> for ViewClientActorInner {
     }
 }
 
+impl Handler<GetTransactionsBySigner> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetTransactionsBySigner,
+    ) -> Result<Vec<SignerTransactionView>, TxStatusError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetTransactionsBySigner"])
+            .start_timer();
+        if self.config.transactions_by_signer_limit.is_none() {
+            return Err(TxStatusError::InternalError(
+                "transactions_by_signer_limit is not set in the config".to_string(),
+            ));
+        }
+        let transactions = self.chain.chain_store().get_transactions_by_signer(&msg.signer_id)?;
+        Ok(transactions
+            .into_iter()
+            .rev()
+            .take(msg.limit.unwrap_or(usize::MAX))
+            .map(|(block_height, transaction_hash, block_hash)| SignerTransactionView {
+                transaction_hash,
+                block_hash,
+                block_height,
+            })
+            .collect())
+    }
+}
+
+impl Handler<GetReceiptTree> for ViewClientActorInner {
+    #[perf]
+    fn handle(&mut self, msg: GetReceiptTree) -> Result<ReceiptTreeView, TxStatusError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer =
+            metrics::VIEW_CLIENT_MESSAGE_TIME.with_label_values(&["GetReceiptTree"]).start_timer();
+        let validator_signer = self.validator.get();
+        let me = validator_signer.as_ref().map(|signer| signer.validator_id());
+        match self.chain.get_receipt_tree(&msg.tx_hash, me) {
+            Ok(receipt_tree) => Ok(receipt_tree),
+            Err(near_chain::Error::DBNotFoundErr(_)) => {
+                Err(TxStatusError::MissingTransaction(msg.tx_hash))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
 impl Handler<GetValidatorInfo> for ViewClientActorInner {
     #[perf]
     fn handle(
@@ -869,7 +1210,17 @@ impl Handler<GetValidatorInfo> for ViewClientActorInner {
                 ValidatorInfoIdentifier::BlockHash(self.chain.header_head()?.last_block_hash)
             }
         };
-        Ok(self.epoch_manager.get_validator_info(epoch_identifier).into_chain_error()?)
+        let mut validator_info =
+            self.epoch_manager.get_validator_info(epoch_identifier).into_chain_error()?;
+        if let Some(validator_delegations) = &self.validator_delegations {
+            validator_delegations.attach(
+                &self.chain,
+                self.epoch_manager.as_ref(),
+                &self.runtime,
+                &mut validator_info,
+            );
+        }
+        Ok(validator_info)
     }
 }
 
@@ -888,6 +1239,40 @@ impl Handler<GetValidatorOrdered> for ViewClientActorInner {
         })?)
     }
 }
+impl Handler<GetProtocolVersionVotes> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetProtocolVersionVotes,
+    ) -> Result<ProtocolVersionVotesView, GetValidatorInfoError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetProtocolVersionVotes"])
+            .start_timer();
+        let header = self.maybe_block_id_to_block_header(msg.block_id)?;
+        Ok(self.epoch_manager.get_protocol_version_votes(header.hash()).into_chain_error()?)
+    }
+}
+
+impl Handler<GetValidatorDuties> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetValidatorDuties,
+    ) -> Result<Vec<EpochValidatorDutiesView>, GetValidatorInfoError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetValidatorDuties"])
+            .start_timer();
+        let mut duties =
+            validator_duties::load_validator_duties(&self.chain.chain_store().store())?;
+        if let Some(epoch_id) = msg.epoch_id {
+            duties.retain(|record| record.epoch_id == epoch_id);
+        }
+        Ok(duties)
+    }
+}
+
 /// Returns a list of change kinds per account in a store for a given block.
 impl Handler<GetStateChangesInBlock> for ViewClientActorInner {
     #[perf]
@@ -1384,20 +1769,33 @@ impl Handler<StateRequestPart> for ViewClientActorInner {
         let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
             .with_label_values(&["StateRequestPart"])
             .start_timer();
-        let StateRequestPart { shard_id, sync_hash, part_id } = msg;
-        if self.throttle_state_sync_request() {
+        let StateRequestPart { shard_id, sync_hash, part_id, peer_id } = msg;
+        // The limit of every peer comes on top of the limit of all the
+        // requests, which the peers could bypass by changing their ids.
+        let throttled = match &self.state_part_server {
+            Some(server) => {
+                server.throttle_peer(
+                    &peer_id,
+                    self.clock.now(),
+                    self.config.view_client_throttle_period,
+                ) || self.throttle_state_sync_request()
+            }
+            None => self.throttle_state_sync_request(),
+        };
+        if throttled {
             metrics::STATE_SYNC_REQUESTS_THROTTLED_TOTAL.inc();
             return None;
         }
         tracing::debug!(target: "sync", ?shard_id, ?sync_hash, ?part_id, "Computing state request part");
         let part = match self.chain.check_sync_hash_validity(&sync_hash) {
             Ok(true) => {
-                let part = match self
-                    .chain
-                    .state_sync_adapter
-                    .get_state_response_part(shard_id, part_id, sync_hash)
-                {
-                    Ok(part) => Some((part_id, part)),
+                let part = match self.get_state_part(shard_id, sync_hash, part_id) {
+                    Ok(Some(part)) => Some((part_id, part)),
+                    Ok(None) => {
+                        tracing::debug!(target: "sync", ?sync_hash, ?shard_id, part_id, "Too many state parts being generated");
+                        // Do not respond, the peer will request the part again.
+                        return None;
+                    }
                     Err(err) => {
                         error!(target: "sync", ?err, ?sync_hash, ?shard_id, part_id, "Cannot build state part");
                         None
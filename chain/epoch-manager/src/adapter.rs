@@ -16,7 +16,7 @@ use near_primitives::types::{
     ValidatorInfoIdentifier,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::EpochValidatorInfo;
+use near_primitives::views::{EpochValidatorInfo, ProtocolVersionVotesView};
 use near_store::{ShardUId, StoreUpdate};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
@@ -532,6 +532,16 @@ pub trait EpochManagerAdapter: Send + Sync {
         epoch_identifier: ValidatorInfoIdentifier,
     ) -> Result<EpochValidatorInfo, EpochError>;
 
+    /// Protocol version votes cast in the epoch of given block up to and
+    /// including that block.
+    ///
+    /// WARNING: this call may be expensive.  Intended for diagnostic use in
+    /// rpc.
+    fn get_protocol_version_votes(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, EpochError>;
+
     fn add_validator_proposals(
         &self,
         block_info: BlockInfo,
@@ -943,6 +953,14 @@ impl EpochManagerAdapter for EpochManagerHandle {
         epoch_manager.get_validator_info(epoch_id)
     }
 
+    fn get_protocol_version_votes(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, EpochError> {
+        let epoch_manager = self.read();
+        epoch_manager.get_protocol_version_votes(block_hash)
+    }
+
     fn add_validator_proposals(
         &self,
         block_info: BlockInfo,
@@ -44,6 +44,22 @@ impl EpochInfoAggregator {
         }
     }
 
+    /// Returns the chunk production and endorsement stats of each validator so far in
+    /// the epoch, summed over all shards.
+    pub fn validator_chunk_stats(&self) -> HashMap<ValidatorId, ChunkStats> {
+        let mut result: HashMap<ValidatorId, ChunkStats> = HashMap::new();
+        for tracker in self.shard_tracker.values() {
+            for (validator_id, stats) in tracker {
+                let entry = result.entry(*validator_id).or_default();
+                *entry.expected_mut() += stats.expected();
+                *entry.produced_mut() += stats.produced();
+                entry.endorsement_stats_mut().expected += stats.endorsement_stats().expected;
+                entry.endorsement_stats_mut().produced += stats.endorsement_stats().produced;
+            }
+        }
+        result
+    }
+
     /// Aggregates data from a block which directly precede the first block this
     /// aggregator has statistic on.
     ///
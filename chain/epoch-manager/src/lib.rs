@@ -1,7 +1,10 @@
 #![cfg_attr(enable_const_type_id, feature(const_type_id))]
 
 pub use crate::adapter::EpochManagerAdapter;
-use crate::metrics::{PROTOCOL_VERSION_NEXT, PROTOCOL_VERSION_VOTES};
+use crate::metrics::{
+    PROTOCOL_VERSION_NEXT, PROTOCOL_VERSION_VOTES, VALIDATOR_ENDORSEMENTS_EXPECTED,
+    VALIDATOR_ENDORSEMENTS_PRODUCED, VALIDATOR_KICKOUT_RISK,
+};
 pub use crate::reward_calculator::NUM_SECONDS_IN_A_YEAR;
 pub use crate::reward_calculator::RewardCalculator;
 use epoch_info_aggregator::EpochInfoAggregator;
@@ -27,7 +30,8 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolFeature, ProtocolVersion};
 use near_primitives::views::{
-    CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, ValidatorKickoutView,
+    CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, ProtocolVersionVoteView,
+    ProtocolVersionVotesView, ValidatorKickoutView, ValidatorProtocolVersionVoteView,
 };
 use near_store::adapter::StoreAdapter;
 use near_store::{DBCol, HEADER_HEAD_KEY, Store, StoreUpdate};
@@ -49,11 +53,13 @@ mod metrics;
 mod reward_calculator;
 pub mod shard_assignment;
 pub mod shard_tracker;
+pub mod simulator;
 pub mod test_utils;
 #[cfg(test)]
 mod tests;
 pub mod validate;
 mod validator_selection;
+pub mod validator_set_export;
 mod validator_stats;
 
 const EPOCH_CACHE_SIZE: usize = 50;
@@ -114,6 +120,17 @@ impl EpochInfoProvider for EpochManagerHandle {
     }
 }
 
+/// Result of tallying protocol version votes of an epoch.
+struct ProtocolVersionVoting {
+    /// Stake voting for each version.
+    versions: HashMap<ProtocolVersion, Balance>,
+    total_block_producer_stake: Balance,
+    /// Stake a version needs to exceed to be adopted.
+    threshold: Balance,
+    /// Version the epoch after the next one will have.
+    next_next_epoch_version: ProtocolVersion,
+}
+
 /// Tracks epoch information across different forks, such as validators.
 /// Note: that even after garbage collection, the data about genesis epoch should be in the store.
 pub struct EpochManager {
@@ -476,6 +493,48 @@ impl EpochManager {
         (validator_block_chunk_stats, validator_kickout)
     }
 
+    /// Tallies protocol version votes of block producers of an epoch.
+    /// Implements https://github.com/near/NEPs/blob/master/specs/ChainSpec/Upgradability.md
+    fn protocol_version_voting(
+        &self,
+        epoch_info: &EpochInfo,
+        next_epoch_info: &EpochInfo,
+        version_tracker: &HashMap<ValidatorId, ProtocolVersion>,
+    ) -> ProtocolVersionVoting {
+        let total_block_producer_stake: u128 = epoch_info
+            .block_producers_settlement()
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>()
+            .iter()
+            .map(|&id| epoch_info.validator_stake(id))
+            .sum();
+
+        let mut versions = HashMap::new();
+        for (validator_id, version) in version_tracker {
+            let stake = epoch_info.validator_stake(*validator_id);
+            *versions.entry(*version).or_insert(0) += stake;
+        }
+
+        let protocol_version = next_epoch_info.protocol_version();
+        let config = self.config.for_protocol_version(protocol_version);
+        let numer = *config.protocol_upgrade_stake_threshold.numer() as u128;
+        let denom = *config.protocol_upgrade_stake_threshold.denom() as u128;
+        let threshold = total_block_producer_stake * numer / denom;
+        // Note: non-deterministic iteration is fine here, there can be only one
+        // version with large enough stake.
+        let next_next_epoch_version = match versions.iter().max_by_key(|&(_, stake)| *stake) {
+            Some((&version, &stake)) if stake > threshold => version,
+            _ => protocol_version,
+        };
+        ProtocolVersionVoting {
+            versions,
+            total_block_producer_stake,
+            threshold,
+            next_next_epoch_version,
+        }
+    }
+
     fn collect_blocks_info(
         &self,
         last_block_info: &BlockInfo,
@@ -494,23 +553,12 @@ impl EpochManager {
         } = self.get_epoch_info_aggregator_upto_last(last_block_hash)?;
         let mut proposals = vec![];
 
-        let total_block_producer_stake: u128 = epoch_info
-            .block_producers_settlement()
-            .iter()
-            .copied()
-            .collect::<HashSet<_>>()
-            .iter()
-            .map(|&id| epoch_info.validator_stake(id))
-            .sum();
-
-        // Next protocol version calculation.
-        // Implements https://github.com/near/NEPs/blob/master/specs/ChainSpec/Upgradability.md
-        let mut versions = HashMap::new();
-        for (validator_id, version) in &version_tracker {
-            let (validator_id, version) = (*validator_id, *version);
-            let stake = epoch_info.validator_stake(validator_id);
-            *versions.entry(version).or_insert(0) += stake;
-        }
+        let ProtocolVersionVoting {
+            versions,
+            total_block_producer_stake,
+            next_next_epoch_version,
+            ..
+        } = self.protocol_version_voting(&epoch_info, &next_epoch_info, &version_tracker);
         PROTOCOL_VERSION_VOTES.reset();
         for (version, stake) in &versions {
             let stake_percent = 100 * stake / total_block_producer_stake;
@@ -519,22 +567,6 @@ impl EpochManager {
             tracing::info!(target: "epoch_manager", ?version, ?stake_percent, "Protocol version voting.");
         }
 
-        let protocol_version = next_epoch_info.protocol_version();
-
-        let config = self.config.for_protocol_version(protocol_version);
-        // Note: non-deterministic iteration is fine here, there can be only one
-        // version with large enough stake.
-        let next_next_epoch_version = if let Some((version, stake)) =
-            versions.into_iter().max_by_key(|&(_version, stake)| stake)
-        {
-            let numer = *config.protocol_upgrade_stake_threshold.numer() as u128;
-            let denom = *config.protocol_upgrade_stake_threshold.denom() as u128;
-            let threshold = total_block_producer_stake * numer / denom;
-            if stake > threshold { version } else { protocol_version }
-        } else {
-            protocol_version
-        };
-
         PROTOCOL_VERSION_NEXT.set(next_next_epoch_version as i64);
         tracing::info!(target: "epoch_manager", ?next_next_epoch_version, "Protocol version voting.");
 
@@ -1016,6 +1048,62 @@ impl EpochManager {
         Ok((stake_info, validator_reward))
     }
 
+    /// Returns protocol version votes cast in the epoch of given block up to
+    /// and including that block.
+    /// WARNING: this function calls EpochManager::get_epoch_info_aggregator_upto_last
+    /// underneath which can be very expensive.
+    pub fn get_protocol_version_votes(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, EpochError> {
+        let epoch_id = self.get_epoch_id(block_hash)?;
+        let epoch_info = self.get_epoch_info(&epoch_id)?;
+        let next_epoch_info = self.get_epoch_info(&self.get_next_epoch_id(block_hash)?)?;
+        let version_tracker = self.get_epoch_info_aggregator_upto_last(block_hash)?.version_tracker;
+        let voting = self.protocol_version_voting(&epoch_info, &next_epoch_info, &version_tracker);
+
+        let mut num_validators = HashMap::<ProtocolVersion, u64>::new();
+        for version in version_tracker.values() {
+            *num_validators.entry(*version).or_default() += 1;
+        }
+        let versions = voting
+            .versions
+            .iter()
+            .map(|(&protocol_version, &stake)| ProtocolVersionVoteView {
+                protocol_version,
+                stake,
+                num_validators: num_validators[&protocol_version],
+            })
+            .sorted_by(|a, b| b.protocol_version.cmp(&a.protocol_version))
+            .collect();
+        let validators = epoch_info
+            .block_producers_settlement()
+            .iter()
+            .copied()
+            .unique()
+            .map(|id| {
+                let validator = epoch_info.get_validator(id);
+                ValidatorProtocolVersionVoteView {
+                    account_id: validator.account_id().clone(),
+                    stake: validator.stake(),
+                    protocol_version: version_tracker.get(&id).copied(),
+                }
+            })
+            .sorted_by(|a, b| b.stake.cmp(&a.stake).then_with(|| a.account_id.cmp(&b.account_id)))
+            .collect();
+        Ok(ProtocolVersionVotesView {
+            epoch_id,
+            epoch_height: epoch_info.epoch_height(),
+            protocol_version: epoch_info.protocol_version(),
+            next_epoch_protocol_version: next_epoch_info.protocol_version(),
+            projected_protocol_version: voting.next_next_epoch_version,
+            total_stake: voting.total_block_producer_stake,
+            threshold_stake: voting.threshold,
+            versions,
+            validators,
+        })
+    }
+
     /// Get validators for current epoch and next epoch.
     /// WARNING: this function calls EpochManager::get_epoch_info_aggregator_upto_last
     /// underneath which can be very expensive.
@@ -1096,6 +1184,7 @@ impl EpochManager {
                             num_expected_endorsements_per_shard: vec![0; shards_endorsed.len()],
                             shards_produced,
                             shards_endorsed,
+                            delegation: None,
                         })
                     })
                     .collect::<Result<Vec<CurrentEpochValidatorInfo>, EpochError>>()?;
@@ -1204,6 +1293,7 @@ impl EpochManager {
                                 .collect(),
                             shards_produced,
                             shards_endorsed,
+                            delegation: None,
                         })
                     })
                     .collect::<Result<Vec<CurrentEpochValidatorInfo>, EpochError>>()?;
@@ -1479,6 +1569,54 @@ impl EpochManager {
                     &self.epoch_info_aggregator,
                 )?;
             }
+            if let Err(err) = self.update_validator_participation_metrics() {
+                tracing::warn!(target: "epoch_manager", ?err, "Failed to update validator participation metrics");
+            }
+        }
+        Ok(())
+    }
+
+    /// Exports the endorsement participation of each validator in the current epoch,
+    /// based on `self.epoch_info_aggregator`, together with whether the validator would
+    /// be kicked out if the epoch ended now. This gives validators an early warning,
+    /// rather than learning about the kickout from `EpochValidatorInfo` afterwards.
+    /// Kickout exemptions, which depend on the stats of all validators at the end of
+    /// the epoch, are not taken into account.
+    fn update_validator_participation_metrics(&self) -> Result<(), EpochError> {
+        let aggregator = &self.epoch_info_aggregator;
+        let epoch_info = self.get_epoch_info(&aggregator.epoch_id)?;
+        let config = self.config.for_protocol_version(epoch_info.protocol_version());
+        let validator_chunk_stats = aggregator.validator_chunk_stats();
+
+        VALIDATOR_ENDORSEMENTS_EXPECTED.reset();
+        VALIDATOR_ENDORSEMENTS_PRODUCED.reset();
+        VALIDATOR_KICKOUT_RISK.reset();
+        for (validator_id, validator) in epoch_info.validators_iter().enumerate() {
+            let validator_id = validator_id as ValidatorId;
+            let account_id = validator.account_id().as_str();
+            let block_stats = aggregator
+                .block_tracker
+                .get(&validator_id)
+                .cloned()
+                .unwrap_or(ValidatorStats { expected: 0, produced: 0 });
+            let chunk_stats = validator_chunk_stats.get(&validator_id).cloned().unwrap_or_default();
+            let endorsement_stats = chunk_stats.endorsement_stats();
+
+            let chunk_validator_only = block_stats.expected == 0 && chunk_stats.expected() == 0;
+            let at_risk = block_stats.less_than(config.block_producer_kickout_threshold)
+                || chunk_stats
+                    .production_stats()
+                    .less_than(config.chunk_producer_kickout_threshold)
+                || (chunk_validator_only
+                    && endorsement_stats.less_than(config.chunk_validator_only_kickout_threshold));
+
+            VALIDATOR_ENDORSEMENTS_EXPECTED
+                .with_label_values(&[account_id])
+                .set(endorsement_stats.expected as i64);
+            VALIDATOR_ENDORSEMENTS_PRODUCED
+                .with_label_values(&[account_id])
+                .set(endorsement_stats.produced as i64);
+            VALIDATOR_KICKOUT_RISK.with_label_values(&[account_id]).set(at_risk as i64);
         }
         Ok(())
     }
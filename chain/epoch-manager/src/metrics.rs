@@ -14,3 +14,30 @@ pub(crate) static PROTOCOL_VERSION_NEXT: LazyLock<IntGauge> = LazyLock::new(|| {
     try_create_int_gauge("near_protocol_version_next", "The protocol version for the next epoch.")
         .unwrap()
 });
+
+pub(crate) static VALIDATOR_ENDORSEMENTS_EXPECTED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_epoch_validator_endorsements_expected",
+        "Number of chunk endorsements expected from a validator so far in the current epoch, up to the last final block",
+        &["account_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static VALIDATOR_ENDORSEMENTS_PRODUCED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_epoch_validator_endorsements_produced",
+        "Number of chunk endorsements produced by a validator so far in the current epoch, up to the last final block",
+        &["account_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static VALIDATOR_KICKOUT_RISK: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_epoch_validator_kickout_risk",
+        "Set to 1 if the validator would be kicked out for low participation if the current epoch ended now",
+        &["account_id"],
+    )
+    .unwrap()
+});
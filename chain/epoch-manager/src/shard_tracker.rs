@@ -6,7 +6,8 @@ use near_chain_primitives::Error;
 use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::sharding::StateSyncInfo;
-use near_primitives::types::{AccountId, EpochId, ShardId};
+use near_primitives::types::{AccountId, EpochHeight, EpochId, ShardId};
+use parking_lot::RwLock;
 use std::sync::Arc;
 
 // bit mask for which shard to track
@@ -27,8 +28,13 @@ pub enum EpochSelection {
 /// For supported configurations, see the `TrackedShardsConfig` documentation.
 #[derive(Clone)]
 pub struct ShardTracker {
-    tracked_shards_config: TrackedShardsConfig,
+    /// The configs in effect from the given epoch heights, in increasing order
+    /// of the heights. The first one, from the node config, is in effect from
+    /// the genesis. The others are reloaded while the node is running, see
+    /// `update_tracked_shards_config`.
+    tracked_shards_configs: Arc<RwLock<Vec<(EpochHeight, Arc<TrackedShardsConfig>)>>>,
     /// Stores shard tracking information by epoch, only useful if TrackedState == Accounts
+    /// or FollowAccounts
     tracking_shards_cache: Arc<SyncLruCache<EpochId, BitMask>>,
     epoch_manager: Arc<dyn EpochManagerAdapter>,
 }
@@ -39,7 +45,10 @@ impl ShardTracker {
         epoch_manager: Arc<dyn EpochManagerAdapter>,
     ) -> Self {
         ShardTracker {
-            tracked_shards_config,
+            tracked_shards_configs: Arc::new(RwLock::new(vec![(
+                0,
+                Arc::new(tracked_shards_config),
+            )])),
             // 1024 epochs on mainnet is about 512 days which is more than enough,
             // and this is a cache anyway. The data size is pretty small as well,
             // only one bit per shard per epoch.
@@ -52,15 +61,67 @@ impl ShardTracker {
         Self::new(TrackedShardsConfig::NoShards, epoch_manager)
     }
 
+    /// Reloads the tracked shards config while the node is running. The new
+    /// config takes effect from the epoch after the next one relative to
+    /// `head_epoch_id`: the shards it adds are state synced at the next epoch
+    /// boundary, like the shards of a new validator assignment, and the shards
+    /// it removes are tracked until then. A config which isn't in effect yet
+    /// is replaced by the new one.
+    /// Returns whether the config changed.
+    pub fn update_tracked_shards_config(
+        &self,
+        tracked_shards_config: TrackedShardsConfig,
+        head_epoch_id: &EpochId,
+    ) -> Result<bool, EpochError> {
+        let head_epoch_height = self.epoch_manager.get_epoch_info(head_epoch_id)?.epoch_height();
+        let effective_epoch_height = head_epoch_height + 2;
+        let mut configs = self.tracked_shards_configs.write();
+        let (_, latest_config) = configs.last().cloned().expect("there is always a config");
+        configs.retain(|&(epoch_height, _)| epoch_height < effective_epoch_height);
+        if configs.last().is_none_or(|(_, config)| **config != tracked_shards_config) {
+            configs.push((effective_epoch_height, Arc::new(tracked_shards_config.clone())));
+        }
+        self.tracking_shards_cache.lock().clear();
+        let is_updated = *latest_config != tracked_shards_config;
+        if is_updated {
+            tracing::info!(target: "chain", ?tracked_shards_config, effective_epoch_height, "Reloaded the tracked shards config");
+        }
+        Ok(is_updated)
+    }
+
+    /// The config in effect in the epoch `epoch_id`.
+    fn config_at_epoch(&self, epoch_id: &EpochId) -> Result<Arc<TrackedShardsConfig>, EpochError> {
+        if let Some(config) = self.single_config() {
+            return Ok(config);
+        }
+        let epoch_height = self.epoch_manager.get_epoch_info(epoch_id)?.epoch_height();
+        let configs = self.tracked_shards_configs.read();
+        let (_, config) = configs
+            .iter()
+            .rev()
+            .find(|&&(effective_epoch_height, _)| effective_epoch_height <= epoch_height)
+            .unwrap_or(&configs[0]);
+        Ok(config.clone())
+    }
+
+    /// The config in effect in every epoch, if it was never reloaded.
+    fn single_config(&self) -> Option<Arc<TrackedShardsConfig>> {
+        match self.tracked_shards_configs.read().as_slice() {
+            [(_, config)] => Some(config.clone()),
+            _ => None,
+        }
+    }
+
     fn tracks_shard_at_epoch(
         &self,
         shard_id: ShardId,
         epoch_id: &EpochId,
     ) -> Result<bool, EpochError> {
-        match &self.tracked_shards_config {
+        match self.config_at_epoch(epoch_id)?.as_ref() {
             TrackedShardsConfig::NoShards => Ok(false),
             TrackedShardsConfig::AllShards => Ok(true),
-            TrackedShardsConfig::Accounts(tracked_accounts) => {
+            TrackedShardsConfig::Accounts(tracked_accounts)
+            | TrackedShardsConfig::FollowAccounts(tracked_accounts) => {
                 let shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
                 let tracking_mask = self.tracking_shards_cache.get_or_try_put(
                     *epoch_id,
@@ -103,6 +164,23 @@ impl ShardTracker {
         prev_hash: &CryptoHash,
     ) -> Result<bool, EpochError> {
         let epoch_id = self.epoch_manager.get_next_epoch_id_from_prev_block(prev_hash)?;
+        if let TrackedShardsConfig::FollowAccounts(tracked_accounts) =
+            self.config_at_epoch(&epoch_id)?.as_ref()
+        {
+            let shard_layout = self.epoch_manager.get_shard_layout_from_prev_block(prev_hash)?;
+            let next_shard_layout = self.epoch_manager.get_shard_layout(&epoch_id)?;
+            if next_shard_layout != shard_layout {
+                // `shard_id` is a shard of the current layout, so it's tracked
+                // if one of the accounts will be in one of its children.
+                for account_id in tracked_accounts {
+                    let next_shard_id = next_shard_layout.account_id_to_shard_id(account_id);
+                    if next_shard_layout.get_parent_shard_id(next_shard_id)? == shard_id {
+                        return Ok(true);
+                    }
+                }
+                return Ok(false);
+            }
+        }
         self.tracks_shard_at_epoch(shard_id, &epoch_id)
     }
 
@@ -112,6 +190,16 @@ impl ShardTracker {
         prev_hash: &CryptoHash,
     ) -> Result<bool, EpochError> {
         let epoch_id = self.epoch_manager.get_prev_epoch_id_from_prev_block(prev_hash)?;
+        if let TrackedShardsConfig::FollowAccounts(_) = self.config_at_epoch(&epoch_id)?.as_ref() {
+            let shard_layout = self.epoch_manager.get_shard_layout_from_prev_block(prev_hash)?;
+            let prev_shard_layout = self.epoch_manager.get_shard_layout(&epoch_id)?;
+            if prev_shard_layout != shard_layout {
+                // `shard_id` is a shard of the current layout, which was
+                // tracked through its parent.
+                let parent_shard_id = shard_layout.get_parent_shard_id(shard_id)?;
+                return self.tracks_shard_at_epoch(parent_shard_id, &epoch_id);
+            }
+        }
         self.tracks_shard_at_epoch(shard_id, &epoch_id)
     }
 
@@ -160,12 +248,12 @@ impl ShardTracker {
             }
         }
 
-        match self.tracked_shards_config {
-            TrackedShardsConfig::NoShards => {
+        match self.single_config().as_deref() {
+            Some(TrackedShardsConfig::NoShards) => {
                 // Avoid looking up EpochId as a performance optimization.
                 false
             }
-            TrackedShardsConfig::AllShards => {
+            Some(TrackedShardsConfig::AllShards) => {
                 // Avoid looking up EpochId as a performance optimization.
                 true
             }
@@ -264,9 +352,10 @@ impl ShardTracker {
             || self.will_care_about_shard(account_id, parent_hash, shard_id, is_me)
     }
 
-    /// Returns whether the node is configured for all shards tracking.
+    /// Returns whether the node is configured for all shards tracking, in
+    /// every epoch since it started.
     pub fn tracks_all_shards(&self) -> bool {
-        self.tracked_shards_config.tracks_all_shards()
+        self.tracked_shards_configs.read().iter().all(|(_, config)| config.tracks_all_shards())
     }
 
     /// Return all shards that whose states need to be caught up
@@ -353,13 +442,15 @@ mod tests {
     use near_chain_configs::test_genesis::TestEpochConfigBuilder;
     use near_crypto::{KeyType, PublicKey};
     use near_primitives::epoch_block_info::BlockInfo;
+    use near_primitives::epoch_manager::EpochConfig;
     use near_primitives::epoch_manager::EpochConfigStore;
     use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardLayout;
     use near_primitives::types::validator_stake::ValidatorStake;
     use near_primitives::types::{AccountInfo, BlockHeight, EpochId, ProtocolVersion, ShardId};
     use near_primitives::version::PROTOCOL_VERSION;
     use near_store::test_utils::create_test_store;
-    use std::collections::HashSet;
+    use std::collections::{BTreeMap, HashSet};
     use std::sync::Arc;
 
     const DEFAULT_TOTAL_SUPPLY: u128 = 1_000_000_000_000;
@@ -519,4 +610,156 @@ mod tests {
         assert_eq!(get_all_shards_will_care_about(&tracker, &shard_ids, &h[6]), subset2);
         assert_eq!(get_all_shards_will_care_about(&tracker, &shard_ids, &h[7]), subset3);
     }
+
+    #[test]
+    fn test_update_tracked_shards_config() {
+        let shard_ids = (0..4).map(ShardId::new).collect_vec();
+        let mut genesis_config = GenesisConfig::default();
+        genesis_config.protocol_version = PROTOCOL_VERSION;
+        genesis_config.validators = vec![AccountInfo {
+            account_id: "test".parse().unwrap(),
+            public_key: PublicKey::empty(KeyType::ED25519),
+            amount: 100,
+        }];
+        let epoch_config = TestEpochConfigBuilder::new().epoch_length(2).build();
+        let config_store = EpochConfigStore::test_single_version(PROTOCOL_VERSION, epoch_config);
+        let epoch_manager = EpochManager::new_arc_handle_from_epoch_config_store(
+            create_test_store(),
+            &genesis_config,
+            config_store,
+        );
+        let tracker = ShardTracker::new(TrackedShardsConfig::NoShards, epoch_manager.clone());
+
+        let h = hash_range(12);
+        {
+            let mut epoch_manager = epoch_manager.write();
+            for i in 0..12 {
+                record_block(
+                    &mut epoch_manager,
+                    if i > 0 { h[i - 1] } else { CryptoHash::default() },
+                    h[i],
+                    i as u64,
+                    vec![],
+                    PROTOCOL_VERSION,
+                );
+            }
+        }
+        let epoch_height =
+            |epoch_id: &EpochId| epoch_manager.get_epoch_info(epoch_id).unwrap().epoch_height();
+
+        // The config is reloaded while the head is h[2].
+        let head_epoch_id = epoch_manager.get_epoch_id(&h[2]).unwrap();
+        let head_epoch_height = epoch_height(&head_epoch_id);
+        assert!(
+            tracker
+                .update_tracked_shards_config(TrackedShardsConfig::AllShards, &head_epoch_id)
+                .unwrap()
+        );
+        assert!(
+            !tracker
+                .update_tracked_shards_config(TrackedShardsConfig::AllShards, &head_epoch_id)
+                .unwrap()
+        );
+        assert!(!tracker.tracks_all_shards());
+
+        // The shards are tracked from the epoch after the next one, and state
+        // synced from the first block of the next one.
+        let all_shards: HashSet<ShardId> = shard_ids.iter().copied().collect();
+        let mut num_tracking_epochs = 0;
+        for i in 3..12 {
+            let epoch_id = epoch_manager.get_epoch_id_from_prev_block(&h[i - 1]).unwrap();
+            let tracks = epoch_height(&epoch_id) >= head_epoch_height + 2;
+            let expected = if tracks { all_shards.clone() } else { HashSet::new() };
+            assert_eq!(get_all_shards_care_about(&tracker, &shard_ids, &h[i - 1]), expected);
+            let state_sync_info = tracker.get_state_sync_info(&None, &h[i], &h[i - 1]).unwrap();
+            assert_eq!(
+                state_sync_info.is_some(),
+                epoch_height(&epoch_id) == head_epoch_height + 1,
+                "block {i}"
+            );
+            num_tracking_epochs += tracks as usize;
+        }
+        assert!(num_tracking_epochs > 0);
+    }
+
+    #[test]
+    fn test_follow_accounts_across_resharding() {
+        let shard_layout = ShardLayout::multi_shard_custom(vec!["test2".parse().unwrap()], 3);
+        let next_shard_layout =
+            ShardLayout::derive_shard_layout(&shard_layout, "test5".parse().unwrap());
+        let mut genesis_config = GenesisConfig::default();
+        genesis_config.protocol_version = PROTOCOL_VERSION - 1;
+        genesis_config.validators = vec![AccountInfo {
+            account_id: "test".parse().unwrap(),
+            public_key: PublicKey::empty(KeyType::ED25519),
+            amount: 100,
+        }];
+        let epoch_config = TestEpochConfigBuilder::new()
+            .epoch_length(2)
+            .shard_layout(shard_layout.clone())
+            .build();
+        let next_epoch_config =
+            EpochConfig { shard_layout: next_shard_layout.clone(), ..epoch_config.clone() };
+        let config_store = EpochConfigStore::test(BTreeMap::from([
+            (PROTOCOL_VERSION - 1, Arc::new(epoch_config)),
+            (PROTOCOL_VERSION, Arc::new(next_epoch_config)),
+        ]));
+        let epoch_manager = EpochManager::new_arc_handle_from_epoch_config_store(
+            create_test_store(),
+            &genesis_config,
+            config_store,
+        );
+
+        let h = hash_range(20);
+        {
+            let mut epoch_manager = epoch_manager.write();
+            for i in 0..h.len() {
+                record_block(
+                    &mut epoch_manager,
+                    if i > 0 { h[i - 1] } else { CryptoHash::default() },
+                    h[i],
+                    i as u64,
+                    vec![],
+                    PROTOCOL_VERSION,
+                );
+            }
+        }
+        // The last block before the shard layout change and the first one after.
+        let resharding_block = (0..h.len())
+            .find(|&i| {
+                let next_epoch_id = epoch_manager.get_next_epoch_id_from_prev_block(&h[i]).unwrap();
+                epoch_manager.get_shard_layout(&next_epoch_id).unwrap() != shard_layout
+            })
+            .expect("the shard layout must change");
+        let resharded_block = (0..h.len())
+            .find(|&i| {
+                epoch_manager.get_shard_layout_from_prev_block(&h[i]).unwrap() != shard_layout
+            })
+            .expect("the shard layout must change");
+
+        let account = "test6".parse().unwrap();
+        let parent_shard_id = shard_layout.account_id_to_shard_id(&account);
+        let child_shard_id = next_shard_layout.account_id_to_shard_id(&account);
+        assert_ne!(parent_shard_id, child_shard_id);
+        let follower = ShardTracker::new(
+            TrackedShardsConfig::FollowAccounts(vec![account.clone()]),
+            epoch_manager.clone(),
+        );
+        let tracker =
+            ShardTracker::new(TrackedShardsConfig::Accounts(vec![account]), epoch_manager);
+
+        // The parent is tracked for the next epoch, so that the child is
+        // built from it rather than state synced.
+        let parent_hash = &h[resharding_block];
+        assert!(follower.cares_about_shard(None, parent_hash, parent_shard_id, true));
+        assert!(follower.will_care_about_shard(None, parent_hash, parent_shard_id, true));
+        assert!(!tracker.will_care_about_shard(None, parent_hash, parent_shard_id, true));
+        assert_eq!(follower.get_state_sync_info(&None, parent_hash, parent_hash).unwrap(), None);
+
+        // After the resharding, the child was tracked through its parent.
+        let parent_hash = &h[resharded_block];
+        assert!(follower.cares_about_shard(None, parent_hash, child_shard_id, true));
+        assert!(follower.cared_about_shard_in_prev_epoch(None, parent_hash, child_shard_id, true));
+        assert!(!tracker.cared_about_shard_in_prev_epoch(None, parent_hash, child_shard_id, true));
+    }
 }
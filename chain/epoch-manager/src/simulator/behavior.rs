@@ -0,0 +1,92 @@
+//! Behaviors of the simulated validators. Adversary strategies are
+//! implemented by overriding the default, honest, decisions.
+
+use super::{BlockIndex, SimApproval, ValidatorIndex};
+use near_primitives::types::BlockHeight;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Decisions of a simulated validator. All methods default to the behavior
+/// of an honest validator.
+pub trait ValidatorBehavior {
+    /// Called when the validator has enough approvals to produce the block at
+    /// `height` on top of `parent`. Returns the delay after which the block is
+    /// produced, or `None` if the block isn't produced at all.
+    fn produce_block(
+        &mut self,
+        _height: BlockHeight,
+        _parent: BlockIndex,
+        _now: Duration,
+    ) -> Option<Duration> {
+        Some(Duration::ZERO)
+    }
+
+    /// Returns whether the approval is sent to the block producer of its
+    /// target height.
+    fn send_approval(&mut self, _approval: &SimApproval, _now: Duration) -> bool {
+        true
+    }
+
+    /// Returns the additional delay of sending the block produced by the
+    /// validator to another validator, or `None` if it isn't sent at all.
+    fn broadcast_delay(&mut self, _height: BlockHeight, _to: ValidatorIndex) -> Option<Duration> {
+        Some(Duration::ZERO)
+    }
+
+    /// Returns whether a block produced by another validator is forwarded to
+    /// the other validators once the validator accepts it.
+    fn forward_block(&mut self, _height: BlockHeight) -> bool {
+        true
+    }
+}
+
+/// Follows the protocol.
+pub struct Honest;
+
+impl ValidatorBehavior for Honest {}
+
+/// Neither produces blocks nor sends approvals.
+pub struct Offline;
+
+impl ValidatorBehavior for Offline {
+    fn produce_block(&mut self, _: BlockHeight, _: BlockIndex, _: Duration) -> Option<Duration> {
+        None
+    }
+
+    fn send_approval(&mut self, _: &SimApproval, _: Duration) -> bool {
+        false
+    }
+
+    fn forward_block(&mut self, _: BlockHeight) -> bool {
+        false
+    }
+}
+
+/// Produces blocks but never approves the blocks of others.
+pub struct WithholdApprovals;
+
+impl ValidatorBehavior for WithholdApprovals {
+    fn send_approval(&mut self, _: &SimApproval, _: Duration) -> bool {
+        false
+    }
+}
+
+/// Produces its blocks late, e.g. because of a slow machine, or to make the
+/// next block producer skip its height.
+pub struct DelayedProduction(pub Duration);
+
+impl ValidatorBehavior for DelayedProduction {
+    fn produce_block(&mut self, _: BlockHeight, _: BlockIndex, _: Duration) -> Option<Duration> {
+        Some(self.0)
+    }
+}
+
+/// Sends its blocks only to the given validators. The other validators only
+/// get the blocks once the given ones forward them.
+pub struct SelectiveBroadcast(pub HashSet<ValidatorIndex>);
+
+impl ValidatorBehavior for SelectiveBroadcast {
+    fn broadcast_delay(&mut self, _: BlockHeight, to: ValidatorIndex) -> Option<Duration> {
+        self.0.contains(&to).then_some(Duration::ZERO)
+    }
+}
@@ -0,0 +1,746 @@
+//! Deterministic simulator of block production and doomslug approvals, for
+//! experiments with consensus parameters and fork choice.
+//!
+//! The simulator models the parts of consensus which determine the shape of
+//! the chain: block producers sampled by stake for every height, the
+//! endorsement and skip timers of doomslug, the 2/3 approval threshold,
+//! doomslug and BFT finality, message delays and the forwarding of blocks
+//! between validators. It doesn't use actors, the
+//! store or signatures, so that thousands of heights are simulated within
+//! milliseconds. Validators follow the protocol unless they are given a
+//! different `ValidatorBehavior`.
+//!
+//! Runs are deterministic given the seed, so `run_simulations` over a range of
+//! seeds yields statistics which can be compared between parameters.
+
+pub mod behavior;
+
+use crate::simulator::behavior::ValidatorBehavior;
+use near_primitives::hash::hash;
+use near_primitives::rand::WeightedIndex;
+use near_primitives::types::{Balance, BlockHeight, BlockHeightDelta};
+use rand::{Rng, SeedableRng};
+use rand_hc::Hc128Rng;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::time::Duration;
+
+/// Have that many iterations in the timer, like doomslug.
+const MAX_TIMER_ITERS: usize = 20;
+
+pub type ValidatorIndex = usize;
+/// Index of a block in `Simulator::blocks`, the genesis block is 0.
+pub type BlockIndex = usize;
+
+/// Rule used by the validators to choose their tip among the known blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForkChoice {
+    /// The block with the largest height, which is what nearcore does.
+    HighestHeight,
+    /// The block with the largest BFT final height, then the largest height.
+    HighestFinalHeight,
+}
+
+#[derive(Clone, Debug)]
+pub struct SimulatorConfig {
+    pub seed: u64,
+    /// The simulation stops once a block at this height is produced.
+    pub target_height: BlockHeight,
+    /// The simulation stops after this much simulated time.
+    pub max_time: Duration,
+    /// Delay between endorsements, `min_block_production_delay` in nearcore.
+    pub endorsement_delay: Duration,
+    /// Delay before the first skip, `max_block_production_delay` in nearcore.
+    pub min_skip_delay: Duration,
+    /// Increase of the skip delay for every height without a final block.
+    pub skip_delay_step: Duration,
+    /// Maximum delay before a skip, `max_block_wait_delay` in nearcore.
+    pub max_skip_delay: Duration,
+    /// Messages are delivered after a delay sampled uniformly from this range.
+    pub min_network_delay: Duration,
+    pub max_network_delay: Duration,
+    pub fork_choice: ForkChoice,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            target_height: 1000,
+            max_time: Duration::from_secs(24 * 60 * 60),
+            endorsement_delay: Duration::from_millis(600),
+            min_skip_delay: Duration::from_millis(2000),
+            skip_delay_step: Duration::from_millis(200),
+            max_skip_delay: Duration::from_millis(6000),
+            min_network_delay: Duration::from_millis(50),
+            max_network_delay: Duration::from_millis(300),
+            fork_choice: ForkChoice::HighestHeight,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SimApprovalInner {
+    /// Approval of the block as the parent of the next height.
+    Endorsement(BlockIndex),
+    /// Approval of skipping the heights after the given one.
+    Skip(BlockHeight),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SimApproval {
+    pub from: ValidatorIndex,
+    pub inner: SimApprovalInner,
+    pub target_height: BlockHeight,
+}
+
+#[derive(Clone, Debug)]
+pub struct SimBlock {
+    pub height: BlockHeight,
+    pub parent: BlockIndex,
+    pub producer: Option<ValidatorIndex>,
+    pub produced_at: Duration,
+    pub last_ds_final_block: BlockIndex,
+    pub last_final_block: BlockIndex,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    Block { to: ValidatorIndex, block: BlockIndex },
+    Approval { to: ValidatorIndex, approval: SimApproval },
+    Timer { validator: ValidatorIndex },
+    Produce { validator: ValidatorIndex, height: BlockHeight, parent: BlockIndex },
+}
+
+#[derive(Default)]
+struct ApprovalTracker {
+    approvers: HashSet<ValidatorIndex>,
+    stake: Balance,
+}
+
+struct ValidatorState {
+    stake: Balance,
+    behavior: Box<dyn ValidatorBehavior>,
+    known_blocks: HashSet<BlockIndex>,
+    /// Blocks waiting for their parent, by the parent.
+    orphans: HashMap<BlockIndex, Vec<BlockIndex>>,
+    tip: BlockIndex,
+    largest_target_height: BlockHeight,
+    timer_started: u64,
+    timer_height: BlockHeight,
+    last_endorsement_sent: u64,
+    endorsement_pending: bool,
+    scheduled_timer: Option<u64>,
+    approvals: BTreeMap<(BlockHeight, SimApprovalInner), ApprovalTracker>,
+    produced_heights: HashSet<BlockHeight>,
+}
+
+pub struct Simulator {
+    config: SimulatorConfig,
+    rng: Hc128Rng,
+    producer_sampler: WeightedIndex,
+    total_stake: Balance,
+    validators: Vec<ValidatorState>,
+    blocks: Vec<SimBlock>,
+    /// Simulated time in milliseconds.
+    now: u64,
+    next_event_id: u64,
+    events: BinaryHeap<Reverse<(u64, u64, Event)>>,
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+impl Simulator {
+    /// Creates the simulator of validators with the given stakes and
+    /// behaviors, starting from the genesis block.
+    pub fn new(
+        config: SimulatorConfig,
+        validators: Vec<(Balance, Box<dyn ValidatorBehavior>)>,
+    ) -> Self {
+        assert!(!validators.is_empty());
+        let rng = Hc128Rng::seed_from_u64(config.seed);
+        let producer_sampler =
+            WeightedIndex::new(validators.iter().map(|(stake, _)| *stake).collect());
+        let total_stake = validators.iter().map(|(stake, _)| stake).sum();
+        let genesis = SimBlock {
+            height: 0,
+            parent: 0,
+            producer: None,
+            produced_at: Duration::ZERO,
+            last_ds_final_block: 0,
+            last_final_block: 0,
+        };
+        let validators = validators
+            .into_iter()
+            .map(|(stake, behavior)| ValidatorState {
+                stake,
+                behavior,
+                known_blocks: HashSet::from([0]),
+                orphans: HashMap::new(),
+                tip: 0,
+                largest_target_height: 0,
+                timer_started: 0,
+                timer_height: 1,
+                last_endorsement_sent: 0,
+                endorsement_pending: true,
+                scheduled_timer: None,
+                approvals: BTreeMap::new(),
+                produced_heights: HashSet::new(),
+            })
+            .collect();
+        Self {
+            config,
+            rng,
+            producer_sampler,
+            total_stake,
+            validators,
+            blocks: vec![genesis],
+            now: 0,
+            next_event_id: 0,
+            events: BinaryHeap::new(),
+        }
+    }
+
+    /// Block producer of the height, sampled by stake like in `EpochInfo`.
+    pub fn block_producer(&self, height: BlockHeight) -> ValidatorIndex {
+        let mut buffer = [0u8; 16];
+        buffer[0..8].copy_from_slice(&self.config.seed.to_le_bytes());
+        buffer[8..16].copy_from_slice(&height.to_le_bytes());
+        self.producer_sampler.sample(hash(&buffer).0)
+    }
+
+    pub fn blocks(&self) -> &[SimBlock] {
+        &self.blocks
+    }
+
+    /// Runs the simulation until a block at the target height is produced or
+    /// the maximum time passes.
+    pub fn run(mut self) -> SimulationStats {
+        for validator in 0..self.validators.len() {
+            self.process_timer(validator);
+        }
+        let max_time = millis(self.config.max_time);
+        while let Some(Reverse((time, _, event))) = self.events.pop() {
+            if time > max_time {
+                break;
+            }
+            self.now = time;
+            match event {
+                Event::Block { to, block } => self.receive_block(to, block),
+                Event::Approval { to, approval } => self.receive_approval(to, approval),
+                Event::Timer { validator } => {
+                    if self.validators[validator].scheduled_timer == Some(time) {
+                        self.validators[validator].scheduled_timer = None;
+                        self.process_timer(validator);
+                    }
+                }
+                Event::Produce { validator, height, parent } => {
+                    self.produce_block(validator, height, parent)
+                }
+            }
+            if self.blocks.iter().any(|block| block.height >= self.config.target_height) {
+                break;
+            }
+        }
+        SimulationStats::new(&self)
+    }
+
+    fn schedule(&mut self, time: u64, event: Event) {
+        self.events.push(Reverse((time, self.next_event_id, event)));
+        self.next_event_id += 1;
+    }
+
+    fn network_delay(&mut self) -> u64 {
+        let min = millis(self.config.min_network_delay);
+        let max = millis(self.config.max_network_delay).max(min);
+        self.rng.gen_range(min..=max)
+    }
+
+    /// Doomslug skip delay given the number of heights since the last final
+    /// block, see `DoomslugTimer::get_delay`.
+    fn skip_delay(&self, heights_since_final: BlockHeightDelta) -> u64 {
+        let step = millis(self.config.skip_delay_step)
+            .saturating_mul(heights_since_final.saturating_sub(2));
+        millis(self.config.max_skip_delay)
+            .min(millis(self.config.min_skip_delay).saturating_add(step))
+    }
+
+    fn is_ancestor(&self, ancestor: BlockIndex, mut block: BlockIndex) -> bool {
+        let height = self.blocks[ancestor].height;
+        while self.blocks[block].height > height {
+            block = self.blocks[block].parent;
+        }
+        block == ancestor
+    }
+
+    fn is_better_tip(&self, block: BlockIndex, tip: BlockIndex) -> bool {
+        let (block, tip) = (&self.blocks[block], &self.blocks[tip]);
+        match self.config.fork_choice {
+            ForkChoice::HighestHeight => block.height > tip.height,
+            ForkChoice::HighestFinalHeight => {
+                let final_height = |block: &SimBlock| self.blocks[block.last_final_block].height;
+                (final_height(block), block.height) > (final_height(tip), tip.height)
+            }
+        }
+    }
+
+    fn receive_block(&mut self, to: ValidatorIndex, block: BlockIndex) {
+        let state = &mut self.validators[to];
+        if state.known_blocks.contains(&block) {
+            return;
+        }
+        let parent = self.blocks[block].parent;
+        if !state.known_blocks.contains(&parent) {
+            state.orphans.entry(parent).or_default().push(block);
+            return;
+        }
+        let mut accepted = vec![block];
+        while let Some(block) = accepted.pop() {
+            let state = &mut self.validators[to];
+            state.known_blocks.insert(block);
+            accepted.extend(state.orphans.remove(&block).unwrap_or_default());
+            let height = self.blocks[block].height;
+            if self.blocks[block].producer != Some(to) && state.behavior.forward_block(height) {
+                self.broadcast_block(to, block);
+            }
+            let state = &self.validators[to];
+            let tip = state.tip;
+            let last_final_block = self.blocks[tip].last_final_block;
+            // Like nearcore, never switch to a chain which doesn't include
+            // the final block.
+            if self.is_better_tip(block, tip) && self.is_ancestor(last_final_block, block) {
+                let state = &mut self.validators[to];
+                state.tip = block;
+                state.timer_started = self.now;
+                state.timer_height = self.blocks[block].height + 1;
+                state.endorsement_pending = true;
+            }
+        }
+        self.process_timer(to);
+    }
+
+    fn receive_approval(&mut self, to: ValidatorIndex, approval: SimApproval) {
+        let stake = self.validators[approval.from].stake;
+        let tracker = self.validators[to]
+            .approvals
+            .entry((approval.target_height, approval.inner))
+            .or_default();
+        if tracker.approvers.insert(approval.from) {
+            tracker.stake += stake;
+        }
+        self.maybe_produce_block(to);
+    }
+
+    /// Sends the endorsements and skips which are due, like
+    /// `Doomslug::process_timer`, and schedules the next timer.
+    fn process_timer(&mut self, validator: ValidatorIndex) {
+        let now = self.now;
+        let mut approvals = vec![];
+        for _ in 0..MAX_TIMER_ITERS {
+            let state = &self.validators[validator];
+            let tip = &self.blocks[state.tip];
+            let largest_final_height = self.blocks[tip.last_ds_final_block].height;
+            let skip_delay =
+                self.skip_delay(state.timer_height.saturating_sub(largest_final_height));
+            let tip_height = tip.height;
+            let tip_index = state.tip;
+            let state = &mut self.validators[validator];
+            if state.endorsement_pending
+                && now >= state.last_endorsement_sent + millis(self.config.endorsement_delay)
+            {
+                if tip_height >= state.largest_target_height {
+                    state.largest_target_height = tip_height + 1;
+                    approvals.push(SimApproval {
+                        from: validator,
+                        inner: SimApprovalInner::Endorsement(tip_index),
+                        target_height: tip_height + 1,
+                    });
+                }
+                state.last_endorsement_sent = now;
+                state.endorsement_pending = false;
+            }
+            if now >= state.timer_started + skip_delay {
+                state.largest_target_height =
+                    state.largest_target_height.max(state.timer_height + 1);
+                approvals.push(SimApproval {
+                    from: validator,
+                    inner: SimApprovalInner::Skip(tip_height),
+                    target_height: state.timer_height + 1,
+                });
+                state.timer_started += skip_delay;
+                state.timer_height += 1;
+            } else {
+                break;
+            }
+        }
+        for approval in approvals {
+            self.send_approval(approval);
+        }
+
+        let state = &self.validators[validator];
+        let largest_final_height = self.blocks[self.blocks[state.tip].last_ds_final_block].height;
+        let mut next_timer = state.timer_started
+            + self.skip_delay(state.timer_height.saturating_sub(largest_final_height));
+        if state.endorsement_pending {
+            next_timer =
+                next_timer.min(state.last_endorsement_sent + millis(self.config.endorsement_delay));
+        }
+        let next_timer = next_timer.max(now);
+        let state = &mut self.validators[validator];
+        if state.scheduled_timer.is_none_or(|scheduled| next_timer < scheduled) {
+            state.scheduled_timer = Some(next_timer);
+            self.schedule(next_timer, Event::Timer { validator });
+        }
+        self.maybe_produce_block(validator);
+    }
+
+    fn send_approval(&mut self, approval: SimApproval) {
+        let from = approval.from;
+        let now = Duration::from_millis(self.now);
+        if !self.validators[from].behavior.send_approval(&approval, now) {
+            return;
+        }
+        let to = self.block_producer(approval.target_height);
+        let delay = if to == from { 0 } else { self.network_delay() };
+        self.schedule(self.now + delay, Event::Approval { to, approval });
+    }
+
+    /// Produces the block at the largest height the validator has enough
+    /// approvals for on top of its tip.
+    fn maybe_produce_block(&mut self, validator: ValidatorIndex) {
+        let state = &self.validators[validator];
+        let tip = state.tip;
+        let tip_height = self.blocks[tip].height;
+        let ready_height = state
+            .approvals
+            .range((tip_height + 1, SimApprovalInner::Endorsement(0))..)
+            .filter(|((height, inner), tracker)| {
+                let expected_inner = if *height == tip_height + 1 {
+                    SimApprovalInner::Endorsement(tip)
+                } else {
+                    SimApprovalInner::Skip(tip_height)
+                };
+                *inner == expected_inner
+                    && tracker.stake * 3 > self.total_stake * 2
+                    && !state.produced_heights.contains(height)
+                    && self.block_producer(*height) == validator
+            })
+            .map(|((height, _), _)| *height)
+            .max();
+        let Some(height) = ready_height else {
+            return;
+        };
+        let state = &mut self.validators[validator];
+        state.produced_heights.insert(height);
+        let now = Duration::from_millis(self.now);
+        if let Some(delay) = state.behavior.produce_block(height, tip, now) {
+            self.schedule(
+                self.now + millis(delay),
+                Event::Produce { validator, height, parent: tip },
+            );
+        }
+    }
+
+    fn produce_block(
+        &mut self,
+        validator: ValidatorIndex,
+        height: BlockHeight,
+        parent: BlockIndex,
+    ) {
+        let prev = &self.blocks[parent];
+        let (last_ds_final_block, last_final_block) = if prev.height + 1 == height {
+            (parent, prev.last_ds_final_block)
+        } else {
+            (prev.last_ds_final_block, prev.last_final_block)
+        };
+        let block = self.blocks.len();
+        self.blocks.push(SimBlock {
+            height,
+            parent,
+            producer: Some(validator),
+            produced_at: Duration::from_millis(self.now),
+            last_ds_final_block,
+            last_final_block,
+        });
+        self.schedule(self.now, Event::Block { to: validator, block });
+        for to in 0..self.validators.len() {
+            if to == validator {
+                continue;
+            }
+            let Some(extra_delay) = self.validators[validator].behavior.broadcast_delay(height, to)
+            else {
+                continue;
+            };
+            let delay = self.network_delay() + millis(extra_delay);
+            self.schedule(self.now + delay, Event::Block { to, block });
+        }
+    }
+
+    fn broadcast_block(&mut self, from: ValidatorIndex, block: BlockIndex) {
+        for to in 0..self.validators.len() {
+            if to != from {
+                let delay = self.network_delay();
+                self.schedule(self.now + delay, Event::Block { to, block });
+            }
+        }
+    }
+}
+
+/// Outcome of a simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationStats {
+    /// Simulated time until the end of the simulation.
+    pub elapsed: Duration,
+    /// Height of the highest block on the canonical chain.
+    pub head_height: BlockHeight,
+    /// Height of the last BFT final block on the canonical chain.
+    pub final_height: BlockHeight,
+    pub blocks_produced: u64,
+    /// Blocks produced but not on the canonical chain.
+    pub orphaned_blocks: u64,
+    /// Heights up to the head without a block on the canonical chain.
+    pub skipped_heights: u64,
+    /// Average time between the blocks of the canonical chain.
+    pub mean_block_time: Duration,
+    /// Average and maximum time between the production of a canonical block
+    /// and the production of the block which made it BFT final.
+    pub mean_finality_lag: Duration,
+    pub max_finality_lag: Duration,
+    /// Number of pairs of conflicting final blocks, which must be 0 while
+    /// less than 1/3 of the stake is adversarial.
+    pub safety_violations: u64,
+    /// Canonical blocks produced by each validator.
+    pub canonical_blocks_by_validator: Vec<u64>,
+}
+
+impl SimulationStats {
+    fn new(simulator: &Simulator) -> Self {
+        let blocks = &simulator.blocks;
+        let head = (0..blocks.len())
+            .reduce(|best, block| if simulator.is_better_tip(block, best) { block } else { best })
+            .unwrap();
+        let mut canonical = vec![head];
+        while *canonical.last().unwrap() != 0 {
+            canonical.push(blocks[*canonical.last().unwrap()].parent);
+        }
+        canonical.reverse();
+
+        // Time at which each block became final.
+        let mut finalized_at: HashMap<BlockIndex, Duration> = HashMap::new();
+        for block in &blocks[1..] {
+            let entry = finalized_at.entry(block.last_final_block).or_insert(block.produced_at);
+            *entry = (*entry).min(block.produced_at);
+        }
+        let head_block = &blocks[head];
+        let final_block = head_block.last_final_block;
+        let mut lags = vec![];
+        let mut lag_end = None;
+        for &block in canonical.iter().rev() {
+            if let Some(&at) = finalized_at.get(&block) {
+                lag_end = Some(lag_end.map_or(at, |end: Duration| end.min(at)));
+            }
+            if block != 0 && blocks[block].height <= blocks[final_block].height {
+                if let Some(end) = lag_end {
+                    lags.push(end.saturating_sub(blocks[block].produced_at));
+                }
+            }
+        }
+
+        let mut final_blocks = finalized_at.keys().copied().collect::<Vec<_>>();
+        final_blocks.sort_by_key(|&block| (blocks[block].height, block));
+        let safety_violations =
+            final_blocks.windows(2).filter(|pair| !simulator.is_ancestor(pair[0], pair[1])).count()
+                as u64;
+
+        let mut canonical_blocks_by_validator = vec![0; simulator.validators.len()];
+        for &block in &canonical {
+            if let Some(producer) = blocks[block].producer {
+                canonical_blocks_by_validator[producer] += 1;
+            }
+        }
+        let num_canonical = canonical.len() as u64 - 1;
+        let blocks_produced = blocks.len() as u64 - 1;
+        Self {
+            elapsed: Duration::from_millis(simulator.now),
+            head_height: head_block.height,
+            final_height: blocks[final_block].height,
+            blocks_produced,
+            orphaned_blocks: blocks_produced - num_canonical,
+            skipped_heights: head_block.height - num_canonical,
+            mean_block_time: if num_canonical == 0 {
+                Duration::ZERO
+            } else {
+                head_block.produced_at / num_canonical as u32
+            },
+            mean_finality_lag: if lags.is_empty() {
+                Duration::ZERO
+            } else {
+                lags.iter().sum::<Duration>() / lags.len() as u32
+            },
+            max_finality_lag: lags.iter().max().copied().unwrap_or_default(),
+            safety_violations,
+            canonical_blocks_by_validator,
+        }
+    }
+}
+
+/// Mean and standard deviation of a value over several simulations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Statistic {
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+impl Statistic {
+    fn new(values: impl Iterator<Item = f64>) -> Self {
+        let values = values.collect::<Vec<_>>();
+        let n = values.len().max(1) as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / n;
+        Self { mean, stddev: variance.sqrt() }
+    }
+}
+
+impl std::fmt::Display for Statistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.3} ± {:.3}", self.mean, self.stddev)
+    }
+}
+
+/// Statistics over simulations with different seeds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationSummary {
+    pub runs: usize,
+    /// Fraction of the heights without a canonical block.
+    pub skipped_ratio: Statistic,
+    /// Fraction of the produced blocks which aren't canonical.
+    pub orphaned_ratio: Statistic,
+    pub block_time_secs: Statistic,
+    pub finality_lag_secs: Statistic,
+    pub safety_violations: u64,
+}
+
+impl SimulationSummary {
+    pub fn new(stats: &[SimulationStats]) -> Self {
+        let ratio = |numerator: u64, denominator: u64| numerator as f64 / denominator.max(1) as f64;
+        Self {
+            runs: stats.len(),
+            skipped_ratio: Statistic::new(
+                stats.iter().map(|stats| ratio(stats.skipped_heights, stats.head_height)),
+            ),
+            orphaned_ratio: Statistic::new(
+                stats.iter().map(|stats| ratio(stats.orphaned_blocks, stats.blocks_produced)),
+            ),
+            block_time_secs: Statistic::new(
+                stats.iter().map(|stats| stats.mean_block_time.as_secs_f64()),
+            ),
+            finality_lag_secs: Statistic::new(
+                stats.iter().map(|stats| stats.mean_finality_lag.as_secs_f64()),
+            ),
+            safety_violations: stats.iter().map(|stats| stats.safety_violations).sum(),
+        }
+    }
+}
+
+impl std::fmt::Display for SimulationSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "runs:              {}", self.runs)?;
+        writeln!(f, "skipped ratio:     {}", self.skipped_ratio)?;
+        writeln!(f, "orphaned ratio:    {}", self.orphaned_ratio)?;
+        writeln!(f, "block time (s):    {}", self.block_time_secs)?;
+        writeln!(f, "finality lag (s):  {}", self.finality_lag_secs)?;
+        write!(f, "safety violations: {}", self.safety_violations)
+    }
+}
+
+/// Runs the simulation once for every seed, creating the validators with
+/// `make_validators`, and summarizes the results.
+pub fn run_simulations(
+    config: &SimulatorConfig,
+    seeds: impl IntoIterator<Item = u64>,
+    make_validators: impl Fn() -> Vec<(Balance, Box<dyn ValidatorBehavior>)>,
+) -> SimulationSummary {
+    let stats = seeds
+        .into_iter()
+        .map(|seed| {
+            let config = SimulatorConfig { seed, ..config.clone() };
+            Simulator::new(config, make_validators()).run()
+        })
+        .collect::<Vec<_>>();
+    SimulationSummary::new(&stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::behavior::{DelayedProduction, Honest, Offline, SelectiveBroadcast};
+    use super::*;
+
+    fn validators(
+        num_validators: usize,
+        make_behavior: impl Fn(ValidatorIndex) -> Box<dyn ValidatorBehavior>,
+    ) -> Vec<(Balance, Box<dyn ValidatorBehavior>)> {
+        (0..num_validators).map(|index| (100, make_behavior(index))).collect()
+    }
+
+    #[test]
+    fn test_honest_validators() {
+        let config = SimulatorConfig { target_height: 1000, ..Default::default() };
+        let stats = Simulator::new(config.clone(), validators(10, |_| Box::new(Honest))).run();
+        assert_eq!(stats.head_height, 1000);
+        assert_eq!(stats.skipped_heights, 0);
+        assert_eq!(stats.orphaned_blocks, 0);
+        assert_eq!(stats.safety_violations, 0);
+        assert!(stats.final_height >= 998);
+        assert!(stats.mean_block_time >= config.endorsement_delay);
+        assert!(stats.mean_block_time < config.endorsement_delay + config.max_network_delay * 2);
+
+        // Runs are deterministic.
+        let again = Simulator::new(config, validators(10, |_| Box::new(Honest))).run();
+        assert_eq!(stats, again);
+    }
+
+    #[test]
+    fn test_offline_validators() {
+        let config = SimulatorConfig { target_height: 300, ..Default::default() };
+        let make_validators = || {
+            validators(10, |index| -> Box<dyn ValidatorBehavior> {
+                if index < 2 { Box::new(Offline) } else { Box::new(Honest) }
+            })
+        };
+        let summary = run_simulations(&config, 0..5, make_validators);
+        assert_eq!(summary.runs, 5);
+        assert_eq!(summary.safety_violations, 0);
+        // The heights of the offline validators are skipped.
+        assert!(summary.skipped_ratio.mean > 0.1 && summary.skipped_ratio.mean < 0.3);
+
+        // Without 2/3 of the stake online no blocks are produced.
+        let config = SimulatorConfig { max_time: Duration::from_secs(600), ..config };
+        let stats = Simulator::new(
+            config,
+            validators(3, |index| -> Box<dyn ValidatorBehavior> {
+                if index == 0 { Box::new(Offline) } else { Box::new(Honest) }
+            }),
+        )
+        .run();
+        assert_eq!(stats.head_height, 0);
+    }
+
+    #[test]
+    fn test_adversaries_create_forks() {
+        let config = SimulatorConfig { target_height: 300, ..Default::default() };
+        let make_validators = || {
+            validators(10, |index| -> Box<dyn ValidatorBehavior> {
+                match index {
+                    0 => Box::new(SelectiveBroadcast(HashSet::from([1, 2, 3]))),
+                    1 => Box::new(DelayedProduction(Duration::from_secs(3))),
+                    _ => Box::new(Honest),
+                }
+            })
+        };
+        let summary = run_simulations(&config, 0..5, make_validators);
+        assert_eq!(summary.safety_violations, 0);
+        assert!(summary.orphaned_ratio.mean > 0.0);
+    }
+}
@@ -344,6 +344,63 @@ fn test_validator_kickout() {
     );
 }
 
+#[test]
+fn test_export_validator_sets() {
+    let amount_staked = 1_000_000;
+    let validators =
+        vec![("test1".parse().unwrap(), amount_staked), ("test2".parse().unwrap(), amount_staked)];
+    let epoch_length = 10;
+    let epoch_manager =
+        setup_default_epoch_manager(validators, epoch_length, 1, 2, 90, 60).into_handle();
+    let h = hash_range((3 * epoch_length) as usize);
+
+    record_block(&mut epoch_manager.write(), CryptoHash::default(), h[0], 0, vec![]);
+    let mut prev_block = h[0];
+    let init_epoch_id = epoch_manager.get_epoch_id_from_prev_block(&prev_block).unwrap();
+    for (i, curr_block) in h.iter().enumerate().skip(1) {
+        let height = i as u64;
+        let epoch_id = epoch_manager.get_epoch_id_from_prev_block(&prev_block).unwrap();
+        let block_producer = epoch_manager.get_block_producer_info(&epoch_id, height).unwrap();
+        // test2 skips its blocks in the first epoch.
+        if block_producer.account_id() != "test2" || epoch_id != init_epoch_id {
+            record_block(&mut epoch_manager.write(), prev_block, *curr_block, height, vec![]);
+            prev_block = *curr_block;
+        }
+    }
+
+    let exports = epoch_manager.read().export_validator_sets(0..100).unwrap();
+    assert!(exports.windows(2).all(|w| w[0].epoch_height <= w[1].epoch_height));
+    let init_epoch = exports.iter().find(|export| export.epoch_id == init_epoch_id).unwrap();
+    assert!(init_epoch.start_height.is_some());
+    assert_eq!(
+        init_epoch.validators.iter().map(|v| (v.account_id.as_str(), v.stake)).collect_vec(),
+        [("test1", amount_staked), ("test2", amount_staked)]
+    );
+    assert!(init_epoch.validators.iter().all(|v| v.num_block_producer_seats > 0));
+    // The first epoch is finished, test1 produced all its blocks while test2
+    // is kicked out.
+    let test1_stats = init_epoch.validators[0].stats.as_ref().unwrap();
+    assert!(test1_stats.num_produced_blocks > 0);
+    assert_eq!(test1_stats.num_produced_blocks, test1_stats.num_expected_blocks);
+    assert_eq!(init_epoch.validators[1].stats, None);
+    let kickout_epoch = exports.iter().find(|export| !export.kickouts.is_empty()).unwrap();
+    assert_eq!(kickout_epoch.epoch_height, init_epoch.epoch_height + 1);
+    assert_eq!(kickout_epoch.kickouts[0].account_id, "test2");
+    assert_eq!(
+        kickout_epoch.validators.iter().map(|v| v.account_id.as_str()).collect_vec(),
+        ["test1"]
+    );
+    assert!(kickout_epoch.rewards.iter().any(|reward| reward.account_id == "test1"));
+
+    let exports = epoch_manager
+        .read()
+        .export_validator_sets(init_epoch.epoch_height..init_epoch.epoch_height + 1)
+        .unwrap();
+    // The genesis epoch info is stored for the first block too.
+    assert!(exports.contains(init_epoch));
+    assert!(exports.iter().all(|export| export.epoch_height == init_epoch.epoch_height));
+}
+
 #[test]
 fn test_validator_unstake() {
     let store = create_test_store();
@@ -1889,6 +1946,55 @@ fn test_protocol_version_switch() {
     );
 }
 
+#[test]
+fn test_protocol_version_votes() {
+    let store = create_test_store();
+
+    let epoch_config =
+        epoch_config(10, 1, 2, 100, 90, 60, 0).for_protocol_version(PROTOCOL_VERSION);
+    let config_store = EpochConfigStore::test(BTreeMap::from_iter(vec![
+        (0, Arc::new(epoch_config.clone())),
+        (PROTOCOL_VERSION, Arc::new(epoch_config)),
+    ]));
+    let config = AllEpochConfig::from_epoch_config_store("test-chain", 10, config_store);
+
+    let amount_staked = 1_000_000;
+    let validators = vec![
+        stake("test1".parse().unwrap(), amount_staked),
+        stake("test2".parse().unwrap(), amount_staked),
+    ];
+    let mut reward_calculator = default_reward_calculator();
+    reward_calculator.genesis_protocol_version = 0;
+    let mut epoch_manager =
+        EpochManager::new(store, config, reward_calculator, validators).unwrap();
+    let h = hash_range(5);
+    record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+    for i in 1..5 {
+        record_block_with_version(
+            &mut epoch_manager,
+            h[i - 1],
+            h[i],
+            i as u64,
+            vec![],
+            PROTOCOL_VERSION,
+        );
+    }
+
+    let votes = epoch_manager.get_protocol_version_votes(&h[4]).unwrap();
+    assert_eq!(votes.protocol_version, 0);
+    assert_eq!(votes.next_epoch_protocol_version, 0);
+    assert_eq!(votes.total_stake, 2 * amount_staked);
+    assert_eq!(votes.threshold_stake, 2 * amount_staked * 80 / 100);
+    assert_eq!(votes.validators.len(), 2);
+    let [version] = votes.versions.as_slice() else { panic!("{:?}", votes.versions) };
+    assert_eq!(version.protocol_version, PROTOCOL_VERSION);
+    let voted = votes.validators.iter().filter(|v| v.protocol_version.is_some());
+    assert_eq!(version.num_validators, voted.clone().count() as u64);
+    assert_eq!(version.stake, voted.map(|v| v.stake).sum::<Balance>());
+    let expected = if version.stake > votes.threshold_stake { PROTOCOL_VERSION } else { 0 };
+    assert_eq!(votes.projected_protocol_version, expected);
+}
+
 #[test]
 fn test_protocol_version_switch_with_shard_layout_change() {
     let store = create_test_store();
@@ -2857,24 +2963,27 @@ fn test_block_and_chunk_producer_not_kicked_out_for_low_endorsements() {
 }
 
 fn test_chunk_header(h: &[CryptoHash], signer: &ValidatorSigner) -> ShardChunkHeader {
-    ShardChunkHeader::V3(ShardChunkHeaderV3::new(
-        h[0],
-        h[2],
-        h[2],
-        h[2],
-        0,
-        1,
-        ShardId::new(0),
-        0,
-        0,
-        0,
-        h[2],
-        h[2],
-        vec![],
-        Default::default(),
-        BandwidthRequests::empty(),
-        signer,
-    ))
+    ShardChunkHeader::V3(
+        ShardChunkHeaderV3::new(
+            h[0],
+            h[2],
+            h[2],
+            h[2],
+            0,
+            1,
+            ShardId::new(0),
+            0,
+            0,
+            0,
+            h[2],
+            h[2],
+            vec![],
+            Default::default(),
+            BandwidthRequests::empty(),
+            signer,
+        )
+        .unwrap(),
+    )
 }
 
 #[test]
@@ -2910,7 +3019,8 @@ fn test_verify_partial_witness_signature() {
         "witness".bytes().collect(),
         7,
         signer.as_ref(),
-    );
+    )
+    .unwrap();
     let chunk_producer =
         epoch_manager.get_chunk_producer_info(&partial_witness.chunk_production_key()).unwrap();
     assert!(partial_witness.verify(chunk_producer.public_key()));
@@ -2928,7 +3038,8 @@ fn test_verify_partial_witness_signature() {
         "witness".bytes().collect(),
         7,
         bad_signer.as_ref(),
-    );
+    )
+    .unwrap();
     assert!(!bad_partial_witness.verify(chunk_producer.public_key()));
 }
 
@@ -3414,3 +3525,24 @@ fn test_get_shard_uids_pending_resharding_double_same() {
     ]);
     assert_eq!(shard_uids, vec![s1].into_iter().collect::<HashSet<_>>());
 }
+
+#[test]
+fn test_aggregator_validator_chunk_stats() {
+    let mut aggregator = EpochInfoAggregator::default();
+    aggregator.shard_tracker.insert(
+        ShardId::new(0),
+        HashMap::from([
+            (0, ChunkStats::new_with_production(3, 4)),
+            (1, ChunkStats::new_with_endorsement(5, 10)),
+        ]),
+    );
+    aggregator
+        .shard_tracker
+        .insert(ShardId::new(1), HashMap::from([(1, ChunkStats::new_with_endorsement(7, 10))]));
+
+    let stats = aggregator.validator_chunk_stats();
+    assert_eq!(stats[&0].production_stats(), &ValidatorStats { produced: 3, expected: 4 });
+    assert_eq!(stats[&0].endorsement_stats(), &ValidatorStats { produced: 0, expected: 0 });
+    assert_eq!(stats[&1].production_stats(), &ValidatorStats { produced: 0, expected: 0 });
+    assert_eq!(stats[&1].endorsement_stats(), &ValidatorStats { produced: 12, expected: 20 });
+}
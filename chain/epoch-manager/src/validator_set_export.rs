@@ -0,0 +1,191 @@
+//! Export of the validator sets of past epochs, for staking auditors and
+//! explorers which would otherwise reconstruct them from the blocks.
+//!
+//! An export is assembled from the `EpochInfo` of the epoch, its
+//! `EpochSummary`, which is stored once the epoch is finished, and its start
+//! height. Note that the kickouts and the rewards stored in the `EpochInfo`
+//! of epoch T are the outcome of epoch T-2, which selected its validators.
+
+use crate::EpochManager;
+use borsh::BorshDeserialize;
+use near_crypto::PublicKey;
+use near_primitives::epoch_info::EpochInfo;
+use near_primitives::epoch_manager::AGGREGATOR_KEY;
+use near_primitives::errors::EpochError;
+use near_primitives::serialize::dec_format;
+use near_primitives::types::{
+    AccountId, Balance, BlockHeight, EpochHeight, EpochId, NumBlocks, ShardId,
+};
+use near_primitives::version::ProtocolVersion;
+use near_primitives::views::ValidatorKickoutView;
+use near_store::DBCol;
+use std::ops::Range;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EpochValidatorSetExport {
+    pub epoch_id: EpochId,
+    pub epoch_height: EpochHeight,
+    pub protocol_version: ProtocolVersion,
+    /// Height of the first block of the epoch, `None` if it didn't start yet.
+    pub start_height: Option<BlockHeight>,
+    #[serde(with = "dec_format")]
+    pub seat_price: Balance,
+    /// Amount minted for the rewards of epoch T-2.
+    #[serde(with = "dec_format")]
+    pub minted_amount: Balance,
+    pub validators: Vec<ValidatorExport>,
+    /// Validators of epoch T-2 which weren't selected for this epoch, sorted
+    /// by account.
+    pub kickouts: Vec<ValidatorKickoutView>,
+    /// Rewards for epoch T-2, including the protocol treasury, sorted by
+    /// account.
+    pub rewards: Vec<ValidatorRewardExport>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorExport {
+    pub account_id: AccountId,
+    pub public_key: PublicKey,
+    #[serde(with = "dec_format")]
+    pub stake: Balance,
+    /// Zero if the validator isn't a block producer.
+    pub num_block_producer_seats: u64,
+    /// Shards of which the validator is a chunk producer.
+    pub chunk_producer_shards: Vec<ShardId>,
+    /// `None` until the epoch is finished, and for the validators kicked out
+    /// for their production in this epoch.
+    pub stats: Option<ValidatorStatsExport>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorStatsExport {
+    pub num_produced_blocks: NumBlocks,
+    pub num_expected_blocks: NumBlocks,
+    pub num_produced_chunks: NumBlocks,
+    pub num_expected_chunks: NumBlocks,
+    pub num_produced_endorsements: NumBlocks,
+    pub num_expected_endorsements: NumBlocks,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorRewardExport {
+    pub account_id: AccountId,
+    #[serde(with = "dec_format")]
+    pub amount: Balance,
+}
+
+impl EpochManager {
+    /// Exports the validator sets of the stored epochs with heights in
+    /// `epoch_heights`, sorted by height. There may be several epochs at the
+    /// same height if the chain forked at an epoch boundary.
+    pub fn export_validator_sets(
+        &self,
+        epoch_heights: Range<EpochHeight>,
+    ) -> Result<Vec<EpochValidatorSetExport>, EpochError> {
+        let mut epochs = vec![];
+        for item in self.store.iter(DBCol::EpochInfo) {
+            let (key, value) = item?;
+            if key.as_ref() == AGGREGATOR_KEY {
+                continue;
+            }
+            let epoch_height = EpochInfo::try_from_slice(&value)?.epoch_height();
+            if epoch_heights.contains(&epoch_height) {
+                epochs.push((epoch_height, EpochId::try_from_slice(&key)?));
+            }
+        }
+        epochs.sort();
+        epochs.into_iter().map(|(_, epoch_id)| self.export_validator_set(&epoch_id)).collect()
+    }
+
+    pub fn export_validator_set(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<EpochValidatorSetExport, EpochError> {
+        let epoch_info = self.get_epoch_info(epoch_id)?;
+        let shard_layout = self.get_shard_layout(epoch_id)?;
+        let start_height = match self.get_epoch_start_from_epoch_id(epoch_id) {
+            Ok(height) => Some(height),
+            Err(EpochError::EpochOutOfBounds(_)) => None,
+            Err(err) => return Err(err),
+        };
+        let summary = match self.get_epoch_validator_info(epoch_id) {
+            Ok(summary) => Some(summary),
+            Err(EpochError::EpochOutOfBounds(_)) => None,
+            Err(err) => return Err(err),
+        };
+
+        let mut num_block_producer_seats = vec![0; epoch_info.validators_len()];
+        for validator_id in epoch_info.block_producers_settlement() {
+            num_block_producer_seats[*validator_id as usize] += 1;
+        }
+        let mut chunk_producer_shards = vec![vec![]; epoch_info.validators_len()];
+        for (shard_index, chunk_producers) in
+            epoch_info.chunk_producers_settlement().iter().enumerate()
+        {
+            let shard_id = shard_layout.get_shard_id(shard_index)?;
+            for validator_id in chunk_producers {
+                chunk_producer_shards[*validator_id as usize].push(shard_id);
+            }
+        }
+
+        let validators = epoch_info
+            .validators_iter()
+            .enumerate()
+            .map(|(validator_id, validator)| {
+                let stats = summary
+                    .as_ref()
+                    .and_then(|summary| {
+                        summary.validator_block_chunk_stats.get(validator.account_id())
+                    })
+                    .map(|stats| ValidatorStatsExport {
+                        num_produced_blocks: stats.block_stats.produced,
+                        num_expected_blocks: stats.block_stats.expected,
+                        num_produced_chunks: stats.chunk_stats.produced(),
+                        num_expected_chunks: stats.chunk_stats.expected(),
+                        num_produced_endorsements: stats.chunk_stats.endorsement_stats().produced,
+                        num_expected_endorsements: stats.chunk_stats.endorsement_stats().expected,
+                    });
+                let (account_id, public_key, stake) = validator.destructure();
+                ValidatorExport {
+                    account_id,
+                    public_key,
+                    stake,
+                    num_block_producer_seats: num_block_producer_seats[validator_id],
+                    chunk_producer_shards: std::mem::take(&mut chunk_producer_shards[validator_id]),
+                    stats,
+                }
+            })
+            .collect();
+
+        let mut kickouts: Vec<_> = epoch_info
+            .validator_kickout()
+            .iter()
+            .map(|(account_id, reason)| ValidatorKickoutView {
+                account_id: account_id.clone(),
+                reason: reason.clone(),
+            })
+            .collect();
+        kickouts.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        let mut rewards: Vec<_> = epoch_info
+            .validator_reward()
+            .iter()
+            .map(|(account_id, amount)| ValidatorRewardExport {
+                account_id: account_id.clone(),
+                amount: *amount,
+            })
+            .collect();
+        rewards.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+
+        Ok(EpochValidatorSetExport {
+            epoch_id: *epoch_id,
+            epoch_height: epoch_info.epoch_height(),
+            protocol_version: epoch_info.protocol_version(),
+            start_height,
+            seat_price: epoch_info.seat_price(),
+            minted_amount: epoch_info.minted_amount(),
+            validators,
+            kickouts,
+            rewards,
+        })
+    }
+}
@@ -4,12 +4,14 @@ use serde_json::{Value, to_value};
 use std::fmt;
 
 #[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcParseError(pub String);
 
 /// This struct may be returned from JSON RPC server in case of error
 /// It is expected that this struct has impl From<_> all other RPC errors
 /// like [RpcBlockError](crate::types::blocks::RpcBlockError)
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct RpcError {
     #[serde(flatten)]
@@ -24,6 +26,7 @@ pub struct RpcError {
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "cause", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcErrorKind {
     RequestValidationError(RpcRequestValidationErrorKind),
@@ -32,6 +35,7 @@ pub enum RpcErrorKind {
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcRequestValidationErrorKind {
     MethodNotFound { method_name: String },
@@ -40,6 +44,8 @@ pub enum RpcRequestValidationErrorKind {
 
 /// A general Server Error
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, ProtocolSchema)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(rename = "RpcServerError"))]
 pub enum ServerError {
     TxExecutionError(TxExecutionError),
     Timeout,
@@ -131,6 +137,18 @@ impl RpcError {
         }
     }
 
+    /// Create an error for a request refused because the server is busy with
+    /// other requests of the same client.  The request may be retried later.
+    pub fn too_many_requests(info: String) -> Self {
+        RpcError::new_handler_error(
+            Some(Value::String(info.clone())),
+            serde_json::json!({
+                "name": "TOO_MANY_REQUESTS",
+                "info": serde_json::json!({"error_message": info})
+            }),
+        )
+    }
+
     /// Create a method not found error.
     pub fn method_not_found(method: String) -> Self {
         RpcError {
@@ -2,4 +2,6 @@
 
 pub mod errors;
 pub mod message;
+#[cfg(feature = "schemars")]
+pub mod schema;
 pub mod types;
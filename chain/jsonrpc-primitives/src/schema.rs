@@ -0,0 +1,250 @@
+//! JSON schema of the RPC methods, generated with `schemars` from the request,
+//! response and error types. Dumped by `neard dump-rpc-schema`, so that clients
+//! in other languages can generate their types instead of maintaining them by
+//! hand.
+
+use crate::errors::RpcError;
+use crate::types::{
+    blocks, changes, chunks, client_config, config, congestion, gas_price, light_client,
+    maintenance, network_info, query, receipts, restore_point, sandbox, split_storage, status,
+    transactions, validator,
+};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::ProtocolVersion;
+use near_primitives::version::PROTOCOL_VERSION;
+use schemars::JsonSchema;
+use schemars::r#gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use std::collections::BTreeMap;
+
+/// Schemas of a method. `error` is the schema of the `cause` of the
+/// `HANDLER_ERROR` kind of `RpcError`.
+#[derive(serde::Serialize, Debug)]
+pub struct RpcMethodSchema {
+    pub params: Schema,
+    pub result: Schema,
+    pub error: Schema,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct RpcSchema {
+    /// Protocol version the schema was generated for. The views change with
+    /// the protocol version, so clients should be regenerated on upgrades.
+    pub protocol_version: ProtocolVersion,
+    pub methods: BTreeMap<String, RpcMethodSchema>,
+    /// Schema of the `error` member of an error response.
+    pub error: Schema,
+    /// Types referenced from the schemas above, by name.
+    pub definitions: schemars::Map<String, Schema>,
+}
+
+struct RpcSchemaBuilder {
+    generator: SchemaGenerator,
+    methods: BTreeMap<String, RpcMethodSchema>,
+}
+
+impl RpcSchemaBuilder {
+    fn method<P: JsonSchema, R: JsonSchema, E: JsonSchema>(&mut self, name: &str) -> &mut Self {
+        let schema = RpcMethodSchema {
+            params: self.generator.subschema_for::<P>(),
+            result: self.generator.subschema_for::<R>(),
+            error: self.generator.subschema_for::<E>(),
+        };
+        let previous = self.methods.insert(name.to_string(), schema);
+        assert!(previous.is_none(), "duplicate RPC method {name}");
+        self
+    }
+}
+
+/// Returns the schema of the methods served by `near-jsonrpc`, except for
+/// `EXPERIMENTAL_genesis_config` and the adversarial ones.
+pub fn rpc_schema() -> RpcSchema {
+    let mut builder = RpcSchemaBuilder {
+        generator: SchemaGenerator::new(SchemaSettings::draft07()),
+        methods: BTreeMap::new(),
+    };
+    builder
+        .method::<blocks::RpcBlockRequest, blocks::RpcBlockResponse, blocks::RpcBlockError>("block")
+        .method::<transactions::RpcSendTransactionRequest, CryptoHash, ()>("broadcast_tx_async")
+        .method::<
+            transactions::RpcSendTransactionRequest,
+            transactions::RpcTransactionResponse,
+            transactions::RpcTransactionError,
+        >("broadcast_tx_commit")
+        .method::<chunks::RpcChunkRequest, chunks::RpcChunkResponse, chunks::RpcChunkError>("chunk")
+        .method::<
+            gas_price::RpcGasPriceRequest,
+            gas_price::RpcGasPriceResponse,
+            gas_price::RpcGasPriceError,
+        >("gas_price")
+        .method::<(), status::RpcHealthResponse, status::RpcStatusError>("health")
+        .method::<
+            light_client::RpcLightClientExecutionProofRequest,
+            light_client::RpcLightClientExecutionProofResponse,
+            light_client::RpcLightClientProofError,
+        >("light_client_proof")
+        .method::<
+            light_client::RpcLightClientNextBlockRequest,
+            light_client::RpcLightClientNextBlockResponse,
+            light_client::RpcLightClientNextBlockError,
+        >("next_light_client_block")
+        .method::<(), network_info::RpcNetworkInfoResponse, network_info::RpcNetworkInfoError>(
+            "network_info",
+        )
+        .method::<query::RpcQueryRequest, query::RpcQueryResponse, query::RpcQueryError>("query")
+        .method::<
+            transactions::RpcSendTransactionRequest,
+            transactions::RpcTransactionResponse,
+            transactions::RpcTransactionError,
+        >("send_tx")
+        .method::<(), status::RpcStatusResponse, status::RpcStatusError>("status")
+        .method::<
+            transactions::RpcTransactionStatusRequest,
+            transactions::RpcTransactionResponse,
+            transactions::RpcTransactionError,
+        >("tx")
+        .method::<
+            validator::RpcValidatorRequest,
+            validator::RpcValidatorResponse,
+            validator::RpcValidatorError,
+        >("validators")
+        .method::<
+            (),
+            client_config::RpcClientConfigResponse,
+            client_config::RpcClientConfigError,
+        >("client_config")
+        .method::<
+            changes::RpcStateChangesInBlockByTypeRequest,
+            changes::RpcStateChangesInBlockResponse,
+            changes::RpcStateChangesError,
+        >("EXPERIMENTAL_changes")
+        .method::<
+            changes::RpcStateChangesInBlockRequest,
+            changes::RpcStateChangesInBlockByTypeResponse,
+            changes::RpcStateChangesError,
+        >("EXPERIMENTAL_changes_in_block")
+        .method::<
+            congestion::RpcCongestionLevelRequest,
+            congestion::RpcCongestionLevelResponse,
+            congestion::RpcCongestionLevelError,
+        >("EXPERIMENTAL_congestion_level")
+        .method::<
+            light_client::RpcLightClientExecutionProofRequest,
+            light_client::RpcLightClientExecutionProofResponse,
+            light_client::RpcLightClientProofError,
+        >("EXPERIMENTAL_light_client_proof")
+        .method::<
+            light_client::RpcLightClientBlockProofRequest,
+            light_client::RpcLightClientBlockProofResponse,
+            light_client::RpcLightClientProofError,
+        >("EXPERIMENTAL_light_client_block_proof")
+        .method::<
+            config::RpcProtocolConfigRequest,
+            config::RpcProtocolConfigResponse,
+            config::RpcProtocolConfigError,
+        >("EXPERIMENTAL_protocol_config")
+        .method::<receipts::RpcReceiptRequest, receipts::RpcReceiptResponse, receipts::RpcReceiptError>(
+            "EXPERIMENTAL_receipt",
+        )
+        .method::<
+            transactions::RpcTransactionStatusRequest,
+            transactions::RpcTransactionResponse,
+            transactions::RpcTransactionError,
+        >("EXPERIMENTAL_tx_status")
+        .method::<
+            validator::RpcValidatorsOrderedRequest,
+            validator::RpcValidatorsOrderedResponse,
+            validator::RpcValidatorError,
+        >("EXPERIMENTAL_validators_ordered")
+        .method::<
+            validator::RpcProtocolVersionVotesRequest,
+            validator::RpcProtocolVersionVotesResponse,
+            validator::RpcValidatorError,
+        >("EXPERIMENTAL_protocol_version_votes")
+        .method::<
+            validator::RpcValidatorDutiesRequest,
+            validator::RpcValidatorDutiesResponse,
+            validator::RpcValidatorError,
+        >("EXPERIMENTAL_validator_duties")
+        .method::<
+            maintenance::RpcMaintenanceWindowsRequest,
+            maintenance::RpcMaintenanceWindowsResponse,
+            maintenance::RpcMaintenanceWindowsError,
+        >("EXPERIMENTAL_maintenance_windows")
+        .method::<
+            transactions::RpcTransactionForwardingRequest,
+            transactions::RpcTransactionForwardingResponse,
+            transactions::RpcTransactionError,
+        >("EXPERIMENTAL_tx_forwarding")
+        .method::<
+            transactions::RpcTransactionsBySignerRequest,
+            transactions::RpcTransactionsBySignerResponse,
+            transactions::RpcTransactionError,
+        >("EXPERIMENTAL_transactions_by_signer")
+        .method::<
+            transactions::RpcTransactionReceiptTreeRequest,
+            transactions::RpcTransactionReceiptTreeResponse,
+            transactions::RpcTransactionError,
+        >("EXPERIMENTAL_tx_receipt_tree")
+        .method::<
+            query::RpcQueryBatchRequest,
+            query::RpcQueryBatchResponse,
+            query::RpcQueryError,
+        >("batch_query")
+        .method::<
+            query::RpcViewAccountsBatchRequest,
+            query::RpcViewAccountsBatchResponse,
+            query::RpcQueryError,
+        >("EXPERIMENTAL_view_accounts_batch")
+        .method::<
+            split_storage::RpcSplitStorageInfoRequest,
+            split_storage::RpcSplitStorageInfoResponse,
+            split_storage::RpcSplitStorageInfoError,
+        >("EXPERIMENTAL_split_storage_info")
+        .method::<
+            restore_point::RpcRestorePointRequest,
+            restore_point::RpcRestorePointResponse,
+            restore_point::RpcRestorePointError,
+        >("EXPERIMENTAL_request_restore_point")
+        .method::<
+            sandbox::RpcSandboxPatchStateRequest,
+            sandbox::RpcSandboxPatchStateResponse,
+            sandbox::RpcSandboxPatchStateError,
+        >("sandbox_patch_state")
+        .method::<
+            sandbox::RpcSandboxFastForwardRequest,
+            sandbox::RpcSandboxFastForwardResponse,
+            sandbox::RpcSandboxFastForwardError,
+        >("sandbox_fast_forward");
+    let RpcSchemaBuilder { mut generator, methods } = builder;
+    let error = generator.subschema_for::<RpcError>();
+    RpcSchema {
+        protocol_version: PROTOCOL_VERSION,
+        methods,
+        error,
+        definitions: generator.take_definitions(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_schema() {
+        let schema = rpc_schema();
+        assert_eq!(schema.protocol_version, PROTOCOL_VERSION);
+        assert!(schema.methods.contains_key("query"));
+        assert!(schema.methods.contains_key("EXPERIMENTAL_tx_status"));
+        for name in ["CryptoHash", "PublicKey", "BlockHeaderView", "RpcQueryError", "RpcError"] {
+            assert!(schema.definitions.contains_key(name), "{name} isn't defined");
+        }
+
+        // All references resolve to definitions.
+        let json = serde_json::to_string(&schema).unwrap();
+        for reference in json.split("\"$ref\":\"#/definitions/").skip(1) {
+            let name = &reference[..reference.find('"').unwrap()];
+            assert!(schema.definitions.contains_key(name), "{name} isn't defined");
+        }
+    }
+}
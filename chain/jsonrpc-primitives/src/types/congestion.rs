@@ -5,12 +5,14 @@ use super::chunks::{ChunkReference, RpcChunkError};
 pub type RpcCongestionLevelError = RpcChunkError;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcCongestionLevelRequest {
     #[serde(flatten)]
     pub chunk_reference: ChunkReference,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcCongestionLevelResponse {
     pub congestion_level: f64,
 }
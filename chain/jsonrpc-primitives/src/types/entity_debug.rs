@@ -95,6 +95,7 @@ pub enum EntityQuery {
     TrieRootByChunkHash { chunk_hash: CryptoHash },
     TrieRootByStateRoot { state_root: CryptoHash, shard_uid: ShardUId },
     ValidatorAssignmentsAtHeight { block_height: BlockHeight, epoch_id: EpochId },
+    WitnessSizeByBlockHashShardId { block_hash: CryptoHash, shard_id: ShardId },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -11,8 +11,10 @@ pub mod maintenance;
 pub mod network_info;
 pub mod query;
 pub mod receipts;
+pub mod restore_point;
 pub mod sandbox;
 pub mod split_storage;
 pub mod status;
+pub mod subscriptions;
 pub mod transactions;
 pub mod validator;
@@ -3,6 +3,7 @@ use near_primitives::types::AccountId;
 use std::net::SocketAddr;
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcPeerInfo {
     pub id: PeerId,
     pub addr: Option<SocketAddr>,
@@ -10,6 +11,7 @@ pub struct RpcPeerInfo {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcKnownProducer {
     pub account_id: AccountId,
     pub addr: Option<SocketAddr>,
@@ -17,6 +19,7 @@ pub struct RpcKnownProducer {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcNetworkInfoResponse {
     pub active_peers: Vec<RpcPeerInfo>,
     pub num_active_peers: usize,
@@ -28,6 +31,7 @@ pub struct RpcNetworkInfoResponse {
 }
 
 #[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcNetworkInfoError {
     #[error("Internal error: {error_message}")]
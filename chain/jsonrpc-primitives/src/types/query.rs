@@ -1,4 +1,5 @@
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcQueryRequest {
     #[serde(flatten)]
     pub block_reference: near_primitives::types::BlockReference,
@@ -7,6 +8,7 @@ pub struct RpcQueryRequest {
 }
 
 #[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcQueryError {
     #[error("There are no fully synchronized blocks on the node yet")]
@@ -62,11 +64,20 @@ pub enum RpcQueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("Transaction is invalid: {error}")]
+    InvalidTransaction {
+        error: near_primitives::errors::InvalidTxError,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("The batch of queries is too large: {error_message}")]
+    BatchTooLarge { error_message: String },
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
     InternalError { error_message: String },
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcQueryResponse {
     #[serde(flatten)]
     pub kind: QueryResponseKind,
@@ -74,15 +85,59 @@ pub struct RpcQueryResponse {
     pub block_hash: near_primitives::hash::CryptoHash,
 }
 
+/// Runs several queries against the same block in a single request.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcQueryBatchRequest {
+    #[serde(flatten)]
+    pub block_reference: near_primitives::types::BlockReference,
+    pub requests: Vec<near_primitives::views::QueryRequest>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcQueryBatchResponse {
+    /// The results in the order of the requests.
+    pub results: Vec<RpcQueryBatchResult>,
+}
+
+/// The result of one of the requests of a batch, which fails on its own.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RpcQueryBatchResult {
+    Result(RpcQueryResponse),
+    Error(RpcQueryError),
+}
+
+/// Looks up the accounts as of the final block in a single request. The node
+/// needs to track the shards of all of the accounts.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcViewAccountsBatchRequest {
+    pub account_ids: Vec<near_primitives::types::AccountId>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcViewAccountsBatchResponse {
+    #[serde(flatten)]
+    pub accounts: near_primitives::views::AccountsBatchView,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum QueryResponseKind {
     ViewAccount(near_primitives::views::AccountView),
     ViewCode(near_primitives::views::ContractCodeView),
+    // Before `ViewState`, whose fields it has too.
+    ViewStatePaged(near_primitives::views::ViewStatePagedResult),
     ViewState(near_primitives::views::ViewStateResult),
     CallResult(near_primitives::views::CallResult),
     AccessKey(near_primitives::views::AccessKeyView),
     AccessKeyList(near_primitives::views::AccessKeyList),
+    DryRunResult(near_primitives::views::DryRunResultView),
 }
 
 impl From<RpcQueryError> for crate::errors::RpcError {
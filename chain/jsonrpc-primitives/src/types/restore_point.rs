@@ -0,0 +1,46 @@
+use near_primitives::types::BlockHeight;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcRestorePointRequest {}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcRestorePointResponse {
+    /// Height of the head when the restore point was requested. The restore
+    /// point is taken asynchronously.
+    pub height: BlockHeight,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcRestorePointError {
+    #[error("Restore points are unavailable: {error_message}")]
+    Unavailable { error_message: String },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcRestorePointError> for crate::errors::RpcError {
+    fn from(error: RpcRestorePointError) -> Self {
+        let error_data = match &error {
+            RpcRestorePointError::Unavailable { .. }
+            | RpcRestorePointError::InternalError { .. } => Some(Value::String(error.to_string())),
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcRestorePointError: {:?}", err),
+                );
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
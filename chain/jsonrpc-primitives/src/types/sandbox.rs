@@ -2,14 +2,18 @@ use near_primitives::state_record::StateRecord;
 use near_primitives::types::BlockHeightDelta;
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcSandboxPatchStateRequest {
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<serde_json::Value>"))]
     pub records: Vec<StateRecord>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcSandboxPatchStateResponse {}
 
 #[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcSandboxPatchStateError {
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
@@ -32,14 +36,17 @@ impl From<RpcSandboxPatchStateError> for crate::errors::RpcError {
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcSandboxFastForwardRequest {
     pub delta_height: BlockHeightDelta,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcSandboxFastForwardResponse {}
 
 #[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcSandboxFastForwardError {
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
@@ -1,6 +1,7 @@
 #[cfg(feature = "debug_types")]
 use near_client_primitives::debug::{
-    DebugBlockStatusData, EpochInfoView, TrackedShardsView, ValidatorStatus,
+    ChunkFetchView, DebugBlockStatusData, EpochInfoView, FlatStorageRebuildView, ReceiptAuditView,
+    TrackedShardsView, ValidatorStatus,
 };
 #[cfg(feature = "debug_types")]
 use near_primitives::views::{
@@ -10,6 +11,7 @@ use near_primitives::views::{
 };
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcStatusResponse {
     #[serde(flatten)]
     pub status_response: near_primitives::views::StatusResponse,
@@ -36,6 +38,9 @@ pub enum DebugStatusResponse {
     Routes(NetworkRoutesView),
     SnapshotHosts(SnapshotHostsView),
     SplitStoreStatus(SplitStorageInfoView),
+    FlatStorageRebuild(FlatStorageRebuildView),
+    ChunkFetch(ChunkFetchView),
+    ReceiptAudit(ReceiptAuditView),
 }
 
 #[cfg(feature = "debug_types")]
@@ -45,15 +50,20 @@ pub struct RpcDebugStatusResponse {
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcHealthResponse;
 
 #[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcStatusError {
     #[error("Node is syncing")]
     NodeIsSyncing,
     #[error("No blocks for {elapsed:?}")]
-    NoNewBlocks { elapsed: time::Duration },
+    NoNewBlocks {
+        #[cfg_attr(feature = "schemars", schemars(with = "(i64, i32)"))]
+        elapsed: time::Duration,
+    },
     #[error("Epoch Out Of Bounds {epoch_id:?}")]
     EpochOutOfBounds { epoch_id: near_primitives::types::EpochId },
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
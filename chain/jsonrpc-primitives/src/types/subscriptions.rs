@@ -0,0 +1,42 @@
+use near_primitives::types::{AccountId, ShardId};
+use near_primitives::views::ChunkView;
+
+/// Filter of the `subscribe_blocks` and `subscribe_chunks` WebSocket methods.
+/// An empty list matches everything.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcSubscriptionRequest {
+    /// Only chunks, or blocks with chunks, with a transaction or a receipt
+    /// from or to one of these accounts.
+    #[serde(default)]
+    pub accounts: Vec<AccountId>,
+    /// Only chunks, or blocks with new chunks, of these shards.
+    #[serde(default)]
+    pub shard_ids: Vec<ShardId>,
+}
+
+impl RpcSubscriptionRequest {
+    /// Whether the chunks of the blocks have to be read to apply the filter.
+    pub fn needs_chunks(&self) -> bool {
+        !self.accounts.is_empty()
+    }
+
+    pub fn matches_shard(&self, shard_id: ShardId) -> bool {
+        self.shard_ids.is_empty() || self.shard_ids.contains(&shard_id)
+    }
+
+    pub fn matches_chunk(&self, chunk: &ChunkView) -> bool {
+        if !self.matches_shard(chunk.header.shard_id) {
+            return false;
+        }
+        if self.accounts.is_empty() {
+            return true;
+        }
+        let touches = |account_id: &AccountId| self.accounts.contains(account_id);
+        chunk.transactions.iter().any(|tx| touches(&tx.signer_id) || touches(&tx.receiver_id))
+            || chunk
+                .receipts
+                .iter()
+                .any(|receipt| touches(&receipt.predecessor_id) || touches(&receipt.receiver_id))
+    }
+}
@@ -3,6 +3,7 @@ use near_primitives::types::AccountId;
 use serde_json::Value;
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcSendTransactionRequest {
     #[serde(rename = "signed_tx_base64")]
     pub signed_transaction: near_primitives::transaction::SignedTransaction,
@@ -11,6 +12,7 @@ pub struct RpcSendTransactionRequest {
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcTransactionStatusRequest {
     #[serde(flatten)]
     pub transaction_info: TransactionInfo,
@@ -19,6 +21,7 @@ pub struct RpcTransactionStatusRequest {
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 #[allow(clippy::large_enum_variant)]
 pub enum TransactionInfo {
@@ -27,12 +30,14 @@ pub enum TransactionInfo {
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum SignedTransaction {
     #[serde(rename = "signed_tx_base64")]
     SignedTransaction(near_primitives::transaction::SignedTransaction),
 }
 
 #[derive(thiserror::Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcTransactionError {
     #[error("An error happened during transaction execution: {context:?}")]
@@ -53,6 +58,7 @@ pub enum RpcTransactionError {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcTransactionResponse {
     #[serde(flatten)]
     pub final_execution_outcome: Option<near_primitives::views::FinalExecutionOutcomeViewEnum>,
@@ -60,6 +66,55 @@ pub struct RpcTransactionResponse {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcTransactionForwardingRequest {
+    pub tx_hash: near_primitives::hash::CryptoHash,
+}
+
+/// Forwarding decisions recently made by the node for a transaction, oldest first.
+/// Empty if the node didn't see the transaction recently.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcTransactionForwardingResponse {
+    pub records: Vec<near_primitives::views::TxForwardingRecordView>,
+    /// Rebroadcast status of the transaction, if it was submitted to this node
+    /// and routed to other chunk producers with `tx_rebroadcast` enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_status: Option<near_primitives::views::TxRoutingStatusView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcTransactionsBySignerRequest {
+    pub signer_id: near_primitives::types::AccountId,
+    /// Return at most this many transactions.  By default all the indexed
+    /// transactions of the signer are returned.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcTransactionReceiptTreeRequest {
+    pub tx_hash: near_primitives::hash::CryptoHash,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcTransactionReceiptTreeResponse {
+    /// The transaction at the root, with the receipts it produced.
+    pub receipt_tree: near_primitives::views::ReceiptTreeView,
+}
+
+/// Recent transactions of a signer, newest first.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RpcTransactionsBySignerResponse {
+    pub transactions: Vec<near_primitives::views::SignerTransactionView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RpcBroadcastTxSyncResponse {
     pub transaction_hash: near_primitives::hash::CryptoHash,
 }
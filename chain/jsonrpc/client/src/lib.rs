@@ -6,14 +6,19 @@ use near_jsonrpc_primitives::types::changes::{
     RpcStateChangesInBlockByTypeRequest, RpcStateChangesInBlockByTypeResponse,
 };
 use near_jsonrpc_primitives::types::transactions::{
-    RpcSendTransactionRequest, RpcTransactionResponse, RpcTransactionStatusRequest,
+    RpcSendTransactionRequest, RpcTransactionReceiptTreeRequest, RpcTransactionReceiptTreeResponse,
+    RpcTransactionResponse, RpcTransactionStatusRequest, RpcTransactionsBySignerRequest,
+    RpcTransactionsBySignerResponse,
+};
+use near_jsonrpc_primitives::types::validator::{
+    RpcProtocolVersionVotesRequest, RpcValidatorDutiesRequest, RpcValidatorsOrderedRequest,
 };
-use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{BlockId, BlockReference, EpochReference, MaybeBlockId, ShardId};
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, GasPriceView, StatusResponse,
+    BlockView, ChunkView, EpochValidatorDutiesView, EpochValidatorInfo, GasPriceView,
+    ProtocolVersionVotesView, StatusResponse,
 };
 use std::time::Duration;
 
@@ -208,6 +213,21 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "query", request)
     }
 
+    pub fn batch_query(
+        &self,
+        request: near_jsonrpc_primitives::types::query::RpcQueryBatchRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::query::RpcQueryBatchResponse> {
+        call_method(&self.client, &self.server_addr, "batch_query", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_view_accounts_batch(
+        &self,
+        request: near_jsonrpc_primitives::types::query::RpcViewAccountsBatchRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::query::RpcViewAccountsBatchResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_view_accounts_batch", request)
+    }
+
     pub fn block_by_id(&self, block_id: BlockId) -> RpcRequest<BlockView> {
         call_method(&self.client, &self.server_addr, "block", [block_id])
     }
@@ -228,6 +248,22 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_tx_status", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_transactions_by_signer(
+        &self,
+        request: RpcTransactionsBySignerRequest,
+    ) -> RpcRequest<RpcTransactionsBySignerResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_transactions_by_signer", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_tx_receipt_tree(
+        &self,
+        request: RpcTransactionReceiptTreeRequest,
+    ) -> RpcRequest<RpcTransactionReceiptTreeResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_tx_receipt_tree", request)
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_changes(
         &self,
@@ -244,6 +280,22 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_validators_ordered", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_protocol_version_votes(
+        &self,
+        request: RpcProtocolVersionVotesRequest,
+    ) -> RpcRequest<ProtocolVersionVotesView> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_protocol_version_votes", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_validator_duties(
+        &self,
+        request: RpcValidatorDutiesRequest,
+    ) -> RpcRequest<Vec<EpochValidatorDutiesView>> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_validator_duties", request)
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_receipt(
         &self,
@@ -269,6 +321,14 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_split_storage_info", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_request_restore_point(
+        &self,
+        request: near_jsonrpc_primitives::types::restore_point::RpcRestorePointRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::restore_point::RpcRestorePointResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_request_restore_point", request)
+    }
+
     pub fn validators(
         &self,
         epoch_id_or_block_id: Option<EpochReference>,
@@ -41,6 +41,24 @@ pub fn start_all_with_validity_period(
     node_type: NodeType,
     transaction_validity_period: NumBlocks,
     enable_doomslug: bool,
+) -> (Addr<ViewClientActor>, tcp::ListenerAddr, Arc<tempfile::TempDir>) {
+    start_all_with_rpc_config(
+        clock,
+        node_type,
+        transaction_validity_period,
+        enable_doomslug,
+        |_| {},
+    )
+}
+
+/// Like `start_all_with_validity_period`, with the config of the RPC server
+/// changed by `configure_rpc`.
+pub fn start_all_with_rpc_config(
+    clock: Clock,
+    node_type: NodeType,
+    transaction_validity_period: NumBlocks,
+    enable_doomslug: bool,
+    configure_rpc: impl FnOnce(&mut RpcConfig),
 ) -> (Addr<ViewClientActor>, tcp::ListenerAddr, Arc<tempfile::TempDir>) {
     let actor_handles = setup_no_network_with_validity_period(
         clock,
@@ -56,13 +74,16 @@ pub fn start_all_with_validity_period(
     );
 
     let addr = tcp::ListenerAddr::reserve_for_test();
+    let mut rpc_config = RpcConfig::new(addr);
+    configure_rpc(&mut rpc_config);
     start_http(
-        RpcConfig::new(addr),
+        rpc_config,
         TEST_GENESIS_CONFIG.clone(),
         actor_handles.client_actor.clone().with_auto_span_context().into_multi_sender(),
         actor_handles.view_client_actor.clone().with_auto_span_context().into_multi_sender(),
         actor_handles.rpc_handler_actor.clone().with_auto_span_context().into_multi_sender(),
         noop().into_multi_sender(),
+        Some(actor_handles.head_events.clone()),
         #[cfg(feature = "test_features")]
         noop().into_multi_sender(),
         Arc::new(DummyEntityDebugHandler {}),
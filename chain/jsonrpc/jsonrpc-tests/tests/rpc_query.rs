@@ -9,7 +9,7 @@ use near_primitives::transaction::SignedTransaction;
 use serde_json::json;
 
 use near_actix_test_utils::run_actix;
-use near_crypto::{InMemorySigner, Signature};
+use near_crypto::{InMemorySigner, KeyType, Signature};
 use near_jsonrpc::client::{ChunkId, JsonRpcClient, new_client};
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
 use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
@@ -325,6 +325,74 @@ fn test_query_state() {
     });
 }
 
+/// Connect to json rpc and query the state of a contract page by page.
+#[test]
+fn test_query_state_paged() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let account: AccountId = "test".parse().unwrap();
+        let code = near_test_contracts::rs_contract().to_vec();
+        deploy_contract(&client, &account, code).await;
+        let signer = InMemorySigner::test_signer(&account);
+        for (nonce, key) in [(2, b"k1"), (3, b"k2")] {
+            let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+            let tx = SignedTransaction::call(
+                nonce,
+                account.clone(),
+                account.clone(),
+                &signer,
+                0,
+                "write_key_value".to_string(),
+                [&key[..], &[0u8; 8][..]].concat(),
+                100_000_000_000_000,
+                block_hash,
+            );
+            let bytes = borsh::to_vec(&tx).unwrap();
+            client
+                .broadcast_tx_commit(near_primitives::serialize::to_base64(&bytes))
+                .await
+                .unwrap();
+        }
+
+        let query_response = client
+            .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::ViewStatePaged {
+                    account_id: account.clone(),
+                    prefix: vec![].into(),
+                    start_key: None,
+                    limit: std::num::NonZeroU32::new(1).unwrap(),
+                    include_proof: false,
+                },
+            })
+            .await
+            .unwrap();
+        let page = if let QueryResponseKind::ViewStatePaged(page) = query_response.kind {
+            page
+        } else {
+            panic!("queried state, but received something else: {:?}", query_response.kind);
+        };
+        assert_eq!(page.values.len(), 1);
+        assert_eq!(page.values[0].key.to_vec(), b"k1".to_vec());
+        assert_eq!(page.next_key.unwrap().to_vec(), b"k2".to_vec());
+
+        // A page can't be empty.
+        let result = test_utils::call_method::<serde_json::Value>(
+            &client.client,
+            &client.server_addr,
+            "query",
+            json!({
+                "request_type": "view_state_paged",
+                "finality": "final",
+                "account_id": account,
+                "prefix_base64": "",
+                "limit": 0,
+            }),
+        )
+        .await;
+        assert!(result.is_err(), "{result:?}");
+    });
+}
+
 /// Connect to json rpc and call function
 #[test]
 fn test_query_call_function() {
@@ -382,6 +450,58 @@ fn test_query_contract_code() {
     });
 }
 
+/// Dry run a transaction, signed and unsigned, against the latest block.
+#[test]
+fn test_query_dry_run_transaction() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let account: AccountId = "test".parse().unwrap();
+        let signer = InMemorySigner::test_signer(&account);
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let tx =
+            SignedTransaction::send_money(1, account.clone(), account, &signer, 100, block_hash);
+        let unsigned_tx =
+            SignedTransaction::new(Signature::empty(KeyType::ED25519), tx.transaction.clone());
+        for (signed_transaction, skip_signature_check) in
+            [(tx.clone(), false), (unsigned_tx.clone(), true)]
+        {
+            let query_response = client
+                .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
+                    block_reference: BlockReference::latest(),
+                    request: QueryRequest::DryRunTransaction {
+                        signed_transaction,
+                        skip_signature_check,
+                        state_overrides: vec![],
+                    },
+                })
+                .await
+                .unwrap();
+            let result = if let QueryResponseKind::DryRunResult(result) = query_response.kind {
+                result
+            } else {
+                panic!(
+                    "dry run a transaction, but received something else: {:?}",
+                    query_response.kind
+                );
+            };
+            assert_eq!(result.transaction_outcome.id, tx.get_hash());
+            assert!(result.gas_burnt > 0);
+        }
+
+        // The signature is checked unless asked otherwise.
+        let result = client
+            .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::DryRunTransaction {
+                    signed_transaction: unsigned_tx,
+                    skip_signature_check: false,
+                    state_overrides: vec![],
+                },
+            })
+            .await;
+        assert!(result.is_err(), "{result:?}");
+    });
+}
+
 async fn deploy_contract(client: &JsonRpcClient, account: &AccountId, code: Vec<u8>) {
     let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
     let signer = InMemorySigner::test_signer(&account);
@@ -0,0 +1,278 @@
+use std::time::Duration;
+
+use actix::System;
+use awc::error::WsProtocolError;
+use awc::ws::{Frame, Message};
+use futures::{SinkExt, Stream, StreamExt};
+use serde_json::{Value, json};
+
+use near_actix_test_utils::run_actix;
+use near_crypto::InMemorySigner;
+use near_jsonrpc::RpcConfig;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::to_base64;
+use near_primitives::transaction::SignedTransaction;
+use near_time::Clock;
+
+use near_jsonrpc_tests as test_utils;
+
+/// Runs `test` with the WebSocket url of the RPC server of a validator, whose
+/// RPC config is changed by `configure_rpc`.
+fn run_ws_test<F>(
+    configure_rpc: impl FnOnce(&mut RpcConfig),
+    test: impl FnOnce(String) -> F + 'static,
+) where
+    F: Future<Output = ()> + 'static,
+{
+    init_test_logger();
+
+    run_actix(async {
+        let (_view_client_addr, addr, runtime_tempdir) = test_utils::start_all_with_rpc_config(
+            Clock::real(),
+            test_utils::NodeType::Validator,
+            100,
+            false,
+            configure_rpc,
+        );
+        actix::spawn(async move {
+            // If runtime tempdir is dropped some parts of the runtime would stop working.
+            let _runtime_tempdir = runtime_tempdir;
+            actix::clock::timeout(Duration::from_secs(60), test(format!("ws://{addr}/ws")))
+                .await
+                .expect("test timed out");
+            System::current().stop();
+        });
+    });
+}
+
+fn request(method: &str, id: u64, params: Value) -> Message {
+    let request = json!({ "jsonrpc": "2.0", "method": method, "id": id, "params": params });
+    Message::Text(request.to_string().into())
+}
+
+/// Returns the next JSON RPC message received, skipping the pings.
+async fn next_message(
+    connection: &mut (impl Stream<Item = Result<Frame, WsProtocolError>> + Unpin),
+) -> Value {
+    loop {
+        match connection.next().await.unwrap().unwrap() {
+            Frame::Text(text) => return serde_json::from_slice(&text).unwrap(),
+            Frame::Ping(_) | Frame::Pong(_) => {}
+            frame => panic!("unexpected frame {frame:?}"),
+        }
+    }
+}
+
+/// Checks that every subscription gets the blocks, or chunks, matching its
+/// filter, without missing any block.
+#[test]
+fn test_subscribe_blocks_and_chunks() {
+    run_ws_test(
+        |_| {},
+        |url| async move {
+            let (_, mut connection) = awc::Client::new().ws(url).connect().await.unwrap();
+            connection.send(request("subscribe_blocks", 1, Value::Null)).await.unwrap();
+            // No block has a new chunk of shard 1, which doesn't exist.
+            connection
+                .send(request("subscribe_blocks", 2, json!({ "shard_ids": [1] })))
+                .await
+                .unwrap();
+            connection
+                .send(request("subscribe_chunks", 3, json!({ "shard_ids": [0] })))
+                .await
+                .unwrap();
+
+            let mut responses = vec![];
+            let mut blocks: Vec<Value> = vec![];
+            let mut chunks: Vec<Value> = vec![];
+            while blocks.len() < 5 {
+                let message = next_message(&mut connection).await;
+                if let Some(result) = message.get("result") {
+                    assert_eq!(result, &Value::Null);
+                    responses.push(message["id"].as_u64().unwrap());
+                    continue;
+                }
+                let params = &message["params"];
+                match (message["method"].as_str().unwrap(), params["id"].as_u64().unwrap()) {
+                    ("block", 1) => blocks.push(params["block"].clone()),
+                    ("chunk", 3) => chunks.push(params["chunk"].clone()),
+                    notification => panic!("unexpected notification {notification:?}"),
+                }
+            }
+            responses.sort();
+            assert_eq!(responses, vec![1, 2, 3]);
+            for pair in blocks.windows(2) {
+                assert_eq!(pair[1]["header"]["prev_hash"], pair[0]["header"]["hash"]);
+            }
+            assert!(!chunks.is_empty());
+            for chunk in &chunks {
+                assert_eq!(chunk["header"]["shard_id"], 0);
+            }
+        },
+    );
+}
+
+/// Checks that the subscriptions above `max_ws_subscriptions` are rejected,
+/// and that a subscription stops counting when its connection is closed.
+#[test]
+fn test_subscriptions_limit() {
+    run_ws_test(
+        |config| config.limits_config.max_ws_subscriptions = 1,
+        |url| async move {
+            let (_, mut first) = awc::Client::new().ws(url.clone()).connect().await.unwrap();
+            first.send(request("subscribe_blocks", 1, Value::Null)).await.unwrap();
+            assert_eq!(next_message(&mut first).await["result"], Value::Null);
+
+            let (_, mut second) = awc::Client::new().ws(url).connect().await.unwrap();
+            second.send(request("subscribe_blocks", 1, Value::Null)).await.unwrap();
+            assert!(next_message(&mut second).await.get("error").is_some());
+
+            first.send(Message::Close(None)).await.unwrap();
+            drop(first);
+            for id in 2.. {
+                second.send(request("subscribe_blocks", id, Value::Null)).await.unwrap();
+                let response = loop {
+                    let message = next_message(&mut second).await;
+                    if message["id"] == id {
+                        break message;
+                    }
+                };
+                if response.get("result") == Some(&Value::Null) {
+                    break;
+                }
+                actix::clock::sleep(Duration::from_millis(100)).await;
+            }
+        },
+    );
+}
+
+/// Checks that the WebSocket connections above `max_ws_connections` are
+/// refused until another connection is closed.
+#[test]
+fn test_ws_connections_limit() {
+    run_ws_test(
+        |config| config.limits_config.max_ws_connections = 1,
+        |url| async move {
+            let (_, mut first) = awc::Client::new().ws(url.clone()).connect().await.unwrap();
+            assert!(awc::Client::new().ws(url.clone()).connect().await.is_err());
+
+            first.send(Message::Close(None)).await.unwrap();
+            drop(first);
+            while awc::Client::new().ws(url.clone()).connect().await.is_err() {
+                actix::clock::sleep(Duration::from_millis(100)).await;
+            }
+        },
+    );
+}
+
+/// Checks that the requests above `max_ws_requests_in_flight` on a connection
+/// get an error, while a subscription is still counted as in flight.
+#[test]
+fn test_ws_requests_in_flight_limit() {
+    run_ws_test(
+        |config| config.limits_config.max_ws_requests_in_flight = 1,
+        |url| async move {
+            let (_, mut connection) = awc::Client::new().ws(url).connect().await.unwrap();
+            connection.send(request("subscribe_blocks", 1, Value::Null)).await.unwrap();
+            assert_eq!(next_message(&mut connection).await["result"], Value::Null);
+
+            connection.send(request("status", 2, Value::Null)).await.unwrap();
+            let response = loop {
+                let message = next_message(&mut connection).await;
+                if message["id"] == 2 {
+                    break message;
+                }
+            };
+            assert_eq!(response["error"]["name"], "HANDLER_ERROR");
+            assert_eq!(response["error"]["cause"]["name"], "TOO_MANY_REQUESTS");
+        },
+    );
+}
+
+/// Checks that `send_tx` over WebSocket notifies the statuses the transaction
+/// reaches before `wait_until`, and then responds with the final result.
+#[test]
+fn test_send_tx_status_notifications() {
+    run_ws_test(
+        |_| {},
+        |url| async move {
+            let (_, mut connection) = awc::Client::new().ws(url).connect().await.unwrap();
+            connection.send(request("block", 1, json!({ "finality": "final" }))).await.unwrap();
+            let block = next_message(&mut connection).await;
+            let block_hash: CryptoHash =
+                block["result"]["header"]["hash"].as_str().unwrap().parse().unwrap();
+
+            let signer = InMemorySigner::test_signer(&"test1".parse().unwrap());
+            let tx = SignedTransaction::send_money(
+                1,
+                "test1".parse().unwrap(),
+                "test2".parse().unwrap(),
+                &signer,
+                100,
+                block_hash,
+            );
+            let tx_base64 = to_base64(&borsh::to_vec(&tx).unwrap());
+            connection
+                .send(request(
+                    "send_tx",
+                    2,
+                    json!({ "signed_tx_base64": tx_base64, "wait_until": "FINAL" }),
+                ))
+                .await
+                .unwrap();
+
+            let mut statuses = vec![];
+            let response = loop {
+                let message = next_message(&mut connection).await;
+                if message["id"] == 2 {
+                    break message;
+                }
+                assert_eq!(message["method"], "tx_status");
+                assert_eq!(message["params"]["id"], 2);
+                statuses.push(message["params"]["status"].clone());
+            };
+            assert!(!statuses.is_empty());
+            assert!(!statuses.contains(&json!("FINAL")));
+            assert_eq!(response["result"]["final_execution_status"], "FINAL");
+            assert_eq!(response["result"]["transaction"]["hash"], json!(tx.get_hash().to_string()));
+        },
+    );
+}
+
+/// Checks that `final_block_subscribe` gets every block which becomes final,
+/// with no gaps, and never a block before it was sent as a head by
+/// `block_subscribe`.
+#[test]
+fn test_final_block_subscribe() {
+    run_ws_test(
+        |_| {},
+        |url| async move {
+            let (_, mut connection) = awc::Client::new().ws(url).connect().await.unwrap();
+            connection.send(request("block_subscribe", 1, Value::Null)).await.unwrap();
+            connection.send(request("final_block_subscribe", 2, Value::Null)).await.unwrap();
+
+            let mut head_hashes = vec![];
+            let mut final_blocks: Vec<Value> = vec![];
+            while final_blocks.len() < 5 {
+                let message = next_message(&mut connection).await;
+                if let Some(result) = message.get("result") {
+                    assert_eq!(result, &Value::Null);
+                    continue;
+                }
+                let params = &message["params"];
+                match (message["method"].as_str().unwrap(), params["id"].as_u64().unwrap()) {
+                    ("block", 1) => head_hashes.push(params["block"]["header"]["hash"].clone()),
+                    ("final_block", 2) => final_blocks.push(params["block"].clone()),
+                    notification => panic!("unexpected notification {notification:?}"),
+                }
+            }
+            for pair in final_blocks.windows(2) {
+                assert_eq!(pair[1]["header"]["prev_hash"], pair[0]["header"]["hash"]);
+            }
+            // The first final blocks may have been heads before the subscription.
+            let last_final_hash = &final_blocks.last().unwrap()["header"]["hash"];
+            assert!(head_hashes.contains(last_final_hash));
+        },
+    );
+}
@@ -7,14 +7,18 @@ use parking_lot::Mutex;
 use near_actix_test_utils::run_actix;
 use near_crypto::InMemorySigner;
 use near_jsonrpc::client::new_client;
-use near_jsonrpc_primitives::types::transactions::{RpcTransactionStatusRequest, TransactionInfo};
+use near_jsonrpc_primitives::types::transactions::{
+    RpcTransactionReceiptTreeRequest, RpcTransactionStatusRequest, TransactionInfo,
+};
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_o11y::testonly::{init_integration_logger, init_test_logger};
 use near_primitives::hash::{CryptoHash, hash};
 use near_primitives::serialize::to_base64;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::BlockReference;
-use near_primitives::views::{FinalExecutionStatus, TxExecutionStatus};
+use near_primitives::views::{
+    ExecutionStatusView, FinalExecutionStatus, ReceiptTreeExecutionStateView, TxExecutionStatus,
+};
 use near_time::Clock;
 
 use near_jsonrpc_tests::{self as test_utils, test_with_client};
@@ -115,6 +119,44 @@ fn test_send_tx_commit() {
     });
 }
 
+/// Test the tree of receipts executed for a committed transaction.
+#[test]
+fn test_tx_receipt_tree() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let signer = InMemorySigner::test_signer(&"test1".parse().unwrap());
+        let tx = SignedTransaction::send_money(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer,
+            100,
+            block_hash,
+        );
+        let bytes = borsh::to_vec(&tx).unwrap();
+        client.broadcast_tx_commit(to_base64(&bytes)).await.unwrap();
+
+        let request = RpcTransactionReceiptTreeRequest { tx_hash: tx.get_hash() };
+        let tree = client.EXPERIMENTAL_tx_receipt_tree(request).await.unwrap().receipt_tree;
+        assert_eq!(tree.id, tx.get_hash());
+        let ReceiptTreeExecutionStateView::Executed(execution) = tree.execution else {
+            panic!("the transaction should be executed");
+        };
+        assert_eq!(execution.executor_id.as_str(), "test1");
+        assert!(execution.gas_burnt > 0);
+        assert_eq!(tree.receipts.len(), 1);
+        let ReceiptTreeExecutionStateView::Executed(transfer) = &tree.receipts[0].execution else {
+            panic!("the transfer should be executed");
+        };
+        assert_eq!(transfer.executor_id.as_str(), "test2");
+        assert_eq!(transfer.status, ExecutionStatusView::SuccessValue(Vec::new()));
+        assert!(transfer.block_height >= execution.block_height);
+
+        let request = RpcTransactionReceiptTreeRequest { tx_hash: CryptoHash::new() };
+        assert!(client.EXPERIMENTAL_tx_receipt_tree(request).await.is_err());
+    });
+}
+
 /// Test that expired transaction should be rejected
 #[test]
 fn test_expired_tx() {
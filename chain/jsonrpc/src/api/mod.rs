@@ -16,9 +16,11 @@ mod maintenance;
 mod network_info;
 mod query;
 mod receipts;
+mod restore_point;
 mod sandbox;
 mod split_storage;
 mod status;
+mod subscriptions;
 mod transactions;
 mod validator;
 
@@ -1,9 +1,14 @@
 use near_async::messaging::AsyncSendError;
 use serde_json::Value;
 
-use near_client_primitives::types::QueryError;
+use near_client_primitives::types::{
+    MAX_QUERY_BATCH_SIZE, MAX_VIEW_ACCOUNTS_BATCH_SIZE, QueryError,
+};
 use near_jsonrpc_primitives::errors::RpcParseError;
-use near_jsonrpc_primitives::types::query::{RpcQueryError, RpcQueryRequest, RpcQueryResponse};
+use near_jsonrpc_primitives::types::query::{
+    RpcQueryBatchRequest, RpcQueryError, RpcQueryRequest, RpcQueryResponse,
+    RpcViewAccountsBatchRequest,
+};
 use near_primitives::types::BlockReference;
 use near_primitives::views::{QueryRequest, QueryResponse};
 
@@ -35,6 +40,32 @@ impl RpcRequest for RpcQueryRequest {
     }
 }
 
+impl RpcRequest for RpcQueryBatchRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        let request: Self = Params::parse(value)?;
+        if request.requests.len() > MAX_QUERY_BATCH_SIZE {
+            return Err(RpcParseError(format!(
+                "At most {} queries can be batched",
+                MAX_QUERY_BATCH_SIZE
+            )));
+        }
+        Ok(request)
+    }
+}
+
+impl RpcRequest for RpcViewAccountsBatchRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        let request: Self = Params::parse(value)?;
+        if request.account_ids.len() > MAX_VIEW_ACCOUNTS_BATCH_SIZE {
+            return Err(RpcParseError(format!(
+                "At most {} accounts can be requested at once",
+                MAX_VIEW_ACCOUNTS_BATCH_SIZE
+            )));
+        }
+        Ok(request)
+    }
+}
+
 fn parse_path_data(path: String, data: String) -> Result<RpcQueryRequest, RpcParseError> {
     // Handle a soft-deprecated version of the query API, which is based on
     // positional arguments with a "path"-style first argument.
@@ -98,6 +129,7 @@ impl RpcFrom<QueryError> for RpcQueryError {
     fn rpc_from(error: QueryError) -> Self {
         match error {
             QueryError::InternalError { error_message } => Self::InternalError { error_message },
+            QueryError::BatchTooLarge { error_message } => Self::BatchTooLarge { error_message },
             QueryError::NoSyncedBlocks => Self::NoSyncedBlocks,
             QueryError::UnavailableShard { requested_shard_id } => {
                 Self::UnavailableShard { requested_shard_id }
@@ -131,6 +163,9 @@ impl RpcFrom<QueryError> for RpcQueryError {
             QueryError::TooLargeContractState { contract_account_id, block_height, block_hash } => {
                 Self::TooLargeContractState { contract_account_id, block_height, block_hash }
             }
+            QueryError::InvalidTransaction { error, block_height, block_hash } => {
+                Self::InvalidTransaction { error, block_height, block_hash }
+            }
         }
     }
 }
@@ -159,6 +194,9 @@ impl RpcFrom<near_primitives::views::QueryResponseKind>
             near_primitives::views::QueryResponseKind::ViewState(view_state_result) => {
                 Self::ViewState(view_state_result)
             }
+            near_primitives::views::QueryResponseKind::ViewStatePaged(view_state_result) => {
+                Self::ViewStatePaged(view_state_result)
+            }
             near_primitives::views::QueryResponseKind::CallResult(call_result) => {
                 Self::CallResult(call_result)
             }
@@ -168,6 +206,9 @@ impl RpcFrom<near_primitives::views::QueryResponseKind>
             near_primitives::views::QueryResponseKind::AccessKeyList(access_key_list) => {
                 Self::AccessKeyList(access_key_list)
             }
+            near_primitives::views::QueryResponseKind::DryRunResult(dry_run_result) => {
+                Self::DryRunResult(dry_run_result)
+            }
         }
     }
 }
@@ -0,0 +1,41 @@
+use near_async::messaging::AsyncSendError;
+use near_client_primitives::types::RequestRestorePointError;
+use near_jsonrpc_primitives::{
+    errors::RpcParseError,
+    types::restore_point::{RpcRestorePointError, RpcRestorePointRequest},
+};
+use serde_json::Value;
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcRestorePointRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcRestorePointError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<RequestRestorePointError> for RpcRestorePointError {
+    fn rpc_from(error: RequestRestorePointError) -> Self {
+        match error {
+            RequestRestorePointError::Unavailable(error_message) => {
+                Self::Unavailable { error_message }
+            }
+            RequestRestorePointError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            RequestRestorePointError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcRestorePointError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
@@ -50,6 +50,15 @@ impl RpcFrom<near_client_primitives::debug::DebugStatusResponse>
                     x,
                 )
             }
+            near_client_primitives::debug::DebugStatusResponse::FlatStorageRebuild(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::FlatStorageRebuild(x)
+            }
+            near_client_primitives::debug::DebugStatusResponse::ChunkFetch(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::ChunkFetch(x)
+            }
+            near_client_primitives::debug::DebugStatusResponse::ReceiptAudit(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::ReceiptAudit(x)
+            }
         }
     }
 }
@@ -0,0 +1,15 @@
+use serde_json::Value;
+
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::subscriptions::RpcSubscriptionRequest;
+
+use super::{Params, RpcRequest};
+
+impl RpcRequest for RpcSubscriptionRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        if value.is_null() {
+            return Ok(Self::default());
+        }
+        Params::parse(value)
+    }
+}
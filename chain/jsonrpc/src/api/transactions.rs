@@ -4,7 +4,9 @@ use serde_json::Value;
 use near_client_primitives::types::TxStatusError;
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::transactions::{
-    RpcSendTransactionRequest, RpcTransactionError, RpcTransactionStatusRequest, TransactionInfo,
+    RpcSendTransactionRequest, RpcTransactionError, RpcTransactionForwardingRequest,
+    RpcTransactionReceiptTreeRequest, RpcTransactionStatusRequest, RpcTransactionsBySignerRequest,
+    TransactionInfo,
 };
 use near_primitives::borsh::BorshDeserialize;
 use near_primitives::transaction::SignedTransaction;
@@ -53,6 +55,24 @@ impl RpcRequest for RpcTransactionStatusRequest {
     }
 }
 
+impl RpcRequest for RpcTransactionForwardingRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcTransactionsBySignerRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcTransactionReceiptTreeRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
 impl RpcFrom<AsyncSendError> for RpcTransactionError {
     fn rpc_from(error: AsyncSendError) -> Self {
         Self::InternalError { debug_info: error.to_string() }
@@ -4,7 +4,8 @@ use serde_json::Value;
 use near_client_primitives::types::GetValidatorInfoError;
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::validator::{
-    RpcValidatorError, RpcValidatorRequest, RpcValidatorsOrderedRequest,
+    RpcProtocolVersionVotesRequest, RpcValidatorDutiesRequest, RpcValidatorError,
+    RpcValidatorRequest, RpcValidatorsOrderedRequest,
 };
 use near_primitives::types::EpochReference;
 
@@ -28,6 +29,18 @@ impl RpcRequest for RpcValidatorsOrderedRequest {
     }
 }
 
+impl RpcRequest for RpcProtocolVersionVotesRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcRequest for RpcValidatorDutiesRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
 impl RpcFrom<AsyncSendError> for RpcValidatorError {
     fn rpc_from(error: AsyncSendError) -> Self {
         Self::InternalError { error_message: error.to_string() }
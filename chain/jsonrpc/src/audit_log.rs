@@ -0,0 +1,257 @@
+//! Audit log of the JSON RPC requests, see `RpcAuditLogConfig`.
+//!
+//! The entries never contain the request parameters, only their size and
+//! salted hash, so that the log can be kept and shared without exposing what
+//! the callers asked for. The caller IP addresses can be truncated as well.
+
+use near_jsonrpc_primitives::errors::{RpcError, RpcErrorKind};
+use near_o11y::rotating_file::RotatingFileWriter;
+use near_o11y::tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use near_primitives::hash::CryptoHash;
+use rand::Rng;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Method names are user input, longer ones are truncated in the log.
+const MAX_METHOD_NAME_LENGTH: usize = 64;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RpcAuditLogConfig {
+    /// Fraction of the requests which are logged, between 0 and 1.
+    pub sample_rate: f64,
+    /// Sample rates of specific methods, overriding `sample_rate`.
+    pub method_sample_rates: HashMap<String, f64>,
+    /// If set, the entries are appended to this file as JSON lines. Otherwise
+    /// they are emitted as tracing events with the `rpc_audit` target.
+    pub file: Option<PathBuf>,
+    /// Size in bytes after which the file is rotated.
+    pub max_file_size: u64,
+    /// Number of rotated files kept next to `file`.
+    pub max_rotated_files: usize,
+    /// If true, only the /24 network of IPv4 and the /48 network of IPv6
+    /// caller addresses are logged.
+    pub redact_caller_ip: bool,
+    /// Secret prepended to the params before hashing them, so that small
+    /// param spaces, e.g. account ids, can't be recovered by hashing guesses.
+    /// If not set, a random salt is used, and the hashes are only comparable
+    /// between the entries logged by the same run of the node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params_hash_salt: Option<String>,
+}
+
+impl Default for RpcAuditLogConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 1.0,
+            method_sample_rates: HashMap::new(),
+            file: None,
+            max_file_size: 100 * 1024 * 1024,
+            max_rotated_files: 5,
+            redact_caller_ip: true,
+            params_hash_salt: None,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct RpcAuditLogEntry<'a> {
+    timestamp_ms: u64,
+    method: &'a str,
+    params_size: usize,
+    params_hash: CryptoHash,
+    caller_ip: Option<IpAddr>,
+    latency_us: u64,
+    error_code: Option<i64>,
+    error_name: Option<&'a str>,
+}
+
+pub(crate) struct RpcAuditLog {
+    config: RpcAuditLogConfig,
+    /// Writer of `config.file` and the guard flushing it when dropped.
+    file: Option<(NonBlocking, WorkerGuard)>,
+    params_hash_salt: Vec<u8>,
+}
+
+impl RpcAuditLog {
+    pub fn new(config: RpcAuditLogConfig) -> std::io::Result<Self> {
+        let file = match &config.file {
+            Some(path) => Some(near_o11y::tracing_appender::non_blocking(RotatingFileWriter::new(
+                path.clone(),
+                config.max_file_size,
+                config.max_rotated_files,
+            )?)),
+            None => None,
+        };
+        let params_hash_salt = match &config.params_hash_salt {
+            Some(salt) => salt.as_bytes().to_vec(),
+            None => rand::thread_rng().r#gen::<[u8; 32]>().to_vec(),
+        };
+        Ok(Self { config, file, params_hash_salt })
+    }
+
+    /// Decides whether a request to the method is logged.
+    pub fn sample(&self, method: &str) -> bool {
+        let rate =
+            self.config.method_sample_rates.get(method).copied().unwrap_or(self.config.sample_rate);
+        rand::thread_rng().gen_bool(rate.clamp(0.0, 1.0))
+    }
+
+    pub fn log(
+        &self,
+        method: &str,
+        params: &Value,
+        caller_ip: Option<IpAddr>,
+        latency: Duration,
+        response: &Result<Value, RpcError>,
+    ) {
+        let params = serde_json::to_vec(params).unwrap_or_default();
+        let salted_params = [self.params_hash_salt.as_slice(), &params].concat();
+        let error = response.as_ref().err();
+        let error_name = error.and_then(|err| match &err.error_struct {
+            Some(RpcErrorKind::HandlerError(error_struct)) => {
+                error_struct.get("name").and_then(|name| name.as_str())
+            }
+            Some(RpcErrorKind::RequestValidationError(_)) => Some("REQUEST_VALIDATION_ERROR"),
+            Some(RpcErrorKind::InternalError(_)) => Some("INTERNAL_ERROR"),
+            None => None,
+        });
+        let entry = RpcAuditLogEntry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            method: truncate(method, MAX_METHOD_NAME_LENGTH),
+            params_size: params.len(),
+            params_hash: CryptoHash::hash_bytes(&salted_params),
+            caller_ip: caller_ip
+                .map(|ip| if self.config.redact_caller_ip { redact(ip) } else { ip }),
+            latency_us: latency.as_micros() as u64,
+            error_code: error.map(|err| err.code),
+            error_name,
+        };
+        let Ok(mut line) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        match &self.file {
+            Some((writer, _guard)) => {
+                line.push(b'\n');
+                let _ = writer.clone().write_all(&line);
+            }
+            None => {
+                tracing::info!(target: "rpc_audit", entry = %String::from_utf8_lossy(&line));
+            }
+        }
+    }
+}
+
+fn truncate(s: &str, max_len: usize) -> &str {
+    if s.len() <= max_len {
+        return s;
+    }
+    let end = (0..=max_len).rev().find(|&end| s.is_char_boundary(end)).unwrap_or(0);
+    &s[..end]
+}
+
+fn redact(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            IpAddr::from([a, b, c, 0])
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            IpAddr::from([segments[0], segments[1], segments[2], 0, 0, 0, 0, 0])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("192.168.1.42".parse().unwrap()),
+            "192.168.1.0".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            redact("2001:db8:1234:5678::1".parse().unwrap()),
+            "2001:db8:1234::".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("query", 64), "query");
+        assert_eq!(truncate("ąęść", 3), "ą");
+    }
+
+    #[test]
+    fn test_sample() {
+        let config = RpcAuditLogConfig {
+            sample_rate: 0.0,
+            method_sample_rates: HashMap::from([("query".to_string(), 1.0)]),
+            ..Default::default()
+        };
+        let audit_log = RpcAuditLog::new(config).unwrap();
+        assert!(audit_log.sample("query"));
+        assert!(!audit_log.sample("block"));
+    }
+
+    #[test]
+    fn test_log_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpc_audit.log");
+        let config = RpcAuditLogConfig { file: Some(path.clone()), ..Default::default() };
+        let audit_log = RpcAuditLog::new(config).unwrap();
+        let params = serde_json::json!({"account_id": "alice.near"});
+        audit_log.log(
+            "query",
+            &params,
+            Some("10.0.0.7".parse().unwrap()),
+            Duration::from_millis(3),
+            &Err(RpcError::parse_error("bad params".to_string())),
+        );
+        drop(audit_log);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("alice.near"));
+        let entry: Value = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(entry["method"], "query");
+        assert_eq!(entry["caller_ip"], "10.0.0.0");
+        assert_eq!(entry["latency_us"], 3000);
+        assert_eq!(entry["error_name"], "REQUEST_VALIDATION_ERROR");
+        let unsalted_hash = CryptoHash::hash_bytes(&serde_json::to_vec(&params).unwrap());
+        assert_ne!(entry["params_hash"], unsalted_hash.to_string());
+    }
+
+    /// Entries are comparable across runs with the same configured salt only.
+    #[test]
+    fn test_params_hash_salt() {
+        let dir = tempfile::tempdir().unwrap();
+        let params = serde_json::json!({"account_id": "alice.near"});
+        let params_hash = |name: &str, salt: Option<&str>| {
+            let path = dir.path().join(name);
+            let config = RpcAuditLogConfig {
+                file: Some(path.clone()),
+                params_hash_salt: salt.map(str::to_string),
+                ..Default::default()
+            };
+            let audit_log = RpcAuditLog::new(config).unwrap();
+            audit_log.log("query", &params, None, Duration::ZERO, &Ok(Value::Null));
+            drop(audit_log);
+            let entry: Value =
+                serde_json::from_str(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+            entry["params_hash"].clone()
+        };
+        assert_eq!(params_hash("a.log", Some("secret")), params_hash("b.log", Some("secret")));
+        assert_ne!(params_hash("c.log", Some("secret")), params_hash("d.log", Some("other")));
+        assert_ne!(params_hash("e.log", None), params_hash("f.log", None));
+    }
+}
@@ -13,8 +13,10 @@ use near_chain_configs::GenesisConfig;
 use near_client::{
     DebugStatus, GetBlock, GetBlockProof, GetChunk, GetClientConfig, GetExecutionOutcome,
     GetGasPrice, GetMaintenanceWindows, GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig,
-    GetReceipt, GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
-    ProcessTxRequest, ProcessTxResponse, Query, Status, TxStatus,
+    GetProtocolVersionVotes, GetReceipt, GetReceiptTree, GetStateChanges, GetStateChangesInBlock,
+    GetTransactionsBySigner, GetTxForwardingInfo, GetTxRoutingStatus, GetValidatorDuties,
+    GetValidatorInfo, GetValidatorOrdered, NewHeadEvent, ProcessTxRequest, ProcessTxResponse,
+    Query, QueryBatch, RequestRestorePoint, Status, TxStatus, ViewAccountsBatch,
 };
 use near_client_primitives::debug::{DebugBlockStatusQuery, DebugBlocksStartingMode};
 use near_client_primitives::types::GetSplitStorageInfo;
@@ -29,6 +31,7 @@ use near_jsonrpc_primitives::types::query::RpcQueryRequest;
 use near_jsonrpc_primitives::types::split_storage::{
     RpcSplitStorageInfoRequest, RpcSplitStorageInfoResponse,
 };
+use near_jsonrpc_primitives::types::subscriptions::RpcSubscriptionRequest;
 use near_jsonrpc_primitives::types::transactions::{
     RpcSendTransactionRequest, RpcTransactionResponse,
 };
@@ -37,18 +40,24 @@ use near_network::tcp::{self, ListenerAddr};
 use near_o11y::metrics::{Encoder, TextEncoder, prometheus};
 use near_primitives::hash::CryptoHash;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::{AccountId, BlockId, BlockReference};
-use near_primitives::views::{QueryRequest, TxExecutionStatus};
+use near_primitives::types::{AccountId, BlockHeight, BlockId, BlockReference};
+use near_primitives::views::{BlockView, ChunkView, QueryRequest, TxExecutionStatus};
 use serde_json::{Value, json};
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, broadcast};
 use tokio::time::{sleep, timeout};
 use tracing::{error, info};
 
 mod api;
+mod audit_log;
 mod metrics;
+mod subscriptions;
+
+pub use audit_log::RpcAuditLogConfig;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
@@ -69,14 +78,41 @@ impl Default for RpcPollingConfig {
 pub struct RpcLimitsConfig {
     /// Maximum byte size of the json payload.
     pub json_payload_max_size: usize,
+    /// Maximum number of block and chunk subscriptions over WebSocket.
+    #[serde(default = "default_max_ws_subscriptions")]
+    pub max_ws_subscriptions: usize,
+    /// Maximum number of open WebSocket connections.
+    #[serde(default = "default_max_ws_connections")]
+    pub max_ws_connections: usize,
+    /// Maximum number of requests handled at the same time on a WebSocket
+    /// connection. A subscription counts as a request until it ends.
+    #[serde(default = "default_max_ws_requests_in_flight")]
+    pub max_ws_requests_in_flight: usize,
 }
 
 impl Default for RpcLimitsConfig {
     fn default() -> Self {
-        Self { json_payload_max_size: 10 * 1024 * 1024 }
+        Self {
+            json_payload_max_size: 10 * 1024 * 1024,
+            max_ws_subscriptions: default_max_ws_subscriptions(),
+            max_ws_connections: default_max_ws_connections(),
+            max_ws_requests_in_flight: default_max_ws_requests_in_flight(),
+        }
     }
 }
 
+fn default_max_ws_subscriptions() -> usize {
+    1000
+}
+
+fn default_max_ws_connections() -> usize {
+    1000
+}
+
+fn default_max_ws_requests_in_flight() -> usize {
+    100
+}
+
 fn default_enable_debug_rpc() -> bool {
     false
 }
@@ -98,6 +134,9 @@ pub struct RpcConfig {
     // be read from this directory, instead of the contents compiled into the binary. This allows
     // for quick iterative development.
     pub experimental_debug_pages_src_path: Option<String>,
+    /// If set, the requests are recorded in an audit log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_log: Option<RpcAuditLogConfig>,
 }
 
 impl Default for RpcConfig {
@@ -110,6 +149,7 @@ impl Default for RpcConfig {
             limits_config: Default::default(),
             enable_debug_rpc: false,
             experimental_debug_pages_src_path: None,
+            audit_log: None,
         }
     }
 }
@@ -128,6 +168,17 @@ fn serialize_response(value: impl serde::ser::Serialize) -> Result<Value, RpcErr
     serde_json::to_value(value).map_err(|err| RpcError::serialization_error(err.to_string()))
 }
 
+/// Updates the request count, processing time and error metrics of a processed request.
+fn record_request_metrics(metrics_name: &str, timer: Instant, response: &Result<Value, RpcError>) {
+    metrics::HTTP_RPC_REQUEST_COUNT.with_label_values(&[metrics_name]).inc();
+    metrics::RPC_PROCESSING_TIME
+        .with_label_values(&[metrics_name])
+        .observe(timer.elapsed().as_secs_f64());
+    if let Err(err) = response {
+        metrics::RPC_ERROR_COUNT.with_label_values(&[metrics_name, &err.code.to_string()]).inc();
+    }
+}
+
 /// Processes a specific method call.
 ///
 /// The arguments for the method (which is implemented by the `callback`) will
@@ -227,6 +278,8 @@ fn process_query_response(
 pub struct ProcessTxSenderForRpc(
     AsyncSender<ProcessTxRequest, ActixResult<ProcessTxRequest>>,
     Sender<ProcessTxRequest>,
+    AsyncSender<GetTxForwardingInfo, ActixResult<GetTxForwardingInfo>>,
+    AsyncSender<GetTxRoutingStatus, ActixResult<GetTxRoutingStatus>>,
 );
 
 #[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
@@ -234,6 +287,7 @@ pub struct ClientSenderForRpc(
     AsyncSender<DebugStatus, ActixResult<DebugStatus>>,
     AsyncSender<GetClientConfig, ActixResult<GetClientConfig>>,
     AsyncSender<GetNetworkInfo, ActixResult<GetNetworkInfo>>,
+    AsyncSender<RequestRestorePoint, ActixResult<RequestRestorePoint>>,
     AsyncSender<Status, ActixResult<Status>>,
     #[cfg(feature = "test_features")] Sender<near_client::NetworkAdversarialMessage>,
     #[cfg(feature = "test_features")]
@@ -258,14 +312,20 @@ pub struct ViewClientSenderForRpc(
     AsyncSender<GetMaintenanceWindows, ActixResult<GetMaintenanceWindows>>,
     AsyncSender<GetNextLightClientBlock, ActixResult<GetNextLightClientBlock>>,
     AsyncSender<GetProtocolConfig, ActixResult<GetProtocolConfig>>,
+    AsyncSender<GetProtocolVersionVotes, ActixResult<GetProtocolVersionVotes>>,
     AsyncSender<GetReceipt, ActixResult<GetReceipt>>,
+    AsyncSender<GetReceiptTree, ActixResult<GetReceiptTree>>,
     AsyncSender<GetSplitStorageInfo, ActixResult<GetSplitStorageInfo>>,
     AsyncSender<GetStateChanges, ActixResult<GetStateChanges>>,
     AsyncSender<GetStateChangesInBlock, ActixResult<GetStateChangesInBlock>>,
+    AsyncSender<GetTransactionsBySigner, ActixResult<GetTransactionsBySigner>>,
+    AsyncSender<GetValidatorDuties, ActixResult<GetValidatorDuties>>,
     AsyncSender<GetValidatorInfo, ActixResult<GetValidatorInfo>>,
     AsyncSender<GetValidatorOrdered, ActixResult<GetValidatorOrdered>>,
     AsyncSender<Query, ActixResult<Query>>,
+    AsyncSender<QueryBatch, ActixResult<QueryBatch>>,
     AsyncSender<TxStatus, ActixResult<TxStatus>>,
+    AsyncSender<ViewAccountsBatch, ActixResult<ViewAccountsBatch>>,
     #[cfg(feature = "test_features")] Sender<near_client::NetworkAdversarialMessage>,
 );
 
@@ -286,6 +346,13 @@ struct JsonRpcHandler {
     view_client_sender: ViewClientSenderForRpc,
     process_tx_sender: ProcessTxSenderForRpc,
     peer_manager_sender: PeerManagerSenderForRpc,
+    subscriptions: Arc<subscriptions::SubscriptionHub>,
+    /// Head updates of the client, which wake up the requests waiting for a
+    /// transaction to reach some status.  `None` without a client.
+    head_events: Option<broadcast::Sender<NewHeadEvent>>,
+    /// Permits for the open WebSocket connections.
+    ws_connections: Arc<Semaphore>,
+    max_ws_requests_in_flight: usize,
     #[cfg(feature = "test_features")]
     gc_sender: GCSenderForRpc,
     polling_config: RpcPollingConfig,
@@ -293,6 +360,7 @@ struct JsonRpcHandler {
     enable_debug_rpc: bool,
     debug_pages_src_path: Option<PathBuf>,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
+    audit_log: Option<Arc<audit_log::RpcAuditLog>>,
 }
 
 impl JsonRpcHandler {
@@ -311,19 +379,230 @@ impl JsonRpcHandler {
     async fn process_request(&self, request: Request) -> Result<Value, RpcError> {
         let timer = Instant::now();
         let (metrics_name, response) = self.process_request_internal(request).await;
+        record_request_metrics(&metrics_name, timer, &response);
+        response
+    }
+
+    /// Records `request` in the audit log, if it is enabled and the request is sampled.
+    fn audit(
+        &self,
+        request: &Request,
+        caller_ip: Option<IpAddr>,
+        latency: Duration,
+        result: &Result<Value, RpcError>,
+    ) {
+        let Some(audit_log) = &self.audit_log else {
+            return;
+        };
+        if audit_log.sample(&request.method) {
+            audit_log.log(&request.method, &request.params, caller_ip, latency, result);
+        }
+    }
 
-        metrics::HTTP_RPC_REQUEST_COUNT.with_label_values(&[&metrics_name]).inc();
-        metrics::RPC_PROCESSING_TIME
-            .with_label_values(&[&metrics_name])
-            .observe(timer.elapsed().as_secs_f64());
+    /// Processes a JSON RPC message received over a WebSocket connection and sends the
+    /// response back to `session`.
+    ///
+    /// `send_tx` requests additionally get a `tx_status` notification, carrying the id
+    /// of the request, for every status the transaction reaches before `wait_until`.
+    async fn process_ws_message(
+        &self,
+        text: &str,
+        caller_ip: Option<IpAddr>,
+        session: &mut actix_ws::Session,
+    ) {
+        let response = match serde_json::from_str::<Message>(text) {
+            Ok(Message::Request(request)) if request.method == "send_tx" => {
+                let id = request.id.clone();
+                let timer = Instant::now();
+                let (progress_sender, mut progress_receiver) =
+                    tokio::sync::mpsc::unbounded_channel();
+                let send = process_method_call(request.clone(), |params| {
+                    self.send_tx_with_progress(params, move |status| {
+                        let _ = progress_sender.send(status);
+                    })
+                });
+                let mut notifications_session = session.clone();
+                let notify = async {
+                    while let Some(status) = progress_receiver.recv().await {
+                        let notification = Message::notification(
+                            "tx_status".to_string(),
+                            json!({ "id": id, "status": status }),
+                        );
+                        let Ok(notification) = serde_json::to_string(&notification) else {
+                            continue;
+                        };
+                        if notifications_session.text(notification).await.is_err() {
+                            break;
+                        }
+                    }
+                };
+                let (result, ()) = tokio::join!(send, notify);
+                record_request_metrics("send_tx", timer, &result);
+                self.audit(&request, caller_ip, timer.elapsed(), &result);
+                Message::response(id, result)
+            }
+            Ok(Message::Request(request))
+                if matches!(
+                    request.method.as_str(),
+                    "subscribe_blocks"
+                        | "subscribe_chunks"
+                        | "block_subscribe"
+                        | "final_block_subscribe"
+                ) =>
+            {
+                return self.subscribe(request, caller_ip, session).await;
+            }
+            Ok(message) => {
+                let timer = Instant::now();
+                let response = self.process(message.clone()).await;
+                if let (Message::Request(request), Message::Response(response)) =
+                    (&message, &response)
+                {
+                    self.audit(request, caller_ip, timer.elapsed(), &response.result);
+                }
+                response
+            }
+            Err(err) => Message::error(RpcError::parse_error(err.to_string())),
+        };
+        send_ws_message(session, &response).await;
+    }
 
-        if let Err(err) = &response {
-            metrics::RPC_ERROR_COUNT
-                .with_label_values(&[&metrics_name, &err.code.to_string()])
-                .inc();
+    /// Serves `subscribe_blocks`, `subscribe_chunks`, and `final_block_subscribe`, until
+    /// the connection is closed.  `block_subscribe` is the same as `subscribe_blocks`.
+    ///
+    /// After the response to the request, a `block` or `chunk` notification carrying the
+    /// id of the request is sent for every new block, or new chunk, matching the filter.
+    /// `final_block_subscribe` sends a `final_block` notification for every block which
+    /// becomes final instead.  The filter is applied before the views are serialized, so
+    /// that a subscriber to a few accounts or shards costs a busy node little bandwidth.
+    /// The blocks which the subscriber doesn't get, e.g. because it falls behind, are
+    /// reported by a `missed_blocks` notification with the range of their heights.
+    async fn subscribe(
+        &self,
+        request: Request,
+        caller_ip: Option<IpAddr>,
+        session: &mut actix_ws::Session,
+    ) {
+        let timer = Instant::now();
+        let id = request.id.clone();
+        let (stream, block_notification) = match request.method.as_str() {
+            "subscribe_blocks" | "block_subscribe" => {
+                (subscriptions::BlockStream::Head, Some("block"))
+            }
+            "final_block_subscribe" => (subscriptions::BlockStream::Final, Some("final_block")),
+            _ => (subscriptions::BlockStream::Head, None),
+        };
+        let subscribed = RpcSubscriptionRequest::parse(request.params.clone())
+            .map_err(RpcError::from)
+            .and_then(|filter| {
+                let needs_chunks = block_notification.is_none() || filter.needs_chunks();
+                let subscription =
+                    self.subscriptions.subscribe(stream, needs_chunks).ok_or_else(|| {
+                        RpcError::new_internal_error(
+                            None,
+                            "The maximum number of subscriptions is reached".to_string(),
+                        )
+                    })?;
+                Ok((filter, subscription))
+            });
+        let response = subscribed.as_ref().map(|_| Value::Null).map_err(Clone::clone);
+        self.audit(&request, caller_ip, timer.elapsed(), &response);
+        let connected = send_ws_message(session, &Message::response(id.clone(), response)).await;
+        let (Ok((filter, mut subscription)), true) = (subscribed, connected) else {
+            return;
+        };
+        // Height of the last block received from the feed, or reported as missed.
+        let mut last_height = subscription.start_height;
+        let mut lagged = false;
+        loop {
+            let event = match subscription.events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!(target: "jsonrpc", skipped, "Subscriber fell behind the new blocks");
+                    lagged = true;
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            if event.stream() != stream {
+                continue;
+            }
+            let connected = match &*event {
+                subscriptions::FeedEvent::Block { block, chunks, .. } => {
+                    let height = block.header.height;
+                    // The blocks after the last one received were missed.
+                    let missed_after = last_height.filter(|last| lagged && last + 1 < height);
+                    lagged = false;
+                    last_height = Some(height);
+                    if let Some(last) = missed_after {
+                        if !notify_missed_blocks(&id, last + 1, height - 1, session).await {
+                            return;
+                        }
+                    }
+                    self.notify_subscriber(&id, block_notification, &filter, block, chunks, session)
+                        .await
+                }
+                subscriptions::FeedEvent::Missed { from_height, to_height, .. } => {
+                    last_height = Some(*to_height);
+                    notify_missed_blocks(&id, *from_height, *to_height, session).await
+                }
+            };
+            if !connected {
+                return;
+            }
         }
+    }
 
-        response
+    /// Sends the notifications about `block`, with its new `chunks`, matching the filter of
+    /// a subscription. Block subscriptions get a single notification named
+    /// `block_notification`, which is `None` for chunk subscriptions. Returns false if the
+    /// connection is closed.
+    async fn notify_subscriber(
+        &self,
+        id: &Value,
+        block_notification: Option<&str>,
+        filter: &RpcSubscriptionRequest,
+        block: &BlockView,
+        chunks: &[ChunkView],
+        session: &mut actix_ws::Session,
+    ) -> bool {
+        let notifications = if let Some(block_notification) = block_notification {
+            let matches = if filter.needs_chunks() {
+                chunks.iter().any(|chunk| filter.matches_chunk(chunk))
+            } else {
+                filter.shard_ids.is_empty()
+                    || block.chunks.iter().any(|chunk| {
+                        chunk.height_included == block.header.height
+                            && filter.matches_shard(chunk.shard_id)
+                    })
+            };
+            if matches {
+                vec![Message::notification(
+                    block_notification.to_string(),
+                    json!({ "id": id, "block": block }),
+                )]
+            } else {
+                vec![]
+            }
+        } else {
+            chunks
+                .iter()
+                .filter(|chunk| filter.matches_chunk(chunk))
+                .map(|chunk| {
+                    Message::notification("chunk".to_string(), json!({ "id": id, "chunk": chunk }))
+                })
+                .collect()
+        };
+        if notifications.is_empty() {
+            // Detects closed connections of subscribers with nothing to receive.
+            return session.ping(b"").await.is_ok();
+        }
+        for notification in &notifications {
+            if !send_ws_message(session, notification).await {
+                return false;
+            }
+        }
+        true
     }
 
     /// Processes the request without updating any metrics.
@@ -360,9 +639,17 @@ impl JsonRpcHandler {
                             "query_view_state"
                         }
                     }
+                    QueryRequest::ViewStatePaged { include_proof, .. } => {
+                        if include_proof {
+                            "query_view_state_paged_with_proof"
+                        } else {
+                            "query_view_state_paged"
+                        }
+                    }
                     QueryRequest::ViewAccessKey { .. } => "query_view_access_key",
                     QueryRequest::ViewAccessKeyList { .. } => "query_view_access_key_list",
                     QueryRequest::CallFunction { .. } => "query_call_function",
+                    QueryRequest::DryRunTransaction { .. } => "query_dry_run_transaction",
                 };
                 (metrics_name.to_string(), process_query_response(self.query(params).await))
             }
@@ -378,6 +665,7 @@ impl JsonRpcHandler {
     ) -> Result<Result<Value, RpcError>, Request> {
         Ok(match request.method.as_ref() {
             // Handlers ordered alphabetically
+            "batch_query" => process_method_call(request, |params| self.batch_query(params)).await,
             "block" => process_method_call(request, |params| self.block(params)).await,
             "broadcast_tx_async" => {
                 process_method_call(request, |params| async {
@@ -447,12 +735,33 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_validators_ordered" => {
                 process_method_call(request, |params| self.validators_ordered(params)).await
             }
+            "EXPERIMENTAL_protocol_version_votes" => {
+                process_method_call(request, |params| self.protocol_version_votes(params)).await
+            }
+            "EXPERIMENTAL_validator_duties" => {
+                process_method_call(request, |params| self.validator_duties(params)).await
+            }
             "EXPERIMENTAL_maintenance_windows" => {
                 process_method_call(request, |params| self.maintenance_windows(params)).await
             }
+            "EXPERIMENTAL_tx_forwarding" => {
+                process_method_call(request, |params| self.tx_forwarding(params)).await
+            }
+            "EXPERIMENTAL_transactions_by_signer" => {
+                process_method_call(request, |params| self.transactions_by_signer(params)).await
+            }
+            "EXPERIMENTAL_tx_receipt_tree" => {
+                process_method_call(request, |params| self.tx_receipt_tree(params)).await
+            }
+            "EXPERIMENTAL_view_accounts_batch" => {
+                process_method_call(request, |params| self.view_accounts_batch(params)).await
+            }
             "EXPERIMENTAL_split_storage_info" => {
                 process_method_call(request, |params| self.split_storage_info(params)).await
             }
+            "EXPERIMENTAL_request_restore_point" => {
+                process_method_call(request, |params| self.request_restore_point(params)).await
+            }
             #[cfg(feature = "sandbox")]
             "sandbox_patch_state" => {
                 process_method_call(request, |params| self.sandbox_patch_state(params)).await
@@ -601,8 +910,29 @@ impl JsonRpcHandler {
     ) -> Result<
         near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
         near_jsonrpc_primitives::types::transactions::RpcTransactionError,
+    > {
+        self.tx_status_fetch_with_progress(tx_info, finality, fetch_receipt, |_| {}).await
+    }
+
+    /// Same as `tx_status_fetch`, but calls `on_progress` every time the transaction is seen
+    /// reaching a new status while waiting for `finality`.
+    ///
+    /// The status is checked again each time the client updates its head, so a new status is
+    /// reported as soon as the block that brings it is processed.  Without head updates it
+    /// falls back to polling.
+    async fn tx_status_fetch_with_progress(
+        &self,
+        tx_info: near_jsonrpc_primitives::types::transactions::TransactionInfo,
+        finality: near_primitives::views::TxExecutionStatus,
+        fetch_receipt: bool,
+        mut on_progress: impl FnMut(TxExecutionStatus),
+    ) -> Result<
+        near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
+        near_jsonrpc_primitives::types::transactions::RpcTransactionError,
     > {
         let (tx_hash, account_id) = tx_info.to_tx_hash_and_account();
+        let mut head_events = self.head_events.as_ref().map(|sender| sender.subscribe());
+        let mut last_status = None;
         let mut tx_status_result =
             Err(near_jsonrpc_primitives::types::transactions::RpcTransactionError::TimeoutError);
         timeout(self.polling_config.polling_timeout, async {
@@ -618,6 +948,10 @@ impl JsonRpcHandler {
                         if tx_execution_status_meets_expectations(&finality, &result.status) {
                             break Ok(result.into())
                         }
+                        if last_status.as_ref() != Some(&result.status) {
+                            last_status = Some(result.status.clone());
+                            on_progress(result.status.clone());
+                        }
                         // else: No such transaction recorded on chain yet
                     },
                     Err(err @ near_jsonrpc_primitives::types::transactions::RpcTransactionError::UnknownTransaction {
@@ -640,7 +974,7 @@ impl JsonRpcHandler {
                     }
                     Err(err) => break Err(err),
                 }
-                sleep(self.polling_config.polling_interval).await;
+                self.wait_for_new_head(&mut head_events).await;
             }
         })
         .await
@@ -657,6 +991,19 @@ impl JsonRpcHandler {
         })?
     }
 
+    /// Waits until the client updates its head, or for a polling interval if
+    /// there are no head updates to wait for.
+    async fn wait_for_new_head(&self, head_events: &mut Option<broadcast::Receiver<NewHeadEvent>>) {
+        match head_events {
+            // The status is read again in full, so the missed updates don't matter.
+            Some(events) => match events.recv().await {
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => *head_events = None,
+            },
+            None => sleep(self.polling_config.polling_interval).await,
+        }
+    }
+
     /// Send a transaction idempotently (subsequent send of the same transaction will not cause
     /// any new side-effects and the result will be the same unless we garbage collected it
     /// already).
@@ -689,12 +1036,80 @@ impl JsonRpcHandler {
         Ok(response)
     }
 
+    async fn transactions_by_signer(
+        &self,
+        request_data: near_jsonrpc_primitives::types::transactions::RpcTransactionsBySignerRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::transactions::RpcTransactionsBySignerResponse,
+        near_jsonrpc_primitives::types::transactions::RpcTransactionError,
+    > {
+        let near_jsonrpc_primitives::types::transactions::RpcTransactionsBySignerRequest {
+            signer_id,
+            limit,
+        } = request_data;
+        let transactions =
+            self.view_client_send(GetTransactionsBySigner { signer_id, limit }).await?;
+        Ok(near_jsonrpc_primitives::types::transactions::RpcTransactionsBySignerResponse {
+            transactions,
+        })
+    }
+
+    async fn tx_receipt_tree(
+        &self,
+        request_data: near_jsonrpc_primitives::types::transactions::RpcTransactionReceiptTreeRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::transactions::RpcTransactionReceiptTreeResponse,
+        near_jsonrpc_primitives::types::transactions::RpcTransactionError,
+    > {
+        let receipt_tree =
+            self.view_client_send(GetReceiptTree { tx_hash: request_data.tx_hash }).await?;
+        Ok(near_jsonrpc_primitives::types::transactions::RpcTransactionReceiptTreeResponse {
+            receipt_tree,
+        })
+    }
+
+    async fn tx_forwarding(
+        &self,
+        request_data: near_jsonrpc_primitives::types::transactions::RpcTransactionForwardingRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::transactions::RpcTransactionForwardingResponse,
+        near_jsonrpc_primitives::types::transactions::RpcTransactionError,
+    > {
+        let records = self
+            .process_tx_sender
+            .send_async(GetTxForwardingInfo { tx_hash: request_data.tx_hash })
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        let routing_status = self
+            .process_tx_sender
+            .send_async(GetTxRoutingStatus { tx_hash: request_data.tx_hash })
+            .await
+            .map_err(RpcFrom::rpc_from)?;
+        Ok(near_jsonrpc_primitives::types::transactions::RpcTransactionForwardingResponse {
+            records,
+            routing_status,
+        })
+    }
+
     async fn send_tx(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcSendTransactionRequest,
     ) -> Result<
         near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
         near_jsonrpc_primitives::types::transactions::RpcTransactionError,
+    > {
+        self.send_tx_with_progress(request_data, |_| {}).await
+    }
+
+    /// Sends the transaction and waits until it reaches `wait_until`, calling `on_progress`
+    /// with every intermediate status seen on the way.
+    async fn send_tx_with_progress(
+        &self,
+        request_data: near_jsonrpc_primitives::types::transactions::RpcSendTransactionRequest,
+        on_progress: impl FnMut(TxExecutionStatus),
+    ) -> Result<
+        near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
+        near_jsonrpc_primitives::types::transactions::RpcTransactionError,
     > {
         if request_data.wait_until == TxExecutionStatus::None {
             self.send_tx_async(request_data);
@@ -706,10 +1121,11 @@ impl JsonRpcHandler {
         let tx = request_data.signed_transaction;
         match self.send_tx_internal(tx.clone(), false).await? {
             ProcessTxResponse::ValidTx | ProcessTxResponse::RequestRouted => {
-                self.tx_status_fetch(
+                self.tx_status_fetch_with_progress(
                     near_jsonrpc_primitives::types::transactions::TransactionInfo::from_signed_tx(tx.clone()),
                     request_data.wait_until,
                     false,
+                    on_progress,
                 ).await
             }
             network_client_response=> {
@@ -806,6 +1222,9 @@ impl JsonRpcHandler {
                     "/debug/api/requested_state_parts" => {
                         self.client_send(DebugStatus::RequestedStateParts).await?.rpc_into()
                     }
+                    "/debug/api/receipt_audit" => {
+                        self.client_send(DebugStatus::ReceiptAudit).await?.rpc_into()
+                    }
                     "/debug/api/peer_store" => self
                         .peer_manager_send(near_network::debug::GetDebugStatus::PeerStore)
                         .await?
@@ -880,6 +1299,43 @@ impl JsonRpcHandler {
         }
     }
 
+    pub async fn debug_fetch_chunk(
+        &self,
+        block_hash: CryptoHash,
+        shard_id: near_primitives::types::ShardId,
+    ) -> Result<
+        Option<near_jsonrpc_primitives::types::status::RpcDebugStatusResponse>,
+        near_jsonrpc_primitives::types::status::RpcStatusError,
+    > {
+        if self.enable_debug_rpc {
+            let debug_status =
+                self.client_send(DebugStatus::FetchChunk(block_hash, shard_id)).await?.rpc_into();
+            Ok(Some(near_jsonrpc_primitives::types::status::RpcDebugStatusResponse {
+                status_response: debug_status,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn debug_rebuild_flat_storage(
+        &self,
+        shard_id: near_primitives::types::ShardId,
+    ) -> Result<
+        Option<near_jsonrpc_primitives::types::status::RpcDebugStatusResponse>,
+        near_jsonrpc_primitives::types::status::RpcStatusError,
+    > {
+        if self.enable_debug_rpc {
+            let debug_status =
+                self.client_send(DebugStatus::RebuildFlatStorage(shard_id)).await?.rpc_into();
+            Ok(Some(near_jsonrpc_primitives::types::status::RpcDebugStatusResponse {
+                status_response: debug_status,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub async fn protocol_config(
         &self,
         request_data: near_jsonrpc_primitives::types::config::RpcProtocolConfigRequest,
@@ -905,6 +1361,50 @@ impl JsonRpcHandler {
         Ok(query_response.rpc_into())
     }
 
+    async fn batch_query(
+        &self,
+        request_data: near_jsonrpc_primitives::types::query::RpcQueryBatchRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::query::RpcQueryBatchResponse,
+        near_jsonrpc_primitives::types::query::RpcQueryError,
+    > {
+        let results = self
+            .view_client_send(QueryBatch {
+                block_reference: request_data.block_reference,
+                requests: request_data.requests,
+            })
+            .await?;
+        let results = results
+            .into_iter()
+            .map(|result| match result {
+                Ok(query_response) => {
+                    near_jsonrpc_primitives::types::query::RpcQueryBatchResult::Result(
+                        query_response.rpc_into(),
+                    )
+                }
+                Err(query_error) => {
+                    near_jsonrpc_primitives::types::query::RpcQueryBatchResult::Error(
+                        query_error.rpc_into(),
+                    )
+                }
+            })
+            .collect();
+        Ok(near_jsonrpc_primitives::types::query::RpcQueryBatchResponse { results })
+    }
+
+    async fn view_accounts_batch(
+        &self,
+        request_data: near_jsonrpc_primitives::types::query::RpcViewAccountsBatchRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::query::RpcViewAccountsBatchResponse,
+        near_jsonrpc_primitives::types::query::RpcQueryError,
+    > {
+        let accounts = self
+            .view_client_send(ViewAccountsBatch { account_ids: request_data.account_ids })
+            .await?;
+        Ok(near_jsonrpc_primitives::types::query::RpcViewAccountsBatchResponse { accounts })
+    }
+
     async fn tx_status_common(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcTransactionStatusRequest,
@@ -1158,6 +1658,32 @@ impl JsonRpcHandler {
         Ok(validators)
     }
 
+    async fn protocol_version_votes(
+        &self,
+        request: near_jsonrpc_primitives::types::validator::RpcProtocolVersionVotesRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcProtocolVersionVotesResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let near_jsonrpc_primitives::types::validator::RpcProtocolVersionVotesRequest { block_id } =
+            request;
+        let votes = self.view_client_send(GetProtocolVersionVotes { block_id }).await?;
+        Ok(votes)
+    }
+
+    async fn validator_duties(
+        &self,
+        request: near_jsonrpc_primitives::types::validator::RpcValidatorDutiesRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcValidatorDutiesResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let near_jsonrpc_primitives::types::validator::RpcValidatorDutiesRequest { epoch_id } =
+            request;
+        let duties = self.view_client_send(GetValidatorDuties { epoch_id }).await?;
+        Ok(duties)
+    }
+
     /// If experimental_debug_pages_src_path config is set, reads the html file from that
     /// directory. Otherwise, returns None.
     fn read_html_file_override(&self, html_file: &'static str) -> Option<String> {
@@ -1196,6 +1722,24 @@ impl JsonRpcHandler {
         Ok(near_jsonrpc_primitives::types::client_config::RpcClientConfigResponse { client_config })
     }
 
+    async fn request_restore_point(
+        &self,
+        _request_data: near_jsonrpc_primitives::types::restore_point::RpcRestorePointRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::restore_point::RpcRestorePointResponse,
+        near_jsonrpc_primitives::types::restore_point::RpcRestorePointError,
+    > {
+        if !self.enable_debug_rpc {
+            return Err(
+                near_jsonrpc_primitives::types::restore_point::RpcRestorePointError::Unavailable {
+                    error_message: "enable_debug_rpc is not set in the RPC config".to_string(),
+                },
+            );
+        }
+        let height = self.client_send(RequestRestorePoint {}).await?;
+        Ok(near_jsonrpc_primitives::types::restore_point::RpcRestorePointResponse { height })
+    }
+
     pub async fn split_storage_info(
         &self,
         _request_data: near_jsonrpc_primitives::types::split_storage::RpcSplitStorageInfoRequest,
@@ -1407,10 +1951,20 @@ async fn handle_unknown_block(
 }
 
 async fn rpc_handler(
+    http_request: HttpRequest,
     request: web::Json<Message>,
     handler: web::Data<JsonRpcHandler>,
 ) -> HttpResponse {
+    let timer = Instant::now();
     let message = handler.process(request.0.clone()).await;
+    if let (Message::Request(rpc_request), Message::Response(response)) = (&request.0, &message) {
+        handler.audit(
+            rpc_request,
+            http_request.peer_addr().map(|addr| addr.ip()),
+            timer.elapsed(),
+            &response.result,
+        );
+    }
 
     let mut response = if let Message::Response(response) = &message {
         match &response.result {
@@ -1421,6 +1975,7 @@ async fn rpc_handler(
                     match error_struct.get("name").and_then(|name| name.as_str()) {
                         Some("UNKNOWN_BLOCK") => handle_unknown_block(request.0, handler).await,
                         Some("TIMEOUT_ERROR") => HttpResponse::RequestTimeout(),
+                        Some("TOO_MANY_REQUESTS") => HttpResponse::TooManyRequests(),
                         _ => HttpResponse::Ok(),
                     }
                 }
@@ -1435,6 +1990,90 @@ async fn rpc_handler(
     response.json(message)
 }
 
+/// Sends a JSON RPC message over WebSocket. Returns false if the connection is closed.
+async fn send_ws_message(session: &mut actix_ws::Session, message: &Message) -> bool {
+    match serde_json::to_string(message) {
+        Ok(message) => session.text(message).await.is_ok(),
+        Err(err) => {
+            error!(target: "jsonrpc", ?err, "Failed to serialize WebSocket message");
+            true
+        }
+    }
+}
+
+/// Sends a `missed_blocks` notification to a subscriber, which didn't get the blocks of the
+/// heights from `from_height` to `to_height`. Returns false if the connection is closed.
+async fn notify_missed_blocks(
+    id: &Value,
+    from_height: BlockHeight,
+    to_height: BlockHeight,
+    session: &mut actix_ws::Session,
+) -> bool {
+    let notification = Message::notification(
+        "missed_blocks".to_string(),
+        json!({ "id": id, "from_height": from_height, "to_height": to_height }),
+    );
+    send_ws_message(session, &notification).await
+}
+
+/// Serves JSON RPC over WebSocket. Each text message is handled as a separate request,
+/// so a `send_tx` waiting for its transaction doesn't hold up later requests.
+///
+/// Connections above `max_ws_connections` are refused, and requests above
+/// `max_ws_requests_in_flight` on a connection get a `TOO_MANY_REQUESTS` error,
+/// which may be retried once an earlier request completes.
+async fn ws_handler(
+    request: HttpRequest,
+    body: web::Payload,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    let Ok(connection_permit) = handler.ws_connections.clone().try_acquire_owned() else {
+        return Ok(HttpResponse::ServiceUnavailable().body("Too many WebSocket connections"));
+    };
+    let caller_ip = request.peer_addr().map(|addr| addr.ip());
+    let (response, mut session, mut messages) = actix_ws::handle(&request, body)?;
+    let requests_in_flight = Arc::new(Semaphore::new(handler.max_ws_requests_in_flight));
+    actix_web::rt::spawn(async move {
+        // The connection counts until its messages stop being read.
+        let _connection_permit = connection_permit;
+        while let Some(Ok(message)) = messages.recv().await {
+            match message {
+                actix_ws::Message::Text(text) => {
+                    let Ok(request_permit) = requests_in_flight.clone().try_acquire_owned() else {
+                        let id = serde_json::from_str::<Message>(&text)
+                            .map(|message| message.id())
+                            .unwrap_or_default();
+                        let err = RpcError::too_many_requests(
+                            "Too many requests in flight on the connection".to_string(),
+                        );
+                        if !send_ws_message(&mut session, &Message::response(id, Err(err))).await {
+                            return;
+                        }
+                        continue;
+                    };
+                    let handler = handler.clone();
+                    let mut session = session.clone();
+                    actix_web::rt::spawn(async move {
+                        let _request_permit = request_permit;
+                        handler.process_ws_message(&text, caller_ip, &mut session).await;
+                    });
+                }
+                actix_ws::Message::Ping(bytes) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                }
+                actix_ws::Message::Close(reason) => {
+                    let _ = session.close(reason).await;
+                    return;
+                }
+                _ => {}
+            }
+        }
+    });
+    Ok(response)
+}
+
 async fn status_handler(handler: web::Data<JsonRpcHandler>) -> Result<HttpResponse, HttpError> {
     metrics::HTTP_STATUS_REQUEST_COUNT.inc();
 
@@ -1531,6 +2170,31 @@ async fn debug_epoch_info_handler(
     }
 }
 
+async fn debug_rebuild_flat_storage_handler(
+    path: web::Path<u64>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    let shard_id = near_primitives::types::ShardId::new(path.into_inner());
+    match handler.debug_rebuild_flat_storage(shard_id).await {
+        Ok(Some(value)) => Ok(HttpResponse::Ok().json(&value)),
+        Ok(None) => Ok(HttpResponse::MethodNotAllowed().finish()),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
+async fn debug_fetch_chunk_handler(
+    path: web::Path<(CryptoHash, u64)>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    let (block_hash, shard_id) = path.into_inner();
+    let shard_id = near_primitives::types::ShardId::new(shard_id);
+    match handler.debug_fetch_chunk(block_hash, shard_id).await {
+        Ok(Some(value)) => Ok(HttpResponse::Ok().json(&value)),
+        Ok(None) => Ok(HttpResponse::MethodNotAllowed().finish()),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
 async fn health_handler(handler: web::Data<JsonRpcHandler>) -> Result<HttpResponse, HttpError> {
     match handler.health().await {
         Ok(value) => Ok(HttpResponse::Ok().json(&value)),
@@ -1623,6 +2287,7 @@ async fn display_debug_html(
         "validator" => Some(debug_page_string!("validator.html", handler)),
         "validator.css" => Some(debug_page_string!("validator.css", handler)),
         "split_store" => Some(debug_page_string!("split_store.html", handler)),
+        "receipt_audit" => Some(debug_page_string!("receipt_audit.html", handler)),
         "congestion_control" => Some(debug_page_string!("congestion_control.html", handler)),
         "congestion_control.css" => Some(debug_page_string!("congestion_control.css", handler)),
         "congestion_control.js" => Some(debug_page_string!("congestion_control.js", handler)),
@@ -1655,6 +2320,7 @@ pub fn start_http(
     view_client_sender: ViewClientSenderForRpc,
     process_tx_sender: ProcessTxSenderForRpc,
     peer_manager_sender: PeerManagerSenderForRpc,
+    head_events: Option<broadcast::Sender<NewHeadEvent>>,
     #[cfg(feature = "test_features")] gc_sender: GCSenderForRpc,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
 ) -> Vec<(&'static str, actix_web::dev::ServerHandle)> {
@@ -1666,7 +2332,22 @@ pub fn start_http(
         limits_config,
         enable_debug_rpc,
         experimental_debug_pages_src_path: debug_pages_src_path,
+        audit_log,
     } = config;
+    let audit_log = audit_log.and_then(|config| match audit_log::RpcAuditLog::new(config) {
+        Ok(audit_log) => Some(Arc::new(audit_log)),
+        Err(err) => {
+            error!(target: "network", ?err, "Could not open the RPC audit log");
+            None
+        }
+    });
+    let subscriptions = Arc::new(subscriptions::SubscriptionHub::new(
+        view_client_sender.clone(),
+        head_events.clone(),
+        polling_config.polling_interval,
+        limits_config.max_ws_subscriptions,
+    ));
+    let ws_connections = Arc::new(Semaphore::new(limits_config.max_ws_connections));
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr.to_string());
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
     info!(target:"network", "Starting http server at {}", addr);
@@ -1679,17 +2360,23 @@ pub fn start_http(
                 view_client_sender: view_client_sender.clone(),
                 process_tx_sender: process_tx_sender.clone(),
                 peer_manager_sender: peer_manager_sender.clone(),
+                subscriptions: subscriptions.clone(),
+                head_events: head_events.clone(),
+                ws_connections: ws_connections.clone(),
+                max_ws_requests_in_flight: limits_config.max_ws_requests_in_flight,
                 polling_config,
                 genesis_config: genesis_config.clone(),
                 enable_debug_rpc,
                 debug_pages_src_path: debug_pages_src_path.clone().map(Into::into),
                 entity_debug_handler: entity_debug_handler.clone(),
+                audit_log: audit_log.clone(),
                 #[cfg(feature = "test_features")]
                 gc_sender: gc_sender.clone(),
             }))
             .app_data(web::JsonConfig::default().limit(limits_config.json_payload_max_size))
             .wrap(middleware::Logger::default())
             .service(web::resource("/").route(web::post().to(rpc_handler)))
+            .service(web::resource("/ws").route(web::get().to(ws_handler)))
             .service(
                 web::resource("/status")
                     .route(web::get().to(status_handler))
@@ -1722,6 +2409,14 @@ pub fn start_http(
                     web::resource("/debug/api/epoch_info/{epoch_id}")
                         .route(web::get().to(debug_epoch_info_handler)),
                 )
+                .service(
+                    web::resource("/debug/api/rebuild_flat_storage/{shard_id}")
+                        .route(web::post().to(debug_rebuild_flat_storage_handler)),
+                )
+                .service(
+                    web::resource("/debug/api/fetch_chunk/{block_hash}/{shard_id}")
+                        .route(web::post().to(debug_fetch_chunk_handler)),
+                )
                 .service(web::resource("/debug/api/{api}").route(web::get().to(debug_handler)))
                 .service(
                     web::resource("/debug/client_config")
@@ -0,0 +1,292 @@
+//! Block and chunk subscriptions over WebSocket, see `JsonRpcHandler::subscribe`.
+//!
+//! The new blocks are read once, together with their new chunks, by a feed
+//! shared by all the subscriptions, which then only filter them.  The feed
+//! runs while there are subscriptions.  It reads the new blocks every time the
+//! client updates its head, or after a polling interval on the nodes without a
+//! client.
+
+use crate::ViewClientSenderForRpc;
+use crate::metrics;
+use near_async::messaging::SendAsync;
+use near_client::{GetBlock, GetChunk, NewHeadEvent};
+use near_client_primitives::types::GetBlockError;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{BlockHeight, BlockId, BlockReference, Finality};
+use near_primitives::views::{BlockView, ChunkView};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+/// Maximum number of heights the feed catches up with at once, e.g. when the
+/// node was syncing.  The blocks of the older heights are reported as missed.
+const MAX_CATCH_UP: BlockHeight = 100;
+
+/// Number of feed events a subscription may fall behind before it misses some.
+const FEED_CAPACITY: usize = 256;
+
+/// The blocks sent by the feed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BlockStream {
+    /// The blocks of the canonical chain, as they become the head.
+    Head,
+    /// The blocks of the canonical chain, as they become final.
+    Final,
+}
+
+/// Sent by the feed to all the subscriptions.
+#[derive(Debug)]
+pub(crate) enum FeedEvent {
+    /// A new block, with its new chunks if a subscription needs them.
+    Block { stream: BlockStream, block: BlockView, chunks: Vec<ChunkView> },
+    /// The blocks of these heights, if any, weren't read.
+    Missed { stream: BlockStream, from_height: BlockHeight, to_height: BlockHeight },
+}
+
+impl FeedEvent {
+    pub fn stream(&self) -> BlockStream {
+        match self {
+            FeedEvent::Block { stream, .. } | FeedEvent::Missed { stream, .. } => *stream,
+        }
+    }
+}
+
+pub(crate) struct SubscriptionHub {
+    view_client_sender: ViewClientSenderForRpc,
+    /// Head updates of the client, not available on the nodes without one.
+    head_events: Option<broadcast::Sender<NewHeadEvent>>,
+    polling_interval: Duration,
+    max_subscriptions: usize,
+    num_subscriptions: AtomicUsize,
+    /// Number of subscriptions which need the chunks of the blocks, the feed
+    /// doesn't read them otherwise.
+    num_chunk_subscriptions: AtomicUsize,
+    /// Heights of the last head and final blocks read by the feed, 0 if none.
+    last_head_height: AtomicU64,
+    last_final_height: AtomicU64,
+    sender: broadcast::Sender<Arc<FeedEvent>>,
+    /// Whether the feed is running.  Locked while a subscription is added, so
+    /// that the feed doesn't stop in the meantime.
+    feed_running: Mutex<bool>,
+}
+
+/// A subscription to the feed.  Counted by `near_rpc_ws_subscriptions` until
+/// it is dropped.
+pub(crate) struct Subscription {
+    hub: Arc<SubscriptionHub>,
+    needs_chunks: bool,
+    pub events: broadcast::Receiver<Arc<FeedEvent>>,
+    /// Height of the last block of the subscribed stream read by the feed when
+    /// subscribed.
+    pub start_height: Option<BlockHeight>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.hub.num_subscriptions.fetch_sub(1, Ordering::SeqCst);
+        if self.needs_chunks {
+            self.hub.num_chunk_subscriptions.fetch_sub(1, Ordering::SeqCst);
+        }
+        metrics::RPC_WS_SUBSCRIPTIONS.dec();
+    }
+}
+
+impl SubscriptionHub {
+    pub fn new(
+        view_client_sender: ViewClientSenderForRpc,
+        head_events: Option<broadcast::Sender<NewHeadEvent>>,
+        polling_interval: Duration,
+        max_subscriptions: usize,
+    ) -> Self {
+        Self {
+            view_client_sender,
+            head_events,
+            polling_interval,
+            max_subscriptions,
+            num_subscriptions: AtomicUsize::new(0),
+            num_chunk_subscriptions: AtomicUsize::new(0),
+            last_head_height: AtomicU64::new(0),
+            last_final_height: AtomicU64::new(0),
+            sender: broadcast::channel(FEED_CAPACITY).0,
+            feed_running: Mutex::new(false),
+        }
+    }
+
+    /// Subscribes to the blocks of `stream`, starting the feed if needed.
+    /// Returns `None` if there are already `max_subscriptions` subscriptions.
+    pub fn subscribe(
+        self: &Arc<Self>,
+        stream: BlockStream,
+        needs_chunks: bool,
+    ) -> Option<Subscription> {
+        let max_subscriptions = self.max_subscriptions;
+        self.num_subscriptions
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |num| {
+                (num < max_subscriptions).then_some(num + 1)
+            })
+            .ok()?;
+        if needs_chunks {
+            self.num_chunk_subscriptions.fetch_add(1, Ordering::SeqCst);
+        }
+        metrics::RPC_WS_SUBSCRIPTIONS.inc();
+
+        let mut feed_running = self.feed_running.lock();
+        let events = self.sender.subscribe();
+        if !*feed_running {
+            *feed_running = true;
+            actix_web::rt::spawn(self.clone().run_feed());
+        }
+        let start_height = Some(self.last_height(stream).load(Ordering::SeqCst)).filter(|h| *h > 0);
+        Some(Subscription { hub: self.clone(), needs_chunks, events, start_height })
+    }
+
+    /// Returns true, after marking the feed as stopped, if there are no
+    /// subscriptions left.
+    fn stop_if_unused(&self) -> bool {
+        let mut feed_running = self.feed_running.lock();
+        if self.sender.receiver_count() > 0 {
+            return false;
+        }
+        *feed_running = false;
+        true
+    }
+
+    fn last_height(&self, stream: BlockStream) -> &AtomicU64 {
+        match stream {
+            BlockStream::Head => &self.last_head_height,
+            BlockStream::Final => &self.last_final_height,
+        }
+    }
+
+    /// Sends the new head and final blocks to the subscriptions every time the
+    /// client updates its head, or after a polling interval, until there are
+    /// no subscriptions.
+    async fn run_feed(self: Arc<Self>) {
+        let mut head_events = self.head_events.as_ref().map(|sender| sender.subscribe());
+        let mut last_head_height = None;
+        let mut last_final_height = None;
+        loop {
+            if self.stop_if_unused() {
+                return;
+            }
+            last_head_height = self
+                .send_new_blocks(BlockStream::Head, last_head_height)
+                .await
+                .or(last_head_height);
+            last_final_height = self
+                .send_new_blocks(BlockStream::Final, last_final_height)
+                .await
+                .or(last_final_height);
+            match &mut head_events {
+                // The new blocks are found by height, so the head updates
+                // missed by the feed itself are harmless.
+                Some(events) => match events.recv().await {
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => head_events = None,
+                },
+                None => sleep(self.polling_interval).await,
+            }
+        }
+    }
+
+    /// Sends the blocks of `stream` after `last_height` up to the current
+    /// head, or last final block.  Returns the height of the latter, or `None`
+    /// if it can't be read.  The first time, when `last_height` is unknown,
+    /// nothing is sent.
+    async fn send_new_blocks(
+        &self,
+        stream: BlockStream,
+        last_height: Option<BlockHeight>,
+    ) -> Option<BlockHeight> {
+        let reference = match stream {
+            BlockStream::Head => BlockReference::latest(),
+            BlockStream::Final => BlockReference::Finality(Finality::Final),
+        };
+        let tip = match self.get_block(reference).await {
+            Ok(tip) => tip,
+            Err(err) => {
+                tracing::debug!(target: "jsonrpc", %err, ?stream, "Failed to get the tip for the subscriptions");
+                return None;
+            }
+        };
+        let tip_height = tip.header.height;
+        let last_height = match last_height {
+            Some(last_height) if last_height < tip_height => last_height,
+            Some(last_height) => return Some(last_height),
+            None => {
+                self.last_height(stream).store(tip_height, Ordering::SeqCst);
+                return Some(tip_height);
+            }
+        };
+        let mut first_height = last_height + 1;
+        if tip_height - last_height > MAX_CATCH_UP {
+            let from_height = first_height;
+            first_height = tip_height + 1 - MAX_CATCH_UP;
+            self.send(FeedEvent::Missed { stream, from_height, to_height: first_height - 1 });
+        }
+        // The blocks below the tip are the ones of the canonical chain, which
+        // are all final below the last final block.
+        for height in first_height..tip_height {
+            match self.get_block(BlockReference::BlockId(BlockId::Height(height))).await {
+                Ok(block) => self.send_block(stream, block).await,
+                // Skipped heights have no block.
+                Err(GetBlockError::UnknownBlock { .. }) => {}
+                Err(err) => {
+                    tracing::debug!(target: "jsonrpc", %err, height, "Failed to get a block for the subscriptions");
+                    self.send(FeedEvent::Missed { stream, from_height: height, to_height: height });
+                }
+            }
+        }
+        self.send_block(stream, tip).await;
+        Some(tip_height)
+    }
+
+    async fn send_block(&self, stream: BlockStream, block: BlockView) {
+        let chunks = if self.num_chunk_subscriptions.load(Ordering::SeqCst) > 0 {
+            self.get_new_chunks(&block).await
+        } else {
+            vec![]
+        };
+        self.last_height(stream).store(block.header.height, Ordering::SeqCst);
+        self.send(FeedEvent::Block { stream, block, chunks });
+    }
+
+    fn send(&self, event: FeedEvent) {
+        // Fails only if there are no subscriptions.
+        let _ = self.sender.send(Arc::new(event));
+    }
+
+    async fn get_block(&self, reference: BlockReference) -> Result<BlockView, GetBlockError> {
+        self.view_client_sender
+            .send_async(GetBlock(reference))
+            .await
+            .map_err(|err| GetBlockError::IOError { error_message: format!("{err:?}") })?
+    }
+
+    /// Reads the chunks of `block` which are new in it.
+    async fn get_new_chunks(&self, block: &BlockView) -> Vec<ChunkView> {
+        let mut chunks = vec![];
+        for header in &block.chunks {
+            if header.height_included != block.header.height {
+                continue;
+            }
+            let chunk_hash = ChunkHash(header.chunk_hash);
+            let chunk = self
+                .view_client_sender
+                .send_async(GetChunk::ChunkHash(chunk_hash))
+                .await
+                .map_err(|err| format!("{err:?}"))
+                .and_then(|chunk| chunk.map_err(|err| err.to_string()));
+            match chunk {
+                Ok(chunk) => chunks.push(chunk),
+                Err(err) => {
+                    tracing::warn!(target: "jsonrpc", %err, chunk_hash = ?header.chunk_hash, "Failed to get a chunk for the subscriptions")
+                }
+            }
+        }
+        chunks
+    }
+}
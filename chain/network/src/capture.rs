@@ -0,0 +1,307 @@
+//! Capture of the peer messages sent and received by the node, see
+//! `TrafficCaptureConfig`.
+//!
+//! Every captured message is appended to the capture file as its length, a
+//! little endian u32, followed by the borsh serialized `CapturedMessage`. The
+//! file is rotated by size and a record is never split between two files, so
+//! every file can be read on its own with `CaptureReader`.
+
+use crate::network_protocol::{Encoding, ParsePeerMessageError, PeerMessage};
+use crate::stats::metrics;
+use near_async::time;
+use near_o11y::rotating_file::RotatingFileWriter;
+use near_primitives::network::PeerId;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread::JoinHandle;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TrafficCaptureConfig {
+    /// File the messages are appended to. The rotated files are kept next to
+    /// it with the `.1`, `.2`, ... suffixes, `.1` being the most recent one.
+    pub file: PathBuf,
+    /// Size in bytes after which the file is rotated.
+    pub max_file_size: u64,
+    /// Number of rotated files kept next to `file`.
+    pub max_rotated_files: usize,
+    /// Total size in bytes of the captured messages waiting to be written.
+    /// Messages captured above it are dropped.
+    pub max_pending_bytes: usize,
+    /// If not empty, only the messages of these types are captured, e.g.
+    /// `Block` or `BlockApproval`. Routed messages have the type of their body.
+    pub message_types: Vec<String>,
+    /// If not empty, only the messages exchanged with these peers are
+    /// captured. Messages of inbound connections received before the
+    /// handshake are then skipped, as the peer isn't known yet.
+    pub peers: Vec<PeerId>,
+}
+
+impl Default for TrafficCaptureConfig {
+    fn default() -> Self {
+        Self {
+            file: "network_capture.bin".into(),
+            max_file_size: 1024 * 1024 * 1024,
+            max_rotated_files: 5,
+            max_pending_bytes: 64 * 1024 * 1024,
+            message_types: vec![],
+            peers: vec![],
+        }
+    }
+}
+
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Received,
+    Sent,
+}
+
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CapturedMessage {
+    /// Unix timestamp in nanoseconds.
+    pub timestamp_nanos: u64,
+    pub direction: Direction,
+    pub peer_addr: String,
+    /// Unknown for inbound connections until the handshake.
+    pub peer_id: Option<PeerId>,
+    pub encoding: Encoding,
+    pub message_type: String,
+    /// The message as it was sent over the connection.
+    pub data: Vec<u8>,
+}
+
+impl CapturedMessage {
+    pub fn parse(&self) -> Result<PeerMessage, ParsePeerMessageError> {
+        PeerMessage::deserialize(self.encoding, &self.data)
+    }
+}
+
+/// Writer of the capture file, which appends the messages on a dedicated
+/// thread. Messages are dropped when more than `max_pending_bytes` of them
+/// wait for the thread.
+pub(crate) struct TrafficCapture {
+    config: TrafficCaptureConfig,
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    pending_bytes: Arc<AtomicUsize>,
+    writer_thread: Option<JoinHandle<()>>,
+}
+
+impl TrafficCapture {
+    pub fn new(config: TrafficCaptureConfig) -> io::Result<Self> {
+        let mut writer = RotatingFileWriter::new(
+            config.file.clone(),
+            config.max_file_size,
+            config.max_rotated_files,
+        )?;
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        let pending_bytes = Arc::new(AtomicUsize::new(0));
+        let writer_thread = std::thread::Builder::new().name("traffic_capture".to_string()).spawn({
+            let pending_bytes = pending_bytes.clone();
+            move || {
+                for record in receiver {
+                    // A single write, so that the record isn't split by the rotation.
+                    if let Err(err) = writer.write_all(&record) {
+                        tracing::warn!(target: "network", ?err, "failed to write a captured message");
+                    }
+                    pending_bytes.fetch_sub(record.len(), Ordering::Relaxed);
+                }
+                let _ = writer.flush();
+            }
+        })?;
+        Ok(Self { config, sender: Some(sender), pending_bytes, writer_thread: Some(writer_thread) })
+    }
+
+    fn is_captured(&self, peer_id: Option<&PeerId>, message_type: &str) -> bool {
+        if !self.config.message_types.is_empty()
+            && !self.config.message_types.iter().any(|t| t == message_type)
+        {
+            return false;
+        }
+        if !self.config.peers.is_empty()
+            && !peer_id.is_some_and(|peer_id| self.config.peers.contains(peer_id))
+        {
+            return false;
+        }
+        true
+    }
+
+    pub fn capture(
+        &self,
+        clock: &time::Clock,
+        direction: Direction,
+        peer_addr: SocketAddr,
+        peer_id: Option<&PeerId>,
+        encoding: Encoding,
+        message_type: &str,
+        data: &[u8],
+    ) {
+        if !self.is_captured(peer_id, message_type) {
+            return;
+        }
+        let message = CapturedMessage {
+            timestamp_nanos: clock.now_utc().unix_timestamp_nanos().try_into().unwrap_or_default(),
+            direction,
+            peer_addr: peer_addr.to_string(),
+            peer_id: peer_id.cloned(),
+            encoding,
+            message_type: message_type.to_string(),
+            data: data.to_vec(),
+        };
+        let Ok(record) = encode_record(&message) else {
+            return;
+        };
+        let len = record.len();
+        let pending_bytes = self.pending_bytes.fetch_add(len, Ordering::Relaxed) + len;
+        let sent = pending_bytes <= self.config.max_pending_bytes
+            && self.sender.as_ref().is_some_and(|sender| sender.send(record).is_ok());
+        if !sent {
+            self.pending_bytes.fetch_sub(len, Ordering::Relaxed);
+            metrics::TRAFFIC_CAPTURE_DROPPED_MESSAGES.inc();
+        }
+    }
+}
+
+impl Drop for TrafficCapture {
+    /// Writes the pending messages before returning.
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer_thread) = self.writer_thread.take() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+fn encode_record(message: &CapturedMessage) -> io::Result<Vec<u8>> {
+    let message = borsh::to_vec(message)?;
+    let len = u32::try_from(message.len()).map_err(io::Error::other)?;
+    let mut record = Vec::with_capacity(message.len() + 4);
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(&message);
+    Ok(record)
+}
+
+/// Iterator over the messages of a capture file.
+pub struct CaptureReader<R> {
+    reader: R,
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    fn read_message(&mut self) -> io::Result<Option<CapturedMessage>> {
+        let mut len = [0u8; 4];
+        // The file ends cleanly only between the records.
+        let mut read = 0;
+        while read < len.len() {
+            match self.reader.read(&mut len[read..])? {
+                0 if read == 0 => return Ok(None),
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => read += n,
+            }
+        }
+        let mut message = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut message)?;
+        borsh::from_slice(&message).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<CapturedMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_message().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{KeyType, SecretKey};
+
+    fn peer_id(seed: &str) -> PeerId {
+        PeerId::new(SecretKey::from_seed(KeyType::ED25519, seed).public_key())
+    }
+
+    #[test]
+    fn test_capture_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TrafficCaptureConfig {
+            file: dir.path().join("capture.bin"),
+            message_types: vec!["Block".to_string()],
+            peers: vec![peer_id("a")],
+            ..Default::default()
+        };
+        let capture = TrafficCapture::new(config).unwrap();
+        assert!(capture.is_captured(Some(&peer_id("a")), "Block"));
+        assert!(!capture.is_captured(Some(&peer_id("a")), "BlockApproval"));
+        assert!(!capture.is_captured(Some(&peer_id("b")), "Block"));
+        assert!(!capture.is_captured(None, "Block"));
+    }
+
+    fn capture_messages(dir: &Path, max_pending_bytes: usize) -> Vec<CapturedMessage> {
+        let file = dir.join(format!("capture_{max_pending_bytes}.bin"));
+        let config =
+            TrafficCaptureConfig { file: file.clone(), max_pending_bytes, ..Default::default() };
+        let capture = TrafficCapture::new(config).unwrap();
+        let clock = time::FakeClock::default();
+        for i in 0..3 {
+            capture.capture(
+                &clock.clock(),
+                Direction::Sent,
+                "127.0.0.1:24567".parse().unwrap(),
+                None,
+                Encoding::Proto,
+                "Block",
+                &[i; 1000],
+            );
+        }
+        drop(capture);
+        CaptureReader::open(&file).unwrap().collect::<io::Result<Vec<_>>>().unwrap()
+    }
+
+    #[test]
+    fn test_capture_max_pending_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(capture_messages(dir.path(), usize::MAX).len(), 3);
+        assert_eq!(capture_messages(dir.path(), 0).len(), 0);
+    }
+
+    #[test]
+    fn test_capture_reader() {
+        let messages = (0..3)
+            .map(|i| CapturedMessage {
+                timestamp_nanos: i,
+                direction: if i % 2 == 0 { Direction::Received } else { Direction::Sent },
+                peer_addr: "127.0.0.1:24567".to_string(),
+                peer_id: (i > 0).then(|| peer_id("a")),
+                encoding: Encoding::Proto,
+                message_type: "Block".to_string(),
+                data: vec![i as u8; i as usize * 10],
+            })
+            .collect::<Vec<_>>();
+        let mut file = vec![];
+        for message in &messages {
+            file.extend(encode_record(message).unwrap());
+        }
+        let read = CaptureReader::new(file.as_slice()).collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(read, messages);
+
+        // A record cut by a crash is an error.
+        file.pop();
+        let read = CaptureReader::new(file.as_slice()).collect::<Vec<_>>();
+        assert_eq!(read.len(), 3);
+        assert!(read[2].is_err());
+    }
+}
@@ -69,6 +69,8 @@ pub struct StateRequestPart {
     pub shard_id: ShardId,
     pub sync_hash: CryptoHash,
     pub part_id: u64,
+    /// Peer which requested the part, used to rate limit the requests per peer.
+    pub peer_id: PeerId,
 }
 
 /// Response to state request.
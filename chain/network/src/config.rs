@@ -1,9 +1,10 @@
 use crate::blacklist;
+use crate::capture::TrafficCaptureConfig;
 use crate::concurrency::rate;
 use crate::network_protocol::PeerAddr;
 use crate::network_protocol::PeerInfo;
 use crate::peer_manager::peer_store;
-use crate::rate_limits::messages_limits;
+use crate::rate_limits::{bandwidth_limits, messages_limits};
 use crate::snapshot_hosts;
 use crate::stun;
 use crate::tcp;
@@ -17,6 +18,9 @@ use near_primitives::network::PeerId;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::types::AccountId;
 use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::version::{
+    PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION, ProtocolVersion,
+};
 use std::collections::HashSet;
 use std::sync::Arc;
 
@@ -196,6 +200,20 @@ pub struct NetworkConfig {
     /// Configuration of rate limits for incoming messages.
     pub received_messages_rate_limits: messages_limits::Config,
 
+    /// Limits on the bandwidth used by the messages sent to each peer.
+    pub sent_bandwidth_limits: bandwidth_limits::Config,
+
+    /// Oldest network protocol version a peer may negotiate in the handshake.
+    /// Never lower than `PEER_MIN_ALLOWED_PROTOCOL_VERSION`.
+    pub min_peer_protocol_version: ProtocolVersion,
+
+    /// Replay-protected routed messages (see `RoutedMessageBody::is_replay_protected`)
+    /// delivered to this node again within this period are dropped. Zero disables it.
+    pub routed_message_replay_window: time::Duration,
+
+    /// Capture of the messages exchanged with the peers, see `crate::capture`.
+    pub traffic_capture: Option<TrafficCaptureConfig>,
+
     #[cfg(test)]
     pub(crate) event_sink:
         near_async::messaging::Sender<crate::peer_manager::peer_manager_actor::Event>,
@@ -328,6 +346,13 @@ impl NetworkConfig {
             snapshot_hosts: snapshot_hosts::Config {
                 snapshot_hosts_cache_size: cfg.snapshot_hosts_cache_size,
                 part_selection_cache_batch_size: 10,
+                // A peer sends all the hosts it knows about when the
+                // connection is established, and then the new ones as they
+                // are published, about once per epoch per host.
+                rate_limit: Some(rate::Limit {
+                    burst: cfg.snapshot_hosts_cache_size as u64,
+                    qps: 1.,
+                }),
             },
             whitelist_nodes: if cfg.whitelist_nodes.is_empty() {
                 vec![]
@@ -385,6 +410,14 @@ impl NetworkConfig {
             },
             // Use a preset to configure rate limits and override entries with user defined values later.
             received_messages_rate_limits: messages_limits::Config::standard_preset(),
+            sent_bandwidth_limits: cfg.sent_bandwidth_limits,
+            min_peer_protocol_version: cfg
+                .min_peer_protocol_version
+                .map_or(PEER_MIN_ALLOWED_PROTOCOL_VERSION, |version| {
+                    version.max(PEER_MIN_ALLOWED_PROTOCOL_VERSION)
+                }),
+            routed_message_replay_window: cfg.routed_message_replay_window.try_into()?,
+            traffic_capture: cfg.traffic_capture,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -426,6 +459,7 @@ impl NetworkConfig {
             snapshot_hosts: snapshot_hosts::Config {
                 snapshot_hosts_cache_size: 1000,
                 part_selection_cache_batch_size: 10,
+                rate_limit: None,
             },
             whitelist_nodes: vec![],
             handshake_timeout: time::Duration::seconds(5),
@@ -463,6 +497,10 @@ impl NetworkConfig {
             }),
             skip_tombstones: None,
             received_messages_rate_limits: messages_limits::Config::default(),
+            sent_bandwidth_limits: bandwidth_limits::Config::default(),
+            min_peer_protocol_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+            routed_message_replay_window: time::Duration::seconds(300),
+            traffic_capture: None,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -503,6 +541,14 @@ impl NetworkConfig {
             );
         }
 
+        if self.min_peer_protocol_version > PROTOCOL_VERSION {
+            anyhow::bail!(
+                "min_peer_protocol_version({}) is newer than the protocol version of this node({})",
+                self.min_peer_protocol_version,
+                PROTOCOL_VERSION
+            );
+        }
+
         if !(self.max_send_peers <= PEERS_RESPONSE_MAX_PEERS) {
             anyhow::bail!(
                 "max_send_peers({}) can be at most {}",
@@ -517,10 +563,14 @@ impl NetworkConfig {
         self.routing_table_update_rate_limit
             .validate()
             .context("routing_table_update_rate_limit")?;
+        if let Some(rate_limit) = &self.snapshot_hosts.rate_limit {
+            rate_limit.validate().context("snapshot_hosts.rate_limit")?;
+        }
 
         if let Err(err) = self.received_messages_rate_limits.validate() {
             anyhow::bail!("One or more invalid rate limits: {err:?}");
         }
+        self.sent_bandwidth_limits.validate().context("sent_bandwidth_limits")?;
 
         Ok(VerifiedConfig { node_id: self.node_id(), inner: self })
     }
@@ -560,6 +610,7 @@ mod test {
     use crate::network_protocol;
     use crate::network_protocol::testonly as data;
     use crate::network_protocol::{AccountData, VersionedAccountData};
+    use crate::rate_limits::bandwidth_limits::{BandwidthLimit, MessageClass};
     use crate::rate_limits::messages_limits::{
         RateLimitedPeerMessageKey::BlockHeaders, SingleMessageConfig,
     };
@@ -716,4 +767,17 @@ mod test {
             .insert(BlockHeaders, SingleMessageConfig::new(1, 4.0, None));
         assert!(nc.verify().is_ok());
     }
+
+    #[test]
+    fn sent_bandwidth_limits_error() {
+        let limit = |bytes_per_second| BandwidthLimit { bytes_per_second, burst_bytes: 1000 };
+        let mut nc = config::NetworkConfig::from_seed("123", tcp::ListenerAddr::reserve_for_test());
+        nc.sent_bandwidth_limits.per_class.insert(MessageClass::StateParts, limit(0));
+        assert!(nc.verify().is_err());
+
+        let mut nc = config::NetworkConfig::from_seed("123", tcp::ListenerAddr::reserve_for_test());
+        nc.sent_bandwidth_limits.per_class.insert(MessageClass::StateParts, limit(1000));
+        nc.sent_bandwidth_limits.per_peer = Some(limit(5000));
+        assert!(nc.verify().is_ok());
+    }
 }
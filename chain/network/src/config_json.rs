@@ -1,7 +1,9 @@
+use crate::capture::TrafficCaptureConfig;
 use crate::network_protocol::PeerAddr;
-use crate::rate_limits::messages_limits;
+use crate::rate_limits::{bandwidth_limits, messages_limits};
 use crate::stun;
 use near_async::time::Duration;
+use near_primitives::version::ProtocolVersion;
 
 /// Time to persist Accounts Id in the router without removing them in seconds.
 pub const TTL_ACCOUNT_ID_ROUTER: i64 = 60 * 60;
@@ -33,6 +35,10 @@ fn default_so_send_buffer_size() -> Option<u32> {
 fn default_peer_recent_time_window() -> Duration {
     Duration::seconds(600)
 }
+/// Consensus messages delivered again within this period are dropped as replays.
+fn default_routed_message_replay_window() -> Duration {
+    Duration::seconds(300)
+}
 /// Number of peers to keep while removing a connection.
 /// Used to avoid disconnecting from peers we have been connected since long time.
 fn default_safe_set_size() -> u32 {
@@ -209,6 +215,28 @@ pub struct Config {
     /// such a case.
     #[serde(default = "default_trusted_stun_servers")]
     pub trusted_stun_servers: Vec<stun::ServerAddr>,
+    /// Routed consensus messages (approvals, chunk endorsements and state witness parts)
+    /// delivered to this node again within this period are dropped as replays.
+    /// Set to zero to disable the replay protection.
+    #[serde(default = "default_routed_message_replay_window")]
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub routed_message_replay_window: Duration,
+    /// Oldest network protocol version that peers are allowed to use.
+    /// Peers which can't negotiate at least this version are refused during the handshake.
+    /// It can only raise the floor above the oldest version supported by this binary,
+    /// e.g. to stop talking to nodes which haven't upgraded yet during a rollout.
+    #[serde(default)]
+    pub min_peer_protocol_version: Option<ProtocolVersion>,
+    /// If set, the messages exchanged with the peers are written to a file
+    /// which can be read with `neard network-capture`.
+    #[serde(default)]
+    pub traffic_capture: Option<TrafficCaptureConfig>,
+    /// Limits on the bandwidth used by the messages sent to each peer, in total and
+    /// per class of messages (blocks, chunks, witnesses, state parts). The messages over
+    /// the limits are delayed, and dropped only once too many messages of their class wait.
+    /// TIER1 connections are not limited.
+    #[serde(default)]
+    pub sent_bandwidth_limits: bandwidth_limits::Config,
     // Experimental part of the JSON config. Regular users/validators should not have to set any values there.
     // Field names in here can change/disappear at any moment without warning.
     #[serde(default)]
@@ -332,6 +360,10 @@ impl Default for Config {
             public_addrs: vec![],
             allow_private_ip_in_public_addrs: false,
             trusted_stun_servers: default_trusted_stun_servers(),
+            routed_message_replay_window: default_routed_message_replay_window(),
+            min_peer_protocol_version: None,
+            traffic_capture: None,
+            sent_bandwidth_limits: Default::default(),
             experimental: Default::default(),
         }
     }
@@ -0,0 +1,199 @@
+//! Topic based gossip of auxiliary data.
+//!
+//! Some data is published by the nodes and flooded to the whole network rather
+//! than routed to a target: every node keeps the latest item of each publisher
+//! and forwards the new ones to its peers. A `Topic` describes how the items
+//! of such data are keyed, versioned and validated. `TopicState` implements the
+//! rest: it keeps the latest item per key, drops the items a peer sends above
+//! the rate limit of the topic, validates the new items on the rayon thread
+//! pool and returns the ones to forward.
+//!
+//! The items of a topic are still carried by a `PeerMessage` of their own, so
+//! the layer doesn't change the wire protocol. SnapshotHostInfos are the first
+//! topic, see `snapshot_hosts`.
+
+use crate::concurrency;
+use crate::concurrency::rate;
+use crate::rate_limits::token_bucket::TokenBucket;
+use crate::stats::metrics;
+use lru::LruCache;
+use near_async::time::Instant;
+use near_primitives::network::PeerId;
+use parking_lot::Mutex;
+use rayon::iter::ParallelBridge;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+/// Number of peers for which the remaining rate limit is tracked.
+const MAX_PEERS_RATE_LIMITED: usize = 1024;
+
+pub(crate) trait Topic: 'static {
+    /// Name of the topic in the metrics.
+    const NAME: &'static str;
+    type Key: Clone + Eq + Hash + Send + 'static;
+    type Item: Send + Sync + 'static;
+    type Error: std::error::Error + Send + 'static;
+
+    /// Every publisher has a single item at a time, stored under its key.
+    fn key(item: &Self::Item) -> Self::Key;
+    /// Whether `item` supersedes `old`, the item stored under the same key.
+    fn is_newer(item: &Self::Item, old: &Self::Item) -> bool;
+    /// Validates the item, e.g. its signature.
+    fn validate(item: &Self::Item) -> Result<(), Self::Error>;
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone)]
+pub(crate) enum GossipError<E> {
+    /// Sending multiple items with the same key is considered malicious,
+    /// since all but one are obviously outdated.
+    #[error("found multiple items with the same key")]
+    DuplicateKey,
+    #[error(transparent)]
+    Invalid(E),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct TopicConfig {
+    /// Maximum number of items stored, the least recently updated are evicted.
+    pub max_items: usize,
+    /// Items accepted from every peer. If not set, all the items are accepted.
+    pub rate_limit: Option<rate::Limit>,
+}
+
+struct Inner<T: Topic> {
+    items: LruCache<T::Key, Arc<T::Item>>,
+    /// Items the peers may still send.
+    peer_rate_limits: LruCache<PeerId, TokenBucket>,
+}
+
+impl<T: Topic> Inner<T> {
+    fn is_new(&self, item: &T::Item) -> bool {
+        match self.items.peek(&T::key(item)) {
+            Some(old) => T::is_newer(item, old),
+            None => true,
+        }
+    }
+
+    fn try_insert(&mut self, item: Arc<T::Item>) -> Option<Arc<T::Item>> {
+        if !self.is_new(&item) {
+            return None;
+        }
+        self.items.push(T::key(&item), item.clone());
+        Some(item)
+    }
+}
+
+pub(crate) struct TopicState<T: Topic> {
+    rate_limit: Option<rate::Limit>,
+    inner: Mutex<Inner<T>>,
+}
+
+impl<T: Topic> TopicState<T> {
+    pub fn new(config: TopicConfig) -> Self {
+        Self {
+            rate_limit: config.rate_limit,
+            inner: Mutex::new(Inner {
+                items: LruCache::new(NonZeroUsize::new(config.max_items).unwrap()),
+                peer_rate_limits: LruCache::new(NonZeroUsize::new(MAX_PEERS_RATE_LIMITED).unwrap()),
+            }),
+        }
+    }
+
+    /// Drops the items `peer_id` sent above the rate limit of the topic.
+    pub fn rate_limit(
+        &self,
+        peer_id: &PeerId,
+        now: Instant,
+        mut items: Vec<Arc<T::Item>>,
+    ) -> Vec<Arc<T::Item>> {
+        let Some(limit) = self.rate_limit else {
+            return items;
+        };
+        let mut inner = self.inner.lock();
+        let bucket = inner.peer_rate_limits.get_or_insert_mut(peer_id.clone(), || {
+            let burst = limit.burst.try_into().unwrap_or(u32::MAX);
+            TokenBucket::new(burst, burst, limit.qps as f32, now)
+                .expect("the rate limit should be validated")
+        });
+        let num_allowed = items.iter().take_while(|_| bucket.acquire(1, now)).count();
+        let num_dropped = items.len() - num_allowed;
+        if num_dropped > 0 {
+            metrics::GOSSIP_ITEMS_DROPPED.with_label_values(&[T::NAME]).inc_by(num_dropped as u64);
+            items.truncate(num_allowed);
+        }
+        items
+    }
+
+    /// Selects the new items and validates them.
+    /// Returns the valid new items and an optional error.
+    /// Note that even if an error is returned, the items validated until then
+    /// are returned anyway.
+    async fn validate(
+        &self,
+        items: Vec<Arc<T::Item>>,
+    ) -> (Vec<Arc<T::Item>>, Option<GossipError<T::Error>>) {
+        // Filter out the items which are outdated or which we already have.
+        let mut new_items = HashMap::new();
+        {
+            let inner = self.inner.lock();
+            for item in items {
+                let key = T::key(&item);
+                if new_items.contains_key(&key) {
+                    return (vec![], Some(GossipError::DuplicateKey));
+                }
+                if inner.is_new(&item) {
+                    new_items.insert(key, item);
+                }
+            }
+        }
+
+        // Validate the items in parallel, stopping at the first error.
+        let (items, result) = concurrency::rayon::run(move || {
+            concurrency::rayon::try_map_result(new_items.into_values().par_bridge(), |item| {
+                T::validate(&item).map(|()| item)
+            })
+        })
+        .await;
+        (items, result.err().map(GossipError::Invalid))
+    }
+
+    /// Validates the items and stores the valid new ones.
+    /// Returns the items stored, which should be forwarded to the peers, and
+    /// optionally a validation error.
+    /// The lock is only acquired for the final update, after the validation.
+    pub async fn insert(
+        &self,
+        items: Vec<Arc<T::Item>>,
+    ) -> (Vec<Arc<T::Item>>, Option<GossipError<T::Error>>) {
+        let (items, err) = self.validate(items).await;
+        // Store the valid items, even if an error has been encountered.
+        let mut inserted = vec![];
+        let mut inner = self.inner.lock();
+        for item in items {
+            if let Some(item) = inner.try_insert(item) {
+                inserted.push(item);
+            }
+        }
+        metrics::GOSSIP_ITEMS_INSERTED.with_label_values(&[T::NAME]).inc_by(inserted.len() as u64);
+        (inserted, err)
+    }
+
+    /// Skips the validation. Used only for the items published by the local
+    /// node. Returns the item if it's new.
+    pub fn insert_skip_validation(&self, item: Arc<T::Item>) -> Option<Arc<T::Item>> {
+        self.inner.lock().try_insert(item)
+    }
+
+    pub fn get(&self, key: &T::Key) -> Option<Arc<T::Item>> {
+        self.inner.lock().items.peek(key).cloned()
+    }
+
+    pub fn items(&self) -> Vec<Arc<T::Item>> {
+        self.inner.lock().items.iter().map(|(_, item)| item.clone()).collect()
+    }
+}
@@ -0,0 +1,110 @@
+use crate::concurrency::rate;
+use crate::gossip::{GossipError, Topic, TopicConfig, TopicState};
+use crate::network_protocol::testonly as data;
+use crate::testonly::{AsSet as _, make_rng};
+use near_async::time;
+use near_o11y::testonly::init_test_logger;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Item {
+    key: u64,
+    version: u64,
+    valid: bool,
+}
+
+fn item(key: u64, version: u64) -> Arc<Item> {
+    Arc::new(Item { key, version, valid: true })
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("invalid item")]
+struct InvalidItem;
+
+struct TestTopic;
+
+impl Topic for TestTopic {
+    const NAME: &'static str = "test";
+    type Key = u64;
+    type Item = Item;
+    type Error = InvalidItem;
+
+    fn key(item: &Item) -> u64 {
+        item.key
+    }
+
+    fn is_newer(item: &Item, old: &Item) -> bool {
+        item.version > old.version
+    }
+
+    fn validate(item: &Item) -> Result<(), InvalidItem> {
+        if item.valid { Ok(()) } else { Err(InvalidItem) }
+    }
+}
+
+#[tokio::test]
+async fn insert() {
+    init_test_logger();
+    let topic = TopicState::<TestTopic>::new(TopicConfig { max_items: 100, rate_limit: None });
+
+    let (res, err) = topic.insert(vec![item(0, 1), item(1, 1)]).await;
+    assert_eq!(err, None);
+    assert_eq!(res.as_set(), [&item(0, 1), &item(1, 1)].into());
+
+    // Only the newer items are stored and returned.
+    let (res, err) = topic.insert(vec![item(0, 2), item(1, 1), item(2, 0)]).await;
+    assert_eq!(err, None);
+    assert_eq!(res.as_set(), [&item(0, 2), &item(2, 0)].into());
+    assert_eq!(topic.get(&0), Some(item(0, 2)));
+    assert_eq!(topic.items().as_set(), [&item(0, 2), &item(1, 1), &item(2, 0)].into());
+
+    // Multiple items with the same key are rejected.
+    let (res, err) = topic.insert(vec![item(3, 1), item(3, 2)]).await;
+    assert_eq!(err, Some(GossipError::DuplicateKey));
+    assert!(res.is_empty());
+    assert_eq!(topic.get(&3), None);
+
+    // The valid items are stored even if another one is invalid.
+    let invalid = Arc::new(Item { key: 4, version: 1, valid: false });
+    let (res, err) = topic.insert(vec![invalid, item(5, 1)]).await;
+    assert_eq!(err, Some(GossipError::Invalid(InvalidItem)));
+    assert!(res.len() <= 1);
+    assert_eq!(topic.get(&4), None);
+
+    assert_eq!(topic.insert_skip_validation(item(6, 1)), Some(item(6, 1)));
+    assert_eq!(topic.insert_skip_validation(item(6, 1)), None);
+}
+
+#[tokio::test]
+async fn evict() {
+    init_test_logger();
+    let topic = TopicState::<TestTopic>::new(TopicConfig { max_items: 2, rate_limit: None });
+    topic.insert(vec![item(0, 1)]).await;
+    topic.insert(vec![item(1, 1)]).await;
+    topic.insert(vec![item(2, 1)]).await;
+    assert_eq!(topic.items().as_set(), [&item(1, 1), &item(2, 1)].into());
+}
+
+#[test]
+fn rate_limit() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let clock = time::FakeClock::default();
+    let topic = TopicState::<TestTopic>::new(TopicConfig {
+        max_items: 100,
+        rate_limit: Some(rate::Limit { burst: 3, qps: 1. }),
+    });
+    let peer0 = data::make_peer_id(rng);
+    let peer1 = data::make_peer_id(rng);
+    let items = |n: u64| (0..n).map(|key| item(key, 1)).collect::<Vec<_>>();
+
+    // Up to the burst is accepted at once.
+    assert_eq!(topic.rate_limit(&peer0, clock.now(), items(5)), items(3));
+    assert!(topic.rate_limit(&peer0, clock.now(), items(1)).is_empty());
+    // The budget of every peer is tracked separately.
+    assert_eq!(topic.rate_limit(&peer1, clock.now(), items(2)), items(2));
+    // The budget is refilled over time.
+    clock.advance(time::Duration::seconds(2));
+    assert_eq!(topic.rate_limit(&peer0, clock.now(), items(5)), items(2));
+}
@@ -1,10 +1,14 @@
 #![cfg_attr(enable_const_type_id, feature(const_type_id))]
 
 pub use crate::peer_manager::peer_manager_actor::{Event, PeerManagerActor};
+pub use crate::rate_limits::bandwidth_limits::{
+    BandwidthLimit, Config as BandwidthLimitsConfig, MessageClass,
+};
 pub use crate::rate_limits::messages_limits::OverrideConfig as MessagesLimitsOverrideConfig;
 
 mod accounts_data;
 mod announce_accounts;
+mod gossip;
 mod network_protocol;
 mod peer;
 mod peer_manager;
@@ -17,6 +21,7 @@ mod stun;
 
 pub mod actix;
 pub mod blacklist;
+pub mod capture;
 pub mod client;
 pub mod concurrency;
 pub mod config;
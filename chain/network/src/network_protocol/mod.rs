@@ -14,6 +14,7 @@ use near_primitives::stateless_validation::contract_distribution::ContractCodeRe
 use near_primitives::stateless_validation::contract_distribution::ContractCodeResponse;
 use near_primitives::stateless_validation::contract_distribution::PartialEncodedContractDeploys;
 use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitness;
+use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitnessRequest;
 use near_primitives::stateless_validation::state_witness::ChunkStateWitnessAck;
 pub use peer::*;
 pub use state_sync::*;
@@ -48,6 +49,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::combine_hash;
 use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::optimistic_block::OptimisticBlock;
+use near_primitives::sharding::receipt_proof_fragment::ReceiptProofFragment;
 use near_primitives::sharding::{
     ChunkHash, PartialEncodedChunk, PartialEncodedChunkPart, ReceiptProof, ShardChunkHeader,
 };
@@ -55,7 +57,7 @@ use near_primitives::state_sync::{ShardStateSyncResponse, ShardStateSyncResponse
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::AccountId;
 use near_primitives::types::{BlockHeight, ShardId};
-use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::validator_signer::{SignerError, ValidatorSigner};
 use near_primitives::views::FinalExecutionOutcomeView;
 use near_schema_checker_lib::ProtocolSchema;
 use protobuf::Message as _;
@@ -196,7 +198,7 @@ impl VersionedAccountData {
                 MAX_ACCOUNT_DATA_SIZE_BYTES
             );
         }
-        let signature = signer.sign_bytes(&payload);
+        let signature = signer.sign_bytes(&payload)?;
         Ok(SignedAccountData {
             account_data: self,
             payload: AccountKeySignedPayload { payload, signature },
@@ -268,18 +270,18 @@ impl OwnedAccount {
     /// Serializes OwnedAccount to proto and signs it using `signer`.
     /// Panics if OwnedAccount.account_key doesn't match signer.public_key(),
     /// as this would likely be a bug.
-    pub fn sign(self, signer: &ValidatorSigner) -> SignedOwnedAccount {
+    pub fn sign(self, signer: &ValidatorSigner) -> Result<SignedOwnedAccount, SignerError> {
         assert_eq!(
             self.account_key,
             signer.public_key(),
             "OwnedAccount.account_key doesn't match the signer's account_key"
         );
         let payload = proto::AccountKeyPayload::from(&self).write_to_bytes().unwrap();
-        let signature = signer.sign_bytes(&payload);
-        SignedOwnedAccount {
+        let signature = signer.sign_bytes(&payload)?;
+        Ok(SignedOwnedAccount {
             owned_account: self,
             payload: AccountKeySignedPayload { payload, signature },
-        }
+        })
     }
 }
 
@@ -459,7 +461,17 @@ impl fmt::Display for PeerMessage {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, strum::IntoStaticStr)]
+#[derive(
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    Debug,
+    Hash,
+    strum::IntoStaticStr,
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+)]
 pub enum Encoding {
     Borsh,
     Proto,
@@ -568,6 +580,10 @@ pub enum RoutedMessageBody {
     ContractCodeResponse(ContractCodeResponse),
     PartialEncodedContractDeploys(PartialEncodedContractDeploys),
     StateHeaderRequest(StateHeaderRequest),
+    PartialEncodedStateWitnessRequest(PartialEncodedStateWitnessRequest),
+    ReceiptProofFragment(ReceiptProofFragment),
+    ChunkRequest(ChunkRequestMsg),
+    ChunkResponse(ChunkResponseMsg),
 }
 
 impl RoutedMessageBody {
@@ -584,6 +600,19 @@ impl RoutedMessageBody {
         }
     }
 
+    /// Whether repeated deliveries of this message to its target are dropped
+    /// as replays, see `NetworkConfig::routed_message_replay_window`. These
+    /// are the consensus messages, which are only meaningful when received once.
+    pub fn is_replay_protected(&self) -> bool {
+        match self {
+            RoutedMessageBody::BlockApproval(_)
+            | RoutedMessageBody::VersionedChunkEndorsement(_)
+            | RoutedMessageBody::PartialEncodedStateWitness(_)
+            | RoutedMessageBody::PartialEncodedStateWitnessForward(_) => true,
+            _ => false,
+        }
+    }
+
     // Return true if we allow the message sent to our own account_id to be redirected back to us.
     // The default behavior is to drop all messages sent to our own account_id.
     // This is helpful in managing scenarios like sending chunk_endorsement to block_producer, where
@@ -680,6 +709,26 @@ impl fmt::Debug for RoutedMessageBody {
                 "StateHeaderRequest(sync_hash={:?}, shard_id={:?})",
                 request.sync_hash, request.shard_id,
             ),
+            RoutedMessageBody::PartialEncodedStateWitnessRequest(request) => {
+                write!(f, "PartialEncodedStateWitnessRequest({:?})", request.chunk_production_key())
+            }
+            RoutedMessageBody::ReceiptProofFragment(fragment) => write!(
+                f,
+                "ReceiptProofFragment({:?}, {}, {}/{})",
+                fragment.chunk_hash,
+                fragment.to_shard_id,
+                fragment.fragment_ord,
+                fragment.num_total_fragments,
+            ),
+            RoutedMessageBody::ChunkRequest(request) => {
+                write!(f, "ChunkRequest({:?})", request.chunk_hash)
+            }
+            RoutedMessageBody::ChunkResponse(response) => write!(
+                f,
+                "ChunkResponse({:?}, {:?})",
+                response.chunk_hash,
+                response.parts.iter().map(|p| p.part_ord).collect::<Vec<_>>()
+            ),
         }
     }
 }
@@ -765,6 +814,7 @@ impl RoutedMessage {
             RoutedMessageBody::Ping(_)
                 | RoutedMessageBody::TxStatusRequest(_, _)
                 | RoutedMessageBody::PartialEncodedChunkRequest(_)
+                | RoutedMessageBody::ChunkRequest(_)
         )
     }
 
@@ -884,6 +934,26 @@ pub struct PartialEncodedChunkResponseMsg {
     pub receipts: Vec<ReceiptProof>,
 }
 
+/// Request for enough parts to reconstruct the whole chunk, also when it is
+/// old and the node never received it, e.g. from an archival node.
+#[derive(
+    Clone, Debug, Eq, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize, ProtocolSchema,
+)]
+pub struct ChunkRequestMsg {
+    pub chunk_hash: ChunkHash,
+}
+
+/// Response to `ChunkRequestMsg` with at most the number of data parts of the
+/// chunk. The receipts are not sent separately as they can be decoded from
+/// the parts.
+#[derive(
+    Clone, Debug, Eq, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize, ProtocolSchema,
+)]
+pub struct ChunkResponseMsg {
+    pub chunk_hash: ChunkHash,
+    pub parts: Vec<PartialEncodedChunkPart>,
+}
+
 #[derive(
     PartialEq, Eq, Clone, Debug, borsh::BorshSerialize, borsh::BorshDeserialize, ProtocolSchema,
 )]
@@ -66,6 +66,7 @@ pub fn make_block(
         None,
         None,
     )
+    .unwrap()
 }
 
 pub fn make_account_id<R: Rng>(rng: &mut R) -> AccountId {
@@ -102,7 +103,7 @@ pub fn make_peer_info<R: Rng>(rng: &mut R) -> PeerInfo {
 pub fn make_announce_account<R: Rng>(rng: &mut R) -> AnnounceAccount {
     let peer_id = make_peer_id(rng);
     let validator_signer = make_validator_signer(rng);
-    AnnounceAccount::new(&validator_signer, peer_id, EpochId::default())
+    AnnounceAccount::new(&validator_signer, peer_id, EpochId::default()).unwrap()
 }
 
 pub fn make_partial_edge<R: Rng>(rng: &mut R) -> PartialEdgeInfo {
@@ -263,6 +264,7 @@ impl Chain {
             tracked_shards: Default::default(),
             block: self.blocks.last().unwrap().clone(),
             tier1_accounts: Arc::new(self.get_tier1_accounts()),
+            next_epoch_producers: Default::default(),
         }
     }
 
@@ -1,4 +1,5 @@
 use crate::accounts_data::AccountDataError;
+use crate::capture;
 use crate::client::{
     AnnounceAccountRequest, BlockHeadersRequest, BlockHeadersResponse, BlockRequest, BlockResponse,
     EpochSyncRequestMessage, EpochSyncResponseMessage, OptimisticBlockMessage, ProcessTxRequest,
@@ -23,7 +24,7 @@ use crate::peer_manager::network_state::{NetworkState, PRUNE_EDGES_AFTER};
 use crate::peer_manager::peer_manager_actor::Event;
 use crate::peer_manager::peer_manager_actor::MAX_TIER2_PEERS;
 use crate::private_actix::{RegisterPeerError, SendMessage};
-use crate::rate_limits::messages_limits;
+use crate::rate_limits::{bandwidth_limits, messages_limits};
 #[cfg(feature = "distance_vector_routing")]
 use crate::routing::NetworkTopologyChange;
 use crate::routing::edge::verify_nonce;
@@ -45,9 +46,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::types::EpochId;
 use near_primitives::utils::DisplayOption;
-use near_primitives::version::{
-    PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION, ProtocolVersion,
-};
+use near_primitives::version::{PROTOCOL_VERSION, ProtocolVersion};
 use parking_lot::Mutex;
 use rand::seq::IteratorRandom;
 use rand::thread_rng;
@@ -330,6 +329,11 @@ impl PeerActor {
             &network_state.config.received_messages_rate_limits,
             clock.now(),
         );
+        let send_queue =
+            bandwidth_limits::ShapedQueue::new(bandwidth_limits::BandwidthLimits::from_config(
+                &network_state.config.sent_bandwidth_limits,
+                clock.now(),
+            ));
         // recv is the HandshakeSignal returned by this spawn_inner() call.
         let (send, recv): (HandshakeSignalSender, HandshakeSignal) =
             tokio::sync::oneshot::channel();
@@ -340,7 +344,13 @@ impl PeerActor {
                 let peer_addr = stream.peer_addr;
                 let stream_type = stream.type_.clone();
                 let stats = Arc::new(connection::Stats::default());
-                let framed = stream::FramedStream::spawn(ctx, stream, stats.clone());
+                let framed = stream::FramedStream::spawn(
+                    ctx,
+                    stream,
+                    stats.clone(),
+                    clock.clone(),
+                    send_queue,
+                );
                 Self {
                     closing_reason: None,
                     clock,
@@ -453,10 +463,28 @@ impl PeerActor {
         };
 
         let bytes = msg.serialize(enc);
+        if let Some(capture) = &self.network_state.traffic_capture {
+            capture.capture(
+                &self.clock,
+                capture::Direction::Sent,
+                self.peer_addr,
+                self.other_peer_id(),
+                enc,
+                msg.msg_variant(),
+                &bytes,
+            );
+        }
         self.tracker.lock().increment_sent(&self.clock, bytes.len() as u64);
         let bytes_len = bytes.len();
         tracing::trace!(target: "network", msg_len = bytes_len);
-        self.framed.send(stream::Frame(bytes));
+        // Messages sent on TIER1 connections and before the handshake completes are never delayed.
+        let class = match &self.peer_status {
+            PeerStatus::Ready(conn) if conn.tier != tcp::Tier::T1 => {
+                Some(bandwidth_limits::MessageClass::of(msg))
+            }
+            _ => None,
+        };
+        self.framed.send(stream::Frame(bytes), class);
         metrics::PEER_DATA_SENT_BYTES.inc_by(bytes_len as u64);
         let msg_type = msg.msg_variant();
         metrics::PEER_MESSAGE_SENT_BY_TYPE_TOTAL.with_label_values(&[msg_type]).inc();
@@ -474,7 +502,7 @@ impl PeerActor {
             };
         let handshake = Handshake {
             protocol_version: spec.protocol_version,
-            oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+            oldest_supported_version: self.network_state.config.min_peer_protocol_version,
             sender_peer_id: self.network_state.config.node_id(),
             target_peer_id: spec.peer_id,
             sender_listen_port: self.network_state.config.node_addr.as_ref().map(|a| a.port()),
@@ -486,13 +514,17 @@ impl PeerActor {
                 archival: self.network_state.config.archive,
             },
             partial_edge_info: spec.partial_edge_info,
-            owned_account: self.network_state.config.validator.signer.get().map(|signer| {
+            owned_account: self.network_state.config.validator.signer.get().and_then(|signer| {
                 OwnedAccount {
                     account_key: signer.public_key(),
                     peer_id: self.network_state.config.node_id(),
                     timestamp: self.clock.now_utc(),
                 }
                 .sign(&signer)
+                .inspect_err(
+                    |err| tracing::error!(target: "network", ?err, "Failed to sign owned account"),
+                )
+                .ok()
             }),
         };
         let msg = match spec.tier {
@@ -564,18 +596,21 @@ impl PeerActor {
                 }
             }
             ConnectingStatus::Inbound { .. } => {
-                if PEER_MIN_ALLOWED_PROTOCOL_VERSION > handshake.protocol_version
+                let min_peer_protocol_version = self.network_state.config.min_peer_protocol_version;
+                if min_peer_protocol_version > handshake.protocol_version
                     || handshake.protocol_version > PROTOCOL_VERSION
                 {
                     tracing::debug!(
                         target: "network",
                         version = handshake.protocol_version,
+                        min_peer_protocol_version,
                         "Received connection from node with unsupported PROTOCOL_VERSION.");
+                    metrics::PEER_PROTOCOL_VERSION_MISMATCH.with_label_values(&["inbound"]).inc();
                     self.send_message_or_log(&PeerMessage::HandshakeFailure(
                         self.my_node_info.clone(),
                         HandshakeFailureReason::ProtocolVersionMismatch {
                             version: PROTOCOL_VERSION,
-                            oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+                            oldest_supported_version: min_peer_protocol_version,
                         },
                     ));
                     return;
@@ -682,6 +717,9 @@ impl PeerActor {
                 type_: self.peer_type,
                 encoding: self.encoding(),
             }),
+            _peer_protocol_version_metric: metrics::peer_protocol_version_point(
+                handshake.protocol_version,
+            ),
             last_time_peer_requested: AtomicCell::new(None),
             last_time_received_message: AtomicCell::new(now),
             established_time: now,
@@ -892,11 +930,16 @@ impl PeerActor {
                         oldest_supported_version,
                     } => {
                         // Retry the handshake with the common protocol version.
+                        let min_peer_protocol_version =
+                            self.network_state.config.min_peer_protocol_version;
                         let common_version = std::cmp::min(version, PROTOCOL_VERSION);
                         if common_version < oldest_supported_version
-                            || common_version < PEER_MIN_ALLOWED_PROTOCOL_VERSION
+                            || common_version < min_peer_protocol_version
                         {
-                            tracing::warn!(target: "network", "Unable to connect to a node ({}) due to a network protocol version mismatch. Our version: {:?}, their: {:?}", peer_info, (PROTOCOL_VERSION, PEER_MIN_ALLOWED_PROTOCOL_VERSION), (version, oldest_supported_version));
+                            tracing::warn!(target: "network", "Unable to connect to a node ({}) due to a network protocol version mismatch. Our version: {:?}, their: {:?}", peer_info, (PROTOCOL_VERSION, min_peer_protocol_version), (version, oldest_supported_version));
+                            metrics::PEER_PROTOCOL_VERSION_MISMATCH
+                                .with_label_values(&["outbound"])
+                                .inc();
                             self.stop(ctx, ClosingReason::HandshakeFailed);
                             return;
                         }
@@ -1097,7 +1140,12 @@ impl PeerActor {
                     .map(|response| PeerMessage::VersionedStateResponse(*response.0)),
                 PeerMessage::StateRequestPart(shard_id, sync_hash, part_id) => network_state
                     .client
-                    .send_async(StateRequestPart { shard_id, sync_hash, part_id })
+                    .send_async(StateRequestPart {
+                        shard_id,
+                        sync_hash,
+                        part_id,
+                        peer_id: peer_id.clone(),
+                    })
                     .await
                     .ok()
                     .flatten()
@@ -1364,8 +1412,12 @@ impl PeerActor {
                     return;
                 }
                 let network_state = self.network_state.clone();
+                let clock = self.clock.clone();
                 ctx.spawn(wrap_future(async move {
-                    if let Some(err) = network_state.add_snapshot_hosts(msg.hosts).await {
+                    if let Some(err) = network_state
+                        .add_snapshot_hosts(&clock, &conn.peer_info.id, msg.hosts)
+                        .await
+                    {
                         conn.stop(Some(match err {
                             SnapshotHostInfoError::VerificationError(
                                 SnapshotHostInfoVerificationError::InvalidSignature,
@@ -1412,6 +1464,16 @@ impl PeerActor {
                         return;
                     }
                 }
+                // Drop consensus messages which were already delivered to us recently.
+                // They are only recorded once their signature is verified below.
+                let replay_hash = (for_me && msg.body.is_replay_protected()).then(|| msg.hash());
+                if let Some(hash) = &replay_hash {
+                    if self.network_state.routed_message_replay_guard.lock().is_replay(now, hash) {
+                        metrics::MessageDropped::Replayed.inc(&msg.body);
+                        tracing::debug!(target: "network", author = %msg.author, body = msg.body_variant(), ?hash, "Dropping replayed routed message");
+                        return;
+                    }
+                }
                 if let RoutedMessageBody::ForwardTx(_) = &msg.body {
                     // Check whenever we exceeded number of transactions we got since last block.
                     // If so, drop the transaction.
@@ -1432,6 +1494,16 @@ impl PeerActor {
                     self.stop(ctx, ClosingReason::Ban(ReasonForBan::InvalidSignature));
                     return;
                 }
+                if let Some(hash) = replay_hash {
+                    let mut replay_guard = self.network_state.routed_message_replay_guard.lock();
+                    // Another connection might have delivered the same message in the meantime.
+                    let recorded = replay_guard.record(now, hash);
+                    metrics::ROUTED_MESSAGE_REPLAY_CACHE_SIZE.set(replay_guard.len() as i64);
+                    if !recorded {
+                        metrics::MessageDropped::Replayed.inc(&msg.body);
+                        return;
+                    }
+                }
 
                 self.network_state.add_route_back(&self.clock, &conn, msg.as_ref());
                 if for_me {
@@ -1711,6 +1783,20 @@ impl actix::Handler<stream::Frame> for PeerActor {
 
         tracing::trace!(target: "network", "Received message: {}", peer_msg);
 
+        if let Some(capture) = &self.network_state.traffic_capture {
+            // Without a known encoding, a message which isn't protobuf is borsh.
+            let encoding = self.encoding().unwrap_or(Encoding::Borsh);
+            capture.capture(
+                &self.clock,
+                capture::Direction::Received,
+                self.peer_addr,
+                self.other_peer_id(),
+                encoding,
+                peer_msg.msg_variant(),
+                &msg,
+            );
+        }
+
         let now = self.clock.now();
         {
             let labels = [peer_msg.msg_variant()];
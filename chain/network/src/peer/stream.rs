@@ -1,9 +1,11 @@
 use crate::peer_manager::connection;
+use crate::rate_limits::bandwidth_limits::{MessageClass, ShapedQueue};
 use crate::stats::metrics;
 use crate::tcp;
 use actix::AsyncContext as _;
 use actix::fut::future::wrap_future;
 use bytesize::{GIB, MIB};
+use near_async::time;
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -57,7 +59,7 @@ pub(crate) enum Error {
 }
 
 pub(crate) struct FramedStream<Actor: actix::Actor> {
-    queue_send: tokio::sync::mpsc::UnboundedSender<Frame>,
+    queue_send: tokio::sync::mpsc::UnboundedSender<(Frame, Option<MessageClass>)>,
     stats: Arc<connection::Stats>,
     send_buf_size_metric: Arc<metrics::IntGaugeGuard>,
     addr: actix::Addr<Actor>,
@@ -73,6 +75,8 @@ where
         ctx: &mut actix::Context<Actor>,
         stream: tcp::Stream,
         stats: Arc<connection::Stats>,
+        clock: time::Clock,
+        send_queue: ShapedQueue<Frame>,
     ) -> Self {
         let (tcp_recv, tcp_send) = tokio::io::split(stream.stream);
         let (queue_send, queue_recv) = tokio::sync::mpsc::unbounded_channel();
@@ -85,7 +89,9 @@ where
             let stats = stats.clone();
             let m = send_buf_size_metric.clone();
             async move {
-                if let Err(err) = Self::run_send_loop(tcp_send, queue_recv, stats, m).await {
+                if let Err(err) =
+                    Self::run_send_loop(tcp_send, queue_recv, stats, m, clock, send_queue).await
+                {
                     addr.do_send(Error::Send(SendError::IO(err)));
                 }
             }
@@ -108,7 +114,8 @@ where
     /// Silently drops message if the connection has been closed.
     /// If the message is too large, it will be silently dropped inside run_send_loop.
     /// Emits a critical error to Actor if send queue is full.
-    pub fn send(&self, frame: Frame) {
+    /// The message is delayed by the bandwidth limits of its `class`, if any.
+    pub fn send(&self, frame: Frame, class: Option<MessageClass>) {
         let msg = &frame.0;
         let mut buf_size =
             self.stats.bytes_to_send.fetch_add(msg.len() as u64, Ordering::Acquire) as usize;
@@ -125,7 +132,7 @@ where
                 want_max_bytes: MAX_WRITE_BUFFER_CAPACITY_BYTES,
             }));
         }
-        let _ = self.queue_send.send(frame);
+        let _ = self.queue_send.send((frame, class));
     }
 
     /// Event loop receiving and processing messages.
@@ -175,17 +182,59 @@ where
             }
         }
     }
+
+    /// Pushes `frame` to `send_queue`, or drops it if the bandwidth limits hold back
+    /// too many messages of its class already.
+    fn push_frame(
+        send_queue: &mut ShapedQueue<Frame>,
+        frame: Frame,
+        class: Option<MessageClass>,
+        stats: &connection::Stats,
+        buf_size_metric: &metrics::IntGaugeGuard,
+    ) {
+        let len = frame.0.len();
+        if send_queue.push(class, len, frame).is_err() {
+            metrics::MessageDropped::MaxCapacityExceeded.inc_unknown_msg();
+            stats.messages_to_send.fetch_sub(1, Ordering::Release);
+            stats.bytes_to_send.fetch_sub(len as u64, Ordering::Release);
+            buf_size_metric.sub(len as i64);
+        }
+    }
+
+    /// Event loop sending the messages pushed to the send queue, in the order
+    /// allowed by the bandwidth limits of `send_queue`.
     async fn run_send_loop(
         tcp_send: WriteHalf,
-        mut queue_recv: tokio::sync::mpsc::UnboundedReceiver<Frame>,
+        mut queue_recv: tokio::sync::mpsc::UnboundedReceiver<(Frame, Option<MessageClass>)>,
         stats: Arc<connection::Stats>,
         buf_size_metric: Arc<metrics::IntGaugeGuard>,
+        clock: time::Clock,
+        mut send_queue: ShapedQueue<Frame>,
     ) -> io::Result<()> {
         const WRITE_BUFFER_CAPACITY: usize = 8 * 1024;
         let mut writer = tokio::io::BufWriter::with_capacity(WRITE_BUFFER_CAPACITY, tcp_send);
-        while let Some(Frame(mut msg)) = queue_recv.recv().await {
+        loop {
+            // Wait for a new message, or until the bandwidth limits let a queued message through.
+            let wait = send_queue.time_until_ready(clock.now());
+            tokio::select! {
+                frame = queue_recv.recv() => match frame {
+                    Some((frame, class)) => {
+                        Self::push_frame(&mut send_queue, frame, class, &stats, &buf_size_metric)
+                    }
+                    None => return Ok(()),
+                },
+                _ = clock.sleep(wait.unwrap_or_default()), if wait.is_some() => {}
+            }
             // Try writing a batch of messages and flush once at the end.
+            let mut written = false;
             loop {
+                while let Ok((frame, class)) = queue_recv.try_recv() {
+                    Self::push_frame(&mut send_queue, frame, class, &stats, &buf_size_metric);
+                }
+                let Some(Frame(msg)) = send_queue.pop_ready(clock.now()) else {
+                    break;
+                };
+                written = true;
                 // TODO(gprusak): sending a too large message should probably be treated as a bug,
                 // since dropping messages may lead to hard-to-debug high-level issues.
                 if msg.len() > NETWORK_MESSAGE_MAX_SIZE_BYTES {
@@ -197,10 +246,9 @@ where
                 stats.messages_to_send.fetch_sub(1, Ordering::Release);
                 stats.bytes_to_send.fetch_sub(msg.len() as u64, Ordering::Release);
                 buf_size_metric.sub(msg.len() as i64);
-                msg = match queue_recv.try_recv() {
-                    Ok(Frame(it)) => it,
-                    Err(_) => break,
-                };
+            }
+            if !written {
+                continue;
             }
             // This is an unconditional flush, which means that even if new messages
             // will be added to the queue in the meantime, we will wait for the buffer
@@ -210,6 +258,5 @@ where
             // and make sure that both are cancellation-safe.
             writer.flush().await?;
         }
-        Ok(())
     }
 }
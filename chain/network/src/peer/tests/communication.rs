@@ -262,3 +262,55 @@ async fn handshake() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[tokio::test]
+// Verifies that peers below the configured min_peer_protocol_version are refused,
+// and that the refusal advertises the configured floor.
+async fn handshake_below_min_peer_protocol_version() {
+    init_test_logger();
+    let mut rng = make_rng(89028037453);
+    let mut clock = time::FakeClock::default();
+
+    let chain = Arc::new(data::Chain::make(&mut clock, &mut rng, 12));
+    let mut inbound_network = chain.make_config(&mut rng);
+    inbound_network.min_peer_protocol_version = PROTOCOL_VERSION;
+    let inbound_cfg =
+        PeerConfig { network: inbound_network, chain: chain.clone(), force_encoding: None };
+    let outbound_cfg = PeerConfig {
+        network: chain.make_config(&mut rng),
+        chain: chain.clone(),
+        force_encoding: None,
+    };
+    let (outbound_stream, inbound_stream) =
+        tcp::Stream::loopback(inbound_cfg.id(), tcp::Tier::T2).await;
+    let inbound = PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await;
+    let outbound_port = outbound_stream.local_addr.port();
+    let mut outbound = Stream::new(None, outbound_stream);
+
+    let mut handshake = Handshake {
+        protocol_version: PROTOCOL_VERSION - 1,
+        oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+        sender_peer_id: outbound_cfg.id(),
+        target_peer_id: inbound.cfg.id(),
+        sender_listen_port: Some(outbound_port),
+        sender_chain_info: outbound_cfg.chain.get_peer_chain_info(),
+        partial_edge_info: outbound_cfg
+            .partial_edge_info(&inbound.cfg.id(), Edge::create_fresh_nonce(&clock.clock())),
+        owned_account: None,
+    };
+    outbound.write(&PeerMessage::Tier2Handshake(handshake.clone())).await;
+    let resp = outbound.read().await.unwrap();
+    assert_matches!(
+        resp,
+        PeerMessage::HandshakeFailure(
+            _,
+            HandshakeFailureReason::ProtocolVersionMismatch { oldest_supported_version, .. }
+        ) if oldest_supported_version == PROTOCOL_VERSION
+    );
+
+    // Retrying at the floor succeeds.
+    handshake.protocol_version = PROTOCOL_VERSION;
+    outbound.write(&PeerMessage::Tier2Handshake(handshake)).await;
+    let resp = outbound.read().await.unwrap();
+    assert_matches!(resp, PeerMessage::Tier2Handshake(_));
+}
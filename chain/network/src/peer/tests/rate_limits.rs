@@ -4,10 +4,11 @@ use crate::network_protocol::{Encoding, PeerMessage};
 use crate::network_protocol::{PartialEncodedChunkRequestMsg, RoutedMessageBody, testonly as data};
 use crate::peer::testonly::{Event, PeerConfig, PeerHandle};
 use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::rate_limits::bandwidth_limits::{BandwidthLimit, MessageClass};
 use crate::rate_limits::messages_limits;
 use crate::tcp;
 use crate::testonly::{Rng, make_rng};
-use near_async::time::FakeClock;
+use near_async::time::{self, FakeClock};
 use near_o11y::testonly::init_test_logger;
 use near_primitives::hash::CryptoHash;
 use rand::Rng as _;
@@ -86,6 +87,73 @@ async fn slow_test_message_rate_limits_over_time() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+// Verifies that the messages sent over the bandwidth limit of their class are delayed, and that
+// the messages of the class are dropped once too many of them wait, without closing the connection.
+async fn test_sent_bandwidth_limits() -> anyhow::Result<()> {
+    init_test_logger();
+    tracing::info!("test_sent_bandwidth_limits");
+
+    let clock = FakeClock::default();
+    let mut rng = make_rng(89028037453);
+    let chain = Arc::new(data::Chain::make(&clock, &mut rng, 12));
+    let block = PeerMessage::Block(chain.blocks[5].clone());
+    let block_len = block.serialize(Encoding::Proto).len();
+
+    // Each block empties the bucket, which is refilled in a second, and up to 3 blocks wait.
+    let mut outbound_network = chain.make_config(&mut rng);
+    let limits = &mut outbound_network.sent_bandwidth_limits;
+    limits
+        .per_class
+        .insert(MessageClass::Blocks, BandwidthLimit { bytes_per_second: 1, burst_bytes: 1 });
+    limits.max_queued_bytes_per_class = 3 * block_len as u32;
+    let inbound_cfg = PeerConfig {
+        chain: chain.clone(),
+        network: chain.make_config(&mut rng),
+        force_encoding: Some(Encoding::Proto),
+    };
+    let outbound_cfg = PeerConfig {
+        chain: chain.clone(),
+        network: outbound_network,
+        force_encoding: Some(Encoding::Proto),
+    };
+    let (outbound_stream, inbound_stream) =
+        tcp::Stream::loopback(inbound_cfg.id(), tcp::Tier::T2).await;
+    let mut inbound = PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await;
+    let mut outbound =
+        PeerHandle::start_endpoint(clock.clock(), outbound_cfg, outbound_stream).await;
+    outbound.complete_handshake().await;
+    inbound.complete_handshake().await;
+
+    const MESSAGES: u32 = 10;
+    let mut events = inbound.events.from_now();
+    for _ in 0..MESSAGES {
+        outbound.send(block.clone()).await;
+    }
+    let received =
+        wait_for_similar_messages(&[block.clone()], &mut events, Duration::from_secs(1)).await[0];
+    assert_eq!(received, 1);
+
+    // Advance the fake clock to let the queued blocks through, one per second.
+    for _ in 0..MESSAGES {
+        clock.advance(time::Duration::seconds(1));
+        sleep(Duration::from_millis(100)).await;
+    }
+    let received =
+        wait_for_similar_messages(&[block.clone()], &mut events, Duration::from_secs(1)).await[0];
+    // The first block is sent right away and up to 3 others wait, the rest are dropped.
+    assert!(received == 2 || received == 3, "received {received} blocks");
+
+    // The connection is still open.
+    let mut events = inbound.events.from_now();
+    clock.advance(time::Duration::seconds(1));
+    outbound.send(block.clone()).await;
+    let received =
+        wait_for_similar_messages(&[block], &mut events, Duration::from_secs(1)).await[0];
+    assert_eq!(received, 1);
+    Ok(())
+}
+
 /// Waits up to `duration` and then checks how many events equal (in type only) to each one of `samples`
 /// have been received.
 ///
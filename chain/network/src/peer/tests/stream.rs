@@ -1,10 +1,12 @@
 use crate::actix::ActixSystem;
 use crate::network_protocol::testonly as data;
 use crate::peer::stream;
+use crate::rate_limits::bandwidth_limits::{BandwidthLimits, ShapedQueue};
 use crate::tcp;
 use crate::testonly::make_rng;
 use actix::Actor as _;
 use actix::ActorContext as _;
+use near_async::time;
 use rand::Rng as _;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -25,7 +27,7 @@ struct SendFrame(stream::Frame);
 impl actix::Handler<SendFrame> for Actor {
     type Result = ();
     fn handle(&mut self, SendFrame(frame): SendFrame, _ctx: &mut Self::Context) {
-        self.stream.send(frame);
+        self.stream.send(frame, None);
     }
 }
 
@@ -55,7 +57,13 @@ impl Actor {
             queue_recv,
             system: ActixSystem::spawn(|| {
                 Actor::create(|ctx| {
-                    let stream = stream::FramedStream::spawn(ctx, s, Arc::default());
+                    let stream = stream::FramedStream::spawn(
+                        ctx,
+                        s,
+                        Arc::default(),
+                        time::Clock::real(),
+                        ShapedQueue::new(BandwidthLimits::default()),
+                    );
                     Self { stream, queue_send }
                 })
             })
@@ -75,13 +75,17 @@ impl tcp::Tier {
             | RoutedMessageBody::VersionedPartialEncodedChunk(..)
             | RoutedMessageBody::ChunkContractAccesses(_)
             | RoutedMessageBody::ContractCodeRequest(_)
-            | RoutedMessageBody::ContractCodeResponse(_) => true,
+            | RoutedMessageBody::ContractCodeResponse(_)
+            | RoutedMessageBody::PartialEncodedStateWitnessRequest(_)
+            | RoutedMessageBody::ReceiptProofFragment(_) => true,
             // Rest
             RoutedMessageBody::ForwardTx(..)
             | RoutedMessageBody::TxStatusRequest(..)
             | RoutedMessageBody::TxStatusResponse(..)
             | RoutedMessageBody::PartialEncodedChunkRequest(..)
             | RoutedMessageBody::PartialEncodedChunkResponse(..)
+            | RoutedMessageBody::ChunkRequest(..)
+            | RoutedMessageBody::ChunkResponse(..)
             | RoutedMessageBody::Ping(..)
             | RoutedMessageBody::Pong(..)
             | RoutedMessageBody::PartialEncodedChunkForward(..)
@@ -156,6 +160,8 @@ pub(crate) struct Connection {
     pub stats: Arc<Stats>,
     /// prometheus gauge point guard.
     pub _peer_connections_metric: metrics::GaugePoint,
+    /// prometheus gauge point guard for the negotiated protocol version.
+    pub _peer_protocol_version_metric: metrics::GaugePoint,
 
     /// Demultiplexer for the calls to send_accounts_data().
     pub send_accounts_data_demux: demux::Demux<Vec<Arc<SignedAccountData>>, ()>,
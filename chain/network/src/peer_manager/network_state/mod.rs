@@ -1,5 +1,6 @@
 use crate::accounts_data::{AccountDataCache, AccountDataError};
 use crate::announce_accounts::AnnounceAccountCache;
+use crate::capture::TrafficCapture;
 use crate::client::{
     BlockApproval, ChunkEndorsementMessage, ClientSenderForNetwork, ProcessTxRequest,
     TxStatusRequest, TxStatusResponse,
@@ -19,6 +20,7 @@ use crate::peer_manager::peer_store;
 use crate::private_actix::RegisterPeerError;
 #[cfg(feature = "distance_vector_routing")]
 use crate::routing::NetworkTopologyChange;
+use crate::routing::replay_guard::ReplayGuard;
 use crate::routing::route_back_cache::RouteBackCache;
 use crate::shards_manager::ShardsManagerRequestFromNetwork;
 use crate::snapshot_hosts::{SnapshotHostInfoError, SnapshotHostsCache};
@@ -26,7 +28,7 @@ use crate::state_witness::{
     ChunkContractAccessesMessage, ChunkStateWitnessAckMessage, ContractCodeRequestMessage,
     ContractCodeResponseMessage, PartialEncodedContractDeploysMessage,
     PartialEncodedStateWitnessForwardMessage, PartialEncodedStateWitnessMessage,
-    PartialWitnessSenderForNetwork,
+    PartialEncodedStateWitnessRequestMessage, PartialWitnessSenderForNetwork,
 };
 use crate::stats::metrics;
 use crate::store;
@@ -61,6 +63,9 @@ pub(crate) const LIMIT_PENDING_PEERS: usize = 60;
 /// production of 1 block should fit).
 const RECENT_ROUTED_MESSAGES_CACHE_SIZE: usize = 10000;
 
+/// Maximal number of routed messages remembered for replay detection.
+const ROUTED_MESSAGE_REPLAY_CACHE_SIZE: usize = 100_000;
+
 /// How long a peer has to be unreachable, until we prune it from the in-memory graph.
 const PRUNE_UNREACHABLE_PEERS_AFTER: time::Duration = time::Duration::hours(1);
 
@@ -145,6 +150,9 @@ pub(crate) struct NetworkState {
     /// It allows us to determine whether messages arrived faster over TIER1 or TIER2 network.
     pub recent_routed_messages: Mutex<lru::LruCache<CryptoHash, ()>>,
 
+    /// Hashes of recently delivered replay-protected routed messages.
+    pub routed_message_replay_guard: Mutex<ReplayGuard>,
+
     /// Hash of messages that requires routing back to respective previous hop.
     pub tier2_route_back: Mutex<RouteBackCache>,
     /// Currently unused, as TIER1 messages do not require a response.
@@ -161,6 +169,9 @@ pub(crate) struct NetworkState {
     /// reached.
     whitelist_nodes: Vec<WhitelistNode>,
 
+    /// Writer of the captured peer messages, if `config.traffic_capture` is set.
+    pub traffic_capture: Option<TrafficCapture>,
+
     /// Mutex which prevents overlapping calls to tier1_advertise_proxies.
     tier1_advertise_proxies_mutex: tokio::sync::Mutex<()>,
     /// Demultiplexer aggregating calls to add_edges(), for V1 routing protocol
@@ -187,6 +198,7 @@ impl NetworkState {
         shards_manager_adapter: Sender<ShardsManagerRequestFromNetwork>,
         partial_witness_adapter: PartialWitnessSenderForNetwork,
         whitelist_nodes: Vec<WhitelistNode>,
+        traffic_capture: Option<TrafficCapture>,
     ) -> Self {
         Self {
             runtime: Runtime::new(),
@@ -222,8 +234,13 @@ impl NetworkState {
             recent_routed_messages: Mutex::new(lru::LruCache::new(
                 NonZeroUsize::new(RECENT_ROUTED_MESSAGES_CACHE_SIZE).unwrap(),
             )),
+            routed_message_replay_guard: Mutex::new(ReplayGuard::new(
+                config.routed_message_replay_window,
+                NonZeroUsize::new(ROUTED_MESSAGE_REPLAY_CACHE_SIZE).unwrap(),
+            )),
             txns_since_last_block: AtomicUsize::new(0),
             whitelist_nodes,
+            traffic_capture,
             add_edges_demux: demux::Demux::new(config.routing_table_update_rate_limit),
             #[cfg(feature = "distance_vector_routing")]
             update_routes_demux: demux::Demux::new(config.routing_table_update_rate_limit),
@@ -758,6 +775,20 @@ impl NetworkState {
                 );
                 None
             }
+            RoutedMessageBody::ChunkRequest(request) => {
+                self.shards_manager_adapter.send(
+                    ShardsManagerRequestFromNetwork::ProcessChunkRequest {
+                        chunk_request: request,
+                        route_back: msg_hash,
+                    },
+                );
+                None
+            }
+            RoutedMessageBody::ChunkResponse(response) => {
+                self.shards_manager_adapter
+                    .send(ShardsManagerRequestFromNetwork::ProcessChunkResponse(response));
+                None
+            }
             RoutedMessageBody::VersionedPartialEncodedChunk(chunk) => {
                 self.shards_manager_adapter
                     .send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunk(chunk));
@@ -768,6 +799,15 @@ impl NetworkState {
                     .send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkForward(msg));
                 None
             }
+            RoutedMessageBody::ReceiptProofFragment(fragment) => {
+                self.shards_manager_adapter.send(
+                    ShardsManagerRequestFromNetwork::ProcessReceiptProofFragment {
+                        fragment,
+                        peer_id: msg_author,
+                    },
+                );
+                None
+            }
             RoutedMessageBody::ChunkStateWitnessAck(ack) => {
                 self.partial_witness_adapter.send(ChunkStateWitnessAckMessage(ack));
                 None
@@ -781,6 +821,11 @@ impl NetworkState {
                     .send(PartialEncodedStateWitnessForwardMessage(witness));
                 None
             }
+            RoutedMessageBody::PartialEncodedStateWitnessRequest(request) => {
+                self.partial_witness_adapter
+                    .send(PartialEncodedStateWitnessRequestMessage(request));
+                None
+            }
             RoutedMessageBody::VersionedChunkEndorsement(endorsement) => {
                 self.client.send_async(ChunkEndorsementMessage(endorsement)).await.ok();
                 None
@@ -869,8 +914,14 @@ impl NetworkState {
 
     pub async fn add_snapshot_hosts(
         self: &Arc<Self>,
+        clock: &time::Clock,
+        peer_id: &PeerId,
         hosts: Vec<Arc<SnapshotHostInfo>>,
     ) -> Option<SnapshotHostInfoError> {
+        let hosts = self.snapshot_hosts.rate_limit(peer_id, clock.now(), hosts);
+        if hosts.is_empty() {
+            return None;
+        }
         let this = self.clone();
         self.spawn(async move {
             // Verify and add the new data to the internal state.
@@ -5,6 +5,7 @@ use crate::network_protocol::{
 };
 use crate::peer::peer_actor::PeerActor;
 use crate::peer_manager::connection;
+use crate::stats::metrics;
 use crate::stun;
 use crate::tcp;
 use crate::types::PeerType;
@@ -306,6 +307,19 @@ impl super::NetworkState {
             let mut handles = vec![];
             let mut account_keys: Vec<_> = proxies_by_account.keys().copied().collect();
             account_keys.shuffle(&mut rand::thread_rng());
+            // Connect to the producers of the next epoch first, so that the
+            // connections are warm by the time the epoch starts.
+            let chain_info = self.chain_info.load();
+            let next_epoch_producers: HashSet<&PublicKey> = (**chain_info)
+                .iter()
+                .flat_map(|info| info.next_epoch_producers.values().flatten())
+                .filter(|key| *key != &validator_signer.public_key())
+                .collect();
+            account_keys.sort_by_key(|key| !next_epoch_producers.contains(key));
+            metrics::TIER1_NEXT_EPOCH_PRODUCERS.set(next_epoch_producers.len() as i64);
+            metrics::TIER1_NEXT_EPOCH_PRODUCERS_CONNECTED
+                .set(next_epoch_producers.iter().filter(|key| safe.contains_key(*key)).count()
+                    as i64);
             for account_key in account_keys {
                 // tier1_connect() is responsible for connecting to proxies
                 // of this node. tier1_connect() connects only to proxies
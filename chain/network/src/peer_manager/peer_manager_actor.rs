@@ -1,3 +1,4 @@
+use crate::capture::TrafficCapture;
 use crate::client::{ClientSenderForNetwork, SetNetworkInfo, StateRequestHeader, StateRequestPart};
 use crate::config;
 use crate::debug::{DebugStatus, GetDebugStatus};
@@ -17,10 +18,11 @@ use crate::stats::metrics;
 use crate::store;
 use crate::tcp;
 use crate::types::{
-    ConnectedPeerInfo, HighestHeightPeerInfo, KnownProducer, NetworkInfo, NetworkRequests,
-    NetworkResponses, PeerInfo, PeerManagerMessageRequest, PeerManagerMessageResponse,
-    PeerManagerSenderForNetwork, PeerType, SetChainInfo, SnapshotHostInfo, StateHeaderRequestBody,
-    StatePartRequestBody, StateSyncEvent, Tier3Request, Tier3RequestBody,
+    AccountIdOrPeerTrackingShard, ConnectedPeerInfo, HighestHeightPeerInfo, KnownProducer,
+    NetworkInfo, NetworkRequests, NetworkResponses, PeerInfo, PeerManagerMessageRequest,
+    PeerManagerMessageResponse, PeerManagerSenderForNetwork, PeerType, SetChainInfo,
+    SnapshotHostInfo, StateHeaderRequestBody, StatePartRequestBody, StateSyncEvent, Tier3Request,
+    Tier3RequestBody,
 };
 use ::time::ext::InstantExt as _;
 use actix::fut::future::wrap_future;
@@ -236,6 +238,12 @@ impl PeerManagerActor {
             }
             v
         };
+        let traffic_capture = match &config.traffic_capture {
+            Some(capture_config) => {
+                Some(TrafficCapture::new(capture_config.clone()).context("TrafficCapture::new")?)
+            }
+            None => None,
+        };
         let my_peer_id = config.node_id();
         let arbiter = actix::Arbiter::new().handle();
         let clock = clock;
@@ -250,6 +258,7 @@ impl PeerManagerActor {
             shards_manager_adapter,
             partial_witness_adapter,
             whitelist_nodes,
+            traffic_capture,
         ));
         arbiter.spawn({
             let arbiter = arbiter.clone();
@@ -767,6 +776,57 @@ impl PeerManagerActor {
         );
     }
 
+    /// Sends the message to the target account or to a random peer tracking the
+    /// shard, see `AccountIdOrPeerTrackingShard`. Returns whether it was sent.
+    fn send_message_to_account_or_peer_tracking_shard(
+        &self,
+        target: &AccountIdOrPeerTrackingShard,
+        body: RoutedMessageBody,
+    ) -> bool {
+        // Make two attempts to send the message. First following the preference of `prefer_peer`,
+        // and if it fails, against the preference.
+        for prefer_peer in &[target.prefer_peer, !target.prefer_peer] {
+            if !prefer_peer {
+                if let Some(account_id) = target.account_id.as_ref() {
+                    if self.state.send_message_to_account(&self.clock, account_id, body.clone()) {
+                        return true;
+                    }
+                }
+            } else {
+                let mut matching_peers = vec![];
+                for (peer_id, peer) in &self.state.tier2.load().ready {
+                    let last_block = peer.last_block.load();
+                    if (peer.archival || !target.only_archival)
+                        && last_block.is_some()
+                        && last_block.as_ref().unwrap().height >= target.min_height
+                        && peer.tracked_shards.contains(&target.shard_id)
+                    {
+                        matching_peers.push(peer_id.clone());
+                    }
+                }
+
+                if let Some(matching_peer) = matching_peers.iter().choose(&mut thread_rng()) {
+                    if self.state.send_message_to_peer(
+                        &self.clock,
+                        tcp::Tier::T2,
+                        self.state.sign_message(
+                            &self.clock,
+                            RawRoutedMessage {
+                                target: PeerIdOrHash::PeerId(matching_peer.clone()),
+                                body: body.clone(),
+                            },
+                        ),
+                    ) {
+                        return true;
+                    }
+                } else {
+                    tracing::debug!(target: "network", ?body, "Failed to find any matching peer for chunk");
+                }
+            }
+        }
+        false
+    }
+
     #[perf]
     fn handle_msg_network_requests(
         &mut self,
@@ -981,59 +1041,10 @@ impl PeerManagerActor {
                 metrics::PARTIAL_ENCODED_CHUNK_REQUEST_DELAY.observe(
                     (self.clock.now().signed_duration_since(create_time)).as_seconds_f64(),
                 );
-                let mut success = false;
-
-                // Make two attempts to send the message. First following the preference of `prefer_peer`,
-                // and if it fails, against the preference.
-                for prefer_peer in &[target.prefer_peer, !target.prefer_peer] {
-                    if !prefer_peer {
-                        if let Some(account_id) = target.account_id.as_ref() {
-                            if self.state.send_message_to_account(
-                                &self.clock,
-                                account_id,
-                                RoutedMessageBody::PartialEncodedChunkRequest(request.clone()),
-                            ) {
-                                success = true;
-                                break;
-                            }
-                        }
-                    } else {
-                        let mut matching_peers = vec![];
-                        for (peer_id, peer) in &self.state.tier2.load().ready {
-                            let last_block = peer.last_block.load();
-                            if (peer.archival || !target.only_archival)
-                                && last_block.is_some()
-                                && last_block.as_ref().unwrap().height >= target.min_height
-                                && peer.tracked_shards.contains(&target.shard_id)
-                            {
-                                matching_peers.push(peer_id.clone());
-                            }
-                        }
-
-                        if let Some(matching_peer) = matching_peers.iter().choose(&mut thread_rng())
-                        {
-                            if self.state.send_message_to_peer(
-                                &self.clock,
-                                tcp::Tier::T2,
-                                self.state.sign_message(
-                                    &self.clock,
-                                    RawRoutedMessage {
-                                        target: PeerIdOrHash::PeerId(matching_peer.clone()),
-                                        body: RoutedMessageBody::PartialEncodedChunkRequest(
-                                            request.clone(),
-                                        ),
-                                    },
-                                ),
-                            ) {
-                                success = true;
-                                break;
-                            }
-                        } else {
-                            tracing::debug!(target: "network", chunk_hash=?request.chunk_hash, "Failed to find any matching peer for chunk");
-                        }
-                    }
-                }
-
+                let success = self.send_message_to_account_or_peer_tracking_shard(
+                    &target,
+                    RoutedMessageBody::PartialEncodedChunkRequest(request.clone()),
+                );
                 if success {
                     NetworkResponses::NoResponse
                 } else {
@@ -1058,6 +1069,33 @@ impl PeerManagerActor {
                     NetworkResponses::RouteNotFound
                 }
             }
+            NetworkRequests::ChunkRequest { target, request } => {
+                if self.send_message_to_account_or_peer_tracking_shard(
+                    &target,
+                    RoutedMessageBody::ChunkRequest(request),
+                ) {
+                    NetworkResponses::NoResponse
+                } else {
+                    NetworkResponses::RouteNotFound
+                }
+            }
+            NetworkRequests::ChunkResponse { route_back, response } => {
+                if self.state.send_message_to_peer(
+                    &self.clock,
+                    tcp::Tier::T2,
+                    self.state.sign_message(
+                        &self.clock,
+                        RawRoutedMessage {
+                            target: PeerIdOrHash::Hash(route_back),
+                            body: RoutedMessageBody::ChunkResponse(response),
+                        },
+                    ),
+                ) {
+                    NetworkResponses::NoResponse
+                } else {
+                    NetworkResponses::RouteNotFound
+                }
+            }
             NetworkRequests::PartialEncodedChunkMessage { account_id, partial_encoded_chunk } => {
                 if self.state.send_message_to_account(
                     &self.clock,
@@ -1080,6 +1118,17 @@ impl PeerManagerActor {
                     NetworkResponses::RouteNotFound
                 }
             }
+            NetworkRequests::ReceiptProofFragment { account_id, fragment } => {
+                if self.state.send_message_to_account(
+                    &self.clock,
+                    &account_id,
+                    RoutedMessageBody::ReceiptProofFragment(fragment),
+                ) {
+                    NetworkResponses::NoResponse
+                } else {
+                    NetworkResponses::RouteNotFound
+                }
+            }
             NetworkRequests::ForwardTx(account_id, tx) => {
                 if self.state.send_message_to_account(
                     &self.clock,
@@ -1143,6 +1192,14 @@ impl PeerManagerActor {
                 }
                 NetworkResponses::NoResponse
             }
+            NetworkRequests::PartialEncodedStateWitnessRequest(target, request) => {
+                self.state.send_message_to_account(
+                    &self.clock,
+                    &target,
+                    RoutedMessageBody::PartialEncodedStateWitnessRequest(request),
+                );
+                NetworkResponses::NoResponse
+            }
             NetworkRequests::EpochSyncRequest { peer_id } => {
                 if self.state.tier2.send_message(peer_id, PeerMessage::EpochSyncRequest.into()) {
                     NetworkResponses::NoResponse
@@ -1345,7 +1402,8 @@ impl actix::Handler<WithSpanContext<Tier3Request>> for PeerManagerActor {
                         }
                     }
                     Tier3RequestBody::StatePart(StatePartRequestBody { shard_id, sync_hash, part_id }) => {
-                        match state.client.send_async(StateRequestPart { shard_id, sync_hash, part_id }).await {
+                        let peer_id = request.peer_info.id.clone();
+                        match state.client.send_async(StateRequestPart { shard_id, sync_hash, part_id, peer_id }).await {
                             Ok(Some(client_response)) => {
                                 PeerMessage::VersionedStateResponse(*client_response.0)
                             }
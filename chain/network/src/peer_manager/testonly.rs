@@ -594,7 +594,7 @@ pub(crate) async fn start(
                     // most other events we send it to the sink (for what? I have no idea).
                     match event {
                         ClientSenderForNetworkMessage::_state_request_part(msg) => {
-                            let StateRequestPart { part_id, shard_id, sync_hash } = msg.message;
+                            let StateRequestPart { part_id, shard_id, sync_hash, .. } = msg.message;
                             let part = Some((part_id, vec![]));
                             let state_response =
                                 ShardStateSyncResponse::V2(ShardStateSyncResponseV2 {
@@ -22,7 +22,7 @@ fn make_block_approval(rng: &mut Rng, signer: &ValidatorSigner) -> Approval {
     let inner = ApprovalInner::Endorsement(data::make_hash(rng));
     let target_height = rng.gen_range(0..100000);
     Approval {
-        signature: signer.sign_bytes(&Approval::get_data_for_sig(&inner, target_height)),
+        signature: signer.sign_bytes(&Approval::get_data_for_sig(&inner, target_height)).unwrap(),
         account_id: signer.validator_id().clone(),
         target_height,
         inner,
@@ -285,6 +285,63 @@ async fn account_keys_change() {
     drop(hub);
 }
 
+// With a limit of 1 new connection per attempt, the first TIER1 connection of
+// a validator should go to a producer of the next epoch.
+#[tokio::test]
+async fn next_epoch_producers_connected_first() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut v0cfg = chain.make_config(rng);
+    v0cfg.tier1.as_mut().unwrap().new_connections_per_attempt = 1;
+    let v0 = start_pm(clock.clock(), TestDB::new(), v0cfg, chain.clone()).await;
+    let mut others = vec![];
+    for _ in 0..4 {
+        others.push(
+            start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await,
+        );
+    }
+    for pm in &others {
+        v0.connect_to(&pm.peer_info(), tcp::Tier::T2).await;
+    }
+
+    let mut all = vec![&v0];
+    all.extend(others.iter());
+    let mut chain_info = peer_manager::testonly::make_chain_info(
+        &chain,
+        &all.iter().map(|pm| &pm.cfg).collect::<Vec<_>>()[..],
+    );
+    // The last validator is a producer of the next epoch.
+    let next_producer = others.last().unwrap().cfg.validator.signer.get().unwrap();
+    chain_info.next_epoch_producers = Arc::new(
+        [(next_producer.validator_id().clone(), [next_producer.public_key()].into())].into(),
+    );
+    for pm in &all {
+        pm.set_chain_info(chain_info.clone()).await;
+    }
+    let mut data = HashSet::new();
+    for pm in &all {
+        data.extend(pm.tier1_advertise_proxies(&clock.clock()).await);
+    }
+    v0.wait_for_accounts_data(&data).await;
+
+    v0.tier1_connect(&clock.clock()).await;
+    // Skip the loop connection v0 established to itself as its own proxy.
+    let v0_key = v0.cfg.validator.signer.get().unwrap().public_key();
+    let connected = v0
+        .with_state(|s| async move {
+            s.tier1.load().ready_by_account_key.keys().cloned().collect::<Vec<_>>()
+        })
+        .await
+        .into_iter()
+        .filter(|key| key != &v0_key)
+        .collect::<Vec<_>>();
+    assert_eq!(connected, vec![next_producer.public_key()]);
+}
+
 // Let's say that a validator has 2 proxies configured. At first proxy0 is available and proxy1 is not,
 // then proxy1 is available and proxy0 is not. In both situations validator should be reachable,
 // as long as it manages to advertise the currently available proxy and the TIER1 nodes connect to
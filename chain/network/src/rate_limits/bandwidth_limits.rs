@@ -0,0 +1,474 @@
+//! This module implements the shaping of the traffic sent to a single peer:
+//! limits on the bandwidth of all the traffic sent to the peer and of each class
+//! of messages, enforced with token buckets counting bytes.
+//!
+//! Unlike the limits on received messages, which drop the messages over the limit,
+//! the messages sent over the limit are delayed until the buckets are refilled. Only
+//! the messages of a class which has too many bytes waiting already are dropped.
+
+use super::token_bucket::TokenBucket;
+use crate::network_protocol::{PeerMessage, RoutedMessageBody};
+use crate::stats::metrics;
+use enum_map::{EnumMap, enum_map};
+use near_async::time::{Duration, Instant};
+use std::collections::{HashMap, VecDeque};
+
+/// Classes of the messages sent to peers, which can be limited separately.
+#[derive(
+    Clone,
+    Copy,
+    enum_map::Enum,
+    strum::Display,
+    strum::IntoStaticStr,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum MessageClass {
+    /// Blocks, optimistic blocks and block headers.
+    Blocks,
+    /// Parts of the chunks and the receipt proofs sent along them.
+    Chunks,
+    /// Parts of the chunk state witnesses and the contract code they require.
+    Witnesses,
+    /// State sync headers and parts.
+    StateParts,
+    /// All the other messages.
+    Other,
+}
+
+impl MessageClass {
+    pub(crate) fn of(message: &PeerMessage) -> Self {
+        match message {
+            PeerMessage::Block(_)
+            | PeerMessage::OptimisticBlock(_)
+            | PeerMessage::BlockHeaders(_) => Self::Blocks,
+            PeerMessage::VersionedStateResponse(_) => Self::StateParts,
+            PeerMessage::Routed(msg) => match msg.body {
+                RoutedMessageBody::PartialEncodedChunkResponse(_)
+                | RoutedMessageBody::VersionedPartialEncodedChunk(_)
+                | RoutedMessageBody::PartialEncodedChunkForward(_)
+                | RoutedMessageBody::ChunkResponse(_)
+                | RoutedMessageBody::ReceiptProofFragment(_) => Self::Chunks,
+                RoutedMessageBody::PartialEncodedStateWitness(_)
+                | RoutedMessageBody::PartialEncodedStateWitnessForward(_)
+                | RoutedMessageBody::ContractCodeResponse(_) => Self::Witnesses,
+                _ => Self::Other,
+            },
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Bandwidth limit enforced by a single token bucket.
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct BandwidthLimit {
+    /// Sustained bandwidth, in bytes per second.
+    pub bytes_per_second: u32,
+    /// Bytes which can be sent at once after an idle period. A message larger than this
+    /// is sent once the bucket is full, so it should be at least the size of the largest
+    /// message of the class for the limit to hold.
+    pub burst_bytes: u32,
+}
+
+impl BandwidthLimit {
+    fn validate(&self) -> anyhow::Result<()> {
+        TokenBucket::validate_refill_rate(self.bytes_per_second as f32)?;
+        if self.bytes_per_second == 0 {
+            anyhow::bail!("bytes_per_second has to be positive");
+        }
+        if self.burst_bytes == 0 {
+            anyhow::bail!("burst_bytes has to be positive");
+        }
+        Ok(())
+    }
+
+    fn new_bucket(&self, start_time: Instant) -> Option<TokenBucket> {
+        match TokenBucket::new(
+            self.burst_bytes,
+            self.burst_bytes,
+            self.bytes_per_second as f32,
+            start_time,
+        ) {
+            Ok(bucket) => Some(bucket),
+            Err(err) => {
+                tracing::warn!(target: "network", "ignoring bandwidth limit due to an error ({err})");
+                None
+            }
+        }
+    }
+}
+
+/// Default for [`Config::max_queued_bytes_per_class`].
+const DEFAULT_MAX_QUEUED_BYTES_PER_CLASS: u32 = 64 * 1024 * 1024;
+
+fn default_max_queued_bytes_per_class() -> u32 {
+    DEFAULT_MAX_QUEUED_BYTES_PER_CLASS
+}
+
+/// Limits on the bandwidth used by the messages sent to each peer.
+/// The messages sent on TIER1 connections are not limited.
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct Config {
+    /// Limit on all the messages sent to a single peer.
+    #[serde(default)]
+    pub per_peer: Option<BandwidthLimit>,
+    /// Limits on the messages of each class sent to a single peer.
+    #[serde(default)]
+    pub per_class: HashMap<MessageClass, BandwidthLimit>,
+    /// Bytes of the messages of a single class which can wait for the limits. The messages
+    /// of the class pushed over it are dropped, e.g. the state parts requested by a peer
+    /// faster than the limits let them through. A single message is always queued.
+    #[serde(default = "default_max_queued_bytes_per_class")]
+    pub max_queued_bytes_per_class: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            per_peer: None,
+            per_class: HashMap::new(),
+            max_queued_bytes_per_class: DEFAULT_MAX_QUEUED_BYTES_PER_CLASS,
+        }
+    }
+}
+
+impl Config {
+    /// Validates this configuration object.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(limit) = &self.per_peer {
+            limit.validate().map_err(|err| anyhow::anyhow!("per_peer: {err}"))?;
+        }
+        for (class, limit) in &self.per_class {
+            limit.validate().map_err(|err| anyhow::anyhow!("per_class.{class}: {err}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Token buckets enforcing the bandwidth limits of a single connection.
+pub(crate) struct BandwidthLimits {
+    peer: Option<TokenBucket>,
+    classes: EnumMap<MessageClass, Option<TokenBucket>>,
+    max_queued_bytes_per_class: u64,
+}
+
+impl Default for BandwidthLimits {
+    fn default() -> Self {
+        Self {
+            peer: None,
+            classes: enum_map! { _ => None },
+            max_queued_bytes_per_class: DEFAULT_MAX_QUEUED_BYTES_PER_CLASS.into(),
+        }
+    }
+}
+
+impl BandwidthLimits {
+    /// Creates all buckets as configured in `config`.
+    pub fn from_config(config: &Config, start_time: Instant) -> Self {
+        let mut classes = enum_map! { _ => None };
+        for (class, limit) in &config.per_class {
+            classes[*class] = limit.new_bucket(start_time);
+        }
+        Self {
+            peer: config.per_peer.and_then(|limit| limit.new_bucket(start_time)),
+            classes,
+            max_queued_bytes_per_class: config.max_queued_bytes_per_class.into(),
+        }
+    }
+
+    /// Whether the messages of `class` can be held back by the limits.
+    fn is_limited(&self, class: MessageClass) -> bool {
+        self.peer.is_some() || self.classes[class].is_some()
+    }
+}
+
+/// Returns how long it takes until the bucket lets `bytes` bytes through.
+/// A message larger than the bucket costs the whole bucket.
+fn time_until_available(
+    bucket: &mut Option<TokenBucket>,
+    bytes: u32,
+    now: Instant,
+) -> Option<Duration> {
+    match bucket {
+        Some(bucket) => bucket.time_until_available(bytes.min(bucket.maximum_size()), now),
+        None => Some(Duration::ZERO),
+    }
+}
+
+fn acquire(bucket: &mut Option<TokenBucket>, bytes: u32, now: Instant) {
+    if let Some(bucket) = bucket {
+        bucket.acquire(bytes.min(bucket.maximum_size()), now);
+    }
+}
+
+struct QueuedItem<T> {
+    /// Order of arrival of the item in the queue.
+    seq: u64,
+    bytes: u32,
+    /// Whether the item has been held back by the limits already.
+    throttled: bool,
+    item: T,
+}
+
+/// Queue of the messages waiting to be sent to a single peer, shaped by its [BandwidthLimits].
+///
+/// Messages of the same class leave the queue in order. A message held back by the limit of
+/// its class lets the younger messages of the other classes through, while a message held back
+/// by the limit of the peer holds back the younger messages as well. Messages without a class
+/// are not limited and don't consume any bandwidth of the limits.
+pub(crate) struct ShapedQueue<T> {
+    limits: BandwidthLimits,
+    classes: EnumMap<MessageClass, VecDeque<QueuedItem<T>>>,
+    /// Total size of the messages in each of `classes`.
+    queued_bytes: EnumMap<MessageClass, u64>,
+    unlimited: VecDeque<QueuedItem<T>>,
+    next_seq: u64,
+}
+
+impl<T> ShapedQueue<T> {
+    pub fn new(limits: BandwidthLimits) -> Self {
+        Self {
+            limits,
+            classes: enum_map! { _ => VecDeque::new() },
+            queued_bytes: enum_map! { _ => 0 },
+            unlimited: VecDeque::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unlimited.is_empty() && self.classes.values().all(|queue| queue.is_empty())
+    }
+
+    /// Pushes `item` of size `bytes` to the queue of its class.
+    /// Returns the item back if it is dropped because the limits already hold back
+    /// `max_queued_bytes_per_class` bytes of its class.
+    pub fn push(&mut self, class: Option<MessageClass>, bytes: usize, item: T) -> Result<(), T> {
+        let bytes = u32::try_from(bytes).unwrap_or(u32::MAX);
+        if let Some(class) = class {
+            let queued_bytes = self.queued_bytes[class] + bytes as u64;
+            if self.limits.is_limited(class)
+                && !self.classes[class].is_empty()
+                && queued_bytes > self.limits.max_queued_bytes_per_class
+            {
+                metrics::PEER_DROPPED_THROTTLED_BYTES
+                    .with_label_values(&[class.into()])
+                    .inc_by(bytes as u64);
+                return Err(item);
+            }
+            self.queued_bytes[class] = queued_bytes;
+        }
+        let item = QueuedItem { seq: self.next_seq, bytes, throttled: false, item };
+        self.next_seq += 1;
+        match class {
+            Some(class) => self.classes[class].push_back(item),
+            None => self.unlimited.push_back(item),
+        }
+        Ok(())
+    }
+
+    /// Classes of the messages at the front of the queues, oldest first.
+    /// `None` stands for the messages without a class.
+    fn fronts(&self) -> Vec<Option<MessageClass>> {
+        let mut fronts: Vec<_> = self
+            .classes
+            .iter()
+            .filter_map(|(class, queue)| Some((queue.front()?.seq, Some(class))))
+            .chain(self.unlimited.front().map(|item| (item.seq, None)))
+            .collect();
+        fronts.sort_by_key(|(seq, _)| *seq);
+        fronts.into_iter().map(|(_, class)| class).collect()
+    }
+
+    /// Removes and returns the oldest message which the limits let through at `now`.
+    pub fn pop_ready(&mut self, now: Instant) -> Option<T> {
+        let mut peer_throttled = false;
+        for class in self.fronts() {
+            let Some(class) = class else {
+                return self.unlimited.pop_front().map(|item| item.item);
+            };
+            if peer_throttled {
+                continue;
+            }
+            let item = self.classes[class].front_mut().unwrap();
+            let class_wait = time_until_available(&mut self.limits.classes[class], item.bytes, now);
+            let peer_wait = time_until_available(&mut self.limits.peer, item.bytes, now);
+            let class_ready = class_wait == Some(Duration::ZERO);
+            let peer_ready = peer_wait == Some(Duration::ZERO);
+            if class_ready && peer_ready {
+                acquire(&mut self.limits.classes[class], item.bytes, now);
+                acquire(&mut self.limits.peer, item.bytes, now);
+                self.queued_bytes[class] -= item.bytes as u64;
+                return self.classes[class].pop_front().map(|item| item.item);
+            }
+            if !item.throttled {
+                item.throttled = true;
+                metrics::PEER_THROTTLED_BYTES
+                    .with_label_values(&[class.into()])
+                    .inc_by(item.bytes as u64);
+            }
+            peer_throttled = class_ready;
+        }
+        None
+    }
+
+    /// Returns how long it takes, starting from `now`, until the limits let a message
+    /// through, or `None` if the queue is empty.
+    pub fn time_until_ready(&mut self, now: Instant) -> Option<Duration> {
+        let mut result: Option<Duration> = None;
+        for class in self.fronts() {
+            let Some(class) = class else {
+                return Some(Duration::ZERO);
+            };
+            let bytes = self.classes[class].front().unwrap().bytes;
+            let class_wait = time_until_available(&mut self.limits.classes[class], bytes, now);
+            let peer_wait = time_until_available(&mut self.limits.peer, bytes, now);
+            let (Some(class_wait), Some(peer_wait)) = (class_wait, peer_wait) else {
+                continue;
+            };
+            let wait = class_wait.max(peer_wait);
+            result = Some(result.map_or(wait, |result| result.min(wait)));
+            // The younger messages can't overtake a message held back by the peer limit only.
+            if class_wait == Duration::ZERO {
+                break;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_protocol::Disconnect;
+    use near_primitives::hash::CryptoHash;
+
+    fn limit(bytes_per_second: u32, burst_bytes: u32) -> BandwidthLimit {
+        BandwidthLimit { bytes_per_second, burst_bytes }
+    }
+
+    #[test]
+    fn message_class() {
+        assert_eq!(MessageClass::of(&PeerMessage::BlockHeaders(vec![])), MessageClass::Blocks);
+        assert_eq!(
+            MessageClass::of(&PeerMessage::BlockRequest(CryptoHash::default())),
+            MessageClass::Other
+        );
+        assert_eq!(
+            MessageClass::of(&PeerMessage::Disconnect(Disconnect {
+                remove_from_connection_store: false
+            })),
+            MessageClass::Other
+        );
+    }
+
+    #[test]
+    fn configuration_errors() {
+        let mut config = Config::default();
+        assert!(config.validate().is_ok());
+
+        config.per_class.insert(MessageClass::StateParts, limit(1000, 1000));
+        config.per_peer = Some(limit(2000, 1000));
+        assert!(config.validate().is_ok());
+
+        config.per_peer = Some(limit(0, 1000));
+        assert!(config.validate().is_err());
+
+        config.per_peer = None;
+        config.per_class.insert(MessageClass::Blocks, limit(1000, 0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn unlimited() {
+        let now = Instant::now();
+        let mut queue = ShapedQueue::new(BandwidthLimits::default());
+        assert!(queue.is_empty());
+        assert_eq!(queue.time_until_ready(now), None);
+        queue.push(Some(MessageClass::StateParts), 100, 1).unwrap();
+        queue.push(None, 100, 2).unwrap();
+        queue.push(Some(MessageClass::Blocks), 100, 3).unwrap();
+        assert_eq!(queue.time_until_ready(now), Some(Duration::ZERO));
+        assert_eq!(queue.pop_ready(now), Some(1));
+        assert_eq!(queue.pop_ready(now), Some(2));
+        assert_eq!(queue.pop_ready(now), Some(3));
+        assert_eq!(queue.pop_ready(now), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn class_limit() {
+        let now = Instant::now();
+        let mut config = Config::default();
+        config.per_class.insert(MessageClass::StateParts, limit(1000, 1000));
+        let mut queue = ShapedQueue::new(BandwidthLimits::from_config(&config, now));
+
+        queue.push(Some(MessageClass::StateParts), 1000, 1).unwrap();
+        queue.push(Some(MessageClass::StateParts), 500, 2).unwrap();
+        queue.push(Some(MessageClass::Blocks), 5000, 3).unwrap();
+        assert_eq!(queue.pop_ready(now), Some(1));
+        // The second state part has to wait, but lets the block through.
+        assert_eq!(queue.pop_ready(now), Some(3));
+        assert_eq!(queue.pop_ready(now), None);
+        assert_eq!(queue.time_until_ready(now), Some(Duration::milliseconds(500)));
+        assert_eq!(queue.pop_ready(now + Duration::milliseconds(500)), Some(2));
+
+        // A message larger than the burst waits for the bucket to be full.
+        let now = now + Duration::milliseconds(500);
+        queue.push(Some(MessageClass::StateParts), 3000, 4).unwrap();
+        assert_eq!(queue.time_until_ready(now), Some(Duration::seconds(1)));
+        assert_eq!(queue.pop_ready(now + Duration::seconds(1)), Some(4));
+    }
+
+    #[test]
+    fn peer_limit() {
+        let now = Instant::now();
+        let config = Config { per_peer: Some(limit(1000, 1000)), ..Default::default() };
+        let mut queue = ShapedQueue::new(BandwidthLimits::from_config(&config, now));
+
+        queue.push(Some(MessageClass::StateParts), 800, 1).unwrap();
+        queue.push(Some(MessageClass::Witnesses), 400, 2).unwrap();
+        queue.push(Some(MessageClass::Blocks), 100, 3).unwrap();
+        queue.push(None, 100, 4).unwrap();
+        assert_eq!(queue.pop_ready(now), Some(1));
+        // The witness is held back by the peer limit, and so is the younger block,
+        // but not the message without a class.
+        assert_eq!(queue.pop_ready(now), Some(4));
+        assert_eq!(queue.pop_ready(now), None);
+        assert_eq!(queue.time_until_ready(now), Some(Duration::milliseconds(200)));
+        let now = now + Duration::milliseconds(200);
+        assert_eq!(queue.pop_ready(now), Some(2));
+        assert_eq!(queue.pop_ready(now), None);
+        assert_eq!(queue.pop_ready(now + Duration::milliseconds(100)), Some(3));
+    }
+
+    #[test]
+    fn max_queued_bytes() {
+        let now = Instant::now();
+        let mut config = Config { max_queued_bytes_per_class: 1000, ..Default::default() };
+        config.per_class.insert(MessageClass::StateParts, limit(1000, 1000));
+        let mut queue = ShapedQueue::new(BandwidthLimits::from_config(&config, now));
+
+        // A single message is queued even if it is larger than the bound.
+        assert_eq!(queue.push(Some(MessageClass::StateParts), 3000, 1), Ok(()));
+        assert_eq!(queue.pop_ready(now), Some(1));
+        assert_eq!(queue.push(Some(MessageClass::StateParts), 600, 2), Ok(()));
+        assert_eq!(queue.push(Some(MessageClass::StateParts), 400, 3), Ok(()));
+        assert_eq!(queue.push(Some(MessageClass::StateParts), 1, 4), Err(4));
+        // The classes which are not limited are not bounded either.
+        assert_eq!(queue.push(Some(MessageClass::Blocks), 5000, 5), Ok(()));
+        assert_eq!(queue.push(Some(MessageClass::Blocks), 5000, 6), Ok(()));
+        assert_eq!(queue.pop_ready(now), Some(5));
+        assert_eq!(queue.pop_ready(now), Some(6));
+
+        // The bytes of the messages sent leave room for new ones.
+        let now = now + Duration::seconds(3);
+        assert_eq!(queue.pop_ready(now), Some(2));
+        assert_eq!(queue.push(Some(MessageClass::StateParts), 600, 7), Ok(()));
+        assert_eq!(queue.push(Some(MessageClass::StateParts), 1, 8), Err(8));
+    }
+}
@@ -114,6 +114,14 @@ impl Config {
             RateLimitedPeerMessageKey::EpochSyncRequest,
             SingleMessageConfig::new(1, 1.0 / 30.0, None),
         );
+        // ChunkRequest asks for a whole chunk, possibly an old one which has to be read from the
+        // store and encoded again, so it costs the responder much more than the sender. A node
+        // only fetches a few chunks at a time and retries each of them every couple of seconds,
+        // so a small burst with a slow refill is enough.
+        config.rate_limits.insert(
+            RateLimitedPeerMessageKey::ChunkRequest,
+            SingleMessageConfig::new(10, 1.0, None),
+        );
         config
     }
 
@@ -167,12 +175,16 @@ pub enum RateLimitedPeerMessageKey {
     StateResponse,
     PartialEncodedChunkRequest,
     PartialEncodedChunkResponse,
+    ChunkRequest,
+    ChunkResponse,
     VersionedPartialEncodedChunk,
     PartialEncodedChunkForward,
+    ReceiptProofFragment,
     ChunkEndorsement,
     ChunkStateWitnessAck,
     PartialEncodedStateWitness,
     PartialEncodedStateWitnessForward,
+    PartialEncodedStateWitnessRequest,
     ChunkContractAccesses,
     ContractCodeRequest,
     ContractCodeResponse,
@@ -213,12 +225,15 @@ fn get_key_and_token_cost(message: &PeerMessage) -> Option<(RateLimitedPeerMessa
             RoutedMessageBody::PartialEncodedChunkResponse(_) => {
                 Some((PartialEncodedChunkResponse, 1))
             }
+            RoutedMessageBody::ChunkRequest(_) => Some((ChunkRequest, 1)),
+            RoutedMessageBody::ChunkResponse(_) => Some((ChunkResponse, 1)),
             RoutedMessageBody::VersionedPartialEncodedChunk(_) => {
                 Some((VersionedPartialEncodedChunk, 1))
             }
             RoutedMessageBody::PartialEncodedChunkForward(_) => {
                 Some((PartialEncodedChunkForward, 1))
             }
+            RoutedMessageBody::ReceiptProofFragment(_) => Some((ReceiptProofFragment, 1)),
             RoutedMessageBody::ChunkStateWitnessAck(_) => Some((ChunkStateWitnessAck, 1)),
             RoutedMessageBody::PartialEncodedStateWitness(_) => {
                 Some((PartialEncodedStateWitness, 1))
@@ -226,6 +241,9 @@ fn get_key_and_token_cost(message: &PeerMessage) -> Option<(RateLimitedPeerMessa
             RoutedMessageBody::PartialEncodedStateWitnessForward(_) => {
                 Some((PartialEncodedStateWitnessForward, 1))
             }
+            RoutedMessageBody::PartialEncodedStateWitnessRequest(_) => {
+                Some((PartialEncodedStateWitnessRequest, 1))
+            }
             RoutedMessageBody::ChunkContractAccesses(_) => Some((ChunkContractAccesses, 1)),
             RoutedMessageBody::ContractCodeRequest(_) => Some((ContractCodeRequest, 1)),
             RoutedMessageBody::ContractCodeResponse(_) => Some((ContractCodeResponse, 1)),
@@ -270,9 +288,12 @@ fn get_key_and_token_cost(message: &PeerMessage) -> Option<(RateLimitedPeerMessa
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::network_protocol::{Disconnect, PeerMessage};
+    use crate::network_protocol::testonly::make_routed_message;
+    use crate::network_protocol::{ChunkRequestMsg, Disconnect, PeerMessage};
+    use crate::testonly::make_rng;
     use near_async::time::{Duration, FakeClock};
     use near_primitives::hash::CryptoHash;
+    use near_primitives::sharding::ChunkHash;
 
     #[test]
     fn is_allowed() {
@@ -480,4 +501,25 @@ mod tests {
         clock.advance(Duration::seconds(30));
         assert!(rate_limits.is_allowed(&PeerMessage::EpochSyncRequest, clock.now()));
     }
+
+    #[test]
+    fn test_chunk_request_rate_limit() {
+        let config = Config::standard_preset();
+        let clock = FakeClock::default();
+        let mut rate_limits = RateLimits::from_config(&config, clock.now());
+        let mut rng = make_rng(921853233);
+        let chunk_request = PeerMessage::Routed(Box::new(make_routed_message(
+            &mut rng,
+            RoutedMessageBody::ChunkRequest(ChunkRequestMsg {
+                chunk_hash: ChunkHash(CryptoHash::default()),
+            }),
+        )));
+        for _ in 0..10 {
+            assert!(rate_limits.is_allowed(&chunk_request, clock.now()));
+        }
+        assert!(!rate_limits.is_allowed(&chunk_request, clock.now()));
+        clock.advance(Duration::seconds(1));
+        assert!(rate_limits.is_allowed(&chunk_request, clock.now()));
+        assert!(!rate_limits.is_allowed(&chunk_request, clock.now()));
+    }
 }
@@ -10,7 +10,7 @@
 //! or delayed. However, this module responsibility stops at telling
 //! whether or not the incoming messages are allowed.
 
-use near_async::time::Instant;
+use near_async::time::{Duration, Instant};
 
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum TokenBucketError {
@@ -80,6 +80,29 @@ impl TokenBucket {
         }
     }
 
+    /// Maximum number of tokens the bucket can hold.
+    pub fn maximum_size(&self) -> u32 {
+        self.maximum_size
+    }
+
+    /// Returns how long it takes, starting from `now`, until `tokens` tokens are available
+    /// in the bucket. Returns zero if they are available already, and `None` if they never
+    /// will be, because `tokens` exceeds `maximum_size` or the bucket is never refilled.
+    ///
+    /// The bucket is refilled, but no tokens are subtracted.
+    pub fn time_until_available(&mut self, tokens: u32, now: Instant) -> Option<Duration> {
+        self.refill(now);
+        let missing = to_tokens_with_parts(tokens).saturating_sub(self.size);
+        if missing == 0 {
+            return Some(Duration::ZERO);
+        }
+        if tokens > self.maximum_size || self.refill_rate == 0.0 {
+            return None;
+        }
+        let seconds = missing as f64 / TOKEN_PARTS_NUMBER as f64 / self.refill_rate as f64;
+        Some(Duration::seconds_f64(seconds))
+    }
+
     /// Refills the bucket with the right number of tokens according to
     /// the `refill_rate` and the new current time `now`.
     ///
@@ -195,6 +218,22 @@ mod tests {
         assert!(bucket.acquire(1, now + Duration::milliseconds(500)));
     }
 
+    #[test]
+    fn time_until_available() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2, 10, 4.0, now).expect("bucket should be well formed");
+        assert_eq!(bucket.time_until_available(2, now), Some(Duration::ZERO));
+        assert_eq!(bucket.time_until_available(4, now), Some(Duration::milliseconds(500)));
+        assert_eq!(bucket.time_until_available(11, now), None);
+        // Nothing is subtracted.
+        assert!(bucket.acquire(2, now));
+        assert_eq!(bucket.time_until_available(1, now), Some(Duration::milliseconds(250)));
+
+        let mut bucket = TokenBucket::new(0, 10, 0.0, now).expect("bucket should be well formed");
+        assert_eq!(bucket.time_until_available(0, now), Some(Duration::ZERO));
+        assert_eq!(bucket.time_until_available(1, now), None);
+    }
+
     #[test]
     fn zero_refill_rate() {
         let now = Instant::now();
@@ -5,6 +5,7 @@ mod edge_cache;
 mod graph;
 #[cfg(feature = "distance_vector_routing")]
 mod graph_v2;
+pub(crate) mod replay_guard;
 pub(crate) mod route_back_cache;
 pub mod routing_table_view;
 
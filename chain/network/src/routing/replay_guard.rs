@@ -0,0 +1,99 @@
+use near_async::time;
+use near_primitives::hash::CryptoHash;
+use std::num::NonZeroUsize;
+
+/// Detects routed messages which are delivered to us more than once.
+///
+/// Routed messages are identified by the hash of their signed content, i.e.
+/// `RoutedMessage::hash()`. Signatures are deterministic, so a message relayed
+/// again by a peer (or resent by its author) has the same hash as the original.
+/// A message is considered a replay if the same hash was recorded within the
+/// last `window`.
+///
+/// The cache is bounded, so under very high load entries may be evicted before
+/// `window` passes. This only weakens the protection, it never drops a message
+/// which wasn't seen before.
+pub(crate) struct ReplayGuard {
+    window: time::Duration,
+    seen: lru::LruCache<CryptoHash, time::Instant>,
+}
+
+impl ReplayGuard {
+    pub fn new(window: time::Duration, capacity: NonZeroUsize) -> Self {
+        Self { window, seen: lru::LruCache::new(capacity) }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.window > time::Duration::ZERO
+    }
+
+    /// Checks whether the message was already recorded within the window,
+    /// without recording it.
+    pub fn is_replay(&self, now: time::Instant, hash: &CryptoHash) -> bool {
+        self.is_enabled()
+            && self.seen.peek(hash).is_some_and(|&seen_at| now <= seen_at + self.window)
+    }
+
+    /// Records the message. Returns false if it is a replay, in which case
+    /// the time of the original message is kept.
+    ///
+    /// Only record messages with a verified signature, otherwise anyone could
+    /// get a message dropped by sending a forged copy of it first.
+    pub fn record(&mut self, now: time::Instant, hash: CryptoHash) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        if self.is_replay(now, &hash) {
+            return false;
+        }
+        self.seen.put(hash, now);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplayGuard;
+    use near_async::time;
+    use near_primitives::hash::hash;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_replays_within_window() {
+        let clock = time::FakeClock::default();
+        let mut guard =
+            ReplayGuard::new(time::Duration::seconds(10), NonZeroUsize::new(2).unwrap());
+        let (a, b, c) = (hash(b"a"), hash(b"b"), hash(b"c"));
+
+        assert!(!guard.is_replay(clock.now(), &a));
+        assert!(guard.record(clock.now(), a));
+        assert!(guard.is_replay(clock.now(), &a));
+        assert!(!guard.record(clock.now(), a));
+
+        // Replays don't extend the window.
+        clock.advance(time::Duration::seconds(6));
+        assert!(!guard.record(clock.now(), a));
+        clock.advance(time::Duration::seconds(6));
+        assert!(guard.record(clock.now(), a));
+
+        // The oldest entry is evicted when the cache is full.
+        assert!(guard.record(clock.now(), b));
+        assert!(guard.record(clock.now(), c));
+        assert!(guard.record(clock.now(), a));
+    }
+
+    #[test]
+    fn test_zero_window_disables_guard() {
+        let clock = time::FakeClock::default();
+        let mut guard = ReplayGuard::new(time::Duration::ZERO, NonZeroUsize::new(2).unwrap());
+        let a = hash(b"a");
+        assert!(guard.record(clock.now(), a));
+        assert!(!guard.is_replay(clock.now(), &a));
+        assert!(guard.record(clock.now(), a));
+        assert_eq!(guard.len(), 0);
+    }
+}
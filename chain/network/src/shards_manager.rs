@@ -1,9 +1,13 @@
 use actix::Message;
 use near_async::time::Instant;
-use near_primitives::{hash::CryptoHash, sharding::PartialEncodedChunk};
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::sharding::PartialEncodedChunk;
+use near_primitives::sharding::receipt_proof_fragment::ReceiptProofFragment;
 
 use crate::types::{
-    PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
+    ChunkRequestMsg, ChunkResponseMsg, PartialEncodedChunkForwardMsg,
+    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
 };
 
 #[derive(Message, Debug, strum::IntoStaticStr, Clone, PartialEq, Eq)]
@@ -12,6 +16,11 @@ use crate::types::{
 pub enum ShardsManagerRequestFromNetwork {
     ProcessPartialEncodedChunk(PartialEncodedChunk),
     ProcessPartialEncodedChunkForward(PartialEncodedChunkForwardMsg),
+    ProcessReceiptProofFragment {
+        fragment: ReceiptProofFragment,
+        /// Author of the message, which the size of the cached fragments is limited by.
+        peer_id: PeerId,
+    },
     ProcessPartialEncodedChunkResponse {
         partial_encoded_chunk_response: PartialEncodedChunkResponseMsg,
         received_time: Instant,
@@ -20,4 +29,9 @@ pub enum ShardsManagerRequestFromNetwork {
         partial_encoded_chunk_request: PartialEncodedChunkRequestMsg,
         route_back: CryptoHash,
     },
+    ProcessChunkRequest {
+        chunk_request: ChunkRequestMsg,
+        route_back: CryptoHash,
+    },
+    ProcessChunkResponse(ChunkResponseMsg),
 }
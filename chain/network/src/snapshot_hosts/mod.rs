@@ -2,22 +2,22 @@
 //!
 //! Each node in the network which is willing to generate and serve state snapshots
 //! publishes a SnapshotHostInfo once per epoch. The info is flooded to all nodes
-//! in the network and stored locally in this cache.
+//! in the network and stored locally in this cache, on top of the `gossip`
+//! topic layer.
 
-use crate::concurrency;
+use crate::concurrency::rate;
+use crate::gossip::{GossipError, Topic, TopicConfig, TopicState};
 use crate::network_protocol::SnapshotHostInfo;
 use crate::network_protocol::SnapshotHostInfoVerificationError;
-use lru::LruCache;
+use near_async::time::Instant;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
 use near_primitives::types::ShardId;
 use parking_lot::Mutex;
 use rand::prelude::IteratorRandom;
 use rand::thread_rng;
-use rayon::iter::ParallelBridge;
 use sha2::{Digest, Sha256};
 use std::collections::{BinaryHeap, HashMap, HashSet};
-use std::num::NonZeroUsize;
 use std::sync::Arc;
 
 #[cfg(test)]
@@ -31,6 +31,38 @@ pub(crate) enum SnapshotHostInfoError {
     VerificationError(#[from] SnapshotHostInfoVerificationError),
 }
 
+impl From<GossipError<SnapshotHostInfoVerificationError>> for SnapshotHostInfoError {
+    fn from(err: GossipError<SnapshotHostInfoVerificationError>) -> Self {
+        match err {
+            GossipError::DuplicateKey => Self::DuplicatePeerId,
+            GossipError::Invalid(err) => Self::VerificationError(err),
+        }
+    }
+}
+
+/// Every node publishes a single SnapshotHostInfo at a time, superseded by the
+/// one of the next epoch.
+struct SnapshotHostsTopic;
+
+impl Topic for SnapshotHostsTopic {
+    const NAME: &'static str = "snapshot_hosts";
+    type Key = PeerId;
+    type Item = SnapshotHostInfo;
+    type Error = SnapshotHostInfoVerificationError;
+
+    fn key(item: &SnapshotHostInfo) -> PeerId {
+        item.peer_id.clone()
+    }
+
+    fn is_newer(item: &SnapshotHostInfo, old: &SnapshotHostInfo) -> bool {
+        item.epoch_height > old.epoch_height
+    }
+
+    fn validate(item: &SnapshotHostInfo) -> Result<(), SnapshotHostInfoVerificationError> {
+        item.verify()
+    }
+}
+
 #[derive(Clone)]
 pub struct Config {
     /// The maximum number of SnapshotHostInfos to store locally.
@@ -41,6 +73,9 @@ pub struct Config {
     /// The number of hosts we'll add to structures related to state part peer
     /// selection each time we need to request parts from a new peer
     pub part_selection_cache_batch_size: u32,
+    /// SnapshotHostInfos accepted from every peer, the ones above the limit
+    /// are dropped. If not set, all the SnapshotHostInfos are accepted.
+    pub rate_limit: Option<rate::Limit>,
 }
 
 /// When multiple hosts offer the same part, this hash is compared
@@ -129,8 +164,6 @@ impl PartPeerSelector {
 }
 
 struct Inner {
-    /// The latest known SnapshotHostInfo for each node in the network
-    hosts: LruCache<PeerId, Arc<SnapshotHostInfo>>,
     /// The hash for the most recent active state sync, inferred from part requests
     sync_hash: Option<CryptoHash>,
     /// Available hosts for the active state sync, by shard
@@ -142,21 +175,9 @@ struct Inner {
 }
 
 impl Inner {
-    fn is_new(&self, h: &SnapshotHostInfo) -> bool {
-        match self.hosts.peek(&h.peer_id) {
-            Some(old) if old.epoch_height >= h.epoch_height => false,
-            _ => true,
-        }
-    }
-
-    /// Inserts d into self.data, if it's new.
-    /// It returns the newly inserted value (or None if nothing changed).
-    /// The returned value should be broadcasted to the network.
-    fn try_insert(&mut self, d: Arc<SnapshotHostInfo>) -> Option<Arc<SnapshotHostInfo>> {
-        if !self.is_new(&d) {
-            return None;
-        }
-
+    /// Updates the hosts for the active state sync with a newly inserted
+    /// SnapshotHostInfo.
+    fn on_inserted(&mut self, d: &SnapshotHostInfo) {
         if self.sync_hash == Some(d.sync_hash) {
             for shard_id in &d.shards {
                 self.hosts_for_shard
@@ -165,27 +186,21 @@ impl Inner {
                     .insert(d.peer_id.clone());
             }
         }
-        self.hosts.push(d.peer_id.clone(), d.clone());
-
-        Some(d)
     }
 
     /// Clears internal state if the sync hash has changed.
-    fn maybe_update_sync_hash(&mut self, sync_hash: &CryptoHash) {
+    fn maybe_update_sync_hash(
+        &mut self,
+        topic: &TopicState<SnapshotHostsTopic>,
+        sync_hash: &CryptoHash,
+    ) {
         if self.sync_hash != Some(*sync_hash) {
             self.sync_hash = Some(*sync_hash);
             self.hosts_for_shard.clear();
             self.peer_selector.clear();
 
-            for (peer_id, info) in &self.hosts {
-                if info.sync_hash == *sync_hash {
-                    for shard_id in &info.shards {
-                        self.hosts_for_shard
-                            .entry(*shard_id)
-                            .or_insert(HashSet::default())
-                            .insert(peer_id.clone());
-                    }
-                }
+            for info in topic.items() {
+                self.on_inserted(&info);
             }
         }
     }
@@ -194,10 +209,11 @@ impl Inner {
     /// selects a host to which the request should be routed.
     pub fn select_host_for_header(
         &mut self,
+        topic: &TopicState<SnapshotHostsTopic>,
         sync_hash: &CryptoHash,
         shard_id: ShardId,
     ) -> Option<PeerId> {
-        self.maybe_update_sync_hash(sync_hash);
+        self.maybe_update_sync_hash(topic, sync_hash);
 
         self.hosts_for_shard.get(&shard_id)?.iter().choose(&mut thread_rng()).cloned()
     }
@@ -206,11 +222,12 @@ impl Inner {
     /// selects a host to which the request should be routed.
     pub fn select_host_for_part(
         &mut self,
+        topic: &TopicState<SnapshotHostsTopic>,
         sync_hash: &CryptoHash,
         shard_id: ShardId,
         part_id: u64,
     ) -> Option<PeerId> {
-        self.maybe_update_sync_hash(sync_hash);
+        self.maybe_update_sync_hash(topic, sync_hash);
 
         let selector =
             self.peer_selector.entry((shard_id, part_id)).or_insert(PartPeerSelector::default());
@@ -248,61 +265,37 @@ impl Inner {
     }
 }
 
-pub(crate) struct SnapshotHostsCache(Mutex<Inner>);
+/// The lock of `inner` may be held while accessing `topic`, never the other
+/// way around.
+pub(crate) struct SnapshotHostsCache {
+    topic: TopicState<SnapshotHostsTopic>,
+    inner: Mutex<Inner>,
+}
 
 impl SnapshotHostsCache {
     pub fn new(config: Config) -> Self {
-        Self(Mutex::new(Inner {
-            hosts: LruCache::new(
-                NonZeroUsize::new(config.snapshot_hosts_cache_size as usize).unwrap(),
-            ),
-            sync_hash: None,
-            hosts_for_shard: HashMap::new(),
-            peer_selector: HashMap::new(),
-            part_selection_cache_batch_size: config.part_selection_cache_batch_size as usize,
-        }))
+        Self {
+            topic: TopicState::new(TopicConfig {
+                max_items: config.snapshot_hosts_cache_size as usize,
+                rate_limit: config.rate_limit,
+            }),
+            inner: Mutex::new(Inner {
+                sync_hash: None,
+                hosts_for_shard: HashMap::new(),
+                peer_selector: HashMap::new(),
+                part_selection_cache_batch_size: config.part_selection_cache_batch_size as usize,
+            }),
+        }
     }
 
-    /// Selects new data and verifies the signatures.
-    /// Returns the verified new data and an optional error.
-    /// Note that even if error has been returned the partially validated output is returned anyway.
-    async fn verify(
+    /// Drops the SnapshotHostInfos `peer_id` sent above the rate limit.
+    pub fn rate_limit(
         &self,
+        peer_id: &PeerId,
+        now: Instant,
         data: Vec<Arc<SnapshotHostInfo>>,
-    ) -> (Vec<Arc<SnapshotHostInfo>>, Option<SnapshotHostInfoError>) {
-        // Filter out any data which is outdated or which we already have.
-        let mut new_data = HashMap::new();
-        {
-            let inner = self.0.lock();
-            for d in data {
-                // Sharing multiple entries for the same peer is considered malicious,
-                // since all but one are obviously outdated.
-                if new_data.contains_key(&d.peer_id) {
-                    return (vec![], Some(SnapshotHostInfoError::DuplicatePeerId));
-                }
-                // It is fine to broadcast data we already know about.
-                // It is fine to broadcast data which we know to be outdated.
-                if inner.is_new(&d) {
-                    new_data.insert(d.peer_id.clone(), d);
-                }
-            }
-        }
-
-        // Verify the signatures in parallel.
-        // Verification will stop at the first encountered error.
-        let (data, verification_result) = concurrency::rayon::run(move || {
-            concurrency::rayon::try_map_result(new_data.into_values().par_bridge(), |d| {
-                match d.verify() {
-                    Ok(()) => Ok(d),
-                    Err(err) => Err(err),
-                }
-            })
-        })
-        .await;
-        match verification_result {
-            Ok(()) => (data, None),
-            Err(err) => (data, Some(SnapshotHostInfoError::VerificationError(err))),
-        }
+    ) -> Vec<Arc<SnapshotHostInfo>> {
+        self.topic.rate_limit(peer_id, now, data)
     }
 
     /// Verifies the signatures and inserts verified data to the cache.
@@ -312,31 +305,30 @@ impl SnapshotHostsCache {
         self: &Self,
         data: Vec<Arc<SnapshotHostInfo>>,
     ) -> (Vec<Arc<SnapshotHostInfo>>, Option<SnapshotHostInfoError>) {
-        // Execute verification on the rayon threadpool.
-        let (data, err) = self.verify(data).await;
-        // Insert the successfully verified data, even if an error has been encountered.
-        let mut newly_inserted_data: Vec<Arc<SnapshotHostInfo>> = vec![];
-        let mut inner = self.0.lock();
-        for d in data {
-            if let Some(inserted) = inner.try_insert(d) {
-                newly_inserted_data.push(inserted);
-            }
+        let (newly_inserted_data, err) = self.topic.insert(data).await;
+        // Adding a host twice is a no-op, in case the sync hash got updated
+        // from the topic since the insertion.
+        let mut inner = self.inner.lock();
+        for d in &newly_inserted_data {
+            inner.on_inserted(d);
         }
-        // Return the inserted data.
-        (newly_inserted_data, err)
+        (newly_inserted_data, err.map(SnapshotHostInfoError::from))
     }
 
     /// Skips signature verification. Used only for the local node's own information.
     pub fn insert_skip_verify(self: &Self, my_info: Arc<SnapshotHostInfo>) {
-        let _ = self.0.lock().try_insert(my_info);
+        let mut inner = self.inner.lock();
+        if let Some(inserted) = self.topic.insert_skip_validation(my_info) {
+            inner.on_inserted(&inserted);
+        }
     }
 
     pub fn get_hosts(&self) -> Vec<Arc<SnapshotHostInfo>> {
-        self.0.lock().hosts.iter().map(|(_, v)| v.clone()).collect()
+        self.topic.items()
     }
 
     pub(crate) fn get_host_info(&self, peer_id: &PeerId) -> Option<Arc<SnapshotHostInfo>> {
-        self.0.lock().hosts.peek(peer_id).cloned()
+        self.topic.get(peer_id)
     }
 
     /// Given a state header request, selects a peer host to which the request should be sent.
@@ -345,7 +337,7 @@ impl SnapshotHostsCache {
         sync_hash: &CryptoHash,
         shard_id: ShardId,
     ) -> Option<PeerId> {
-        self.0.lock().select_host_for_header(sync_hash, shard_id)
+        self.inner.lock().select_host_for_header(&self.topic, sync_hash, shard_id)
     }
 
     /// Given a state part request, selects a peer host to which the request should be sent.
@@ -355,18 +347,18 @@ impl SnapshotHostsCache {
         shard_id: ShardId,
         part_id: u64,
     ) -> Option<PeerId> {
-        self.0.lock().select_host_for_part(sync_hash, shard_id, part_id)
+        self.inner.lock().select_host_for_part(&self.topic, sync_hash, shard_id, part_id)
     }
 
     /// Triggered by state sync actor after processing a state part.
     pub fn part_received(&self, shard_id: ShardId, part_id: u64) {
-        let mut inner = self.0.lock();
+        let mut inner = self.inner.lock();
         inner.peer_selector.remove(&(shard_id, part_id));
     }
 
     #[cfg(test)]
     pub(crate) fn has_selector(&self, shard_id: ShardId, part_id: u64) -> bool {
-        let inner = self.0.lock();
+        let inner = self.inner.lock();
         inner.peer_selector.contains_key(&(shard_id, part_id))
     }
 }
@@ -49,7 +49,11 @@ async fn happy_path() {
     let peer1 = PeerId::new(key1.public_key());
     let peer2 = PeerId::new(key2.public_key());
 
-    let config = Config { snapshot_hosts_cache_size: 100, part_selection_cache_batch_size: 1 };
+    let config = Config {
+        snapshot_hosts_cache_size: 100,
+        part_selection_cache_batch_size: 1,
+        rate_limit: None,
+    };
     let cache = SnapshotHostsCache::new(config);
     assert_eq!(cache.get_hosts().len(), 0); // initially empty
 
@@ -86,7 +90,11 @@ async fn invalid_signature() {
     let peer0 = PeerId::new(key0.public_key());
     let peer1 = PeerId::new(key1.public_key());
 
-    let config = Config { snapshot_hosts_cache_size: 100, part_selection_cache_batch_size: 1 };
+    let config = Config {
+        snapshot_hosts_cache_size: 100,
+        part_selection_cache_batch_size: 1,
+        rate_limit: None,
+    };
     let cache = SnapshotHostsCache::new(config);
 
     let sid_vec = |v: &[u64]| v.iter().cloned().map(Into::into).collect_vec();
@@ -122,7 +130,11 @@ async fn too_many_shards() {
     let peer0 = PeerId::new(key0.public_key());
     let peer1 = PeerId::new(key1.public_key());
 
-    let config = Config { snapshot_hosts_cache_size: 100, part_selection_cache_batch_size: 1 };
+    let config = Config {
+        snapshot_hosts_cache_size: 100,
+        part_selection_cache_batch_size: 1,
+        rate_limit: None,
+    };
     let cache = SnapshotHostsCache::new(config);
 
     let sid_vec = |v: &[u64]| v.iter().cloned().map(Into::into).collect_vec();
@@ -160,7 +172,11 @@ async fn duplicate_peer_id() {
     let key0 = data::make_secret_key(rng);
     let peer0 = PeerId::new(key0.public_key());
 
-    let config = Config { snapshot_hosts_cache_size: 100, part_selection_cache_batch_size: 1 };
+    let config = Config {
+        snapshot_hosts_cache_size: 100,
+        part_selection_cache_batch_size: 1,
+        rate_limit: None,
+    };
     let cache = SnapshotHostsCache::new(config);
 
     let sid_vec = |v: &[u64]| v.iter().cloned().map(Into::into).collect_vec();
@@ -189,7 +205,11 @@ async fn test_lru_eviction() {
     let peer1 = PeerId::new(key1.public_key());
     let peer2 = PeerId::new(key2.public_key());
 
-    let config = Config { snapshot_hosts_cache_size: 2, part_selection_cache_batch_size: 1 };
+    let config = Config {
+        snapshot_hosts_cache_size: 2,
+        part_selection_cache_batch_size: 1,
+        rate_limit: None,
+    };
     let cache = SnapshotHostsCache::new(config);
 
     let sid_vec = |v: &[u64]| v.iter().cloned().map(Into::into).collect_vec();
@@ -312,8 +332,11 @@ async fn run_select_peer_test(
     part_id: u64,
     part_selection_cache_batch_size: u32,
 ) {
-    let config =
-        Config { snapshot_hosts_cache_size: peers.len() as u32, part_selection_cache_batch_size };
+    let config = Config {
+        snapshot_hosts_cache_size: peers.len() as u32,
+        part_selection_cache_batch_size,
+        rate_limit: None,
+    };
     let cache = SnapshotHostsCache::new(config);
 
     tracing::debug!("start run_select_peer_test");
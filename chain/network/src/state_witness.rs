@@ -3,7 +3,9 @@ use near_async::{MultiSend, MultiSendMessage, MultiSenderFrom};
 use near_primitives::stateless_validation::contract_distribution::{
     ChunkContractAccesses, ContractCodeRequest, ContractCodeResponse, PartialEncodedContractDeploys,
 };
-use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitness;
+use near_primitives::stateless_validation::partial_witness::{
+    PartialEncodedStateWitness, PartialEncodedStateWitnessRequest,
+};
 use near_primitives::stateless_validation::state_witness::ChunkStateWitnessAck;
 
 #[derive(actix::Message, Clone, Debug, PartialEq, Eq)]
@@ -18,6 +20,12 @@ pub struct PartialEncodedStateWitnessMessage(pub PartialEncodedStateWitness);
 #[rtype(result = "()")]
 pub struct PartialEncodedStateWitnessForwardMessage(pub PartialEncodedStateWitness);
 
+/// Message to partial witness actor (on a chunk producer) that requests the parts
+/// of a state witness produced earlier, on behalf of a chunk validator that missed them.
+#[derive(actix::Message, Clone, Debug, PartialEq, Eq)]
+#[rtype(result = "()")]
+pub struct PartialEncodedStateWitnessRequestMessage(pub PartialEncodedStateWitnessRequest);
+
 /// Message to partial witness actor (on a chunk validator) that contains code-hashes of
 /// the contracts that are accessed when applying the previous chunk.
 #[derive(actix::Message, Clone, Debug, PartialEq, Eq)]
@@ -49,6 +57,7 @@ pub struct PartialWitnessSenderForNetwork {
     pub chunk_state_witness_ack: Sender<ChunkStateWitnessAckMessage>,
     pub partial_encoded_state_witness: Sender<PartialEncodedStateWitnessMessage>,
     pub partial_encoded_state_witness_forward: Sender<PartialEncodedStateWitnessForwardMessage>,
+    pub partial_encoded_state_witness_request: Sender<PartialEncodedStateWitnessRequestMessage>,
     pub chunk_contract_accesses: Sender<ChunkContractAccessesMessage>,
     pub contract_code_request: Sender<ContractCodeRequestMessage>,
     pub contract_code_response: Sender<ContractCodeResponseMessage>,
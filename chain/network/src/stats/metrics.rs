@@ -10,6 +10,7 @@ use near_o11y::metrics::{
     try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
     try_create_int_gauge, try_create_int_gauge_vec,
 };
+use near_primitives::version::ProtocolVersion;
 use std::sync::LazyLock;
 
 /// Labels represents a schema of an IntGaugeVec metric.
@@ -114,6 +115,46 @@ pub static PEER_CONNECTIONS: LazyLock<Gauge<Connection>> =
 pub(crate) static PEER_CONNECTIONS_TOTAL: LazyLock<IntGauge> = LazyLock::new(|| {
     try_create_int_gauge("near_peer_connections_total", "Number of connected peers").unwrap()
 });
+
+pub(crate) static TIER1_NEXT_EPOCH_PRODUCERS: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_tier1_next_epoch_producers",
+        "Number of keys of block and chunk producers of the next epoch, other than this node",
+    )
+    .unwrap()
+});
+pub(crate) static TIER1_NEXT_EPOCH_PRODUCERS_CONNECTED: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_tier1_next_epoch_producers_connected",
+        "Number of keys of block and chunk producers of the next epoch with a TIER1 connection, as of the last connection attempt",
+    )
+    .unwrap()
+});
+
+static PEER_PROTOCOL_VERSIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_peer_protocol_versions",
+        "Number of connected peers by the protocol version negotiated in the handshake",
+        &["protocol_version"],
+    )
+    .unwrap()
+});
+
+/// Counts a connected peer in `near_peer_protocol_versions` until the point is dropped.
+pub(crate) fn peer_protocol_version_point(version: ProtocolVersion) -> GaugePoint {
+    let point = PEER_PROTOCOL_VERSIONS.with_label_values(&[&version.to_string()]);
+    point.inc();
+    GaugePoint(point)
+}
+
+pub(crate) static PEER_PROTOCOL_VERSION_MISMATCH: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_protocol_version_mismatch_total",
+        "Number of handshakes refused because no common protocol version could be negotiated",
+        &["direction"],
+    )
+    .unwrap()
+});
 pub(crate) static PEER_DATA_RECEIVED_BYTES: LazyLock<IntCounter> = LazyLock::new(|| {
     try_create_int_counter("near_peer_data_received_bytes", "Total data received from peers")
         .unwrap()
@@ -145,6 +186,14 @@ pub(crate) static PEER_DATA_SENT_BYTES: LazyLock<IntCounter> = LazyLock::new(||
     try_create_int_counter("near_peer_data_sent_bytes", "Total data sent to peers").unwrap()
 });
 
+pub(crate) static TRAFFIC_CAPTURE_DROPPED_MESSAGES: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_traffic_capture_dropped_messages",
+        "Number of peer messages not captured because too many were pending to be written",
+    )
+    .unwrap()
+});
+
 pub(crate) static PEER_DATA_READ_BUFFER_SIZE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
     try_create_int_gauge_vec(
         "near_peer_read_buffer_size",
@@ -204,6 +253,23 @@ pub(crate) static PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL: LazyLock<IntCounterVe
         )
         .unwrap()
     });
+pub(crate) static PEER_THROTTLED_BYTES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_throttled_bytes",
+        "Data sent to peers which was delayed by the outbound bandwidth limits, by message class",
+        &["class"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_DROPPED_THROTTLED_BYTES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_dropped_throttled_bytes",
+        "Data to send to peers which was dropped because too much data of its class was \
+         delayed by the outbound bandwidth limits already, by message class",
+        &["class"],
+    )
+    .unwrap()
+});
 pub(crate) static SYNC_ACCOUNTS_DATA: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_sync_accounts_data",
@@ -221,6 +287,23 @@ pub(crate) static SYNC_SNAPSHOT_HOSTS: LazyLock<IntCounterVec> = LazyLock::new(|
     .unwrap()
 });
 
+pub(crate) static GOSSIP_ITEMS_INSERTED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_gossip_items_inserted",
+        "Number of new gossiped items stored, by topic",
+        &["topic"],
+    )
+    .unwrap()
+});
+pub(crate) static GOSSIP_ITEMS_DROPPED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_gossip_items_dropped",
+        "Number of gossiped items dropped because the peer exceeded the rate limit, by topic",
+        &["topic"],
+    )
+    .unwrap()
+});
+
 pub(crate) static REQUEST_COUNT_BY_TYPE_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_requests_count_by_type_total",
@@ -453,6 +536,14 @@ fn record_routed_msg_hops(msg: &RoutedMessageV2) {
         .inc();
 }
 
+pub(crate) static ROUTED_MESSAGE_REPLAY_CACHE_SIZE: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_routed_message_replay_cache_size",
+        "Number of routed messages remembered for replay detection",
+    )
+    .unwrap()
+});
+
 #[derive(Clone, Copy, strum::AsRefStr)]
 pub(crate) enum MessageDropped {
     NoRouteFound,
@@ -461,6 +552,7 @@ pub(crate) enum MessageDropped {
     MaxCapacityExceeded,
     TransactionsPerBlockExceeded,
     Duplicate,
+    Replayed,
 }
 
 impl MessageDropped {
@@ -1,13 +1,13 @@
-/// Type that belong to the network protocol.
+/// Exported types, which are part of network protocol.
 pub use crate::network_protocol::{
-    Disconnect, Encoding, Handshake, HandshakeFailureReason, PeerMessage, RoutingTableUpdate,
-    SignedAccountData,
+    ChunkRequestMsg, ChunkResponseMsg, Edge, PartialEdgeInfo, PartialEncodedChunkForwardMsg,
+    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerChainInfoV2, PeerInfo,
+    SnapshotHostInfo, StateResponseInfo, StateResponseInfoV1, StateResponseInfoV2,
 };
-/// Exported types, which are part of network protocol.
+/// Type that belong to the network protocol.
 pub use crate::network_protocol::{
-    Edge, PartialEdgeInfo, PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg,
-    PartialEncodedChunkResponseMsg, PeerChainInfoV2, PeerInfo, SnapshotHostInfo, StateResponseInfo,
-    StateResponseInfoV1, StateResponseInfoV2,
+    Disconnect, Encoding, Handshake, HandshakeFailureReason, ParsePeerMessageError, PeerMessage,
+    RoutingTableUpdate, SignedAccountData,
 };
 use crate::routing::routing_table_view::RoutingTableInfo;
 pub use crate::state_sync::StateSyncResponse;
@@ -21,11 +21,13 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::optimistic_block::OptimisticBlock;
 use near_primitives::sharding::PartialEncodedChunkWithArcReceipts;
+use near_primitives::sharding::receipt_proof_fragment::ReceiptProofFragment;
 use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
 use near_primitives::stateless_validation::contract_distribution::{
     ChunkContractAccesses, ContractCodeRequest, ContractCodeResponse, PartialEncodedContractDeploys,
 };
 use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitness;
+use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitnessRequest;
 use near_primitives::stateless_validation::state_witness::ChunkStateWitnessAck;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, BlockHeight, EpochHeight, ShardId};
@@ -166,6 +168,10 @@ pub struct ChainInfo {
     // Peers acting on behalf of these accounts have a higher
     // priority on the NEAR network than other peers.
     pub tier1_accounts: Arc<AccountKeys>,
+    // Block and chunk producers of the next epoch, a subset of `tier1_accounts`.
+    // TIER1 connections to them are established before connections to the
+    // other accounts, so that they are ready by the time the epoch starts.
+    pub next_epoch_producers: Arc<AccountKeys>,
 }
 
 #[derive(Debug, actix::Message)]
@@ -273,6 +279,10 @@ pub enum NetworkRequests {
     },
     /// Information about chunk such as its header, some subset of parts and/or incoming receipts
     PartialEncodedChunkResponse { route_back: CryptoHash, response: PartialEncodedChunkResponseMsg },
+    /// Request enough parts to reconstruct a whole chunk
+    ChunkRequest { target: AccountIdOrPeerTrackingShard, request: ChunkRequestMsg },
+    /// Parts of a whole chunk
+    ChunkResponse { route_back: CryptoHash, response: ChunkResponseMsg },
     /// Information about chunk such as its header, some subset of parts and/or incoming receipts
     PartialEncodedChunkMessage {
         account_id: AccountId,
@@ -280,6 +290,8 @@ pub enum NetworkRequests {
     },
     /// Forwarding a chunk part to a validator tracking the shard
     PartialEncodedChunkForward { account_id: AccountId, forward: PartialEncodedChunkForwardMsg },
+    /// Fragment of a receipt proof too large to be sent with the chunk parts
+    ReceiptProofFragment { account_id: AccountId, fragment: ReceiptProofFragment },
     /// Valid transaction but since we are not validators we send this transaction to current validators.
     ForwardTx(AccountId, SignedTransaction),
     /// Query transaction status
@@ -292,6 +304,9 @@ pub enum NetworkRequests {
     PartialEncodedStateWitness(Vec<(AccountId, PartialEncodedStateWitness)>),
     /// Message from chunk validator to all other chunk validators to forward state witness part.
     PartialEncodedStateWitnessForward(Vec<AccountId>, PartialEncodedStateWitness),
+    /// Message from chunk validator to chunk producer to request the parts of a state witness
+    /// that the validator missed, e.g. because it was behind the chain at the time.
+    PartialEncodedStateWitnessRequest(AccountId, PartialEncodedStateWitnessRequest),
     /// Requests an epoch sync
     EpochSyncRequest { peer_id: PeerId },
     /// Response to an epoch sync request
@@ -1,4 +1,6 @@
-use crate::types::{PoolKey, TransactionGroup, TransactionGroupIterator};
+use crate::types::{
+    PoolKey, TransactionClassifier, TransactionGroup, TransactionGroupIterator, TransactionLane,
+};
 use near_crypto::PublicKey;
 use near_o11y::metrics::prometheus::core::{AtomicI64, GenericGauge};
 use near_primitives::epoch_info::RngSeed;
@@ -6,8 +8,9 @@ use near_primitives::hash::{CryptoHash, hash};
 use near_primitives::transaction::{SignedTransaction, ValidatedTransaction};
 use near_primitives::types::AccountId;
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::ops::Bound;
+use std::sync::Arc;
 
 mod metrics;
 pub mod types;
@@ -34,6 +37,16 @@ pub struct TransactionPool {
     key_seed: RngSeed,
     /// The key after which the pool iterator starts. Doesn't have to be present in the pool.
     last_used_key: PoolKey,
+    /// Decides the lane of the inserted transactions. All of them are in the
+    /// regular lane if not set.
+    classifier: Option<Arc<dyn TransactionClassifier>>,
+    /// Keys of the groups in the priority lane, a subset of the keys of `transactions`.
+    priority_keys: BTreeSet<PoolKey>,
+    /// Same as `last_used_key`, for the priority lane.
+    last_used_priority_key: PoolKey,
+    /// Number of transactions in the groups of the priority lane, including
+    /// the groups taken by a pool iterator.
+    num_priority_transactions: usize,
     /// If set, new transactions that bring the size of the pool over this limit will be rejected.
     total_transaction_size_limit: Option<u64>,
     /// Total size of transactions in the pool measured in bytes.
@@ -41,6 +54,8 @@ pub struct TransactionPool {
     /// Metrics tracked for transaction pool.
     transaction_pool_count_metric: GenericGauge<AtomicI64>,
     transaction_pool_size_metric: GenericGauge<AtomicI64>,
+    priority_lane_count_metric: GenericGauge<AtomicI64>,
+    regular_lane_count_metric: GenericGauge<AtomicI64>,
 }
 
 impl TransactionPool {
@@ -53,22 +68,40 @@ impl TransactionPool {
             metrics::TRANSACTION_POOL_COUNT.with_label_values(&[metrics_label]);
         let transaction_pool_size_metric =
             metrics::TRANSACTION_POOL_SIZE.with_label_values(&[metrics_label]);
+        let lane_count_metric = |lane: TransactionLane| {
+            metrics::TRANSACTION_POOL_LANE_COUNT.with_label_values(&[metrics_label, lane.as_str()])
+        };
+        let priority_lane_count_metric = lane_count_metric(TransactionLane::Priority);
+        let regular_lane_count_metric = lane_count_metric(TransactionLane::Regular);
         // A `get()` call initializes a metric even if its value is zero.
         transaction_pool_count_metric.get();
         transaction_pool_size_metric.get();
+        priority_lane_count_metric.get();
+        regular_lane_count_metric.get();
 
         Self {
             key_seed,
             transactions: BTreeMap::new(),
             unique_transactions: HashSet::new(),
             last_used_key: CryptoHash::default(),
+            classifier: None,
+            priority_keys: BTreeSet::new(),
+            last_used_priority_key: CryptoHash::default(),
+            num_priority_transactions: 0,
             total_transaction_size_limit,
             total_transaction_size: 0,
             transaction_pool_count_metric,
             transaction_pool_size_metric,
+            priority_lane_count_metric,
+            regular_lane_count_metric,
         }
     }
 
+    /// Sets the hook deciding the lane of the transactions inserted from now on.
+    pub fn set_classifier(&mut self, classifier: Arc<dyn TransactionClassifier>) {
+        self.classifier = Some(classifier);
+    }
+
     fn key(&self, account_id: &AccountId, public_key: &PublicKey) -> PoolKey {
         let mut v = borsh::to_vec(&public_key).unwrap();
         v.extend_from_slice(&self.key_seed);
@@ -108,16 +141,90 @@ impl TransactionPool {
         self.total_transaction_size = new_total_transaction_size;
         let signer_id = validated_tx.signer_id();
         let signer_public_key = validated_tx.public_key();
-        self.transactions
-            .entry(self.key(signer_id, signer_public_key))
-            .or_insert_with(Vec::new)
-            .push(validated_tx);
+        let key = self.key(signer_id, signer_public_key);
+        let lane = match &self.classifier {
+            Some(classifier) => classifier.lane(validated_tx.to_signed_tx()),
+            None => TransactionLane::Regular,
+        };
+        let group = self.transactions.entry(key).or_insert_with(Vec::new);
+        // The group moves to the priority lane together with the transactions
+        // which are already in it.
+        if lane == TransactionLane::Priority && self.priority_keys.insert(key) {
+            self.num_priority_transactions += group.len();
+        }
+        if self.priority_keys.contains(&key) {
+            self.num_priority_transactions += 1;
+        }
+        group.push(validated_tx);
 
         self.transaction_pool_count_metric.inc();
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
+        self.update_lane_metrics();
         InsertTransactionResult::Success
     }
 
+    fn update_lane_metrics(&self) {
+        let num_priority = self.num_priority_transactions;
+        self.priority_lane_count_metric.set(num_priority as i64);
+        self.regular_lane_count_metric.set((self.unique_transactions.len() - num_priority) as i64);
+    }
+
+    /// Returns the key of the next group of the priority lane and removes it
+    /// from the lane, continuing after the last used one.
+    fn next_priority_key(&mut self) -> Option<PoolKey> {
+        let key = *self
+            .priority_keys
+            .range((Bound::Excluded(self.last_used_priority_key), Bound::Unbounded))
+            .next()
+            .or_else(|| self.priority_keys.first())?;
+        self.priority_keys.remove(&key);
+        self.last_used_priority_key = key;
+        Some(key)
+    }
+
+    /// Returns the key of the next group, continuing after the last used one.
+    /// Once the priority lane is drained, all the remaining groups are regular.
+    fn next_regular_key(&mut self) -> Option<PoolKey> {
+        let key = *self
+            .transactions
+            .range((Bound::Excluded(self.last_used_key), Bound::Unbounded))
+            .next()
+            .map(|(k, _v)| k)
+            .or_else(|| self.transactions.keys().next())?;
+        self.last_used_key = key;
+        Some(key)
+    }
+
+    /// Removes the group from the pool and sorts its transactions by nonce.
+    fn take_group(&mut self, key: PoolKey, lane: TransactionLane) -> TransactionGroup {
+        let mut validated_txs = self.transactions.remove(&key).expect("the key is in the pool");
+        validated_txs.sort_by_key(|vt| std::cmp::Reverse(vt.nonce()));
+        TransactionGroup {
+            key,
+            lane,
+            transactions: validated_txs,
+            removed_transaction_hashes: vec![],
+            removed_transaction_size: 0,
+            deferred: false,
+        }
+    }
+
+    /// Forgets the transactions pulled from a group taken by a pool iterator.
+    fn release_removed_transactions(&mut self, group: &TransactionGroup) {
+        for hash in &group.removed_transaction_hashes {
+            self.unique_transactions.remove(hash);
+        }
+        if group.lane == TransactionLane::Priority {
+            self.num_priority_transactions -= group.removed_transaction_hashes.len();
+        }
+        // See the comment in `insert_transaction` where we increase the size for reasoning
+        // why panicking here catches a logic error.
+        self.total_transaction_size = self
+            .total_transaction_size
+            .checked_sub(group.removed_transaction_size)
+            .expect("Total transaction size dropped below zero");
+    }
+
     /// Returns a pool iterator wrapper that implements an iterator-like trait to iterate over
     /// transaction groups in the proper order defined by the protocol.
     /// When the iterator is dropped, all remaining groups are inserted back into the pool.
@@ -146,6 +253,7 @@ impl TransactionPool {
         }
         for (key, hashes) in grouped_transactions {
             if let Entry::Occupied(mut entry) = self.transactions.entry(key) {
+                let len = entry.get().len();
                 entry.get_mut().retain(|tx| {
                     if !hashes.contains(&tx.get_hash()) {
                         return true;
@@ -158,8 +266,12 @@ impl TransactionPool {
                         .expect("Total transaction size dropped below zero");
                     false
                 });
+                if self.priority_keys.contains(&key) {
+                    self.num_priority_transactions -= len - entry.get().len();
+                }
                 if entry.get().is_empty() {
                     entry.remove_entry();
+                    self.priority_keys.remove(&key);
                 }
             }
         }
@@ -167,6 +279,12 @@ impl TransactionPool {
         // We can update metrics only once for the whole batch of transactions.
         self.transaction_pool_count_metric.set(self.unique_transactions.len() as i64);
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
+        self.update_lane_metrics();
+    }
+
+    /// Returns all transactions in the pool, in no particular order.
+    pub fn transactions(&self) -> impl Iterator<Item = &ValidatedTransaction> {
+        self.transactions.values().flatten()
     }
 
     /// Returns the number of unique transactions in the pool.
@@ -187,112 +305,115 @@ pub struct PoolIteratorWrapper<'a> {
     /// Mutable reference to the pool, to avoid exposing it while the iterator exists.
     pool: &'a mut TransactionPool,
 
-    /// Queue of transaction groups. Each group there is sorted by nonce.
+    /// Queue of transaction groups of the priority lane. Each group there is sorted by nonce.
+    priority_groups: VecDeque<TransactionGroup>,
+    /// Queue of transaction groups of the regular lane. Each group there is sorted by nonce.
     sorted_groups: VecDeque<TransactionGroup>,
+    /// Deferred groups, returned back to the pool when the iterator is dropped.
+    deferred_groups: Vec<TransactionGroup>,
 }
 
 impl<'a> PoolIteratorWrapper<'a> {
     pub fn new(pool: &'a mut TransactionPool) -> Self {
-        Self { pool, sorted_groups: Default::default() }
+        Self {
+            pool,
+            priority_groups: Default::default(),
+            sorted_groups: Default::default(),
+            deferred_groups: Default::default(),
+        }
+    }
+
+    /// Moves the first non-empty group of the queue to its back, discarding
+    /// the empty groups before it and setting aside the deferred ones.
+    /// Returns false if the queue got empty.
+    fn rotate(
+        pool: &mut TransactionPool,
+        groups: &mut VecDeque<TransactionGroup>,
+        deferred_groups: &mut Vec<TransactionGroup>,
+    ) -> bool {
+        while let Some(sorted_group) = groups.pop_front() {
+            if sorted_group.transactions.is_empty() {
+                pool.release_removed_transactions(&sorted_group);
+                pool.transaction_pool_count_metric.set(pool.unique_transactions.len() as i64);
+                pool.transaction_pool_size_metric.set(pool.transaction_size() as i64);
+                pool.update_lane_metrics();
+            } else if sorted_group.deferred {
+                deferred_groups.push(sorted_group);
+            } else {
+                groups.push_back(sorted_group);
+                return true;
+            }
+        }
+        false
     }
 }
 
-/// The iterator works with the following algorithm:
-/// On next(), the iterator tries to get a transaction group from the pool, sorts transactions in
-/// it, and add it to the back of the sorted groups queue.
+/// The iterator works with the following algorithm, first for the groups of
+/// the priority lane and then for the regular ones:
+/// On next(), the iterator tries to get a transaction group of the lane from the pool, sorts
+/// transactions in it, and add it to the back of the sorted groups queue of the lane.
 /// Remembers the last used key, so it can continue from the next key.
 ///
-/// If the pool is empty, the iterator gets the group from the front of the sorted groups queue.
+/// If the lane is empty in the pool, the iterator gets the group from the front of the sorted
+/// groups queue of the lane.
 ///
 /// If this group is empty (no transactions left inside), then the iterator discards it and
-/// updates `unique_transactions` in the pool. Then gets the next one.
+/// updates `unique_transactions` in the pool. If it was deferred, the iterator sets it aside
+/// until it's dropped. Then gets the next one.
 ///
 /// Once a non-empty group is found, this group is pushed to the back of the sorted groups queue
 /// and the iterator returns a mutable reference to this group.
 ///
-/// If the sorted groups queue is empty, the iterator returns None.
+/// If the sorted groups queue of the lane is empty, the iterator moves to the next lane, and
+/// returns None after the regular one.
 ///
 /// When the iterator is dropped, `unique_transactions` in the pool is updated for every group.
-/// And all non-empty group from the sorted groups queue are inserted back into the pool.
+/// And all non-empty group from the sorted groups queues are inserted back into the pool.
 impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
     fn next(&mut self) -> Option<&mut TransactionGroup> {
-        if !self.pool.transactions.is_empty() {
-            let key = *self
-                .pool
-                .transactions
-                .range((Bound::Excluded(self.pool.last_used_key), Bound::Unbounded))
-                .next()
-                .map(|(k, _v)| k)
-                .unwrap_or_else(|| {
-                    self.pool
-                        .transactions
-                        .keys()
-                        .next()
-                        .expect("we've just checked that the map is not empty")
-                });
-            self.pool.last_used_key = key;
-            let mut validated_txs =
-                self.pool.transactions.remove(&key).expect("just checked existence");
-            validated_txs.sort_by_key(|vt| std::cmp::Reverse(vt.nonce()));
-            self.sorted_groups.push_back(TransactionGroup {
-                key,
-                transactions: validated_txs,
-                removed_transaction_hashes: vec![],
-                removed_transaction_size: 0,
-            });
-            Some(self.sorted_groups.back_mut().expect("just pushed"))
-        } else {
-            while let Some(sorted_group) = self.sorted_groups.pop_front() {
-                if sorted_group.transactions.is_empty() {
-                    for hash in sorted_group.removed_transaction_hashes {
-                        self.pool.unique_transactions.remove(&hash);
-                    }
-                    // See the comment in `insert_transaction` where we increase the size for reasoning
-                    // why panicking here catches a logic error.
-                    self.pool.total_transaction_size = self
-                        .pool
-                        .total_transaction_size
-                        .checked_sub(sorted_group.removed_transaction_size)
-                        .expect("Total transaction size dropped below zero");
-
-                    self.pool
-                        .transaction_pool_count_metric
-                        .set(self.pool.unique_transactions.len() as i64);
-                    self.pool.transaction_pool_size_metric.set(self.pool.transaction_size() as i64);
-                } else {
-                    self.sorted_groups.push_back(sorted_group);
-                    return Some(self.sorted_groups.back_mut().expect("just pushed"));
-                }
-            }
-            None
+        if let Some(key) = self.pool.next_priority_key() {
+            let group = self.pool.take_group(key, TransactionLane::Priority);
+            self.priority_groups.push_back(group);
+            return self.priority_groups.back_mut();
+        }
+        if Self::rotate(self.pool, &mut self.priority_groups, &mut self.deferred_groups) {
+            return self.priority_groups.back_mut();
+        }
+        if let Some(key) = self.pool.next_regular_key() {
+            let group = self.pool.take_group(key, TransactionLane::Regular);
+            self.sorted_groups.push_back(group);
+            return self.sorted_groups.back_mut();
         }
+        if Self::rotate(self.pool, &mut self.sorted_groups, &mut self.deferred_groups) {
+            return self.sorted_groups.back_mut();
+        }
+        None
     }
 }
 
 /// When a pool iterator is dropped, all remaining non empty transaction groups from the sorted
-/// groups queue are inserted back into the pool. And removed transactions hashes from groups are
+/// groups queues are inserted back into the pool. And removed transactions hashes from groups are
 /// removed from the pool's unique_transactions.
 impl<'a> Drop for PoolIteratorWrapper<'a> {
     fn drop(&mut self) {
-        for group in self.sorted_groups.drain(..) {
-            for hash in group.removed_transaction_hashes {
-                self.pool.unique_transactions.remove(&hash);
-            }
-            // See the comment in `insert_transaction` where we increase the size for reasoning
-            // why panicking here catches a logic error.
-            self.pool.total_transaction_size = self
-                .pool
-                .total_transaction_size
-                .checked_sub(group.removed_transaction_size)
-                .expect("Total transaction size dropped below zero");
-
+        for group in self
+            .priority_groups
+            .drain(..)
+            .chain(self.sorted_groups.drain(..))
+            .chain(self.deferred_groups.drain(..))
+        {
+            self.pool.release_removed_transactions(&group);
             if !group.transactions.is_empty() {
+                if group.lane == TransactionLane::Priority {
+                    self.pool.priority_keys.insert(group.key);
+                }
                 self.pool.transactions.insert(group.key, group.transactions);
             }
         }
         // We can update metrics only once for the whole batch of transactions.
         self.pool.transaction_pool_count_metric.set(self.pool.unique_transactions.len() as i64);
         self.pool.transaction_pool_size_metric.set(self.pool.transaction_size() as i64);
+        self.pool.update_lane_metrics();
     }
 }
 
@@ -310,9 +431,11 @@ impl TransactionGroupIteratorWrapper {
             .into_iter()
             .map(|validated_tx| TransactionGroup {
                 key: PoolKey::default(),
+                lane: TransactionLane::Regular,
                 transactions: vec![validated_tx],
                 removed_transaction_hashes: vec![],
                 removed_transaction_size: 0,
+                deferred: false,
             })
             .collect();
 
@@ -597,6 +720,33 @@ mod tests {
         assert_ne!(nonces, new_nonces);
     }
 
+    /// Test that a deferred group isn't returned by the iterator again, and that
+    /// its remaining transactions stay in the pool.
+    #[test]
+    fn test_pool_iterator_defer() {
+        let mut transactions = generate_transactions("alice.near", "alice.near", 1, 3);
+        transactions.extend(generate_transactions("carol.near", "carol.near", 1, 3));
+
+        let (nonces, mut pool) = process_txs_to_nonces(transactions, 0);
+        assert!(nonces.is_empty());
+        let mut res = vec![];
+        let mut pool_iter = pool.pool_iterator();
+        while let Some(iter) = pool_iter.next() {
+            let tx = iter.next().unwrap();
+            if tx.signer_id().as_str() == "alice.near" {
+                iter.defer();
+            }
+            res.push(tx);
+        }
+        drop(pool_iter);
+
+        let signers = res.iter().map(|tx| tx.signer_id().as_str()).collect::<Vec<_>>();
+        assert_eq!(signers.iter().filter(|signer| **signer == "alice.near").count(), 1);
+        assert_eq!(signers.iter().filter(|signer| **signer == "carol.near").count(), 3);
+        assert_eq!(pool.len(), 2);
+        assert!(pool.transactions().all(|tx| tx.signer_id().as_str() == "alice.near"));
+    }
+
     #[test]
     fn test_transaction_pool_size() {
         let mut pool = TransactionPool::new(TEST_SEED, None, "");
@@ -632,4 +782,75 @@ mod tests {
             }
         }
     }
+
+    struct PrioritySigners(Vec<AccountId>);
+
+    impl TransactionClassifier for PrioritySigners {
+        fn lane(&self, tx: &SignedTransaction) -> TransactionLane {
+            if self.0.contains(tx.transaction.signer_id()) {
+                TransactionLane::Priority
+            } else {
+                TransactionLane::Regular
+            }
+        }
+    }
+
+    /// The transactions of the priority lane are pulled before the others,
+    /// and the lane of the groups left in the pool is kept.
+    #[test]
+    fn test_priority_lane() {
+        let mut pool = TransactionPool::new(TEST_SEED, None, "test_priority_lane");
+        pool.set_classifier(Arc::new(PrioritySigners(vec![
+            "alice.near".parse().unwrap(),
+            "carol.near".parse().unwrap(),
+        ])));
+        let mut transactions = [
+            generate_transactions("alice.near", "alice.near", 1, 3),
+            generate_transactions("bob.near", "bob.near", 11, 13),
+            generate_transactions("carol.near", "carol.near", 21, 22),
+            generate_transactions("dave.near", "dave.near", 31, 32),
+        ]
+        .concat();
+        transactions.shuffle(&mut thread_rng());
+        for tx in transactions {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        assert_lane_counts(&pool, 5, 5);
+
+        let mut nonces: Vec<u64> =
+            prepare_transactions(&mut pool, 6).iter().map(|tx| tx.transaction.nonce()).collect();
+        // The groups of a lane are pulled in a random order.
+        sort_pairs(&mut nonces[..4]);
+        assert_eq!(nonces[..5], [1, 21, 2, 22, 3]);
+        assert!(nonces[5] == 11 || nonces[5] == 31);
+        assert_eq!(pool.len(), 4);
+        assert_lane_counts(&pool, 0, 4);
+
+        // The new transactions of the priority lane go first again.
+        pool.insert_transaction(
+            generate_transactions("carol.near", "carol.near", 23, 23).remove(0),
+        );
+        let nonces: Vec<u64> =
+            prepare_transactions(&mut pool, 1).iter().map(|tx| tx.transaction.nonce()).collect();
+        assert_eq!(nonces, [23]);
+        assert_eq!(pool.len(), 4);
+        assert_lane_counts(&pool, 0, 4);
+
+        // A regular group moves to the priority lane with all its transactions.
+        let erin_txs = generate_transactions("erin.near", "erin.near", 41, 43);
+        assert_eq!(pool.insert_transaction(erin_txs[0].clone()), InsertTransactionResult::Success);
+        assert_lane_counts(&pool, 0, 5);
+        pool.set_classifier(Arc::new(PrioritySigners(vec!["erin.near".parse().unwrap()])));
+        for tx in erin_txs[1..].iter().cloned() {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+        assert_lane_counts(&pool, 3, 4);
+        pool.remove_transactions(&[erin_txs[1].to_signed_tx().clone()]);
+        assert_lane_counts(&pool, 2, 4);
+    }
+
+    fn assert_lane_counts(pool: &TransactionPool, num_priority: i64, num_regular: i64) {
+        assert_eq!(pool.priority_lane_count_metric.get(), num_priority);
+        assert_eq!(pool.regular_lane_count_metric.get(), num_regular);
+    }
 }
@@ -18,3 +18,12 @@ pub static TRANSACTION_POOL_SIZE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
     )
     .unwrap()
 });
+
+pub static TRANSACTION_POOL_LANE_COUNT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_int_gauge_vec(
+        "near_transaction_pool_lane_entries",
+        "Number of transactions currently tracked by the node in a given shard pool, by priority lane",
+        &["shard_id", "lane"],
+    )
+    .unwrap()
+});
@@ -1,5 +1,5 @@
 use near_primitives::hash::CryptoHash;
-use near_primitives::transaction::ValidatedTransaction;
+use near_primitives::transaction::{SignedTransaction, ValidatedTransaction};
 
 /// Trait acts like an iterator. It iterates over transactions groups by returning mutable
 /// references to them. Each transaction group implements a draining iterator to pull transactions.
@@ -8,6 +8,29 @@ pub trait TransactionGroupIterator {
     fn next(&mut self) -> Option<&mut TransactionGroup>;
 }
 
+/// Lane of a transaction group in the pool. The groups of the priority lane
+/// are drained before any group of the regular lane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionLane {
+    Priority,
+    Regular,
+}
+
+impl TransactionLane {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionLane::Priority => "priority",
+            TransactionLane::Regular => "regular",
+        }
+    }
+}
+
+/// Hook deciding the lane of the transactions inserted into the pool.
+/// A group goes to the priority lane as soon as one of its transactions does.
+pub trait TransactionClassifier: Send + Sync {
+    fn lane(&self, tx: &SignedTransaction) -> TransactionLane;
+}
+
 /// A hash of (an AccountId, a PublicKey and a seed).
 /// Used to randomize the order of the keys.
 pub(crate) type PoolKey = CryptoHash;
@@ -16,12 +39,16 @@ pub(crate) type PoolKey = CryptoHash;
 pub struct TransactionGroup {
     /// The key of the group.
     pub(crate) key: PoolKey,
+    /// The lane the group was taken from.
+    pub(crate) lane: TransactionLane,
     /// Ordered transactions by nonce in non-increasing order (e.g. 3, 2, 2).
     pub(crate) transactions: Vec<ValidatedTransaction>,
     /// Hashes of the transactions that were pulled from the group using `.next()`.
     pub(crate) removed_transaction_hashes: Vec<CryptoHash>,
     /// Total size of transactions that were pulled from the group using `.next()`.
     pub(crate) removed_transaction_size: u64,
+    /// Whether the group was deferred using `.defer()`.
+    pub(crate) deferred: bool,
 }
 
 impl TransactionGroup {
@@ -40,4 +67,10 @@ impl TransactionGroup {
     pub fn peek_next(&self) -> Option<&ValidatedTransaction> {
         self.transactions.last()
     }
+
+    /// Leaves the remaining transactions of the group in the pool, and stops the
+    /// iterator from returning the group again.
+    pub fn defer(&mut self) {
+        self.deferred = true;
+    }
 }
@@ -114,6 +114,18 @@ pub trait DelayedActionRunnerExt<T> {
         dur: Duration,
         f: impl FnOnce(&mut T, &mut dyn DelayedActionRunner<T>) + Send + 'static,
     );
+
+    /// Runs `f` repeatedly, the first time after one delay of the schedule.
+    /// Unlike an action rescheduling itself with `run_later`, the timer keeps
+    /// the same behavior under the real clock and the TestLoop virtual clock,
+    /// including the jitter.
+    fn run_periodically(
+        &mut self,
+        name: &'static str,
+        schedule: PeriodicSchedule,
+        f: impl FnMut(&mut T) + Send + 'static,
+    ) where
+        T: 'static;
 }
 
 impl<T, Runner> DelayedActionRunnerExt<T> for Runner
@@ -128,6 +140,17 @@ where
     ) {
         self.run_later_boxed(name, dur, Box::new(f));
     }
+
+    fn run_periodically(
+        &mut self,
+        name: &'static str,
+        schedule: PeriodicSchedule,
+        f: impl FnMut(&mut T) + Send + 'static,
+    ) where
+        T: 'static,
+    {
+        run_periodically_from(self, name, schedule, 0, f);
+    }
 }
 
 impl<T> DelayedActionRunnerExt<T> for dyn DelayedActionRunner<T> + '_ {
@@ -139,6 +162,86 @@ impl<T> DelayedActionRunnerExt<T> for dyn DelayedActionRunner<T> + '_ {
     ) {
         self.run_later_boxed(name, dur, Box::new(f));
     }
+
+    fn run_periodically(
+        &mut self,
+        name: &'static str,
+        schedule: PeriodicSchedule,
+        f: impl FnMut(&mut T) + Send + 'static,
+    ) where
+        T: 'static,
+    {
+        run_periodically_from(self, name, schedule, 0, f);
+    }
+}
+
+/// Schedule of an action run with `DelayedActionRunnerExt::run_periodically`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeriodicSchedule {
+    pub period: Duration,
+    /// Up to this much is added to every period. The jitter is pseudo-random
+    /// but derived only from the node id, the name of the action and the
+    /// number of runs, so that TestLoop runs stay reproducible.
+    pub jitter: Duration,
+    /// Hash of the node id, see `with_node_id`.
+    node_seed: u64,
+}
+
+/// Offset basis of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+fn fnv1a(seed: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(seed, |h, b| (h ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+impl PeriodicSchedule {
+    pub fn new(period: Duration) -> Self {
+        Self { period, jitter: Duration::ZERO, node_seed: FNV_OFFSET_BASIS }
+    }
+
+    pub fn with_jitter(self, jitter: Duration) -> Self {
+        Self { jitter, ..self }
+    }
+
+    /// Mixes the id of the node, e.g. its account id or peer id, into the
+    /// jitter, so that the nodes running the same action don't all wait for
+    /// the same delays.
+    pub fn with_node_id(self, node_id: &str) -> Self {
+        Self { node_seed: fnv1a(FNV_OFFSET_BASIS, node_id.as_bytes()), ..self }
+    }
+
+    /// Delay before the run number `run` of the action `name`.
+    pub fn delay(&self, name: &str, run: u64) -> Duration {
+        let max_jitter = self.jitter.whole_nanoseconds().max(0) as u64;
+        if max_jitter == 0 {
+            return self.period;
+        }
+        // SplitMix64 of the FNV-1a hash of the node id and the name, mixed
+        // with the run.
+        let seed = fnv1a(self.node_seed, name.as_bytes());
+        let mut z = seed.wrapping_add(run.wrapping_mul(0x9e3779b97f4a7c15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        self.period + Duration::nanoseconds((z % (max_jitter + 1)) as i64)
+    }
+}
+
+fn run_periodically_from<T: 'static>(
+    runner: &mut dyn DelayedActionRunner<T>,
+    name: &'static str,
+    schedule: PeriodicSchedule,
+    run: u64,
+    mut f: impl FnMut(&mut T) + Send + 'static,
+) {
+    runner.run_later_boxed(
+        name,
+        schedule.delay(name, run),
+        Box::new(move |this, runner| {
+            f(this);
+            run_periodically_from(runner, name, schedule, run + 1, f);
+        }),
+    );
 }
 
 /// Implementation of `DelayedActionRunner` for Actix. With this, any code
@@ -463,7 +463,10 @@ enum AdvanceDecision {
 
 #[cfg(test)]
 mod tests {
-    use crate::futures::FutureSpawnerExt;
+    use crate::futures::{
+        DelayedActionRunner, DelayedActionRunnerExt, FutureSpawnerExt, PeriodicSchedule,
+    };
+    use crate::messaging::Actor;
     use crate::test_loop::TestLoopV2;
     use std::sync::Arc;
     use std::sync::atomic::{AtomicUsize, Ordering};
@@ -507,4 +510,57 @@ mod tests {
         test_loop.run_for(Duration::seconds(30));
         assert_eq!(finished.load(Ordering::Relaxed), 2);
     }
+
+    struct PeriodicActor {
+        clock: crate::time::Clock,
+        schedule: PeriodicSchedule,
+        runs: Vec<crate::time::Instant>,
+    }
+
+    impl Actor for PeriodicActor {
+        fn start_actor(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
+            ctx.run_periodically("periodic", self.schedule, |this| {
+                this.runs.push(this.clock.now());
+            });
+        }
+    }
+
+    // Tests that periodic actions run on the virtual clock with the delays of
+    // their schedule.
+    #[test]
+    fn test_run_periodically() {
+        let mut test_loop = TestLoopV2::new();
+        let clock = test_loop.clock();
+        let start_time = clock.now();
+        let schedule =
+            PeriodicSchedule::new(Duration::seconds(1)).with_jitter(Duration::milliseconds(100));
+        let actor = PeriodicActor { clock, schedule, runs: vec![] };
+        let sender = test_loop.data.register_actor("test", actor, None);
+
+        let duration = Duration::seconds(10);
+        test_loop.run_for(duration);
+        let mut expected = vec![];
+        let mut at = start_time;
+        for run in 0.. {
+            let delay = schedule.delay("periodic", run);
+            assert!(delay >= Duration::seconds(1));
+            assert!(delay <= Duration::milliseconds(1100));
+            at = at + delay;
+            if at > start_time + duration {
+                break;
+            }
+            expected.push(at);
+        }
+        assert!(expected.len() >= 9);
+        assert_eq!(test_loop.data.get(&sender.actor_handle()).runs, expected);
+        // Not all the delays are the same.
+        assert!(
+            (1..10).any(|run| schedule.delay("periodic", run) != schedule.delay("periodic", 0))
+        );
+        // The nodes running the same action get different delays.
+        let node0 = schedule.with_node_id("node0");
+        let node1 = schedule.with_node_id("node1");
+        assert!((0..10).any(|run| node0.delay("periodic", run) != node1.delay("periodic", run)));
+        assert_eq!(node0.delay("periodic", 3), schedule.with_node_id("node0").delay("periodic", 3));
+    }
 }
@@ -2,6 +2,8 @@
 use crate::ExternalStorageLocation::GCS;
 use crate::MutableConfigValue;
 use bytesize::ByteSize;
+use near_crypto::PublicKey;
+use near_primitives::hash::CryptoHash;
 use near_primitives::types::{
     AccountId, BlockHeight, BlockHeightDelta, Gas, NumBlocks, NumSeats, ShardId,
 };
@@ -24,6 +26,27 @@ pub enum LogSummaryStyle {
     Colored,
 }
 
+/// Preset of the data kept by the node, on top of the garbage collection
+/// configured with `GCConfig`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeProfile {
+    /// Keeps all the data the node needs as a validator.
+    #[default]
+    Default,
+    /// For RPC nodes which never validate.  The data used only to produce and
+    /// validate state witnesses is deleted and the chunk extras of the blocks
+    /// on forks are deleted once they are far enough below the final block.
+    /// Can't be used with a validator key.
+    RpcLight,
+}
+
+impl NodeProfile {
+    pub fn is_default(&self) -> bool {
+        *self == NodeProfile::Default
+    }
+}
+
 /// Minimum number of epochs for which we keep store data
 pub const MIN_GC_NUM_EPOCHS_TO_KEEP: u64 = 3;
 
@@ -53,6 +76,15 @@ pub enum TrackedShardsConfig {
     Schedule(Vec<Vec<ShardId>>),
     /// Tracks shards that contain one of the given account.
     Accounts(Vec<AccountId>),
+    /// Tracks the shards that contain one of the given accounts, following
+    /// the accounts across resharding: in the last epoch before a shard
+    /// layout change, the parents of the new shards containing the accounts
+    /// are tracked for the next epoch, and once the layout changed, the
+    /// shards are known to have been tracked in the previous epoch through
+    /// their parents. Shards the accounts move to are state synced during the
+    /// epoch before the move.
+    #[serde(alias = "follow-accounts")]
+    FollowAccounts(Vec<AccountId>),
 }
 
 impl TrackedShardsConfig {
@@ -65,10 +97,11 @@ impl TrackedShardsConfig {
     }
 
     pub fn tracks_any_account(&self) -> bool {
-        if let TrackedShardsConfig::Accounts(accounts) = &self {
-            return !accounts.is_empty();
+        match self {
+            TrackedShardsConfig::Accounts(accounts)
+            | TrackedShardsConfig::FollowAccounts(accounts) => !accounts.is_empty(),
+            _ => false,
         }
-        false
     }
 
     /// For backward compatibility, we support `tracked_shards`, `tracked_shard_schedule`,
@@ -185,6 +218,21 @@ pub enum ExternalStorageLocation {
     },
 }
 
+/// Configures state sync from a local directory of previously obtained state headers and
+/// parts, e.g. copied with rsync from a state dump or available on a mounted volume.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct LocalDirSyncConfig {
+    /// Directory containing the headers and parts, laid out the same way as a state dump
+    /// to `ExternalStorageLocation::Filesystem`.
+    pub root_dir: PathBuf,
+    /// Throttle reading parts from the directory to this many concurrent requests.
+    #[serde(default = "default_num_concurrent_requests")]
+    pub num_concurrent_requests: u32,
+    /// Number of concurrent requests to use during catchup.
+    #[serde(default = "default_num_concurrent_requests_during_catchup")]
+    pub num_concurrent_requests_during_catchup: u32,
+}
+
 /// Configures how to dump state to external storage.
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct DumpConfig {
@@ -212,6 +260,9 @@ pub enum SyncConfig {
     Peers,
     /// Expects parts to be available in external storage.
     ExternalStorage(ExternalStorageConfig),
+    /// Reads headers and parts only from a local directory provided by the operator.
+    /// Everything read from the directory is validated the same way as data from peers.
+    LocalDir(LocalDirSyncConfig),
 }
 
 impl Default for SyncConfig {
@@ -480,6 +531,12 @@ pub fn default_orphan_state_witness_max_size() -> ByteSize {
     ByteSize::mb(40)
 }
 
+/// Returns the default number of heights for which a chunk producer keeps the witness
+/// parts it produced, to answer re-requests from chunk validators.
+pub fn default_produced_state_witness_retention_heights() -> BlockHeightDelta {
+    5
+}
+
 /// Returns the default value for the thread count associated with rpc-handler actor (currently
 /// handling incoming transactions and chunk endorsement validations).
 /// In the benchmarks no performance gains were observed when increasing the number of threads
@@ -492,10 +549,52 @@ pub fn default_rpc_handler_thread_count() -> usize {
 /// This allows nodes to push and pull chunks from a central stream.
 /// The two benefits of this approach are: (1) less request/response traffic
 /// on the peer-to-peer network and (2) lower latency for RPC nodes indexing the chain.
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
 pub struct ChunkDistributionNetworkConfig {
     pub enabled: bool,
     pub uris: ChunkDistributionUris,
+    /// Number of times a failed lookup of a chunk is retried before the chunk
+    /// is requested from the peers only.
+    #[serde(default = "default_chunk_distribution_lookup_retries")]
+    pub lookup_retries: u32,
+    /// Delay before the first retry of a failed lookup, doubled for every
+    /// following retry.
+    #[serde(
+        default = "default_chunk_distribution_retry_backoff",
+        with = "near_time::serde_duration_as_std"
+    )]
+    pub retry_backoff: Duration,
+    /// If a lookup hasn't returned the chunk within this delay, the chunk is
+    /// requested from the peers too, and the first copy received is used.
+    #[serde(
+        default = "default_chunk_distribution_hedge_delay",
+        with = "near_time::serde_duration_as_std"
+    )]
+    pub hedge_delay: Duration,
+}
+
+impl Default for ChunkDistributionNetworkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            uris: ChunkDistributionUris::default(),
+            lookup_retries: default_chunk_distribution_lookup_retries(),
+            retry_backoff: default_chunk_distribution_retry_backoff(),
+            hedge_delay: default_chunk_distribution_hedge_delay(),
+        }
+    }
+}
+
+fn default_chunk_distribution_lookup_retries() -> u32 {
+    2
+}
+
+fn default_chunk_distribution_retry_backoff() -> Duration {
+    Duration::milliseconds(50)
+}
+
+fn default_chunk_distribution_hedge_delay() -> Duration {
+    Duration::milliseconds(300)
 }
 
 /// URIs for the Chunk Distribution Network feature.
@@ -507,6 +606,147 @@ pub struct ChunkDistributionUris {
     pub set: String,
 }
 
+/// Config of the delegation info attached to the current validators.
+///
+/// The delegations are read with view calls to the staking pool contracts in
+/// the state of the first block of the epoch, in the background the first
+/// time the epoch is requested, and are cached per epoch.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ValidatorDelegationInfoConfig {
+    /// Code hashes of the known staking pool contracts. Validators whose
+    /// contract has another code hash get no delegation info. Must not be
+    /// empty.
+    pub staking_pool_code_hashes: Vec<CryptoHash>,
+}
+
+/// Config of the priority lane of the transaction pool, whose transactions are
+/// included in the chunks before any other transaction.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct TransactionPriorityConfig {
+    /// Transactions of these signers are in the priority lane.
+    pub priority_signers: Vec<AccountId>,
+    /// Transactions with at least this priority fee are in the priority lane.
+    /// Only `TransactionV1` has a priority fee.
+    pub min_priority_fee: Option<u64>,
+}
+
+/// Config of the admission of the transactions of the pool into the chunks
+/// produced by the node, which keeps a single signer flooding the pool from
+/// filling the chunks.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct TransactionAdmissionConfig {
+    /// At most this many transactions of a single signer are included in a chunk.
+    pub max_transactions_per_signer: usize,
+    /// The limit of a signer whose transactions were left in the pool because
+    /// of it is halved for every consecutive chunk of the shard in which that
+    /// happens, down to this value. It's reset once the signer stays under its
+    /// limit in a chunk.
+    pub min_transactions_per_signer: usize,
+}
+
+impl Default for TransactionAdmissionConfig {
+    fn default() -> Self {
+        Self { max_transactions_per_signer: 100, min_transactions_per_signer: 10 }
+    }
+}
+
+/// Config of the serving of state parts to the peers syncing state, which keeps
+/// the generation of the parts from starving the other view client requests.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct StatePartServingConfig {
+    /// At most this many state parts are generated at the same time. The state
+    /// requests are served by as many view client threads of their own, and
+    /// wait in their queue instead of holding up the other requests.
+    pub max_concurrent_part_generation: usize,
+    /// At most this many state part requests of a single peer are served per
+    /// `view_client_throttle_period`.
+    pub max_requests_per_peer: usize,
+    /// Number of recently generated state parts kept in memory to serve the
+    /// requests for the same part without generating it again.
+    pub part_cache_size: usize,
+}
+
+impl Default for StatePartServingConfig {
+    fn default() -> Self {
+        Self { max_concurrent_part_generation: 2, max_requests_per_peer: 5, part_cache_size: 16 }
+    }
+}
+
+/// Config of the rebroadcast of the transactions submitted to the node and
+/// forwarded to other chunk producers, in case the forwarded copies are dropped.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct TxRebroadcastConfig {
+    /// Number of blocks after the submission during which a transaction not
+    /// seen included is rebroadcast, once per block.
+    pub num_blocks: BlockHeightDelta,
+    /// The transaction is rebroadcast to the chunk producers of its shard at
+    /// the next `num_chunk_producers` heights.
+    pub num_chunk_producers: usize,
+}
+
+impl Default for TxRebroadcastConfig {
+    fn default() -> Self {
+        Self { num_blocks: 10, num_chunk_producers: 2 }
+    }
+}
+
+/// Config of the fast bootstrap of RPC nodes, which only need the recent state
+/// and the headers.
+///
+/// A node which falls behind by more than `block_sync_depth` blocks state syncs
+/// to the epoch of its header head, even if it's the next epoch, instead of
+/// downloading and applying the blocks in between. Combined with epoch sync,
+/// a new node downloads the headers from the epoch sync boundary and the state
+/// of its tracked shards, and then only the blocks since the sync block.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct FastBootstrapConfig {
+    /// Number of blocks the node is willing to catch up with block sync.
+    pub block_sync_depth: BlockHeightDelta,
+}
+
+impl Default for FastBootstrapConfig {
+    fn default() -> Self {
+        Self { block_sync_depth: 500 }
+    }
+}
+
+/// Config of a remote signing service holding the validator key.
+///
+/// Requests are sent to the first endpoint which responds, starting from the
+/// one which responded last. The service is responsible for double-sign
+/// protection and refuses to sign conflicting data, in which case the node
+/// neither retries nor falls back to the local key.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RemoteSignerConfig {
+    /// Validator account the key belongs to.
+    pub account_id: AccountId,
+    /// Public key of the validator key held by the service.
+    pub public_key: PublicKey,
+    /// Base URLs of the service, e.g. `https://signer-1:8443`.
+    pub endpoints: Vec<String>,
+    /// Timeout of a single request to an endpoint.
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub timeout: Duration,
+    /// PEM file with the CA certificate of the service. If not set, the
+    /// system roots are used.
+    pub ca_cert_file: Option<PathBuf>,
+    /// PEM files with the certificate and PKCS #8 key of the node, for mutual
+    /// TLS.
+    pub client_cert_file: Option<PathBuf>,
+    pub client_key_file: Option<PathBuf>,
+    /// If set, the validator key file is used to sign when none of the
+    /// endpoints is reachable. The local key has no double-sign protection, so
+    /// the service must never be reachable by another node at the same time.
+    #[serde(default)]
+    pub fallback_to_local_key: bool,
+}
+
 /// ClientConfig where some fields can be updated at runtime.
 #[derive(Clone, serde::Serialize)]
 pub struct ClientConfig {
@@ -528,6 +768,12 @@ pub struct ClientConfig {
     pub max_block_wait_delay: Duration,
     /// Multiplier for the wait time for all chunks to be received.
     pub chunk_wait_mult: Rational32,
+    /// Maximum time to delay block production for the missing chunks of the
+    /// shards tracked by the block producer.
+    pub own_chunks_grace_period: Option<Duration>,
+    /// Maximum deterministic delay of block production, derived from the
+    /// block producer and the height, to spread the blocks of the network.
+    pub block_production_jitter: Option<Duration>,
     /// Skip waiting for sync (for testing or single node testnet).
     pub skip_sync_wait: bool,
     /// How often to check that we are not out of sync.
@@ -592,6 +838,10 @@ pub struct ClientConfig {
     pub view_client_threads: usize,
     /// Number of seconds between state requests for view client.
     pub view_client_throttle_period: Duration,
+    /// If set, the state parts are served with per peer rate limits, a cache
+    /// of the recently generated parts and a limit of the parts generated at
+    /// the same time, instead of the rate limit shared by all state requests.
+    pub state_part_serving: Option<StatePartServingConfig>,
     /// Upper bound of the byte size of contract state that is still viewable. None is no limit
     pub trie_viewer_state_size_limit: Option<u64>,
     /// Max burnt gas per view method.  If present, overrides value stored in
@@ -609,6 +859,9 @@ pub struct ClientConfig {
     pub state_sync: StateSyncConfig,
     /// Options for epoch sync.
     pub epoch_sync: EpochSyncConfig,
+    /// If set, the node state syncs instead of block syncing whenever it's
+    /// more than `block_sync_depth` blocks behind.
+    pub fast_bootstrap: Option<FastBootstrapConfig>,
     /// Limit of the size of per-shard transaction pool measured in bytes. If not set, the size
     /// will be unbounded.
     pub transaction_pool_size_limit: Option<u64>,
@@ -619,11 +872,51 @@ pub struct ClientConfig {
     /// If the node is not a chunk producer within that many blocks, then route
     /// to upcoming chunk producers.
     pub tx_routing_height_horizon: BlockHeightDelta,
+    /// If set, the transactions submitted to the node and forwarded to other
+    /// chunk producers are rebroadcast until they are seen included. Only the
+    /// transactions of the tracked shards are rebroadcast.
+    pub tx_rebroadcast: Option<TxRebroadcastConfig>,
     /// Limit the time of adding transactions to a chunk.
     /// A node produces a chunk by adding transactions from the transaction pool until
     /// some limit is reached. This time limit ensures that adding transactions won't take
     /// longer than the specified duration, which helps to produce the chunk quickly.
     pub produce_chunk_add_transactions_time_limit: MutableConfigValue<Option<Duration>>,
+    /// If true, a chunk producer validates and orders the pooled transactions
+    /// on top of the current head between its chunk production slots, so that
+    /// less work is left to do once the slot arrives.
+    pub speculative_transaction_preparation: bool,
+    /// If true, flat storage deltas, outcomes and chunk apply stats of blocks
+    /// which don't become the head are only written once their fork is
+    /// extended.
+    pub defer_fork_postprocessing: bool,
+    /// If true, the node does all the work of its validator, but the blocks,
+    /// chunks, state witnesses, endorsements and approvals it produces are
+    /// not sent to the network.
+    pub shadow_validation: bool,
+    /// If true, the receipts delivered between the shards tracked by the node
+    /// are checked against the receipts the shards produced, for debugging.
+    pub enable_receipt_audit: bool,
+    /// If true, the transactions in the pool are saved to the store when the
+    /// node shuts down, and validated again and put back in the pool when it
+    /// starts.
+    pub persist_tx_pool: bool,
+    /// If set, up to this many of the most recent transactions of each signer
+    /// are indexed in the store.
+    pub transactions_by_signer_limit: Option<usize>,
+    /// If set, at most this many chunks of a block are applied at once.
+    /// Otherwise all of them are applied in parallel.
+    pub max_concurrent_shard_applies: Option<usize>,
+    /// If set, some transactions of the pool are included in the chunks before
+    /// the others.
+    pub transaction_priority: Option<TransactionPriorityConfig>,
+    /// If set, limits the number of transactions of a single signer in the
+    /// chunks produced by the node.
+    pub transaction_admission: Option<TransactionAdmissionConfig>,
+    /// Preset of the data kept by the node.
+    pub node_profile: NodeProfile,
+    /// If set, the current validators returned by the `validators` RPC method
+    /// include the delegations to their staking pools.
+    pub validator_delegation_info: Option<ValidatorDelegationInfoConfig>,
     /// Optional config for the Chunk Distribution Network feature.
     /// If set to `None` then this node does not participate in the Chunk Distribution Network.
     /// Nodes not participating will still function fine, but possibly with higher
@@ -638,6 +931,9 @@ pub struct ClientConfig {
     /// We keep only orphan witnesses which are smaller than this size.
     /// This limits the maximum memory usage of OrphanStateWitnessPool.
     pub orphan_state_witness_max_size: ByteSize,
+    /// Number of heights for which the chunk producer keeps the parts of the state witnesses
+    /// it distributed, so that chunk validators which missed them can request them again.
+    pub produced_state_witness_retention_heights: BlockHeightDelta,
     /// Save observed instances of ChunkStateWitness to the database in DBCol::LatestChunkStateWitnesses.
     /// Saving the latest witnesses is useful for analysis and debugging.
     /// When this option is enabled, the node will save ALL witnesses it observes, even invalid ones,
@@ -676,6 +972,8 @@ impl ClientConfig {
             max_block_production_delay: Duration::milliseconds(max_block_prod_time as i64),
             max_block_wait_delay: Duration::milliseconds(3 * min_block_prod_time as i64),
             chunk_wait_mult: Rational32::new(1, 6),
+            own_chunks_grace_period: None,
+            block_production_jitter: None,
             skip_sync_wait,
             sync_check_period: Duration::milliseconds(100),
             sync_step_period: Duration::milliseconds(10),
@@ -710,6 +1008,7 @@ impl ClientConfig {
             log_summary_style: LogSummaryStyle::Colored,
             view_client_threads: 1,
             view_client_throttle_period: Duration::seconds(1),
+            state_part_serving: None,
             trie_viewer_state_size_limit: None,
             max_gas_burnt_view: None,
             enable_statistics_export: true,
@@ -717,6 +1016,7 @@ impl ClientConfig {
             state_sync_enabled,
             state_sync: StateSyncConfig::default(),
             epoch_sync: EpochSyncConfig::default(),
+            fast_bootstrap: None,
             transaction_pool_size_limit: None,
             enable_multiline_logging: false,
             resharding_config: MutableConfigValue::new(
@@ -724,13 +1024,27 @@ impl ClientConfig {
                 "resharding_config",
             ),
             tx_routing_height_horizon: 4,
+            tx_rebroadcast: None,
             produce_chunk_add_transactions_time_limit: MutableConfigValue::new(
                 default_produce_chunk_add_transactions_time_limit(),
                 "produce_chunk_add_transactions_time_limit",
             ),
+            speculative_transaction_preparation: false,
+            defer_fork_postprocessing: false,
+            shadow_validation: false,
+            enable_receipt_audit: false,
+            persist_tx_pool: false,
+            transactions_by_signer_limit: None,
+            max_concurrent_shard_applies: None,
+            transaction_priority: None,
+            transaction_admission: None,
+            node_profile: NodeProfile::Default,
+            validator_delegation_info: None,
             chunk_distribution_network: None,
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
+            produced_state_witness_retention_heights:
+                default_produced_state_witness_retention_heights(),
             save_latest_witnesses: false,
             transaction_request_handler_threads: default_rpc_handler_thread_count(),
         }
@@ -240,6 +240,11 @@ pub struct GenesisConfig {
     /// if algorithm is able to choose assignment with better balance of
     /// number of chunk producers for shards.
     pub chunk_producer_assignment_changes_limit: NumSeats,
+    /// Accounts whose incoming receipts fail with `ActionErrorKind::AccountPaused`
+    /// instead of being executed, for the emergency response of private chains.
+    /// Refunds are still applied to them. Not allowed on mainnet and testnet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paused_accounts: Vec<AccountId>,
 }
 
 impl GenesisConfig {
@@ -801,10 +806,12 @@ impl GenesisChangeConfig {
 // and `EpochConfig` fields, similar to how `RuntimeConfig` is represented as a
 // separate struct and not inlined.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ProtocolConfigView {
     /// Current Protocol Version
     pub protocol_version: ProtocolVersion,
     /// Official time of blockchain start.
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub genesis_time: DateTime<Utc>,
     /// ID of the blockchain. This must be unique for every blockchain.
     /// If your testnet blockchains do not have unique chain IDs, you will have a bad time.
@@ -820,6 +827,7 @@ pub struct ProtocolConfigView {
     /// Enable dynamic re-sharding.
     pub dynamic_resharding: bool,
     /// Threshold of stake that needs to indicate that they ready for upgrade.
+    #[cfg_attr(feature = "schemars", schemars(with = "(i32, i32)"))]
     pub protocol_upgrade_stake_threshold: Rational32,
     /// Epoch length counted in block heights.
     pub epoch_length: BlockHeightDelta,
@@ -827,9 +835,11 @@ pub struct ProtocolConfigView {
     pub gas_limit: Gas,
     /// Minimum gas price. It is also the initial gas price.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub min_gas_price: Balance,
     /// Maximum gas price.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub max_gas_price: Balance,
     /// Threshold for kicking out block producers, between 0 and 100.
     pub block_producer_kickout_threshold: u8,
@@ -840,18 +850,23 @@ pub struct ProtocolConfigView {
     /// Number of target chunk validator mandates for each shard.
     pub target_validator_mandates_per_shard: NumSeats,
     /// Online minimum threshold below which validator doesn't receive reward.
+    #[cfg_attr(feature = "schemars", schemars(with = "(i32, i32)"))]
     pub online_min_threshold: Rational32,
     /// Online maximum threshold above which validator gets full reward.
+    #[cfg_attr(feature = "schemars", schemars(with = "(i32, i32)"))]
     pub online_max_threshold: Rational32,
     /// Gas price adjustment rate
+    #[cfg_attr(feature = "schemars", schemars(with = "(i32, i32)"))]
     pub gas_price_adjustment_rate: Rational32,
     /// Runtime configuration (mostly economics constants).
     pub runtime_config: RuntimeConfigView,
     /// Number of blocks for which a given transaction is valid
     pub transaction_validity_period: NumBlocks,
     /// Protocol treasury rate
+    #[cfg_attr(feature = "schemars", schemars(with = "(i32, i32)"))]
     pub protocol_reward_rate: Rational32,
     /// Maximum inflation on the total supply every epoch.
+    #[cfg_attr(feature = "schemars", schemars(with = "(i32, i32)"))]
     pub max_inflation_rate: Rational32,
     /// Expected number of blocks per year
     pub num_blocks_per_year: NumBlocks,
@@ -859,6 +874,7 @@ pub struct ProtocolConfigView {
     pub protocol_treasury_account: AccountId,
     /// Fishermen stake threshold.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub fishermen_threshold: Balance,
     /// The minimum stake required for staking is last seat price divided by this number.
     pub minimum_stake_divisor: u64,
@@ -866,6 +882,7 @@ pub struct ProtocolConfigView {
     pub max_kickout_stake_perc: u8,
     /// The lowest ratio s/s_total any block producer can have.
     /// See <https://github.com/near/NEPs/pull/167> for details
+    #[cfg_attr(feature = "schemars", schemars(with = "(i32, i32)"))]
     pub minimum_stake_ratio: Rational32,
     /// If true, shuffle the chunk producers across shards. In other words, if
     /// the shard assignments were `[S_0, S_1, S_2, S_3]` where `S_i` represents
@@ -187,6 +187,15 @@ impl<'a> GenesisValidator<'a> {
             let error_message = format!("Epoch Length must be greater than 0");
             self.validation_errors.push_genesis_semantics_error(error_message)
         }
+
+        if !self.genesis_config.paused_accounts.is_empty()
+            && (self.genesis_config.chain_id == near_primitives::chains::MAINNET
+                || self.genesis_config.chain_id == near_primitives::chains::TESTNET)
+        {
+            let error_message =
+                format!("paused_accounts are not allowed on {}", self.genesis_config.chain_id);
+            self.validation_errors.push_genesis_semantics_error(error_message)
+        }
     }
 
     fn result_with_full_error(&self) -> Result<(), ValidationError> {
@@ -321,4 +330,24 @@ mod test {
         let genesis = &Genesis::new(config, records).unwrap();
         validate_genesis(genesis).unwrap();
     }
+
+    #[test]
+    #[should_panic(expected = "paused_accounts are not allowed on mainnet")]
+    fn test_paused_accounts_on_mainnet() {
+        let mut config = GenesisConfig::default();
+        config.chain_id = near_primitives::chains::MAINNET.to_string();
+        config.validators = vec![AccountInfo {
+            account_id: "test".parse().unwrap(),
+            public_key: VALID_ED25519_RISTRETTO_KEY.parse().unwrap(),
+            amount: 10,
+        }];
+        config.total_supply = 110;
+        config.paused_accounts = vec!["test".parse().unwrap()];
+        let records = GenesisRecords(vec![StateRecord::Account {
+            account_id: "test".parse().unwrap(),
+            account: create_account(),
+        }]);
+        let genesis = &Genesis::new(config, records).unwrap();
+        validate_genesis(genesis).unwrap();
+    }
 }
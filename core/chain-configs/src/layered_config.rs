@@ -0,0 +1,469 @@
+//! Layered node configuration.
+//!
+//! The effective node configuration is assembled from several layers, each
+//! one overriding the values of the previous ones:
+//!
+//! 1. built-in defaults,
+//! 2. the `config.json` file,
+//! 3. environment variables prefixed with [`CONFIG_ENV_PREFIX`],
+//! 4. command line flags.
+//!
+//! Layers are merged on the JSON representation of the config. For every leaf
+//! value of the merged config we remember which layer it came from, so that
+//! operators can find out why a node ended up with a particular setting.
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Prefix of the environment variables overriding config values.
+///
+/// The rest of the variable name is the path to the overridden value, with
+/// path segments separated by a double underscore, e.g.
+/// `NEAR_CONFIG__network__addr=0.0.0.0:24567`. Values overriding strings are
+/// taken as is, other values are parsed as JSON and fall back to plain strings
+/// if they are not valid JSON.
+pub const CONFIG_ENV_PREFIX: &str = "NEAR_CONFIG__";
+
+const ENV_PATH_SEPARATOR: &str = "__";
+
+/// Where the effective value of a config field comes from.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Env,
+    Cli,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConfigSource::Default => "default",
+            ConfigSource::File => "file",
+            ConfigSource::Env => "env",
+            ConfigSource::Cli => "cli",
+        })
+    }
+}
+
+/// A single override of a config value, addressed by a dot-separated path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigOverride {
+    /// Replaces the value at the path.
+    Set(String, Value),
+    /// Appends the value to the array at the path.
+    Append(String, Value),
+    /// Sets the value at the path only if it is missing or null. The value is
+    /// recorded as a default, this is meant for filling in optional sections
+    /// before overriding some of their fields.
+    Insert(String, Value),
+}
+
+/// Merged JSON config together with the provenance of each of its leaves.
+#[derive(Clone, Debug)]
+pub struct LayeredConfig {
+    value: Value,
+    provenance: BTreeMap<String, ConfigSource>,
+}
+
+impl LayeredConfig {
+    /// Creates the config from the defaults layer.
+    pub fn new(defaults: Value) -> Self {
+        let mut provenance = BTreeMap::new();
+        record_leaves(&defaults, "", ConfigSource::Default, &mut provenance);
+        Self { value: defaults, provenance }
+    }
+
+    /// Merges `layer` on top of the current config.
+    ///
+    /// Objects are merged recursively, any other value (including arrays)
+    /// replaces the current one.
+    pub fn apply_layer(&mut self, source: ConfigSource, layer: Value) {
+        merge(&mut self.value, layer, "", source, &mut self.provenance);
+    }
+
+    /// Applies overrides from `vars`, ignoring the variables without the
+    /// [`CONFIG_ENV_PREFIX`].
+    pub fn apply_env(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<()> {
+        for (name, raw) in vars {
+            let Some(path) = name.strip_prefix(CONFIG_ENV_PREFIX) else {
+                continue;
+            };
+            if path.is_empty() {
+                anyhow::bail!("environment variable {name} does not specify a config path");
+            }
+            let path = path.split(ENV_PATH_SEPARATOR).collect::<Vec<_>>().join(".");
+            let value = match self.lookup(&path) {
+                Some(Value::String(_)) => Value::String(raw),
+                _ => serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+            };
+            self.apply_override(ConfigSource::Env, ConfigOverride::Set(path, value))
+                .with_context(|| format!("invalid override in {name}"))?;
+        }
+        Ok(())
+    }
+
+    /// Applies a single override.
+    ///
+    /// A boolean or a number can only be replaced by a value of the same type
+    /// or by null, so that a mistyped override is reported here instead of
+    /// failing or being coerced when the config is deserialized.
+    pub fn apply_override(
+        &mut self,
+        source: ConfigSource,
+        config_override: ConfigOverride,
+    ) -> anyhow::Result<()> {
+        let (path, value) = match &config_override {
+            ConfigOverride::Set(path, value)
+            | ConfigOverride::Append(path, value)
+            | ConfigOverride::Insert(path, value) => (path.clone(), value.clone()),
+        };
+        let mut current = &mut self.value;
+        let mut current_path = String::new();
+        for segment in path.split('.') {
+            if segment.is_empty() {
+                anyhow::bail!("invalid config path {path:?}");
+            }
+            if !current.is_object() {
+                // A scalar on the way is replaced by an object holding the override.
+                check_same_type(current, &Value::Object(Map::new()))
+                    .with_context(|| format!("invalid override of {path}"))?;
+                self.provenance.remove(&current_path);
+                *current = Value::Object(Map::new());
+            }
+            current = current.as_object_mut().unwrap().entry(segment).or_insert(Value::Null);
+            current_path = join_path(&current_path, segment);
+        }
+        let source = match config_override {
+            ConfigOverride::Set(..) => {
+                check_same_type(current, &value)
+                    .with_context(|| format!("invalid override of {path}"))?;
+                *current = value;
+                source
+            }
+            ConfigOverride::Append(..) => {
+                match current {
+                    Value::Array(values) => values.push(value),
+                    Value::Null => *current = Value::Array(vec![value]),
+                    _ => anyhow::bail!("config value at {path} is not an array"),
+                }
+                source
+            }
+            ConfigOverride::Insert(..) => {
+                if !current.is_null() {
+                    return Ok(());
+                }
+                *current = value;
+                ConfigSource::Default
+            }
+        };
+        let value = current.clone();
+        forget_leaves(&path, &mut self.provenance);
+        record_leaves(&value, &path, source, &mut self.provenance);
+        Ok(())
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Returns the source of every leaf of the effective config, keyed by
+    /// dot-separated path.
+    pub fn provenance(&self) -> &BTreeMap<String, ConfigSource> {
+        &self.provenance
+    }
+
+    /// Returns the source of the value at `path`. For objects, this is the
+    /// highest priority source of any of its leaves.
+    pub fn source_of(&self, path: &str) -> Option<ConfigSource> {
+        self.provenance
+            .iter()
+            .filter(|(leaf, _)| is_same_or_child(leaf, path))
+            .map(|(_, source)| *source)
+            .max()
+    }
+
+    /// Deserializes the effective config.
+    ///
+    /// Overrides from the environment and the command line must take effect,
+    /// so overriding a field unknown to `T` is an error. Unknown fields from
+    /// the other layers are ignored, as when reading the file directly.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
+        let mut ignored = Vec::new();
+        let config = serde_ignored::deserialize(self.value.clone(), |path| {
+            ignored.push(path.to_string());
+        })
+        .map_err(|err| {
+            let overrides = self.overridden_paths();
+            let err = anyhow::Error::new(err);
+            if overrides.is_empty() {
+                err
+            } else {
+                err.context(format!("invalid config with overrides of {}", overrides.join(", ")))
+            }
+        })?;
+        for path in ignored {
+            // Optional values show up as `?` segments.
+            let path = path.split('.').filter(|segment| *segment != "?").collect::<Vec<_>>();
+            let path = path.join(".");
+            if let Some(source @ (ConfigSource::Env | ConfigSource::Cli)) = self.source_of(&path) {
+                anyhow::bail!("{source} override of {path} does not match any config field");
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the paths of the leaves set by the environment or the command
+    /// line.
+    fn overridden_paths(&self) -> Vec<&str> {
+        self.provenance
+            .iter()
+            .filter(|(_, source)| matches!(source, ConfigSource::Env | ConfigSource::Cli))
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+
+    /// Returns the leaves of the effective config with their sources, in
+    /// the order of their paths.
+    pub fn effective_values(&self) -> Vec<(String, &Value, ConfigSource)> {
+        self.provenance
+            .iter()
+            .filter_map(|(path, source)| Some((path.clone(), self.lookup(path)?, *source)))
+            .collect()
+    }
+
+    fn lookup(&self, path: &str) -> Option<&Value> {
+        if path.is_empty() {
+            return Some(&self.value);
+        }
+        path.split('.').try_fold(&self.value, |value, segment| value.get(segment))
+    }
+}
+
+fn is_same_or_child(path: &str, parent: &str) -> bool {
+    parent.is_empty()
+        || path == parent
+        || path.strip_prefix(parent).is_some_and(|rest| rest.starts_with('.'))
+}
+
+fn check_same_type(current: &Value, value: &Value) -> anyhow::Result<()> {
+    match (current, value) {
+        (Value::Bool(_), Value::Bool(_) | Value::Null)
+        | (Value::Number(_), Value::Number(_) | Value::Null) => Ok(()),
+        (Value::Bool(_), _) => anyhow::bail!("expected a boolean, got {value}"),
+        (Value::Number(_), _) => anyhow::bail!("expected a number, got {value}"),
+        _ => Ok(()),
+    }
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() { key.to_string() } else { format!("{prefix}.{key}") }
+}
+
+/// Empty objects are recorded as leaves so that they still show up in the
+/// provenance.
+fn record_leaves(
+    value: &Value,
+    path: &str,
+    source: ConfigSource,
+    provenance: &mut BTreeMap<String, ConfigSource>,
+) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                record_leaves(value, &join_path(path, key), source, provenance);
+            }
+        }
+        _ => {
+            provenance.insert(path.to_string(), source);
+        }
+    }
+}
+
+fn forget_leaves(path: &str, provenance: &mut BTreeMap<String, ConfigSource>) {
+    provenance.retain(|leaf, _| !is_same_or_child(leaf, path));
+}
+
+fn merge(
+    target: &mut Value,
+    layer: Value,
+    path: &str,
+    source: ConfigSource,
+    provenance: &mut BTreeMap<String, ConfigSource>,
+) {
+    match (target, layer) {
+        (Value::Object(target), Value::Object(layer)) if !layer.is_empty() => {
+            // The object used to be an empty leaf.
+            provenance.remove(path);
+            for (key, value) in layer {
+                let child_path = join_path(path, &key);
+                match target.get_mut(&key) {
+                    Some(child) => merge(child, value, &child_path, source, provenance),
+                    None => {
+                        record_leaves(&value, &child_path, source, provenance);
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, layer) => {
+            forget_leaves(path, provenance);
+            record_leaves(&layer, path, source, provenance);
+            *target = layer;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigOverride, ConfigSource, LayeredConfig};
+    use serde_json::json;
+
+    fn config() -> LayeredConfig {
+        let mut config = LayeredConfig::new(json!({
+            "archive": false,
+            "network": { "addr": "0.0.0.0:24567", "boot_nodes": "" },
+            "telemetry": { "endpoints": [] },
+        }));
+        config.apply_layer(ConfigSource::File, json!({ "network": { "boot_nodes": "a@b:1" } }));
+        config
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let mut config = config();
+        config
+            .apply_env(vec![
+                ("NEAR_CONFIG__network__addr".to_string(), "1.2.3.4:5".to_string()),
+                ("NEAR_CONFIG__archive".to_string(), "true".to_string()),
+                ("UNRELATED".to_string(), "1".to_string()),
+            ])
+            .unwrap();
+        config
+            .apply_override(ConfigSource::Cli, ConfigOverride::Set("archive".into(), json!(false)))
+            .unwrap();
+
+        assert_eq!(
+            config.value(),
+            &json!({
+                "archive": false,
+                "network": { "addr": "1.2.3.4:5", "boot_nodes": "a@b:1" },
+                "telemetry": { "endpoints": [] },
+            })
+        );
+        assert_eq!(config.source_of("archive"), Some(ConfigSource::Cli));
+        assert_eq!(config.source_of("network.addr"), Some(ConfigSource::Env));
+        assert_eq!(config.source_of("network.boot_nodes"), Some(ConfigSource::File));
+        assert_eq!(config.source_of("telemetry.endpoints"), Some(ConfigSource::Default));
+        assert_eq!(config.source_of("network"), Some(ConfigSource::Env));
+    }
+
+    #[test]
+    fn test_append_and_replace_objects() {
+        let mut config = config();
+        config
+            .apply_override(
+                ConfigSource::Cli,
+                ConfigOverride::Append("telemetry.endpoints".into(), json!("http://x")),
+            )
+            .unwrap();
+        assert_eq!(config.value()["telemetry"]["endpoints"], json!(["http://x"]));
+        assert_eq!(config.source_of("telemetry.endpoints"), Some(ConfigSource::Cli));
+
+        // Replacing an object with a scalar drops the provenance of its leaves.
+        config
+            .apply_override(ConfigSource::Cli, ConfigOverride::Set("network".into(), json!(null)))
+            .unwrap();
+        assert_eq!(config.provenance().get("network.addr"), None);
+        assert_eq!(config.source_of("network"), Some(ConfigSource::Cli));
+
+        config
+            .apply_override(
+                ConfigSource::Cli,
+                ConfigOverride::Insert("rpc".into(), json!({ "addr": "0.0.0.0:3030" })),
+            )
+            .unwrap();
+        config
+            .apply_override(
+                ConfigSource::Cli,
+                ConfigOverride::Insert("rpc".into(), json!({ "addr": "ignored" })),
+            )
+            .unwrap();
+        assert_eq!(config.value()["rpc"], json!({ "addr": "0.0.0.0:3030" }));
+        assert_eq!(config.source_of("rpc.addr"), Some(ConfigSource::Default));
+
+        assert!(
+            config
+                .apply_override(
+                    ConfigSource::Cli,
+                    ConfigOverride::Append("archive".into(), json!(true))
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_mistyped_overrides() {
+        let mut config = config();
+        for config_override in [
+            ConfigOverride::Set("archive".into(), json!("yes")),
+            ConfigOverride::Set("archive.enabled".into(), json!(true)),
+        ] {
+            assert!(config.apply_override(ConfigSource::Cli, config_override).is_err());
+        }
+        assert!(
+            config
+                .apply_env(vec![("NEAR_CONFIG__archive".to_string(), "ture".to_string())])
+                .is_err()
+        );
+        assert_eq!(config.value()["archive"], json!(false));
+
+        // Values overriding strings are not parsed as JSON.
+        config
+            .apply_env(vec![("NEAR_CONFIG__network__boot_nodes".to_string(), "123".to_string())])
+            .unwrap();
+        assert_eq!(config.value()["network"]["boot_nodes"], json!("123"));
+    }
+
+    #[derive(serde::Deserialize)]
+    struct NetworkConfig {
+        #[allow(dead_code)]
+        addr: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct TestConfig {
+        #[allow(dead_code)]
+        network: NetworkConfig,
+    }
+
+    #[test]
+    fn test_deserialize_overrides() {
+        // Unknown fields from the defaults and the file are ignored.
+        let mut config = config();
+        config.deserialize::<TestConfig>().unwrap();
+
+        config
+            .apply_override(
+                ConfigSource::Cli,
+                ConfigOverride::Set("network.adr".into(), json!("1.2.3.4:5")),
+            )
+            .unwrap();
+        let err = config.deserialize::<TestConfig>().unwrap_err();
+        assert_eq!(err.to_string(), "cli override of network.adr does not match any config field");
+
+        let mut config = self::config();
+        config
+            .apply_override(ConfigSource::Cli, ConfigOverride::Set("network.addr".into(), json!(5)))
+            .unwrap();
+        let err = config.deserialize::<TestConfig>().unwrap_err();
+        assert_eq!(err.to_string(), "invalid config with overrides of network.addr");
+    }
+}
@@ -1,6 +1,7 @@
 mod client_config;
 mod genesis_config;
 pub mod genesis_validate;
+mod layered_config;
 #[cfg(feature = "metrics")]
 mod metrics;
 #[cfg(feature = "test_genesis")]
@@ -13,26 +14,31 @@ pub use client_config::{
     ChunkDistributionNetworkConfig, ChunkDistributionUris, ClientConfig,
     DEFAULT_GC_NUM_EPOCHS_TO_KEEP, DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL, DumpConfig, EpochSyncConfig,
-    ExternalStorageConfig, ExternalStorageLocation, GCConfig, LogSummaryStyle,
-    MIN_GC_NUM_EPOCHS_TO_KEEP, ReshardingConfig, ReshardingHandle, StateSyncConfig, SyncConfig,
-    TEST_STATE_SYNC_TIMEOUT, TrackedShardsConfig, default_chunk_wait_mult,
-    default_enable_multiline_logging, default_epoch_sync,
-    default_header_sync_expected_height_per_second, default_header_sync_initial_timeout,
-    default_header_sync_progress_timeout, default_header_sync_stall_ban_timeout,
-    default_log_summary_period, default_orphan_state_witness_max_size,
-    default_orphan_state_witness_pool_size, default_produce_chunk_add_transactions_time_limit,
-    default_state_sync_enabled, default_state_sync_external_backoff,
-    default_state_sync_external_timeout, default_state_sync_p2p_timeout,
-    default_state_sync_retry_backoff, default_sync_check_period, default_sync_height_threshold,
-    default_sync_max_block_requests, default_sync_step_period, default_transaction_pool_size_limit,
-    default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
-    default_view_client_threads, default_view_client_throttle_period,
+    ExternalStorageConfig, ExternalStorageLocation, FastBootstrapConfig, GCConfig,
+    LocalDirSyncConfig, LogSummaryStyle, MIN_GC_NUM_EPOCHS_TO_KEEP, NodeProfile,
+    RemoteSignerConfig, ReshardingConfig, ReshardingHandle, StatePartServingConfig,
+    StateSyncConfig, SyncConfig, TEST_STATE_SYNC_TIMEOUT, TrackedShardsConfig,
+    TransactionAdmissionConfig, TransactionPriorityConfig, TxRebroadcastConfig,
+    ValidatorDelegationInfoConfig, default_chunk_wait_mult, default_enable_multiline_logging,
+    default_epoch_sync, default_header_sync_expected_height_per_second,
+    default_header_sync_initial_timeout, default_header_sync_progress_timeout,
+    default_header_sync_stall_ban_timeout, default_log_summary_period,
+    default_orphan_state_witness_max_size, default_orphan_state_witness_pool_size,
+    default_produce_chunk_add_transactions_time_limit,
+    default_produced_state_witness_retention_heights, default_state_sync_enabled,
+    default_state_sync_external_backoff, default_state_sync_external_timeout,
+    default_state_sync_p2p_timeout, default_state_sync_retry_backoff, default_sync_check_period,
+    default_sync_height_threshold, default_sync_max_block_requests, default_sync_step_period,
+    default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
+    default_tx_routing_height_horizon, default_view_client_threads,
+    default_view_client_throttle_period,
 };
 pub use genesis_config::{
     Genesis, GenesisChangeConfig, GenesisConfig, GenesisContents, GenesisRecords,
     GenesisValidationMode, ProtocolConfig, ProtocolConfigView, get_initial_supply,
     stream_records_from_file,
 };
+pub use layered_config::{CONFIG_ENV_PREFIX, ConfigOverride, ConfigSource, LayeredConfig};
 use near_primitives::types::{Balance, BlockHeightDelta, Gas, NumBlocks, NumSeats};
 use num_rational::Rational32;
 pub use updatable_config::{MutableConfigValue, MutableValidatorSigner, UpdatableClientConfig};
@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
+use anyhow::Context;
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account, AccountContract};
 use near_primitives::epoch_manager::{EpochConfig, EpochConfigStore};
@@ -8,10 +10,11 @@ use near_primitives::state_record::StateRecord;
 use near_primitives::test_utils::{create_test_signer, create_user_test_signer};
 use near_primitives::types::{
     AccountId, AccountInfo, Balance, BlockHeight, BlockHeightDelta, Gas, NumBlocks, NumSeats,
-    ProtocolVersion,
+    NumShards, ProtocolVersion,
 };
 use near_primitives::utils::from_timestamp;
 use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::views::EpochValidatorInfo;
 use near_time::Clock;
 use num_rational::Rational32;
 
@@ -573,6 +576,85 @@ impl ValidatorsSpec {
             num_chunk_validator_seats,
         }
     }
+
+    /// Specifies the validators of an epoch of a real network, e.g. mainnet,
+    /// as returned by the `validators` RPC method.  Stakes and the number of
+    /// seats are taken from the epoch, so that the validator selection picks
+    /// the same block producers, chunk producers and chunk validators.
+    ///
+    /// Public keys are replaced by the ones of test signers so that the
+    /// validators can be run by the test.  Shard assignment is recomputed from
+    /// the stakes, so to get a similar one the genesis should use a shard
+    /// layout with the same number of shards as the epoch, see
+    /// [`num_shards_of_epoch`].
+    pub fn from_epoch_validator_info(info: &EpochValidatorInfo) -> Self {
+        let mut current_validators = info
+            .current_validators
+            .iter()
+            .filter(|validator| !validator.is_slashed)
+            .collect::<Vec<_>>();
+        current_validators
+            .sort_by(|a, b| b.stake.cmp(&a.stake).then(a.account_id.cmp(&b.account_id)));
+        assert!(!current_validators.is_empty(), "epoch has no validators");
+        let validators = current_validators
+            .iter()
+            .map(|validator| AccountInfo {
+                public_key: create_test_signer(validator.account_id.as_str()).public_key(),
+                account_id: validator.account_id.clone(),
+                amount: validator.stake,
+            })
+            .collect::<Vec<_>>();
+        // The RPC doesn't tell block producers apart.  On real networks every
+        // chunk producer is also a block producer.
+        let num_chunk_producer_seats = current_validators
+            .iter()
+            .filter(|validator| !validator.shards_produced.is_empty())
+            .count() as NumSeats;
+        ValidatorsSpec::Raw {
+            num_block_producer_seats: num_chunk_producer_seats,
+            num_chunk_producer_seats,
+            num_chunk_validator_seats: validators.len() as NumSeats,
+            validators,
+        }
+    }
+
+    /// Same as [`Self::from_epoch_validator_info`] but reads the response of
+    /// the `validators` RPC method from a JSON file.  Both the whole JSON-RPC
+    /// response and just its `result` are accepted, so e.g. the output of
+    ///
+    /// ```text
+    /// curl -s https://rpc.mainnet.near.org -H 'Content-Type: application/json' \
+    ///     -d '{"jsonrpc": "2.0", "id": "dontcare", "method": "validators", "params": [null]}'
+    /// ```
+    ///
+    /// can be used as is.
+    pub fn from_epoch_validator_info_file(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self::from_epoch_validator_info(&read_epoch_validator_info(path)?))
+    }
+}
+
+/// Reads the response of the `validators` RPC method from a JSON file.  See
+/// [`ValidatorsSpec::from_epoch_validator_info_file`].
+pub fn read_epoch_validator_info(path: &Path) -> anyhow::Result<EpochValidatorInfo> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut value: serde_json::Value = serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    if let Some(result) = value.get_mut("result") {
+        value = result.take();
+    }
+    serde_json::from_value(value)
+        .with_context(|| format!("{} is not a response of the validators method", path.display()))
+}
+
+/// Returns the number of shards which had chunk producers assigned in the
+/// epoch.
+pub fn num_shards_of_epoch(info: &EpochValidatorInfo) -> NumShards {
+    info.current_validators
+        .iter()
+        .flat_map(|validator| &validator.shards_produced)
+        .collect::<HashSet<_>>()
+        .len() as NumShards
 }
 
 struct DerivedValidatorSetup {
@@ -10,7 +10,7 @@ use time::Duration;
 #[cfg(feature = "metrics")]
 use time::OffsetDateTime as Utc;
 
-use crate::ReshardingConfig;
+use crate::{ReshardingConfig, TrackedShardsConfig};
 
 /// A wrapper for a config value that can be updated while the node is running.
 /// When initializing sub-objects (e.g. `ShardsManager`), please make sure to
@@ -109,6 +109,12 @@ pub struct UpdatableClientConfig {
     #[serde(default)]
     #[serde(with = "near_time::serde_opt_duration_as_std")]
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+
+    /// Shards to track. A reloaded config takes effect from the epoch after
+    /// the next one, the newly tracked shards are state synced at the next
+    /// epoch boundary.
+    #[serde(default)]
+    pub tracked_shards_config: Option<TrackedShardsConfig>,
 }
 
 pub type MutableValidatorSigner = MutableConfigValue<Option<Arc<ValidatorSigner>>>;
@@ -0,0 +1,163 @@
+//! Encryption of secret keys stored on disk with a passphrase.
+//!
+//! The encryption key is derived from the passphrase with Argon2id and the
+//! secret key is encrypted with XChaCha20-Poly1305. The public key is used as
+//! the associated data, so that a ciphertext can't be moved to a key file of
+//! another key.
+
+use crate::{PublicKey, SecretKey};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyEncryptionError {
+    #[error("invalid key derivation parameters: {0}")]
+    InvalidKdfParams(String),
+    #[error("failed to encrypt the secret key")]
+    Encryption,
+    #[error("invalid salt or nonce length")]
+    InvalidLength,
+    #[error("wrong passphrase or corrupted key file")]
+    Decryption,
+    #[error("decrypted secret key is invalid: {0}")]
+    InvalidSecretKey(String),
+    #[error("decrypted secret key doesn't match the public key {0}")]
+    PublicKeyMismatch(PublicKey),
+}
+
+/// Parameters of Argon2id, see RFC 9106.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory size in KiB.
+    pub m_cost: u32,
+    /// Number of iterations.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl Default for Argon2Params {
+    /// The second recommended option of RFC 9106, which uses 64 MiB of memory.
+    fn default() -> Self {
+        Self { m_cost: 65536, t_cost: 3, p_cost: 4 }
+    }
+}
+
+impl Argon2Params {
+    fn derive_key(
+        &self,
+        passphrase: &[u8],
+        salt: &[u8],
+    ) -> Result<[u8; KEY_LEN], KeyEncryptionError> {
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(KEY_LEN))
+            .map_err(|err| KeyEncryptionError::InvalidKdfParams(err.to_string()))?;
+        let argon2 =
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut key = [0u8; KEY_LEN];
+        argon2
+            .hash_password_into(passphrase, salt, &mut key)
+            .map_err(|err| KeyEncryptionError::InvalidKdfParams(err.to_string()))?;
+        Ok(key)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedSecretKey {
+    pub kdf: Argon2Params,
+    #[serde(with = "hex::serde")]
+    pub salt: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedSecretKey {
+    #[cfg(feature = "rand")]
+    pub fn encrypt(
+        secret_key: &SecretKey,
+        passphrase: &[u8],
+        kdf: Argon2Params,
+    ) -> Result<Self, KeyEncryptionError> {
+        use rand::RngCore;
+
+        let mut salt = vec![0u8; SALT_LEN];
+        let mut nonce = vec![0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let key = kdf.derive_key(passphrase, &salt)?;
+        let public_key = secret_key.public_key().to_string();
+        let plaintext = secret_key.to_string();
+        let ciphertext = XChaCha20Poly1305::new(&key.into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload { msg: plaintext.as_bytes(), aad: public_key.as_bytes() },
+            )
+            .map_err(|_| KeyEncryptionError::Encryption)?;
+        Ok(Self { kdf, salt, nonce, ciphertext })
+    }
+
+    /// Decrypts the secret key of `public_key`.
+    pub fn decrypt(
+        &self,
+        public_key: &PublicKey,
+        passphrase: &[u8],
+    ) -> Result<SecretKey, KeyEncryptionError> {
+        if self.salt.len() != SALT_LEN || self.nonce.len() != NONCE_LEN {
+            return Err(KeyEncryptionError::InvalidLength);
+        }
+        let key = self.kdf.derive_key(passphrase, &self.salt)?;
+        let aad = public_key.to_string();
+        let plaintext = XChaCha20Poly1305::new(&key.into())
+            .decrypt(
+                XNonce::from_slice(&self.nonce),
+                Payload { msg: &self.ciphertext, aad: aad.as_bytes() },
+            )
+            .map_err(|_| KeyEncryptionError::Decryption)?;
+        let secret_key: SecretKey = std::str::from_utf8(&plaintext)
+            .map_err(|err| KeyEncryptionError::InvalidSecretKey(err.to_string()))?
+            .parse()
+            .map_err(|err: crate::ParseKeyError| {
+                KeyEncryptionError::InvalidSecretKey(err.to_string())
+            })?;
+        if &secret_key.public_key() != public_key {
+            return Err(KeyEncryptionError::PublicKeyMismatch(public_key.clone()));
+        }
+        Ok(secret_key)
+    }
+}
+
+#[cfg(all(test, feature = "rand"))]
+mod tests {
+    use super::*;
+    use crate::KeyType;
+
+    /// Cheap parameters, the default ones take a while in debug builds.
+    fn test_params() -> Argon2Params {
+        Argon2Params { m_cost: 64, t_cost: 1, p_cost: 1 }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let secret_key = SecretKey::from_random(KeyType::ED25519);
+        let public_key = secret_key.public_key();
+        let encrypted =
+            EncryptedSecretKey::encrypt(&secret_key, b"passphrase", test_params()).unwrap();
+        assert_eq!(encrypted.decrypt(&public_key, b"passphrase").unwrap(), secret_key);
+        assert!(matches!(
+            encrypted.decrypt(&public_key, b"wrong"),
+            Err(KeyEncryptionError::Decryption)
+        ));
+
+        // The ciphertext is bound to the public key.
+        let other_key = SecretKey::from_random(KeyType::ED25519).public_key();
+        assert!(matches!(
+            encrypted.decrypt(&other_key, b"passphrase"),
+            Err(KeyEncryptionError::Decryption)
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::key_encryption::{Argon2Params, EncryptedSecretKey};
 use crate::{PublicKey, SecretKey};
 use near_account_id::AccountId;
 use std::fs::File;
@@ -35,16 +36,86 @@ impl KeyFile {
     }
 
     pub fn from_file(path: &Path) -> io::Result<Self> {
-        let mut file = File::open(path)?;
-        let mut json_config_str = String::new();
-        file.read_to_string(&mut json_config_str)?;
-        let json_str_without_comments: String =
-            near_config_utils::strip_comments_from_json_str(&json_config_str)?;
+        Ok(serde_json::from_str(&read_json_file(path)?)?)
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn encrypt(&self, passphrase: &[u8], kdf: Argon2Params) -> io::Result<EncryptedKeyFile> {
+        Ok(EncryptedKeyFile {
+            account_id: self.account_id.clone(),
+            public_key: self.public_key.clone(),
+            encrypted_secret_key: EncryptedSecretKey::encrypt(&self.secret_key, passphrase, kdf)
+                .map_err(io::Error::other)?,
+        })
+    }
+}
+
+/// Key file with the secret key encrypted by a passphrase, see
+/// `EncryptedSecretKey`.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct EncryptedKeyFile {
+    pub account_id: AccountId,
+    pub public_key: PublicKey,
+    pub encrypted_secret_key: EncryptedSecretKey,
+}
 
-        Ok(serde_json::from_str(&json_str_without_comments)?)
+impl EncryptedKeyFile {
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        let mut file = KeyFile::create(path)?;
+        file.write_all(data.as_bytes())
+    }
+
+    pub fn decrypt(&self, passphrase: &[u8]) -> io::Result<KeyFile> {
+        let secret_key = self
+            .encrypted_secret_key
+            .decrypt(&self.public_key, passphrase)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(KeyFile {
+            account_id: self.account_id.clone(),
+            public_key: self.public_key.clone(),
+            secret_key,
+        })
     }
 }
 
+/// Key file as stored on disk, with the secret key either in plain text or
+/// encrypted.
+pub enum StoredKeyFile {
+    Plain(KeyFile),
+    Encrypted(EncryptedKeyFile),
+}
+
+impl StoredKeyFile {
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let json_str = read_json_file(path)?;
+        let value: serde_json::Value = serde_json::from_str(&json_str)?;
+        if value.get("encrypted_secret_key").is_some() {
+            Ok(Self::Encrypted(serde_json::from_value(value)?))
+        } else {
+            Ok(Self::Plain(serde_json::from_value(value)?))
+        }
+    }
+
+    /// Returns the key file, decrypting it with the passphrase returned by
+    /// `passphrase` if needed. The passphrase is only requested for
+    /// encrypted files.
+    pub fn unlock(self, passphrase: impl FnOnce() -> io::Result<Vec<u8>>) -> io::Result<KeyFile> {
+        match self {
+            Self::Plain(key_file) => Ok(key_file),
+            Self::Encrypted(key_file) => key_file.decrypt(&passphrase()?),
+        }
+    }
+}
+
+// The file can be JSON with comments.
+fn read_json_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut json_config_str = String::new();
+    file.read_to_string(&mut json_config_str)?;
+    near_config_utils::strip_comments_from_json_str(&json_config_str)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -111,4 +182,34 @@ mod test {
         let inner_msg = err.into_inner().unwrap().to_string();
         assert!(inner_msg.contains("duplicate field"));
     }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_encrypted_key_file() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("key-file");
+
+        let secret_key: SecretKey = SECRET_KEY.parse().unwrap();
+        let public_key = secret_key.public_key();
+        let key = KeyFile { account_id: ACCOUNT_ID.parse().unwrap(), public_key, secret_key };
+        let kdf = Argon2Params { m_cost: 64, t_cost: 1, p_cost: 1 };
+        key.encrypt(b"passphrase", kdf).unwrap().write_to_file(&path).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains(SECRET_KEY));
+
+        let StoredKeyFile::Encrypted(encrypted) = StoredKeyFile::from_file(&path).unwrap() else {
+            panic!("expected an encrypted key file");
+        };
+        assert!(encrypted.decrypt(b"wrong").is_err());
+        let unlocked =
+            StoredKeyFile::from_file(&path).unwrap().unlock(|| Ok(b"passphrase".to_vec())).unwrap();
+        assert_eq!(unlocked.secret_key, key.secret_key);
+
+        // Plain key files don't need a passphrase.
+        key.write_to_file(&path).unwrap();
+        let unlocked = StoredKeyFile::from_file(&path)
+            .unwrap()
+            .unlock(|| panic!("passphrase requested for a plain key file"))
+            .unwrap();
+        assert_eq!(unlocked.secret_key, key.secret_key);
+    }
 }
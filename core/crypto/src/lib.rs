@@ -2,7 +2,8 @@
 #![deny(clippy::arithmetic_side_effects)]
 
 pub use errors::{ParseKeyError, ParseKeyTypeError, ParseSignatureError};
-pub use key_file::KeyFile;
+pub use key_encryption::{Argon2Params, EncryptedSecretKey, KeyEncryptionError};
+pub use key_file::{EncryptedKeyFile, KeyFile, StoredKeyFile};
 pub use signature::{
     ED25519PublicKey, ED25519SecretKey, KeyType, PublicKey, Secp256K1PublicKey, Secp256K1Signature,
     SecretKey, Signature,
@@ -18,6 +19,7 @@ mod util;
 
 mod errors;
 pub mod key_conversion;
+mod key_encryption;
 mod key_file;
 mod signature;
 mod signer;
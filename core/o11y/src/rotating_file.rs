@@ -0,0 +1,94 @@
+//! File writer which rotates the file once it reaches a maximum size.
+//!
+//! Meant to be wrapped with `tracing_appender::non_blocking`, so that the
+//! writes happen on a dedicated thread.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Appends to `path` and, once writing would make the file larger than
+/// `max_size` bytes, renames it to `<path>.1`, shifting the older files up to
+/// `<path>.<max_rotated_files>` and removing the oldest one.
+pub struct RotatingFileWriter {
+    path: PathBuf,
+    max_size: u64,
+    max_rotated_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFileWriter {
+    pub fn new(path: PathBuf, max_size: u64, max_rotated_files: usize) -> io::Result<Self> {
+        let file = Self::open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, max_size, max_rotated_files, file, size })
+    }
+
+    fn open(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_rotated_files == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for index in (1..self.max_rotated_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index.saturating_add(1)))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = Self::open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFileWriter {
+    /// Writes all of `buf` to the same file, so that the writes of whole
+    /// lines never get split between two files.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len() as u64;
+        if self.size > 0 && self.size.saturating_add(len) > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size = self.size.saturating_add(len);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let mut writer = RotatingFileWriter::new(path.clone(), 10, 2).unwrap();
+        for line in ["aaaa\n", "bbbb\n", "cccc\n", "dddd\n", "eeee\n", "ffff\n", "gggg\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "gggg\n");
+        assert_eq!(read(&writer.rotated_path(1)), "eeee\nffff\n");
+        assert_eq!(read(&writer.rotated_path(2)), "cccc\ndddd\n");
+        assert!(!writer.rotated_path(3).exists());
+    }
+}
@@ -23,6 +23,7 @@ pub const INLINE_DISK_VALUE_THRESHOLD: usize = 4000;
     serde_repr::Serialize_repr,
     serde_repr::Deserialize_repr,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 pub enum AccountIdValidityRulesVersion {
     /// Skip account ID validation according to legacy rules.
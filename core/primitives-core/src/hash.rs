@@ -162,6 +162,18 @@ impl<'de> serde::Deserialize<'de> for CryptoHash {
     }
 }
 
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for CryptoHash {
+    fn schema_name() -> String {
+        "CryptoHash".to_string()
+    }
+
+    /// Base58-encoded string, see the serde implementation.
+    fn json_schema(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+        generator.subschema_for::<String>()
+    }
+}
+
 impl std::str::FromStr for CryptoHash {
     type Err = Box<dyn std::error::Error + Send + Sync>;
 
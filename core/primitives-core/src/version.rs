@@ -307,6 +307,15 @@ pub enum ProtocolFeature {
     /// Move from ChunkStateWitness being a single struct to a versioned enum.
     VersionedStateWitness,
     SaturatingFloatToInt,
+    /// Send receipt proofs which are too large to be sent together with the
+    /// chunk parts as separate fragments of balanced size, see
+    /// `ReceiptProofFragment`.
+    ReceiptProofFragments,
+    /// Enforce `per_receipt_storage_proof_size_limit` on the storage proof of
+    /// all the actions of a receipt, not only within function calls, and report
+    /// the storage proof size of every transaction and receipt in its execution
+    /// outcome, see `ExecutionMetadata::V4`.
+    PerReceiptStorageProofLimit,
 }
 
 impl ProtocolFeature {
@@ -408,6 +417,8 @@ impl ProtocolFeature {
             // that always enables this for mocknet (see config_mocknet function).
             ProtocolFeature::ShuffleShardAssignments => 143,
             ProtocolFeature::ExcludeExistingCodeFromWitnessForCodeLen => 148,
+            ProtocolFeature::ReceiptProofFragments => 149,
+            ProtocolFeature::PerReceiptStorageProofLimit => 150,
             // Place features that are not yet in Nightly below this line.
         }
     }
@@ -427,7 +438,7 @@ pub const MIN_SUPPORTED_PROTOCOL_VERSION: ProtocolVersion = 75;
 const STABLE_PROTOCOL_VERSION: ProtocolVersion = 78;
 
 // On nightly, pick big enough version to support all features.
-const NIGHTLY_PROTOCOL_VERSION: ProtocolVersion = 149;
+const NIGHTLY_PROTOCOL_VERSION: ProtocolVersion = 150;
 
 /// Largest protocol version supported by the current binary.
 pub const PROTOCOL_VERSION: ProtocolVersion =
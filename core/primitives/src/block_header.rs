@@ -5,7 +5,7 @@ use crate::network::PeerId;
 use crate::stateless_validation::chunk_endorsements_bitmap::ChunkEndorsementsBitmap;
 use crate::types::validator_stake::{ValidatorStake, ValidatorStakeIter, ValidatorStakeV1};
 use crate::types::{AccountId, Balance, BlockHeight, EpochId, MerkleHash, NumBlocks};
-use crate::validator_signer::ValidatorSigner;
+use crate::validator_signer::{SignerError, ValidatorSigner};
 use crate::version::ProtocolVersion;
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::{KeyType, PublicKey, Signature};
@@ -354,11 +354,11 @@ impl Approval {
         parent_height: BlockHeight,
         target_height: BlockHeight,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = ApprovalInner::new(&parent_hash, parent_height, target_height);
 
-        let signature = signer.sign_bytes(&Approval::get_data_for_sig(&inner, target_height));
-        Approval { inner, target_height, signature, account_id: signer.validator_id().clone() }
+        let signature = signer.sign_bytes(&Approval::get_data_for_sig(&inner, target_height))?;
+        Ok(Approval { inner, target_height, signature, account_id: signer.validator_id().clone() })
     }
 
     pub fn get_data_for_sig(inner: &ApprovalInner, target_height: BlockHeight) -> Vec<u8> {
@@ -609,7 +609,7 @@ impl BlockHeader {
         block_merkle_root: CryptoHash,
         prev_height: BlockHeight,
         chunk_endorsements: Option<ChunkEndorsementsBitmap>,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         Self::new_impl(
             latest_protocol_version,
             height,
@@ -700,7 +700,8 @@ impl BlockHeader {
             block_merkle_root,
             prev_height,
             chunk_endorsements,
-        );
+        )
+        .expect("an existing signature is used as is");
         // Note: We do not panic but only log if the hash of the created header does not match the expected hash (From the view)
         // because there are tests that check if we can downgrade a BlockHeader's view a previous version, in which case the hash
         // of the header changes.
@@ -739,7 +740,7 @@ impl BlockHeader {
         block_merkle_root: CryptoHash,
         prev_height: BlockHeight,
         chunk_endorsements: Option<ChunkEndorsementsBitmap>,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner_lite = BlockHeaderInnerLite {
             height,
             epoch_id,
@@ -777,14 +778,14 @@ impl BlockHeader {
             chunk_endorsements,
         };
         let (hash, signature) =
-            Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest);
-        Self::BlockHeaderV5(Arc::new(BlockHeaderV5 {
+            Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest)?;
+        Ok(Self::BlockHeaderV5(Arc::new(BlockHeaderV5 {
             prev_hash,
             inner_lite,
             inner_rest,
             signature,
             hash,
-        }))
+        })))
     }
 
     /// Helper function for `new_impl` and `old_impl` to compute the hash and signature of the hash from the block header parts.
@@ -796,7 +797,7 @@ impl BlockHeader {
         prev_hash: CryptoHash,
         inner_lite: &BlockHeaderInnerLite,
         inner_rest: &T,
-    ) -> (CryptoHash, Signature)
+    ) -> Result<(CryptoHash, Signature), SignerError>
     where
         T: BorshSerialize + ?Sized,
     {
@@ -806,8 +807,8 @@ impl BlockHeader {
             &borsh::to_vec(&inner_rest).expect("Failed to serialize"),
         );
         match signature_source {
-            SignatureSource::Signer(signer) => (hash, signer.sign_bytes(hash.as_ref())),
-            SignatureSource::Signature(signature) => (hash, signature),
+            SignatureSource::Signer(signer) => Ok((hash, signer.sign_bytes(hash.as_ref())?)),
+            SignatureSource::Signature(signature) => Ok((hash, signature)),
         }
     }
 
@@ -855,6 +856,7 @@ impl BlockHeader {
             0,                     // prev_height
             Some(ChunkEndorsementsBitmap::genesis()),
         )
+        .expect("the genesis header is not signed")
     }
 
     #[inline]
@@ -0,0 +1,188 @@
+//! Proofs that a block header field has a given value, checked against the
+//! hash of the block.
+//!
+//! Bridges and other light clients track the chain by block hashes (and the
+//! `next_bp_hash` chain of block producer sets). The fields of
+//! `BlockHeaderInnerLite` are proven by revealing the lite header, since the
+//! block hash commits to it directly. Per shard fields, such as the state root
+//! of a single shard, are only committed to through merkle roots in the lite
+//! header and additionally need the merkle path of the shard's value.
+
+use crate::hash::CryptoHash;
+use crate::merkle::{Direction, MerklePath, verify_path};
+use crate::types::ShardIndex;
+use crate::views::LightClientBlockLiteView;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Chunk header field included in the block header via a merkle root over all
+/// shards of the block.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardHeaderField {
+    /// `prev_state_root` of the chunk, merklized into `inner_lite.prev_state_root`.
+    PrevStateRoot,
+    /// `prev_outcome_root` of the chunk, merklized into `inner_lite.outcome_root`.
+    PrevOutcomeRoot,
+}
+
+/// Proof of the value of a `ShardHeaderField` of a shard in a block.
+#[derive(BorshSerialize, BorshDeserialize, serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct ShardHeaderFieldProof {
+    /// Lite header of the block, its hash is the block hash.
+    pub header: LightClientBlockLiteView,
+    pub field: ShardHeaderField,
+    /// Index of the shard in the shard layout of the block.
+    pub shard_index: ShardIndex,
+    pub value: CryptoHash,
+    pub path: MerklePath,
+}
+
+impl ShardHeaderFieldProof {
+    /// Root of the merkle tree over all shards committed to by the header.
+    fn root(&self) -> CryptoHash {
+        match self.field {
+            ShardHeaderField::PrevStateRoot => self.header.inner_lite.prev_state_root,
+            ShardHeaderField::PrevOutcomeRoot => self.header.inner_lite.outcome_root,
+        }
+    }
+
+    /// Checks that the proof is valid for the shard `shard_index` of the block
+    /// with the given hash. The merkle root doesn't commit to the number of
+    /// shards, so it has to come from the shard layout of the block's epoch,
+    /// as known to the verifier. Otherwise the value of one shard could be
+    /// passed off as the value of another.
+    pub fn verify(&self, block_hash: &CryptoHash, num_shards: usize) -> bool {
+        self.shard_index < num_shards
+            && self
+                .path
+                .iter()
+                .map(|item| item.direction.clone())
+                .eq(path_directions(self.shard_index, num_shards))
+            && self.header.hash() == *block_hash
+            && verify_path(self.root(), &self.path, self.value)
+    }
+}
+
+/// Directions of the merkle path of the leaf `index` of a tree built by
+/// `merklize` over `num_leaves` leaves. The last node of a level with an odd
+/// number of nodes is moved to the next level as is, without a path item.
+fn path_directions(mut index: usize, mut num_leaves: usize) -> Vec<Direction> {
+    let mut directions = vec![];
+    while num_leaves > 1 {
+        if index % 2 == 1 {
+            directions.push(Direction::Left);
+        } else if index + 1 < num_leaves {
+            directions.push(Direction::Right);
+        }
+        index /= 2;
+        num_leaves = num_leaves.div_ceil(2);
+    }
+    directions
+}
+
+/// Checks that the lite header belongs to the block with the given hash, which
+/// proves all of the fields of `header.inner_lite`, e.g. `next_bp_hash`.
+pub fn verify_header_lite(header: &LightClientBlockLiteView, block_hash: &CryptoHash) -> bool {
+    header.hash() == *block_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::merklize;
+    use crate::views::BlockHeaderInnerLiteView;
+
+    fn proof(values: &[CryptoHash], shard_index: ShardIndex) -> ShardHeaderFieldProof {
+        let (root, paths) = merklize(values);
+        let header = LightClientBlockLiteView {
+            prev_block_hash: CryptoHash::hash_bytes(b"prev"),
+            inner_rest_hash: CryptoHash::hash_bytes(b"rest"),
+            inner_lite: BlockHeaderInnerLiteView {
+                height: 10,
+                epoch_id: CryptoHash::default(),
+                next_epoch_id: CryptoHash::default(),
+                prev_state_root: root,
+                outcome_root: CryptoHash::default(),
+                timestamp: 0,
+                timestamp_nanosec: 0,
+                next_bp_hash: CryptoHash::default(),
+                block_merkle_root: CryptoHash::default(),
+            },
+        };
+        ShardHeaderFieldProof {
+            header,
+            field: ShardHeaderField::PrevStateRoot,
+            shard_index,
+            value: values[shard_index],
+            path: paths[shard_index].clone(),
+        }
+    }
+
+    #[test]
+    fn test_verify_shard_header_field_proof() {
+        let values: Vec<_> = (0..5u8).map(|i| CryptoHash::hash_bytes(&[i])).collect();
+        for shard_index in 0..values.len() {
+            let proof = proof(&values, shard_index);
+            let block_hash = proof.header.hash();
+            assert!(verify_header_lite(&proof.header, &block_hash));
+            assert!(proof.verify(&block_hash, values.len()));
+            assert!(!proof.verify(&CryptoHash::default(), values.len()));
+
+            let mut wrong_value = proof.clone();
+            wrong_value.value = CryptoHash::default();
+            assert!(!wrong_value.verify(&block_hash, values.len()));
+
+            let mut wrong_field = proof;
+            wrong_field.field = ShardHeaderField::PrevOutcomeRoot;
+            assert!(!wrong_field.verify(&block_hash, values.len()));
+        }
+    }
+
+    #[test]
+    fn test_path_directions() {
+        for num_leaves in 1..20u8 {
+            let values: Vec<_> = (0..num_leaves).map(|i| CryptoHash::hash_bytes(&[i])).collect();
+            let (_, paths) = merklize(&values);
+            for (index, path) in paths.iter().enumerate() {
+                let directions: Vec<_> = path.iter().map(|item| item.direction.clone()).collect();
+                assert_eq!(directions, path_directions(index, values.len()));
+            }
+        }
+    }
+
+    /// The value of a shard with a valid merkle path must not verify as the
+    /// value of another shard.
+    #[test]
+    fn test_verify_shard_header_field_proof_wrong_shard() {
+        let values: Vec<_> = (0..5u8).map(|i| CryptoHash::hash_bytes(&[i])).collect();
+        for shard_index in 0..values.len() {
+            let proof = proof(&values, shard_index);
+            let block_hash = proof.header.hash();
+            for wrong_index in (0..values.len() + 1).filter(|index| *index != shard_index) {
+                let mut wrong_shard = proof.clone();
+                wrong_shard.shard_index = wrong_index;
+                assert!(!wrong_shard.verify(&block_hash, values.len()));
+            }
+        }
+
+        // The last shard is moved up the tree without siblings, so its path
+        // is the same as the path of the second shard of a two shard tree,
+        // which is why the number of shards has to come from the verifier.
+        let proof = proof(&values, 4);
+        let block_hash = proof.header.hash();
+        let mut wrong_shard = proof.clone();
+        wrong_shard.shard_index = 1;
+        assert!(!wrong_shard.verify(&block_hash, values.len()));
+        assert!(wrong_shard.verify(&block_hash, 2));
+    }
+}
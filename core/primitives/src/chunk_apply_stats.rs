@@ -1,12 +1,14 @@
 use std::collections::BTreeMap;
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use near_primitives_core::types::{Balance, BlockHeight, Gas, ShardId};
+use near_primitives_core::hash::CryptoHash;
+use near_primitives_core::types::{AccountId, Balance, BlockHeight, Gas, ShardId};
 
 use crate::bandwidth_scheduler::{
     Bandwidth, BandwidthRequest, BandwidthRequestValues, BandwidthRequests,
     BandwidthSchedulerParams, BlockBandwidthRequests,
 };
+use crate::stateless_validation::witness_size::WitnessSizeStats;
 
 /// Information gathered during chunk application.
 /// Provides insight into what happened when the chunk was applied.
@@ -40,6 +42,10 @@ pub struct ChunkApplyStatsV0 {
     pub bandwidth_scheduler: BandwidthSchedulerStats,
     /// Balance stats - used in balance checker.
     pub balance: BalanceStats,
+    /// Storage proof size stats - soft limit, proof attributed to every transaction and receipt.
+    pub witness_size: WitnessSizeStats,
+    /// Storage I/O done while applying the chunk.
+    pub storage_io: StorageIoStats,
 }
 
 impl ChunkApplyStatsV0 {
@@ -53,6 +59,8 @@ impl ChunkApplyStatsV0 {
             bandwidth_scheduler: Default::default(),
             balance: Default::default(),
             receipt_sink: Default::default(),
+            witness_size: Default::default(),
+            storage_io: Default::default(),
         }
     }
 
@@ -75,6 +83,8 @@ impl ChunkApplyStatsV0 {
             bandwidth_scheduler: Default::default(),
             balance: Default::default(),
             receipt_sink: Default::default(),
+            witness_size: Default::default(),
+            storage_io: Default::default(),
         }
     }
 }
@@ -161,6 +171,10 @@ pub struct ReceiptSinkStats {
     /// Whether `is_outgoing_metadata_ready` is true for all shards. This must be true before
     /// resharding can start.
     pub all_outgoing_metadatas_ready: bool,
+    /// Every new outgoing receipt generated during this chunk application, in the order in
+    /// which it was passed to the receipt sink. Shows which receipts generate the most
+    /// cross-shard traffic.
+    pub new_outgoing_receipts: Vec<OutgoingReceiptStats>,
 }
 
 impl ReceiptSinkStats {
@@ -171,6 +185,19 @@ impl ReceiptSinkStats {
     }
 }
 
+/// Size and destination of a single outgoing receipt.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct OutgoingReceiptStats {
+    pub receipt_id: CryptoHash,
+    pub predecessor_id: AccountId,
+    pub receiver_id: AccountId,
+    pub receiver_shard_id: ShardId,
+    /// Size of the receipt, as calculated by `congestion_control::compute_receipt_size`.
+    pub size: u64,
+    /// Whether the receipt was forwarded in this chunk or put in the outgoing buffer.
+    pub forwarded: bool,
+}
+
 #[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct OutgoingLimitStats {
     pub size: u64,
@@ -209,6 +236,26 @@ pub struct BalanceStats {
     pub global_actions_burnt_amount: Balance,
 }
 
+/// Stats about the storage I/O done while applying a chunk, which tell whether
+/// the shard is bound by the storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct StorageIoStats {
+    /// Trie nodes and values read from the store. The lookups served by the
+    /// shard cache, the memtries or the accounting cache are not counted.
+    pub trie_node_reads: u64,
+    /// Trie nodes inserted or dereferenced by the changes of the chunk.
+    pub trie_node_writes: u64,
+    /// Keys found in the flat storage.
+    pub flat_storage_hits: u64,
+    /// Keys looked up in the flat storage and not found.
+    pub flat_storage_misses: u64,
+    /// Bytes of the trie nodes and values read from the store, and of the
+    /// values inlined in the flat storage.
+    pub bytes_read: u64,
+    /// Bytes of the trie nodes and values inserted by the changes of the chunk.
+    pub bytes_written: u64,
+}
+
 /// Convert a bandwidth request from the bitmap representation to a list of requested values.
 fn get_requested_values(
     bandwidth_request: &BandwidthRequest,
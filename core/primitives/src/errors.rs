@@ -22,6 +22,7 @@ use std::fmt::{Debug, Display};
     serde::Serialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum TxExecutionError {
     /// An error happened during Action execution
     ActionError(ActionError),
@@ -88,6 +89,7 @@ impl std::error::Error for RuntimeError {}
     BorshDeserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum MissingTrieValueContext {
     /// Missing trie value when reading from TrieIterator.
     TrieIterator,
@@ -123,6 +125,7 @@ impl MissingTrieValueContext {
     BorshDeserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum StorageError {
     /// Key-value db internal failure
     StorageInternalError,
@@ -166,6 +169,7 @@ impl std::error::Error for StorageError {}
     serde::Serialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum InvalidTxError {
     /// Happens if a wrong AccessKey used or AccessKey has not enough permissions
     InvalidAccessKeyError(InvalidAccessKeyError),
@@ -197,8 +201,10 @@ pub enum InvalidTxError {
     NotEnoughBalance {
         signer_id: AccountId,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         balance: Balance,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         cost: Balance,
     },
     /// Signer account doesn't have enough balance after transaction.
@@ -207,6 +213,7 @@ pub enum InvalidTxError {
         signer_id: AccountId,
         /// Required balance to cover the state.
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         amount: Balance,
     },
     /// An integer overflow occurred during transaction cost estimation.
@@ -263,6 +270,7 @@ impl std::error::Error for InvalidTxError {}
     serde::Serialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum InvalidAccessKeyError {
     /// The access key identified by the `public_key` doesn't exist for the account
     AccessKeyNotFound { account_id: AccountId, public_key: Box<PublicKey> },
@@ -277,8 +285,10 @@ pub enum InvalidAccessKeyError {
         account_id: AccountId,
         public_key: Box<PublicKey>,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         allowance: Balance,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         cost: Balance,
     },
     /// Having a deposit with a function call action is not allowed with a function call access key.
@@ -297,6 +307,7 @@ pub enum InvalidAccessKeyError {
     serde::Deserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ActionsValidationError {
     /// The delete action must be a final action in transaction
     DeleteActionMustBeFinal,
@@ -345,6 +356,7 @@ pub enum ActionsValidationError {
     serde::Deserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ReceiptValidationError {
     /// The `predecessor_id` of a Receipt is not valid.
     InvalidPredecessorId { account_id: String },
@@ -498,6 +510,7 @@ impl std::error::Error for ActionsValidationError {}
     serde::Serialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ActionError {
     /// Index of the failed action in the transaction.
     /// Action index is not defined if ActionError.kind is `ActionErrorKind::LackBalanceForState`
@@ -519,6 +532,7 @@ impl std::error::Error for ActionError {}
     serde::Serialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ActionErrorKind {
     /// Happens when CreateAccount action tries to create an account with account_id which is already exists in the storage
     AccountAlreadyExists {
@@ -566,6 +580,7 @@ pub enum ActionErrorKind {
         account_id: AccountId,
         /// Balance required to complete an action.
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         amount: Balance,
     },
     /// Account is not yet staked, but tries to unstake
@@ -576,17 +591,22 @@ pub enum ActionErrorKind {
     TriesToStake {
         account_id: AccountId,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         stake: Balance,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         locked: Balance,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         balance: Balance,
     },
     InsufficientStake {
         account_id: AccountId,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         stake: Balance,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         minimum_stake: Balance,
     },
     /// An error occurred during a `FunctionCall` Action, parameter is debug message.
@@ -631,6 +651,17 @@ pub enum ActionErrorKind {
     GlobalContractDoesNotExist {
         identifier: GlobalContractIdentifier,
     },
+    /// The receiver of the receipt is paused with `paused_accounts` in the
+    /// genesis config of a private chain.
+    AccountPaused {
+        account_id: AccountId,
+    },
+    /// The storage proof recorded while executing the actions of the receipt
+    /// exceeds `per_receipt_storage_proof_size_limit`.
+    StorageProofSizeExceeded {
+        size: u64,
+        limit: u64,
+    },
 }
 
 impl From<ActionErrorKind> for ActionError {
@@ -920,6 +951,14 @@ impl Display for ActionErrorKind {
             ActionErrorKind::GlobalContractDoesNotExist { identifier } => {
                 write!(f, "Global contract identifier {:?} not found", identifier)
             }
+            ActionErrorKind::AccountPaused { account_id } => {
+                write!(f, "Account {:?} is paused and doesn't execute receipts", account_id)
+            }
+            ActionErrorKind::StorageProofSizeExceeded { size, limit } => write!(
+                f,
+                "The storage proof of the receipt has {} bytes, exceeding the limit of {} bytes",
+                size, limit
+            ),
         }
     }
 }
@@ -1037,6 +1076,7 @@ impl From<ShardLayoutError> for EpochError {
     serde::Serialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Error that can occur while preparing or executing Wasm smart-contract.
 pub enum PrepareError {
     /// Error happened while serializing the module.
@@ -1079,6 +1119,7 @@ pub enum PrepareError {
     strum::IntoStaticStr,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum WasmTrap {
     /// An `unreachable` opcode was executed.
     Unreachable,
@@ -1112,6 +1153,7 @@ pub enum WasmTrap {
     strum::IntoStaticStr,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum HostError {
     /// String encoding is bad UTF-16 sequence
     BadUTF16,
@@ -1195,6 +1237,7 @@ pub enum HostError {
     strum::IntoStaticStr,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum MethodResolveError {
     MethodEmptyName,
     MethodNotFound,
@@ -1213,6 +1256,7 @@ pub enum MethodResolveError {
     strum::IntoStaticStr,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum CompilationError {
     CodeDoesNotExist {
         account_id: AccountId,
@@ -1241,6 +1285,7 @@ pub enum CompilationError {
     serde::Deserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum FunctionCallError {
     /// Wasm compilation error
     CompilationError(CompilationError),
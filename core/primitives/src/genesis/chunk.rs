@@ -85,7 +85,8 @@ fn genesis_chunk(
         BandwidthRequests::empty(),
         &EmptyValidatorSigner::default().into(),
         rs,
-    );
+    )
+    .expect("empty signer never fails");
     chunk.into_parts().1
 }
 
@@ -119,7 +120,8 @@ pub fn prod_genesis_chunks(
         CryptoHash::default(),
         vec![],
         &EmptyValidatorSigner::default().into(),
-    );
+    )
+    .expect("empty signer never fails");
 
     let mut chunk = ShardChunk::V1(ShardChunkV1 {
         chunk_hash: header.chunk_hash(),
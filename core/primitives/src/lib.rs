@@ -13,6 +13,7 @@ pub mod bandwidth_scheduler;
 pub mod block;
 pub mod block_body;
 pub mod block_header;
+pub mod block_header_proof;
 pub mod challenge;
 pub mod chunk_apply_stats;
 pub mod congestion_info;
@@ -1,6 +1,6 @@
 use crate::hash::CryptoHash;
 use crate::types::{AccountId, EpochId};
-use crate::validator_signer::ValidatorSigner;
+use crate::validator_signer::{SignerError, ValidatorSigner};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::{PublicKey, Signature};
 use near_schema_checker_lib::ProtocolSchema;
@@ -22,6 +22,7 @@ use std::sync::Arc;
     serde::Deserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PeerId(Arc<PublicKey>);
 
 impl PeerId {
@@ -67,16 +68,29 @@ pub struct AnnounceAccount {
 }
 
 impl AnnounceAccount {
-    pub fn new(signer: &ValidatorSigner, peer_id: PeerId, epoch_id: EpochId) -> Self {
-        let signature = Self::sign(signer, &peer_id, &epoch_id);
-        Self { account_id: signer.validator_id().clone(), peer_id: peer_id, epoch_id, signature }
+    pub fn new(
+        signer: &ValidatorSigner,
+        peer_id: PeerId,
+        epoch_id: EpochId,
+    ) -> Result<Self, SignerError> {
+        let signature = Self::sign(signer, &peer_id, &epoch_id)?;
+        Ok(Self {
+            account_id: signer.validator_id().clone(),
+            peer_id: peer_id,
+            epoch_id,
+            signature,
+        })
     }
 
     pub fn hash(&self) -> CryptoHash {
         Self::build_header_hash(&self.account_id, &self.peer_id, &self.epoch_id)
     }
 
-    fn sign(signer: &ValidatorSigner, peer_id: &PeerId, epoch_id: &EpochId) -> Signature {
+    fn sign(
+        signer: &ValidatorSigner,
+        peer_id: &PeerId,
+        epoch_id: &EpochId,
+    ) -> Result<Signature, SignerError> {
         let hash = Self::build_header_hash(signer.validator_id(), peer_id, epoch_id);
         signer.sign_bytes(hash.as_ref())
     }
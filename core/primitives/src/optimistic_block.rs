@@ -2,6 +2,8 @@
 use crate::block::BlockHeader;
 use crate::hash::{CryptoHash, hash};
 use crate::types::{BlockHeight, SignatureDifferentiator};
+#[cfg(feature = "clock")]
+use crate::validator_signer::SignerError;
 use borsh::{BorshDeserialize, BorshSerialize};
 #[cfg(feature = "rand")]
 use near_crypto::InMemorySigner;
@@ -58,11 +60,11 @@ impl OptimisticBlock {
         signer: &crate::validator_signer::ValidatorSigner,
         now: u64,
         sandbox_delta_time: Option<near_time::Duration>,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         use crate::utils::get_block_metadata;
         let prev_block_hash = *prev_block_header.hash();
         let (time, vrf_value, vrf_proof, random_value) =
-            get_block_metadata(prev_block_header, signer, now, sandbox_delta_time);
+            get_block_metadata(prev_block_header, signer, now, sandbox_delta_time)?;
 
         let inner = OptimisticBlockInner {
             prev_block_hash,
@@ -75,9 +77,9 @@ impl OptimisticBlock {
         };
 
         let hash = hash(&borsh::to_vec(&inner).expect("Failed to serialize"));
-        let signature = signer.sign_bytes(hash.as_ref());
+        let signature = signer.sign_bytes(hash.as_ref())?;
 
-        Self { inner, signature, hash }
+        Ok(Self { inner, signature, hash })
     }
 
     #[cfg(all(feature = "clock", feature = "test_features"))]
@@ -88,8 +90,8 @@ impl OptimisticBlock {
         now: u64,
         sandbox_delta_time: Option<near_time::Duration>,
         adv_type: OptimisticBlockAdvType,
-    ) -> Self {
-        let original = Self::produce(prev_block_header, height, signer, now, sandbox_delta_time);
+    ) -> Result<Self, SignerError> {
+        let original = Self::produce(prev_block_header, height, signer, now, sandbox_delta_time)?;
         Self::alter(&original, signer, adv_type)
     }
 
@@ -98,7 +100,7 @@ impl OptimisticBlock {
         original: &OptimisticBlock,
         signer: &crate::validator_signer::ValidatorSigner,
         adv_type: OptimisticBlockAdvType,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let mut inner = original.inner.clone();
         match adv_type {
             OptimisticBlockAdvType::Normal => {}
@@ -122,12 +124,12 @@ impl OptimisticBlock {
 
         let hash = hash(&borsh::to_vec(&inner).expect("Failed to serialize"));
         let signature = if let OptimisticBlockAdvType::InvalidSignature = adv_type {
-            signer.sign_bytes(CryptoHash::default().as_ref())
+            signer.sign_bytes(CryptoHash::default().as_ref())?
         } else {
-            signer.sign_bytes(hash.as_ref())
+            signer.sign_bytes(hash.as_ref())?
         };
 
-        Self { inner, signature, hash }
+        Ok(Self { inner, signature, hash })
     }
 
     /// Recompute the hash after deserialization.
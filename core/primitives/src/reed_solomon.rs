@@ -9,10 +9,44 @@ use std::sync::Arc;
 /// This should help with making the code a bit more understandable.
 pub type ReedSolomonPart = Option<Box<[u8]>>;
 
+mod simd;
+
+/// Implementation used to compute the Reed-Solomon parts.
+///
+/// Both backends compute the same code, so the parts they produce are byte for
+/// byte identical and the choice doesn't affect compatibility with other nodes.
+/// Note that this rules out codes over other fields or with other generator
+/// matrices (e.g. the ones of the `reed-solomon-simd` crate), even if they are
+/// faster.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReedSolomonBackend {
+    /// The `reed-solomon-erasure` crate.
+    Erasure,
+    /// Multiplies the parts by the coefficients of the code with SIMD kernels
+    /// (AVX2 or SSSE3 on x86_64, NEON on aarch64), and computes all the missing
+    /// parts directly from the present ones in a single pass over the data.
+    Simd,
+}
+
+impl ReedSolomonBackend {
+    /// Backend used by `reed_solomon_encode` and `reed_solomon_decode`. The
+    /// SIMD backend is enabled by the `rs_simd` feature.
+    pub const DEFAULT: ReedSolomonBackend =
+        if cfg!(feature = "rs_simd") { Self::Simd } else { Self::Erasure };
+}
+
 // Encode function takes a serializable object and returns a tuple of parts and length of encoded data
 pub fn reed_solomon_encode<T: BorshSerialize>(
     rs: &ReedSolomon,
     data: &T,
+) -> (Vec<ReedSolomonPart>, usize) {
+    reed_solomon_encode_with_backend(ReedSolomonBackend::DEFAULT, rs, data)
+}
+
+pub fn reed_solomon_encode_with_backend<T: BorshSerialize>(
+    backend: ReedSolomonBackend,
+    rs: &ReedSolomon,
+    data: &T,
 ) -> (Vec<ReedSolomonPart>, usize) {
     let mut bytes = borsh::to_vec(data).unwrap();
     let encoded_length = bytes.len();
@@ -33,7 +67,7 @@ pub fn reed_solomon_encode<T: BorshSerialize>(
         .collect_vec();
 
     // Fine to unwrap here as we just constructed the parts
-    rs.reconstruct(&mut parts).unwrap();
+    reconstruct(backend, rs, &mut parts).unwrap();
 
     (parts, encoded_length)
 }
@@ -46,7 +80,16 @@ pub fn reed_solomon_decode<T: BorshDeserialize>(
     parts: &mut [ReedSolomonPart],
     encoded_length: usize,
 ) -> Result<T, Error> {
-    if let Err(err) = rs.reconstruct(parts) {
+    reed_solomon_decode_with_backend(ReedSolomonBackend::DEFAULT, rs, parts, encoded_length)
+}
+
+pub fn reed_solomon_decode_with_backend<T: BorshDeserialize>(
+    backend: ReedSolomonBackend,
+    rs: &ReedSolomon,
+    parts: &mut [ReedSolomonPart],
+    encoded_length: usize,
+) -> Result<T, Error> {
+    if let Err(err) = reconstruct(backend, rs, parts) {
         return Err(Error::other(err));
     }
 
@@ -60,6 +103,19 @@ pub fn reed_solomon_decode<T: BorshDeserialize>(
     T::try_from_slice(&encoded_data)
 }
 
+/// Fills in the missing parts, both when encoding (all parity parts missing)
+/// and when decoding.
+fn reconstruct(
+    backend: ReedSolomonBackend,
+    rs: &ReedSolomon,
+    parts: &mut [ReedSolomonPart],
+) -> Result<(), reed_solomon_erasure::Error> {
+    match backend {
+        ReedSolomonBackend::Erasure => rs.reconstruct(parts),
+        ReedSolomonBackend::Simd => simd::reconstruct(rs, parts),
+    }
+}
+
 pub fn reed_solomon_part_length(encoded_length: usize, data_parts: usize) -> usize {
     (encoded_length + data_parts - 1) / data_parts
 }
@@ -246,3 +302,103 @@ impl<T: ReedSolomonEncoderDeserialize> ReedSolomonPartsTracker<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ReedSolomonBackend, ReedSolomonPart, reconstruct, reed_solomon_decode_with_backend,
+        reed_solomon_encode_with_backend,
+    };
+    use rand::{Rng, SeedableRng};
+    use reed_solomon_erasure::galois_8::ReedSolomon;
+
+    fn random_bytes(rng: &mut impl Rng, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; len];
+        rng.fill(&mut bytes[..]);
+        bytes
+    }
+
+    /// The SIMD backend must produce exactly the parts of `reed-solomon-erasure`,
+    /// otherwise nodes using different backends can't exchange parts.
+    #[test]
+    fn test_backends_produce_identical_parts() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for (data_parts, parity_parts) in [(1, 2), (6, 11), (33, 67)] {
+            let rs = ReedSolomon::new(data_parts, parity_parts).unwrap();
+            for len in [0, 1, 1000, 100_000, (1 << 20) + 123] {
+                let data = random_bytes(&mut rng, len);
+                let erasure =
+                    reed_solomon_encode_with_backend(ReedSolomonBackend::Erasure, &rs, &data);
+                let simd = reed_solomon_encode_with_backend(ReedSolomonBackend::Simd, &rs, &data);
+                assert_eq!(erasure, simd, "parts = {data_parts} + {parity_parts}, len = {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends_decode_each_other() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(43);
+        let rs = ReedSolomon::new(6, 11).unwrap();
+        let data = random_bytes(&mut rng, (1 << 20) + 123);
+        for (encode_backend, decode_backend) in [
+            (ReedSolomonBackend::Erasure, ReedSolomonBackend::Simd),
+            (ReedSolomonBackend::Simd, ReedSolomonBackend::Erasure),
+        ] {
+            let (parts, encoded_length) =
+                reed_solomon_encode_with_backend(encode_backend, &rs, &data);
+            // Drop a random subset of the parts, keeping just enough to decode.
+            let mut received: Vec<ReedSolomonPart> = parts.clone();
+            let mut dropped = 0;
+            while dropped < rs.parity_shard_count() {
+                let ord = rng.gen_range(0..received.len());
+                if received[ord].take().is_some() {
+                    dropped += 1;
+                }
+            }
+            let decoded: Vec<u8> = reed_solomon_decode_with_backend(
+                decode_backend,
+                &rs,
+                &mut received,
+                encoded_length,
+            )
+            .unwrap();
+            assert_eq!(decoded, data);
+            assert_eq!(received, parts);
+
+            // One part less is not enough.
+            received = parts.clone();
+            for part in received.iter_mut().take(rs.parity_shard_count() + 1) {
+                *part = None;
+            }
+            assert!(
+                reed_solomon_decode_with_backend::<Vec<u8>>(
+                    decode_backend,
+                    &rs,
+                    &mut received,
+                    encoded_length
+                )
+                .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_return_same_errors() {
+        let rs = ReedSolomon::new(4, 2).unwrap();
+        let part = |len: usize| Some(vec![7; len].into_boxed_slice());
+        let cases: Vec<Vec<ReedSolomonPart>> = vec![
+            vec![part(8); 5],
+            vec![part(8); 7],
+            vec![part(8), part(8), part(8), None, None, None],
+            vec![None; 6],
+            vec![part(8), part(8), part(8), part(9), None, None],
+            vec![part(0), part(0), part(0), part(0), None, None],
+        ];
+        for parts in cases {
+            let erasure = reconstruct(ReedSolomonBackend::Erasure, &rs, &mut parts.clone());
+            let simd = reconstruct(ReedSolomonBackend::Simd, &rs, &mut parts.clone());
+            assert_eq!(erasure, simd, "{parts:?}");
+            assert!(simd.is_err());
+        }
+    }
+}
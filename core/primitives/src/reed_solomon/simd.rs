@@ -0,0 +1,465 @@
+//! Reed-Solomon codec with SIMD kernels for the field arithmetic.
+//!
+//! This computes exactly the code of `reed_solomon_erasure::galois_8::ReedSolomon`:
+//! the field is GF(2^8) with the polynomial x^8 + x^4 + x^3 + x^2 + 1, and the
+//! generator matrix is the Vandermonde matrix multiplied by the inverse of its
+//! top square, so that the data parts are kept as they are. Any set of data
+//! parts has a single codeword, so the parts are byte for byte identical to the
+//! ones of the default backend, regardless of which parts are used to decode.
+//!
+//! Multiplying a part by a constant uses the split table technique: the
+//! products of the constant with the low and the high nibbles of a byte are
+//! looked up with byte shuffles (`pshufb` on x86_64, `tbl` on aarch64) and
+//! xored, which processes 16 or 32 bytes per instruction.
+//!
+//! The generator matrices and the coefficients of the missing parts are
+//! cached, like `reed_solomon_erasure` caches its decoding matrices, so that
+//! the matrix inversions are only done once per code and set of missing parts.
+
+use super::ReedSolomonPart;
+use lru::LruCache;
+use parking_lot::Mutex;
+use reed_solomon_erasure::Error;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, LazyLock};
+
+/// The field polynomial x^8 + x^4 + x^3 + x^2 + 1.
+const FIELD_POLYNOMIAL: u16 = 0x11d;
+
+/// Number of generator matrices kept in the cache, one per number of data and
+/// total parts.
+const GENERATOR_CACHE_SIZE: usize = 16;
+
+/// Number of coefficient matrices kept in the cache, one per code and set of
+/// missing parts. Same as the decoding matrix cache of `reed_solomon_erasure`.
+const COEFFICIENTS_CACHE_SIZE: usize = 254;
+
+/// Number of bytes of every part processed at once. All the inputs and outputs
+/// of a block fit into the cache, so that every byte is loaded from memory
+/// only once.
+const BLOCK_LENGTH: usize = 16 * 1024;
+
+struct Tables {
+    /// `exp[i]` is 2^i. The table is doubled so that the sum of two
+    /// logarithms can be looked up without a modulo.
+    exp: [u8; 510],
+    log: [u8; 256],
+}
+
+static TABLES: LazyLock<Tables> = LazyLock::new(|| {
+    let mut exp = [0; 510];
+    let mut log = [0; 256];
+    let mut x: u16 = 1;
+    for i in 0..255 {
+        exp[i] = x as u8;
+        exp[i + 255] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= FIELD_POLYNOMIAL;
+        }
+    }
+    Tables { exp, log }
+});
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    let tables = &*TABLES;
+    tables.exp[tables.log[a as usize] as usize + tables.log[b as usize] as usize]
+}
+
+fn inv(a: u8) -> u8 {
+    debug_assert_ne!(a, 0);
+    let tables = &*TABLES;
+    tables.exp[255 - tables.log[a as usize] as usize]
+}
+
+fn pow(a: u8, n: usize) -> u8 {
+    if n == 0 {
+        return 1;
+    }
+    if a == 0 {
+        return 0;
+    }
+    let tables = &*TABLES;
+    tables.exp[tables.log[a as usize] as usize * n % 255]
+}
+
+type Matrix = Vec<Vec<u8>>;
+
+/// Generator matrices by the number of data and total parts.
+static GENERATORS: LazyLock<Mutex<LruCache<(usize, usize), Arc<Matrix>>>> =
+    LazyLock::new(|| Mutex::new(LruCache::new(NonZeroUsize::new(GENERATOR_CACHE_SIZE).unwrap())));
+
+/// Coefficients of the missing parts in terms of the first `data_count`
+/// present parts, by the number of data and total parts and the indices of
+/// the missing parts.
+static COEFFICIENTS: LazyLock<Mutex<LruCache<(usize, usize, Vec<usize>), Arc<Matrix>>>> =
+    LazyLock::new(|| {
+        Mutex::new(LruCache::new(NonZeroUsize::new(COEFFICIENTS_CACHE_SIZE).unwrap()))
+    });
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    a.iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|col| row.iter().zip(b).fold(0, |acc, (&x, b_row)| acc ^ mul(x, b_row[col])))
+                .collect()
+        })
+        .collect()
+}
+
+/// Inverts the matrix with Gauss-Jordan elimination, returns None if it is
+/// singular.
+fn invert(mut matrix: Matrix) -> Option<Matrix> {
+    let size = matrix.len();
+    let mut inverse: Matrix = (0..size)
+        .map(|row| (0..size).map(|col| if row == col { 1 } else { 0 }).collect())
+        .collect();
+    for col in 0..size {
+        let pivot = (col..size).find(|&row| matrix[row][col] != 0)?;
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let scale = inv(matrix[col][col]);
+        for x in matrix[col].iter_mut().chain(inverse[col].iter_mut()) {
+            *x = mul(*x, scale);
+        }
+        for row in 0..size {
+            let factor = matrix[row][col];
+            if row == col || factor == 0 {
+                continue;
+            }
+            for i in 0..size {
+                matrix[row][i] ^= mul(factor, matrix[col][i]);
+                inverse[row][i] ^= mul(factor, inverse[col][i]);
+            }
+        }
+    }
+    Some(inverse)
+}
+
+/// The generator matrix of `reed_solomon_erasure`, with `total_count` rows and
+/// `data_count` columns. Its top square is the identity.
+fn generator_matrix(data_count: usize, total_count: usize) -> Matrix {
+    let vandermonde: Matrix = (0..total_count)
+        .map(|row| (0..data_count).map(|col| pow(row as u8, col)).collect())
+        .collect();
+    let top = invert(vandermonde[..data_count].to_vec()).expect("Vandermonde matrix is invertible");
+    multiply(&vandermonde, &top)
+}
+
+fn cached_generator_matrix(data_count: usize, total_count: usize) -> Arc<Matrix> {
+    let key = (data_count, total_count);
+    if let Some(generator) = GENERATORS.lock().get(&key) {
+        return generator.clone();
+    }
+    // The matrix is computed without holding the lock, another thread may
+    // compute it concurrently, which is harmless.
+    let generator = Arc::new(generator_matrix(data_count, total_count));
+    GENERATORS.lock().put(key, generator.clone());
+    generator
+}
+
+/// The coefficients of the `missing` parts in terms of the first `data_count`
+/// `present` parts.
+fn coefficients(
+    data_count: usize,
+    total_count: usize,
+    present: &[usize],
+    missing: &[usize],
+) -> Arc<Matrix> {
+    let key = (data_count, total_count, missing.to_vec());
+    if let Some(coefficients) = COEFFICIENTS.lock().get(&key) {
+        return coefficients.clone();
+    }
+    // Any `data_count` rows of the generator matrix are linearly independent,
+    // so the data parts can be computed from the first `data_count` present
+    // parts. Multiplying the rows of the missing parts by that decoding matrix
+    // gives their coefficients in terms of the present parts directly.
+    let generator = cached_generator_matrix(data_count, total_count);
+    let decoding = invert(present[..data_count].iter().map(|&i| generator[i].clone()).collect())
+        .expect("rows of the generator matrix are linearly independent");
+    let coefficients =
+        Arc::new(multiply(&missing.iter().map(|&i| generator[i].clone()).collect(), &decoding));
+    COEFFICIENTS.lock().put(key, coefficients.clone());
+    coefficients
+}
+
+/// Fills in the missing parts, with the same validation and errors as
+/// `ReedSolomon::reconstruct`.
+pub(super) fn reconstruct(rs: &ReedSolomon, parts: &mut [ReedSolomonPart]) -> Result<(), Error> {
+    let data_count = rs.data_shard_count();
+    let total_count = rs.total_shard_count();
+    if parts.len() < total_count {
+        return Err(Error::TooFewShards);
+    }
+    if parts.len() > total_count {
+        return Err(Error::TooManyShards);
+    }
+    let mut part_length = 0;
+    for part in parts.iter().flatten() {
+        if part.is_empty() {
+            return Err(Error::EmptyShard);
+        }
+        if part_length != 0 && part.len() != part_length {
+            return Err(Error::IncorrectShardSize);
+        }
+        part_length = part.len();
+    }
+    let present: Vec<usize> = (0..total_count).filter(|&i| parts[i].is_some()).collect();
+    let missing: Vec<usize> = (0..total_count).filter(|&i| parts[i].is_none()).collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if present.len() < data_count {
+        return Err(Error::TooFewShardsPresent);
+    }
+
+    let coefficients = coefficients(data_count, total_count, &present, &missing);
+    let inputs: Vec<&[u8]> =
+        present[..data_count].iter().map(|&i| parts[i].as_deref().unwrap()).collect();
+    let mut outputs = vec![vec![0; part_length].into_boxed_slice(); missing.len()];
+    for start in (0..part_length).step_by(BLOCK_LENGTH) {
+        let end = part_length.min(start + BLOCK_LENGTH);
+        for (output, row) in outputs.iter_mut().zip(coefficients.iter()) {
+            for (input, &c) in inputs.iter().zip(row) {
+                mul_add(c, &input[start..end], &mut output[start..end]);
+            }
+        }
+    }
+    for (i, output) in missing.into_iter().zip(outputs) {
+        parts[i] = Some(output);
+    }
+    Ok(())
+}
+
+/// Products of a constant with all the possible low and high nibbles.
+struct NibbleTables {
+    low: [u8; 16],
+    high: [u8; 16],
+}
+
+impl NibbleTables {
+    fn new(c: u8) -> Self {
+        Self {
+            low: std::array::from_fn(|x| mul(c, x as u8)),
+            high: std::array::from_fn(|x| mul(c, (x as u8) << 4)),
+        }
+    }
+
+    fn mul(&self, x: u8) -> u8 {
+        self.low[(x & 0x0f) as usize] ^ self.high[(x >> 4) as usize]
+    }
+}
+
+/// dst += c * src
+fn mul_add(c: u8, src: &[u8], dst: &mut [u8]) {
+    debug_assert_eq!(src.len(), dst.len());
+    match c {
+        0 => return,
+        1 => {
+            dst.iter_mut().zip(src).for_each(|(d, s)| *d ^= s);
+            return;
+        }
+        _ => {}
+    }
+    let tables = NibbleTables::new(c);
+    let done = simd_mul_add(&tables, src, dst);
+    for (d, &s) in dst[done..].iter_mut().zip(&src[done..]) {
+        *d ^= tables.mul(s);
+    }
+}
+
+/// Processes a prefix of the parts with the widest kernel the CPU supports and
+/// returns its length. The rest is left to the scalar loop.
+fn simd_mul_add(tables: &NibbleTables, src: &[u8], dst: &mut [u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            return unsafe { x86_64::mul_add_avx2(tables, src, dst) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: the CPU supports SSSE3.
+            return unsafe { x86_64::mul_add_ssse3(tables, src, dst) };
+        }
+        0
+    }
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    {
+        // SAFETY: the target supports NEON.
+        unsafe { aarch64::mul_add_neon(tables, src, dst) }
+    }
+    #[cfg(not(any(
+        target_arch = "x86_64",
+        all(target_arch = "aarch64", target_feature = "neon")
+    )))]
+    {
+        let _ = (tables, src, dst);
+        0
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64 {
+    use super::NibbleTables;
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn mul_add_avx2(tables: &NibbleTables, src: &[u8], dst: &mut [u8]) -> usize {
+        let len = src.len().min(dst.len()) / 32 * 32;
+        // SAFETY: all the loads and stores are within the first `len` bytes.
+        unsafe {
+            let low = _mm256_broadcastsi128_si256(_mm_loadu_si128(tables.low.as_ptr().cast()));
+            let high = _mm256_broadcastsi128_si256(_mm_loadu_si128(tables.high.as_ptr().cast()));
+            let mask = _mm256_set1_epi8(0x0f);
+            for i in (0..len).step_by(32) {
+                let s = _mm256_loadu_si256(src.as_ptr().add(i).cast());
+                let d = _mm256_loadu_si256(dst.as_ptr().add(i).cast());
+                let l = _mm256_shuffle_epi8(low, _mm256_and_si256(s, mask));
+                let h =
+                    _mm256_shuffle_epi8(high, _mm256_and_si256(_mm256_srli_epi64::<4>(s), mask));
+                let p = _mm256_xor_si256(l, h);
+                _mm256_storeu_si256(dst.as_mut_ptr().add(i).cast(), _mm256_xor_si256(d, p));
+            }
+        }
+        len
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn mul_add_ssse3(tables: &NibbleTables, src: &[u8], dst: &mut [u8]) -> usize {
+        let len = src.len().min(dst.len()) / 16 * 16;
+        // SAFETY: all the loads and stores are within the first `len` bytes.
+        unsafe {
+            let low = _mm_loadu_si128(tables.low.as_ptr().cast());
+            let high = _mm_loadu_si128(tables.high.as_ptr().cast());
+            let mask = _mm_set1_epi8(0x0f);
+            for i in (0..len).step_by(16) {
+                let s = _mm_loadu_si128(src.as_ptr().add(i).cast());
+                let d = _mm_loadu_si128(dst.as_ptr().add(i).cast());
+                let l = _mm_shuffle_epi8(low, _mm_and_si128(s, mask));
+                let h = _mm_shuffle_epi8(high, _mm_and_si128(_mm_srli_epi64::<4>(s), mask));
+                let p = _mm_xor_si128(l, h);
+                _mm_storeu_si128(dst.as_mut_ptr().add(i).cast(), _mm_xor_si128(d, p));
+            }
+        }
+        len
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod aarch64 {
+    use super::NibbleTables;
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn mul_add_neon(tables: &NibbleTables, src: &[u8], dst: &mut [u8]) -> usize {
+        let len = src.len().min(dst.len()) / 16 * 16;
+        // SAFETY: all the loads and stores are within the first `len` bytes.
+        unsafe {
+            let low = vld1q_u8(tables.low.as_ptr());
+            let high = vld1q_u8(tables.high.as_ptr());
+            let mask = vdupq_n_u8(0x0f);
+            for i in (0..len).step_by(16) {
+                let s = vld1q_u8(src.as_ptr().add(i));
+                let d = vld1q_u8(dst.as_ptr().add(i));
+                let l = vqtbl1q_u8(low, vandq_u8(s, mask));
+                let h = vqtbl1q_u8(high, vshrq_n_u8::<4>(s));
+                vst1q_u8(dst.as_mut_ptr().add(i), veorq_u8(d, veorq_u8(l, h)));
+            }
+        }
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        NibbleTables, cached_generator_matrix, coefficients, generator_matrix, invert, mul,
+        mul_add, multiply,
+    };
+
+    #[test]
+    fn test_field_arithmetic() {
+        // Check multiplication against carry-less multiplication modulo the polynomial.
+        for a in 0..=255u8 {
+            let tables = NibbleTables::new(a);
+            for b in 0..=255u8 {
+                let mut expected = 0u16;
+                for bit in 0..8 {
+                    if b & (1 << bit) != 0 {
+                        expected ^= (a as u16) << bit;
+                    }
+                }
+                for bit in (8..15).rev() {
+                    if expected & (1 << bit) != 0 {
+                        expected ^= super::FIELD_POLYNOMIAL << (bit - 8);
+                    }
+                }
+                assert_eq!(mul(a, b), expected as u8, "{a} * {b}");
+                assert_eq!(tables.mul(b), expected as u8, "{a} * {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_generator_matrix() {
+        let generator = generator_matrix(4, 7);
+        for (i, row) in generator[..4].iter().enumerate() {
+            for (j, &x) in row.iter().enumerate() {
+                assert_eq!(x, if i == j { 1 } else { 0 });
+            }
+        }
+        let rows = vec![
+            generator[1].clone(),
+            generator[4].clone(),
+            generator[5].clone(),
+            generator[6].clone(),
+        ];
+        let inverse = invert(rows.clone()).unwrap();
+        assert_eq!(multiply(&rows, &inverse), generator[..4].to_vec());
+    }
+
+    #[test]
+    fn test_cached_matrices() {
+        let generator = cached_generator_matrix(4, 7);
+        assert_eq!(*generator, generator_matrix(4, 7));
+        assert!(std::sync::Arc::ptr_eq(&generator, &cached_generator_matrix(4, 7)));
+
+        // Data parts 0 and 2 missing: they are computed from parts 1, 3, 4 and 5.
+        let rows = vec![
+            generator[1].clone(),
+            generator[3].clone(),
+            generator[4].clone(),
+            generator[5].clone(),
+        ];
+        let missing = [0, 2];
+        let missing_coefficients = coefficients(4, 7, &[1, 3, 4, 5, 6], &missing);
+        assert_eq!(
+            multiply(&missing_coefficients, &rows),
+            vec![generator[0].clone(), generator[2].clone()]
+        );
+        assert!(std::sync::Arc::ptr_eq(
+            &missing_coefficients,
+            &coefficients(4, 7, &[1, 3, 4, 5, 6], &missing)
+        ));
+    }
+
+    #[test]
+    fn test_mul_add() {
+        // Lengths around the vector widths exercise the scalar tail too.
+        let src: Vec<u8> = (0..100).map(|i| (i * 37 + 11) as u8).collect();
+        for c in [0, 1, 2, 0x53, 0xff] {
+            for len in [0, 1, 15, 16, 17, 31, 32, 33, 100] {
+                let mut dst: Vec<u8> = (0..len).map(|i| i as u8).collect();
+                mul_add(c, &src[..len], &mut dst);
+                for i in 0..len {
+                    assert_eq!(dst[i], i as u8 ^ mul(c, src[i]), "c = {c}, len = {len}");
+                }
+            }
+        }
+    }
+}
@@ -46,6 +46,7 @@ pub type ShardVersion = u32;
     Eq,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ShardLayout {
     V0(ShardLayoutV0),
     V1(ShardLayoutV1),
@@ -68,6 +69,7 @@ pub enum ShardLayout {
     Eq,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ShardLayoutV0 {
     /// Map accounts evenly across all shards
     num_shards: NumShards,
@@ -109,6 +111,7 @@ pub fn shard_uids_to_ids(shard_uids: &[ShardUId]) -> Vec<ShardId> {
     Eq,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ShardLayoutV1 {
     /// The boundary accounts are the accounts on boundaries between shards.
     /// Each shard contains a range of accounts from one boundary account to
@@ -183,6 +186,7 @@ pub struct ShardLayoutV2 {
 /// Counterpart to `ShardLayoutV2` composed of maps with string keys to aid
 /// serde serialization.
 #[derive(serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 struct SerdeShardLayoutV2 {
     boundary_accounts: Vec<AccountId>,
     shard_ids: Vec<ShardId>,
@@ -291,6 +295,17 @@ impl<'de> serde::Deserialize<'de> for ShardLayoutV2 {
     }
 }
 
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for ShardLayoutV2 {
+    fn schema_name() -> String {
+        "ShardLayoutV2".to_string()
+    }
+
+    fn json_schema(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+        <SerdeShardLayoutV2 as schemars::JsonSchema>::json_schema(generator)
+    }
+}
+
 impl ShardLayoutV2 {
     pub fn account_id_to_shard_id(&self, account_id: &AccountId) -> ShardId {
         // TODO(resharding) - This could be optimized.
@@ -0,0 +1,313 @@
+//! Splitting of large `ReceiptProof`s into fragments of balanced size, see
+//! `ProtocolFeature::ReceiptProofFragments`.
+//!
+//! A receipt proof is sent to every node tracking its destination shard
+//! together with the parts of the chunk. A single very large cross-shard
+//! receipt would make that message much larger than the others, so such
+//! proofs are sent as separate fragments instead. The fragments are the parts
+//! of a Reed-Solomon code over the borsh serialized proof, so the proof can be
+//! reassembled from any `num_data_fragments` of them. Each fragment carries the
+//! metadata needed to reassemble the proof and the merkle path of its data
+//! within all the fragments, so that fragments can be validated on their own.
+//! The reassembled proof is then validated against the
+//! `prev_outgoing_receipts_root` of the chunk header like any other proof.
+
+use super::{ChunkHash, ReceiptProof};
+use crate::hash::CryptoHash;
+use crate::merkle::{MerklePath, verify_path};
+use crate::types::ShardId;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_fmt::AbbrBytes;
+use near_schema_checker_lib::ProtocolSchema;
+
+/// Maximum number of data fragments of a proof. Together with the parity
+/// fragments they have to fit in the 256 parts of the Reed-Solomon code.
+pub const MAX_RECEIPT_PROOF_DATA_FRAGMENTS: usize = 128;
+
+/// Number of parity fragments sent along with `num_data_fragments` data
+/// fragments, so that the proof can be reassembled if up to a third of the
+/// fragments are lost.
+pub fn num_parity_fragments(num_data_fragments: usize) -> usize {
+    num_data_fragments.div_ceil(2)
+}
+
+/// Fragment of a `ReceiptProof` of a chunk.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Eq, PartialEq, ProtocolSchema)]
+pub struct ReceiptProofFragment {
+    pub chunk_hash: ChunkHash,
+    /// Destination shard of the receipts in the proof.
+    pub to_shard_id: ShardId,
+    /// Merkle root of the data of all fragments of the proof, including the
+    /// parity fragments.
+    pub fragments_root: CryptoHash,
+    /// Number of fragments needed to reassemble the proof.
+    pub num_data_fragments: u32,
+    /// Number of data and parity fragments of the proof.
+    pub num_total_fragments: u32,
+    /// Length of the borsh serialized `ReceiptProof`.
+    pub encoded_length: u64,
+    pub fragment_ord: u32,
+    /// Reed-Solomon part of the borsh serialized `ReceiptProof`.
+    pub data: Vec<u8>,
+    /// Merkle path of `data` against `fragments_root`.
+    pub proof: MerklePath,
+}
+
+impl std::fmt::Debug for ReceiptProofFragment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReceiptProofFragment")
+            .field("chunk_hash", &self.chunk_hash)
+            .field("to_shard_id", &self.to_shard_id)
+            .field("fragments_root", &self.fragments_root)
+            .field("num_data_fragments", &self.num_data_fragments)
+            .field("num_total_fragments", &self.num_total_fragments)
+            .field("encoded_length", &self.encoded_length)
+            .field("fragment_ord", &self.fragment_ord)
+            .field("data", &AbbrBytes(&self.data))
+            .finish()
+    }
+}
+
+impl ReceiptProofFragment {
+    /// Checks that the metadata of the fragment is consistent and that its
+    /// data belongs to `fragments_root`. The reassembled proof still has to be
+    /// verified against the chunk header.
+    pub fn verify(&self) -> bool {
+        let num_data_fragments = self.num_data_fragments as usize;
+        (1..=MAX_RECEIPT_PROOF_DATA_FRAGMENTS).contains(&num_data_fragments)
+            && self.num_total_fragments as usize
+                == num_data_fragments + num_parity_fragments(num_data_fragments)
+            && self.fragment_ord < self.num_total_fragments
+            && self.encoded_length > 0
+            && self.data.len() as u64 == self.encoded_length.div_ceil(num_data_fragments as u64)
+            && verify_path(self.fragments_root, &self.proof, &self.data)
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReceiptProofFragmentsError {
+    #[error("expected at least {expected} fragments, got {actual}")]
+    NotEnoughFragments { expected: usize, actual: usize },
+    #[error("fragment {fragment_ord} doesn't match the other fragments")]
+    MismatchedFragment { fragment_ord: u32 },
+    #[error("fragment {fragment_ord} is invalid")]
+    InvalidFragment { fragment_ord: u32 },
+    #[error("reassembled receipt proof can't be decoded: {0}")]
+    InvalidReceiptProof(String),
+}
+
+/// Splits the proof into data fragments of at most `max_fragment_size` bytes
+/// each, unless that would take more than `MAX_RECEIPT_PROOF_DATA_FRAGMENTS`,
+/// and adds the parity fragments. All fragments have the same size.
+#[cfg(feature = "solomon")]
+pub fn split_receipt_proof(
+    chunk_hash: &ChunkHash,
+    receipt_proof: &ReceiptProof,
+    max_fragment_size: usize,
+) -> Vec<ReceiptProofFragment> {
+    use crate::merkle::merklize;
+    use crate::reed_solomon::reed_solomon_encode;
+    use reed_solomon_erasure::galois_8::ReedSolomon;
+
+    assert!(max_fragment_size > 0);
+    let size = borsh::object_length(receipt_proof).expect("borsh serialization should not fail");
+    let num_data_fragments =
+        size.div_ceil(max_fragment_size).clamp(1, MAX_RECEIPT_PROOF_DATA_FRAGMENTS);
+    let rs = ReedSolomon::new(num_data_fragments, num_parity_fragments(num_data_fragments))
+        .expect("number of fragments should be valid");
+    let (parts, encoded_length) = reed_solomon_encode(&rs, receipt_proof);
+    let data = parts
+        .into_iter()
+        .map(|part| part.expect("all parts are present after encoding").into_vec())
+        .collect::<Vec<_>>();
+    let (fragments_root, paths) = merklize(&data);
+    let num_total_fragments = data.len() as u32;
+    data.into_iter()
+        .zip(paths)
+        .enumerate()
+        .map(|(fragment_ord, (data, proof))| ReceiptProofFragment {
+            chunk_hash: chunk_hash.clone(),
+            to_shard_id: receipt_proof.1.to_shard_id,
+            fragments_root,
+            num_data_fragments: num_data_fragments as u32,
+            num_total_fragments,
+            encoded_length: encoded_length as u64,
+            fragment_ord: fragment_ord as u32,
+            data,
+            proof,
+        })
+        .collect()
+}
+
+/// Reassembles the proof from at least `num_data_fragments` of its fragments,
+/// in any order. Duplicate fragments are ignored.
+#[cfg(feature = "solomon")]
+pub fn assemble_receipt_proof(
+    fragments: Vec<ReceiptProofFragment>,
+) -> Result<ReceiptProof, ReceiptProofFragmentsError> {
+    use crate::reed_solomon::reed_solomon_decode;
+    use reed_solomon_erasure::galois_8::ReedSolomon;
+
+    let Some(first) = fragments.first() else {
+        return Err(ReceiptProofFragmentsError::NotEnoughFragments { expected: 1, actual: 0 });
+    };
+    if !first.verify() {
+        return Err(ReceiptProofFragmentsError::InvalidFragment {
+            fragment_ord: first.fragment_ord,
+        });
+    }
+    let (chunk_hash, to_shard_id, fragments_root) =
+        (first.chunk_hash.clone(), first.to_shard_id, first.fragments_root);
+    let (num_data_fragments, num_total_fragments, encoded_length) =
+        (first.num_data_fragments, first.num_total_fragments, first.encoded_length);
+    let mut parts = vec![None; num_total_fragments as usize];
+    for fragment in fragments {
+        if fragment.num_data_fragments != num_data_fragments
+            || fragment.num_total_fragments != num_total_fragments
+            || fragment.encoded_length != encoded_length
+            || fragment.chunk_hash != chunk_hash
+            || fragment.to_shard_id != to_shard_id
+            || fragment.fragments_root != fragments_root
+        {
+            return Err(ReceiptProofFragmentsError::MismatchedFragment {
+                fragment_ord: fragment.fragment_ord,
+            });
+        }
+        if !fragment.verify() {
+            return Err(ReceiptProofFragmentsError::InvalidFragment {
+                fragment_ord: fragment.fragment_ord,
+            });
+        }
+        parts[fragment.fragment_ord as usize] = Some(fragment.data.into_boxed_slice());
+    }
+    let num_data_fragments = num_data_fragments as usize;
+    let num_present = parts.iter().flatten().count();
+    if num_present < num_data_fragments {
+        return Err(ReceiptProofFragmentsError::NotEnoughFragments {
+            expected: num_data_fragments,
+            actual: num_present,
+        });
+    }
+    let rs = ReedSolomon::new(num_data_fragments, parts.len() - num_data_fragments)
+        .expect("number of fragments was verified");
+    let receipt_proof: ReceiptProof = reed_solomon_decode(&rs, &mut parts, encoded_length as usize)
+        .map_err(|err| ReceiptProofFragmentsError::InvalidReceiptProof(err.to_string()))?;
+    if receipt_proof.1.to_shard_id != to_shard_id {
+        return Err(ReceiptProofFragmentsError::InvalidReceiptProof(format!(
+            "proof is for shard {} instead of {}",
+            receipt_proof.1.to_shard_id, to_shard_id
+        )));
+    }
+    Ok(receipt_proof)
+}
+
+#[cfg(all(test, feature = "solomon"))]
+mod tests {
+    use super::*;
+    use crate::receipt::{Receipt, ReceiptPriority};
+    use crate::sharding::ShardProof;
+
+    fn receipt_proof(num_receipts: usize) -> ReceiptProof {
+        let receipts = (0..num_receipts)
+            .map(|i| {
+                Receipt::new_balance_refund(
+                    &format!("account{i}.near").parse().unwrap(),
+                    i as u128,
+                    ReceiptPriority::NoPriority,
+                )
+            })
+            .collect();
+        let shard_proof = ShardProof {
+            from_shard_id: ShardId::new(0),
+            to_shard_id: ShardId::new(1),
+            proof: vec![],
+        };
+        ReceiptProof(receipts, shard_proof)
+    }
+
+    #[test]
+    fn test_split_and_assemble_receipt_proof() {
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        let receipt_proof = receipt_proof(20);
+        let size = borsh::object_length(&receipt_proof).unwrap();
+        for max_fragment_size in [1, 7, size / 3, size - 1, size, size * 2] {
+            let mut fragments = split_receipt_proof(&chunk_hash, &receipt_proof, max_fragment_size);
+            let num_data_fragments =
+                size.div_ceil(max_fragment_size).min(MAX_RECEIPT_PROOF_DATA_FRAGMENTS);
+            assert_eq!(
+                fragments.len(),
+                num_data_fragments + num_parity_fragments(num_data_fragments)
+            );
+            let fragment_size = fragments[0].data.len();
+            assert!(fragments.iter().all(|fragment| fragment.data.len() == fragment_size));
+            if num_data_fragments < MAX_RECEIPT_PROOF_DATA_FRAGMENTS {
+                assert!(fragment_size <= max_fragment_size);
+            }
+            assert!(fragments.iter().all(ReceiptProofFragment::verify));
+
+            fragments.reverse();
+            assert_eq!(assemble_receipt_proof(fragments).unwrap(), receipt_proof);
+        }
+    }
+
+    /// Any `num_data_fragments` of the fragments are enough to reassemble the
+    /// proof.
+    #[test]
+    fn test_assemble_receipt_proof_from_any_fragments() {
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        let receipt_proof = receipt_proof(20);
+        let fragments = split_receipt_proof(&chunk_hash, &receipt_proof, 100);
+        let num_data_fragments = fragments[0].num_data_fragments as usize;
+        assert!(num_data_fragments > 2);
+        for skip in 0..fragments.len() {
+            let subset = fragments
+                .iter()
+                .cycle()
+                .skip(skip)
+                .take(num_data_fragments)
+                .cloned()
+                .collect::<Vec<_>>();
+            assert_eq!(assemble_receipt_proof(subset).unwrap(), receipt_proof);
+        }
+    }
+
+    #[test]
+    fn test_assemble_receipt_proof_errors() {
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        let fragments = split_receipt_proof(&chunk_hash, &receipt_proof(20), 100);
+        let num_data_fragments = fragments[0].num_data_fragments as usize;
+        assert!(num_data_fragments > 2);
+
+        let not_enough = fragments[..num_data_fragments - 1].to_vec();
+        assert_eq!(
+            assemble_receipt_proof(not_enough),
+            Err(ReceiptProofFragmentsError::NotEnoughFragments {
+                expected: num_data_fragments,
+                actual: num_data_fragments - 1
+            })
+        );
+
+        let mut tampered = fragments.clone();
+        tampered[1].data[0] ^= 1;
+        assert_eq!(
+            assemble_receipt_proof(tampered),
+            Err(ReceiptProofFragmentsError::InvalidFragment { fragment_ord: 1 })
+        );
+
+        let mut mixed = fragments.clone();
+        mixed[2].chunk_hash = ChunkHash::default();
+        assert_eq!(
+            assemble_receipt_proof(mixed),
+            Err(ReceiptProofFragmentsError::MismatchedFragment { fragment_ord: 2 })
+        );
+
+        let mut wrong_metadata = fragments;
+        for fragment in &mut wrong_metadata {
+            fragment.num_total_fragments += 1;
+        }
+        assert_eq!(
+            assemble_receipt_proof(wrong_metadata),
+            Err(ReceiptProofFragmentsError::InvalidFragment { fragment_ord: 0 })
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt::Debug;
 
 use crate::sharding::{ChunkHash, ShardChunkHeader};
 use crate::types::{EpochId, SignatureDifferentiator};
-use crate::validator_signer::ValidatorSigner;
+use crate::validator_signer::{SignerError, ValidatorSigner};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::{PublicKey, Signature};
 use near_primitives_core::types::{AccountId, BlockHeight, ShardId};
@@ -24,7 +24,7 @@ impl ChunkEndorsement {
         epoch_id: EpochId,
         chunk_header: &ShardChunkHeader,
         signer: &ValidatorSigner,
-    ) -> ChunkEndorsement {
+    ) -> Result<ChunkEndorsement, SignerError> {
         let inner = ChunkEndorsementInner::new(chunk_header.chunk_hash());
         let metadata = ChunkEndorsementMetadata {
             account_id: signer.validator_id().clone(),
@@ -32,10 +32,10 @@ impl ChunkEndorsement {
             epoch_id,
             height_created: chunk_header.height_created(),
         };
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        let metadata_signature = signer.sign_bytes(&borsh::to_vec(&metadata).unwrap());
+        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap())?;
+        let metadata_signature = signer.sign_bytes(&borsh::to_vec(&metadata).unwrap())?;
         let endorsement = ChunkEndorsementV2 { inner, signature, metadata, metadata_signature };
-        ChunkEndorsement::V2(endorsement)
+        Ok(ChunkEndorsement::V2(endorsement))
     }
 
     pub fn chunk_production_key(&self) -> ChunkProductionKey {
@@ -77,6 +77,24 @@ impl ChunkEndorsement {
         }
     }
 
+    /// Same as calling `verify` for every endorsement with the public key next to it, but
+    /// verifies all the signatures in one batch.
+    pub fn verify_batch(endorsements: &[(&ChunkEndorsement, &PublicKey)]) -> bool {
+        let signed_data: Vec<_> = endorsements
+            .iter()
+            .map(|(endorsement, public_key)| match endorsement {
+                ChunkEndorsement::V1 => unreachable!("V1 chunk endorsement is deprecated"),
+                ChunkEndorsement::V2(v2) => (v2, v2.signed_data(), *public_key),
+            })
+            .collect();
+        let mut items = vec![];
+        for (v2, (inner, metadata), public_key) in &signed_data {
+            items.push((inner.as_slice(), &v2.signature, *public_key));
+            items.push((metadata.as_slice(), &v2.metadata_signature, *public_key));
+        }
+        Signature::verify_batch(&items)
+    }
+
     pub fn validate_signature(
         chunk_hash: ChunkHash,
         signature: &Signature,
@@ -117,11 +135,15 @@ pub struct ChunkEndorsementV2 {
 
 impl ChunkEndorsementV2 {
     fn verify(&self, public_key: &PublicKey) -> bool {
-        let inner = borsh::to_vec(&self.inner).unwrap();
-        let metadata = borsh::to_vec(&self.metadata).unwrap();
+        let (inner, metadata) = self.signed_data();
         self.signature.verify(&inner, public_key)
             && self.metadata_signature.verify(&metadata, public_key)
     }
+
+    /// Data signed by `signature` and `metadata_signature` respectively.
+    fn signed_data(&self) -> (Vec<u8>, Vec<u8>) {
+        (borsh::to_vec(&self.inner).unwrap(), borsh::to_vec(&self.metadata).unwrap())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, ProtocolSchema)]
@@ -13,7 +13,8 @@ use super::ChunkProductionKey;
 #[cfg(feature = "solomon")]
 use crate::reed_solomon::{ReedSolomonEncoderDeserialize, ReedSolomonEncoderSerialize};
 use crate::types::SignatureDifferentiator;
-use crate::{utils::compression::CompressedData, validator_signer::ValidatorSigner};
+use crate::utils::compression::CompressedData;
+use crate::validator_signer::{SignerError, ValidatorSigner};
 
 // Data structures for chunk producers to send accessed contracts to chunk validators.
 
@@ -38,8 +39,8 @@ impl ChunkContractAccesses {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
-        Self::V1(ChunkContractAccessesV1::new(next_chunk, contracts, main_transition, signer))
+    ) -> Result<Self, SignerError> {
+        Ok(Self::V1(ChunkContractAccessesV1::new(next_chunk, contracts, main_transition, signer)?))
     }
 
     pub fn contracts(&self) -> &[CodeHash] {
@@ -80,10 +81,10 @@ impl ChunkContractAccessesV1 {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = ChunkContractAccessesInner::new(next_chunk, contracts, main_transition);
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap())?;
+        Ok(Self { inner, signature })
     }
 
     fn verify_signature(&self, public_key: &PublicKey) -> bool {
@@ -135,8 +136,8 @@ impl ContractCodeRequest {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
-        Self::V1(ContractCodeRequestV1::new(next_chunk, contracts, main_transition, signer))
+    ) -> Result<Self, SignerError> {
+        Ok(Self::V1(ContractCodeRequestV1::new(next_chunk, contracts, main_transition, signer)?))
     }
 
     pub fn requester(&self) -> &AccountId {
@@ -183,15 +184,15 @@ impl ContractCodeRequestV1 {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = ContractCodeRequestInner::new(
             signer.validator_id().clone(),
             next_chunk,
             contracts,
             main_transition,
         );
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap())?;
+        Ok(Self { inner, signature })
     }
 
     pub fn verify_signature(&self, public_key: &PublicKey) -> bool {
@@ -418,8 +419,8 @@ impl PartialEncodedContractDeploys {
         key: ChunkProductionKey,
         part: PartialEncodedContractDeploysPart,
         signer: &ValidatorSigner,
-    ) -> Self {
-        Self::V1(PartialEncodedContractDeploysV1::new(key, part, signer))
+    ) -> Result<Self, SignerError> {
+        Ok(Self::V1(PartialEncodedContractDeploysV1::new(key, part, signer)?))
     }
 
     pub fn chunk_production_key(&self) -> &ChunkProductionKey {
@@ -464,10 +465,10 @@ impl PartialEncodedContractDeploysV1 {
         key: ChunkProductionKey,
         part: PartialEncodedContractDeploysPart,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = PartialEncodedContractDeploysInner::new(key, part);
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap())?;
+        Ok(Self { inner, signature })
     }
 
     pub fn verify_signature(&self, public_key: &PublicKey) -> bool {
@@ -11,6 +11,7 @@ pub mod partial_witness;
 pub mod state_witness;
 pub mod stored_chunk_state_transition_data;
 pub mod validator_assignment;
+pub mod witness_size;
 
 /// This struct contains combination of fields that uniquely identify chunk production.
 /// It means that for a given instance only one chunk could be produced.
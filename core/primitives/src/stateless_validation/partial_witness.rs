@@ -3,11 +3,11 @@ use std::fmt::{Debug, Formatter};
 use super::ChunkProductionKey;
 use crate::sharding::ShardChunkHeader;
 use crate::types::{EpochId, SignatureDifferentiator};
-use crate::validator_signer::ValidatorSigner;
+use crate::validator_signer::{SignerError, ValidatorSigner};
 use borsh::{BorshDeserialize, BorshSerialize};
 use bytesize::ByteSize;
 use near_crypto::{PublicKey, Signature};
-use near_primitives_core::types::{BlockHeight, ShardId};
+use near_primitives_core::types::{AccountId, BlockHeight, ShardId};
 use near_schema_checker_lib::ProtocolSchema;
 
 /// Represents max allowed size of the compressed state witness,
@@ -47,7 +47,7 @@ impl PartialEncodedStateWitness {
         part: Vec<u8>,
         encoded_length: usize,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = PartialEncodedStateWitnessInner::new(
             epoch_id,
             chunk_header,
@@ -55,8 +55,8 @@ impl PartialEncodedStateWitness {
             part,
             encoded_length,
         );
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap())?;
+        Ok(Self { inner, signature })
     }
 
     pub fn chunk_production_key(&self) -> ChunkProductionKey {
@@ -119,3 +119,45 @@ impl PartialEncodedStateWitnessInner {
         }
     }
 }
+
+/// Sent by a chunk validator to the chunk producer to ask for the parts of a state witness
+/// that it has not received, e.g. because it was behind the chain at the time the witness
+/// was distributed. The chunk producer answers by forwarding the retained parts.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, ProtocolSchema)]
+pub struct PartialEncodedStateWitnessRequest {
+    inner: PartialEncodedStateWitnessRequestInner,
+    signature: Signature,
+}
+
+impl PartialEncodedStateWitnessRequest {
+    pub fn new(key: ChunkProductionKey, signer: &ValidatorSigner) -> Result<Self, SignerError> {
+        let inner = PartialEncodedStateWitnessRequestInner {
+            requester: signer.validator_id().clone(),
+            key,
+            signature_differentiator: "PartialEncodedStateWitnessRequest".to_owned(),
+        };
+        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap())?;
+        Ok(Self { inner, signature })
+    }
+
+    pub fn requester(&self) -> &AccountId {
+        &self.inner.requester
+    }
+
+    pub fn chunk_production_key(&self) -> &ChunkProductionKey {
+        &self.inner.key
+    }
+
+    pub fn verify_signature(&self, public_key: &PublicKey) -> bool {
+        self.signature.verify(&borsh::to_vec(&self.inner).unwrap(), public_key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, ProtocolSchema)]
+pub struct PartialEncodedStateWitnessRequestInner {
+    /// Account of the chunk validator requesting the witness. Used for signature
+    /// verification and to identify the node to send the parts to.
+    requester: AccountId,
+    key: ChunkProductionKey,
+    signature_differentiator: SignatureDifferentiator,
+}
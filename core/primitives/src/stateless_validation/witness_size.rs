@@ -0,0 +1,150 @@
+//! Attribution of the storage proof of a chunk to the transactions and
+//! receipts applied in it.
+//!
+//! The storage proof recorded while applying a chunk is the largest part of
+//! its state witness. The runtime enforces a soft limit on it
+//! (`main_storage_proof_size_soft_limit`): once the recorded proof exceeds the
+//! limit, the remaining local and incoming receipts are deferred to the
+//! delayed receipts queue and no more delayed receipts are processed.
+//! `WitnessSizeStats` records how much of the proof each transaction and
+//! receipt is responsible for, which explains why a chunk hit the limit.
+//! Only the largest ones are kept, as the stats are persisted for every chunk.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives_core::hash::CryptoHash;
+use near_primitives_core::types::AccountId;
+
+/// Maximum number of attributions kept in `WitnessSizeStats`.  When exceeded,
+/// the smallest attribution is only counted in the totals.
+pub const MAX_STORAGE_PROOF_ATTRIBUTIONS: usize = 32;
+
+/// Where the transaction or receipt a storage proof is attributed to comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub enum StorageProofSource {
+    Transaction,
+    LocalReceipt,
+    DelayedReceipt,
+    IncomingReceipt,
+}
+
+/// Storage proof recorded while applying a single transaction or receipt.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub struct StorageProofAttribution {
+    pub source: StorageProofSource,
+    /// Hash of the transaction or id of the receipt.
+    pub id: CryptoHash,
+    /// Signer of the transaction or receiver of the receipt.
+    pub account_id: AccountId,
+    /// Growth of the upper bound of the recorded storage proof size, in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub struct WitnessSizeStats {
+    /// Soft limit of the storage proof size, in bytes.
+    pub soft_limit: u64,
+    /// Upper bound of the storage proof size of the whole chunk, in bytes.
+    pub storage_proof_size: u64,
+    /// Whether the soft limit was exceeded while applying the chunk.
+    pub soft_limit_exceeded: bool,
+    /// Number of local and incoming receipts put in the delayed receipts queue
+    /// because the soft limit was exceeded.
+    pub deferred_receipts_num: u64,
+    /// The transactions and receipts with the largest storage proof, at most
+    /// `MAX_STORAGE_PROOF_ATTRIBUTIONS`, in the order of application.
+    pub attributions: Vec<StorageProofAttribution>,
+    /// Number of transactions and receipts dropped from `attributions`.
+    pub dropped_attributions_num: u64,
+    /// Total storage proof of the transactions and receipts dropped from
+    /// `attributions`, in bytes.
+    pub dropped_attributions_size: u64,
+}
+
+impl WitnessSizeStats {
+    pub fn new(soft_limit: u64) -> WitnessSizeStats {
+        WitnessSizeStats { soft_limit, ..Default::default() }
+    }
+
+    pub fn record(
+        &mut self,
+        source: StorageProofSource,
+        id: CryptoHash,
+        account_id: AccountId,
+        size: u64,
+    ) {
+        self.attributions.push(StorageProofAttribution { source, id, account_id, size });
+        if self.attributions.len() > MAX_STORAGE_PROOF_ATTRIBUTIONS {
+            let (smallest, _) = self
+                .attributions
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, attribution)| attribution.size)
+                .unwrap();
+            let dropped = self.attributions.remove(smallest);
+            self.dropped_attributions_num += 1;
+            self.dropped_attributions_size += dropped.size;
+        }
+    }
+
+    pub fn record_deferred_receipt(&mut self) {
+        self.deferred_receipts_num += 1;
+    }
+
+    /// Returns the `n` attributions with the largest storage proof, the
+    /// largest first.
+    pub fn largest_attributions(&self, n: usize) -> Vec<&StorageProofAttribution> {
+        let mut attributions = self.attributions.iter().collect::<Vec<_>>();
+        attributions.sort_by(|a, b| b.size.cmp(&a.size));
+        attributions.truncate(n);
+        attributions
+    }
+
+    /// Total storage proof attributed to the transactions and receipts.
+    /// It doesn't include the proof recorded outside of them, e.g. when
+    /// reading the delayed receipts queue or the outgoing buffers.
+    pub fn attributed_size(&self) -> u64 {
+        self.attributions.iter().map(|attribution| attribution.size).sum::<u64>()
+            + self.dropped_attributions_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_STORAGE_PROOF_ATTRIBUTIONS, StorageProofSource, WitnessSizeStats};
+    use near_primitives_core::hash::CryptoHash;
+
+    #[test]
+    fn largest_attributions() {
+        let mut stats = WitnessSizeStats::new(100);
+        for (i, size) in [10, 50, 30].into_iter().enumerate() {
+            stats.record(
+                StorageProofSource::IncomingReceipt,
+                CryptoHash::hash_bytes(&[i as u8]),
+                "test".parse().unwrap(),
+                size,
+            );
+        }
+        let largest = stats.largest_attributions(2);
+        assert_eq!(largest.iter().map(|a| a.size).collect::<Vec<_>>(), vec![50, 30]);
+        assert_eq!(stats.attributed_size(), 90);
+    }
+
+    #[test]
+    fn attributions_bounded() {
+        let mut stats = WitnessSizeStats::new(100);
+        let num = MAX_STORAGE_PROOF_ATTRIBUTIONS as u64 + 10;
+        for size in 0..num {
+            stats.record(
+                StorageProofSource::Transaction,
+                CryptoHash::hash_bytes(&size.to_le_bytes()),
+                "test".parse().unwrap(),
+                size,
+            );
+        }
+        assert_eq!(stats.attributions.len(), MAX_STORAGE_PROOF_ATTRIBUTIONS);
+        assert_eq!(stats.attributions[0].size, 10);
+        assert_eq!(stats.dropped_attributions_num, 10);
+        assert_eq!(stats.dropped_attributions_size, (0..10).sum::<u64>());
+        assert_eq!(stats.attributed_size(), (0..num).sum::<u64>());
+    }
+}
@@ -459,7 +459,7 @@ impl BlockHeader {
             &self.inner_lite_bytes(),
             &self.inner_rest_bytes(),
         );
-        let signature = signer.sign_bytes(hash.as_ref());
+        let signature = signer.sign_bytes(hash.as_ref()).expect("failed to sign block header");
         match self {
             BlockHeader::BlockHeaderV1(header) => {
                 let header = Arc::make_mut(header);
@@ -897,6 +897,7 @@ impl TestBlockBuilder {
             None,
             None,
         )
+        .unwrap()
     }
 }
 
@@ -225,6 +225,34 @@ impl ValidatedTransaction {
         config: &RuntimeConfig,
         signed_tx: SignedTransaction,
     ) -> Result<Self, (InvalidTxError, SignedTransaction)> {
+        let signed_tx = Self::check_version_and_size(config, signed_tx)?;
+        if signed_tx
+            .signature
+            .verify(signed_tx.get_hash().as_ref(), signed_tx.transaction.public_key())
+        {
+            Ok(Self(signed_tx))
+        } else {
+            Err((InvalidTxError::InvalidSignature, signed_tx))
+        }
+    }
+
+    /// Performs the same checks as `new`, except for the verification of the
+    /// signature.  Only enabled for the runtime, which dry runs transactions
+    /// before they are signed and never includes them in a chunk.
+    #[cfg(feature = "dry_run")]
+    #[allow(clippy::result_large_err)]
+    pub fn new_unsigned(
+        config: &RuntimeConfig,
+        signed_tx: SignedTransaction,
+    ) -> Result<Self, (InvalidTxError, SignedTransaction)> {
+        Ok(Self(Self::check_version_and_size(config, signed_tx)?))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn check_version_and_size(
+        config: &RuntimeConfig,
+        signed_tx: SignedTransaction,
+    ) -> Result<SignedTransaction, (InvalidTxError, SignedTransaction)> {
         // Don't allow V1 currently. This will be changed when the new protocol version is introduced.
         if matches!(signed_tx.transaction, Transaction::V1(_)) {
             return Err((InvalidTxError::InvalidTransactionVersion, signed_tx));
@@ -237,15 +265,7 @@ impl ValidatedTransaction {
                 signed_tx,
             ));
         }
-
-        if signed_tx
-            .signature
-            .verify(signed_tx.get_hash().as_ref(), signed_tx.transaction.public_key())
-        {
-            Ok(Self(signed_tx))
-        } else {
-            Err((InvalidTxError::InvalidSignature, signed_tx))
-        }
+        Ok(signed_tx)
     }
 
     /// This method should only be used for test purposes.  This is because
@@ -413,6 +433,22 @@ impl<'de> serde::Deserialize<'de> for SignedTransaction {
     }
 }
 
+/// Base64-encoded borsh serialization of the transaction.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for SignedTransaction {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "SignedTransaction".to_string()
+    }
+
+    fn json_schema(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+        generator.subschema_for::<String>()
+    }
+}
+
 /// The status of execution for a transaction or a receipt.
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Default, ProtocolSchema)]
 pub enum ExecutionStatus {
@@ -536,6 +572,31 @@ pub enum ExecutionMetadata {
     V2(crate::profile_data_v2::ProfileDataV2),
     /// V3: With ProfileData by gas parameters
     V3(Box<ProfileDataV3>),
+    /// V4: V3 with the storage proof size of the execution
+    V4(Box<ExecutionMetadataV4>),
+}
+
+impl ExecutionMetadata {
+    /// The profile data by gas parameters, if the metadata has it.
+    pub fn profile_data_v3(&self) -> Option<&ProfileDataV3> {
+        match self {
+            ExecutionMetadata::V1 | ExecutionMetadata::V2(_) => None,
+            ExecutionMetadata::V3(profile) => Some(profile),
+            ExecutionMetadata::V4(metadata) => metadata.profile.as_ref(),
+        }
+    }
+}
+
+#[derive(
+    BorshSerialize, BorshDeserialize, PartialEq, Clone, Eq, Debug, Default, ProtocolSchema,
+)]
+pub struct ExecutionMetadataV4 {
+    /// Profile of the gas used by the receipt, `None` for transactions.
+    pub profile: Option<ProfileDataV3>,
+    /// Upper bound of the size of the storage proof recorded while executing
+    /// the transaction or receipt, in bytes. This is what counts towards the
+    /// storage proof size limits of the receipt and of the chunk.
+    pub storage_proof_size: u64,
 }
 
 impl fmt::Debug for ExecutionOutcome {
@@ -211,6 +211,45 @@ pub fn get_outcome_id_block_hash_rev(key: &[u8]) -> std::io::Result<(CryptoHash,
     Ok((outcome_id, block_hash))
 }
 
+/// Separates the signer from the rest of a `DBCol::TransactionsBySigner` key.
+/// A comma is not allowed in account ids, so the keys of one signer are never
+/// a prefix of the keys of another.
+const TRANSACTIONS_BY_SIGNER_SEPARATOR: u8 = b',';
+
+/// Prefix of all `DBCol::TransactionsBySigner` keys of the given signer.
+pub fn get_transactions_by_signer_prefix(signer_id: &AccountId) -> Vec<u8> {
+    let mut res = Vec::with_capacity(signer_id.as_str().len() + 1);
+    res.extend_from_slice(signer_id.as_str().as_bytes());
+    res.push(TRANSACTIONS_BY_SIGNER_SEPARATOR);
+    res
+}
+
+/// Key of a transaction in `DBCol::TransactionsBySigner`. The height is big
+/// endian so that the keys of a signer are sorted from the oldest.
+pub fn get_transactions_by_signer_key(
+    signer_id: &AccountId,
+    height: BlockHeight,
+    tx_hash: &CryptoHash,
+) -> Vec<u8> {
+    let mut res = get_transactions_by_signer_prefix(signer_id);
+    res.extend_from_slice(&height.to_be_bytes());
+    res.extend_from_slice(tx_hash.as_ref());
+    res
+}
+
+/// Returns the height and the transaction hash of a `DBCol::TransactionsBySigner`
+/// key with the signer prefix already stripped.
+pub fn get_transactions_by_signer_key_rev(
+    key: &[u8],
+) -> std::io::Result<(BlockHeight, CryptoHash)> {
+    if key.len() != 40 {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid key length"));
+    }
+    let height = BlockHeight::from_be_bytes(key[..8].try_into().unwrap());
+    let tx_hash = CryptoHash::try_from(&key[8..]).unwrap();
+    Ok((height, tx_hash))
+}
+
 /// Creates a new Receipt ID from a given signed transaction and a block height or hash.
 /// This method is backward compatible, so it takes the current protocol version.
 pub fn create_receipt_id_from_transaction(
@@ -462,7 +501,10 @@ pub fn get_block_metadata(
     signer: &crate::validator_signer::ValidatorSigner,
     now: u64,
     sandbox_delta_time: Option<near_time::Duration>,
-) -> (u64, near_crypto::vrf::Value, near_crypto::vrf::Proof, CryptoHash) {
+) -> Result<
+    (u64, near_crypto::vrf::Value, near_crypto::vrf::Proof, CryptoHash),
+    crate::validator_signer::SignerError,
+> {
     #[cfg(feature = "sandbox")]
     let now = now + sandbox_delta_time.unwrap().whole_nanoseconds() as u64;
     #[cfg(not(feature = "sandbox"))]
@@ -474,9 +516,9 @@ pub fn get_block_metadata(
     };
 
     let (vrf_value, vrf_proof) =
-        signer.compute_vrf_with_proof(prev_block_header.random_value().as_ref());
+        signer.compute_vrf_with_proof(prev_block_header.random_value().as_ref())?;
     let random_value = hash(vrf_value.0.as_ref());
-    (time, vrf_value, vrf_proof, random_value)
+    Ok((time, vrf_value, vrf_proof, random_value))
 }
 
 #[cfg(test)]
@@ -13,6 +13,8 @@ pub enum ValidatorSigner {
     Empty(EmptyValidatorSigner),
     /// Default validator signer that holds data in memory.
     InMemory(InMemoryValidatorSigner),
+    /// Validator signer which delegates signing to a `ValidatorSigningBackend`.
+    External(ExternalValidatorSigner),
 }
 
 /// Validator signer that is used to sign blocks and approvals.
@@ -22,6 +24,7 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(signer) => signer.validator_id(),
             ValidatorSigner::InMemory(signer) => signer.validator_id(),
+            ValidatorSigner::External(signer) => signer.validator_id(),
         }
     }
 
@@ -30,30 +33,36 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(signer) => signer.public_key(),
             ValidatorSigner::InMemory(signer) => signer.public_key(),
+            ValidatorSigner::External(signer) => signer.backend.public_key(),
         }
     }
 
-    pub fn sign_bytes(&self, data: &[u8]) -> Signature {
+    /// Signs `data`. Only a signer which holds its key outside of the node
+    /// can fail, in which case nothing must be sent instead of the signed
+    /// message.
+    pub fn sign_bytes(&self, data: &[u8]) -> Result<Signature, SignerError> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.noop_signature(),
-            ValidatorSigner::InMemory(signer) => signer.sign_bytes(data),
+            ValidatorSigner::Empty(signer) => Ok(signer.noop_signature()),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_bytes(data)),
+            ValidatorSigner::External(signer) => signer.backend.sign_bytes(data),
         }
     }
 
     pub fn compute_vrf_with_proof(
         &self,
         data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), SignerError> {
         match self {
             ValidatorSigner::Empty(_) => unimplemented!(),
-            ValidatorSigner::InMemory(signer) => signer.compute_vrf_with_proof(data),
+            ValidatorSigner::InMemory(signer) => Ok(signer.compute_vrf_with_proof(data)),
+            ValidatorSigner::External(signer) => signer.backend.compute_vrf_with_proof(data),
         }
     }
 
     /// Used by test infrastructure, only implement if make sense for testing otherwise raise `unimplemented`.
     pub fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
         match self {
-            ValidatorSigner::Empty(_) => unimplemented!(),
+            ValidatorSigner::Empty(_) | ValidatorSigner::External(_) => unimplemented!(),
             ValidatorSigner::InMemory(signer) => signer.write_to_file(path),
         }
     }
@@ -146,3 +155,57 @@ impl InMemoryValidatorSigner {
         self.signer.write_to_file(path)
     }
 }
+
+/// Error of a validator signer which can't sign the data.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum SignerError {
+    /// The signer refused to sign, e.g. because of its double-sign protection.
+    #[error("signer refused to sign: {0}")]
+    Refused(String),
+    /// The signer couldn't be reached or returned an invalid result.
+    #[error("signer is unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// Interface of keys which are held outside of the node, e.g. in an HSM or by
+/// a remote signer. Implementations are wrapped in `ExternalValidatorSigner`
+/// and used wherever a `ValidatorSigner` is.
+pub trait ValidatorSigningBackend: Send + Sync + Debug {
+    fn public_key(&self) -> PublicKey;
+
+    fn sign_bytes(&self, data: &[u8]) -> Result<Signature, SignerError>;
+
+    fn compute_vrf_with_proof(
+        &self,
+        data: &[u8],
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), SignerError>;
+}
+
+/// Signer that delegates signing to a `ValidatorSigningBackend`.
+#[derive(Clone, Debug)]
+pub struct ExternalValidatorSigner {
+    account_id: AccountId,
+    backend: Arc<dyn ValidatorSigningBackend>,
+}
+
+impl ExternalValidatorSigner {
+    pub fn new(
+        account_id: AccountId,
+        backend: Arc<dyn ValidatorSigningBackend>,
+    ) -> ValidatorSigner {
+        ValidatorSigner::External(Self { account_id, backend })
+    }
+
+    pub fn validator_id(&self) -> &AccountId {
+        &self.account_id
+    }
+}
+
+/// Signers are equal if they sign for the same validator with the same key,
+/// which is what matters when the validator key is reloaded.
+impl PartialEq for ExternalValidatorSigner {
+    fn eq(&self, other: &Self) -> bool {
+        self.account_id == other.account_id
+            && self.backend.public_key() == other.backend.public_key()
+    }
+}
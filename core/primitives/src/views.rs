@@ -18,6 +18,7 @@ use crate::errors::TxExecutionError;
 use crate::hash::{CryptoHash, hash};
 use crate::merkle::{MerklePath, combine_hash};
 use crate::network::PeerId;
+use crate::profile_data_v3::ProfileDataV3;
 use crate::receipt::{
     ActionReceipt, DataReceipt, DataReceiver, GlobalContractDistributionReceipt, Receipt,
     ReceiptEnum, ReceiptV1,
@@ -62,10 +63,13 @@ use validator_stake_view::ValidatorStakeView;
 
 /// A view of the account
 #[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AccountView {
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub amount: Balance,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub locked: Balance,
     pub code_hash: CryptoHash,
     pub storage_usage: StorageUsage,
@@ -81,9 +85,11 @@ pub struct AccountView {
 /// A view of the contract code.
 #[serde_as]
 #[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ContractCodeView {
     #[serde(rename = "code_base64")]
     #[serde_as(as = "Base64")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub code: Vec<u8>,
     pub hash: CryptoHash,
 }
@@ -143,9 +149,11 @@ impl From<AccountView> for Account {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum AccessKeyPermissionView {
     FunctionCall {
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
         allowance: Option<Balance>,
         receiver_id: String,
         method_names: Vec<String>,
@@ -191,6 +199,7 @@ impl From<AccessKeyPermissionView> for AccessKeyPermission {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AccessKeyView {
     pub nonce: Nonce,
     pub permission: AccessKeyPermissionView,
@@ -210,6 +219,7 @@ impl From<AccessKeyView> for AccessKey {
 
 /// Item of the state, key and value are serialized in base64 and proof for inclusion of given state item.
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StateItem {
     pub key: StoreKey,
     pub value: StoreValue,
@@ -217,32 +227,78 @@ pub struct StateItem {
 
 #[serde_as]
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ViewStateResult {
     pub values: Vec<StateItem>,
     #[serde_as(as = "Vec<Base64>")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proof: Vec<Arc<[u8]>>,
+}
+
+/// A page of the contract state, see [`QueryRequest::ViewStatePaged`].
+#[serde_as]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ViewStatePagedResult {
+    pub values: Vec<StateItem>,
+    /// The `start_key_base64` of the next page, null on the last page.
+    /// Always present, which tells this result apart from `ViewStateResult`.
+    #[serde(
+        rename = "next_key_base64",
+        deserialize_with = "<Option<StoreKey> as serde::Deserialize>::deserialize"
+    )]
+    pub next_key: Option<StoreKey>,
+    #[serde_as(as = "Vec<Base64>")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub proof: Vec<Arc<[u8]>>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CallResult {
     pub result: Vec<u8>,
     pub logs: Vec<String>,
 }
 
+/// Result of the dry run of a transaction. Nothing is included in a block, so
+/// the outcomes have no proof and refer to the block of the state.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DryRunResultView {
+    pub transaction_outcome: ExecutionOutcomeWithIdView,
+    /// Outcomes of the receipts executed, in the order of execution.
+    pub receipts_outcome: Vec<ExecutionOutcomeWithIdView>,
+    /// Receipts which weren't executed: the receipts for other shards, the
+    /// ones still waiting for data, and the yielded and resumed promises.
+    pub receipts_not_executed: Vec<ReceiptView>,
+    /// Gas burnt by the transaction and the receipts executed.
+    pub gas_burnt: Gas,
+    /// Tokens burnt by the transaction and the receipts executed.
+    #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub tokens_burnt: Balance,
+    /// Changes of the state made by the transaction and the receipts executed.
+    pub state_changes: StateChangesView,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct QueryError {
     pub error: String,
     pub logs: Vec<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AccessKeyInfoView {
     pub public_key: PublicKey,
     pub access_key: AccessKeyView,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AccessKeyList {
     pub keys: Vec<AccessKeyInfoView>,
 }
@@ -256,6 +312,7 @@ impl FromIterator<AccessKeyInfoView> for AccessKeyList {
 // cspell:words deepsize
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct KnownPeerStateView {
     pub peer_id: PeerId,
     pub status: String,
@@ -267,6 +324,7 @@ pub struct KnownPeerStateView {
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ConnectionInfoView {
     pub peer_id: PeerId,
     pub addr: String,
@@ -276,6 +334,7 @@ pub struct ConnectionInfoView {
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SnapshotHostInfoView {
     pub peer_id: PeerId,
     pub sync_hash: CryptoHash,
@@ -289,12 +348,15 @@ pub enum QueryResponseKind {
     ViewAccount(AccountView),
     ViewCode(ContractCodeView),
     ViewState(ViewStateResult),
+    ViewStatePaged(ViewStatePagedResult),
     CallResult(CallResult),
     AccessKey(AccessKeyView),
     AccessKeyList(AccessKeyList),
+    DryRunResult(DryRunResultView),
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "request_type", rename_all = "snake_case")]
 pub enum QueryRequest {
     ViewAccount {
@@ -310,6 +372,23 @@ pub enum QueryRequest {
         #[serde(default, skip_serializing_if = "is_false")]
         include_proof: bool,
     },
+    /// Like `ViewState`, but returns at most `limit` items starting at
+    /// `start_key`, and the key to start the next page at. The items are read
+    /// from flat storage when possible, and the size of the state of the
+    /// contract isn't limited. Each page is limited by the node's
+    /// `trie_viewer_state_size_limit` instead, but has at least one item.
+    ViewStatePaged {
+        account_id: AccountId,
+        #[serde(rename = "prefix_base64")]
+        prefix: StoreKey,
+        /// Key of the first item of the page, relative to the contract data
+        /// like the keys returned.
+        #[serde(rename = "start_key_base64", default, skip_serializing_if = "Option::is_none")]
+        start_key: Option<StoreKey>,
+        limit: std::num::NonZeroU32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        include_proof: bool,
+    },
     ViewAccessKey {
         account_id: AccountId,
         public_key: PublicKey,
@@ -323,6 +402,36 @@ pub enum QueryRequest {
         #[serde(rename = "args_base64")]
         args: FunctionArgs,
     },
+    /// Simulates the transaction on top of the state of the block, without
+    /// changing the state. The transaction and the receipts it produces for
+    /// the shard of the signer are executed, the other receipts are returned.
+    DryRunTransaction {
+        #[serde(rename = "signed_tx_base64")]
+        signed_transaction: SignedTransaction,
+        /// Doesn't verify the signature, to simulate the transaction before it
+        /// is signed.
+        #[serde(default, skip_serializing_if = "is_false")]
+        skip_signature_check: bool,
+        /// Changes of the state made before the transaction is executed.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        state_overrides: Vec<StateOverrideView>,
+    },
+}
+
+/// A change of the state made for the dry run of a transaction only.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateOverrideView {
+    /// Sets the balance of the account, which is created if it doesn't exist.
+    AccountBalance {
+        account_id: AccountId,
+        #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        amount: Balance,
+    },
+    /// Adds the access key to the account, or replaces it.
+    AccessKey { account_id: AccountId, public_key: PublicKey, access_key: AccessKeyView },
 }
 
 fn is_false(v: &bool) -> bool {
@@ -337,16 +446,19 @@ pub struct QueryResponse {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StatusSyncInfo {
     pub latest_block_hash: CryptoHash,
     pub latest_block_height: BlockHeight,
     pub latest_state_root: CryptoHash,
     #[serde(with = "near_time::serde_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub latest_block_time: Utc,
     pub syncing: bool,
     pub earliest_block_hash: Option<CryptoHash>,
     pub earliest_block_height: Option<BlockHeight>,
     #[serde(with = "near_time::serde_opt_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
     pub earliest_block_time: Option<Utc>,
     pub epoch_id: Option<EpochId>,
     pub epoch_start_height: Option<BlockHeight>,
@@ -354,11 +466,13 @@ pub struct StatusSyncInfo {
 
 // TODO: add more information to ValidatorInfo
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ValidatorInfo {
     pub account_id: AccountId,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PeerInfoView {
     pub addr: String,
     pub account_id: Option<AccountId>,
@@ -381,6 +495,7 @@ pub struct PeerInfoView {
 /// Information about a Producer: its account name, peer_id and a list of connected peers that
 /// the node can use to send message for this producer.
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct KnownProducerView {
     pub account_id: AccountId,
     pub peer_id: PublicKey,
@@ -388,21 +503,25 @@ pub struct KnownProducerView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Tier1ProxyView {
     pub addr: std::net::SocketAddr,
     pub peer_id: PublicKey,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AccountDataView {
     pub peer_id: PublicKey,
     pub proxies: Vec<Tier1ProxyView>,
     pub account_key: PublicKey,
     #[serde(with = "near_time::serde_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub timestamp: Utc,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct NetworkInfoView {
     pub peer_max_count: u32,
     pub num_connected_peers: usize,
@@ -414,6 +533,7 @@ pub struct NetworkInfoView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum SyncStatusView {
     /// Initial state. Not enough peers to do anything yet.
     AwaitingPeers,
@@ -445,6 +565,7 @@ pub enum SyncStatusView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StateSyncStatusView {
     pub sync_hash: CryptoHash,
     pub shard_sync_status: HashMap<ShardId, String>,
@@ -453,21 +574,25 @@ pub struct StateSyncStatusView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PeerStoreView {
     pub peer_states: Vec<KnownPeerStateView>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RecentOutboundConnectionsView {
     pub recent_outbound_connections: Vec<ConnectionInfoView>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SnapshotHostsView {
     pub hosts: Vec<SnapshotHostInfoView>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct EdgeView {
     pub peer0: PeerId,
     pub peer1: PeerId,
@@ -475,12 +600,14 @@ pub struct EdgeView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct NetworkGraphView {
     pub edges: Vec<EdgeView>,
     pub next_hops: HashMap<PeerId, Vec<PeerId>>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LabeledEdgeView {
     pub peer0: u32,
     pub peer1: u32,
@@ -488,18 +615,21 @@ pub struct LabeledEdgeView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct EdgeCacheView {
     pub peer_labels: HashMap<PeerId, u32>,
     pub spanning_trees: HashMap<u32, Vec<LabeledEdgeView>>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PeerDistancesView {
     pub distance: Vec<Option<u32>>,
     pub min_nonce: u64,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct NetworkRoutesView {
     pub edge_cache: EdgeCacheView,
     pub local_edges: HashMap<PeerId, EdgeView>,
@@ -508,18 +638,21 @@ pub struct NetworkRoutesView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ShardSyncDownloadView {
     pub downloads: Vec<DownloadStatusView>,
     pub status: String,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DownloadStatusView {
     pub error: bool,
     pub done: bool,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CatchupStatusView {
     // This is the first block of the epoch that we are catching up
     pub sync_block_hash: CryptoHash,
@@ -531,6 +664,7 @@ pub struct CatchupStatusView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RequestedStatePartsView {
     // This is the first block of the epoch that was requested
     pub block_hash: CryptoHash,
@@ -539,6 +673,7 @@ pub struct RequestedStatePartsView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BlockStatusView {
     pub height: BlockHeight,
     pub hash: CryptoHash,
@@ -557,6 +692,7 @@ impl From<Tip> for BlockStatusView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PartElapsedTimeView {
     pub part_id: u64,
     pub elapsed_ms: u128,
@@ -569,6 +705,7 @@ impl PartElapsedTimeView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BlockByChunksView {
     pub height: BlockHeight,
     pub hash: CryptoHash,
@@ -577,6 +714,7 @@ pub struct BlockByChunksView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ChainProcessingInfo {
     pub num_blocks_in_processing: usize,
     pub num_orphans: usize,
@@ -588,10 +726,12 @@ pub struct ChainProcessingInfo {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BlockProcessingInfo {
     pub height: BlockHeight,
     pub hash: CryptoHash,
     #[serde(with = "near_time::serde_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub received_timestamp: Utc,
     /// Time (in ms) between when the block was first received and when it was processed
     pub in_progress_ms: u128,
@@ -619,6 +759,7 @@ pub struct BlockProcessingInfo {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum BlockProcessingStatus {
     Orphan,
     WaitingForChunks,
@@ -639,6 +780,7 @@ pub enum BlockProcessingStatus {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum DroppedReason {
     // If the node has already processed a block at this height
     HeightProcessed,
@@ -647,6 +789,7 @@ pub enum DroppedReason {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ChunkProcessingInfo {
     pub height_created: BlockHeight,
     pub shard_id: ShardId,
@@ -658,9 +801,11 @@ pub struct ChunkProcessingInfo {
     pub status: ChunkProcessingStatus,
     /// Timestamp of first time when we request for this chunk.
     #[serde(with = "near_time::serde_opt_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
     pub requested_timestamp: Option<Utc>,
     /// Timestamp of when the chunk is complete
     #[serde(with = "near_time::serde_opt_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
     pub completed_timestamp: Option<Utc>,
     /// Time (in millis) that it takes between when the chunk is requested and when it is completed.
     pub request_duration: Option<u64>,
@@ -668,20 +813,25 @@ pub struct ChunkProcessingInfo {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PartCollectionInfo {
     pub part_owner: AccountId,
     // Time when the part is received through any message
     #[serde(with = "near_time::serde_opt_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
     pub received_time: Option<Utc>,
     // Time when we receive a PartialEncodedChunkForward containing this part
     #[serde(with = "near_time::serde_opt_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
     pub forwarded_received_time: Option<Utc>,
     // Time when we receive the PartialEncodedChunk message containing this part
     #[serde(with = "near_time::serde_opt_utc_as_iso")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
     pub chunk_received_time: Option<Utc>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ChunkProcessingStatus {
     NeedToRequest,
     Requested,
@@ -689,6 +839,7 @@ pub enum ChunkProcessingStatus {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DetailedDebugStatus {
     pub network_info: NetworkInfoView,
     pub sync_status: String,
@@ -698,8 +849,33 @@ pub struct DetailedDebugStatus {
     pub block_production_delay_millis: u64,
 }
 
+/// Whether the binary supports the protocol versions of the network, now and
+/// after the upgrades decided or being voted for.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ProtocolReadinessView {
+    /// Oldest protocol version supported by the binary.
+    pub min_supported_protocol_version: ProtocolVersion,
+    /// Latest protocol version supported by the binary.
+    pub max_supported_protocol_version: ProtocolVersion,
+    /// Protocol version of the current epoch.
+    pub protocol_version: ProtocolVersion,
+    /// Protocol version of the next epoch, decided by the previous voting.
+    pub next_epoch_protocol_version: ProtocolVersion,
+    /// Protocol version of the epoch after the next one if the current epoch
+    /// ended with the votes cast so far.
+    pub projected_protocol_version: ProtocolVersion,
+    /// Whether the binary supports all the protocol versions above.
+    pub is_ready: bool,
+    /// Estimated height of the first block of the first epoch with a protocol
+    /// version the binary doesn't support, from which the node can't follow
+    /// the chain anymore. Only set if the binary isn't ready.
+    pub deadline_height: Option<BlockHeight>,
+}
+
 // TODO: add more information to status.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StatusResponse {
     /// Binary version.
     pub version: Version,
@@ -731,9 +907,14 @@ pub struct StatusResponse {
     /// Information about last blocks, network, epoch and chain & chunk info.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detailed_debug_status: Option<DetailedDebugStatus>,
+    /// Whether the binary supports the protocol versions the network upgrades
+    /// to. Not known until the node checked it after the start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_readiness: Option<ProtocolReadinessView>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BlockHeaderView {
     pub height: BlockHeight,
     pub prev_height: Option<BlockHeight>,
@@ -752,20 +933,25 @@ pub struct BlockHeaderView {
     /// Legacy json number. Should not be used.
     pub timestamp: u64,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub timestamp_nanosec: u64,
     pub random_value: CryptoHash,
     pub validator_proposals: Vec<ValidatorStakeView>,
     pub chunk_mask: Vec<bool>,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub gas_price: Balance,
     pub block_ordinal: Option<NumBlocks>,
     /// TODO(2271): deprecated.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub rent_paid: Balance,
     /// TODO(2271): deprecated.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub validator_reward: Balance,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub total_supply: Balance,
     // Deprecated
     pub challenges_result: Vec<SlashedValidator>,
@@ -870,6 +1056,7 @@ impl From<BlockHeaderView> for BlockHeader {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BlockHeaderInnerLiteView {
     pub height: BlockHeight,
     pub epoch_id: CryptoHash,
@@ -879,6 +1066,7 @@ pub struct BlockHeaderInnerLiteView {
     /// Legacy json number. Should not be used.
     pub timestamp: u64,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub timestamp_nanosec: u64,
     pub next_bp_hash: CryptoHash,
     pub block_merkle_root: CryptoHash,
@@ -917,6 +1105,7 @@ impl From<BlockHeaderInnerLiteView> for BlockHeaderInnerLite {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ChunkHeaderView {
     pub chunk_hash: CryptoHash,
     pub prev_block_hash: CryptoHash,
@@ -931,11 +1120,14 @@ pub struct ChunkHeaderView {
     pub gas_limit: Gas,
     /// TODO(2271): deprecated.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub rent_paid: Balance,
     /// TODO(2271): deprecated.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub validator_reward: Balance,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub balance_burnt: Balance,
     pub outgoing_receipts_root: CryptoHash,
     pub tx_root: CryptoHash,
@@ -1077,6 +1269,7 @@ impl From<ChunkHeaderView> for ShardChunkHeader {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BlockView {
     pub author: AccountId,
     pub header: BlockHeaderView,
@@ -1094,6 +1287,7 @@ impl BlockView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ChunkView {
     pub author: AccountId,
     pub header: ChunkHeaderView,
@@ -1131,10 +1325,12 @@ impl ChunkView {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ActionView {
     CreateAccount,
     DeployContract {
         #[serde_as(as = "Base64")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         code: Vec<u8>,
     },
     FunctionCall {
@@ -1142,14 +1338,17 @@ pub enum ActionView {
         args: FunctionArgs,
         gas: Gas,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         deposit: Balance,
     },
     Transfer {
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         deposit: Balance,
     },
     Stake {
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         stake: Balance,
         public_key: PublicKey,
     },
@@ -1169,10 +1368,12 @@ pub enum ActionView {
     },
     DeployGlobalContract {
         #[serde_as(as = "Base64")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         code: Vec<u8>,
     },
     DeployGlobalContractByAccountId {
         #[serde_as(as = "Base64")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         code: Vec<u8>,
     },
     UseGlobalContract {
@@ -1303,6 +1504,7 @@ impl TryFrom<ActionView> for Action {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SignedTransactionView {
     pub signer_id: AccountId,
     pub public_key: PublicKey,
@@ -1347,6 +1549,7 @@ impl From<SignedTransaction> for SignedTransactionView {
     Clone,
     Default,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum FinalExecutionStatus {
     /// The execution has not yet started.
     #[default]
@@ -1356,7 +1559,11 @@ pub enum FinalExecutionStatus {
     /// The execution has failed with the given error.
     Failure(TxExecutionError),
     /// The execution has succeeded and returned some value or an empty vec encoded in base64.
-    SuccessValue(#[serde_as(as = "Base64")] Vec<u8>),
+    SuccessValue(
+        #[serde_as(as = "Base64")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        Vec<u8>,
+    ),
 }
 
 impl fmt::Debug for FinalExecutionStatus {
@@ -1382,6 +1589,7 @@ impl fmt::Debug for FinalExecutionStatus {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ServerError {
     TxExecutionError(TxExecutionError),
     Timeout,
@@ -1392,13 +1600,18 @@ pub enum ServerError {
 #[derive(
     BorshSerialize, BorshDeserialize, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ExecutionStatusView {
     /// The execution is pending or unknown.
     Unknown,
     /// The execution has failed.
     Failure(TxExecutionError),
     /// The final action succeeded and returned some value or an empty vec encoded in base64.
-    SuccessValue(#[serde_as(as = "Base64")] Vec<u8>),
+    SuccessValue(
+        #[serde_as(as = "Base64")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        Vec<u8>,
+    ),
     /// The final action of the receipt returned a promise or the signed transaction was converted
     /// to a receipt. Contains the receipt_id of the generated receipt.
     SuccessReceiptId(CryptoHash),
@@ -1442,10 +1655,12 @@ impl From<ExecutionStatus> for ExecutionStatusView {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CostGasUsed {
     pub cost_category: String,
     pub cost: String,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub gas_used: Gas,
 }
 
@@ -1459,9 +1674,14 @@ pub struct CostGasUsed {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ExecutionMetadataView {
     pub version: u32,
     pub gas_profile: Option<Vec<CostGasUsed>>,
+    /// Size of the storage proof recorded by the execution, in bytes. Only
+    /// available from version 4.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_proof_size: Option<u64>,
 }
 
 impl Default for ExecutionMetadataView {
@@ -1476,6 +1696,11 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
             ExecutionMetadata::V1 => 1,
             ExecutionMetadata::V2(_) => 2,
             ExecutionMetadata::V3(_) => 3,
+            ExecutionMetadata::V4(_) => 4,
+        };
+        let storage_proof_size = match &metadata {
+            ExecutionMetadata::V4(metadata) => Some(metadata.storage_proof_size),
+            _ => None,
         };
         let mut gas_profile = match metadata {
             ExecutionMetadata::V1 => None,
@@ -1508,43 +1733,8 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
 
                 Some(costs)
             }
-            ExecutionMetadata::V3(profile) => {
-                // Add actions, wasm op, and ext costs in groups.
-                // actions costs are 1-to-1
-                let mut costs: Vec<CostGasUsed> = ActionCosts::iter()
-                    .filter_map(|cost| {
-                        let gas_used = profile.get_action_cost(cost);
-                        (gas_used > 0).then(|| {
-                            CostGasUsed::action(
-                                format!("{:?}", cost).to_ascii_uppercase(),
-                                gas_used,
-                            )
-                        })
-                    })
-                    .collect();
-
-                // wasm op is a single cost, for historical reasons it is inaccurately displayed as "wasm host"
-                let wasm_gas_used = profile.get_wasm_cost();
-                if wasm_gas_used > 0 {
-                    costs.push(CostGasUsed::wasm_host(
-                        "WASM_INSTRUCTION".to_string(),
-                        wasm_gas_used,
-                    ));
-                }
-
-                // ext costs are 1-to-1
-                for ext_cost in ExtCosts::iter() {
-                    let gas_used = profile.get_ext_cost(ext_cost);
-                    if gas_used > 0 {
-                        costs.push(CostGasUsed::wasm_host(
-                            format!("{:?}", ext_cost).to_ascii_uppercase(),
-                            gas_used,
-                        ));
-                    }
-                }
-
-                Some(costs)
-            }
+            ExecutionMetadata::V3(profile) => Some(profile_v3_costs(&profile)),
+            ExecutionMetadata::V4(metadata) => metadata.profile.as_ref().map(profile_v3_costs),
         };
         if let Some(ref mut costs) = gas_profile {
             // The order doesn't really matter, but the default one is just
@@ -1557,10 +1747,41 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
                 lhs.cost_category.cmp(&rhs.cost_category).then_with(|| lhs.cost.cmp(&rhs.cost))
             });
         }
-        ExecutionMetadataView { version, gas_profile }
+        ExecutionMetadataView { version, gas_profile, storage_proof_size }
     }
 }
 
+fn profile_v3_costs(profile: &ProfileDataV3) -> Vec<CostGasUsed> {
+    // Add actions, wasm op, and ext costs in groups.
+    // actions costs are 1-to-1
+    let mut costs: Vec<CostGasUsed> = ActionCosts::iter()
+        .filter_map(|cost| {
+            let gas_used = profile.get_action_cost(cost);
+            (gas_used > 0)
+                .then(|| CostGasUsed::action(format!("{:?}", cost).to_ascii_uppercase(), gas_used))
+        })
+        .collect();
+
+    // wasm op is a single cost, for historical reasons it is inaccurately displayed as "wasm host"
+    let wasm_gas_used = profile.get_wasm_cost();
+    if wasm_gas_used > 0 {
+        costs.push(CostGasUsed::wasm_host("WASM_INSTRUCTION".to_string(), wasm_gas_used));
+    }
+
+    // ext costs are 1-to-1
+    for ext_cost in ExtCosts::iter() {
+        let gas_used = profile.get_ext_cost(ext_cost);
+        if gas_used > 0 {
+            costs.push(CostGasUsed::wasm_host(
+                format!("{:?}", ext_cost).to_ascii_uppercase(),
+                gas_used,
+            ));
+        }
+    }
+
+    costs
+}
+
 impl CostGasUsed {
     pub fn action(cost: String, gas_used: Gas) -> Self {
         Self { cost_category: "ACTION_COST".to_string(), cost, gas_used }
@@ -1581,6 +1802,7 @@ impl CostGasUsed {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ExecutionOutcomeView {
     /// Logs from this transaction or receipt.
     pub logs: Vec<String>,
@@ -1594,6 +1816,7 @@ pub struct ExecutionOutcomeView {
     /// `tokens_burnt` also contains the penalty subtracted from refunds, while
     /// `gas_burnt` only contains the gas that we actually burn for the execution.
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub tokens_burnt: Balance,
     /// The id of the account on which the execution happens. For transaction this is signer_id,
     /// for receipt this is receiver_id.
@@ -1665,6 +1888,7 @@ impl ExecutionOutcomeView {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ExecutionOutcomeWithIdView {
     pub proof: MerklePath,
     pub block_hash: CryptoHash,
@@ -1705,6 +1929,7 @@ pub struct TxStatusView {
     Eq,
     PartialEq,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum TxExecutionStatus {
     /// Transaction is waiting to be included into the block
@@ -1728,6 +1953,7 @@ pub enum TxExecutionStatus {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 // FinalExecutionOutcomeWithReceipt is a superset of FinalExecutionOutcome that includes additional information about receipts.
 // For proper deserialization we need to have more specific variant first.
@@ -1761,6 +1987,7 @@ impl TxStatusView {
 #[derive(
     BorshSerialize, BorshDeserialize, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FinalExecutionOutcomeView {
     /// Execution status defined by chain.rs:get_final_transaction_result
     /// FinalExecutionStatus::NotStarted - the tx is not converted to the receipt yet
@@ -1799,6 +2026,7 @@ impl fmt::Debug for FinalExecutionOutcomeView {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FinalExecutionOutcomeWithReceiptView {
     /// Final outcome view without receipts
     #[serde(flatten)]
@@ -1817,6 +2045,7 @@ pub mod validator_stake_view {
     #[derive(
         BorshSerialize, BorshDeserialize, serde::Serialize, Deserialize, Debug, Clone, Eq, PartialEq,
     )]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
     #[serde(tag = "validator_stake_struct_version")]
     pub enum ValidatorStakeView {
         V1(ValidatorStakeViewV1),
@@ -1873,10 +2102,12 @@ pub mod validator_stake_view {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ValidatorStakeViewV1 {
     pub account_id: AccountId,
     pub public_key: PublicKey,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub stake: Balance,
 }
 
@@ -1890,6 +2121,7 @@ pub struct ValidatorStakeViewV1 {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ReceiptView {
     pub predecessor_id: AccountId,
     pub receiver_id: AccountId,
@@ -1913,6 +2145,7 @@ pub struct ReceiptView {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DataReceiverView {
     pub data_id: CryptoHash,
     pub receiver_id: AccountId,
@@ -1929,11 +2162,13 @@ pub struct DataReceiverView {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ReceiptEnumView {
     Action {
         signer_id: AccountId,
         signer_public_key: PublicKey,
         #[serde(with = "dec_format")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         gas_price: Balance,
         output_data_receivers: Vec<DataReceiverView>,
         input_data_ids: Vec<CryptoHash>,
@@ -1944,6 +2179,7 @@ pub enum ReceiptEnumView {
     Data {
         data_id: CryptoHash,
         #[serde_as(as = "Option<Base64>")]
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
         data: Option<Vec<u8>>,
         #[serde(default = "default_is_promise")]
         is_promise_resume: bool,
@@ -1953,6 +2189,7 @@ pub enum ReceiptEnumView {
         target_shard: ShardId,
         already_delivered_shards: Vec<ShardId>,
         #[serde_as(as = "Base64")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         code: Vec<u8>,
     },
 }
@@ -2090,6 +2327,7 @@ impl TryFrom<ReceiptView> for Receipt {
 
 /// Information about this epoch validators and next epoch validators
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, ProtocolSchema)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct EpochValidatorInfo {
     /// Validators for the current epoch
     pub current_validators: Vec<CurrentEpochValidatorInfo>,
@@ -2120,17 +2358,20 @@ pub struct EpochValidatorInfo {
     serde::Deserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ValidatorKickoutView {
     pub account_id: AccountId,
     pub reason: ValidatorKickoutReason,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, ProtocolSchema)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CurrentEpochValidatorInfo {
     pub account_id: AccountId,
     pub public_key: PublicKey,
     pub is_slashed: bool,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub stake: Balance,
     /// Shards this validator is assigned to as chunk producer in the current epoch.
     #[serde(rename = "shards")]
@@ -2160,6 +2401,23 @@ pub struct CurrentEpochValidatorInfo {
     pub num_expected_endorsements_per_shard: Vec<NumBlocks>,
     /// Shards this validator is assigned to as chunk validator in the current epoch.
     pub shards_endorsed: Vec<ShardId>,
+    /// Delegations to the staking pool of the validator at the start of the
+    /// epoch. Only set if `validator_delegation_info` is enabled in the config
+    /// of the node and the validator is a known staking pool contract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegation: Option<ValidatorDelegationView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, ProtocolSchema)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ValidatorDelegationView {
+    /// Number of accounts with a non-zero stake in the pool, including the
+    /// owner of the pool if it staked.
+    pub num_delegators: u64,
+    /// Total stake of these accounts.
+    #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub total_delegated: Balance,
 }
 
 #[derive(
@@ -2173,14 +2431,135 @@ pub struct CurrentEpochValidatorInfo {
     serde::Deserialize,
     ProtocolSchema,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct NextEpochValidatorInfo {
     pub account_id: AccountId,
     pub public_key: PublicKey,
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub stake: Balance,
     pub shards: Vec<ShardId>,
 }
 
+/// Protocol version voting of block producers in an epoch, as seen at a given
+/// block.  Votes cast during epoch T decide the protocol version of epoch T+2.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ProtocolVersionVotesView {
+    pub epoch_id: EpochId,
+    pub epoch_height: EpochHeight,
+    /// Protocol version of the epoch the votes are cast in.
+    pub protocol_version: ProtocolVersion,
+    /// Protocol version of the next epoch, decided by the previous voting.
+    pub next_epoch_protocol_version: ProtocolVersion,
+    /// Protocol version of the epoch after the next one if the epoch ended
+    /// with the votes cast so far.
+    pub projected_protocol_version: ProtocolVersion,
+    /// Total stake of the block producers of the epoch.
+    #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub total_stake: Balance,
+    /// Stake a version needs to exceed to be adopted.
+    #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub threshold_stake: Balance,
+    /// Stake voting for each version, highest version first.
+    pub versions: Vec<ProtocolVersionVoteView>,
+    /// Vote of each block producer of the epoch.
+    pub validators: Vec<ValidatorProtocolVersionVoteView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ProtocolVersionVoteView {
+    pub protocol_version: ProtocolVersion,
+    #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub stake: Balance,
+    pub num_validators: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ValidatorProtocolVersionVoteView {
+    pub account_id: AccountId,
+    #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub stake: Balance,
+    /// Version from the latest block produced by the validator in the epoch
+    /// or `None` if it hasn't produced any yet.
+    pub protocol_version: Option<ProtocolVersion>,
+}
+
+/// Duties of the node's own validator account in an epoch, evaluated on the
+/// final chain.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct EpochValidatorDutiesView {
+    pub epoch_id: EpochId,
+    pub account_id: AccountId,
+    /// Height of the last final block whose duties were evaluated.
+    pub last_evaluated_height: BlockHeight,
+    pub block_production: ValidatorDutyStatsView,
+    pub chunk_production: ValidatorDutyStatsView,
+    pub chunk_endorsement: ValidatorDutyStatsView,
+    /// Missed duties, most recent last.  Only the most recent ones are kept.
+    pub misses: Vec<MissedValidatorDutyView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ValidatorDutyStatsView {
+    pub expected: u64,
+    pub fulfilled: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MissedValidatorDutyView {
+    pub kind: ValidatorDutyKind,
+    /// Height of the block (for block production) or of the block which
+    /// should have included the chunk.
+    pub height: BlockHeight,
+    pub shard_id: Option<ShardId>,
+    pub reason: MissedValidatorDutyReason,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorDutyKind {
+    BlockProduction,
+    ChunkProduction,
+    ChunkEndorsement,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MissedValidatorDutyReason {
+    /// The node didn't produce the block or chunk at all.
+    NotProduced,
+    /// The block was produced but didn't end up on the final chain.
+    Orphaned,
+    /// The chunk was produced but not included, e.g. because the block
+    /// producer was missing its parts or endorsements.
+    NotIncluded,
+    /// The state witness for the chunk never arrived.
+    WitnessMissing,
+    /// The state witness arrived after the block including the chunk was
+    /// produced.
+    WitnessLate,
+    /// Validating the chunk didn't finish before the block including it was
+    /// produced.
+    ApplySlow,
+    /// Validating the chunk failed.
+    ValidationFailed,
+    /// The endorsement was sent in time but the block producer didn't include
+    /// it.
+    EndorsementNotIncluded,
+}
+
 #[derive(
     PartialEq,
     Eq,
@@ -2191,6 +2570,7 @@ pub struct NextEpochValidatorInfo {
     serde::Serialize,
     serde::Deserialize,
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LightClientBlockView {
     pub prev_block_hash: CryptoHash,
     pub next_block_inner_hash: CryptoHash,
@@ -2201,6 +2581,7 @@ pub struct LightClientBlockView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, BorshDeserialize, BorshSerialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LightClientBlockLiteView {
     pub prev_block_hash: CryptoHash,
     pub inner_rest_hash: CryptoHash,
@@ -2230,8 +2611,10 @@ impl LightClientBlockLiteView {
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct GasPriceView {
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub gas_price: Balance,
 }
 
@@ -2240,6 +2623,7 @@ pub struct GasPriceView {
 /// [serializable view]: ./index.html
 /// [`StateChangesRequest`]: ../types/struct.StateChangesRequest.html
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "changes_type", rename_all = "snake_case")]
 pub enum StateChangesRequestView {
     AccountChanges {
@@ -2288,6 +2672,7 @@ impl From<StateChangesRequestView> for StateChangesRequest {
 /// [serializable view]: ./index.html
 /// [`StateChangeKind`]: ../types/struct.StateChangeKind.html
 #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum StateChangeKindView {
     AccountTouched { account_id: AccountId },
@@ -2315,6 +2700,7 @@ pub type StateChangesKindsView = Vec<StateChangeKindView>;
 
 /// See crate::types::StateChangeCause for details.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum StateChangeCauseView {
     NotWritableToDisk,
@@ -2360,6 +2746,7 @@ impl From<StateChangeCause> for StateChangeCauseView {
 
 #[serde_as]
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case", tag = "type", content = "change")]
 pub enum StateChangeValueView {
     AccountUpdate {
@@ -2395,6 +2782,7 @@ pub enum StateChangeValueView {
         account_id: AccountId,
         #[serde(rename = "code_base64")]
         #[serde_as(as = "Base64")]
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
         code: Vec<u8>,
     },
     ContractCodeDeletion {
@@ -2434,6 +2822,7 @@ impl From<StateChangeValue> for StateChangeValueView {
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StateChangeWithCauseView {
     pub cause: StateChangeCauseView,
     #[serde(flatten)]
@@ -2454,6 +2843,7 @@ pub type MaintenanceWindowsView = Vec<Range<BlockHeight>>;
 
 /// Contains the split storage information.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SplitStorageInfoView {
     pub head_height: Option<BlockHeight>,
     pub final_head_height: Option<BlockHeight>,
@@ -2462,12 +2852,156 @@ pub struct SplitStorageInfoView {
     pub hot_db_kind: Option<String>,
 }
 
+/// What the RPC handler did with a transaction it received.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TxForwardingOutcomeView {
+    /// The transaction is valid and in the pool of this node (or was there already).
+    Accepted,
+    /// The transaction was forwarded to other chunk producers.
+    Routed,
+    /// The transaction failed validation.
+    Invalid { error: String },
+    /// The node doesn't track the shard of the transaction and only checked it.
+    DoesNotTrackShard,
+    /// The transaction was dropped without being forwarded.
+    Dropped { error: Option<String> },
+}
+
+/// A chunk producer a transaction was forwarded to.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TxForwardingTargetView {
+    pub account_id: AccountId,
+    /// The lowest height at which the account produces a chunk for the shard of the
+    /// transaction, among the heights considered when forwarding.
+    pub height: BlockHeight,
+}
+
+/// A single forwarding decision of the RPC handler for a transaction.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TxForwardingRecordView {
+    /// Height of the head when the transaction was processed.
+    pub head_height: BlockHeight,
+    /// Whether the transaction was received from another node rather than submitted locally.
+    pub is_forwarded: bool,
+    pub outcome: TxForwardingOutcomeView,
+    pub forwarded_to: Vec<TxForwardingTargetView>,
+}
+
+/// Whether a transaction rebroadcast by the RPC handler made it into a chunk.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TxRoutingStateView {
+    /// The transaction is not known to be included yet and is still rebroadcast.
+    Pending,
+    /// The transaction is included in a chunk of a block processed by the node.
+    Included,
+    /// The transaction was neither seen included before the end of the rebroadcast
+    /// window nor before it expired, and is no longer rebroadcast.
+    Abandoned,
+}
+
+/// Routing status of a transaction submitted to the node and forwarded to other
+/// chunk producers.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TxRoutingStatusView {
+    pub state: TxRoutingStateView,
+    /// Height of the head when the transaction was submitted.
+    pub received_height: BlockHeight,
+    /// Height of the head when the transaction was last forwarded.
+    pub last_forwarded_height: BlockHeight,
+    /// Number of times the transaction was rebroadcast after the submission.
+    pub num_rebroadcasts: u64,
+    /// Chunk producers the transaction was last forwarded to.
+    pub forwarded_to: Vec<TxForwardingTargetView>,
+}
+
+/// A recent transaction of a signer, from the index of transactions by signer.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SignerTransactionView {
+    pub transaction_hash: CryptoHash,
+    /// The block in which the chunk including the transaction was applied.
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+}
+
+/// A transaction or receipt in the tree of receipts executed for a
+/// transaction, with the receipts it produced as its children.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ReceiptTreeView {
+    /// Hash of the transaction at the root of the tree, id of the receipt
+    /// otherwise.
+    pub id: CryptoHash,
+    pub execution: ReceiptTreeExecutionStateView,
+    /// Receipts produced by the execution.
+    pub receipts: Vec<ReceiptTreeView>,
+}
+
+/// Whether a transaction or receipt in a `ReceiptTreeView` is executed.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum ReceiptTreeExecutionStateView {
+    /// The receipt isn't executed yet.
+    Pending,
+    /// The receiver of the receipt is on a shard the node doesn't track, so
+    /// whether it's executed, and the receipts it produced, aren't known.
+    Untracked {
+        shard_id: ShardId,
+    },
+    Executed(ReceiptTreeExecutionView),
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ReceiptTreeExecutionView {
+    /// The signer of the transaction, or the receiver of the receipt.
+    pub executor_id: AccountId,
+    pub shard_id: ShardId,
+    /// The block in which the chunk executing the transaction or receipt was
+    /// applied.
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+    pub gas_burnt: Gas,
+    #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub tokens_burnt: Balance,
+    pub status: ExecutionStatusView,
+}
+
+/// Accounts looked up in a batch, as of the final block.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AccountsBatchView {
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+    /// In the order of the requested accounts.
+    pub accounts: Vec<AccountsBatchEntryView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AccountsBatchEntryView {
+    pub account_id: AccountId,
+    /// `None` if the account doesn't exist.
+    pub account: Option<AccountView>,
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CongestionInfoView {
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub delayed_receipts_gas: u128,
 
     #[serde(with = "dec_format")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub buffered_receipts_gas: u128,
 
     pub receipt_bytes: u64,
@@ -2526,7 +3060,7 @@ mod tests {
     use super::{ExecutionMetadataView, FinalExecutionOutcomeViewEnum};
     use crate::profile_data_v2::ProfileDataV2;
     use crate::profile_data_v3::ProfileDataV3;
-    use crate::transaction::ExecutionMetadata;
+    use crate::transaction::{ExecutionMetadata, ExecutionMetadataV4};
 
     /// The JSON representation used in RPC responses must not remove or rename
     /// fields, only adding fields is allowed or we risk breaking clients.
@@ -2565,6 +3099,32 @@ mod tests {
         insta::assert_json_snapshot!(view);
     }
 
+    /// `ExecutionMetadataView` with metadata V4 has the profile of V3 and the
+    /// storage proof size.
+    #[test]
+    fn test_exec_metadata_v4_view() {
+        let v3_view =
+            ExecutionMetadataView::from(ExecutionMetadata::V3(ProfileDataV3::test().into()));
+        let metadata = ExecutionMetadata::V4(Box::new(ExecutionMetadataV4 {
+            profile: Some(ProfileDataV3::test()),
+            storage_proof_size: 1234,
+        }));
+        let view = ExecutionMetadataView::from(metadata);
+        assert_eq!(view.version, 4);
+        assert_eq!(view.gas_profile, v3_view.gas_profile);
+        assert_eq!(view.storage_proof_size, Some(1234));
+        assert!(v3_view.storage_proof_size.is_none());
+
+        let tx_metadata = ExecutionMetadata::V4(Box::new(ExecutionMetadataV4 {
+            profile: None,
+            storage_proof_size: 100,
+        }));
+        let view = ExecutionMetadataView::from(tx_metadata);
+        assert_eq!(view.gas_profile, None);
+        let json = serde_json::to_value(&view).unwrap();
+        assert_eq!(json["storage_proof_size"], 100);
+    }
+
     #[test]
     fn test_deserialize_execution_outcome_with_receipt() {
         // Real JSON-RPC response for 'EXPERIMENTAL_tx_status' method
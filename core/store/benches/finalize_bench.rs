@@ -115,24 +115,27 @@ fn create_benchmark_receipts() -> Vec<Receipt> {
 }
 
 fn create_chunk_header(height: u64, shard_id: ShardId) -> ShardChunkHeader {
-    ShardChunkHeader::V3(ShardChunkHeaderV3::new(
-        CryptoHash::default(),
-        CryptoHash::default(),
-        CryptoHash::default(),
-        CryptoHash::default(),
-        1,
-        height,
-        shard_id,
-        0,
-        0,
-        0,
-        CryptoHash::default(),
-        CryptoHash::default(),
-        vec![],
-        Default::default(),
-        BandwidthRequests::empty(),
-        &validator_signer(),
-    ))
+    ShardChunkHeader::V3(
+        ShardChunkHeaderV3::new(
+            CryptoHash::default(),
+            CryptoHash::default(),
+            CryptoHash::default(),
+            CryptoHash::default(),
+            1,
+            height,
+            shard_id,
+            0,
+            0,
+            0,
+            CryptoHash::default(),
+            CryptoHash::default(),
+            vec![],
+            Default::default(),
+            BandwidthRequests::empty(),
+            &validator_signer(),
+        )
+        .unwrap(),
+    )
 }
 
 fn create_action_receipt(
@@ -203,6 +206,7 @@ fn create_encoded_shard_chunk(
         &validator_signer(),
         &rs,
     )
+    .unwrap()
 }
 
 fn encoded_chunk_to_partial_encoded_chunk(
@@ -16,8 +16,11 @@ use near_primitives::sharding::{
 use near_primitives::state_sync::{ShardStateSyncResponseHeader, StateHeaderKey};
 use near_primitives::transaction::{ExecutionOutcomeWithProof, SignedTransaction};
 use near_primitives::types::chunk_extra::ChunkExtra;
-use near_primitives::types::{BlockHeight, EpochId, NumBlocks, ShardId};
-use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, index_to_bytes};
+use near_primitives::types::{AccountId, BlockHeight, EpochId, NumBlocks, ShardId};
+use near_primitives::utils::{
+    get_block_shard_id, get_outcome_id_block_hash, get_transactions_by_signer_key_rev,
+    get_transactions_by_signer_prefix, index_to_bytes,
+};
 use near_primitives::views::LightClientBlockView;
 use std::collections::{HashMap, HashSet};
 use std::io;
@@ -249,6 +252,45 @@ impl ChainStoreAdapter {
         )
     }
 
+    /// Returns the skip ancestor of the given block, if one was recorded.
+    /// See `DBCol::BlockSkipAncestor`.
+    pub fn get_block_skip_ancestor(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<Option<(BlockHeight, CryptoHash)>, Error> {
+        self.store.get_ser(DBCol::BlockSkipAncestor, block_hash.as_ref()).map_err(|e| e.into())
+    }
+
+    /// Returns the transactions of the given signer recorded in
+    /// `DBCol::TransactionsBySigner` as (block height, transaction hash, block
+    /// hash) tuples, the oldest first.
+    pub fn get_transactions_by_signer(
+        &self,
+        signer_id: &AccountId,
+    ) -> Result<Vec<(BlockHeight, CryptoHash, CryptoHash)>, Error> {
+        let prefix = get_transactions_by_signer_prefix(signer_id);
+        self.store
+            .iter_prefix_ser::<CryptoHash>(DBCol::TransactionsBySigner, &prefix)
+            .map(|item| {
+                let (key, block_hash) = item?;
+                let (height, tx_hash) = get_transactions_by_signer_key_rev(&key[prefix.len()..])?;
+                Ok((height, tx_hash, block_hash))
+            })
+            .collect()
+    }
+
+    /// Whether the chunk of the shard in the block was applied, see
+    /// `DBCol::ChunkApplyJournal`.
+    pub fn is_chunk_apply_journaled(
+        &self,
+        block_hash: &CryptoHash,
+        shard_id: ShardId,
+    ) -> Result<bool, Error> {
+        self.store
+            .exists(DBCol::ChunkApplyJournal, &get_block_shard_id(block_hash, shard_id))
+            .map_err(|e| e.into())
+    }
+
     pub fn get_chunk_apply_stats(
         &self,
         block_hash: &CryptoHash,
@@ -6,6 +6,7 @@ use crate::{DBCol, DBTransaction, Database, Store, TrieChanges, metrics};
 use borsh::BorshDeserialize;
 use near_primitives::block::{Block, BlockHeader, Tip};
 use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::PartialMerkleTree;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::sharding::ShardChunk;
 use near_primitives::types::BlockHeight;
@@ -360,6 +361,58 @@ pub fn copy_all_data_to_cold(
     Ok(CopyAllDataToColdStatus::EverythingCopied)
 }
 
+/// Copies the `BlockHeader`, `BlockHeight`, `BlockMerkleTree` and `BlockOrdinal`
+/// columns of the blocks already in `cold_db` to it. These columns used not to
+/// be copied to the cold storage, so the cold storage created before needs this
+/// once to serve the light client proofs of its old blocks.
+///
+/// The headers and heights are taken from the cold blocks. The block merkle
+/// trees and ordinals are taken from `hot_store`, for the blocks it has them
+/// for. Returns the number of blocks backfilled.
+pub fn backfill_cold_block_columns(
+    cold_db: Arc<ColdDB>,
+    hot_store: &Store,
+    batch_size: usize,
+) -> io::Result<u64> {
+    let mut transaction = BatchTransaction::new(cold_db.clone(), batch_size);
+    let mut num_blocks = 0;
+    for result in cold_db.iter(DBCol::Block) {
+        let (block_hash_key, value) = result?;
+        let block = Block::try_from_slice(&value)?;
+        let header = block.header();
+        transaction.set_and_write_if_full(
+            DBCol::BlockHeader,
+            block_hash_key.to_vec(),
+            borsh::to_vec(header)?,
+        )?;
+        transaction.set_and_write_if_full(
+            DBCol::BlockHeight,
+            header.height().to_le_bytes().to_vec(),
+            block_hash_key.to_vec(),
+        )?;
+        if let Some(block_merkle_tree) =
+            hot_store.get_for_cold(DBCol::BlockMerkleTree, &block_hash_key)?
+        {
+            // The ordinal of the block is the size of its merkle tree, see
+            // `get_keys_from_store`.
+            let ordinal = PartialMerkleTree::try_from_slice(&block_merkle_tree)?.size();
+            transaction.set_and_write_if_full(
+                DBCol::BlockOrdinal,
+                ordinal.to_le_bytes().to_vec(),
+                block_hash_key.to_vec(),
+            )?;
+            transaction.set_and_write_if_full(
+                DBCol::BlockMerkleTree,
+                block_hash_key.to_vec(),
+                block_merkle_tree,
+            )?;
+        }
+        num_blocks += 1;
+    }
+    transaction.write()?;
+    Ok(num_blocks)
+}
+
 // The copy_state_from_store function depends on the state nodes to be present
 // in the trie changes. This isn't the case for genesis so instead this method
 // can be used to copy the genesis records from hot to cold.
@@ -430,6 +483,13 @@ fn get_keys_from_store(
                 }
                 DBKeyType::BlockHeight => vec![height_key.to_vec()],
                 DBKeyType::BlockHash => vec![block_hash_key.to_vec()],
+                DBKeyType::BlockOrdinal => {
+                    // The ordinal of the block is the size of its merkle tree,
+                    // the same way as it's computed when saving the block.
+                    let block_merkle_tree: PartialMerkleTree =
+                        store.get_ser_or_err_for_cold(DBCol::BlockMerkleTree, &block_hash_key)?;
+                    vec![block_merkle_tree.size().to_le_bytes().to_vec()]
+                }
                 DBKeyType::PreviousBlockHash => {
                     vec![block.header().prev_hash().as_bytes().to_vec()]
                 }
@@ -322,6 +322,35 @@ pub enum DBCol {
     /// - *Rows*: BlockShardId (BlockHash || ShardId) - 40 bytes
     /// - *Column type*: `ChunkApplyStats`
     ChunkApplyStats,
+    /// Skip-list pointers over the block headers, used to find ancestors of a block in
+    /// logarithmic rather than linear number of header reads.
+    /// Each block points to its ancestor with the largest height not exceeding the block's
+    /// skip height, see `near_chain::store::ancestor_skip_list`.
+    /// - *Rows*: block hash (CryptoHash)
+    /// - *Content type*: (BlockHeight, CryptoHash) of the skip ancestor
+    BlockSkipAncestor,
+    /// Index of the recent transactions of each signer, only maintained when
+    /// `transactions_by_signer_limit` is set in the client config.
+    /// Keeps at most that many transactions per signer, the rest are removed
+    /// together with the blocks which included them.
+    /// - *Rows*: signer AccountId || ',' || BlockHeight (big endian) of the
+    ///   including block || transaction hash
+    /// - *Column type*: `CryptoHash` of the block in which the transaction was applied
+    TransactionsBySigner,
+    /// Completion markers of the applied chunks, written in the same update as
+    /// the results of the chunk. The chunks with a marker aren't applied again,
+    /// e.g. when catch up starts again from the first block of the epoch after
+    /// a restart.
+    /// - *Rows*: BlockShardId (BlockHash || ShardId) - 40 bytes
+    /// - *Column type*: `()`
+    ChunkApplyJournal,
+    /// Postprocessing writes of fork blocks which were deferred, see
+    /// `defer_fork_postprocessing`. Kept so that the forks can still be
+    /// extended after a restart. Removed once written or once the block can
+    /// no longer be extended.
+    /// - *Rows*: block hash (CryptoHash)
+    /// - *Column type*: `DeferredBlockPostprocessing`
+    DeferredPostprocessing,
 }
 
 /// Defines different logical parts of a db key.
@@ -470,6 +499,16 @@ impl DBCol {
             | DBCol::StateShardUIdMapping
             | DBCol::ChunkApplyStats => true,
 
+            // These are not GC-ed, but they are needed for the block proofs of
+            // the light client proofs. They are copied so that the archival
+            // node can serve the proofs when the hot storage doesn't go back to
+            // genesis, e.g. when it was bootstrapped with epoch sync. See
+            // `backfill_cold_block_columns` for the blocks copied before.
+            DBCol::BlockHeader
+            | DBCol::BlockHeight
+            | DBCol::BlockMerkleTree
+            | DBCol::BlockOrdinal => true,
+
             // TODO
             DBCol::ChallengedBlocks => false,
             DBCol::Misc => false,
@@ -498,14 +537,17 @@ impl DBCol {
             DBCol::_ReceiptIdToShardId => false,
             // This can be re-constructed from the Chunks column, so no need to store in Cold DB.
             DBCol::PartialChunks => false,
+            // Only the recent transactions are indexed, archival nodes have indexers for that.
+            DBCol::TransactionsBySigner => false,
+            // ChunkApplyJournal is only needed while catching up.
+            DBCol::ChunkApplyJournal => false,
+            // DeferredPostprocessing is only needed to extend forks above the final head.
+            DBCol::DeferredPostprocessing => false,
 
             // Columns that are not GC-ed need not be copied to the cold storage.
-            DBCol::BlockHeader
-            | DBCol::_GCCount
-            | DBCol::BlockHeight
+            DBCol::_GCCount
             | DBCol::_Peers
             | DBCol::RecentOutboundConnections
-            | DBCol::BlockMerkleTree
             | DBCol::AccountAnnouncements
             | DBCol::EpochLightClientBlocks
             | DBCol::PeerComponent
@@ -514,7 +556,6 @@ impl DBCol {
             | DBCol::EpochInfo
             | DBCol::EpochStart
             | DBCol::EpochValidatorInfo
-            | DBCol::BlockOrdinal
             | DBCol::_ChunkPerHeightShard
             | DBCol::_NextBlockWithNewChunk
             | DBCol::_LastBlockWithNewChunk
@@ -528,7 +569,8 @@ impl DBCol {
             | DBCol::FlatStorageStatus
             | DBCol::EpochSyncProof
             | DBCol::StateSyncHashes
-            | DBCol::StateSyncNewChunks => false,
+            | DBCol::StateSyncNewChunks
+            | DBCol::BlockSkipAncestor => false,
         }
     }
 
@@ -605,6 +647,12 @@ impl DBCol {
             DBCol::StateSyncHashes => &[DBKeyType::EpochId],
             DBCol::StateSyncNewChunks => &[DBKeyType::BlockHash],
             DBCol::ChunkApplyStats => &[DBKeyType::BlockHash, DBKeyType::ShardId],
+            DBCol::BlockSkipAncestor => &[DBKeyType::BlockHash],
+            DBCol::TransactionsBySigner => {
+                &[DBKeyType::AccountId, DBKeyType::BlockHeight, DBKeyType::TransactionHash]
+            }
+            DBCol::ChunkApplyJournal => &[DBKeyType::BlockHash, DBKeyType::ShardId],
+            DBCol::DeferredPostprocessing => &[DBKeyType::BlockHash],
         }
     }
 }
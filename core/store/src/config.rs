@@ -5,7 +5,7 @@ use crate::trie::{
 use near_primitives::chains::MAINNET;
 use near_primitives::epoch_manager::EpochConfigStore;
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, BlockHeight, BlockHeightDelta};
 use near_primitives::version::{PROTOCOL_VERSION, ProtocolFeature};
 use near_time::Duration;
 use std::{collections::HashMap, str::FromStr};
@@ -61,6 +61,13 @@ pub struct StoreConfig {
     /// the performance of the storage
     pub block_size: bytesize::ByteSize,
 
+    /// Soft limit on the disk space taken by a single shard in the State and
+    /// FlatState columns.  When a shard goes over it, a warning is logged and
+    /// the `near_shard_disk_usage_over_soft_quota` gauge is set.  Nothing else
+    /// happens, the node keeps writing.  Disabled by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard_disk_usage_soft_quota: Option<bytesize::ByteSize>,
+
     /// Trie cache configuration per shard for normal (non-view) caches.
     pub trie_cache: TrieCacheConfig,
     /// Trie cache configuration per shard for view caches.
@@ -89,6 +96,14 @@ pub struct StoreConfig {
     #[serde(rename = "load_mem_tries_for_tracked_shards")]
     pub load_memtries_for_tracked_shards: bool,
 
+    /// If set, a sample of the trie keys read during block processing is
+    /// aggregated into a per-shard heat map of key prefixes, which is
+    /// periodically saved to the database.  Useful for tuning the prefetcher
+    /// and the trie caches, see `neard view-state trie-access-heat-map`.
+    /// Disabled by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trie_access_heat_map: Option<TrieAccessHeatMapConfig>,
+
     /// Path where to create RocksDB checkpoints during database migrations or
     /// `false` to disable that feature.
     ///
@@ -131,6 +146,67 @@ impl StoreConfig {
 #[serde(default)]
 pub struct StateSnapshotConfig {
     pub state_snapshot_type: StateSnapshotType,
+    /// When to take restore points in addition to the snapshot at the
+    /// beginning of every epoch.
+    #[serde(skip_serializing_if = "StateSnapshotPolicy::is_default")]
+    pub policy: StateSnapshotPolicy,
+}
+
+/// Policy for taking restore points, i.e. full checkpoints of the hot database
+/// taken between epoch boundaries.
+///
+/// Restore points are independent of the state snapshot used for state sync,
+/// they are stored in a separate directory and are never opened by the node.
+/// Only effective if state snapshots are enabled.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct StateSnapshotPolicy {
+    /// Take a restore point whenever the head reaches a multiple of this
+    /// height. If the block at the multiple is skipped, the restore point is
+    /// taken at the first block after it.
+    pub every_n_blocks: Option<BlockHeightDelta>,
+    /// Take a restore point at the last block of an epoch which is followed by
+    /// a protocol upgrade.
+    pub before_protocol_upgrade: bool,
+    /// Allow requesting restore points with the
+    /// `EXPERIMENTAL_request_restore_point` RPC method. The method is only
+    /// served if `enable_debug_rpc` is set in the RPC config.
+    pub on_demand: bool,
+    /// Number of restore points to keep. The oldest ones are deleted when a
+    /// new restore point is taken.
+    pub max_restore_points: usize,
+    /// Restore points are skipped if less space than this is available on
+    /// the disk holding them.
+    pub min_free_disk_space: bytesize::ByteSize,
+}
+
+impl Default for StateSnapshotPolicy {
+    fn default() -> Self {
+        Self {
+            every_n_blocks: None,
+            before_protocol_upgrade: false,
+            on_demand: false,
+            max_restore_points: 2,
+            min_free_disk_space: bytesize::ByteSize::gb(100),
+        }
+    }
+}
+
+impl StateSnapshotPolicy {
+    fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Whether any restore points can be taken under this policy.
+    pub fn takes_restore_points(&self) -> bool {
+        self.every_n_blocks.is_some() || self.before_protocol_upgrade || self.on_demand
+    }
+
+    /// Whether the head moving from `prev_height` to `height` reaches a
+    /// multiple of `every_n_blocks`.
+    pub fn reaches_every_n_blocks(&self, prev_height: BlockHeight, height: BlockHeight) -> bool {
+        self.every_n_blocks.is_some_and(|n| n > 0 && height / n > prev_height / n)
+    }
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -261,6 +337,8 @@ impl Default for StoreConfig {
             // we use it since then.
             block_size: bytesize::ByteSize::kib(16),
 
+            shard_disk_usage_soft_quota: None,
+
             trie_cache: TrieCacheConfig {
                 default_max_bytes: bytesize::ByteSize::mb(500),
                 per_shard_max_bytes: Self::default_per_shard_max_bytes(),
@@ -300,6 +378,8 @@ impl Default for StoreConfig {
             load_memtries_for_shards: Default::default(),
             load_memtries_for_tracked_shards: false,
 
+            trie_access_heat_map: None,
+
             migration_snapshot: Default::default(),
 
             state_snapshot_config: Default::default(),
@@ -343,6 +423,34 @@ impl Default for MigrationSnapshot {
     }
 }
 
+/// Configuration of the trie access heat map.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TrieAccessHeatMapConfig {
+    /// Only every `sample_rate`-th key read is recorded.
+    pub sample_rate: u64,
+    /// Reads of contract storage are grouped by the account and this many
+    /// leading bytes of the storage key.
+    pub contract_data_prefix_len: usize,
+    /// Maximum number of key prefixes kept for a shard.  Beyond that, the
+    /// least read prefixes are dropped and only counted in the total.
+    pub max_prefixes_per_shard: usize,
+    /// How often the heat map is saved to the database.
+    #[serde(with = "near_time::serde_duration_as_std")]
+    pub save_period: Duration,
+}
+
+impl Default for TrieAccessHeatMapConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 100,
+            contract_data_prefix_len: 2,
+            max_prefixes_per_shard: 10_000,
+            save_period: Duration::minutes(5),
+        }
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct TrieCacheConfig {
@@ -373,6 +481,14 @@ pub struct SplitStorageConfig {
     #[serde(default = "default_enable_split_storage_view_client")]
     pub enable_split_storage_view_client: bool,
 
+    /// Makes the view client read the blocks, chunks, transactions, receipts
+    /// and outcomes garbage collected from the hot store from the cold store,
+    /// when it doesn't use the split store, see
+    /// `enable_split_storage_view_client`.  The state is still only read from
+    /// the hot store.
+    #[serde(default)]
+    pub enable_cold_read_through_view_client: bool,
+
     #[serde(default = "default_cold_store_initial_migration_batch_size")]
     pub cold_store_initial_migration_batch_size: usize,
     #[serde(default = "default_cold_store_initial_migration_loop_sleep_duration")]
@@ -391,6 +507,7 @@ impl Default for SplitStorageConfig {
     fn default() -> Self {
         SplitStorageConfig {
             enable_split_storage_view_client: default_enable_split_storage_view_client(),
+            enable_cold_read_through_view_client: false,
             cold_store_initial_migration_batch_size:
                 default_cold_store_initial_migration_batch_size(),
             cold_store_initial_migration_loop_sleep_duration:
@@ -525,3 +642,20 @@ impl<'a> ArchivalConfig<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::StateSnapshotPolicy;
+
+    #[test]
+    fn test_reaches_every_n_blocks() {
+        let policy = StateSnapshotPolicy { every_n_blocks: Some(10), ..Default::default() };
+        assert!(policy.reaches_every_n_blocks(9, 10));
+        assert!(!policy.reaches_every_n_blocks(10, 11));
+        assert!(!policy.reaches_every_n_blocks(11, 19));
+        // The block at height 20 is skipped.
+        assert!(policy.reaches_every_n_blocks(19, 21));
+        assert!(policy.reaches_every_n_blocks(5, 35));
+        assert!(!StateSnapshotPolicy::default().reaches_every_n_blocks(9, 10));
+    }
+}
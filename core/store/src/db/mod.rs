@@ -43,6 +43,12 @@ pub const STATE_SNAPSHOT_KEY: &[u8; 18] = b"STATE_SNAPSHOT_KEY";
 pub const FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS_KEY: &[u8] =
     b"FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS";
 pub const LATEST_WITNESSES_INFO: &[u8] = b"LATEST_WITNESSES_INFO";
+pub const VALIDATOR_DUTIES_KEY: &[u8] = b"VALIDATOR_DUTIES";
+pub const TRIE_ACCESS_HEAT_MAP_KEY: &[u8] = b"TRIE_ACCESS_HEAT_MAP";
+pub const MIGRATION_PROGRESS_KEY: &[u8] = b"MIGRATION_PROGRESS";
+pub const TX_POOL_KEY: &[u8] = b"TX_POOL";
+pub const CORRUPTED_CHUNKS_KEY: &[u8] = b"CORRUPTED_CHUNKS";
+pub const RPC_LIGHT_CHUNK_EXTRA_TAIL_KEY: &[u8] = b"RPC_LIGHT_CHUNK_EXTRA_TAIL";
 
 #[derive(Default, Debug)]
 pub struct DBTransaction {
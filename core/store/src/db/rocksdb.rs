@@ -14,6 +14,7 @@ use tracing::warn;
 
 use super::metadata;
 
+mod disk_usage;
 mod instance_tracker;
 pub(crate) mod snapshot;
 
@@ -55,6 +56,9 @@ pub struct RocksDB {
     /// want.
     cf_handles: enum_map::EnumMap<DBCol, Option<std::ptr::NonNull<ColumnFamily>>>,
 
+    /// Approximate disk usage of each shard.  Only tracked for hot storage.
+    disk_usage: Option<disk_usage::ShardDiskUsage>,
+
     // RAII-style of keeping track of the number of instances of RocksDB and
     // counting total sum of max_open_files.
     _instance_tracker: instance_tracker::InstanceTracker,
@@ -120,7 +124,11 @@ impl RocksDB {
             .map_err(io::Error::other)?;
         let (db, db_opt) = Self::open_db(path, store_config, mode, temp, columns)?;
         let cf_handles = Self::get_cf_handles(&db, columns);
-        Ok(Self { db, db_opt, cf_handles, _instance_tracker: counter })
+        let disk_usage = (temp == Temperature::Hot)
+            .then(|| disk_usage::ShardDiskUsage::new(store_config.shard_disk_usage_soft_quota));
+        let this = Self { db, db_opt, cf_handles, disk_usage, _instance_tracker: counter };
+        this.refresh_disk_usage();
+        Ok(this)
     }
 
     /// Opens the database with given column families configured.
@@ -296,9 +304,25 @@ impl RocksDB {
         let none = Option::<&[u8]>::None;
         tracing::info!(target: "store::db::rocksdb", col = %col, "RocksDB::compact_column");
         self.db.compact_range_cf(self.cf_handle(col)?, none, none);
+        if let Some(disk_usage) = &self.disk_usage {
+            if disk_usage::TRACKED_COLUMNS.contains(&col) {
+                disk_usage.refresh_column(&self.db, col, self.cf_handle(col)?);
+            }
+        }
         Ok(())
     }
 
+    /// Resets the per-shard disk usage estimate to the sizes reported by
+    /// RocksDB.
+    fn refresh_disk_usage(&self) {
+        let Some(disk_usage) = &self.disk_usage else { return };
+        for (col, cf_handle) in self.cf_handles() {
+            if disk_usage::TRACKED_COLUMNS.contains(&col) {
+                disk_usage.refresh_column(&self.db, col, cf_handle);
+            }
+        }
+    }
+
     #[tracing::instrument(
         target = "store::db::rocksdb",
         level = "trace",
@@ -389,6 +413,9 @@ impl Database for RocksDB {
         skip_all
     )]
     fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        if let Some(disk_usage) = &self.disk_usage {
+            disk_usage.record_transaction(&transaction);
+        }
         let write_batch_start = std::time::Instant::now();
         let batch = self.build_write_batch(transaction)?;
         let elapsed = write_batch_start.elapsed();
@@ -442,6 +469,12 @@ impl Database for RocksDB {
             }
         }
         self.get_cf_statistics(&mut result);
+        if let Some(disk_usage) = &self.disk_usage {
+            if disk_usage.needs_refresh() {
+                self.refresh_disk_usage();
+            }
+            disk_usage.export();
+        }
         if result.data.is_empty() { None } else { Some(result) }
     }
 
@@ -812,6 +845,50 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_shard_disk_usage() {
+        use near_primitives::shard_layout::ShardUId;
+        use near_primitives::types::ShardId;
+
+        let (_tmp_dir, opener) = NodeStorage::test_opener();
+        let store = opener.open().unwrap().get_hot_store();
+        let ptr = (&*store.storage) as *const (dyn Database + 'static);
+        let rocksdb = unsafe { &*(ptr as *const RocksDB) };
+        let disk_usage = rocksdb.disk_usage.as_ref().unwrap();
+        assert!(disk_usage.get().is_empty());
+
+        let shard0 = ShardUId::new(3, ShardId::new(0));
+        let shard1 = ShardUId::new(3, ShardId::new(1));
+        let key = |shard_uid: ShardUId, suffix: u8| [&shard_uid.to_bytes()[..], &[suffix]].concat();
+        let mut store_update = store.store_update();
+        store_update.increment_refcount(DBCol::State, &key(shard0, 0), &[0; 100]);
+        store_update.increment_refcount(DBCol::State, &key(shard0, 1), &[0; 100]);
+        store_update.set(DBCol::FlatState, &key(shard1, 0), &[0; 50]);
+        store_update.commit().unwrap();
+
+        let usage = disk_usage.get();
+        assert_eq!(usage.len(), 2);
+        assert!(usage[&(shard0, DBCol::State)].written >= 2 * 109);
+        assert!(usage[&(shard1, DBCol::FlatState)].written >= 59);
+
+        // Decrementing refcounts doesn’t free any space until compaction.
+        let mut store_update = store.store_update();
+        store_update.decrement_refcount(DBCol::State, &key(shard0, 0));
+        store_update.commit().unwrap();
+        assert_eq!(disk_usage.get(), usage);
+
+        // single_thread_rocksdb makes compact hang forever
+        if !cfg!(feature = "single_thread_rocksdb") {
+            rocksdb.flush().unwrap();
+            rocksdb.compact_column(DBCol::State).unwrap();
+            let usage = disk_usage.get();
+            let state = usage[&(shard0, DBCol::State)];
+            assert_eq!(state.written, 0);
+            assert!(state.on_disk > 0);
+            assert!(!usage.contains_key(&(shard1, DBCol::State)));
+        }
+    }
+
     #[test]
     fn rocksdb_merge_sanity() {
         let (_tmp_dir, opener) = NodeStorage::test_opener();
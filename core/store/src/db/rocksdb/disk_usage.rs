@@ -0,0 +1,206 @@
+//! Approximate accounting of on-disk bytes taken by each shard.
+//!
+//! Keys of [`DBCol::State`] and [`DBCol::FlatState`] start with the
+//! [`ShardUId`] of the shard they belong to, so the space a shard takes is the
+//! approximate size of its key range as reported by RocksDB.  Asking RocksDB is
+//! cheap but the answer only reflects SST files, so in between refreshes bytes
+//! written by transactions are added on top.  Deletions are not subtracted
+//! since RocksDB only reclaims the space on compaction, at which point the next
+//! refresh picks the change up.
+
+use crate::DBCol;
+use crate::db::{DBOp, DBTransaction, refcount};
+use crate::metrics;
+use ::rocksdb::{ColumnFamily, DB};
+use near_primitives::shard_layout::ShardUId;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Columns whose keys are prefixed with the shard uid.
+pub(super) const TRACKED_COLUMNS: [DBCol; 2] = [DBCol::State, DBCol::FlatState];
+
+/// How often the estimate is reset to the sizes reported by RocksDB when
+/// exporting statistics.
+const REFRESH_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub(super) struct ColumnUsage {
+    /// Size of the shard’s key range in SST files as of the last refresh.
+    pub on_disk: u64,
+    /// Bytes written for the shard since the last refresh.
+    pub written: u64,
+}
+
+impl ColumnUsage {
+    pub fn total(&self) -> u64 {
+        self.on_disk.saturating_add(self.written)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    usage: HashMap<(ShardUId, DBCol), ColumnUsage>,
+    /// Shards which were over the soft quota during the last export.  Used to
+    /// only warn when a shard crosses the quota rather than on every export.
+    over_quota: HashSet<ShardUId>,
+    last_refresh: Option<Instant>,
+}
+
+pub(super) struct ShardDiskUsage {
+    soft_quota: Option<bytesize::ByteSize>,
+    state: parking_lot::Mutex<State>,
+}
+
+impl ShardDiskUsage {
+    pub fn new(soft_quota: Option<bytesize::ByteSize>) -> Self {
+        Self { soft_quota, state: Default::default() }
+    }
+
+    /// Adds bytes written by the transaction to the shards they belong to.
+    ///
+    /// Must be called before the transaction is written.
+    pub fn record_transaction(&self, transaction: &DBTransaction) {
+        let mut written = HashMap::<(ShardUId, DBCol), u64>::new();
+        for op in &transaction.ops {
+            let (col, key, value) = match op {
+                DBOp::Set { col, key, value } | DBOp::Insert { col, key, value } => {
+                    (*col, key, value)
+                }
+                DBOp::UpdateRefcount { col, key, value } => {
+                    // Decrements only take space until the next compaction.
+                    if refcount::decode_value_with_rc(value).1 <= 0 {
+                        continue;
+                    }
+                    (*col, key, value)
+                }
+                DBOp::Delete { .. } | DBOp::DeleteAll { .. } | DBOp::DeleteRange { .. } => continue,
+            };
+            if !TRACKED_COLUMNS.contains(&col) {
+                continue;
+            }
+            let Some(shard_uid) = shard_uid_of_key(key) else { continue };
+            *written.entry((shard_uid, col)).or_default() += (key.len() + value.len()) as u64;
+        }
+        if written.is_empty() {
+            return;
+        }
+        let mut state = self.state.lock();
+        for (key, bytes) in written {
+            let usage = state.usage.entry(key).or_default();
+            usage.written = usage.written.saturating_add(bytes);
+        }
+    }
+
+    /// Replaces the estimate for given column with the sizes of all shard key
+    /// ranges present in it as reported by RocksDB.
+    pub fn refresh_column(&self, db: &DB, col: DBCol, cf: &ColumnFamily) {
+        let mut sizes = Vec::new();
+        let mut iter = db.raw_iterator_cf(cf);
+        iter.seek_to_first();
+        while let Some(key) = iter.key() {
+            let Some(shard_uid) = shard_uid_of_key(key) else { break };
+            let start = shard_uid.to_bytes();
+            let end = next_prefix(start);
+            let range = match &end {
+                Some(end) => ::rocksdb::Range::new(&start, end),
+                None => ::rocksdb::Range::new(&start, &[0xff; 9]),
+            };
+            let size = db.get_approximate_sizes_cf(cf, &[range]).first().copied().unwrap_or(0);
+            sizes.push((shard_uid, size));
+            match end {
+                Some(end) => iter.seek(end),
+                None => break,
+            }
+        }
+        let mut state = self.state.lock();
+        state.usage.retain(|(_, c), _| *c != col);
+        for (shard_uid, on_disk) in sizes {
+            state.usage.insert((shard_uid, col), ColumnUsage { on_disk, written: 0 });
+        }
+    }
+
+    /// Returns whether the estimate is stale, in which case the caller should
+    /// call [`Self::refresh_column`] for all tracked columns.  Marks the
+    /// estimate as fresh.
+    pub fn needs_refresh(&self) -> bool {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        if state.last_refresh.is_some_and(|last| now.duration_since(last) < REFRESH_PERIOD) {
+            return false;
+        }
+        state.last_refresh = Some(now);
+        true
+    }
+
+    /// Returns current estimate of bytes taken by each shard in each column.
+    pub fn get(&self) -> HashMap<(ShardUId, DBCol), ColumnUsage> {
+        self.state.lock().usage.clone()
+    }
+
+    /// Sets the per-shard gauges and warns about shards which went over the
+    /// soft quota.
+    pub fn export(&self) {
+        let mut state = self.state.lock();
+        let mut totals = BTreeMap::<ShardUId, u64>::new();
+        for ((shard_uid, col), usage) in &state.usage {
+            let shard_label = shard_uid.to_string();
+            let col: &str = (*col).into();
+            metrics::SHARD_DISK_USAGE_BYTES
+                .with_label_values(&[&shard_label, col])
+                .set(usage.total() as i64);
+            *totals.entry(*shard_uid).or_default() += usage.total();
+        }
+        let Some(soft_quota) = self.soft_quota else { return };
+        let mut over_quota = HashSet::new();
+        for (shard_uid, total) in totals {
+            let is_over = total > soft_quota.as_u64();
+            metrics::SHARD_DISK_USAGE_OVER_SOFT_QUOTA
+                .with_label_values(&[&shard_uid.to_string()])
+                .set(is_over as i64);
+            if !is_over {
+                continue;
+            }
+            if !state.over_quota.contains(&shard_uid) {
+                tracing::warn!(
+                    target: "store::db::rocksdb",
+                    %shard_uid,
+                    usage = %bytesize::ByteSize::b(total),
+                    %soft_quota,
+                    "Shard disk usage exceeds the soft quota"
+                );
+            }
+            over_quota.insert(shard_uid);
+        }
+        state.over_quota = over_quota;
+    }
+}
+
+fn shard_uid_of_key(key: &[u8]) -> Option<ShardUId> {
+    ShardUId::try_from(key.get(..8)?).ok()
+}
+
+/// Returns the smallest 8-byte value greater than all keys starting with
+/// `prefix` or `None` if there’s no such value.
+fn next_prefix(mut prefix: [u8; 8]) -> Option<[u8; 8]> {
+    for byte in prefix.iter_mut().rev() {
+        if *byte == u8::MAX {
+            *byte = 0;
+        } else {
+            *byte += 1;
+            return Some(prefix);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_prefix;
+
+    #[test]
+    fn test_next_prefix() {
+        assert_eq!(next_prefix([0; 8]), Some([0, 0, 0, 0, 0, 0, 0, 1]));
+        assert_eq!(next_prefix([1, 0, 0, 0, 0, 0, 0, 255]), Some([1, 0, 0, 0, 0, 0, 1, 0]));
+        assert_eq!(next_prefix([255; 8]), None);
+    }
+}
@@ -100,6 +100,41 @@ impl Snapshot {
         Ok(Self(Some(snapshot_path)))
     }
 
+    /// Returns the snapshot of the database if it exists, e.g. because it was
+    /// created before a migration which has been interrupted.
+    pub fn existing(db_path: &std::path::Path, config: &crate::StoreConfig) -> Self {
+        match config.migration_snapshot.get_path(db_path) {
+            Some(snapshot_path) if snapshot_path.exists() => Self(Some(snapshot_path)),
+            _ => Self::none(),
+        }
+    }
+
+    /// Replaces the database with its snapshot, rolling back the migration
+    /// the snapshot was created for.
+    ///
+    /// Returns the path of the restored snapshot, or `None` if the database has
+    /// no snapshot.  The snapshot is moved, hence it must be on the same file
+    /// system as the database.
+    pub fn restore(
+        db_path: &std::path::Path,
+        config: &crate::StoreConfig,
+    ) -> io::Result<Option<std::path::PathBuf>> {
+        let Self(Some(snapshot_path)) = Self::existing(db_path, config) else {
+            return Ok(None);
+        };
+        tracing::info!(target: "db", snapshot_path=%snapshot_path.display(),
+                       "Restoring the database from the snapshot");
+        // By default the snapshot is inside of the database directory, so it’s
+        // moved out of it before the database is deleted.
+        let mut restored_path = db_path.as_os_str().to_owned();
+        restored_path.push(".restored");
+        let restored_path = std::path::PathBuf::from(restored_path);
+        std::fs::rename(&snapshot_path, &restored_path)?;
+        std::fs::remove_dir_all(db_path)?;
+        std::fs::rename(&restored_path, db_path)?;
+        Ok(Some(snapshot_path))
+    }
+
     /// Deletes the checkpoint from the file system.
     ///
     /// Does nothing if the object has been created via [`Self::none`].
@@ -204,3 +239,32 @@ fn test_snapshot_recovery() {
 
     snapshot.remove().unwrap();
 }
+
+/// Tests that the database can be replaced with its snapshot.
+#[test]
+fn test_snapshot_restore() {
+    const KEY: &[u8] = b"key";
+    const COL: crate::DBCol = crate::DBCol::BlockMisc;
+
+    let (_tmpdir, opener) = crate::NodeStorage::test_opener();
+    let set = |value: &[u8]| {
+        let store = opener.open().unwrap().get_hot_store();
+        let mut update = store.store_update();
+        update.set_raw_bytes(COL, KEY, value);
+        update.commit().unwrap();
+    };
+
+    // Nothing to restore without a snapshot.
+    set(b"old");
+    assert_eq!(None, Snapshot::restore(&opener.path(), &opener.config()).unwrap());
+
+    let snapshot = Snapshot::new(&opener.path(), &opener.config(), Temperature::Hot).unwrap();
+    let path = snapshot.0.clone().unwrap();
+    core::mem::drop(snapshot);
+    set(b"new");
+
+    assert_eq!(Some(path.clone()), Snapshot::restore(&opener.path(), &opener.config()).unwrap());
+    assert!(!path.exists());
+    let store = opener.open().unwrap().get_hot_store();
+    assert_eq!(Some(&b"old"[..]), store.get(COL, KEY).unwrap().as_deref());
+}
@@ -81,6 +81,7 @@ impl Database for SplitDB {
             return Ok(Some(hot_result));
         }
         if col.is_cold() {
+            crate::metrics::SPLIT_DB_COLD_READS.with_label_values(&[<&str>::from(col)]).inc();
             return self.cold.get_raw_bytes(col, key);
         }
         Ok(None)
@@ -99,6 +100,7 @@ impl Database for SplitDB {
             return Ok(Some(hot_result));
         }
         if col.is_cold() {
+            crate::metrics::SPLIT_DB_COLD_READS.with_label_values(&[<&str>::from(col)]).inc();
             return self.cold.get_with_rc_stripped(col, key);
         }
         Ok(None)
@@ -214,7 +216,9 @@ mod test {
 
     use super::*;
 
+    use crate::db::metadata::{DB_VERSION, DbKind};
     use crate::db::{ColdDB, DBOp, DBTransaction, testdb::TestDB};
+    use crate::test_utils::create_test_node_storage_with_cold;
 
     const FOO: &[u8] = b"FOO";
     const BAR: &[u8] = b"BAR";
@@ -282,7 +286,7 @@ mod test {
 
         // Test 3: Try reading from a non-cold column and verify it returns None
         // even if the value is set in the cold db.
-        let col = DBCol::BlockHeader;
+        let col = DBCol::EpochInfo;
         let key = BAZ;
 
         set(&cold, col, key, BAZ);
@@ -290,6 +294,21 @@ mod test {
         assert_eq!(value, None);
     }
 
+    #[test]
+    fn test_read_through_store() {
+        // Not available until the initial migration to the cold store is finished.
+        let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Archive);
+        assert!(storage.get_read_through_store().unwrap().is_none());
+
+        let (storage, _, cold) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Hot);
+        let store = storage.get_read_through_store().unwrap().unwrap();
+        // A block garbage collected from the hot store is read from the cold store.
+        let cold: Arc<dyn Database> = cold;
+        set(&cold, DBCol::Block, FOO, FOO_VALUE);
+        assert!(storage.get_hot_store().get(DBCol::Block, FOO).unwrap().is_none());
+        assert_eq!(store.get(DBCol::Block, FOO).unwrap().as_deref(), Some(FOO_VALUE));
+    }
+
     #[test]
     fn test_get_with_rc_stripped() {
         let hot = create_hot();
@@ -1,10 +1,17 @@
 use crate::adapter::flat_store::FlatStoreAdapter;
+use crate::trie::update::make_prefix_range_end_bound;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state::FlatStateValue;
+use std::ops::ControlFlow;
 
 use super::FlatStorage;
-use super::types::FlatStateIterator;
+use super::types::{FlatStateIterator, FlatStorageResult};
+
+/// Number of flat state entries read at once by [`FlatStorageChunkView::iter_prefix`].
+/// The flat storage is locked while they are read, so the head updates are
+/// blocked for at most that long by a single iteration.
+const ITER_PREFIX_PAGE_SIZE: usize = 1000;
 
 /// Struct for getting value references from the flat storage, corresponding
 /// to some block defined in `blocks_to_head`.
@@ -53,6 +60,55 @@ impl FlatStorageChunkView {
         self.store.iter_range(self.flat_storage.shard_uid(), from, to)
     }
 
+    /// Calls `f` with the flat state entries with keys starting with
+    /// `prefix`, in key order, as of `FlatStorageChunkView::block_hash`, until
+    /// it breaks.
+    ///
+    /// Deltas on top of the flat head can't be merged into a range iteration,
+    /// so `None` is returned unless `block_hash` is the flat head itself. The
+    /// entries are read in pages and the flat storage is only locked while a
+    /// page is read, so the head may move in the middle of the iteration. In
+    /// that case `None` is returned too and the entries already passed to `f`
+    /// must be discarded.
+    pub fn iter_prefix(
+        &self,
+        prefix: &[u8],
+        f: impl FnMut(Vec<u8>, FlatStateValue) -> ControlFlow<()>,
+    ) -> Option<FlatStorageResult<()>> {
+        self.iter_prefix_from(prefix, prefix, f)
+    }
+
+    /// Same as [`Self::iter_prefix`], but skips the keys smaller than `from`.
+    pub fn iter_prefix_from(
+        &self,
+        prefix: &[u8],
+        from: &[u8],
+        mut f: impl FnMut(Vec<u8>, FlatStateValue) -> ControlFlow<()>,
+    ) -> Option<FlatStorageResult<()>> {
+        let to = make_prefix_range_end_bound(prefix);
+        let mut from = std::cmp::max(prefix, from).to_vec();
+        loop {
+            let (entries, next_key) = match self.flat_storage.read_range_at_head(
+                &self.block_hash,
+                &from,
+                to.as_deref(),
+                ITER_PREFIX_PAGE_SIZE,
+            )? {
+                Ok(page) => page,
+                Err(err) => return Some(Err(err)),
+            };
+            for (key, value) in entries {
+                if f(key, value).is_break() {
+                    return Some(Ok(()));
+                }
+            }
+            match next_key {
+                Some(next_key) => from = next_key,
+                None => return Some(Ok(())),
+            }
+        }
+    }
+
     pub fn get_head_hash(&self) -> CryptoHash {
         self.flat_storage.get_head_hash()
     }
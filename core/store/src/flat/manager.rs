@@ -7,7 +7,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::{BlockHeight, RawStateChangesWithTrieKey};
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tracing::debug;
 
@@ -42,6 +42,8 @@ pub struct FlatStorageManagerInner {
     /// Set to Some() when there's a state snapshot in progress. Used to signal to the resharding flat
     /// storage catchup code that it shouldn't advance past this block height
     want_snapshot: Mutex<Option<SnapshotBlock>>,
+    /// Shards whose flat storage is being rebuilt, see `FlatStorageRebuilder`.
+    rebuilding: Mutex<HashSet<ShardUId>>,
 }
 
 impl FlatStorageManager {
@@ -50,6 +52,7 @@ impl FlatStorageManager {
             store,
             flat_storages: Default::default(),
             want_snapshot: Default::default(),
+            rebuilding: Default::default(),
         }))
     }
 
@@ -83,7 +86,8 @@ impl FlatStorageManager {
     pub fn create_flat_storage_for_shard(&self, shard_uid: ShardUId) -> Result<(), StorageError> {
         tracing::debug!(target: "store", ?shard_uid, "Creating flat storage for shard");
         let want_snapshot = self.0.want_snapshot.lock();
-        let disable_updates = want_snapshot.is_some();
+        // The flat head of a shard being rebuilt stays pinned until the rebuild ends.
+        let disable_updates = want_snapshot.is_some() || self.is_rebuilding(shard_uid);
 
         let mut flat_storages = self.0.flat_storages.lock();
         let flat_storage = FlatStorage::new(self.0.store.clone(), shard_uid)?;
@@ -276,6 +280,122 @@ impl FlatStorageManager {
         }
     }
 
+    /// Starts the rebuild of the flat storage of `shard_uid`, which must be
+    /// loaded and ready. Pins the flat head, so that the values on disk stay
+    /// at the flat head until the rebuilt values are swapped in, and removes
+    /// the values copied by an interrupted rebuild. Returns the flat head.
+    pub(crate) fn start_rebuild(&self, shard_uid: ShardUId) -> Result<BlockInfo, StorageError> {
+        let Some(flat_storage) = self.get_flat_storage_for_shard(shard_uid) else {
+            return Err(StorageError::StorageInconsistentState(format!(
+                "Cannot rebuild flat storage for shard {shard_uid} which is not loaded"
+            )));
+        };
+        if !self.0.rebuilding.lock().insert(shard_uid) {
+            return Err(StorageError::StorageInconsistentState(format!(
+                "Flat storage for shard {shard_uid} is already being rebuilt"
+            )));
+        }
+        flat_storage.set_flat_head_update_mode(false);
+        // The status is read after pinning the flat head, so it can't move anymore.
+        let flat_head = match self.0.store.get_flat_storage_status(shard_uid) {
+            Ok(FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head })) => flat_head,
+            status => {
+                self.end_rebuild(shard_uid);
+                return Err(StorageError::StorageInconsistentState(format!(
+                    "Cannot rebuild flat storage for shard {shard_uid} with status {status:?}"
+                )));
+            }
+        };
+        let mut store_update = self.0.store.store_update();
+        store_update.remove_all_values(rebuild_shard_uid(shard_uid));
+        if store_update.commit().is_err() {
+            self.end_rebuild(shard_uid);
+            return Err(StorageError::StorageInternalError);
+        }
+        tracing::info!(target: "store", ?shard_uid, ?flat_head, "Started flat storage rebuild");
+        Ok(flat_head)
+    }
+
+    /// Replaces the values of the flat storage of `shard_uid` by the values
+    /// rebuilt at `flat_head`. The values that differ are written in a single
+    /// store update, so readers see either all the old values or all the
+    /// rebuilt ones. Returns the number of values replaced.
+    pub(crate) fn swap_rebuilt_values(
+        &self,
+        shard_uid: ShardUId,
+        flat_head: &BlockInfo,
+    ) -> Result<u64, StorageError> {
+        let Some(flat_storage) = self.get_flat_storage_for_shard(shard_uid) else {
+            return Err(StorageError::StorageInconsistentState(format!(
+                "Flat storage for shard {shard_uid} was unloaded during the rebuild"
+            )));
+        };
+        // Both the values on disk and the rebuilt values are at the pinned flat
+        // head, so the difference can be computed without blocking the readers.
+        let mut store_update = self.0.store.store_update();
+        let mut num_replaced = 0;
+        let mut current = self.0.store.iter(shard_uid).peekable();
+        let mut rebuilt = self.0.store.iter(rebuild_shard_uid(shard_uid)).peekable();
+        loop {
+            let ordering = match (current.peek(), rebuilt.peek()) {
+                (None, None) => break,
+                (Some(Err(_)), _) => return Err(current.next().unwrap().unwrap_err().into()),
+                (_, Some(Err(_))) => return Err(rebuilt.next().unwrap().unwrap_err().into()),
+                (Some(Ok(_)), None) => std::cmp::Ordering::Less,
+                (None, Some(Ok(_))) => std::cmp::Ordering::Greater,
+                (Some(Ok((current_key, _))), Some(Ok((rebuilt_key, _)))) => {
+                    current_key.cmp(rebuilt_key)
+                }
+            };
+            match ordering {
+                std::cmp::Ordering::Less => {
+                    let (key, _) = current.next().unwrap()?;
+                    store_update.set(shard_uid, key, None);
+                    num_replaced += 1;
+                }
+                std::cmp::Ordering::Greater => {
+                    let (key, value) = rebuilt.next().unwrap()?;
+                    store_update.set(shard_uid, key, Some(value));
+                    num_replaced += 1;
+                }
+                std::cmp::Ordering::Equal => {
+                    let (_, current_value) = current.next().unwrap()?;
+                    let (key, value) = rebuilt.next().unwrap()?;
+                    if current_value != value {
+                        store_update.set(shard_uid, key, Some(value));
+                        num_replaced += 1;
+                    }
+                }
+            }
+        }
+        store_update.remove_all_values(rebuild_shard_uid(shard_uid));
+
+        flat_storage.commit_values_at_head(flat_head, store_update)?;
+        Ok(num_replaced)
+    }
+
+    /// Ends the rebuild of the flat storage of `shard_uid`, whether it
+    /// succeeded or not: removes the rebuilt values left and lets the flat
+    /// head move again, unless a state snapshot is wanted.
+    pub(crate) fn end_rebuild(&self, shard_uid: ShardUId) {
+        let mut store_update = self.0.store.store_update();
+        store_update.remove_all_values(rebuild_shard_uid(shard_uid));
+        if let Err(err) = store_update.commit() {
+            tracing::error!(target: "store", ?shard_uid, ?err, "Failed to remove the rebuilt flat storage values");
+        }
+        let want_snapshot = self.0.want_snapshot.lock();
+        self.0.rebuilding.lock().remove(&shard_uid);
+        if want_snapshot.is_none() {
+            if let Some(flat_storage) = self.get_flat_storage_for_shard(shard_uid) {
+                flat_storage.set_flat_head_update_mode(true);
+            }
+        }
+    }
+
+    pub fn is_rebuilding(&self, shard_uid: ShardUId) -> bool {
+        self.0.rebuilding.lock().contains(&shard_uid)
+    }
+
     /// Returns None if there's no resharding flat storage split in progress
     /// If there is, returns Some(None) if there's at least one child shard that hasn't been split and had its
     /// status set to `CatchingUp`. If they've all been split already and are in the catchup phase,
@@ -334,9 +454,13 @@ impl FlatStorageManager {
             }
             *want_snapshot = None;
         }
+        let rebuilding = self.0.rebuilding.lock();
         let flat_storages = self.0.flat_storages.lock();
-        for flat_storage in flat_storages.values() {
-            flat_storage.set_flat_head_update_mode(true);
+        for (shard_uid, flat_storage) in flat_storages.iter() {
+            // The flat head of a shard being rebuilt stays pinned until the rebuild ends.
+            if !rebuilding.contains(shard_uid) {
+                flat_storage.set_flat_head_update_mode(true);
+            }
         }
         tracing::debug!(target: "store", "Unlocked flat head updates");
     }
@@ -354,3 +478,9 @@ impl FlatStorageManager {
         want_snapshot.as_ref().map(|s| s.block_hash)
     }
 }
+
+/// Prefix under which the values of the flat storage of `shard_uid` are
+/// rebuilt before they are swapped in. No shard layout uses this version.
+pub(crate) fn rebuild_shard_uid(shard_uid: ShardUId) -> ShardUId {
+    ShardUId { version: u32::MAX, shard_id: shard_uid.shard_id }
+}
@@ -24,11 +24,14 @@
 //!                     for example, all block deltas that are stored in flat storage and a representation
 //!                     of the chain formed by these blocks (because we can't access ChainStore
 //!                     inside flat storage).
+//!
+//! `FlatStorageRebuilder` reconstructs the flat storage of a shard from its trie, without resync.
 
 mod chunk_view;
 pub mod delta;
 mod manager;
 mod metrics;
+mod rebuild;
 mod storage;
 #[cfg(test)]
 pub mod test_utils;
@@ -40,6 +43,7 @@ pub use manager::FlatStorageManager;
 pub use metrics::{
     FlatStorageReshardingShardCatchUpMetrics, FlatStorageReshardingShardSplitMetrics,
 };
+pub use rebuild::FlatStorageRebuilder;
 pub use storage::FlatStorage;
 pub use types::{
     BlockInfo, FetchingStateStatus, FlatStateIterator, FlatStorageCreationStatus, FlatStorageError,
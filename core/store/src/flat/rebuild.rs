@@ -0,0 +1,282 @@
+//! Online rebuild of the flat storage of a single shard.
+//!
+//! A corrupted or inconsistent flat storage could only be fixed by deleting
+//! the data and syncing again. The rebuild reconstructs it from the trie
+//! instead, while the node keeps processing blocks and reading the state
+//! from the flat storage:
+//! 1. `FlatStorageRebuilder::start` pins the flat head of the shard, so that
+//!    the values on disk stay at the flat head. The deltas of the blocks
+//!    processed during the rebuild accumulate in the meantime.
+//! 2. `FlatStorageRebuilder::run` copies the values of the trie at the flat
+//!    head under a separate prefix of `DBCol::FlatState`, then replaces the
+//!    values that differ in a single store update and lets the flat head move
+//!    again. Readers never see a partially rebuilt flat storage.
+//!
+//! If the node is restarted during the rebuild, the flat storage of the shard
+//! is loaded unchanged, and the values copied so far are removed when the
+//! rebuild is started again.
+
+use near_primitives::errors::StorageError;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::state::FlatStateValue;
+
+use super::manager::rebuild_shard_uid;
+use super::{BlockInfo, FlatStorageManager};
+use crate::Trie;
+use crate::adapter::flat_store::FlatStoreAdapter;
+
+/// Number of values written to the flat storage in a single store update.
+const REBUILD_BATCH_SIZE: usize = 100_000;
+
+pub struct FlatStorageRebuilder {
+    manager: FlatStorageManager,
+    store: FlatStoreAdapter,
+    shard_uid: ShardUId,
+    flat_head: BlockInfo,
+    /// Trie of the shard at `flat_head`, i.e. with the state after applying
+    /// the chunk of the block.
+    trie: Trie,
+}
+
+impl FlatStorageRebuilder {
+    /// Starts the rebuild of the flat storage of `shard_uid`, which must be
+    /// loaded and ready. `trie_at` returns the trie of the shard at the flat
+    /// head, which stays pinned until the rebuild ends.
+    pub fn start<E: From<StorageError>>(
+        manager: FlatStorageManager,
+        store: FlatStoreAdapter,
+        shard_uid: ShardUId,
+        trie_at: impl FnOnce(&BlockInfo) -> Result<Trie, E>,
+    ) -> Result<Self, E> {
+        let flat_head = manager.start_rebuild(shard_uid)?;
+        match trie_at(&flat_head) {
+            Ok(trie) => Ok(Self { manager, store, shard_uid, flat_head, trie }),
+            Err(err) => {
+                manager.end_rebuild(shard_uid);
+                Err(err)
+            }
+        }
+    }
+
+    pub fn flat_head(&self) -> &BlockInfo {
+        &self.flat_head
+    }
+
+    /// Copies the values of the trie and swaps them in the flat storage.
+    /// It can take hours for a large shard, so it should run in the background.
+    /// Returns the number of values which were replaced.
+    pub fn run(self) -> Result<u64, StorageError> {
+        let result = self
+            .copy_values()
+            .and_then(|_| self.manager.swap_rebuilt_values(self.shard_uid, &self.flat_head));
+        self.manager.end_rebuild(self.shard_uid);
+        result
+    }
+
+    fn copy_values(&self) -> Result<u64, StorageError> {
+        let shard_uid = self.shard_uid;
+        let rebuild_shard_uid = rebuild_shard_uid(shard_uid);
+        let mut num_values = 0;
+        let mut store_update = self.store.store_update();
+        for item in self.trie.disk_iter()? {
+            let (key, value) = item?;
+            store_update.set(rebuild_shard_uid, key, Some(FlatStateValue::on_disk(&value)));
+            num_values += 1;
+            if num_values % REBUILD_BATCH_SIZE as u64 == 0 {
+                store_update.commit().map_err(|_| StorageError::StorageInternalError)?;
+                store_update = self.store.store_update();
+                tracing::info!(target: "store", ?shard_uid, num_values, "Flat storage rebuild in progress");
+            }
+        }
+        store_update.commit().map_err(|_| StorageError::StorageInternalError)?;
+        tracing::info!(target: "store", ?shard_uid, num_values, flat_head = ?self.flat_head, "Copied the values of the trie for flat storage rebuild");
+        Ok(num_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlatStorageRebuilder;
+    use crate::adapter::StoreAdapter;
+    use crate::adapter::flat_store::FlatStoreAdapter;
+    use crate::flat::manager::rebuild_shard_uid;
+    use crate::flat::test_utils::MockChain;
+    use crate::flat::{
+        FlatStateChanges, FlatStateDelta, FlatStateDeltaMetadata, FlatStorageManager,
+        FlatStorageReadyStatus, FlatStorageStatus,
+    };
+    use crate::test_utils::{TestTriesBuilder, test_populate_trie};
+    use crate::{ShardTries, StorageError};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::state::{FlatStateValue, ValueRef};
+
+    /// Creates a trie with three values and a corrupted flat storage at block
+    /// 0 of `chain`: a stale value and a missing one.
+    fn setup(chain: &MockChain) -> (ShardTries, FlatStoreAdapter, CryptoHash) {
+        let shard_uid = ShardUId::single_shard();
+        let tries = TestTriesBuilder::new().build();
+        let changes = vec![
+            (b"alice".to_vec(), Some(b"1".to_vec())),
+            (b"bob".to_vec(), Some(vec![2; 1000])),
+            (b"carol".to_vec(), Some(b"3".to_vec())),
+        ];
+        let state_root = test_populate_trie(&tries, &CryptoHash::default(), shard_uid, changes);
+        let store = tries.store().store().flat_store();
+        let mut store_update = store.store_update();
+        store_update.set_flat_storage_status(
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_update.set(shard_uid, b"alice".to_vec(), Some(FlatStateValue::on_disk(b"1")));
+        store_update.set(shard_uid, b"dave".to_vec(), Some(FlatStateValue::on_disk(b"4")));
+        store_update.commit().unwrap();
+        (tries, store, state_root)
+    }
+
+    fn start(
+        manager: &FlatStorageManager,
+        tries: &ShardTries,
+        state_root: CryptoHash,
+    ) -> FlatStorageRebuilder {
+        let shard_uid = ShardUId::single_shard();
+        FlatStorageRebuilder::start(manager.clone(), tries.store().flat_store(), shard_uid, |_| {
+            Ok::<_, StorageError>(tries.get_trie_for_shard(shard_uid, state_root))
+        })
+        .unwrap()
+    }
+
+    fn values(store: &FlatStoreAdapter, shard_uid: ShardUId) -> Vec<(Vec<u8>, ValueRef)> {
+        store
+            .iter(shard_uid)
+            .map(|item| {
+                let (key, value) = item.unwrap();
+                (key, value.to_value_ref())
+            })
+            .collect()
+    }
+
+    fn expected_values() -> Vec<(Vec<u8>, ValueRef)> {
+        vec![
+            (b"alice".to_vec(), ValueRef::new(b"1")),
+            (b"bob".to_vec(), ValueRef::new(&[2; 1000])),
+            (b"carol".to_vec(), ValueRef::new(b"3")),
+        ]
+    }
+
+    #[test]
+    fn rebuild_flat_storage() {
+        let shard_uid = ShardUId::single_shard();
+        let chain = MockChain::linear_chain(1);
+        let (tries, store, state_root) = setup(&chain);
+        let manager = FlatStorageManager::new(store.clone());
+        manager.create_flat_storage_for_shard(shard_uid).unwrap();
+
+        let rebuilder = start(&manager, &tries, state_root);
+        assert!(manager.is_rebuilding(shard_uid));
+        assert_eq!(rebuilder.flat_head(), &chain.get_block(0));
+        // The flat storage keeps serving the old values during the rebuild.
+        let flat_storage = manager.get_flat_storage_for_shard(shard_uid).unwrap();
+        let block_hash = chain.get_block_hash(0);
+        assert!(flat_storage.contains_key(&block_hash, b"dave").unwrap());
+        // `bob` is missing and `dave` is stale.
+        assert_eq!(rebuilder.run().unwrap(), 2);
+
+        assert!(!manager.is_rebuilding(shard_uid));
+        assert!(!flat_storage.contains_key(&block_hash, b"dave").unwrap());
+        assert_eq!(
+            manager.get_flat_storage_status(shard_uid),
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) })
+        );
+        assert_eq!(values(&store, shard_uid), expected_values());
+        assert_eq!(store.iter(rebuild_shard_uid(shard_uid)).count(), 0);
+    }
+
+    /// Checks that a node restarted during the rebuild loads the flat storage
+    /// as it was before the rebuild, and that the rebuild can be started again.
+    #[test]
+    fn restart_during_rebuild() {
+        let shard_uid = ShardUId::single_shard();
+        let chain = MockChain::linear_chain(1);
+        let (tries, store, state_root) = setup(&chain);
+        let manager = FlatStorageManager::new(store.clone());
+        manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let old_values = values(&store, shard_uid);
+
+        // The node stops after copying the values, before swapping them in.
+        let rebuilder = start(&manager, &tries, state_root);
+        rebuilder.copy_values().unwrap();
+        drop(rebuilder);
+        assert_eq!(store.iter(rebuild_shard_uid(shard_uid)).count(), 3);
+
+        let manager = FlatStorageManager::new(store.clone());
+        assert_eq!(
+            manager.get_flat_storage_status(shard_uid),
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) })
+        );
+        manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        assert!(!manager.is_rebuilding(shard_uid));
+        assert_eq!(values(&store, shard_uid), old_values);
+
+        // Leave a stale value among the copied ones, it is removed on start.
+        let mut store_update = store.store_update();
+        store_update.set(
+            rebuild_shard_uid(shard_uid),
+            b"erin".to_vec(),
+            Some(FlatStateValue::on_disk(b"5")),
+        );
+        store_update.commit().unwrap();
+        assert_eq!(start(&manager, &tries, state_root).run().unwrap(), 2);
+        assert_eq!(values(&store, shard_uid), expected_values());
+    }
+
+    /// Checks that the blocks processed during the rebuild are applied on top
+    /// of the rebuilt values.
+    #[test]
+    fn deltas_during_rebuild() {
+        let shard_uid = ShardUId::single_shard();
+        let chain = MockChain::linear_chain(3);
+        let (tries, store, state_root) = setup(&chain);
+        let manager = FlatStorageManager::new(store.clone());
+        manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = manager.get_flat_storage_for_shard(shard_uid).unwrap();
+
+        let rebuilder = start(&manager, &tries, state_root);
+        for height in 1..3 {
+            let mut changes = FlatStateChanges::default();
+            changes.insert(b"alice".to_vec(), Some(FlatStateValue::on_disk(&[height as u8])));
+            let delta = FlatStateDelta {
+                changes,
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block(height),
+                    prev_block_with_changes: None,
+                },
+            };
+            flat_storage.add_delta(delta).unwrap().commit().unwrap();
+            // The flat head is pinned until the rebuild ends.
+            flat_storage.update_flat_head(&chain.get_block_hash(height)).unwrap();
+            assert_eq!(
+                manager.get_flat_storage_status(shard_uid),
+                FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) })
+            );
+        }
+        rebuilder.run().unwrap();
+
+        let block_hash = chain.get_block_hash(2);
+        assert_eq!(
+            flat_storage.get_value(&block_hash, b"alice").unwrap().map(|v| v.to_value_ref()),
+            Some(ValueRef::new(&[2]))
+        );
+        assert!(flat_storage.contains_key(&block_hash, b"bob").unwrap());
+        assert!(!flat_storage.contains_key(&block_hash, b"dave").unwrap());
+
+        flat_storage.update_flat_head(&block_hash).unwrap();
+        assert_eq!(
+            manager.get_flat_storage_status(shard_uid),
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(2) })
+        );
+        let mut expected = expected_values();
+        expected[0].1 = ValueRef::new(&[2]);
+        assert_eq!(values(&store, shard_uid), expected);
+    }
+}
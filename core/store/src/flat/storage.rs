@@ -17,7 +17,7 @@ use crate::flat::{FlatStorageReadyStatus, FlatStorageStatus};
 use super::FlatStorageReshardingStatus;
 use super::delta::{CachedFlatStateDelta, FlatStateDelta};
 use super::metrics::FlatStorageMetrics;
-use super::types::FlatStorageError;
+use super::types::{FlatStorageError, FlatStorageResult};
 
 /// FlatStorage stores information on which blocks flat storage current supports key lookups on.
 /// Note that this struct is shared by multiple threads, the chain thread, threads that apply chunks,
@@ -486,6 +486,53 @@ impl FlatStorage {
         Ok(())
     }
 
+    /// Commits `store_update` to the values of the flat storage if its head is
+    /// still `flat_head`. No value is read while the update is committed.
+    pub(crate) fn commit_values_at_head(
+        &self,
+        flat_head: &BlockInfo,
+        store_update: FlatStoreUpdateAdapter,
+    ) -> Result<(), StorageError> {
+        let guard = self.0.write();
+        if guard.flat_head != *flat_head {
+            return Err(StorageError::StorageInconsistentState(format!(
+                "Flat head of shard {} moved from {flat_head:?} to {:?}",
+                guard.shard_uid, guard.flat_head
+            )));
+        }
+        store_update.commit().map_err(|_| StorageError::StorageInternalError)
+    }
+
+    /// Reads up to `limit` flat state entries in `from..to` if `block_hash` is
+    /// the flat head, and returns None otherwise. The key of the next entry in
+    /// the range is returned too if there is one. The flat storage is locked
+    /// while the entries are read, so that the head can't move and change the
+    /// flat state in the middle of a page, and `limit` bounds for how long.
+    pub(crate) fn read_range_at_head(
+        &self,
+        block_hash: &CryptoHash,
+        from: &[u8],
+        to: Option<&[u8]>,
+        limit: usize,
+    ) -> Option<FlatStorageResult<(Vec<(Vec<u8>, FlatStateValue)>, Option<Vec<u8>>)>> {
+        let guard = self.0.read();
+        if &guard.flat_head.hash != block_hash {
+            return None;
+        }
+        let read_page = || {
+            let mut entries = Vec::new();
+            for item in guard.store.iter_range(guard.shard_uid, Some(from), to) {
+                let (key, value) = item?;
+                if entries.len() == limit {
+                    return Ok((entries, Some(key)));
+                }
+                entries.push((key, value));
+            }
+            Ok((entries, None))
+        };
+        Some(read_page())
+    }
+
     pub(crate) fn get_head_hash(&self) -> CryptoHash {
         let guard = self.0.read();
         guard.flat_head.hash
@@ -686,6 +733,58 @@ mod tests {
         assert_eq!(flat_storage.update_flat_head_impl(&flat_head_hash, false), Ok(()));
     }
 
+    #[test]
+    fn read_range_at_head() {
+        let chain = MockChain::linear_chain(3);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store().flat_store();
+        let mut store_update = store.store_update();
+        store_update.set_flat_storage_status(
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_update.set(shard_uid, vec![1, 1], Some(FlatStateValue::value_ref(&[0])));
+        store_update.set(shard_uid, vec![2], Some(FlatStateValue::value_ref(&[0])));
+        for i in 1..3 {
+            let delta = FlatStateDelta {
+                changes: FlatStateChanges::from([(
+                    vec![1, i as u8 + 1],
+                    Some(FlatStateValue::value_ref(&[i as u8])),
+                )]),
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block(i),
+                    prev_block_with_changes: None,
+                },
+            };
+            store_update.set_delta(shard_uid, &delta);
+        }
+        store_update.commit().unwrap();
+
+        let flat_storage_manager = FlatStorageManager::new(store);
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+        let keys_at = |block_hash: &CryptoHash, limit| {
+            let (entries, next_key) =
+                flat_storage.read_range_at_head(block_hash, &[1], Some(&[2]), limit)?.unwrap();
+            Some((entries.into_iter().map(|(key, _)| key).collect::<Vec<_>>(), next_key))
+        };
+
+        assert_eq!(keys_at(&chain.get_block_hash(0), 10), Some((vec![vec![1, 1]], None)));
+        // The deltas of blocks after the head can't be read.
+        assert_eq!(keys_at(&chain.get_block_hash(1), 10), None);
+        flat_storage.update_flat_head(&chain.get_block_hash(1)).unwrap();
+        assert_eq!(keys_at(&chain.get_block_hash(0), 10), None);
+        assert_eq!(
+            keys_at(&chain.get_block_hash(1), 10),
+            Some((vec![vec![1, 1], vec![1, 2]], None))
+        );
+        // A partial page ends with the key of the next entry.
+        assert_eq!(
+            keys_at(&chain.get_block_hash(1), 1),
+            Some((vec![vec![1, 1]], Some(vec![1, 2])))
+        );
+    }
+
     // This tests basic use cases for FlatStorageChunkView and FlatStorage.
     // We created a linear chain with no forks, start with flat head at the genesis block, then
     // moves the flat head forward, which checking that chunk_view.get_ref() still returns the correct
@@ -22,6 +22,24 @@ pub(crate) static DATABASE_OP_LATENCY_HIST: LazyLock<HistogramVec> = LazyLock::n
     .unwrap()
 });
 
+pub(crate) static SHARD_DISK_USAGE_BYTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_shard_disk_usage_bytes",
+        "Approximate number of bytes on disk taken by a shard in a column",
+        &["shard_uid", "column"],
+    )
+    .unwrap()
+});
+
+pub(crate) static SHARD_DISK_USAGE_OVER_SOFT_QUOTA: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_shard_disk_usage_over_soft_quota",
+        "Whether the disk usage of a shard exceeds the configured soft quota",
+        &["shard_uid"],
+    )
+    .unwrap()
+});
+
 // TODO(#9054): Rename the metric to be consistent with "accounting cache".
 pub static CHUNK_CACHE_HITS: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
@@ -232,6 +250,14 @@ pub static COLD_MIGRATION_READS: LazyLock<IntCounterVec> = LazyLock::new(|| {
     )
     .unwrap()
 });
+pub static SPLIT_DB_COLD_READS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_split_db_cold_reads",
+        "Number of reads of the split store which weren't found in the hot store and were made to the cold store, by column.",
+        &["col"],
+    )
+    .unwrap()
+});
 pub static COLD_HEAD_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
     try_create_int_gauge("near_cold_head_height", "Height of the head of cold storage").unwrap()
 });
@@ -257,6 +283,20 @@ pub(crate) static CREATE_STATE_SNAPSHOT_ELAPSED: LazyLock<Histogram> = LazyLock:
     .unwrap()
 });
 
+pub(crate) static CREATE_RESTORE_POINT_ELAPSED: LazyLock<Histogram> = LazyLock::new(|| {
+    try_create_histogram_with_buckets(
+        "near_create_restore_point_elapsed_sec",
+        "Latency of making a restore point of the hot database, in seconds",
+        exponential_buckets(0.01, 1.3, 30).unwrap(),
+    )
+    .unwrap()
+});
+
+pub(crate) static RESTORE_POINTS: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge("near_restore_points", "Number of restore points kept by the node")
+        .unwrap()
+});
+
 pub(crate) static DELETE_STATE_SNAPSHOT_ELAPSED: LazyLock<Histogram> = LazyLock::new(|| {
     try_create_histogram_with_buckets(
         "near_delete_state_snapshot_elapsed_sec",
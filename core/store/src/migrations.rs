@@ -1,4 +1,5 @@
-use crate::db::metadata::{DbKind, KIND_KEY};
+use crate::db::MIGRATION_PROGRESS_KEY;
+use crate::db::metadata::{DB_VERSION, DbKind, DbVersion, KIND_KEY, VERSION_KEY};
 use crate::{DBCol, Store, StoreUpdate};
 use anyhow::{Context, anyhow};
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -114,22 +115,265 @@ impl<'a> BatchedStoreUpdate<'a> {
     }
 }
 
+/// Size of the batches written by [`MigrationContext::checkpoint`].
+const MIGRATION_BATCH_SIZE: usize = 10_000_000;
+/// How often [`MigrationContext::checkpoint`] logs the progress.
+const LOG_PROGRESS_EVERY: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Progress of a database migration, stored in `DBCol::Misc` from the moment
+/// the migration snapshot is taken until the database is at [`DB_VERSION`].
+///
+/// If the node is stopped in the middle of a migration, the next run finds it,
+/// keeps the existing snapshot and resumes the migration to `version + 1`
+/// after `resume_key`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// Version the database is being migrated from.
+    pub version: DbVersion,
+    /// Last key given to [`MigrationContext::checkpoint`] whose batch has been
+    /// written, if any.
+    pub resume_key: Option<Vec<u8>>,
+    /// Number of entries migrated so far.
+    pub entries: u64,
+}
+
+impl MigrationProgress {
+    pub fn read(store: &Store) -> std::io::Result<Option<Self>> {
+        store.get_ser(DBCol::Misc, MIGRATION_PROGRESS_KEY)
+    }
+
+    /// Records that the migration from `version` has started.
+    pub fn start(store: &Store, version: DbVersion) -> std::io::Result<()> {
+        let mut update = store.store_update();
+        let progress = Self { version, resume_key: None, entries: 0 };
+        update.set_ser(DBCol::Misc, MIGRATION_PROGRESS_KEY, &progress)?;
+        update.commit()
+    }
+}
+
+/// Changes of a single column made by a migration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColumnChanges {
+    pub written: u64,
+    pub bytes_written: u64,
+    pub deleted: u64,
+    /// Whether all the entries of the column were deleted.
+    pub cleared: bool,
+}
+
+impl ColumnChanges {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What a migration from `version` to `version + 1` has changed or, when
+/// reported by a dry run, would change.
+#[derive(Debug)]
+pub struct MigrationReport {
+    pub version: DbVersion,
+    pub columns: enum_map::EnumMap<DBCol, ColumnChanges>,
+    /// Entries migrated by the run which was resumed, if any.
+    pub resumed_entries: Option<u64>,
+    pub duration: std::time::Duration,
+}
+
+impl std::fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "version {} to {}", self.version, self.version + 1)?;
+        if let Some(entries) = self.resumed_entries {
+            write!(f, " (resumed after {entries} entries)")?;
+        }
+        write!(f, ", took {:.1?}", self.duration)?;
+        let mut unchanged = true;
+        for (col, changes) in &self.columns {
+            if changes.is_empty() {
+                continue;
+            }
+            unchanged = false;
+            write!(f, "\n  {col}:")?;
+            if changes.cleared {
+                write!(f, " cleared,")?;
+            }
+            write!(
+                f,
+                " {} written ({}), {} deleted",
+                changes.written,
+                bytesize::to_string(changes.bytes_written, true),
+                changes.deleted
+            )?;
+        }
+        if unchanged {
+            write!(f, "\n  no changes")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes of a migration from `version` to `version + 1`, see
+/// [`crate::StoreMigrator::migrate`].
+///
+/// The changes are written in a single batch by [`Self::finish`], together
+/// with the new database version, unless the migration calls
+/// [`Self::checkpoint`]. Long migrations iterating over a column should do so
+/// after every entry, so that the changes are written in batches and the
+/// migration resumes after the last written batch when interrupted.
+pub struct MigrationContext<'a> {
+    store: &'a Store,
+    update: StoreUpdate,
+    batch_size: usize,
+    progress: MigrationProgress,
+    report: MigrationReport,
+    started: std::time::Instant,
+    last_logged: std::time::Instant,
+}
+
+impl<'a> MigrationContext<'a> {
+    /// Continues the interrupted migration from `version`, if there's one.
+    pub fn new(store: &'a Store, version: DbVersion) -> std::io::Result<Self> {
+        let progress = match MigrationProgress::read(store)? {
+            Some(progress) if progress.version == version && progress.resume_key.is_some() => {
+                info!(target: "migrations", version, entries = progress.entries, "Resuming the interrupted migration");
+                progress
+            }
+            _ => MigrationProgress { version, resume_key: None, entries: 0 },
+        };
+        let resumed_entries = progress.resume_key.as_ref().map(|_| progress.entries);
+        let now = std::time::Instant::now();
+        Ok(Self {
+            store,
+            update: store.store_update(),
+            batch_size: 0,
+            progress,
+            report: MigrationReport {
+                version,
+                columns: Default::default(),
+                resumed_entries,
+                duration: Default::default(),
+            },
+            started: now,
+            last_logged: now,
+        })
+    }
+
+    /// The store to read from. Reads don't see the changes which haven't
+    /// been written yet.
+    pub fn store(&self) -> &'a Store {
+        self.store
+    }
+
+    /// Key after which an interrupted migration resumes, see [`Self::checkpoint`].
+    pub fn resume_key(&self) -> Option<&[u8]> {
+        self.progress.resume_key.as_deref()
+    }
+
+    fn record_write(&mut self, col: DBCol, key: &[u8], value: &[u8]) {
+        let size = key.len() + value.len() + 8;
+        self.batch_size += size;
+        let changes = &mut self.report.columns[col];
+        changes.written += 1;
+        changes.bytes_written += size as u64;
+    }
+
+    pub fn set(&mut self, col: DBCol, key: &[u8], value: &[u8]) {
+        self.record_write(col, key, value);
+        self.update.set(col, key, value);
+    }
+
+    pub fn set_ser<T: BorshSerialize>(
+        &mut self,
+        col: DBCol,
+        key: &[u8],
+        value: &T,
+    ) -> std::io::Result<()> {
+        let value = borsh::to_vec(value)?;
+        self.set(col, key, &value);
+        Ok(())
+    }
+
+    pub fn insert_ser<T: BorshSerialize>(
+        &mut self,
+        col: DBCol,
+        key: &[u8],
+        value: &T,
+    ) -> std::io::Result<()> {
+        let value = borsh::to_vec(value)?;
+        self.record_write(col, key, &value);
+        self.update.insert(col, key.to_vec(), value);
+        Ok(())
+    }
+
+    pub fn delete(&mut self, col: DBCol, key: &[u8]) {
+        self.report.columns[col].deleted += 1;
+        self.update.delete(col, key);
+    }
+
+    pub fn delete_all(&mut self, col: DBCol) {
+        self.report.columns[col].cleared = true;
+        self.update.delete_all(col);
+    }
+
+    /// Marks the entries up to `key` as migrated. Once the batch is big
+    /// enough, it's written together with `key`, so that an interrupted
+    /// migration resumes after it. Returns whether the batch was written, after
+    /// which the reads see the changes made so far.
+    pub fn checkpoint(&mut self, key: &[u8]) -> std::io::Result<bool> {
+        self.progress.entries += 1;
+        if self.batch_size < MIGRATION_BATCH_SIZE {
+            return Ok(false);
+        }
+        self.progress.resume_key = Some(key.to_vec());
+        let mut update = std::mem::replace(&mut self.update, self.store.store_update());
+        update.set_ser(DBCol::Misc, MIGRATION_PROGRESS_KEY, &self.progress)?;
+        update.commit()?;
+        self.batch_size = 0;
+        if self.last_logged.elapsed() >= LOG_PROGRESS_EVERY {
+            self.last_logged = std::time::Instant::now();
+            info!(target: "migrations", version = self.progress.version, entries = self.progress.entries, elapsed = ?self.started.elapsed(), "Migration in progress");
+        }
+        Ok(true)
+    }
+
+    /// Writes the remaining changes and sets the database version to
+    /// `version + 1`.
+    pub fn finish(mut self) -> std::io::Result<MigrationReport> {
+        let version = self.progress.version + 1;
+        if version == DB_VERSION {
+            self.update.delete(DBCol::Misc, MIGRATION_PROGRESS_KEY);
+        } else {
+            let progress = MigrationProgress { version, resume_key: None, entries: 0 };
+            self.update.set_ser(DBCol::Misc, MIGRATION_PROGRESS_KEY, &progress)?;
+        }
+        self.update.set(DBCol::DbVersion, VERSION_KEY, version.to_string().as_bytes());
+        self.update.commit()?;
+        self.report.duration = self.started.elapsed();
+        Ok(self.report)
+    }
+}
+
 /// Migrates the database from version 32 to 33.
 ///
 /// This removes the TransactionResult column and moves it to TransactionResultForBlock.
 /// The new column removes the need for high-latency read-modify-write operations when committing
 /// new blocks.
-pub fn migrate_32_to_33(store: &Store) -> anyhow::Result<()> {
-    let mut update = BatchedStoreUpdate::new(&store, 10_000_000);
-    for row in store.iter_ser::<Vec<ExecutionOutcomeWithIdAndProof>>(DBCol::_TransactionResult) {
-        let (_, mut outcomes) = row?;
+pub fn migrate_32_to_33(ctx: &mut MigrationContext) -> anyhow::Result<()> {
+    let store = ctx.store();
+    // Resume right after the last migrated key.
+    let lower_bound = ctx.resume_key().map(|key| {
+        let mut key = key.to_vec();
+        key.push(0);
+        key
+    });
+    for row in store.iter_range(DBCol::_TransactionResult, lower_bound.as_deref(), None) {
+        let (key, value) = row?;
+        let mut outcomes = Vec::<ExecutionOutcomeWithIdAndProof>::try_from_slice(&value)?;
         // It appears that it was possible that the same entry in the original column contained
         // duplicate outcomes. We remove them here to avoid panicking due to issuing a
         // self-overwriting transaction.
         outcomes.sort_by_key(|outcome| (*outcome.id(), outcome.block_hash));
         outcomes.dedup_by_key(|outcome| (*outcome.id(), outcome.block_hash));
         for outcome in outcomes {
-            update.insert_ser(
+            ctx.insert_ser(
                 DBCol::TransactionResultForBlock,
                 &get_outcome_id_block_hash(outcome.id(), &outcome.block_hash),
                 &ExecutionOutcomeWithProof {
@@ -138,11 +382,9 @@ pub fn migrate_32_to_33(store: &Store) -> anyhow::Result<()> {
                 },
             )?;
         }
+        ctx.checkpoint(&key)?;
     }
-    update.finish()?;
-    let mut delete_old_update = store.store_update();
-    delete_old_update.delete_all(DBCol::_TransactionResult);
-    delete_old_update.commit()?;
+    ctx.delete_all(DBCol::_TransactionResult);
     Ok(())
 }
 
@@ -155,11 +397,14 @@ pub fn migrate_32_to_33(store: &Store) -> anyhow::Result<()> {
 /// If the database has IS_ARCHIVAL key in BlockMisc column set to true, this
 /// overrides value of is_node_archival argument.  Otherwise, the kind of the
 /// resulting database is determined based on that argument.
-pub fn migrate_33_to_34(store: &Store, mut is_node_archival: bool) -> anyhow::Result<()> {
+pub fn migrate_33_to_34(
+    ctx: &mut MigrationContext,
+    mut is_node_archival: bool,
+) -> anyhow::Result<()> {
     const IS_ARCHIVE_KEY: &[u8; 10] = b"IS_ARCHIVE";
 
     let is_store_archival =
-        store.get_ser::<bool>(DBCol::BlockMisc, IS_ARCHIVE_KEY)?.unwrap_or_default();
+        ctx.store().get_ser::<bool>(DBCol::BlockMisc, IS_ARCHIVE_KEY)?.unwrap_or_default();
 
     if is_store_archival != is_node_archival {
         if is_store_archival {
@@ -173,14 +418,12 @@ pub fn migrate_33_to_34(store: &Store, mut is_node_archival: bool) -> anyhow::Re
         is_node_archival = true;
     }
 
-    let mut update = store.store_update();
     if is_store_archival {
-        update.delete(DBCol::BlockMisc, IS_ARCHIVE_KEY);
+        ctx.delete(DBCol::BlockMisc, IS_ARCHIVE_KEY);
     }
     let kind = if is_node_archival { DbKind::Archive } else { DbKind::RPC };
-    update.set(DBCol::DbVersion, KIND_KEY, <&str>::from(kind).as_bytes());
-    update.delete_all(DBCol::_GCCount);
-    update.commit()?;
+    ctx.set(DBCol::DbVersion, KIND_KEY, <&str>::from(kind).as_bytes());
+    ctx.delete_all(DBCol::_GCCount);
     Ok(())
 }
 
@@ -188,10 +431,8 @@ pub fn migrate_33_to_34(store: &Store, mut is_node_archival: bool) -> anyhow::Re
 ///
 /// This involves deleting contents of Peers column which is now
 /// deprecated and no longer used.
-pub fn migrate_34_to_35(store: &Store) -> anyhow::Result<()> {
-    let mut update = store.store_update();
-    update.delete_all(DBCol::_Peers);
-    update.commit()?;
+pub fn migrate_34_to_35(ctx: &mut MigrationContext) -> anyhow::Result<()> {
+    ctx.delete_all(DBCol::_Peers);
     Ok(())
 }
 
@@ -199,13 +440,12 @@ pub fn migrate_34_to_35(store: &Store) -> anyhow::Result<()> {
 ///
 /// This involves rewriting all FlatStateChanges entries in the new format.
 /// The size of that column should not exceed several dozens of entries.
-pub fn migrate_36_to_37(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_36_to_37(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     #[derive(borsh::BorshDeserialize)]
     struct LegacyFlatStateChanges(HashMap<Vec<u8>, Option<near_primitives::state::ValueRef>>);
 
-    let mut update = store.store_update();
-    update.delete_all(DBCol::FlatStateChanges);
-    for result in store.iter(DBCol::FlatStateChanges) {
+    ctx.delete_all(DBCol::FlatStateChanges);
+    for result in ctx.store().iter(DBCol::FlatStateChanges) {
         let (key, old_value) = result?;
         let new_value = borsh::to_vec(&crate::flat::FlatStateChanges(
             LegacyFlatStateChanges::try_from_slice(&old_value)?
@@ -214,9 +454,8 @@ pub fn migrate_36_to_37(store: &Store) -> anyhow::Result<()> {
                 .map(|(key, value_ref)| (key, value_ref.map(|v| FlatStateValue::Ref(v))))
                 .collect(),
         ))?;
-        update.set(DBCol::FlatStateChanges, &key, &new_value);
+        ctx.set(DBCol::FlatStateChanges, &key, &new_value);
     }
-    update.commit()?;
     Ok(())
 }
 
@@ -224,23 +463,21 @@ pub fn migrate_36_to_37(store: &Store) -> anyhow::Result<()> {
 ///
 /// Rewrites FlatStateDeltaMetadata to add a bit to Metadata, `prev_block_with_changes`.
 /// That bit is initialized with a `None` regardless of the corresponding flat state changes.
-pub fn migrate_37_to_38(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_37_to_38(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     #[derive(borsh::BorshDeserialize)]
     struct LegacyFlatStateDeltaMetadata {
         block: crate::flat::BlockInfo,
     }
 
-    let mut update = store.store_update();
-    update.delete_all(DBCol::FlatStateDeltaMetadata);
-    for result in store.iter(DBCol::FlatStateDeltaMetadata) {
+    ctx.delete_all(DBCol::FlatStateDeltaMetadata);
+    for result in ctx.store().iter(DBCol::FlatStateDeltaMetadata) {
         let (key, old_value) = result?;
         let LegacyFlatStateDeltaMetadata { block } =
             LegacyFlatStateDeltaMetadata::try_from_slice(&old_value)?;
         let new_value =
             crate::flat::FlatStateDeltaMetadata { block, prev_block_with_changes: None };
-        update.set(DBCol::FlatStateDeltaMetadata, &key, &borsh::to_vec(&new_value)?);
+        ctx.set(DBCol::FlatStateDeltaMetadata, &key, &borsh::to_vec(&new_value)?);
     }
-    update.commit()?;
     Ok(())
 }
 
@@ -268,7 +505,7 @@ struct LegacyEpochSummaryV38 {
 /// Migrates the database from version 38 to 39.
 ///
 /// Rewrites Epoch summary to include endorsement stats.
-pub fn migrate_38_to_39(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_38_to_39(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     #[derive(BorshSerialize, BorshDeserialize)]
     struct EpochInfoAggregator<T> {
         /// Map from validator index to (num_blocks_produced, num_blocks_expected) so far in the given epoch.
@@ -288,7 +525,7 @@ pub fn migrate_38_to_39(store: &Store) -> anyhow::Result<()> {
     type LegacyEpochInfoAggregator = EpochInfoAggregator<ValidatorStats>;
     type NewEpochInfoAggregator = EpochInfoAggregator<ChunkStats>;
 
-    let mut update = store.store_update();
+    let store = ctx.store();
 
     // Update EpochInfoAggregator
     let maybe_legacy_aggregator: Option<LegacyEpochInfoAggregator> =
@@ -317,7 +554,7 @@ pub fn migrate_38_to_39(store: &Store) -> anyhow::Result<()> {
             epoch_id: legacy_aggregator.epoch_id,
             last_block_hash: legacy_aggregator.last_block_hash,
         };
-        update.set_ser(DBCol::EpochInfo, AGGREGATOR_KEY, &new_aggregator)?;
+        ctx.set_ser(DBCol::EpochInfo, AGGREGATOR_KEY, &new_aggregator)?;
     }
 
     // Update EpochSummary
@@ -344,10 +581,8 @@ pub fn migrate_38_to_39(store: &Store) -> anyhow::Result<()> {
                 .collect(),
             next_next_epoch_version: legacy_summary.next_version,
         };
-        update.set(DBCol::EpochValidatorInfo, &key, &borsh::to_vec(&new_value)?);
+        ctx.set(DBCol::EpochValidatorInfo, &key, &borsh::to_vec(&new_value)?);
     }
-
-    update.commit()?;
     Ok(())
 }
 
@@ -355,12 +590,10 @@ pub fn migrate_38_to_39(store: &Store) -> anyhow::Result<()> {
 ///
 /// This involves deleting contents of _ReceiptIdToShardId column which is now
 /// deprecated and no longer used.
-pub fn migrate_39_to_40(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_39_to_40(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     let _span =
         tracing::info_span!(target: "migrations", "Deleting contents of deprecated _ReceiptIdToShardId column").entered();
-    let mut update = store.store_update();
-    update.delete_all(DBCol::_ReceiptIdToShardId);
-    update.commit()?;
+    ctx.delete_all(DBCol::_ReceiptIdToShardId);
     Ok(())
 }
 
@@ -368,7 +601,7 @@ pub fn migrate_39_to_40(store: &Store) -> anyhow::Result<()> {
 ///
 /// The migration replaces non-enum StoredChunkStateTransitionData struct with its enum version V1.
 /// NOTE: The data written by this migration is overridden by migrate_42_to_43 to a different format.
-pub fn migrate_40_to_41(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_40_to_41(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     #[derive(BorshDeserialize)]
     pub struct DeprecatedStoredChunkStateTransitionData {
         pub base_state: PartialState,
@@ -377,8 +610,7 @@ pub fn migrate_40_to_41(store: &Store) -> anyhow::Result<()> {
 
     let _span =
         tracing::info_span!(target: "migrations", "Replacing StoredChunkStateTransitionData with its enum version V1").entered();
-    let mut update = store.store_update();
-    for result in store.iter(DBCol::StateTransitionData) {
+    for result in ctx.store().iter(DBCol::StateTransitionData) {
         let (key, old_value) = result?;
         let DeprecatedStoredChunkStateTransitionData { base_state, receipts_hash } =
             DeprecatedStoredChunkStateTransitionData::try_from_slice(&old_value)?;
@@ -389,16 +621,15 @@ pub fn migrate_40_to_41(store: &Store) -> anyhow::Result<()> {
                 contract_accesses: Default::default(),
             },
         ))?;
-        update.set(DBCol::StateTransitionData, &key, &new_value);
+        ctx.set(DBCol::StateTransitionData, &key, &new_value);
     }
-    update.commit()?;
     Ok(())
 }
 
 /// Migrates the database from version 41 to 42.
 ///
 /// This rewrites the contents of the StateDlInfos column
-pub fn migrate_41_to_42(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_41_to_42(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     #[derive(BorshSerialize, BorshDeserialize)]
     struct LegacyShardInfo(ShardId, ChunkHash);
 
@@ -408,9 +639,7 @@ pub fn migrate_41_to_42(store: &Store) -> anyhow::Result<()> {
         shards: Vec<LegacyShardInfo>,
     }
 
-    let mut update = store.store_update();
-
-    for row in store.iter_ser::<LegacyStateSyncInfo>(DBCol::StateDlInfos) {
+    for row in ctx.store().iter_ser::<LegacyStateSyncInfo>(DBCol::StateDlInfos) {
         let (key, LegacyStateSyncInfo { sync_hash, shards }) =
             row.context("failed deserializing legacy StateSyncInfo in StateDlInfos")?;
 
@@ -423,11 +652,9 @@ pub fn migrate_41_to_42(store: &Store) -> anyhow::Result<()> {
         let shards =
             shards.into_iter().map(|LegacyShardInfo(shard_id, _chunk_hash)| shard_id).collect();
         let new_info = StateSyncInfo::V0(StateSyncInfoV0 { sync_hash, shards });
-        update
-            .set_ser(DBCol::StateDlInfos, &key, &new_info)
+        ctx.set_ser(DBCol::StateDlInfos, &key, &new_info)
             .context("failed writing to StateDlInfos")?;
     }
-    update.commit()?;
     Ok(())
 }
 
@@ -465,11 +692,10 @@ struct DeprecatedStoredChunkStateTransitionDataV3 {
 /// Migrates the database from version 42 to 43.
 ///
 /// Merges versions V1-V3 of StoredChunkStateTransitionData into a single version.
-pub fn migrate_42_to_43(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_42_to_43(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     let _span =
         tracing::info_span!(target: "migrations", "Merging versions V1-V3 of StoredChunkStateTransitionData into single version").entered();
-    let mut update = store.store_update();
-    for result in store.iter(DBCol::StateTransitionData) {
+    for result in ctx.store().iter(DBCol::StateTransitionData) {
         let (key, old_value) = result?;
 
         let old_data = DeprecatedStoredChunkStateTransitionDataEnum::try_from_slice(&old_value).map_err(|err| {
@@ -511,20 +737,17 @@ pub fn migrate_42_to_43(store: &Store) -> anyhow::Result<()> {
                 contract_accesses,
                 contract_deploys,
             }))?;
-        update.set(DBCol::StateTransitionData, &key, &new_value);
+        ctx.set(DBCol::StateTransitionData, &key, &new_value);
     }
-    update.commit()?;
     Ok(())
 }
 
 /// Migrates the database from version 44 to 45.
 ///
 /// Removes STATE_TRANSITION_START_HEIGHTS key from DBCol::Misc that is no longer needed.
-pub fn migrate_44_to_45(store: &Store) -> anyhow::Result<()> {
+pub fn migrate_44_to_45(ctx: &mut MigrationContext) -> anyhow::Result<()> {
     pub const STATE_TRANSITION_START_HEIGHTS: &[u8] = b"STATE_TRANSITION_START_HEIGHTS";
 
-    let mut update = store.store_update();
-    update.delete(DBCol::Misc, STATE_TRANSITION_START_HEIGHTS);
-    update.commit()?;
+    ctx.delete(DBCol::Misc, STATE_TRANSITION_START_HEIGHTS);
     Ok(())
 }
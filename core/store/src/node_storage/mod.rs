@@ -157,6 +157,21 @@ impl NodeStorage {
         self.get_split_db().map(|split_db| Store { storage: split_db })
     }
 
+    /// Returns the split store, for the chain data lookups of the view client
+    /// of an archival node which doesn't use the split store for everything,
+    /// see `SplitStorageConfig::enable_cold_read_through_view_client`. The
+    /// lookups of the data garbage collected from the hot store read through
+    /// to the cold store.
+    ///
+    /// Not available until the initial migration to the cold store is
+    /// finished: until then the hot store keeps all the data.
+    pub fn get_read_through_store(&self) -> io::Result<Option<Store>> {
+        if self.get_hot_store().get_db_kind()? != Some(metadata::DbKind::Hot) {
+            return Ok(None);
+        }
+        Ok(self.get_split_store())
+    }
+
     pub fn get_split_db(&self) -> Option<Arc<SplitDB>> {
         self.cold_storage
             .as_ref()
@@ -1,10 +1,15 @@
 use crate::config::ArchivalConfig;
+use crate::db::Database;
 use crate::db::rocksdb::RocksDB;
 use crate::db::rocksdb::snapshot::{Snapshot, SnapshotError, SnapshotRemoveError};
 use crate::metadata::{DB_VERSION, DbKind, DbMetadata, DbVersion};
+use crate::migrations::{MigrationContext, MigrationProgress, MigrationReport};
 use crate::{DBCol, DBTransaction, Mode, NodeStorage, Store, StoreConfig, Temperature};
 use std::sync::Arc;
 
+/// Directory of the database copy migrated by [`StoreOpener::dry_run_migrations`].
+const MIGRATION_DRY_RUN_DIR: &str = "migration-dry-run";
+
 #[derive(Debug, thiserror::Error)]
 pub enum StoreOpenerError {
     /// I/O or RocksDB-level error while opening or accessing the database.
@@ -345,6 +350,63 @@ impl<'a> StoreOpener<'a> {
         Ok((hot_snapshot, cold_snapshot))
     }
 
+    /// Runs the pending migrations on copies of the databases and reports
+    /// what they changed, leaving the databases untouched.
+    ///
+    /// The copies are RocksDB checkpoints, i.e. hard links to the files of the
+    /// databases, in the `migration-dry-run` directory of each database. They
+    /// are deleted afterwards.
+    pub fn dry_run_migrations(
+        &self,
+    ) -> Result<Vec<(Temperature, MigrationReport)>, StoreOpenerError> {
+        let mut reports = vec![];
+        for opener in std::iter::once(&self.hot).chain(&self.cold) {
+            let dry_run = Self::dry_run(opener, &self.migrator)?;
+            reports.extend(dry_run.into_iter().map(|report| (opener.temp, report)));
+        }
+        Ok(reports)
+    }
+
+    fn dry_run(
+        opener: &DBOpener,
+        migrator: &Option<&dyn StoreMigrator>,
+    ) -> Result<Vec<MigrationReport>, StoreOpenerError> {
+        let metadata = opener.get_metadata()?.ok_or(StoreOpenerError::DbDoesNotExist)?;
+        let version = metadata.version;
+        if version == DB_VERSION {
+            return Ok(vec![]);
+        }
+        let migrator = Self::get_migrator(version, migrator)?;
+
+        let path = opener.path.join(MIGRATION_DRY_RUN_DIR);
+        if path.exists() {
+            // Left over by an interrupted dry run.
+            std::fs::remove_dir_all(&path)?;
+        }
+        tracing::info!(target: "db_opener", path=%path.display(), "Creating a copy of the database for the migration dry run");
+        {
+            let (db, _) = opener.open(Mode::ReadWriteExisting, version)?;
+            db.create_checkpoint(&path, None).map_err(StoreOpenerError::CheckpointError)?;
+        }
+        let copy = DBOpener { path: path.clone(), config: opener.config, temp: opener.temp };
+        let reports = Self::run_migrations(Mode::ReadWriteExisting, &copy, migrator, version);
+        std::fs::remove_dir_all(&path)?;
+        reports
+    }
+
+    /// Replaces the databases with their migration snapshots, rolling back the
+    /// migration which failed or was interrupted. Returns the paths of the
+    /// restored snapshots.
+    pub fn restore_migration_snapshots(&self) -> std::io::Result<Vec<std::path::PathBuf>> {
+        let mut restored = vec![];
+        for opener in std::iter::once(&self.hot).chain(&self.cold) {
+            if let Some(path) = Snapshot::restore(&opener.path, opener.config)? {
+                restored.push(path);
+            }
+        }
+        Ok(restored)
+    }
+
     // Creates the DB if it doesn't exist.
     fn ensure_created(mode: Mode, opener: &DBOpener) -> Result<(), StoreOpenerError> {
         let meta = opener.get_metadata()?;
@@ -440,7 +502,32 @@ impl<'a> StoreOpener<'a> {
             });
         }
 
-        // Figure out if we have migrator which supports the database version.
+        let migrator = Self::get_migrator(version, migrator)?;
+        let snapshot = Self::migration_snapshot(mode, opener, version)?;
+        Self::run_migrations(mode, opener, migrator, version)?;
+
+        if cfg!(feature = "nightly") {
+            let version = 10000;
+            tracing::info!(target: "db_opener", path=%opener.path.display(),
+            "Setting the database version to {version} for nightly");
+
+            // Set some dummy value to avoid conflict with other migrations from
+            // nightly features.
+            let store = Self::open_store(mode, opener, DB_VERSION)?;
+            store.set_db_version(version)?;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Returns the migrator if it supports migrating from `version`.
+    fn get_migrator<'m>(
+        version: DbVersion,
+        migrator: &Option<&'m dyn StoreMigrator>,
+    ) -> Result<&'m dyn StoreMigrator, StoreOpenerError> {
+        if version > DB_VERSION {
+            return Err(StoreOpenerError::DbVersionTooNew { got: version, want: DB_VERSION });
+        }
         let migrator = migrator
             .ok_or(StoreOpenerError::DbVersionMismatch { got: version, want: DB_VERSION })?;
         if let Err(release) = migrator.check_support(version) {
@@ -450,10 +537,40 @@ impl<'a> StoreOpener<'a> {
                 latest_release: release,
             });
         }
+        Ok(migrator)
+    }
 
+    /// Creates the snapshot of the database before migrating it from
+    /// `version`. If a previous migration was interrupted, the snapshot
+    /// created before it is kept instead, since the database has already been
+    /// partially migrated.
+    fn migration_snapshot(
+        mode: Mode,
+        opener: &DBOpener,
+        version: DbVersion,
+    ) -> Result<Snapshot, StoreOpenerError> {
+        {
+            let store = Self::open_store(mode, opener, version)?;
+            if let Some(progress) = MigrationProgress::read(&store)? {
+                tracing::info!(target: "db_opener", path=%opener.path.display(), from_version = progress.version, "Resuming the interrupted database migration");
+                return Ok(opener.existing_snapshot());
+            }
+        }
         let snapshot = opener.snapshot()?;
+        let store = Self::open_store(mode, opener, version)?;
+        MigrationProgress::start(&store, version)?;
+        Ok(snapshot)
+    }
 
-        for version in version..DB_VERSION {
+    /// Migrates the database from `from_version` to [`DB_VERSION`].
+    fn run_migrations(
+        mode: Mode,
+        opener: &DBOpener,
+        migrator: &dyn StoreMigrator,
+        from_version: DbVersion,
+    ) -> Result<Vec<MigrationReport>, StoreOpenerError> {
+        let mut reports = vec![];
+        for version in from_version..DB_VERSION {
             tracing::info!(target: "db_opener", path=%opener.path.display(),
                            "Migrating the database from version {} to {}",
                            version, version + 1);
@@ -464,22 +581,13 @@ impl<'a> StoreOpener<'a> {
             // be better to wrap it in the ColdDB object instead.
 
             let store = Self::open_store(mode, opener, version)?;
-            migrator.migrate(&store, version).map_err(StoreOpenerError::MigrationError)?;
-            store.set_db_version(version + 1)?;
-        }
-
-        if cfg!(feature = "nightly") {
-            let version = 10000;
-            tracing::info!(target: "db_opener", path=%opener.path.display(),
-            "Setting the database version to {version} for nightly");
-
-            // Set some dummy value to avoid conflict with other migrations from
-            // nightly features.
-            let store = Self::open_store(mode, opener, DB_VERSION)?;
-            store.set_db_version(version)?;
+            let mut ctx = MigrationContext::new(&store, version)?;
+            migrator.migrate(&mut ctx, version).map_err(StoreOpenerError::MigrationError)?;
+            let report = ctx.finish()?;
+            tracing::info!(target: "db_opener", path=%opener.path.display(), "Migrated the database {report}");
+            reports.push(report);
         }
-
-        Ok(snapshot)
+        Ok(reports)
     }
 
     fn open_store(
@@ -568,6 +676,11 @@ impl<'a> DBOpener<'a> {
     fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
         Snapshot::new(&self.path, &self.config, self.temp)
     }
+
+    /// Returns the snapshot created before an interrupted migration.
+    fn existing_snapshot(&self) -> Snapshot {
+        Snapshot::existing(&self.path, &self.config)
+    }
 }
 
 pub trait StoreMigrator {
@@ -584,14 +697,15 @@ pub trait StoreMigrator {
 
     /// Performs database migration from given version to the next one.
     ///
-    /// The function only does single migration from `version` to `version + 1`.
-    /// It doesn’t update database’s metadata (i.e. what version is stored in
-    /// the database) which is responsibility of the caller.
+    /// The function only does single migration from `version` to `version + 1`,
+    /// making all the changes through `ctx`.  It doesn’t update database’s
+    /// metadata (i.e. what version is stored in the database) which is done by
+    /// the caller with the last batch of the changes.
     ///
     /// **Panics** if `version` is not supported (the caller is supposed to
     /// check support via [`Self::check_support`] method) or if it’s greater or
     /// equal to [`DB_VERSION`].
-    fn migrate(&self, store: &Store, version: DbVersion) -> anyhow::Result<()>;
+    fn migrate(&self, ctx: &mut MigrationContext, version: DbVersion) -> anyhow::Result<()>;
 }
 
 /// Creates checkpoint of hot storage in `home_dir.join(checkpoint_relative_path)`
@@ -746,4 +860,59 @@ mod tests {
         check_keys_existence(&store.get_hot_store(), &DBCol::Chunks, &keys, false);
         check_keys_existence(&store.get_hot_store(), &DBCol::BlockHeader, &keys, false);
     }
+
+    /// Migrator of the last version which writes the key it resumed after.
+    struct TestMigrator;
+
+    impl StoreMigrator for TestMigrator {
+        fn check_support(&self, _version: DbVersion) -> Result<(), &'static str> {
+            Ok(())
+        }
+
+        fn migrate(&self, ctx: &mut MigrationContext, version: DbVersion) -> anyhow::Result<()> {
+            assert_eq!(version, DB_VERSION - 1);
+            let resume_key = ctx.resume_key().unwrap_or_default().to_vec();
+            ctx.set(DBCol::BlockMisc, b"RESUMED_AFTER", &resume_key);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_migration_dry_run_and_resume() {
+        let (_home_dir, opener) = NodeStorage::test_opener();
+        let opener = opener.with_migrator(&TestMigrator);
+        let resumed_after = |store: &Store| store.get(DBCol::BlockMisc, b"RESUMED_AFTER").unwrap();
+        {
+            // A migration to the last version interrupted after "key".
+            let store = opener.open().unwrap().get_hot_store();
+            store.set_db_version(DB_VERSION - 1).unwrap();
+            let mut update = store.store_update();
+            let progress = MigrationProgress {
+                version: DB_VERSION - 1,
+                resume_key: Some(b"key".to_vec()),
+                entries: 10,
+            };
+            update.set_ser(DBCol::Misc, crate::db::MIGRATION_PROGRESS_KEY, &progress).unwrap();
+            update.commit().unwrap();
+        }
+
+        let reports = opener.dry_run_migrations().unwrap();
+        assert_eq!(reports.len(), 1);
+        let (temp, report) = &reports[0];
+        assert_eq!(*temp, Temperature::Hot);
+        assert_eq!(report.version, DB_VERSION - 1);
+        assert_eq!(report.resumed_entries, Some(10));
+        assert_eq!(report.columns[DBCol::BlockMisc].written, 1);
+        assert!(!opener.path().join(MIGRATION_DRY_RUN_DIR).exists());
+        {
+            // The dry run left the database untouched.
+            let store = opener.open_unsafe().unwrap().get_hot_store();
+            assert_eq!(store.get_db_version().unwrap(), Some(DB_VERSION - 1));
+            assert!(resumed_after(&store).is_none());
+        }
+
+        let store = opener.open().unwrap().get_hot_store();
+        assert_eq!(resumed_after(&store).as_deref(), Some(&b"key"[..]));
+        assert_eq!(MigrationProgress::read(&store).unwrap(), None);
+    }
 }
@@ -123,14 +123,21 @@ impl Store {
 
     /// Saves state (`State` and `FlatState` columns) to given file.
     ///
+    /// See [`Self::save_columns_to_file`] for description of the file format.
+    pub fn save_state_to_file(&self, filename: &Path) -> io::Result<()> {
+        self.save_columns_to_file(&STATE_COLUMNS, filename)
+    }
+
+    /// Saves raw contents of given columns to given file.
+    ///
     /// The format of the file is a list of `(column_index as u8, key_length as
     /// u32, key, value_length as u32, value)` records terminated by a single
-    /// 255 byte.  `column_index` refers to state columns listed in
-    /// `STATE_COLUMNS` array.
-    pub fn save_state_to_file(&self, filename: &Path) -> io::Result<()> {
+    /// 255 byte.  `column_index` refers to the position of the column in
+    /// `columns`, so the same list must be passed when loading the file.
+    pub fn save_columns_to_file(&self, columns: &[DBCol], filename: &Path) -> io::Result<()> {
         let file = File::create(filename)?;
         let mut file = std::io::BufWriter::new(file);
-        for (column_index, &column) in STATE_COLUMNS.iter().enumerate() {
+        for (column_index, &column) in columns.iter().enumerate() {
             assert!(column_index < STATE_FILE_END_MARK.into());
             let column_index: u8 = column_index.try_into().unwrap();
             for item in self.storage.iter_raw_bytes(column) {
@@ -143,7 +150,7 @@ impl Store {
 
     /// Loads state (`State` and `FlatState` columns) from given file.
     ///
-    /// See [`Self::save_state_to_file`] for description of the file format.
+    /// See [`Self::save_columns_to_file`] for description of the file format.
     #[tracing::instrument(
         level = "info",
         // FIXME: start moving things into tighter modules so that its easier to selectively trace
@@ -154,6 +161,12 @@ impl Store {
         fields(filename = %filename.display())
     )]
     pub fn load_state_from_file(&self, filename: &Path) -> io::Result<()> {
+        self.load_columns_from_file(&STATE_COLUMNS, filename)
+    }
+
+    /// Loads raw contents of columns from a file written by
+    /// [`Self::save_columns_to_file`] with the same `columns`.
+    pub fn load_columns_from_file(&self, columns: &[DBCol], filename: &Path) -> io::Result<()> {
         let file = File::open(filename)?;
         let mut file = std::io::BufReader::new(file);
         let mut transaction = DBTransaction::new();
@@ -163,7 +176,7 @@ impl Store {
                 break;
             }
             let (key, value) = BorshDeserialize::deserialize_reader(&mut file)?;
-            transaction.set(STATE_COLUMNS[usize::from(column)], key, value);
+            transaction.set(columns[usize::from(column)], key, value);
         }
         self.storage.write(transaction)
     }
@@ -0,0 +1,289 @@
+//! Sampled heat map of trie keys read during block processing.
+//!
+//! Every `sample_rate`-th key read through a non-view trie is attributed to a
+//! [`TrieKeyPrefix`], i.e. the trie column, the account and, for contract
+//! storage, a few leading bytes of the storage key.  The counts are kept per
+//! shard and periodically saved to [`DBCol::Misc`], where they can be analyzed
+//! with `neard view-state trie-access-heat-map` to decide what is worth
+//! prefetching or keeping in the trie caches.
+
+use crate::config::TrieAccessHeatMapConfig;
+use crate::db::TRIE_ACCESS_HEAT_MAP_KEY;
+use crate::{DBCol, Store};
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::trie_key::{col, trie_key_parsers};
+use near_primitives::types::AccountId;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Group of trie keys whose reads are counted together.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TrieKeyPrefix {
+    /// Column of the keys, see `near_primitives::trie_key::col`.
+    pub column: u8,
+    /// Account the keys belong to, for columns which have one.
+    pub account_id: Option<AccountId>,
+    /// Leading bytes of the storage key, for contract data only.
+    pub data_prefix: Vec<u8>,
+}
+
+impl TrieKeyPrefix {
+    fn from_raw_key(raw_key: &[u8], contract_data_prefix_len: usize) -> Option<Self> {
+        let column = *raw_key.first()?;
+        let account_id = trie_key_parsers::parse_account_id_from_raw_key(raw_key).ok().flatten();
+        let data_prefix = match &account_id {
+            Some(account_id) if column == col::CONTRACT_DATA => {
+                let data_key =
+                    trie_key_parsers::parse_data_key_from_contract_data_key(raw_key, account_id)
+                        .ok()?;
+                data_key[..data_key.len().min(contract_data_prefix_len)].to_vec()
+            }
+            _ => vec![],
+        };
+        Some(Self { column, account_id, data_prefix })
+    }
+}
+
+/// Heat map of a single shard.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ShardTrieAccessHeatMap {
+    pub shard_uid: ShardUId,
+    /// Number of sampled reads, including the ones of dropped prefixes.
+    pub total_samples: u64,
+    /// Number of sampled reads of each prefix, most read first.
+    pub prefixes: Vec<(TrieKeyPrefix, u64)>,
+}
+
+/// Heat map saved in the database.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TrieAccessHeatMap {
+    /// Sample rate the reads were collected with.
+    pub sample_rate: u64,
+    pub shards: Vec<ShardTrieAccessHeatMap>,
+}
+
+impl TrieAccessHeatMap {
+    /// Returns the heat map last saved by the node, if any.
+    pub fn load(store: &Store) -> io::Result<Option<Self>> {
+        store.get_ser(DBCol::Misc, TRIE_ACCESS_HEAT_MAP_KEY)
+    }
+}
+
+#[derive(Default)]
+struct ShardState {
+    total_samples: u64,
+    prefixes: HashMap<TrieKeyPrefix, u64>,
+}
+
+impl ShardState {
+    /// Returns the prefixes, most read first.
+    fn sorted_prefixes(&self) -> Vec<(TrieKeyPrefix, u64)> {
+        let mut prefixes = self
+            .prefixes
+            .iter()
+            .map(|(prefix, count)| (prefix.clone(), *count))
+            .collect::<Vec<_>>();
+        prefixes.sort_by(|a, b| b.1.cmp(&a.1));
+        prefixes
+    }
+
+    /// Keeps only the `max_prefixes` most read prefixes.
+    fn truncate(&mut self, max_prefixes: usize) {
+        if self.prefixes.len() <= max_prefixes {
+            return;
+        }
+        let mut prefixes = self.sorted_prefixes();
+        prefixes.truncate(max_prefixes);
+        self.prefixes = prefixes.into_iter().collect();
+    }
+}
+
+struct CollectorState {
+    shards: HashMap<ShardUId, ShardState>,
+    last_saved: Instant,
+}
+
+/// Collects the heat map for all non-view tries of a `ShardTries`.
+pub(crate) struct TrieAccessHeatMapCollector {
+    config: TrieAccessHeatMapConfig,
+    store: Store,
+    reads: AtomicU64,
+    state: parking_lot::Mutex<CollectorState>,
+}
+
+impl TrieAccessHeatMapCollector {
+    /// Continues the heat map saved in the database, unless it was collected
+    /// with a different sample rate.
+    pub fn new(config: TrieAccessHeatMapConfig, store: Store) -> Self {
+        let mut shards = HashMap::new();
+        match TrieAccessHeatMap::load(&store) {
+            Ok(Some(heat_map)) if heat_map.sample_rate == config.sample_rate => {
+                for shard in heat_map.shards {
+                    let state = ShardState {
+                        total_samples: shard.total_samples,
+                        prefixes: shard.prefixes.into_iter().collect(),
+                    };
+                    shards.insert(shard.shard_uid, state);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tracing::warn!(target: "store::trie", ?err, "Failed to load trie access heat map");
+            }
+        }
+        Self {
+            config,
+            store,
+            reads: AtomicU64::new(0),
+            state: parking_lot::Mutex::new(CollectorState { shards, last_saved: Instant::now() }),
+        }
+    }
+
+    pub fn record(&self, shard_uid: ShardUId, raw_key: &[u8]) {
+        if self.reads.fetch_add(1, Ordering::Relaxed) % self.config.sample_rate.max(1) != 0 {
+            return;
+        }
+        let Some(prefix) =
+            TrieKeyPrefix::from_raw_key(raw_key, self.config.contract_data_prefix_len)
+        else {
+            return;
+        };
+        let mut state = self.state.lock();
+        let shard = state.shards.entry(shard_uid).or_default();
+        shard.total_samples += 1;
+        *shard.prefixes.entry(prefix).or_default() += 1;
+        // Let the map grow over the limit for a while, so that new prefixes
+        // have a chance to gather some reads before being dropped.
+        if shard.prefixes.len() > 2 * self.config.max_prefixes_per_shard {
+            shard.truncate(self.config.max_prefixes_per_shard);
+        }
+    }
+
+    /// Saves the heat map if it hasn't been saved for `save_period`.
+    pub fn maybe_save(&self) -> io::Result<()> {
+        let heat_map = {
+            let mut state = self.state.lock();
+            if state.last_saved.elapsed() < self.config.save_period.unsigned_abs() {
+                return Ok(());
+            }
+            state.last_saved = Instant::now();
+            let shards = state
+                .shards
+                .iter_mut()
+                .map(|(shard_uid, shard)| {
+                    shard.truncate(self.config.max_prefixes_per_shard);
+                    ShardTrieAccessHeatMap {
+                        shard_uid: *shard_uid,
+                        total_samples: shard.total_samples,
+                        prefixes: shard.sorted_prefixes(),
+                    }
+                })
+                .collect();
+            TrieAccessHeatMap { sample_rate: self.config.sample_rate, shards }
+        };
+        let mut store_update = self.store.store_update();
+        store_update.set_ser(DBCol::Misc, TRIE_ACCESS_HEAT_MAP_KEY, &heat_map)?;
+        store_update.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrieAccessHeatMap, TrieAccessHeatMapCollector, TrieKeyPrefix};
+    use crate::Trie;
+    use crate::adapter::StoreAdapter;
+    use crate::config::TrieAccessHeatMapConfig;
+    use crate::test_utils::{TestTriesBuilder, create_test_store, test_populate_trie};
+    use crate::trie::AccessOptions;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::trie_key::{TrieKey, col};
+    use near_time::Duration;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_trie_access_heat_map() {
+        let store = create_test_store();
+        let config = TrieAccessHeatMapConfig {
+            sample_rate: 1,
+            contract_data_prefix_len: 1,
+            max_prefixes_per_shard: 2,
+            save_period: Duration::ZERO,
+        };
+        let collector = TrieAccessHeatMapCollector::new(config.clone(), store.clone());
+        let shard_uid = ShardUId::single_shard();
+        let alice = "alice.near".parse().unwrap();
+        let contract_data = |key: &[u8]| {
+            TrieKey::ContractData { account_id: "token.near".parse().unwrap(), key: key.to_vec() }
+                .to_vec()
+        };
+        collector.record(shard_uid, &contract_data(b"a1"));
+        collector.record(shard_uid, &contract_data(b"a2"));
+        collector.record(shard_uid, &contract_data(b"b1"));
+        collector.record(shard_uid, &TrieKey::Account { account_id: alice }.to_vec());
+        collector.record(shard_uid, &TrieKey::DelayedReceiptIndices.to_vec());
+        collector.maybe_save().unwrap();
+
+        let heat_map = TrieAccessHeatMap::load(&store).unwrap().unwrap();
+        assert_eq!(heat_map.shards.len(), 1);
+        let shard = &heat_map.shards[0];
+        assert_eq!(shard.total_samples, 5);
+        // Only the 2 most read prefixes are kept.
+        assert_eq!(shard.prefixes.len(), 2);
+        let expected_prefix = TrieKeyPrefix {
+            column: col::CONTRACT_DATA,
+            account_id: Some("token.near".parse().unwrap()),
+            data_prefix: b"a".to_vec(),
+        };
+        assert_eq!(shard.prefixes[0], (expected_prefix, 2));
+
+        // A new collector continues from the saved heat map.
+        let collector = TrieAccessHeatMapCollector::new(config, store.clone());
+        collector.record(shard_uid, &TrieKey::DelayedReceiptIndices.to_vec());
+        collector.maybe_save().unwrap();
+        let heat_map = TrieAccessHeatMap::load(&store).unwrap().unwrap();
+        assert_eq!(heat_map.shards[0].total_samples, 6);
+    }
+
+    /// Reads with `Trie::get` and `Trie::contains_key` are recorded.
+    #[test]
+    fn test_trie_access_heat_map_trie_reads() {
+        let tries = TestTriesBuilder::new().build();
+        let shard_uid = ShardUId::single_shard();
+        let key = TrieKey::Account { account_id: "alice.near".parse().unwrap() }.to_vec();
+        let missing_key = TrieKey::Account { account_id: "bob.near".parse().unwrap() }.to_vec();
+        let root = test_populate_trie(
+            &tries,
+            &Trie::EMPTY_ROOT,
+            shard_uid,
+            vec![(key.clone(), Some(vec![1]))],
+        );
+
+        let config = TrieAccessHeatMapConfig {
+            sample_rate: 1,
+            contract_data_prefix_len: 1,
+            max_prefixes_per_shard: 10,
+            save_period: Duration::ZERO,
+        };
+        let store = tries.store().store();
+        let collector = Arc::new(TrieAccessHeatMapCollector::new(config, store.clone()));
+        let mut trie = tries.get_trie_for_shard(shard_uid, root);
+        trie.set_access_heat_map(collector.clone(), shard_uid);
+        assert_eq!(trie.get(&key, AccessOptions::DEFAULT).unwrap(), Some(vec![1]));
+        assert_eq!(trie.get(&missing_key, AccessOptions::DEFAULT).unwrap(), None);
+        assert!(trie.contains_key(&key, AccessOptions::DEFAULT).unwrap());
+        collector.maybe_save().unwrap();
+
+        let heat_map = TrieAccessHeatMap::load(&store).unwrap().unwrap();
+        let shard = &heat_map.shards[0];
+        assert_eq!(shard.total_samples, 3);
+        let prefix = |account_id: &str| TrieKeyPrefix {
+            column: col::ACCOUNT,
+            account_id: Some(account_id.parse().unwrap()),
+            data_prefix: vec![],
+        };
+        assert_eq!(shard.prefixes, vec![(prefix("alice.near"), 2), (prefix("bob.near"), 1)]);
+    }
+}
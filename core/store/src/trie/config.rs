@@ -1,5 +1,5 @@
 use crate::StoreConfig;
-use crate::config::{PrefetchConfig, TrieCacheConfig};
+use crate::config::{PrefetchConfig, TrieAccessHeatMapConfig, TrieCacheConfig};
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::AccountId;
 use std::str::FromStr;
@@ -37,6 +37,9 @@ pub struct TrieConfig {
     pub load_memtries_for_shards: Vec<ShardUId>,
     /// Whether mem-trie should be loaded for each tracked shard.
     pub load_memtries_for_tracked_shards: bool,
+
+    /// If set, a heat map of trie keys read during block processing is collected.
+    pub access_heat_map: Option<TrieAccessHeatMapConfig>,
 }
 
 impl TrieConfig {
@@ -64,6 +67,7 @@ impl TrieConfig {
         this.kaiching_prefetch_config.clone_from(&config.kaiching_prefetch_config);
         this.load_memtries_for_shards.clone_from(&config.load_memtries_for_shards);
         this.load_memtries_for_tracked_shards = config.load_memtries_for_tracked_shards;
+        this.access_heat_map.clone_from(&config.trie_access_heat_map);
 
         this
     }
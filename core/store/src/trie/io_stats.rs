@@ -0,0 +1,101 @@
+//! Counters of the storage I/O done through a [`Trie`](super::Trie).
+//!
+//! The counters are shared by the tries derived from the same trie, e.g. the
+//! recording trie used to apply a chunk, so the runtime can read them once the
+//! trie is consumed.
+
+use near_primitives::chunk_apply_stats::StorageIoStats;
+use near_primitives::state::FlatStateValue;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Default, Debug)]
+pub struct TrieIoCounters {
+    trie_node_reads: AtomicU64,
+    flat_storage_hits: AtomicU64,
+    flat_storage_misses: AtomicU64,
+    bytes_read: AtomicU64,
+}
+
+impl TrieIoCounters {
+    pub(crate) fn record_trie_node_read(&self, len: usize) {
+        self.trie_node_reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_flat_storage_lookup(&self, value: Option<&FlatStateValue>) {
+        match value {
+            Some(value) => {
+                self.flat_storage_hits.fetch_add(1, Ordering::Relaxed);
+                // The values which are not inlined are read from the trie
+                // storage when dereferenced.
+                if let FlatStateValue::Inlined(value) = value {
+                    self.bytes_read.fetch_add(value.len() as u64, Ordering::Relaxed);
+                }
+            }
+            None => {
+                self.flat_storage_misses.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the reads counted so far. The writes are only known from the
+    /// trie changes, so they are left to the caller.
+    pub fn read_stats(&self) -> StorageIoStats {
+        StorageIoStats {
+            trie_node_reads: self.trie_node_reads.load(Ordering::Relaxed),
+            flat_storage_hits: self.flat_storage_hits.load(Ordering::Relaxed),
+            flat_storage_misses: self.flat_storage_misses.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{TestTriesBuilder, test_populate_trie};
+    use crate::trie::AccessOptions;
+    use crate::{Trie, TrieDBStorage};
+    use near_primitives::shard_layout::ShardUId;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_trie_io_counters() {
+        let shard_uid = ShardUId::single_shard();
+        let tries = TestTriesBuilder::new().build();
+        let changes =
+            vec![(b"alice".to_vec(), Some(vec![1; 100])), (b"bob".to_vec(), Some(vec![2]))];
+        let state_root = test_populate_trie(&tries, &Trie::EMPTY_ROOT, shard_uid, changes);
+
+        // The counters are shared with the recording trie derived from the trie.
+        let storage = TrieDBStorage::new(tries.store(), shard_uid);
+        let trie = Trie::new(Arc::new(storage), state_root, None);
+        let io_counters = trie.io_counters();
+        let trie = trie.recording_reads_new_recorder();
+        assert_eq!(trie.get(b"alice", AccessOptions::DEFAULT).unwrap(), Some(vec![1; 100]));
+        drop(trie);
+
+        let stats = io_counters.read_stats();
+        // At least the root, the leaf and the value.
+        assert!(stats.trie_node_reads >= 3, "{stats:?}");
+        assert!(stats.bytes_read >= 100, "{stats:?}");
+        assert_eq!(stats.flat_storage_hits + stats.flat_storage_misses, 0);
+        assert_eq!(stats.trie_node_writes, 0);
+    }
+
+    /// The nodes served by the shard cache are not read from the store.
+    #[test]
+    fn test_trie_io_counters_skip_shard_cache() {
+        let shard_uid = ShardUId::single_shard();
+        let tries = TestTriesBuilder::new().build();
+        let changes = vec![(b"alice".to_vec(), Some(vec![1; 100]))];
+        let state_root = test_populate_trie(&tries, &Trie::EMPTY_ROOT, shard_uid, changes);
+
+        // The inserted nodes are already in the shard cache.
+        let trie = tries.get_trie_for_shard(shard_uid, state_root);
+        assert_eq!(trie.get(b"alice", AccessOptions::DEFAULT).unwrap(), Some(vec![1; 100]));
+        let stats = trie.io_counters().read_stats();
+        assert_eq!(stats.trie_node_reads, 0, "{stats:?}");
+        assert_eq!(stats.bytes_read, 0, "{stats:?}");
+    }
+}
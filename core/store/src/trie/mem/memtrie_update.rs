@@ -274,6 +274,11 @@ impl<'a, M: ArenaMemory> MemTrieUpdate<'a, M> {
     ) -> Result<(), StorageError> {
         self.generic_insert(0, key, GenericTrieValue::MemtrieOnly(value), AccessOptions::DEFAULT)
     }
+
+    /// Deletes the given key from the trie. Deleting a non-existent key is a no-op.
+    pub fn delete(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        self.generic_delete(0, key, AccessOptions::DEFAULT)
+    }
 }
 
 impl<'a, M: ArenaMemory> MemTrieUpdate<'a, M> {
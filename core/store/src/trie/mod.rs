@@ -2,10 +2,12 @@ use self::mem::flexible_data::value::ValueView;
 use self::trie_storage::TrieMemoryPartialStorage;
 use crate::StorageError;
 use crate::flat::{FlatStateChanges, FlatStorageChunkView};
+use crate::trie::access_heat_map::TrieAccessHeatMapCollector;
 pub use crate::trie::config::TrieConfig;
 pub(crate) use crate::trie::config::{
     DEFAULT_SHARD_CACHE_DELETIONS_QUEUE_CAPACITY, DEFAULT_SHARD_CACHE_TOTAL_SIZE_LIMIT,
 };
+use crate::trie::io_stats::TrieIoCounters;
 pub use crate::trie::nibble_slice::NibbleSlice;
 pub use crate::trie::prefetching_trie_storage::{PrefetchApi, PrefetchError};
 pub use crate::trie::shard_tries::{KeyForStateChanges, ShardTries, WrappedTrieChanges};
@@ -33,12 +35,14 @@ use ops::insert_delete::GenericTrieUpdateInsertDelete;
 #[cfg(test)]
 use ops::interface::{GenericNodeOrIndex, GenericTrieNode, GenericTrieUpdate};
 use ops::interface::{GenericTrieValue, UpdatedNodeId};
+use ops::iter::TrieItem;
 use ops::resharding::{GenericTrieUpdateRetain, RetainMode};
 use parking_lot::{RwLock, RwLockReadGuard};
 pub use raw_node::{Children, RawTrieNode, RawTrieNodeWithSize};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write;
 use std::hash::Hash;
+use std::ops::ControlFlow;
 use std::str;
 use std::sync::Arc;
 pub use trie_recording::{SubtreeSize, TrieRecorder, TrieRecorderStats};
@@ -46,8 +50,10 @@ use trie_storage_update::{
     TrieStorageNodeWithSize, TrieStorageUpdate, UpdatedTrieStorageNodeWithSize,
 };
 
+pub mod access_heat_map;
 mod config;
 mod from_flat;
+pub mod io_stats;
 pub mod iterator;
 pub mod mem;
 mod nibble_slice;
@@ -270,6 +276,11 @@ pub struct Trie {
     // FIXME(nagisa): lets get rid of this field somehow? it seems to be utilized mostly for/in
     // tests.
     use_access_tracker: bool,
+    /// If present, key reads are sampled into the trie access heat map of
+    /// the given shard.
+    access_heat_map: Option<(Arc<TrieAccessHeatMapCollector>, ShardUId)>,
+    /// Storage I/O done through this trie and the ones derived from it.
+    io_counters: Arc<TrieIoCounters>,
 }
 
 /// Trait for reading data from a trie.
@@ -608,6 +619,8 @@ impl Trie {
             use_access_tracker: use_trie_accounting_cache,
             flat_storage_chunk_view,
             recorder: None,
+            access_heat_map: None,
+            io_counters: Default::default(),
         }
     }
 
@@ -616,6 +629,20 @@ impl Trie {
         self.memtries.is_some()
     }
 
+    pub(crate) fn set_access_heat_map(
+        &mut self,
+        collector: Arc<TrieAccessHeatMapCollector>,
+        shard_uid: ShardUId,
+    ) {
+        self.access_heat_map = Some((collector, shard_uid));
+    }
+
+    /// Returns the counters of the storage I/O done through this trie, which
+    /// stay available once the trie is consumed.
+    pub fn io_counters(&self) -> Arc<TrieIoCounters> {
+        self.io_counters.clone()
+    }
+
     /// Helper to simulate gas costs as if flat storage was present.
     pub fn set_use_trie_accounting_cache(&mut self, value: bool) {
         self.use_access_tracker = value;
@@ -646,6 +673,8 @@ impl Trie {
         );
         trie.recorder = Some(recorder);
         trie.use_access_tracker = self.use_access_tracker;
+        trie.access_heat_map.clone_from(&self.access_heat_map);
+        trie.io_counters = self.io_counters.clone();
         trie
     }
 
@@ -755,13 +784,13 @@ impl Trie {
             match access_options.trie_access_tracker.track_mem_lookup(hash) {
                 Some(v) => v,
                 None => {
-                    let v = self.storage.retrieve_raw_bytes(hash)?;
+                    let v = self.retrieve_raw_bytes_counted(hash)?;
                     access_options.trie_access_tracker.track_disk_lookup(*hash, Arc::clone(&v));
                     v
                 }
             }
         } else {
-            self.storage.retrieve_raw_bytes(hash)?
+            self.retrieve_raw_bytes_counted(hash)?
         };
         if access_options.enable_state_witness_recording {
             if let Some(recorder) = &self.recorder {
@@ -771,6 +800,16 @@ impl Trie {
         Ok(result)
     }
 
+    /// Reads the node from the storage, counting it in the I/O stats only if
+    /// it was read from the store.
+    fn retrieve_raw_bytes_counted(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
+        let (v, from_store) = self.storage.retrieve_raw_bytes_from_store(hash)?;
+        if from_store {
+            self.io_counters.record_trie_node_read(v.len());
+        }
+        Ok(v)
+    }
+
     #[cfg(test)]
     fn memory_usage_verify(
         &self,
@@ -1212,6 +1251,7 @@ impl Trie {
     ) -> Result<Option<OptimizedValueRef>, StorageError> {
         let flat_storage_chunk_view = self.flat_storage_chunk_view.as_ref().unwrap();
         let value = flat_storage_chunk_view.get_value(key)?;
+        self.io_counters.record_flat_storage_lookup(value.as_ref());
         if operation_options.enable_state_witness_recording && self.recorder.is_some() {
             // If recording, we need to look up in the trie as well to record the trie nodes,
             // as they are needed to prove the value. Also, it's important that this lookup
@@ -1424,6 +1464,15 @@ impl Trie {
         Ok(bytes.to_vec())
     }
 
+    /// Samples the read of the key into the trie access heat map, if it is
+    /// collected. Called for all key lookups, whether they are served from
+    /// memtries, flat storage or the trie.
+    fn record_access(&self, key: &[u8]) {
+        if let Some((collector, shard_uid)) = &self.access_heat_map {
+            collector.record(*shard_uid, key);
+        }
+    }
+
     /// Check if the column contains a value with the given `key`.
     ///
     /// This method is guaranteed to not inspect the value stored for this key, which would
@@ -1442,6 +1491,7 @@ impl Trie {
         mode: KeyLookupMode,
         opts: AccessOptions,
     ) -> Result<bool, StorageError> {
+        self.record_access(key);
         let use_trie_accounting_cache = mode == KeyLookupMode::MemOrTrie || self.use_access_tracker;
         if self.memtries.is_some() {
             return Ok(self
@@ -1487,6 +1537,7 @@ impl Trie {
         mode: KeyLookupMode,
         opts: AccessOptions,
     ) -> Result<Option<OptimizedValueRef>, StorageError> {
+        self.record_access(key);
         let use_trie_accounting_cache = mode == KeyLookupMode::MemOrTrie || self.use_access_tracker;
         if self.memtries.is_some() {
             self.lookup_from_memory(key, use_trie_accounting_cache, opts, |v| {
@@ -1690,6 +1741,124 @@ impl Trie {
         DiskTrieIterator::new(DiskTrieIteratorInner::new(self), prune_condition)
     }
 
+    /// Returns all the key-value pairs with keys starting with `prefix`, in key order.
+    ///
+    /// If `include_proof` is set, the on-disk trie is iterated and the nodes visited
+    /// along the way are returned too, which proves the returned range against the
+    /// state root. Otherwise flat storage is used if it holds exactly the state of this
+    /// trie, so that no intermediate trie nodes need to be read; the trie is used as a
+    /// fallback when it doesn't.
+    pub fn get_items_with_prefix(
+        &self,
+        prefix: &[u8],
+        include_proof: bool,
+    ) -> Result<(Vec<TrieItem>, Vec<Arc<[u8]>>), StorageError> {
+        if !include_proof {
+            // Only the value refs are collected from flat storage, the values
+            // are read afterwards.
+            let mut flat_entries = vec![];
+            let flat_result = self.flat_storage_chunk_view.as_ref().and_then(|view| {
+                view.iter_prefix(prefix, |key, value| {
+                    flat_entries.push((key, value));
+                    ControlFlow::Continue(())
+                })
+            });
+            if let Some(flat_result) = flat_result {
+                flat_result?;
+                let items = flat_entries
+                    .into_iter()
+                    .map(|(key, value)| self.read_flat_state_value(value).map(|value| (key, value)))
+                    .collect::<Result<_, _>>()?;
+                return Ok((items, vec![]));
+            }
+        }
+        let mut iter = self.disk_iter()?;
+        iter.remember_visited_nodes(include_proof);
+        iter.seek_prefix(prefix)?;
+        let items = (&mut iter).collect::<Result<Vec<_>, _>>()?;
+        Ok((items, iter.into_visited_nodes()))
+    }
+
+    /// Returns up to `limit` key-value pairs with keys starting with `prefix`
+    /// and not smaller than `start_key`, in key order, along with the first
+    /// key of the next page if there is one. The page also ends before the
+    /// total size of its keys and values exceeds `max_bytes`, but it always
+    /// has at least one item.
+    ///
+    /// Flat storage and the trie are used the same way as in
+    /// [`Trie::get_items_with_prefix`]. Only the values of the returned items
+    /// are read.
+    pub fn get_items_with_prefix_paged(
+        &self,
+        prefix: &[u8],
+        start_key: &[u8],
+        limit: usize,
+        max_bytes: usize,
+        include_proof: bool,
+    ) -> Result<(Vec<TrieItem>, Option<Vec<u8>>, Vec<Arc<[u8]>>), StorageError> {
+        let is_page_full = |num_items: usize, page_bytes: &mut usize, item_bytes: usize| {
+            if num_items == limit || (num_items > 0 && *page_bytes + item_bytes > max_bytes) {
+                return true;
+            }
+            *page_bytes += item_bytes;
+            false
+        };
+        if !include_proof {
+            let mut entries = vec![];
+            let mut next_key = None;
+            let mut page_bytes = 0;
+            let flat_result = self.flat_storage_chunk_view.as_ref().and_then(|view| {
+                view.iter_prefix_from(prefix, start_key, |key, value| {
+                    let value_len = match &value {
+                        FlatStateValue::Ref(value_ref) => value_ref.len(),
+                        FlatStateValue::Inlined(value) => value.len(),
+                    };
+                    if is_page_full(entries.len(), &mut page_bytes, key.len() + value_len) {
+                        next_key = Some(key);
+                        return ControlFlow::Break(());
+                    }
+                    entries.push((key, value));
+                    ControlFlow::Continue(())
+                })
+            });
+            if let Some(flat_result) = flat_result {
+                flat_result?;
+                let items = entries
+                    .into_iter()
+                    .map(|(key, value)| self.read_flat_state_value(value).map(|value| (key, value)))
+                    .collect::<Result<_, _>>()?;
+                return Ok((items, next_key, vec![]));
+            }
+        }
+        let mut iter = self.disk_iter()?;
+        iter.remember_visited_nodes(include_proof);
+        iter.seek(std::cmp::max(prefix, start_key))?;
+        let mut items = vec![];
+        let mut next_key = None;
+        let mut page_bytes = 0;
+        for item in &mut iter {
+            let (key, value) = item?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            if is_page_full(items.len(), &mut page_bytes, key.len() + value.len()) {
+                next_key = Some(key);
+                break;
+            }
+            items.push((key, value));
+        }
+        Ok((items, next_key, iter.into_visited_nodes()))
+    }
+
+    fn read_flat_state_value(&self, value: FlatStateValue) -> Result<Vec<u8>, StorageError> {
+        match value {
+            FlatStateValue::Ref(value_ref) => {
+                self.retrieve_value(&value_ref.hash, AccessOptions::DEFAULT)
+            }
+            FlatStateValue::Inlined(value) => Ok(value),
+        }
+    }
+
     /// Grabs a read lock on the trie, so that a memtrie iterator can be
     /// constructed afterward. This is needed because memtries are not
     /// thread-safe.
@@ -2094,6 +2263,98 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_items_with_prefix() {
+        let sid = ShardUId::single_shard();
+        let bid = CryptoHash::default();
+        let tries = TestTriesBuilder::new().with_flat_storage(true).build();
+        let initial = vec![
+            (vec![99, 44, 100, 58, 58, 49], Some(vec![1])),
+            (vec![99, 44, 100, 58, 58, 50], Some(vec![2; 100])),
+            (vec![99, 44, 100, 58, 58, 50, 51], Some(vec![3])),
+            (vec![99, 44, 100, 58, 59], Some(vec![4])),
+        ];
+        test_populate_flat_storage(&tries, sid, &bid, &bid, &initial);
+        let root = test_populate_trie(&tries, &Trie::EMPTY_ROOT, sid, initial);
+        let expected = vec![
+            (vec![99, 44, 100, 58, 58, 50], vec![2; 100]),
+            (vec![99, 44, 100, 58, 58, 50, 51], vec![3]),
+        ];
+
+        let trie = tries.get_trie_with_block_hash_for_shard(sid, root, &bid, true);
+        assert!(trie.has_flat_storage_chunk_view());
+        let (items, proof) = trie.get_items_with_prefix(&[99, 44, 100, 58, 58, 50], false).unwrap();
+        assert_eq!(items, expected);
+        assert!(proof.is_empty());
+        let (items, proof) = trie.get_items_with_prefix(&[99, 44, 100, 58, 58, 50], true).unwrap();
+        assert_eq!(items, expected);
+        assert!(!proof.is_empty());
+
+        // Without flat storage the items are read from the trie.
+        let trie = tries.get_view_trie_for_shard(sid, root);
+        assert!(!trie.has_flat_storage_chunk_view());
+        let (items, _) = trie.get_items_with_prefix(&[99, 44, 100, 58, 58, 50], false).unwrap();
+        assert_eq!(items, expected);
+    }
+
+    #[test]
+    fn test_get_items_with_prefix_paged() {
+        let sid = ShardUId::single_shard();
+        let bid = CryptoHash::default();
+        let tries = TestTriesBuilder::new().with_flat_storage(true).build();
+        let initial = vec![
+            (vec![99, 44, 100, 58, 58, 49], Some(vec![1])),
+            (vec![99, 44, 100, 58, 58, 50], Some(vec![2; 100])),
+            (vec![99, 44, 100, 58, 58, 50, 51], Some(vec![3])),
+            (vec![99, 44, 100, 58, 58, 51], Some(vec![4])),
+            (vec![99, 44, 100, 58, 59], Some(vec![5])),
+        ];
+        test_populate_flat_storage(&tries, sid, &bid, &bid, &initial);
+        let root = test_populate_trie(&tries, &Trie::EMPTY_ROOT, sid, initial);
+        let prefix = [99, 44, 100, 58, 58];
+
+        let flat_trie = tries.get_trie_with_block_hash_for_shard(sid, root, &bid, true);
+        let view_trie = tries.get_view_trie_for_shard(sid, root);
+        for (trie, include_proof) in [(&flat_trie, false), (&flat_trie, true), (&view_trie, false)]
+        {
+            let (items, next_key, _) = trie
+                .get_items_with_prefix_paged(&prefix, &[], 2, usize::MAX, include_proof)
+                .unwrap();
+            assert_eq!(
+                items,
+                vec![
+                    (vec![99, 44, 100, 58, 58, 49], vec![1]),
+                    (vec![99, 44, 100, 58, 58, 50], vec![2; 100])
+                ]
+            );
+            let next_key = next_key.unwrap();
+            assert_eq!(next_key, vec![99, 44, 100, 58, 58, 50, 51]);
+
+            let (items, next_key, proof) = trie
+                .get_items_with_prefix_paged(&prefix, &next_key, 2, usize::MAX, include_proof)
+                .unwrap();
+            assert_eq!(
+                items,
+                vec![
+                    (vec![99, 44, 100, 58, 58, 50, 51], vec![3]),
+                    (vec![99, 44, 100, 58, 58, 51], vec![4])
+                ]
+            );
+            assert_eq!(next_key, None);
+            assert_eq!(proof.is_empty(), !include_proof);
+
+            // The second item doesn't fit in the byte budget left by the
+            // first one, and a single item is returned even if it doesn't fit.
+            for max_bytes in [110, 1] {
+                let (items, next_key, _) = trie
+                    .get_items_with_prefix_paged(&prefix, &[], 2, max_bytes, include_proof)
+                    .unwrap();
+                assert_eq!(items, vec![(vec![99, 44, 100, 58, 58, 49], vec![1])]);
+                assert_eq!(next_key, Some(vec![99, 44, 100, 58, 58, 50]));
+            }
+        }
+    }
+
     #[test]
     fn test_equal_leafs() {
         let initial = vec![
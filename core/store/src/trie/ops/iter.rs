@@ -116,6 +116,14 @@ where
         Ok(())
     }
 
+    /// Position the iterator on the first element with key >= `key`. Unlike
+    /// [`Self::seek_prefix`], the iteration doesn't stop after the keys
+    /// starting with `key`.
+    pub fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> Result<(), StorageError> {
+        self.seek_nibble_slice(NibbleSlice::new(key.as_ref()), false)?;
+        Ok(())
+    }
+
     /// Returns the hash of the last node.
     fn seek_nibble_slice(
         &mut self,
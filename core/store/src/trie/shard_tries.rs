@@ -5,6 +5,7 @@ use crate::adapter::StoreAdapter;
 use crate::adapter::trie_store::{TrieStoreAdapter, TrieStoreUpdateAdapter};
 use crate::flat::FlatStorageManager;
 use crate::trie::TrieRefcountAddition;
+use crate::trie::access_heat_map::TrieAccessHeatMapCollector;
 use crate::trie::config::TrieConfig;
 use crate::trie::mem::loading::load_trie_from_flat_state_and_delta;
 use crate::trie::prefetching_trie_storage::PrefetchingThreadsHandle;
@@ -54,6 +55,8 @@ struct ShardTriesInner {
     /// We would like to apply the same set of trie changes to the child memtrie to keep
     /// a consistent view across forks.
     temp_split_shard_map: RwLock<HashMap<ShardUId, Vec<ShardUId>>>,
+    /// Collects the heat map of keys read by non-view tries, if enabled.
+    access_heat_map: Option<Arc<TrieAccessHeatMapCollector>>,
 }
 
 #[derive(Clone)]
@@ -70,6 +73,10 @@ impl ShardTries {
         let caches = Self::create_initial_caches(&trie_config, &shard_uids, false);
         let view_caches = Self::create_initial_caches(&trie_config, &shard_uids, true);
         metrics::HAS_STATE_SNAPSHOT.set(0);
+        let access_heat_map = trie_config
+            .access_heat_map
+            .clone()
+            .map(|config| Arc::new(TrieAccessHeatMapCollector::new(config, store.store())));
         ShardTries(Arc::new(ShardTriesInner {
             store,
             trie_config,
@@ -81,6 +88,7 @@ impl ShardTries {
             state_snapshot: Default::default(),
             state_snapshot_config,
             temp_split_shard_map: Default::default(),
+            access_heat_map,
         }))
     }
 
@@ -166,13 +174,17 @@ impl ShardTries {
                     children_memtries.insert(shard_uid, memtrie);
                 }
             }
-            Trie::new_with_memtries(
+            let mut trie = Trie::new_with_memtries(
                 storage,
                 memtries,
                 children_memtries,
                 state_root,
                 flat_storage_chunk_view,
-            )
+            );
+            if let Some(access_heat_map) = &self.0.access_heat_map {
+                trie.set_access_heat_map(access_heat_map.clone(), shard_uid);
+            }
+            trie
         }
     }
 
@@ -194,6 +206,15 @@ impl ShardTries {
         self.get_trie_for_shard_internal(shard_uid, state_root, true, None)
     }
 
+    /// Saves the trie access heat map to the database if it is collected and
+    /// it hasn't been saved for a while.
+    pub fn maybe_save_access_heat_map(&self) {
+        let Some(access_heat_map) = &self.0.access_heat_map else { return };
+        if let Err(err) = access_heat_map.maybe_save() {
+            tracing::warn!(target: "store::trie", ?err, "Failed to save trie access heat map");
+        }
+    }
+
     pub fn store_update(&self) -> TrieStoreUpdateAdapter<'static> {
         self.0.store.store_update()
     }
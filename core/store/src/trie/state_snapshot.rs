@@ -3,6 +3,7 @@ use crate::ShardTries;
 use crate::StoreConfig;
 use crate::adapter::StoreAdapter;
 use crate::adapter::trie_store::TrieStoreAdapter;
+use crate::config::StateSnapshotPolicy;
 use crate::flat::{FlatStorageManager, FlatStorageStatus};
 use crate::{DBCol, NodeStorage, checkpoint_hot_storage_and_cleanup_columns, metrics};
 use near_primitives::block::Block;
@@ -12,8 +13,8 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state::PartialState;
 use near_primitives::state_part::PartId;
-use near_primitives::types::ShardIndex;
 use near_primitives::types::StateRoot;
+use near_primitives::types::{BlockHeight, ShardIndex};
 use std::error::Error;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -134,7 +135,14 @@ impl StateSnapshot {
 #[derive(Debug)]
 pub enum StateSnapshotConfig {
     Disabled,
-    Enabled { state_snapshots_dir: PathBuf },
+    Enabled {
+        state_snapshots_dir: PathBuf,
+        /// Directory holding the restore points. Kept separately from
+        /// `state_snapshots_dir`, which is wiped whenever the state snapshot
+        /// is deleted.
+        restore_points_dir: PathBuf,
+        policy: StateSnapshotPolicy,
+    },
 }
 
 pub fn state_snapshots_dir(
@@ -145,6 +153,10 @@ pub fn state_snapshots_dir(
     home_dir.as_ref().join(hot_store_path).join(state_snapshots_subdir)
 }
 
+/// Name of the directory next to the state snapshots directory holding the
+/// restore points.
+const RESTORE_POINTS_SUBDIR: &str = "restore_points";
+
 impl StateSnapshotConfig {
     pub fn enabled(
         home_dir: impl AsRef<Path>,
@@ -156,17 +168,50 @@ impl StateSnapshotConfig {
         // * The best place for checkpoints is within the `hot_store_path`, because that directory is often a separate disk.
         Self::Enabled {
             state_snapshots_dir: state_snapshots_dir(
+                &home_dir,
+                &hot_store_path,
+                state_snapshots_subdir,
+            ),
+            restore_points_dir: state_snapshots_dir(
                 home_dir,
                 hot_store_path,
-                state_snapshots_subdir,
+                RESTORE_POINTS_SUBDIR,
             ),
+            policy: StateSnapshotPolicy::default(),
         }
     }
 
+    /// Sets the policy for taking restore points. Has no effect if state
+    /// snapshots are disabled.
+    pub fn with_policy(mut self, new_policy: StateSnapshotPolicy) -> Self {
+        if let StateSnapshotConfig::Enabled { policy, .. } = &mut self {
+            *policy = new_policy;
+        }
+        self
+    }
+
     pub fn state_snapshots_dir(&self) -> Option<&Path> {
         match self {
             StateSnapshotConfig::Disabled => None,
-            StateSnapshotConfig::Enabled { state_snapshots_dir } => Some(state_snapshots_dir),
+            StateSnapshotConfig::Enabled { state_snapshots_dir, .. } => Some(state_snapshots_dir),
+        }
+    }
+
+    pub fn restore_points_dir(&self) -> Option<&Path> {
+        match self {
+            StateSnapshotConfig::Disabled => None,
+            StateSnapshotConfig::Enabled { restore_points_dir, .. } => Some(restore_points_dir),
+        }
+    }
+
+    /// Returns the restore point policy, or `None` if no restore points
+    /// should be taken.
+    pub fn restore_point_policy(&self) -> Option<&StateSnapshotPolicy> {
+        match self {
+            StateSnapshotConfig::Enabled { policy, .. } if policy.takes_restore_points() => {
+                Some(policy)
+            }
+            _ => None,
         }
     }
 }
@@ -399,4 +444,71 @@ impl ShardTries {
         tracing::info!(target: "runtime", ?snapshot_hash, ?snapshot_path, "Detected and opened a state snapshot.");
         Ok(())
     }
+
+    /// Takes a full checkpoint of the hot database as a restore point for the
+    /// block at `height`. Returns the path of the new restore point, or `None`
+    /// if one already exists for that block.
+    pub fn create_restore_point(
+        &self,
+        height: BlockHeight,
+        block_hash: CryptoHash,
+    ) -> Result<Option<PathBuf>, anyhow::Error> {
+        let _span = tracing::info_span!(target: "state_snapshot", "create_restore_point", height, ?block_hash)
+            .entered();
+        let restore_points_dir = self
+            .state_snapshot_config()
+            .restore_points_dir()
+            .ok_or_else(|| anyhow::anyhow!("State snapshots disabled"))?;
+        let restore_point_dir = restore_points_dir.join(format!("{height}-{block_hash}"));
+        if restore_point_dir.exists() {
+            tracing::warn!(target: "state_snapshot", ?restore_point_dir, "Restore point already exists");
+            return Ok(None);
+        }
+        let _timer = metrics::CREATE_RESTORE_POINT_ELAPSED.start_timer();
+        // The storage is only needed to finish the checkpoint, drop it right away.
+        drop(checkpoint_hot_storage_and_cleanup_columns(
+            &self.store().store(),
+            &restore_point_dir,
+            None,
+        )?);
+        tracing::info!(target: "state_snapshot", ?restore_point_dir, "Made a restore point");
+        Ok(Some(restore_point_dir))
+    }
+
+    /// Returns the heights and paths of the existing restore points, oldest first.
+    pub fn list_restore_points(&self) -> Result<Vec<(BlockHeight, PathBuf)>, io::Error> {
+        let Some(restore_points_dir) = self.state_snapshot_config().restore_points_dir() else {
+            return Ok(vec![]);
+        };
+        if !restore_points_dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut restore_points = vec![];
+        for entry in std::fs::read_dir(restore_points_dir)? {
+            let path = entry?.path();
+            let height = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.split_once('-'))
+                .and_then(|(height, _hash)| height.parse::<BlockHeight>().ok());
+            // Leave alone whatever the operator put in the directory.
+            if let Some(height) = height {
+                restore_points.push((height, path));
+            }
+        }
+        restore_points.sort();
+        Ok(restore_points)
+    }
+
+    /// Deletes the oldest restore points so that at most `max_restore_points` remain.
+    pub fn prune_restore_points(&self, max_restore_points: usize) -> Result<(), io::Error> {
+        let restore_points = self.list_restore_points()?;
+        let num_to_delete = restore_points.len().saturating_sub(max_restore_points);
+        for (height, path) in &restore_points[..num_to_delete] {
+            tracing::info!(target: "state_snapshot", height, ?path, "Deleting restore point");
+            std::fs::remove_dir_all(path)?;
+        }
+        metrics::RESTORE_POINTS.set((restore_points.len() - num_to_delete) as i64);
+        Ok(())
+    }
 }
@@ -297,6 +297,15 @@ pub trait TrieStorage: Send + Sync {
     /// [`StorageError`] if the storage fails internally or the hash is not present.
     fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError>;
 
+    /// Same as `retrieve_raw_bytes`, also returning whether the bytes were read
+    /// from the store, rather than from a cache or from memory.
+    fn retrieve_raw_bytes_from_store(
+        &self,
+        hash: &CryptoHash,
+    ) -> Result<(Arc<[u8]>, bool), StorageError> {
+        Ok((self.retrieve_raw_bytes(hash)?, false))
+    }
+
     /// DEPRECATED.
     /// Returns `TrieCachingStorage` if `TrieStorage` is implemented by it.
     /// TODO (#9004) remove all remaining calls.
@@ -436,14 +445,16 @@ impl TrieCachingStorage {
     /// Reads value if it is not in shard cache. Handles dropping the cache
     /// lock. Either waits for prefetcher to fetch it or reads it from DB.
     /// It is responsibility of caller to release the prefetch slot later.
+    /// Also returns whether the value was read from DB, by this thread or by
+    /// the prefetcher.
     fn read_for_shard_cache_miss(
         &self,
         guard: MutexGuard<TrieCacheInner>,
         hash: &CryptoHash,
-    ) -> Result<Arc<[u8]>, StorageError> {
+    ) -> Result<(Arc<[u8]>, bool), StorageError> {
         let Some(prefetcher) = &self.prefetch_api else {
             std::mem::drop(guard);
-            return self.read_from_db(hash);
+            return Ok((self.read_from_db(hash)?, true));
         };
 
         let prefetch_state = prefetcher.prefetching.get_or_set_fetching(*hash);
@@ -458,7 +469,7 @@ impl TrieCachingStorage {
             // so the main thread should fetch data from DB on its own.
             PrefetcherResult::SlotReserved => {
                 self.metrics.prefetch_not_requested.inc();
-                self.read_from_db(hash)?
+                (self.read_from_db(hash)?, true)
             }
             // `MemoryLimitReached` is not really relevant for the main thread,
             // we always have to go to DB even if we could not stage a new prefetch.
@@ -466,12 +477,12 @@ impl TrieCachingStorage {
             // a prefetcher trying to fetch the same value before we can put it in the shard cache.
             PrefetcherResult::MemoryLimitReached => {
                 self.metrics.prefetch_memory_limit_reached.inc();
-                self.read_from_db(hash)?
+                (self.read_from_db(hash)?, true)
             }
             PrefetcherResult::Prefetched(value) => {
                 near_o11y::io_trace!(count: "prefetch_hit");
                 self.metrics.prefetch_hits.inc();
-                value
+                (value, true)
             }
             PrefetcherResult::Pending => {
                 near_o11y::io_trace!(count: "prefetch_pending");
@@ -479,7 +490,7 @@ impl TrieCachingStorage {
                 std::thread::yield_now();
                 // If data is already being prefetched, wait for that instead of sending a new request.
                 match prefetcher.prefetching.blocking_get(*hash) {
-                    Some(value) => value,
+                    Some(value) => (value, true),
                     // Only main thread (this one) removes values from staging area,
                     // therefore blocking read will usually not return empty unless there
                     // was a storage error. Or in the case of forks and parallel chunk
@@ -490,10 +501,10 @@ impl TrieCachingStorage {
                     None => {
                         if let Some(value) = self.shard_cache.get(hash) {
                             self.metrics.prefetch_conflict.inc();
-                            value
+                            (value, false)
                         } else {
                             self.metrics.prefetch_retry.inc();
-                            self.read_from_db(hash)?
+                            (self.read_from_db(hash)?, true)
                         }
                     }
                 }
@@ -505,6 +516,13 @@ impl TrieCachingStorage {
 
 impl TrieStorage for TrieCachingStorage {
     fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
+        Ok(self.retrieve_raw_bytes_from_store(hash)?.0)
+    }
+
+    fn retrieve_raw_bytes_from_store(
+        &self,
+        hash: &CryptoHash,
+    ) -> Result<(Arc<[u8]>, bool), StorageError> {
         // Try to get value from shard cache containing most recently touched nodes.
         let mut guard = self.shard_cache.lock();
         self.metrics.shard_cache_size.set(guard.len() as i64);
@@ -512,12 +530,12 @@ impl TrieStorage for TrieCachingStorage {
         if let Some(val) = guard.get(hash) {
             self.metrics.shard_cache_hits.inc();
             near_o11y::io_trace!(count: "shard_cache_hit");
-            return Ok(val);
+            return Ok((val, false));
         }
 
         self.metrics.shard_cache_misses.inc();
         near_o11y::io_trace!(count: "shard_cache_miss");
-        let val = match self.read_for_shard_cache_miss(guard, hash) {
+        let (val, from_store) = match self.read_for_shard_cache_miss(guard, hash) {
             Ok(val) => val,
             Err(e) => {
                 // Only release after attempt to read the value. See comment on fn release.
@@ -545,7 +563,7 @@ impl TrieStorage for TrieCachingStorage {
             prefetcher.prefetching.release(hash);
         }
 
-        Ok(val)
+        Ok((val, from_store))
     }
 
     fn as_caching_storage(&self) -> Option<&TrieCachingStorage> {
@@ -582,6 +600,13 @@ impl TrieStorage for TrieDBStorage {
     fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
         self.store.get(self.shard_uid, hash)
     }
+
+    fn retrieve_raw_bytes_from_store(
+        &self,
+        hash: &CryptoHash,
+    ) -> Result<(Arc<[u8]>, bool), StorageError> {
+        Ok((self.retrieve_raw_bytes(hash)?, true))
+    }
 }
 
 #[cfg(test)]
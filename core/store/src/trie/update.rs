@@ -19,6 +19,8 @@ use std::collections::BTreeMap;
 
 mod iterator;
 
+pub(crate) use iterator::make_prefix_range_end_bound;
+
 /// Key-value update. Contains a TrieKey and a value.
 pub struct TrieKeyValueUpdate {
     pub trie_key: TrieKey,
@@ -146,7 +146,7 @@ impl<'a> std::iter::FusedIterator for TrieUpdateIterator<'a> {}
 ///
 /// In other words, the smallest value larger than the `prefix` which does not
 /// start with the `prefix`.  If no such value exists, returns `None`.
-fn make_prefix_range_end_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+pub(crate) fn make_prefix_range_end_bound(prefix: &[u8]) -> Option<Vec<u8>> {
     let ffs = prefix.iter().rev().take_while(|&&byte| byte == u8::MAX).count();
     let next = &prefix[..(prefix.len() - ffs)];
     if next.is_empty() {
@@ -0,0 +1,257 @@
+use crate::env::setup::ActorHandlesForTesting;
+use near_async::time::{Clock, Duration};
+use near_chain::Block;
+use near_client::{BlockApproval, BlockResponse, GetBlock, GetBlockWithMerkleTree};
+use near_network::types::PeerInfo;
+use near_o11y::WithSpanContextExt;
+use near_primitives::block::{Approval, BlockHeader};
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::PartialMerkleTree;
+use near_primitives::sharding::ShardChunkHeader;
+use near_primitives::test_utils::create_test_signer;
+use near_primitives::types::{AccountId, Balance, BlockHeight, BlockReference, EpochId};
+use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives_core::num_rational::Ratio;
+use std::collections::HashMap;
+
+/// A block known to the driver.
+struct DriverBlock {
+    header: BlockHeader,
+    chunks: Vec<ShardChunkHeader>,
+    /// Merkle tree of the blocks up to and including this one.
+    block_merkle_tree: PartialMerkleTree,
+}
+
+/// Parameters of a block built by the driver, which tests may change before
+/// building the block with `build_block_with_params`.
+pub struct BlockParams {
+    pub epoch_id: EpochId,
+    pub next_epoch_id: EpochId,
+    pub minted_amount: Option<Balance>,
+}
+
+/// Scripts the chain seen by the client of a test node. The driver produces
+/// the blocks on top of the head of the node when it's created, or of the
+/// blocks it produced since, and sends them to the client as if they came
+/// from a peer, so that tests don't have to build, sign and send them, nor
+/// the approvals of the other validators:
+///
+/// ```ignore
+/// let mut driver = ChainDriver::new(clock, actor_handles, "test1".parse().unwrap()).await;
+/// driver.produce_block().await;
+/// driver.send_approvals(&["test2".parse().unwrap()], 2).await;
+/// driver.skip_height();
+/// driver.produce_block().await;
+/// driver.fork_at(1);
+/// driver.finalize_up_to(4).await;
+/// driver.wait_for_head().await;
+/// ```
+///
+/// All the blocks are signed by the same block producer, reuse the chunks of
+/// the previous block and carry no approvals, so the node is expected to run
+/// with doomslug disabled and the blocks to stay within the first epoch. The
+/// approvals sent with `send_approvals` are only seen by the node, for the
+/// blocks it produces itself.
+pub struct ChainDriver {
+    clock: Clock,
+    actor_handles: ActorHandlesForTesting,
+    signer: ValidatorSigner,
+    blocks: HashMap<CryptoHash, DriverBlock>,
+    /// The block the next one is produced on top of.
+    tip: CryptoHash,
+    /// Height of the next block produced.
+    next_height: BlockHeight,
+}
+
+impl ChainDriver {
+    /// Creates a driver producing blocks signed by `block_producer` on top of
+    /// the current head of the node.
+    pub async fn new(
+        clock: Clock,
+        actor_handles: ActorHandlesForTesting,
+        block_producer: AccountId,
+    ) -> Self {
+        let (last_block, block_merkle_tree) = actor_handles
+            .view_client_actor
+            .send(GetBlockWithMerkleTree::latest().with_span_context())
+            .await
+            .unwrap()
+            .unwrap();
+        let mut block_merkle_tree = PartialMerkleTree::clone(&block_merkle_tree);
+        block_merkle_tree.insert(last_block.header.hash);
+        let tip = last_block.header.hash;
+        let next_height = last_block.header.height + 1;
+        let block = DriverBlock {
+            header: last_block.header.into(),
+            chunks: last_block.chunks.into_iter().map(Into::into).collect(),
+            block_merkle_tree,
+        };
+        Self {
+            clock,
+            actor_handles,
+            signer: create_test_signer(block_producer.as_str()),
+            blocks: HashMap::from([(tip, block)]),
+            tip,
+            next_height,
+        }
+    }
+
+    /// Returns the header of the block the next one is produced on top of.
+    pub fn tip(&self) -> &BlockHeader {
+        &self.blocks[&self.tip].header
+    }
+
+    /// Produces the next block on top of the tip, sends it to the client and
+    /// makes it the new tip.
+    pub async fn produce_block(&mut self) -> Block {
+        self.produce_block_with_params(self.next_block_params()).await
+    }
+
+    /// Same as `produce_block`, but with the given block parameters.
+    pub async fn produce_block_with_params(&mut self, params: BlockParams) -> Block {
+        let block = self.build_block_with_params(params);
+        let prev = &self.blocks[&self.tip];
+        let mut block_merkle_tree = prev.block_merkle_tree.clone();
+        block_merkle_tree.insert(*block.hash());
+        let driver_block = DriverBlock {
+            header: block.header().clone(),
+            chunks: block.chunks().iter_raw().cloned().collect(),
+            block_merkle_tree,
+        };
+        self.blocks.insert(*block.hash(), driver_block);
+        self.tip = *block.hash();
+        self.next_height += 1;
+
+        self.send_block(block.clone(), false).await;
+        block
+    }
+
+    /// Builds the next block on top of the tip without sending it or moving
+    /// the tip, so that tests can tamper with it before `send_block`.
+    pub fn build_block(&self) -> Block {
+        self.build_block_with_params(self.next_block_params())
+    }
+
+    /// Parameters of the next block built on top of the tip.
+    pub fn next_block_params(&self) -> BlockParams {
+        let prev = &self.blocks[&self.tip].header;
+        let next_epoch_id = if prev.prev_hash() == &CryptoHash::default() {
+            EpochId(*prev.hash())
+        } else {
+            *prev.next_epoch_id()
+        };
+        BlockParams { epoch_id: *prev.epoch_id(), next_epoch_id, minted_amount: None }
+    }
+
+    /// Same as `build_block`, but with the given block parameters.
+    pub fn build_block_with_params(&self, params: BlockParams) -> Block {
+        let BlockParams { epoch_id, next_epoch_id, minted_amount } = params;
+        let prev = &self.blocks[&self.tip];
+        Block::produce(
+            PROTOCOL_VERSION,
+            &prev.header,
+            self.next_height,
+            prev.header.block_ordinal() + 1,
+            prev.chunks.clone(),
+            vec![vec![]; prev.chunks.len()],
+            epoch_id,
+            next_epoch_id,
+            None,
+            vec![],
+            Ratio::from_integer(0),
+            0,
+            100,
+            minted_amount,
+            &self.signer,
+            *prev.header.next_bp_hash(),
+            prev.block_merkle_tree.root(),
+            self.clock.clone(),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    /// Sends `block` to the client as if it came from a random peer.
+    pub async fn send_block(&self, block: Block, was_requested: bool) {
+        self.actor_handles
+            .client_actor
+            .send(
+                BlockResponse { block, peer_id: PeerInfo::random().id, was_requested }
+                    .with_span_context(),
+            )
+            .await
+            .unwrap();
+    }
+
+    /// Sends to the client the approvals of the tip by `approvers` for the
+    /// block at `target_height`, as if they came from random peers.
+    pub async fn send_approvals(&self, approvers: &[AccountId], target_height: BlockHeight) {
+        let tip = self.tip();
+        for approver in approvers {
+            let signer = create_test_signer(approver.as_str());
+            let approval =
+                Approval::new(*tip.hash(), tip.height(), target_height, &signer).unwrap();
+            self.actor_handles
+                .client_actor
+                .send(BlockApproval(approval, PeerInfo::random().id).with_span_context())
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Leaves the next height without a block.
+    pub fn skip_height(&mut self) {
+        self.next_height += 1;
+    }
+
+    /// Makes the block at `height` on the chain of the tip the new tip, so
+    /// that the next blocks are produced on a fork starting after it.
+    pub fn fork_at(&mut self, height: BlockHeight) {
+        let mut hash = self.tip;
+        loop {
+            let Some(block) = self.blocks.get(&hash) else {
+                panic!("no block at height {height} known to the driver");
+            };
+            if block.header.height() == height {
+                break;
+            }
+            assert!(block.header.height() > height, "no block at height {height} on the chain");
+            hash = *block.header.prev_hash();
+        }
+        self.tip = hash;
+        self.next_height = height + 1;
+    }
+
+    /// Produces blocks at consecutive heights until the block at `height`, or
+    /// a later one, is final on the chain of the tip.
+    pub async fn finalize_up_to(&mut self, height: BlockHeight) {
+        while self.final_height() < height {
+            self.produce_block().await;
+        }
+    }
+
+    /// Waits until the head of the node is the tip of the driver.
+    pub async fn wait_for_head(&self) {
+        loop {
+            let head = self
+                .actor_handles
+                .view_client_actor
+                .send(GetBlock(BlockReference::latest()).with_span_context())
+                .await
+                .unwrap();
+            if head.is_ok_and(|head| head.header.hash == self.tip) {
+                return;
+            }
+            actix::clock::sleep(Duration::milliseconds(10).unsigned_abs()).await;
+        }
+    }
+
+    /// Height of the last final block on the chain of the tip, or 0 if the
+    /// driver doesn't know it.
+    fn final_height(&self) -> BlockHeight {
+        let last_final_block = self.tip().last_final_block();
+        self.blocks.get(last_final_block).map_or(0, |block| block.header.height())
+    }
+}
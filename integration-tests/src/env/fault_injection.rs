@@ -0,0 +1,365 @@
+//! Seeded faults of the mock network of `setup_mock_all_validators`.
+//!
+//! Every message sent by a validator to another one is dropped, or delivered
+//! after the latency of the link between them and the delay of its type. The
+//! decisions and the delays are based on a random number generated from the
+//! seed, the sender, the recipient, the message type and the number of
+//! messages of that type the sender already sent to the recipient, so the
+//! same seed gives the same faults to the n-th message of a type on a link.
+//! The run as a whole is still not deterministic: the scheduling of the actors
+//! and the timers decide which messages are sent and at which height, which
+//! the partitions depend on. Running the test again with the seed it printed
+//! in `NEAR_FAULT_INJECTION_SEED` makes a failure more likely to reproduce,
+//! but doesn't guarantee it.
+
+use near_async::time::Duration;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockHeight};
+use parking_lot::Mutex;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Environment variable overriding the seed of the faults.
+pub const FAULT_INJECTION_SEED_ENV: &str = "NEAR_FAULT_INJECTION_SEED";
+
+/// Chunk messages and requests dropped by `FaultInjectionConfig::drop_chunks`.
+const CHUNK_MESSAGE_TYPES: [&str; 5] = [
+    "PartialEncodedChunkRequest",
+    "PartialEncodedChunkResponse",
+    "PartialEncodedChunkMessage",
+    "PartialEncodedChunkForward",
+    "ReceiptProofFragment",
+];
+
+/// Message type of the optimistic blocks, faulted independently of `Block`.
+const OPTIMISTIC_BLOCK_MESSAGE_TYPE: &str = "OptimisticBlock";
+
+#[derive(Clone, Copy, Debug)]
+pub enum DelayDistribution {
+    Constant(Duration),
+    Uniform { min: Duration, max: Duration },
+}
+
+impl DelayDistribution {
+    /// Delay of `base` plus a uniformly distributed jitter of up to `jitter`.
+    pub fn with_jitter(base: Duration, jitter: Duration) -> Self {
+        DelayDistribution::Uniform { min: base, max: base + jitter }
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match *self {
+            DelayDistribution::Constant(delay) => delay,
+            DelayDistribution::Uniform { min, max } => {
+                let nanos = rng.gen_range(min.whole_nanoseconds()..=max.whole_nanoseconds());
+                Duration::nanoseconds(nanos as i64)
+            }
+        }
+    }
+}
+
+/// While the height of the sender is in `heights`, messages are only delivered
+/// between the validators of the same group. Validators which aren't in any
+/// group are cut off from all the others.
+#[derive(Clone, Debug)]
+pub struct Partition {
+    pub heights: Range<BlockHeight>,
+    pub groups: Vec<Vec<AccountId>>,
+}
+
+impl Partition {
+    fn separates(&self, height: BlockHeight, a: &AccountId, b: &AccountId) -> bool {
+        self.heights.contains(&height)
+            && !self.groups.iter().any(|group| group.contains(a) && group.contains(b))
+    }
+}
+
+/// Faults of the mock network. Message types are the variant names of
+/// `NetworkRequests`, e.g. `PartialEncodedChunkMessage` or `Approval`. Only the
+/// messages pushed from a validator to another one are faulted, not the
+/// requests served by the view client of the peer.
+///
+/// The latencies are sampled independently for every message, so with jitter
+/// the messages of a link may be delivered out of order.
+#[derive(Clone, Debug, Default)]
+pub struct FaultInjectionConfig {
+    /// Seed of the faults. If not set, it's read from
+    /// `NEAR_FAULT_INJECTION_SEED` or chosen randomly.
+    pub seed: Option<u64>,
+    /// Probability of dropping a message, by message type.
+    pub drop_rates: HashMap<String, f64>,
+    /// Delay of the delivered messages, by message type.
+    pub delays: HashMap<String, DelayDistribution>,
+    /// Latency of the links which aren't in `link_latencies`.
+    pub default_latency: Option<DelayDistribution>,
+    /// Latency by pair of validators, the same in both directions.
+    pub link_latencies: HashMap<(AccountId, AccountId), DelayDistribution>,
+    pub partitions: Vec<Partition>,
+    /// Heights of the optimistic blocks which are dropped for all recipients.
+    pub optimistic_block_drop_heights: Vec<Range<BlockHeight>>,
+}
+
+impl FaultInjectionConfig {
+    /// No faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops 1 in 5 of the chunk messages and requests.
+    pub fn drop_chunks() -> Self {
+        CHUNK_MESSAGE_TYPES
+            .iter()
+            .fold(Self::new(), |config, message_type| config.drop_rate(message_type, 0.2))
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn drop_rate(mut self, message_type: &str, rate: f64) -> Self {
+        assert!((0.0..=1.0).contains(&rate), "invalid drop rate {rate}");
+        self.drop_rates.insert(message_type.to_string(), rate);
+        self
+    }
+
+    pub fn delay(mut self, message_type: &str, delay: DelayDistribution) -> Self {
+        self.delays.insert(message_type.to_string(), delay);
+        self
+    }
+
+    /// Sets the latency of all the links without their own latency.
+    pub fn latency(mut self, latency: DelayDistribution) -> Self {
+        self.default_latency = Some(latency);
+        self
+    }
+
+    /// Sets the latency of the link between `a` and `b`.
+    pub fn link_latency(mut self, a: AccountId, b: AccountId, latency: DelayDistribution) -> Self {
+        self.link_latencies.insert(Self::link(a, b), latency);
+        self
+    }
+
+    fn link(a: AccountId, b: AccountId) -> (AccountId, AccountId) {
+        if a <= b { (a, b) } else { (b, a) }
+    }
+
+    fn link_latency_of(&self, a: &AccountId, b: &AccountId) -> Option<&DelayDistribution> {
+        self.link_latencies.get(&Self::link(a.clone(), b.clone())).or(self.default_latency.as_ref())
+    }
+
+    pub fn partition(mut self, heights: Range<BlockHeight>, groups: Vec<Vec<AccountId>>) -> Self {
+        self.partitions.push(Partition { heights, groups });
+        self
+    }
+
+    /// Drops the optimistic blocks of the heights in `heights`, while the
+    /// blocks are still delivered, e.g. to test that the chunk producers fall
+    /// back to applying the chunks once the block arrives.
+    pub fn drop_optimistic_blocks(mut self, heights: Range<BlockHeight>) -> Self {
+        self.optimistic_block_drop_heights.push(heights);
+        self
+    }
+
+    /// Delays the optimistic blocks, independently of the blocks.
+    pub fn delay_optimistic_blocks(self, delay: DelayDistribution) -> Self {
+        self.delay(OPTIMISTIC_BLOCK_MESSAGE_TYPE, delay)
+    }
+}
+
+/// What happens to a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Fault {
+    Drop,
+    Deliver { delay: Duration },
+}
+
+pub(crate) struct FaultInjector {
+    config: FaultInjectionConfig,
+    seed: u64,
+    /// Number of messages by sender, recipient and message type.
+    counters: Mutex<HashMap<(AccountId, AccountId, String), u64>>,
+}
+
+impl FaultInjector {
+    /// Prints the seed, so that it's shown in the output of a failed test.
+    pub fn new(config: FaultInjectionConfig) -> Self {
+        let seed = config
+            .seed
+            .or_else(|| {
+                std::env::var(FAULT_INJECTION_SEED_ENV).ok().map(|seed| {
+                    seed.parse().unwrap_or_else(|_| panic!("invalid {FAULT_INJECTION_SEED_ENV}"))
+                })
+            })
+            .unwrap_or_else(rand::random);
+        println!("fault injection seed: {seed}, rerun with {FAULT_INJECTION_SEED_ENV}={seed}");
+        Self { config, seed, counters: Mutex::new(HashMap::new()) }
+    }
+
+    /// Whether the optimistic block of `height` is dropped for all recipients.
+    pub fn drops_optimistic_block(&self, height: BlockHeight) -> bool {
+        self.config.optimistic_block_drop_heights.iter().any(|heights| heights.contains(&height))
+    }
+
+    pub fn fault(
+        &self,
+        message_type: &str,
+        sender: &AccountId,
+        recipient: &AccountId,
+        sender_height: BlockHeight,
+    ) -> Fault {
+        if sender == recipient {
+            return Fault::Deliver { delay: Duration::ZERO };
+        }
+        if self.config.partitions.iter().any(|p| p.separates(sender_height, sender, recipient)) {
+            return Fault::Drop;
+        }
+        let index = {
+            let mut counters = self.counters.lock();
+            let counter = counters
+                .entry((sender.clone(), recipient.clone(), message_type.to_string()))
+                .or_default();
+            *counter += 1;
+            *counter
+        };
+        let hash = CryptoHash::hash_borsh((self.seed, sender, recipient, message_type, index));
+        let mut rng = ChaCha20Rng::from_seed(hash.0);
+        if let Some(rate) = self.config.drop_rates.get(message_type) {
+            if rng.gen_bool(*rate) {
+                return Fault::Drop;
+            }
+        }
+        let latency = match self.config.link_latency_of(sender, recipient) {
+            Some(distribution) => distribution.sample(&mut rng),
+            None => Duration::ZERO,
+        };
+        let delay = match self.config.delays.get(message_type) {
+            Some(distribution) => distribution.sample(&mut rng),
+            None => Duration::ZERO,
+        };
+        Fault::Deliver { delay: latency + delay }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_injection_is_seeded() {
+        let accounts: Vec<AccountId> =
+            ["test0", "test1", "test2"].iter().map(|a| a.parse().unwrap()).collect();
+        let config = FaultInjectionConfig::drop_chunks()
+            .seed(42)
+            .delay(
+                "Block",
+                DelayDistribution::Uniform {
+                    min: Duration::milliseconds(10),
+                    max: Duration::milliseconds(50),
+                },
+            )
+            .partition(10..20, vec![vec![accounts[0].clone(), accounts[1].clone()]]);
+        let faults = |injector: &FaultInjector| {
+            let mut faults = vec![];
+            for height in 0..100 {
+                for message_type in ["Block", "PartialEncodedChunkMessage", "Approval"] {
+                    faults.push(injector.fault(message_type, &accounts[0], &accounts[1], height));
+                    faults.push(injector.fault(message_type, &accounts[1], &accounts[2], height));
+                }
+            }
+            faults
+        };
+        let first = faults(&FaultInjector::new(config.clone()));
+        assert_eq!(first, faults(&FaultInjector::new(config)));
+
+        let injector = FaultInjector::new(FaultInjectionConfig::new().seed(42));
+        assert!(faults(&injector).iter().all(|f| *f == Fault::Deliver { delay: Duration::ZERO }));
+
+        // Partitioned between test1 and test2 at heights 10..20 only.
+        assert_eq!(first[6 * 15 + 5], Fault::Drop);
+        assert_eq!(first[6 * 15 + 4], Fault::Deliver { delay: Duration::ZERO });
+        // Some of the chunks are dropped and the blocks are delayed.
+        assert!(first.iter().skip(2).step_by(6).any(|f| *f == Fault::Drop));
+        assert!(first.iter().step_by(6).all(
+            |f| matches!(f, Fault::Deliver { delay } if *delay >= Duration::milliseconds(10))
+        ));
+    }
+
+    #[test]
+    fn test_link_latency() {
+        let accounts: Vec<AccountId> =
+            ["test0", "test1", "test2"].iter().map(|a| a.parse().unwrap()).collect();
+        let config = FaultInjectionConfig::new()
+            .seed(42)
+            .latency(DelayDistribution::Constant(Duration::milliseconds(5)))
+            .link_latency(
+                accounts[1].clone(),
+                accounts[0].clone(),
+                DelayDistribution::with_jitter(
+                    Duration::milliseconds(100),
+                    Duration::milliseconds(20),
+                ),
+            )
+            .delay("Block", DelayDistribution::Constant(Duration::milliseconds(1)));
+        let delay = |injector: &FaultInjector, message_type, sender: usize, recipient: usize| {
+            match injector.fault(message_type, &accounts[sender], &accounts[recipient], 0) {
+                Fault::Deliver { delay } => delay,
+                Fault::Drop => panic!("no drops configured"),
+            }
+        };
+        let approval_delays = |injector: &FaultInjector, sender, recipient| {
+            (0..50).map(|_| delay(injector, "Approval", sender, recipient)).collect::<Vec<_>>()
+        };
+
+        // The link between test0 and test1 is slow in both directions. The
+        // jitter is sampled for every message sent over the link, the same way
+        // for the same seed.
+        let injector = FaultInjector::new(config.clone());
+        for (sender, recipient) in [(0, 1), (1, 0)] {
+            let delays = approval_delays(&injector, sender, recipient);
+            assert!(
+                delays.iter().all(
+                    |d| (Duration::milliseconds(100)..=Duration::milliseconds(120)).contains(d)
+                )
+            );
+            assert!(delays.iter().any(|d| *d != delays[0]));
+            assert_eq!(
+                delays,
+                approval_delays(&FaultInjector::new(config.clone()), sender, recipient)
+            );
+        }
+        let delay =
+            |message_type, sender, recipient| delay(&injector, message_type, sender, recipient);
+        // The other links have the default latency, added to the delay of the message type.
+        assert_eq!(delay("Approval", 0, 2), Duration::milliseconds(5));
+        assert_eq!(delay("Block", 2, 1), Duration::milliseconds(6));
+        // Messages to self are delivered immediately.
+        assert_eq!(delay("Block", 1, 1), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_optimistic_block_faults() {
+        let accounts: Vec<AccountId> =
+            ["test0", "test1"].iter().map(|a| a.parse().unwrap()).collect();
+        let config = FaultInjectionConfig::new()
+            .seed(42)
+            .drop_optimistic_blocks(5..10)
+            .delay_optimistic_blocks(DelayDistribution::Constant(Duration::milliseconds(30)));
+        let injector = FaultInjector::new(config);
+
+        assert!(!injector.drops_optimistic_block(4));
+        assert!(injector.drops_optimistic_block(5));
+        assert!(injector.drops_optimistic_block(9));
+        assert!(!injector.drops_optimistic_block(10));
+        // Only the optimistic blocks are delayed.
+        assert_eq!(
+            injector.fault(OPTIMISTIC_BLOCK_MESSAGE_TYPE, &accounts[0], &accounts[1], 0),
+            Fault::Deliver { delay: Duration::milliseconds(30) }
+        );
+        assert_eq!(
+            injector.fault("Block", &accounts[0], &accounts[1], 0),
+            Fault::Deliver { delay: Duration::ZERO }
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Invariants checked on every client of a `TestEnv`.
+//!
+//! Every client gets an `InvariantObserver` when the `TestEnv` is built. It is
+//! run after each block processed through the environment and asserts the
+//! invariants which must hold for any test:
+//! - the final head never goes back,
+//! - there are never two final blocks at the same height, i.e. the new final
+//!   head descends from the previous one,
+//! - the new head block has enough endorsement stake for each of its chunks,
+//! - the tail never goes back and garbage collection doesn't remove the final
+//!   blocks at or above the tail,
+//! - with `DoomslugThresholdMode::SingleBlockFinality`, the final head is the
+//!   head.
+//!
+//! On violation the test panics with a dump of the chain of the client, so
+//! tests don't have to re-implement these checks. Tests which corrupt the
+//! chain on purpose opt out with `TestEnvBuilder::check_invariants(false)`.
+
+use near_chain::DoomslugThresholdMode;
+use near_chain::stateless_validation::chunk_endorsement::validate_chunk_endorsements_in_block;
+use near_chain::types::Tip;
+use near_client::Client;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockHeight};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+/// Number of the latest final blocks included in the dump.
+const DUMP_FINAL_BLOCKS: usize = 10;
+
+pub struct InvariantObserver {
+    account_id: AccountId,
+    doomslug_threshold_mode: DoomslugThresholdMode,
+    /// Hash of every final block observed, by height.
+    final_blocks: BTreeMap<BlockHeight, CryptoHash>,
+    /// Heights of the final blocks which were in the store when observed and
+    /// are at or above the tail, so they must not be garbage collected.
+    stored_final_blocks: BTreeSet<BlockHeight>,
+    final_head: Option<Tip>,
+    head: Option<Tip>,
+    tail: Option<BlockHeight>,
+}
+
+impl InvariantObserver {
+    pub fn new(account_id: AccountId, doomslug_threshold_mode: DoomslugThresholdMode) -> Self {
+        Self {
+            account_id,
+            doomslug_threshold_mode,
+            final_blocks: BTreeMap::new(),
+            stored_final_blocks: BTreeSet::new(),
+            final_head: None,
+            head: None,
+            tail: None,
+        }
+    }
+
+    /// Checks the invariants against the current state of the chain of the
+    /// client and panics with a dump of the chain on violation.
+    pub fn check(&mut self, client: &Client) {
+        let violations = self.violations(client);
+        if !violations.is_empty() {
+            panic!("{}", self.dump(client, &violations));
+        }
+    }
+
+    fn violations(&mut self, client: &Client) -> Vec<String> {
+        let chain = &client.chain;
+        let mut violations = vec![];
+
+        let final_head = chain.final_head().unwrap();
+        if let Some(prev_final_head) = &self.final_head {
+            if final_head.height < prev_final_head.height {
+                violations.push(format!(
+                    "final head went back from {} to {}",
+                    prev_final_head.height, final_head.height
+                ));
+            }
+        }
+        // Walk back from the final head to the latest final block observed,
+        // which must be one of its ancestors. The headers removed by state
+        // sync can't be checked.
+        let last_final_block =
+            self.final_blocks.last_key_value().map(|(&height, &hash)| (height, hash));
+        let mut hash = final_head.last_block_hash;
+        while let Ok(header) = chain.get_block_header(&hash) {
+            let height = header.height();
+            if let Some((last_final_height, last_final_hash)) = last_final_block {
+                if height <= last_final_height {
+                    match self.final_blocks.get(&height) {
+                        Some(final_hash) if final_hash == &hash => {}
+                        Some(final_hash) => violations.push(format!(
+                            "two final blocks at height {height}: {final_hash} and {hash}"
+                        )),
+                        None => violations.push(format!(
+                            "final head {} doesn't descend from the final block {last_final_hash} at height {last_final_height}",
+                            final_head.last_block_hash
+                        )),
+                    }
+                    break;
+                }
+            }
+            self.final_blocks.insert(height, hash);
+            if chain.block_exists(&hash).unwrap() {
+                self.stored_final_blocks.insert(height);
+            }
+            if height == chain.genesis().height() {
+                break;
+            }
+            hash = *header.prev_hash();
+        }
+
+        let head = chain.head().unwrap();
+        if self.doomslug_threshold_mode == DoomslugThresholdMode::SingleBlockFinality
+            && final_head.last_block_hash != head.last_block_hash
+        {
+            violations.push(format!(
+                "final head {} at height {} isn't the head {} at height {} with single block finality",
+                final_head.last_block_hash, final_head.height, head.last_block_hash, head.height
+            ));
+        }
+        if self
+            .head
+            .as_ref()
+            .is_none_or(|prev_head| prev_head.last_block_hash != head.last_block_hash)
+            && head.height != chain.genesis().height()
+        {
+            match chain.get_block(&head.last_block_hash) {
+                Ok(block) => {
+                    if let Err(err) =
+                        validate_chunk_endorsements_in_block(client.epoch_manager.as_ref(), &block)
+                    {
+                        violations.push(format!(
+                            "head block {} doesn't have enough chunk endorsements: {err}",
+                            head.last_block_hash
+                        ));
+                    }
+                }
+                Err(err) => violations
+                    .push(format!("head block {} is missing: {err}", head.last_block_hash)),
+            }
+        }
+
+        let tail = chain.tail().unwrap();
+        if let Some(prev_tail) = self.tail {
+            if tail < prev_tail {
+                violations.push(format!("tail went back from {prev_tail} to {tail}"));
+            }
+        }
+        self.stored_final_blocks = self.stored_final_blocks.split_off(&tail);
+        for height in &self.stored_final_blocks {
+            let hash = &self.final_blocks[height];
+            if !chain.block_exists(hash).unwrap() {
+                violations.push(format!(
+                    "final block {hash} at height {height} was garbage collected above the tail {tail}"
+                ));
+            }
+        }
+        if final_head.height >= tail {
+            if let Err(err) = chain.get_block(&final_head.last_block_hash) {
+                violations.push(format!(
+                    "final head block {} at height {} is above the tail {tail} but missing: {err}",
+                    final_head.last_block_hash, final_head.height
+                ));
+            }
+        }
+
+        self.final_head = Some(final_head);
+        self.head = Some(head);
+        self.tail = Some(tail);
+        violations
+    }
+
+    fn dump(&self, client: &Client, violations: &[String]) -> String {
+        let chain = &client.chain;
+        let mut dump = format!("invariants violated by client {}:\n", self.account_id);
+        for violation in violations {
+            writeln!(dump, "  - {violation}").unwrap();
+        }
+        writeln!(dump, "head: {:?}", chain.head()).unwrap();
+        writeln!(dump, "header head: {:?}", chain.header_head()).unwrap();
+        writeln!(dump, "final head: {:?}", chain.final_head()).unwrap();
+        writeln!(dump, "tail: {:?}", chain.tail()).unwrap();
+        writeln!(dump, "head block header: {:?}", chain.head_header()).unwrap();
+        writeln!(dump, "latest final blocks:").unwrap();
+        for (height, hash) in self.final_blocks.iter().rev().take(DUMP_FINAL_BLOCKS) {
+            writeln!(dump, "  {height}: {hash}").unwrap();
+        }
+        dump
+    }
+}
@@ -1,4 +1,10 @@
+pub mod chain_driver;
+pub mod fault_injection;
+pub mod invariant_observer;
+pub mod network_recorder;
 pub mod nightshade_setup;
 pub mod setup;
+pub mod test_cluster_builder;
 pub mod test_env;
 pub mod test_env_builder;
+pub mod test_node_builder;
@@ -0,0 +1,229 @@
+//! Recording of the network requests sent by a client, for tests which assert
+//! on them.
+//!
+//! `RecordingPeerManagerAdapter` wraps the `PeerManagerAdapter` given to the
+//! setup functions, forwards every message to it and keeps the last
+//! `NetworkRequests` in a ring buffer, which tests query by message type, i.e.
+//! the name of the variant, and by target account.
+
+use near_async::messaging::{CanSend, IntoMultiSender, IntoSender, MessageWithCallback};
+use near_network::test_utils::MockPeerManagerAdapter;
+use near_network::types::{
+    NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest, PeerManagerMessageResponse,
+    SetChainInfo, StateSyncEvent,
+};
+use near_primitives::types::AccountId;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Number of requests kept by `RecordingPeerManagerAdapter::new_mock`.
+pub const DEFAULT_RECORDING_CAPACITY: usize = 10_000;
+
+struct Recording {
+    requests: VecDeque<NetworkRequests>,
+    /// Number of requests evicted from the ring buffer.
+    num_evicted: usize,
+}
+
+pub struct RecordingPeerManagerAdapter {
+    inner: PeerManagerAdapter,
+    capacity: usize,
+    recording: Mutex<Recording>,
+}
+
+impl RecordingPeerManagerAdapter {
+    /// Records the requests sent to `inner`, keeping the last `capacity` ones.
+    pub fn new(inner: PeerManagerAdapter, capacity: usize) -> Arc<Self> {
+        assert!(capacity > 0, "the recording capacity must be positive");
+        Arc::new(Self {
+            inner,
+            capacity,
+            recording: Mutex::new(Recording { requests: VecDeque::new(), num_evicted: 0 }),
+        })
+    }
+
+    /// Records the requests without sending them anywhere. Async requests
+    /// are answered with `NetworkResponses::NoResponse`.
+    pub fn new_mock() -> Arc<Self> {
+        Self::new(
+            Arc::new(MockPeerManagerAdapter::default()).as_multi_sender(),
+            DEFAULT_RECORDING_CAPACITY,
+        )
+    }
+
+    /// The adapter to pass to the setup functions.
+    pub fn adapter(self: &Arc<Self>) -> PeerManagerAdapter {
+        PeerManagerAdapter {
+            async_request_sender: self.as_sender(),
+            request_sender: self.as_sender(),
+            set_chain_info_sender: self.as_sender(),
+            state_sync_event_sender: self.as_sender(),
+        }
+    }
+
+    fn record(&self, request: &PeerManagerMessageRequest) {
+        let PeerManagerMessageRequest::NetworkRequests(request) = request else {
+            return;
+        };
+        let mut recording = self.recording.lock();
+        if recording.requests.len() == self.capacity {
+            recording.requests.pop_front();
+            recording.num_evicted += 1;
+        }
+        recording.requests.push_back(request.clone());
+    }
+
+    /// The recorded requests of the given type, e.g. `"Block"`, sent to
+    /// `target`, in the order they were sent. `None` matches anything.
+    pub fn find(
+        &self,
+        message_type: Option<&str>,
+        target: Option<&AccountId>,
+    ) -> Vec<NetworkRequests> {
+        self.recording
+            .lock()
+            .requests
+            .iter()
+            .filter(|request| message_type.is_none_or(|t| request.as_ref() == t))
+            .filter(|request| target.is_none_or(|target| targets(request).contains(&target)))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of recorded requests of the given type sent to `target`.
+    pub fn count(&self, message_type: Option<&str>, target: Option<&AccountId>) -> usize {
+        self.find(message_type, target).len()
+    }
+
+    /// All the recorded requests, in the order they were sent.
+    pub fn requests(&self) -> Vec<NetworkRequests> {
+        self.find(None, None)
+    }
+
+    /// Number of requests which were evicted because the ring buffer was full.
+    pub fn num_evicted(&self) -> usize {
+        self.recording.lock().num_evicted
+    }
+
+    pub fn clear(&self) {
+        let mut recording = self.recording.lock();
+        recording.requests.clear();
+        recording.num_evicted = 0;
+    }
+}
+
+impl CanSend<MessageWithCallback<PeerManagerMessageRequest, PeerManagerMessageResponse>>
+    for RecordingPeerManagerAdapter
+{
+    fn send(
+        &self,
+        message: MessageWithCallback<PeerManagerMessageRequest, PeerManagerMessageResponse>,
+    ) {
+        self.record(&message.message);
+        self.inner.async_request_sender.send(message);
+    }
+}
+
+impl CanSend<PeerManagerMessageRequest> for RecordingPeerManagerAdapter {
+    fn send(&self, message: PeerManagerMessageRequest) {
+        self.record(&message);
+        self.inner.request_sender.send(message);
+    }
+}
+
+impl CanSend<SetChainInfo> for RecordingPeerManagerAdapter {
+    fn send(&self, message: SetChainInfo) {
+        self.inner.set_chain_info_sender.send(message);
+    }
+}
+
+impl CanSend<StateSyncEvent> for RecordingPeerManagerAdapter {
+    fn send(&self, message: StateSyncEvent) {
+        self.inner.state_sync_event_sender.send(message);
+    }
+}
+
+/// Accounts the request is addressed to. Empty for the requests which are
+/// broadcast or sent to a peer rather than an account.
+fn targets(request: &NetworkRequests) -> Vec<&AccountId> {
+    match request {
+        NetworkRequests::OptimisticBlock { chunk_producers, .. } => {
+            chunk_producers.iter().collect()
+        }
+        NetworkRequests::PartialEncodedChunkRequest { target, .. }
+        | NetworkRequests::ChunkRequest { target, .. } => target.account_id.iter().collect(),
+        NetworkRequests::PartialEncodedChunkMessage { account_id, .. }
+        | NetworkRequests::PartialEncodedChunkForward { account_id, .. }
+        | NetworkRequests::ReceiptProofFragment { account_id, .. }
+        | NetworkRequests::ForwardTx(account_id, _)
+        | NetworkRequests::TxStatus(account_id, _, _)
+        | NetworkRequests::ChunkStateWitnessAck(account_id, _)
+        | NetworkRequests::ChunkEndorsement(account_id, _)
+        | NetworkRequests::PartialEncodedStateWitnessRequest(account_id, _)
+        | NetworkRequests::ContractCodeRequest(account_id, _)
+        | NetworkRequests::ContractCodeResponse(account_id, _) => vec![account_id],
+        NetworkRequests::PartialEncodedStateWitness(parts) => {
+            parts.iter().map(|(account_id, _)| account_id).collect()
+        }
+        NetworkRequests::PartialEncodedStateWitnessForward(account_ids, _)
+        | NetworkRequests::ChunkContractAccesses(account_ids, _)
+        | NetworkRequests::PartialEncodedContractDeploys(account_ids, _) => {
+            account_ids.iter().collect()
+        }
+        NetworkRequests::Block { .. }
+        | NetworkRequests::Approval { .. }
+        | NetworkRequests::BlockRequest { .. }
+        | NetworkRequests::BlockHeadersRequest { .. }
+        | NetworkRequests::StateRequestHeader { .. }
+        | NetworkRequests::StateRequestPart { .. }
+        | NetworkRequests::BanPeer { .. }
+        | NetworkRequests::AnnounceAccount(_)
+        | NetworkRequests::SnapshotHostInfo { .. }
+        | NetworkRequests::PartialEncodedChunkResponse { .. }
+        | NetworkRequests::ChunkResponse { .. }
+        | NetworkRequests::EpochSyncRequest { .. }
+        | NetworkRequests::EpochSyncResponse { .. } => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_async::messaging::SendAsync;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::ShardId;
+
+    #[test]
+    fn test_recording_peer_manager_adapter() {
+        let inner = Arc::new(MockPeerManagerAdapter::default());
+        let recorder = RecordingPeerManagerAdapter::new(inner.as_multi_sender(), 3);
+        let adapter = recorder.adapter();
+        let account = |name: &str| -> AccountId { name.parse().unwrap() };
+        let tx_status = |target: &str| {
+            NetworkRequests::TxStatus(account(target), account("test0"), CryptoHash::default())
+        };
+        let state_request = NetworkRequests::StateRequestHeader {
+            shard_id: ShardId::new(0),
+            sync_hash: CryptoHash::default(),
+            sync_prev_prev_hash: CryptoHash::default(),
+        };
+        for request in [tx_status("test1"), state_request.clone(), tx_status("test2")] {
+            adapter.send(PeerManagerMessageRequest::NetworkRequests(request));
+        }
+
+        // The requests are forwarded.
+        assert_eq!(inner.requests.read().len(), 3);
+        assert_eq!(recorder.count(Some("TxStatus"), None), 2);
+        assert_eq!(recorder.find(Some("TxStatus"), Some(&account("test2"))), [tx_status("test2")]);
+        assert_eq!(recorder.find(None, Some(&account("test3"))), []);
+        assert_eq!(recorder.find(Some("StateRequestHeader"), None), [state_request.clone()]);
+
+        // Only the last 3 requests are kept.
+        let _ = adapter.send_async(PeerManagerMessageRequest::NetworkRequests(tx_status("test3")));
+        assert_eq!(recorder.num_evicted(), 1);
+        assert_eq!(recorder.requests(), [state_request, tx_status("test2"), tx_status("test3")]);
+        recorder.clear();
+        assert!(recorder.requests().is_empty());
+    }
+}
@@ -6,37 +6,40 @@ use actix::{Actor, Addr, Context};
 use futures::{FutureExt, future};
 use near_async::actix::AddrWithAutoSpanContextExt;
 use near_async::actix_wrapper::{ActixWrapper, spawn_actix_actor};
-use near_async::futures::ActixFutureSpawner;
+use near_async::futures::{ActixFutureSpawner, AsyncComputationSpawner};
 use near_async::messaging::{
     CanSend, IntoMultiSender, IntoSender, LateBoundSender, SendAsync, Sender, noop,
 };
-use near_async::time::{Clock, Duration, Instant, Utc};
+use near_async::test_loop::TestLoopV2;
+use near_async::test_loop::sender::TestLoopSender;
+use near_async::time::{Clock, Duration, Instant};
 use near_chain::rayon_spawner::RayonAsyncComputationSpawner;
 use near_chain::resharding::resharding_actor::ReshardingActor;
 use near_chain::resharding::types::ReshardingSender;
 use near_chain::state_snapshot_actor::SnapshotCallbacks;
-use near_chain::test_utils::{KeyValueRuntime, MockEpochManager, ValidatorSchedule};
+use near_chain::test_utils::{KeyValueRuntime, ValidatorSchedule};
 use near_chain::types::{ChainConfig, RuntimeAdapter};
 use near_chain::{Chain, ChainGenesis, DoomslugThresholdMode};
 use near_chain_configs::{
-    ChunkDistributionNetworkConfig, ClientConfig, Genesis, MutableConfigValue, ReshardingConfig,
-    ReshardingHandle, TrackedShardsConfig,
+    ChunkDistributionNetworkConfig, ClientConfig, MutableConfigValue, MutableValidatorSigner,
+    ReshardingConfig, ReshardingHandle, TrackedShardsConfig, TransactionAdmissionConfig,
 };
 use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::shards_manager_actor::{ShardsManagerActor, start_shards_manager};
 use near_chunks::test_utils::SynchronousShardsManagerAdapter;
 use near_client::adversarial::Controls;
-use near_client::client_actor::ClientActorInner;
+use near_client::client_actor::{ClientActorInner, ClientSenderForPartialWitness};
+use near_client::sync_jobs_actor::SyncJobsActor;
 use near_client::{
     Client, ClientActor, PartialWitnessActor, PartialWitnessSenderForClient, RpcHandler,
     RpcHandlerConfig, StartClientResult, SyncStatus, ViewClientActor, ViewClientActorInner,
     start_client,
 };
-use near_client::{RpcHandlerActor, spawn_rpc_handler_actor};
+use near_client::{NewHeadEvent, RpcHandlerActor, spawn_rpc_handler_actor};
 use near_crypto::{KeyType, PublicKey};
+use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_tracker::ShardTracker;
-use near_epoch_manager::{EpochManager, EpochManagerAdapter};
 use near_network::client::{
     AnnounceAccountRequest, BlockApproval, BlockHeadersRequest, BlockHeadersResponse, BlockRequest,
     BlockResponse, ChunkEndorsementMessage, OptimisticBlockMessage, SetNetworkInfo,
@@ -46,7 +49,8 @@ use near_network::shards_manager::ShardsManagerRequestFromNetwork;
 use near_network::state_witness::{
     ChunkContractAccessesMessage, ContractCodeRequestMessage, ContractCodeResponseMessage,
     PartialEncodedContractDeploysMessage, PartialEncodedStateWitnessForwardMessage,
-    PartialEncodedStateWitnessMessage, PartialWitnessSenderForNetwork,
+    PartialEncodedStateWitnessMessage, PartialEncodedStateWitnessRequestMessage,
+    PartialWitnessSenderForNetwork,
 };
 use near_network::types::{BlockInfo, PeerChainInfo};
 use near_network::types::{
@@ -60,23 +64,24 @@ use near_primitives::epoch_info::RngSeed;
 use near_primitives::genesis::GenesisId;
 use near_primitives::hash::{CryptoHash, hash};
 use near_primitives::network::PeerId;
-use near_primitives::test_utils::create_test_signer;
-use near_primitives::types::{AccountId, BlockHeightDelta, EpochId, NumBlocks, NumSeats, ShardId};
+use near_primitives::types::{
+    AccountId, BlockHeight, BlockHeightDelta, EpochId, NumBlocks, NumSeats, ShardId,
+};
 use near_primitives::validator_signer::{EmptyValidatorSigner, ValidatorSigner};
 use near_primitives::version::{PROTOCOL_VERSION, get_protocol_upgrade_schedule};
 use near_store::adapter::StoreAdapter;
-use near_store::genesis::initialize_genesis_state;
-use near_store::test_utils::create_test_store;
 use near_telemetry::TelemetryActor;
-use nearcore::NightshadeRuntime;
 use num_rational::Ratio;
 use parking_lot::RwLock;
-use rand::{Rng, thread_rng};
+use rand::Rng;
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
 use std::sync::{Arc, OnceLock};
+use tokio::sync::broadcast;
 
+use crate::env::fault_injection::{Fault, FaultInjectionConfig, FaultInjector};
+use crate::env::test_node_builder::{TestNodeBuilder, TestNodeRuntime};
 use crate::utils::block_stats::BlockStats;
 use crate::utils::peer_manager_mock::PeerManagerMock;
 
@@ -87,216 +92,199 @@ pub const MIN_BLOCK_PROD_TIME: Duration = Duration::milliseconds(100);
 /// max block production time in milliseconds
 pub const MAX_BLOCK_PROD_TIME: Duration = Duration::milliseconds(200);
 
-/// Sets up ClientActor and ViewClientActor viewing the same store/runtime.
-fn setup_with_mock_epoch_manager(
-    clock: Clock,
-    vs: ValidatorSchedule,
-    epoch_length: BlockHeightDelta,
-    account_id: AccountId,
-    skip_sync_wait: bool,
-    min_block_prod_time: u64,
-    max_block_prod_time: u64,
-    enable_doomslug: bool,
-    archive: bool,
-    state_sync_enabled: bool,
-    network_adapter: PeerManagerAdapter,
-    transaction_validity_period: NumBlocks,
-    genesis_time: Utc,
-    chunk_distribution_config: Option<ChunkDistributionNetworkConfig>,
-) -> (
-    Addr<ClientActor>,
-    Addr<ViewClientActor>,
-    Addr<RpcHandlerActor>,
-    ShardsManagerAdapterForTest,
-    PartialWitnessSenderForNetwork,
-) {
-    let store = create_test_store();
-    let num_validator_seats = vs.all_block_producers().count() as NumSeats;
-    let epoch_manager = MockEpochManager::new_with_validators(store.clone(), vs, epoch_length);
-    let runtime = KeyValueRuntime::new_with_no_gc(store.clone(), epoch_manager.as_ref(), archive);
+/// Everything `setup` and `setup_in_test_loop` need to wire the actors of a
+/// node. Built by `TestNodeBuilder`.
+pub(crate) struct SetupOptions {
+    pub epoch_manager: Arc<dyn EpochManagerAdapter>,
+    pub genesis_time: time::OffsetDateTime,
+    pub transaction_validity_period: u64,
+    pub epoch_length: u64,
+    pub account_id: AccountId,
+    pub signer: Option<Arc<ValidatorSigner>>,
+    pub tracked_shards: TrackedShardsConfig,
+    pub skip_sync_wait: bool,
+    pub min_block_prod_time: u64,
+    pub max_block_prod_time: u64,
+    pub num_validator_seats: u64,
+    pub archive: bool,
+    pub state_sync_enabled: bool,
+    pub chunk_distribution_config: Option<ChunkDistributionNetworkConfig>,
+    pub clock: Clock,
+    pub runtime: Arc<dyn RuntimeAdapter>,
+    pub network_adapter: PeerManagerAdapter,
+    pub store: near_store::Store,
+    pub enable_doomslug: bool,
+    /// Chain genesis matching the genesis the runtime was initialized with,
+    /// if given one. Otherwise a test chain genesis is built from the
+    /// options above.
+    pub chain_genesis: Option<ChainGenesis>,
+}
 
-    setup(SetupOptions {
-        epoch_manager,
-        genesis_time,
+/// Chain genesis of the test nodes whose runtime isn't initialized with a
+/// genesis.
+pub(crate) fn test_chain_genesis(
+    genesis_time: time::OffsetDateTime,
+    transaction_validity_period: u64,
+    epoch_length: u64,
+) -> ChainGenesis {
+    ChainGenesis {
+        time: genesis_time,
+        height: 0,
+        gas_limit: 1_000_000,
+        min_gas_price: 100,
+        max_gas_price: 1_000_000_000,
+        total_supply: 3_000_000_000_000_000_000_000_000_000_000_000,
+        gas_price_adjustment_rate: Ratio::from_integer(0),
         transaction_validity_period,
         epoch_length,
-        account_id,
-        skip_sync_wait,
-        min_block_prod_time,
-        max_block_prod_time,
-        num_validator_seats,
-        archive,
-        state_sync_enabled,
-        chunk_distribution_config,
-        clock,
-        runtime,
-        network_adapter,
-        store,
-        enable_doomslug,
-    })
+        protocol_version: PROTOCOL_VERSION,
+        chain_id: "integration_test".to_string(),
+    }
 }
 
-/// Sets up ClientActor and ViewClientActor viewing the same store/runtime using real epoch
-/// manager.
-fn setup_with_real_epoch_manager(
-    clock: Clock,
-    validators: Vec<AccountId>,
-    epoch_length: BlockHeightDelta,
-    account_id: AccountId,
-    skip_sync_wait: bool,
-    min_block_prod_time: u64,
-    max_block_prod_time: u64,
-    enable_doomslug: bool,
-    archive: bool,
-    state_sync_enabled: bool,
-    network_adapter: PeerManagerAdapter,
-    transaction_validity_period: NumBlocks,
-    genesis_time: Utc,
-    chunk_distribution_config: Option<ChunkDistributionNetworkConfig>,
-) -> (
-    (
-        Addr<ClientActor>,
-        Addr<ViewClientActor>,
-        Addr<RpcHandlerActor>,
-        ShardsManagerAdapterForTest,
-        PartialWitnessSenderForNetwork,
-    ),
-    tempfile::TempDir,
-) {
-    let store = create_test_store();
-
-    let num_validator_seats = validators.len() as NumSeats;
-
-    let mut validators = validators;
-
-    // Certain tests depend on these accounts existing so we make them available here.
-    // This is mostly due to historical reasons - those tests used to use heavily mocked testing
-    // environment that didn't check account existence.
-    for account in ["test2", "test"].into_iter().map(|acc| acc.parse().unwrap()) {
-        if !validators.contains(&account) {
-            validators.push(account);
+impl SetupOptions {
+    fn chain_genesis(&self) -> ChainGenesis {
+        if let Some(chain_genesis) = &self.chain_genesis {
+            return chain_genesis.clone();
         }
+        test_chain_genesis(self.genesis_time, self.transaction_validity_period, self.epoch_length)
     }
 
-    let mut genesis = Genesis::test(validators, num_validator_seats);
-    genesis.config.epoch_length = epoch_length;
-    initialize_genesis_state(store.clone(), &genesis, None);
-
-    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &genesis.config, None);
-
-    let tempdir = tempfile::TempDir::new().unwrap();
-
-    let runtime = NightshadeRuntime::test(
-        tempdir.path(),
-        store.clone(),
-        &genesis.config,
-        epoch_manager.clone(),
-    );
+    fn client_config(&self) -> ClientConfig {
+        let mut config = ClientConfig::test(
+            self.skip_sync_wait,
+            self.min_block_prod_time,
+            self.max_block_prod_time,
+            self.num_validator_seats,
+            self.archive,
+            true,
+            self.state_sync_enabled,
+        );
+        config.chunk_distribution_network = self.chunk_distribution_config.clone();
+        config.tracked_shards_config = self.tracked_shards.clone();
+        config
+    }
 
-    (
-        setup(SetupOptions {
-            epoch_manager,
-            genesis_time,
-            transaction_validity_period,
-            epoch_length,
-            account_id,
-            skip_sync_wait,
-            min_block_prod_time,
-            max_block_prod_time,
-            num_validator_seats,
-            archive,
-            state_sync_enabled,
-            chunk_distribution_config,
-            clock,
-            runtime,
-            network_adapter,
-            store,
-            enable_doomslug,
-        }),
-        tempdir,
-    )
+    fn into_parts(self) -> SetupParts {
+        let chain_genesis = self.chain_genesis();
+        let config = self.client_config();
+        let rpc_handler_config = RpcHandlerConfig {
+            handler_threads: config.transaction_request_handler_threads,
+            tx_routing_height_horizon: config.tx_routing_height_horizon,
+            epoch_length: config.epoch_length,
+            transaction_validity_period: self.transaction_validity_period,
+            tx_rebroadcast: config.tx_rebroadcast.clone(),
+        };
+        let shards_manager_signer =
+            Some(Arc::new(EmptyValidatorSigner::new(self.account_id.clone())));
+        SetupParts {
+            shard_tracker: ShardTracker::new(self.tracked_shards, self.epoch_manager.clone()),
+            signer: MutableConfigValue::new(self.signer, "validator_signer"),
+            shards_manager_signer: MutableConfigValue::new(
+                shards_manager_signer,
+                "validator_signer",
+            ),
+            chain_genesis,
+            config,
+            rpc_handler_config,
+            account_id: self.account_id,
+            clock: self.clock,
+            epoch_manager: self.epoch_manager,
+            runtime: self.runtime,
+            network_adapter: self.network_adapter,
+            store: self.store,
+            enable_doomslug: self.enable_doomslug,
+        }
+    }
 }
 
-struct SetupOptions {
-    epoch_manager: Arc<dyn EpochManagerAdapter>,
-    genesis_time: time::OffsetDateTime,
-    transaction_validity_period: u64,
-    epoch_length: u64,
+/// The parts of a node which `setup` and `setup_in_test_loop` build the same
+/// way, before starting its actors with actix or in the test loop.
+struct SetupParts {
     account_id: AccountId,
-    skip_sync_wait: bool,
-    min_block_prod_time: u64,
-    max_block_prod_time: u64,
-    num_validator_seats: u64,
-    archive: bool,
-    state_sync_enabled: bool,
-    chunk_distribution_config: Option<ChunkDistributionNetworkConfig>,
     clock: Clock,
+    chain_genesis: ChainGenesis,
+    config: ClientConfig,
+    epoch_manager: Arc<dyn EpochManagerAdapter>,
+    shard_tracker: ShardTracker,
     runtime: Arc<dyn RuntimeAdapter>,
     network_adapter: PeerManagerAdapter,
     store: near_store::Store,
+    signer: MutableValidatorSigner,
+    /// Signer of the shards manager, which only needs the account id.
+    shards_manager_signer: MutableValidatorSigner,
+    rpc_handler_config: RpcHandlerConfig,
     enable_doomslug: bool,
 }
 
-fn setup(
-    SetupOptions {
-        epoch_manager,
-        genesis_time,
-        transaction_validity_period,
-        epoch_length,
-        account_id,
-        skip_sync_wait,
-        min_block_prod_time,
-        max_block_prod_time,
-        num_validator_seats,
-        archive,
-        state_sync_enabled,
-        chunk_distribution_config,
-        clock,
-        runtime,
-        network_adapter,
-        store,
-        enable_doomslug,
-    }: SetupOptions,
+impl SetupParts {
+    fn partial_witness_actor(
+        &self,
+        client_sender: ClientSenderForPartialWitness,
+        compile_contracts_spawner: Arc<dyn AsyncComputationSpawner>,
+        partial_witness_spawner: Arc<dyn AsyncComputationSpawner>,
+    ) -> PartialWitnessActor {
+        PartialWitnessActor::new(
+            self.clock.clone(),
+            self.network_adapter.clone(),
+            client_sender,
+            self.signer.clone(),
+            self.epoch_manager.clone(),
+            self.runtime.clone(),
+            compile_contracts_spawner,
+            partial_witness_spawner,
+            self.config.produced_state_witness_retention_heights,
+        )
+    }
+
+    fn resharding_actor(&self) -> ReshardingActor {
+        ReshardingActor::new(
+            self.epoch_manager.clone(),
+            self.runtime.clone(),
+            ReshardingHandle::new(),
+            self.config.resharding_config.clone(),
+        )
+    }
+}
+
+pub(crate) fn setup(
+    options: SetupOptions,
 ) -> (
     Addr<ActixWrapper<ClientActorInner>>,
     Addr<near_async::actix_wrapper::SyncActixWrapper<ViewClientActorInner>>,
     Addr<near_async::actix_wrapper::SyncActixWrapper<RpcHandler>>,
     ShardsManagerAdapterForTest,
     PartialWitnessSenderForNetwork,
+    broadcast::Sender<NewHeadEvent>,
 ) {
-    let shard_tracker = ShardTracker::new(TrackedShardsConfig::AllShards, epoch_manager.clone());
-    let chain_genesis = ChainGenesis {
-        time: genesis_time,
-        height: 0,
-        gas_limit: 1_000_000,
-        min_gas_price: 100,
-        max_gas_price: 1_000_000_000,
-        total_supply: 3_000_000_000_000_000_000_000_000_000_000_000,
-        gas_price_adjustment_rate: Ratio::from_integer(0),
-        transaction_validity_period,
-        epoch_length,
-        protocol_version: PROTOCOL_VERSION,
-        chain_id: "integration_test".to_string(),
-    };
+    let parts = options.into_parts();
+    let client_adapter_for_partial_witness_actor = LateBoundSender::new();
+    let (partial_witness_addr, _) = spawn_actix_actor(parts.partial_witness_actor(
+        client_adapter_for_partial_witness_actor.as_multi_sender(),
+        Arc::new(RayonAsyncComputationSpawner),
+        Arc::new(RayonAsyncComputationSpawner),
+    ));
+    let partial_witness_adapter = partial_witness_addr.with_auto_span_context();
 
-    let signer = MutableConfigValue::new(
-        Some(Arc::new(create_test_signer(account_id.as_str()))),
-        "validator_signer",
-    );
+    let (resharding_sender_addr, _) = spawn_actix_actor(parts.resharding_actor());
+    let resharding_sender = resharding_sender_addr.with_auto_span_context();
+
+    let SetupParts {
+        clock,
+        chain_genesis,
+        config,
+        epoch_manager,
+        shard_tracker,
+        runtime,
+        network_adapter,
+        store,
+        signer,
+        shards_manager_signer,
+        rpc_handler_config,
+        enable_doomslug,
+        ..
+    } = parts;
     let telemetry = ActixWrapper::new(TelemetryActor::default()).start();
-    let config = {
-        let mut base = ClientConfig::test(
-            skip_sync_wait,
-            min_block_prod_time,
-            max_block_prod_time,
-            num_validator_seats,
-            archive,
-            true,
-            state_sync_enabled,
-        );
-        base.chunk_distribution_network = chunk_distribution_config;
-        base
-    };
 
     let adv = Controls::default();
 
@@ -307,62 +295,36 @@ fn setup(
         epoch_manager.clone(),
         shard_tracker.clone(),
         runtime.clone(),
+        None,
         network_adapter.clone(),
         config.clone(),
         adv.clone(),
     );
 
-    let client_adapter_for_partial_witness_actor = LateBoundSender::new();
-    let (partial_witness_addr, _) = spawn_actix_actor(PartialWitnessActor::new(
-        clock.clone(),
-        network_adapter.clone(),
-        client_adapter_for_partial_witness_actor.as_multi_sender(),
-        signer.clone(),
-        epoch_manager.clone(),
-        runtime.clone(),
-        Arc::new(RayonAsyncComputationSpawner),
-        Arc::new(RayonAsyncComputationSpawner),
-    ));
-    let partial_witness_adapter = partial_witness_addr.with_auto_span_context();
-
-    let (resharding_sender_addr, _) = spawn_actix_actor(ReshardingActor::new(
-        epoch_manager.clone(),
-        runtime.clone(),
-        ReshardingHandle::new(),
-        config.resharding_config.clone(),
-    ));
-    let resharding_sender = resharding_sender_addr.with_auto_span_context();
-
     let shards_manager_adapter_for_client = LateBoundSender::new();
-    let StartClientResult { client_actor, tx_pool, chunk_endorsement_tracker, .. } = start_client(
-        clock,
-        config.clone(),
-        chain_genesis,
-        epoch_manager.clone(),
-        shard_tracker.clone(),
-        runtime.clone(),
-        PeerId::new(PublicKey::empty(KeyType::ED25519)),
-        Arc::new(ActixFutureSpawner),
-        network_adapter.clone(),
-        shards_manager_adapter_for_client.as_sender(),
-        signer.clone(),
-        telemetry.with_auto_span_context().into_sender(),
-        None,
-        None,
-        adv,
-        None,
-        partial_witness_adapter.clone().into_multi_sender(),
-        enable_doomslug,
-        Some(TEST_SEED),
-        resharding_sender.into_multi_sender(),
-    );
-
-    let rpc_handler_config = RpcHandlerConfig {
-        handler_threads: config.transaction_request_handler_threads,
-        tx_routing_height_horizon: config.tx_routing_height_horizon,
-        epoch_length: config.epoch_length,
-        transaction_validity_period,
-    };
+    let StartClientResult { client_actor, tx_pool, chunk_endorsement_tracker, head_events, .. } =
+        start_client(
+            clock,
+            config.clone(),
+            chain_genesis,
+            epoch_manager.clone(),
+            shard_tracker.clone(),
+            runtime.clone(),
+            PeerId::new(PublicKey::empty(KeyType::ED25519)),
+            Arc::new(ActixFutureSpawner),
+            network_adapter.clone(),
+            shards_manager_adapter_for_client.as_sender(),
+            signer.clone(),
+            telemetry.with_auto_span_context().into_sender(),
+            None,
+            None,
+            adv,
+            None,
+            partial_witness_adapter.clone().into_multi_sender(),
+            enable_doomslug,
+            Some(TEST_SEED),
+            resharding_sender.into_multi_sender(),
+        );
 
     let rpc_handler_addr = spawn_rpc_handler_actor(
         rpc_handler_config,
@@ -375,14 +337,13 @@ fn setup(
         network_adapter.clone(),
     );
 
-    let validator_signer = Some(Arc::new(EmptyValidatorSigner::new(account_id)));
     let (shards_manager_addr, _) = start_shards_manager(
         epoch_manager.clone(),
         epoch_manager,
         shard_tracker,
         network_adapter.into_sender(),
         client_actor.clone().with_auto_span_context().into_sender(),
-        MutableConfigValue::new(validator_signer, "validator_signer"),
+        shards_manager_signer,
         store,
         config.chunk_request_retry_period,
     );
@@ -397,9 +358,169 @@ fn setup(
         rpc_handler_addr,
         shards_manager_adapter.into_multi_sender(),
         partial_witness_adapter.into_multi_sender(),
+        head_events,
     )
 }
 
+/// Senders of the actors set up by `TestNodeBuilder::build_in_test_loop`, the
+/// TestLoop counterpart of `ActorHandlesForTesting`. The state of the actors
+/// can be inspected with `test_loop.data.get(&sender.actor_handle())`.
+#[derive(Clone)]
+pub struct TestLoopActorHandles {
+    pub client_sender: TestLoopSender<ClientActorInner>,
+    pub view_client_sender: TestLoopSender<ViewClientActorInner>,
+    pub rpc_handler_sender: TestLoopSender<RpcHandler>,
+    pub shards_manager_adapter: ShardsManagerAdapterForTest,
+    pub partial_witness_sender: PartialWitnessSenderForNetwork,
+    /// Home dir of the runtime, see `ActorHandlesForTesting::runtime_tempdir`.
+    pub runtime_tempdir: Option<Arc<tempfile::TempDir>>,
+    /// Runtime of the node if it's the `KeyValueRuntime`, e.g. to save a
+    /// snapshot of its chain.
+    pub kv_runtime: Option<Arc<KeyValueRuntime>>,
+}
+
+/// Sets up the same actors as `setup`, registered in the test loop. The events
+/// of the actors are identified by the account id.
+pub(crate) fn setup_in_test_loop(
+    test_loop: &mut TestLoopV2,
+    options: SetupOptions,
+) -> TestLoopActorHandles {
+    let parts = options.into_parts();
+    let identifier = parts.account_id.to_string();
+
+    let client_adapter = LateBoundSender::new();
+    let shards_manager_adapter = LateBoundSender::new();
+    let partial_witness_adapter = LateBoundSender::new();
+    let resharding_adapter = LateBoundSender::new();
+    let sync_jobs_adapter = LateBoundSender::new();
+
+    let partial_witness_actor = parts.partial_witness_actor(
+        client_adapter.as_multi_sender(),
+        Arc::new(test_loop.async_computation_spawner(&identifier, |_| Duration::milliseconds(80))),
+        Arc::new(test_loop.async_computation_spawner(&identifier, |_| Duration::milliseconds(80))),
+    );
+    let resharding_actor = parts.resharding_actor();
+
+    let SetupParts {
+        clock,
+        chain_genesis,
+        config,
+        epoch_manager,
+        shard_tracker,
+        runtime,
+        network_adapter,
+        store,
+        signer,
+        shards_manager_signer,
+        rpc_handler_config,
+        enable_doomslug,
+        ..
+    } = parts;
+
+    let view_client_actor = ViewClientActorInner::new(
+        clock.clone(),
+        signer.clone(),
+        chain_genesis.clone(),
+        epoch_manager.clone(),
+        shard_tracker.clone(),
+        runtime.clone(),
+        None,
+        network_adapter.clone(),
+        config.clone(),
+        Controls::default(),
+    )
+    .unwrap();
+
+    let sync_jobs_actor = SyncJobsActor::new(client_adapter.as_multi_sender());
+
+    let protocol_upgrade_schedule = get_protocol_upgrade_schedule(&chain_genesis.chain_id);
+    let client = Client::new(
+        clock.clone(),
+        config.clone(),
+        chain_genesis,
+        epoch_manager.clone(),
+        shard_tracker.clone(),
+        runtime.clone(),
+        network_adapter.clone(),
+        shards_manager_adapter.as_sender(),
+        signer.clone(),
+        if enable_doomslug {
+            DoomslugThresholdMode::TwoThirds
+        } else {
+            DoomslugThresholdMode::NoApprovals
+        },
+        TEST_SEED,
+        None,
+        Arc::new(test_loop.async_computation_spawner(&identifier, |_| Duration::milliseconds(80))),
+        partial_witness_adapter.as_multi_sender(),
+        resharding_adapter.as_multi_sender(),
+        Arc::new(test_loop.future_spawner(&identifier)),
+        client_adapter.as_multi_sender(),
+        client_adapter.as_multi_sender(),
+        protocol_upgrade_schedule,
+    )
+    .unwrap();
+
+    let shards_manager = ShardsManagerActor::new(
+        clock.clone(),
+        shards_manager_signer,
+        epoch_manager.clone(),
+        epoch_manager.clone(),
+        shard_tracker.clone(),
+        network_adapter.clone().into_sender(),
+        client_adapter.as_sender(),
+        store.chunk_store(),
+        client.chain.head().unwrap(),
+        client.chain.header_head().unwrap(),
+        config.chunk_request_retry_period,
+    );
+
+    let client_actor = ClientActorInner::new(
+        clock,
+        client,
+        PeerId::new(PublicKey::empty(KeyType::ED25519)),
+        network_adapter.clone(),
+        noop().into_sender(),
+        None,
+        Controls::default(),
+        None,
+        sync_jobs_adapter.as_multi_sender(),
+    )
+    .unwrap();
+
+    let rpc_handler = RpcHandler::new(
+        rpc_handler_config,
+        client_actor.client.chunk_producer.sharded_tx_pool.clone(),
+        client_actor.client.chunk_endorsement_tracker.clone(),
+        epoch_manager,
+        shard_tracker,
+        signer,
+        runtime,
+        network_adapter,
+    );
+
+    let data = &mut test_loop.data;
+    data.register_actor(&identifier, sync_jobs_actor, Some(sync_jobs_adapter));
+    data.register_actor(&identifier, resharding_actor, Some(resharding_adapter));
+    let client_sender = data.register_actor(&identifier, client_actor, Some(client_adapter));
+    let view_client_sender = data.register_actor(&identifier, view_client_actor, None);
+    let rpc_handler_sender = data.register_actor(&identifier, rpc_handler, None);
+    let shards_manager_sender =
+        data.register_actor(&identifier, shards_manager, Some(shards_manager_adapter));
+    let partial_witness_sender =
+        data.register_actor(&identifier, partial_witness_actor, Some(partial_witness_adapter));
+
+    TestLoopActorHandles {
+        client_sender,
+        view_client_sender,
+        rpc_handler_sender,
+        shards_manager_adapter: shards_manager_sender.into_multi_sender(),
+        partial_witness_sender: partial_witness_sender.into_multi_sender(),
+        runtime_tempdir: None,
+        kv_runtime: None,
+    }
+}
+
 /// Sets up ClientActor and ViewClientActor with mock PeerManager.
 pub fn setup_mock(
     clock: Clock,
@@ -444,33 +565,15 @@ pub fn setup_mock_with_validity_period(
     transaction_validity_period: NumBlocks,
 ) -> ActorHandlesForTesting {
     let network_adapter = LateBoundSender::new();
-    let (
-        (
-            client_addr,
-            view_client_addr,
-            rpc_handler_addr,
-            shards_manager_adapter,
-            partial_witness_sender,
-        ),
-        runtime_tempdir,
-    ) = setup_with_real_epoch_manager(
-        clock.clone(),
-        validators,
-        10,
-        account_id,
-        skip_sync_wait,
-        MIN_BLOCK_PROD_TIME.whole_milliseconds() as u64,
-        MAX_BLOCK_PROD_TIME.whole_milliseconds() as u64,
-        enable_doomslug,
-        false,
-        true,
-        network_adapter.as_multi_sender(),
-        transaction_validity_period,
-        clock.now_utc(),
-        None,
-    );
-    let client_addr1 = client_addr.clone();
-    let rpc_handler_addr1 = rpc_handler_addr.clone();
+    let actor_handles = TestNodeBuilder::new(clock, account_id, TestNodeRuntime::Nightshade)
+        .validators(validators)
+        .skip_sync_wait(skip_sync_wait)
+        .enable_doomslug(enable_doomslug)
+        .transaction_validity_period(transaction_validity_period)
+        .network_adapter(network_adapter.as_multi_sender())
+        .build();
+    let client_addr1 = actor_handles.client_actor.clone();
+    let rpc_handler_addr1 = actor_handles.rpc_handler_actor.clone();
 
     let network_actor = PeerManagerMock::new(move |msg, ctx| {
         peermanager_mock(&msg, ctx, client_addr1.clone(), rpc_handler_addr1.clone())
@@ -478,15 +581,7 @@ pub fn setup_mock_with_validity_period(
     .start();
 
     network_adapter.bind(network_actor);
-
-    ActorHandlesForTesting {
-        client_actor: client_addr,
-        view_client_actor: view_client_addr,
-        rpc_handler_actor: rpc_handler_addr,
-        shards_manager_adapter,
-        partial_witness_sender,
-        runtime_tempdir: Some(runtime_tempdir.into()),
-    }
+    actor_handles
 }
 
 #[derive(Clone)]
@@ -496,28 +591,61 @@ pub struct ActorHandlesForTesting {
     pub rpc_handler_actor: Addr<RpcHandlerActor>,
     pub shards_manager_adapter: ShardsManagerAdapterForTest,
     pub partial_witness_sender: PartialWitnessSenderForNetwork,
+    /// Head updates of the client.
+    pub head_events: broadcast::Sender<NewHeadEvent>,
     // If testing something with runtime that needs runtime home dir users should make sure that
     // this TempDir isn't dropped before test finishes, but is dropped after to avoid leaking temp
     // dirs.
     pub runtime_tempdir: Option<Arc<tempfile::TempDir>>,
 }
 
+/// Delivers the messages of a validator to the other validators, applying the
+/// faults and latencies of the `FaultInjector`.
+struct MessageDelivery<'a> {
+    fault_injector: &'a FaultInjector,
+    message_type: &'a str,
+    sender: &'a AccountId,
+    sender_height: BlockHeight,
+    validators: &'a [AccountId],
+}
+
+impl MessageDelivery<'_> {
+    fn deliver(&self, recipient: usize, send: impl FnOnce() + 'static) {
+        let fault = self.fault_injector.fault(
+            self.message_type,
+            self.sender,
+            &self.validators[recipient],
+            self.sender_height,
+        );
+        match fault {
+            Fault::Drop => {}
+            Fault::Deliver { delay } if delay <= Duration::ZERO => send(),
+            Fault::Deliver { delay } => {
+                actix::spawn(async move {
+                    actix::clock::sleep(delay.unsigned_abs()).await;
+                    send();
+                });
+            }
+        }
+    }
+}
+
 fn send_chunks<T, I, F>(
     connectors: &[ActorHandlesForTesting],
     recipients: I,
     target: T,
-    drop_chunks: bool,
+    delivery: &MessageDelivery,
     send_to: F,
 ) where
     T: Eq,
     I: Iterator<Item = (usize, T)>,
-    F: Fn(&ShardsManagerAdapterForTest),
+    F: Fn(&ShardsManagerAdapterForTest) + Clone + 'static,
 {
     for (i, name) in recipients {
         if name == target {
-            if !drop_chunks || !thread_rng().gen_ratio(1, 5) {
-                send_to(&connectors[i].shards_manager_adapter);
-            }
+            let shards_manager_adapter = connectors[i].shards_manager_adapter.clone();
+            let send_to = send_to.clone();
+            delivery.deliver(i, move || send_to(&shards_manager_adapter));
         }
     }
 }
@@ -526,7 +654,7 @@ fn send_chunks<T, I, F>(
 /// See comments for `setup_mock_all_validators` for argument definitions.
 fn process_peer_manager_message_default(
     msg: PeerManagerMessageRequest,
-    drop_chunks: bool,
+    fault_injector: &FaultInjector,
     tamper_with_fg: bool,
     check_block_stats: bool,
     account_id: AccountId,
@@ -595,6 +723,13 @@ fn process_peer_manager_message_default(
         client_sender.send(SetNetworkInfo(info).with_span_context());
     }
 
+    let delivery = MessageDelivery {
+        fault_injector,
+        message_type: msg.as_network_requests_ref().as_ref(),
+        sender: &account_id,
+        sender_height: last_height.read()[my_ord],
+        validators: &validators,
+    };
     match msg.as_network_requests_ref() {
         NetworkRequests::Block { block } => {
             if check_block_stats {
@@ -603,15 +738,19 @@ fn process_peer_manager_message_default(
                 block_stats2.check_stats(false);
             }
 
-            for actor_handles in connectors {
-                actor_handles.client_actor.do_send(
-                    BlockResponse {
-                        block: block.clone(),
-                        peer_id: PeerInfo::random().id,
-                        was_requested: false,
-                    }
-                    .with_span_context(),
-                );
+            for (i, actor_handles) in connectors.iter().enumerate() {
+                let client_actor = actor_handles.client_actor.clone();
+                let block = block.clone();
+                delivery.deliver(i, move || {
+                    client_actor.do_send(
+                        BlockResponse {
+                            block,
+                            peer_id: PeerInfo::random().id,
+                            was_requested: false,
+                        }
+                        .with_span_context(),
+                    );
+                });
             }
 
             let mut last_height1 = last_height.write();
@@ -622,6 +761,8 @@ fn process_peer_manager_message_default(
 
             hash_to_height.write().insert(*block.header().hash(), block.header().height());
         }
+        NetworkRequests::OptimisticBlock { optimistic_block, .. }
+            if fault_injector.drops_optimistic_block(optimistic_block.height()) => {}
         NetworkRequests::OptimisticBlock { chunk_producers, optimistic_block } => {
             // TODO(#10584): maybe go through an adapter to facilitate testing.
             for (i, name) in validators.iter().enumerate() {
@@ -632,16 +773,18 @@ fn process_peer_manager_message_default(
                     optimistic_block: optimistic_block.clone(),
                     from_peer: PeerInfo::random().id,
                 };
-                connectors[i].client_actor.do_send(msg.with_span_context());
+                let client_actor = connectors[i].client_actor.clone();
+                delivery.deliver(i, move || client_actor.do_send(msg.with_span_context()));
             }
         }
         NetworkRequests::PartialEncodedChunkRequest { target, request, .. } => {
+            let request = request.clone();
             send_chunks(
                 connectors,
                 validators.iter().map(|s| Some(s.clone())).enumerate(),
                 target.account_id.as_ref().map(|s| s.clone()),
-                drop_chunks,
-                |c| {
+                &delivery,
+                move |c| {
                     c.send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkRequest {
                         partial_encoded_chunk_request: request.clone(),
                         route_back: my_address,
@@ -650,20 +793,28 @@ fn process_peer_manager_message_default(
             );
         }
         NetworkRequests::PartialEncodedChunkResponse { route_back, response } => {
-            send_chunks(connectors, addresses.iter().enumerate(), route_back, drop_chunks, |c| {
-                c.send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkResponse {
-                    partial_encoded_chunk_response: response.clone(),
-                    received_time: Instant::now(),
-                });
-            });
+            let response = response.clone();
+            send_chunks(
+                connectors,
+                addresses.iter().enumerate(),
+                route_back,
+                &delivery,
+                move |c| {
+                    c.send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkResponse {
+                        partial_encoded_chunk_response: response.clone(),
+                        received_time: Instant::now(),
+                    });
+                },
+            );
         }
         NetworkRequests::PartialEncodedChunkMessage { account_id, partial_encoded_chunk } => {
+            let partial_encoded_chunk = partial_encoded_chunk.clone();
             send_chunks(
                 connectors,
                 validators.iter().cloned().enumerate(),
                 account_id.clone(),
-                drop_chunks,
-                |c| {
+                &delivery,
+                move |c| {
                     c.send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunk(
                         partial_encoded_chunk.clone().into(),
                     ));
@@ -671,18 +822,35 @@ fn process_peer_manager_message_default(
             );
         }
         NetworkRequests::PartialEncodedChunkForward { account_id, forward } => {
+            let forward = forward.clone();
             send_chunks(
                 connectors,
                 validators.iter().cloned().enumerate(),
                 account_id.clone(),
-                drop_chunks,
-                |c| {
+                &delivery,
+                move |c| {
                     c.send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkForward(
                         forward.clone(),
                     ));
                 },
             );
         }
+        NetworkRequests::ReceiptProofFragment { account_id, fragment } => {
+            let fragment = fragment.clone();
+            let peer_id = my_key_pair.id.clone();
+            send_chunks(
+                connectors,
+                validators.iter().cloned().enumerate(),
+                account_id.clone(),
+                &delivery,
+                move |c| {
+                    c.send(ShardsManagerRequestFromNetwork::ProcessReceiptProofFragment {
+                        fragment: fragment.clone(),
+                        peer_id: peer_id.clone(),
+                    });
+                },
+            );
+        }
         NetworkRequests::BlockRequest { hash, peer_id } => {
             for (i, peer_info) in key_pairs.iter().enumerate() {
                 let peer_id = peer_id.clone();
@@ -780,6 +948,7 @@ fn process_peer_manager_message_default(
                                 shard_id: *shard_id,
                                 sync_hash: *sync_hash,
                                 part_id: *part_id,
+                                peer_id: PeerId::random(),
                             }
                             .with_span_context(),
                         )
@@ -836,10 +1005,9 @@ fn process_peer_manager_message_default(
             if do_propagate {
                 for (i, name) in validators.iter().enumerate() {
                     if name == &approval_message.target {
-                        connectors[i].client_actor.do_send(
-                            BlockApproval(approval.clone(), my_key_pair.id.clone())
-                                .with_span_context(),
-                        );
+                        let client_actor = connectors[i].client_actor.clone();
+                        let msg = BlockApproval(approval.clone(), my_key_pair.id.clone());
+                        delivery.deliver(i, move || client_actor.do_send(msg.with_span_context()));
                     }
                 }
             }
@@ -873,9 +1041,9 @@ fn process_peer_manager_message_default(
         NetworkRequests::ChunkEndorsement(account, endorsement) => {
             for (i, name) in validators.iter().enumerate() {
                 if name == account {
-                    connectors[i]
-                        .rpc_handler_actor
-                        .do_send(ChunkEndorsementMessage(endorsement.clone()).with_span_context());
+                    let rpc_handler_actor = connectors[i].rpc_handler_actor.clone();
+                    let msg = ChunkEndorsementMessage(endorsement.clone());
+                    delivery.deliver(i, move || rpc_handler_actor.do_send(msg.with_span_context()));
                 }
             }
         }
@@ -883,9 +1051,9 @@ fn process_peer_manager_message_default(
             for (account, partial_witness) in partial_witnesses {
                 for (i, name) in validators.iter().enumerate() {
                     if name == account {
-                        connectors[i]
-                            .partial_witness_sender
-                            .send(PartialEncodedStateWitnessMessage(partial_witness.clone()));
+                        let sender = connectors[i].partial_witness_sender.clone();
+                        let msg = PartialEncodedStateWitnessMessage(partial_witness.clone());
+                        delivery.deliver(i, move || sender.send(msg));
                     }
                 }
             }
@@ -894,20 +1062,29 @@ fn process_peer_manager_message_default(
             for account in accounts {
                 for (i, name) in validators.iter().enumerate() {
                     if name == account {
-                        connectors[i].partial_witness_sender.send(
-                            PartialEncodedStateWitnessForwardMessage(partial_witness.clone()),
-                        );
+                        let sender = connectors[i].partial_witness_sender.clone();
+                        let msg = PartialEncodedStateWitnessForwardMessage(partial_witness.clone());
+                        delivery.deliver(i, move || sender.send(msg));
                     }
                 }
             }
         }
+        NetworkRequests::PartialEncodedStateWitnessRequest(account, request) => {
+            for (i, name) in validators.iter().enumerate() {
+                if name == account {
+                    let sender = connectors[i].partial_witness_sender.clone();
+                    let msg = PartialEncodedStateWitnessRequestMessage(request.clone());
+                    delivery.deliver(i, move || sender.send(msg));
+                }
+            }
+        }
         NetworkRequests::ChunkContractAccesses(accounts, accesses) => {
             for account in accounts {
                 for (i, name) in validators.iter().enumerate() {
                     if name == account {
-                        connectors[i]
-                            .partial_witness_sender
-                            .send(ChunkContractAccessesMessage(accesses.clone()));
+                        let sender = connectors[i].partial_witness_sender.clone();
+                        let msg = ChunkContractAccessesMessage(accesses.clone());
+                        delivery.deliver(i, move || sender.send(msg));
                     }
                 }
             }
@@ -915,18 +1092,18 @@ fn process_peer_manager_message_default(
         NetworkRequests::ContractCodeRequest(account, request) => {
             for (i, name) in validators.iter().enumerate() {
                 if name == account {
-                    connectors[i]
-                        .partial_witness_sender
-                        .send(ContractCodeRequestMessage(request.clone()));
+                    let sender = connectors[i].partial_witness_sender.clone();
+                    let msg = ContractCodeRequestMessage(request.clone());
+                    delivery.deliver(i, move || sender.send(msg));
                 }
             }
         }
         NetworkRequests::ContractCodeResponse(account, response) => {
             for (i, name) in validators.iter().enumerate() {
                 if name == account {
-                    connectors[i]
-                        .partial_witness_sender
-                        .send(ContractCodeResponseMessage(response.clone()));
+                    let sender = connectors[i].partial_witness_sender.clone();
+                    let msg = ContractCodeResponseMessage(response.clone());
+                    delivery.deliver(i, move || sender.send(msg));
                 }
             }
         }
@@ -934,9 +1111,9 @@ fn process_peer_manager_message_default(
             for account in accounts {
                 for (i, name) in validators.iter().enumerate() {
                     if name == account {
-                        connectors[i]
-                            .partial_witness_sender
-                            .send(PartialEncodedContractDeploysMessage(deploys.clone()));
+                        let sender = connectors[i].partial_witness_sender.clone();
+                        let msg = PartialEncodedContractDeploysMessage(deploys.clone());
+                        delivery.deliver(i, move || sender.send(msg));
                     }
                 }
             }
@@ -944,6 +1121,8 @@ fn process_peer_manager_message_default(
         NetworkRequests::ForwardTx(_, _)
         | NetworkRequests::BanPeer { .. }
         | NetworkRequests::TxStatus(_, _, _)
+        | NetworkRequests::ChunkRequest { .. }
+        | NetworkRequests::ChunkResponse { .. }
         | NetworkRequests::SnapshotHostInfo { .. }
         | NetworkRequests::ChunkStateWitnessAck(_, _)
         | NetworkRequests::EpochSyncRequest { .. }
@@ -966,7 +1145,9 @@ fn process_peer_manager_message_default(
 ///                 equal to `block_prod_time` if `tamper_with_fg` is `true`, otherwise it is
 ///                 `block_prod_time * 2`
 ///
-/// `drop_chunks` - if set to true, 10% of all the chunk messages / requests will be dropped
+/// `fault_injection` - seeded faults and per-link latencies of the messages between the
+///                 validators, see `FaultInjectionConfig`. The seed is printed, so that the faults
+///                 of a failed run can be injected again.
 ///
 /// `tamper_with_fg` - if set to true, will split the heights into groups of 100. For some groups
 ///                 all the approvals will be dropped (thus completely disabling the finality gadget
@@ -997,28 +1178,68 @@ pub fn setup_mock_all_validators(
     key_pairs: Vec<PeerInfo>,
     skip_sync_wait: bool,
     block_prod_time: u64,
-    drop_chunks: bool,
+    fault_injection: FaultInjectionConfig,
     tamper_with_fg: bool,
     epoch_length: BlockHeightDelta,
     enable_doomslug: bool,
     archive: Vec<bool>,
     check_block_stats: bool,
     chunk_distribution_config: Option<ChunkDistributionNetworkConfig>,
-    peer_manager_mock: Box<
-        dyn FnMut(
-            // Peer validators
-            &[ActorHandlesForTesting],
-            // Validator that sends the message
-            AccountId,
-            // The message itself
-            &PeerManagerMessageRequest,
-        ) -> (PeerManagerMessageResponse, /* perform default */ bool),
-    >,
+    peer_manager_mock: Box<PeerManagerMockFn>,
 ) -> (Vec<ActorHandlesForTesting>, Arc<RwLock<BlockStats>>) {
-    let peer_manager_mock = Arc::new(RwLock::new(peer_manager_mock));
     let validators = vs.all_validators().cloned().collect::<Vec<_>>();
-    let addresses: Vec<_> = (0..key_pairs.len()).map(|i| hash(vec![i as u8].as_ref())).collect();
     let genesis_time = clock.now_utc();
+    setup_mock_network(
+        clock.clone(),
+        validators,
+        key_pairs,
+        fault_injection,
+        tamper_with_fg,
+        check_block_stats,
+        peer_manager_mock,
+        |index, account_id, network_adapter| {
+            TestNodeBuilder::new(clock.clone(), account_id, TestNodeRuntime::KeyValue)
+                .validator_schedule(vs.clone())
+                .epoch_length(epoch_length)
+                .genesis_time(genesis_time)
+                .skip_sync_wait(skip_sync_wait)
+                .block_prod_time(block_prod_time, block_prod_time * 3)
+                .enable_doomslug(enable_doomslug)
+                .archive(archive[index])
+                .state_sync_enabled(false)
+                .transaction_validity_period(10000)
+                .chunk_distribution_config(chunk_distribution_config.clone())
+                .network_adapter(network_adapter)
+                .build()
+        },
+    )
+}
+
+/// Callback of the mock network, see `setup_mock_all_validators`. It's given
+/// the actors of all the nodes, the validator sending the message and the
+/// message, and returns `(response, perform_default)`.
+pub type PeerManagerMockFn =
+    dyn FnMut(
+        &[ActorHandlesForTesting],
+        AccountId,
+        &PeerManagerMessageRequest,
+    ) -> (PeerManagerMessageResponse, /* perform default */ bool);
+
+/// Connects the nodes of `validators`, built by `build_node` with the adapter
+/// of their network, through a mock network which delivers their messages to
+/// each other. See `setup_mock_all_validators` for the other arguments.
+pub(crate) fn setup_mock_network(
+    clock: Clock,
+    validators: Vec<AccountId>,
+    key_pairs: Vec<PeerInfo>,
+    fault_injection: FaultInjectionConfig,
+    tamper_with_fg: bool,
+    check_block_stats: bool,
+    peer_manager_mock: Box<PeerManagerMockFn>,
+    mut build_node: impl FnMut(usize, AccountId, PeerManagerAdapter) -> ActorHandlesForTesting,
+) -> (Vec<ActorHandlesForTesting>, Arc<RwLock<BlockStats>>) {
+    let peer_manager_mock = Arc::new(RwLock::new(peer_manager_mock));
+    let addresses: Vec<_> = (0..key_pairs.len()).map(|i| hash(vec![i as u8].as_ref())).collect();
     let mut ret = vec![];
 
     let connectors: Arc<OnceLock<Vec<ActorHandlesForTesting>>> = Default::default();
@@ -1029,11 +1250,11 @@ pub fn setup_mock_all_validators(
     let largest_endorsed_height = Arc::new(RwLock::new(vec![0u64; key_pairs.len()]));
     let largest_skipped_height = Arc::new(RwLock::new(vec![0u64; key_pairs.len()]));
     let hash_to_height = Arc::new(RwLock::new(HashMap::new()));
-    let block_stats = Arc::new(RwLock::new(BlockStats::new(clock.clone())));
+    let block_stats = Arc::new(RwLock::new(BlockStats::new(clock)));
+    let fault_injector = Arc::new(FaultInjector::new(fault_injection));
 
     for (index, account_id) in validators.clone().into_iter().enumerate() {
         let account_id1 = account_id.clone();
-        let vs = vs.clone();
         let block_stats1 = block_stats.clone();
         let validators_clone2 = validators.clone();
         let key_pairs = key_pairs.clone();
@@ -1045,8 +1266,7 @@ pub fn setup_mock_all_validators(
         let largest_endorsed_height1 = largest_endorsed_height.clone();
         let largest_skipped_height1 = largest_skipped_height.clone();
         let hash_to_height1 = hash_to_height.clone();
-        let archive1 = archive.clone();
-        let chunk_distribution_config1 = chunk_distribution_config.clone();
+        let fault_injector1 = fault_injector.clone();
         let client_sender = LateBoundSender::new();
         let client_sender1 = client_sender.clone();
         let pm = PeerManagerMock::new(move |msg, _ctx| {
@@ -1060,7 +1280,7 @@ pub fn setup_mock_all_validators(
             if perform_default {
                 process_peer_manager_message_default(
                     msg,
-                    drop_chunks,
+                    &fault_injector1,
                     tamper_with_fg,
                     check_block_stats,
                     account_id1.clone(),
@@ -1081,39 +1301,9 @@ pub fn setup_mock_all_validators(
         })
         .start();
 
-        let (
-            client_addr,
-            view_client_addr,
-            rpc_handler_addr,
-            shards_manager_adapter,
-            partial_witness_sender,
-        ) = setup_with_mock_epoch_manager(
-            clock.clone(),
-            vs,
-            epoch_length,
-            account_id.clone(),
-            skip_sync_wait,
-            block_prod_time,
-            block_prod_time * 3,
-            enable_doomslug,
-            archive1[index],
-            false,
-            pm.into_multi_sender(),
-            10000,
-            genesis_time,
-            // ctx,
-            chunk_distribution_config1,
-        );
-        client_sender.bind(client_addr.clone());
-
-        ret.push(ActorHandlesForTesting {
-            client_actor: client_addr,
-            view_client_actor: view_client_addr,
-            rpc_handler_actor: rpc_handler_addr,
-            shards_manager_adapter,
-            partial_witness_sender,
-            runtime_tempdir: None,
-        });
+        let actor_handles = build_node(index, account_id, pm.into_multi_sender());
+        client_sender.bind(actor_handles.client_actor.clone());
+        ret.push(actor_handles);
     }
     hash_to_height.write().insert(CryptoHash::default(), 0);
     connectors.set(ret.clone()).ok().unwrap();
@@ -1179,7 +1369,7 @@ pub fn setup_no_network_with_validity_period(
 pub fn setup_client_with_runtime(
     clock: Clock,
     num_validator_seats: NumSeats,
-    enable_doomslug: bool,
+    doomslug_threshold_mode: DoomslugThresholdMode,
     network_adapter: PeerManagerAdapter,
     shards_manager_adapter: SynchronousShardsManagerAdapter,
     chain_genesis: ChainGenesis,
@@ -1189,6 +1379,7 @@ pub fn setup_client_with_runtime(
     rng_seed: RngSeed,
     archive: bool,
     save_trie_changes: bool,
+    transaction_admission: Option<TransactionAdmissionConfig>,
     snapshot_callbacks: Option<SnapshotCallbacks>,
     partial_witness_adapter: PartialWitnessSenderForClient,
     validator_signer: Arc<ValidatorSigner>,
@@ -1197,6 +1388,7 @@ pub fn setup_client_with_runtime(
     let mut config =
         ClientConfig::test(true, 10, 20, num_validator_seats, archive, save_trie_changes, true);
     config.epoch_length = chain_genesis.epoch_length;
+    config.transaction_admission = transaction_admission;
     let protocol_upgrade_schedule = get_protocol_upgrade_schedule(&chain_genesis.chain_id);
     let mut client = Client::new(
         clock,
@@ -1208,7 +1400,7 @@ pub fn setup_client_with_runtime(
         network_adapter,
         shards_manager_adapter.into_sender(),
         MutableConfigValue::new(Some(validator_signer), "validator_signer"),
-        enable_doomslug,
+        doomslug_threshold_mode,
         rng_seed,
         snapshot_callbacks,
         Arc::new(RayonAsyncComputationSpawner),
@@ -1254,6 +1446,10 @@ pub fn setup_synchronous_shards_manager(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            defer_fork_postprocessing: false,
+            transactions_by_signer_limit: None,
+            max_concurrent_shard_applies: None,
+            enable_receipt_audit: false,
         }, // irrelevant
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -1294,6 +1490,7 @@ pub fn setup_tx_request_handler(
         tx_routing_height_horizon: client_config.tx_routing_height_horizon,
         epoch_length: chain_genesis.epoch_length,
         transaction_validity_period: chain_genesis.transaction_validity_period,
+        tx_rebroadcast: client_config.tx_rebroadcast,
     };
 
     RpcHandler::new(
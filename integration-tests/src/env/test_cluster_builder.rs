@@ -0,0 +1,184 @@
+//! Clusters of nodes with the real `EpochManager` and `NightshadeRuntime`.
+//!
+//! `setup_mock_all_validators` connects nodes running the `MockEpochManager`
+//! and the `KeyValueRuntime`, which don't implement the protocol. The nodes of
+//! a `TestClusterBuilder` go through the same mock network, fault injection
+//! included, but run the real epoch manager and runtime on top of a genesis
+//! shared by the cluster, so the tests exercise the actual protocol.
+
+use near_async::time::Clock;
+use near_chain_configs::{Genesis, TrackedShardsConfig};
+use near_network::types::{NetworkResponses, PeerInfo, PeerManagerMessageResponse};
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::{AccountId, BlockHeightDelta, NumSeats};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::fault_injection::FaultInjectionConfig;
+use super::setup::{
+    ActorHandlesForTesting, MIN_BLOCK_PROD_TIME, PeerManagerMockFn, setup_mock_network,
+};
+use super::test_node_builder::{TestNodeBuilder, TestNodeRuntime};
+use crate::utils::block_stats::BlockStats;
+
+/// Options of a single node of the cluster.
+#[derive(Default)]
+struct NodeOptions {
+    genesis: Option<Genesis>,
+    tracked_shards: Option<TrackedShardsConfig>,
+    archive: bool,
+}
+
+/// A builder for a cluster of validator nodes, each running the real
+/// `EpochManager` and `NightshadeRuntime`, connected by a mock network.
+pub struct TestClusterBuilder {
+    clock: Clock,
+    validators: Vec<AccountId>,
+    genesis: Option<Genesis>,
+    shard_layout: ShardLayout,
+    epoch_length: BlockHeightDelta,
+    block_prod_time: u64,
+    fault_injection: FaultInjectionConfig,
+    check_block_stats: bool,
+    peer_manager_mock: Box<PeerManagerMockFn>,
+    nodes: HashMap<usize, NodeOptions>,
+}
+
+impl TestClusterBuilder {
+    /// Constructs a new builder for a cluster with a node for every validator.
+    /// By default the genesis has a single shard, every node tracks all the
+    /// shards and the messages are delivered as they are.
+    pub fn new(clock: Clock, validators: Vec<AccountId>) -> Self {
+        assert!(!validators.is_empty(), "the cluster needs at least one validator");
+        Self {
+            clock,
+            validators,
+            genesis: None,
+            shard_layout: ShardLayout::single_shard(),
+            epoch_length: 10,
+            block_prod_time: MIN_BLOCK_PROD_TIME.whole_milliseconds() as u64,
+            fault_injection: FaultInjectionConfig::new(),
+            check_block_stats: false,
+            peer_manager_mock: Box::new(|_, _, _| {
+                (PeerManagerMessageResponse::NetworkResponses(NetworkResponses::NoResponse), true)
+            }),
+            nodes: HashMap::new(),
+        }
+    }
+
+    /// Sets the shard layout of the test genesis built for the cluster.
+    pub fn shard_layout(mut self, shard_layout: ShardLayout) -> Self {
+        self.shard_layout = shard_layout;
+        self
+    }
+
+    /// Sets the epoch length of the test genesis built for the cluster.
+    pub fn epoch_length(mut self, epoch_length: BlockHeightDelta) -> Self {
+        self.epoch_length = epoch_length;
+        self
+    }
+
+    /// Sets the genesis of all the nodes, replacing the test genesis with the
+    /// validators of the cluster. The shard layout and the epoch length are
+    /// then given by the genesis.
+    pub fn genesis(mut self, genesis: Genesis) -> Self {
+        self.genesis = Some(genesis);
+        self
+    }
+
+    /// Sets the minimum block production time, in milliseconds. The maximum is
+    /// three times as long.
+    pub fn block_prod_time(mut self, block_prod_time: u64) -> Self {
+        self.block_prod_time = block_prod_time;
+        self
+    }
+
+    pub fn fault_injection(mut self, fault_injection: FaultInjectionConfig) -> Self {
+        self.fault_injection = fault_injection;
+        self
+    }
+
+    pub fn check_block_stats(mut self, check_block_stats: bool) -> Self {
+        self.check_block_stats = check_block_stats;
+        self
+    }
+
+    /// Sets the callback of the mock network which can override the delivery
+    /// of the messages, see `setup_mock_all_validators`.
+    pub fn peer_manager_mock(mut self, peer_manager_mock: Box<PeerManagerMockFn>) -> Self {
+        self.peer_manager_mock = peer_manager_mock;
+        self
+    }
+
+    /// Sets the genesis of the node at `index` only, e.g. to test a node
+    /// started with a different genesis than the rest of the cluster.
+    pub fn node_genesis(mut self, index: usize, genesis: Genesis) -> Self {
+        self.node(index).genesis = Some(genesis);
+        self
+    }
+
+    pub fn tracked_shards(mut self, index: usize, tracked_shards: TrackedShardsConfig) -> Self {
+        self.node(index).tracked_shards = Some(tracked_shards);
+        self
+    }
+
+    pub fn archive(mut self, index: usize, archive: bool) -> Self {
+        self.node(index).archive = archive;
+        self
+    }
+
+    fn node(&mut self, index: usize) -> &mut NodeOptions {
+        assert!(index < self.validators.len(), "no node {index} in the cluster");
+        self.nodes.entry(index).or_default()
+    }
+
+    /// Spawns the actors of all the nodes as actix actors and connects them.
+    /// Returns the actors of the nodes, in the order of the validators, and
+    /// the stats of the blocks they produced.
+    pub fn build(self) -> (Vec<ActorHandlesForTesting>, Arc<RwLock<BlockStats>>) {
+        let Self {
+            clock,
+            validators,
+            genesis,
+            shard_layout,
+            epoch_length,
+            block_prod_time,
+            fault_injection,
+            check_block_stats,
+            peer_manager_mock,
+            mut nodes,
+        } = self;
+        let genesis = genesis.unwrap_or_else(|| {
+            let mut genesis = Genesis::from_accounts(
+                clock.clone(),
+                validators.clone(),
+                validators.len() as NumSeats,
+                shard_layout,
+            );
+            genesis.config.epoch_length = epoch_length;
+            genesis
+        });
+        let key_pairs = validators.iter().map(|_| PeerInfo::random()).collect();
+        setup_mock_network(
+            clock.clone(),
+            validators,
+            key_pairs,
+            fault_injection,
+            false,
+            check_block_stats,
+            peer_manager_mock,
+            |index, account_id, network_adapter| {
+                let node = nodes.remove(&index).unwrap_or_default();
+                TestNodeBuilder::new(clock.clone(), account_id, TestNodeRuntime::Nightshade)
+                    .genesis(node.genesis.unwrap_or_else(|| genesis.clone()))
+                    .tracked_shards(node.tracked_shards.unwrap_or(TrackedShardsConfig::AllShards))
+                    .archive(node.archive)
+                    .block_prod_time(block_prod_time, block_prod_time * 3)
+                    .state_sync_enabled(false)
+                    .network_adapter(network_adapter)
+                    .build()
+            },
+        )
+    }
+}
@@ -7,12 +7,12 @@ use near_chain::stateless_validation::processing_tracker::{
 };
 use near_chain::test_utils::ValidatorSchedule;
 use near_chain::types::Tip;
-use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
-use near_chain_configs::{Genesis, GenesisConfig};
+use near_chain::{ChainGenesis, ChainStoreAccess, DoomslugThresholdMode, Provenance};
+use near_chain_configs::{Genesis, GenesisConfig, TransactionAdmissionConfig};
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::test_utils::{MockClientAdapterForShardsManager, SynchronousShardsManagerAdapter};
 use near_client::{Client, DistributeStateWitnessRequest, RpcHandler};
-use near_crypto::{InMemorySigner, Signer};
+use near_crypto::{InMemorySigner, KeyType, PublicKey, Signer};
 use near_epoch_manager::shard_assignment::{account_id_to_shard_id, shard_id_to_uid};
 use near_network::client::ProcessTxResponse;
 use near_network::shards_manager::ShardsManagerRequestFromNetwork;
@@ -27,6 +27,7 @@ use near_primitives::block::Block;
 use near_primitives::epoch_info::RngSeed;
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
 use near_primitives::sharding::{ChunkHash, PartialEncodedChunk};
 use near_primitives::stateless_validation::ChunkProductionKey;
 use near_primitives::stateless_validation::state_witness::ChunkStateWitness;
@@ -48,6 +49,7 @@ use time::ext::InstantExt as _;
 
 use crate::utils::mock_partial_witness_adapter::MockPartialWitnessAdapter;
 
+use super::invariant_observer::InvariantObserver;
 use super::setup::{TEST_SEED, setup_client_with_runtime};
 use super::test_env_builder::TestEnvBuilder;
 
@@ -66,6 +68,8 @@ pub struct TestEnv {
     pub shards_manager_adapters: Vec<SynchronousShardsManagerAdapter>,
     pub clients: Vec<Client>,
     pub rpc_handlers: Vec<RpcHandler>,
+    /// One per client, empty if the invariants aren't checked.
+    pub(crate) invariant_observers: Vec<InvariantObserver>,
     pub(crate) account_indices: AccountIndices,
     pub(crate) paused_blocks: Arc<Mutex<HashMap<CryptoHash, Arc<OnceLock<()>>>>>,
     // random seed to be inject in each client according to AccountId
@@ -73,6 +77,8 @@ pub struct TestEnv {
     pub(crate) seeds: HashMap<AccountId, RngSeed>,
     pub(crate) archive: bool,
     pub(crate) save_trie_changes: bool,
+    pub(crate) doomslug_threshold_mode: DoomslugThresholdMode,
+    pub(crate) transaction_admission: Option<TransactionAdmissionConfig>,
 }
 
 pub struct StateWitnessPropagationOutput {
@@ -140,6 +146,17 @@ impl TestEnv {
         }
         self.process_shards_manager_responses(id);
         self.propagate_chunk_state_witnesses_and_endorsements(false);
+        self.check_invariants(id);
+    }
+
+    /// Checks the invariants of the chain of client `id`, see
+    /// `InvariantObserver`. Done after every block processed with
+    /// `process_block`, tests which process blocks directly in the clients
+    /// can call it themselves.
+    pub fn check_invariants(&mut self, id: usize) {
+        if let Some(observer) = self.invariant_observers.get_mut(id) {
+            observer.check(&self.clients[id]);
+        }
     }
 
     /// Produces block by given client, which may kick off chunk production.
@@ -234,6 +251,10 @@ impl TestEnv {
                 let network_adapter = network_adapters.get(i).unwrap();
                 let _span =
                     tracing::debug_span!(target: "test", "process_partial_encoded_chunks", client=i).entered();
+                let peer_id = PeerId::new(PublicKey::from_seed(
+                    KeyType::ED25519,
+                    self.get_client_id(i).as_str(),
+                ));
 
                 keep_going |= network_adapter.handle_filtered(|request| match request {
                     PeerManagerMessageRequest::NetworkRequests(
@@ -266,6 +287,17 @@ impl TestEnv {
                         self.shards_manager(&account_id).send(message);
                         None
                     }
+                    PeerManagerMessageRequest::NetworkRequests(
+                        NetworkRequests::ReceiptProofFragment { account_id, fragment },
+                    ) => {
+                        let message =
+                            ShardsManagerRequestFromNetwork::ProcessReceiptProofFragment {
+                                fragment,
+                                peer_id: peer_id.clone(),
+                            };
+                        self.shards_manager(&account_id).send(message);
+                        None
+                    }
                     _ => Some(request),
                 });
             }
@@ -353,6 +385,10 @@ impl TestEnv {
                         .chunk_inclusion_tracker
                         .mark_chunk_header_ready_for_inclusion(chunk_header, chunk_producer);
                 }
+                ShardsManagerResponse::ChunkFetched(shard_chunk) => {
+                    let signer = self.clients[id].validator_signer.get();
+                    self.clients[id].on_chunk_fetched(shard_chunk, None, &signer);
+                }
             }
             any_processed = true;
         }
@@ -677,7 +713,7 @@ impl TestEnv {
         self.clients[idx] = setup_client_with_runtime(
             self.clock.clone(),
             num_validator_seats,
-            false,
+            self.doomslug_threshold_mode,
             self.network_adapters[idx].clone().as_multi_sender(),
             self.shards_manager_adapters[idx].clone(),
             self.chain_genesis.clone(),
@@ -687,6 +723,7 @@ impl TestEnv {
             rng_seed,
             self.archive,
             self.save_trie_changes,
+            self.transaction_admission.clone(),
             None,
             self.clients[idx].partial_witness_adapter.clone(),
             self.clients[idx].validator_signer.get().unwrap(),
@@ -2,10 +2,10 @@ use actix_rt::System;
 use itertools::{Itertools, multizip};
 use near_async::messaging::{IntoMultiSender, IntoSender, noop};
 use near_async::time::Clock;
-use near_chain::state_snapshot_actor::SnapshotCallbacks;
+use near_chain::state_snapshot_actor::{RestorePointTrigger, SnapshotCallbacks};
 use near_chain::types::RuntimeAdapter;
-use near_chain::{Block, ChainGenesis};
-use near_chain_configs::{Genesis, GenesisConfig, TrackedShardsConfig};
+use near_chain::{Block, ChainGenesis, DoomslugThresholdMode};
+use near_chain_configs::{Genesis, GenesisConfig, TrackedShardsConfig, TransactionAdmissionConfig};
 use near_chunks::test_utils::MockClientAdapterForShardsManager;
 use near_client::Client;
 use near_epoch_manager::shard_tracker::ShardTracker;
@@ -14,8 +14,9 @@ use near_network::test_utils::MockPeerManagerAdapter;
 use near_parameters::RuntimeConfigStore;
 use near_primitives::epoch_info::RngSeed;
 use near_primitives::epoch_manager::{AllEpochConfigTestOverrides, EpochConfig, EpochConfigStore};
+use near_primitives::hash::CryptoHash;
 use near_primitives::test_utils::create_test_signer;
-use near_primitives::types::{AccountId, ShardIndex};
+use near_primitives::types::{AccountId, BlockHeight, ShardIndex};
 use near_store::genesis::initialize_genesis_state;
 use near_store::test_utils::create_test_store;
 use near_store::{NodeStorage, ShardUId, Store, StoreConfig, TrieConfig};
@@ -27,6 +28,7 @@ use std::sync::Arc;
 
 use crate::utils::mock_partial_witness_adapter::MockPartialWitnessAdapter;
 
+use super::invariant_observer::InvariantObserver;
 use super::setup::{
     TEST_SEED, setup_client_with_runtime, setup_synchronous_shards_manager,
     setup_tx_request_handler,
@@ -54,6 +56,9 @@ pub struct TestEnvBuilder {
     archive: bool,
     save_trie_changes: bool,
     state_snapshot_enabled: bool,
+    check_invariants: bool,
+    doomslug_threshold_mode: DoomslugThresholdMode,
+    transaction_admission: Option<TransactionAdmissionConfig>,
 }
 
 /// Builder for the [`TestEnv`] structure.
@@ -84,6 +89,9 @@ impl TestEnvBuilder {
             archive: false,
             save_trie_changes: true,
             state_snapshot_enabled: false,
+            check_invariants: true,
+            doomslug_threshold_mode: DoomslugThresholdMode::NoApprovals,
+            transaction_admission: None,
         }
     }
 
@@ -471,6 +479,33 @@ impl TestEnvBuilder {
         self
     }
 
+    /// Whether the invariants of the chains of the clients are checked after
+    /// every block processed, see `InvariantObserver`. Enabled by default, so
+    /// only tests which corrupt the chain on purpose should disable it.
+    pub fn check_invariants(mut self, check_invariants: bool) -> Self {
+        self.check_invariants = check_invariants;
+        self
+    }
+
+    /// Makes every block which becomes the head of a client final, so that the
+    /// chain finalizes immediately and the blocks not building on the head are
+    /// dropped, see `DoomslugThresholdMode::SingleBlockFinality`. Meant for
+    /// tests which don't want to deal with forks.
+    pub fn single_block_finality(mut self) -> Self {
+        self.doomslug_threshold_mode = DoomslugThresholdMode::SingleBlockFinality;
+        self
+    }
+
+    /// Limits the number of transactions of a single signer in the chunks
+    /// produced by the clients, see `ClientConfig::transaction_admission`.
+    pub fn transaction_admission(
+        mut self,
+        transaction_admission: TransactionAdmissionConfig,
+    ) -> Self {
+        self.transaction_admission = Some(transaction_admission);
+        self
+    }
+
     /// Constructs new `TestEnv` structure.
     ///
     /// If no clients were configured (either through count or vector) one
@@ -546,15 +581,23 @@ impl TestEnvBuilder {
                         tracing::info!(target: "state_snapshot", "delete_snapshot_callback");
                         tries.delete_state_snapshot();
                     });
+                    let tries = runtime.get_tries();
+                    let make_restore_point_callback = Arc::new(
+                        move |trigger: RestorePointTrigger, height: BlockHeight, block_hash: CryptoHash| {
+                            tracing::info!(target: "state_snapshot", ?trigger, height, "make_restore_point_callback");
+                            tries.create_restore_point(height, block_hash).unwrap();
+                        },
+                    );
                     let snapshot_callbacks = SnapshotCallbacks {
                         make_snapshot_callback,
                         delete_snapshot_callback,
+                        make_restore_point_callback,
                     };
                     let validator_signer = Arc::new(create_test_signer(client_accounts[i].as_str()));
                     setup_client_with_runtime(
                         clock.clone(),
                         u64::try_from(num_validators).unwrap(),
-                        false,
+                        self.doomslug_threshold_mode,
                         network_adapter.as_multi_sender(),
                         shards_manager_adapter,
                         chain_genesis.clone(),
@@ -564,6 +607,7 @@ impl TestEnvBuilder {
                         rng_seed,
                         self.archive,
                         self.save_trie_changes,
+                        self.transaction_admission.clone(),
                         Some(snapshot_callbacks),
                         partial_witness_adapter.into_multi_sender(),
                         validator_signer,
@@ -585,6 +629,17 @@ impl TestEnvBuilder {
             })
             .collect();
 
+        let invariant_observers = if self.check_invariants {
+            client_accounts
+                .iter()
+                .map(|account_id| {
+                    InvariantObserver::new(account_id.clone(), self.doomslug_threshold_mode)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
         TestEnv {
             clock,
             chain_genesis,
@@ -595,6 +650,7 @@ impl TestEnvBuilder {
             shards_manager_adapters,
             clients,
             rpc_handlers: tx_request_handlers,
+            invariant_observers,
             account_indices: AccountIndices(
                 self.clients
                     .into_iter()
@@ -606,6 +662,8 @@ impl TestEnvBuilder {
             seeds,
             archive: self.archive,
             save_trie_changes: self.save_trie_changes,
+            doomslug_threshold_mode: self.doomslug_threshold_mode,
+            transaction_admission: self.transaction_admission,
         }
     }
 
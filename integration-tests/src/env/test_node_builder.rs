@@ -0,0 +1,382 @@
+use near_async::messaging::{IntoMultiSender, noop};
+use near_async::test_loop::TestLoopV2;
+use near_async::time::{Clock, Utc};
+use near_chain::ChainGenesis;
+use near_chain::test_utils::{
+    KeyValueRuntime, KeyValueRuntimeOptions, MockEpochManager, ValidatorSchedule,
+};
+use near_chain::types::RuntimeAdapter;
+use near_chain_configs::{ChunkDistributionNetworkConfig, Genesis, TrackedShardsConfig};
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_network::types::PeerManagerAdapter;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::test_utils::create_test_signer;
+use near_primitives::types::{AccountId, BlockHeightDelta, NumBlocks, NumSeats};
+use near_primitives::validator_signer::ValidatorSigner;
+use near_store::genesis::initialize_genesis_state;
+use near_store::test_utils::create_test_store;
+use nearcore::NightshadeRuntime;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::setup::{
+    ActorHandlesForTesting, MAX_BLOCK_PROD_TIME, MIN_BLOCK_PROD_TIME, SetupOptions,
+    TestLoopActorHandles, setup, setup_in_test_loop, test_chain_genesis,
+};
+
+/// Runtime of a node set up by [`TestNodeBuilder`], together with the epoch
+/// manager it works with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestNodeRuntime {
+    /// `KeyValueRuntime` with the `MockEpochManager`, which follows the
+    /// validator schedule as is.
+    KeyValue,
+    /// `NightshadeRuntime` with the real `EpochManager`, initialized from a
+    /// test genesis with the validators of the schedule.
+    Nightshade,
+}
+
+/// A builder for the actors of a single node, spawned as actix actors or
+/// registered in a test loop.
+pub struct TestNodeBuilder {
+    clock: Clock,
+    account_id: AccountId,
+    runtime: TestNodeRuntime,
+    validators: ValidatorSchedule,
+    shard_layout: Option<ShardLayout>,
+    genesis: Option<Genesis>,
+    tracked_shards: TrackedShardsConfig,
+    archive: bool,
+    memtrie: bool,
+    signer: Option<Arc<ValidatorSigner>>,
+    epoch_length: BlockHeightDelta,
+    genesis_time: Option<Utc>,
+    transaction_validity_period: NumBlocks,
+    skip_sync_wait: bool,
+    min_block_prod_time: u64,
+    max_block_prod_time: u64,
+    enable_doomslug: bool,
+    state_sync_enabled: bool,
+    chunk_distribution_config: Option<ChunkDistributionNetworkConfig>,
+    network_adapter: Option<PeerManagerAdapter>,
+    kv_snapshot: Option<PathBuf>,
+}
+
+impl TestNodeBuilder {
+    /// Constructs a new builder for the node of `account_id`. By default the
+    /// account is the only validator, all the shards are tracked and the
+    /// validator key of the node is the test signer of the account.
+    pub fn new(clock: Clock, account_id: AccountId, runtime: TestNodeRuntime) -> Self {
+        Self {
+            clock,
+            validators: ValidatorSchedule::new()
+                .block_producers_per_epoch(vec![vec![account_id.clone()]]),
+            signer: Some(Arc::new(create_test_signer(account_id.as_str()))),
+            account_id,
+            runtime,
+            shard_layout: None,
+            genesis: None,
+            tracked_shards: TrackedShardsConfig::AllShards,
+            archive: false,
+            memtrie: false,
+            epoch_length: 10,
+            genesis_time: None,
+            transaction_validity_period: 100,
+            skip_sync_wait: true,
+            min_block_prod_time: MIN_BLOCK_PROD_TIME.whole_milliseconds() as u64,
+            max_block_prod_time: MAX_BLOCK_PROD_TIME.whole_milliseconds() as u64,
+            enable_doomslug: false,
+            state_sync_enabled: true,
+            chunk_distribution_config: None,
+            network_adapter: None,
+            kv_snapshot: None,
+        }
+    }
+
+    /// Sets the validators, which are the block producers of every epoch.
+    pub fn validators(self, validators: Vec<AccountId>) -> Self {
+        self.validator_schedule(
+            ValidatorSchedule::new().block_producers_per_epoch(vec![validators]),
+        )
+    }
+
+    /// Sets the validators of every epoch. The `Nightshade` runtime only uses
+    /// them as the validators of the genesis.
+    pub fn validator_schedule(mut self, validators: ValidatorSchedule) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Sets the shard layout of the genesis. Only supported by the `Nightshade`
+    /// runtime, the shards of the `KeyValue` runtime are given by the validator
+    /// schedule.
+    pub fn shard_layout(mut self, shard_layout: ShardLayout) -> Self {
+        self.shard_layout = Some(shard_layout);
+        self
+    }
+
+    /// Sets the genesis, which also gives the epoch length and the transaction
+    /// validity period. Only supported by the `Nightshade` runtime, which
+    /// otherwise builds a test genesis with the validators of the schedule at
+    /// the current time of the clock. The nodes of the same chain need the
+    /// same genesis.
+    pub fn genesis(mut self, genesis: Genesis) -> Self {
+        self.genesis = Some(genesis);
+        self
+    }
+
+    pub fn tracked_shards(mut self, tracked_shards: TrackedShardsConfig) -> Self {
+        self.tracked_shards = tracked_shards;
+        self
+    }
+
+    pub fn archive(mut self, archive: bool) -> Self {
+        self.archive = archive;
+        self
+    }
+
+    /// Writes the state of the `KeyValue` runtime to memtries, see
+    /// `KeyValueRuntimeOptions::memtrie`. Not supported by the `Nightshade`
+    /// runtime.
+    pub fn memtrie(mut self, memtrie: bool) -> Self {
+        self.memtrie = memtrie;
+        self
+    }
+
+    /// Overrides the validator key of the node. `None` sets up a node without
+    /// a validator key.
+    pub fn signer(mut self, signer: Option<Arc<ValidatorSigner>>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    pub fn epoch_length(mut self, epoch_length: BlockHeightDelta) -> Self {
+        self.epoch_length = epoch_length;
+        self
+    }
+
+    /// Sets the genesis time. If not set, the current time of the clock is used.
+    pub fn genesis_time(mut self, genesis_time: Utc) -> Self {
+        self.genesis_time = Some(genesis_time);
+        self
+    }
+
+    pub fn transaction_validity_period(mut self, transaction_validity_period: NumBlocks) -> Self {
+        self.transaction_validity_period = transaction_validity_period;
+        self
+    }
+
+    pub fn skip_sync_wait(mut self, skip_sync_wait: bool) -> Self {
+        self.skip_sync_wait = skip_sync_wait;
+        self
+    }
+
+    /// Sets the min and max block production time, in milliseconds.
+    pub fn block_prod_time(mut self, min_block_prod_time: u64, max_block_prod_time: u64) -> Self {
+        self.min_block_prod_time = min_block_prod_time;
+        self.max_block_prod_time = max_block_prod_time;
+        self
+    }
+
+    pub fn enable_doomslug(mut self, enable_doomslug: bool) -> Self {
+        self.enable_doomslug = enable_doomslug;
+        self
+    }
+
+    pub fn state_sync_enabled(mut self, state_sync_enabled: bool) -> Self {
+        self.state_sync_enabled = state_sync_enabled;
+        self
+    }
+
+    pub fn chunk_distribution_config(
+        mut self,
+        chunk_distribution_config: Option<ChunkDistributionNetworkConfig>,
+    ) -> Self {
+        self.chunk_distribution_config = chunk_distribution_config;
+        self
+    }
+
+    /// Sets the adapter the messages to the network are sent to. If not set,
+    /// they are dropped.
+    pub fn network_adapter(mut self, network_adapter: PeerManagerAdapter) -> Self {
+        self.network_adapter = Some(network_adapter);
+        self
+    }
+
+    /// Restores the chain saved with `KeyValueRuntime::save_snapshot` before
+    /// setting up the node, which then goes on from the head of the snapshot.
+    /// Only supported by the `KeyValue` runtime, which must be set up with the
+    /// validator schedule, the options and the genesis time of the node the
+    /// snapshot was saved from.
+    pub fn restore_kv_snapshot(mut self, path: PathBuf) -> Self {
+        self.kv_snapshot = Some(path);
+        self
+    }
+
+    /// Spawns the actors of the node as actix actors.
+    pub fn build(self) -> ActorHandlesForTesting {
+        let (options, runtime_tempdir, _) = self.into_setup_options();
+        let (
+            client_actor,
+            view_client_actor,
+            rpc_handler_actor,
+            shards_manager_adapter,
+            partial_witness_sender,
+            head_events,
+        ) = setup(options);
+        ActorHandlesForTesting {
+            client_actor,
+            view_client_actor,
+            rpc_handler_actor,
+            shards_manager_adapter,
+            partial_witness_sender,
+            head_events,
+            runtime_tempdir: runtime_tempdir.map(Arc::new),
+        }
+    }
+
+    /// Registers the actors of the node in the test loop, so they run on its
+    /// virtual clock and their messages are delivered one at a time, in a
+    /// deterministic order. The clock of the builder is replaced by the one of
+    /// the test loop.
+    pub fn build_in_test_loop(mut self, test_loop: &mut TestLoopV2) -> TestLoopActorHandles {
+        self.clock = test_loop.clock();
+        let (options, runtime_tempdir, kv_runtime) = self.into_setup_options();
+        let mut actor_handles = setup_in_test_loop(test_loop, options);
+        actor_handles.runtime_tempdir = runtime_tempdir.map(Arc::new);
+        actor_handles.kv_runtime = kv_runtime;
+        actor_handles
+    }
+
+    /// Creates the store, the epoch manager and the runtime of the node.
+    /// Returns the home dir of the runtime too, if it needs one, and the
+    /// runtime if it's the `KeyValueRuntime`.
+    fn into_setup_options(
+        mut self,
+    ) -> (SetupOptions, Option<tempfile::TempDir>, Option<Arc<KeyValueRuntime>>) {
+        let store = create_test_store();
+        let genesis_time = self.genesis_time.unwrap_or_else(|| self.clock.now_utc());
+        let mut chain_genesis = None;
+        let mut kv_runtime = None;
+        let (epoch_manager, runtime, num_validator_seats, runtime_tempdir): (
+            Arc<dyn EpochManagerAdapter>,
+            Arc<dyn RuntimeAdapter>,
+            NumSeats,
+            Option<tempfile::TempDir>,
+        ) = match self.runtime {
+            TestNodeRuntime::KeyValue => {
+                assert!(
+                    self.shard_layout.is_none(),
+                    "the shards of the KeyValue runtime are given by the validator schedule"
+                );
+                assert!(self.genesis.is_none(), "the KeyValue runtime doesn't use a genesis");
+                let num_validator_seats = self.validators.all_block_producers().count() as NumSeats;
+                let epoch_manager = MockEpochManager::new_with_validators(
+                    store.clone(),
+                    self.validators,
+                    self.epoch_length,
+                );
+                let kv_chain_genesis = test_chain_genesis(
+                    genesis_time,
+                    self.transaction_validity_period,
+                    self.epoch_length,
+                );
+                let runtime = KeyValueRuntime::new_with_options(
+                    store.clone(),
+                    &epoch_manager,
+                    KeyValueRuntimeOptions {
+                        no_gc: self.archive,
+                        memtrie: self.memtrie,
+                        chain_genesis: Some(kv_chain_genesis.clone()),
+                        ..Default::default()
+                    },
+                );
+                if let Some(path) = &self.kv_snapshot {
+                    runtime.restore_snapshot(path).unwrap();
+                }
+                chain_genesis = Some(kv_chain_genesis);
+                kv_runtime = Some(runtime.clone());
+                (epoch_manager, runtime, num_validator_seats, None)
+            }
+            TestNodeRuntime::Nightshade => {
+                assert!(!self.memtrie, "the memtries are only set up for the KeyValue runtime");
+                assert!(
+                    self.kv_snapshot.is_none(),
+                    "snapshots are only supported by the KeyValue runtime"
+                );
+                let (genesis, num_validator_seats) = match self.genesis {
+                    Some(genesis) => {
+                        assert!(
+                            self.shard_layout.is_none(),
+                            "the shard layout is given by the genesis"
+                        );
+                        self.epoch_length = genesis.config.epoch_length;
+                        self.transaction_validity_period =
+                            genesis.config.transaction_validity_period;
+                        chain_genesis = Some(ChainGenesis::new(&genesis.config));
+                        let num_validator_seats = genesis.config.num_block_producer_seats;
+                        (genesis, num_validator_seats)
+                    }
+                    None => {
+                        let mut validators =
+                            self.validators.all_validators().cloned().collect::<Vec<_>>();
+                        let num_validator_seats = validators.len() as NumSeats;
+                        // Certain tests depend on these accounts existing so we make them available here.
+                        // This is mostly due to historical reasons - those tests used to use heavily mocked
+                        // testing environment that didn't check account existence.
+                        for account in ["test2", "test"].into_iter().map(|acc| acc.parse().unwrap())
+                        {
+                            if !validators.contains(&account) {
+                                validators.push(account);
+                            }
+                        }
+                        let mut genesis = Genesis::from_accounts(
+                            self.clock.clone(),
+                            validators,
+                            num_validator_seats,
+                            self.shard_layout.unwrap_or_else(ShardLayout::single_shard),
+                        );
+                        genesis.config.epoch_length = self.epoch_length;
+                        (genesis, num_validator_seats)
+                    }
+                };
+                initialize_genesis_state(store.clone(), &genesis, None);
+
+                let epoch_manager =
+                    EpochManager::new_arc_handle(store.clone(), &genesis.config, None);
+                let tempdir = tempfile::TempDir::new().unwrap();
+                let runtime = NightshadeRuntime::test(
+                    tempdir.path(),
+                    store.clone(),
+                    &genesis.config,
+                    epoch_manager.clone(),
+                );
+                (epoch_manager, runtime, num_validator_seats, Some(tempdir))
+            }
+        };
+
+        let options = SetupOptions {
+            epoch_manager,
+            genesis_time,
+            transaction_validity_period: self.transaction_validity_period,
+            epoch_length: self.epoch_length,
+            account_id: self.account_id,
+            signer: self.signer,
+            tracked_shards: self.tracked_shards,
+            skip_sync_wait: self.skip_sync_wait,
+            min_block_prod_time: self.min_block_prod_time,
+            max_block_prod_time: self.max_block_prod_time,
+            num_validator_seats,
+            archive: self.archive,
+            state_sync_enabled: self.state_sync_enabled,
+            chunk_distribution_config: self.chunk_distribution_config,
+            clock: self.clock,
+            runtime,
+            network_adapter: self.network_adapter.unwrap_or_else(|| noop().into_multi_sender()),
+            store,
+            enable_doomslug: self.enable_doomslug,
+            chain_genesis,
+        };
+        (options, runtime_tempdir, kv_runtime)
+    }
+}
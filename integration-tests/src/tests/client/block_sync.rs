@@ -7,6 +7,7 @@ use near_chain::Provenance;
 use near_chain::test_utils::wait_for_all_blocks_in_processing;
 use near_chain_configs::Genesis;
 use near_client::sync::block::BlockSync;
+use near_client_primitives::types::SyncStatus;
 use near_crypto::{KeyType, PublicKey};
 use near_network::test_utils::MockPeerManagerAdapter;
 use near_network::types::{
@@ -80,6 +81,7 @@ fn test_block_sync() {
         block_fetch_horizon,
         false,
         true,
+        None,
     );
     let mut env = test_env_with_epoch_length(100);
     let mut blocks = vec![];
@@ -159,6 +161,7 @@ fn test_block_sync_archival() {
         block_fetch_horizon,
         true,
         true,
+        None,
     );
     let mut env = test_env_with_epoch_length(5);
     let mut blocks = vec![];
@@ -188,3 +191,45 @@ fn test_block_sync_archival() {
         blocks.iter().take(max_block_requests).map(|b| *b.hash()).collect::<HashSet<_>>()
     );
 }
+
+/// With fast bootstrap, a node in the epoch before the header head state syncs
+/// instead of block syncing through the epoch boundary.
+#[test]
+fn test_block_sync_fast_bootstrap() {
+    let block_fetch_horizon = 10;
+    let max_block_requests = 10;
+    let mut env = test_env_with_epoch_length(20);
+    let mut blocks = vec![];
+    for i in 1..36 {
+        let block = env.clients[0].produce_block(i).unwrap().unwrap();
+        blocks.push(block.clone());
+        env.process_block(0, block, Provenance::PRODUCED);
+    }
+    for block in &blocks[..5] {
+        env.process_block(1, block.clone(), Provenance::NONE);
+    }
+    let block_headers = blocks.iter().map(|b| b.header().clone()).collect::<Vec<_>>();
+    let peer_infos = create_highest_height_peer_infos(2);
+    env.clients[1].chain.sync_block_headers(block_headers).unwrap();
+
+    let run = |fast_bootstrap_depth| {
+        let network_adapter = Arc::new(MockPeerManagerAdapter::default());
+        let mut block_sync = BlockSync::new(
+            Clock::real(),
+            network_adapter.as_multi_sender(),
+            block_fetch_horizon,
+            false,
+            true,
+            fast_bootstrap_depth,
+        );
+        let mut sync_status = SyncStatus::AwaitingPeers;
+        block_sync
+            .run(&mut sync_status, &env.clients[1].chain, 35, &peer_infos, max_block_requests)
+            .unwrap()
+    };
+    // The header head is in the next epoch, so the node block syncs by default.
+    assert!(!run(None));
+    assert!(run(Some(10)));
+    // Within the depth, it block syncs.
+    assert!(!run(Some(50)));
+}
@@ -0,0 +1,52 @@
+use crate::env::nightshade_setup::TestEnvNightshadeSetupExt;
+use crate::env::test_env::TestEnv;
+use near_chain::{ChainStoreAccess, Provenance};
+use near_chain_configs::Genesis;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::utils::get_block_shard_id;
+use near_store::DBCol;
+
+/// Check that the applied chunks are journaled, together with their chunk extras.
+#[test]
+fn test_chunk_apply_journal_written_with_chunk_extra() {
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let shard_uid = ShardUId::single_shard();
+    for height in 1..=3 {
+        env.produce_block(0, height);
+        let block_hash = env.clients[0].chain.get_block_hash_by_height(height).unwrap();
+        let chain_store = env.clients[0].chain.chain_store();
+        assert!(chain_store.is_chunk_apply_journaled(&block_hash, shard_uid.shard_id()).unwrap());
+        assert!(env.clients[0].chain.get_chunk_extra(&block_hash, &shard_uid).is_ok());
+    }
+
+    let block = env.clients[0].produce_block(4).unwrap().unwrap();
+    let chain_store = env.clients[0].chain.chain_store();
+    assert!(!chain_store.is_chunk_apply_journaled(block.hash(), shard_uid.shard_id()).unwrap());
+}
+
+/// Check that a journaled chunk isn't applied again, e.g. after a restart.
+#[test]
+fn test_chunk_apply_journal_skips_applied_chunk() {
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let shard_uid = ShardUId::single_shard();
+    env.produce_block(0, 1);
+
+    let block = env.clients[0].produce_block(2).unwrap().unwrap();
+    let block_hash = *block.hash();
+    let mut store_update = env.clients[0].chain.chain_store().store().store_update();
+    store_update
+        .set_ser(
+            DBCol::ChunkApplyJournal,
+            &get_block_shard_id(&block_hash, shard_uid.shard_id()),
+            &(),
+        )
+        .unwrap();
+    store_update.commit().unwrap();
+
+    env.clients[0].process_block_test_no_produce_chunk(block.into(), Provenance::PRODUCED).unwrap();
+    assert_eq!(env.clients[0].chain.head().unwrap().last_block_hash, block_hash);
+    // Applying the chunk would have saved its chunk extra.
+    assert!(env.clients[0].chain.get_chunk_extra(&block_hash, &shard_uid).is_err());
+}
@@ -1,7 +1,10 @@
 use borsh::BorshDeserialize;
-use near_chain::Provenance;
+use near_async::messaging::{Handler, IntoMultiSender, noop};
+use near_async::time::Clock;
+use near_chain::{ChainGenesis, Provenance};
 use near_chain_configs::{Genesis, MutableConfigValue};
-use near_client::ProcessTxResponse;
+use near_client::adversarial::Controls;
+use near_client::{GetBlock, GetChunk, ProcessTxResponse, ViewClientActorInner};
 use near_crypto::{InMemorySigner, KeyType, Signer};
 use near_epoch_manager::EpochManager;
 use near_o11y::testonly::init_test_logger;
@@ -10,13 +13,15 @@ use near_primitives::sharding::ShardChunk;
 use near_primitives::transaction::{
     Action, DeployContractAction, FunctionCallAction, SignedTransaction,
 };
+use near_primitives::types::{BlockId, BlockReference};
 use near_primitives_core::hash::CryptoHash;
 use near_primitives_core::types::AccountId;
 use near_store::archive::cold_storage::{
-    copy_all_data_to_cold, test_cold_genesis_update, test_get_store_initial_writes,
-    test_get_store_reads, update_cold_db, update_cold_head,
+    backfill_cold_block_columns, copy_all_data_to_cold, test_cold_genesis_update,
+    test_get_store_initial_writes, test_get_store_reads, update_cold_db, update_cold_head,
 };
 use near_store::db::metadata::{DB_VERSION, DbKind};
+use near_store::db::{DBTransaction, Database};
 use near_store::test_utils::create_test_node_storage_with_cold;
 use near_store::{COLD_HEAD_KEY, DBCol, HEAD_KEY, Store};
 use nearcore::{NearConfig, cold_storage::spawn_cold_store_loop};
@@ -424,6 +429,43 @@ fn test_initial_copy_to_cold_medium_batch() {
     test_initial_copy_to_cold(5000);
 }
 
+/// Checks that `backfill_cold_block_columns` fills in the block columns of the
+/// cold db which only has the blocks, the way the cold db populated by the older
+/// versions does.
+#[test]
+fn test_backfill_cold_block_columns() {
+    init_test_logger();
+
+    let epoch_length = 5;
+    let max_height = epoch_length * 4;
+
+    let mut genesis = Genesis::test(vec![test0(), test1()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    for height in 1..max_height {
+        env.produce_block(0, height);
+    }
+
+    let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Archive);
+    let cold_db = storage.cold_db().unwrap();
+    let cold_store = storage.get_cold_store().unwrap();
+    let client_store = env.clients[0].runtime_adapter.store();
+
+    let mut transaction = DBTransaction::new();
+    for (key, value) in client_store.iter(DBCol::Block).map(Result::unwrap) {
+        transaction.set(DBCol::Block, key.to_vec(), value.to_vec());
+    }
+    cold_db.write(transaction).unwrap();
+
+    let num_blocks = backfill_cold_block_columns(cold_db.clone(), &client_store, 0).unwrap();
+    assert_eq!(num_blocks, max_height);
+
+    for col in [DBCol::BlockHeader, DBCol::BlockHeight, DBCol::BlockMerkleTree, DBCol::BlockOrdinal]
+    {
+        assert!(check_iter(&client_store, &cold_store, col, &vec![]) > 0, "col: {col:?}");
+    }
+}
+
 /// This test checks that garbage collection does not remove data needed for cold storage migration prematurely.
 /// Test flow:
 /// - Produce a lot of blocks.
@@ -527,3 +569,70 @@ fn test_cold_loop_on_gc_boundary() {
         end_cold_head
     );
 }
+
+/// Checks that the view client of an archival node which doesn't use the split
+/// store reads the blocks and chunks garbage collected from the hot store from
+/// the cold store when it's given the read-through store, see
+/// `SplitStorageConfig::enable_cold_read_through_view_client`.
+#[test]
+fn test_view_client_cold_read_through() {
+    init_test_logger();
+
+    let epoch_length = 5;
+    let max_height = epoch_length * 4;
+
+    let mut genesis = Genesis::test(vec![test0(), test1()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Hot);
+    let hot_store = storage.get_hot_store();
+    let mut env = TestEnv::builder(&genesis.config)
+        .archive(true)
+        .save_trie_changes(true)
+        .stores(vec![hot_store.clone()])
+        .nightshade_runtimes(&genesis)
+        .build();
+    for height in 1..max_height {
+        env.produce_block(0, height);
+    }
+
+    let keep_going = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+    let cold_db = storage.cold_db().unwrap();
+    copy_all_data_to_cold(cold_db.clone(), &hot_store, 1000000, &keep_going).unwrap();
+
+    // Remove a block and its chunk from the hot store, as the garbage
+    // collection does.
+    let block = env.clients[0].chain.get_block_by_height(epoch_length).unwrap();
+    let chunk_hash = block.chunks().get(0).unwrap().chunk_hash();
+    assert!(hot_store.exists(DBCol::Chunks, chunk_hash.as_ref()).unwrap());
+    let mut store_update = hot_store.store_update();
+    store_update.delete(DBCol::Block, block.hash().as_ref());
+    store_update.delete(DBCol::Chunks, chunk_hash.as_ref());
+    store_update.commit().unwrap();
+
+    let client = &env.clients[0];
+    let new_view_client = |read_through_store: Option<Store>| {
+        ViewClientActorInner::new(
+            Clock::real(),
+            MutableConfigValue::new(None, "validator_signer"),
+            ChainGenesis::new(&genesis.config),
+            client.epoch_manager.clone(),
+            client.shard_tracker.clone(),
+            client.runtime_adapter.clone(),
+            read_through_store,
+            noop().into_multi_sender(),
+            client.config.clone(),
+            Controls::default(),
+        )
+        .unwrap()
+    };
+    let get_block = GetBlock(BlockReference::BlockId(BlockId::Hash(*block.hash())));
+    let get_chunk = GetChunk::ChunkHash(chunk_hash.clone());
+
+    let mut view_client = new_view_client(None);
+    assert!(view_client.handle(get_block.clone()).is_err());
+    assert!(view_client.handle(get_chunk.clone()).is_err());
+
+    let mut view_client = new_view_client(storage.get_read_through_store().unwrap());
+    assert_eq!(view_client.handle(get_block).unwrap().header.hash, *block.hash());
+    assert_eq!(view_client.handle(get_chunk).unwrap().header.chunk_hash, chunk_hash.0);
+}
@@ -1,6 +1,10 @@
 use near_chain::Provenance;
 use near_chain_configs::Genesis;
 use near_crypto::KeyType;
+#[cfg(feature = "test_features")]
+use near_network::test_utils::random_peer_id;
+#[cfg(feature = "test_features")]
+use near_network::types::NetworkRequests;
 use near_o11y::testonly::init_test_logger;
 use near_primitives::block::{Approval, ApprovalType};
 use near_primitives::hash::CryptoHash;
@@ -37,7 +41,7 @@ fn test_processing_skips_on_forks() {
     let validator_signer =
         InMemoryValidatorSigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
     let approval =
-        Approval::new(CryptoHash::default(), 1, second_fork_heigh + 1, &validator_signer);
+        Approval::new(CryptoHash::default(), 1, second_fork_heigh + 1, &validator_signer).unwrap();
     let client_signer = env.clients[1].validator_signer.get();
     env.clients[1].collect_block_approval(&approval, ApprovalType::SelfApproval, &client_signer);
     assert!(
@@ -48,3 +52,64 @@ fn test_processing_skips_on_forks() {
             .is_empty()
     );
 }
+
+// Checks that a validator which double signs its approvals sends two conflicting approvals for the
+// same target height, and that the block producer only counts the last one.
+#[test]
+#[cfg(feature = "test_features")]
+fn test_double_signed_approvals() {
+    init_test_logger();
+
+    let genesis =
+        Genesis::test(["test0", "test1"].into_iter().map(|acc| acc.parse().unwrap()).collect(), 2);
+    let mut env =
+        TestEnv::builder_from_genesis(&genesis).clients_count(2).validator_seats(2).build();
+    for height in 1..=3 {
+        let head = env.clients[0].chain.head().unwrap();
+        let block_producer = env.get_block_producer_at_offset(&head, 1);
+        let block = env.client(&block_producer).produce_block(height).unwrap().unwrap();
+        for i in 0..env.clients.len() {
+            env.process_block(i, block.clone(), Provenance::NONE);
+        }
+    }
+
+    let head = env.clients[0].chain.head().unwrap();
+    let block_producer = env.get_block_producer_at_offset(&head, 1);
+    let adversary = 1 - env.get_client_index(&block_producer);
+    env.clients[adversary].adv.set_double_sign_approvals(true);
+    while env.network_adapters[adversary].pop().is_some() {}
+
+    let signer = env.clients[adversary].validator_signer.get();
+    let approval =
+        Approval::new(head.last_block_hash, head.height, head.height + 1, signer.as_ref().unwrap())
+            .unwrap();
+    env.clients[adversary]
+        .send_block_approval(&head.last_block_hash, approval.clone(), &signer)
+        .unwrap();
+    let mut approvals = vec![];
+    while let Some(request) = env.network_adapters[adversary].pop() {
+        if let NetworkRequests::Approval { approval_message } = request.as_network_requests() {
+            assert_eq!(approval_message.target, block_producer);
+            approvals.push(approval_message.approval);
+        }
+    }
+    assert_eq!(approvals.len(), 2);
+    assert_eq!(approvals[0], approval);
+    let conflicting_approval = &approvals[1];
+    assert_eq!(conflicting_approval.target_height, approval.target_height);
+    assert_eq!(conflicting_approval.account_id, approval.account_id);
+    assert_ne!(conflicting_approval.inner, approval.inner);
+
+    let client = env.client(&block_producer);
+    let client_signer = client.validator_signer.get();
+    for approval in &approvals {
+        client.collect_block_approval(
+            approval,
+            ApprovalType::PeerApproval(random_peer_id()),
+            &client_signer,
+        );
+    }
+    let status = client.doomslug.approval_status_at_height(&(head.height + 1));
+    assert_eq!(status.approvals.len(), 1);
+    assert_eq!(status.approvals[&approval.account_id].0, conflicting_approval.inner);
+}
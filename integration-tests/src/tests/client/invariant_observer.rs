@@ -0,0 +1,56 @@
+use near_chain::ChainStoreAccess;
+use near_chain::types::Tip;
+use near_chain_configs::{DEFAULT_GC_NUM_EPOCHS_TO_KEEP, Genesis};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::types::BlockHeight;
+use near_store::{DBCol, FINAL_HEAD_KEY};
+
+use crate::env::nightshade_setup::TestEnvNightshadeSetupExt;
+use crate::env::test_env::TestEnv;
+
+fn setup_env(epoch_length: BlockHeight, num_blocks: BlockHeight) -> TestEnv {
+    init_test_logger();
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    for height in 1..=num_blocks {
+        env.produce_block(0, height);
+    }
+    env
+}
+
+/// The invariants are checked on every client and hold while the chain is
+/// garbage collected.
+#[test]
+fn test_invariants_hold_with_gc() {
+    let epoch_length = 5;
+    let env = setup_env(epoch_length, epoch_length * (DEFAULT_GC_NUM_EPOCHS_TO_KEEP + 2));
+    assert_eq!(env.invariant_observers.len(), env.clients.len());
+    assert!(env.clients[0].chain.tail().unwrap() > 0);
+}
+
+#[test]
+#[should_panic(expected = "was garbage collected above the tail")]
+fn test_final_block_removed() {
+    let mut env = setup_env(100, 10);
+    let chain = &env.clients[0].chain;
+    let final_head = chain.final_head().unwrap();
+    let block_hash = *chain.get_block_by_height(final_head.height - 1).unwrap().hash();
+    let mut store_update = chain.chain_store().store().store_update();
+    store_update.delete(DBCol::Block, block_hash.as_ref());
+    store_update.commit().unwrap();
+    env.check_invariants(0);
+}
+
+#[test]
+#[should_panic(expected = "final head went back")]
+fn test_final_head_went_back() {
+    let mut env = setup_env(100, 10);
+    let chain = &env.clients[0].chain;
+    let final_head = chain.final_head().unwrap();
+    let header = chain.get_block_header_by_height(final_head.height - 1).unwrap();
+    let mut store_update = chain.chain_store().store().store_update();
+    store_update.set_ser(DBCol::BlockMisc, FINAL_HEAD_KEY, &Tip::from_header(&header)).unwrap();
+    store_update.commit().unwrap();
+    env.check_invariants(0);
+}
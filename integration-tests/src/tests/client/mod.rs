@@ -1,12 +1,14 @@
 mod benchmarks;
 mod block_corruption;
 mod block_sync;
+mod chunk_apply_journal;
 mod chunks_management;
 mod cold_storage;
 mod doomslug;
 mod flat_storage;
 mod invalid_chunk;
 mod invalid_txs;
+mod invariant_observer;
 mod maintenance_windows;
 mod process_blocks;
 mod process_blocks2;
@@ -17,4 +19,5 @@ mod sandbox;
 mod state_dump;
 mod state_snapshot;
 mod sync_state_nodes;
+mod test_cluster;
 mod undo_block;
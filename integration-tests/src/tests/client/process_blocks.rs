@@ -1,28 +1,38 @@
+use crate::env::chain_driver::{BlockParams, ChainDriver};
+use crate::env::network_recorder::RecordingPeerManagerAdapter;
 use crate::env::nightshade_setup::TestEnvNightshadeSetupExt;
 use crate::env::setup::setup_mock;
 use crate::env::test_env::TestEnv;
 use crate::env::test_env_builder::TestEnvBuilder;
+use crate::env::test_node_builder::{TestNodeBuilder, TestNodeRuntime};
 use crate::utils::process_blocks::{
     deploy_test_contract, prepare_env_with_congestion, set_block_protocol_version,
 };
 use actix::System;
 use assert_matches::assert_matches;
-use futures::{FutureExt, future};
 use itertools::Itertools;
 use near_actix_test_utils::run_actix;
+use near_async::messaging::{Handler, IntoMultiSender};
+use near_async::test_loop::TestLoopV2;
 use near_async::time::{Clock, Duration};
+use near_chain::test_utils::ValidatorSchedule;
 use near_chain::types::{LatestKnown, RuntimeAdapter};
 use near_chain::validate::validate_chunk_with_chunk_extra;
 use near_chain::{Block, BlockProcessingArtifact, ChainStoreAccess, Error, Provenance};
 use near_chain::{ChainStore, MerkleProofAccess};
 use near_chain_configs::test_utils::{TESTING_INIT_BALANCE, TESTING_INIT_STAKE};
-use near_chain_configs::{DEFAULT_GC_NUM_EPOCHS_TO_KEEP, Genesis, NEAR_BASE};
+use near_chain_configs::{
+    DEFAULT_GC_NUM_EPOCHS_TO_KEEP, Genesis, MutableConfigValue, NEAR_BASE, TrackedShardsConfig,
+    TransactionAdmissionConfig, TxRebroadcastConfig,
+};
 use near_client::test_utils::create_chunk_on_height;
 use near_client::{
-    BlockApproval, BlockResponse, GetBlockWithMerkleTree, ProcessTxResponse, ProduceChunkResult,
-    SetNetworkInfo,
+    Client, GetBlock, GetProtocolConfig, GetTxRoutingStatus, ProcessTxResponse, ProduceChunkResult,
+    RebroadcastPendingTxs, RpcHandler, RpcHandlerConfig, SetNetworkInfo, TxPoolSaver,
 };
 use near_crypto::{InMemorySigner, KeyType, Signature};
+use near_epoch_manager::EpochManagerAdapter;
+use near_epoch_manager::shard_tracker::ShardTracker;
 use near_network::test_utils::{MockPeerManagerAdapter, wait_or_panic};
 use near_network::types::{
     BlockInfo, ConnectedPeerInfo, HighestHeightPeerInfo, NetworkInfo, PeerChainInfo,
@@ -34,12 +44,11 @@ use near_o11y::WithSpanContextExt;
 use near_o11y::testonly::{init_integration_logger, init_test_logger};
 use near_parameters::{ActionCosts, ExtCosts};
 use near_parameters::{RuntimeConfig, RuntimeConfigStore};
-use near_primitives::block::Approval;
 use near_primitives::errors::TxExecutionError;
 use near_primitives::errors::{ActionError, ActionErrorKind, InvalidTxError};
 use near_primitives::genesis::GenesisId;
 use near_primitives::hash::{CryptoHash, hash};
-use near_primitives::merkle::{PartialMerkleTree, verify_hash};
+use near_primitives::merkle::verify_hash;
 use near_primitives::receipt::DelayedReceiptIndices;
 use near_primitives::shard_layout::{ShardUId, get_block_shard_uid};
 use near_primitives::sharding::{
@@ -57,14 +66,19 @@ use near_primitives::transaction::{
     Transaction, TransactionV0,
 };
 use near_primitives::trie_key::TrieKey;
-use near_primitives::types::{AccountId, BlockHeight, EpochId, NumBlocks};
+use near_primitives::types::{
+    AccountId, BlockHeight, BlockReference, EpochId, Finality, NumBlocks,
+};
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{FinalExecutionStatus, QueryRequest, QueryResponseKind};
+use near_primitives::views::{
+    FinalExecutionStatus, QueryRequest, QueryResponseKind, TxRoutingStateView,
+};
 use near_primitives_core::num_rational::Ratio;
 use near_store::NodeStorage;
 use near_store::adapter::StoreUpdateAdapter;
 use near_store::archive::cold_storage::{update_cold_db, update_cold_head};
 use near_store::db::metadata::{DB_VERSION, DbKind};
+use near_store::db::{CORRUPTED_CHUNKS_KEY, TX_POOL_KEY};
 use near_store::test_utils::create_test_node_storage_with_cold;
 use near_store::{DBCol, TrieChanges, get};
 use parking_lot::RwLock;
@@ -101,6 +115,253 @@ fn produce_two_blocks() {
     });
 }
 
+/// Same as `produce_two_blocks`, with the actors running in the test loop.
+#[test]
+fn produce_two_blocks_in_test_loop() {
+    init_test_logger();
+    let mut test_loop = TestLoopV2::new();
+    let network_recorder = RecordingPeerManagerAdapter::new_mock();
+    let actor_handles =
+        TestNodeBuilder::new(test_loop.clock(), "test".parse().unwrap(), TestNodeRuntime::KeyValue)
+            .network_adapter(network_recorder.adapter())
+            .build_in_test_loop(&mut test_loop);
+
+    test_loop.run_until(|_| network_recorder.count(Some("Block"), None) >= 2, Duration::seconds(5));
+    let client_actor = test_loop.data.get(&actor_handles.client_sender.actor_handle());
+    assert!(client_actor.client.chain.head().unwrap().height >= 2);
+    test_loop.shutdown_and_drain_remaining_events(Duration::seconds(1));
+}
+
+/// Checks that a node with the `KeyValue` runtime writing its state to
+/// memtries keeps producing blocks after the oldest roots are garbage
+/// collected, and that the state at the head is read back from the memtrie.
+#[test]
+fn produce_blocks_with_kv_memtrie_in_test_loop() {
+    init_test_logger();
+    let mut test_loop = TestLoopV2::new();
+    let epoch_length = 5;
+    let actor_handles =
+        TestNodeBuilder::new(test_loop.clock(), "test".parse().unwrap(), TestNodeRuntime::KeyValue)
+            .epoch_length(epoch_length)
+            .memtrie(true)
+            .build_in_test_loop(&mut test_loop);
+    let client_handle = actor_handles.client_sender.actor_handle();
+
+    let height = (DEFAULT_GC_NUM_EPOCHS_TO_KEEP + 2) * epoch_length;
+    test_loop.run_until(
+        |data| data.get(&client_handle).client.chain.head().unwrap().height >= height,
+        Duration::seconds(60),
+    );
+    let client = &test_loop.data.get(&client_handle).client;
+    let head = client.chain.head().unwrap();
+    let shard_uid =
+        client.epoch_manager.get_shard_layout(&head.epoch_id).unwrap().shard_uids().next().unwrap();
+    let chunk_extra = client.chain.get_chunk_extra(&head.last_block_hash, &shard_uid).unwrap();
+    let response = client
+        .runtime_adapter
+        .query(
+            shard_uid,
+            chunk_extra.state_root(),
+            head.height,
+            0,
+            &head.prev_block_hash,
+            &head.last_block_hash,
+            &head.epoch_id,
+            &QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+        )
+        .unwrap();
+    let QueryResponseKind::ViewAccount(account) = response.kind else {
+        panic!("the response should be an account view");
+    };
+    assert_eq!(account.amount, 1000);
+    test_loop.shutdown_and_drain_remaining_events(Duration::seconds(1));
+}
+
+/// A node with the `KeyValue` runtime restored from a snapshot of the chain of
+/// another node should open the chain at the head of the snapshot and keep
+/// producing blocks on top of it, into the next epochs.
+#[test]
+fn restore_kv_runtime_snapshot_in_test_loop() {
+    init_test_logger();
+    let mut test_loop = TestLoopV2::new();
+    let validator: AccountId = "test".parse().unwrap();
+    let epoch_length = 5;
+    let genesis_time = test_loop.clock().now_utc();
+    let actor_handles =
+        TestNodeBuilder::new(test_loop.clock(), validator.clone(), TestNodeRuntime::KeyValue)
+            .epoch_length(epoch_length)
+            .genesis_time(genesis_time)
+            .build_in_test_loop(&mut test_loop);
+    let client_handle = actor_handles.client_sender.actor_handle();
+    test_loop.run_until(
+        |data| data.get(&client_handle).client.chain.head().unwrap().height >= 3 * epoch_length,
+        Duration::seconds(30),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot");
+    actor_handles.kv_runtime.as_ref().unwrap().save_snapshot(&path).unwrap();
+    let head = test_loop.data.get(&client_handle).client.chain.head().unwrap();
+
+    // The restored node runs in the same test loop, so that its clock is past
+    // the timestamps of the restored blocks, and produces them with the key of
+    // the validator of the snapshot.
+    let restored_handles = TestNodeBuilder::new(
+        test_loop.clock(),
+        "restored".parse().unwrap(),
+        TestNodeRuntime::KeyValue,
+    )
+    .validators(vec![validator.clone()])
+    .signer(Some(Arc::new(create_test_signer(validator.as_str()))))
+    .epoch_length(epoch_length)
+    .genesis_time(genesis_time)
+    .restore_kv_snapshot(path)
+    .build_in_test_loop(&mut test_loop);
+    let restored_handle = restored_handles.client_sender.actor_handle();
+    let restored_head = test_loop.data.get(&restored_handle).client.chain.head().unwrap();
+    assert_eq!(restored_head.last_block_hash, head.last_block_hash);
+
+    test_loop.run_until(
+        |data| {
+            data.get(&restored_handle).client.chain.head().unwrap().height
+                >= head.height + 2 * epoch_length
+        },
+        Duration::seconds(30),
+    );
+    let chain = &test_loop.data.get(&restored_handle).client.chain;
+    assert_eq!(chain.get_block_hash_by_height(head.height).unwrap(), head.last_block_hash);
+    assert_ne!(chain.head().unwrap().epoch_id, head.epoch_id);
+    test_loop.shutdown_and_drain_remaining_events(Duration::seconds(1));
+}
+
+/// The protocol config served by the view client of a `KeyValue` node should
+/// describe the chain genesis the node was started with.
+#[test]
+fn protocol_config_with_kv_runtime_in_test_loop() {
+    init_test_logger();
+    let mut test_loop = TestLoopV2::new();
+    let transaction_validity_period = 20;
+    let actor_handles =
+        TestNodeBuilder::new(test_loop.clock(), "test".parse().unwrap(), TestNodeRuntime::KeyValue)
+            .transaction_validity_period(transaction_validity_period)
+            .build_in_test_loop(&mut test_loop);
+    let client_handle = actor_handles.client_sender.actor_handle();
+    test_loop.run_until(
+        |data| data.get(&client_handle).client.chain.head().unwrap().height >= 2,
+        Duration::seconds(10),
+    );
+
+    let view_client_handle = actor_handles.view_client_sender.actor_handle();
+    let config = test_loop
+        .data
+        .get_mut(&view_client_handle)
+        .handle(GetProtocolConfig(BlockReference::Finality(Finality::None)))
+        .unwrap();
+    assert_eq!(config.chain_id, "integration_test");
+    assert_eq!(config.gas_limit, 1_000_000);
+    assert_eq!(config.min_gas_price, 100);
+    assert_eq!(config.max_gas_price, 1_000_000_000);
+    assert_eq!(config.transaction_validity_period, transaction_validity_period);
+    test_loop.shutdown_and_drain_remaining_events(Duration::seconds(1));
+}
+
+/// A node with the `MockEpochManager` following the staking proposals should
+/// take the stake of a stake transaction of its validator two epochs later, and
+/// report it only in the epoch info of the epochs since.
+#[test]
+fn staking_proposals_with_kv_runtime_in_test_loop() {
+    init_test_logger();
+    let mut test_loop = TestLoopV2::new();
+    let account_id: AccountId = "test".parse().unwrap();
+    let actor_handles =
+        TestNodeBuilder::new(test_loop.clock(), account_id.clone(), TestNodeRuntime::KeyValue)
+            .validator_schedule(
+                ValidatorSchedule::new()
+                    .block_producers_per_epoch(vec![vec![account_id.clone()]])
+                    .staking_proposals(true),
+            )
+            .epoch_length(5)
+            .build_in_test_loop(&mut test_loop);
+    let client_handle = actor_handles.client_sender.actor_handle();
+    test_loop.run_until(
+        |data| data.get(&client_handle).client.chain.head().unwrap().height >= 2,
+        Duration::seconds(10),
+    );
+
+    let head = test_loop.data.get(&client_handle).client.chain.head().unwrap();
+    let signer = InMemorySigner::test_signer(&account_id);
+    let stake = 2_000_000;
+    let tx = SignedTransaction::stake(
+        1,
+        account_id.clone(),
+        &signer,
+        stake,
+        signer.public_key(),
+        head.last_block_hash,
+    );
+    let rpc_handler = test_loop.data.get(&actor_handles.rpc_handler_sender.actor_handle());
+    assert_eq!(rpc_handler.process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+
+    let validator_stake = |client: &Client, epoch_id: &EpochId| {
+        let epoch_info = client.epoch_manager.get_epoch_info(epoch_id).unwrap();
+        assert_eq!(epoch_info.block_producers_settlement().len(), 1);
+        epoch_info.get_validator_by_account(&account_id).unwrap().stake()
+    };
+    test_loop.run_until(
+        |data| {
+            let client = &data.get(&client_handle).client;
+            validator_stake(client, &client.chain.head().unwrap().epoch_id) == stake
+        },
+        Duration::seconds(30),
+    );
+    let client = &test_loop.data.get(&client_handle).client;
+    assert_eq!(validator_stake(client, &head.epoch_id), 1_000_000);
+    test_loop.shutdown_and_drain_remaining_events(Duration::seconds(1));
+}
+
+/// Checks that a block producer producing equivocating blocks sends two blocks
+/// at the same height on top of the same block, and that the second one is
+/// dropped by a node which has processed the first one.
+#[test]
+#[cfg(feature = "test_features")]
+fn produce_equivocating_blocks_in_test_loop() {
+    init_test_logger();
+    let mut test_loop = TestLoopV2::new();
+    let network_recorder = RecordingPeerManagerAdapter::new_mock();
+    let actor_handles =
+        TestNodeBuilder::new(test_loop.clock(), "test".parse().unwrap(), TestNodeRuntime::KeyValue)
+            .network_adapter(network_recorder.adapter())
+            .build_in_test_loop(&mut test_loop);
+    let client_handle = actor_handles.client_sender.actor_handle();
+    test_loop.data.get_mut(&client_handle).adv.set_produce_equivocating_blocks(true);
+
+    test_loop.run_until(|_| network_recorder.count(Some("Block"), None) >= 2, Duration::seconds(5));
+    let blocks = network_recorder
+        .find(Some("Block"), None)
+        .into_iter()
+        .map(|request| match request {
+            NetworkRequests::Block { block } => block,
+            _ => unreachable!(),
+        })
+        .collect_vec();
+    let (block, equivocating_block) = (&blocks[0], &blocks[1]);
+    assert_ne!(block.hash(), equivocating_block.hash());
+    assert_eq!(block.header().height(), equivocating_block.header().height());
+    assert_eq!(block.header().prev_hash(), equivocating_block.header().prev_hash());
+
+    let height = block.header().height();
+    test_loop.run_until(
+        |data| data.get(&client_handle).client.chain.head().unwrap().height >= height,
+        Duration::seconds(5),
+    );
+    let client = &mut test_loop.data.get_mut(&client_handle).client;
+    let signer = client.validator_signer.get();
+    client.receive_block(equivocating_block.clone(), PeerInfo::random().id, false, None, &signer);
+    assert!(client.chain.get_block(equivocating_block.hash()).is_err());
+    assert_eq!(&client.chain.get_block_hash_by_height(height).unwrap(), block.hash());
+    test_loop.shutdown_and_drain_remaining_events(Duration::seconds(1));
+}
+
 /// Runs client that receives a block from network and announces header to the network with approval.
 /// Need 3 block producers, to receive approval.
 #[test]
@@ -128,48 +389,56 @@ fn receive_network_block() {
                 PeerManagerMessageResponse::NetworkResponses(NetworkResponses::NoResponse)
             }),
         );
-        let actor = actor_handles
-            .view_client_actor
-            .send(GetBlockWithMerkleTree::latest().with_span_context());
-        let actor = actor.then(move |res| {
-            let (last_block, block_merkle_tree) = res.unwrap().unwrap();
-            let mut block_merkle_tree = PartialMerkleTree::clone(&block_merkle_tree);
-            block_merkle_tree.insert(last_block.header.hash);
-            let signer = create_test_signer("test1");
-            let next_block_ordinal = last_block.header.block_ordinal.unwrap() + 1;
-            let block = Block::produce(
-                PROTOCOL_VERSION,
-                &last_block.header.clone().into(),
-                last_block.header.height + 1,
-                next_block_ordinal,
-                last_block.chunks.iter().cloned().map(Into::into).collect(),
-                vec![vec![]; last_block.chunks.len()],
-                EpochId::default(),
-                if last_block.header.prev_hash == CryptoHash::default() {
-                    EpochId(last_block.header.hash)
-                } else {
-                    EpochId(last_block.header.next_epoch_id)
-                },
-                None,
-                vec![],
-                Ratio::from_integer(0),
-                0,
-                100,
-                None,
-                &signer,
-                last_block.header.next_bp_hash,
-                block_merkle_tree.root(),
-                Clock::real(),
-                None,
-                None,
-            );
-            actor_handles.client_actor.do_send(
-                BlockResponse { block, peer_id: PeerInfo::random().id, was_requested: false }
-                    .with_span_context(),
-            );
-            future::ready(())
+        actix::spawn(async move {
+            let mut driver =
+                ChainDriver::new(Clock::real(), actor_handles, "test1".parse().unwrap()).await;
+            driver.produce_block().await;
+        });
+        near_network::test_utils::wait_or_panic(5000);
+    });
+}
+
+/// Builds a fork with the `ChainDriver` and checks that the node switches to it
+/// once it's higher than the main chain, and that the blocks on it become final.
+#[test]
+fn chain_driver_fork_and_finality() {
+    init_test_logger();
+    run_actix(async {
+        let actor_handles = TestNodeBuilder::new(
+            Clock::real(),
+            "test2".parse().unwrap(),
+            TestNodeRuntime::Nightshade,
+        )
+        .validators(vec!["test1".parse().unwrap()])
+        .signer(None)
+        .build();
+        let view_client = actor_handles.view_client_actor.clone();
+        actix::spawn(async move {
+            let mut driver =
+                ChainDriver::new(Clock::real(), actor_handles, "test1".parse().unwrap()).await;
+            for _ in 0..3 {
+                driver.produce_block().await;
+            }
+            driver.wait_for_head().await;
+
+            // The fork skips heights 2 and 3, so its first block is higher than the main chain.
+            driver.fork_at(1);
+            driver.skip_height();
+            driver.skip_height();
+            let fork_block = driver.produce_block().await;
+            assert_eq!(fork_block.header().height(), 4);
+            driver.wait_for_head().await;
+
+            driver.finalize_up_to(5).await;
+            driver.wait_for_head().await;
+            let final_block = view_client
+                .send(GetBlock(BlockReference::Finality(Finality::Final)).with_span_context())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(final_block.header.height, 5);
+            System::current().stop();
         });
-        actix::spawn(actor);
         near_network::test_utils::wait_or_panic(5000);
     });
 }
@@ -183,6 +452,7 @@ fn produce_block_with_approvals() {
 
     // Which of validators is test producer depends on deterministic rng setup for the test.
     let block_producer = "test3";
+    let approvers = validators[2..].to_vec();
     run_actix(async {
         let actor_handles = setup_mock(
             Clock::real(),
@@ -213,75 +483,19 @@ fn produce_block_with_approvals() {
                 PeerManagerMessageResponse::NetworkResponses(NetworkResponses::NoResponse)
             }),
         );
-        let actor = actor_handles
-            .view_client_actor
-            .send(GetBlockWithMerkleTree::latest().with_span_context());
-        let actor = actor.then(move |res| {
-            let (last_block, block_merkle_tree) = res.unwrap().unwrap();
-            let mut block_merkle_tree = PartialMerkleTree::clone(&block_merkle_tree);
-            block_merkle_tree.insert(last_block.header.hash);
-            let signer1 = create_test_signer(&block_producer);
-            let next_block_ordinal = last_block.header.block_ordinal.unwrap() + 1;
-
-            let chunks = last_block.chunks.into_iter().map(Into::into).collect_vec();
-            let chunk_endorsements = vec![vec![]; chunks.len()];
-            let block = Block::produce(
-                PROTOCOL_VERSION,
-                &last_block.header.clone().into(),
-                last_block.header.height + 1,
-                next_block_ordinal,
-                chunks,
-                chunk_endorsements,
-                EpochId::default(),
-                if last_block.header.prev_hash == CryptoHash::default() {
-                    EpochId(last_block.header.hash)
-                } else {
-                    EpochId(last_block.header.next_epoch_id)
-                },
-                None,
-                vec![],
-                Ratio::from_integer(0),
-                0,
-                100,
-                Some(0),
-                &signer1,
-                last_block.header.next_bp_hash,
-                block_merkle_tree.root(),
-                Clock::real(),
-                None,
-                None,
-            );
-            actor_handles.client_actor.do_send(
-                BlockResponse {
-                    block: block.clone(),
-                    peer_id: PeerInfo::random().id,
-                    was_requested: false,
-                }
-                .with_span_context(),
-            );
-
-            for i in 3..11 {
-                let s = AccountId::try_from(if i > 10 {
-                    "test1".to_string()
-                } else {
-                    format!("test{}", i)
-                })
-                .unwrap();
-                let signer = create_test_signer(s.as_str());
-                let approval = Approval::new(
-                    *block.hash(),
-                    block.header().height(),
-                    10, // the height at which "test1" is producing
-                    &signer,
-                );
-                actor_handles
-                    .client_actor
-                    .do_send(BlockApproval(approval, PeerInfo::random().id).with_span_context());
-            }
-
-            future::ready(())
+        actix::spawn(async move {
+            let mut driver =
+                ChainDriver::new(Clock::real(), actor_handles, block_producer.parse().unwrap())
+                    .await;
+            let params = BlockParams {
+                epoch_id: EpochId::default(),
+                minted_amount: Some(0),
+                ..driver.next_block_params()
+            };
+            driver.produce_block_with_params(params).await;
+            // 10 is the height at which "test1" is producing.
+            driver.send_approvals(&approvers, 10).await;
         });
-        actix::spawn(actor);
         near_network::test_utils::wait_or_panic(5000);
     });
 }
@@ -324,66 +538,26 @@ fn invalid_blocks_common(is_requested: bool) {
                 PeerManagerMessageResponse::NetworkResponses(NetworkResponses::NoResponse)
             }),
         );
-        let actor = actor_handles
-            .view_client_actor
-            .send(GetBlockWithMerkleTree::latest().with_span_context());
-        let actor = actor.then(move |res| {
-            let (last_block, block_merkle_tree) = res.unwrap().unwrap();
-            let mut block_merkle_tree = PartialMerkleTree::clone(&block_merkle_tree);
-            block_merkle_tree.insert(last_block.header.hash);
-            let signer = create_test_signer("test");
-            let next_block_ordinal = last_block.header.block_ordinal.unwrap() + 1;
-            let valid_block = Block::produce(
-                PROTOCOL_VERSION,
-                &last_block.header.clone().into(),
-                last_block.header.height + 1,
-                next_block_ordinal,
-                last_block.chunks.iter().cloned().map(Into::into).collect(),
-                vec![vec![]; last_block.chunks.len()],
-                EpochId::default(),
-                if last_block.header.prev_hash == CryptoHash::default() {
-                    EpochId(last_block.header.hash)
-                } else {
-                    EpochId(last_block.header.next_epoch_id)
-                },
-                None,
-                vec![],
-                Ratio::from_integer(0),
-                0,
-                100,
-                Some(0),
-                &signer,
-                last_block.header.next_bp_hash,
-                block_merkle_tree.root(),
-                Clock::real(),
-                None,
-                None,
-            );
+        actix::spawn(async move {
+            let driver =
+                ChainDriver::new(Clock::real(), actor_handles, "test".parse().unwrap()).await;
+            let params = BlockParams {
+                epoch_id: EpochId::default(),
+                minted_amount: Some(0),
+                ..driver.next_block_params()
+            };
+            let valid_block = driver.build_block_with_params(params);
             // Send block with invalid chunk mask
             let mut block = valid_block.clone();
             block.mut_header().set_chunk_mask(vec![]);
             block.mut_header().init();
-            actor_handles.client_actor.do_send(
-                BlockResponse {
-                    block: block.clone(),
-                    peer_id: PeerInfo::random().id,
-                    was_requested: is_requested,
-                }
-                .with_span_context(),
-            );
+            driver.send_block(block, is_requested).await;
 
             // Send blocks with invalid protocol version
             let mut block = valid_block.clone();
             block.mut_header().set_latest_protocol_version(PROTOCOL_VERSION - 1);
             block.mut_header().init();
-            actor_handles.client_actor.do_send(
-                BlockResponse {
-                    block: block.clone(),
-                    peer_id: PeerInfo::random().id,
-                    was_requested: is_requested,
-                }
-                .with_span_context(),
-            );
+            driver.send_block(block, is_requested).await;
 
             // Send block with invalid chunk signature
             let mut block = valid_block.clone();
@@ -401,41 +575,18 @@ fn invalid_blocks_common(is_requested: bool) {
                 }
             };
             block.set_chunks(chunks);
-            actor_handles.client_actor.do_send(
-                BlockResponse {
-                    block: block.clone(),
-                    peer_id: PeerInfo::random().id,
-                    was_requested: is_requested,
-                }
-                .with_span_context(),
-            );
+            driver.send_block(block, is_requested).await;
 
             // Send proper block.
             let block2 = valid_block;
-            actor_handles.client_actor.do_send(
-                BlockResponse {
-                    block: block2.clone(),
-                    peer_id: PeerInfo::random().id,
-                    was_requested: is_requested,
-                }
-                .with_span_context(),
-            );
+            driver.send_block(block2.clone(), is_requested).await;
             if is_requested {
                 let mut block3 = block2;
                 block3.mut_header().set_chunk_headers_root(hash(&[1]));
                 block3.mut_header().init();
-                actor_handles.client_actor.do_send(
-                    BlockResponse {
-                        block: block3.clone(),
-                        peer_id: PeerInfo::random().id,
-                        was_requested: is_requested,
-                    }
-                    .with_span_context(),
-                );
+                driver.send_block(block3, is_requested).await;
             }
-            future::ready(())
         });
-        actix::spawn(actor);
         near_network::test_utils::wait_or_panic(5000);
     });
 }
@@ -668,6 +819,46 @@ fn test_invalid_height_too_old() {
     assert_matches!(res.unwrap_err(), Error::InvalidBlockHeight(_));
 }
 
+/// A chunk corrupted in the store is evicted when the block is processed, and
+/// the block is processed once the chunk is downloaded again.
+#[test]
+fn test_corrupted_chunk_evicted() {
+    init_test_logger();
+    let mut env = TestEnv::default_builder().build();
+    for i in 1..4 {
+        env.produce_block(0, i);
+    }
+    let block = env.clients[0].produce_block(4).unwrap().unwrap();
+    let chunk_hash = block.chunks()[0].chunk_hash();
+    let chunk = env.clients[0].chain.get_chunk(&chunk_hash).unwrap();
+
+    let store = env.clients[0].chain.chain_store().store();
+    let mut store_update = store.store_update();
+    store_update.delete(DBCol::Chunks, chunk_hash.as_ref());
+    store_update.commit().unwrap();
+    let mut store_update = store.store_update();
+    store_update.insert(DBCol::Chunks, chunk_hash.as_ref().to_vec(), vec![1, 2, 3]);
+    store_update.commit().unwrap();
+
+    let res = env.clients[0].process_block_test(block.clone().into(), Provenance::PRODUCED);
+    assert_matches!(res.unwrap_err(), Error::CorruptedChunk(_));
+    assert!(!env.clients[0].chain.chain_store().chunk_exists(&chunk_hash).unwrap());
+    // The eviction is saved, so that the chunk is fetched again until it's
+    // saved, also after a restart.
+    assert!(store.exists(DBCol::Misc, CORRUPTED_CHUNKS_KEY).unwrap());
+    let to_fetch = env.clients[0].chain.corrupted_chunks_to_fetch().unwrap();
+    assert_eq!(
+        to_fetch.iter().map(|header| header.chunk_hash()).collect_vec(),
+        vec![chunk_hash.clone()]
+    );
+
+    let signer = env.clients[0].validator_signer.get();
+    env.clients[0].on_chunk_fetched(chunk, None, &signer);
+    env.clients[0].finish_blocks_in_processing();
+    assert_eq!(env.clients[0].chain.head().unwrap().last_block_hash, *block.hash());
+    assert!(env.clients[0].chain.corrupted_chunks_to_fetch().unwrap().is_empty());
+}
+
 #[test]
 fn test_bad_orphan() {
     let mut env = TestEnv::default_builder().build();
@@ -1367,6 +1558,213 @@ fn test_tx_forwarding() {
     );
 }
 
+/// Checks that the chunk produced on top of the head uses the transactions
+/// speculatively prepared on top of it, and that the speculative preparation
+/// is bounded by the chunk transactions time limit.
+#[test]
+fn test_speculative_transaction_preparation() {
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = 100;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    env.clients[0].config.speculative_transaction_preparation = true;
+
+    let signer = InMemorySigner::test_signer(&"test0".parse().unwrap());
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let tx = SignedTransaction::send_money(
+        1,
+        "test0".parse().unwrap(),
+        "test1".parse().unwrap(),
+        &signer,
+        100,
+        genesis_hash,
+    );
+    assert_eq!(env.rpc_handlers[0].process_tx(tx.clone(), true, false), ProcessTxResponse::ValidTx);
+
+    // No transaction fits into the time limit, so the speculative preparation
+    // leaves the transaction in the pool and the chunk on top of the same head
+    // gets none.
+    let validator_signer = env.clients[0].validator_signer.get();
+    env.clients[0].config.produce_chunk_add_transactions_time_limit.update(Some(Duration::ZERO));
+    env.clients[0].speculatively_prepare_transactions(&validator_signer);
+    env.clients[0].config.produce_chunk_add_transactions_time_limit.update(None);
+    let chunk = create_chunk_on_height(&mut env.clients[0], 1).chunk;
+    assert!(chunk.to_shard_chunk().to_transactions().is_empty());
+
+    env.clients[0].speculatively_prepare_transactions(&validator_signer);
+    let chunk = create_chunk_on_height(&mut env.clients[0], 1).chunk;
+    assert_eq!(chunk.to_shard_chunk().to_transactions(), [tx]);
+}
+
+/// Checks that the limit of a signer whose transactions are left in the pool
+/// because of it is halved in every next chunk, down to the minimum, and that
+/// it's reset once the signer stays under its limit.
+#[test]
+fn test_transaction_admission_signer_limits() {
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = 100;
+    let mut env = TestEnv::builder(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .transaction_admission(TransactionAdmissionConfig {
+            max_transactions_per_signer: 8,
+            min_transactions_per_signer: 2,
+        })
+        .build();
+
+    let signer = InMemorySigner::test_signer(&"test0".parse().unwrap());
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let txs = (1..=20)
+        .map(|nonce| {
+            SignedTransaction::send_money(
+                nonce,
+                "test0".parse().unwrap(),
+                "test1".parse().unwrap(),
+                &signer,
+                100,
+                genesis_hash,
+            )
+        })
+        .collect_vec();
+    for tx in &txs {
+        assert_eq!(
+            env.rpc_handlers[0].process_tx(tx.clone(), true, false),
+            ProcessTxResponse::ValidTx
+        );
+    }
+
+    // All the chunks are produced on top of the genesis, so the transactions
+    // of a chunk are back in the pool for the next one.
+    let num_transactions = (1..=4)
+        .map(|height| {
+            let chunk = create_chunk_on_height(&mut env.clients[0], height).chunk;
+            chunk.to_shard_chunk().to_transactions().len()
+        })
+        .collect_vec();
+    assert_eq!(num_transactions, [8, 4, 2, 2]);
+
+    // With two transactions left in the pool the signer stays under its limit.
+    env.clients[0]
+        .chunk_producer
+        .sharded_tx_pool
+        .lock()
+        .remove_transactions(ShardUId::single_shard(), &txs[2..]);
+    let chunk = create_chunk_on_height(&mut env.clients[0], 5).chunk;
+    assert_eq!(chunk.to_shard_chunk().to_transactions(), &txs[..2]);
+
+    for tx in &txs[2..] {
+        assert_eq!(
+            env.rpc_handlers[0].process_tx(tx.clone(), true, false),
+            ProcessTxResponse::ValidTx
+        );
+    }
+    let chunk = create_chunk_on_height(&mut env.clients[0], 6).chunk;
+    assert_eq!(chunk.to_shard_chunk().to_transactions().len(), 8);
+}
+
+/// Checks that the transactions routed to other chunk producers are
+/// rebroadcast on new heads until they are seen included or their window ends,
+/// and that a node which doesn't track the shard of a transaction doesn't
+/// rebroadcast it.
+#[test]
+fn test_tx_rebroadcast() {
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = 100;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    // RPC handlers of nodes without a validator key, which route all the
+    // transactions to test0.
+    let rpc_handler = |env: &TestEnv,
+                       tracked_shards,
+                       network_adapter: &Arc<MockPeerManagerAdapter>| {
+        let client = &env.clients[0];
+        RpcHandler::new(
+            RpcHandlerConfig {
+                handler_threads: 1,
+                tx_routing_height_horizon: client.config.tx_routing_height_horizon,
+                epoch_length: genesis.config.epoch_length,
+                transaction_validity_period: genesis.config.transaction_validity_period,
+                tx_rebroadcast: Some(TxRebroadcastConfig { num_blocks: 5, num_chunk_producers: 1 }),
+            },
+            client.chunk_producer.sharded_tx_pool.clone(),
+            client.chunk_endorsement_tracker.clone(),
+            client.epoch_manager.clone(),
+            ShardTracker::new(tracked_shards, client.epoch_manager.clone()),
+            MutableConfigValue::new(None, "validator_signer"),
+            client.runtime_adapter.clone(),
+            network_adapter.as_multi_sender(),
+        )
+    };
+    let network_adapter = Arc::new(MockPeerManagerAdapter::default());
+    let mut handler = rpc_handler(&env, TrackedShardsConfig::AllShards, &network_adapter);
+    let untracking_network_adapter = Arc::new(MockPeerManagerAdapter::default());
+    let mut untracking_handler =
+        rpc_handler(&env, TrackedShardsConfig::new_empty(), &untracking_network_adapter);
+
+    let signer = InMemorySigner::test_signer(&"test0".parse().unwrap());
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let tx = |nonce| {
+        SignedTransaction::send_money(
+            nonce,
+            "test0".parse().unwrap(),
+            "test1".parse().unwrap(),
+            &signer,
+            100,
+            genesis_hash,
+        )
+    };
+    // Only `included_tx` reaches test0.
+    let (included_tx, dropped_tx) = (tx(1), tx(2));
+    for tx in [&included_tx, &dropped_tx] {
+        assert_eq!(handler.process_tx(tx.clone(), false, false), ProcessTxResponse::RequestRouted);
+        assert_eq!(
+            untracking_handler.process_tx(tx.clone(), false, false),
+            ProcessTxResponse::RequestRouted
+        );
+    }
+    assert_eq!(
+        env.rpc_handlers[0].process_tx(included_tx.clone(), true, false),
+        ProcessTxResponse::ValidTx
+    );
+    network_adapter.requests.write().clear();
+    untracking_network_adapter.requests.write().clear();
+
+    let routing_status = |handler: &mut RpcHandler, tx: &SignedTransaction| {
+        handler.handle(GetTxRoutingStatus { tx_hash: tx.get_hash() })
+    };
+    for height in 1..=7 {
+        handler.handle(RebroadcastPendingTxs);
+        untracking_handler.handle(RebroadcastPendingTxs);
+        env.produce_block(0, height);
+    }
+    handler.handle(RebroadcastPendingTxs);
+
+    // The included transaction is rebroadcast until it is seen on chain, the
+    // other one until the end of its window.
+    let included_status = routing_status(&mut handler, &included_tx).unwrap();
+    assert_eq!(included_status.state, TxRoutingStateView::Included);
+    assert!(included_status.num_rebroadcasts > 0);
+    let dropped_status = routing_status(&mut handler, &dropped_tx).unwrap();
+    assert_eq!(dropped_status.state, TxRoutingStateView::Abandoned);
+    assert_eq!(dropped_status.num_rebroadcasts, 6);
+    assert_eq!(dropped_status.last_forwarded_height, 5);
+    let forwarded_txs = network_adapter
+        .requests
+        .read()
+        .iter()
+        .map(|request| match request.as_network_requests_ref() {
+            NetworkRequests::ForwardTx(account_id, tx) => {
+                assert_eq!(account_id.as_str(), "test0");
+                tx.get_hash()
+            }
+            request => panic!("unexpected request {request:?}"),
+        })
+        .collect::<HashSet<_>>();
+    assert_eq!(forwarded_txs, HashSet::from([included_tx.get_hash(), dropped_tx.get_hash()]));
+
+    // The node which doesn't track the shard wouldn't see the transactions
+    // included, so it doesn't rebroadcast them.
+    assert!(routing_status(&mut untracking_handler, &dropped_tx).is_none());
+    assert!(untracking_network_adapter.requests.read().is_empty());
+}
+
 #[test]
 fn test_tx_forwarding_no_double_forwarding() {
     let mut genesis = Genesis::test(TestEnvBuilder::make_accounts(50), 50);
@@ -1949,7 +2347,7 @@ fn test_validate_chunk_extra() {
         let outcome_root = Block::compute_outcome_root(block.chunks().iter_deprecated());
         block.mut_header().set_prev_outcome_root(outcome_root);
         let endorsement =
-            ChunkEndorsement::new(EpochId::default(), &chunk_header, &validator_signer);
+            ChunkEndorsement::new(EpochId::default(), &chunk_header, &validator_signer).unwrap();
         block.set_chunk_endorsements(vec![vec![Some(Box::new(endorsement.signature()))]]);
         let body_hash = block.compute_block_body_hash().unwrap();
         block.mut_header().set_block_body_hash(body_hash);
@@ -2720,6 +3118,46 @@ fn test_discard_non_finalizable_block() {
     );
 }
 
+/// With single block finality the head is final as soon as it's processed, so
+/// a block built on the previous head can't be finalized anymore right after
+/// the next block, while doomslug finalizes the previous head only two blocks
+/// later, see `test_discard_non_finalizable_block`. The last final block in the
+/// headers still follows doomslug.
+#[test]
+fn test_single_block_finality() {
+    let genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .single_block_finality()
+        .build();
+
+    let first_block = env.clients[0].produce_block(1).unwrap().unwrap();
+    env.process_block(0, first_block.clone(), Provenance::PRODUCED);
+    // Produce, but not process a block on top of block (1).
+    let fork_block = env.clients[0].produce_block(3).unwrap().unwrap();
+    env.clients[0]
+        .chain
+        .mut_chain_store()
+        .save_latest_known(LatestKnown {
+            height: first_block.header().height(),
+            seen: first_block.header().raw_timestamp(),
+        })
+        .unwrap();
+    env.produce_block(0, 2);
+
+    let chain = &env.clients[0].chain;
+    let head = chain.head().unwrap();
+    let final_head = chain.final_head().unwrap();
+    assert_eq!(head.height, 2);
+    assert_eq!(final_head.last_block_hash, head.last_block_hash);
+    let head_header = chain.get_block_header(&head.last_block_hash).unwrap();
+    assert_ne!(head_header.last_final_block(), &final_head.last_block_hash);
+    assert_matches!(
+        env.clients[0].process_block_test(fork_block.into(), Provenance::NONE).unwrap_err(),
+        Error::CannotBeFinalized
+    );
+}
+
 /// Final state should be consistent when a node switches between forks in the following scenario
 ///                      /-----------h+2
 /// h-2 ---- h-1 ------ h
@@ -2946,6 +3384,28 @@ fn prepare_env_with_transaction() -> (TestEnv, CryptoHash) {
     (env, tx_hash)
 }
 
+/// Checks that the transactions saved at shutdown are back in the pool of the
+/// restarted client, and that they are removed from the store once restored.
+#[test]
+fn test_restore_tx_pool_after_restart() {
+    let (mut env, tx_hash) = prepare_env_with_transaction();
+    let store = env.clients[0].runtime_adapter.store().clone();
+    let saver =
+        TxPoolSaver::new(store.clone(), env.clients[0].chunk_producer.sharded_tx_pool.clone());
+    assert_eq!(saver.save().unwrap(), 1);
+
+    env.restart(0);
+    let pool_tx_hashes = |env: &TestEnv| {
+        let pool = env.clients[0].chunk_producer.sharded_tx_pool.lock();
+        pool.transactions().map(|(_, tx)| tx.get_hash()).collect_vec()
+    };
+    assert!(pool_tx_hashes(&env).is_empty());
+    let signer = env.clients[0].validator_signer.get();
+    env.clients[0].restore_tx_pool(&signer).unwrap();
+    assert_eq!(pool_tx_hashes(&env), vec![tx_hash]);
+    assert!(store.get(DBCol::Misc, TX_POOL_KEY).unwrap().is_none());
+}
+
 #[test]
 fn test_not_broadcast_block_on_accept() {
     let epoch_length = 5;
@@ -2,23 +2,17 @@ use actix::System;
 use futures::{FutureExt, future};
 use near_actix_test_utils::run_actix;
 use near_async::time::{Clock, Duration};
-use near_client::{
-    GetBlock, GetBlockWithMerkleTree, GetExecutionOutcomesForBlock, Query, Status, TxStatus,
-};
+use near_client::{GetBlock, GetExecutionOutcomesForBlock, Query, Status, TxStatus};
 use near_crypto::InMemorySigner;
-use near_network::client::{BlockResponse, ProcessTxRequest, ProcessTxResponse};
-use near_network::types::PeerInfo;
+use near_network::client::{ProcessTxRequest, ProcessTxResponse};
 use near_o11y::WithSpanContextExt;
 use near_o11y::testonly::init_test_logger;
-use near_primitives::block::{Block, BlockHeader};
-use near_primitives::merkle::PartialMerkleTree;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{BlockReference, EpochId, ShardId};
-use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{QueryRequest, QueryResponseKind};
-use num_rational::Ratio;
 
+use crate::env::chain_driver::{BlockParams, ChainDriver};
 use crate::env::setup::setup_no_network;
 
 /// Query account from view client
@@ -66,72 +60,29 @@ fn query_status_not_crash() {
             false,
         );
         let signer = create_test_signer("test");
-        let actor = actor_handles
-            .view_client_actor
-            .send(GetBlockWithMerkleTree::latest().with_span_context());
-        let actor = actor.then(move |res| {
-            let (block, block_merkle_tree) = res.unwrap().unwrap();
-            let mut block_merkle_tree = PartialMerkleTree::clone(&block_merkle_tree);
-            let header: BlockHeader = block.header.clone().into();
-            block_merkle_tree.insert(*header.hash());
-            let mut next_block = Block::produce(
-                PROTOCOL_VERSION,
-                &header,
-                block.header.height + 1,
-                header.block_ordinal() + 1,
-                block.chunks.iter().cloned().map(|c| c.into()).collect(),
-                vec![vec![]; block.chunks.len()],
-                EpochId(block.header.next_epoch_id),
-                EpochId(block.header.hash),
-                None,
-                vec![],
-                Ratio::from_integer(0),
-                0,
-                100,
-                None,
-                &signer,
-                block.header.next_bp_hash,
-                block_merkle_tree.root(),
-                Clock::real(),
-                None,
-                None,
-            );
+        let client_actor = actor_handles.client_actor.clone();
+        actix::spawn(async move {
+            let driver =
+                ChainDriver::new(Clock::real(), actor_handles, "test".parse().unwrap()).await;
+            let tip = driver.tip();
+            let params = BlockParams {
+                epoch_id: *tip.next_epoch_id(),
+                next_epoch_id: EpochId(*tip.hash()),
+                minted_amount: None,
+            };
+            let mut next_block = driver.build_block_with_params(params);
             let timestamp = next_block.header().timestamp();
             next_block
                 .mut_header()
                 .set_timestamp((timestamp + Duration::seconds(60)).unix_timestamp_nanos() as u64);
             next_block.mut_header().resign(&signer);
+            driver.send_block(next_block, false).await;
 
-            actix::spawn(
-                actor_handles
-                    .client_actor
-                    .send(
-                        BlockResponse {
-                            block: next_block,
-                            peer_id: PeerInfo::random().id,
-                            was_requested: false,
-                        }
-                        .with_span_context(),
-                    )
-                    .then(move |_| {
-                        actix::spawn(
-                            actor_handles
-                                .client_actor
-                                .send(
-                                    Status { is_health_check: true, detailed: false }
-                                        .with_span_context(),
-                                )
-                                .then(move |_| {
-                                    System::current().stop();
-                                    future::ready(())
-                                }),
-                        );
-                        future::ready(())
-                    }),
-            );
-            future::ready(())
+            let _ = client_actor
+                .send(Status { is_health_check: true, detailed: false }.with_span_context())
+                .await;
+            System::current().stop();
         });
-        actix::spawn(actor);
         near_network::test_utils::wait_or_panic(5000);
     });
 }
@@ -64,7 +64,7 @@ fn set_up_test_env_for_state_snapshots(
     let state_snapshots_dir = state_snapshots_dir(&home_dir, "data", "state_snapshot");
     let state_snapshot_config = match snapshot_type {
         StateSnapshotType::Enabled => {
-            StateSnapshotConfig::Enabled { state_snapshots_dir: state_snapshots_dir.clone() }
+            StateSnapshotConfig::enabled(&home_dir, "data", "state_snapshot")
         }
         StateSnapshotType::Disabled => StateSnapshotConfig::Disabled,
     };
@@ -126,6 +126,32 @@ fn test_maybe_open_state_snapshot_garbage_snapshot() {
     assert!(result.is_err());
 }
 
+#[test]
+// restore points are pruned oldest first, anything else in the directory is left alone
+fn test_prune_restore_points() {
+    init_test_logger();
+    let store = create_test_store();
+    let test_env = set_up_test_env_for_state_snapshots(&store, StateSnapshotType::Enabled);
+    let restore_points_dir =
+        test_env.shard_tries.state_snapshot_config().restore_points_dir().unwrap().to_path_buf();
+    for height in [30, 10, 20] {
+        std::fs::create_dir_all(restore_points_dir.join(format!("{height}-{}", CryptoHash::new())))
+            .unwrap();
+    }
+    std::fs::create_dir_all(restore_points_dir.join("operator-backup")).unwrap();
+
+    test_env.shard_tries.prune_restore_points(2).unwrap();
+    let heights = test_env
+        .shard_tries
+        .list_restore_points()
+        .unwrap()
+        .into_iter()
+        .map(|(height, _path)| height)
+        .collect::<Vec<_>>();
+    assert_eq!(heights, vec![20, 30]);
+    assert!(restore_points_dir.join("operator-backup").exists());
+}
+
 #[test]
 fn test_state_snapshot_disabled() -> anyhow::Result<()> {
     init_test_logger();
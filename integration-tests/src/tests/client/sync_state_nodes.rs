@@ -2,13 +2,16 @@ use actix::{Actor, System};
 use futures::{FutureExt, future};
 use itertools::Itertools;
 use near_actix_test_utils::run_actix;
-use near_async::time::Duration;
-use near_chain::Provenance;
+use near_async::messaging::{Handler, IntoMultiSender, noop};
+use near_async::time::{Clock, Duration};
+use near_chain::{ChainGenesis, Provenance};
 use near_chain_configs::ExternalStorageLocation::Filesystem;
 use near_chain_configs::{
-    DumpConfig, ExternalStorageConfig, Genesis, SyncConfig, TrackedShardsConfig,
+    DumpConfig, ExternalStorageConfig, Genesis, MutableConfigValue, StatePartServingConfig,
+    SyncConfig, TrackedShardsConfig,
 };
-use near_client::{GetBlock, ProcessTxResponse};
+use near_client::adversarial::Controls;
+use near_client::{GetBlock, ProcessTxResponse, ViewClientActorInner};
 use near_client_primitives::types::GetValidatorInfo;
 use near_crypto::InMemorySigner;
 use near_network::client::{StateRequestHeader, StateRequestPart, StateResponse};
@@ -16,6 +19,7 @@ use near_network::tcp;
 use near_network::test_utils::{WaitOrTimeoutActor, convert_boot_nodes, wait_or_timeout};
 use near_o11y::WithSpanContextExt;
 use near_o11y::testonly::{init_integration_logger, init_test_logger};
+use near_primitives::network::PeerId;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_part::PartId;
 use near_primitives::state_sync::StatePartKey;
@@ -771,8 +775,13 @@ fn slow_test_state_sync_headers() {
                     // Make StateRequestPart and expect that the response contains a part and part_id = 0 and the node has all parts cached.
                     let state_response_info = match view_client1
                         .send(
-                            StateRequestPart { shard_id, sync_hash, part_id: 0 }
-                                .with_span_context(),
+                            StateRequestPart {
+                                shard_id,
+                                sync_hash,
+                                part_id: 0,
+                                peer_id: PeerId::random(),
+                            }
+                            .with_span_context(),
                         )
                         .await
                     {
@@ -932,8 +941,13 @@ fn slow_test_state_sync_headers_no_tracked_shards() {
                     // Make StateRequestPart and expect that the response contains a part and part_id = 0 and the node has all parts cached.
                     let state_response_info = match view_client2
                         .send(
-                            StateRequestPart { shard_id, sync_hash, part_id: 0 }
-                                .with_span_context(),
+                            StateRequestPart {
+                                shard_id,
+                                sync_hash,
+                                part_id: 0,
+                                peer_id: PeerId::random(),
+                            }
+                            .with_span_context(),
                         )
                         .await
                     {
@@ -959,3 +973,70 @@ fn slow_test_state_sync_headers_no_tracked_shards() {
         drop(_dir2);
     });
 }
+
+/// Tests the limits of the state part requests served by the view client with
+/// `state_part_serving`.
+#[test]
+fn slow_test_state_part_serving() {
+    init_test_logger();
+    let epoch_length = 8;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config)
+        .use_state_snapshots()
+        .real_stores()
+        .nightshade_runtimes(&genesis)
+        .build();
+    // Go up to the end of the next epoch, plus one block for the state snapshot.
+    for height in 1..=epoch_length * 2 + 1 {
+        env.produce_block(0, height);
+    }
+    let client = &mut env.clients[0];
+    let head = client.chain.head().unwrap();
+    let sync_hash = client.chain.get_sync_hash(&head.last_block_hash).unwrap().unwrap();
+    let shard_id = client.epoch_manager.shard_ids(&head.epoch_id).unwrap()[0];
+
+    let mut config = client.config.clone();
+    config.view_client_throttle_period = Duration::hours(1);
+    // No part can be generated, so only the parts stored already are served.
+    config.state_part_serving = Some(StatePartServingConfig {
+        max_concurrent_part_generation: 0,
+        max_requests_per_peer: 3,
+        part_cache_size: 1,
+    });
+    let mut view_client = ViewClientActorInner::new(
+        Clock::real(),
+        MutableConfigValue::new(None, "validator_signer"),
+        ChainGenesis::new(&genesis.config),
+        client.epoch_manager.clone(),
+        client.shard_tracker.clone(),
+        client.runtime_adapter.clone(),
+        None,
+        noop().into_multi_sender(),
+        config,
+        Controls::default(),
+    )
+    .unwrap();
+    let mut request_part = |peer_id: &PeerId| {
+        let request =
+            StateRequestPart { shard_id, sync_hash, part_id: 0, peer_id: peer_id.clone() };
+        let StateResponse(response) = view_client.handle(request)?;
+        response.take_state_response().take_part()
+    };
+
+    let peer_id = PeerId::random();
+    assert_eq!(request_part(&peer_id), None);
+    let part =
+        client.chain.state_sync_adapter.get_state_response_part(shard_id, 0, sync_hash).unwrap();
+    assert_eq!(request_part(&peer_id), Some((0, part.clone())));
+    assert_eq!(request_part(&peer_id), Some((0, part)));
+    // The peer made `max_requests_per_peer` requests already.
+    assert_eq!(request_part(&peer_id), None);
+
+    // The peers changing their ids are still limited by the 30 requests per
+    // `view_client_throttle_period` served in total.
+    for _ in 3..30 {
+        assert!(request_part(&PeerId::random()).is_some());
+    }
+    assert_eq!(request_part(&PeerId::random()), None);
+}
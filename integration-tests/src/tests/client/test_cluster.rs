@@ -0,0 +1,200 @@
+use actix::System;
+use near_actix_test_utils::run_actix;
+use near_async::time::{Clock, Duration};
+use near_chain::test_utils::ValidatorSchedule;
+use near_chain_configs::TrackedShardsConfig;
+use near_client::GetBlock;
+use near_network::types::{NetworkResponses, PeerInfo, PeerManagerMessageResponse};
+use near_o11y::WithSpanContextExt;
+use near_o11y::testonly::init_integration_logger;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::{AccountId, BlockId, BlockReference};
+use std::collections::HashSet;
+
+use crate::env::fault_injection::{DelayDistribution, FaultInjectionConfig};
+use crate::env::setup::setup_mock_all_validators;
+use crate::env::test_cluster_builder::TestClusterBuilder;
+
+/// Runs a cluster of validators with the real runtime through a couple of
+/// epochs, with a node which only tracks the shards it validates and an
+/// archival node.
+#[test]
+fn test_cluster_produces_blocks() {
+    init_integration_logger();
+    run_actix(async {
+        let validators: Vec<AccountId> =
+            ["test0", "test1", "test2"].iter().map(|account| account.parse().unwrap()).collect();
+        let (nodes, _) = TestClusterBuilder::new(Clock::real(), validators)
+            .shard_layout(ShardLayout::multi_shard(2, 0))
+            .epoch_length(5)
+            .tracked_shards(1, TrackedShardsConfig::NoShards)
+            .archive(2, true)
+            .build();
+
+        actix::spawn(async move {
+            loop {
+                let mut heights = vec![];
+                for node in &nodes {
+                    let block = node
+                        .view_client_actor
+                        .send(GetBlock::latest().with_span_context())
+                        .await
+                        .unwrap()
+                        .unwrap();
+                    heights.push(block.header.height);
+                }
+                if heights.iter().all(|height| *height > 12) {
+                    // The blocks up to height 10 are final on all the nodes, so
+                    // they must have the same blocks there, and the chunks of
+                    // both shards must keep being produced.
+                    let mut shards_with_new_chunks = HashSet::new();
+                    for height in 6..=10 {
+                        let mut hashes = HashSet::new();
+                        for node in &nodes {
+                            let block = node
+                                .view_client_actor
+                                .send(
+                                    GetBlock(BlockReference::BlockId(BlockId::Height(height)))
+                                        .with_span_context(),
+                                )
+                                .await
+                                .unwrap();
+                            hashes.insert(block.as_ref().ok().map(|block| block.header.hash));
+                            let Ok(block) = block else { continue };
+                            for chunk in &block.chunks {
+                                if chunk.is_new_chunk(height) {
+                                    shards_with_new_chunks.insert(chunk.shard_id);
+                                }
+                            }
+                        }
+                        assert_eq!(hashes.len(), 1, "the nodes disagree on the block at {height}");
+                    }
+                    assert_eq!(shards_with_new_chunks.len(), 2, "no new chunks in some shard");
+                    System::current().stop();
+                    break;
+                }
+                actix::clock::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        });
+        near_network::test_utils::wait_or_panic(60000);
+    });
+}
+
+/// Sums the counter `name` over all the shards. The metrics are global, so the
+/// tests compare the values before and after running the cluster.
+fn shard_counter(name: &str) -> f64 {
+    let Some(family) =
+        near_o11y::metrics::prometheus::gather().into_iter().find(|m| m.get_name() == name)
+    else {
+        return 0.0;
+    };
+    family.get_metric().iter().map(|metric| metric.get_counter().get_value()).sum()
+}
+
+/// Drops the optimistic blocks for a while and delays them afterwards, so the
+/// chunk producers have to fall back to the blocks, and checks that the chain
+/// keeps going with the chunks applied without an optimistic block.
+#[test]
+fn test_cluster_without_optimistic_blocks() {
+    init_integration_logger();
+    let misses_before = shard_counter("near_apply_chunk_results_cache_misses");
+    run_actix(async {
+        let validators: Vec<AccountId> =
+            ["test0", "test1", "test2"].iter().map(|account| account.parse().unwrap()).collect();
+        let fault_injection = FaultInjectionConfig::new()
+            .drop_optimistic_blocks(3..8)
+            .delay_optimistic_blocks(DelayDistribution::Constant(Duration::milliseconds(500)));
+        let (nodes, _) = TestClusterBuilder::new(Clock::real(), validators)
+            .epoch_length(5)
+            .fault_injection(fault_injection)
+            .build();
+
+        actix::spawn(async move {
+            loop {
+                let block = nodes[0]
+                    .view_client_actor
+                    .send(GetBlock::latest().with_span_context())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                if block.header.height > 12 {
+                    // Without the optimistic blocks at heights 3 to 7, none of
+                    // the nodes has the results of the chunks of these blocks
+                    // cached and they all apply the chunks with the blocks.
+                    let misses =
+                        shard_counter("near_apply_chunk_results_cache_misses") - misses_before;
+                    assert!(
+                        misses >= 5.0 * nodes.len() as f64,
+                        "expected the chunks of the blocks without an optimistic block to be applied, got {misses} cache misses"
+                    );
+                    System::current().stop();
+                    break;
+                }
+                actix::clock::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        });
+        near_network::test_utils::wait_or_panic(60000);
+    });
+}
+
+/// Slows down the link between the two validators of the mock network, which
+/// take turns producing the blocks, so that every block has to cross the link
+/// before the next one is produced on top of it.
+#[test]
+fn test_mock_network_link_latency() {
+    init_integration_logger();
+    run_actix(async {
+        let validators: Vec<AccountId> =
+            ["test0", "test1"].iter().map(|account| account.parse().unwrap()).collect();
+        let vs = ValidatorSchedule::new().block_producers_per_epoch(vec![validators.clone()]);
+        let key_pairs = validators.iter().map(|_| PeerInfo::random()).collect();
+        let latency = Duration::milliseconds(500);
+        let fault_injection = FaultInjectionConfig::new().link_latency(
+            validators[0].clone(),
+            validators[1].clone(),
+            DelayDistribution::with_jitter(latency, Duration::milliseconds(100)),
+        );
+        let clock = Clock::real();
+        let start = clock.now();
+        let (nodes, _) = setup_mock_all_validators(
+            clock.clone(),
+            vs,
+            key_pairs,
+            true,
+            100,
+            fault_injection,
+            false,
+            100,
+            true,
+            vec![false; 2],
+            false,
+            None,
+            Box::new(|_, _, _| {
+                (PeerManagerMessageResponse::NetworkResponses(NetworkResponses::NoResponse), true)
+            }),
+        );
+
+        actix::spawn(async move {
+            loop {
+                let block = nodes[0]
+                    .view_client_actor
+                    .send(GetBlock::latest().with_span_context())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                if block.header.height >= 6 {
+                    // Without the latency, the blocks would be produced every
+                    // 100ms. The blocks 2 to 6 each waited for the previous
+                    // block to cross the link, with some margin for the time it
+                    // took to start the nodes.
+                    let elapsed = clock.now() - start;
+                    assert!(elapsed >= latency * 4, "6 blocks produced in {elapsed}");
+                    System::current().stop();
+                    break;
+                }
+                actix::clock::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        });
+        near_network::test_utils::wait_or_panic(30000);
+    });
+}
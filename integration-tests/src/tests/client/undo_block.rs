@@ -2,6 +2,7 @@ use near_chain::{ChainStore, ChainStoreAccess, Provenance};
 use near_chain_configs::Genesis;
 use near_epoch_manager::EpochManagerAdapter;
 use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardUId;
 use near_store::Store;
 use near_store::test_utils::create_test_store;
 use near_undo_block::undo_block;
@@ -49,6 +50,11 @@ fn test_undo_block(epoch_length: u64, stop_height: u64) {
     // after undo, the current head should be the prev_block_hash
     assert_eq!(chain_store.head().unwrap().last_block_hash.as_bytes(), prev_block_hash.as_bytes());
     assert_eq!(chain_store.head().unwrap().height, stop_height - 1);
+    // the chunk of the undone block has to be applied again
+    let shard_id = ShardUId::single_shard().shard_id();
+    assert!(
+        !chain_store.is_chunk_apply_journaled(&current_head.last_block_hash, shard_id).unwrap()
+    );
 
     // set up an environment again with the same store
     let (mut env, _) = setup_env(&genesis, store);
@@ -350,3 +350,12 @@ fn slow_test_banning_chunk_producer_when_seeing_invalid_chunk() {
     test.env.clients[7].chunk_producer.produce_invalid_chunks = true;
     test_banning_chunk_producer_when_seeing_invalid_chunk_base(test);
 }
+
+#[test]
+#[cfg(feature = "test_features")]
+fn slow_test_banning_chunk_producer_when_seeing_invalid_chunk_header() {
+    init_test_logger();
+    let mut test = AdversarialBehaviorTestData::new();
+    test.env.clients[7].chunk_producer.adv.set_produce_invalid_chunk_headers(true);
+    test_banning_chunk_producer_when_seeing_invalid_chunk_base(test);
+}
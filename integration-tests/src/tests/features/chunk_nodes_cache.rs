@@ -9,9 +9,7 @@ use near_crypto::{InMemorySigner, Signer};
 use near_parameters::{ExtCosts, RuntimeConfigStore};
 use near_primitives::hash::CryptoHash;
 use near_primitives::test_utils::encode;
-use near_primitives::transaction::{
-    Action, ExecutionMetadata, FunctionCallAction, SignedTransaction,
-};
+use near_primitives::transaction::{Action, FunctionCallAction, SignedTransaction};
 use near_primitives::types::{BlockHeightDelta, Gas};
 use near_primitives::version::{PROTOCOL_VERSION, ProtocolVersion};
 use near_primitives::views::FinalExecutionStatus;
@@ -121,20 +119,19 @@ fn compare_node_counts() {
             let receipt_execution_outcome =
                 env.clients[0].chain.get_execution_outcome(&receipt_ids[0]).unwrap();
             let metadata = receipt_execution_outcome.outcome_with_id.outcome.metadata;
-            match metadata {
-                ExecutionMetadata::V1 => panic!("ExecutionMetadata cannot be empty"),
-                ExecutionMetadata::V2(_profile_data) => panic!("expected newest ExecutionMetadata"),
-                ExecutionMetadata::V3(profile_data) => TrieNodesCount {
-                    db_reads: {
-                        let cost = profile_data.get_ext_cost(ExtCosts::touching_trie_node);
-                        assert_eq!(cost % touching_trie_node_cost, 0);
-                        cost / touching_trie_node_cost
-                    },
-                    mem_reads: {
-                        let cost = profile_data.get_ext_cost(ExtCosts::read_cached_trie_node);
-                        assert_eq!(cost % read_cached_trie_node_cost, 0);
-                        cost / read_cached_trie_node_cost
-                    },
+            let Some(profile_data) = metadata.profile_data_v3() else {
+                panic!("expected newest ExecutionMetadata, got {:?}", metadata);
+            };
+            TrieNodesCount {
+                db_reads: {
+                    let cost = profile_data.get_ext_cost(ExtCosts::touching_trie_node);
+                    assert_eq!(cost % touching_trie_node_cost, 0);
+                    cost / touching_trie_node_cost
+                },
+                mem_reads: {
+                    let cost = profile_data.get_ext_cost(ExtCosts::read_cached_trie_node);
+                    assert_eq!(cost % read_cached_trie_node_cost, 0);
+                    cost / read_cached_trie_node_cost
                 },
             }
         })
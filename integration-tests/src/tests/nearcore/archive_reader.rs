@@ -0,0 +1,75 @@
+use crate::utils::test_helpers::heavy_test;
+use actix::System;
+use near_actix_test_utils::run_actix;
+use near_chain_configs::Genesis;
+use near_client::GetBlock;
+use near_network::tcp;
+use near_network::test_utils::wait_or_timeout;
+use near_o11y::WithSpanContextExt;
+use near_o11y::testonly::init_integration_logger;
+use near_primitives::types::BlockHeight;
+use near_store::db::RocksDB;
+use nearcore::archive_reader::start_archive_reader;
+use nearcore::{load_test_config, start_with_config};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Runs a node for a few blocks and then serves its home dir with the archive
+/// reader. The archive reader must see the blocks of the node and must not
+/// write to the home dir.
+#[test]
+fn slow_test_archive_reader() {
+    heavy_test(|| {
+        init_integration_logger();
+        let genesis = Genesis::test(vec!["test1".parse().unwrap()], 1);
+        let mut near_config =
+            load_test_config("test1", tcp::ListenerAddr::reserve_for_test(), genesis);
+        near_config.client_config.min_num_peers = 0;
+        let dir = tempfile::Builder::new().prefix("archive_reader").tempdir().unwrap();
+
+        let head_height = Arc::new(AtomicU64::new(0));
+        run_actix({
+            let near_config = near_config.clone();
+            let dir = dir.path().to_path_buf();
+            async move {
+                let nearcore::NearNode { view_client, .. } =
+                    start_with_config(&dir, near_config).expect("start_with_config");
+                wait_or_timeout(100, 30000, || async {
+                    match view_client.send(GetBlock::latest().with_span_context()).await {
+                        Ok(Ok(block)) if block.header.height > 5 => ControlFlow::Break(()),
+                        _ => ControlFlow::Continue(()),
+                    }
+                })
+                .await
+                .unwrap();
+                System::current().stop();
+            }
+        });
+        RocksDB::block_until_all_instances_are_dropped();
+
+        // The archive reader must not recreate the compiled contract cache.
+        let contracts_dir = dir.path().join("data").join("contracts");
+        std::fs::remove_dir_all(&contracts_dir).unwrap();
+        run_actix({
+            let head_height = head_height.clone();
+            let dir = dir.path().to_path_buf();
+            async move {
+                let node = start_archive_reader(&dir, near_config).unwrap();
+                let block = node
+                    .view_client
+                    .send(GetBlock::latest().with_span_context())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                head_height.store(block.header.height, Ordering::Relaxed);
+                System::current().stop();
+            }
+        });
+        RocksDB::block_until_all_instances_are_dropped();
+
+        let head_height: BlockHeight = head_height.load(Ordering::Relaxed);
+        assert!(head_height > 5, "head height {head_height}");
+        assert!(!contracts_dir.exists());
+    });
+}
@@ -1,3 +1,4 @@
+mod archive_reader;
 mod economics;
 mod node_cluster;
 mod rpc_error_structs;
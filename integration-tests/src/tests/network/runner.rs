@@ -128,6 +128,7 @@ fn setup_network_node(
         epoch_manager.clone(),
         shard_tracker.clone(),
         runtime.clone(),
+        None,
         network_adapter.as_multi_sender(),
         client_config.clone(),
         adv,
@@ -137,6 +138,7 @@ fn setup_network_node(
         tx_routing_height_horizon: client_config.tx_routing_height_horizon,
         epoch_length: client_config.epoch_length,
         transaction_validity_period: genesis.config.transaction_validity_period,
+        tx_rebroadcast: client_config.tx_rebroadcast.clone(),
     };
     let rpc_handler = spawn_rpc_handler_actor(
         rpc_handler_config,
@@ -167,13 +169,19 @@ fn setup_network_node(
         runtime,
         Arc::new(RayonAsyncComputationSpawner),
         Arc::new(RayonAsyncComputationSpawner),
+        client_config.produced_state_witness_retention_heights,
     ));
     shards_manager_adapter.bind(shards_manager_actor.with_auto_span_context());
     let peer_manager = PeerManagerActor::spawn(
         time::Clock::real(),
         db.clone(),
         config,
-        client_sender_for_network(client_actor, view_client_addr, rpc_handler),
+        client_sender_for_network(
+            client_actor,
+            view_client_addr.clone(),
+            view_client_addr,
+            rpc_handler,
+        ),
         network_adapter.as_multi_sender(),
         shards_manager_adapter.as_sender(),
         partial_witness_actor.with_auto_span_context().into_multi_sender(),
@@ -1,14 +1,19 @@
-use std::{collections::HashMap, io, sync::Arc};
+use std::{collections::HashMap, io, num::NonZeroU32, sync::Arc};
 
 use borsh::BorshDeserialize;
 
+use near_chain_configs::MIN_GAS_PRICE;
+use near_crypto::InMemorySigner;
+use near_parameters::RuntimeConfig;
 use near_primitives::{
-    account::{Account, AccountContract},
+    account::{AccessKey, Account, AccountContract},
+    errors::InvalidTxError,
     hash::{CryptoHash, hash as sha256},
     serialize::to_base64,
+    transaction::SignedTransaction,
     trie_key::trie_key_parsers,
     types::{AccountId, StateRoot},
-    views::StateItem,
+    views::{StateItem, StateOverrideView},
 };
 use near_primitives::{
     test_utils::MockEpochInfoProvider,
@@ -19,7 +24,7 @@ use near_primitives::{
 use near_store::{NibbleSlice, RawTrieNode, RawTrieNodeWithSize, ShardUId, set_account};
 use node_runtime::state_viewer::errors;
 use node_runtime::state_viewer::*;
-use testlib::runtime_utils::alice_account;
+use testlib::runtime_utils::{alice_account, bob_account};
 
 use crate::utils::runtime_utils::{TEST_SHARD_UID, get_runtime_and_trie, get_test_trie_viewer};
 
@@ -422,3 +427,205 @@ fn test_log_when_panic() {
 
     assert_eq!(logs, vec!["hello".to_string()]);
 }
+
+#[test]
+fn test_view_state_paged() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let shard_uid = TEST_SHARD_UID;
+    let mut state_update = tries.new_trie_update(shard_uid, root);
+    for key in [&b"a1"[..], b"b1", b"b2", b"b3", b"c1"] {
+        state_update.set(
+            TrieKey::ContractData { account_id: alice_account(), key: key.to_vec() },
+            key.to_vec(),
+        );
+    }
+    state_update.set(
+        TrieKey::ContractData { account_id: bob_account(), key: b"b4".to_vec() },
+        b"b4".to_vec(),
+    );
+    state_update.commit(StateChangeCause::InitialState);
+    let trie_changes = state_update.finalize().unwrap().trie_changes;
+    let mut db_changes = tries.store_update();
+    let new_root = tries.apply_all(&trie_changes, shard_uid, &mut db_changes);
+    db_changes.commit().unwrap();
+
+    let state_update = tries.new_trie_update(shard_uid, new_root);
+    let trie_viewer = TrieViewer::default();
+    let keys = |result: &near_primitives::views::ViewStatePagedResult| {
+        result.values.iter().map(|item| item.key.to_vec()).collect::<Vec<_>>()
+    };
+    let limit = |limit| NonZeroU32::new(limit).unwrap();
+
+    let page = trie_viewer
+        .view_state_paged(&state_update, &alice_account(), b"b", b"", limit(2), false)
+        .unwrap();
+    assert_eq!(keys(&page), vec![b"b1".to_vec(), b"b2".to_vec()]);
+    assert_eq!(page.values[0].value.to_vec(), b"b1".to_vec());
+    let next_key = page.next_key.unwrap();
+    assert_eq!(next_key.to_vec(), b"b3".to_vec());
+
+    let page = trie_viewer
+        .view_state_paged(&state_update, &alice_account(), b"b", &next_key, limit(2), true)
+        .unwrap();
+    assert_eq!(keys(&page), vec![b"b3".to_vec()]);
+    assert_eq!(page.next_key, None);
+    assert!(!page.proof.is_empty());
+
+    // The pages of the whole state add up to the result of `view_state`.
+    let mut start_key = vec![];
+    let mut paged_keys = vec![];
+    loop {
+        let page = trie_viewer
+            .view_state_paged(&state_update, &alice_account(), b"", &start_key, limit(1), false)
+            .unwrap();
+        paged_keys.extend(keys(&page));
+        match page.next_key {
+            Some(next_key) => start_key = next_key.to_vec(),
+            None => break,
+        }
+    }
+    let view_state = trie_viewer.view_state(&state_update, &alice_account(), b"", false).unwrap();
+    assert_eq!(
+        paged_keys,
+        view_state.values.iter().map(|item| item.key.to_vec()).collect::<Vec<_>>()
+    );
+
+    let result = trie_viewer.view_state_paged(
+        &state_update,
+        &"unknown.near".parse().unwrap(),
+        b"",
+        b"",
+        limit(1),
+        false,
+    );
+    assert!(result.is_err());
+
+    // The pages are also limited by the state size limit of the viewer, but
+    // have at least one item.
+    let trie_viewer = TrieViewer::new(Some(1), None);
+    let page = trie_viewer
+        .view_state_paged(&state_update, &alice_account(), b"b", b"", limit(2), false)
+        .unwrap();
+    assert_eq!(keys(&page), vec![b"b1".to_vec()]);
+    assert_eq!(page.next_key.unwrap().to_vec(), b"b2".to_vec());
+}
+
+fn dry_run_view_state() -> ViewApplyState {
+    ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        shard_id: ShardUId::single_shard().shard_id(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+    }
+}
+
+#[test]
+fn test_dry_run_transaction() {
+    let (viewer, root) = get_test_trie_viewer();
+    let signer = InMemorySigner::test_signer(&alice_account());
+    let tx = SignedTransaction::send_money(
+        1,
+        alice_account(),
+        bob_account(),
+        &signer,
+        100,
+        CryptoHash::default(),
+    );
+    let result = viewer
+        .dry_run_transaction(
+            root,
+            dry_run_view_state(),
+            Arc::new(RuntimeConfig::test()),
+            MIN_GAS_PRICE,
+            tx.clone(),
+            false,
+            &[],
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+
+    assert_eq!(result.outcomes[0].id, tx.get_hash());
+    // The transfer and the gas refund are both executed on the only shard.
+    assert!(result.outcomes.len() >= 2);
+    assert!(result.receipts_not_executed.is_empty());
+    assert!(result.state_changes.iter().any(|change| {
+        matches!(&change.trie_key, TrieKey::Account { account_id } if account_id == &bob_account())
+    }));
+}
+
+#[test]
+fn test_dry_run_transaction_with_overrides() {
+    let (viewer, root) = get_test_trie_viewer();
+    let account_id: AccountId = "dave.near".parse().unwrap();
+    let signer = InMemorySigner::test_signer(&account_id);
+    let other_signer = InMemorySigner::test_signer(&bob_account());
+    // Signed with a key `dave.near` doesn't have.
+    let tx = SignedTransaction::new(
+        other_signer.sign(&[]),
+        SignedTransaction::send_money(
+            1,
+            account_id.clone(),
+            alice_account(),
+            &signer,
+            100,
+            CryptoHash::default(),
+        )
+        .transaction,
+    );
+    let state_overrides = vec![
+        StateOverrideView::AccountBalance {
+            account_id: account_id.clone(),
+            amount: 10u128.pow(24),
+        },
+        StateOverrideView::AccessKey {
+            account_id: account_id.clone(),
+            public_key: signer.public_key(),
+            access_key: AccessKey::full_access().into(),
+        },
+    ];
+
+    let err = viewer
+        .dry_run_transaction(
+            get_test_trie_viewer().1,
+            dry_run_view_state(),
+            Arc::new(RuntimeConfig::test()),
+            MIN_GAS_PRICE,
+            tx.clone(),
+            false,
+            &state_overrides,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        errors::DryRunTransactionError::InvalidTransaction {
+            error: InvalidTxError::InvalidSignature
+        }
+    ));
+
+    let result = viewer
+        .dry_run_transaction(
+            root,
+            dry_run_view_state(),
+            Arc::new(RuntimeConfig::test()),
+            MIN_GAS_PRICE,
+            tx,
+            true,
+            &state_overrides,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+    assert!(result.outcomes.len() >= 2);
+    // Overrides are applied but not reported as changes.
+    assert!(result.state_changes.iter().all(|change| {
+        change.changes.iter().all(|c| !matches!(c.cause, StateChangeCause::InitialState))
+    }));
+    assert!(result.state_changes.iter().any(|change| {
+        matches!(&change.trie_key, TrieKey::Account { account_id } if account_id == &alice_account())
+    }));
+}
@@ -3,7 +3,7 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 
 use near_async::messaging::CanSend;
-use near_client::DistributeStateWitnessRequest;
+use near_client::{DistributeStateWitnessRequest, RequestMissingStateWitnessRequest};
 
 #[derive(Clone, Default)]
 pub struct MockPartialWitnessAdapter {
@@ -16,6 +16,10 @@ impl CanSend<DistributeStateWitnessRequest> for MockPartialWitnessAdapter {
     }
 }
 
+impl CanSend<RequestMissingStateWitnessRequest> for MockPartialWitnessAdapter {
+    fn send(&self, _msg: RequestMissingStateWitnessRequest) {}
+}
+
 impl MockPartialWitnessAdapter {
     pub fn pop_distribution_request(&self) -> Option<DistributeStateWitnessRequest> {
         self.distribution_request.write().pop_front()
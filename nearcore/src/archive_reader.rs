@@ -0,0 +1,133 @@
+//! Read-only "archive reader" mode.
+//!
+//! Opens an existing home dir without networking or block processing and only
+//! serves the view part of the JSON RPC from its database. This makes it cheap
+//! to serve historical snapshots of the chain, e.g. quarterly archives, without
+//! running a syncing node for each of them.
+//!
+//! Requests which need the client, the transaction handler or the network
+//! (e.g. `status`, `send_tx` or `network_info`) fail, since those components are
+//! not running.
+//!
+//! Nothing is written to the home dir: the database is opened in read-only
+//! mode, the state snapshot isn't opened and compiled contracts aren't cached.
+use crate::NearConfig;
+#[cfg(feature = "json_rpc")]
+use crate::entity_debug::EntityDebugHandlerImpl;
+use actix::Addr;
+use anyhow::Context;
+use near_async::actix::AddrWithAutoSpanContextExt;
+use near_async::messaging::{IntoMultiSender, noop};
+use near_async::time::Clock;
+use near_chain::ChainGenesis;
+use near_chain::runtime::NightshadeRuntime;
+use near_client::{ViewClientActor, ViewClientActorInner};
+use near_epoch_manager::EpochManager;
+use near_epoch_manager::shard_tracker::ShardTracker;
+use near_store::{Mode, NodeStorage, StateSnapshotConfig, TrieConfig};
+use near_vm_runner::{ContractRuntimeCache, NoContractRuntimeCache};
+use std::path::Path;
+#[cfg(feature = "json_rpc")]
+use std::sync::Arc;
+
+pub struct ArchiveReaderNode {
+    pub view_client: Addr<ViewClientActor>,
+    pub rpc_servers: Vec<(&'static str, actix_web::dev::ServerHandle)>,
+}
+
+/// Opens the database of the node in read-only mode. Unlike `open_storage`,
+/// this never migrates the database, so the snapshot must have been written
+/// by a compatible version of neard.
+fn open_read_only_storage(
+    home_dir: &Path,
+    near_config: &mut NearConfig,
+) -> anyhow::Result<NodeStorage> {
+    let opener = NodeStorage::opener(
+        home_dir,
+        &near_config.config.store,
+        near_config.config.archival_config(),
+    );
+    let storage = opener.open_in_mode(Mode::ReadOnly).with_context(|| {
+        format!("unable to open database at {} in read-only mode", opener.path().display())
+    })?;
+    near_config.config.archive = storage.is_archive()?;
+    Ok(storage)
+}
+
+/// Starts serving the view RPC from the database in `home_dir`.
+///
+/// Must be called from within an actix system.
+pub fn start_archive_reader(
+    home_dir: &Path,
+    mut config: NearConfig,
+) -> anyhow::Result<ArchiveReaderNode> {
+    let storage = open_read_only_storage(home_dir, &mut config)?;
+    // Prefer the split store so that data which was already moved to the cold
+    // database is still visible.
+    let store = storage.get_split_store().unwrap_or_else(|| storage.get_hot_store());
+
+    let epoch_manager =
+        EpochManager::new_arc_handle(store.clone(), &config.genesis.config, Some(home_dir));
+    let shard_tracker = ShardTracker::new(
+        config.client_config.tracked_shards_config.clone(),
+        epoch_manager.clone(),
+    );
+    // Unlike `NightshadeRuntime::from_config`, don't open the state snapshot
+    // and don't use the compiled contract cache of the home dir, which would be
+    // written to by view calls.
+    let runtime = NightshadeRuntime::new(
+        store.clone(),
+        ContractRuntimeCache::handle(&NoContractRuntimeCache),
+        &config.genesis.config,
+        epoch_manager.clone(),
+        config.client_config.trie_viewer_state_size_limit,
+        config.client_config.max_gas_burnt_view,
+        None,
+        config.config.gc.gc_num_epochs_to_keep(),
+        TrieConfig::from_store_config(&config.config.store),
+        StateSnapshotConfig::Disabled,
+    );
+    let chain_genesis = ChainGenesis::new(&config.genesis.config);
+
+    let view_client = ViewClientActorInner::spawn_actix_actor(
+        Clock::real(),
+        config.validator_signer.clone(),
+        chain_genesis,
+        epoch_manager.clone(),
+        shard_tracker,
+        runtime.clone(),
+        None,
+        noop().into_multi_sender(),
+        config.client_config.clone(),
+        near_client::adversarial::Controls::new(config.client_config.archive),
+    );
+
+    #[allow(unused_mut)]
+    let mut rpc_servers = Vec::new();
+    #[cfg(feature = "json_rpc")]
+    if let Some(rpc_config) = config.rpc_config {
+        let entity_debug_handler = EntityDebugHandlerImpl {
+            epoch_manager,
+            runtime,
+            hot_store: storage.get_hot_store(),
+            cold_store: storage.get_cold_store(),
+        };
+        rpc_servers.extend(near_jsonrpc::start_http(
+            rpc_config,
+            config.genesis.config.clone(),
+            noop().into_multi_sender(),
+            view_client.clone().with_auto_span_context().into_multi_sender(),
+            noop().into_multi_sender(),
+            noop().into_multi_sender(),
+            None,
+            #[cfg(feature = "test_features")]
+            noop().into_multi_sender(),
+            Arc::new(entity_debug_handler),
+        ));
+    }
+    if rpc_servers.is_empty() {
+        tracing::warn!(target: "neard", home_dir = %home_dir.display(), "RPC is disabled, archive reader will not serve any requests");
+    }
+
+    Ok(ArchiveReaderNode { view_client, rpc_servers })
+}
@@ -1,5 +1,6 @@
 use crate::download_file::{FileDownloadError, run_download_file};
 use crate::dyn_config::LOG_CONFIG_FILENAME;
+use crate::key_unlock::{KeyPassphraseSource, unlock_key_file};
 use anyhow::{Context, anyhow, bail};
 use bytesize::ByteSize;
 use near_async::time::{Clock, Duration};
@@ -11,27 +12,31 @@ use near_chain_configs::test_utils::{
 use near_chain_configs::{
     BLOCK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_PRODUCER_KICKOUT_THRESHOLD,
     CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD, ChunkDistributionNetworkConfig, ClientConfig,
-    EXPECTED_EPOCH_LENGTH, EpochSyncConfig, FAST_EPOCH_LENGTH, FISHERMEN_THRESHOLD,
-    GAS_PRICE_ADJUSTMENT_RATE, GCConfig, GENESIS_CONFIG_FILENAME, Genesis, GenesisConfig,
-    GenesisValidationMode, INITIAL_GAS_LIMIT, LogSummaryStyle, MAX_INFLATION_RATE,
-    MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, MutableConfigValue, MutableValidatorSigner,
-    NEAR_BASE, NUM_BLOCK_PRODUCER_SEATS, NUM_BLOCKS_PER_YEAR, PROTOCOL_REWARD_RATE,
-    PROTOCOL_UPGRADE_STAKE_THRESHOLD, ReshardingConfig, StateSyncConfig,
-    TRANSACTION_VALIDITY_PERIOD, TrackedShardsConfig, default_chunk_wait_mult,
-    default_enable_multiline_logging, default_epoch_sync,
+    ConfigOverride, ConfigSource, EXPECTED_EPOCH_LENGTH, EpochSyncConfig, FAST_EPOCH_LENGTH,
+    FISHERMEN_THRESHOLD, FastBootstrapConfig, GAS_PRICE_ADJUSTMENT_RATE, GCConfig,
+    GENESIS_CONFIG_FILENAME, Genesis, GenesisConfig, GenesisValidationMode, INITIAL_GAS_LIMIT,
+    LayeredConfig, LogSummaryStyle, MAX_INFLATION_RATE, MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE,
+    MutableConfigValue, MutableValidatorSigner, NEAR_BASE, NUM_BLOCK_PRODUCER_SEATS,
+    NUM_BLOCKS_PER_YEAR, NodeProfile, PROTOCOL_REWARD_RATE, PROTOCOL_UPGRADE_STAKE_THRESHOLD,
+    RemoteSignerConfig, ReshardingConfig, StatePartServingConfig, StateSyncConfig,
+    TRANSACTION_VALIDITY_PERIOD, TrackedShardsConfig, TransactionAdmissionConfig,
+    TransactionPriorityConfig, TxRebroadcastConfig, ValidatorDelegationInfoConfig,
+    default_chunk_wait_mult, default_enable_multiline_logging, default_epoch_sync,
     default_header_sync_expected_height_per_second, default_header_sync_initial_timeout,
     default_header_sync_progress_timeout, default_header_sync_stall_ban_timeout,
     default_log_summary_period, default_orphan_state_witness_max_size,
     default_orphan_state_witness_pool_size, default_produce_chunk_add_transactions_time_limit,
-    default_state_sync_enabled, default_state_sync_external_backoff,
-    default_state_sync_external_timeout, default_state_sync_p2p_timeout,
-    default_state_sync_retry_backoff, default_sync_check_period, default_sync_height_threshold,
-    default_sync_max_block_requests, default_sync_step_period, default_transaction_pool_size_limit,
-    default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
-    default_view_client_threads, default_view_client_throttle_period, get_initial_supply,
+    default_produced_state_witness_retention_heights, default_state_sync_enabled,
+    default_state_sync_external_backoff, default_state_sync_external_timeout,
+    default_state_sync_p2p_timeout, default_state_sync_retry_backoff, default_sync_check_period,
+    default_sync_height_threshold, default_sync_max_block_requests, default_sync_step_period,
+    default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
+    default_tx_routing_height_horizon, default_view_client_threads,
+    default_view_client_throttle_period, get_initial_supply,
 };
+use near_client::RemoteSigner;
 use near_config_utils::{DownloadConfigType, ValidationError, ValidationErrors};
-use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer};
+use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer, StoredKeyFile};
 use near_epoch_manager::EpochManagerHandle;
 #[cfg(feature = "json_rpc")]
 use near_jsonrpc::RpcConfig;
@@ -46,7 +51,9 @@ use near_primitives::types::{
     ShardId,
 };
 use near_primitives::utils::{from_timestamp, get_num_seats_per_shard};
-use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+use near_primitives::validator_signer::{
+    ExternalValidatorSigner, InMemoryValidatorSigner, ValidatorSigner,
+};
 use near_primitives::version::PROTOCOL_VERSION;
 #[cfg(feature = "rosetta_rpc")]
 use near_rosetta_rpc::RosettaRpcConfig;
@@ -138,6 +145,20 @@ pub struct Consensus {
     /// Multiplier for the wait time for all chunks to be received.
     #[serde(default = "default_chunk_wait_mult")]
     pub chunk_wait_mult: Rational32,
+    /// If set, a block producer which is ready to produce a block waits up to
+    /// this long for the chunks of the shards it tracks, which are usually
+    /// about to complete. The wait is capped at `min_block_production_delay`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "near_async::time::serde_opt_duration_as_std")]
+    pub own_chunks_grace_period: Option<Duration>,
+    /// If set, a block producer which is ready to produce a block waits for an
+    /// offset of up to this long, derived from its account id and the height,
+    /// so that the block producers of the network don't all send their blocks
+    /// at the same moments. The offset is capped at half of
+    /// `min_block_production_delay`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "near_async::time::serde_opt_duration_as_std")]
+    pub block_production_jitter: Option<Duration>,
     /// Produce empty blocks, use `false` for testing.
     pub produce_empty_blocks: bool,
     /// Horizon at which instead of fetching block, fetch full state.
@@ -208,6 +229,8 @@ impl Default for Consensus {
             max_block_production_delay: Duration::milliseconds(MAX_BLOCK_PRODUCTION_DELAY),
             max_block_wait_delay: Duration::milliseconds(MAX_BLOCK_WAIT_DELAY),
             chunk_wait_mult: Rational32::new(1, CHUNK_WAIT_DENOMINATOR),
+            own_chunks_grace_period: None,
+            block_production_jitter: None,
             produce_empty_blocks: true,
             block_fetch_horizon: BLOCK_FETCH_HORIZON,
             block_header_fetch_horizon: BLOCK_HEADER_FETCH_HORIZON,
@@ -238,6 +261,15 @@ pub struct Config {
     pub genesis_records_file: Option<String>,
     pub validator_key_file: String,
     pub node_key_file: String,
+    /// Source of the passphrase of encrypted key files. If not set, the
+    /// passphrase is read from the `NEAR_KEY_PASSPHRASE` environment variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_passphrase: Option<KeyPassphraseSource>,
+    /// If set, the validator key is held by a remote signing service, and
+    /// `validator_key_file` is only used if the service allows falling back
+    /// to the local key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<RemoteSignerConfig>,
     #[cfg(feature = "json_rpc")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rpc: Option<RpcConfig>,
@@ -291,6 +323,11 @@ pub struct Config {
     pub view_client_threads: usize,
     #[serde(with = "near_async::time::serde_duration_as_std")]
     pub view_client_throttle_period: Duration,
+    /// If set, the state parts requested by the peers are served with per peer
+    /// rate limits, a cache of the recently generated parts and a limit of the
+    /// parts generated at the same time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_part_serving: Option<StatePartServingConfig>,
     pub trie_viewer_state_size_limit: Option<u64>,
     /// If set, overrides value in genesis configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -317,6 +354,12 @@ pub struct Config {
     pub state_sync: Option<StateSyncConfig>,
     /// Options for epoch sync
     pub epoch_sync: Option<EpochSyncConfig>,
+    /// Bootstrap mode for RPC nodes which only need the recent state and the
+    /// headers.  If set, the node state syncs to the latest epoch instead of
+    /// downloading and applying the blocks whenever it's more than
+    /// `block_sync_depth` blocks behind.  Can't be used by archival nodes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fast_bootstrap: Option<FastBootstrapConfig>,
     /// Limit of the size of per-shard transaction pool measured in bytes. If not set, the size
     /// will be unbounded.
     ///
@@ -330,6 +373,13 @@ pub struct Config {
     /// If the node is not a chunk producer within that many blocks, then route
     /// to upcoming chunk producers.
     pub tx_routing_height_horizon: BlockHeightDelta,
+    /// If set, the transactions submitted to the node and forwarded to other
+    /// chunk producers are rebroadcast to the upcoming chunk producers of their
+    /// shard at every block, until they are seen included or `num_blocks`
+    /// blocks have passed.  Their routing status is returned by the `tx` RPC
+    /// method.  Inclusion is only seen in the shards tracked by the node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_rebroadcast: Option<TxRebroadcastConfig>,
     /// Limit the time of adding transactions to a chunk.
     ///
     /// A node produces a chunk by adding transactions from the transaction pool until
@@ -338,6 +388,77 @@ pub struct Config {
     #[serde(default)]
     #[serde(with = "near_async::time::serde_opt_duration_as_std")]
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+    /// If true, the chunk producer speculatively validates and orders the pooled
+    /// transactions on top of the current head between its chunk production slots.
+    ///
+    /// The speculative result is reused if the chunk is produced on top of the
+    /// same head and is discarded as soon as the head changes.
+    #[serde(skip_serializing_if = "is_false")]
+    pub speculative_transaction_preparation: bool,
+    /// If true, blocks which don't become the head of the chain get their
+    /// flat storage deltas, transaction outcomes and chunk apply stats kept in
+    /// memory instead of written to the database.  The writes happen once the
+    /// fork is extended by another block and are dropped if the fork never
+    /// is, which saves IO during forky periods.
+    #[serde(skip_serializing_if = "is_false")]
+    pub defer_fork_postprocessing: bool,
+    /// If true, the node does all the work of its validator, including block,
+    /// chunk and state witness production, but never sends the results to the
+    /// network and doesn't claim the validator account on the network.  It
+    /// measures whether new hardware keeps up before moving the validator key
+    /// over to it.  The diverted messages are counted by the
+    /// `near_shadow_validation_diverted_messages_total` metric.
+    #[serde(skip_serializing_if = "is_false")]
+    pub shadow_validation: bool,
+    /// If true, the node checks that the receipts produced by the shards it
+    /// tracks are delivered exactly once to their target shards, with the
+    /// next chunk of the producing shard.  The receipts lost, delivered more
+    /// than once or delivered without being produced are shown by the
+    /// `/debug/api/receipt_audit` debug page and counted by the
+    /// `near_receipt_audit_discrepancies_total` metric.
+    #[serde(skip_serializing_if = "is_false")]
+    pub enable_receipt_audit: bool,
+    /// If true, the transactions in the transaction pool are saved to the
+    /// database when the node shuts down and loaded back when it starts, so
+    /// that a restart doesn't drop them.  They are validated again on top of
+    /// the head at startup, and the ones which expired or became invalid in
+    /// the meantime are dropped.
+    #[serde(skip_serializing_if = "is_false")]
+    pub persist_tx_pool: bool,
+    /// If set, the node indexes up to this many of the most recent
+    /// transactions of each signer, so that they can be listed with the
+    /// `EXPERIMENTAL_transactions_by_signer` RPC method.  Transactions are
+    /// dropped from the index when their chunks are garbage collected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transactions_by_signer_limit: Option<usize>,
+    /// If set, at most this many chunks of a block are applied at once, each
+    /// on a thread of the rayon pool.  By default all the tracked shards are
+    /// applied in parallel, which on nodes tracking many shards keeps the
+    /// state of all of them in memory at the same time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_shard_applies: Option<usize>,
+    /// If set, the transactions of the `priority_signers`, and the ones with a
+    /// priority fee of at least `min_priority_fee`, are included in the chunks
+    /// produced by the node before any other transaction of its pool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_priority: Option<TransactionPriorityConfig>,
+    /// If set, at most `max_transactions_per_signer` transactions of a single
+    /// signer are included in the chunks produced by the node. The limit of a
+    /// signer which keeps reaching it is halved in the following chunks, down
+    /// to `min_transactions_per_signer`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_admission: Option<TransactionAdmissionConfig>,
+    /// Preset of the data kept by the node.  With `"rpc-light"`, meant for RPC
+    /// nodes which never validate, the node deletes the data used only by
+    /// validators and the chunk extras of the old blocks on forks.
+    #[serde(skip_serializing_if = "NodeProfile::is_default")]
+    pub node_profile: NodeProfile,
+    /// If set, the current validators returned by the `validators` RPC method
+    /// include the number of delegators and the total stake delegated to their
+    /// staking pools.  The node then makes view calls to the staking pool
+    /// contracts once per epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validator_delegation_info: Option<ValidatorDelegationInfoConfig>,
     /// Optional config for the Chunk Distribution Network feature.
     ///
     /// If set to `None` then this node does not participate in the Chunk Distribution Network.
@@ -355,6 +476,9 @@ pub struct Config {
     /// This limits the maximum memory usage of OrphanStateWitnessPool.
     /// TODO(#10259) - consider merging this limit with the non-orphan witness size limit.
     pub orphan_state_witness_max_size: ByteSize,
+    /// Number of heights for which the chunk producer keeps the parts of the state witnesses
+    /// it distributed, so that chunk validators which missed them can request them again.
+    pub produced_state_witness_retention_heights: BlockHeightDelta,
     /// The number of the contracts kept loaded up for execution.
     ///
     /// Each loaded contract will increase the baseline memory use of the node appreciably.
@@ -378,6 +502,8 @@ impl Default for Config {
             genesis_records_file: None,
             validator_key_file: VALIDATOR_KEY_FILE.to_string(),
             node_key_file: NODE_KEY_FILE.to_string(),
+            key_passphrase: None,
+            remote_signer: None,
             #[cfg(feature = "json_rpc")]
             rpc: Some(RpcConfig::default()),
             #[cfg(feature = "rosetta_rpc")]
@@ -399,6 +525,7 @@ impl Default for Config {
             gc: GCConfig::default(),
             view_client_threads: default_view_client_threads(),
             view_client_throttle_period: default_view_client_throttle_period(),
+            state_part_serving: None,
             trie_viewer_state_size_limit: default_trie_viewer_state_size_limit(),
             max_gas_burnt_view: None,
             store: near_store::StoreConfig::default(),
@@ -408,16 +535,31 @@ impl Default for Config {
             expected_shutdown: None,
             state_sync: None,
             epoch_sync: default_epoch_sync(),
+            fast_bootstrap: None,
             state_sync_enabled: default_state_sync_enabled(),
             transaction_pool_size_limit: default_transaction_pool_size_limit(),
             enable_multiline_logging: default_enable_multiline_logging(),
             resharding_config: ReshardingConfig::default(),
             tx_routing_height_horizon: default_tx_routing_height_horizon(),
+            tx_rebroadcast: None,
             produce_chunk_add_transactions_time_limit:
                 default_produce_chunk_add_transactions_time_limit(),
+            speculative_transaction_preparation: false,
+            defer_fork_postprocessing: false,
+            shadow_validation: false,
+            enable_receipt_audit: false,
+            persist_tx_pool: false,
+            transactions_by_signer_limit: None,
+            max_concurrent_shard_applies: None,
+            transaction_priority: None,
+            transaction_admission: None,
+            node_profile: NodeProfile::Default,
+            validator_delegation_info: None,
             chunk_distribution_network: None,
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
+            produced_state_witness_retention_heights:
+                default_produced_state_witness_retention_heights(),
             max_loaded_contracts: 256,
             save_latest_witnesses: false,
             transaction_request_handler_threads: 4,
@@ -440,15 +582,8 @@ impl Config {
     /// Skips semantic validation on field values.
     /// This function should only return error for file issues.
     pub fn from_file_skip_validation(path: &Path) -> Result<Self, ValidationError> {
-        let json_str =
-            std::fs::read_to_string(path).map_err(|_| ValidationError::ConfigFileError {
-                error_message: format!("Failed to read config from {}", path.display()),
-            })?;
+        let json_str_without_comments = Self::read_json(path)?;
         let mut unrecognized_fields = Vec::new();
-        let json_str_without_comments = near_config_utils::strip_comments_from_json_str(&json_str)
-            .map_err(|_| ValidationError::ConfigFileError {
-                error_message: format!("Failed to strip comments from {}", path.display()),
-            })?;
         let config: Config = serde_ignored::deserialize(
             &mut serde_json::Deserializer::from_str(&json_str_without_comments),
             |field| unrecognized_fields.push(field.to_string()),
@@ -470,6 +605,49 @@ impl Config {
         Ok(config)
     }
 
+    /// Reads config.json and strips the comments from it.
+    fn read_json(path: &Path) -> Result<String, ValidationError> {
+        let json_str =
+            std::fs::read_to_string(path).map_err(|_| ValidationError::ConfigFileError {
+                error_message: format!("Failed to read config from {}", path.display()),
+            })?;
+        near_config_utils::strip_comments_from_json_str(&json_str).map_err(|_| {
+            ValidationError::ConfigFileError {
+                error_message: format!("Failed to strip comments from {}", path.display()),
+            }
+        })
+    }
+
+    /// Loads config.json as a layered config. In order of increasing priority,
+    /// the layers are the defaults, the file, the `NEAR_CONFIG__*` environment
+    /// variables if `env_overrides` is set, and `cli_overrides`.
+    ///
+    /// Fields missing from the file get the same defaults as when deserializing
+    /// the file directly. Overrides of the wrong type or of unknown fields are
+    /// errors, either here or when deserializing the result.
+    pub fn load_layered(
+        path: &Path,
+        env_overrides: bool,
+        cli_overrides: Vec<ConfigOverride>,
+    ) -> anyhow::Result<LayeredConfig> {
+        let file_config = Self::from_file_skip_validation(path)?;
+        let file_layer: serde_json::Value = serde_json::from_str(&Self::read_json(path)?)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let mut layered = LayeredConfig::new(serde_json::to_value(&file_config)?);
+        layered.apply_layer(ConfigSource::File, file_layer);
+        if env_overrides {
+            // `std::env::vars` would panic on variables which are not valid unicode.
+            layered.apply_env(std::env::vars_os().filter_map(|(name, value)| {
+                Some((name.into_string().ok()?, value.into_string().ok()?))
+            }))?;
+        }
+        for config_override in cli_overrides {
+            layered.apply_override(ConfigSource::Cli, config_override)?;
+        }
+        Ok(layered)
+    }
+
     fn validate(&self) -> Result<(), ValidationError> {
         crate::config_validate::validate_config(self)
     }
@@ -559,6 +737,8 @@ impl NearConfig {
                 max_block_production_delay: config.consensus.max_block_production_delay,
                 max_block_wait_delay: config.consensus.max_block_wait_delay,
                 chunk_wait_mult: config.consensus.chunk_wait_mult,
+                own_chunks_grace_period: config.consensus.own_chunks_grace_period,
+                block_production_jitter: config.consensus.block_production_jitter,
                 skip_sync_wait: config.network.skip_sync_wait,
                 sync_check_period: config.consensus.sync_check_period,
                 sync_step_period: config.consensus.sync_step_period,
@@ -593,6 +773,7 @@ impl NearConfig {
                 gc: config.gc,
                 view_client_threads: config.view_client_threads,
                 view_client_throttle_period: config.view_client_throttle_period,
+                state_part_serving: config.state_part_serving,
                 trie_viewer_state_size_limit: config.trie_viewer_state_size_limit,
                 max_gas_burnt_view: config.max_gas_burnt_view,
                 enable_statistics_export: config.store.enable_statistics_export,
@@ -600,6 +781,7 @@ impl NearConfig {
                 state_sync_enabled: config.state_sync_enabled,
                 state_sync: config.state_sync.unwrap_or_default(),
                 epoch_sync: config.epoch_sync.unwrap_or_default(),
+                fast_bootstrap: config.fast_bootstrap,
                 transaction_pool_size_limit: config.transaction_pool_size_limit,
                 enable_multiline_logging: config.enable_multiline_logging.unwrap_or(true),
                 resharding_config: MutableConfigValue::new(
@@ -607,13 +789,27 @@ impl NearConfig {
                     "resharding_config",
                 ),
                 tx_routing_height_horizon: config.tx_routing_height_horizon,
+                tx_rebroadcast: config.tx_rebroadcast,
                 produce_chunk_add_transactions_time_limit: MutableConfigValue::new(
                     config.produce_chunk_add_transactions_time_limit,
                     "produce_chunk_add_transactions_time_limit",
                 ),
+                speculative_transaction_preparation: config.speculative_transaction_preparation,
+                defer_fork_postprocessing: config.defer_fork_postprocessing,
+                shadow_validation: config.shadow_validation,
+                enable_receipt_audit: config.enable_receipt_audit,
+                persist_tx_pool: config.persist_tx_pool,
+                transactions_by_signer_limit: config.transactions_by_signer_limit,
+                max_concurrent_shard_applies: config.max_concurrent_shard_applies,
+                transaction_priority: config.transaction_priority,
+                transaction_admission: config.transaction_admission,
+                node_profile: config.node_profile,
+                validator_delegation_info: config.validator_delegation_info,
                 chunk_distribution_network: config.chunk_distribution_network,
                 orphan_state_witness_pool_size: config.orphan_state_witness_pool_size,
                 orphan_state_witness_max_size: config.orphan_state_witness_max_size,
+                produced_state_witness_retention_heights: config
+                    .produced_state_witness_retention_heights,
                 save_latest_witnesses: config.save_latest_witnesses,
                 transaction_request_handler_threads: config.transaction_request_handler_threads,
             },
@@ -622,7 +818,13 @@ impl NearConfig {
             network_config: NetworkConfig::new(
                 config.network,
                 network_key_pair.secret_key,
-                validator_signer.clone(),
+                // A shadow validator must not take over the account of the
+                // validator on the network.
+                if config.shadow_validation {
+                    MutableConfigValue::new(None, "validator_signer")
+                } else {
+                    validator_signer.clone()
+                },
                 config.archive,
             )?,
             telemetry_config: config.telemetry,
@@ -685,7 +887,8 @@ impl NightshadeRuntime {
                     home_dir,
                     config.config.store.path.as_ref().unwrap_or(&"data".into()),
                     "state_snapshot",
-                ),
+                )
+                .with_policy(config.config.store.state_snapshot_config.policy.clone()),
                 StateSnapshotType::Disabled => StateSnapshotConfig::Disabled,
             };
         // FIXME: this (and other contract runtime resources) should probably get constructed by
@@ -1395,49 +1598,125 @@ impl From<NodeKeyFile> for KeyFile {
     }
 }
 
-pub fn load_validator_key(validator_file: &Path) -> anyhow::Result<Option<Arc<ValidatorSigner>>> {
+/// Reads the node key, which may have an empty account id, decrypting it if
+/// it's encrypted.
+pub fn load_node_key(
+    node_key_file: &Path,
+    home_dir: &Path,
+    passphrase: &KeyPassphraseSource,
+) -> anyhow::Result<KeyFile> {
+    if let Ok(StoredKeyFile::Encrypted(key_file)) = StoredKeyFile::from_file(node_key_file) {
+        return Ok(key_file.decrypt(&passphrase.read(home_dir)?)?);
+    }
+    Ok(NodeKeyFile::from_file(node_key_file)?.into())
+}
+
+pub fn load_validator_key(
+    validator_file: &Path,
+    home_dir: &Path,
+    passphrase: &KeyPassphraseSource,
+) -> anyhow::Result<Option<Arc<ValidatorSigner>>> {
     if !validator_file.exists() {
         return Ok(None);
     }
-    match InMemoryValidatorSigner::from_file(&validator_file) {
-        Ok(signer) => Ok(Some(Arc::new(signer))),
-        Err(_) => {
-            let error_message =
-                format!("Failed initializing validator signer from {}", validator_file.display());
+    match unlock_key_file(validator_file, home_dir, passphrase) {
+        Ok(key_file) => {
+            let signer = InMemorySigner::from_secret_key(key_file.account_id, key_file.secret_key);
+            Ok(Some(Arc::new(InMemoryValidatorSigner::from_signer(signer))))
+        }
+        Err(err) => {
+            let error_message = format!(
+                "Failed initializing validator signer from {}: {err:#}",
+                validator_file.display()
+            );
             Err(anyhow!(error_message))
         }
     }
 }
 
+/// Loads the validator signer: the remote signer if it's configured,
+/// otherwise the validator key file.
+pub fn load_validator_signer(
+    home_dir: &Path,
+    config: &Config,
+) -> anyhow::Result<Option<Arc<ValidatorSigner>>> {
+    let passphrase = config.key_passphrase.clone().unwrap_or_default();
+    let validator_file = home_dir.join(&config.validator_key_file);
+    let Some(remote_signer_config) = &config.remote_signer else {
+        return load_validator_key(&validator_file, home_dir, &passphrase);
+    };
+    let local_signer = if remote_signer_config.fallback_to_local_key {
+        load_validator_key(&validator_file, home_dir, &passphrase)?
+    } else {
+        None
+    };
+    let remote_signer = RemoteSigner::new(remote_signer_config, local_signer)
+        .context("Failed initializing the remote signer")?;
+    Ok(Some(Arc::new(ExternalValidatorSigner::new(
+        remote_signer_config.account_id.clone(),
+        Arc::new(remote_signer),
+    ))))
+}
+
 pub fn load_config(
     dir: &Path,
     genesis_validation: GenesisValidationMode,
+) -> anyhow::Result<NearConfig> {
+    load_config_with_overrides(dir, genesis_validation, false, vec![])
+}
+
+/// Like `load_config`, with the environment overrides if `env_overrides` is set
+/// and `cli_overrides` applied on top of config.json, see `Config::load_layered`.
+/// Every overridden value is logged together with where it comes from.
+pub fn load_config_with_overrides(
+    dir: &Path,
+    genesis_validation: GenesisValidationMode,
+    env_overrides: bool,
+    cli_overrides: Vec<ConfigOverride>,
 ) -> anyhow::Result<NearConfig> {
     let mut validation_errors = ValidationErrors::new();
 
     // if config.json has file issues, the program will directly panic
-    let config = Config::from_file_skip_validation(&dir.join(CONFIG_FILENAME))?;
+    let layered = Config::load_layered(&dir.join(CONFIG_FILENAME), env_overrides, cli_overrides)?;
+    for (path, value, source) in layered.effective_values() {
+        if matches!(source, ConfigSource::Env | ConfigSource::Cli) {
+            info!(target: "near", %source, "Config value {path} overridden with {value}");
+        }
+    }
+    let config: Config = layered.deserialize().context("Failed to apply config overrides")?;
     // do config.json validation later so that genesis_file, validator_file and genesis_file can be validated before program panic
     if let Err(e) = config.validate() {
         validation_errors.push_errors(e)
     };
 
-    let validator_file: PathBuf = dir.join(&config.validator_key_file);
-    let validator_signer = match load_validator_key(&validator_file) {
+    let passphrase = config.key_passphrase.clone().unwrap_or_default();
+    let validator_signer = match load_validator_signer(dir, &config) {
         Ok(validator_signer) => validator_signer,
         Err(e) => {
             validation_errors.push_validator_key_file_error(e.to_string());
             None
         }
     };
+    if config.node_profile == NodeProfile::RpcLight && validator_signer.is_some() {
+        validation_errors.push_config_semantics_error(format!(
+            "'config.node_profile' \"rpc-light\" can't be used by a validator, remove the validator key {}",
+            dir.join(&config.validator_key_file).display()
+        ));
+    }
+    if config.fast_bootstrap.is_some() && validator_signer.is_some() {
+        validation_errors.push_config_semantics_error(format!(
+            "'config.fast_bootstrap' can't be used by a validator, remove the validator key {}",
+            dir.join(&config.validator_key_file).display()
+        ));
+    }
 
     let node_key_path = dir.join(&config.node_key_file);
-    let network_signer_result = NodeKeyFile::from_file(&node_key_path);
+    let network_signer_result = load_node_key(&node_key_path, dir, &passphrase);
     let network_signer = match network_signer_result {
         Ok(node_key_file) => Some(node_key_file),
-        Err(_) => {
+        Err(err) => {
             let error_message =
-                format!("Failed reading node key file from {}", node_key_path.display());
+                format!("Failed reading node key file from {}: {err:#}", node_key_path.display());
             validation_errors.push_node_key_file_error(error_message);
             None
         }
@@ -1476,7 +1755,7 @@ pub fn load_config(
     let near_config = NearConfig::new(
         config,
         genesis.unwrap(),
-        network_signer.unwrap().into(),
+        network_signer.unwrap(),
         MutableConfigValue::new(validator_signer, "validator_signer"),
     )?;
     Ok(near_config)
@@ -1515,7 +1794,9 @@ mod tests {
 
     use itertools::Itertools;
     use near_async::time::Duration;
-    use near_chain_configs::{GCConfig, Genesis, GenesisValidationMode, TrackedShardsConfig};
+    use near_chain_configs::{
+        ConfigOverride, ConfigSource, GCConfig, Genesis, GenesisValidationMode, TrackedShardsConfig,
+    };
     use near_crypto::InMemorySigner;
     use near_primitives::types::{AccountId, NumShards, ShardId};
     use near_store::ShardUId;
@@ -1524,6 +1805,7 @@ mod tests {
 
     use crate::config::{
         CONFIG_FILENAME, Config, create_localnet_configs, generate_or_load_key, init_configs,
+        load_config,
     };
 
     #[test]
@@ -1666,6 +1948,42 @@ mod tests {
         let _validator_signer = InMemorySigner::from_file(&validator_key_file).unwrap();
     }
 
+    #[test]
+    fn test_fast_bootstrap_validator_key() {
+        let temp_dir = tempdir().unwrap();
+        init_configs(
+            &temp_dir.path(),
+            Some("localnet".to_string()),
+            Some(AccountId::from_str("account.near").unwrap()),
+            Some("seed1"),
+            1,
+            false,
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let config_path = temp_dir.path().join(CONFIG_FILENAME);
+        let mut config = Config::from_file(&config_path).unwrap();
+        config.fast_bootstrap = Some(Default::default());
+        config.write_to_file(&config_path).unwrap();
+
+        let err = load_config(temp_dir.path(), GenesisValidationMode::UnsafeFast).unwrap_err();
+        assert!(
+            err.to_string().contains("'config.fast_bootstrap' can't be used by a validator"),
+            "{err}"
+        );
+
+        // Without the validator key the node doesn't validate.
+        std::fs::remove_file(temp_dir.path().join(&config.validator_key_file)).unwrap();
+        load_config(temp_dir.path(), GenesisValidationMode::UnsafeFast).unwrap();
+    }
+
     /// Tests that loading a config.json file works and results in values being
     /// correctly parsed and defaults being applied correctly applied.
     /// We skip config validation since we only care about Config being correctly loaded from file.
@@ -1713,6 +2031,54 @@ mod tests {
         }
     }
 
+    /// Loading through the layers must give the same config as reading the file
+    /// directly, with command line overrides applied on top.
+    #[test]
+    fn test_load_layered_config() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("res/example-config-gc.json");
+        let file_config = Config::from_file_skip_validation(&path).unwrap();
+
+        let layered = Config::load_layered(&path, false, vec![]).unwrap();
+        let config: Config = layered.deserialize().unwrap();
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            serde_json::to_value(&file_config).unwrap()
+        );
+        assert_eq!(layered.source_of("gc_blocks_limit"), Some(ConfigSource::File));
+
+        let layered = Config::load_layered(
+            &path,
+            false,
+            vec![
+                ConfigOverride::Set("gc_blocks_limit".to_string(), json!(7)),
+                ConfigOverride::Append("telemetry.endpoints".to_string(), json!("http://x")),
+            ],
+        )
+        .unwrap();
+        let config: Config = layered.deserialize().unwrap();
+        assert_eq!(config.gc.gc_blocks_limit, 7);
+        assert_eq!(config.telemetry.endpoints.last().unwrap(), "http://x");
+        assert_eq!(layered.source_of("gc_blocks_limit"), Some(ConfigSource::Cli));
+        assert_eq!(layered.source_of("gc_fork_clean_step"), Some(ConfigSource::File));
+
+        // Overrides which can't take effect are errors.
+        assert!(
+            Config::load_layered(
+                &path,
+                false,
+                vec![ConfigOverride::Set("gc_blocks_limit".to_string(), json!("7"))],
+            )
+            .is_err()
+        );
+        let layered = Config::load_layered(
+            &path,
+            false,
+            vec![ConfigOverride::Set("consensus.min_num_peerz".to_string(), json!(1))],
+        )
+        .unwrap();
+        assert!(layered.deserialize::<Config>().is_err());
+    }
+
     #[test]
     fn test_create_localnet_configs_track_single_shard() {
         let num_shards = 4;
@@ -28,7 +28,16 @@ fn test_config_duration_all_std() {
     // because there are Vec's. So it's best-effort.
     let config = Config {
         chunk_distribution_network: Some(Default::default()),
-        store: StoreConfig { path: Some(Default::default()), ..Default::default() },
+        consensus: crate::config::Consensus {
+            own_chunks_grace_period: Some(Default::default()),
+            block_production_jitter: Some(Default::default()),
+            ..Default::default()
+        },
+        store: StoreConfig {
+            path: Some(Default::default()),
+            trie_access_heat_map: Some(Default::default()),
+            ..Default::default()
+        },
         cold_store: Some(StoreConfig { path: Some(Default::default()), ..Default::default() }),
         enable_multiline_logging: Some(Default::default()),
         expected_shutdown: Some(Default::default()),
@@ -38,6 +47,7 @@ fn test_config_duration_all_std() {
         rpc: Some(RpcConfig {
             experimental_debug_pages_src_path: Some(Default::default()),
             prometheus_addr: Some(Default::default()),
+            audit_log: Some(Default::default()),
             ..Default::default()
         }),
         rosetta_rpc: Some(Default::default()),
@@ -49,6 +59,7 @@ fn test_config_duration_all_std() {
         state_sync: Some(Default::default()),
         trie_viewer_state_size_limit: Some(Default::default()),
         network: near_network::config_json::Config {
+            traffic_capture: Some(Default::default()),
             experimental: ExperimentalConfig {
                 network_config_overrides: NetworkConfigOverrides {
                     accounts_data_broadcast_rate_limit_burst: Some(0),
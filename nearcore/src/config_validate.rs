@@ -1,4 +1,4 @@
-use near_chain_configs::{ExternalStorageLocation, SyncConfig};
+use near_chain_configs::{ExternalStorageLocation, NodeProfile, SyncConfig};
 use near_config_utils::{ValidationError, ValidationErrors};
 use std::collections::HashSet;
 use std::path::Path;
@@ -171,6 +171,23 @@ impl<'a> ConfigValidator<'a> {
                         self.validation_errors.push_config_semantics_error(error_message);
                     }
                 }
+                SyncConfig::LocalDir(config) => {
+                    if !config.root_dir.is_dir() {
+                        let error_message = format!(
+                            "'config.state_sync.sync.LocalDir.root_dir' needs to be an existing directory, got {:?}.",
+                            config.root_dir
+                        );
+                        self.validation_errors.push_config_semantics_error(error_message);
+                    }
+                    if config.num_concurrent_requests == 0
+                        || config.num_concurrent_requests_during_catchup == 0
+                    {
+                        let error_message = format!(
+                            "'config.state_sync.sync.LocalDir.num_concurrent_requests' and 'config.state_sync.sync.LocalDir.num_concurrent_requests_during_catchup' need to be greater than 0"
+                        );
+                        self.validation_errors.push_config_semantics_error(error_message);
+                    }
+                }
             }
         }
 
@@ -187,7 +204,129 @@ impl<'a> ConfigValidator<'a> {
             );
             self.validation_errors.push_config_semantics_error(error_message);
         }
+        if let Some(tx_rebroadcast) = &self.config.tx_rebroadcast {
+            let num_chunk_producers = tx_rebroadcast.num_chunk_producers;
+            if num_chunk_producers == 0 || num_chunk_producers > 100 {
+                let error_message = format!(
+                    "'config.tx_rebroadcast.num_chunk_producers' needs to be between 1 and 100, got {num_chunk_producers}."
+                );
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+        if let Some(transaction_admission) = &self.config.transaction_admission {
+            let min = transaction_admission.min_transactions_per_signer;
+            let max = transaction_admission.max_transactions_per_signer;
+            if min == 0 || min > max {
+                let error_message = format!(
+                    "'config.transaction_admission.min_transactions_per_signer' needs to be between 1 and 'config.transaction_admission.max_transactions_per_signer', got {min} and {max}."
+                );
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+        if let Some(state_part_serving) = &self.config.state_part_serving {
+            let max_concurrent = state_part_serving.max_concurrent_part_generation;
+            let view_client_threads = self.config.view_client_threads;
+            if max_concurrent == 0 || max_concurrent > view_client_threads {
+                let error_message = format!(
+                    "'config.state_part_serving.max_concurrent_part_generation' needs to be between 1 and 'config.view_client_threads', got {max_concurrent} and {view_client_threads}."
+                );
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if state_part_serving.max_requests_per_peer == 0 {
+                let error_message =
+                    "'config.state_part_serving.max_requests_per_peer' needs to be greater than 0"
+                        .to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if state_part_serving.part_cache_size == 0 {
+                let error_message =
+                    "'config.state_part_serving.part_cache_size' needs to be greater than 0"
+                        .to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+        if self.config.max_concurrent_shard_applies == Some(0) {
+            let error_message =
+                "'config.max_concurrent_shard_applies' should be greater than 0 if set".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
         self.validate_tracked_shards_config();
+        self.validate_node_profile();
+        self.validate_validator_delegation_info();
+        self.validate_fast_bootstrap();
+        self.validate_split_storage();
+    }
+
+    fn validate_split_storage(&mut self) {
+        let Some(split_storage) = &self.config.split_storage else {
+            return;
+        };
+        if split_storage.enable_cold_read_through_view_client
+            && split_storage.enable_split_storage_view_client
+        {
+            let error_message = "'config.split_storage.enable_cold_read_through_view_client' and 'config.split_storage.enable_split_storage_view_client' cannot be both set, the split storage view client already reads from the cold store.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+    }
+
+    /// The validator key is checked when it's loaded, see
+    /// `load_config_with_overrides`.
+    fn validate_fast_bootstrap(&mut self) {
+        if self.config.fast_bootstrap.is_none() {
+            return;
+        }
+        if self.config.archive {
+            let error_message =
+                "'config.fast_bootstrap' can't be used by archival nodes, they don't state sync."
+                    .to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+        if !self.config.state_sync_enabled {
+            let error_message =
+                "'config.fast_bootstrap' requires 'config.state_sync_enabled' to be true."
+                    .to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+        if self
+            .config
+            .epoch_sync
+            .as_ref()
+            .is_some_and(|epoch_sync| epoch_sync.disable_epoch_sync_for_bootstrapping)
+        {
+            let error_message = "'config.fast_bootstrap' requires epoch sync, 'config.epoch_sync.disable_epoch_sync_for_bootstrapping' can't be true.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+        if self.config.remote_signer.is_some() {
+            let error_message = "'config.fast_bootstrap' can't be used with 'config.remote_signer', it is only meant for nodes which don't validate.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+    }
+
+    /// The validator key is checked when it's loaded, see
+    /// `load_config_with_overrides`.
+    fn validate_node_profile(&mut self) {
+        if self.config.node_profile != NodeProfile::RpcLight {
+            return;
+        }
+        if self.config.archive {
+            let error_message =
+                "'config.node_profile' \"rpc-light\" can't be used by archival nodes.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+        if self.config.remote_signer.is_some() {
+            let error_message = "'config.node_profile' \"rpc-light\" can't be used with 'config.remote_signer', it is only meant for nodes which don't validate.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+    }
+
+    fn validate_validator_delegation_info(&mut self) {
+        let Some(validator_delegation_info) = &self.config.validator_delegation_info else {
+            return;
+        };
+        if validator_delegation_info.staking_pool_code_hashes.is_empty() {
+            let error_message = "'config.validator_delegation_info.staking_pool_code_hashes' must not be empty, only the known staking pool contracts are queried.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
     }
 
     fn validate_tracked_shards_config(&mut self) {
@@ -224,7 +363,13 @@ impl<'a> ConfigValidator<'a> {
 
 #[cfg(test)]
 mod tests {
-    use near_chain_configs::TrackedShardsConfig;
+    use near_async::time::Duration;
+    use near_chain_configs::{
+        EpochSyncConfig, LocalDirSyncConfig, RemoteSignerConfig, StatePartServingConfig,
+        StateSyncConfig, TrackedShardsConfig, TxRebroadcastConfig, ValidatorDelegationInfoConfig,
+    };
+    use near_crypto::{KeyType, PublicKey};
+    use near_store::config::SplitStorageConfig;
 
     use super::*;
 
@@ -237,6 +382,66 @@ mod tests {
         validate_config(&config).unwrap();
     }
 
+    #[test]
+    #[should_panic(expected = "\"rpc-light\" can't be used by archival nodes")]
+    fn test_rpc_light_archive() {
+        let mut config = Config::default();
+        config.node_profile = NodeProfile::RpcLight;
+        config.archive = true;
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "'config.fast_bootstrap' can't be used by archival nodes")]
+    fn test_fast_bootstrap_archive() {
+        let mut config = Config::default();
+        config.fast_bootstrap = Some(Default::default());
+        config.archive = true;
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "'config.fast_bootstrap' requires epoch sync")]
+    fn test_fast_bootstrap_without_epoch_sync() {
+        let mut config = Config::default();
+        config.fast_bootstrap = Some(Default::default());
+        config.epoch_sync = Some(EpochSyncConfig {
+            disable_epoch_sync_for_bootstrapping: true,
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "'config.fast_bootstrap' can't be used with 'config.remote_signer'")]
+    fn test_fast_bootstrap_remote_signer() {
+        let mut config = Config::default();
+        config.fast_bootstrap = Some(Default::default());
+        config.remote_signer = Some(RemoteSignerConfig {
+            account_id: "test0".parse().unwrap(),
+            public_key: PublicKey::empty(KeyType::ED25519),
+            endpoints: vec!["https://signer:8443".to_string()],
+            timeout: Duration::seconds(1),
+            ca_cert_file: None,
+            client_cert_file: None,
+            client_key_file: None,
+            fallback_to_local_key: false,
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "'config.split_storage.enable_cold_read_through_view_client' and")]
+    fn test_cold_read_through_with_split_storage_view_client() {
+        let mut config = Config::default();
+        config.split_storage = Some(SplitStorageConfig {
+            enable_split_storage_view_client: true,
+            enable_cold_read_through_view_client: true,
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
+
     #[test]
     #[should_panic(expected = "gc config values should all be greater than 0")]
     fn test_gc_config_value_nonzero() {
@@ -245,6 +450,14 @@ mod tests {
         validate_config(&config).unwrap();
     }
 
+    #[test]
+    #[should_panic(expected = "'config.max_concurrent_shard_applies' should be greater than 0")]
+    fn test_max_concurrent_shard_applies_nonzero() {
+        let mut config = Config::default();
+        config.max_concurrent_shard_applies = Some(0);
+        validate_config(&config).unwrap();
+    }
+
     #[test]
     #[should_panic(
         expected = "Configuration with archive = false and save_trie_changes = false is not supported"
@@ -309,4 +522,56 @@ mod tests {
         config.tx_routing_height_horizon = 1_000_000_000;
         validate_config(&config).unwrap();
     }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.tx_rebroadcast.num_chunk_producers' needs to be between 1 and 100, got 0."
+    )]
+    fn test_tx_rebroadcast_no_chunk_producers() {
+        let mut config = Config::default();
+        config.tx_rebroadcast =
+            Some(TxRebroadcastConfig { num_blocks: 10, num_chunk_producers: 0 });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.state_part_serving.max_concurrent_part_generation' needs to be between 1 and 'config.view_client_threads', got 8 and 4."
+    )]
+    fn test_state_part_serving_too_many_concurrent_generations() {
+        let mut config = Config::default();
+        config.view_client_threads = 4;
+        config.state_part_serving = Some(StatePartServingConfig {
+            max_concurrent_part_generation: 8,
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "'config.validator_delegation_info.staking_pool_code_hashes' must not be empty"
+    )]
+    fn test_validator_delegation_info_no_code_hashes() {
+        let mut config = Config::default();
+        config.validator_delegation_info = Some(ValidatorDelegationInfoConfig::default());
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "'config.state_sync.sync.LocalDir.root_dir' needs to be an existing directory"
+    )]
+    fn test_state_sync_local_dir_missing() {
+        let mut config = Config::default();
+        config.state_sync = Some(StateSyncConfig {
+            dump: None,
+            sync: SyncConfig::LocalDir(LocalDirSyncConfig {
+                root_dir: "/nonexistent/state-parts".into(),
+                num_concurrent_requests: 1,
+                num_concurrent_requests_during_catchup: 1,
+            }),
+        });
+        validate_config(&config).unwrap();
+    }
 }
@@ -66,6 +66,7 @@ pub fn get_updatable_client_config(config: &Config) -> UpdatableClientConfig {
         expected_shutdown: config.expected_shutdown,
         resharding_config: config.resharding_config,
         produce_chunk_add_transactions_time_limit: config.produce_chunk_add_transactions_time_limit,
+        tracked_shards_config: Some(config.tracked_shards_config()),
     }
 }
 
@@ -110,7 +111,7 @@ fn read_validator_key(
     config: &Config,
 ) -> Result<Option<Arc<ValidatorSigner>>, UpdatableConfigLoaderError> {
     let validator_file: PathBuf = home_dir.join(&config.validator_key_file);
-    match crate::config::load_validator_key(&validator_file) {
+    match crate::config::load_validator_signer(home_dir, config) {
         Ok(Some(validator_signer)) => {
             tracing::info!(target: "neard", "Hot loading validator key {}.", validator_file.display());
             Ok(Some(validator_signer))
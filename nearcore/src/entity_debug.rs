@@ -12,6 +12,7 @@ use near_jsonrpc_primitives::types::entity_debug::{
     EntityDataStruct, EntityDataValue, EntityDebugHandler, EntityQuery, EntityQueryWithParams,
 };
 use near_primitives::block::Tip;
+use near_primitives::chunk_apply_stats::ChunkApplyStats;
 use near_primitives::congestion_info::CongestionInfo;
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::epoch_manager::AGGREGATOR_KEY;
@@ -467,6 +468,16 @@ impl EntityDebugHandlerImpl {
                 };
                 Ok(serialize_entity(&ret))
             }
+            EntityQuery::WitnessSizeByBlockHashShardId { block_hash, shard_id } => {
+                let stats = store
+                    .get_ser::<ChunkApplyStats>(
+                        DBCol::ChunkApplyStats,
+                        &get_block_shard_id(&block_hash, shard_id),
+                    )?
+                    .ok_or_else(|| anyhow!("Chunk apply stats not found"))?;
+                let ChunkApplyStats::V0(stats) = stats;
+                Ok(serialize_entity(&stats.witness_size))
+            }
         }
     }
 
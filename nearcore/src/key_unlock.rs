@@ -0,0 +1,187 @@
+//! Unlocking of the encrypted `node_key.json` and `validator_key.json`.
+//!
+//! Key files may have their secret key encrypted with a passphrase, see
+//! `near_crypto::EncryptedKeyFile`. The passphrase is read at startup, and
+//! when the validator key is reloaded, from the source configured with
+//! `key_passphrase` in config.json. A KMS is supported by a command which
+//! decrypts the passphrase and prints it, e.g. `aws kms decrypt`.
+
+use crate::config::{Config, load_node_key};
+use anyhow::{Context, bail};
+use near_crypto::{Argon2Params, KeyFile, StoredKeyFile};
+use std::path::{Path, PathBuf};
+
+/// Environment variable with the passphrase, used when `key_passphrase` isn't
+/// set in config.json.
+pub const KEY_PASSPHRASE_ENV_VAR: &str = "NEAR_KEY_PASSPHRASE";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyPassphraseSource {
+    /// Environment variable holding the passphrase.
+    Env(String),
+    /// File holding the passphrase, relative to the home directory. A trailing
+    /// newline is ignored.
+    File(PathBuf),
+    /// Command printing the passphrase to stdout, e.g. a KMS client. A
+    /// trailing newline is ignored.
+    Command(Vec<String>),
+}
+
+impl Default for KeyPassphraseSource {
+    fn default() -> Self {
+        Self::Env(KEY_PASSPHRASE_ENV_VAR.to_string())
+    }
+}
+
+impl KeyPassphraseSource {
+    pub fn read(&self, home_dir: &Path) -> anyhow::Result<Vec<u8>> {
+        let mut passphrase = match self {
+            Self::Env(var) => std::env::var(var)
+                .with_context(|| format!("key passphrase environment variable {var} isn't set"))?
+                .into_bytes(),
+            Self::File(path) => {
+                let path = home_dir.join(path);
+                std::fs::read(&path).with_context(|| {
+                    format!("failed to read the key passphrase from {}", path.display())
+                })?
+            }
+            Self::Command(command) => {
+                let Some((program, args)) = command.split_first() else {
+                    bail!("key passphrase command is empty");
+                };
+                let output = std::process::Command::new(program)
+                    .args(args)
+                    .current_dir(home_dir)
+                    .stderr(std::process::Stdio::inherit())
+                    .output()
+                    .with_context(|| {
+                        format!("failed to run the key passphrase command {program}")
+                    })?;
+                if !output.status.success() {
+                    bail!("key passphrase command {program} failed with {}", output.status);
+                }
+                output.stdout
+            }
+        };
+        if passphrase.ends_with(b"\n") {
+            passphrase.pop();
+            if passphrase.ends_with(b"\r") {
+                passphrase.pop();
+            }
+        }
+        if passphrase.is_empty() {
+            bail!("key passphrase is empty");
+        }
+        Ok(passphrase)
+    }
+}
+
+/// Reads the key file, decrypting it if it's encrypted.
+pub fn unlock_key_file(
+    path: &Path,
+    home_dir: &Path,
+    source: &KeyPassphraseSource,
+) -> anyhow::Result<KeyFile> {
+    let key_file = StoredKeyFile::from_file(path)
+        .with_context(|| format!("failed to read the key file {}", path.display()))?;
+    key_file
+        .unlock(|| source.read(home_dir).map_err(std::io::Error::other))
+        .with_context(|| format!("failed to unlock the key file {}", path.display()))
+}
+
+pub fn is_encrypted_key_file(path: &Path) -> bool {
+    matches!(StoredKeyFile::from_file(path), Ok(StoredKeyFile::Encrypted(_)))
+}
+
+/// Writes the key file encrypted with the passphrase to `path`.
+pub fn encrypt_key_file(
+    path: &Path,
+    key_file: &KeyFile,
+    passphrase: &[u8],
+    kdf: Argon2Params,
+) -> anyhow::Result<()> {
+    let encrypted = key_file.encrypt(passphrase, kdf)?;
+    // Check that the file can be unlocked before replacing the plain one.
+    encrypted.decrypt(passphrase)?;
+    encrypted
+        .write_to_file(path)
+        .with_context(|| format!("failed to write the key file {}", path.display()))
+}
+
+/// Encrypts the node and validator key files of the home directory which
+/// aren't encrypted yet, with the passphrase configured in `config`. Returns
+/// the paths of the encrypted files.
+pub fn encrypt_home_dir_keys(home_dir: &Path, config: &Config) -> anyhow::Result<Vec<PathBuf>> {
+    let source = config.key_passphrase.clone().unwrap_or_default();
+    let node_key_path = home_dir.join(&config.node_key_file);
+    let validator_key_path = home_dir.join(&config.validator_key_file);
+    let mut key_files = vec![];
+    if !is_encrypted_key_file(&node_key_path) {
+        key_files.push((node_key_path.clone(), load_node_key(&node_key_path, home_dir, &source)?));
+    }
+    if validator_key_path.exists() && !is_encrypted_key_file(&validator_key_path) {
+        let key_file = KeyFile::from_file(&validator_key_path).with_context(|| {
+            format!("failed to read the key file {}", validator_key_path.display())
+        })?;
+        key_files.push((validator_key_path, key_file));
+    }
+    if key_files.is_empty() {
+        return Ok(vec![]);
+    }
+    let passphrase = source.read(home_dir)?;
+    let mut encrypted = vec![];
+    for (path, key_file) in key_files {
+        encrypt_key_file(&path, &key_file, &passphrase, Argon2Params::default())?;
+        encrypted.push(path);
+    }
+    Ok(encrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{KeyType, SecretKey};
+
+    #[test]
+    fn test_read_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("passphrase"), "secret\n").unwrap();
+        let source = KeyPassphraseSource::File("passphrase".into());
+        assert_eq!(source.read(dir.path()).unwrap(), b"secret");
+
+        let source = KeyPassphraseSource::Command(vec!["echo".into(), "secret".into()]);
+        assert_eq!(source.read(dir.path()).unwrap(), b"secret");
+
+        let source = KeyPassphraseSource::Command(vec!["false".into()]);
+        assert!(source.read(dir.path()).is_err());
+
+        let source = KeyPassphraseSource::Env("NEAR_KEY_PASSPHRASE_TEST_UNSET".into());
+        assert!(source.read(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_and_unlock_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("passphrase"), "secret").unwrap();
+        let source = KeyPassphraseSource::File("passphrase".into());
+        let path = dir.path().join("validator_key.json");
+        let secret_key = SecretKey::from_random(KeyType::ED25519);
+        let key_file = KeyFile {
+            account_id: "test".parse().unwrap(),
+            public_key: secret_key.public_key(),
+            secret_key,
+        };
+        key_file.write_to_file(&path).unwrap();
+
+        assert!(!is_encrypted_key_file(&path));
+        let kdf = Argon2Params { m_cost: 64, t_cost: 1, p_cost: 1 };
+        encrypt_key_file(&path, &key_file, b"secret", kdf).unwrap();
+        assert!(is_encrypted_key_file(&path));
+        let unlocked = unlock_key_file(&path, dir.path(), &source).unwrap();
+        assert_eq!(unlocked.secret_key, key_file.secret_key);
+
+        std::fs::write(dir.path().join("passphrase"), "wrong").unwrap();
+        assert!(unlock_key_file(&path, dir.path(), &source).is_err());
+    }
+}
@@ -19,35 +19,39 @@ use near_chain::rayon_spawner::RayonAsyncComputationSpawner;
 use near_chain::resharding::resharding_actor::ReshardingActor;
 pub use near_chain::runtime::NightshadeRuntime;
 use near_chain::state_snapshot_actor::{
-    SnapshotCallbacks, StateSnapshotActor, get_delete_snapshot_callback, get_make_snapshot_callback,
+    SnapshotCallbacks, StateSnapshotActor, get_delete_snapshot_callback,
+    get_make_restore_point_callback, get_make_snapshot_callback,
 };
 use near_chain::types::RuntimeAdapter;
 use near_chain::{Chain, ChainGenesis};
-use near_chain_configs::ReshardingHandle;
+use near_chain_configs::{ClientConfig, ReshardingHandle};
 use near_chunks::shards_manager_actor::start_shards_manager;
 use near_client::adapter::client_sender_for_network;
 use near_client::gc_actor::GCActor;
 use near_client::{
-    ClientActor, ConfigUpdater, PartialWitnessActor, RpcHandlerActor, RpcHandlerConfig,
-    StartClientResult, ViewClientActor, ViewClientActorInner, spawn_rpc_handler_actor,
-    start_client,
+    ClientActor, ConfigUpdater, NewHeadEvent, PartialWitnessActor, RebroadcastPendingTxs,
+    RpcHandlerActor, RpcHandlerConfig, StartClientResult, TxPoolSaver, ViewClientActor,
+    ViewClientActorInner, shadow_validation_network_adapter, spawn_rpc_handler_actor, start_client,
 };
 use near_epoch_manager::EpochManager;
 use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_network::PeerManagerActor;
+use near_network::types::PeerManagerAdapter;
 use near_primitives::genesis::GenesisId;
 use near_primitives::types::EpochId;
 use near_store::db::metadata::DbKind;
 use near_store::genesis::initialize_sharded_genesis_state;
 use near_store::metrics::spawn_db_metrics_loop;
-use near_store::{NodeStorage, Store, StoreOpenerError};
+use near_store::migrations::MigrationReport;
+use near_store::{NodeStorage, Store, StoreOpenerError, Temperature};
 use near_telemetry::TelemetryActor;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 
 pub mod append_only_map;
+pub mod archive_reader;
 pub mod cold_storage;
 pub mod config;
 #[cfg(test)]
@@ -58,6 +62,7 @@ pub mod dyn_config;
 #[cfg(feature = "json_rpc")]
 pub mod entity_debug;
 mod entity_debug_serializer;
+pub mod key_unlock;
 mod metrics;
 pub mod migrations;
 pub mod state_sync;
@@ -126,7 +131,8 @@ pub fn open_storage(home_dir: &Path, near_config: &mut NearConfig) -> anyhow::Re
             Err(anyhow::anyhow!(
                 "Detected an existing database migration snapshot at ‘{}’.\n\
                  Probably a database migration got interrupted and your database is corrupted.\n\
-                 Please replace files in ‘{}’ with contents of the snapshot, delete the snapshot and try again.",
+                 Please replace files in ‘{}’ with contents of the snapshot, e.g. with \
+                 `neard database rollback-migration`, delete the snapshot and try again.",
                 snap_path.display(),
                 opener.path().display(),
             ))
@@ -185,6 +191,24 @@ pub fn open_storage(home_dir: &Path, near_config: &mut NearConfig) -> anyhow::Re
     Ok(storage)
 }
 
+/// Reports what the pending database migrations would change, without
+/// changing the database.
+pub fn dry_run_migrations(
+    home_dir: &Path,
+    near_config: &NearConfig,
+) -> anyhow::Result<Vec<(Temperature, MigrationReport)>> {
+    let migrator = migrations::Migrator::new(near_config);
+    let opener = NodeStorage::opener(
+        home_dir,
+        &near_config.config.store,
+        near_config.config.archival_config(),
+    )
+    .with_migrator(&migrator);
+    opener.dry_run_migrations().with_context(|| {
+        format!("unable to dry run the migrations of the database at {}", opener.path().display())
+    })
+}
+
 // Safely get the split store while checking that all conditions to use it are met.
 fn get_split_store(config: &NearConfig, storage: &NodeStorage) -> anyhow::Result<Option<Store>> {
     // SplitStore should only be used on archival nodes.
@@ -212,6 +236,46 @@ fn get_split_store(config: &NearConfig, storage: &NodeStorage) -> anyhow::Result
     Ok(storage.get_split_store())
 }
 
+// Get the store through which the view client reads the chain data garbage
+// collected from the hot store from the cold store, if it's enabled and the
+// view client doesn't use the split store.
+fn get_read_through_store(
+    config: &NearConfig,
+    storage: &NodeStorage,
+) -> anyhow::Result<Option<Store>> {
+    if !config.config.archive || config.config.cold_store.is_none() {
+        return Ok(None);
+    }
+    let Some(split_storage) = &config.config.split_storage else {
+        return Ok(None);
+    };
+    if split_storage.enable_split_storage_view_client
+        || !split_storage.enable_cold_read_through_view_client
+    {
+        return Ok(None);
+    }
+    Ok(storage.get_read_through_store()?)
+}
+
+/// Makes the RPC handler rebroadcast its pending transactions on every new head.
+fn spawn_tx_rebroadcast_loop(
+    mut head_events: broadcast::Receiver<NewHeadEvent>,
+    rpc_handler: Addr<RpcHandlerActor>,
+) {
+    let rebroadcast_sender = rpc_handler.with_auto_span_context().into_sender();
+    actix::spawn(async move {
+        loop {
+            match head_events.recv().await {
+                // Heads skipped by a lagging receiver need a single rebroadcast.
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    rebroadcast_sender.send(RebroadcastPendingTxs)
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
 pub struct NearNode {
     pub client: Addr<ClientActor>,
     pub view_client: Addr<ViewClientActor>,
@@ -232,6 +296,9 @@ pub struct NearNode {
     pub state_sync_runtime: Arc<tokio::runtime::Runtime>,
     /// Shard tracker, allows querying of which shards are tracked by this node.
     pub shard_tracker: ShardTracker,
+    /// Saves the transaction pool at shutdown, set if `persist_tx_pool` is
+    /// enabled.
+    pub tx_pool_saver: Option<TxPoolSaver>,
 }
 
 pub fn start_with_config(home_dir: &Path, config: NearConfig) -> anyhow::Result<NearNode> {
@@ -316,6 +383,7 @@ pub fn start_with_config_and_synchronization(
         } else {
             (epoch_manager.clone(), shard_tracker.clone(), runtime.clone())
         };
+    let read_through_store = get_read_through_store(&config, &storage)?;
 
     let cold_store_loop_handle = spawn_cold_store_loop(&config, &storage, epoch_manager.clone())?;
 
@@ -340,6 +408,12 @@ pub fn start_with_config_and_synchronization(
     let client_adapter_for_shards_manager = LateBoundSender::new();
     let client_adapter_for_partial_witness_actor = LateBoundSender::new();
     let adv = near_client::adversarial::Controls::new(config.client_config.archive);
+    // The network adapter of the actors doing the validator duties.
+    let validator_network_adapter: PeerManagerAdapter = if config.client_config.shadow_validation {
+        shadow_validation_network_adapter(network_adapter.as_multi_sender())
+    } else {
+        network_adapter.as_multi_sender()
+    };
 
     let view_client_addr = ViewClientActorInner::spawn_actix_actor(
         Clock::real(),
@@ -348,10 +422,32 @@ pub fn start_with_config_and_synchronization(
         view_epoch_manager.clone(),
         view_shard_tracker.clone(),
         view_runtime.clone(),
+        read_through_store.clone(),
         network_adapter.as_multi_sender(),
         config.client_config.clone(),
         adv.clone(),
     );
+    // With `state_part_serving`, the state requests of the peers wait in the
+    // queue of view client threads of their own, one per part which can be
+    // generated at the same time, instead of holding up the other requests.
+    let state_request_view_client_addr = match &config.client_config.state_part_serving {
+        Some(state_part_serving) => ViewClientActorInner::spawn_actix_actor(
+            Clock::real(),
+            config.validator_signer.clone(),
+            chain_genesis.clone(),
+            view_epoch_manager.clone(),
+            view_shard_tracker.clone(),
+            view_runtime.clone(),
+            read_through_store,
+            network_adapter.as_multi_sender(),
+            ClientConfig {
+                view_client_threads: state_part_serving.max_concurrent_part_generation,
+                ..config.client_config.clone()
+            },
+            adv.clone(),
+        ),
+        None => view_client_addr.clone(),
+    };
 
     let state_snapshot_sender = LateBoundSender::new();
     let state_snapshot_actor = StateSnapshotActor::new(
@@ -366,21 +462,29 @@ pub fn start_with_config_and_synchronization(
         state_snapshot_addr.clone().with_auto_span_context().into_multi_sender(),
     );
     let make_snapshot_callback = get_make_snapshot_callback(
-        state_snapshot_addr.with_auto_span_context().into_multi_sender(),
+        state_snapshot_addr.clone().with_auto_span_context().into_multi_sender(),
         runtime.get_flat_storage_manager(),
     );
-    let snapshot_callbacks = SnapshotCallbacks { make_snapshot_callback, delete_snapshot_callback };
+    let make_restore_point_callback = get_make_restore_point_callback(
+        state_snapshot_addr.with_auto_span_context().into_multi_sender(),
+    );
+    let snapshot_callbacks = SnapshotCallbacks {
+        make_snapshot_callback,
+        delete_snapshot_callback,
+        make_restore_point_callback,
+    };
 
     let (partial_witness_actor, partial_witness_arbiter) =
         spawn_actix_actor(PartialWitnessActor::new(
             Clock::real(),
-            network_adapter.as_multi_sender(),
+            validator_network_adapter.clone(),
             client_adapter_for_partial_witness_actor.as_multi_sender(),
             config.validator_signer.clone(),
             epoch_manager.clone(),
             runtime.clone(),
             Arc::new(RayonAsyncComputationSpawner),
             Arc::new(RayonAsyncComputationSpawner),
+            config.client_config.produced_state_witness_retention_heights,
         ));
 
     let (_gc_actor, gc_arbiter) = spawn_actix_actor(GCActor::new(
@@ -391,6 +495,7 @@ pub fn start_with_config_and_synchronization(
         shard_tracker.clone(),
         config.validator_signer.clone(),
         config.client_config.gc.clone(),
+        config.client_config.node_profile,
         config.client_config.archive,
     ));
 
@@ -411,6 +516,7 @@ pub fn start_with_config_and_synchronization(
         client_arbiter_handle,
         tx_pool,
         chunk_endorsement_tracker,
+        head_events,
     } = start_client(
         Clock::real(),
         config.client_config.clone(),
@@ -420,7 +526,7 @@ pub fn start_with_config_and_synchronization(
         runtime.clone(),
         node_id,
         state_sync_spawner.clone(),
-        network_adapter.as_multi_sender(),
+        validator_network_adapter.clone(),
         shards_manager_adapter.as_sender(),
         config.validator_signer.clone(),
         telemetry.with_auto_span_context().into_sender(),
@@ -439,7 +545,7 @@ pub fn start_with_config_and_synchronization(
         epoch_manager.clone(),
         view_epoch_manager.clone(),
         shard_tracker.clone(),
-        network_adapter.as_sender(),
+        validator_network_adapter.request_sender,
         client_adapter_for_shards_manager.as_sender(),
         config.validator_signer.clone(),
         split_store.unwrap_or_else(|| storage.get_hot_store()),
@@ -447,11 +553,16 @@ pub fn start_with_config_and_synchronization(
     );
     shards_manager_adapter.bind(shards_manager_actor.with_auto_span_context());
 
+    let tx_pool_saver = config
+        .client_config
+        .persist_tx_pool
+        .then(|| TxPoolSaver::new(runtime.store().clone(), tx_pool.clone()));
     let rpc_handler_config = RpcHandlerConfig {
         handler_threads: config.client_config.transaction_request_handler_threads,
         tx_routing_height_horizon: config.client_config.tx_routing_height_horizon,
         epoch_length: config.client_config.epoch_length,
         transaction_validity_period: config.genesis.config.transaction_validity_period,
+        tx_rebroadcast: config.client_config.tx_rebroadcast.clone(),
     };
     let rpc_handler = spawn_rpc_handler_actor(
         rpc_handler_config,
@@ -463,6 +574,9 @@ pub fn start_with_config_and_synchronization(
         view_runtime.clone(),
         network_adapter.as_multi_sender(),
     );
+    if config.client_config.tx_rebroadcast.is_some() {
+        spawn_tx_rebroadcast_loop(head_events.subscribe(), rpc_handler.clone());
+    }
 
     let mut state_sync_dumper = StateSyncDumper {
         clock: Clock::real(),
@@ -488,6 +602,7 @@ pub fn start_with_config_and_synchronization(
         client_sender_for_network(
             client_actor.clone(),
             view_client_addr.clone(),
+            state_request_view_client_addr,
             rpc_handler.clone(),
         ),
         network_adapter.as_multi_sender(),
@@ -512,6 +627,7 @@ pub fn start_with_config_and_synchronization(
             view_client_addr.clone().with_auto_span_context().into_multi_sender(),
             rpc_handler.clone().with_auto_span_context().into_multi_sender(),
             network_actor.into_multi_sender(),
+            Some(head_events),
             #[cfg(feature = "test_features")]
             _gc_actor.with_auto_span_context().into_multi_sender(),
             Arc::new(entity_debug_handler),
@@ -569,5 +685,6 @@ pub fn start_with_config_and_synchronization(
         resharding_handle,
         state_sync_runtime,
         shard_tracker,
+        tx_pool_saver,
     })
 }
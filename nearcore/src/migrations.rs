@@ -1,7 +1,7 @@
 use near_chain::{ChainStore, ChainStoreAccess};
 use near_primitives::utils::index_to_bytes;
 use near_store::db::metadata::{DB_VERSION, DbVersion};
-use near_store::migrations::BatchedStoreUpdate;
+use near_store::migrations::{BatchedStoreUpdate, MigrationContext};
 use near_store::{DBCol, Store};
 
 /// Fix an issue with block ordinal (#5761)
@@ -69,28 +69,32 @@ impl<'a> near_store::StoreMigrator for Migrator<'a> {
         }
     }
 
-    fn migrate(&self, store: &Store, version: DbVersion) -> anyhow::Result<()> {
+    fn migrate(&self, ctx: &mut MigrationContext, version: DbVersion) -> anyhow::Result<()> {
         match version {
             0..=31 => unreachable!(),
-            32 => near_store::migrations::migrate_32_to_33(store),
-            33 => {
-                near_store::migrations::migrate_33_to_34(store, self.config.client_config.archive)
-            }
-            34 => near_store::migrations::migrate_34_to_35(store),
+            32 => near_store::migrations::migrate_32_to_33(ctx),
+            33 => near_store::migrations::migrate_33_to_34(ctx, self.config.client_config.archive),
+            34 => near_store::migrations::migrate_34_to_35(ctx),
             35 => {
                 tracing::info!(target: "migrations", "Migrating DB version from 35 to 36. Flat storage data will be created on disk.");
                 tracing::info!(target: "migrations", "It will happen in parallel with regular block processing. ETA is 15h for RPC node and 2d for archival node.");
                 Ok(())
             }
-            36 => near_store::migrations::migrate_36_to_37(store),
-            37 => near_store::migrations::migrate_37_to_38(store),
-            38 => near_store::migrations::migrate_38_to_39(store),
-            39 => near_store::migrations::migrate_39_to_40(store),
-            40 => near_store::migrations::migrate_40_to_41(store),
-            41 => near_store::migrations::migrate_41_to_42(store),
-            42 => near_store::migrations::migrate_42_to_43(store),
+            36 => near_store::migrations::migrate_36_to_37(ctx),
+            37 => near_store::migrations::migrate_37_to_38(ctx),
+            38 => near_store::migrations::migrate_38_to_39(ctx),
+            39 => near_store::migrations::migrate_39_to_40(ctx),
+            40 => near_store::migrations::migrate_40_to_41(ctx),
+            41 => near_store::migrations::migrate_41_to_42(ctx),
+            42 => near_store::migrations::migrate_42_to_43(ctx),
             43 => Ok(()), // DBCol::ChunkApplyStats column added, no need to perform a migration
-            44 => near_store::migrations::migrate_44_to_45(store),
+            44 => near_store::migrations::migrate_44_to_45(ctx),
+            45 => near_chain::migrate_45_to_46(ctx),
+            46 => near_chain::migrate_46_to_47(
+                ctx,
+                self.config.client_config.transactions_by_signer_limit,
+            ),
+            47 => Ok(()), // DBCol::ChunkApplyJournal and DBCol::DeferredPostprocessing columns added
             DB_VERSION.. => unreachable!(),
         }
     }
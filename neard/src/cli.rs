@@ -1,8 +1,8 @@
-#[cfg(unix)]
 use anyhow::Context;
 use near_amend_genesis::AmendGenesisCommand;
-use near_chain_configs::{GenesisValidationMode, TrackedShardsConfig};
-use near_client::ConfigUpdater;
+use near_chain_configs::{ConfigOverride, GenesisValidationMode, TrackedShardsConfig};
+use near_chain_data::cli::ChainDataCommand;
+use near_client::{ClientActor, ConfigUpdater, DebugStatus, DebugStatusResponse, Status};
 use near_cold_store_tool::ColdStoreCommand;
 use near_config_utils::DownloadConfigType;
 use near_database_tool::commands::DatabaseCommand;
@@ -12,7 +12,9 @@ use near_flat_storage::commands::FlatStorageCommand;
 use near_fork_network::cli::ForkNetworkCommand;
 use near_jsonrpc_primitives::types::light_client::RpcLightClientExecutionProofResponse;
 use near_mirror::MirrorCommand;
-use near_network::tcp;
+use near_network::types::PeerInfo;
+use near_network_capture::NetworkCaptureCommand;
+use near_o11y::WithSpanContextExt;
 use near_o11y::tracing_subscriber::EnvFilter;
 use near_o11y::{
     BuildEnvFilterError, EnvFilterBuilder, default_subscriber,
@@ -22,7 +24,8 @@ use near_ping::PingCommand;
 use near_primitives::epoch_manager::EpochConfigStore;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::compute_root_from_path;
-use near_primitives::types::{Gas, NumSeats, NumShards, ProtocolVersion, ShardId};
+use near_primitives::types::{BlockHeight, Gas, NumSeats, NumShards, ProtocolVersion, ShardId};
+use near_primitives::views::SyncStatusView;
 use near_replay_archive_tool::ReplayArchiveCommand;
 use near_state_parts::cli::StatePartsCommand;
 use near_state_parts_dump_check::cli::StatePartsDumpCheckCommand;
@@ -37,6 +40,7 @@ use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::Receiver;
 use tracing::{debug, error, info, warn};
@@ -115,6 +119,9 @@ impl NeardCmd {
             NeardSubCommand::Mirror(cmd) => {
                 cmd.run()?;
             }
+            NeardSubCommand::NetworkCapture(cmd) => {
+                cmd.run()?;
+            }
             NeardSubCommand::AmendGenesis(cmd) => {
                 cmd.run()?;
             }
@@ -130,6 +137,23 @@ impl NeardCmd {
             NeardSubCommand::ValidateConfig(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
+            NeardSubCommand::Config(cmd) => {
+                cmd.run(&home_dir)?;
+            }
+            NeardSubCommand::EncryptKeys(cmd) => {
+                cmd.run(&home_dir)?;
+            }
+            NeardSubCommand::ArchiveReader(cmd) => {
+                cmd.run(&home_dir, genesis_validation)?;
+            }
+            NeardSubCommand::SyncFromEpoch(cmd) => {
+                cmd.run(
+                    &home_dir,
+                    genesis_validation,
+                    neard_cmd.opts.verbose_target(),
+                    &neard_cmd.opts.o11y,
+                )?;
+            }
             NeardSubCommand::UndoBlock(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
@@ -150,12 +174,18 @@ impl NeardCmd {
             NeardSubCommand::ReplayArchive(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
+            NeardSubCommand::ChainData(cmd) => {
+                cmd.run(&home_dir, genesis_validation)?;
+            }
             NeardSubCommand::DumpTestContracts(cmd) => {
                 cmd.run()?;
             }
             NeardSubCommand::DumpEpochConfigs(cmd) => {
                 cmd.run(&home_dir)?;
             }
+            NeardSubCommand::DumpRpcSchema(cmd) => {
+                cmd.run()?;
+            }
         };
         Ok(())
     }
@@ -233,6 +263,10 @@ pub(super) enum NeardSubCommand {
     /// from it, reproducing traffic and state as closely as possible.
     Mirror(MirrorCommand),
 
+    /// Read the peer message capture files written when
+    /// `network.traffic_capture` is set in config.json.
+    NetworkCapture(NetworkCaptureCommand),
+
     /// Amend a genesis/records file created by `dump-state`.
     AmendGenesis(AmendGenesisCommand),
 
@@ -248,6 +282,23 @@ pub(super) enum NeardSubCommand {
     /// validate config files including genesis.json and config.json
     ValidateConfig(ValidateConfigCommand),
 
+    /// Inspect the node configuration
+    Config(ConfigCommand),
+
+    /// Encrypt node_key.json and validator_key.json with the passphrase from
+    /// the `key_passphrase` source of config.json, `NEAR_KEY_PASSPHRASE` by
+    /// default
+    EncryptKeys(EncryptKeysCommand),
+
+    /// Serve the view RPC from existing home dirs in read-only mode, without
+    /// networking or block processing
+    ArchiveReader(ArchiveReaderCommand),
+
+    /// Bootstraps a new node from genesis with epoch sync against the given
+    /// boot nodes, followed by header and state sync, printing the progress.
+    /// Exits once the node is ready to sync blocks with `neard run`.
+    SyncFromEpoch(SyncFromEpochCommand),
+
     /// reset the head of the chain locally to the prev block of current head
     UndoBlock(UndoBlockCommand),
 
@@ -263,11 +314,19 @@ pub(super) enum NeardSubCommand {
     /// Replays the blocks in the chain from an archival node.
     ReplayArchive(ReplayArchiveCommand),
 
+    /// Export blocks, chunks and receipts into flat files for the mock node
+    /// and the benchmarks.
+    ChainData(ChainDataCommand),
+
     /// Placeholder for test contracts subcommand
     DumpTestContracts(DumpTestContractCommand),
 
     /// Dump hard-coded epoch configs into JSON files
     DumpEpochConfigs(DumpEpochConfigsCommand),
+
+    /// Dump the JSON schema of the RPC methods, their params, results and
+    /// errors, for the protocol version of this binary
+    DumpRpcSchema(DumpRpcSchemaCommand),
 }
 
 #[allow(unused)]
@@ -411,8 +470,10 @@ impl InitCmd {
     }
 }
 
-#[derive(clap::Parser)]
-pub(super) struct RunCmd {
+/// Command line overrides of config.json values, shared by `run` and
+/// `config show`.
+#[derive(clap::Args)]
+pub(super) struct ConfigOverrideArgs {
     /// Configure node to run as archival node which prevents deletion of old
     /// blocks.  This is a persistent setting; once client is started as
     /// archival node, it cannot be run in non-archival mode.
@@ -459,72 +520,119 @@ pub(super) struct RunCmd {
     max_gas_burnt_view: Option<Gas>,
 }
 
-impl RunCmd {
-    pub(super) fn run(
-        self,
-        home_dir: &Path,
-        genesis_validation: GenesisValidationMode,
-        verbose_target: Option<&str>,
-        o11y_opts: &near_o11y::Options,
-    ) {
-        // Load configs from home.
-        let mut near_config = nearcore::config::load_config(home_dir, genesis_validation)
-            .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
-
-        check_release_build(&near_config.client_config.chain_id);
-        check_kernel_params();
-
-        // Set current version in client config.
-        near_config.client_config.version = crate::neard_version();
-        // Override some parameters from command line.
+impl ConfigOverrideArgs {
+    /// Translates the flags into overrides of the corresponding config.json
+    /// fields. Fails on flag values the node couldn't use.
+    pub(super) fn into_config_overrides(self) -> anyhow::Result<Vec<ConfigOverride>> {
+        let mut overrides = vec![];
         if let Some(produce_empty_blocks) = self.produce_empty_blocks {
-            near_config.client_config.produce_empty_blocks = produce_empty_blocks;
+            overrides.push(ConfigOverride::Set(
+                "consensus.produce_empty_blocks".to_string(),
+                produce_empty_blocks.into(),
+            ));
         }
         if let Some(connect_to_reliable_peers_on_startup) =
             self.connect_to_reliable_peers_on_startup
         {
-            near_config.network_config.connect_to_reliable_peers_on_startup =
-                connect_to_reliable_peers_on_startup;
+            overrides.push(ConfigOverride::Set(
+                "network.experimental.network_config_overrides.connect_to_reliable_peers_on_startup"
+                    .to_string(),
+                connect_to_reliable_peers_on_startup.into(),
+            ));
         }
         if let Some(boot_nodes) = self.boot_nodes {
             if !boot_nodes.is_empty() {
-                near_config.network_config.peer_store.boot_nodes = boot_nodes
-                    .split(',')
-                    .map(|chunk| chunk.parse().expect("Failed to parse PeerInfo"))
-                    .collect();
+                for boot_node in boot_nodes.split(',') {
+                    PeerInfo::from_str(boot_node)
+                        .with_context(|| format!("invalid --boot-nodes entry {boot_node:?}"))?;
+                }
+                overrides
+                    .push(ConfigOverride::Set("network.boot_nodes".to_string(), boot_nodes.into()));
             }
         }
         if let Some(min_peers) = self.min_peers {
-            near_config.client_config.min_num_peers = min_peers;
+            overrides
+                .push(ConfigOverride::Set("consensus.min_num_peers".to_string(), min_peers.into()));
         }
         if let Some(network_addr) = self.network_addr {
-            near_config.network_config.node_addr =
-                Some(near_network::tcp::ListenerAddr::new(network_addr));
+            overrides.push(ConfigOverride::Set(
+                "network.addr".to_string(),
+                network_addr.to_string().into(),
+            ));
         }
         #[cfg(feature = "json_rpc")]
         if self.disable_rpc {
-            near_config.rpc_config = None;
-        } else {
+            overrides.push(ConfigOverride::Set("rpc".to_string(), Value::Null));
+        } else if self.rpc_addr.is_some() || self.rpc_prometheus_addr.is_some() {
+            // The rpc section may be missing from config.json, fill it in with
+            // the defaults first like `init` would.
+            let mut default_config = serde_json::to_value(nearcore::config::Config::default())
+                .expect("Config is serializable");
+            overrides.push(ConfigOverride::Insert("rpc".to_string(), default_config["rpc"].take()));
             if let Some(rpc_addr) = self.rpc_addr {
-                near_config.rpc_config.get_or_insert(Default::default()).addr =
-                    tcp::ListenerAddr::new(rpc_addr.parse().unwrap());
+                overrides.push(ConfigOverride::Set("rpc.addr".to_string(), rpc_addr.into()));
             }
             if let Some(rpc_prometheus_addr) = self.rpc_prometheus_addr {
-                near_config.rpc_config.get_or_insert(Default::default()).prometheus_addr =
-                    Some(rpc_prometheus_addr);
+                overrides.push(ConfigOverride::Set(
+                    "rpc.prometheus_addr".to_string(),
+                    rpc_prometheus_addr.into(),
+                ));
             }
         }
         if let Some(telemetry_url) = self.telemetry_url {
             if !telemetry_url.is_empty() {
-                near_config.telemetry_config.endpoints.push(telemetry_url);
+                overrides.push(ConfigOverride::Append(
+                    "telemetry.endpoints".to_string(),
+                    telemetry_url.into(),
+                ));
             }
         }
         if self.archive {
-            near_config.client_config.archive = true;
+            overrides.push(ConfigOverride::Set("archive".to_string(), true.into()));
         }
-        if self.max_gas_burnt_view.is_some() {
-            near_config.client_config.max_gas_burnt_view = self.max_gas_burnt_view;
+        if let Some(max_gas_burnt_view) = self.max_gas_burnt_view {
+            overrides.push(ConfigOverride::Set(
+                "max_gas_burnt_view".to_string(),
+                max_gas_burnt_view.into(),
+            ));
         }
+        Ok(overrides)
+    }
+}
+
+#[derive(clap::Parser)]
+pub(super) struct RunCmd {
+    #[clap(flatten)]
+    config_overrides: ConfigOverrideArgs,
+}
+
+impl RunCmd {
+    pub(super) fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+        verbose_target: Option<&str>,
+        o11y_opts: &near_o11y::Options,
+    ) {
+        let config_overrides = self
+            .config_overrides
+            .into_config_overrides()
+            .unwrap_or_else(|e| panic!("Invalid command line flags: {:#}", e));
+        // Load configs from home, with environment variables and command line
+        // flags taking precedence.
+        let mut near_config = nearcore::config::load_config_with_overrides(
+            home_dir,
+            genesis_validation,
+            true,
+            config_overrides,
+        )
+        .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+
+        check_release_build(&near_config.client_config.chain_id);
+        check_kernel_params();
+
+        // Set current version in client config.
+        near_config.client_config.version = crate::neard_version();
 
         #[cfg(feature = "sandbox")]
         {
@@ -566,6 +674,7 @@ impl RunCmd {
                 cold_store_loop_handle,
                 mut state_sync_dumper,
                 resharding_handle,
+                tx_pool_saver,
                 ..
             } = nearcore::start_with_config_and_synchronization(
                 home_dir,
@@ -596,6 +705,14 @@ impl RunCmd {
                 debug!(target: "neard", "{} server stopped", name);
             }))
             .await;
+            if let Some(tx_pool_saver) = tx_pool_saver {
+                match tx_pool_saver.save() {
+                    Ok(num_txs) => info!(target: "neard", num_txs, "Saved the transaction pool"),
+                    Err(err) => {
+                        error!(target: "neard", ?err, "Failed to save the transaction pool")
+                    }
+                }
+            }
             actix::System::current().stop();
             // Disable the subscriber to properly shutdown the tracer.
             near_o11y::reload(Some("error"), None, Some("off")).unwrap();
@@ -862,6 +979,27 @@ impl DumpEpochConfigsCommand {
     }
 }
 
+#[derive(clap::Parser)]
+pub(super) struct DumpRpcSchemaCommand {
+    /// File to write the schema to. If not provided, the schema is printed to
+    /// stdout.
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+impl DumpRpcSchemaCommand {
+    pub(super) fn run(self) -> anyhow::Result<()> {
+        let schema = near_jsonrpc_primitives::schema::rpc_schema();
+        let json = serde_json::to_string_pretty(&schema)?;
+        match self.output {
+            Some(path) => std::fs::write(&path, json)
+                .with_context(|| format!("failed to write the schema to {}", path.display()))?,
+            None => println!("{json}"),
+        }
+        Ok(())
+    }
+}
+
 #[derive(clap::Parser)]
 pub(super) struct ValidateConfigCommand {}
 
@@ -876,6 +1014,310 @@ impl ValidateConfigCommand {
     }
 }
 
+#[derive(clap::Parser)]
+pub(super) struct ConfigCommand {
+    #[clap(subcommand)]
+    subcmd: ConfigSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum ConfigSubCommand {
+    /// Print config.json, or the effective config after applying the
+    /// environment and command line overrides.
+    Show(ConfigShowCommand),
+}
+
+#[derive(clap::Parser)]
+struct ConfigShowCommand {
+    /// Print the effective config, i.e. config.json with the `NEAR_CONFIG__*`
+    /// environment variables and the given command line flags applied.
+    #[clap(long)]
+    effective: bool,
+    /// Instead of the JSON config, print each effective value together with
+    /// where it comes from (default, file, env or cli). Implies `--effective`.
+    #[clap(long)]
+    provenance: bool,
+    /// Flags of `neard run` overriding config.json values.
+    #[clap(flatten)]
+    config_overrides: ConfigOverrideArgs,
+}
+
+impl ConfigCommand {
+    pub(super) fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        match self.subcmd {
+            ConfigSubCommand::Show(cmd) => cmd.run(home_dir),
+        }
+    }
+}
+
+impl ConfigShowCommand {
+    fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let path = home_dir.join(nearcore::config::CONFIG_FILENAME);
+        if !self.effective && !self.provenance {
+            let config = nearcore::config::Config::from_file_skip_validation(&path)?;
+            println!("{}", serde_json::to_string_pretty(&config)?);
+            return Ok(());
+        }
+
+        let layered = nearcore::config::Config::load_layered(
+            &path,
+            true,
+            self.config_overrides.into_config_overrides()?,
+        )?;
+        // Only report configs the node would actually accept.
+        let config: nearcore::config::Config =
+            layered.deserialize().context("Failed to apply config overrides")?;
+        if self.provenance {
+            for (path, value, source) in layered.effective_values() {
+                println!("{path} = {value} ({source})");
+            }
+        } else {
+            println!("{}", serde_json::to_string_pretty(&config)?);
+        }
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser)]
+pub(super) struct EncryptKeysCommand {}
+
+impl EncryptKeysCommand {
+    pub(super) fn run(&self, home_dir: &Path) -> anyhow::Result<()> {
+        let path = home_dir.join(nearcore::config::CONFIG_FILENAME);
+        let config = nearcore::config::Config::from_file_skip_validation(&path)?;
+        let encrypted = nearcore::key_unlock::encrypt_home_dir_keys(home_dir, &config)?;
+        if encrypted.is_empty() {
+            println!("All key files are already encrypted");
+        }
+        for path in encrypted {
+            println!("Encrypted {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser)]
+pub(super) struct ArchiveReaderCommand {
+    /// Additional home dirs to serve next to `--home`. Each one is served on
+    /// the RPC address from its own config.json, so they must not overlap.
+    #[clap(long)]
+    additional_home: Vec<PathBuf>,
+}
+
+impl ArchiveReaderCommand {
+    pub(super) fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let home_dirs: Vec<PathBuf> =
+            std::iter::once(home_dir.to_path_buf()).chain(self.additional_home).collect();
+        let configs = home_dirs
+            .iter()
+            .map(|dir| {
+                nearcore::config::load_config(dir, genesis_validation)
+                    .with_context(|| format!("Error loading config from {}", dir.display()))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let sys = actix::System::new();
+        sys.block_on(async move {
+            let mut rpc_servers = Vec::new();
+            for (dir, config) in home_dirs.iter().zip(configs) {
+                let node = nearcore::archive_reader::start_archive_reader(dir, config)
+                    .with_context(|| {
+                        format!("Error starting archive reader for {}", dir.display())
+                    })?;
+                info!(target: "neard", home_dir = %dir.display(), "Serving archive");
+                rpc_servers.extend(node.rpc_servers);
+            }
+
+            tokio::signal::ctrl_c().await?;
+            warn!(target: "neard", "Ctrl+C, stopping...");
+            futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
+                server.stop(true).await;
+                debug!(target: "neard", "{} server stopped", name);
+            }))
+            .await;
+            actix::System::current().stop();
+            anyhow::Ok(())
+        })?;
+        sys.run()?;
+        info!(target: "neard", "Waiting for RocksDB to gracefully shutdown");
+        RocksDB::block_until_all_instances_are_dropped();
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser)]
+pub(super) struct SyncFromEpochCommand {
+    /// Boot nodes to bootstrap from in <node_key>@<ip_addr> format separated
+    /// by commas. They replace the boot nodes of config.json.
+    #[clap(long)]
+    boot_nodes: String,
+    /// Interval between the progress reports, in seconds.
+    #[clap(long, default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    progress_interval: u64,
+}
+
+impl SyncFromEpochCommand {
+    pub(super) fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+        verbose_target: Option<&str>,
+        o11y_opts: &near_o11y::Options,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.boot_nodes.is_empty(), "--boot-nodes must not be empty");
+        let mut near_config = nearcore::config::load_config_with_overrides(
+            home_dir,
+            genesis_validation,
+            false,
+            vec![ConfigOverride::Set("network.boot_nodes".to_string(), self.boot_nodes.into())],
+        )
+        .context("Error loading config")?;
+        // Epoch sync skips the history which an archival node must have.
+        anyhow::ensure!(
+            !near_config.config.archive,
+            "epoch sync can't bootstrap an archival node, use a node with `archive: false`"
+        );
+        near_config.client_config.version = crate::neard_version();
+        // Bootstrapping is the whole point of the command, whatever config.json says.
+        near_config.client_config.epoch_sync.disable_epoch_sync_for_bootstrapping = false;
+        let genesis_height = near_config.genesis.config.genesis_height;
+        let progress_interval = Duration::from_secs(self.progress_interval);
+
+        let (tx_crash, mut rx_crash) = broadcast::channel::<()>(16);
+        let sys = actix::System::new();
+        let result = sys.block_on(async move {
+            let _subscriber_guard = default_subscriber_with_opentelemetry(
+                make_env_filter(verbose_target).unwrap(),
+                o11y_opts,
+                near_config.client_config.chain_id.clone(),
+                near_config.network_config.node_key.public_key().clone(),
+                near_config.network_config.validator.account_id(),
+            )
+            .await
+            .global();
+
+            let nearcore::NearNode {
+                client,
+                rpc_servers,
+                cold_store_loop_handle,
+                mut state_sync_dumper,
+                resharding_handle,
+                ..
+            } = nearcore::start_with_config_and_synchronization(
+                home_dir,
+                near_config,
+                Some(tx_crash),
+                None,
+            )
+            .context("Error starting the node")?;
+
+            let result = tokio::select! {
+                result = wait_for_bootstrap(&client, genesis_height, progress_interval) => result,
+                sig = wait_for_interrupt_signal(home_dir, &mut rx_crash) => {
+                    Err(anyhow::anyhow!("{sig}, bootstrap interrupted"))
+                }
+            };
+            info!(target: "neard", "Stopping the node... this may take a few minutes.");
+            if let Some(handle) = cold_store_loop_handle {
+                handle.stop()
+            }
+            state_sync_dumper.stop_and_await();
+            resharding_handle.stop();
+            futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
+                server.stop(true).await;
+                debug!(target: "neard", "{} server stopped", name);
+            }))
+            .await;
+            actix::System::current().stop();
+            result
+        });
+        sys.run()?;
+        info!(target: "neard", "Waiting for RocksDB to gracefully shutdown");
+        RocksDB::block_until_all_instances_are_dropped();
+        result?;
+        println!("The node is bootstrapped, start it with `neard run`");
+        Ok(())
+    }
+}
+
+/// Prints the sync status of the client every `progress_interval` until it is
+/// done with epoch, header and state sync and moves on to block sync. The
+/// client reports no sync before it starts syncing too, so the bootstrap is
+/// only done once a sync step was seen or the head moved past genesis.
+async fn wait_for_bootstrap(
+    client: &actix::Addr<ClientActor>,
+    genesis_height: BlockHeight,
+    progress_interval: Duration,
+) -> anyhow::Result<()> {
+    let status = client
+        .send(Status { is_health_check: false, detailed: false }.with_span_context())
+        .await??;
+    // Epoch sync only bootstraps a node from genesis, an existing node would
+    // silently fall back to header sync.
+    anyhow::ensure!(
+        status.sync_info.latest_block_height == genesis_height,
+        "the node already has blocks up to height {}, epoch sync requires an empty home dir",
+        status.sync_info.latest_block_height
+    );
+
+    let mut interval = tokio::time::interval(progress_interval);
+    let mut synced = false;
+    loop {
+        interval.tick().await;
+        let DebugStatusResponse::SyncStatus(sync_status) =
+            client.send(DebugStatus::SyncStatus.with_span_context()).await??
+        else {
+            anyhow::bail!("unexpected response to the sync status request");
+        };
+        if !matches!(
+            sync_status,
+            SyncStatusView::AwaitingPeers
+                | SyncStatusView::BlockSync { .. }
+                | SyncStatusView::NoSync
+        ) {
+            synced = true;
+        }
+        match sync_status {
+            SyncStatusView::AwaitingPeers => println!("Waiting for peers"),
+            SyncStatusView::EpochSync { source_peer_height, source_peer_id, .. } => {
+                println!(
+                    "Epoch sync: requested the proof from {source_peer_id} at height {source_peer_height}"
+                )
+            }
+            SyncStatusView::EpochSyncDone => println!("Epoch sync done"),
+            SyncStatusView::HeaderSync { current_height, highest_height, .. } => {
+                println!("Header sync: {current_height} / {highest_height}")
+            }
+            SyncStatusView::StateSync(status) => {
+                let mut shards = status.shard_sync_status.into_iter().collect::<Vec<_>>();
+                shards.sort();
+                let shards = shards
+                    .iter()
+                    .map(|(shard_id, shard_status)| format!("{shard_id}: {shard_status}"))
+                    .collect::<Vec<_>>();
+                println!("State sync to {}: {}", status.sync_hash, shards.join(", "));
+            }
+            SyncStatusView::StateSyncDone => println!("State sync done"),
+            SyncStatusView::BlockSync { .. } | SyncStatusView::NoSync => {
+                if synced {
+                    return Ok(());
+                }
+                let status = client
+                    .send(Status { is_health_check: false, detailed: false }.with_span_context())
+                    .await??;
+                if status.sync_info.latest_block_height > genesis_height {
+                    return Ok(());
+                }
+                println!("Waiting for the sync to start");
+            }
+        }
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn normalize_whitespace(s: &str) -> String {
     s.split_whitespace().collect::<Vec<_>>().join(" ")
@@ -934,7 +1376,9 @@ fn check_kernel_params() {
 
 #[cfg(test)]
 mod tests {
-    use super::{CryptoHash, NeardCmd, NeardSubCommand, VerifyProofError, VerifyProofSubCommand};
+    use super::{
+        CryptoHash, NeardCmd, NeardSubCommand, RunCmd, VerifyProofError, VerifyProofSubCommand,
+    };
     use clap::Parser;
     use std::str::FromStr;
 
@@ -965,6 +1409,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sync_from_epoch_progress_interval() {
+        let parse = |interval: &str| {
+            NeardCmd::try_parse_from(&[
+                "test",
+                "sync-from-epoch",
+                "--boot-nodes=ed25519:7PGseFbWxvYVgZ89K1uTJKYoKetWs7BJtbyXDzfbAcqX@127.0.0.1:24567",
+                &format!("--progress-interval={interval}"),
+            ])
+        };
+        assert!(parse("0").is_err());
+        match parse("1").unwrap().subcmd {
+            NeardSubCommand::SyncFromEpoch(cmd) => assert_eq!(cmd.progress_interval, 1),
+            _ => panic!("incorrect subcommand"),
+        }
+    }
+
+    #[test]
+    fn run_boot_nodes() {
+        let overrides = |boot_nodes: &str| {
+            RunCmd::parse_from(&["run", &format!("--boot-nodes={boot_nodes}")])
+                .config_overrides
+                .into_config_overrides()
+        };
+        let boot_node = "ed25519:7PGseFbWxvYVgZ89K1uTJKYoKetWs7BJtbyXDzfbAcqX@127.0.0.1:24567";
+        assert_eq!(overrides(&format!("{boot_node},{boot_node}")).unwrap().len(), 1);
+        assert!(overrides(&format!("{boot_node},127.0.0.1:24567")).is_err());
+        assert!(overrides("ed25519:not-a-key@127.0.0.1:24567").is_err());
+    }
+
     #[test]
     fn verify_proof_test() {
         assert_eq!(
@@ -44,6 +44,10 @@ pub struct ExecutionResultState {
     current_account_balance: Balance,
     /// Storage usage of the current account at the moment
     current_storage_usage: StorageUsage,
+    /// Size of the contract linear memory when the execution finished.
+    peak_linear_memory: u64,
+    /// Peak memory held by the registers during the execution.
+    peak_host_memory: u64,
 }
 
 impl ExecutionResultState {
@@ -67,6 +71,8 @@ impl ExecutionResultState {
             return_data: ReturnData::None,
             current_account_balance,
             current_storage_usage,
+            peak_linear_memory: 0,
+            peak_host_memory: 0,
         }
     }
 
@@ -123,6 +129,8 @@ impl ExecutionResultState {
 
         let mut profile = self.gas_counter.profile_data();
         profile.compute_wasm_instruction_cost(burnt_gas);
+        profile.peak_linear_memory = self.peak_linear_memory;
+        profile.peak_host_memory = self.peak_host_memory;
         let compute_usage = profile.total_compute_usage(&self.config.ext_costs);
 
         VMOutcome {
@@ -262,6 +270,16 @@ impl<'a> VMLogic<'a> {
         &self.result_state.logs
     }
 
+    /// Records the memory high-water marks of the execution in the result
+    /// state, so that they end up in the profile of the outcome.
+    ///
+    /// Should be called by the runners once the contract method has returned,
+    /// with the final size of the guest linear memory in bytes.
+    pub fn record_memory_usage(&mut self, linear_memory: u64) {
+        self.result_state.peak_linear_memory = linear_memory;
+        self.result_state.peak_host_memory = self.registers.peak_memory_usage();
+    }
+
     #[cfg(test)]
     pub(super) fn config(&self) -> &Config {
         &self.config
@@ -12,6 +12,22 @@ fn test_one_register() {
     logic.assert_read_register(&[0, 1, 2], 0);
 }
 
+#[test]
+fn test_peak_register_memory_usage() {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut logic = logic_builder.build();
+
+    logic.wrapped_internal_write_register(0, &[0; 100]).unwrap();
+    logic.wrapped_internal_write_register(1, &[0; 50]).unwrap();
+    // Shrinking a register lowers the current usage but not the peak.
+    logic.wrapped_internal_write_register(0, &[0; 10]).unwrap();
+    logic.record_memory_usage(1 << 16);
+
+    let outcome = logic.compute_outcome();
+    assert_eq!(outcome.profile.peak_linear_memory, 1 << 16);
+    assert_eq!(outcome.profile.peak_host_memory, 100 + 50 + 2 * 8);
+}
+
 #[test]
 fn test_non_existent_register() {
     let mut logic_builder = VMLogicBuilder::default();
@@ -124,6 +124,9 @@ pub(super) struct Registers {
     /// (i.e. size of `u64`).  Total usage is sum over all registers.  This only
     /// approximates actual usage in memory.
     total_memory_usage: u64,
+
+    /// Highest value `total_memory_usage` has reached during the execution.
+    peak_memory_usage: u64,
 }
 
 impl Registers {
@@ -151,6 +154,11 @@ impl Registers {
         self.registers.get(&register_id).map(|data| &data[..])
     }
 
+    /// Returns the highest total memory usage of the registers so far.
+    pub(super) fn peak_memory_usage(&self) -> u64 {
+        self.peak_memory_usage
+    }
+
     /// Returns length of register with given index or None if no such register.
     pub(super) fn get_len(&self, register_id: u64) -> Option<u64> {
         self.registers.get(&register_id).map(|data| data.len() as u64)
@@ -224,6 +232,7 @@ impl Registers {
             return Err(HostError::MemoryAccessViolation.into());
         }
         self.total_memory_usage = usage;
+        self.peak_memory_usage = self.peak_memory_usage.max(usage);
         Ok(entry)
     }
 }
@@ -63,6 +63,11 @@ impl NearVmMemory {
         Ok(unsafe { core::slice::from_raw_parts_mut(ptr, len) })
     }
 
+    /// Returns the current size of the guest memory in bytes.
+    pub(super) fn size_in_bytes(&self) -> u64 {
+        self.0.size().bytes().0 as u64
+    }
+
     pub(super) fn vm(&self) -> VMMemory {
         VMMemory::new(self.0.clone(), None)
     }
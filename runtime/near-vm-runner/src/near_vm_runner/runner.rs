@@ -691,9 +691,14 @@ impl crate::PreparedContract for VMResult<PreparedContract> {
         };
         let config = Arc::clone(&result_state.config);
         let vmmemory = memory.vm();
+        let memory_handle = memory.clone();
         let mut logic = VMLogic::new(ext, context, fees_config, result_state, &mut memory);
         let import = build_imports(vmmemory, &mut logic, config, artifact.engine());
-        let result = match vm.run_method(&artifact, import, entrypoint)? {
+        let run_result = vm.run_method(&artifact, import, entrypoint)?;
+        logic.record_memory_usage(memory_handle.size_in_bytes());
+        // Release the extra reference so that the memory below is still dropped lazily.
+        drop(memory_handle);
+        let result = match run_result {
             Ok(()) => Ok(VMOutcome::ok(logic.result_state)),
             Err(err) => Ok(VMOutcome::abort(logic.result_state, err)),
         };
@@ -14,6 +14,18 @@ pub struct ProfileDataV3 {
     pub wasm_ext_profile: EnumMap<ExtCosts, Gas>,
     /// Gas spent on execution inside the WASM VM.
     pub wasm_gas: Gas,
+    /// Size of the contract linear memory at the end of the call, in bytes.
+    ///
+    /// Wasm memory can only grow, so this is also its high-water mark.
+    ///
+    /// Not part of the borsh representation, the memory high-water marks are
+    /// only kept in memory for metrics and debugging.
+    pub peak_linear_memory: u64,
+    /// Peak amount of memory held in registers by the host on behalf of the
+    /// contract, in bytes. Counted the same way as `registers_memory_limit`.
+    ///
+    /// Not part of the borsh representation, see `peak_linear_memory`.
+    pub peak_host_memory: u64,
 }
 
 impl Default for ProfileDataV3 {
@@ -29,6 +41,8 @@ impl ProfileDataV3 {
             actions_profile: enum_map! { _ => 0 },
             wasm_ext_profile: enum_map! { _ => 0 },
             wasm_gas: 0,
+            peak_linear_memory: 0,
+            peak_host_memory: 0,
         }
     }
 
@@ -57,6 +71,8 @@ impl ProfileDataV3 {
             *gas = gas.saturating_add(*other_gas);
         }
         self.wasm_gas = self.wasm_gas.saturating_add(other.wasm_gas);
+        self.peak_linear_memory = self.peak_linear_memory.max(other.peak_linear_memory);
+        self.peak_host_memory = self.peak_host_memory.max(other.peak_host_memory);
     }
 
     #[inline]
@@ -147,7 +163,13 @@ impl BorshDeserialize for ProfileDataV3 {
             cost => ext_array.get(borsh_ext_index(cost)).copied().unwrap_or(0)
         };
 
-        Ok(Self { actions_profile, wasm_ext_profile, wasm_gas })
+        Ok(Self {
+            actions_profile,
+            wasm_ext_profile,
+            wasm_gas,
+            peak_linear_memory: 0,
+            peak_host_memory: 0,
+        })
     }
 }
 
@@ -226,6 +248,11 @@ impl fmt::Debug for ProfileDataV3 {
                 writeln!(f, "{} -> {}", cost, d)?;
             }
         }
+        if self.peak_linear_memory != 0 || self.peak_host_memory != 0 {
+            writeln!(f, "------ Memory --------")?;
+            writeln!(f, "peak linear memory -> {} bytes", self.peak_linear_memory)?;
+            writeln!(f, "peak host memory -> {} bytes", self.peak_host_memory)?;
+        }
         writeln!(f, "------------------------------")?;
         Ok(())
     }
@@ -388,6 +415,27 @@ mod test {
         assert_eq!(profile_data.get_ext_cost(ExtCosts::storage_read_base), 33);
     }
 
+    #[test]
+    fn test_merge_memory_high_water_marks() {
+        let mut profile_data = ProfileDataV3::default();
+        profile_data.peak_linear_memory = 1 << 20;
+        profile_data.peak_host_memory = 100;
+
+        let mut profile_data2 = ProfileDataV3::default();
+        profile_data2.peak_linear_memory = 1 << 16;
+        profile_data2.peak_host_memory = 200;
+
+        profile_data.merge(&profile_data2);
+        assert_eq!(profile_data.peak_linear_memory, 1 << 20);
+        assert_eq!(profile_data.peak_host_memory, 200);
+
+        // Memory usage is not persisted.
+        let buf = borsh::to_vec(&profile_data).unwrap();
+        let restored: ProfileDataV3 = BorshDeserialize::deserialize(&mut buf.as_slice()).unwrap();
+        assert_eq!(restored.peak_linear_memory, 0);
+        assert_eq!(restored.peak_host_memory, 0);
+    }
+
     #[test]
     fn test_total_compute_usage() {
         let ext_costs_config = ExtCostsConfig::test_with_undercharging_factor(3);
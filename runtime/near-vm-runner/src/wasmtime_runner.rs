@@ -394,10 +394,16 @@ impl crate::PreparedContract for VMResult<PreparedContract> {
         match linker.instantiate(&mut store, &module) {
             Ok(instance) => match instance.get_func(&mut store, &method) {
                 Some(func) => match func.typed::<(), ()>(&mut store) {
-                    Ok(run) => match run.call(&mut store, ()) {
-                        Ok(_) => Ok(VMOutcome::ok(logic.result_state)),
-                        Err(err) => Ok(VMOutcome::abort(logic.result_state, err.into_vm_error()?)),
-                    },
+                    Ok(run) => {
+                        let call_result = run.call(&mut store, ());
+                        logic.record_memory_usage(memory_copy.data_size(&store) as u64);
+                        match call_result {
+                            Ok(_) => Ok(VMOutcome::ok(logic.result_state)),
+                            Err(err) => {
+                                Ok(VMOutcome::abort(logic.result_state, err.into_vm_error()?))
+                            }
+                        }
+                    }
                     Err(err) => Ok(VMOutcome::abort(logic.result_state, err.into_vm_error()?)),
                 },
                 None => {
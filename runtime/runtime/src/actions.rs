@@ -206,6 +206,13 @@ pub(crate) fn action_function_call(
         None,
     )?;
 
+    metrics::report_function_call_memory_usage(
+        account_id,
+        &apply_state.shard_id.to_string(),
+        apply_state.epoch_height,
+        &outcome.profile,
+        &config.wasm_config.limit_config,
+    );
     match &outcome.aborted {
         None => {
             metrics::FUNCTION_CALL_PROCESSED.with_label_values(&["ok"]).inc();
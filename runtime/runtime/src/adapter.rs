@@ -2,11 +2,12 @@ use crate::near_primitives::shard_layout::ShardUId;
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
-    AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
+    AccountId, Balance, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::ViewStateResult;
+use near_primitives::views::{StateOverrideView, ViewStatePagedResult, ViewStateResult};
 use near_vm_runner::ContractCode;
 
 /// Adapter for querying runtime.
@@ -43,6 +44,29 @@ pub trait ViewRuntimeAdapter {
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, crate::state_viewer::errors::CallFunctionError>;
 
+    /// Applies `signed_tx` on top of `state_root` without persisting anything.
+    /// See [`crate::state_viewer::TrieViewer::dry_run_transaction`].
+    fn dry_run_transaction(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        height: BlockHeight,
+        block_timestamp: u64,
+        last_block_hash: &CryptoHash,
+        block_hash: &CryptoHash,
+        epoch_height: EpochHeight,
+        epoch_id: &EpochId,
+        gas_price: Balance,
+        signed_tx: SignedTransaction,
+        skip_signature_check: bool,
+        state_overrides: &[StateOverrideView],
+        epoch_info_provider: &dyn EpochInfoProvider,
+        current_protocol_version: ProtocolVersion,
+    ) -> Result<
+        crate::state_viewer::DryRunResult,
+        crate::state_viewer::errors::DryRunTransactionError,
+    >;
+
     fn view_access_key(
         &self,
         shard_uid: &ShardUId,
@@ -58,12 +82,29 @@ pub trait ViewRuntimeAdapter {
         account_id: &AccountId,
     ) -> Result<Vec<(PublicKey, AccessKey)>, crate::state_viewer::errors::ViewAccessKeyError>;
 
+    /// `block_hash` is the block whose post-state `state_root` is; it lets the
+    /// contract data be read from flat storage when that is possible.
     fn view_state(
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
     ) -> Result<ViewStateResult, crate::state_viewer::errors::ViewStateError>;
+
+    /// Reads a page of the contract data, see
+    /// [`crate::state_viewer::TrieViewer::view_state_paged`].
+    fn view_state_paged(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        block_hash: &CryptoHash,
+        account_id: &AccountId,
+        prefix: &[u8],
+        start_key: &[u8],
+        limit: std::num::NonZeroU32,
+        include_proof: bool,
+    ) -> Result<ViewStatePagedResult, crate::state_viewer::errors::ViewStateError>;
 }
@@ -9,7 +9,9 @@ use near_parameters::{ActionCosts, RuntimeConfig};
 use near_primitives::bandwidth_scheduler::{
     BandwidthRequest, BandwidthRequests, BandwidthRequestsV1, BandwidthSchedulerParams,
 };
-use near_primitives::chunk_apply_stats::{ChunkApplyStatsV0, ReceiptSinkStats, ReceiptsStats};
+use near_primitives::chunk_apply_stats::{
+    ChunkApplyStatsV0, OutgoingReceiptStats, ReceiptSinkStats, ReceiptsStats,
+};
 use near_primitives::congestion_info::{CongestionControl, CongestionInfo, CongestionInfoV1};
 use near_primitives::errors::{EpochError, IntegerOverflowError, RuntimeError};
 use near_primitives::receipt::{
@@ -339,6 +341,14 @@ impl ReceiptSinkV2 {
         let shard = receipt.receiver_shard_id(&shard_layout)?;
         let size = compute_receipt_size(&receipt)?;
         let gas = compute_receipt_congestion_gas(&receipt, &apply_state.config)?;
+        let mut receipt_stats = OutgoingReceiptStats {
+            receipt_id: *receipt.receipt_id(),
+            predecessor_id: receipt.predecessor_id().clone(),
+            receiver_id: receipt.receiver_id().clone(),
+            receiver_shard_id: shard,
+            size,
+            forwarded: true,
+        };
 
         match Self::try_forward(
             receipt,
@@ -360,8 +370,10 @@ impl ReceiptSinkV2 {
                     shard,
                     apply_state.config.use_state_stored_receipt,
                 )?;
+                receipt_stats.forwarded = false;
             }
         }
+        self.stats.new_outgoing_receipts.push(receipt_stats);
         Ok(())
     }
 
@@ -42,9 +42,10 @@ use near_primitives::receipt::{
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::state_record::StateRecord;
 use near_primitives::stateless_validation::contract_distribution::ContractUpdates;
+use near_primitives::stateless_validation::witness_size::{StorageProofSource, WitnessSizeStats};
 use near_primitives::transaction::{
-    Action, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus, LogEntry,
-    SignedTransaction, TransferAction, ValidatedTransaction,
+    Action, ExecutionMetadata, ExecutionMetadataV4, ExecutionOutcome, ExecutionOutcomeWithId,
+    ExecutionStatus, LogEntry, SignedTransaction, TransferAction, ValidatedTransaction,
 };
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
@@ -287,11 +288,30 @@ pub struct GasRefundResult {
     pub refund_penalty: Balance,
 }
 
-pub struct Runtime {}
+pub struct Runtime {
+    /// Accounts whose action receipts fail with `ActionErrorKind::AccountPaused`,
+    /// see `GenesisConfig::paused_accounts`.
+    paused_accounts: HashSet<AccountId>,
+}
 
 impl Runtime {
     pub fn new() -> Self {
-        Self {}
+        Self { paused_accounts: HashSet::new() }
+    }
+
+    pub fn with_paused_accounts(
+        mut self,
+        paused_accounts: impl IntoIterator<Item = AccountId>,
+    ) -> Self {
+        self.paused_accounts = paused_accounts.into_iter().collect();
+        self
+    }
+
+    /// Refunds are still applied to the paused accounts, so that the failed
+    /// receipts sent by them don't burn their deposits.
+    fn is_paused(&self, receipt: &Receipt) -> bool {
+        self.paused_accounts.contains(receipt.receiver_id())
+            && !receipt.predecessor_id().is_system()
     }
 
     fn print_log(log: &[LogEntry]) {
@@ -362,6 +382,7 @@ impl Runtime {
     ) -> Result<(Receipt, ExecutionOutcomeWithId), InvalidTxError> {
         let span = tracing::Span::current();
         metrics::TRANSACTION_PROCESSED_TOTAL.inc();
+        let storage_proof_size_before = state_update.trie().recorded_storage_size_upper_bound();
         let (mut signer, mut access_key) = get_signer_and_access_key(state_update, &validated_tx)?;
 
         let verification_result = verify_and_charge_tx_ephemeral(
@@ -411,6 +432,26 @@ impl Runtime {
         }
         let gas_burnt = verification_result.gas_burnt;
         let compute_usage = verification_result.gas_burnt;
+        let storage_proof_size =
+            state_update.trie().recorded_storage_size_upper_bound() - storage_proof_size_before;
+        stats.witness_size.record(
+            StorageProofSource::Transaction,
+            validated_tx.get_hash(),
+            validated_tx.signer_id().clone(),
+            storage_proof_size as u64,
+        );
+        // TODO: profile data is only counted in apply_action, which only happened at process_receipt
+        // VerificationResult needs updates to incorporate profile data to support profile data of txns
+        let metadata = if ProtocolFeature::PerReceiptStorageProofLimit
+            .enabled(apply_state.current_protocol_version)
+        {
+            ExecutionMetadata::V4(Box::new(ExecutionMetadataV4 {
+                profile: None,
+                storage_proof_size: storage_proof_size as u64,
+            }))
+        } else {
+            ExecutionMetadata::V1
+        };
         let outcome = ExecutionOutcomeWithId {
             id: validated_tx.get_hash(),
             outcome: ExecutionOutcome {
@@ -422,9 +463,7 @@ impl Runtime {
                 compute_usage: Some(compute_usage),
                 tokens_burnt: verification_result.burnt_amount,
                 executor_id: validated_tx.signer_id().clone(),
-                // TODO: profile data is only counted in apply_action, which only happened at process_receipt
-                // VerificationResult needs updates to incorporate profile data to support profile data of txns
-                metadata: ExecutionMetadata::V1,
+                metadata,
             },
         };
         span.record("gas_burnt", gas_burnt);
@@ -646,6 +685,9 @@ impl Runtime {
             }
             _ => unreachable!("given receipt should be an action receipt"),
         };
+        let storage_proof_size_before = state_update.trie().recorded_storage_size_upper_bound();
+        let limit_storage_proof_size = ProtocolFeature::PerReceiptStorageProofLimit
+            .enabled(apply_state.current_protocol_version);
         let account_id = receipt.receiver_id();
         // Collecting input data and removing it from the state
         let promise_results = action_receipt
@@ -684,48 +726,76 @@ impl Runtime {
         // TODO(#8806): Support compute costs for actions. For now they match burnt gas.
         result.compute_usage = exec_fees;
 
-        // Executing actions one by one
-        for (action_index, action) in action_receipt.actions.iter().enumerate() {
-            let action_hash = create_action_hash_from_receipt_id(
-                apply_state.current_protocol_version,
-                receipt.receipt_id(),
-                &apply_state.block_hash,
-                apply_state.block_height,
-                action_index,
-            );
-            let mut new_result = self.apply_action(
-                action,
-                state_update,
-                apply_state,
-                preparation_pipeline,
-                &mut account,
-                &mut actor_id,
-                receipt,
-                action_receipt,
-                Arc::clone(&promise_results),
-                &action_hash,
-                action_index,
-                &action_receipt.actions,
-                epoch_info_provider,
-                stats,
-            )?;
-            if new_result.result.is_ok() {
-                if let Err(e) = new_result.new_receipts.iter().try_for_each(|receipt| {
-                    validate_receipt(
-                        &apply_state.config.wasm_config.limit_config,
-                        receipt,
-                        apply_state.current_protocol_version,
-                        ValidateReceiptMode::NewReceipt,
-                    )
-                }) {
-                    new_result.result = Err(ActionErrorKind::NewReceiptValidationError(e).into());
+        if self.is_paused(receipt) {
+            metrics::PAUSED_ACCOUNT_RECEIPTS_TOTAL.inc();
+            result.result = Err(ActionError {
+                index: Some(0),
+                kind: ActionErrorKind::AccountPaused { account_id: account_id.clone() },
+            });
+        } else {
+            // Executing actions one by one
+            for (action_index, action) in action_receipt.actions.iter().enumerate() {
+                let action_hash = create_action_hash_from_receipt_id(
+                    apply_state.current_protocol_version,
+                    receipt.receipt_id(),
+                    &apply_state.block_hash,
+                    apply_state.block_height,
+                    action_index,
+                );
+                let mut new_result = self.apply_action(
+                    action,
+                    state_update,
+                    apply_state,
+                    preparation_pipeline,
+                    &mut account,
+                    &mut actor_id,
+                    receipt,
+                    action_receipt,
+                    Arc::clone(&promise_results),
+                    &action_hash,
+                    action_index,
+                    &action_receipt.actions,
+                    epoch_info_provider,
+                    stats,
+                )?;
+                if new_result.result.is_ok() {
+                    if let Err(e) = new_result.new_receipts.iter().try_for_each(|receipt| {
+                        validate_receipt(
+                            &apply_state.config.wasm_config.limit_config,
+                            receipt,
+                            apply_state.current_protocol_version,
+                            ValidateReceiptMode::NewReceipt,
+                        )
+                    }) {
+                        new_result.result =
+                            Err(ActionErrorKind::NewReceiptValidationError(e).into());
+                    }
+                }
+                result.merge(new_result)?;
+                if limit_storage_proof_size && result.result.is_ok() {
+                    let size = state_update.trie().recorded_storage_size_upper_bound()
+                        - storage_proof_size_before;
+                    let limit = apply_state
+                        .config
+                        .wasm_config
+                        .limit_config
+                        .per_receipt_storage_proof_size_limit;
+                    if size > limit {
+                        result.merge(ActionResult {
+                            result: Err(ActionErrorKind::StorageProofSizeExceeded {
+                                size: size as u64,
+                                limit: limit as u64,
+                            }
+                            .into()),
+                            ..Default::default()
+                        })?;
+                    }
+                }
+                // TODO storage error
+                if let Err(ref mut res) = result.result {
+                    res.index = Some(action_index as u64);
+                    break;
                 }
-            }
-            result.merge(new_result)?;
-            // TODO storage error
-            if let Err(ref mut res) = result.result {
-                res.index = Some(action_index as u64);
-                break;
             }
         }
 
@@ -934,6 +1004,17 @@ impl Runtime {
 
         Self::print_log(&result.logs);
 
+        let profile = conversions::Convert::convert(*result.profile);
+        let metadata = if limit_storage_proof_size {
+            let storage_proof_size =
+                state_update.trie().recorded_storage_size_upper_bound() - storage_proof_size_before;
+            ExecutionMetadata::V4(Box::new(ExecutionMetadataV4 {
+                profile: Some(profile),
+                storage_proof_size: storage_proof_size as u64,
+            }))
+        } else {
+            ExecutionMetadata::V3(Box::new(profile))
+        };
         Ok(ExecutionOutcomeWithId {
             id: *receipt.receipt_id(),
             outcome: ExecutionOutcome {
@@ -944,9 +1025,7 @@ impl Runtime {
                 compute_usage: Some(result.compute_usage),
                 tokens_burnt,
                 executor_id: account_id.clone(),
-                metadata: ExecutionMetadata::V3(Box::new(conversions::Convert::convert(
-                    *result.profile,
-                ))),
+                metadata,
             },
         })
     }
@@ -1708,6 +1787,7 @@ impl Runtime {
     fn process_receipt_with_metrics(
         &self,
         receipt: &Receipt,
+        source: StorageProofSource,
         processing_state: &mut ApplyProcessingReceiptState,
         mut receipt_sink: &mut ReceiptSink,
         mut validator_proposals: &mut Vec<ValidatorStake>,
@@ -1757,6 +1837,12 @@ impl Runtime {
                 .with_label_values(&[shard_id_str.as_str()])
                 .observe(recorded_storage_proof_ratio);
         }
+        processing_state.stats.witness_size.record(
+            source,
+            *receipt.receipt_id(),
+            receipt.receiver_id().clone(),
+            recorded_storage_upper_bound_diff as u64,
+        );
 
         if let Some(outcome_with_id) = result? {
             let gas_burnt = outcome_with_id.outcome.gas_burnt;
@@ -1806,9 +1892,12 @@ impl Runtime {
         );
 
         for receipt in &local_receipts {
-            if processing_state.total.compute >= compute_limit
-                || processing_state.state_update.trie.check_proof_size_limit_exceed()
-            {
+            let proof_size_limit_exceeded =
+                processing_state.state_update.trie.check_proof_size_limit_exceed();
+            if processing_state.total.compute >= compute_limit || proof_size_limit_exceeded {
+                if proof_size_limit_exceeded {
+                    processing_state.stats.witness_size.record_deferred_receipt();
+                }
                 processing_state.delayed_receipts.push(
                     &mut processing_state.state_update,
                     &receipt,
@@ -1832,6 +1921,7 @@ impl Runtime {
                 // the `verify_and_charge_transaction`.
                 self.process_receipt_with_metrics(
                     &receipt,
+                    StorageProofSource::LocalReceipt,
                     &mut processing_state,
                     receipt_sink,
                     validator_proposals,
@@ -1933,6 +2023,7 @@ impl Runtime {
 
             self.process_receipt_with_metrics(
                 &receipt,
+                StorageProofSource::DelayedReceipt,
                 &mut processing_state,
                 receipt_sink,
                 validator_proposals,
@@ -1990,9 +2081,12 @@ impl Runtime {
                 ValidateReceiptMode::ExistingReceipt,
             )
             .map_err(RuntimeError::ReceiptValidationError)?;
-            if processing_state.total.compute >= compute_limit
-                || processing_state.state_update.trie.check_proof_size_limit_exceed()
-            {
+            let proof_size_limit_exceeded =
+                processing_state.state_update.trie.check_proof_size_limit_exceed();
+            if processing_state.total.compute >= compute_limit || proof_size_limit_exceeded {
+                if proof_size_limit_exceeded {
+                    processing_state.stats.witness_size.record_deferred_receipt();
+                }
                 processing_state.delayed_receipts.push(
                     &mut processing_state.state_update,
                     receipt,
@@ -2015,6 +2109,7 @@ impl Runtime {
 
                 self.process_receipt_with_metrics(
                     &receipt,
+                    StorageProofSource::IncomingReceipt,
                     &mut processing_state,
                     receipt_sink,
                     validator_proposals,
@@ -2163,6 +2258,9 @@ impl Runtime {
         self.apply_state_patch(&mut state_update, state_patch);
         let chunk_recorded_size_upper_bound =
             state_update.trie.recorded_storage_size_upper_bound() as f64;
+        stats.witness_size.storage_proof_size =
+            state_update.trie.recorded_storage_size_upper_bound() as u64;
+        stats.witness_size.soft_limit_exceeded = state_update.trie.check_proof_size_limit_exceed();
         let shard_id_str = apply_state.shard_id.to_string();
         metrics::CHUNK_RECORDED_SIZE_UPPER_BOUND
             .with_label_values(&[shard_id_str.as_str()])
@@ -2493,7 +2591,10 @@ impl<'a> ApplyProcessingState<'a> {
             gas: 0,
             compute: 0,
         };
-        let stats = ChunkApplyStatsV0::new(apply_state.block_height, apply_state.shard_id);
+        let mut stats = ChunkApplyStatsV0::new(apply_state.block_height, apply_state.shard_id);
+        stats.witness_size = WitnessSizeStats::new(
+            apply_state.config.witness_config.main_storage_proof_size_soft_limit as u64,
+        );
         Self {
             protocol_version,
             apply_state,
@@ -2716,7 +2817,7 @@ pub mod estimator {
             apply_state.cache.as_ref().map(|c| c.handle()),
             state_update.contract_storage(),
         );
-        let apply_result = Runtime {}.apply_action_receipt(
+        let apply_result = Runtime::new().apply_action_receipt(
             state_update,
             apply_state,
             &empty_pipeline,
@@ -7,13 +7,18 @@ use near_o11y::metrics::{
     try_create_int_counter_vec, try_create_int_gauge_vec,
 };
 use near_parameters::config::CongestionControlConfig;
+use near_parameters::vm::LimitConfig;
 use near_primitives::congestion_info::CongestionInfo;
-use near_primitives::types::ShardId;
+use near_primitives::types::{AccountId, EpochHeight, ShardId};
 use near_store::Trie;
 use near_store::trie::SubtreeSize;
 use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
+/// Size of a single page of wasm linear memory.
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
 pub static ACTION_CALLED_COUNT: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_action_called_count",
@@ -23,6 +28,14 @@ pub static ACTION_CALLED_COUNT: LazyLock<IntCounterVec> = LazyLock::new(|| {
     .unwrap()
 });
 
+pub static PAUSED_ACCOUNT_RECEIPTS_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_paused_account_receipts_total",
+        "The number of receipts failed because their receiver is a paused account",
+    )
+    .unwrap()
+});
+
 pub static TRANSACTION_APPLIED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
     try_create_int_counter(
         "near_transaction_applied_total",
@@ -202,6 +215,40 @@ pub static FUNCTION_CALL_PROCESSED_CACHE_ERRORS: LazyLock<IntCounterVec> = LazyL
     )
     .unwrap()
 });
+pub static FUNCTION_CALL_PEAK_LINEAR_MEMORY_BYTES: LazyLock<HistogramVec> = LazyLock::new(|| {
+    try_create_histogram_vec(
+        "near_function_call_peak_linear_memory_bytes",
+        "Size of the contract linear memory at the end of a function call, in bytes",
+        &["shard_id"],
+        // 64 KiB (one wasm page) to 64 MiB
+        Some(exponential_buckets(65536., 2., 11).unwrap()),
+    )
+    .unwrap()
+});
+pub static FUNCTION_CALL_PEAK_HOST_MEMORY_BYTES: LazyLock<HistogramVec> = LazyLock::new(|| {
+    try_create_histogram_vec(
+        "near_function_call_peak_host_memory_bytes",
+        "Peak memory held in registers on behalf of a contract during a function call, in bytes",
+        &["shard_id"],
+        // 1 KiB to 1 GiB
+        Some(exponential_buckets(1024., 4., 11).unwrap()),
+    )
+    .unwrap()
+});
+/// Only contracts which come close to the memory limits are labelled by account
+/// to keep the cardinality of the metric bounded. The gauge is reset at the
+/// start of every epoch, so it doesn't keep reporting contracts which are no
+/// longer called.
+pub static FUNCTION_CALL_HIGH_MEMORY_CONTRACT_PEAK_BYTES: LazyLock<IntGaugeVec> = LazyLock::new(
+    || {
+        try_create_int_gauge_vec(
+            "near_function_call_high_memory_contract_peak_bytes",
+            "Highest memory usage in the current epoch of contracts which used more than half of the linear memory or register memory limit",
+            &["account_id", "memory_type"],
+        )
+        .unwrap()
+    },
+);
 static CHUNK_COMPUTE: LazyLock<HistogramVec> = LazyLock::new(|| {
     try_create_histogram_vec(
         "near_chunk_compute",
@@ -832,3 +879,49 @@ pub fn report_recorded_column_sizes(trie: &Trie, apply_state: &ApplyState) {
         .with_label_values(&[shard_id_str.as_str(), "values"])
         .observe(total_size.values_size as f64);
 }
+
+/// Epoch height of the values in `FUNCTION_CALL_HIGH_MEMORY_CONTRACT_PEAK_BYTES`.
+static HIGH_MEMORY_CONTRACT_EPOCH_HEIGHT: AtomicU64 = AtomicU64::new(0);
+
+/// Reports the memory high-water marks of a single function call.
+///
+/// Contracts are additionally labelled by account when they use more than half
+/// of either limit, which makes it possible to identify the ones at risk of
+/// running out of memory.
+pub(crate) fn report_function_call_memory_usage(
+    account_id: &AccountId,
+    shard_id: &str,
+    epoch_height: EpochHeight,
+    profile: &near_vm_runner::ProfileDataV3,
+    limit_config: &LimitConfig,
+) {
+    // Shards are applied concurrently and possibly for different epochs, e.g.
+    // during catchup, so the epoch only ever moves forward.
+    if HIGH_MEMORY_CONTRACT_EPOCH_HEIGHT.fetch_max(epoch_height, Ordering::Relaxed) < epoch_height {
+        FUNCTION_CALL_HIGH_MEMORY_CONTRACT_PEAK_BYTES.reset();
+    }
+
+    FUNCTION_CALL_PEAK_LINEAR_MEMORY_BYTES
+        .with_label_values(&[shard_id])
+        .observe(profile.peak_linear_memory as f64);
+    FUNCTION_CALL_PEAK_HOST_MEMORY_BYTES
+        .with_label_values(&[shard_id])
+        .observe(profile.peak_host_memory as f64);
+
+    let max_linear_memory = u64::from(limit_config.max_memory_pages) * WASM_PAGE_SIZE;
+    let usages = [
+        ("linear", profile.peak_linear_memory, max_linear_memory),
+        ("host", profile.peak_host_memory, limit_config.registers_memory_limit),
+    ];
+    for (memory_type, peak, limit) in usages {
+        if peak <= limit / 2 {
+            continue;
+        }
+        let gauge = FUNCTION_CALL_HIGH_MEMORY_CONTRACT_PEAK_BYTES
+            .with_label_values(&[account_id.as_str(), memory_type]);
+        let peak = i64::try_from(peak).unwrap_or(i64::MAX);
+        if peak > gauge.get() {
+            gauge.set(peak);
+        }
+    }
+}
@@ -54,6 +54,14 @@ pub enum CallFunctionError {
     VMError { error_message: String },
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum DryRunTransactionError {
+    #[error("Transaction is invalid: {error}")]
+    InvalidTransaction { error: near_primitives::errors::InvalidTxError },
+    #[error("Internal error: #{error_message}")]
+    InternalError { error_message: String },
+}
+
 impl From<ViewAccountError> for ViewContractCodeError {
     fn from(view_account_error: ViewAccountError) -> Self {
         match view_account_error {
@@ -99,3 +107,26 @@ impl From<near_primitives::errors::StorageError> for CallFunctionError {
         Self::InternalError { error_message: storage_error.to_string() }
     }
 }
+
+impl From<near_primitives::errors::StorageError> for DryRunTransactionError {
+    fn from(storage_error: near_primitives::errors::StorageError) -> Self {
+        Self::InternalError { error_message: storage_error.to_string() }
+    }
+}
+
+impl From<near_primitives::errors::RuntimeError> for DryRunTransactionError {
+    fn from(runtime_error: near_primitives::errors::RuntimeError) -> Self {
+        match runtime_error {
+            near_primitives::errors::RuntimeError::InvalidTxError(error) => {
+                Self::InvalidTransaction { error }
+            }
+            _ => Self::InternalError { error_message: runtime_error.to_string() },
+        }
+    }
+}
+
+impl From<near_primitives::errors::EpochError> for DryRunTransactionError {
+    fn from(epoch_error: near_primitives::errors::EpochError) -> Self {
+        Self::InternalError { error_message: epoch_error.to_string() }
+    }
+}
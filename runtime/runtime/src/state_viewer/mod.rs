@@ -1,32 +1,58 @@
-use crate::ApplyState;
 use crate::actions::execute_function_call;
+use crate::bandwidth_scheduler::BandwidthSchedulerOutput;
+use crate::config::tx_cost;
+use crate::congestion_control::{OutgoingLimit, ReceiptSink, ReceiptSinkV2};
 use crate::ext::RuntimeExt;
 use crate::pipelining::ReceiptPreparationPipeline;
 use crate::receipt_manager::ReceiptManager;
+use crate::verifier::{validate_actions, validate_transaction};
+use crate::{ApplyState, Runtime};
 use near_crypto::{KeyType, PublicKey};
-use near_parameters::RuntimeConfigStore;
-use near_primitives::account::{AccessKey, Account};
+use near_parameters::{RuntimeConfig, RuntimeConfigStore};
+use near_primitives::account::{AccessKey, Account, AccountContract};
 use near_primitives::apply::ApplyChunkReason;
-use near_primitives::bandwidth_scheduler::BlockBandwidthRequests;
+use near_primitives::bandwidth_scheduler::{BandwidthSchedulerParams, BlockBandwidthRequests};
 use near_primitives::borsh::BorshDeserialize;
+use near_primitives::chunk_apply_stats::{ChunkApplyStatsV0, ReceiptSinkStats};
+use near_primitives::congestion_info::CongestionInfo;
+use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
-use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptV1};
-use near_primitives::transaction::FunctionCallAction;
+use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptV1, ReceivedData};
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::transaction::{
+    ExecutionOutcomeWithId, FunctionCallAction, SignedTransaction, ValidatedTransaction,
+};
 use near_primitives::trie_key::trie_key_parsers;
 use near_primitives::types::{
-    AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, ShardId,
+    AccountId, Balance, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas,
+    RawStateChangesWithTrieKey, ShardId, StateChangeCause,
 };
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{StateItem, ViewStateResult};
+use near_primitives::views::{StateItem, StateOverrideView, ViewStatePagedResult, ViewStateResult};
 use near_primitives_core::config::ViewConfig;
-use near_store::{TrieUpdate, get_access_key, get_account};
+use near_store::trie::outgoing_metadata::{OutgoingMetadatas, ReceiptGroupsConfig};
+use near_store::trie::receipts_column_helper::ShardsOutgoingReceiptBuffer;
+use near_store::{
+    TrieUpdate, get_access_key, get_account, has_received_data, set_access_key, set_account,
+    set_received_data,
+};
 use near_vm_runner::logic::{ProtocolVersion, ReturnData};
 use near_vm_runner::{ContractCode, ContractRuntimeCache};
+use std::collections::VecDeque;
+use std::num::{NonZeroU32, NonZeroU64};
 use std::{str, sync::Arc, time::Instant};
 use tracing::debug;
 
 pub mod errors;
 
+/// Maximum number of items returned by `TrieViewer::view_state_paged`.
+pub const MAX_VIEW_STATE_PAGE_SIZE: u32 = 1000;
+
+/// Maximum total size of the keys and values returned by
+/// `TrieViewer::view_state_paged`, unless the state size limit of the viewer
+/// is smaller.
+pub const MAX_VIEW_STATE_PAGE_BYTES: u64 = 4 * 1024 * 1024;
+
 /// State for the view call.
 #[derive(Debug)]
 pub struct ViewApplyState {
@@ -169,20 +195,66 @@ impl TrieViewer {
             }
         };
 
-        let mut values = vec![];
         let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
         let acc_sep_len = query.len() - prefix.len();
-        let mut iter = state_update.trie().disk_iter()?;
-        iter.remember_visited_nodes(include_proof);
-        iter.seek_prefix(&query)?;
-        for item in &mut iter {
-            let (key, value) = item?;
-            values.push(StateItem { key: key[acc_sep_len..].to_vec().into(), value: value.into() });
-        }
-        let proof = iter.into_visited_nodes();
+        let (items, proof) = state_update.trie().get_items_with_prefix(&query, include_proof)?;
+        let values = items
+            .into_iter()
+            .map(|(key, value)| StateItem {
+                key: key[acc_sep_len..].to_vec().into(),
+                value: value.into(),
+            })
+            .collect();
         Ok(ViewStateResult { values, proof })
     }
 
+    /// Returns a page of at most `limit` items of the contract state starting
+    /// at `start_key`. Unlike `view_state`, the size of the whole state isn't
+    /// limited, since only one page is read at a time. Instead, the size of
+    /// each page is limited by the state size limit of the viewer, except that
+    /// a page always has at least one item.
+    pub fn view_state_paged(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+        prefix: &[u8],
+        start_key: &[u8],
+        limit: NonZeroU32,
+        include_proof: bool,
+    ) -> Result<ViewStatePagedResult, errors::ViewStateError> {
+        if get_account(state_update, account_id)?.is_none() {
+            return Err(errors::ViewStateError::AccountDoesNotExist {
+                requested_account_id: account_id.clone(),
+            });
+        }
+
+        let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
+        let acc_sep_len = query.len() - prefix.len();
+        let mut start = query[..acc_sep_len].to_vec();
+        start.extend_from_slice(start_key);
+        let limit = limit.get().min(MAX_VIEW_STATE_PAGE_SIZE) as usize;
+        let max_bytes = self
+            .state_size_limit
+            .map_or(MAX_VIEW_STATE_PAGE_BYTES, |limit| limit.min(MAX_VIEW_STATE_PAGE_BYTES))
+            as usize;
+        let (items, next_key, proof) = state_update.trie().get_items_with_prefix_paged(
+            &query,
+            &start,
+            limit,
+            max_bytes,
+            include_proof,
+        )?;
+        let values = items
+            .into_iter()
+            .map(|(key, value)| StateItem {
+                key: key[acc_sep_len..].to_vec().into(),
+                value: value.into(),
+            })
+            .collect();
+        let next_key = next_key.map(|key| key[acc_sep_len..].to_vec().into());
+        Ok(ViewStatePagedResult { values, next_key, proof })
+    }
+
     pub fn call_function(
         &self,
         mut state_update: TrieUpdate,
@@ -306,4 +378,238 @@ impl TrieViewer {
             Ok(result)
         }
     }
+
+    /// Applies `signed_tx` on top of `state_update` without persisting
+    /// anything. Receipts addressed to the same shard are executed right away,
+    /// everything else is returned as not executed.
+    ///
+    /// `state_overrides` are applied before the transaction and are not
+    /// reported in the resulting state changes.
+    pub fn dry_run_transaction(
+        &self,
+        mut state_update: TrieUpdate,
+        view_state: ViewApplyState,
+        config: Arc<RuntimeConfig>,
+        gas_price: Balance,
+        signed_tx: SignedTransaction,
+        skip_signature_check: bool,
+        state_overrides: &[StateOverrideView],
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Result<DryRunResult, errors::DryRunTransactionError> {
+        let protocol_version = view_state.current_protocol_version;
+        apply_state_overrides(&mut state_update, &config, state_overrides)?;
+
+        let validated_tx = if skip_signature_check {
+            match validate_actions(
+                &config.wasm_config.limit_config,
+                signed_tx.transaction.actions(),
+                protocol_version,
+            ) {
+                Ok(()) => ValidatedTransaction::new_unsigned(&config, signed_tx),
+                Err(err) => Err((InvalidTxError::ActionsValidation(err), signed_tx)),
+            }
+        } else {
+            validate_transaction(&config, signed_tx, protocol_version)
+        }
+        .map_err(|(error, _)| errors::DryRunTransactionError::InvalidTransaction { error })?;
+        let cost =
+            tx_cost(&config, validated_tx.to_tx(), gas_price, protocol_version).map_err(|_| {
+                errors::DryRunTransactionError::InvalidTransaction {
+                    error: InvalidTxError::CostOverflow,
+                }
+            })?;
+
+        let apply_state = ApplyState {
+            apply_reason: ApplyChunkReason::ViewTrackedShard,
+            block_height: view_state.block_height,
+            prev_block_hash: view_state.prev_block_hash,
+            block_hash: view_state.block_hash,
+            shard_id: view_state.shard_id,
+            epoch_id: view_state.epoch_id,
+            epoch_height: view_state.epoch_height,
+            gas_price,
+            block_timestamp: view_state.block_timestamp,
+            gas_limit: None,
+            random_seed: *state_update.get_root(),
+            current_protocol_version: protocol_version,
+            config: Arc::clone(&config),
+            cache: view_state.cache,
+            is_new_chunk: false,
+            congestion_info: Default::default(),
+            bandwidth_requests: BlockBandwidthRequests::empty(),
+            trie_access_tracker_state: Default::default(),
+        };
+        let runtime = Runtime::new();
+        let mut stats = ChunkApplyStatsV0::new(view_state.block_height, view_state.shard_id);
+        let (receipt, tx_outcome) = runtime
+            .process_transaction(&mut state_update, &apply_state, &validated_tx, &cost, &mut stats)
+            .map_err(|error| errors::DryRunTransactionError::InvalidTransaction { error })?;
+
+        let shard_layout = epoch_info_provider.shard_layout(&view_state.epoch_id)?;
+        let pipeline = ReceiptPreparationPipeline::new(
+            Arc::clone(&config),
+            apply_state.cache.as_ref().map(|v| v.handle()),
+            state_update.contract_storage(),
+        );
+        let mut outcomes = vec![tx_outcome];
+        let mut receipts_not_executed = vec![];
+        let mut postponed_receipts: Vec<Receipt> = vec![];
+        let mut validator_proposals = vec![];
+        let mut local_receipts = VecDeque::from([receipt]);
+        while let Some(receipt) = local_receipts.pop_front() {
+            if shard_layout.account_id_to_shard_id(receipt.receiver_id()) != view_state.shard_id {
+                receipts_not_executed.push(receipt);
+                continue;
+            }
+            match receipt.receipt() {
+                ReceiptEnum::Action(_) => {
+                    if !has_all_input_data(&state_update, &receipt)? {
+                        postponed_receipts.push(receipt);
+                        continue;
+                    }
+                    let mut receipt_sink =
+                        dry_run_receipt_sink(&state_update, &apply_state, &shard_layout)?;
+                    let outcome = runtime.apply_action_receipt(
+                        &mut state_update,
+                        &apply_state,
+                        &pipeline,
+                        &receipt,
+                        &mut receipt_sink,
+                        &mut validator_proposals,
+                        &mut stats,
+                        epoch_info_provider,
+                    )?;
+                    outcomes.push(outcome);
+                    local_receipts.extend(
+                        receipt_sink.finalize_stats_get_outgoing_receipts(&mut stats.receipt_sink),
+                    );
+                }
+                ReceiptEnum::Data(data_receipt) => {
+                    set_received_data(
+                        &mut state_update,
+                        receipt.receiver_id().clone(),
+                        data_receipt.data_id,
+                        &ReceivedData { data: data_receipt.data.clone() },
+                    );
+                    state_update.commit(StateChangeCause::ReceiptProcessing {
+                        receipt_hash: *receipt.receipt_id(),
+                    });
+                    // Data may unblock a postponed receipt, give those another try.
+                    local_receipts.extend(std::mem::take(&mut postponed_receipts));
+                }
+                ReceiptEnum::PromiseYield(_)
+                | ReceiptEnum::PromiseResume(_)
+                | ReceiptEnum::GlobalContractDistribution(_) => {
+                    receipts_not_executed.push(receipt);
+                }
+            }
+        }
+        receipts_not_executed.extend(postponed_receipts);
+
+        let state_changes = state_update
+            .finalize()?
+            .state_changes
+            .into_iter()
+            .filter_map(|mut change| {
+                change.changes.retain(|c| !matches!(c.cause, StateChangeCause::InitialState));
+                (!change.changes.is_empty()).then_some(change)
+            })
+            .collect();
+        Ok(DryRunResult { outcomes, receipts_not_executed, state_changes })
+    }
+}
+
+/// Result of [`TrieViewer::dry_run_transaction`].
+#[derive(Debug)]
+pub struct DryRunResult {
+    /// Outcome of the transaction followed by outcomes of the receipts
+    /// executed during the dry run, in execution order.
+    pub outcomes: Vec<ExecutionOutcomeWithId>,
+    /// Receipts which would have been executed on another shard or in a later
+    /// block.
+    pub receipts_not_executed: Vec<Receipt>,
+    /// Changes made by the transaction and its receipts, excluding overrides.
+    pub state_changes: Vec<RawStateChangesWithTrieKey>,
+}
+
+fn apply_state_overrides(
+    state_update: &mut TrieUpdate,
+    config: &RuntimeConfig,
+    state_overrides: &[StateOverrideView],
+) -> Result<(), errors::DryRunTransactionError> {
+    if state_overrides.is_empty() {
+        return Ok(());
+    }
+    for state_override in state_overrides {
+        match state_override {
+            StateOverrideView::AccountBalance { account_id, amount } => {
+                let mut account = get_account(state_update, account_id)?.unwrap_or_else(|| {
+                    Account::new(
+                        0,
+                        0,
+                        AccountContract::None,
+                        config.fees.storage_usage_config.num_bytes_account,
+                    )
+                });
+                account.set_amount(*amount);
+                set_account(state_update, account_id.clone(), &account);
+            }
+            StateOverrideView::AccessKey { account_id, public_key, access_key } => {
+                set_access_key(
+                    state_update,
+                    account_id.clone(),
+                    public_key.clone(),
+                    &access_key.clone().into(),
+                );
+            }
+        }
+    }
+    state_update.commit(StateChangeCause::InitialState);
+    Ok(())
+}
+
+fn has_all_input_data(
+    state_update: &TrieUpdate,
+    receipt: &Receipt,
+) -> Result<bool, errors::DryRunTransactionError> {
+    let ReceiptEnum::Action(action_receipt) = receipt.receipt() else {
+        return Ok(true);
+    };
+    for data_id in &action_receipt.input_data_ids {
+        if !has_received_data(state_update, receipt.receiver_id(), *data_id)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Receipt sink which forwards everything: the dry run only needs to know
+/// where receipts go, not whether the receiving shard can take them.
+fn dry_run_receipt_sink(
+    state_update: &TrieUpdate,
+    apply_state: &ApplyState,
+    shard_layout: &ShardLayout,
+) -> Result<ReceiptSink, errors::DryRunTransactionError> {
+    let outgoing_limit = shard_layout
+        .shard_ids()
+        .map(|shard_id| (shard_id, OutgoingLimit { gas: Gas::MAX, size: u64::MAX }))
+        .collect();
+    let outgoing_metadatas = OutgoingMetadatas::load(
+        state_update,
+        shard_layout.shard_ids(),
+        ReceiptGroupsConfig::default_config(),
+    )?;
+    let params = BandwidthSchedulerParams::new(
+        NonZeroU64::new(shard_layout.num_shards()).expect("ShardLayout has zero shards!"),
+        &apply_state.config,
+    );
+    Ok(ReceiptSink::V2(ReceiptSinkV2 {
+        own_congestion_info: CongestionInfo::default(),
+        outgoing_limit,
+        outgoing_buffers: ShardsOutgoingReceiptBuffer::load(&state_update.trie)?,
+        outgoing_receipts: Vec::new(),
+        outgoing_metadatas,
+        bandwidth_scheduler_output: BandwidthSchedulerOutput::no_granted_bandwidth(params),
+        stats: ReceiptSinkStats::default(),
+    }))
 }
@@ -25,10 +25,11 @@ use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptPrior
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::state::PartialState;
 use near_primitives::stateless_validation::contract_distribution::CodeHash;
+use near_primitives::stateless_validation::witness_size::StorageProofSource;
 use near_primitives::test_utils::{MockEpochInfoProvider, account_new};
 use near_primitives::transaction::{
-    AddKeyAction, DeleteKeyAction, DeployContractAction, ExecutionOutcomeWithId, ExecutionStatus,
-    FunctionCallAction, SignedTransaction, TransferAction, ValidatedTransaction,
+    AddKeyAction, DeleteKeyAction, DeployContractAction, ExecutionMetadata, ExecutionOutcomeWithId,
+    ExecutionStatus, FunctionCallAction, SignedTransaction, TransferAction, ValidatedTransaction,
 };
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
@@ -267,6 +268,104 @@ fn test_apply_refund_receipts() {
     }
 }
 
+#[test]
+fn test_apply_receipt_to_paused_account() {
+    let initial_balance = to_yocto(1_000_000);
+    let initial_locked = to_yocto(500_000);
+    let (runtime, tries, root, apply_state, _, epoch_info_provider) = setup_runtime(
+        vec![alice_account(), bob_account()],
+        initial_balance,
+        initial_locked,
+        10u64.pow(15),
+    );
+    let runtime = runtime.with_paused_accounts([alice_account()]);
+
+    let receipts = generate_receipts(to_yocto(10_000), 1);
+    let shard_uid = ShardUId::single_shard();
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(shard_uid, root),
+            &None,
+            &apply_state,
+            &receipts,
+            SignedValidPeriodTransactions::empty(),
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+
+    let action_error = assert_matches!(
+        &apply_result.outcomes[0].outcome.status,
+        ExecutionStatus::Failure(TxExecutionError::ActionError(ae)) => ae
+    );
+    assert_eq!(action_error.index, Some(0));
+    assert_eq!(action_error.kind, ActionErrorKind::AccountPaused { account_id: alice_account() });
+    // The deposit is refunded to the sender.
+    assert!(!apply_result.outgoing_receipts.is_empty());
+    assert!(apply_result.outgoing_receipts.iter().all(|r| r.receiver_id() == &bob_account()));
+
+    let mut store_update = tries.store_update();
+    let root = tries.apply_all(&apply_result.trie_changes, shard_uid, &mut store_update);
+    store_update.commit().unwrap();
+    let state = tries.new_trie_update(shard_uid, root);
+    let account = get_account(&state, &alice_account()).unwrap().unwrap();
+    assert_eq!(account.amount(), initial_balance);
+}
+
+#[test]
+fn test_per_receipt_storage_proof_limit() {
+    if !ProtocolFeature::PerReceiptStorageProofLimit.enabled(PROTOCOL_VERSION) {
+        return;
+    }
+    let (runtime, tries, root, mut apply_state, _, epoch_info_provider) = setup_runtime(
+        vec![alice_account(), bob_account()],
+        to_yocto(1_000_000),
+        to_yocto(500_000),
+        10u64.pow(15),
+    );
+    let receipts = generate_receipts(to_yocto(10_000), 1);
+    let shard_uid = ShardUId::single_shard();
+    let apply = |apply_state: &ApplyState| {
+        runtime
+            .apply(
+                tries.get_trie_for_shard(shard_uid, root).recording_reads_new_recorder(),
+                &None,
+                apply_state,
+                &receipts,
+                SignedValidPeriodTransactions::empty(),
+                &epoch_info_provider,
+                Default::default(),
+            )
+            .unwrap()
+    };
+
+    // The storage proof size is reported in the outcome.
+    let apply_result = apply(&apply_state);
+    let outcome = &apply_result.outcomes[0].outcome;
+    assert_matches!(outcome.status, ExecutionStatus::SuccessValue(_));
+    let metadata = assert_matches!(&outcome.metadata, ExecutionMetadata::V4(metadata) => metadata);
+    assert!(metadata.profile.is_some());
+    assert!(metadata.storage_proof_size > 0);
+
+    // The receipt fails once its storage proof exceeds the limit, even without
+    // function calls.
+    let mut config = RuntimeConfig::clone(&apply_state.config);
+    let mut wasm_config = near_parameters::vm::Config::clone(&config.wasm_config);
+    wasm_config.limit_config.per_receipt_storage_proof_size_limit = 1;
+    config.wasm_config = Arc::new(wasm_config);
+    apply_state.config = Arc::new(config);
+    let apply_result = apply(&apply_state);
+    let action_error = assert_matches!(
+        &apply_result.outcomes[0].outcome.status,
+        ExecutionStatus::Failure(TxExecutionError::ActionError(ae)) => ae
+    );
+    assert_eq!(action_error.index, Some(0));
+    assert_matches!(
+        action_error.kind,
+        ActionErrorKind::StorageProofSizeExceeded { size, limit: 1 } if size > 1
+    );
+}
+
 #[test]
 fn test_apply_delayed_receipts_feed_all_at_once() {
     let initial_balance = to_yocto(1_000_000);
@@ -822,6 +921,15 @@ fn test_apply_surplus_gas_for_transfer() {
     assert_eq!(result.stats.balance.gas_deficit_amount, 0);
     assert_eq!(result.stats.balance.tx_burnt_amount, expected_burnt_amount);
     assert_eq!(result.outgoing_receipts.len(), expected_receipts);
+
+    let receipt_stats = &result.stats.receipt_sink.new_outgoing_receipts;
+    assert_eq!(receipt_stats.len(), expected_receipts);
+    for (stats, receipt) in receipt_stats.iter().zip(&result.outgoing_receipts) {
+        assert_eq!(&stats.receipt_id, receipt.receipt_id());
+        assert_eq!(&stats.receiver_id, receipt.receiver_id());
+        assert!(stats.forwarded);
+        assert!(stats.size > 0);
+    }
 }
 
 #[test]
@@ -1410,6 +1518,14 @@ fn test_main_storage_proof_size_soft_limit() {
 
     // We expect function_call_fn(bob_account()) to be in delayed receipts
     assert_eq!(apply_result.delayed_receipts_count, 1);
+    // Only the receipt to alice_account was applied, the other one was deferred.
+    let witness_size = &apply_result.stats.witness_size;
+    assert!(witness_size.soft_limit_exceeded);
+    assert_eq!(witness_size.deferred_receipts_num, 1);
+    assert_eq!(witness_size.attributions.len(), 1);
+    assert_eq!(witness_size.attributions[0].source, StorageProofSource::IncomingReceipt);
+    assert_eq!(witness_size.attributions[0].account_id, alice_account());
+    assert!(witness_size.attributions[0].size > 0);
 
     // Since contracts are excluded from the partial state, we will get missing trie error below.
     let partial_storage = apply_result.proof.unwrap();
@@ -0,0 +1,27 @@
+use near_async::time::Duration;
+use near_o11y::testonly::init_test_logger;
+
+use super::transfer_storm::{BOUNDARY_ACCOUNTS, NUM_ACCOUNTS, transfer_storm_txs};
+use super::{finish_benchmark, run_benchmark, setup_with_config};
+use crate::utils::transactions::{get_shared_block_hash, make_accounts};
+
+/// The transfer storm with the maximum block production jitter. The block
+/// intervals of its report, compared with those of `transfer_storm`, show how
+/// much the jitter spreads the blocks, and the latency and throughput what it
+/// costs.
+#[test]
+#[ignore]
+fn benchmark_block_production_jitter() {
+    init_test_logger();
+    let accounts = make_accounts(NUM_ACCOUNTS);
+    let mut env = setup_with_config(&accounts, &BOUNDARY_ACCOUNTS, |config, _| {
+        config.block_production_jitter = Some(config.min_block_production_delay / 2);
+    });
+
+    let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+    let txs = transfer_storm_txs(&accounts, block_hash);
+    let report = run_benchmark("block_production_jitter", &mut env, txs, Duration::seconds(120));
+    finish_benchmark(&report);
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
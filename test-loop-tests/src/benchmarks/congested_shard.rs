@@ -0,0 +1,56 @@
+use itertools::Itertools;
+use near_async::time::Duration;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::test_utils::create_user_test_signer;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+
+use super::{finish_benchmark, run_benchmark, setup};
+use crate::utils::TGAS;
+use crate::utils::transactions::{get_shared_block_hash, make_accounts, run_tx};
+
+const NUM_ACCOUNTS: usize = 200;
+const BURN_GAS: u64 = 250 * TGAS;
+
+/// All the accounts call a single contract with function calls burning most of
+/// their gas, so that its shard gets congested. The transactions rejected
+/// because of the congestion are retried, which adds to their latency.
+#[test]
+#[ignore]
+fn benchmark_congested_shard() {
+    init_test_logger();
+    let contract_id: AccountId = "000".parse().unwrap();
+    let mut accounts = make_accounts(NUM_ACCOUNTS);
+    accounts.push(contract_id.clone());
+    let mut env = setup(&accounts, &["account3", "account5", "account7"]);
+
+    let rpc_id = env.node_datas[0].account_id.clone();
+    let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+    let code = near_test_contracts::rs_contract().to_vec();
+    let signer = create_user_test_signer(&contract_id);
+    let deploy = SignedTransaction::deploy_contract(1, &contract_id, code, &signer, block_hash);
+    run_tx(&mut env.test_loop, &rpc_id, deploy, &env.node_datas, Duration::seconds(20));
+
+    let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+    let args = BURN_GAS.to_le_bytes().to_vec();
+    let txs = accounts[..NUM_ACCOUNTS]
+        .iter()
+        .map(|sender| {
+            SignedTransaction::call(
+                1,
+                sender.clone(),
+                contract_id.clone(),
+                &create_user_test_signer(sender),
+                0,
+                "burn_gas_raw".to_string(),
+                args.clone(),
+                300 * TGAS,
+                block_hash,
+            )
+        })
+        .collect_vec();
+    let report = run_benchmark("congested_shard", &mut env, txs, Duration::seconds(300));
+    finish_benchmark(&report);
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
@@ -0,0 +1,70 @@
+use itertools::Itertools;
+use near_async::time::Duration;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::test_utils::create_user_test_signer;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+
+use super::{finish_benchmark, run_benchmark, setup};
+use crate::utils::TGAS;
+use crate::utils::transactions::{get_shared_block_hash, make_accounts, run_txs_parallel};
+
+const NUM_ACCOUNTS: usize = 100;
+const CALLS_PER_ACCOUNT: u64 = 3;
+const BURN_GAS: u64 = 50 * TGAS;
+
+/// Every account calls contracts deployed on all the shards with function
+/// calls burning a fixed amount of gas.
+#[test]
+#[ignore]
+fn benchmark_contract_heavy() {
+    init_test_logger();
+    let contracts: Vec<AccountId> =
+        ["account0_contract", "account4_contract", "account6_contract", "account8_contract"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+    let mut accounts = make_accounts(NUM_ACCOUNTS);
+    accounts.extend(contracts.iter().cloned());
+    let mut env = setup(&accounts, &["account3", "account5", "account7"]);
+
+    let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+    let code = near_test_contracts::rs_contract().to_vec();
+    let deploys = contracts
+        .iter()
+        .map(|contract_id| {
+            let signer = create_user_test_signer(contract_id);
+            SignedTransaction::deploy_contract(1, contract_id, code.clone(), &signer, block_hash)
+        })
+        .collect_vec();
+    run_txs_parallel(&mut env.test_loop, deploys, &env.node_datas, Duration::seconds(20));
+
+    let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+    let args = BURN_GAS.to_le_bytes().to_vec();
+    let txs = accounts[..NUM_ACCOUNTS]
+        .iter()
+        .flat_map(|sender| {
+            let signer = create_user_test_signer(sender);
+            (1..=CALLS_PER_ACCOUNT)
+                .map(|nonce| {
+                    let contract_id = &contracts[nonce as usize % contracts.len()];
+                    SignedTransaction::call(
+                        nonce,
+                        sender.clone(),
+                        contract_id.clone(),
+                        &signer,
+                        0,
+                        "burn_gas_raw".to_string(),
+                        args.clone(),
+                        100 * TGAS,
+                        block_hash,
+                    )
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+    let report = run_benchmark("contract_heavy", &mut env, txs, Duration::seconds(120));
+    finish_benchmark(&report);
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
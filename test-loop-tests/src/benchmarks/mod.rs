@@ -0,0 +1,391 @@
+//! Load benchmarks running standardized multi-node scenarios on TestLoop.
+//!
+//! Unlike the tests, the benchmarks don't check the behavior of the chain but
+//! measure how fast it processes a fixed workload:
+//! - throughput, the number of transactions executed per second,
+//! - latency, the time from the submission of a transaction to its final
+//!   outcome,
+//! - apply time, the time taken to apply all the chunks of a block,
+//! - block interval, the time between two consecutive blocks.
+//!
+//! Throughput, latency and block interval are measured in the virtual time of the test loop,
+//! so they only depend on the protocol and on the node logic and are the same
+//! on all machines. Apply time is measured in real time by the
+//! `near_apply_all_chunks_time` metric, which is shared by all the tests of the
+//! process, so the benchmarks should be run on their own:
+//!
+//! ```text
+//! cargo test -p test-loop-tests --features test_features benchmark_ -- --ignored --test-threads=1
+//! ```
+//!
+//! Every report is compared against the baseline checked in at
+//! `res/benchmarks/<name>.json` and the benchmark fails if any metric got
+//! worse by more than the tolerance, or if the baseline is missing. The following environment variables
+//! control the output:
+//! - `NEAR_BENCHMARK_OUTPUT_DIR`, directory the reports are written to,
+//! - `NEAR_BENCHMARK_UPDATE_BASELINES`, if set the baselines are overwritten
+//!   with the reports instead of being compared with them.
+
+mod block_production_jitter;
+mod congested_shard;
+mod contract_heavy;
+mod transfer_storm;
+
+use std::path::PathBuf;
+use std::task::Poll;
+
+use itertools::Itertools;
+use near_async::time::Duration;
+use near_chain_configs::ClientConfig;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_o11y::metrics::prometheus;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+use near_primitives::views::FinalExecutionStatus;
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::setup::env::TestLoopEnv;
+use crate::utils::ONE_NEAR;
+use crate::utils::transactions::TransactionRunner;
+
+const NUM_VALIDATORS: usize = 4;
+
+/// Relative decrease of the throughput reported as a regression.
+const THROUGHPUT_TOLERANCE: f64 = 0.1;
+/// Relative increase of the latency percentiles reported as a regression.
+const LATENCY_TOLERANCE: f64 = 0.1;
+/// Relative increase of the apply time percentiles reported as a regression.
+/// Larger than the others as apply time depends on the machine and its load.
+const APPLY_TIME_TOLERANCE: f64 = 0.5;
+
+const APPLY_TIME_METRIC: &str = "near_apply_all_chunks_time";
+
+/// Names of the scenarios, each of which has its baseline checked in.
+const BENCHMARKS: [&str; 4] =
+    ["transfer_storm", "block_production_jitter", "contract_heavy", "congested_shard"];
+
+/// Summary of the distribution of a measured value.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Distribution {
+    pub count: u64,
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    fn from_samples(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(f64::total_cmp);
+        let percentile = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+        Self {
+            count: samples.len() as u64,
+            min: samples[0],
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: samples[samples.len() - 1],
+        }
+    }
+
+    /// Approximates the distribution of the values observed by a histogram
+    /// between the two snapshots of its buckets, taken by `histogram_buckets`.
+    /// Every value is approximated by the upper bound of its bucket.
+    fn from_histogram(before: &[(f64, u64)], after: &[(f64, u64)]) -> Self {
+        let buckets = after
+            .iter()
+            .enumerate()
+            .map(|(i, &(bound, count))| (bound, count - before.get(i).map_or(0, |b| b.1)))
+            .collect_vec();
+        let count = buckets.last().map_or(0, |b| b.1);
+        if count == 0 {
+            return Self::default();
+        }
+        let percentile = |q: f64| {
+            let rank = ((count as f64 * q).ceil() as u64).max(1);
+            buckets.iter().find(|b| b.1 >= rank).unwrap().0
+        };
+        Self {
+            count,
+            min: percentile(0.0),
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: percentile(1.0),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct BenchmarkReport {
+    pub name: String,
+    pub num_txs: u64,
+    /// Transactions per second of virtual time.
+    pub throughput: f64,
+    /// Seconds of virtual time.
+    pub latency: Distribution,
+    /// Seconds of real time.
+    pub apply_time: Distribution,
+    /// Seconds of virtual time between the blocks produced during the run.
+    #[serde(default)]
+    pub block_interval: Distribution,
+}
+
+impl BenchmarkReport {
+    /// Returns the descriptions of the metrics which got worse than the
+    /// baseline by more than the tolerance.
+    fn regressions(&self, baseline: &BenchmarkReport) -> Vec<String> {
+        let mut regressions = vec![];
+        if self.throughput < baseline.throughput * (1.0 - THROUGHPUT_TOLERANCE) {
+            regressions.push(format!(
+                "throughput {:.2} is below the baseline {:.2}",
+                self.throughput, baseline.throughput
+            ));
+        }
+        let mut check = |metric: &str, value: f64, baseline: f64, tolerance: f64| {
+            if value > baseline * (1.0 + tolerance) {
+                regressions
+                    .push(format!("{metric} {value:.4} is above the baseline {baseline:.4}"));
+            }
+        };
+        check("latency p50", self.latency.p50, baseline.latency.p50, LATENCY_TOLERANCE);
+        check("latency p90", self.latency.p90, baseline.latency.p90, LATENCY_TOLERANCE);
+        check("latency p99", self.latency.p99, baseline.latency.p99, LATENCY_TOLERANCE);
+        check("apply time p50", self.apply_time.p50, baseline.apply_time.p50, APPLY_TIME_TOLERANCE);
+        check("apply time p90", self.apply_time.p90, baseline.apply_time.p90, APPLY_TIME_TOLERANCE);
+        regressions
+    }
+}
+
+/// Returns the cumulative bucket counts of the histogram summed over its
+/// labels, the last bucket being the total count.
+fn histogram_buckets(name: &str) -> Vec<(f64, u64)> {
+    let Some(family) = prometheus::gather().into_iter().find(|m| m.get_name() == name) else {
+        return vec![];
+    };
+    let mut buckets: Vec<(f64, u64)> = vec![];
+    for metric in family.get_metric() {
+        let histogram = metric.get_histogram();
+        let cumulative = histogram
+            .get_bucket()
+            .iter()
+            .map(|b| (b.get_upper_bound(), b.get_cumulative_count()))
+            // The values above the last bound are counted at that bound.
+            .chain(std::iter::once((f64::NAN, histogram.get_sample_count())));
+        for (i, (bound, count)) in cumulative.enumerate() {
+            if i == buckets.len() {
+                buckets.push((bound, 0));
+            }
+            buckets[i].1 += count;
+        }
+    }
+    if let [.., last_bound, last] = buckets.as_mut_slice() {
+        last.0 = last_bound.0;
+    }
+    buckets
+}
+
+/// Sets up `NUM_VALIDATORS` validators tracking all shards, with the given
+/// accounts and shard boundaries.
+fn setup(accounts: &[AccountId], boundary_accounts: &[&str]) -> TestLoopEnv {
+    setup_with_config(accounts, boundary_accounts, |_, _| {})
+}
+
+/// Like `setup`, with `config_modifier` applied to the config of every node.
+fn setup_with_config(
+    accounts: &[AccountId],
+    boundary_accounts: &[&str],
+    config_modifier: impl Fn(&mut ClientConfig, usize) + 'static,
+) -> TestLoopEnv {
+    let clients = accounts.iter().take(NUM_VALIDATORS).cloned().collect_vec();
+    let validators_spec =
+        ValidatorsSpec::desired_roles(&clients.iter().map(|a| a.as_str()).collect_vec(), &[]);
+    let boundary_accounts = boundary_accounts.iter().map(|a| a.parse().unwrap()).collect();
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .epoch_length(100)
+        .shard_layout(ShardLayout::multi_shard_custom(boundary_accounts, 1))
+        .validators_spec(validators_spec)
+        .add_user_accounts_simple(accounts, 1_000_000 * ONE_NEAR)
+        .genesis_height(10000)
+        .transaction_validity_period(1000)
+        .build();
+    let epoch_config_store =
+        TestEpochConfigBuilder::from_genesis(&genesis).build_store_for_genesis_protocol_version();
+    TestLoopBuilder::new()
+        .genesis(genesis)
+        .epoch_config_store(epoch_config_store)
+        .clients(clients)
+        .config_modifier(config_modifier)
+        .track_all_shards()
+        .build()
+        .warmup()
+}
+
+/// Submits all the transactions at once to the first node and waits until
+/// they are executed, retrying the ones rejected because of congestion.
+/// Panics if any of them fails.
+fn run_benchmark(
+    name: &str,
+    env: &mut TestLoopEnv,
+    txs: Vec<SignedTransaction>,
+    maximum_duration: Duration,
+) -> BenchmarkReport {
+    let num_txs = txs.len();
+    let mut runners = txs.into_iter().map(|tx| TransactionRunner::new(tx, true)).collect_vec();
+    let mut latencies: Vec<Option<Duration>> = vec![None; num_txs];
+
+    let clock = env.test_loop.clock();
+    let client_handle = env.node_datas[0].client_sender.actor_handle();
+    let rpc_sender = &env.node_datas[0].rpc_handler_sender;
+    let future_spawner = env.test_loop.future_spawner("Benchmark");
+    let apply_time_before = histogram_buckets(APPLY_TIME_METRIC);
+    let start_height = env.test_loop.data.get(&client_handle).client.chain.head().unwrap().height;
+    let start = clock.now();
+    env.test_loop.run_until(
+        |test_loop_data| {
+            let client = &test_loop_data.get(&client_handle).client;
+            for (runner, latency) in runners.iter_mut().zip(latencies.iter_mut()) {
+                if latency.is_some() {
+                    continue;
+                }
+                let Poll::Ready(result) = runner.poll(rpc_sender, client, &future_spawner) else {
+                    continue;
+                };
+                let status = result.expect("transaction rejected").status;
+                assert!(
+                    matches!(status, FinalExecutionStatus::SuccessValue(_)),
+                    "transaction failed: {status:?}"
+                );
+                *latency = Some(clock.now() - start);
+            }
+            latencies.iter().all(Option::is_some)
+        },
+        maximum_duration,
+    );
+    let apply_time_after = histogram_buckets(APPLY_TIME_METRIC);
+
+    let chain = &env.test_loop.data.get(&client_handle).client.chain;
+    let end_height = chain.head().unwrap().height;
+    let block_times = (start_height..=end_height)
+        .filter_map(|height| chain.get_block_header_by_height(height).ok())
+        .map(|header| header.raw_timestamp())
+        .collect_vec();
+    let block_intervals = block_times
+        .iter()
+        .tuple_windows()
+        .map(|(prev, next)| next.saturating_sub(*prev) as f64 / 1e9)
+        .collect_vec();
+
+    let latencies = latencies.into_iter().map(|l| l.unwrap().as_seconds_f64()).collect_vec();
+    let duration = latencies.iter().copied().fold(0.0, f64::max);
+    BenchmarkReport {
+        name: name.to_string(),
+        num_txs: num_txs as u64,
+        throughput: if duration > 0.0 { num_txs as f64 / duration } else { 0.0 },
+        latency: Distribution::from_samples(latencies),
+        apply_time: Distribution::from_histogram(&apply_time_before, &apply_time_after),
+        block_interval: Distribution::from_samples(block_intervals),
+    }
+}
+
+fn baseline_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res/benchmarks").join(format!("{name}.json"))
+}
+
+fn write_report(path: &PathBuf, report: &BenchmarkReport) {
+    let json = serde_json::to_string_pretty(report).unwrap();
+    std::fs::write(path, json + "\n")
+        .unwrap_or_else(|err| panic!("failed to write {}: {err}", path.display()));
+}
+
+fn read_baseline(name: &str) -> BenchmarkReport {
+    let path = baseline_path(name);
+    let baseline = std::fs::read(&path).unwrap_or_else(|err| {
+        panic!(
+            "no baseline at {}: {err}, record it with NEAR_BENCHMARK_UPDATE_BASELINES",
+            path.display()
+        )
+    });
+    serde_json::from_slice(&baseline).unwrap()
+}
+
+/// Writes the report and compares it against its baseline, see the module
+/// documentation.
+fn finish_benchmark(report: &BenchmarkReport) {
+    tracing::info!(target: "test", ?report, "benchmark finished");
+    if let Some(dir) = std::env::var_os("NEAR_BENCHMARK_OUTPUT_DIR") {
+        let dir = PathBuf::from(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        write_report(&dir.join(format!("{}.json", report.name)), report);
+    }
+
+    let baseline_path = baseline_path(&report.name);
+    if std::env::var_os("NEAR_BENCHMARK_UPDATE_BASELINES").is_some() {
+        write_report(&baseline_path, report);
+        return;
+    }
+    let baseline = read_baseline(&report.name);
+    assert_eq!(
+        report.num_txs, baseline.num_txs,
+        "{} doesn't run the workload of its baseline, update it",
+        report.name
+    );
+    let regressions = report.regressions(&baseline);
+    assert!(regressions.is_empty(), "{} regressed: {}", report.name, regressions.join(", "));
+}
+
+#[test]
+fn test_distribution() {
+    let samples = (1..=100).map(|i| i as f64).collect_vec();
+    let distribution = Distribution::from_samples(samples);
+    assert_eq!(
+        distribution,
+        Distribution { count: 100, min: 1.0, p50: 51.0, p90: 90.0, p99: 99.0, max: 100.0 }
+    );
+
+    // 2 values in (0, 1], 7 in (1, 2] and 1 above 2, counted as 2.
+    let before = [(1.0, 1), (2.0, 1), (2.0, 2)];
+    let after = [(1.0, 3), (2.0, 10), (2.0, 12)];
+    let distribution = Distribution::from_histogram(&before, &after);
+    assert_eq!(
+        distribution,
+        Distribution { count: 10, min: 1.0, p50: 2.0, p90: 2.0, p99: 2.0, max: 2.0 }
+    );
+}
+
+#[test]
+fn test_regressions() {
+    let baseline = BenchmarkReport {
+        name: "test".to_string(),
+        num_txs: 100,
+        throughput: 10.0,
+        latency: Distribution { count: 100, min: 1.0, p50: 2.0, p90: 3.0, p99: 4.0, max: 5.0 },
+        apply_time: Distribution { count: 10, min: 0.1, p50: 0.2, p90: 0.3, p99: 0.4, max: 0.5 },
+        block_interval: Distribution::default(),
+    };
+    assert!(baseline.regressions(&baseline).is_empty());
+
+    let mut report = baseline.clone();
+    report.throughput = 9.5;
+    report.apply_time.p50 = 0.25;
+    assert!(report.regressions(&baseline).is_empty());
+
+    report.throughput = 8.0;
+    report.latency.p90 = 4.0;
+    assert_eq!(report.regressions(&baseline).len(), 2);
+}
+
+#[test]
+fn test_baselines() {
+    for name in BENCHMARKS {
+        let baseline = read_baseline(name);
+        assert_eq!(baseline.name, name);
+        assert!(baseline.num_txs > 0 && baseline.throughput > 0.0, "{name}");
+        assert!(baseline.latency.p50 > 0.0 && baseline.apply_time.p50 > 0.0, "{name}");
+    }
+}
@@ -0,0 +1,55 @@
+use itertools::Itertools;
+use near_async::time::Duration;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::hash::CryptoHash;
+use near_primitives::test_utils::create_user_test_signer;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+
+use super::{finish_benchmark, run_benchmark, setup};
+use crate::utils::ONE_NEAR;
+use crate::utils::transactions::{get_shared_block_hash, make_accounts};
+
+pub(super) const NUM_ACCOUNTS: usize = 200;
+pub(super) const BOUNDARY_ACCOUNTS: [&str; 3] = ["account3", "account5", "account7"];
+const TXS_PER_ACCOUNT: u64 = 5;
+
+/// Every account sends a few transfers to the next one, so that all the shards
+/// get local and cross-shard transfers.
+pub(super) fn transfer_storm_txs(
+    accounts: &[AccountId],
+    block_hash: CryptoHash,
+) -> Vec<SignedTransaction> {
+    accounts
+        .iter()
+        .circular_tuple_windows()
+        .flat_map(|(sender, receiver)| {
+            let signer = create_user_test_signer(sender);
+            (1..=TXS_PER_ACCOUNT).map(move |nonce| {
+                SignedTransaction::send_money(
+                    nonce,
+                    sender.clone(),
+                    receiver.clone(),
+                    &signer,
+                    ONE_NEAR,
+                    block_hash,
+                )
+            })
+        })
+        .collect_vec()
+}
+
+#[test]
+#[ignore]
+fn benchmark_transfer_storm() {
+    init_test_logger();
+    let accounts = make_accounts(NUM_ACCOUNTS);
+    let mut env = setup(&accounts, &BOUNDARY_ACCOUNTS);
+
+    let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+    let txs = transfer_storm_txs(&accounts, block_hash);
+    let report = run_benchmark("transfer_storm", &mut env, txs, Duration::seconds(120));
+    finish_benchmark(&report);
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
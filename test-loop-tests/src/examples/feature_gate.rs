@@ -0,0 +1,67 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use itertools::Itertools;
+use near_async::time::Duration;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::AccountId;
+use near_primitives::version::ProtocolFeature;
+
+use crate::utils::ONE_NEAR;
+use crate::utils::protocol_versions::{FeatureGateCase, ProtocolVersionMatrix};
+
+const EPOCH_LENGTH: u64 = 10;
+
+/// Runs the same network before, after and across the upgrade to a protocol
+/// feature and checks which epochs have the feature enabled.
+#[test]
+fn slow_test_feature_gate_matrix() {
+    init_test_logger();
+    ProtocolVersionMatrix::new(ProtocolFeature::ReducedGasRefunds).run(|case| {
+        let accounts =
+            (0..4).map(|i| format!("account{}", i).parse().unwrap()).collect::<Vec<AccountId>>();
+        let validators_spec =
+            ValidatorsSpec::desired_roles(&accounts.iter().map(|a| a.as_str()).collect_vec(), &[]);
+        let genesis = case
+            .genesis_builder()
+            .epoch_length(EPOCH_LENGTH)
+            .shard_layout(ShardLayout::multi_shard(2, 1))
+            .validators_spec(validators_spec)
+            .add_user_accounts_simple(&accounts, 1_000_000 * ONE_NEAR)
+            .build();
+        let epoch_config_store = TestEpochConfigBuilder::build_store_from_genesis(&genesis);
+        let mut env = case
+            .test_loop_builder()
+            .genesis(genesis)
+            .epoch_config_store(epoch_config_store)
+            .clients(accounts)
+            .build()
+            .warmup();
+
+        let client_handle = env.node_datas[0].client_sender.actor_handle();
+        let feature_enabled_in_epochs = RefCell::new(BTreeSet::new());
+        env.test_loop.run_until(
+            |test_loop_data| {
+                let client = &test_loop_data.get(&client_handle).client;
+                let tip = client.chain.head().unwrap();
+                let protocol_version =
+                    client.epoch_manager.get_epoch_protocol_version(&tip.epoch_id).unwrap();
+                feature_enabled_in_epochs
+                    .borrow_mut()
+                    .insert(case.feature_enabled(protocol_version));
+                tip.height >= 4 * EPOCH_LENGTH
+            },
+            Duration::seconds(5 * EPOCH_LENGTH as i64),
+        );
+
+        let expected = match case.case {
+            FeatureGateCase::Disabled => BTreeSet::from([false]),
+            FeatureGateCase::Enabled => BTreeSet::from([true]),
+            FeatureGateCase::Upgrade => BTreeSet::from([false, true]),
+        };
+        assert_eq!(feature_enabled_in_epochs.into_inner(), expected, "{case:?}");
+        env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+    });
+}
@@ -1,7 +1,10 @@
+mod feature_gate;
 #[cfg(feature = "test_features")]
 mod missing_chunk;
 mod multinode;
+mod real_validators;
 mod resharding;
 mod restart_node;
 mod simple;
+mod snapshot;
 mod validator_rotation;
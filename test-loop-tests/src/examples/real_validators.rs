@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use near_async::time::Duration;
+use near_chain_configs::test_genesis::{
+    TestEpochConfigBuilder, ValidatorsSpec, num_shards_of_epoch, read_epoch_validator_info,
+};
+use near_crypto::{KeyType, PublicKey};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::{AccountId, Balance, ShardId};
+use near_primitives::views::{CurrentEpochValidatorInfo, EpochValidatorInfo};
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::utils::ONE_NEAR;
+
+const EPOCH_LENGTH: u64 = 10;
+
+fn validator(account_id: &str, stake: Balance, shards: &[u64]) -> CurrentEpochValidatorInfo {
+    let shards_produced = shards.iter().copied().map(ShardId::new).collect_vec();
+    CurrentEpochValidatorInfo {
+        account_id: account_id.parse().unwrap(),
+        public_key: PublicKey::empty(KeyType::ED25519),
+        is_slashed: false,
+        stake: stake * ONE_NEAR,
+        shards_produced: shards_produced.clone(),
+        num_produced_blocks: 0,
+        num_expected_blocks: 0,
+        num_produced_chunks: 0,
+        num_expected_chunks: 0,
+        num_produced_chunks_per_shard: vec![],
+        num_expected_chunks_per_shard: vec![],
+        num_produced_endorsements: 0,
+        num_expected_endorsements: 0,
+        num_produced_endorsements_per_shard: vec![],
+        num_expected_endorsements_per_shard: vec![],
+        shards_endorsed: shards_produced,
+        delegation: None,
+    }
+}
+
+/// Writes a response of the `validators` RPC method as it would be exported
+/// from a real network, with a few large validators and a long tail of small
+/// ones.
+fn export_epoch_validator_info(path: &std::path::Path) {
+    let current_validators = vec![
+        validator("figment.poolv1.near", 40_000_000, &[0]),
+        validator("astro-stakers.poolv1.near", 25_000_000, &[1]),
+        validator("bisontrails.poolv1.near", 12_000_000, &[0]),
+        validator("zavodil.poolv1.near", 8_000_000, &[1]),
+        validator("epic.poolv1.near", 5_000_000, &[0]),
+        validator("staked.poolv1.near", 3_000_000, &[1]),
+        validator("small1.poolv1.near", 1_000_000, &[]),
+        validator("small2.poolv1.near", 500_000, &[]),
+    ];
+    let info = EpochValidatorInfo {
+        current_validators,
+        next_validators: vec![],
+        current_fishermen: vec![],
+        next_fishermen: vec![],
+        current_proposals: vec![],
+        prev_epoch_kickout: vec![],
+        epoch_start_height: 1000,
+        epoch_height: 10,
+    };
+    let response = serde_json::json!({"jsonrpc": "2.0", "id": "dontcare", "result": info});
+    std::fs::write(path, serde_json::to_string_pretty(&response).unwrap()).unwrap();
+}
+
+/// Runs a network with the validators of an exported epoch and checks that
+/// they get the same roles and stakes as in the export.
+#[test]
+fn test_validators_from_exported_epoch() {
+    init_test_logger();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("validators.json");
+    export_epoch_validator_info(&path);
+
+    let info = read_epoch_validator_info(&path).unwrap();
+    let validators_spec = ValidatorsSpec::from_epoch_validator_info(&info);
+    let accounts =
+        info.current_validators.iter().map(|validator| validator.account_id.clone()).collect_vec();
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .epoch_length(EPOCH_LENGTH)
+        .shard_layout(ShardLayout::multi_shard(num_shards_of_epoch(&info), 1))
+        .validators_spec(validators_spec)
+        .add_user_accounts_simple(&accounts, 1_000_000 * ONE_NEAR)
+        .build();
+    let epoch_config_store = TestEpochConfigBuilder::build_store_from_genesis(&genesis);
+    let mut env = TestLoopBuilder::new()
+        .genesis(genesis)
+        .epoch_config_store(epoch_config_store)
+        .clients(accounts)
+        .build()
+        .warmup();
+
+    let client_handle = env.node_datas[0].client_sender.actor_handle();
+    let client = &env.test_loop.data.get(&client_handle).client;
+    let epoch_id = client.chain.head().unwrap().epoch_id;
+    let epoch_manager = &client.epoch_manager;
+    let expected_stakes = info
+        .current_validators
+        .iter()
+        .map(|validator| (validator.account_id.clone(), validator.stake))
+        .collect::<HashSet<_>>();
+    let stakes = epoch_manager
+        .get_epoch_all_validators(&epoch_id)
+        .unwrap()
+        .into_iter()
+        .map(|validator| (validator.account_id().clone(), validator.stake()))
+        .collect::<HashSet<_>>();
+    assert_eq!(stakes, expected_stakes);
+    let expected_chunk_producers = info
+        .current_validators
+        .iter()
+        .filter(|validator| !validator.shards_produced.is_empty())
+        .map(|validator| validator.account_id.clone())
+        .collect::<HashSet<_>>();
+    let chunk_producers = epoch_manager
+        .get_epoch_chunk_producers(&epoch_id)
+        .unwrap()
+        .into_iter()
+        .map(|validator| validator.take_account_id())
+        .collect::<HashSet<AccountId>>();
+    assert_eq!(chunk_producers, expected_chunk_producers);
+
+    let target_height = 3 * EPOCH_LENGTH;
+    env.test_loop.run_until(
+        |test_loop_data| {
+            test_loop_data.get(&client_handle).client.chain.head().unwrap().height >= target_height
+        },
+        Duration::seconds(3 * EPOCH_LENGTH as i64),
+    );
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
@@ -1,7 +1,7 @@
 use near_async::messaging::{IntoMultiSender, IntoSender, LateBoundSender, noop};
 use near_async::test_loop::TestLoopV2;
 use near_async::time::Duration;
-use near_chain::ChainGenesis;
+use near_chain::{ChainGenesis, DoomslugThresholdMode};
 use near_chain_configs::test_genesis::{TestGenesisBuilder, ValidatorsSpec};
 use near_chain_configs::{ClientConfig, MutableConfigValue, TrackedShardsConfig};
 use near_chunks::shards_manager_actor::ShardsManagerActor;
@@ -97,7 +97,7 @@ fn test_client_with_simple_test_loop() {
         noop().into_multi_sender(),
         shards_manager_adapter.as_sender(),
         validator_signer.clone(),
-        true,
+        DoomslugThresholdMode::TwoThirds,
         [0; 32],
         None,
         Arc::new(test_loop.async_computation_spawner("node0", |_| Duration::milliseconds(80))),
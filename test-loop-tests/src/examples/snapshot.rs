@@ -0,0 +1,69 @@
+use itertools::Itertools;
+use near_async::time::Duration;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::types::{AccountId, BlockHeight};
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::setup::env::TestLoopEnv;
+use crate::utils::ONE_NEAR;
+
+const NUM_CLIENTS: usize = 4;
+const EPOCH_LENGTH: u64 = 5;
+
+fn builder() -> TestLoopBuilder {
+    let accounts = (0..NUM_CLIENTS).map(|i| format!("account{}", i).parse().unwrap()).collect_vec();
+    let validators_spec =
+        ValidatorsSpec::desired_roles(&accounts.iter().map(AccountId::as_str).collect_vec(), &[]);
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .epoch_length(EPOCH_LENGTH)
+        .validators_spec(validators_spec)
+        .add_user_accounts_simple(&accounts, 1_000_000 * ONE_NEAR)
+        .build();
+    let epoch_config_store =
+        TestEpochConfigBuilder::from_genesis(&genesis).build_store_for_genesis_protocol_version();
+    TestLoopBuilder::new().genesis(genesis).epoch_config_store(epoch_config_store).clients(accounts)
+}
+
+fn head_height(env: &TestLoopEnv) -> BlockHeight {
+    let client_handle = env.node_datas[0].client_sender.actor_handle();
+    env.test_loop.data.get(&client_handle).client.chain.head().unwrap().height
+}
+
+/// Runs a network for a few epochs, saves a snapshot of it and checks that
+/// several networks restored from the snapshot continue the same chain.
+#[test]
+fn test_snapshot_and_restore() {
+    init_test_logger();
+    let snapshot_dir = tempfile::tempdir().unwrap();
+
+    let mut env = builder().build().warmup();
+    let snapshot_height = 3 * EPOCH_LENGTH;
+    let client_handle = env.node_datas[0].client_sender.actor_handle();
+    env.test_loop.run_until(
+        |test_loop_data| {
+            test_loop_data.get(&client_handle).client.chain.head().unwrap().height
+                >= snapshot_height
+        },
+        Duration::seconds(3 * EPOCH_LENGTH as i64),
+    );
+    let snapshot_height = head_height(&env);
+    let snapshot_final_head =
+        env.test_loop.data.get(&client_handle).client.chain.final_head().unwrap();
+    env.save_snapshot(snapshot_dir.path());
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+
+    for _ in 0..2 {
+        let mut env = builder().restore_from_snapshot(snapshot_dir.path()).build();
+        assert_eq!(head_height(&env), snapshot_height);
+        env.test_loop.run_for(Duration::seconds(2 * EPOCH_LENGTH as i64));
+        assert!(head_height(&env) > snapshot_height + EPOCH_LENGTH);
+        for data in &env.node_datas {
+            let client = &env.test_loop.data.get(&data.client_sender.actor_handle()).client;
+            let block_hash =
+                client.chain.get_block_hash_by_height(snapshot_final_head.height).unwrap();
+            assert_eq!(block_hash, snapshot_final_head.last_block_hash);
+        }
+        env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+    }
+}
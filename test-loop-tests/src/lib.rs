@@ -1,4 +1,5 @@
 #![cfg(test)]
+mod benchmarks;
 mod examples;
 mod setup;
 mod tests;
@@ -1,7 +1,7 @@
 use itertools::Itertools;
 use near_chain_configs::test_genesis::TestGenesisBuilder;
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tempfile::TempDir;
@@ -25,6 +25,7 @@ use crate::utils::peer_manager_actor::{TestLoopNetworkSharedState, UnreachableAc
 
 use super::env::TestLoopEnv;
 use super::setup::setup_client;
+use super::snapshot::TestLoopSnapshot;
 use super::state::{NodeSetupState, SharedState};
 
 pub(crate) const MIN_BLOCK_PROD_TIME: u64 = 600;
@@ -56,6 +57,8 @@ pub(crate) struct TestLoopBuilder {
     /// Upgrade schedule which determines when the clients start voting for new protocol versions.
     /// If not explicitly set, the chain_id from genesis determines the schedule.
     upgrade_schedule: Option<ProtocolUpgradeVotingSchedule>,
+    /// Snapshot to start the nodes from instead of genesis.
+    snapshot: Option<TestLoopSnapshot>,
 }
 
 impl TestLoopBuilder {
@@ -74,6 +77,7 @@ impl TestLoopBuilder {
             track_all_shards: false,
             load_memtries_for_tracked_shards: true,
             upgrade_schedule: None,
+            snapshot: None,
         }
     }
 
@@ -156,6 +160,15 @@ impl TestLoopBuilder {
         self
     }
 
+    /// Start the nodes from a snapshot saved with `TestLoopEnv::save_snapshot`
+    /// instead of genesis. The genesis, epoch configs and clients must be the
+    /// same as in the test which saved the snapshot. The chain is already
+    /// warmed up, so warmup is skipped.
+    pub fn restore_from_snapshot(mut self, dir: &Path) -> Self {
+        self.snapshot = Some(TestLoopSnapshot::open(dir));
+        self.skip_warmup()
+    }
+
     /// Build the test loop environment.
     pub(crate) fn build(self) -> TestLoopEnv {
         self.ensure_genesis().ensure_epoch_config_store().ensure_clients().build_impl()
@@ -180,7 +193,7 @@ impl TestLoopBuilder {
         self
     }
 
-    fn build_impl(self) -> TestLoopEnv {
+    fn build_impl(mut self) -> TestLoopEnv {
         let warmup_pending = self.warmup_pending.clone();
         self.test_loop.send_adhoc_event("warmup_pending".into(), move |_| {
             assert!(
@@ -189,9 +202,20 @@ impl TestLoopBuilder {
             );
         });
 
-        let node_states =
+        let mut node_states =
             (0..self.clients.len()).map(|idx| self.setup_node_state(idx)).collect_vec();
+        let snapshot = self.snapshot.take();
+        if let Some(snapshot) = &snapshot {
+            for node_state in &mut node_states {
+                assert!(node_state.split_store.is_none(), "archival nodes can't be restored");
+                node_state.store = snapshot.load_store(&node_state.account_id);
+            }
+        }
         let (mut test_loop, shared_state) = self.setup_shared_state();
+        if let Some(snapshot) = &snapshot {
+            snapshot.restore_clock(&mut test_loop);
+            snapshot.restore_shared_files(&shared_state);
+        }
         let datas = node_states
             .into_iter()
             .map(|node_state| {
@@ -199,6 +223,9 @@ impl TestLoopBuilder {
                 setup_client(account_id.as_str(), &mut test_loop, node_state, &shared_state)
             })
             .collect_vec();
+        if let Some(snapshot) = &snapshot {
+            snapshot.restore_tx_pools(&test_loop, &datas);
+        }
 
         TestLoopEnv { test_loop, node_datas: datas, shared_state }
     }
@@ -2,4 +2,5 @@ pub mod builder;
 pub mod drop_condition;
 pub mod env;
 pub mod setup;
+pub mod snapshot;
 pub mod state;
@@ -3,13 +3,14 @@ use std::sync::Arc;
 use near_async::messaging::{IntoMultiSender, IntoSender, LateBoundSender, noop};
 use near_async::test_loop::TestLoopV2;
 use near_async::time::Duration;
-use near_chain::ChainGenesis;
 use near_chain::resharding::resharding_actor::ReshardingActor;
 use near_chain::runtime::NightshadeRuntime;
 use near_chain::state_snapshot_actor::{
-    SnapshotCallbacks, StateSnapshotActor, get_delete_snapshot_callback, get_make_snapshot_callback,
+    SnapshotCallbacks, StateSnapshotActor, get_delete_snapshot_callback,
+    get_make_restore_point_callback, get_make_snapshot_callback,
 };
 use near_chain::types::RuntimeAdapter;
+use near_chain::{ChainGenesis, DoomslugThresholdMode};
 use near_chain_configs::{MutableConfigValue, ReshardingHandle};
 use near_chunks::shards_manager_actor::ShardsManagerActor;
 use near_client::client_actor::ClientActorInner;
@@ -17,9 +18,11 @@ use near_client::gc_actor::GCActor;
 use near_client::sync_jobs_actor::SyncJobsActor;
 use near_client::{
     Client, PartialWitnessActor, RpcHandler, RpcHandlerConfig, ViewClientActorInner,
+    shadow_validation_network_adapter,
 };
 use near_epoch_manager::EpochManager;
 use near_epoch_manager::shard_tracker::ShardTracker;
+use near_network::types::PeerManagerAdapter;
 use near_primitives::genesis::GenesisId;
 use near_primitives::network::PeerId;
 use near_primitives::test_utils::create_test_signer;
@@ -60,6 +63,12 @@ pub fn setup_client(
     let partial_witness_adapter = LateBoundSender::new();
     let sync_jobs_adapter = LateBoundSender::new();
     let resharding_sender = LateBoundSender::new();
+    // The network adapter of the actors doing the validator duties.
+    let validator_network_adapter: PeerManagerAdapter = if client_config.shadow_validation {
+        shadow_validation_network_adapter(network_adapter.as_multi_sender())
+    } else {
+        network_adapter.as_multi_sender()
+    };
 
     let homedir = tempdir.path().join(format!("{}", identifier));
     std::fs::create_dir_all(&homedir).expect("Unable to create homedir");
@@ -104,7 +113,13 @@ pub fn setup_client(
         state_snapshot_adapter.as_multi_sender(),
         runtime_adapter.get_flat_storage_manager(),
     );
-    let snapshot_callbacks = SnapshotCallbacks { make_snapshot_callback, delete_snapshot_callback };
+    let make_restore_point_callback =
+        get_make_restore_point_callback(state_snapshot_adapter.as_multi_sender());
+    let snapshot_callbacks = SnapshotCallbacks {
+        make_snapshot_callback,
+        delete_snapshot_callback,
+        make_restore_point_callback,
+    };
 
     let validator_signer = MutableConfigValue::new(
         Some(Arc::new(create_test_signer(account_id.as_str()))),
@@ -128,10 +143,10 @@ pub fn setup_client(
         epoch_manager.clone(),
         shard_tracker.clone(),
         runtime_adapter.clone(),
-        network_adapter.as_multi_sender(),
+        validator_network_adapter.clone(),
         client_to_shards_manager_sender.as_sender(),
         validator_signer.clone(),
-        true,
+        DoomslugThresholdMode::TwoThirds,
         [0; 32],
         Some(snapshot_callbacks),
         Arc::new(test_loop.async_computation_spawner(identifier, |_| Duration::milliseconds(80))),
@@ -178,6 +193,7 @@ pub fn setup_client(
         view_epoch_manager.clone(),
         view_shard_tracker,
         view_runtime_adapter,
+        None,
         network_adapter.as_multi_sender(),
         client_config.clone(),
         near_client::adversarial::Controls::default(),
@@ -190,7 +206,7 @@ pub fn setup_client(
         epoch_manager.clone(),
         view_epoch_manager,
         shard_tracker.clone(),
-        network_adapter.as_sender(),
+        validator_network_adapter.request_sender.clone(),
         client_adapter.as_sender(),
         store.chunk_store(),
         client.chain.head().unwrap(),
@@ -202,7 +218,7 @@ pub fn setup_client(
         test_loop.clock(),
         client,
         peer_id.clone(),
-        network_adapter.as_multi_sender(),
+        validator_network_adapter.clone(),
         noop().into_sender(),
         None,
         Default::default(),
@@ -216,6 +232,7 @@ pub fn setup_client(
         tx_routing_height_horizon: client_config.tx_routing_height_horizon,
         epoch_length: client_config.epoch_length,
         transaction_validity_period: genesis.config.transaction_validity_period,
+        tx_rebroadcast: client_config.tx_rebroadcast.clone(),
     };
     let rpc_handler = RpcHandler::new(
         rpc_handler_config,
@@ -230,13 +247,14 @@ pub fn setup_client(
 
     let partial_witness_actor = PartialWitnessActor::new(
         test_loop.clock(),
-        network_adapter.as_multi_sender(),
+        validator_network_adapter,
         client_adapter.as_multi_sender(),
         validator_signer.clone(),
         epoch_manager.clone(),
         runtime_adapter.clone(),
         Arc::new(test_loop.async_computation_spawner(identifier, |_| Duration::milliseconds(80))),
         Arc::new(test_loop.async_computation_spawner(identifier, |_| Duration::milliseconds(80))),
+        client_config.produced_state_witness_retention_heights,
     );
 
     let peer_manager_actor = TestLoopPeerManagerActor::new(
@@ -259,6 +277,7 @@ pub fn setup_client(
         shard_tracker.clone(),
         validator_signer.clone(),
         client_config.gc.clone(),
+        client_config.node_profile,
         client_config.archive,
     );
     // We don't send messages to `GCActor` so adapter is not needed.
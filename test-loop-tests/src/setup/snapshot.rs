@@ -0,0 +1,170 @@
+//! Snapshots of a running test loop network.
+//!
+//! Setting up a scenario, e.g. reaching a post-resharding state, can take a
+//! large part of a test's run time.  [`TestLoopEnv::save_snapshot`] saves the
+//! stores and transaction pools of all nodes to a directory and
+//! [`super::builder::TestLoopBuilder::restore_from_snapshot`] starts a new
+//! network from it, so that many test cases can share the same setup.
+//!
+//! Events in flight, e.g. network messages or pending approvals, are not part of
+//! the snapshot, so the restored nodes behave as if all of them were restarted
+//! at the same time.  Archival nodes are not supported.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use itertools::Itertools;
+use near_async::test_loop::TestLoopV2;
+use near_async::time::{Duration, FakeClock};
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::transaction::{SignedTransaction, ValidatedTransaction};
+use near_primitives::types::AccountId;
+use near_store::test_utils::create_test_store;
+use near_store::{DBCol, Store};
+use std::path::{Path, PathBuf};
+use strum::IntoEnumIterator;
+
+use super::env::TestLoopEnv;
+use super::state::{NodeExecutionData, SharedState};
+
+const MANIFEST_FILE: &str = "manifest";
+/// Directory with state parts dumped by the nodes, relative to both the
+/// snapshot directory and the test directory.
+const STATE_SYNC_DIR: &str = "state_sync";
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct SnapshotManifest {
+    /// Virtual time elapsed since the start of the test loop, in nanoseconds.
+    elapsed_nanos: i64,
+    accounts: Vec<AccountId>,
+}
+
+fn store_file(dir: &Path, account_id: &AccountId) -> PathBuf {
+    dir.join(format!("{}.store", account_id))
+}
+
+fn tx_pool_file(dir: &Path, account_id: &AccountId) -> PathBuf {
+    dir.join(format!("{}.txs", account_id))
+}
+
+/// Virtual time of a test loop relative to its start.
+fn elapsed(test_loop: &TestLoopV2) -> Duration {
+    test_loop.clock().now_utc() - FakeClock::default().now_utc()
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let to = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &to);
+        } else {
+            std::fs::copy(entry.path(), to).unwrap();
+        }
+    }
+}
+
+impl TestLoopEnv {
+    /// Saves the state of all nodes to `dir`, so that the network can later be
+    /// restored with `TestLoopBuilder::restore_from_snapshot`.  Should be
+    /// called between `run_*` calls, e.g. once `run_until` reached the height
+    /// of interest.
+    pub fn save_snapshot(&self, dir: &Path) {
+        std::fs::create_dir_all(dir).unwrap();
+        let columns = DBCol::iter().collect_vec();
+        // Nodes which were restarted appear several times, the last one is
+        // the one which is running.
+        let node_datas =
+            self.node_datas.iter().rev().unique_by(|data| &data.account_id).collect_vec();
+        let mut accounts = vec![];
+        for data in node_datas.into_iter().rev() {
+            let client = &self.test_loop.data.get(&data.client_sender.actor_handle()).client;
+            assert!(!client.config.archive, "snapshots of archival nodes are not supported");
+            let account_id = &data.account_id;
+            client
+                .chain
+                .chain_store
+                .store()
+                .save_columns_to_file(&columns, &store_file(dir, account_id))
+                .unwrap();
+            let txs: Vec<(ShardUId, SignedTransaction)> = client
+                .chunk_producer
+                .sharded_tx_pool
+                .lock()
+                .transactions()
+                .map(|(shard_uid, tx)| (shard_uid, tx.to_signed_tx().clone()))
+                .collect();
+            std::fs::write(tx_pool_file(dir, account_id), borsh::to_vec(&txs).unwrap()).unwrap();
+            accounts.push(account_id.clone());
+        }
+
+        let state_sync_dir = self.shared_state.tempdir.path().join(STATE_SYNC_DIR);
+        if state_sync_dir.exists() {
+            copy_dir(&state_sync_dir, &dir.join(STATE_SYNC_DIR));
+        }
+
+        let manifest = SnapshotManifest {
+            elapsed_nanos: elapsed(&self.test_loop).whole_nanoseconds().try_into().unwrap(),
+            accounts,
+        };
+        std::fs::write(dir.join(MANIFEST_FILE), borsh::to_vec(&manifest).unwrap()).unwrap();
+        tracing::info!(target: "test_loop", dir = %dir.display(), "Saved test loop snapshot");
+    }
+}
+
+/// Snapshot saved by [`TestLoopEnv::save_snapshot`].
+pub(crate) struct TestLoopSnapshot {
+    dir: PathBuf,
+    manifest: SnapshotManifest,
+}
+
+impl TestLoopSnapshot {
+    pub fn open(dir: &Path) -> Self {
+        let manifest = std::fs::read(dir.join(MANIFEST_FILE)).unwrap();
+        let manifest = SnapshotManifest::try_from_slice(&manifest).unwrap();
+        Self { dir: dir.to_path_buf(), manifest }
+    }
+
+    /// Returns a new store with the contents of the store of given node.
+    pub fn load_store(&self, account_id: &AccountId) -> Store {
+        assert!(
+            self.manifest.accounts.contains(account_id),
+            "node {account_id} is not in the snapshot"
+        );
+        let store = create_test_store();
+        let columns = DBCol::iter().collect_vec();
+        store.load_columns_from_file(&columns, &store_file(&self.dir, account_id)).unwrap();
+        store
+    }
+
+    /// Advances the clock of a new test loop to the time of the snapshot, so
+    /// that the timestamps of new blocks follow the ones in the stores.  Must
+    /// be called before any node is set up.
+    pub fn restore_clock(&self, test_loop: &mut TestLoopV2) {
+        let elapsed = Duration::nanoseconds(self.manifest.elapsed_nanos) - elapsed(test_loop);
+        test_loop.run_for(elapsed);
+    }
+
+    /// Copies files shared by the nodes, e.g. dumped state parts, to the
+    /// directory of the new test.
+    pub fn restore_shared_files(&self, shared_state: &SharedState) {
+        let state_sync_dir = self.dir.join(STATE_SYNC_DIR);
+        if state_sync_dir.exists() {
+            copy_dir(&state_sync_dir, &shared_state.tempdir.path().join(STATE_SYNC_DIR));
+        }
+    }
+
+    /// Puts the saved transactions back into the pools of the nodes.
+    pub fn restore_tx_pools(&self, test_loop: &TestLoopV2, node_datas: &[NodeExecutionData]) {
+        for data in node_datas {
+            let txs = std::fs::read(tx_pool_file(&self.dir, &data.account_id)).unwrap();
+            let txs = Vec::<(ShardUId, SignedTransaction)>::try_from_slice(&txs).unwrap();
+            let client = &test_loop.data.get(&data.client_sender.actor_handle()).client;
+            let mut pool = client.chunk_producer.sharded_tx_pool.lock();
+            for (shard_uid, txs) in txs.into_iter().into_group_map() {
+                // The transactions were validated before they got into the pool.
+                let txs = txs.into_iter().map(ValidatedTransaction::new_for_test);
+                pool.reintroduce_transactions(shard_uid, txs);
+            }
+        }
+    }
+}
@@ -314,6 +314,7 @@ fn slow_test_chunks_produced_and_distributed_chunk_distribution_network_disabled
     let config = ChunkDistributionNetworkConfig {
         enabled: false,
         uris: ChunkDistributionUris { set: String::new(), get: String::new() },
+        ..Default::default()
     };
     Test {
         min_validators: 1,
@@ -335,6 +336,7 @@ fn slow_test_chunks_produced_and_distributed_chunk_distribution_network_wrong_ur
             set: "http://www.fake-set-url.com".into(),
             get: "http://www.fake-get-url.com".into(),
         },
+        ..Default::default()
     };
     Test {
         min_validators: 1,
@@ -354,6 +356,7 @@ fn slow_test_chunks_produced_and_distributed_chunk_distribution_network_incorrec
     let config = ChunkDistributionNetworkConfig {
         enabled: false,
         uris: ChunkDistributionUris { set: String::new(), get: "https://www.google.com".into() },
+        ..Default::default()
     };
     Test {
         min_validators: 1,
@@ -23,8 +23,14 @@ mod multinode_stateless_validators;
 mod optimistic_block;
 mod process_blocks;
 mod protocol_upgrade;
+mod query_batch;
+mod receipt_audit;
+mod receipt_proof_fragments;
 mod reject_outdated_blocks;
 mod resharding_v3;
+mod shadow_validation;
 mod state_sync;
 mod syncing;
+mod transactions_by_signer;
+mod view_accounts_batch;
 mod view_requests_to_archival_node;
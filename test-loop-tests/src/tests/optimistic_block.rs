@@ -102,6 +102,7 @@ fn make_invalid_ob(env: &TestLoopEnv, adv_type: OptimisticBlockAdvType) -> Optim
         None,
         adv_type,
     )
+    .unwrap()
 }
 
 #[test]
@@ -263,7 +264,8 @@ fn alter_optimistic_block_at_height(
                             OptimisticBlockAdvType::InvalidTimestamp(
                                 optimistic_block.block_timestamp() - 15000000,
                             ),
-                        );
+                        )
+                        .unwrap();
                         return Some(NetworkRequests::OptimisticBlock {
                             chunk_producers: chunk_producers.clone(),
                             optimistic_block: altered_ob,
@@ -0,0 +1,80 @@
+use assert_matches::assert_matches;
+use near_async::messaging::Handler as _;
+use near_async::time::Duration;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_client::{
+    MAX_QUERY_BATCH_EXECUTIONS, MAX_QUERY_BATCH_SIZE, Query, QueryBatch, QueryError,
+};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::{AccountId, BlockReference, Finality};
+use near_primitives::views::QueryRequest;
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::utils::ONE_NEAR;
+
+/// Checks that every request of a batch spanning several shards gets the same
+/// result as the equivalent `Query`, that a failing request doesn't fail the
+/// others, and that batches over the limits are rejected.
+#[test]
+fn test_query_batch() {
+    init_test_logger();
+    let validator: AccountId = "validator0".parse().unwrap();
+    let alice: AccountId = "alice".parse().unwrap();
+    let carol: AccountId = "carol".parse().unwrap();
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .shard_layout(ShardLayout::multi_shard_custom(vec!["b".parse().unwrap()], 0))
+        .validators_spec(ValidatorsSpec::desired_roles(&[validator.as_str()], &[]))
+        .add_user_accounts_simple(&[alice.clone(), carol.clone()], 1_000_000 * ONE_NEAR)
+        .build();
+    let epoch_config_store = TestEpochConfigBuilder::build_store_from_genesis(&genesis);
+    let mut env = TestLoopBuilder::new()
+        .genesis(genesis)
+        .epoch_config_store(epoch_config_store)
+        .clients(vec![validator.clone()])
+        .build()
+        .warmup();
+
+    let view_client_handle = env.node_datas[0].view_client_sender.actor_handle();
+    let view_client = env.test_loop.data.get_mut(&view_client_handle);
+    let block_reference = BlockReference::Finality(Finality::None);
+    let requests = vec![
+        QueryRequest::ViewAccount { account_id: alice.clone() },
+        QueryRequest::ViewAccount { account_id: "unknown".parse().unwrap() },
+        QueryRequest::ViewAccessKeyList { account_id: carol.clone() },
+        QueryRequest::ViewAccount { account_id: carol },
+    ];
+    let results = view_client
+        .handle(QueryBatch { block_reference: block_reference.clone(), requests: requests.clone() })
+        .unwrap();
+    assert_eq!(results.len(), requests.len());
+    assert!(results[1].is_err());
+    for (request, result) in requests.into_iter().zip(results) {
+        let query = view_client.handle(Query::new(block_reference.clone(), request));
+        match (result, query) {
+            (Ok(result), Ok(query)) => assert_eq!(result, query),
+            (Err(result), Err(query)) => assert_eq!(result.to_string(), query.to_string()),
+            (result, query) => panic!("batch returned {result:?}, query returned {query:?}"),
+        }
+    }
+
+    let too_many =
+        vec![QueryRequest::ViewAccount { account_id: alice.clone() }; MAX_QUERY_BATCH_SIZE + 1];
+    assert_matches!(
+        view_client
+            .handle(QueryBatch { block_reference: block_reference.clone(), requests: too_many }),
+        Err(QueryError::BatchTooLarge { .. })
+    );
+    let call = QueryRequest::CallFunction {
+        account_id: alice,
+        method_name: "method".to_string(),
+        args: vec![].into(),
+    };
+    let too_many_calls = vec![call; MAX_QUERY_BATCH_EXECUTIONS + 1];
+    assert_matches!(
+        view_client.handle(QueryBatch { block_reference, requests: too_many_calls }),
+        Err(QueryError::BatchTooLarge { .. })
+    );
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
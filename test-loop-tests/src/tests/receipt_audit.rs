@@ -0,0 +1,53 @@
+use near_async::time::Duration;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::AccountId;
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::utils::ONE_NEAR;
+use crate::utils::transactions::execute_money_transfers;
+
+/// Runs money transfers between the accounts of three shards with the receipt
+/// audit enabled. The audit must see the receipts delivered between the shards
+/// and find no discrepancy on this healthy chain.
+#[test]
+fn test_receipt_audit_without_discrepancies() {
+    init_test_logger();
+    let validator: AccountId = "validator0".parse().unwrap();
+    let accounts: Vec<AccountId> = (0..9).map(|i| format!("account{i}").parse().unwrap()).collect();
+    let boundary_accounts = vec!["account3".parse().unwrap(), "account6".parse().unwrap()];
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .epoch_length(10)
+        .shard_layout(ShardLayout::multi_shard_custom(boundary_accounts, 1))
+        .validators_spec(ValidatorsSpec::desired_roles(&[validator.as_str()], &[]))
+        .add_user_accounts_simple(&accounts, 1_000_000 * ONE_NEAR)
+        .build();
+    let epoch_config_store = TestEpochConfigBuilder::build_store_from_genesis(&genesis);
+    let mut env = TestLoopBuilder::new()
+        .genesis(genesis)
+        .epoch_config_store(epoch_config_store)
+        .clients(vec![validator])
+        .config_modifier(|config, _| {
+            config.enable_receipt_audit = true;
+        })
+        .build()
+        .warmup();
+
+    // Every account sends money to the next one, so that some of the
+    // transfers cross the boundaries of the shards.
+    execute_money_transfers(&mut env.test_loop, &env.node_datas, &accounts).unwrap();
+
+    let client_handle = env.node_datas[0].client_sender.actor_handle();
+    let chain = &env.test_loop.data.get(&client_handle).client.chain;
+    let view = chain.get_receipt_audit_view().unwrap();
+    assert!(view.audited_height.is_some());
+    assert!(
+        view.num_receipts_delivered >= accounts.len() as u64,
+        "{} receipts delivered",
+        view.num_receipts_delivered
+    );
+    assert!(view.discrepancies.is_empty(), "discrepancies: {:?}", view.discrepancies);
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use near_async::time::Duration;
+use near_network::types::NetworkRequests;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::test_utils::create_user_test_signer;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+
+use crate::setup::env::TestLoopEnv;
+use crate::utils::TGAS;
+use crate::utils::setups::standard_setup_1;
+use crate::utils::transactions::{get_shared_block_hash, run_tx};
+
+/// Sends a large cross-shard receipt, whose proof is sent as fragments, and
+/// drops the first fragment sent to every node. The remaining fragments are
+/// enough to reassemble the proof, so the receipt is still executed.
+#[test]
+#[cfg_attr(not(feature = "nightly"), ignore)]
+fn test_receipt_proof_fragments_with_lost_fragment() {
+    init_test_logger();
+    let TestLoopEnv { mut test_loop, node_datas, shared_state } = standard_setup_1();
+
+    let sent_fragments = Arc::new(AtomicUsize::new(0));
+    for node_data in &node_datas {
+        let peer_manager = node_data.peer_manager_sender.actor_handle();
+        let sent_fragments = sent_fragments.clone();
+        test_loop.data.get_mut(&peer_manager).register_override_handler(Box::new(
+            move |request| -> Option<NetworkRequests> {
+                if let NetworkRequests::ReceiptProofFragment { fragment, .. } = &request {
+                    sent_fragments.fetch_add(1, Ordering::Relaxed);
+                    if fragment.fragment_ord == 0 {
+                        return None;
+                    }
+                }
+                Some(request)
+            },
+        ));
+    }
+
+    // account0 and account9 are on different shards, see `standard_setup_1`.
+    let sender: AccountId = "account0".parse().unwrap();
+    let receiver: AccountId = "account9".parse().unwrap();
+    let rpc_id = "account4".parse().unwrap();
+    for (nonce, account) in [(1, &sender), (2, &receiver)] {
+        let deploy_tx = SignedTransaction::deploy_contract(
+            nonce,
+            account,
+            near_test_contracts::rs_contract().into(),
+            &create_user_test_signer(account).into(),
+            get_shared_block_hash(&node_datas, &test_loop.data),
+        );
+        run_tx(&mut test_loop, &rpc_id, deploy_tx, &node_datas, Duration::seconds(5));
+    }
+
+    // The receipt of about 1MB is much larger than the parts of the chunk
+    // which sends it.
+    let large_receipt_tx = SignedTransaction::call(
+        3,
+        sender.clone(),
+        sender.clone(),
+        &create_user_test_signer(&sender).into(),
+        0,
+        "generate_large_receipt".into(),
+        format!(
+            r#"{{"account_id": "{receiver}", "method_name": "noop", "total_args_size": 1000000}}"#
+        )
+        .into(),
+        300 * TGAS,
+        get_shared_block_hash(&node_datas, &test_loop.data),
+    );
+    run_tx(&mut test_loop, &rpc_id, large_receipt_tx, &node_datas, Duration::seconds(10));
+    assert!(sent_fragments.load(Ordering::Relaxed) > 0);
+
+    TestLoopEnv { test_loop, node_datas, shared_state }
+        .shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use near_async::time::Duration;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_network::types::NetworkRequests;
+use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::AccountId;
+use parking_lot::Mutex;
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::utils::ONE_NEAR;
+
+/// Returns the kind of the message if it carries something signed by the
+/// validator key.
+fn signed_message_kind(request: &NetworkRequests) -> Option<&'static str> {
+    let kind = match request {
+        NetworkRequests::Block { .. } => "block",
+        NetworkRequests::OptimisticBlock { .. } => "optimistic_block",
+        NetworkRequests::Approval { .. } => "approval",
+        NetworkRequests::AnnounceAccount(_) => "announce_account",
+        NetworkRequests::PartialEncodedChunkMessage { .. } => "partial_encoded_chunk",
+        NetworkRequests::PartialEncodedChunkForward { .. } => "partial_encoded_chunk_forward",
+        NetworkRequests::ChunkEndorsement(..) => "chunk_endorsement",
+        NetworkRequests::ChunkStateWitnessAck(..) => "chunk_state_witness_ack",
+        NetworkRequests::PartialEncodedStateWitness(_) => "partial_encoded_state_witness",
+        NetworkRequests::PartialEncodedStateWitnessForward(..) => {
+            "partial_encoded_state_witness_forward"
+        }
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn diverted_messages(kind: &str) -> f64 {
+    let Some(family) = near_o11y::metrics::prometheus::gather()
+        .into_iter()
+        .find(|m| m.get_name() == "near_shadow_validation_diverted_messages_total")
+    else {
+        return 0.0;
+    };
+    family
+        .get_metric()
+        .iter()
+        .filter(|metric| metric.get_label().iter().any(|label| label.get_value() == kind))
+        .map(|metric| metric.get_counter().get_value())
+        .sum()
+}
+
+/// Runs one of four validators with `shadow_validation`. The shadow validator
+/// produces blocks, chunks and state witnesses, and validates the chunks it is
+/// assigned to, while the chain keeps going without it and nothing it signs
+/// reaches the network.
+#[test]
+fn test_shadow_validation() {
+    init_test_logger();
+    let accounts: Vec<AccountId> =
+        (0..4).map(|i| format!("validator{i}").parse().unwrap()).collect();
+    let validators: Vec<_> = accounts.iter().map(|account| account.as_str()).collect();
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .epoch_length(10)
+        .shard_layout(ShardLayout::multi_shard(2, 1))
+        .validators_spec(ValidatorsSpec::desired_roles(&validators, &[]))
+        .add_user_accounts_simple(&accounts, 1_000_000 * ONE_NEAR)
+        .build();
+    let epoch_config_store = TestEpochConfigBuilder::build_store_from_genesis(&genesis);
+    let shadow_index = 3;
+    let mut env = TestLoopBuilder::new()
+        .genesis(genesis)
+        .epoch_config_store(epoch_config_store)
+        .clients(accounts)
+        .config_modifier(move |config, client_index| {
+            config.shadow_validation = client_index == shadow_index;
+        })
+        .track_all_shards()
+        // The warmup expects all the chunks to be produced.
+        .skip_warmup()
+        .build();
+
+    let sent = Arc::new(Mutex::new(vec![]));
+    let peer_manager = env.node_datas[shadow_index].peer_manager_sender.actor_handle();
+    env.test_loop.data.get_mut(&peer_manager).register_override_handler(Box::new({
+        let sent = sent.clone();
+        move |request| {
+            if let Some(kind) = signed_message_kind(&request) {
+                sent.lock().push(kind);
+            }
+            Some(request)
+        }
+    }));
+
+    let client = env.node_datas[0].client_sender.actor_handle();
+    let shadow_client = env.node_datas[shadow_index].client_sender.actor_handle();
+    let genesis_height = env.test_loop.data.get(&client).client.chain.genesis().height();
+    env.test_loop.run_until(
+        |test_loop_data| {
+            test_loop_data.get(&client).client.chain.final_head().unwrap().height
+                > genesis_height + 15
+        },
+        Duration::seconds(30),
+    );
+
+    // The shadow validator follows the chain of the network.
+    let head = env.test_loop.data.get(&client).client.chain.head().unwrap();
+    let shadow_head = env.test_loop.data.get(&shadow_client).client.chain.head().unwrap();
+    assert!(shadow_head.height + 1 >= head.height);
+    assert_eq!(sent.lock().as_slice(), &[] as &[&str]);
+    for kind in
+        ["block", "partial_encoded_chunk", "partial_encoded_state_witness", "chunk_endorsement"]
+    {
+        assert!(diverted_messages(kind) > 0.0, "no {kind} diverted");
+    }
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
@@ -2,10 +2,10 @@ use near_async::messaging::{Handler, SendAsync};
 use near_async::test_loop::TestLoopV2;
 use near_async::time::Duration;
 use near_chain::ChainStoreAccess;
-use near_chain_configs::TrackedShardsConfig;
 use near_chain_configs::test_genesis::{
     TestEpochConfigBuilder, TestGenesisBuilder, ValidatorsSpec,
 };
+use near_chain_configs::{LocalDirSyncConfig, SyncConfig, TrackedShardsConfig};
 use near_network::client::{ProcessTxRequest, StateRequestHeader};
 use near_o11y::testonly::init_test_logger;
 use near_primitives::epoch_manager::EpochConfigStore;
@@ -382,7 +382,10 @@ fn run_test(state: TestState) {
     env.shutdown_and_drain_remaining_events(Duration::seconds(3));
 }
 
-fn run_test_with_added_node(state: TestState) {
+/// Adds a node which syncs from scratch. With `sync_from_local_dir` the node reads
+/// the state only from the directory the other nodes dump the state to, as if
+/// it was provided by the operator.
+fn run_test_with_added_node(state: TestState, sync_from_local_dir: bool) {
     let TestState { mut env, mut accounts, skip_block_height } = state;
 
     if let Some(accounts) = accounts.as_mut() {
@@ -453,12 +456,20 @@ fn run_test_with_added_node(state: TestState) {
     let genesis = env.shared_state.genesis.clone();
     let tempdir_path = env.shared_state.tempdir.path().to_path_buf();
     let account_id: AccountId = "sync-from-scratch".parse().unwrap();
+    let local_dir = tempdir_path.join("state_sync");
     let new_node_state = NodeStateBuilder::new(genesis, tempdir_path)
         .account_id(account_id.clone())
         .config_modifier(move |config| {
             // Lower the threshold at which state sync is chosen over block sync
             config.block_fetch_horizon = 5;
             config.tracked_shards_config = TrackedShardsConfig::AllShards;
+            if sync_from_local_dir {
+                config.state_sync.sync = SyncConfig::LocalDir(LocalDirSyncConfig {
+                    root_dir: local_dir.clone(),
+                    num_concurrent_requests: 1,
+                    num_concurrent_requests_during_catchup: 1,
+                });
+            }
         })
         .build();
     env.add_node(account_id.as_str(), new_node_state);
@@ -534,7 +545,7 @@ fn run_state_sync_test_case(t: StateSyncTest) {
         t.skip_block_sync_height_delta,
         &t.extra_node_shard_schedule,
     );
-    run_test_with_added_node(state);
+    run_test_with_added_node(state, false);
 }
 
 // The normal case with 2 nodes and no missing chunks.
@@ -863,6 +874,14 @@ fn slow_test_state_sync_fork_before_sync() {
     run_state_sync_test_case(params);
 }
 
+// A new node syncs the state only from a local directory holding the dumped parts.
+#[test]
+fn slow_test_state_sync_from_local_dir() {
+    init_test_logger();
+    let state = setup_initial_blockchain(2, 2, 2, 2, true, HashMap::new(), None, &None);
+    run_test_with_added_node(state, true);
+}
+
 fn await_sync_hash(env: &mut TestLoopEnv) -> CryptoHash {
     env.test_loop.run_until(
         |data| {
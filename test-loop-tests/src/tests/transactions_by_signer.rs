@@ -0,0 +1,78 @@
+use near_async::time::Duration;
+use near_chain::ChainStoreAccess;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::test_utils::create_user_test_signer;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+use near_store::DBCol;
+use near_store::migrations::MigrationContext;
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::utils::transactions::{get_next_nonce, get_shared_block_hash, run_tx};
+use crate::utils::{ONE_NEAR, get_node_client};
+
+const TRANSACTIONS_BY_SIGNER_LIMIT: usize = 3;
+
+/// Checks that the most recent transactions of a signer are indexed and that
+/// the older ones are dropped from the index once there are too many.
+#[test]
+fn test_transactions_by_signer_index() {
+    init_test_logger();
+    let validator: AccountId = "validator0".parse().unwrap();
+    let sender: AccountId = "sender".parse().unwrap();
+    let receiver: AccountId = "receiver".parse().unwrap();
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .validators_spec(ValidatorsSpec::desired_roles(&[validator.as_str()], &[]))
+        .add_user_accounts_simple(&[sender.clone(), receiver.clone()], 1_000_000 * ONE_NEAR)
+        .build();
+    let epoch_config_store = TestEpochConfigBuilder::build_store_from_genesis(&genesis);
+    let mut env = TestLoopBuilder::new()
+        .genesis(genesis)
+        .epoch_config_store(epoch_config_store)
+        .clients(vec![validator.clone()])
+        .config_modifier(|config, _| {
+            config.transactions_by_signer_limit = Some(TRANSACTIONS_BY_SIGNER_LIMIT);
+        })
+        .build()
+        .warmup();
+
+    let signer = create_user_test_signer(&sender);
+    let mut nonce = get_next_nonce(&env.test_loop.data, &env.node_datas, &sender);
+    let mut tx_hashes = vec![];
+    for _ in 0..5 {
+        let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+        let tx = SignedTransaction::send_money(
+            nonce,
+            sender.clone(),
+            receiver.clone(),
+            &signer,
+            ONE_NEAR,
+            block_hash,
+        );
+        nonce += 1;
+        tx_hashes.push(tx.get_hash());
+        run_tx(&mut env.test_loop, &validator, tx, &env.node_datas, Duration::seconds(5));
+    }
+
+    let client = get_node_client(&env, &validator);
+    let indexed = client.chain.chain_store().get_transactions_by_signer(&sender).unwrap();
+    let indexed_tx_hashes = indexed.iter().map(|(_, tx_hash, _)| *tx_hash).collect::<Vec<_>>();
+    assert_eq!(indexed_tx_hashes, tx_hashes[tx_hashes.len() - TRANSACTIONS_BY_SIGNER_LIMIT..]);
+    for &(height, _, block_hash) in &indexed {
+        assert_eq!(client.chain.get_block_hash_by_height(height).unwrap(), block_hash);
+    }
+    assert!(client.chain.chain_store().get_transactions_by_signer(&receiver).unwrap().is_empty());
+
+    // The migration rebuilds the same index from the stored chunks.
+    let store = client.chain.chain_store().store();
+    let mut store_update = store.store_update();
+    store_update.delete_all(DBCol::TransactionsBySigner);
+    store_update.commit().unwrap();
+    let mut ctx = MigrationContext::new(&store, 46).unwrap();
+    near_chain::migrate_46_to_47(&mut ctx, Some(TRANSACTIONS_BY_SIGNER_LIMIT)).unwrap();
+    ctx.finish().unwrap();
+    assert_eq!(client.chain.chain_store().get_transactions_by_signer(&sender).unwrap(), indexed);
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
@@ -0,0 +1,84 @@
+use assert_matches::assert_matches;
+use near_async::messaging::Handler as _;
+use near_async::time::Duration;
+use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
+use near_client::{MAX_VIEW_ACCOUNTS_BATCH_SIZE, Query, QueryError, ViewAccountsBatch};
+use near_o11y::testonly::init_test_logger;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::test_utils::create_user_test_signer;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, BlockId, BlockReference};
+use near_primitives::views::{QueryRequest, QueryResponseKind};
+
+use crate::setup::builder::TestLoopBuilder;
+use crate::utils::ONE_NEAR;
+use crate::utils::transactions::{get_next_nonce, get_shared_block_hash, run_tx};
+
+/// Checks that the accounts read from flat storage in a batch match the
+/// results of the trie based `Query`, including accounts changed in the
+/// latest blocks and accounts which don't exist.
+#[test]
+fn test_view_accounts_batch() {
+    init_test_logger();
+    let validator: AccountId = "validator0".parse().unwrap();
+    let alice: AccountId = "alice".parse().unwrap();
+    let carol: AccountId = "carol".parse().unwrap();
+    let genesis = TestLoopBuilder::new_genesis_builder()
+        .shard_layout(ShardLayout::multi_shard_custom(vec!["b".parse().unwrap()], 0))
+        .validators_spec(ValidatorsSpec::desired_roles(&[validator.as_str()], &[]))
+        .add_user_accounts_simple(&[alice.clone(), carol.clone()], 1_000_000 * ONE_NEAR)
+        .build();
+    let epoch_config_store = TestEpochConfigBuilder::build_store_from_genesis(&genesis);
+    let mut env = TestLoopBuilder::new()
+        .genesis(genesis)
+        .epoch_config_store(epoch_config_store)
+        .clients(vec![validator.clone()])
+        .build()
+        .warmup();
+
+    let signer = create_user_test_signer(&alice);
+    let nonce = get_next_nonce(&env.test_loop.data, &env.node_datas, &alice);
+    let block_hash = get_shared_block_hash(&env.node_datas, &env.test_loop.data);
+    let tx = SignedTransaction::send_money(
+        nonce,
+        alice.clone(),
+        carol.clone(),
+        &signer,
+        ONE_NEAR,
+        block_hash,
+    );
+    run_tx(&mut env.test_loop, &validator, tx, &env.node_datas, Duration::seconds(5));
+
+    let view_client_handle = env.node_datas[0].view_client_sender.actor_handle();
+    let view_client = env.test_loop.data.get_mut(&view_client_handle);
+    let unknown: AccountId = "unknown".parse().unwrap();
+    let account_ids = vec![carol.clone(), unknown.clone(), alice.clone(), validator.clone()];
+    let batch = view_client.handle(ViewAccountsBatch { account_ids: account_ids.clone() }).unwrap();
+    assert_eq!(
+        batch.accounts.iter().map(|entry| entry.account_id.clone()).collect::<Vec<_>>(),
+        account_ids
+    );
+    for entry in batch.accounts {
+        let query = view_client.handle(Query::new(
+            BlockReference::BlockId(BlockId::Hash(batch.block_hash)),
+            QueryRequest::ViewAccount { account_id: entry.account_id.clone() },
+        ));
+        if entry.account_id == unknown {
+            assert!(entry.account.is_none());
+            assert!(query.is_err());
+            continue;
+        }
+        let QueryResponseKind::ViewAccount(account) = query.unwrap().kind else {
+            panic!("unexpected query response");
+        };
+        assert_eq!(entry.account, Some(account));
+    }
+
+    let too_many = vec![alice; MAX_VIEW_ACCOUNTS_BATCH_SIZE + 1];
+    assert_matches!(
+        view_client.handle(ViewAccountsBatch { account_ids: too_many }),
+        Err(QueryError::BatchTooLarge { .. })
+    );
+
+    env.shutdown_and_drain_remaining_events(Duration::seconds(20));
+}
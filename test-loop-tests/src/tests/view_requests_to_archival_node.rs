@@ -7,17 +7,18 @@ use near_async::test_loop::data::TestLoopDataHandle;
 use near_async::time::Duration;
 use near_chain_configs::test_genesis::{TestEpochConfigBuilder, ValidatorsSpec};
 use near_client::{
-    GetBlock, GetChunk, GetExecutionOutcomesForBlock, GetProtocolConfig, GetShardChunk,
-    GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
-    ViewClientActorInner,
+    GetBlock, GetBlockProof, GetChunk, GetExecutionOutcome, GetExecutionOutcomesForBlock,
+    GetProtocolConfig, GetShardChunk, GetStateChanges, GetStateChangesInBlock, GetValidatorInfo,
+    GetValidatorOrdered, ViewClientActorInner,
 };
 use near_network::client::BlockHeadersRequest;
 use near_o11y::testonly::init_test_logger;
+use near_primitives::merkle::{compute_root_from_path_and_item, verify_hash, verify_path};
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::sharding::ChunkHash;
 use near_primitives::types::{
     AccountId, BlockHeight, BlockId, BlockReference, EpochId, EpochReference, Finality,
-    SyncCheckpoint,
+    SyncCheckpoint, TransactionOrReceiptId,
 };
 use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{
@@ -157,6 +158,7 @@ impl<'a> ViewClientTester<'a> {
         self.check_get_state_changes_in_block();
         self.check_get_state_changes();
         self.check_get_execution_outcomes(shard_layout);
+        self.check_get_execution_outcome_proof();
     }
 
     fn get_block_at_height(&mut self, height: BlockHeight) -> BlockView {
@@ -417,6 +419,64 @@ impl<'a> ViewClientTester<'a> {
         assert_eq!(outcomes[&s3].len(), 0);
     }
 
+    /// Issues the [`GetExecutionOutcome`] and [`GetBlockProof`] requests, which make up the
+    /// light client proof, for a transaction from a garbage collected block and verifies the proofs.
+    fn check_get_execution_outcome_proof(&mut self) {
+        let block = self.get_block_at_height(6);
+        let outcomes = self
+            .send(GetExecutionOutcomesForBlock { block_hash: block.header.hash }, ARCHIVAL_CLIENT)
+            .unwrap();
+        let outcome = outcomes.values().flatten().next().unwrap().clone();
+
+        let request = || GetExecutionOutcome {
+            id: TransactionOrReceiptId::Transaction {
+                transaction_hash: outcome.id,
+                sender_id: outcome.outcome.executor_id.clone(),
+            },
+        };
+        let response = self.send(request(), ARCHIVAL_CLIENT).unwrap();
+        assert_eq!(response.outcome_proof.id, outcome.id);
+        // The validators garbage collected the block of the outcome.
+        assert!(self.send(request(), 0).is_err());
+
+        // The outcome is included in the outcome root of the block with the next chunk.
+        let outcome_block = self
+            .send(
+                GetBlock(BlockReference::BlockId(BlockId::Hash(response.outcome_proof.block_hash))),
+                ARCHIVAL_CLIENT,
+            )
+            .unwrap();
+        let chunk_outcome_root = compute_root_from_path_and_item(
+            &response.outcome_proof.proof,
+            &response.outcome_proof.to_hashes(),
+        );
+        assert!(verify_path(
+            outcome_block.header.outcome_root,
+            &response.outcome_root_proof,
+            &chunk_outcome_root
+        ));
+
+        // The block is included in the block merkle tree of the light client head.
+        let head = self
+            .send(GetBlock(BlockReference::Finality(Finality::Final)), ARCHIVAL_CLIENT)
+            .unwrap();
+        let block_proof = self
+            .send(
+                GetBlockProof {
+                    block_hash: outcome_block.header.hash,
+                    head_block_hash: head.header.hash,
+                },
+                ARCHIVAL_CLIENT,
+            )
+            .unwrap();
+        assert_eq!(block_proof.block_header_lite.inner_lite.height, outcome_block.header.height);
+        assert!(verify_hash(
+            head.header.block_merkle_root,
+            &block_proof.proof,
+            outcome_block.header.hash
+        ));
+    }
+
     /// Generates variations of the [`GetStateChanges`] request and issues them to the view client of the archival node.
     fn check_get_state_changes(&mut self) {
         let block = self.get_block_at_height(6);
@@ -22,7 +22,7 @@ use near_network::state_witness::{
     ChunkContractAccessesMessage, ChunkStateWitnessAckMessage, ContractCodeRequestMessage,
     ContractCodeResponseMessage, PartialEncodedContractDeploysMessage,
     PartialEncodedStateWitnessForwardMessage, PartialEncodedStateWitnessMessage,
-    PartialWitnessSenderForNetwork,
+    PartialEncodedStateWitnessRequestMessage, PartialWitnessSenderForNetwork,
 };
 use near_network::types::{
     HighestHeightPeerInfo, NetworkInfo, NetworkRequests, NetworkResponses, PeerInfo,
@@ -572,6 +572,13 @@ fn network_message_to_partial_witness_handler(
             }
             None
         }
+        NetworkRequests::PartialEncodedStateWitnessRequest(target, request) => {
+            shared_state
+                .senders_for_account(&my_account_id, &target)
+                .partial_witness_sender
+                .send(PartialEncodedStateWitnessRequestMessage(request));
+            None
+        }
         NetworkRequests::ChunkContractAccesses(chunk_validators, accesses) => {
             for target in chunk_validators {
                 shared_state
@@ -646,6 +653,30 @@ fn network_message_to_shards_manager_handler(
                 });
             None
         }
+        NetworkRequests::ChunkRequest { target, request } => {
+            // Peers tracking the shard aren't modelled, so only the requests with
+            // a target account are delivered.
+            let Some(target) = target.account_id else {
+                return None;
+            };
+            assert!(target != my_account_id, "Sending message to self not supported.");
+            let my_peer_id = shared_state.account_to_peer_id(&my_account_id);
+            let route_back = shared_state.generate_route_back(&my_peer_id);
+            shared_state.senders_for_account(&my_account_id, &target).shards_manager_sender.send(
+                ShardsManagerRequestFromNetwork::ProcessChunkRequest {
+                    chunk_request: request,
+                    route_back,
+                },
+            );
+            None
+        }
+        NetworkRequests::ChunkResponse { route_back, response } => {
+            shared_state
+                .senders_for_route_back(&my_account_id, &route_back)
+                .shards_manager_sender
+                .send(ShardsManagerRequestFromNetwork::ProcessChunkResponse(response));
+            None
+        }
         NetworkRequests::PartialEncodedChunkMessage { account_id, partial_encoded_chunk } => {
             assert!(account_id != my_account_id, "Sending message to self not supported.");
             shared_state
@@ -664,6 +695,18 @@ fn network_message_to_shards_manager_handler(
                 .send(ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkForward(forward));
             None
         }
+        NetworkRequests::ReceiptProofFragment { account_id, fragment } => {
+            assert!(account_id != my_account_id, "Sending message to self not supported.");
+            let peer_id = shared_state.account_to_peer_id(&my_account_id);
+            shared_state
+                .senders_for_account(&my_account_id, &account_id)
+                .shards_manager_sender
+                .send(ShardsManagerRequestFromNetwork::ProcessReceiptProofFragment {
+                    fragment,
+                    peer_id,
+                });
+            None
+        }
         _ => Some(request),
     })
 }
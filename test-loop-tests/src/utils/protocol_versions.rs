@@ -0,0 +1,108 @@
+use near_chain_configs::test_genesis::TestGenesisBuilder;
+use near_primitives::types::ProtocolVersion;
+use near_primitives::upgrade_schedule::ProtocolUpgradeVotingSchedule;
+use near_primitives::version::{MIN_SUPPORTED_PROTOCOL_VERSION, PROTOCOL_VERSION, ProtocolFeature};
+
+use crate::setup::builder::TestLoopBuilder;
+
+/// How the protocol versions of a test network relate to a protocol feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FeatureGateCase {
+    /// The network stays at the last protocol version without the feature.
+    Disabled,
+    /// The network starts at the first protocol version with the feature.
+    Enabled,
+    /// The network starts without the feature and upgrades to the first
+    /// protocol version with the feature as soon as possible.
+    Upgrade,
+}
+
+impl FeatureGateCase {
+    pub const ALL: [FeatureGateCase; 3] =
+        [FeatureGateCase::Disabled, FeatureGateCase::Enabled, FeatureGateCase::Upgrade];
+}
+
+/// Protocol versions of a single case of a [`ProtocolVersionMatrix`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ProtocolVersionCase {
+    pub case: FeatureGateCase,
+    pub feature: ProtocolFeature,
+    /// Protocol version of the genesis.
+    pub genesis_protocol_version: ProtocolVersion,
+    /// Protocol version the network runs at after all upgrades.
+    pub target_protocol_version: ProtocolVersion,
+}
+
+impl ProtocolVersionCase {
+    fn new(feature: ProtocolFeature, case: FeatureGateCase) -> Self {
+        let enabled_version = feature.protocol_version();
+        let (genesis_protocol_version, target_protocol_version) = match case {
+            FeatureGateCase::Disabled => (enabled_version - 1, enabled_version - 1),
+            FeatureGateCase::Enabled => (enabled_version, enabled_version),
+            FeatureGateCase::Upgrade => (enabled_version - 1, enabled_version),
+        };
+        Self { case, feature, genesis_protocol_version, target_protocol_version }
+    }
+
+    /// Genesis builder with the protocol version of the case. The epoch
+    /// config store built from it with `build_store_for_genesis_protocol_version`
+    /// also covers the protocol version after the upgrade.
+    pub fn genesis_builder(&self) -> TestGenesisBuilder {
+        TestLoopBuilder::new_genesis_builder().protocol_version(self.genesis_protocol_version)
+    }
+
+    /// Test loop builder whose nodes vote for the target protocol version of
+    /// the case right away, and never for a later one.
+    pub fn test_loop_builder(&self) -> TestLoopBuilder {
+        TestLoopBuilder::new().protocol_upgrade_schedule(
+            ProtocolUpgradeVotingSchedule::new_immediate(self.target_protocol_version),
+        )
+    }
+
+    /// Whether the feature is enabled at the given protocol version, e.g. of
+    /// the current epoch.
+    pub fn feature_enabled(&self, protocol_version: ProtocolVersion) -> bool {
+        self.feature.enabled(protocol_version)
+    }
+}
+
+/// Runs the same test scenario with the protocol versions before, after and
+/// across the upgrade to a protocol feature, so that feature gate tests don't
+/// have to set up the genesis and the upgrade schedule for each of them.
+///
+/// ```ignore
+/// ProtocolVersionMatrix::new(ProtocolFeature::GlobalContracts).run(|case| {
+///     let genesis = case.genesis_builder().validators_spec(..).build();
+///     let env = case.test_loop_builder().genesis(genesis)..build().warmup();
+///     ...
+/// });
+/// ```
+pub(crate) struct ProtocolVersionMatrix {
+    feature: ProtocolFeature,
+}
+
+impl ProtocolVersionMatrix {
+    pub fn new(feature: ProtocolFeature) -> Self {
+        Self { feature }
+    }
+
+    /// Runs the scenario for each case. Cases which the binary can't run are
+    /// skipped, i.e. all of them when the feature isn't enabled in
+    /// `PROTOCOL_VERSION` and the ones starting before the feature when its
+    /// previous protocol version is no longer supported.
+    pub fn run(self, mut scenario: impl FnMut(ProtocolVersionCase)) {
+        if !self.feature.enabled(PROTOCOL_VERSION) {
+            tracing::info!(target: "test", feature = ?self.feature, "feature is not enabled, skipping");
+            return;
+        }
+        for case in FeatureGateCase::ALL {
+            let case = ProtocolVersionCase::new(self.feature, case);
+            if case.genesis_protocol_version < MIN_SUPPORTED_PROTOCOL_VERSION {
+                tracing::info!(target: "test", ?case, "protocol version is not supported, skipping");
+                continue;
+            }
+            tracing::info!(target: "test", ?case, "running feature gate case");
+            scenario(case);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Loads a dataset exported with `neard chain-data export`, from the directory
+//! in `NEAR_CHAIN_DATA_DIR`, to measure the deserialization of the blocks and
+//! the chunks which the benchmarks built on the datasets pay for.
+
+use bencher::{Bencher, benchmark_group, benchmark_main};
+use near_chain_data::ChainDataReader;
+use std::path::PathBuf;
+
+fn load_chain_data(bench: &mut Bencher) {
+    let dir: PathBuf = std::env::var("NEAR_CHAIN_DATA_DIR")
+        .expect("NEAR_CHAIN_DATA_DIR must point to an exported dataset")
+        .into();
+    let reader = ChainDataReader::open(&dir).unwrap();
+    bench.iter(|| {
+        let mut num_chunks = 0;
+        for record in reader.iter() {
+            num_chunks += record.unwrap().chunks.len();
+        }
+        num_chunks
+    });
+}
+
+benchmark_group!(benches, load_chain_data);
+benchmark_main!(benches);
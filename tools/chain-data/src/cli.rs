@@ -0,0 +1,75 @@
+use crate::{export_chain_data, export_store};
+use anyhow::bail;
+use near_chain_configs::GenesisValidationMode;
+use near_primitives::types::BlockHeight;
+use near_store::adapter::StoreAdapter;
+use near_store::{Mode, NodeStorage};
+use nearcore::load_config;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Parser)]
+pub struct ChainDataCommand {
+    #[clap(subcommand)]
+    subcmd: ChainDataSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum ChainDataSubCommand {
+    /// Export the blocks of a height range, with their chunks and receipts,
+    /// into flat files which can be loaded by the mock node and the benchmarks.
+    Export(ExportCommand),
+}
+
+#[derive(clap::Parser)]
+struct ExportCommand {
+    /// First height to export.
+    #[clap(long)]
+    start_height: BlockHeight,
+    /// Last height to export, inclusive.
+    #[clap(long)]
+    end_height: BlockHeight,
+    /// Directory to write the dataset to.
+    #[clap(long)]
+    output_dir: PathBuf,
+}
+
+impl ChainDataCommand {
+    pub fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        match self.subcmd {
+            ChainDataSubCommand::Export(cmd) => cmd.run(home_dir, genesis_validation),
+        }
+    }
+}
+
+impl ExportCommand {
+    fn run(self, home_dir: &Path, genesis_validation: GenesisValidationMode) -> anyhow::Result<()> {
+        if self.start_height > self.end_height {
+            bail!("start height {} is above end height {}", self.start_height, self.end_height);
+        }
+        let near_config = load_config(home_dir, genesis_validation)?;
+        let storage = NodeStorage::opener(
+            home_dir,
+            &near_config.config.store,
+            near_config.config.archival_config(),
+        )
+        .open_in_mode(Mode::ReadOnly)?;
+        let num_blocks = export_chain_data(
+            &export_store(&storage).chain_store(),
+            &near_config.genesis.config.chain_id,
+            self.start_height,
+            self.end_height,
+            &self.output_dir,
+        )?;
+        println!(
+            "Exported {num_blocks} blocks from heights {}..={} to {}",
+            self.start_height,
+            self.end_height,
+            self.output_dir.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,411 @@
+//! Chain data exported from the store of a node into flat files, so that the
+//! mock node and the benchmarks can run on the same datasets, e.g. a range of
+//! mainnet blocks, without a copy of the database they were taken from.
+//!
+//! A dataset is a directory with two files:
+//! - `records.bin`, the borsh-serialized `ChainDataRecord`s of the blocks, in
+//!   the order of their heights;
+//! - `index.bin`, the borsh-serialized `ChainDataIndex`, with the offset and
+//!   the length of the record of every height.
+//!
+//! Heights without a block have no record.
+
+pub mod cli;
+
+use anyhow::{Context, bail};
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::block::Block;
+use near_primitives::hash::CryptoHash;
+use near_primitives::receipt::Receipt;
+use near_primitives::sharding::{PartialEncodedChunk, ReceiptProof, ShardChunk};
+use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::utils::{get_block_shard_id, index_to_bytes};
+use near_store::adapter::StoreAdapter;
+use near_store::adapter::chain_store::ChainStoreAdapter;
+use near_store::{DBCol, NodeStorage, Store};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+const RECORDS_FILE: &str = "records.bin";
+const INDEX_FILE: &str = "index.bin";
+/// Version of the format, to be bumped on incompatible changes of the records.
+const FORMAT_VERSION: u32 = 1;
+
+/// A block with its new chunks and their receipts.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChainDataRecord {
+    pub block: Block,
+    /// The chunks of the shards tracked by the exporting node.
+    pub chunks: Vec<ShardChunk>,
+    /// The partial chunks, with the parts the node owned or all of them if it
+    /// tracked the shard. The mock node serves its chunks from them.
+    pub partial_chunks: Vec<PartialEncodedChunk>,
+    pub incoming_receipts: Vec<(ShardId, Vec<ReceiptProof>)>,
+    pub outgoing_receipts: Vec<(ShardId, Vec<Receipt>)>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+struct IndexEntry {
+    height: BlockHeight,
+    block_hash: CryptoHash,
+    offset: u64,
+    len: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+struct ChainDataIndex {
+    version: u32,
+    chain_id: String,
+    entries: Vec<IndexEntry>,
+}
+
+/// Writes a dataset, one block at a time in increasing order of heights.
+pub struct ChainDataWriter {
+    dir: PathBuf,
+    records: BufWriter<File>,
+    offset: u64,
+    index: ChainDataIndex,
+}
+
+impl ChainDataWriter {
+    pub fn create(dir: &Path, chain_id: &str) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed creating {}", dir.display()))?;
+        let records = File::create(dir.join(RECORDS_FILE))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            records: BufWriter::new(records),
+            offset: 0,
+            index: ChainDataIndex {
+                version: FORMAT_VERSION,
+                chain_id: chain_id.to_string(),
+                entries: vec![],
+            },
+        })
+    }
+
+    pub fn append(&mut self, record: &ChainDataRecord) -> anyhow::Result<()> {
+        let height = record.block.header().height();
+        if let Some(last) = self.index.entries.last() {
+            if last.height >= height {
+                bail!("block at height {height} appended after height {}", last.height);
+            }
+        }
+        let bytes = borsh::to_vec(record)?;
+        self.records.write_all(&bytes)?;
+        self.index.entries.push(IndexEntry {
+            height,
+            block_hash: *record.block.hash(),
+            offset: self.offset,
+            len: bytes.len() as u64,
+        });
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the index. The dataset can't be read before.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.records.flush()?;
+        std::fs::write(self.dir.join(INDEX_FILE), borsh::to_vec(&self.index)?)?;
+        Ok(())
+    }
+}
+
+/// Returns the store to export from. Archival nodes export from the split
+/// store, so that the blocks already moved to the cold store are included.
+pub fn export_store(storage: &NodeStorage) -> Store {
+    storage.get_split_store().unwrap_or_else(|| storage.get_hot_store())
+}
+
+/// Reads the record of the block at `height` from the store. Returns `None`
+/// if there's no block at that height. Chunks and receipts missing from the
+/// store, e.g. of the shards the node doesn't track, are left out. Errors
+/// reading the store are returned, so that a failed export isn't mistaken for
+/// a complete one.
+pub fn read_record(
+    chain_store: &ChainStoreAdapter,
+    height: BlockHeight,
+) -> anyhow::Result<Option<ChainDataRecord>> {
+    let store = chain_store.store();
+    let Some(block_hash) =
+        store.get_ser::<CryptoHash>(DBCol::BlockHeight, &index_to_bytes(height))?
+    else {
+        return Ok(None);
+    };
+    let block = chain_store.get_block(&block_hash)?;
+    let mut record = ChainDataRecord {
+        block: block.clone(),
+        chunks: vec![],
+        partial_chunks: vec![],
+        incoming_receipts: vec![],
+        outgoing_receipts: vec![],
+    };
+    for chunk_header in block.chunks().iter_deprecated() {
+        let shard_id = chunk_header.shard_id();
+        let block_shard_id = get_block_shard_id(&block_hash, shard_id);
+        if chunk_header.is_new_chunk(height) {
+            let chunk_hash = chunk_header.chunk_hash();
+            if let Some(chunk) = store.get_ser(DBCol::Chunks, chunk_hash.as_ref())? {
+                record.chunks.push(chunk);
+            }
+            if let Some(partial_chunk) = store.get_ser(DBCol::PartialChunks, chunk_hash.as_ref())? {
+                record.partial_chunks.push(partial_chunk);
+            }
+        }
+        if let Some(receipts) = store.get_ser(DBCol::IncomingReceipts, &block_shard_id)? {
+            record.incoming_receipts.push((shard_id, receipts));
+        }
+        if let Some(receipts) = store.get_ser(DBCol::OutgoingReceipts, &block_shard_id)? {
+            record.outgoing_receipts.push((shard_id, receipts));
+        }
+    }
+    Ok(Some(record))
+}
+
+/// Exports the blocks from `start_height` to `end_height`, inclusive, into a
+/// dataset in `dir`. Returns the number of exported blocks.
+pub fn export_chain_data(
+    chain_store: &ChainStoreAdapter,
+    chain_id: &str,
+    start_height: BlockHeight,
+    end_height: BlockHeight,
+    dir: &Path,
+) -> anyhow::Result<usize> {
+    let mut writer = ChainDataWriter::create(dir, chain_id)?;
+    let mut num_blocks = 0;
+    for height in start_height..=end_height {
+        let Some(record) = read_record(chain_store, height)? else {
+            continue;
+        };
+        writer.append(&record)?;
+        num_blocks += 1;
+        if num_blocks % 1000 == 0 {
+            tracing::info!(target: "chain-data", height, num_blocks, "Exported blocks");
+        }
+    }
+    writer.finish()?;
+    Ok(num_blocks)
+}
+
+/// Reads a dataset written by `ChainDataWriter`.
+pub struct ChainDataReader {
+    records: File,
+    index: ChainDataIndex,
+}
+
+impl ChainDataReader {
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        let index_path = dir.join(INDEX_FILE);
+        let index = std::fs::read(&index_path)
+            .with_context(|| format!("failed reading {}", index_path.display()))?;
+        let index = ChainDataIndex::try_from_slice(&index)
+            .with_context(|| format!("failed parsing {}", index_path.display()))?;
+        if index.version != FORMAT_VERSION {
+            bail!("unsupported chain data version {}, expected {FORMAT_VERSION}", index.version);
+        }
+        let records = File::open(dir.join(RECORDS_FILE))?;
+        Ok(Self { records, index })
+    }
+
+    pub fn chain_id(&self) -> &str {
+        &self.index.chain_id
+    }
+
+    /// Heights of the blocks in the dataset, in increasing order.
+    pub fn heights(&self) -> impl Iterator<Item = BlockHeight> + '_ {
+        self.index.entries.iter().map(|entry| entry.height)
+    }
+
+    pub fn last_height(&self) -> Option<BlockHeight> {
+        self.index.entries.last().map(|entry| entry.height)
+    }
+
+    pub fn get(&self, height: BlockHeight) -> anyhow::Result<Option<ChainDataRecord>> {
+        let Ok(position) = self.index.entries.binary_search_by_key(&height, |entry| entry.height)
+        else {
+            return Ok(None);
+        };
+        self.read(&self.index.entries[position]).map(Some)
+    }
+
+    /// Reads the records in increasing order of heights.
+    pub fn iter(&self) -> impl Iterator<Item = anyhow::Result<ChainDataRecord>> + '_ {
+        self.index.entries.iter().map(|entry| self.read(entry))
+    }
+
+    fn read(&self, entry: &IndexEntry) -> anyhow::Result<ChainDataRecord> {
+        let mut bytes = vec![0; entry.len as usize];
+        self.records.read_exact_at(&mut bytes, entry.offset)?;
+        let record = ChainDataRecord::try_from_slice(&bytes)
+            .with_context(|| format!("failed parsing the record at height {}", entry.height))?;
+        if *record.block.hash() != entry.block_hash {
+            bail!("the record at height {} doesn't match its block hash", entry.height);
+        }
+        Ok(record)
+    }
+
+    /// Writes the blocks, chunks and receipts of the dataset into the columns
+    /// of `store` they are read from by the chain, so that e.g. the mock node
+    /// can serve them. The store must already have the genesis and the epochs
+    /// of the dataset. The head and the other chain metadata are left as is.
+    pub fn import(&self, store: &Store) -> anyhow::Result<()> {
+        for record in self.iter() {
+            let record = record?;
+            let block_hash = record.block.hash();
+            let mut store_update = store.store_update();
+            store_update.insert_ser(DBCol::Block, block_hash.as_ref(), &record.block)?;
+            store_update.insert_ser(
+                DBCol::BlockHeader,
+                block_hash.as_ref(),
+                record.block.header(),
+            )?;
+            store_update.set_ser(
+                DBCol::BlockHeight,
+                &index_to_bytes(record.block.header().height()),
+                block_hash,
+            )?;
+            for chunk in &record.chunks {
+                store_update.insert_ser(DBCol::Chunks, chunk.chunk_hash().as_ref(), chunk)?;
+            }
+            for partial_chunk in &record.partial_chunks {
+                store_update.insert_ser(
+                    DBCol::PartialChunks,
+                    partial_chunk.chunk_hash().as_ref(),
+                    partial_chunk,
+                )?;
+            }
+            for (shard_id, receipts) in &record.incoming_receipts {
+                store_update.set_ser(
+                    DBCol::IncomingReceipts,
+                    &get_block_shard_id(block_hash, *shard_id),
+                    receipts,
+                )?;
+            }
+            for (shard_id, receipts) in &record.outgoing_receipts {
+                store_update.set_ser(
+                    DBCol::OutgoingReceipts,
+                    &get_block_shard_id(block_hash, *shard_id),
+                    receipts,
+                )?;
+            }
+            store_update.commit()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::genesis::{genesis_block, genesis_chunks};
+    use near_primitives::test_utils::{TestBlockBuilder, create_test_signer};
+    use near_primitives::version::PROTOCOL_VERSION;
+    use near_store::Trie;
+    use near_store::db::metadata::{DB_VERSION, DbKind};
+    use near_store::test_utils::{create_test_node_storage_with_cold, create_test_store};
+    use near_time::{Clock, Utc};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_export_and_import() {
+        let shard_ids = vec![ShardId::new(0)];
+        let genesis_chunks = genesis_chunks(
+            vec![Trie::EMPTY_ROOT],
+            vec![Default::default()],
+            &shard_ids,
+            1_000_000,
+            0,
+            PROTOCOL_VERSION,
+        );
+        let genesis = genesis_block(
+            PROTOCOL_VERSION,
+            genesis_chunks.into_iter().map(|chunk| chunk.take_header()).collect(),
+            Utc::now_utc(),
+            0,
+            100,
+            1_000_000_000,
+            &[],
+        );
+        let signer = Arc::new(create_test_signer("test0"));
+        let mut blocks = vec![genesis];
+        for _ in 0..3 {
+            let block =
+                TestBlockBuilder::new(Clock::real(), blocks.last().unwrap(), signer.clone())
+                    .build();
+            blocks.push(block);
+        }
+        let record = |block: &Block| ChainDataRecord {
+            block: block.clone(),
+            chunks: vec![],
+            partial_chunks: vec![],
+            incoming_receipts: vec![(ShardId::new(0), vec![])],
+            outgoing_receipts: vec![],
+        };
+
+        // Height 2 is skipped.
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = ChainDataWriter::create(dir.path(), "test").unwrap();
+        for block in [&blocks[0], &blocks[1], &blocks[3]] {
+            writer.append(&record(block)).unwrap();
+        }
+        assert!(writer.append(&record(&blocks[2])).is_err());
+        writer.finish().unwrap();
+
+        let reader = ChainDataReader::open(dir.path()).unwrap();
+        assert_eq!(reader.chain_id(), "test");
+        assert_eq!(reader.heights().collect::<Vec<_>>(), [0, 1, 3]);
+        assert_eq!(reader.last_height(), Some(3));
+        assert_eq!(reader.get(1).unwrap(), Some(record(&blocks[1])));
+        assert_eq!(reader.get(2).unwrap(), None);
+
+        // The imported blocks are read back from the store.
+        let store = create_test_store();
+        reader.import(&store).unwrap();
+        let chain_store = store.chain_store();
+        for height in [0, 1, 3] {
+            let imported = read_record(&chain_store, height).unwrap().unwrap();
+            assert_eq!(Some(imported), reader.get(height).unwrap());
+        }
+        assert!(read_record(&chain_store, 2).unwrap().is_none());
+
+        // The blocks moved to the cold store of an archival node are exported.
+        let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Hot);
+        let mut store_update = storage.get_cold_store().unwrap().store_update();
+        for record in reader.iter() {
+            let record = record.unwrap();
+            let block_hash = record.block.hash();
+            store_update.insert_ser(DBCol::Block, block_hash.as_ref(), &record.block).unwrap();
+            store_update
+                .set_ser(
+                    DBCol::BlockHeight,
+                    &index_to_bytes(record.block.header().height()),
+                    block_hash,
+                )
+                .unwrap();
+            for (shard_id, receipts) in &record.incoming_receipts {
+                store_update
+                    .set_ser(
+                        DBCol::IncomingReceipts,
+                        &get_block_shard_id(block_hash, *shard_id),
+                        receipts,
+                    )
+                    .unwrap();
+            }
+        }
+        store_update.commit().unwrap();
+        let export_dir = tempfile::tempdir().unwrap();
+        let num_blocks = export_chain_data(
+            &export_store(&storage).chain_store(),
+            "test",
+            0,
+            3,
+            export_dir.path(),
+        )
+        .unwrap();
+        assert_eq!(num_blocks, 3);
+        let exported = ChainDataReader::open(export_dir.path()).unwrap();
+        assert_eq!(exported.get(3).unwrap(), reader.get(3).unwrap());
+    }
+}
@@ -8,7 +8,9 @@ use near_epoch_manager::{EpochManager, EpochManagerAdapter, EpochManagerHandle};
 use near_primitives::block::Tip;
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::hash::CryptoHash;
-use near_store::archive::cold_storage::{copy_all_data_to_cold, update_cold_db, update_cold_head};
+use near_store::archive::cold_storage::{
+    backfill_cold_block_columns, copy_all_data_to_cold, update_cold_db, update_cold_head,
+};
 use near_store::db::metadata::DbKind;
 use near_store::{COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY, TAIL_KEY};
 use near_store::{DBCol, NodeStorage, Store, StoreOpener};
@@ -57,6 +59,10 @@ enum SubCommand {
     /// Modifies cold db from config to be considered not initialized.
     /// Doesn't actually delete any data, except for HEAD and COLD_HEAD in BlockMisc.
     ResetCold(ResetColdCmd),
+    /// Copy the block headers, heights, merkle trees and ordinals of the blocks
+    /// already in cold db to it. Needed once for the cold db populated by the
+    /// versions which didn't copy these columns, to serve light client proofs.
+    BackfillBlockColumns(BackfillBlockColumnsCmd),
 }
 
 impl ColdStoreCommand {
@@ -96,6 +102,7 @@ impl ColdStoreCommand {
             SubCommand::PrepareHot(cmd) => cmd.run(&storage, &home_dir, &near_config),
             SubCommand::CheckStateRoot(cmd) => cmd.run(&storage),
             SubCommand::ResetCold(cmd) => cmd.run(&storage),
+            SubCommand::BackfillBlockColumns(cmd) => cmd.run(&storage),
         }
     }
 
@@ -675,3 +682,24 @@ impl ResetColdCmd {
         Ok(())
     }
 }
+
+#[derive(clap::Args)]
+struct BackfillBlockColumnsCmd {
+    /// Threshold size of the write transaction.
+    #[clap(short = 'b', long, default_value_t = 500_000_000)]
+    batch_size: usize,
+}
+
+impl BackfillBlockColumnsCmd {
+    pub fn run(self, storage: &NodeStorage) -> anyhow::Result<()> {
+        let cold_db =
+            storage.cold_db().ok_or_else(|| anyhow::anyhow!("Cold storage is not configured"))?;
+        let num_blocks = backfill_cold_block_columns(
+            cold_db.clone(),
+            &storage.get_hot_store(),
+            self.batch_size,
+        )?;
+        println!("Backfilled the block columns of {num_blocks} blocks");
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ use crate::compact::RunCompactionCommand;
 use crate::drop_column::DropColumnCommand;
 use crate::make_snapshot::MakeSnapshotCommand;
 use crate::memtrie::LoadMemTrieCommand;
+use crate::rollback_migration::RollbackMigrationCommand;
 use crate::run_migrations::RunMigrationsCommand;
 use crate::set_version::SetVersionCommand;
 use crate::state_perf::StatePerfCommand;
@@ -46,6 +47,9 @@ enum SubCommand {
     /// Run migrations
     RunMigrations(RunMigrationsCommand),
 
+    /// Restore the database from the snapshot created before a migration
+    RollbackMigration(RollbackMigrationCommand),
+
     /// Run performance test for State column reads.
     /// Uses RocksDB data specified via --home argument.
     StatePerf(StatePerfCommand),
@@ -83,6 +87,7 @@ impl DatabaseCommand {
                 cmd.run(home, &near_config.config.store, near_config.config.archival_config())
             }
             SubCommand::RunMigrations(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::RollbackMigration(cmd) => cmd.run(home, genesis_validation),
             SubCommand::StatePerf(cmd) => cmd.run(home),
             SubCommand::LoadMemTrie(cmd) => cmd.run(home, genesis_validation),
             SubCommand::WriteCryptoHash(cmd) => cmd.run(home, genesis_validation),
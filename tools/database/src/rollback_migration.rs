@@ -0,0 +1,41 @@
+use near_chain_configs::GenesisValidationMode;
+use near_store::NodeStorage;
+use std::path::Path;
+
+use crate::utils::get_user_confirmation;
+
+/// Restores the databases from the snapshots created before a migration,
+/// see `store.migration_snapshot` in `config.json`.
+#[derive(clap::Args)]
+pub(crate) struct RollbackMigrationCommand {}
+
+impl RollbackMigrationCommand {
+    pub(crate) fn run(
+        &self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(&home_dir, genesis_validation)?;
+        let opener = NodeStorage::opener(
+            home_dir,
+            &near_config.config.store,
+            near_config.config.archival_config(),
+        );
+        if !get_user_confirmation(
+            "WARNING: You are about to replace the database with its migration snapshot.\n\
+             All the changes made since the snapshot was created will be lost.",
+        ) {
+            println!("Operation canceled.");
+            return Ok(());
+        }
+
+        let restored = opener.restore_migration_snapshots()?;
+        if restored.is_empty() {
+            println!("No migration snapshot found");
+        }
+        for path in restored {
+            println!("Restored the database from {}", path.display());
+        }
+        Ok(())
+    }
+}
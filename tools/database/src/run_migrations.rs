@@ -2,7 +2,12 @@ use near_chain_configs::GenesisValidationMode;
 use std::path::Path;
 
 #[derive(clap::Args)]
-pub(crate) struct RunMigrationsCommand {}
+pub(crate) struct RunMigrationsCommand {
+    /// Run the migrations on a copy of the database and print what they
+    /// change, without changing the database.
+    #[clap(long)]
+    dry_run: bool,
+}
 
 impl RunMigrationsCommand {
     pub(crate) fn run(
@@ -12,6 +17,16 @@ impl RunMigrationsCommand {
     ) -> anyhow::Result<()> {
         let mut near_config = nearcore::config::load_config(&home_dir, genesis_validation)
             .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+        if self.dry_run {
+            let reports = nearcore::dry_run_migrations(home_dir, &near_config)?;
+            if reports.is_empty() {
+                println!("The database is up to date");
+            }
+            for (temperature, report) in reports {
+                println!("{} database {report}", <&str>::from(temperature));
+            }
+            return Ok(());
+        }
         nearcore::open_storage(home_dir, &mut near_config)?;
         Ok(())
     }
@@ -339,6 +339,19 @@ const validatorAssignmentsAtHeight = {
     },
 };
 
+const storageProofAttribution = {
+    struct: {
+        account_id: accountId,
+    },
+    titleKey: 'account_id',
+};
+
+const witnessSize = {
+    struct: {
+        attributions: { array: storageProofAttribution },
+    },
+};
+
 const rawTrieNode = {
     struct: {
         extension: nibbles,
@@ -378,4 +391,5 @@ export const fieldSemantics: Record<EntityType, FieldSemantic> = {
     TrieNode: trieNode,
     TrieRoot: triePath,
     ValidatorAssignmentsAtHeight: validatorAssignmentsAtHeight,
+    WitnessSize: witnessSize,
 };
@@ -47,7 +47,8 @@ export type EntityType =
     | 'Transaction'
     | 'TrieNode'
     | 'TrieRoot'
-    | 'ValidatorAssignmentsAtHeight';
+    | 'ValidatorAssignmentsAtHeight'
+    | 'WitnessSize';
 
 /// Interface for a concrete entity key.
 export interface EntityKey {
@@ -170,6 +171,7 @@ export type EntityQuery = {
     TrieRootByChunkHash?: { chunk_hash: string };
     TrieRootByStateRoot?: { state_root: string; shard_uid: string };
     ValidatorAssignmentsAtHeight?: { block_height: number; epoch_id: string };
+    WitnessSizeByBlockHashShardId?: { block_hash: string; shard_id: number };
 };
 
 export type EntityQueryWithParams = EntityQuery & {
@@ -221,6 +223,7 @@ export const entityQueryTypes: EntityQueryType[] = [
     'TrieRootByChunkHash',
     'TrieRootByStateRoot',
     'ValidatorAssignmentsAtHeight',
+    'WitnessSizeByBlockHashShardId',
 ];
 
 /// See entityQueryKeyTypes.
@@ -295,6 +298,7 @@ export const entityQueryKeyTypes: Record<EntityQueryType, EntityQueryKeySpec[]>
     TrieRootByChunkHash: [queryKey('chunk_hash')],
     TrieRootByStateRoot: [queryKey('state_root'), implicitQueryKey('shard_uid')],
     ValidatorAssignmentsAtHeight: [queryKey('block_height'), implicitQueryKey('epoch_id')],
+    WitnessSizeByBlockHashShardId: [queryKey('block_hash'), implicitQueryKey('shard_id')],
 };
 
 /// Specifies the expected output entity type for each query.
@@ -337,4 +341,5 @@ export const entityQueryOutputType: Record<EntityQueryType, EntityType> = {
     TrieRootByChunkHash: 'TrieRoot',
     TrieRootByStateRoot: 'TrieRoot',
     ValidatorAssignmentsAtHeight: 'ValidatorAssignmentsAtHeight',
+    WitnessSizeByBlockHashShardId: 'WitnessSize',
 };
@@ -9,7 +9,7 @@ use mock_node::setup::setup_mock_node;
 use near_o11y::testonly::init_integration_logger;
 use near_primitives::types::BlockHeight;
 use near_primitives::version::ProtocolVersion;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 /// Program to start a mock node, which starts a TCP server and accepts incoming
@@ -42,6 +42,11 @@ struct Cli {
     /// If set, advertise that the node is archival in the handshake
     #[clap(long)]
     archival: bool,
+    /// Directory of a dataset exported with `neard chain-data export`, to serve
+    /// instead of the blocks in the DB of the home dir. The home dir must have
+    /// the genesis and the epochs of the dataset.
+    #[clap(long)]
+    chain_data: Option<PathBuf>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -70,6 +75,7 @@ fn main() -> anyhow::Result<()> {
             args.target_height,
             args.handshake_protocol_version,
             args.archival,
+            args.chain_data.as_deref(),
         )
         .context("failed setting up mock node")?;
 
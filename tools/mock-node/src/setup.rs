@@ -4,6 +4,7 @@ use crate::{MockNetworkConfig, MockNode};
 use anyhow::Context;
 use near_chain::{Chain, ChainGenesis, DoomslugThresholdMode};
 use near_chain_configs::GenesisValidationMode;
+use near_chain_data::ChainDataReader;
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::{EpochManager, EpochManagerAdapter};
 use near_network::tcp;
@@ -76,6 +77,8 @@ pub(crate) fn setup_mock_peer(
 /// Some(), it will first send a block at that height, so that the connecting node sees
 /// that its head is at that height, and it will then send higher heights periodically.
 /// If target_height is Some(), it will not send any blocks or chunks of higher height.
+/// If `chain_data` is Some(), the blocks and chunks exported there with `neard chain-data export`
+/// are first imported into the DB, and served up to the last height of the dataset.
 pub fn setup_mock_node(
     home_dir: &Path,
     network_config: MockNetworkConfig,
@@ -83,6 +86,7 @@ pub fn setup_mock_node(
     target_height: Option<BlockHeight>,
     handshake_protocol_version: Option<ProtocolVersion>,
     archival: bool,
+    chain_data: Option<&Path>,
 ) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
     let near_config = nearcore::config::load_config(home_dir, GenesisValidationMode::Full)
         .context("Error loading config")?;
@@ -95,6 +99,21 @@ pub fn setup_mock_node(
     .open()
     .context("failed opening storage")?
     .get_hot_store();
+    let chain_data_height = match chain_data {
+        Some(dir) => {
+            let reader = ChainDataReader::open(dir).context("failed opening chain data")?;
+            if reader.chain_id() != near_config.genesis.config.chain_id {
+                anyhow::bail!(
+                    "chain data of {} can't be served on {}",
+                    reader.chain_id(),
+                    near_config.genesis.config.chain_id
+                );
+            }
+            reader.import(&store).context("failed importing chain data")?;
+            reader.last_height()
+        }
+        None => None,
+    };
     let epoch_manager =
         EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config, Some(home_dir));
     let shard_tracker = ShardTracker::new(
@@ -121,7 +140,8 @@ pub fn setup_mock_node(
     let epoch_id = head.epoch_id;
     let shard_layout = epoch_manager.get_shard_layout(&epoch_id).unwrap();
 
-    let target_height = min(target_height.unwrap_or(head.height), head.height);
+    let last_height = chain_data_height.unwrap_or(head.height);
+    let target_height = min(target_height.unwrap_or(last_height), last_height);
 
     Ok(setup_mock_peer(
         chain,
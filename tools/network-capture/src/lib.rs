@@ -0,0 +1,166 @@
+//! Reader of the peer message capture files written by the node when
+//! `network.traffic_capture` is set in config.json.
+
+use anyhow::Context;
+use near_crypto::PublicKey;
+use near_network::capture::{CaptureReader, CapturedMessage, Direction};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+pub struct NetworkCaptureCommand {
+    #[clap(subcommand)]
+    subcmd: SubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum SubCommand {
+    /// Print the captured messages, one per line.
+    Print(PrintCmd),
+    /// Print the number and the total size of the captured messages by
+    /// direction and type.
+    Stats(StatsCmd),
+}
+
+impl NetworkCaptureCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        match self.subcmd {
+            SubCommand::Print(cmd) => cmd.run(),
+            SubCommand::Stats(cmd) => cmd.run(),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum DirectionArg {
+    Received,
+    Sent,
+}
+
+#[derive(clap::Args)]
+struct Filter {
+    /// Only the messages of these types, e.g. `Block` or `BlockApproval`.
+    #[clap(long)]
+    message_type: Vec<String>,
+    /// Only the messages exchanged with these peers, given by their public keys.
+    #[clap(long)]
+    peer: Vec<PublicKey>,
+    /// Only the messages in this direction.
+    #[clap(long)]
+    direction: Option<DirectionArg>,
+}
+
+impl Filter {
+    fn matches(&self, message: &CapturedMessage) -> bool {
+        if !self.message_type.is_empty() && !self.message_type.contains(&message.message_type) {
+            return false;
+        }
+        if !self.peer.is_empty()
+            && !message
+                .peer_id
+                .as_ref()
+                .is_some_and(|peer_id| self.peer.contains(peer_id.public_key()))
+        {
+            return false;
+        }
+        match (self.direction, message.direction) {
+            (None, _)
+            | (Some(DirectionArg::Received), Direction::Received)
+            | (Some(DirectionArg::Sent), Direction::Sent) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Calls `f` with the messages of the files matching the filter, in the order
+/// of the files.
+fn for_each_message(
+    files: &[PathBuf],
+    filter: &Filter,
+    mut f: impl FnMut(CapturedMessage) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    for file in files {
+        let reader = CaptureReader::open(file)
+            .with_context(|| format!("failed to open {}", file.display()))?;
+        for message in reader {
+            let message = message.with_context(|| format!("failed to read {}", file.display()))?;
+            if filter.matches(&message) {
+                f(message)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(clap::Args)]
+struct PrintCmd {
+    /// Capture files. Rotated files should be listed oldest first to print
+    /// the messages in order.
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+    #[clap(flatten)]
+    filter: Filter,
+    /// Also print the parsed messages.
+    #[clap(long)]
+    verbose: bool,
+}
+
+impl PrintCmd {
+    fn run(self) -> anyhow::Result<()> {
+        for_each_message(&self.files, &self.filter, |message| {
+            let time = chrono::DateTime::from_timestamp_nanos(
+                message.timestamp_nanos.try_into().unwrap_or(i64::MAX),
+            );
+            let peer_id = match &message.peer_id {
+                Some(peer_id) => peer_id.to_string(),
+                None => "-".to_string(),
+            };
+            println!(
+                "{} {:?} {} {} {:?} {} {}",
+                time.to_rfc3339(),
+                message.direction,
+                message.peer_addr,
+                peer_id,
+                message.encoding,
+                message.message_type,
+                message.data.len(),
+            );
+            if self.verbose {
+                match message.parse() {
+                    Ok(parsed) => println!("  {:?}", parsed),
+                    Err(err) => println!("  failed to parse: {err}"),
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(clap::Args)]
+struct StatsCmd {
+    /// Capture files.
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+    #[clap(flatten)]
+    filter: Filter,
+}
+
+impl StatsCmd {
+    fn run(self) -> anyhow::Result<()> {
+        // Number of messages and bytes by direction and message type.
+        let mut stats = BTreeMap::<(String, String), (u64, u64)>::new();
+        for_each_message(&self.files, &self.filter, |message| {
+            let entry = stats
+                .entry((format!("{:?}", message.direction), message.message_type))
+                .or_default();
+            entry.0 += 1;
+            entry.1 += message.data.len() as u64;
+            Ok(())
+        })?;
+        println!("{:<10} {:<40} {:>10} {:>14}", "direction", "type", "messages", "bytes");
+        for ((direction, message_type), (count, bytes)) in stats {
+            println!("{direction:<10} {message_type:<40} {count:>10} {bytes:>14}");
+        }
+        Ok(())
+    }
+}
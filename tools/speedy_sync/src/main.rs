@@ -261,6 +261,10 @@ fn load_snapshot(load_cmd: LoadCmd) {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            defer_fork_postprocessing: false,
+            transactions_by_signer_limit: None,
+            max_concurrent_shard_applies: None,
+            enable_receipt_audit: false,
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
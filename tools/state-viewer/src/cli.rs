@@ -3,6 +3,7 @@ use crate::congestion_control::CongestionControlCmd;
 use crate::contract_accounts::ContractAccountFilter;
 use crate::replay_headers::replay_headers;
 use crate::rocksdb_stats::get_rocksdb_stats;
+use crate::trie_access_heat_map::TrieAccessHeatMapCmd;
 use crate::trie_iteration_benchmark::TrieIterationBenchmarkCmd;
 
 use crate::latest_witnesses::StateWitnessCmd;
@@ -85,6 +86,10 @@ pub enum StateViewerSubCommand {
     /// Regenerates epoch info based on previous epoch.
     #[clap(alias = "epoch_analysis")]
     EpochAnalysis(EpochAnalysisCmd),
+    /// Export the validator sets, stakes, assignments, kickouts and rewards of
+    /// a range of epochs as JSON.
+    #[clap(alias = "export_validator_sets")]
+    ExportValidatorSets(ExportValidatorSetsCmd),
     /// Looks up a certain partial chunk.
     #[clap(alias = "partial_chunks")]
     PartialChunks(PartialChunksCmd),
@@ -107,6 +112,8 @@ pub enum StateViewerSubCommand {
     /// Iterates over the Flat State and prints some statistics.
     /// e.g. large accounts, total, average and median size, middle account
     StateStats(StateStatsCmd),
+    /// Print the heat map of trie keys read during block processing.
+    TrieAccessHeatMap(TrieAccessHeatMapCmd),
     /// Benchmark how long does it take to iterate the trie.
     TrieIterationBenchmark(TrieIterationBenchmarkCmd),
     /// View head of the storage.
@@ -185,6 +192,7 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::DumpTx(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::EpochInfo(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::EpochAnalysis(cmd) => cmd.run(near_config, store),
+            StateViewerSubCommand::ExportValidatorSets(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::PartialChunks(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::Receipts(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::ReplayHeaders(cmd) => cmd.run(home_dir, near_config, store),
@@ -197,6 +205,7 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::ViewChain(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ViewGenesis(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ViewTrie(cmd) => cmd.run(store),
+            StateViewerSubCommand::TrieAccessHeatMap(cmd) => cmd.run(store),
             StateViewerSubCommand::TrieIterationBenchmark(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::StateWitness(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::CongestionControl(cmd) => cmd.run(home_dir, near_config, store),
@@ -672,6 +681,31 @@ impl EpochAnalysisCmd {
     }
 }
 
+#[derive(clap::Parser)]
+pub struct ExportValidatorSetsCmd {
+    /// First epoch height to export.
+    #[clap(long)]
+    start_epoch_height: EpochHeight,
+    /// Last epoch height to export, inclusive. Defaults to the start one.
+    #[clap(long)]
+    end_epoch_height: Option<EpochHeight>,
+    /// File to write the JSON to. Printed to stdout if not set.
+    #[clap(long, value_parser)]
+    output: Option<PathBuf>,
+}
+
+impl ExportValidatorSetsCmd {
+    pub fn run(self, near_config: NearConfig, store: Store) {
+        let end_epoch_height = self.end_epoch_height.unwrap_or(self.start_epoch_height);
+        export_validator_sets(
+            self.start_epoch_height..end_epoch_height + 1,
+            self.output.as_deref(),
+            near_config,
+            store,
+        );
+    }
+}
+
 #[derive(clap::Parser)]
 pub struct PartialChunksCmd {
     #[clap(long)]
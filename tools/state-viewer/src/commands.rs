@@ -63,6 +63,7 @@ use std::collections::HashMap;
 use std::collections::{BTreeMap, BinaryHeap};
 use std::fs::{self, File};
 use std::io::Write;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use yansi::Color::Red;
@@ -524,6 +525,11 @@ pub(crate) fn get_chunk(chunk_hash: ChunkHash, near_config: NearConfig, store: S
     );
     let chunk = chain_store.get_chunk(&chunk_hash);
     println!("Chunk: {:#?}", chunk);
+    if let Err(Error::ChunkMissing(_)) = chunk {
+        println!(
+            "A running node with the debug RPC enabled can fetch the chunk from its peers with a POST to `/debug/api/fetch_chunk/{{block_hash}}/{{shard_id}}`"
+        );
+    }
 }
 
 pub(crate) fn print_chunk_apply_stats(
@@ -941,6 +947,23 @@ pub(crate) fn print_epoch_info(
     );
 }
 
+pub(crate) fn export_validator_sets(
+    epoch_heights: Range<EpochHeight>,
+    output: Option<&Path>,
+    near_config: NearConfig,
+    store: Store,
+) {
+    let epoch_manager = EpochManager::new_arc_handle(store, &near_config.genesis.config, None);
+    let exports = epoch_manager.read().export_validator_sets(epoch_heights).unwrap();
+    match output {
+        Some(output) => {
+            serde_json::to_writer_pretty(File::create(output).unwrap(), &exports).unwrap();
+            println!("Exported {} epochs to {}", exports.len(), output.display());
+        }
+        None => println!("{}", serde_json::to_string_pretty(&exports).unwrap()),
+    }
+}
+
 pub(crate) fn print_epoch_analysis(
     epoch_height: EpochHeight,
     mode: EpochAnalysisMode,
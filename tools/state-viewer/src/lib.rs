@@ -15,6 +15,7 @@ mod scan_db;
 mod state_changes;
 mod state_dump;
 mod state_parts;
+mod trie_access_heat_map;
 mod trie_iteration_benchmark;
 mod tx_dump;
 pub mod util;
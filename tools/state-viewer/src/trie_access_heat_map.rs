@@ -0,0 +1,84 @@
+use near_primitives::trie_key::col::ALL_COLUMNS_WITH_NAMES;
+use near_store::trie::access_heat_map::{TrieAccessHeatMap, TrieKeyPrefix};
+use near_store::{ShardUId, Store};
+use std::collections::BTreeMap;
+
+/// Prints the heat map of trie keys read during block processing, collected
+/// when `store.trie_access_heat_map` is set in the config.
+#[derive(clap::Parser)]
+pub struct TrieAccessHeatMapCmd {
+    /// Print the heat map only for the given shard.
+    #[clap(long)]
+    shard_uid: Option<ShardUId>,
+    /// Number of the most read key prefixes to print for each shard.
+    #[clap(long, default_value = "50")]
+    top: usize,
+}
+
+impl TrieAccessHeatMapCmd {
+    pub fn run(self, store: Store) {
+        let Some(heat_map) = TrieAccessHeatMap::load(&store).unwrap() else {
+            println!("No trie access heat map found, is store.trie_access_heat_map enabled?");
+            return;
+        };
+        println!("Sample rate: 1 in {} reads", heat_map.sample_rate);
+        for shard in heat_map.shards {
+            if self.shard_uid.is_some_and(|shard_uid| shard_uid != shard.shard_uid) {
+                continue;
+            }
+            let total = shard.total_samples.max(1) as f64;
+            let share = |count: u64| 100.0 * count as f64 / total;
+            println!("\nShard {}: {} sampled reads", shard.shard_uid, shard.total_samples);
+
+            let mut by_column = BTreeMap::<&str, u64>::new();
+            for (prefix, count) in &shard.prefixes {
+                *by_column.entry(column_name(prefix.column)).or_default() += count;
+            }
+            let dropped = shard.total_samples - by_column.values().sum::<u64>();
+            println!("{:<32} {:>12} {:>8}", "column", "samples", "share");
+            for (column, count) in by_column {
+                println!("{:<32} {:>12} {:>7.2}%", column, count, share(count));
+            }
+            if dropped > 0 {
+                println!("{:<32} {:>12} {:>7.2}%", "(dropped prefixes)", dropped, share(dropped));
+            }
+
+            // The cumulative share tells how much of the reads the given
+            // number of prefixes account for, e.g. when deciding what to
+            // prefetch.
+            println!("\n{:>12} {:>8} {:>8}  prefix", "samples", "share", "cumul.");
+            let mut cumulative = 0;
+            for (prefix, count) in shard.prefixes.iter().take(self.top) {
+                cumulative += count;
+                println!(
+                    "{:>12} {:>7.2}% {:>7.2}%  {}",
+                    count,
+                    share(*count),
+                    share(cumulative),
+                    format_prefix(prefix)
+                );
+            }
+        }
+    }
+}
+
+fn column_name(column: u8) -> &'static str {
+    ALL_COLUMNS_WITH_NAMES
+        .iter()
+        .find(|(col, _)| *col == column)
+        .map_or("Unknown", |(_, name)| name)
+}
+
+fn format_prefix(prefix: &TrieKeyPrefix) -> String {
+    let mut result = column_name(prefix.column).to_string();
+    if let Some(account_id) = &prefix.account_id {
+        result.push_str(&format!(" {account_id}"));
+    }
+    if !prefix.data_prefix.is_empty() {
+        result.push_str(" 0x");
+        for byte in &prefix.data_prefix {
+            result.push_str(&format!("{byte:02x}"));
+        }
+    }
+    result
+}